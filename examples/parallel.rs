@@ -0,0 +1,139 @@
+extern crate crayon;
+extern crate env_logger;
+
+use crayon::impl_vertex;
+use crayon::prelude::*;
+
+impl_vertex! {
+    Vertex {
+        position => [Position; Float; 2; false],
+    }
+}
+
+/// The number of quads in each row and column.
+const GRID: usize = 128;
+/// The number of quads recorded by each job.
+const BATCH: usize = 1024;
+
+struct Window {
+    surface: SurfaceHandle,
+    shader: ShaderHandle,
+    mesh: MeshHandle,
+    buffers: Vec<DrawCommandBuffer<u32>>,
+    time: f32,
+}
+
+impl Window {
+    fn build() -> CrResult<Self> {
+        let verts: [Vertex; 4] = [
+            Vertex::new([-1.0, -1.0]),
+            Vertex::new([1.0, -1.0]),
+            Vertex::new([1.0, 1.0]),
+            Vertex::new([-1.0, 1.0]),
+        ];
+        let idxes: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+        // Create vertex buffer object.
+        let mut params = MeshParams::default();
+        params.num_verts = 4;
+        params.num_idxes = 6;
+        params.layout = Vertex::layout();
+
+        let data = MeshData {
+            vptr: Vertex::encode(&verts[..]).into(),
+            iptr: IndexFormat::encode(&idxes).into(),
+        };
+
+        let mesh = video::create_mesh(params, Some(data))?;
+
+        // Create the view state.
+        let mut setup = SurfaceParams::default();
+        setup.set_clear(Color::gray(), None, None);
+        let surface = video::create_surface(setup)?;
+
+        // Create shader state.
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 2)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Offset", UniformVariableType::Vector2f)
+            .with("u_Scale", UniformVariableType::F32)
+            .with("u_Color", UniformVariableType::Vector4f)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+        let vs = include_str!("shaders/parallel.vs").to_owned();
+        let fs = include_str!("shaders/parallel.fs").to_owned();
+        let shader = video::create_shader(params, vs, fs)?;
+
+        // One buffer per job, so the jobs could record without any synchronization.
+        let buffers = (0..(GRID * GRID + BATCH - 1) / BATCH)
+            .map(|_| DrawCommandBuffer::new())
+            .collect();
+
+        Ok(Window {
+            surface,
+            shader,
+            mesh,
+            buffers,
+            time: 0.0,
+        })
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        video::delete_mesh(self.mesh);
+        video::delete_shader(self.shader);
+        video::delete_surface(self.surface);
+    }
+}
+
+impl LifecycleListener for Window {
+    fn on_update(&mut self) -> CrResult<()> {
+        let duration = application::frame_duration();
+        self.time += duration.as_secs() as f32 + duration.subsec_millis() as f32 / 1000.0;
+
+        let (shader, mesh, time) = (self.shader, self.mesh, self.time);
+        let scale = 1.0 / GRID as f32;
+
+        sched::scope(|s| {
+            for (i, buffer) in self.buffers.iter_mut().enumerate() {
+                s.spawn(move |_| {
+                    let from = i * BATCH;
+                    let to = (from + BATCH).min(GRID * GRID);
+
+                    for index in from..to {
+                        let (x, y) = ((index % GRID) as f32, (index / GRID) as f32);
+                        let wave = ((x + y) * 0.1 + time).sin() * 0.5 + 0.5;
+
+                        let mut dc = Draw::new(shader, mesh);
+                        dc.set_uniform_variable(
+                            "u_Offset",
+                            [(x * 2.0 + 1.0) * scale - 1.0, (y * 2.0 + 1.0) * scale - 1.0],
+                        );
+                        dc.set_uniform_variable("u_Scale", scale * (0.5 + wave * 0.4));
+                        dc.set_uniform_variable("u_Color", [wave, x * scale, y * scale, 1.0]);
+
+                        // Quads are sorted by their rows, and the ones with the same order
+                        // keep the order of buffers and recording.
+                        buffer.draw(index as u32 / GRID as u32, dc);
+                    }
+                });
+            }
+        });
+
+        DrawCommandBuffer::submit_all(self.surface, &mut self.buffers)?;
+        Ok(())
+    }
+}
+
+main!({
+    let mut params = Params::default();
+    params.window.title = "CR: Parallel".into();
+    params.window.size = (512, 512).into();
+    crayon::application::setup(params, Window::build).unwrap();
+});
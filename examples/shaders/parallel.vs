@@ -0,0 +1,11 @@
+#version 100
+precision lowp float;
+
+attribute vec2 Position;
+
+uniform vec2 u_Offset;
+uniform float u_Scale;
+
+void main(){
+    gl_Position = vec4(Position * u_Scale + u_Offset, 0.0, 1.0);
+}
@@ -2,7 +2,11 @@ impl_handle!(AudioClipHandle);
 
 #[derive(Debug, Clone)]
 pub struct AudioClip {
+    /// The decoded samples, which are empty if the clip is streamed.
     pub pcm: Vec<i16>,
     pub channels: u8,
     pub sample_rate: u32,
+    /// The encoded OGG Vorbis data of long clips, e.g. music tracks. They are decoded on
+    /// demand while playing instead of being kept in memory as samples.
+    pub stream: Option<Vec<u8>>,
 }
@@ -1,4 +1,5 @@
 use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use crayon::errors::Result;
@@ -9,12 +10,25 @@ use super::clip::*;
 
 pub const MAGIC: [u8; 8] = [b'C', b'S', b'F', b'X', b' ', 0, 0, 1];
 
+/// The default size in bytes of encoded data, above which the clips are streamed.
+pub const STREAMING_THRESHOLD: usize = 1024 * 1024;
+
 #[derive(Clone)]
-pub struct AudioClipLoader {}
+pub struct AudioClipLoader {
+    streaming_threshold: Arc<AtomicUsize>,
+}
 
 impl AudioClipLoader {
     pub(crate) fn new() -> Self {
-        AudioClipLoader {}
+        AudioClipLoader {
+            streaming_threshold: Arc::new(AtomicUsize::new(STREAMING_THRESHOLD)),
+        }
+    }
+
+    /// Sets the size in bytes of encoded data, above which the clips loaded afterwards
+    /// are streamed.
+    pub fn set_streaming_threshold(&self, bytes: usize) {
+        self.streaming_threshold.store(bytes, Ordering::Relaxed);
     }
 }
 
@@ -24,19 +38,34 @@ impl ResourceLoader for AudioClipLoader {
     type Resource = Arc<AudioClip>;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if &bytes[0..8] != MAGIC {
+        if bytes.len() < 8 || &bytes[0..8] != MAGIC {
             bail!("[AudioClipLoader] MAGIC number not match.");
         }
 
         let cursor = Cursor::new(&bytes[8..]);
-        let mut stream_reader = OggStreamReader::new(cursor).unwrap();
+        let mut stream_reader = OggStreamReader::new(cursor)?;
 
         let mut clip = AudioClip {
             channels: stream_reader.ident_hdr.audio_channels,
             sample_rate: stream_reader.ident_hdr.audio_sample_rate,
             pcm: Vec::new(),
+            stream: None,
         };
 
+        if bytes.len() - 8 > self.streaming_threshold.load(Ordering::Relaxed) {
+            clip.stream = Some(bytes[8..].to_vec());
+
+            info!(
+                "[AudioClipLoader] loads clip {:?} (channels {:?} sample_rate {:?} stream: {:?}).",
+                handle,
+                clip.channels,
+                clip.sample_rate,
+                bytes.len() - 8
+            );
+
+            return Ok(clip);
+        }
+
         while let Some(v) = stream_reader.read_dec_packet_itl()? {
             clip.pcm.extend(&v);
         }
@@ -0,0 +1,30 @@
+impl_handle!(AudioBusHandle);
+
+/// The parameters of audio bus, which mixes the sounds of a group, e.g. music, sound
+/// effects or voices, before they are sent to its parent.
+///
+/// The buses make a graph that ends at the master output. The sources and buses without
+/// a parent, or whose parent has been deleted, are sent to the master output directly.
+#[derive(Debug, Copy, Clone)]
+pub struct AudioBusParams {
+    /// The bus that the output of this bus is sent to.
+    pub parent: Option<AudioBusHandle>,
+    /// The volume of the mixed sounds.
+    pub volume: f32,
+    /// Silences the bus and all the buses that sent to it.
+    pub mute: bool,
+    /// The cutoff frequency in Hz of the low-pass filter, e.g. to muffle the sounds when
+    /// the game is paused.
+    pub low_pass: Option<f32>,
+}
+
+impl Default for AudioBusParams {
+    fn default() -> Self {
+        AudioBusParams {
+            parent: None,
+            volume: 1.0,
+            mute: false,
+            low_pass: None,
+        }
+    }
+}
@@ -15,6 +15,7 @@ extern crate failure;
 extern crate lewton;
 
 pub mod assets;
+pub mod bus;
 pub mod source;
 
 mod mixer;
@@ -22,6 +23,7 @@ mod system;
 
 pub mod prelude {
     pub use assets::prelude::AudioClipHandle;
+    pub use bus::{AudioBusHandle, AudioBusParams};
     pub use source::{AudioSource, AudioSourceAttenuation, AudioSourceHandle, AudioSourceWrap};
 }
 
@@ -33,6 +35,7 @@ use crayon::res::prelude::ResourceState;
 use crayon::uuid::Uuid;
 
 use self::assets::prelude::AudioClipHandle;
+use self::bus::{AudioBusHandle, AudioBusParams};
 use self::inside::ctx;
 use self::source::{AudioSource, AudioSourceHandle};
 
@@ -45,6 +48,17 @@ where
     ctx().set_listener(position);
 }
 
+/// Sets the size in bytes of encoded data, above which the clips created afterwards are
+/// streamed, e.g. long music tracks. The streamed clips are decoded while playing instead
+/// of being decoded into memory when loaded.
+///
+/// Only the OGG Vorbis clips are supported, MP3 files should be converted into OGG Vorbis
+/// before being imported.
+#[inline]
+pub fn set_streaming_threshold(bytes: usize) {
+    ctx().set_streaming_threshold(bytes);
+}
+
 /// Creates a clip object from file asynchronously.
 #[inline]
 pub fn create_clip_from<T: AsRef<str>>(url: T) -> Result<AudioClipHandle> {
@@ -104,6 +118,37 @@ pub fn set_pitch(handle: AudioSourceHandle, pitch: f32) {
     ctx().set_pitch(handle, pitch);
 }
 
+/// Creates a bus that mixes the sounds sent to it, returning a `AudioBusHandle` for it.
+#[inline]
+pub fn create_bus(params: AudioBusParams) -> Result<AudioBusHandle> {
+    ctx().create_bus(params)
+}
+
+/// Deletes a bus, the sounds and buses sent to it are sent to the master output then.
+#[inline]
+pub fn delete_bus(handle: AudioBusHandle) {
+    ctx().delete_bus(handle);
+}
+
+/// Sets the volume of a bus.
+#[inline]
+pub fn set_bus_volume(handle: AudioBusHandle, volume: f32) {
+    ctx().set_bus_volume(handle, volume);
+}
+
+/// Mutes or unmutes a bus.
+#[inline]
+pub fn set_bus_mute(handle: AudioBusHandle, mute: bool) {
+    ctx().set_bus_mute(handle, mute);
+}
+
+/// Sets the cutoff frequency in Hz of the low-pass filter of a bus, or disables the
+/// filter with `None`.
+#[inline]
+pub fn set_bus_low_pass(handle: AudioBusHandle, cutoff: Option<f32>) {
+    ctx().set_bus_low_pass(handle, cutoff);
+}
+
 mod inside {
     use super::system::AudioSystem;
 
@@ -5,9 +5,9 @@ use cpal::{self, EventLoop, StreamData, UnknownTypeOutputBuffer};
 use crayon::errors::Result;
 
 use super::sampler::Sampler;
-use super::Command;
+use super::{Command, Rewinds};
 
-pub fn run(rx: Arc<RwLock<Vec<Command>>>) -> Result<()> {
+pub fn run(rx: Arc<RwLock<Vec<Command>>>, rewinds: Rewinds) -> Result<()> {
     let device = cpal::default_output_device()
         .ok_or_else(|| format_err!("No avaiable audio output device"))?;
 
@@ -24,7 +24,7 @@ pub fn run(rx: Arc<RwLock<Vec<Command>>>) -> Result<()> {
         format
     );
 
-    let mut sampler = Sampler::new(format.channels as u8, format.sample_rate.0 as u32);
+    let mut sampler = Sampler::new(format.channels as u8, format.sample_rate.0 as u32, rewinds);
     Builder::new()
         .name("Audio".into())
         .spawn(move || {
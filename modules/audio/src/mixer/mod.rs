@@ -5,6 +5,7 @@ mod webaudio;
 
 mod headless;
 mod sampler;
+mod stream;
 
 use std::sync::{Arc, RwLock};
 
@@ -14,27 +15,39 @@ use crayon::res::utils::prelude::ResourcePool;
 use crayon::utils::prelude::HandlePool;
 
 use assets::prelude::{AudioClip, AudioClipHandle, AudioClipLoader};
+use bus::{AudioBusHandle, AudioBusParams};
 use source::{AudioSource, AudioSourceHandle};
 
+use self::stream::Stream;
+
+/// The looping sources of streamed clips, which request the rewound streams from the
+/// main thread.
+pub type Rewinds = Arc<RwLock<Vec<(AudioSourceHandle, Arc<AudioClip>)>>>;
+
 pub struct Mixer {
     sources: RwLock<HandlePool<AudioSourceHandle>>,
+    buses: RwLock<HandlePool<AudioBusHandle>>,
     tx: Arc<RwLock<Vec<Command>>>,
+    rewinds: Rewinds,
     clips: Arc<RwLock<ResourcePool<AudioClipHandle, AudioClipLoader>>>,
 }
 
 impl Mixer {
     pub fn new(clips: Arc<RwLock<ResourcePool<AudioClipHandle, AudioClipLoader>>>) -> Result<Self> {
         let tx = Arc::new(RwLock::new(Vec::new()));
+        let rewinds = Arc::new(RwLock::new(Vec::new()));
 
         #[cfg(not(target_arch = "wasm32"))]
-        cpal::run(tx.clone())?;
+        cpal::run(tx.clone(), rewinds.clone())?;
 
         #[cfg(target_arch = "wasm32")]
-        webaudio::run(tx.clone())?;
+        webaudio::run(tx.clone(), rewinds.clone())?;
 
         Ok(Mixer {
             sources: RwLock::new(HandlePool::new()),
+            buses: RwLock::new(HandlePool::new()),
             tx: tx,
+            rewinds: rewinds,
             clips: clips,
         })
     }
@@ -47,10 +60,28 @@ impl Mixer {
 
         Ok(Mixer {
             sources: RwLock::new(HandlePool::new()),
+            buses: RwLock::new(HandlePool::new()),
             tx: tx,
+            rewinds: Arc::new(RwLock::new(Vec::new())),
             clips: clips,
         })
     }
+
+    /// Creates the rewound streams that have been requested by the looping sources.
+    pub fn advance(&self) {
+        let rewinds: Vec<_> = self.rewinds.write().unwrap().drain(..).collect();
+        for (handle, clip) in rewinds {
+            let cmd = match Stream::new(clip) {
+                Ok(stream) => Command::SetStream(handle, stream),
+                Err(err) => {
+                    warn!("Failed to rewind the stream of {:?}: {}", handle, err);
+                    Command::DeleteSource(handle)
+                }
+            };
+
+            self.tx.write().unwrap().push(cmd);
+        }
+    }
 }
 
 impl Drop for Mixer {
@@ -63,9 +94,20 @@ impl Drop for Mixer {
 impl Mixer {
     #[inline]
     pub fn create_source(&self, params: AudioSource) -> Result<AudioSourceHandle> {
+        if let Some(bus) = params.bus {
+            if !self.buses.read().unwrap().contains(bus) {
+                bail!("The AudioBus {:?} is not available.", bus);
+            }
+        }
+
         if let Some(clip) = self.clips.read().unwrap().resource(params.clip).cloned() {
+            let stream = match clip.stream {
+                Some(_) => Some(Stream::new(clip.clone())?),
+                None => None,
+            };
+
             let handle = self.sources.write().unwrap().create();
-            let cmd = Command::CreateSource(handle, params, clip);
+            let cmd = Command::CreateSource(handle, params, clip, stream);
             self.tx.write().unwrap().push(cmd);
             Ok(handle)
         } else {
@@ -102,15 +144,62 @@ impl Mixer {
         let cmd = Command::SetPosition(handle, position);
         self.tx.write().unwrap().push(cmd);
     }
+
+    pub fn create_bus(&self, params: AudioBusParams) -> Result<AudioBusHandle> {
+        let mut buses = self.buses.write().unwrap();
+        if let Some(parent) = params.parent {
+            if !buses.contains(parent) {
+                bail!("The parent AudioBus {:?} is not available.", parent);
+            }
+        }
+
+        let handle = buses.create();
+        let cmd = Command::CreateBus(handle, params);
+        self.tx.write().unwrap().push(cmd);
+        Ok(handle)
+    }
+
+    #[inline]
+    pub fn delete_bus(&self, handle: AudioBusHandle) {
+        if self.buses.write().unwrap().free(handle) {
+            let cmd = Command::DeleteBus(handle);
+            self.tx.write().unwrap().push(cmd);
+        }
+    }
+
+    #[inline]
+    pub fn set_bus_volume(&self, handle: AudioBusHandle, volume: f32) {
+        let cmd = Command::SetBusVolume(handle, volume);
+        self.tx.write().unwrap().push(cmd);
+    }
+
+    #[inline]
+    pub fn set_bus_mute(&self, handle: AudioBusHandle, mute: bool) {
+        let cmd = Command::SetBusMute(handle, mute);
+        self.tx.write().unwrap().push(cmd);
+    }
+
+    #[inline]
+    pub fn set_bus_low_pass(&self, handle: AudioBusHandle, cutoff: Option<f32>) {
+        let cmd = Command::SetBusLowPass(handle, cutoff);
+        self.tx.write().unwrap().push(cmd);
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum Command {
     SetListener(Vector3<f32>),
-    CreateSource(AudioSourceHandle, AudioSource, Arc<AudioClip>),
+    CreateSource(AudioSourceHandle, AudioSource, Arc<AudioClip>, Option<Stream>),
+    /// Sets the rewound stream of a looping source.
+    SetStream(AudioSourceHandle, Stream),
     DeleteSource(AudioSourceHandle),
     SetVolume(AudioSourceHandle, f32),
     SetPitch(AudioSourceHandle, f32),
     SetPosition(AudioSourceHandle, Vector3<f32>),
+    CreateBus(AudioBusHandle, AudioBusParams),
+    DeleteBus(AudioBusHandle),
+    SetBusVolume(AudioBusHandle, f32),
+    SetBusMute(AudioBusHandle, bool),
+    SetBusLowPass(AudioBusHandle, Option<f32>),
     Discard,
 }
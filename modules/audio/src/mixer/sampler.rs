@@ -3,26 +3,35 @@ use std::sync::Arc;
 use crayon::math::prelude::Vector3;
 
 use assets::prelude::AudioClip;
+use bus::{AudioBusHandle, AudioBusParams};
 use source::{AudioSource, AudioSourceAttenuation, AudioSourceHandle, AudioSourceWrap};
 
-use super::Command;
+use super::stream::Stream;
+use super::{Command, Rewinds};
 
 pub struct Sampler {
+    rewinds: Rewinds,
     channels: u8,
     sample_rate: u32,
     listener: Vector3<f32>,
     channels_iter: u8,
     samplers: Vec<Option<AudioSourceSampler>>,
+    buses: Vec<Option<AudioBusSampler>>,
+    // The indices of buses, which are sorted from the deepest to the master output.
+    buses_order: Vec<usize>,
 }
 
 impl Sampler {
-    pub fn new(channels: u8, sample_rate: u32) -> Self {
+    pub fn new(channels: u8, sample_rate: u32, rewinds: Rewinds) -> Self {
         Sampler {
+            rewinds: rewinds,
             channels: channels,
             sample_rate: sample_rate,
             listener: Vector3::new(0.0, 0.0, 0.0),
             channels_iter: 0,
             samplers: Vec::new(),
+            buses: Vec::new(),
+            buses_order: Vec::new(),
         }
     }
 
@@ -39,10 +48,31 @@ impl Sampler {
     }
 
     pub fn sample(&mut self) -> f32 {
+        for v in self.buses.iter_mut().filter_map(|v| v.as_mut()) {
+            v.sum = 0.0;
+        }
+
         let mut sum = 0.0;
         for v in &mut self.samplers {
             if let Some(ref source) = v {
-                sum += source.sample(self.channels_iter, self.listener);
+                let v = source.sample(self.channels_iter, self.listener);
+                match Self::bus(&mut self.buses, source.bus) {
+                    Some(bus) => bus.sum += v,
+                    None => sum += v,
+                }
+            }
+        }
+
+        for &i in &self.buses_order {
+            let (v, parent) = {
+                let bus = self.buses[i].as_mut().unwrap();
+                let v = bus.sample(self.channels_iter, self.sample_rate);
+                (v, bus.params.parent)
+            };
+
+            match Self::bus(&mut self.buses, parent) {
+                Some(bus) => bus.sum += v,
+                None => sum += v,
             }
         }
 
@@ -58,6 +88,11 @@ impl Sampler {
 
                 if free {
                     *v = None;
+                } else if let Some(ref mut source) = v {
+                    if source.request_rewind() {
+                        let rewind = (source.handle, source.clip.clone());
+                        self.rewinds.write().unwrap().push(rewind);
+                    }
                 }
             }
         }
@@ -69,11 +104,31 @@ impl Sampler {
         for cmd in bufs {
             match cmd {
                 Command::SetListener(position) => self.set_listener(position),
-                Command::CreateSource(handle, source, c) => self.create_source(handle, source, c),
+                Command::CreateSource(handle, source, c, stream) => {
+                    self.create_source(handle, source, c, stream)
+                }
+                Command::SetStream(handle, stream) => self.set_stream(handle, stream),
                 Command::DeleteSource(handle) => self.delete_source(handle),
                 Command::SetPitch(handle, pitch) => self.set_pitch(handle, pitch),
                 Command::SetVolume(handle, volume) => self.set_volume(handle, volume),
                 Command::SetPosition(handle, emitter) => self.set_position(handle, emitter),
+                Command::CreateBus(handle, params) => self.create_bus(handle, params),
+                Command::DeleteBus(handle) => self.delete_bus(handle),
+                Command::SetBusVolume(handle, volume) => {
+                    if let Some(v) = Self::bus(&mut self.buses, Some(handle)) {
+                        v.params.volume = volume;
+                    }
+                }
+                Command::SetBusMute(handle, mute) => {
+                    if let Some(v) = Self::bus(&mut self.buses, Some(handle)) {
+                        v.params.mute = mute;
+                    }
+                }
+                Command::SetBusLowPass(handle, cutoff) => {
+                    if let Some(v) = Self::bus(&mut self.buses, Some(handle)) {
+                        v.params.low_pass = cutoff;
+                    }
+                }
                 Command::Discard => {
                     return false;
                 }
@@ -88,6 +143,7 @@ impl Sampler {
         handle: AudioSourceHandle,
         source: AudioSource,
         clip: Arc<AudioClip>,
+        stream: Option<Stream>,
     ) {
         if let AudioSourceWrap::Repeat(v) = source.loops {
             if v <= 0 {
@@ -95,12 +151,81 @@ impl Sampler {
             }
         }
 
+        if clip.stream.is_some() && stream.is_none() {
+            warn!("Failed to play {:?}: the stream is not created.", handle);
+            return;
+        }
+
+        let mut sampler = AudioSourceSampler::new(handle, clip, source, stream);
+        if sampler.request_rewind() {
+            let rewind = (handle, sampler.clip.clone());
+            self.rewinds.write().unwrap().push(rewind);
+        }
+
+        let index = handle.index() as usize;
+        while self.samplers.len() <= index {
+            self.samplers.push(None);
+        }
+
+        self.samplers[index] = Some(sampler);
+    }
+
+    pub fn set_stream(&mut self, handle: AudioSourceHandle, stream: Stream) {
+        let index = handle.index() as usize;
+        if let Some(v) = self.samplers.get_mut(index).and_then(|v| v.as_mut()) {
+            // The source might have been replaced by another one with the same index.
+            if v.handle == handle && Arc::ptr_eq(&v.clip, stream.clip()) {
+                v.rewound = Some(stream);
+                v.requested = false;
+            }
+        }
+    }
+
+    pub fn create_bus(&mut self, handle: AudioBusHandle, params: AudioBusParams) {
+        let depth = Self::bus(&mut self.buses, params.parent)
+            .map(|v| v.depth + 1)
+            .unwrap_or(0);
+
         let index = handle.index() as usize;
-        if self.samplers.len() <= index {
-            self.samplers.resize(index + 1, None);
+        while self.buses.len() <= index {
+            self.buses.push(None);
         }
 
-        self.samplers[index] = Some(AudioSourceSampler::new(clip, source));
+        self.buses[index] = Some(AudioBusSampler {
+            handle: handle,
+            params: params,
+            depth: depth,
+            filter: vec![0.0; self.channels as usize],
+            sum: 0.0,
+        });
+
+        self.sort_buses();
+    }
+
+    pub fn delete_bus(&mut self, handle: AudioBusHandle) {
+        if Self::bus(&mut self.buses, Some(handle)).is_some() {
+            self.buses[handle.index() as usize] = None;
+            self.sort_buses();
+        }
+    }
+
+    fn sort_buses(&mut self) {
+        let buses = &self.buses;
+        self.buses_order = (0..buses.len()).filter(|&i| buses[i].is_some()).collect();
+        self.buses_order
+            .sort_by_key(|&i| ::std::cmp::Reverse(buses[i].as_ref().unwrap().depth));
+    }
+
+    #[inline]
+    fn bus(
+        buses: &mut [Option<AudioBusSampler>],
+        handle: Option<AudioBusHandle>,
+    ) -> Option<&mut AudioBusSampler> {
+        let handle = handle?;
+        buses
+            .get_mut(handle.index() as usize)
+            .and_then(|v| v.as_mut())
+            .filter(|v| v.handle == handle)
     }
 
     #[inline]
@@ -141,25 +266,89 @@ impl Sampler {
     }
 }
 
-#[derive(Clone)]
+pub struct AudioBusSampler {
+    handle: AudioBusHandle,
+    params: AudioBusParams,
+    depth: usize,
+    // The outputs of low-pass filter of each channel.
+    filter: Vec<f32>,
+    sum: f32,
+}
+
+impl AudioBusSampler {
+    pub fn sample(&mut self, channels_iter: u8, sample_rate: u32) -> f32 {
+        let mut v = self.sum;
+
+        if let Some(cutoff) = self.params.low_pass {
+            // One-pole low-pass filter.
+            let a = 1.0 - (-2.0 * ::std::f32::consts::PI * cutoff / sample_rate as f32).exp();
+            let y = &mut self.filter[channels_iter as usize];
+            *y += a.min(1.0).max(0.0) * (v - *y);
+            v = *y;
+        }
+
+        if self.params.mute {
+            0.0
+        } else {
+            v * self.params.volume
+        }
+    }
+}
+
 pub struct AudioSourceSampler {
+    handle: AudioSourceHandle,
     clip: Arc<AudioClip>,
     volume: f32,
     pitch: f32,
     loops: AudioSourceWrap,
     attenuation: Option<AudioSourceAttenuation>,
+    bus: Option<AudioBusHandle>,
     iter: f32,
+    stream: Option<Stream>,
+    // The stream that decodes from the beginning, which is swapped in once the current
+    // one has ended. It's created on the main thread.
+    rewound: Option<Stream>,
+    // Whether the rewound stream has been requested.
+    requested: bool,
+    // Whether the source is waiting for the rewound stream.
+    rewinding: bool,
 }
 
 impl AudioSourceSampler {
-    pub fn new(clip: Arc<AudioClip>, source: AudioSource) -> Self {
+    pub fn new(
+        handle: AudioSourceHandle,
+        clip: Arc<AudioClip>,
+        source: AudioSource,
+        stream: Option<Stream>,
+    ) -> Self {
         AudioSourceSampler {
+            handle: handle,
             clip: clip,
             volume: source.volume,
             pitch: source.pitch,
             loops: source.loops,
             attenuation: source.attenuation,
+            bus: source.bus,
             iter: 0.0,
+            stream: stream,
+            rewound: None,
+            requested: false,
+            rewinding: false,
+        }
+    }
+
+    /// Returns true if the rewound stream should be requested from the main thread.
+    pub fn request_rewind(&mut self) -> bool {
+        let repeats = match self.loops {
+            AudioSourceWrap::Repeat(c) => c > 1,
+            AudioSourceWrap::Infinite => true,
+        };
+
+        if self.stream.is_some() && self.rewound.is_none() && !self.requested && repeats {
+            self.requested = true;
+            true
+        } else {
+            false
         }
     }
 
@@ -181,11 +370,16 @@ impl AudioSourceSampler {
     }
 
     pub fn sample(&self, channels_iter: u8, listener: Vector3<f32>) -> f32 {
-        let mut idx = (self.iter as usize) * (self.clip.channels as usize);
-        idx += (channels_iter % self.clip.channels) as usize;
+        let v = if let Some(ref stream) = self.stream {
+            stream.sample(self.iter as usize, channels_iter)
+        } else {
+            let mut idx = (self.iter as usize) * (self.clip.channels as usize);
+            idx += (channels_iter % self.clip.channels) as usize;
+            self.clip.pcm.get(idx).cloned()
+        };
 
-        if idx < self.clip.pcm.len() {
-            let mut v = sample_i16_to_f32(self.clip.pcm[idx]) * self.volume;
+        if let Some(v) = v {
+            let mut v = sample_i16_to_f32(v) * self.volume;
 
             if let Some(attenuation) = self.attenuation {
                 v *= attenuation.volume(listener);
@@ -198,28 +392,73 @@ impl AudioSourceSampler {
     }
 
     pub fn advance(&mut self, sample_rate: u32) -> bool {
+        // Keeps silent until the rewound stream has been received.
+        if self.rewinding {
+            match self.rewound.take() {
+                Some(v) => {
+                    self.stream = Some(v);
+                    self.rewinding = false;
+                }
+                None => return false,
+            }
+        }
+
         let pitch = self.pitch.min(100.0).max(0.01);
         self.iter += pitch * (self.clip.sample_rate as f32) / (sample_rate as f32);
 
-        let samples = (self.clip.pcm.len() as f32) / (self.clip.channels as f32);
-        while (self.iter as usize) * (self.clip.channels as usize) >= self.clip.pcm.len() {
-            match self.loops {
-                AudioSourceWrap::Repeat(ref mut c) => {
-                    if *c > 1 {
-                        *c -= 1;
-                        self.iter -= samples;
-                    } else {
+        if let Some(ref mut stream) = self.stream {
+            loop {
+                match stream.seek(self.iter as usize) {
+                    Ok(true) => return false,
+                    Ok(false) => {
+                        let frames = stream.len();
+                        if frames == 0 || !Self::repeat(&mut self.loops) {
+                            return true;
+                        }
+
+                        self.iter -= frames as f32;
+                        match self.rewound.take() {
+                            Some(v) => *stream = v,
+                            None => {
+                                self.rewinding = true;
+                                return false;
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        warn!("Failed to decode audio stream: {}", err);
                         return true;
                     }
                 }
-                AudioSourceWrap::Infinite => {
-                    self.iter -= samples;
-                }
             }
         }
 
+        let samples = (self.clip.pcm.len() as f32) / (self.clip.channels as f32);
+        while (self.iter as usize) * (self.clip.channels as usize) >= self.clip.pcm.len() {
+            if !Self::repeat(&mut self.loops) {
+                return true;
+            }
+
+            self.iter -= samples;
+        }
+
         false
     }
+
+    #[inline]
+    fn repeat(loops: &mut AudioSourceWrap) -> bool {
+        match *loops {
+            AudioSourceWrap::Repeat(ref mut c) => {
+                if *c > 1 {
+                    *c -= 1;
+                    true
+                } else {
+                    false
+                }
+            }
+            AudioSourceWrap::Infinite => true,
+        }
+    }
 }
 
 #[inline]
@@ -246,3 +485,98 @@ pub fn sample_f32_to_i16(sample: f32) -> i16 {
 pub fn sample_f32_to_u16(sample: f32) -> u16 {
     (((sample + 1.0) * 0.5) * ::std::u16::MAX as f32).round() as u16
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, RwLock};
+
+    use super::*;
+    use assets::prelude::AudioClipHandle;
+    use crayon::utils::handle::HandleLike;
+
+    // A silent OGG Vorbis stream of mono 8000 Hz, with 5 audio packets of short blocks
+    // that are decoded into 4 * 32 frames.
+    const VORBIS: &[u8] = &[
+        0x4f, 0x67, 0x67, 0x53, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x59, 0xe7, 0xff, 0x90, 0x01, 0x1e, 0x01, 0x76,
+        0x6f, 0x72, 0x62, 0x69, 0x73, 0x00, 0x00, 0x00, 0x00, 0x01, 0x40, 0x1f, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x66, 0x01, 0x4f, 0x67,
+        0x67, 0x53, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x9a, 0x61, 0x4e, 0x0c, 0x02, 0x10, 0x34, 0x03, 0x76, 0x6f,
+        0x72, 0x62, 0x69, 0x73, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x05, 0x76,
+        0x6f, 0x72, 0x62, 0x69, 0x73, 0x00, 0x42, 0x43, 0x56, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x01, 0x4f, 0x67, 0x67, 0x53, 0x00, 0x04, 0x80, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x9d, 0xcd, 0xa1,
+        0x49, 0x05, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    const FRAMES: usize = 128;
+
+    fn clip() -> Arc<AudioClip> {
+        Arc::new(AudioClip {
+            pcm: Vec::new(),
+            channels: 1,
+            sample_rate: 8000,
+            stream: Some(VORBIS.to_vec()),
+        })
+    }
+
+    #[test]
+    fn stream() {
+        let mut stream = Stream::new(clip()).unwrap();
+        assert!(stream.seek(FRAMES - 1).unwrap());
+        assert_eq!(stream.sample(FRAMES - 1, 0), Some(0));
+        assert!(!stream.seek(FRAMES).unwrap());
+        assert_eq!(stream.len(), FRAMES);
+    }
+
+    #[test]
+    fn rewind() {
+        let clip = clip();
+        let rewinds = Arc::new(RwLock::new(Vec::new()));
+        let mut sampler = Sampler::new(1, 8000, rewinds.clone());
+
+        let handle = AudioSourceHandle::new(0, 1);
+        let mut source = AudioSource::from(AudioClipHandle::new(0, 1));
+        source.loops = AudioSourceWrap::Repeat(2);
+
+        let stream = Stream::new(clip.clone()).unwrap();
+        sampler.create_source(handle, source, clip.clone(), Some(stream));
+
+        // The rewound stream is requested from the main thread once the source is created.
+        let (v, c) = rewinds.write().unwrap().pop().unwrap();
+        assert_eq!(v, handle);
+        assert!(Arc::ptr_eq(&c, &clip));
+
+        // The source keeps silent without skipping frames until the rewound stream has
+        // been received.
+        for _ in 0..FRAMES * 2 {
+            sampler.sample();
+        }
+
+        assert!(sampler.samplers[0].as_ref().unwrap().rewinding);
+        assert_eq!(sampler.samplers[0].as_ref().unwrap().iter, 0.0);
+
+        // The streams of stale handles are ignored.
+        let stale = AudioSourceHandle::new(0, 2);
+        let cmd = Command::SetStream(stale, Stream::new(clip.clone()).unwrap());
+        sampler.update(Some(cmd).into_iter());
+        sampler.sample();
+        assert!(sampler.samplers[0].as_ref().unwrap().rewinding);
+
+        let cmd = Command::SetStream(handle, Stream::new(clip.clone()).unwrap());
+        sampler.update(Some(cmd).into_iter());
+
+        for _ in 0..FRAMES - 1 {
+            sampler.sample();
+        }
+
+        assert!(sampler.samplers[0].is_some());
+        assert!(rewinds.read().unwrap().is_empty());
+
+        sampler.sample();
+        assert!(sampler.samplers[0].is_none());
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crayon::errors::Result;
+use lewton::inside_ogg::OggStreamReader;
+
+use assets::prelude::AudioClip;
+
+/// The encoded data of streamed clip.
+struct StreamBytes(Arc<AudioClip>);
+
+impl AsRef<[u8]> for StreamBytes {
+    fn as_ref(&self) -> &[u8] {
+        self.0.stream.as_ref().map(|v| &v[..]).unwrap_or(&[])
+    }
+}
+
+/// Decodes the packets of streamed clip on demand, only the samples of current packet
+/// are kept in memory.
+///
+/// Parsing the headers of stream is expensive, so the streams are created on the main
+/// thread and sent to the mixer, instead of being created in the audio callbacks.
+pub struct Stream {
+    clip: Arc<AudioClip>,
+    reader: OggStreamReader<Cursor<StreamBytes>>,
+    buffer: Vec<i16>,
+    // The index of the first frame in buffer.
+    start: usize,
+}
+
+impl Stream {
+    /// Creates a stream that decodes from the beginning of `clip`, the first packet is
+    /// decoded in advance.
+    pub fn new(clip: Arc<AudioClip>) -> Result<Self> {
+        let reader = OggStreamReader::new(Cursor::new(StreamBytes(clip.clone())))?;
+
+        let mut stream = Stream {
+            clip: clip,
+            reader: reader,
+            buffer: Vec::new(),
+            start: 0,
+        };
+
+        stream.seek(0)?;
+        Ok(stream)
+    }
+
+    /// The clip that is decoded.
+    #[inline]
+    pub fn clip(&self) -> &Arc<AudioClip> {
+        &self.clip
+    }
+
+    #[inline]
+    fn frames(&self) -> usize {
+        self.buffer.len() / (self.clip.channels as usize)
+    }
+
+    /// Gets the sample at `frame`, which should have been decoded with `seek`.
+    pub fn sample(&self, frame: usize, channel: u8) -> Option<i16> {
+        if frame < self.start {
+            return None;
+        }
+
+        let channels = self.clip.channels as usize;
+        let idx = (frame - self.start) * channels + (channel as usize % channels);
+        self.buffer.get(idx).cloned()
+    }
+
+    /// Decodes the packets until `frame`. Returns false if the stream has ended before it,
+    /// the total number of frames is returned by `len` then.
+    pub fn seek(&mut self, frame: usize) -> Result<bool> {
+        while frame >= self.start + self.frames() {
+            self.start += self.frames();
+            self.buffer.clear();
+
+            match self.reader.read_dec_packet_itl()? {
+                Some(v) => self.buffer = v,
+                None => return Ok(false),
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// The number of frames that have been decoded.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.start + self.frames()
+    }
+}
+
+impl fmt::Debug for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Stream")
+            .field("start", &self.start)
+            .field("frames", &self.frames())
+            .finish()
+    }
+}
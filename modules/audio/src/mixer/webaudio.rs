@@ -8,18 +8,18 @@ use wasm_bindgen::JsCast;
 use web_sys::{AudioContext, AudioProcessingEvent};
 
 use super::sampler::Sampler;
-use super::Command;
+use super::{Command, Rewinds};
 
 const CHANNELS: u8 = 2;
 
-pub fn run(rx: Arc<RwLock<Vec<Command>>>) -> Result<()> {
+pub fn run(rx: Arc<RwLock<Vec<Command>>>, rewinds: Rewinds) -> Result<()> {
     info!("Create web audio mixer.",);
 
     let ctx = AudioContext::new().unwrap();
 
     let closure = Rc::new(RefCell::new(None));
     let clone = closure.clone();
-    let mut sampler = Sampler::new(CHANNELS, ctx.sample_rate() as u32);
+    let mut sampler = Sampler::new(CHANNELS, ctx.sample_rate() as u32, rewinds);
 
     let mut bufs = Vec::new();
     for _ in 0..CHANNELS {
@@ -1,6 +1,7 @@
 use crayon::math::prelude::Vector3;
 
 use assets::prelude::AudioClipHandle;
+use bus::AudioBusHandle;
 
 impl_handle!(AudioSourceHandle);
 
@@ -16,6 +17,8 @@ pub struct AudioSource {
     pub loops: AudioSourceWrap,
     /// Sets the spatial information of playing sound.
     pub attenuation: Option<AudioSourceAttenuation>,
+    /// Sets the bus that playing sound is sent to, or the master output if `None`.
+    pub bus: Option<AudioBusHandle>,
 }
 
 impl From<AudioClipHandle> for AudioSource {
@@ -26,6 +29,7 @@ impl From<AudioClipHandle> for AudioSource {
             pitch: 1.0,
             loops: AudioSourceWrap::Repeat(1),
             attenuation: None,
+            bus: None,
         }
     }
 }
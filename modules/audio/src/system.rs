@@ -7,23 +7,27 @@ use crayon::res::utils::prelude::{ResourcePool, ResourceState};
 use crayon::uuid::Uuid;
 
 use super::assets::prelude::{AudioClipHandle, AudioClipLoader};
+use super::bus::{AudioBusHandle, AudioBusParams};
 use super::mixer::Mixer;
 use super::source::{AudioSource, AudioSourceHandle};
 
 /// The centralized management of audio sub-system.
 pub struct AudioSystem {
     lis: LifecycleListenerHandle,
+    loader: AudioClipLoader,
     clips: Arc<RwLock<ResourcePool<AudioClipHandle, AudioClipLoader>>>,
-    mixer: Mixer,
+    mixer: Arc<Mixer>,
 }
 
 struct AudioState {
     clips: Arc<RwLock<ResourcePool<AudioClipHandle, AudioClipLoader>>>,
+    mixer: Arc<Mixer>,
 }
 
 impl LifecycleListener for AudioState {
     fn on_pre_update(&mut self) -> Result<()> {
         self.clips.write().unwrap().advance()?;
+        self.mixer.advance();
         Ok(())
     }
 }
@@ -36,19 +40,22 @@ impl Drop for AudioSystem {
 
 impl AudioSystem {
     pub fn new() -> Result<Self> {
-        let clips = Arc::new(RwLock::new(ResourcePool::new(AudioClipLoader::new())));
-        let mixer = if crayon::application::headless() {
+        let loader = AudioClipLoader::new();
+        let clips = Arc::new(RwLock::new(ResourcePool::new(loader.clone())));
+        let mixer = Arc::new(if crayon::application::headless() {
             Mixer::headless(clips.clone())?
         } else {
             Mixer::new(clips.clone())?
-        };
+        });
 
         let state = AudioState {
             clips: clips.clone(),
+            mixer: mixer.clone(),
         };
 
         Ok(AudioSystem {
             lis: crayon::application::attach(state),
+            loader: loader,
             clips: clips,
             mixer: mixer,
         })
@@ -63,6 +70,13 @@ impl AudioSystem {
         self.mixer.set_listener(position.into());
     }
 
+    /// Sets the size in bytes of encoded data, above which the clips created afterwards
+    /// are streamed.
+    #[inline]
+    pub fn set_streaming_threshold(&self, bytes: usize) {
+        self.loader.set_streaming_threshold(bytes);
+    }
+
     /// Creates a clip object from file asynchronously.
     #[inline]
     pub fn create_clip_from<T: AsRef<str>>(&self, url: T) -> Result<AudioClipHandle> {
@@ -121,4 +135,34 @@ impl AudioSystem {
     pub fn set_pitch(&self, handle: AudioSourceHandle, pitch: f32) {
         self.mixer.set_pitch(handle, pitch);
     }
+
+    /// Creates a bus that mixes the sounds sent to it, returning a `AudioBusHandle` for it.
+    #[inline]
+    pub fn create_bus(&self, params: AudioBusParams) -> Result<AudioBusHandle> {
+        self.mixer.create_bus(params)
+    }
+
+    /// Deletes a bus, the sounds and buses sent to it are sent to the master output then.
+    #[inline]
+    pub fn delete_bus(&self, handle: AudioBusHandle) {
+        self.mixer.delete_bus(handle);
+    }
+
+    /// Sets the volume of a bus.
+    #[inline]
+    pub fn set_bus_volume(&self, handle: AudioBusHandle, volume: f32) {
+        self.mixer.set_bus_volume(handle, volume);
+    }
+
+    /// Mutes or unmutes a bus.
+    #[inline]
+    pub fn set_bus_mute(&self, handle: AudioBusHandle, mute: bool) {
+        self.mixer.set_bus_mute(handle, mute);
+    }
+
+    /// Sets the cutoff frequency in Hz of the low-pass filter of a bus.
+    #[inline]
+    pub fn set_bus_low_pass(&self, handle: AudioBusHandle, cutoff: Option<f32>) {
+        self.mixer.set_bus_low_pass(handle, cutoff);
+    }
 }
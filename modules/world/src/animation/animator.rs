@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use crayon::math::prelude::{InnerSpace, Matrix4};
+
+use assets::prelude::{AnimationClip, AnimationClipHandle, AnimationEvent};
+use assets::prelude::{AnimationController, AnimationControllerHandle, AnimationParameter};
+use assets::prelude::{Skeleton, SkeletonHandle};
+use spatial::prelude::Transform;
+
+use super::controller::StateMachine;
+
+/// The `Animator` component plays animation clips on a skeleton, and blends between
+/// them smoothly when cross-fading. The resulting skinning matrices will be exposed
+/// to the `MeshRenderer` on the same entity, so skinned meshes could be deformed in
+/// vertex shader.
+///
+/// Instead of playing clips directly, the animator could also be driven by an
+/// `AnimationController`, whose states and transitions are controlled by the parameters
+/// set from game code.
+#[derive(Debug, Clone)]
+pub struct Animator {
+    /// The skeleton animated by this animator.
+    pub skeleton: SkeletonHandle,
+    /// The playback speed of clips.
+    pub speed: f32,
+
+    current: Option<Playback>,
+    previous: Option<Playback>,
+    fade: (f32, f32),
+    machine: Option<StateMachine>,
+
+    pose: Vec<Transform>,
+    blend: Vec<Transform>,
+    skinning: Vec<Matrix4<f32>>,
+    events: Vec<(AnimationClipHandle, AnimationEvent)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Playback {
+    clip: AnimationClipHandle,
+    time: f32,
+    looping: bool,
+}
+
+impl Playback {
+    fn advance(&mut self, dt: f32, duration: f32) {
+        self.time += dt;
+
+        if self.looping && duration > 0.0 {
+            self.time %= duration;
+        } else {
+            self.time = self.time.min(duration);
+        }
+    }
+}
+
+impl Animator {
+    pub fn new(skeleton: SkeletonHandle) -> Self {
+        Animator {
+            skeleton,
+            speed: 1.0,
+            current: None,
+            previous: None,
+            fade: (0.0, 0.0),
+            machine: None,
+            pose: Vec::new(),
+            blend: Vec::new(),
+            skinning: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Plays the animation clip immediately, the controller will be removed.
+    pub fn play(&mut self, clip: AnimationClipHandle, looping: bool) {
+        self.machine = None;
+        self.current = Some(Playback {
+            clip,
+            time: 0.0,
+            looping,
+        });
+
+        self.previous = None;
+    }
+
+    /// Fades the animation clip in over `duration` seconds, and fades the current
+    /// playing clip out. The controller will be removed.
+    pub fn cross_fade(&mut self, clip: AnimationClipHandle, looping: bool, duration: f32) {
+        self.machine = None;
+
+        if duration <= 0.0 || self.current.is_none() {
+            self.play(clip, looping);
+            return;
+        }
+
+        self.previous = self.current.take();
+        self.current = Some(Playback {
+            clip,
+            time: 0.0,
+            looping,
+        });
+
+        self.fade = (0.0, duration);
+    }
+
+    /// Stops playing, the skeleton will be reset to bind pose.
+    pub fn stop(&mut self) {
+        self.machine = None;
+        self.current = None;
+        self.previous = None;
+    }
+
+    /// Drives this animator with the controller, which starts from its default state.
+    /// The clips that are playing will be stopped.
+    pub fn set_controller(&mut self, controller: Option<AnimationControllerHandle>) {
+        self.current = None;
+        self.previous = None;
+        self.machine = controller.map(StateMachine::new);
+    }
+
+    /// Gets the controller that drives this animator.
+    #[inline]
+    pub fn controller(&self) -> Option<AnimationControllerHandle> {
+        self.machine.as_ref().map(|v| v.handle)
+    }
+
+    /// Sets the float parameter of controller.
+    #[inline]
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.set_parameter(name, AnimationParameter::Float(value));
+    }
+
+    /// Sets the bool parameter of controller.
+    #[inline]
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.set_parameter(name, AnimationParameter::Bool(value));
+    }
+
+    /// Sets the trigger parameter of controller, which is reset once a transition that
+    /// tests it is taken.
+    #[inline]
+    pub fn set_trigger(&mut self, name: &str) {
+        self.set_parameter(name, AnimationParameter::Trigger(true));
+    }
+
+    /// Resets the trigger parameter of controller.
+    #[inline]
+    pub fn reset_trigger(&mut self, name: &str) {
+        self.set_parameter(name, AnimationParameter::Trigger(false));
+    }
+
+    /// Sets the parameter of controller. It's ignored if there is no controller.
+    pub fn set_parameter(&mut self, name: &str, value: AnimationParameter) {
+        if let Some(ref mut machine) = self.machine {
+            machine.set(name, value);
+        }
+    }
+
+    /// Gets the parameter of controller.
+    #[inline]
+    pub fn parameter(&self, name: &str) -> Option<AnimationParameter> {
+        self.machine.as_ref().and_then(|v| v.get(name))
+    }
+
+    /// Gets the index of current state in controller.
+    #[inline]
+    pub fn state(&self) -> Option<usize> {
+        self.machine.as_ref().and_then(|v| v.state())
+    }
+
+    /// Gets the normalized time of current state in controller.
+    #[inline]
+    pub fn state_time(&self) -> f32 {
+        self.machine.as_ref().map(|v| v.time()).unwrap_or(0.0)
+    }
+
+    /// Gets the animation clip that is playing.
+    #[inline]
+    pub fn clip(&self) -> Option<AnimationClipHandle> {
+        self.current.map(|v| v.clip)
+    }
+
+    /// Gets the playback position of current animation clip in seconds.
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.current.map(|v| v.time).unwrap_or(0.0)
+    }
+
+    /// Returns true if it is cross-fading between clips.
+    #[inline]
+    pub fn is_fading(&self) -> bool {
+        self.previous.is_some() || self.machine.as_ref().map(|v| v.is_fading()) == Some(true)
+    }
+
+    /// Gets the local transforms of bones evaluated in last update.
+    #[inline]
+    pub fn pose(&self) -> &[Transform] {
+        &self.pose
+    }
+
+    /// Gets the skinning matrices evaluated in last update.
+    #[inline]
+    pub fn skinning_matrices(&self) -> &[Matrix4<f32>] {
+        &self.skinning
+    }
+
+    /// Gets the events of clips that are crossed in last update.
+    #[inline]
+    pub(crate) fn events(&self) -> &[(AnimationClipHandle, AnimationEvent)] {
+        &self.events
+    }
+
+    /// Advances the playback by `dt` seconds and evaluates the skinning matrices.
+    pub(crate) fn advance(&mut self, dt: f32) {
+        if let Some(skeleton) = crate::skeleton(self.skeleton) {
+            self.evaluate(
+                &skeleton,
+                dt,
+                crate::animation_controller,
+                crate::animation_clip,
+            );
+        }
+    }
+
+    fn evaluate<F1, F2>(&mut self, skeleton: &Skeleton, dt: f32, controllers: F1, clips: F2)
+    where
+        F1: Fn(AnimationControllerHandle) -> Option<Arc<AnimationController>>,
+        F2: Fn(AnimationClipHandle) -> Option<Arc<AnimationClip>>,
+    {
+        let dt = dt * self.speed;
+
+        self.events.clear();
+        self.pose.clear();
+        self.pose
+            .extend(skeleton.bones.iter().map(|v| v.local_transform));
+
+        if let Some(ref mut machine) = self.machine {
+            if let Some(controller) = controllers(machine.handle) {
+                machine.evaluate(&controller, dt, clips, &mut self.pose, &mut self.events);
+            }
+
+            skeleton.skinning(&self.pose, &mut self.skinning);
+            return;
+        }
+
+        if let Some(ref mut playback) = self.current {
+            if let Some(clip) = clips(playback.clip) {
+                let (from, handle) = (playback.time, playback.clip);
+                playback.advance(dt, clip.duration);
+                clip.sample(playback.time, &mut self.pose);
+
+                // The events of clip that is fading out are not fired.
+                let events = &mut self.events;
+                clip.crossed_events(from, playback.time, |v| events.push((handle, v.clone())));
+            }
+        }
+
+        if let Some(mut playback) = self.previous {
+            self.fade.0 += dt;
+
+            if self.fade.0 >= self.fade.1 {
+                self.previous = None;
+            } else if let Some(clip) = clips(playback.clip) {
+                playback.advance(dt, clip.duration);
+                self.previous = Some(playback);
+
+                self.blend.clear();
+                self.blend
+                    .extend(skeleton.bones.iter().map(|v| v.local_transform));
+                clip.sample(playback.time, &mut self.blend);
+
+                let weight = self.fade.0 / self.fade.1;
+                for (dst, src) in self.pose.iter_mut().zip(self.blend.iter()) {
+                    *dst = interpolate(src, dst, weight);
+                }
+            }
+        }
+
+        skeleton.skinning(&self.pose, &mut self.skinning);
+    }
+}
+
+pub(crate) fn interpolate(lhs: &Transform, rhs: &Transform, t: f32) -> Transform {
+    Transform {
+        position: lhs.position.lerp(rhs.position, t),
+        rotation: lhs.rotation.slerp(rhs.rotation, t),
+        scale: lhs.scale + (rhs.scale - lhs.scale) * t,
+    }
+}
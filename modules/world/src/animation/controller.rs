@@ -0,0 +1,508 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use assets::prelude::{AnimationClip, AnimationClipHandle, AnimationEvent};
+use assets::prelude::{AnimationCondition, AnimationController, AnimationControllerHandle};
+use assets::prelude::{AnimationMotion, AnimationParameter};
+use spatial::prelude::Transform;
+
+use super::animator::interpolate;
+
+/// The runtime states of `AnimationController` per `Animator`.
+#[derive(Debug, Clone)]
+pub(crate) struct StateMachine {
+    pub handle: AnimationControllerHandle,
+    parameters: BTreeMap<String, AnimationParameter>,
+    initialized: bool,
+
+    current: Option<Playback>,
+    previous: Option<Playback>,
+    fade: (f32, f32),
+
+    bind: Vec<Transform>,
+    blend: Vec<Transform>,
+    scratch: Vec<Transform>,
+    weights: Vec<(usize, f32)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Playback {
+    state: usize,
+    /// The normalized time, which is in [0, 1) for looping states.
+    time: f32,
+}
+
+impl StateMachine {
+    pub fn new(handle: AnimationControllerHandle) -> Self {
+        StateMachine {
+            handle: handle,
+            parameters: BTreeMap::new(),
+            initialized: false,
+            current: None,
+            previous: None,
+            fade: (0.0, 0.0),
+            bind: Vec::new(),
+            blend: Vec::new(),
+            scratch: Vec::new(),
+            weights: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn set(&mut self, name: &str, value: AnimationParameter) {
+        self.parameters.insert(name.to_owned(), value);
+    }
+
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<AnimationParameter> {
+        self.parameters.get(name).cloned()
+    }
+
+    #[inline]
+    pub fn state(&self) -> Option<usize> {
+        self.current.map(|v| v.state)
+    }
+
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.current.map(|v| v.time).unwrap_or(0.0)
+    }
+
+    #[inline]
+    pub fn is_fading(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    /// Takes the transitions whose conditions are met, advances the states by `dt` seconds,
+    /// and evaluates the local transforms of bones into `pose`, which is filled with the
+    /// bind pose already. The events crossed by current state are pushed into `events`.
+    pub fn evaluate<F>(
+        &mut self,
+        controller: &AnimationController,
+        dt: f32,
+        clips: F,
+        pose: &mut Vec<Transform>,
+        events: &mut Vec<(AnimationClipHandle, AnimationEvent)>,
+    ) where
+        F: Fn(AnimationClipHandle) -> Option<Arc<AnimationClip>>,
+    {
+        if !self.initialized {
+            // The values that have been set before controller is ready are kept.
+            for (k, v) in &controller.parameters {
+                self.parameters.entry(k.clone()).or_insert(*v);
+            }
+
+            self.initialized = true;
+        }
+
+        // The machine is reset if the controller is hot-reloaded with fewer states.
+        let len = controller.states.len();
+        if self.current.map(|v| v.state >= len).unwrap_or(true) {
+            self.current = Some(Playback {
+                state: controller.default_state,
+                time: 0.0,
+            });
+
+            self.previous = None;
+        }
+
+        if self.previous.map(|v| v.state >= len).unwrap_or(false) {
+            self.previous = None;
+        }
+
+        self.transit(controller);
+
+        self.bind.clear();
+        self.bind.extend_from_slice(pose);
+
+        if let Some(mut playback) = self.current {
+            let from = playback.time;
+            self.advance(controller, &mut playback, dt, &clips);
+            self.current = Some(playback);
+
+            // Only the events of the dominant clip in blend tree are fired, so the events
+            // like footsteps are not duplicated.
+            let dominant = self
+                .weights
+                .iter()
+                .max_by(|lhs, rhs| lhs.1.partial_cmp(&rhs.1).unwrap_or(Ordering::Equal))
+                .and_then(|v| controller.clips.get(v.0).cloned());
+
+            if let Some(handle) = dominant {
+                if let Some(clip) = clips(handle) {
+                    let (from, to) = (from * clip.duration, playback.time * clip.duration);
+                    clip.crossed_events(from, to, |v| events.push((handle, v.clone())));
+                }
+            }
+
+            self.sample(controller, playback, &clips, pose);
+        }
+
+        if let Some(mut playback) = self.previous {
+            self.fade.0 += dt;
+
+            if self.fade.0 >= self.fade.1 {
+                self.previous = None;
+            } else {
+                self.advance(controller, &mut playback, dt, &clips);
+                self.previous = Some(playback);
+
+                let mut blend = ::std::mem::replace(&mut self.blend, Vec::new());
+                blend.clear();
+                blend.extend_from_slice(&self.bind);
+                self.sample(controller, playback, &clips, &mut blend);
+
+                let weight = self.fade.0 / self.fade.1;
+                for (dst, src) in pose.iter_mut().zip(blend.iter()) {
+                    *dst = interpolate(src, dst, weight);
+                }
+
+                self.blend = blend;
+            }
+        }
+    }
+
+    /// Takes the first transition from current state whose conditions are met, and
+    /// consumes the triggers it tested.
+    fn transit(&mut self, controller: &AnimationController) {
+        let current = match self.current {
+            Some(v) => v,
+            None => return,
+        };
+
+        let parameters = &self.parameters;
+        let transition = controller.transitions.iter().find(|v| {
+            let from = match v.from {
+                Some(from) => from == current.state,
+                None => v.to != current.state,
+            };
+
+            from && v.exit_time.map(|t| current.time >= t).unwrap_or(true)
+                && v.conditions.iter().all(|c| test(parameters, c))
+        });
+
+        if let Some(transition) = transition {
+            for v in &transition.conditions {
+                if let AnimationCondition::If(ref name) = *v {
+                    if let Some(&mut AnimationParameter::Trigger(ref mut set)) =
+                        self.parameters.get_mut(name)
+                    {
+                        *set = false;
+                    }
+                }
+            }
+
+            self.current = Some(Playback {
+                state: transition.to,
+                time: 0.0,
+            });
+
+            if transition.duration > 0.0 {
+                self.previous = Some(current);
+                self.fade = (0.0, transition.duration);
+            } else {
+                self.previous = None;
+            }
+        }
+    }
+
+    fn advance<F>(
+        &mut self,
+        controller: &AnimationController,
+        playback: &mut Playback,
+        dt: f32,
+        clips: &F,
+    ) where
+        F: Fn(AnimationClipHandle) -> Option<Arc<AnimationClip>>,
+    {
+        let state = &controller.states[playback.state];
+        weights(&state.motion, &self.parameters, &mut self.weights);
+
+        // The duration of blend tree is weighted by the children, so the clips with
+        // different lengths are played in sync.
+        let mut duration = 0.0;
+        for &(index, weight) in &self.weights {
+            if let Some(clip) = controller.clips.get(index).and_then(|&v| clips(v)) {
+                duration += clip.duration * weight;
+            }
+        }
+
+        if duration <= 0.0 {
+            return;
+        }
+
+        playback.time += dt * state.speed / duration;
+        if state.looping {
+            playback.time -= playback.time.floor();
+        } else {
+            playback.time = playback.time.max(0.0).min(1.0);
+        }
+    }
+
+    fn sample<F>(
+        &mut self,
+        controller: &AnimationController,
+        playback: Playback,
+        clips: &F,
+        pose: &mut [Transform],
+    ) where
+        F: Fn(AnimationClipHandle) -> Option<Arc<AnimationClip>>,
+    {
+        let state = &controller.states[playback.state];
+        weights(&state.motion, &self.parameters, &mut self.weights);
+
+        let mut total = 0.0;
+        for &(index, weight) in &self.weights {
+            let clip = match controller.clips.get(index).and_then(|&v| clips(v)) {
+                Some(clip) => clip,
+                None => continue,
+            };
+
+            let time = playback.time * clip.duration;
+            if total <= 0.0 {
+                clip.sample(time, pose);
+            } else {
+                self.scratch.clear();
+                self.scratch.extend_from_slice(&self.bind);
+                clip.sample(time, &mut self.scratch);
+
+                // Accumulates the weighted average incrementally.
+                let t = weight / (total + weight);
+                for (dst, src) in pose.iter_mut().zip(self.scratch.iter()) {
+                    *dst = interpolate(dst, src, t);
+                }
+            }
+
+            total += weight;
+        }
+    }
+}
+
+fn float(parameters: &BTreeMap<String, AnimationParameter>, name: &str) -> f32 {
+    match parameters.get(name) {
+        Some(&AnimationParameter::Float(v)) => v,
+        _ => 0.0,
+    }
+}
+
+fn test(parameters: &BTreeMap<String, AnimationParameter>, condition: &AnimationCondition) -> bool {
+    let set = |name: &str| match parameters.get(name) {
+        Some(&AnimationParameter::Bool(v)) | Some(&AnimationParameter::Trigger(v)) => v,
+        _ => false,
+    };
+
+    match *condition {
+        AnimationCondition::Greater(ref name, v) => float(parameters, name) > v,
+        AnimationCondition::Less(ref name, v) => float(parameters, name) < v,
+        AnimationCondition::If(ref name) => set(name),
+        AnimationCondition::IfNot(ref name) => !set(name),
+    }
+}
+
+/// Evaluates the normalized weights of clips in motion, the clips with zero or invalid
+/// weights are skipped.
+fn weights(
+    motion: &AnimationMotion,
+    parameters: &BTreeMap<String, AnimationParameter>,
+    out: &mut Vec<(usize, f32)>,
+) {
+    out.clear();
+
+    match *motion {
+        AnimationMotion::Clip(clip) => out.push((clip, 1.0)),
+        AnimationMotion::Blend1D {
+            ref parameter,
+            ref children,
+        } => {
+            let v = float(parameters, parameter);
+            match children.iter().position(|c| c.0 > v) {
+                Some(0) => out.push((children[0].1, 1.0)),
+                Some(next) => {
+                    let (lhs, rhs) = (children[next - 1], children[next]);
+                    let t = (v - lhs.0) / (rhs.0 - lhs.0);
+                    out.push((lhs.1, 1.0 - t));
+                    out.push((rhs.1, t));
+                }
+                None => {
+                    if let Some(last) = children.last() {
+                        out.push((last.1, 1.0));
+                    }
+                }
+            }
+        }
+        AnimationMotion::Blend2D {
+            parameters: ref names,
+            ref children,
+        } => {
+            let p = [float(parameters, &names.0), float(parameters, &names.1)];
+
+            // Gradient band interpolation, each child is weighted by the minimum of its
+            // influences against the other children.
+            for (i, lhs) in children.iter().enumerate() {
+                let mut weight = 1.0f32;
+                for (j, rhs) in children.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+
+                    let d = [rhs.0[0] - lhs.0[0], rhs.0[1] - lhs.0[1]];
+                    let len2 = d[0] * d[0] + d[1] * d[1];
+                    if len2 > 0.0 {
+                        let dot = (p[0] - lhs.0[0]) * d[0] + (p[1] - lhs.0[1]) * d[1];
+                        weight = weight.min(1.0 - dot / len2);
+                    }
+                }
+
+                out.push((lhs.1, weight.max(0.0)));
+            }
+        }
+    }
+
+    out.retain(|v| v.1.is_finite() && v.1 > 0.0);
+
+    let total: f32 = out.iter().map(|v| v.1).sum();
+    if total > 0.0 {
+        for v in out.iter_mut() {
+            v.1 /= total;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assets::prelude::*;
+    use crayon::math::prelude::Vector3;
+    use crayon::utils::handle::Handle;
+
+    fn clip(duration: f32, x: f32) -> Arc<AnimationClip> {
+        let mut channel = AnimationChannel::default();
+        channel
+            .positions
+            .push(Keyframe::new(0.0, Vector3::new(x, 0.0, 0.0)));
+
+        Arc::new(AnimationClip {
+            duration: duration,
+            channels: vec![channel],
+            events: vec![AnimationEvent::new(0.5, "step")],
+        })
+    }
+
+    #[test]
+    fn blend() {
+        let motion = AnimationMotion::Blend2D {
+            parameters: ("x".into(), "y".into()),
+            children: vec![([0.0, 0.0], 0), ([1.0, 0.0], 1), ([0.0, 1.0], 2)],
+        };
+
+        let mut parameters = BTreeMap::new();
+        let mut out = Vec::new();
+        weights(&motion, &parameters, &mut out);
+        assert_eq!(out, vec![(0, 1.0)]);
+
+        parameters.insert("x".into(), AnimationParameter::Float(0.5));
+        weights(&motion, &parameters, &mut out);
+        assert_eq!(out, vec![(0, 0.5), (1, 0.5)]);
+
+        let motion = AnimationMotion::Blend1D {
+            parameter: "x".into(),
+            children: vec![(0.0, 0), (1.0, 1)],
+        };
+
+        weights(&motion, &parameters, &mut out);
+        assert_eq!(out, vec![(0, 0.5), (1, 0.5)]);
+        parameters.insert("x".into(), AnimationParameter::Float(2.0));
+        weights(&motion, &parameters, &mut out);
+        assert_eq!(out, vec![(1, 1.0)]);
+
+        let motion = AnimationMotion::Blend1D {
+            parameter: "x".into(),
+            children: vec![(0.0, 0), (::std::f32::NAN, 1), (1.0, 2)],
+        };
+
+        parameters.insert("x".into(), AnimationParameter::Float(0.5));
+        weights(&motion, &parameters, &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn transitions() {
+        let (walk, run) = (Handle::new(1, 1).into(), Handle::new(2, 1).into());
+        let clips = |v| {
+            Some(if v == walk {
+                clip(1.0, 1.0)
+            } else {
+                clip(2.0, 3.0)
+            })
+        };
+
+        let mut controller = AnimationController::new();
+        controller.add_parameter("speed", AnimationParameter::Float(0.0));
+        controller.add_parameter("jump", AnimationParameter::Trigger(false));
+
+        let (walk, run) = (controller.add_clip(walk), controller.add_clip(run));
+        let motion = AnimationMotion::Blend1D {
+            parameter: "speed".into(),
+            children: vec![(0.0, walk), (1.0, run)],
+        };
+
+        let locomotion = controller.add_state(AnimationState::new("locomotion", motion));
+        let mut jump = AnimationState::new("jump", AnimationMotion::Clip(walk));
+        jump.looping = false;
+        let jump = controller.add_state(jump);
+
+        let condition = AnimationCondition::If("jump".into());
+        controller.add_transition(AnimationTransition::new(None, jump, 0.0).when(condition));
+        controller.add_transition(AnimationTransition::new(Some(jump), locomotion, 0.0).after(1.0));
+        controller.validate().unwrap();
+
+        let mut machine = StateMachine::new(Handle::new(1, 1).into());
+        machine.set("speed", AnimationParameter::Float(0.5));
+
+        let mut pose = vec![Transform::default()];
+        let mut events = Vec::new();
+        machine.evaluate(&controller, 0.3, &clips, &mut pose, &mut events);
+        assert_eq!(machine.state(), Some(locomotion));
+        assert_eq!(pose[0].position.x, 2.0);
+        assert!((machine.time() - 0.2).abs() < 1e-6);
+        assert!(events.is_empty());
+
+        machine.set("jump", AnimationParameter::Trigger(true));
+        machine.evaluate(&controller, 0.5, &clips, &mut pose, &mut events);
+        assert_eq!(machine.state(), Some(jump));
+        assert_eq!(
+            machine.get("jump"),
+            Some(AnimationParameter::Trigger(false))
+        );
+        assert_eq!(pose[0].position.x, 1.0);
+
+        assert!(events.is_empty());
+
+        // The event at 0.5s of jump is crossed.
+        machine.evaluate(&controller, 1.0, &clips, &mut pose, &mut events);
+        assert_eq!(machine.time(), 1.0);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].1.name, "step");
+
+        events.clear();
+        machine.evaluate(&controller, 0.0, &clips, &mut pose, &mut events);
+        assert!(events.is_empty());
+        assert_eq!(machine.state(), Some(locomotion));
+
+        // The invalid parameters never poison the blending.
+        machine.set("speed", AnimationParameter::Float(::std::f32::NAN));
+        machine.evaluate(&controller, 0.1, &clips, &mut pose, &mut events);
+        assert!(pose[0].position.x.is_finite());
+
+        // The state out of range of the reloaded controller is reset.
+        machine.set("jump", AnimationParameter::Trigger(true));
+        machine.evaluate(&controller, 0.1, &clips, &mut pose, &mut events);
+        assert_eq!(machine.state(), Some(jump));
+
+        controller.states.truncate(1);
+        controller.transitions.clear();
+        machine.evaluate(&controller, 0.1, &clips, &mut pose, &mut events);
+        assert_eq!(machine.state(), Some(locomotion));
+    }
+}
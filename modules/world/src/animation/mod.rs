@@ -0,0 +1,104 @@
+//! Skeletal animations, which deform skinned meshes with the bones of skeleton.
+//!
+//! The `Animator` component plays `AnimationClip`s on a `Skeleton`, and the skinning
+//! matrices will be copied into the `MeshRenderer` on the same entity every frame.
+//! Then renderers could upload them to do GPU skinning in vertex shader.
+//!
+//! Animators could also be driven by `AnimationController`s, which are state machines
+//! of clips and blend trees. The transitions between states are controlled by the
+//! parameters set from game code, e.g. `animator.set_float("speed", 2.0)`.
+
+mod animator;
+mod controller;
+
+pub mod prelude {
+    pub use super::animator::Animator;
+    pub use super::{AnimationNotify, Animations};
+}
+
+use assets::prelude::{AnimationClipHandle, AnimationEvent};
+use renderable::Renderable;
+use utils::dispatcher::StoragePtrs;
+use utils::prelude::Component;
+use Entity;
+
+use self::animator::Animator;
+
+/// An event of clip that has been crossed by the playback of `Animator`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationNotify {
+    pub ent: Entity,
+    pub clip: AnimationClipHandle,
+    pub event: AnimationEvent,
+}
+
+pub struct Animations {
+    animators: Component<Animator>,
+    events: Vec<AnimationNotify>,
+}
+
+impl Animations {
+    pub fn new() -> Self {
+        Animations {
+            animators: Component::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Exposes the storage of animators to the systems of `Dispatcher`.
+    pub(crate) fn expose(&mut self, ptrs: &mut StoragePtrs) {
+        ptrs.insert(&mut self.animators);
+    }
+
+    #[inline]
+    pub fn add_animator(&mut self, ent: Entity, animator: Animator) {
+        self.animators.add(ent, animator);
+    }
+
+    #[inline]
+    pub fn animator(&self, ent: Entity) -> Option<&Animator> {
+        self.animators.get(ent)
+    }
+
+    #[inline]
+    pub fn animator_mut(&mut self, ent: Entity) -> Option<&mut Animator> {
+        self.animators.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_animator(&mut self, ent: Entity) {
+        self.animators.remove(ent);
+    }
+}
+
+impl Animations {
+    /// Gets the events of clips that are fired in last advance, in the order of entities
+    /// and then time.
+    #[inline]
+    pub fn events(&self) -> &[AnimationNotify] {
+        &self.events
+    }
+
+    /// Advances all the animators by `dt` seconds, and exposes the skinning matrices
+    /// to mesh renderers.
+    pub fn advance(&mut self, dt: f32, renderables: &mut Renderable) {
+        self.events.clear();
+
+        for (i, v) in self.animators.data.iter_mut().enumerate() {
+            v.advance(dt);
+
+            let ent = self.animators.entities[i];
+            self.events
+                .extend(v.events().iter().map(|&(clip, ref event)| AnimationNotify {
+                    ent: ent,
+                    clip: clip,
+                    event: event.clone(),
+                }));
+
+            if let Some(mesh) = renderables.mesh_mut(self.animators.entities[i]) {
+                mesh.skinning.clear();
+                mesh.skinning.extend_from_slice(v.skinning_matrices());
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use crayon::errors::*;
+use crayon::math::prelude::{InnerSpace, Quaternion, Vector3};
+use crayon::res::utils::prelude::ResourceState;
+use crayon::sched::prelude::LatchProbe;
+
+use spatial::prelude::Transform;
+
+impl_handle!(AnimationClipHandle);
+
+/// An animation clip stores the keyframes of bones over a period of time.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AnimationClip {
+    /// The length of this clip in seconds.
+    pub duration: f32,
+    /// The animated channels of bones.
+    pub channels: Vec<AnimationChannel>,
+    /// The events sorted by time, which are fired when the playback crosses them.
+    pub events: Vec<AnimationEvent>,
+}
+
+/// An event attached to the timeline of clip, e.g. plays the footstep sound when the
+/// foot touches the ground. The fired events are read with `Animations::events`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnimationEvent {
+    /// The time of this event in seconds.
+    pub time: f32,
+    pub name: String,
+    /// The user defined payload, which is empty by default.
+    #[serde(default)]
+    pub payload: String,
+}
+
+impl AnimationEvent {
+    pub fn new<T: Into<String>>(time: f32, name: T) -> Self {
+        AnimationEvent {
+            time: time,
+            name: name.into(),
+            payload: String::new(),
+        }
+    }
+}
+
+/// The keyframes that animate a single bone. The keyframes of each property must
+/// be sorted by time, and empty properties keep the values of bind pose.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AnimationChannel {
+    /// The index of bone in skeleton.
+    pub bone: usize,
+    pub positions: Vec<Keyframe<Vector3<f32>>>,
+    pub rotations: Vec<Keyframe<Quaternion<f32>>>,
+    pub scales: Vec<Keyframe<f32>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Keyframe<T> {
+    /// The time of this keyframe in seconds.
+    pub time: f32,
+    pub value: T,
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f32, value: T) -> Self {
+        Keyframe { time, value }
+    }
+}
+
+impl AnimationClip {
+    pub fn validate(&self) -> Result<()> {
+        fn sorted<T>(frames: &[Keyframe<T>]) -> bool {
+            frames.windows(2).all(|v| v[0].time <= v[1].time)
+        }
+
+        if self.duration < 0.0 {
+            bail!("The duration of animation clip should be positive.");
+        }
+
+        for v in &self.channels {
+            if !sorted(&v.positions) || !sorted(&v.rotations) || !sorted(&v.scales) {
+                bail!("The keyframes of bone {} are not sorted by time.", v.bone);
+            }
+        }
+
+        if self.events.windows(2).any(|v| v[0].time > v[1].time) {
+            bail!("The events of animation clip are not sorted by time.");
+        }
+
+        Ok(())
+    }
+
+    /// Samples the local transforms of bones at specified time, and writes them into `pose`.
+    /// Bones that are out of range of `pose` will be ignored.
+    pub fn sample(&self, time: f32, pose: &mut [Transform]) {
+        for v in &self.channels {
+            if let Some(transform) = pose.get_mut(v.bone) {
+                if let Some(position) = sample(&v.positions, time, |a, b, t| a.lerp(b, t)) {
+                    transform.position = position;
+                }
+
+                if let Some(rotation) = sample(&v.rotations, time, |a, b, t| a.slerp(b, t)) {
+                    transform.rotation = rotation;
+                }
+
+                if let Some(scale) = sample(&v.scales, time, |a, b, t| a + (b - a) * t) {
+                    transform.scale = scale;
+                }
+            }
+        }
+    }
+
+    /// Visits the events that are crossed when the playback moves from `from` to `to`,
+    /// which are in `[from, to)`. If `to` is less than `from`, the playback is considered
+    /// to have wrapped around the end of looping clip. The events at the end are included
+    /// once the playback reaches it.
+    pub fn crossed_events<F>(&self, from: f32, to: f32, mut visitor: F)
+    where
+        F: FnMut(&AnimationEvent),
+    {
+        let end = self.duration;
+        for v in &self.events {
+            let crossed = if to < from {
+                v.time >= from || v.time < to
+            } else {
+                (v.time >= from && v.time < to) || (v.time >= end && to >= end && from < to)
+            };
+
+            if crossed {
+                visitor(v);
+            }
+        }
+    }
+}
+
+fn sample<T, F>(frames: &[Keyframe<T>], time: f32, interpolate: F) -> Option<T>
+where
+    T: Copy,
+    F: Fn(T, T, f32) -> T,
+{
+    let first = frames.first()?;
+    if time <= first.time {
+        return Some(first.value);
+    }
+
+    let next = frames.iter().position(|v| v.time > time);
+    match next {
+        Some(next) => {
+            let (lhs, rhs) = (&frames[next - 1], &frames[next]);
+            let t = (time - lhs.time) / (rhs.time - lhs.time);
+            Some(interpolate(lhs.value, rhs.value, t))
+        }
+        None => frames.last().map(|v| v.value),
+    }
+}
+
+impl LatchProbe for AnimationClipHandle {
+    fn is_set(&self) -> bool {
+        ResourceState::NotReady != crate::animation_clip_state(*self)
+    }
+}
@@ -0,0 +1,55 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crayon::bincode;
+use crayon::errors::Result;
+use crayon::res::utils::prelude::ResourceLoader;
+
+use super::animation_clip::*;
+
+// The last byte is the version, which is 2 since the events are added to clips.
+pub const MAGIC: [u8; 8] = [
+    'A' as u8, 'N' as u8, 'I' as u8, 'M' as u8, ' ' as u8, 0, 0, 2,
+];
+
+#[derive(Clone)]
+pub struct AnimationClipLoader {}
+
+impl AnimationClipLoader {
+    pub fn new() -> Self {
+        AnimationClipLoader {}
+    }
+}
+
+impl ResourceLoader for AnimationClipLoader {
+    type Handle = AnimationClipHandle;
+    type Intermediate = AnimationClip;
+    type Resource = Arc<AnimationClip>;
+
+    fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
+        if &bytes[0..8] != &MAGIC[..] {
+            bail!("[AnimationClipLoader] MAGIC number not match.");
+        }
+
+        let mut file = Cursor::new(&bytes[8..]);
+        let clip: AnimationClip = bincode::deserialize_from(&mut file)?;
+        clip.validate()?;
+
+        info!(
+            "[AnimationClipLoader] load {:?}. (Channels: {})",
+            handle,
+            clip.channels.len()
+        );
+
+        Ok(clip)
+    }
+
+    fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
+        info!("[AnimationClipLoader] create {:?}.", handle);
+        Ok(Arc::new(item))
+    }
+
+    fn delete(&self, handle: Self::Handle, _: Self::Resource) {
+        info!("[AnimationClipLoader] delete {:?}.", handle);
+    }
+}
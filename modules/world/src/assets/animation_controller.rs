@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+
+use crayon::errors::*;
+use crayon::res::utils::prelude::ResourceState;
+use crayon::sched::prelude::LatchProbe;
+use crayon::uuid::Uuid;
+
+use super::animation_clip::AnimationClipHandle;
+
+impl_handle!(AnimationControllerHandle);
+
+/// An animation controller is a state machine of animations. Each state plays a clip or
+/// blends several clips by parameters, and the transitions between states are taken
+/// when their conditions are met, with cross-fading.
+///
+/// The parameters are set from game code per `Animator`, e.g. sets the float `speed` to
+/// blend idle, walk and run clips, and sets the trigger `jump` to jump.
+///
+/// The clips referenced by motions are owned by controller, and will be deleted with it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AnimationController {
+    /// The parameters with their default values, keyed by names.
+    pub parameters: BTreeMap<String, AnimationParameter>,
+    pub states: Vec<AnimationState>,
+    pub transitions: Vec<AnimationTransition>,
+    /// The index of state that is entered first.
+    pub default_state: usize,
+    pub universe_clips: Vec<Uuid>,
+
+    #[serde(skip)]
+    pub clips: Vec<AnimationClipHandle>,
+}
+
+/// The typed value of parameter.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AnimationParameter {
+    Float(f32),
+    Bool(bool),
+    /// A bool that is reset automatically when it's consumed by a transition.
+    Trigger(bool),
+}
+
+/// A state that plays a motion.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnimationState {
+    pub name: String,
+    pub motion: AnimationMotion,
+    /// The playback speed of motion.
+    pub speed: f32,
+    pub looping: bool,
+}
+
+/// The motion of state, which references clips with the indices in `clips` of controller.
+///
+/// The clips of blend trees are synchronized by normalized time, so they should have the
+/// similar cycles, e.g. a step of left foot and then right foot.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum AnimationMotion {
+    /// Plays a single clip.
+    Clip(usize),
+    /// Blends the clips linearly by a float parameter, the children are `(threshold, clip)`
+    /// pairs sorted by thresholds.
+    Blend1D {
+        parameter: String,
+        children: Vec<(f32, usize)>,
+    },
+    /// Blends the clips by two float parameters, e.g. the velocity on the ground. The
+    /// children are `(position, clip)` pairs, which are weighted with gradient bands.
+    Blend2D {
+        parameters: (String, String),
+        children: Vec<([f32; 2], usize)>,
+    },
+}
+
+/// A transition between states.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnimationTransition {
+    /// The index of source state, or transits from any state if none.
+    pub from: Option<usize>,
+    /// The index of destination state.
+    pub to: usize,
+    /// The conditions that must be all met.
+    pub conditions: Vec<AnimationCondition>,
+    /// The normalized time of source state, before which the transition is not taken.
+    pub exit_time: Option<f32>,
+    /// The duration of cross-fading in seconds.
+    pub duration: f32,
+}
+
+/// The condition of transition, which tests a parameter.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum AnimationCondition {
+    /// The float parameter is greater than the value.
+    Greater(String, f32),
+    /// The float parameter is less than the value.
+    Less(String, f32),
+    /// The bool parameter is true, or the trigger is set.
+    If(String),
+    /// The bool parameter is false, or the trigger is not set.
+    IfNot(String),
+}
+
+impl AnimationController {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a parameter with its default value.
+    pub fn add_parameter<T: Into<String>>(&mut self, name: T, value: AnimationParameter) {
+        self.parameters.insert(name.into(), value);
+    }
+
+    /// Adds a clip, and returns its index which could be referenced by motions.
+    pub fn add_clip(&mut self, clip: AnimationClipHandle) -> usize {
+        match self.clips.iter().position(|&v| v == clip) {
+            Some(index) => index,
+            None => {
+                self.clips.push(clip);
+                self.clips.len() - 1
+            }
+        }
+    }
+
+    /// Adds a state, and returns its index.
+    pub fn add_state(&mut self, state: AnimationState) -> usize {
+        self.states.push(state);
+        self.states.len() - 1
+    }
+
+    /// Adds a transition.
+    pub fn add_transition(&mut self, transition: AnimationTransition) {
+        self.transitions.push(transition);
+    }
+
+    /// Finds the index of state by name.
+    pub fn state<T: AsRef<str>>(&self, name: T) -> Option<usize> {
+        let name = name.as_ref();
+        self.states.iter().position(|v| v.name == name)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.states.is_empty() {
+            bail!("The animation controller should have at least one state.");
+        }
+
+        if self.default_state >= self.states.len() {
+            bail!("The default state {} is out of range.", self.default_state);
+        }
+
+        for v in &self.states {
+            let clips = match v.motion {
+                AnimationMotion::Clip(clip) => vec![clip],
+                AnimationMotion::Blend1D { ref children, .. } => {
+                    if children.windows(2).any(|v| v[0].0 > v[1].0) {
+                        bail!("The children of state {} are not sorted.", v.name);
+                    }
+
+                    children.iter().map(|v| v.1).collect()
+                }
+                AnimationMotion::Blend2D { ref children, .. } => {
+                    children.iter().map(|v| v.1).collect()
+                }
+            };
+
+            if clips.iter().any(|&clip| clip >= self.clips.len()) {
+                bail!("The clips of state {} are out of range.", v.name);
+            }
+        }
+
+        for v in &self.transitions {
+            if v.to >= self.states.len() || v.from.map(|v| v >= self.states.len()) == Some(true) {
+                bail!("The states of transition are out of range.");
+            }
+
+            if v.conditions.is_empty() && v.exit_time.is_none() {
+                bail!("The transition should have either conditions or exit time.");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl AnimationState {
+    /// Creates a looping state with normal speed.
+    pub fn new<T: Into<String>>(name: T, motion: AnimationMotion) -> Self {
+        AnimationState {
+            name: name.into(),
+            motion: motion,
+            speed: 1.0,
+            looping: true,
+        }
+    }
+}
+
+impl AnimationTransition {
+    /// Creates a transition that is taken once the conditions are met.
+    pub fn new(from: Option<usize>, to: usize, duration: f32) -> Self {
+        AnimationTransition {
+            from: from,
+            to: to,
+            conditions: Vec::new(),
+            exit_time: None,
+            duration: duration,
+        }
+    }
+
+    /// Adds a condition.
+    pub fn when(mut self, condition: AnimationCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Sets the normalized exit time.
+    pub fn after(mut self, exit_time: f32) -> Self {
+        self.exit_time = Some(exit_time);
+        self
+    }
+}
+
+impl LatchProbe for AnimationControllerHandle {
+    fn is_set(&self) -> bool {
+        ResourceState::NotReady != crate::animation_controller_state(*self)
+    }
+}
@@ -0,0 +1,78 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crayon::bincode;
+use crayon::errors::Result;
+use crayon::res::utils::prelude::ResourceLoader;
+
+use super::animation_controller::*;
+
+pub const MAGIC: [u8; 8] = [
+    'A' as u8, 'C' as u8, 'T' as u8, 'L' as u8, ' ' as u8, 0, 0, 1,
+];
+
+#[derive(Clone)]
+pub struct AnimationControllerLoader {}
+
+impl AnimationControllerLoader {
+    pub fn new() -> Self {
+        AnimationControllerLoader {}
+    }
+}
+
+impl ResourceLoader for AnimationControllerLoader {
+    type Handle = AnimationControllerHandle;
+    type Intermediate = AnimationController;
+    type Resource = Arc<AnimationController>;
+
+    fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
+        if &bytes[0..8] != &MAGIC[..] {
+            bail!("[AnimationControllerLoader] MAGIC number not match.");
+        }
+
+        let mut file = Cursor::new(&bytes[8..]);
+        let mut controller: AnimationController = bincode::deserialize_from(&mut file)?;
+
+        // The clips created already are deleted if any of them fails.
+        let mut result = Ok(());
+        for &v in &controller.universe_clips {
+            match crate::create_animation_clip_from_uuid(v) {
+                Ok(clip) => controller.clips.push(clip),
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        if let Err(err) = result.and_then(|_| controller.validate()) {
+            for &v in &controller.clips {
+                crate::delete_animation_clip(v);
+            }
+
+            return Err(err);
+        }
+
+        info!(
+            "[AnimationControllerLoader] load {:?}. (States: {}, Transitions: {}, Clips: {})",
+            handle,
+            controller.states.len(),
+            controller.transitions.len(),
+            controller.clips.len()
+        );
+
+        Ok(controller)
+    }
+
+    fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
+        info!("[AnimationControllerLoader] create {:?}.", handle);
+        Ok(Arc::new(item))
+    }
+
+    fn delete(&self, handle: Self::Handle, controller: Self::Resource) {
+        info!("[AnimationControllerLoader] delete {:?}.", handle);
+        for &v in &controller.clips {
+            crate::delete_animation_clip(v);
+        }
+    }
+}
@@ -0,0 +1,1262 @@
+//! Importer of glTF 2.0 documents.
+//!
+//! Both the binary container (`.glb`) and the JSON format with embedded buffers (`.gltf`
+//! with `data:` uris) are supported. The importer produces a `Prefab` which contains the
+//! node hierarchy and meshes, along with the `Skeleton`s and `AnimationClip`s described
+//! by skins and animations.
+//!
+//! Since glTF uses a right handed coordinate system, the z-axis of positions, normals,
+//! tangents and transforms will be flipped to fit our left handed, y-up world. The tangents
+//! are generated if the primitives have normals and texture coordinates but no tangents.
+//!
+//! The materials are imported into `PbrMaterial`s. Images are expected to be compiled
+//! into textures by the asset pipeline, so only images referenced by external uris are
+//! imported with `video::create_texture_from`, the embedded ones are skipped with a
+//! warning. Buffers referenced by external files are not supported.
+//!
+//! Since `Transform` supports uniform scaling only, the x-axis scale is used for nodes
+//! and animations with non-uniform scaling, and a warning will be emitted.
+
+use std::collections::BTreeMap;
+
+use crayon::errors::*;
+use crayon::math::prelude::*;
+use crayon::video;
+use crayon::video::assets::mesh::*;
+use crayon::video::assets::shader::Attribute;
+use crayon::video::assets::tangents;
+use crayon::video::assets::texture::TextureHandle;
+use serde_json;
+
+use super::animation_clip::{AnimationChannel, AnimationClip, Keyframe};
+use super::prefab::{Prefab, PrefabNode};
+use super::skeleton::{Bone, Skeleton};
+use renderable::prelude::{PbrMaterial, PbrRenderer};
+use scene::Scene;
+use spatial::prelude::Transform;
+use Entity;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+/// The assets imported from a glTF document.
+#[derive(Debug, Clone)]
+pub struct GltfAsset {
+    /// The node hierarchy of default scene, and the meshes used by it.
+    pub prefab: Prefab,
+    /// The skeletons of skins, with the same order of `skins` in document.
+    pub skeletons: Vec<Skeleton>,
+    /// The animation clips, whose channels are bound to the bones of first skeleton.
+    pub animations: Vec<AnimationClip>,
+    /// The materials, with the same order of `materials` in document.
+    pub materials: Vec<PbrMaterial>,
+    /// The material index of each mesh in prefab, which is the material of its first
+    /// primitive.
+    pub mesh_materials: Vec<Option<usize>>,
+}
+
+impl GltfAsset {
+    /// Adds the materials into the `PbrRenderer` of scene, for `root` and its descendants
+    /// whose meshes are created by this asset. Returns the number of entities that have
+    /// been assigned with materials.
+    pub fn add_materials(&self, scene: &mut Scene<PbrRenderer>, root: Entity) -> usize {
+        let mut ents = vec![root];
+        ents.extend(scene.nodes.descendants(root));
+
+        let mut count = 0;
+        for ent in ents {
+            let mesh = match scene.mesh(ent) {
+                Some(v) => v.mesh,
+                None => continue,
+            };
+
+            let material = self
+                .prefab
+                .meshes
+                .iter()
+                .position(|&v| v == mesh)
+                .and_then(|v| self.mesh_materials[v])
+                .map(|v| self.materials[v]);
+
+            if let Some(material) = material {
+                scene.renderer.add(ent, material);
+                count += 1;
+            }
+        }
+
+        count
+    }
+}
+
+/// Returns true if the bytes looks like a glTF document.
+pub fn is_gltf(bytes: &[u8]) -> bool {
+    if bytes.len() >= 4 && read_u32(bytes, 0) == GLB_MAGIC {
+        return true;
+    }
+
+    bytes
+        .iter()
+        .find(|v| !v.is_ascii_whitespace())
+        .map(|&v| v == b'{')
+        .unwrap_or(false)
+}
+
+/// Imports a glTF document. Meshes will be created during importing, and the ownership
+/// of them is transfered to the returned prefab. The uris of images are used as is.
+///
+/// The materials are assigned to the instances of prefab with `GltfAsset::add_materials`.
+pub fn import(bytes: &[u8]) -> Result<GltfAsset> {
+    import_from(bytes, "")
+}
+
+/// Imports a glTF document located at `location`, the relative uris of images will be
+/// resolved against it. The ownership of textures is transfered to the returned
+/// materials.
+pub fn import_from<T: AsRef<str>>(bytes: &[u8], location: T) -> Result<GltfAsset> {
+    let (json, bin) = split(bytes)?;
+    let document: Document = serde_json::from_slice(json)?;
+
+    let mut buffers = Vec::new();
+    for (i, v) in document.buffers.iter().enumerate() {
+        let data = match v.uri {
+            Some(ref uri) => decode_data_uri(uri)?,
+            None if i == 0 && bin.is_some() => bin.unwrap().to_vec(),
+            None => bail!("[glTF] Buffer {} does not have any data.", i),
+        };
+
+        if data.len() < v.byte_length {
+            bail!("[glTF] Buffer {} is shorter than its byte length.", i);
+        }
+
+        buffers.push(data);
+    }
+
+    let importer = Importer {
+        document: &document,
+        buffers: &buffers,
+        location: location.as_ref(),
+    };
+
+    let skeletons = importer.skeletons()?;
+    let animations = importer.animations()?;
+    let (prefab, meshes) = importer.prefab()?;
+    let materials = importer.materials();
+    let mesh_materials = meshes
+        .iter()
+        .map(|&v| importer.document.meshes[v].primitives[0].material)
+        .map(|v| v.filter(|&v| v < materials.len()))
+        .collect();
+
+    info!(
+        "[glTF] import document. (Nodes: {}, Meshes: {}, Skeletons: {}, Animations: {})",
+        prefab.nodes.len(),
+        prefab.meshes.len(),
+        skeletons.len(),
+        animations.len()
+    );
+
+    Ok(GltfAsset {
+        prefab,
+        skeletons,
+        animations,
+        materials,
+        mesh_materials,
+    })
+}
+
+struct Importer<'a> {
+    document: &'a Document,
+    buffers: &'a [Vec<u8>],
+    location: &'a str,
+}
+
+impl<'a> Importer<'a> {
+    /// Returns the prefab, and the index in document of each mesh in prefab.
+    fn prefab(&self) -> Result<(Prefab, Vec<usize>)> {
+        // Imports all the root nodes if there is no scene at all.
+        let scene = self.document.scene.unwrap_or(0);
+        let roots: Vec<usize> = match self.document.scenes.get(scene) {
+            Some(v) => v.nodes.clone(),
+            None if self.document.scenes.is_empty() => (0..self.document.nodes.len())
+                .filter(|&v| self.parent(v).is_none())
+                .collect(),
+            None => bail!("[glTF] Scene {} is out of bounds.", scene),
+        };
+
+        let mut prefab = Prefab {
+            nodes: Vec::new(),
+            universe_meshes: Vec::new(),
+            meshes: Vec::new(),
+        };
+
+        let mut meshes = BTreeMap::new();
+        if let Err(err) = self.prefab_nodes(&roots, &mut prefab, &mut meshes) {
+            for &v in &prefab.meshes {
+                video::delete_mesh(v);
+            }
+
+            return Err(err);
+        }
+
+        let mut indices = vec![0; prefab.meshes.len()];
+        for (&k, &v) in &meshes {
+            indices[v] = k;
+        }
+
+        Ok((prefab, indices))
+    }
+
+    fn prefab_nodes(
+        &self,
+        siblings: &[usize],
+        prefab: &mut Prefab,
+        meshes: &mut BTreeMap<usize, usize>,
+    ) -> Result<Option<usize>> {
+        let mut first = None;
+        let mut prev: Option<usize> = None;
+
+        for &v in siblings {
+            let node = self
+                .document
+                .nodes
+                .get(v)
+                .ok_or_else(|| format_err!("[glTF] Node {} is out of bounds.", v))?;
+
+            let mesh_renderer = match node.mesh {
+                Some(mesh) => {
+                    if !meshes.contains_key(&mesh) {
+                        let handle = self.mesh(mesh)?;
+                        meshes.insert(mesh, prefab.meshes.len());
+                        prefab.meshes.push(handle);
+                    }
+
+                    meshes.get(&mesh).cloned()
+                }
+                None => None,
+            };
+
+            let index = prefab.nodes.len();
+            prefab.nodes.push(PrefabNode {
+                name: node.name.clone().unwrap_or_else(|| format!("Node{}", v)),
+                local_transform: node.transform(),
+                first_child: None,
+                next_sib: None,
+                mesh_renderer,
+            });
+
+            if let Some(prev) = prev {
+                prefab.nodes[prev].next_sib = Some(index);
+            }
+
+            if first.is_none() {
+                first = Some(index);
+            }
+
+            prev = Some(index);
+            prefab.nodes[index].first_child = self.prefab_nodes(&node.children, prefab, meshes)?;
+        }
+
+        Ok(first)
+    }
+
+    fn mesh(&self, index: usize) -> Result<MeshHandle> {
+        let mesh = self
+            .document
+            .meshes
+            .get(index)
+            .ok_or_else(|| format_err!("[glTF] Mesh {} is out of bounds.", index))?;
+
+        if mesh.primitives.is_empty() {
+            bail!("[glTF] Mesh {} does not have any primitive.", index);
+        }
+
+        let first = &mesh.primitives[0];
+        let mut builder = VertexLayout::build();
+        let mut attributes = Vec::new();
+        for (name, &accessor) in &first.attributes {
+            if let Some(attribute) = attribute(name) {
+                let accessor = self.accessor(accessor)?;
+                let format = vertex_format(accessor.component_type)?;
+                let size = accessor.num_components()?;
+                if size > 4 {
+                    bail!("[glTF] Attribute {} could not be a matrix.", name);
+                }
+
+                builder = builder.with(attribute, format, size as u8, accessor.normalized);
+                attributes.push((name.as_str(), attribute));
+            }
+        }
+
+        let layout = builder.finish();
+        let stride = layout.stride() as usize;
+
+        let mut params = MeshParams::default();
+        params.layout = layout;
+        params.primitive = primitive(first.mode)?;
+
+        let mut vptr = Vec::new();
+        let mut idxes = Vec::new();
+        let mut aabb: Option<Aabb3<f32>> = None;
+
+        for (i, v) in mesh.primitives.iter().enumerate() {
+            if primitive(v.mode)? != params.primitive {
+                bail!("[glTF] Primitives of mesh {} have different modes.", index);
+            }
+
+            if v.attributes.len() != first.attributes.len() {
+                bail!(
+                    "[glTF] Primitives of mesh {} have different layouts.",
+                    index
+                );
+            }
+
+            let position = *v
+                .attributes
+                .get("POSITION")
+                .ok_or_else(|| format_err!("[glTF] Mesh {} does not have positions.", index))?;
+
+            // The number of vertices is bounded by the buffer with `view`.
+            self.view(position)?;
+            let num = self.accessor(position)?.count;
+            let base = params.num_verts;
+            params.num_verts += num;
+
+            let len = params
+                .num_verts
+                .checked_mul(stride)
+                .ok_or_else(|| format_err!("[glTF] Mesh {} has too many vertices.", index))?;
+            vptr.resize(len, 0);
+
+            for &(name, attribute) in &attributes {
+                let accessor = *v.attributes.get(name).ok_or_else(|| {
+                    format_err!(
+                        "[glTF] Primitives of mesh {} have different layouts.",
+                        index
+                    )
+                })?;
+
+                let element = layout.element(attribute).unwrap();
+                let offset = layout.offset(attribute).unwrap() as usize;
+                let flip = match attribute {
+                    Attribute::Position | Attribute::Normal | Attribute::Tangent => {
+                        element.format == VertexFormat::Float
+                    }
+                    _ => false,
+                };
+
+                self.copy_elements(
+                    accessor,
+                    num,
+                    &mut vptr[base * stride..],
+                    stride,
+                    offset,
+                    flip,
+                )?;
+            }
+
+            {
+                let accessor = self.accessor(position)?;
+                if let (Some(min), Some(max)) = (&accessor.min, &accessor.max) {
+                    if min.len() >= 3 && max.len() >= 3 {
+                        let v = Aabb3::new(
+                            [min[0], min[1], -max[2]].into(),
+                            [max[0], max[1], -min[2]].into(),
+                        );
+
+                        aabb = Some(match aabb {
+                            Some(aabb) => aabb.grow(v.min).grow(v.max),
+                            None => v,
+                        });
+                    }
+                }
+            }
+
+            if i > 0 {
+                params.sub_mesh_offsets.push(idxes.len());
+            }
+
+            let start = idxes.len();
+            match v.indices {
+                Some(accessor) => {
+                    for index in self.read_indices(accessor)? {
+                        idxes.push(index + base as u32);
+                    }
+                }
+                None => idxes.extend((base..base + num).map(|v| v as u32)),
+            }
+
+            // Reverses the winding order since we have flipped the z-axis.
+            if params.primitive == MeshPrimitive::Triangles {
+                for v in idxes[start..].chunks_mut(3) {
+                    if v.len() == 3 {
+                        v.swap(1, 2);
+                    }
+                }
+            }
+        }
+
+        params.num_idxes = idxes.len();
+        params.aabb = aabb.unwrap_or_else(Aabb3::zero);
+
+        let iptr = if params.num_verts <= ::std::u16::MAX as usize {
+            params.index_format = IndexFormat::U16;
+            let idxes: Vec<u16> = idxes.iter().map(|&v| v as u16).collect();
+            IndexFormat::encode(&idxes).to_vec()
+        } else {
+            params.index_format = IndexFormat::U32;
+            IndexFormat::encode(&idxes).to_vec()
+        };
+
+        let data = MeshData {
+            vptr: vptr.into_boxed_slice(),
+            iptr: iptr.into_boxed_slice(),
+        };
+
+        let (params, data) = if params.layout.element(Attribute::Tangent).is_none()
+            && tangents::supported(&params)
+        {
+            tangents::generate(&params, &data)?
+        } else {
+            (params, data)
+        };
+
+        let handle = video::create_mesh(params, data)?;
+        Ok(handle)
+    }
+
+    fn materials(&self) -> Vec<PbrMaterial> {
+        self.document
+            .materials
+            .iter()
+            .map(|v| {
+                let pbr = &v.pbr_metallic_roughness;
+                let [r, g, b, a] = pbr.base_color_factor;
+                let [er, eg, eb] = v.emissive_factor;
+
+                let mut material = PbrMaterial::default();
+                material.albedo = Color::new(r, g, b, a);
+                material.albedo_texture = self.texture(pbr.base_color_texture.as_ref(), false);
+                material.metallic = pbr.metallic_factor;
+                material.roughness = pbr.roughness_factor;
+                material.metallic_roughness_texture =
+                    self.texture(pbr.metallic_roughness_texture.as_ref(), false);
+                material.emissive = Color::new(er, eg, eb, 1.0);
+                material.emissive_texture = self.texture(v.emissive_texture.as_ref(), false);
+
+                if let Some(ref info) = v.normal_texture {
+                    material.normal_texture = self.texture(Some(info), false);
+                    material.normal_scale = info.scale;
+                }
+
+                if let Some(ref info) = v.occlusion_texture {
+                    material.occlusion_texture = self.texture(Some(info), true);
+                    material.occlusion_strength = info.strength;
+                    material.occlusion_texcoord = info.tex_coord;
+                }
+
+                material
+            })
+            .collect()
+    }
+
+    /// Creates the texture of image used by `info`. Failures are not fatal here, the
+    /// material just goes without this texture.
+    fn texture(&self, info: Option<&TextureInfoDesc>, texcoords: bool) -> Option<TextureHandle> {
+        let info = info?;
+        if !texcoords && info.tex_coord != 0 {
+            warn!(
+                "[glTF] Texture {} uses texture coordinates {}, which is not supported.",
+                info.index, info.tex_coord
+            );
+            return None;
+        }
+
+        let image = self
+            .document
+            .textures
+            .get(info.index)
+            .and_then(|v| v.source)
+            .and_then(|v| self.document.images.get(v));
+
+        let uri = match image.and_then(|v| v.uri.as_ref()) {
+            Some(uri) if !uri.starts_with("data:") => uri,
+            _ => {
+                warn!(
+                    "[glTF] Texture {} does not reference an external image.",
+                    info.index
+                );
+                return None;
+            }
+        };
+
+        let url = resolve(self.location, uri);
+        match video::create_texture_from(&url) {
+            Ok(handle) => Some(handle),
+            Err(err) => {
+                warn!("[glTF] Failed to create texture {}. {}", url, err);
+                None
+            }
+        }
+    }
+
+    fn skeletons(&self) -> Result<Vec<Skeleton>> {
+        let mut skeletons = Vec::new();
+        for (i, skin) in self.document.skins.iter().enumerate() {
+            let matrices = match skin.inverse_bind_matrices {
+                Some(accessor) => self.read_f32(accessor)?,
+                None => Vec::new(),
+            };
+
+            let mut skeleton = Skeleton::default();
+            for (j, &v) in skin.joints.iter().enumerate() {
+                let node = self
+                    .document
+                    .nodes
+                    .get(v)
+                    .ok_or_else(|| format_err!("[glTF] Node {} is out of bounds.", v))?;
+
+                let parent = self
+                    .parent(v)
+                    .and_then(|p| skin.joints.iter().position(|&j| j == p));
+
+                let mut bone = Bone::default();
+                bone.name = node.name.clone().unwrap_or_else(|| format!("Node{}", v));
+                bone.parent = parent;
+                bone.local_transform = node.transform();
+
+                if let Some(m) = matrices.get(j * 16..(j + 1) * 16) {
+                    bone.inverse_bind_matrix = flip_matrix(m);
+                }
+
+                skeleton.bones.push(bone);
+            }
+
+            if let Err(err) = skeleton.validate() {
+                bail!("[glTF] Joints of skin {} are not sorted. {}", i, err);
+            }
+
+            skeletons.push(skeleton);
+        }
+
+        Ok(skeletons)
+    }
+
+    fn animations(&self) -> Result<Vec<AnimationClip>> {
+        let joints = match self.document.skins.first() {
+            Some(v) => &v.joints,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut clips = Vec::new();
+        for v in &self.document.animations {
+            let mut clip = AnimationClip::default();
+            let mut channels: BTreeMap<usize, AnimationChannel> = BTreeMap::new();
+
+            for c in &v.channels {
+                let bone = match c
+                    .target
+                    .node
+                    .and_then(|n| joints.iter().position(|&j| j == n))
+                {
+                    Some(bone) => bone,
+                    None => continue,
+                };
+
+                let sampler = v
+                    .samplers
+                    .get(c.sampler)
+                    .ok_or_else(|| format_err!("[glTF] Sampler {} is out of bounds.", c.sampler))?;
+
+                let times = self.read_f32(sampler.input)?;
+                let values = self.read_f32(sampler.output)?;
+                let width = match c.target.path.as_str() {
+                    "translation" | "scale" => 3,
+                    "rotation" => 4,
+                    _ => continue,
+                };
+
+                // Only the values of cubic spline will be used, the tangents are ignored.
+                let (stride, offset) = if sampler.interpolation == "CUBICSPLINE" {
+                    (width * 3, width)
+                } else {
+                    (width, 0)
+                };
+
+                let channel = channels.entry(bone).or_insert_with(|| AnimationChannel {
+                    bone,
+                    ..Default::default()
+                });
+
+                let mut warned = false;
+                for (i, &time) in times.iter().enumerate() {
+                    let start = i * stride + offset;
+                    let v = match values.get(start..start + width) {
+                        Some(v) => v,
+                        None => bail!("[glTF] The output of sampler {} is too short.", c.sampler),
+                    };
+
+                    clip.duration = clip.duration.max(time);
+                    match c.target.path.as_str() {
+                        "translation" => {
+                            let value = Vector3::new(v[0], v[1], -v[2]);
+                            channel.positions.push(Keyframe::new(time, value));
+                        }
+                        "rotation" => {
+                            let value = Quaternion::new(v[3], -v[0], -v[1], v[2]);
+                            channel.rotations.push(Keyframe::new(time, value));
+                        }
+                        _ => {
+                            if !is_uniform(Vector3::new(v[0], v[1], v[2])) && !warned {
+                                warn!(
+                                    "[glTF] Non-uniform scales of sampler {} is not supported.",
+                                    c.sampler
+                                );
+                                warned = true;
+                            }
+
+                            channel.scales.push(Keyframe::new(time, v[0]));
+                        }
+                    }
+                }
+            }
+
+            clip.channels = channels.into_iter().map(|(_, v)| v).collect();
+            clip.validate()?;
+            clips.push(clip);
+        }
+
+        Ok(clips)
+    }
+
+    fn parent(&self, node: usize) -> Option<usize> {
+        self.document
+            .nodes
+            .iter()
+            .position(|v| v.children.contains(&node))
+    }
+
+    fn accessor(&self, index: usize) -> Result<&'a AccessorDesc> {
+        let accessor = self
+            .document
+            .accessors
+            .get(index)
+            .ok_or_else(|| format_err!("[glTF] Accessor {} is out of bounds.", index))?;
+
+        if accessor.sparse.is_some() {
+            bail!("[glTF] Sparse accessor {} is not supported.", index);
+        }
+
+        Ok(accessor)
+    }
+
+    /// Returns the bytes, stride and element size of accessor.
+    fn view(&self, index: usize) -> Result<(&'a [u8], usize, usize)> {
+        let accessor = self.accessor(index)?;
+        let size = component_size(accessor.component_type)? * accessor.num_components()?;
+
+        let view = accessor
+            .buffer_view
+            .and_then(|v| self.document.buffer_views.get(v))
+            .ok_or_else(|| format_err!("[glTF] Accessor {} does not have buffer view.", index))?;
+
+        let buffer = self
+            .buffers
+            .get(view.buffer)
+            .ok_or_else(|| format_err!("[glTF] Buffer {} is out of bounds.", view.buffer))?;
+
+        let stride = view.byte_stride.unwrap_or(size);
+        if stride < size {
+            bail!("[glTF] The stride of accessor {} is too small.", index);
+        }
+
+        let start = view.byte_offset.checked_add(accessor.byte_offset);
+        let end = view.byte_offset.checked_add(view.byte_length);
+        let len = match accessor.count {
+            0 => Some(0),
+            n => stride.checked_mul(n - 1).and_then(|v| v.checked_add(size)),
+        };
+
+        match (
+            start,
+            end,
+            len.and_then(|v| start.and_then(|s| s.checked_add(v))),
+        ) {
+            (Some(start), Some(end), Some(v)) if v <= end && end <= buffer.len() => {
+                Ok((&buffer[start..end], stride, size))
+            }
+            _ => bail!("[glTF] Accessor {} is out of bounds.", index),
+        }
+    }
+
+    fn copy_elements(
+        &self,
+        index: usize,
+        num: usize,
+        dst: &mut [u8],
+        stride: usize,
+        offset: usize,
+        flip: bool,
+    ) -> Result<()> {
+        let (src, src_stride, size) = self.view(index)?;
+        if self.accessor(index)?.count != num {
+            bail!("[glTF] Attributes have different number of vertices.");
+        }
+
+        // The source has been checked by `view`, so only the destination is left.
+        let len = match num {
+            0 => Some(0),
+            n => stride
+                .checked_mul(n - 1)
+                .and_then(|v| v.checked_add(offset))
+                .and_then(|v| v.checked_add(size)),
+        };
+
+        if len.map(|v| v > dst.len()).unwrap_or(true) {
+            bail!("[glTF] Accessor {} does not fit into the vertices.", index);
+        }
+
+        for i in 0..num {
+            let from = &src[i * src_stride..i * src_stride + size];
+            let to = &mut dst[i * stride + offset..i * stride + offset + size];
+            to.copy_from_slice(from);
+
+            if flip && size >= 12 {
+                let z = (-read_f32(to, 8)).to_bits();
+                for (k, v) in to[8..12].iter_mut().enumerate() {
+                    *v = (z >> (k * 8)) as u8;
+                }
+            }
+
+            // The handedness of tangents is reversed by the flipping too.
+            if flip && size >= 16 {
+                let w = (-read_f32(to, 12)).to_bits();
+                for (k, v) in to[12..16].iter_mut().enumerate() {
+                    *v = (w >> (k * 8)) as u8;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_indices(&self, index: usize) -> Result<Vec<u32>> {
+        let accessor = self.accessor(index)?;
+        let (src, stride, _) = self.view(index)?;
+
+        let mut idxes = Vec::with_capacity(accessor.count);
+        for i in 0..accessor.count {
+            let v = match accessor.component_type {
+                5121 => u32::from(src[i * stride]),
+                5123 => u32::from(read_u16(src, i * stride)),
+                5125 => read_u32(src, i * stride),
+                v => bail!("[glTF] Component type {} could not be used as index.", v),
+            };
+
+            idxes.push(v);
+        }
+
+        Ok(idxes)
+    }
+
+    fn read_f32(&self, index: usize) -> Result<Vec<f32>> {
+        let accessor = self.accessor(index)?;
+        let (src, stride, _) = self.view(index)?;
+        let num = accessor.num_components()?;
+        let norm = accessor.normalized;
+
+        let mut values = Vec::with_capacity(accessor.count * num);
+        for i in 0..accessor.count {
+            for j in 0..num {
+                let v = match accessor.component_type {
+                    5120 => {
+                        let v = f32::from(src[i * stride + j] as i8);
+                        if norm {
+                            (v / 127.0).max(-1.0)
+                        } else {
+                            v
+                        }
+                    }
+                    5121 => {
+                        let v = f32::from(src[i * stride + j]);
+                        if norm {
+                            v / 255.0
+                        } else {
+                            v
+                        }
+                    }
+                    5122 => {
+                        let v = f32::from(read_u16(src, i * stride + j * 2) as i16);
+                        if norm {
+                            (v / 32767.0).max(-1.0)
+                        } else {
+                            v
+                        }
+                    }
+                    5123 => {
+                        let v = f32::from(read_u16(src, i * stride + j * 2));
+                        if norm {
+                            v / 65535.0
+                        } else {
+                            v
+                        }
+                    }
+                    5125 => read_u32(src, i * stride + j * 4) as f32,
+                    _ => read_f32(src, i * stride + j * 4),
+                };
+
+                values.push(v);
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+fn split(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>)> {
+    if bytes.len() < 12 || read_u32(bytes, 0) != GLB_MAGIC {
+        return Ok((bytes, None));
+    }
+
+    if read_u32(bytes, 4) != 2 {
+        bail!("[glTF] Only version 2.0 of binary glTF is supported.");
+    }
+
+    let len = (read_u32(bytes, 8) as usize).min(bytes.len());
+    let mut json = None;
+    let mut bin = None;
+    let mut cursor = 12;
+
+    while cursor + 8 <= len {
+        let chunk_len = read_u32(bytes, cursor) as usize;
+        let chunk_type = read_u32(bytes, cursor + 4);
+        let start = cursor + 8;
+        if start + chunk_len > len {
+            bail!("[glTF] Chunk is out of bounds.");
+        }
+
+        match chunk_type {
+            GLB_CHUNK_JSON => json = Some(&bytes[start..start + chunk_len]),
+            GLB_CHUNK_BIN => bin = Some(&bytes[start..start + chunk_len]),
+            _ => {}
+        }
+
+        cursor = start + chunk_len;
+    }
+
+    let json = json.ok_or_else(|| format_err!("[glTF] JSON chunk is missing."))?;
+    Ok((json, bin))
+}
+
+fn decode_data_uri(uri: &str) -> Result<Vec<u8>> {
+    if !uri.starts_with("data:") {
+        bail!("[glTF] External buffer {} is not supported.", uri);
+    }
+
+    match uri.find(";base64,") {
+        Some(v) => decode_base64(&uri[v + 8..]),
+        None => bail!("[glTF] Only base64 encoded data uri is supported."),
+    }
+}
+
+fn decode_base64(src: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(src.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+
+    for c in src.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => bail!("[glTF] Invalid base64 character {:?}.", c as char),
+        };
+
+        acc = (acc << 6) | u32::from(v);
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Resolves the relative `uri` against the directory of `location`, e.g. `tex.png` in
+/// `res:models/box.gltf` is resolved to `res:models/tex.png`.
+fn resolve(location: &str, uri: &str) -> String {
+    if location.is_empty() || uri.contains(':') {
+        return uri.to_owned();
+    }
+
+    let (shortcut, path) = match location.find(':') {
+        Some(v) => location.split_at(v + 1),
+        None => ("", location),
+    };
+
+    let mut components: Vec<&str> = path.split('/').filter(|v| !v.is_empty()).collect();
+    components.pop();
+
+    for v in uri.split('/') {
+        match v {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(v),
+        }
+    }
+
+    format!("{}{}", shortcut, components.join("/"))
+}
+
+fn attribute(name: &str) -> Option<Attribute> {
+    match name {
+        "POSITION" => Some(Attribute::Position),
+        "NORMAL" => Some(Attribute::Normal),
+        "TANGENT" => Some(Attribute::Tangent),
+        "COLOR_0" => Some(Attribute::Color0),
+        "COLOR_1" => Some(Attribute::Color1),
+        "TEXCOORD_0" => Some(Attribute::Texcoord0),
+        "TEXCOORD_1" => Some(Attribute::Texcoord1),
+        "TEXCOORD_2" => Some(Attribute::Texcoord2),
+        "TEXCOORD_3" => Some(Attribute::Texcoord3),
+        "JOINTS_0" => Some(Attribute::Indices),
+        "WEIGHTS_0" => Some(Attribute::Weight),
+        _ => None,
+    }
+}
+
+fn vertex_format(component_type: u32) -> Result<VertexFormat> {
+    match component_type {
+        5120 => Ok(VertexFormat::Byte),
+        5121 => Ok(VertexFormat::UByte),
+        5122 => Ok(VertexFormat::Short),
+        5123 => Ok(VertexFormat::UShort),
+        5126 => Ok(VertexFormat::Float),
+        v => bail!(
+            "[glTF] Component type {} could not be used as attribute.",
+            v
+        ),
+    }
+}
+
+fn component_size(component_type: u32) -> Result<usize> {
+    match component_type {
+        5120 | 5121 => Ok(1),
+        5122 | 5123 => Ok(2),
+        5125 | 5126 => Ok(4),
+        v => bail!("[glTF] Undefined component type {}.", v),
+    }
+}
+
+fn primitive(mode: Option<u32>) -> Result<MeshPrimitive> {
+    match mode.unwrap_or(4) {
+        0 => Ok(MeshPrimitive::Points),
+        1 => Ok(MeshPrimitive::Lines),
+        3 => Ok(MeshPrimitive::LineStrip),
+        4 => Ok(MeshPrimitive::Triangles),
+        5 => Ok(MeshPrimitive::TriangleStrip),
+        v => bail!("[glTF] Primitive mode {} is not supported.", v),
+    }
+}
+
+/// Returns true if the scale is uniform within a relative tolerance.
+fn is_uniform(v: Vector3<f32>) -> bool {
+    let max = v.x.max(v.y).max(v.z);
+    let min = v.x.min(v.y).min(v.z);
+    max - min <= max.abs().max(min.abs()) * 1e-4
+}
+
+/// Converts a column-major matrix into our left handed coordinate system.
+fn flip_matrix(m: &[f32]) -> Matrix4<f32> {
+    let mut v = [[0.0; 4]; 4];
+    for (i, column) in v.iter_mut().enumerate() {
+        for (j, e) in column.iter_mut().enumerate() {
+            let sign = if (i == 2) != (j == 2) { -1.0 } else { 1.0 };
+            *e = m[i * 4 + j] * sign;
+        }
+    }
+
+    v.into()
+}
+
+#[inline]
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from(bytes[offset]) | (u16::from(bytes[offset + 1]) << 8)
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from(read_u16(bytes, offset)) | (u32::from(read_u16(bytes, offset + 2)) << 16)
+}
+
+#[inline]
+fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_bits(read_u32(bytes, offset))
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct Document {
+    scene: Option<usize>,
+    scenes: Vec<SceneDesc>,
+    nodes: Vec<NodeDesc>,
+    meshes: Vec<MeshDesc>,
+    materials: Vec<MaterialDesc>,
+    textures: Vec<TextureDesc>,
+    images: Vec<ImageDesc>,
+    accessors: Vec<AccessorDesc>,
+    buffer_views: Vec<BufferViewDesc>,
+    buffers: Vec<BufferDesc>,
+    skins: Vec<SkinDesc>,
+    animations: Vec<AnimationDesc>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct SceneDesc {
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct NodeDesc {
+    name: Option<String>,
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    matrix: Option<Vec<f32>>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+impl NodeDesc {
+    fn transform(&self) -> Transform {
+        let mut transform = Transform::default();
+
+        if let Some(ref m) = self.matrix {
+            if m.len() == 16 {
+                let m = flip_matrix(m);
+                let x = m.x.truncate();
+                let y = m.y.truncate();
+                let z = m.z.truncate();
+                let s = Vector3::new(x.magnitude(), y.magnitude(), z.magnitude());
+
+                if s.x > ::std::f32::EPSILON
+                    && s.y > ::std::f32::EPSILON
+                    && s.z > ::std::f32::EPSILON
+                {
+                    transform.position = m.w.truncate();
+                    transform.rotation = Matrix3::from_cols(x / s.x, y / s.y, z / s.z).into();
+                    transform.scale = self.uniform_scale(s);
+                }
+            }
+
+            return transform;
+        }
+
+        if let Some(v) = self.translation {
+            transform.position = Vector3::new(v[0], v[1], -v[2]);
+        }
+
+        if let Some(v) = self.rotation {
+            transform.rotation = Quaternion::new(v[3], -v[0], -v[1], v[2]);
+        }
+
+        if let Some(v) = self.scale {
+            transform.scale = self.uniform_scale(v.into());
+        }
+
+        transform
+    }
+
+    fn uniform_scale(&self, v: Vector3<f32>) -> f32 {
+        if !is_uniform(v) {
+            warn!(
+                "[glTF] Non-uniform scale {:?} of node {:?} is not supported.",
+                v, self.name
+            );
+        }
+
+        v.x
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct MeshDesc {
+    primitives: Vec<PrimitiveDesc>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct PrimitiveDesc {
+    attributes: BTreeMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    mode: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct MaterialDesc {
+    pbr_metallic_roughness: PbrMetallicRoughnessDesc,
+    normal_texture: Option<TextureInfoDesc>,
+    occlusion_texture: Option<TextureInfoDesc>,
+    emissive_texture: Option<TextureInfoDesc>,
+    emissive_factor: [f32; 3],
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, rename_all = "camelCase")]
+struct PbrMetallicRoughnessDesc {
+    base_color_factor: [f32; 4],
+    base_color_texture: Option<TextureInfoDesc>,
+    metallic_factor: f32,
+    roughness_factor: f32,
+    metallic_roughness_texture: Option<TextureInfoDesc>,
+}
+
+impl Default for PbrMetallicRoughnessDesc {
+    fn default() -> Self {
+        PbrMetallicRoughnessDesc {
+            base_color_factor: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+        }
+    }
+}
+
+/// The reference to texture, `scale` is used by normal textures and `strength` is used
+/// by occlusion textures only.
+#[derive(Deserialize, Debug)]
+#[serde(default, rename_all = "camelCase")]
+struct TextureInfoDesc {
+    index: usize,
+    tex_coord: usize,
+    scale: f32,
+    strength: f32,
+}
+
+impl Default for TextureInfoDesc {
+    fn default() -> Self {
+        TextureInfoDesc {
+            index: 0,
+            tex_coord: 0,
+            scale: 1.0,
+            strength: 1.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct TextureDesc {
+    source: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct ImageDesc {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct AccessorDesc {
+    buffer_view: Option<usize>,
+    byte_offset: usize,
+    component_type: u32,
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    min: Option<Vec<f32>>,
+    max: Option<Vec<f32>>,
+    sparse: Option<serde_json::Value>,
+}
+
+impl AccessorDesc {
+    fn num_components(&self) -> Result<usize> {
+        match self.kind.as_str() {
+            "SCALAR" => Ok(1),
+            "VEC2" => Ok(2),
+            "VEC3" => Ok(3),
+            "VEC4" | "MAT2" => Ok(4),
+            "MAT3" => Ok(9),
+            "MAT4" => Ok(16),
+            v => bail!("[glTF] Undefined accessor type {}.", v),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct BufferViewDesc {
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct BufferDesc {
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct SkinDesc {
+    inverse_bind_matrices: Option<usize>,
+    joints: Vec<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct AnimationDesc {
+    channels: Vec<ChannelDesc>,
+    samplers: Vec<SamplerDesc>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct ChannelDesc {
+    sampler: usize,
+    target: TargetDesc,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct TargetDesc {
+    node: Option<usize>,
+    path: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+struct SamplerDesc {
+    input: usize,
+    output: usize,
+    interpolation: String,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        SamplerDesc {
+            input: 0,
+            output: 0,
+            interpolation: "LINEAR".into(),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+
+use crayon::errors::*;
+use crayon::math::prelude::Vector4;
+use crayon::res::utils::prelude::ResourceState;
+use crayon::sched::prelude::LatchProbe;
+use crayon::uuid::Uuid;
+use crayon::video::assets::texture::TextureHandle;
+
+impl_handle!(LightmapAtlasHandle);
+
+/// A lightmap atlas asset holds the pre-baked lightmaps of a scene, and the regions of
+/// meshes in them. It is usually produced by offline baking tools along with the second
+/// set of texture coordinates of meshes, see `Scene::set_lightmaps`.
+///
+/// The textures are owned by atlas, and will be deleted with it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LightmapAtlas {
+    /// The regions of meshes, keyed by the paths of entities in scene.
+    pub entries: BTreeMap<String, LightmapEntry>,
+    pub universe_textures: Vec<Uuid>,
+
+    #[serde(skip)]
+    pub textures: Vec<TextureHandle>,
+}
+
+/// The region of mesh in lightmap atlas.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct LightmapEntry {
+    /// The index of texture in `textures` of atlas.
+    pub texture: usize,
+    /// The scale (xy) and offset (zw) that transform the second set of texture
+    /// coordinates of mesh into the region in texture.
+    pub scale_offset: [f32; 4],
+}
+
+impl LightmapAtlas {
+    /// Adds the region of entity at path, which refers to the texture.
+    pub fn add<T1, T2>(&mut self, path: T1, texture: TextureHandle, scale_offset: T2)
+    where
+        T1: Into<String>,
+        T2: Into<Vector4<f32>>,
+    {
+        let index = match self.textures.iter().position(|&v| v == texture) {
+            Some(index) => index,
+            None => {
+                self.textures.push(texture);
+                self.textures.len() - 1
+            }
+        };
+
+        let entry = LightmapEntry {
+            texture: index,
+            scale_offset: scale_offset.into().into(),
+        };
+
+        self.entries.insert(path.into(), entry);
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let len = self.textures.len().max(self.universe_textures.len());
+        for (k, v) in &self.entries {
+            if v.texture >= len {
+                bail!("The lightmap of {} is out of bounds.", k);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl LatchProbe for LightmapAtlasHandle {
+    fn is_set(&self) -> bool {
+        ResourceState::NotReady != crate::lightmap_atlas_state(*self)
+    }
+}
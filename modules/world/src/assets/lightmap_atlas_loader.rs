@@ -0,0 +1,71 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crayon::errors::Result;
+use crayon::res::utils::prelude::ResourceLoader;
+use crayon::{bincode, video};
+
+use super::lightmap_atlas::*;
+
+pub const MAGIC: [u8; 8] = [
+    'L' as u8, 'M' as u8, 'A' as u8, 'P' as u8, ' ' as u8, 0, 0, 1,
+];
+
+#[derive(Clone)]
+pub struct LightmapAtlasLoader {}
+
+impl LightmapAtlasLoader {
+    pub fn new() -> Self {
+        LightmapAtlasLoader {}
+    }
+}
+
+impl ResourceLoader for LightmapAtlasLoader {
+    type Handle = LightmapAtlasHandle;
+    type Intermediate = LightmapAtlas;
+    type Resource = Arc<LightmapAtlas>;
+
+    fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
+        if &bytes[0..8] != &MAGIC[..] {
+            bail!("[LightmapAtlasLoader] MAGIC number not match.");
+        }
+
+        let mut file = Cursor::new(&bytes[8..]);
+        let mut atlas: LightmapAtlas = bincode::deserialize_from(&mut file)?;
+        atlas.validate()?;
+
+        for &v in &atlas.universe_textures {
+            match video::create_texture_from_uuid(v) {
+                Ok(texture) => atlas.textures.push(texture),
+                Err(err) => {
+                    for &v in &atlas.textures {
+                        video::delete_texture(v);
+                    }
+
+                    return Err(err);
+                }
+            }
+        }
+
+        info!(
+            "[LightmapAtlasLoader] load {:?}. (Entries: {}, Textures: {})",
+            handle,
+            atlas.entries.len(),
+            atlas.textures.len()
+        );
+
+        Ok(atlas)
+    }
+
+    fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
+        info!("[LightmapAtlasLoader] create {:?}.", handle);
+        Ok(Arc::new(item))
+    }
+
+    fn delete(&self, handle: Self::Handle, atlas: Self::Resource) {
+        info!("[LightmapAtlasLoader] delete {:?}.", handle);
+        for &v in &atlas.textures {
+            video::delete_texture(v);
+        }
+    }
+}
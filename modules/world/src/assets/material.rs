@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crayon::errors::*;
+use crayon::math::prelude::{Color, Matrix4, Vector2, Vector3, Vector4};
+use crayon::res::utils::prelude::ResourceState;
+use crayon::sched::prelude::LatchProbe;
+use crayon::uuid::Uuid;
+use crayon::video::assets::shader::RenderState;
+use crayon::video::assets::texture::TextureHandle;
+
+use renderable::prelude::RenderQueue;
+
+impl_handle!(MaterialHandle);
+
+/// A material asset binds a named shader with a table of typed parameters, which are
+/// set as uniform variables when drawing meshes with `MaterialRenderer`.
+///
+/// The textures referenced by parameters are owned by material, and will be deleted
+/// with it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Material {
+    /// The name of shader, which is registered into `MaterialRenderer`.
+    pub shader: String,
+    /// The parameters keyed by the names of uniform variables.
+    pub params: BTreeMap<String, MaterialParam>,
+    /// Overrides the render state of shader if set.
+    pub render_state: Option<RenderState>,
+    /// The queue that meshes are drawn in.
+    #[serde(default)]
+    pub queue: RenderQueue,
+    pub universe_textures: Vec<Uuid>,
+
+    #[serde(skip)]
+    pub textures: Vec<TextureHandle>,
+}
+
+/// The typed value of material parameter.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MaterialParam {
+    F32(f32),
+    Vector2f([f32; 2]),
+    Vector3f([f32; 3]),
+    Vector4f([f32; 4]),
+    Matrix4f([[f32; 4]; 4]),
+    /// The index of texture in `textures` of material.
+    Texture(usize),
+}
+
+impl Material {
+    /// Creates a new material with the name of shader.
+    pub fn new<T: Into<String>>(shader: T) -> Self {
+        Material {
+            shader: shader.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the value of parameter.
+    pub fn set<T1, T2>(&mut self, name: T1, v: T2)
+    where
+        T1: Into<String>,
+        T2: Into<MaterialParam>,
+    {
+        self.params.insert(name.into(), v.into());
+    }
+
+    /// Sets the texture of parameter.
+    pub fn set_texture<T: Into<String>>(&mut self, name: T, texture: TextureHandle) {
+        let index = match self.textures.iter().position(|&v| v == texture) {
+            Some(index) => index,
+            None => {
+                self.textures.push(texture);
+                self.textures.len() - 1
+            }
+        };
+
+        self.set(name, MaterialParam::Texture(index));
+    }
+
+    /// Gets the value of parameter.
+    pub fn param<T: AsRef<str>>(&self, name: T) -> Option<MaterialParam> {
+        self.params.get(name.as_ref()).cloned()
+    }
+
+    /// Gets the texture of parameter.
+    pub fn texture<T: AsRef<str>>(&self, name: T) -> Option<TextureHandle> {
+        match self.param(name) {
+            Some(MaterialParam::Texture(index)) => self.textures.get(index).cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let len = self.textures.len().max(self.universe_textures.len());
+        for (k, v) in &self.params {
+            if let MaterialParam::Texture(index) = *v {
+                if index >= len {
+                    bail!("The texture of parameter {} is out of bounds.", k);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<f32> for MaterialParam {
+    fn from(v: f32) -> Self {
+        MaterialParam::F32(v)
+    }
+}
+
+impl From<[f32; 2]> for MaterialParam {
+    fn from(v: [f32; 2]) -> Self {
+        MaterialParam::Vector2f(v)
+    }
+}
+
+impl From<Vector2<f32>> for MaterialParam {
+    fn from(v: Vector2<f32>) -> Self {
+        MaterialParam::Vector2f(v.into())
+    }
+}
+
+impl From<[f32; 3]> for MaterialParam {
+    fn from(v: [f32; 3]) -> Self {
+        MaterialParam::Vector3f(v)
+    }
+}
+
+impl From<Vector3<f32>> for MaterialParam {
+    fn from(v: Vector3<f32>) -> Self {
+        MaterialParam::Vector3f(v.into())
+    }
+}
+
+impl From<[f32; 4]> for MaterialParam {
+    fn from(v: [f32; 4]) -> Self {
+        MaterialParam::Vector4f(v)
+    }
+}
+
+impl From<Vector4<f32>> for MaterialParam {
+    fn from(v: Vector4<f32>) -> Self {
+        MaterialParam::Vector4f(v.into())
+    }
+}
+
+impl From<Color<f32>> for MaterialParam {
+    fn from(v: Color<f32>) -> Self {
+        MaterialParam::Vector4f(v.rgba())
+    }
+}
+
+impl From<Matrix4<f32>> for MaterialParam {
+    fn from(v: Matrix4<f32>) -> Self {
+        MaterialParam::Matrix4f(v.into())
+    }
+}
+
+impl LatchProbe for MaterialHandle {
+    fn is_set(&self) -> bool {
+        ResourceState::NotReady != crate::material_state(*self)
+    }
+}
@@ -0,0 +1,64 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crayon::errors::Result;
+use crayon::res::utils::prelude::ResourceLoader;
+use crayon::{bincode, video};
+
+use super::material::*;
+
+pub const MAGIC: [u8; 8] = [
+    'M' as u8, 'A' as u8, 'T' as u8, 'L' as u8, ' ' as u8, 0, 0, 1,
+];
+
+#[derive(Clone)]
+pub struct MaterialLoader {}
+
+impl MaterialLoader {
+    pub fn new() -> Self {
+        MaterialLoader {}
+    }
+}
+
+impl ResourceLoader for MaterialLoader {
+    type Handle = MaterialHandle;
+    type Intermediate = Material;
+    type Resource = Arc<Material>;
+
+    fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
+        if &bytes[0..8] != &MAGIC[..] {
+            bail!("[MaterialLoader] MAGIC number not match.");
+        }
+
+        let mut file = Cursor::new(&bytes[8..]);
+        let mut material: Material = bincode::deserialize_from(&mut file)?;
+        material.validate()?;
+
+        for &v in &material.universe_textures {
+            let texture = video::create_texture_from_uuid(v)?;
+            material.textures.push(texture);
+        }
+
+        info!(
+            "[MaterialLoader] load {:?}. (Shader: {}, Params: {}, Textures: {})",
+            handle,
+            material.shader,
+            material.params.len(),
+            material.textures.len()
+        );
+
+        Ok(material)
+    }
+
+    fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
+        info!("[MaterialLoader] create {:?}.", handle);
+        Ok(Arc::new(item))
+    }
+
+    fn delete(&self, handle: Self::Handle, material: Self::Resource) {
+        info!("[MaterialLoader] delete {:?}.", handle);
+        for &v in &material.textures {
+            video::delete_texture(v);
+        }
+    }
+}
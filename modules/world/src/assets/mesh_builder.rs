@@ -1,4 +1,5 @@
 use crayon::errors::*;
+use crayon::math::prelude::Aabb3;
 
 use crayon::utils::hash::FastHashMap;
 use crayon::video;
@@ -26,6 +27,7 @@ pub fn quad() -> Result<MeshHandle> {
     params.num_verts = verts.len();
     params.num_idxes = idxes.len();
     params.layout = Vertex::layout();
+    params.aabb = Aabb3::new([-0.5, -0.5, 0.0].into(), [0.5, 0.5, 0.0].into());
 
     let data = MeshData {
         vptr: Vertex::encode(&verts[..]).into(),
@@ -95,6 +97,7 @@ pub fn cube() -> Result<MeshHandle> {
     params.num_verts = verts.len();
     params.num_idxes = idxes.len();
     params.layout = Vertex::layout();
+    params.aabb = Aabb3::new([-0.5, -0.5, -0.5].into(), [0.5, 0.5, 0.5].into());
 
     let data = MeshData {
         vptr: Vertex::encode(&verts[..]).into(),
@@ -204,6 +207,7 @@ pub fn sphere(iteration: usize) -> Result<MeshHandle> {
     params.num_verts = verts.len();
     params.num_idxes = idxes.len();
     params.layout = Vertex::layout();
+    params.aabb = Aabb3::new([-1.0, -1.0, -1.0].into(), [1.0, 1.0, 1.0].into());
 
     let data = MeshData {
         vptr: Vertex::encode(&verts[..]).into(),
@@ -1,10 +1,39 @@
+pub mod animation_clip;
+pub mod animation_clip_loader;
+pub mod animation_controller;
+pub mod animation_controller_loader;
+pub mod gltf_importer;
+pub mod lightmap_atlas;
+pub mod lightmap_atlas_loader;
+pub mod material;
+pub mod material_loader;
 pub mod prefab;
 pub mod prefab_loader;
+pub mod skeleton;
+pub mod skeleton_loader;
+pub mod texture_atlas_loader;
 
 pub mod mesh_builder;
 pub mod texture_builder;
 
 pub mod prelude {
+    pub use super::animation_clip::{
+        AnimationChannel, AnimationClip, AnimationClipHandle, AnimationEvent, Keyframe,
+    };
+    pub use super::animation_clip_loader::AnimationClipLoader;
+    pub use super::animation_controller::{
+        AnimationCondition, AnimationController, AnimationControllerHandle, AnimationMotion,
+        AnimationParameter, AnimationState, AnimationTransition,
+    };
+    pub use super::animation_controller_loader::AnimationControllerLoader;
+    pub use super::gltf_importer::GltfAsset;
+    pub use super::lightmap_atlas::{LightmapAtlas, LightmapAtlasHandle, LightmapEntry};
+    pub use super::lightmap_atlas_loader::LightmapAtlasLoader;
+    pub use super::material::{Material, MaterialHandle, MaterialParam};
+    pub use super::material_loader::MaterialLoader;
     pub use super::prefab::{Prefab, PrefabHandle};
     pub use super::prefab_loader::PrefabLoader;
+    pub use super::skeleton::{Bone, Skeleton, SkeletonHandle};
+    pub use super::skeleton_loader::SkeletonLoader;
+    pub use super::texture_atlas_loader::TextureAtlasLoader;
 }
@@ -5,6 +5,7 @@ use crayon::errors::Result;
 use crayon::res::utils::prelude::ResourceLoader;
 use crayon::{bincode, video};
 
+use super::gltf_importer;
 use super::prefab::*;
 
 pub const MAGIC: [u8; 8] = [
@@ -26,6 +27,44 @@ impl ResourceLoader for PrefabLoader {
     type Resource = Arc<Prefab>;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
+        if gltf_importer::is_gltf(bytes) {
+            let asset = gltf_importer::import(bytes)?;
+
+            // Prefabs are independent of renderers, so the materials could not be carried
+            // by them. Use `gltf_importer::import_from` and `GltfAsset::add_materials` to
+            // keep the materials instead.
+            for v in &asset.materials {
+                let textures = [
+                    v.albedo_texture,
+                    v.metallic_roughness_texture,
+                    v.normal_texture,
+                    v.occlusion_texture,
+                    v.emissive_texture,
+                ];
+
+                for &texture in textures.iter().filter_map(|v| v.as_ref()) {
+                    video::delete_texture(texture);
+                }
+            }
+
+            if !asset.materials.is_empty() {
+                warn!(
+                    "[PrefabLoader] the materials of glTF {:?} are discarded.",
+                    handle
+                );
+            }
+
+            let prefab = asset.prefab;
+            info!(
+                "[PrefabLoader] import glTF {:?}. (Nodes: {}, Meshes: {})",
+                handle,
+                prefab.nodes.len(),
+                prefab.meshes.len()
+            );
+
+            return Ok(prefab);
+        }
+
         if &bytes[0..8] != &MAGIC[..] {
             bail!("[PrefabLoader] MAGIC number not match.");
         }
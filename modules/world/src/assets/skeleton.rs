@@ -0,0 +1,96 @@
+use crayon::errors::*;
+use crayon::math::prelude::{Matrix4, SquareMatrix};
+use crayon::res::utils::prelude::ResourceState;
+use crayon::sched::prelude::LatchProbe;
+
+use spatial::prelude::Transform;
+
+impl_handle!(SkeletonHandle);
+
+/// A skeleton asset describes the bone hierarchy of skinned meshes.
+///
+/// Bones are stored in topological order, which means that the parent of any bone
+/// must appear before the bone itself. So the poses in model space could be resolved
+/// in one pass.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Skeleton {
+    pub bones: Vec<Bone>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bone {
+    /// The name of this bone.
+    pub name: String,
+    /// The parent index of this bone.
+    pub parent: Option<usize>,
+    /// The transformation in local space when binding.
+    pub local_transform: Transform,
+    /// The matrix that transforms vertices from model space into the space of this bone.
+    pub inverse_bind_matrix: Matrix4<f32>,
+}
+
+impl Default for Bone {
+    fn default() -> Self {
+        Bone {
+            name: String::new(),
+            parent: None,
+            local_transform: Transform::default(),
+            inverse_bind_matrix: Matrix4::identity(),
+        }
+    }
+}
+
+impl Skeleton {
+    pub fn validate(&self) -> Result<()> {
+        for (i, v) in self.bones.iter().enumerate() {
+            if let Some(parent) = v.parent {
+                if parent >= i {
+                    bail!(
+                        "The parent of bone {} should be placed before itself.",
+                        v.name
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds the index of bone by name.
+    pub fn find<T: AsRef<str>>(&self, name: T) -> Option<usize> {
+        let name = name.as_ref();
+        self.bones.iter().position(|v| v.name == name)
+    }
+
+    /// Gets the local transforms of bones when binding.
+    pub fn bind_pose(&self) -> Vec<Transform> {
+        self.bones.iter().map(|v| v.local_transform).collect()
+    }
+
+    /// Calculates the skinning matrices of specified pose in local space, which transform
+    /// vertices from model space of bind pose into the model space of current pose.
+    pub fn skinning(&self, pose: &[Transform], matrices: &mut Vec<Matrix4<f32>>) {
+        assert!(pose.len() >= self.bones.len());
+
+        let mut globals: Vec<Transform> = Vec::with_capacity(self.bones.len());
+        for (i, v) in self.bones.iter().enumerate() {
+            let transform = match v.parent {
+                Some(parent) => globals[parent] * pose[i],
+                None => pose[i],
+            };
+
+            globals.push(transform);
+        }
+
+        matrices.clear();
+        for (i, v) in self.bones.iter().enumerate() {
+            matrices.push(globals[i].matrix() * v.inverse_bind_matrix);
+        }
+    }
+}
+
+impl LatchProbe for SkeletonHandle {
+    fn is_set(&self) -> bool {
+        ResourceState::NotReady != crate::skeleton_state(*self)
+    }
+}
@@ -0,0 +1,54 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crayon::bincode;
+use crayon::errors::Result;
+use crayon::res::utils::prelude::ResourceLoader;
+
+use super::skeleton::*;
+
+pub const MAGIC: [u8; 8] = [
+    'S' as u8, 'K' as u8, 'E' as u8, 'L' as u8, ' ' as u8, 0, 0, 1,
+];
+
+#[derive(Clone)]
+pub struct SkeletonLoader {}
+
+impl SkeletonLoader {
+    pub fn new() -> Self {
+        SkeletonLoader {}
+    }
+}
+
+impl ResourceLoader for SkeletonLoader {
+    type Handle = SkeletonHandle;
+    type Intermediate = Skeleton;
+    type Resource = Arc<Skeleton>;
+
+    fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
+        if &bytes[0..8] != &MAGIC[..] {
+            bail!("[SkeletonLoader] MAGIC number not match.");
+        }
+
+        let mut file = Cursor::new(&bytes[8..]);
+        let skeleton: Skeleton = bincode::deserialize_from(&mut file)?;
+        skeleton.validate()?;
+
+        info!(
+            "[SkeletonLoader] load {:?}. (Bones: {})",
+            handle,
+            skeleton.bones.len()
+        );
+
+        Ok(skeleton)
+    }
+
+    fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
+        info!("[SkeletonLoader] create {:?}.", handle);
+        Ok(Arc::new(item))
+    }
+
+    fn delete(&self, handle: Self::Handle, _: Self::Resource) {
+        info!("[SkeletonLoader] delete {:?}.", handle);
+    }
+}
@@ -0,0 +1,60 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crayon::errors::Result;
+use crayon::res::utils::prelude::ResourceLoader;
+use crayon::{bincode, video};
+
+use sprite::prelude::{TextureAtlas, TextureAtlasHandle};
+
+pub const MAGIC: [u8; 8] = [
+    'S' as u8, 'A' as u8, 'T' as u8, 'L' as u8, ' ' as u8, 0, 0, 1,
+];
+
+#[derive(Clone)]
+pub struct TextureAtlasLoader {}
+
+impl TextureAtlasLoader {
+    pub fn new() -> Self {
+        TextureAtlasLoader {}
+    }
+}
+
+impl ResourceLoader for TextureAtlasLoader {
+    type Handle = TextureAtlasHandle;
+    type Intermediate = TextureAtlas;
+    type Resource = Arc<TextureAtlas>;
+
+    fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
+        if &bytes[0..8] != &MAGIC[..] {
+            bail!("[TextureAtlasLoader] MAGIC number not match.");
+        }
+
+        let mut file = Cursor::new(&bytes[8..]);
+        let mut atlas: TextureAtlas = bincode::deserialize_from(&mut file)?;
+        atlas.validate()?;
+
+        if let Some(uuid) = atlas.universe_texture() {
+            let texture = video::create_texture_from_uuid(uuid)?;
+            atlas.set_texture(texture);
+        }
+
+        info!(
+            "[TextureAtlasLoader] load {:?}. (Frames: {})",
+            handle,
+            atlas.len()
+        );
+
+        Ok(atlas)
+    }
+
+    fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
+        info!("[TextureAtlasLoader] create {:?}.", handle);
+        Ok(Arc::new(item))
+    }
+
+    fn delete(&self, handle: Self::Handle, atlas: Self::Resource) {
+        info!("[TextureAtlasLoader] delete {:?}.", handle);
+        video::delete_texture(atlas.texture());
+    }
+}
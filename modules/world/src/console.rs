@@ -0,0 +1,446 @@
+//! The developer console, which runs the registered commands and shows the logs in game.
+//!
+//! The console is drawn on top of the window like the performance HUD, and toggled with
+//! `console::set_visible` or the hotkey (`F1` by default). While it's visible, the typed
+//! characters are captured into the command line. `Return` runs the line, `Up` and `Down`
+//! browse the history, and `Tab` completes the name of registered commands.
+//!
+//! ```rust,ignore
+//! console::register("spawn", |args| {
+//!     let n: usize = args.get(0).map(|v| v.parse()).unwrap_or(Ok(1))?;
+//!     console::print(format!("spawned {} monsters.", n));
+//!     Ok(())
+//! });
+//! ```
+//!
+//! The records of `info!`, `warn!` and so on are printed in console as well, which are
+//! filtered by the levels of `crayon::logging`.
+//!
+//! Notes that the input is still dispatched to the application when the console is
+//! visible.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crayon::errors::*;
+use crayon::input::prelude::Key;
+use crayon::logging::{Level, LogRecord, LogSink};
+use crayon::math::prelude::Color;
+use crayon::video;
+use crayon::window;
+
+use sprite::batch::SpriteBatch;
+use text::canvas::Canvas;
+use text::prelude::BitmapFont;
+
+/// The maximum number of lines kept in console.
+pub const MAX_CONSOLE_LINES: usize = 256;
+/// The maximum number of command lines kept in history.
+pub const MAX_CONSOLE_HISTORY: usize = 64;
+
+// The layout of console in points.
+const PADDING: f32 = 6.0;
+const LINE_HEIGHT: f32 = 12.0;
+
+type Command = Arc<dyn Fn(&[&str]) -> Result<()> + Send + Sync>;
+type Lines = Arc<Mutex<VecDeque<ConsoleLine>>>;
+
+/// A line printed in console.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleLine {
+    pub level: Level,
+    pub text: String,
+}
+
+/// Registers a command, which is invoked with the whitespace separated arguments after
+/// its name. The command registered with the same name is replaced.
+pub fn register<T>(name: &str, command: T)
+where
+    T: Fn(&[&str]) -> Result<()> + Send + Sync + 'static,
+{
+    ::inside::ctx()
+        .console()
+        .lock()
+        .unwrap()
+        .register(name, Arc::new(command));
+}
+
+/// Removes a registered command.
+pub fn unregister(name: &str) {
+    ::inside::ctx().console().lock().unwrap().unregister(name);
+}
+
+/// Runs the command line as if it's typed in console. The errors are printed in console
+/// as well.
+pub fn execute(line: &str) -> Result<()> {
+    let (command, args) = ::inside::ctx().console().lock().unwrap().submit(line)?;
+    let args: Vec<_> = args.iter().map(|v| v.as_str()).collect();
+
+    if let Err(err) = command(&args) {
+        print_with(Level::Error, format!("{}", err));
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Gets the names of registered commands that start with `prefix`.
+pub fn complete(prefix: &str) -> Vec<String> {
+    ::inside::ctx().console().lock().unwrap().complete(prefix)
+}
+
+/// Gets the command lines that have been executed, from the oldest one to the latest.
+pub fn history() -> Vec<String> {
+    ::inside::ctx().console().lock().unwrap().history.clone()
+}
+
+/// Prints the text in console.
+#[inline]
+pub fn print<T: Into<String>>(text: T) {
+    print_with(Level::Info, text);
+}
+
+/// Prints the text in console with the level, which decides its color.
+pub fn print_with<T: Into<String>>(level: Level, text: T) {
+    push(
+        ::inside::ctx().console().lock().unwrap().lines(),
+        level,
+        &text.into(),
+    );
+}
+
+/// Gets the lines in console, from the oldest one to the latest.
+pub fn lines() -> Vec<ConsoleLine> {
+    let lines = ::inside::ctx().console().lock().unwrap().lines().clone();
+    let lines = lines.lock().unwrap();
+    lines.iter().cloned().collect()
+}
+
+/// Removes all the lines in console.
+pub fn clear() {
+    let lines = ::inside::ctx().console().lock().unwrap().lines().clone();
+    lines.lock().unwrap().clear();
+}
+
+/// Sets whether the console is shown.
+pub fn set_visible(visible: bool) {
+    ::inside::ctx().console().lock().unwrap().visible = visible;
+}
+
+/// Checks if the console is shown.
+pub fn visible() -> bool {
+    ::inside::ctx().console().lock().unwrap().visible
+}
+
+/// Sets the key that toggles the console, defaults to `Key::F1`. The hotkey is disabled
+/// with `None`.
+pub fn set_hotkey(hotkey: Option<Key>) {
+    ::inside::ctx().console().lock().unwrap().hotkey = hotkey;
+}
+
+/// The sink of logging that prints the records in console.
+pub(crate) struct ConsoleSink(Lines);
+
+impl LogSink for ConsoleSink {
+    fn write(&self, record: &LogRecord) {
+        push(&self.0, record.level, &record.message);
+    }
+}
+
+fn push(lines: &Lines, level: Level, text: &str) {
+    let mut lines = lines.lock().unwrap();
+    for v in text.split('\n') {
+        if lines.len() >= MAX_CONSOLE_LINES {
+            lines.pop_front();
+        }
+
+        lines.push_back(ConsoleLine {
+            level: level,
+            text: v.to_owned(),
+        });
+    }
+}
+
+pub(crate) struct Console {
+    visible: bool,
+    hotkey: Option<Key>,
+    commands: Vec<(String, Command)>,
+    history: Vec<String>,
+    browsing: Option<usize>,
+    input: String,
+    lines: Lines,
+    resources: Option<(SpriteBatch, BitmapFont)>,
+    canvas: Canvas,
+}
+
+impl Drop for Console {
+    fn drop(&mut self) {
+        if let Some((_, font)) = self.resources.take() {
+            video::delete_texture(font.texture());
+        }
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Console {
+            visible: false,
+            hotkey: Some(Key::F1),
+            commands: Vec::new(),
+            history: Vec::new(),
+            browsing: None,
+            input: String::new(),
+            lines: Arc::new(Mutex::new(VecDeque::new())),
+            resources: None,
+            canvas: Canvas::new(),
+        }
+    }
+
+    #[inline]
+    pub fn lines(&self) -> &Lines {
+        &self.lines
+    }
+
+    #[inline]
+    pub fn sink(&self) -> ConsoleSink {
+        ConsoleSink(self.lines.clone())
+    }
+
+    pub fn register(&mut self, name: &str, command: Command) {
+        match self.commands.binary_search_by(|v| v.0.as_str().cmp(name)) {
+            Ok(i) => self.commands[i].1 = command,
+            Err(i) => self.commands.insert(i, (name.to_owned(), command)),
+        }
+    }
+
+    pub fn unregister(&mut self, name: &str) {
+        self.commands.retain(|v| v.0 != name);
+    }
+
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        self.commands
+            .iter()
+            .filter(|v| v.0.starts_with(prefix))
+            .map(|v| v.0.clone())
+            .collect()
+    }
+
+    /// Echos the line and records it in history, then finds the command and arguments.
+    pub fn submit(&mut self, line: &str) -> Result<(Command, Vec<String>)> {
+        let line = line.trim();
+        push(&self.lines, Level::Info, &format!("> {}", line));
+
+        if !line.is_empty() && self.history.last().map(|v| v != line).unwrap_or(true) {
+            if self.history.len() >= MAX_CONSOLE_HISTORY {
+                self.history.remove(0);
+            }
+
+            self.history.push(line.to_owned());
+        }
+
+        self.browsing = None;
+
+        let mut args = line.split_whitespace();
+        let name = match args.next() {
+            Some(name) => name,
+            None => bail!("The command line is empty."),
+        };
+
+        match self.commands.iter().find(|v| v.0 == name) {
+            Some(v) => Ok((v.1.clone(), args.map(|v| v.to_owned()).collect())),
+            None => {
+                push(
+                    &self.lines,
+                    Level::Error,
+                    &format!("Unknown command `{}`.", name),
+                );
+                bail!("Unknown command `{}`.", name);
+            }
+        }
+    }
+
+    /// Moves through the history, the latest line is picked first.
+    fn browse(&mut self, backward: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+
+        self.browsing = match (self.browsing, backward) {
+            (None, true) => Some(self.history.len() - 1),
+            (None, false) => None,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < self.history.len() => Some(i + 1),
+            (Some(_), false) => None,
+        };
+
+        self.input = match self.browsing {
+            Some(i) => self.history[i].clone(),
+            None => String::new(),
+        };
+    }
+
+    /// Completes the name of command in the input, the candidates are printed if there
+    /// are more than one.
+    fn complete_input(&mut self) {
+        if self.input.contains(char::is_whitespace) {
+            return;
+        }
+
+        let candidates = self.complete(&self.input);
+        match candidates.len() {
+            0 => {}
+            1 => self.input = format!("{} ", candidates[0]),
+            _ => {
+                let mut common = candidates[0].clone();
+                for v in &candidates[1..] {
+                    while !v.starts_with(common.as_str()) {
+                        common.pop();
+                    }
+                }
+
+                push(&self.lines, Level::Info, &candidates.join("  "));
+                self.input = common;
+            }
+        }
+    }
+
+    /// Handles the input, and draws the console if it's visible. Returns the command
+    /// lines submitted during this frame, which should be executed without locking the
+    /// console.
+    pub fn advance(&mut self) -> Result<Vec<String>> {
+        use crayon::input;
+
+        let mut submitted = Vec::new();
+        if let Some(key) = self.hotkey {
+            if input::is_key_press(key) {
+                self.visible = !self.visible;
+                return Ok(submitted);
+            }
+        }
+
+        if !self.visible {
+            return Ok(submitted);
+        }
+
+        self.input
+            .extend(input::text().chars().filter(|v| !v.is_control()));
+
+        if input::is_key_press(Key::Back) || input::is_key_repeat(Key::Back) {
+            self.input.pop();
+        }
+
+        if input::is_key_press(Key::Return) || input::is_key_press(Key::NumpadEnter) {
+            submitted.push(::std::mem::replace(&mut self.input, String::new()));
+        }
+
+        if input::is_key_press(Key::Up) {
+            self.browse(true);
+        }
+
+        if input::is_key_press(Key::Down) {
+            self.browse(false);
+        }
+
+        if input::is_key_press(Key::Tab) {
+            self.complete_input();
+        }
+
+        if input::is_key_press(Key::Escape) {
+            self.visible = false;
+            return Ok(submitted);
+        }
+
+        self.draw()?;
+        Ok(submitted)
+    }
+
+    fn draw(&mut self) -> Result<()> {
+        if self.resources.is_none() {
+            let font = ::text::builtin::font()?;
+            self.resources = Some((SpriteBatch::new()?, font));
+        }
+
+        let (batch, font) = self.resources.as_mut().unwrap();
+
+        // The console takes the upper half of window.
+        let dimensions = window::dimensions();
+        let width = dimensions.x as f32;
+        let height = (dimensions.y as f32 * 0.5).max(PADDING * 2.0 + LINE_HEIGHT);
+        let rows = ((height - PADDING * 2.0) / LINE_HEIGHT) as usize;
+
+        let canvas = &mut self.canvas;
+        canvas.begin();
+        canvas.rect([0.0, 0.0], [width, height], Color::new(0.0, 0.0, 0.0, 0.75));
+
+        let prompt = [PADDING, height - PADDING - LINE_HEIGHT];
+        let text = format!("> {}_", self.input);
+        canvas.text(font, prompt, LINE_HEIGHT, &text, Color::white());
+
+        // The lines are not locked while drawing, since the records might be logged.
+        let lines: Vec<_> = {
+            let lines = self.lines.lock().unwrap();
+            let skip = lines.len().saturating_sub(rows.saturating_sub(1));
+            lines.iter().skip(skip).cloned().collect()
+        };
+
+        for (i, v) in lines.iter().enumerate() {
+            let color = match v.level {
+                Level::Error => Color::new(0.9, 0.3, 0.3, 1.0),
+                Level::Warn => Color::new(0.9, 0.8, 0.3, 1.0),
+                Level::Info => Color::white(),
+                Level::Debug | Level::Trace => Color::new(0.6, 0.6, 0.6, 1.0),
+            };
+
+            let position = [PADDING, PADDING + i as f32 * LINE_HEIGHT];
+            canvas.text(font, position, LINE_HEIGHT, &v.text, color);
+        }
+
+        canvas.draw(batch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        let mut console = Console::new();
+        console.register("spawn", Arc::new(|_: &[&str]| Ok(())));
+        console.register("speed", Arc::new(|_: &[&str]| Ok(())));
+        console.register("quit", Arc::new(|_: &[&str]| Ok(())));
+
+        assert_eq!(console.complete("sp"), vec!["spawn", "speed"]);
+        assert_eq!(console.complete("q"), vec!["quit"]);
+
+        console.input = "sp".into();
+        console.complete_input();
+        assert_eq!(console.input, "sp");
+        console.input = "spa".into();
+        console.complete_input();
+        assert_eq!(console.input, "spawn ");
+
+        let (_, args) = console.submit(" spawn 3  orc ").unwrap();
+        assert_eq!(args, vec!["3", "orc"]);
+        assert!(console.submit("jump").is_err());
+        assert!(console.submit("jump").is_err());
+
+        console.unregister("quit");
+        assert!(console.submit("quit").is_err());
+        assert_eq!(console.history, vec!["spawn 3  orc", "jump", "quit"]);
+
+        console.browse(true);
+        assert_eq!(console.input, "quit");
+        console.browse(true);
+        console.browse(true);
+        console.browse(true);
+        assert_eq!(console.input, "spawn 3  orc");
+        console.browse(false);
+        assert_eq!(console.input, "jump");
+
+        let lines = console.lines.lock().unwrap();
+        assert_eq!(lines[0].text, "spawn  speed");
+        assert_eq!(lines[1].text, "> spawn 3  orc");
+        assert_eq!(lines[3].level, Level::Error);
+    }
+}
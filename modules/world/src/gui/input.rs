@@ -0,0 +1,69 @@
+use crayon::input::prelude::{Key, MouseButton};
+use crayon::math::prelude::Vector2;
+use crayon::{input, window};
+
+/// The snapshot of input that drives `Gui` during one frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuiInput {
+    /// The dimensions of window in points.
+    pub dimensions: Vector2<f32>,
+    /// The ratio that converts points into pixels.
+    pub device_pixel_ratio: f32,
+    /// The position of mouse in points, which takes the top-left corner of window as
+    /// origin.
+    pub mouse_position: Vector2<f32>,
+    /// Is the left mouse button held down.
+    pub mouse_down: bool,
+    /// Has the left mouse button been pressed during last frame.
+    pub mouse_press: bool,
+    /// Has the left mouse button been released during last frame.
+    pub mouse_release: bool,
+    /// The characters typed during last frame, including the ones committed by input
+    /// methods.
+    pub text: String,
+    /// Has the backspace key been pressed or repeated during last frame.
+    pub backspace: bool,
+    /// Has the return key been pressed during last frame.
+    pub enter: bool,
+    /// Has the escape key been pressed during last frame.
+    pub escape: bool,
+}
+
+impl Default for GuiInput {
+    fn default() -> Self {
+        GuiInput {
+            dimensions: Vector2::new(0.0, 0.0),
+            device_pixel_ratio: 1.0,
+            mouse_position: Vector2::new(0.0, 0.0),
+            mouse_down: false,
+            mouse_press: false,
+            mouse_release: false,
+            text: String::new(),
+            backspace: false,
+            enter: false,
+            escape: false,
+        }
+    }
+}
+
+impl GuiInput {
+    /// Captures the input of window in last frame.
+    pub fn capture() -> Self {
+        let dimensions = window::dimensions();
+        let dimensions = Vector2::new(dimensions.x as f32, dimensions.y as f32);
+        let mouse = input::mouse_position();
+
+        GuiInput {
+            dimensions: dimensions,
+            device_pixel_ratio: window::device_pixel_ratio(),
+            mouse_position: Vector2::new(mouse.x, dimensions.y - mouse.y),
+            mouse_down: input::is_mouse_down(MouseButton::Left),
+            mouse_press: input::is_mouse_press(MouseButton::Left),
+            mouse_release: input::is_mouse_release(MouseButton::Left),
+            text: input::text().chars().filter(|v| !v.is_control()).collect(),
+            backspace: input::is_key_press(Key::Back) || input::is_key_repeat(Key::Back),
+            enter: input::is_key_press(Key::Return) || input::is_key_press(Key::NumpadEnter),
+            escape: input::is_key_press(Key::Escape),
+        }
+    }
+}
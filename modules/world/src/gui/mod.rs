@@ -0,0 +1,791 @@
+//! An immediate-mode GUI on top of the window, which is useful for in-game debugging
+//! tools and simple menus.
+//!
+//! Widgets are declared every frame between `Gui::begin` and `Gui::end`, and they return
+//! the interactions of user immediately. The values are modified in-place, so there is
+//! no state to be synchronized with the application:
+//!
+//! ```rust,ignore
+//! self.gui.begin()?;
+//! self.gui.begin_window("Debug", [16.0, 16.0], [200.0, 160.0]);
+//! if self.gui.button("Spawn") {
+//!     self.spawn();
+//! }
+//!
+//! self.gui.checkbox("Wireframe", &mut self.wireframe);
+//! self.gui.slider("Speed", &mut self.speed, 0.0, 10.0);
+//! self.gui.text_field("Name", &mut self.name);
+//! self.gui.end_window();
+//! self.gui.end()?;
+//! ```
+//!
+//! The widgets declared outside windows are placed at the top-left corner of window.
+//! Widgets are identified by their labels, so the labels should be unique in the same
+//! window. Windows could be dragged with their title bars, and the clicked window is
+//! brought to front. The contents of windows are clipped with the scissor test.
+//!
+//! All the rectangles and glyphs are batched into the dynamic mesh of `SpriteBatch`, and
+//! each window takes two draw calls at most, one for rectangles and one for texts. The
+//! positions and sizes are measured in points, and take the top-left corner of window
+//! as origin.
+//!
+//! Text fields receive the characters committed by input methods (IME) as well.
+
+mod input;
+mod style;
+
+pub mod prelude {
+    pub use super::input::GuiInput;
+    pub use super::style::GuiStyle;
+    pub use super::Gui;
+}
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crayon::errors::*;
+use crayon::math::prelude::{Aabb2, Color, EuclideanSpace, Vector2};
+use crayon::video;
+
+use sprite::batch::SpriteBatch;
+use text::canvas::Canvas;
+use text::prelude::BitmapFont;
+
+use self::input::GuiInput;
+use self::style::GuiStyle;
+
+/// The immediate-mode GUI, see the module documentation for details.
+pub struct Gui {
+    style: GuiStyle,
+    input: GuiInput,
+    font: Option<(BitmapFont, bool)>,
+    batch: Option<SpriteBatch>,
+    canvas: Canvas,
+
+    root: Window,
+    windows: Vec<Window>,
+    current: Option<usize>,
+    hovered_window: u64,
+
+    active: Option<u64>,
+    focused: Option<u64>,
+    focus_kept: bool,
+    dragging: Option<(u64, Vector2<f32>)>,
+}
+
+/// The state of window, which is kept across frames.
+struct Window {
+    id: u64,
+    title: Option<String>,
+    position: Vector2<f32>,
+    size: Vector2<f32>,
+    alive: bool,
+    cursor: Vector2<f32>,
+    rects: Vec<(Aabb2<f32>, Color<f32>)>,
+    texts: Vec<(Vector2<f32>, String, Color<f32>)>,
+}
+
+impl Window {
+    fn new(id: u64, position: Vector2<f32>, size: Vector2<f32>) -> Self {
+        Window {
+            id: id,
+            title: None,
+            position: position,
+            size: size,
+            alive: false,
+            cursor: position,
+            rects: Vec::new(),
+            texts: Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn rect(&self) -> Aabb2<f32> {
+        aabb(self.position, self.position + self.size)
+    }
+}
+
+impl Drop for Gui {
+    fn drop(&mut self) {
+        if let Some((font, true)) = self.font.take() {
+            video::delete_texture(font.texture());
+        }
+    }
+}
+
+impl Gui {
+    /// Creates a new `Gui` with the default style and the built-in font.
+    pub fn new() -> Self {
+        Gui {
+            style: GuiStyle::default(),
+            input: GuiInput::default(),
+            font: None,
+            batch: None,
+            canvas: Canvas::new(),
+            root: Window::new(0, Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0)),
+            windows: Vec::new(),
+            current: None,
+            hovered_window: 0,
+            active: None,
+            focused: None,
+            focus_kept: false,
+            dragging: None,
+        }
+    }
+
+    /// Sets the style of widgets.
+    #[inline]
+    pub fn set_style(&mut self, style: GuiStyle) {
+        self.style = style;
+    }
+
+    /// Gets the style of widgets.
+    #[inline]
+    pub fn style(&self) -> &GuiStyle {
+        &self.style
+    }
+
+    /// Sets the font of texts, which replaces the built-in font. The texture of font is
+    /// still owned by the caller.
+    pub fn set_font(&mut self, font: BitmapFont) {
+        if let Some((font, true)) = self.font.take() {
+            video::delete_texture(font.texture());
+        }
+
+        self.font = Some((font, false));
+    }
+
+    /// Checks if any text field is focused, which is useful to stop the keyboard
+    /// shortcuts of application while typing.
+    #[inline]
+    pub fn is_typing(&self) -> bool {
+        self.focused.is_some()
+    }
+
+    /// Begins a new frame with the input of window.
+    pub fn begin(&mut self) -> Result<()> {
+        self.begin_with(GuiInput::capture())
+    }
+
+    /// Begins a new frame with `input`.
+    pub fn begin_with(&mut self, input: GuiInput) -> Result<()> {
+        if self.font.is_none() {
+            self.font = Some((::text::builtin::font()?, true));
+        }
+
+        self.input = input;
+        self.current = None;
+        self.focus_kept = false;
+
+        // Finds the top-most window under mouse with the positions in last frame.
+        let mouse = self.input.mouse_position;
+        self.hovered_window = self
+            .windows
+            .iter()
+            .rev()
+            .find(|v| v.alive && contains(&v.rect(), mouse))
+            .map(|v| v.id)
+            .unwrap_or(self.root.id);
+
+        if self.input.mouse_press {
+            let hovered = self.hovered_window;
+            if let Some(i) = self.windows.iter().position(|v| v.id == hovered) {
+                let window = self.windows.remove(i);
+                self.windows.push(window);
+            }
+        }
+
+        for v in &mut self.windows {
+            v.alive = false;
+        }
+
+        let padding = self.style.padding;
+        self.root.size = self.input.dimensions;
+        self.root.cursor = Vector2::new(padding, padding);
+        self.root.rects.clear();
+        self.root.texts.clear();
+        Ok(())
+    }
+
+    /// Ends the frame, and draws the windows and widgets.
+    pub fn end(&mut self) -> Result<()> {
+        if self.font.is_none() {
+            bail!("[Gui] `begin` should be called before `end`.");
+        }
+
+        self.finish();
+
+        if self.batch.is_none() {
+            self.batch = Some(SpriteBatch::new()?);
+        }
+
+        self.canvas.draw(self.batch.as_mut().unwrap())
+    }
+
+    /// Begins a window with title bar, which could be dragged by mouse. The `position`
+    /// and `size` are only used when the window is shown at the first time.
+    pub fn begin_window<T1, T2>(&mut self, title: &str, position: T1, size: T2)
+    where
+        T1: Into<Vector2<f32>>,
+        T2: Into<Vector2<f32>>,
+    {
+        self.begin_container(title, Some(title), position.into(), size.into());
+    }
+
+    /// Begins a panel, which is a fixed window without title bar.
+    pub fn begin_panel<T1, T2>(&mut self, name: &str, position: T1, size: T2)
+    where
+        T1: Into<Vector2<f32>>,
+        T2: Into<Vector2<f32>>,
+    {
+        let (position, size) = (position.into(), size.into());
+        self.begin_container(name, None, position, size);
+
+        // Panels are always placed at the given position.
+        let window = self.window_mut();
+        window.cursor += position - window.position;
+        window.position = position;
+        window.size = size;
+        window.rects[0].0 = aabb(position, position + size);
+    }
+
+    /// Ends the current window or panel, the widgets after this are placed outside.
+    #[inline]
+    pub fn end_window(&mut self) {
+        self.current = None;
+    }
+
+    /// Adds a line of text.
+    pub fn label(&mut self, text: &str) {
+        let height = self.style.text_height;
+        let rect = self.allocate(height);
+        let color = self.style.text;
+        self.window_mut()
+            .texts
+            .push((rect.min.to_vec(), text.to_owned(), color));
+    }
+
+    /// Adds a button, and returns true if it's clicked.
+    pub fn button(&mut self, label: &str) -> bool {
+        let id = self.id(label);
+        let rect = self.allocate(self.style.widget_height);
+        let (hovered, active, clicked) = self.interact(id, rect);
+
+        let color = self.widget_color(hovered, active);
+        self.push_rect(rect, color);
+        self.push_text_centered(rect, label, self.style.text);
+        clicked
+    }
+
+    /// Adds a checkbox, and returns true if the value is toggled.
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let id = self.id(label);
+        let height = self.style.widget_height;
+        let rect = self.allocate(height);
+        let (hovered, active, clicked) = self.interact(id, rect);
+
+        if clicked {
+            *value = !*value;
+        }
+
+        let rbox = Aabb2::new(rect.min, [rect.min.x + height, rect.max.y].into());
+        let color = self.widget_color(hovered, active);
+        self.push_rect(rbox, color);
+
+        if *value {
+            let inset = (height * 0.25).floor();
+            let min = [rbox.min.x + inset, rbox.min.y + inset];
+            let max = [rbox.max.x - inset, rbox.max.y - inset];
+            self.push_rect(Aabb2::new(min.into(), max.into()), self.style.accent);
+        }
+
+        let position = [rect.min.x + height + self.style.spacing, rect.min.y];
+        let label_rect = Aabb2::new(position.into(), rect.max);
+        self.push_text_left(label_rect, label, self.style.text);
+        clicked
+    }
+
+    /// Adds a horizontal slider, and returns true if the value is changed by dragging.
+    pub fn slider(&mut self, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let id = self.id(label);
+        let rect = self.allocate(self.style.widget_height);
+        let (hovered, active, _) = self.interact(id, rect);
+
+        let width = (rect.max.x - rect.min.x).max(::std::f32::EPSILON);
+        let mut changed = false;
+        if active {
+            let t = ((self.input.mouse_position.x - rect.min.x) / width)
+                .max(0.0)
+                .min(1.0);
+            let v = min + (max - min) * t;
+            changed = v != *value;
+            *value = v;
+        }
+
+        let color = self.widget_color(hovered, active);
+        self.push_rect(rect, color);
+
+        let range = max - min;
+        let t = if range.abs() > ::std::f32::EPSILON {
+            ((*value - min) / range).max(0.0).min(1.0)
+        } else {
+            0.0
+        };
+
+        let handle = (self.style.widget_height * 0.5).floor();
+        let x = rect.min.x + (width - handle) * t;
+        let hrect = Aabb2::new([x, rect.min.y].into(), [x + handle, rect.max.y].into());
+        self.push_rect(hrect, self.style.accent);
+
+        let text = format!("{}: {:.2}", label, *value);
+        self.push_text_centered(rect, &text, self.style.text);
+        changed
+    }
+
+    /// Adds a single-line text field, and returns true if the text is changed. The
+    /// field is focused by clicking, and it's unfocused by `Return`, `Escape` or clicking
+    /// elsewhere. The `label` is shown as placeholder when the text is empty.
+    pub fn text_field(&mut self, label: &str, text: &mut String) -> bool {
+        let id = self.id(label);
+        let rect = self.allocate(self.style.widget_height);
+        let (hovered, _, _) = self.interact(id, rect);
+
+        if self.input.mouse_press {
+            if hovered {
+                self.focused = Some(id);
+            } else if self.focused == Some(id) {
+                self.focused = None;
+            }
+        }
+
+        let mut changed = false;
+        let focused = self.focused == Some(id);
+        if focused {
+            self.focus_kept = true;
+
+            if !self.input.text.is_empty() {
+                text.push_str(&self.input.text);
+                changed = true;
+            }
+
+            if self.input.backspace && text.pop().is_some() {
+                changed = true;
+            }
+
+            if self.input.enter || self.input.escape {
+                self.focused = None;
+            }
+        }
+
+        let color = self.widget_color(hovered, focused);
+        self.push_rect(rect, color);
+
+        let inset = self.style.spacing;
+        let trect = Aabb2::new([rect.min.x + inset, rect.min.y].into(), rect.max);
+        if text.is_empty() && !focused {
+            self.push_text_left(trect, label, self.style.placeholder);
+        } else {
+            let end = self.push_text_left(trect, text, self.style.text);
+            if focused {
+                let (top, bottom) = (rect.min.y + 3.0, rect.max.y - 3.0);
+                let caret = Aabb2::new([end, top].into(), [end + 1.0, bottom].into());
+                self.push_rect(caret, self.style.accent);
+            }
+        }
+
+        changed
+    }
+
+    fn begin_container(
+        &mut self,
+        name: &str,
+        title: Option<&str>,
+        position: Vector2<f32>,
+        size: Vector2<f32>,
+    ) {
+        let id = hash(self.root.id, name);
+        let index = match self.windows.iter().position(|v| v.id == id) {
+            Some(index) => index,
+            None => {
+                self.windows.push(Window::new(id, position, size));
+                self.windows.len() - 1
+            }
+        };
+
+        let style = self.style;
+        let input = self.input.clone();
+        let hovered = self.hovered_window == id;
+        let mut dragging = self.dragging;
+
+        {
+            let window = &mut self.windows[index];
+            window.alive = true;
+            window.title = title.map(|v| v.to_owned());
+            window.rects.clear();
+            window.texts.clear();
+
+            if title.is_some() {
+                let size = Vector2::new(window.size.x, style.title_height);
+                let bar = aabb(window.position, window.position + size);
+
+                if hovered && input.mouse_press && contains(&bar, input.mouse_position) {
+                    dragging = Some((id, input.mouse_position - window.position));
+                }
+
+                if let Some((v, offset)) = dragging {
+                    if v == id && input.mouse_down {
+                        window.position = input.mouse_position - offset;
+                    }
+                }
+            }
+
+            let title_height = if title.is_some() {
+                style.title_height
+            } else {
+                0.0
+            };
+
+            window.rects.push((window.rect(), style.window));
+            if let Some(title) = title {
+                let min = window.position;
+                let max = Vector2::new(min.x + window.size.x, min.y + title_height);
+                window.rects.push((aabb(min, max), style.title));
+
+                let y = min.y + ((title_height - style.text_height) * 0.5).floor();
+                let position = Vector2::new(min.x + style.padding, y);
+                window.texts.push((position, title.to_owned(), style.text));
+            }
+
+            window.cursor =
+                window.position + Vector2::new(style.padding, title_height + style.padding);
+        }
+
+        self.dragging = dragging;
+        self.current = Some(index);
+    }
+
+    fn finish(&mut self) {
+        if !self.input.mouse_down {
+            self.active = None;
+            self.dragging = None;
+        }
+
+        // The focused text field is not declared in this frame any more.
+        if !self.focus_kept {
+            self.focused = None;
+        }
+
+        self.current = None;
+        self.canvas
+            .begin_with(self.input.dimensions, self.input.device_pixel_ratio);
+
+        let font = &self.font.as_ref().unwrap().0;
+        let height = self.style.text_height;
+
+        Self::draw_window(&mut self.canvas, font, height, &self.root, None);
+        for v in self.windows.iter().filter(|v| v.alive) {
+            let rect = v.rect();
+            let clip = Some((rect.min.into(), rect.max.into()));
+            Self::draw_window(&mut self.canvas, font, height, v, clip);
+        }
+    }
+
+    /// Draws the rectangles of window before texts, so they are batched into two draw
+    /// calls.
+    fn draw_window(
+        canvas: &mut Canvas,
+        font: &BitmapFont,
+        height: f32,
+        window: &Window,
+        clip: Option<([f32; 2], [f32; 2])>,
+    ) {
+        canvas.clip(clip);
+
+        for &(rect, color) in &window.rects {
+            canvas.rect(rect.min.into(), rect.max.into(), color);
+        }
+
+        for &(position, ref text, color) in &window.texts {
+            canvas.text(font, position.into(), height, text, color);
+        }
+    }
+
+    fn id(&self, label: &str) -> u64 {
+        let parent = match self.current {
+            Some(i) => self.windows[i].id,
+            None => self.root.id,
+        };
+
+        hash(parent, label)
+    }
+
+    fn window_mut(&mut self) -> &mut Window {
+        match self.current {
+            Some(i) => &mut self.windows[i],
+            None => &mut self.root,
+        }
+    }
+
+    /// Allocates a row with `height` in the current window.
+    fn allocate(&mut self, height: f32) -> Aabb2<f32> {
+        let (padding, spacing) = (self.style.padding, self.style.spacing);
+        let window = self.window_mut();
+
+        let width = if window.size.x > padding * 2.0 {
+            window.size.x - padding * 2.0
+        } else {
+            0.0
+        };
+
+        let min = window.cursor;
+        let max = Vector2::new(min.x + width, min.y + height);
+        window.cursor.y += height + spacing;
+        aabb(min, max)
+    }
+
+    /// Returns whether the widget is hovered, active and clicked.
+    fn interact(&mut self, id: u64, rect: Aabb2<f32>) -> (bool, bool, bool) {
+        let (window, bounds) = match self.current {
+            Some(i) => (self.windows[i].id, Some(self.windows[i].rect())),
+            None => (self.root.id, None),
+        };
+
+        let mouse = self.input.mouse_position;
+        let hovered = self.dragging.is_none()
+            && self.hovered_window == window
+            && contains(&rect, mouse)
+            && bounds.map(|v| contains(&v, mouse)).unwrap_or(true);
+
+        if hovered && self.input.mouse_press {
+            self.active = Some(id);
+        }
+
+        let active = self.active == Some(id);
+        let clicked = active && hovered && self.input.mouse_release;
+        (hovered, active, clicked)
+    }
+
+    fn widget_color(&self, hovered: bool, active: bool) -> Color<f32> {
+        if active {
+            self.style.widget_active
+        } else if hovered {
+            self.style.widget_hovered
+        } else {
+            self.style.widget
+        }
+    }
+
+    fn push_rect(&mut self, rect: Aabb2<f32>, color: Color<f32>) {
+        self.window_mut().rects.push((rect, color));
+    }
+
+    /// Places the text at the left side of rectangle, and returns the right edge of it.
+    fn push_text_left(&mut self, rect: Aabb2<f32>, text: &str, color: Color<f32>) -> f32 {
+        let size = self.measure(text);
+        let y = rect.min.y + ((rect.max.y - rect.min.y - size.y) * 0.5).floor();
+        let position = Vector2::new(rect.min.x, y);
+        self.window_mut()
+            .texts
+            .push((position, text.to_owned(), color));
+        rect.min.x + size.x
+    }
+
+    fn push_text_centered(&mut self, rect: Aabb2<f32>, text: &str, color: Color<f32>) {
+        let size = self.measure(text);
+        let x = rect.min.x + ((rect.max.x - rect.min.x - size.x) * 0.5).floor();
+        let y = rect.min.y + ((rect.max.y - rect.min.y - size.y) * 0.5).floor();
+        let position = Vector2::new(x, y);
+        self.window_mut()
+            .texts
+            .push((position, text.to_owned(), color));
+    }
+
+    /// Measures the size of text in points.
+    fn measure(&self, text: &str) -> Vector2<f32> {
+        let font = &self.font.as_ref().unwrap().0;
+        let scale = self.style.text_height / font.line_height().max(::std::f32::EPSILON);
+        font.measure(text) * scale
+    }
+}
+
+fn hash(parent: u64, label: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    parent.hash(&mut hasher);
+    label.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[inline]
+fn aabb(min: Vector2<f32>, max: Vector2<f32>) -> Aabb2<f32> {
+    Aabb2::new([min.x, min.y].into(), [max.x, max.y].into())
+}
+
+#[inline]
+fn contains(rect: &Aabb2<f32>, point: Vector2<f32>) -> bool {
+    point.x >= rect.min.x && point.x < rect.max.x && point.y >= rect.min.y && point.y < rect.max.y
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crayon::video::assets::prelude::TextureHandle;
+
+    fn input(x: f32, y: f32, down: bool, press: bool, release: bool) -> GuiInput {
+        GuiInput {
+            dimensions: Vector2::new(320.0, 240.0),
+            mouse_position: Vector2::new(x, y),
+            mouse_down: down,
+            mouse_press: press,
+            mouse_release: release,
+            ..GuiInput::default()
+        }
+    }
+
+    fn gui() -> Gui {
+        let mut gui = Gui::new();
+        let font = BitmapFont::grid(TextureHandle::default(), (96, 48), (6, 8), ' ');
+        gui.set_font(font);
+        gui
+    }
+
+    #[test]
+    fn widgets() {
+        assert!(Gui::new().end().is_err());
+
+        let mut gui = gui();
+        let mut checked = false;
+        let mut speed = 0.5;
+
+        // The first button takes [6, 6] to [314, 24], and the rows below are separated
+        // by 4 points.
+        gui.begin_with(input(10.0, 10.0, true, true, false))
+            .unwrap();
+        assert!(!gui.button("Spawn"));
+        assert!(!gui.checkbox("Wireframe", &mut checked));
+        gui.finish();
+
+        gui.begin_with(input(10.0, 10.0, false, false, true))
+            .unwrap();
+        assert!(gui.button("Spawn"));
+        assert!(!gui.checkbox("Wireframe", &mut checked));
+        gui.finish();
+
+        // Releasing outside the pressed widget does not click it.
+        gui.begin_with(input(10.0, 30.0, true, true, false))
+            .unwrap();
+        gui.button("Spawn");
+        gui.checkbox("Wireframe", &mut checked);
+        gui.finish();
+
+        gui.begin_with(input(10.0, 60.0, false, false, true))
+            .unwrap();
+        gui.button("Spawn");
+        assert!(!gui.checkbox("Wireframe", &mut checked));
+        assert!(!checked);
+        gui.finish();
+
+        gui.begin_with(input(10.0, 30.0, true, true, false))
+            .unwrap();
+        gui.button("Spawn");
+        gui.checkbox("Wireframe", &mut checked);
+        gui.finish();
+
+        gui.begin_with(input(10.0, 30.0, false, false, true))
+            .unwrap();
+        gui.button("Spawn");
+        assert!(gui.checkbox("Wireframe", &mut checked));
+        assert!(checked);
+        gui.finish();
+
+        // The slider follows the mouse while it's active, even outside its row.
+        gui.begin_with(input(6.0, 60.0, true, true, false)).unwrap();
+        gui.button("Spawn");
+        gui.checkbox("Wireframe", &mut checked);
+        assert!(gui.slider("Speed", &mut speed, 0.0, 2.0));
+        assert_eq!(speed, 0.0);
+        gui.finish();
+
+        gui.begin_with(input(400.0, 0.0, true, false, false))
+            .unwrap();
+        gui.button("Spawn");
+        gui.checkbox("Wireframe", &mut checked);
+        assert!(gui.slider("Speed", &mut speed, 0.0, 2.0));
+        assert_eq!(speed, 2.0);
+        gui.finish();
+    }
+
+    #[test]
+    fn text_field() {
+        let mut gui = gui();
+        let mut name = "ab".to_owned();
+
+        gui.begin_with(input(10.0, 10.0, true, true, false))
+            .unwrap();
+        assert!(!gui.text_field("Name", &mut name));
+        gui.finish();
+        assert!(gui.is_typing());
+
+        let mut typing = input(10.0, 10.0, false, false, true);
+        typing.text = "cd".to_owned();
+        typing.backspace = true;
+        gui.begin_with(typing).unwrap();
+        assert!(gui.text_field("Name", &mut name));
+        gui.finish();
+        assert_eq!(name, "abc");
+
+        // Clicking elsewhere unfocuses the text field.
+        gui.begin_with(input(10.0, 100.0, true, true, false))
+            .unwrap();
+        gui.text_field("Name", &mut name);
+        gui.finish();
+        assert!(!gui.is_typing());
+
+        let mut typing = input(10.0, 100.0, false, false, true);
+        typing.text = "e".to_owned();
+        gui.begin_with(typing).unwrap();
+        assert!(!gui.text_field("Name", &mut name));
+        gui.finish();
+        assert_eq!(name, "abc");
+    }
+
+    #[test]
+    fn windows() {
+        let mut gui = gui();
+        let frame = |gui: &mut Gui, input: GuiInput| {
+            gui.begin_with(input).unwrap();
+            gui.begin_window("Back", [100.0, 100.0], [200.0, 100.0]);
+            let back = gui.button("Button");
+            gui.end_window();
+            gui.begin_window("Front", [150.0, 120.0], [100.0, 100.0]);
+            let front = gui.button("Button");
+            gui.end_window();
+            gui.finish();
+            (back, front)
+        };
+
+        frame(&mut gui, input(0.0, 0.0, false, false, false));
+        assert_eq!(gui.windows[1].position, Vector2::new(150.0, 120.0));
+
+        // The widgets of back window are covered by the front window.
+        frame(&mut gui, input(160.0, 130.0, true, true, false));
+        assert_eq!(
+            frame(&mut gui, input(160.0, 130.0, false, false, true)),
+            (false, false)
+        );
+
+        frame(&mut gui, input(160.0, 150.0, true, true, false));
+        assert_eq!(
+            frame(&mut gui, input(160.0, 150.0, false, false, true)),
+            (false, true)
+        );
+
+        // Drags the back window with its title bar, which brings it to front.
+        frame(&mut gui, input(110.0, 105.0, true, true, false));
+        assert_eq!(gui.windows[1].id, hash(0, "Back"));
+        frame(&mut gui, input(150.0, 205.0, true, false, false));
+        frame(&mut gui, input(150.0, 205.0, false, false, true));
+        assert_eq!(gui.windows[1].position, Vector2::new(140.0, 200.0));
+        frame(&mut gui, input(150.0, 305.0, false, false, false));
+        assert_eq!(gui.windows[1].position, Vector2::new(140.0, 200.0));
+    }
+}
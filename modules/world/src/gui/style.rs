@@ -0,0 +1,55 @@
+use crayon::math::prelude::Color;
+
+/// The metrics and colors of windows and widgets, all the metrics are measured in
+/// points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GuiStyle {
+    /// The height of texts.
+    pub text_height: f32,
+    /// The height of buttons, checkboxes, sliders and text fields.
+    pub widget_height: f32,
+    /// The height of the title bars of windows.
+    pub title_height: f32,
+    /// The space between the borders of windows and their contents.
+    pub padding: f32,
+    /// The vertical space between widgets.
+    pub spacing: f32,
+
+    /// The color of texts.
+    pub text: Color<f32>,
+    /// The color of the placeholders of empty text fields.
+    pub placeholder: Color<f32>,
+    /// The background color of windows.
+    pub window: Color<f32>,
+    /// The color of the title bars of windows.
+    pub title: Color<f32>,
+    /// The color of widgets.
+    pub widget: Color<f32>,
+    /// The color of widgets under mouse.
+    pub widget_hovered: Color<f32>,
+    /// The color of widgets that are pressed or focused.
+    pub widget_active: Color<f32>,
+    /// The color of the handles of sliders, the marks of checkboxes and the carets of
+    /// text fields.
+    pub accent: Color<f32>,
+}
+
+impl Default for GuiStyle {
+    fn default() -> Self {
+        GuiStyle {
+            text_height: 12.0,
+            widget_height: 18.0,
+            title_height: 18.0,
+            padding: 6.0,
+            spacing: 4.0,
+            text: Color::white(),
+            placeholder: Color::new(0.6, 0.6, 0.6, 1.0),
+            window: Color::new(0.1, 0.1, 0.1, 0.85),
+            title: Color::new(0.2, 0.3, 0.5, 1.0),
+            widget: Color::new(0.25, 0.25, 0.25, 1.0),
+            widget_hovered: Color::new(0.35, 0.35, 0.35, 1.0),
+            widget_active: Color::new(0.45, 0.45, 0.45, 1.0),
+            accent: Color::new(0.4, 0.6, 0.9, 1.0),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! The built-in performance HUD, which shows the FPS, a graph of frame times, and the
+//! statistics of video device in the top-left corner of window.
+//!
+//! The HUD is drawn with `SpriteBatch` into a dedicated surface, after all the listeners
+//! attached after `crayon_world::setup` have finished their `on_post_update`. So it's on
+//! top of everything drawn by the application. It's hidden by default unless it's enabled
+//! with `HudParams` in `crayon_world::setup_with`, and could be toggled with
+//! `crayon_world::set_hud_visible` or the hotkey (`F3` by default).
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crayon::application;
+use crayon::errors::*;
+use crayon::input::prelude::Key;
+use crayon::math::prelude::Color;
+use crayon::video;
+
+use sprite::batch::SpriteBatch;
+use text::canvas::Canvas;
+use text::prelude::BitmapFont;
+
+/// The number of frames shown in the graph of frame times.
+pub const HUD_FRAMES: usize = 120;
+
+// The layout of HUD in points.
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+const LINE_HEIGHT: f32 = 12.0;
+const GRAPH_HEIGHT: f32 = 48.0;
+const BAR_WIDTH: f32 = 2.0;
+
+/// The setup parameters of performance HUD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HudParams {
+    /// Shows the HUD since setup.
+    pub visible: bool,
+    /// The key that toggles the HUD, which is disabled with `None`.
+    pub hotkey: Option<Key>,
+}
+
+impl Default for HudParams {
+    fn default() -> Self {
+        HudParams {
+            visible: false,
+            hotkey: Some(Key::F3),
+        }
+    }
+}
+
+pub(crate) struct PerformanceHud {
+    visible: bool,
+    hotkey: Option<Key>,
+    samples: VecDeque<f32>,
+    resources: Option<(SpriteBatch, BitmapFont)>,
+    canvas: Canvas,
+}
+
+impl Drop for PerformanceHud {
+    fn drop(&mut self) {
+        if let Some((_, font)) = self.resources.take() {
+            video::delete_texture(font.texture());
+        }
+    }
+}
+
+impl PerformanceHud {
+    pub fn new(params: HudParams) -> Self {
+        PerformanceHud {
+            visible: params.visible,
+            hotkey: params.hotkey,
+            samples: VecDeque::with_capacity(HUD_FRAMES),
+            resources: None,
+            canvas: Canvas::new(),
+        }
+    }
+
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    #[inline]
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    #[inline]
+    pub fn set_hotkey(&mut self, hotkey: Option<Key>) {
+        self.hotkey = hotkey;
+    }
+
+    /// Records the duration of last frame, and draws the HUD if it's visible.
+    pub fn advance(&mut self) -> Result<()> {
+        if let Some(key) = self.hotkey {
+            if crayon::input::is_key_press(key) {
+                self.visible = !self.visible;
+            }
+        }
+
+        let ms = millis(application::frame_duration());
+        if self.samples.len() >= HUD_FRAMES {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(ms);
+
+        if !self.visible {
+            return Ok(());
+        }
+
+        if self.resources.is_none() {
+            let font = ::text::builtin::font()?;
+            self.resources = Some((SpriteBatch::new()?, font));
+        }
+
+        let (batch, font) = self.resources.as_mut().unwrap();
+        let info = video::frame_info();
+        let lines = [
+            format!("FPS {}  FRAME {:.1} MS", application::fps(), ms),
+            if info.surfaces.is_empty() {
+                "GPU N/A".to_owned()
+            } else {
+                format!("GPU {:.2} MS", millis(info.gpu_time()))
+            },
+            format!("DRAWS {}  TRIS {}", info.drawcall, info.triangles),
+            format!("STATE CHANGES {}", info.state_changes),
+            format!(
+                "MESH {:.1} MB  TEX {:.1} MB",
+                info.mesh_memory as f32 / (1024.0 * 1024.0),
+                info.texture_memory as f32 / (1024.0 * 1024.0)
+            ),
+        ];
+
+        let width = PADDING * 2.0 + HUD_FRAMES as f32 * BAR_WIDTH;
+        let height = PADDING * 3.0 + lines.len() as f32 * LINE_HEIGHT + GRAPH_HEIGHT;
+
+        let canvas = &mut self.canvas;
+        canvas.begin();
+        canvas.rect(
+            [MARGIN, MARGIN],
+            [MARGIN + width, MARGIN + height],
+            Color::new(0.0, 0.0, 0.0, 0.6),
+        );
+
+        for (i, line) in lines.iter().enumerate() {
+            let position = [MARGIN + PADDING, MARGIN + PADDING + i as f32 * LINE_HEIGHT];
+            canvas.text(font, position, LINE_HEIGHT, line, Color::white());
+        }
+
+        // The graph of frame times, whose scale keeps the spikes visible.
+        let bottom = MARGIN + height - PADDING;
+        let limit = self
+            .samples
+            .iter()
+            .fold(1000.0 / 30.0, |acc, &v| v.max(acc));
+        for (i, &v) in self.samples.iter().enumerate() {
+            let color = if v <= 1000.0 / 60.0 + 0.5 {
+                Color::new(0.2, 0.8, 0.2, 1.0)
+            } else if v <= 1000.0 / 30.0 + 0.5 {
+                Color::new(0.9, 0.8, 0.2, 1.0)
+            } else {
+                Color::new(0.9, 0.2, 0.2, 1.0)
+            };
+
+            let x = MARGIN + PADDING + i as f32 * BAR_WIDTH;
+            let y = bottom - (v / limit).min(1.0) * GRAPH_HEIGHT;
+            canvas.rect([x, y], [x + BAR_WIDTH, bottom], color);
+        }
+
+        canvas.draw(batch)
+    }
+}
+
+fn millis(v: Duration) -> f32 {
+    v.as_secs() as f32 * 1000.0 + v.subsec_micros() as f32 / 1000.0
+}
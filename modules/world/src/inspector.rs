@@ -0,0 +1,467 @@
+//! The remote inspector, which exposes the live scene to external tools over a local TCP
+//! connection.
+//!
+//! The requests and responses are JSON objects, one per line. The entities are identified
+//! by the pairs of index and version, and the components are represented with their serde
+//! representations, so the user-defined components should be registered with
+//! `Scene::register_component` to be inspected.
+//!
+//! ```text
+//! > {"cmd": "entities"}
+//! < {"ok": [{"entity": [1, 1], "name": "player", "parent": null}]}
+//! > {"cmd": "inspect", "entity": [1, 1]}
+//! < {"ok": {"entity": [1, 1], "name": "player", "layers": 1, "components": {...}}}
+//! > {"cmd": "edit", "entity": [1, 1], "component": "health", "value": 100}
+//! > {"cmd": "systems"}
+//! > {"cmd": "toggle", "system": "game::Movement", "enabled": false}
+//! ```
+//!
+//! The `Inspector` is polled in the game loop, so the requests are handled on the main
+//! thread between frames without synchronizing the scene.
+//!
+//! ```rust,ignore
+//! let mut inspector = Inspector::new(DEFAULT_INSPECTOR_PORT)?;
+//!
+//! fn on_update(&mut self) -> Result<()> {
+//!     self.inspector.advance(&mut self.scene, Some(&mut self.dispatcher));
+//!     self.scene.dispatch(&mut self.dispatcher);
+//! }
+//! ```
+//!
+//! Notes that the inspector is only listening on the loopback interface, and it's meant
+//! for the development builds with the `inspector` feature.
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+use crayon::errors::Result;
+use crayon::utils::handle::{HandleIndex, HandleLike};
+use serde_json::{self, Value};
+
+use renderable::prelude::Renderer;
+use scene::Scene;
+use spatial::prelude::Transform;
+use utils::prelude::Dispatcher;
+use Entity;
+
+/// The default port that inspector listens on.
+pub const DEFAULT_INSPECTOR_PORT: u16 = 9339;
+
+/// The maximum length of request in bytes, the clients sending longer lines are
+/// disconnected.
+pub const MAX_INSPECTOR_REQUEST: usize = 64 * 1024;
+
+/// The requests that inspector handles.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum InspectorRequest {
+    /// Lists all the entities.
+    Entities,
+    /// Gets the name, layers, transform and registered components of entity.
+    Inspect { entity: (HandleIndex, HandleIndex) },
+    /// Replaces the component of entity with value. Besides the registered components,
+    /// the `name`, `layers` and `transform` of entity could be edited too.
+    Edit {
+        entity: (HandleIndex, HandleIndex),
+        component: String,
+        value: Value,
+    },
+    /// Lists the systems of dispatcher.
+    Systems,
+    /// Enables or disables the systems with name.
+    Toggle { system: String, enabled: bool },
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+enum InspectorResponse {
+    Ok(Value),
+    Err(String),
+}
+
+#[derive(Serialize, Debug)]
+struct EntityInfo {
+    entity: (HandleIndex, HandleIndex),
+    name: String,
+    parent: Option<(HandleIndex, HandleIndex)>,
+}
+
+#[derive(Serialize, Debug)]
+struct EntityDetails {
+    entity: (HandleIndex, HandleIndex),
+    name: String,
+    layers: u32,
+    transform: Transform,
+    components: serde_json::Map<String, Value>,
+}
+
+#[derive(Serialize, Debug)]
+struct SystemInfo {
+    name: String,
+    enabled: bool,
+}
+
+struct InspectorClient {
+    stream: TcpStream,
+    addr: SocketAddr,
+    buf: Vec<u8>,
+    // The responses that have not been written into socket yet.
+    pending: Vec<u8>,
+}
+
+/// The server of remote inspector.
+pub struct Inspector {
+    listener: TcpListener,
+    clients: Vec<InspectorClient>,
+}
+
+impl Inspector {
+    /// Starts listening on the `port` of loopback interface. The port is picked by OS if
+    /// it's 0.
+    pub fn new(port: u16) -> Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        info!("[Inspector] Listening on {}.", listener.local_addr()?);
+
+        Ok(Inspector {
+            listener: listener,
+            clients: Vec::new(),
+        })
+    }
+
+    /// Gets the address that inspector is listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Gets the number of connected clients.
+    #[inline]
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Accepts the new connections, and handles the pending requests with `scene` and the
+    /// optional `dispatcher`. It never blocks on reading or writing, the responses that
+    /// could not be written are buffered and flushed in the following calls.
+    pub fn advance<R: Renderer>(
+        &mut self,
+        scene: &mut Scene<R>,
+        mut dispatcher: Option<&mut Dispatcher>,
+    ) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    if let Err(err) = stream.set_nonblocking(true) {
+                        warn!("[Inspector] Failed to accept {}. {}", addr, err);
+                        continue;
+                    }
+
+                    info!("[Inspector] Accepting connection from {}.", addr);
+                    self.clients.push(InspectorClient {
+                        stream: stream,
+                        addr: addr,
+                        buf: Vec::new(),
+                        pending: Vec::new(),
+                    });
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    warn!("[Inspector] Failed to accept connection. {}", err);
+                    break;
+                }
+            }
+        }
+
+        let mut i = 0;
+        while i < self.clients.len() {
+            let rsp = Self::serve(&mut self.clients[i], scene, &mut dispatcher);
+            if let Err(err) = rsp {
+                let client = self.clients.swap_remove(i);
+                info!(
+                    "[Inspector] Connection with {} closed. {}",
+                    client.addr, err
+                );
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Handles the request with `scene` and the optional `dispatcher`.
+    pub fn execute<R: Renderer>(
+        scene: &mut Scene<R>,
+        dispatcher: Option<&mut Dispatcher>,
+        request: &InspectorRequest,
+    ) -> Result<Value> {
+        let v = match *request {
+            InspectorRequest::Entities => {
+                let mut entities = Vec::with_capacity(scene.len());
+                for ent in scene.entities() {
+                    entities.push(EntityInfo {
+                        entity: (ent.index(), ent.version()),
+                        name: scene.name(ent).unwrap_or("").to_owned(),
+                        parent: scene.parent(ent).map(|v| (v.index(), v.version())),
+                    });
+                }
+
+                serde_json::to_value(entities)?
+            }
+            InspectorRequest::Inspect { entity } => {
+                let ent = Self::entity(scene, entity)?;
+
+                let mut components = serde_json::Map::new();
+                for (name, json) in scene.serialize_components(ent)? {
+                    components.insert(name, serde_json::from_str(&json)?);
+                }
+
+                serde_json::to_value(EntityDetails {
+                    entity: entity,
+                    name: scene.name(ent).unwrap_or("").to_owned(),
+                    layers: scene.layers(ent),
+                    transform: scene.local_transform(ent).unwrap(),
+                    components: components,
+                })?
+            }
+            InspectorRequest::Edit {
+                entity,
+                ref component,
+                ref value,
+            } => {
+                let ent = Self::entity(scene, entity)?;
+                match component.as_str() {
+                    "name" => {
+                        let name: String = serde_json::from_value(value.clone())?;
+                        scene.set_name(ent, name);
+                    }
+                    "layers" => scene.set_layers(ent, serde_json::from_value(value.clone())?),
+                    "transform" => {
+                        scene.set_local_transform(ent, serde_json::from_value(value.clone())?)
+                    }
+                    _ => scene.deserialize_component(ent, component, &value.to_string())?,
+                }
+
+                Value::Null
+            }
+            InspectorRequest::Systems => {
+                let dispatcher = dispatcher.ok_or_else(|| format_err!("No dispatcher."))?;
+                let systems: Vec<_> = dispatcher
+                    .systems()
+                    .map(|(name, enabled)| SystemInfo {
+                        name: name.to_owned(),
+                        enabled: enabled,
+                    })
+                    .collect();
+
+                serde_json::to_value(systems)?
+            }
+            InspectorRequest::Toggle {
+                ref system,
+                enabled,
+            } => {
+                let dispatcher = dispatcher.ok_or_else(|| format_err!("No dispatcher."))?;
+                if !dispatcher.set_enabled(system, enabled) {
+                    bail!("System {} is not found.", system);
+                }
+
+                Value::Null
+            }
+        };
+
+        Ok(v)
+    }
+
+    fn entity<R: Renderer>(scene: &Scene<R>, v: (HandleIndex, HandleIndex)) -> Result<Entity> {
+        let ent = Entity::new(v.0, v.1);
+        if !scene.contains(ent) {
+            bail!("{:?} does not exist.", ent);
+        }
+
+        Ok(ent)
+    }
+
+    fn serve<R: Renderer>(
+        client: &mut InspectorClient,
+        scene: &mut Scene<R>,
+        dispatcher: &mut Option<&mut Dispatcher>,
+    ) -> Result<()> {
+        // The requests of client are not read until its responses have been written, so
+        // a client that never reads could not grow the buffers unboundedly. And at most
+        // `MAX_INSPECTOR_REQUEST` bytes are read in one call to keep the frame responsive.
+        let mut bytes = [0; 1024];
+        let mut len = 0;
+        while len <= MAX_INSPECTOR_REQUEST {
+            while !client.pending.is_empty() {
+                match client.stream.write(&client.pending) {
+                    Ok(0) => bail!("Disconnected."),
+                    Ok(n) => {
+                        client.pending.drain(..n);
+                    }
+                    Err(ref err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+
+            match client.stream.read(&mut bytes) {
+                Ok(0) => bail!("Disconnected."),
+                Ok(n) => {
+                    client.buf.extend_from_slice(&bytes[..n]);
+                    len += n;
+                }
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => return Err(err.into()),
+            }
+
+            while let Some(n) = client.buf.iter().position(|&v| v == b'\n') {
+                let line: Vec<_> = client.buf.drain(..n + 1).collect();
+
+                let rsp = serde_json::from_slice(&line)
+                    .map_err(|err| err.into())
+                    .and_then(|request| {
+                        let dispatcher = dispatcher.as_mut().map(|v| &mut **v);
+                        Self::execute(scene, dispatcher, &request)
+                    });
+
+                let rsp = match rsp {
+                    Ok(v) => InspectorResponse::Ok(v),
+                    Err(err) => InspectorResponse::Err(format!("{}", err)),
+                };
+
+                serde_json::to_writer(&mut client.pending, &rsp)?;
+                client.pending.push(b'\n');
+            }
+
+            if client.buf.len() > MAX_INSPECTOR_REQUEST {
+                bail!("Request exceeds {} bytes.", MAX_INSPECTOR_REQUEST);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use renderable::headless::HeadlessRenderer;
+    use std::io::{BufRead, BufReader};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Health(u32);
+
+    #[test]
+    fn inspect() {
+        let mut scene = Scene::new(HeadlessRenderer::new());
+        scene.register_component::<Health>("health").unwrap();
+
+        let e1 = scene.create("player");
+        scene.add_component(e1, Health(100));
+
+        let mut inspector = Inspector::new(0).unwrap();
+        let mut stream = TcpStream::connect(inspector.local_addr().unwrap()).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut request = |inspector: &mut Inspector, scene: &mut Scene<_>, line: &str| {
+            stream.write_all(line.as_bytes()).unwrap();
+            stream.write_all(b"\n").unwrap();
+
+            let mut rsp = String::new();
+            while rsp.is_empty() {
+                inspector.advance(scene, None);
+                reader.get_mut().set_nonblocking(true).unwrap();
+                if let Err(err) = reader.read_line(&mut rsp) {
+                    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+                }
+            }
+
+            serde_json::from_str::<Value>(&rsp).unwrap()
+        };
+
+        let entity = format!("[{}, {}]", e1.index(), e1.version());
+        let v = request(&mut inspector, &mut scene, r#"{"cmd": "entities"}"#);
+        assert_eq!(v["ok"][0]["name"], "player");
+        assert_eq!(inspector.clients(), 1);
+
+        let line = format!(
+            r#"{{"cmd": "edit", "entity": {}, "component": "health", "value": 50}}"#,
+            entity
+        );
+        let v = request(&mut inspector, &mut scene, &line);
+        assert!(v["err"].is_null());
+        assert_eq!(scene.component::<Health>(e1), Some(&Health(50)));
+
+        let line = format!(r#"{{"cmd": "inspect", "entity": {}}}"#, entity);
+        let v = request(&mut inspector, &mut scene, &line);
+        assert_eq!(v["ok"]["components"]["health"], 50);
+
+        let v = request(&mut inspector, &mut scene, r#"{"cmd": "systems"}"#);
+        assert!(v["err"].is_string());
+
+        let v = request(&mut inspector, &mut scene, "blahblah");
+        assert!(v["err"].is_string());
+    }
+
+    #[test]
+    fn oversized() {
+        let mut scene = Scene::new(HeadlessRenderer::new());
+        let mut inspector = Inspector::new(0).unwrap();
+        let mut stream = TcpStream::connect(inspector.local_addr().unwrap()).unwrap();
+        stream.set_nonblocking(true).unwrap();
+
+        // The clients sending lines longer than the limit are disconnected.
+        let bytes = [b' '; 1024];
+        let mut accepted = false;
+        for _ in 0..(MAX_INSPECTOR_REQUEST / 1024) * 16 {
+            let _ = stream.write(&bytes);
+            inspector.advance(&mut scene, None);
+
+            if inspector.clients() > 0 {
+                accepted = true;
+            } else if accepted {
+                break;
+            }
+        }
+
+        assert!(accepted);
+        assert_eq!(inspector.clients(), 0);
+    }
+
+    #[test]
+    fn systems() {
+        use utils::prelude::{ComponentView, System, SystemAccess};
+
+        struct Movement;
+
+        impl System for Movement {
+            fn access(&self) -> SystemAccess {
+                SystemAccess::new().write::<u32>()
+            }
+
+            fn run(&mut self, _: &ComponentView) {}
+        }
+
+        let mut scene = Scene::new(HeadlessRenderer::new());
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add(Movement);
+
+        let name = ::std::any::type_name::<Movement>().to_owned();
+        let request = InspectorRequest::Toggle {
+            system: name.clone(),
+            enabled: false,
+        };
+
+        Inspector::execute(&mut scene, Some(&mut dispatcher), &request).unwrap();
+        let v = Inspector::execute(
+            &mut scene,
+            Some(&mut dispatcher),
+            &InspectorRequest::Systems,
+        );
+        assert_eq!(v.unwrap()[0]["enabled"], false);
+
+        let request = InspectorRequest::Toggle {
+            system: "Gravity".into(),
+            enabled: false,
+        };
+
+        assert!(Inspector::execute(&mut scene, Some(&mut dispatcher), &request).is_err());
+    }
+}
@@ -6,35 +6,71 @@ extern crate failure;
 extern crate serde;
 
 extern crate inlinable_string;
+extern crate serde_json;
 
+pub mod animation;
 pub mod assets;
+pub mod console;
+pub mod gui;
+pub mod hud;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+pub mod raycast;
 pub mod renderable;
 pub mod scene;
+pub mod serialization;
 pub mod spatial;
+pub mod sprite;
 pub mod tags;
+pub mod text;
+pub mod ui;
 pub mod utils;
 
 pub mod prelude {
+    pub use super::animation::prelude::*;
     pub use super::assets::prelude::*;
+    pub use super::gui::prelude::*;
+    pub use super::raycast::{MeshCollider, RayHit};
     pub use super::renderable::prelude::*;
     pub use super::scene::Scene;
+    pub use super::serialization::SceneData;
     pub use super::spatial::prelude::*;
+    pub use super::sprite::prelude::*;
+    pub use super::text::prelude::*;
+    pub use super::ui::prelude::*;
+    pub use super::utils::component::{Added, ChangeTicks, Changed, Filter};
+    pub use super::utils::dispatcher::{ComponentView, Dispatcher, System, SystemAccess};
+    pub use super::utils::storage::{ComponentStorage, StorageKind};
     pub use super::Entity;
 }
 
 mod system;
 
-pub use self::inside::{discard, setup};
+pub use self::inside::{discard, setup, setup_with};
 pub use self::system::WorldDefaultResources;
 
+use crayon::input::prelude::Key;
 use crayon::res::utils::prelude::ResourceState;
+use crayon::uuid::Uuid;
 use std::sync::Arc;
 
-use self::assets::prelude::{Prefab, PrefabHandle};
+use self::assets::prelude::{AnimationClip, AnimationClipHandle, Prefab, PrefabHandle};
+use self::assets::prelude::{AnimationController, AnimationControllerHandle};
+use self::assets::prelude::{LightmapAtlas, LightmapAtlasHandle};
+use self::assets::prelude::{Material, MaterialHandle, Skeleton, SkeletonHandle};
+use self::hud::HudParams;
 use self::inside::ctx;
+use self::sprite::prelude::{TextureAtlas, TextureAtlasHandle};
 
 pub type Result<T> = ::std::result::Result<T, failure::Error>;
 
+/// The setup parameters of world system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WorldParams {
+    /// The setup parameters of performance HUD.
+    pub hud: HudParams,
+}
+
 impl_handle!(Entity);
 
 /// Creates a prefab object.
@@ -75,6 +111,259 @@ pub fn delete_prefab(handle: PrefabHandle) {
     ctx().delete_prefab(handle);
 }
 
+/// Creates a skeleton object.
+///
+/// A skeleton asset describes the bone hierarchy which could be used to deform
+/// skinned meshes.
+#[inline]
+pub fn create_skeleton(skeleton: Skeleton) -> Result<SkeletonHandle> {
+    ctx().create_skeleton(skeleton)
+}
+
+/// Create a skeleton object from file asynchronously.
+#[inline]
+pub fn create_skeleton_from<T: AsRef<str>>(url: T) -> Result<SkeletonHandle> {
+    ctx().create_skeleton_from(url)
+}
+
+/// Return the skeleton obejct if exists.
+#[inline]
+pub fn skeleton(handle: SkeletonHandle) -> Option<Arc<Skeleton>> {
+    ctx().skeleton(handle)
+}
+
+/// Query the resource state of specified skeleton.
+#[inline]
+pub fn skeleton_state(handle: SkeletonHandle) -> ResourceState {
+    ctx().skeleton_state(handle)
+}
+
+/// Delete a skeleton object from this world.
+#[inline]
+pub fn delete_skeleton(handle: SkeletonHandle) {
+    ctx().delete_skeleton(handle);
+}
+
+/// Creates a animation clip object.
+///
+/// A animation clip stores the keyframes of bones, which could be played by
+/// `Animator`s with compatible skeletons.
+#[inline]
+pub fn create_animation_clip(clip: AnimationClip) -> Result<AnimationClipHandle> {
+    ctx().create_animation_clip(clip)
+}
+
+/// Create a animation clip object from file asynchronously.
+#[inline]
+pub fn create_animation_clip_from<T: AsRef<str>>(url: T) -> Result<AnimationClipHandle> {
+    ctx().create_animation_clip_from(url)
+}
+
+/// Create a animation clip object from file asynchronously.
+#[inline]
+pub fn create_animation_clip_from_uuid(uuid: Uuid) -> Result<AnimationClipHandle> {
+    ctx().create_animation_clip_from_uuid(uuid)
+}
+
+/// Return the animation clip obejct if exists.
+#[inline]
+pub fn animation_clip(handle: AnimationClipHandle) -> Option<Arc<AnimationClip>> {
+    ctx().animation_clip(handle)
+}
+
+/// Query the resource state of specified animation clip.
+#[inline]
+pub fn animation_clip_state(handle: AnimationClipHandle) -> ResourceState {
+    ctx().animation_clip_state(handle)
+}
+
+/// Delete a animation clip object from this world.
+#[inline]
+pub fn delete_animation_clip(handle: AnimationClipHandle) {
+    ctx().delete_animation_clip(handle);
+}
+
+/// Creates a animation controller object.
+///
+/// A animation controller is a state machine of animations, which drives `Animator`s
+/// with the parameters set from game code. The ownership of clips is transfered to the
+/// controller.
+#[inline]
+pub fn create_animation_controller(
+    controller: AnimationController,
+) -> Result<AnimationControllerHandle> {
+    ctx().create_animation_controller(controller)
+}
+
+/// Create a animation controller object from file asynchronously.
+#[inline]
+pub fn create_animation_controller_from<T: AsRef<str>>(
+    url: T,
+) -> Result<AnimationControllerHandle> {
+    ctx().create_animation_controller_from(url)
+}
+
+/// Return the animation controller obejct if exists.
+#[inline]
+pub fn animation_controller(handle: AnimationControllerHandle) -> Option<Arc<AnimationController>> {
+    ctx().animation_controller(handle)
+}
+
+/// Query the resource state of specified animation controller.
+#[inline]
+pub fn animation_controller_state(handle: AnimationControllerHandle) -> ResourceState {
+    ctx().animation_controller_state(handle)
+}
+
+/// Delete a animation controller object from this world.
+#[inline]
+pub fn delete_animation_controller(handle: AnimationControllerHandle) {
+    ctx().delete_animation_controller(handle);
+}
+
+/// Creates a material object.
+///
+/// A material binds a named shader with a table of typed parameters, which could be
+/// drawn by `MaterialRenderer`. The ownership of textures is transfered to the material.
+#[inline]
+pub fn create_material(material: Material) -> Result<MaterialHandle> {
+    ctx().create_material(material)
+}
+
+/// Create a material object from file asynchronously.
+#[inline]
+pub fn create_material_from<T: AsRef<str>>(url: T) -> Result<MaterialHandle> {
+    ctx().create_material_from(url)
+}
+
+/// Create a material object from file asynchronously.
+#[inline]
+pub fn create_material_from_uuid(uuid: Uuid) -> Result<MaterialHandle> {
+    ctx().create_material_from_uuid(uuid)
+}
+
+/// Gets the uuid of material, if it is created from file.
+#[inline]
+pub fn material_uuid(handle: MaterialHandle) -> Option<Uuid> {
+    ctx().material_uuid(handle)
+}
+
+/// Return the material obejct if exists.
+#[inline]
+pub fn material(handle: MaterialHandle) -> Option<Arc<Material>> {
+    ctx().material(handle)
+}
+
+/// Query the resource state of specified material.
+#[inline]
+pub fn material_state(handle: MaterialHandle) -> ResourceState {
+    ctx().material_state(handle)
+}
+
+/// Delete a material object from this world.
+#[inline]
+pub fn delete_material(handle: MaterialHandle) {
+    ctx().delete_material(handle);
+}
+
+/// Creates a lightmap atlas object.
+///
+/// A lightmap atlas holds the pre-baked lightmaps of scene, which could be applied with
+/// `Scene::set_lightmaps`. The ownership of textures is transfered to the atlas.
+#[inline]
+pub fn create_lightmap_atlas(atlas: LightmapAtlas) -> Result<LightmapAtlasHandle> {
+    ctx().create_lightmap_atlas(atlas)
+}
+
+/// Create a lightmap atlas object from file asynchronously.
+#[inline]
+pub fn create_lightmap_atlas_from<T: AsRef<str>>(url: T) -> Result<LightmapAtlasHandle> {
+    ctx().create_lightmap_atlas_from(url)
+}
+
+/// Create a lightmap atlas object from file asynchronously.
+#[inline]
+pub fn create_lightmap_atlas_from_uuid(uuid: Uuid) -> Result<LightmapAtlasHandle> {
+    ctx().create_lightmap_atlas_from_uuid(uuid)
+}
+
+/// Return the lightmap atlas obejct if exists.
+#[inline]
+pub fn lightmap_atlas(handle: LightmapAtlasHandle) -> Option<Arc<LightmapAtlas>> {
+    ctx().lightmap_atlas(handle)
+}
+
+/// Query the resource state of specified lightmap atlas.
+#[inline]
+pub fn lightmap_atlas_state(handle: LightmapAtlasHandle) -> ResourceState {
+    ctx().lightmap_atlas_state(handle)
+}
+
+/// Delete a lightmap atlas object from this world.
+#[inline]
+pub fn delete_lightmap_atlas(handle: LightmapAtlasHandle) {
+    ctx().delete_lightmap_atlas(handle);
+}
+
+/// Creates a texture atlas object.
+///
+/// A texture atlas packs the frames of sprites and UI images into one texture, so they
+/// could be drawn in a single batch. The ownership of texture is transfered to the atlas.
+#[inline]
+pub fn create_texture_atlas(atlas: TextureAtlas) -> Result<TextureAtlasHandle> {
+    ctx().create_texture_atlas(atlas)
+}
+
+/// Create a texture atlas object from file asynchronously.
+#[inline]
+pub fn create_texture_atlas_from<T: AsRef<str>>(url: T) -> Result<TextureAtlasHandle> {
+    ctx().create_texture_atlas_from(url)
+}
+
+/// Create a texture atlas object from file asynchronously.
+#[inline]
+pub fn create_texture_atlas_from_uuid(uuid: Uuid) -> Result<TextureAtlasHandle> {
+    ctx().create_texture_atlas_from_uuid(uuid)
+}
+
+/// Return the texture atlas obejct if exists.
+#[inline]
+pub fn texture_atlas(handle: TextureAtlasHandle) -> Option<Arc<TextureAtlas>> {
+    ctx().texture_atlas(handle)
+}
+
+/// Query the resource state of specified texture atlas.
+#[inline]
+pub fn texture_atlas_state(handle: TextureAtlasHandle) -> ResourceState {
+    ctx().texture_atlas_state(handle)
+}
+
+/// Delete a texture atlas object from this world.
+#[inline]
+pub fn delete_texture_atlas(handle: TextureAtlasHandle) {
+    ctx().delete_texture_atlas(handle);
+}
+
+/// Sets whether the performance HUD is shown, which displays the FPS, a graph of frame
+/// times and the statistics of video device on top of everything.
+#[inline]
+pub fn set_hud_visible(visible: bool) {
+    ctx().set_hud_visible(visible);
+}
+
+/// Checks if the performance HUD is shown.
+#[inline]
+pub fn hud_visible() -> bool {
+    ctx().hud_visible()
+}
+
+/// Sets the key that toggles the performance HUD, defaults to `Key::F3`. The hotkey is
+/// disabled with `None`.
+#[inline]
+pub fn set_hud_hotkey(hotkey: Option<Key>) {
+    ctx().set_hud_hotkey(hotkey);
+}
+
 /// Return the default resources in this world.
 #[inline]
 pub fn default() -> WorldDefaultResources {
@@ -83,6 +372,7 @@ pub fn default() -> WorldDefaultResources {
 
 mod inside {
     use super::system::WorldSystem;
+    use super::WorldParams;
 
     static mut CTX: *const WorldSystem = std::ptr::null();
 
@@ -98,13 +388,20 @@ mod inside {
         }
     }
 
-    /// Setup the world system.
+    /// Setup the world system with default parameters.
     pub fn setup() -> Result<(), failure::Error> {
+        setup_with(WorldParams::default())
+    }
+
+    /// Setup the world system.
+    pub fn setup_with(params: WorldParams) -> Result<(), failure::Error> {
         unsafe {
             debug_assert!(CTX.is_null(), "duplicated setup of world system.");
 
-            let ctx = WorldSystem::new()?;
+            let ctx = WorldSystem::new(params)?;
             CTX = Box::into_raw(Box::new(ctx));
+
+            info!("Setup world system.");
             Ok(())
         }
     }
@@ -0,0 +1,277 @@
+//! Raycasting against the meshes in scene, e.g. picking entities with mouse.
+
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crayon::errors::Result;
+use crayon::math::prelude::*;
+use crayon::video::prelude::*;
+
+use renderable::prelude::MeshRenderer;
+use spatial::prelude::{Bvh, SceneGraph, Transform};
+use utils::prelude::Component;
+use Entity;
+
+/// The intersection of ray and entity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// The entity that was hit.
+    pub ent: Entity,
+    /// The distance from the origin of ray to the hit point.
+    pub distance: f32,
+    /// The hit point in world space.
+    pub position: Vector3<f32>,
+}
+
+/// The triangles of mesh that are tested against rays exactly, instead of the bounding
+/// box of `MeshRenderer`.
+///
+/// Meshes are uploaded to GPU without keeping their vertices, so the collider should be
+/// created from the `MeshData` before that. It is cheap to clone, the triangles are
+/// shared between clones.
+#[derive(Debug, Clone)]
+pub struct MeshCollider {
+    triangles: Arc<Vec<[Vector3<f32>; 3]>>,
+    aabb: Aabb3<f32>,
+}
+
+impl MeshCollider {
+    /// Creates a new collider from triangles in local space of entity.
+    pub fn new(triangles: Vec<[Vector3<f32>; 3]>) -> Self {
+        let mut aabb = None;
+        for v in triangles.iter().flat_map(|v| v.iter()) {
+            let p = EuclideanSpace::from_vec(*v);
+            aabb = Some(
+                aabb.map(|aabb: Aabb3<f32>| aabb.grow(p))
+                    .unwrap_or_else(|| Aabb3::new(p, p)),
+            );
+        }
+
+        MeshCollider {
+            triangles: Arc::new(triangles),
+            aabb: aabb.unwrap_or_else(Aabb3::zero),
+        }
+    }
+
+    /// Creates a new collider from the float positions of mesh, whose primitives must be
+    /// triangles or triangle strips.
+    pub fn from_mesh(params: &MeshParams, data: &MeshData) -> Result<Self> {
+        params.validate(Some(data))?;
+
+        let element = params
+            .layout
+            .element(Attribute::Position)
+            .filter(|v| v.format == VertexFormat::Float && v.size >= 3)
+            .ok_or_else(|| format_err!("The mesh does not have float positions."))?;
+
+        let offset = params.layout.offset(element.name).unwrap() as usize;
+        let stride = params.layout.stride() as usize;
+        let position = |i: usize| -> Result<Vector3<f32>> {
+            let bytes = data.vptr.get(i * stride + offset..i * stride + offset + 12);
+            let bytes = bytes.ok_or_else(|| format_err!("Vertex {} is out of bounds.", i))?;
+            Ok(Vector3::new(
+                read_f32(bytes, 0),
+                read_f32(bytes, 4),
+                read_f32(bytes, 8),
+            ))
+        };
+
+        let indices: Vec<usize> = match params.index_format {
+            IndexFormat::U16 => data
+                .iptr
+                .chunks(2)
+                .take(params.num_idxes)
+                .map(|v| read_u16(v, 0) as usize)
+                .collect(),
+            IndexFormat::U32 => data
+                .iptr
+                .chunks(4)
+                .take(params.num_idxes)
+                .map(|v| read_u32(v, 0) as usize)
+                .collect(),
+        };
+
+        let mut triangles = Vec::new();
+        match params.primitive {
+            MeshPrimitive::Triangles => {
+                for v in indices.chunks(3).filter(|v| v.len() == 3) {
+                    triangles.push([position(v[0])?, position(v[1])?, position(v[2])?]);
+                }
+            }
+            MeshPrimitive::TriangleStrip => {
+                for v in indices.windows(3) {
+                    triangles.push([position(v[0])?, position(v[1])?, position(v[2])?]);
+                }
+            }
+            v => bail!("The primitive {:?} of mesh is not triangles.", v),
+        }
+
+        Ok(MeshCollider::new(triangles))
+    }
+
+    /// Gets the triangles in local space of entity.
+    #[inline]
+    pub fn triangles(&self) -> &[[Vector3<f32>; 3]] {
+        &self.triangles
+    }
+
+    /// Tests the intersection with ray in local space, returns the distance to the
+    /// nearest triangle.
+    pub fn intersect(&self, ray: &Ray<f32>) -> Option<f32> {
+        ray.intersect_aabb(&self.aabb)?;
+
+        self.triangles
+            .iter()
+            .filter_map(|v| ray.intersect_triangle(v[0], v[1], v[2]))
+            .fold(None, |acc: Option<f32>, v| {
+                Some(acc.map(|acc| acc.min(v)).unwrap_or(v))
+            })
+    }
+}
+
+/// Tests the ray against the visible meshes and colliders, returns the hits sorted from
+/// near to far.
+///
+/// The meshes whose bounding boxes in `bvhs` are up to date are found with them, and the
+/// ones that have moved or changed since the hierarchies were updated are tested one by one.
+pub(crate) fn raycast(
+    ray: Ray<f32>,
+    sg: &SceneGraph,
+    meshes: &Component<MeshRenderer>,
+    colliders: &Component<MeshCollider>,
+    bvhs: &[Bvh<Entity>],
+) -> Vec<RayHit> {
+    let mut hits = Vec::new();
+
+    for (i, v) in colliders.data.iter().enumerate() {
+        let ent = colliders.entities[i];
+        if meshes.get(ent).map(|v| !v.visible).unwrap_or(false) {
+            continue;
+        }
+
+        if let Some(local) = sg.transform(ent).and_then(|v| to_local(&ray, v)) {
+            if let Some(distance) = v.intersect(&local) {
+                hits.push(RayHit {
+                    ent: ent,
+                    distance: distance,
+                    position: ray.at(distance),
+                });
+            }
+        }
+    }
+
+    let fresh = |ent: Entity, v: &MeshRenderer| {
+        bvhs.iter().any(|v| v.contains(ent))
+            && !sg.is_dirty(ent)
+            && v.bounds
+                .map(|(handle, _)| handle == v.mesh)
+                .unwrap_or(false)
+    };
+
+    let mut candidates = Vec::new();
+    for v in bvhs {
+        v.raycast(&ray, &mut candidates);
+    }
+
+    candidates.retain(|&ent| meshes.get(ent).map(|v| fresh(ent, v)).unwrap_or(false));
+
+    for (i, v) in meshes.data.iter().enumerate() {
+        if !fresh(meshes.entities[i], v) {
+            candidates.push(meshes.entities[i]);
+        }
+    }
+
+    for ent in candidates {
+        let v = meshes.get(ent).unwrap();
+        if !v.visible || colliders.has(ent) {
+            continue;
+        }
+
+        let aabb = match v.bounds {
+            Some((handle, aabb)) if handle == v.mesh => Some(aabb),
+            _ => crayon::video::mesh(v.mesh).map(|params| params.aabb),
+        };
+
+        // Meshes without valid bounding boxes are never hit.
+        let aabb = match aabb {
+            Some(aabb) if aabb.min != aabb.max => aabb,
+            _ => continue,
+        };
+
+        if let Some(local) = sg.transform(ent).and_then(|v| to_local(&ray, v)) {
+            if let Some(distance) = local.intersect_aabb(&aabb) {
+                hits.push(RayHit {
+                    ent: ent,
+                    distance: distance,
+                    position: ray.at(distance),
+                });
+            }
+        }
+    }
+
+    // The hits with invalid distances, e.g. casted by degenerated rays, are dropped.
+    hits.retain(|v| v.distance.is_finite());
+    hits.sort_by(|lhs, rhs| {
+        lhs.distance
+            .partial_cmp(&rhs.distance)
+            .unwrap_or(Ordering::Equal)
+    });
+    hits
+}
+
+// Transforms the ray into the local space of transform. The direction is not normalized,
+// so the distances in local space are the same as the ones in world space.
+fn to_local(ray: &Ray<f32>, transform: Transform) -> Option<Ray<f32>> {
+    transform.inverse().map(|v| Ray {
+        origin: v.transform_point(ray.origin),
+        direction: v.transform_vector(ray.direction),
+    })
+}
+
+#[inline]
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from(bytes[offset]) | (u16::from(bytes[offset + 1]) << 8)
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from(read_u16(bytes, offset)) | (u32::from(read_u16(bytes, offset + 2)) << 16)
+}
+
+#[inline]
+fn read_f32(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_bits(read_u32(bytes, offset))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn collider() {
+        let collider = MeshCollider::new(vec![[
+            Vector3::new(-1.0, -1.0, 1.0),
+            Vector3::new(1.0, -1.0, 1.0),
+            Vector3::new(0.0, 1.0, 1.0),
+        ]]);
+
+        let ray = Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        assert_ulps_eq!(collider.intersect(&ray).unwrap(), 2.0);
+
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 2.0), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(collider.intersect(&ray), None);
+
+        let ray = Ray::new(Vector3::new(0.9, 0.9, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(collider.intersect(&ray), None);
+
+        let transform = Transform {
+            position: Vector3::new(0.0, 0.0, 2.0),
+            scale: 2.0,
+            ..Default::default()
+        };
+
+        let ray = Ray::new(Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        let local = to_local(&ray, transform).unwrap();
+        assert_ulps_eq!(collider.intersect(&local).unwrap(), 5.0);
+    }
+}
@@ -2,17 +2,33 @@
 
 use crayon::math::prelude::*;
 use crayon::video::assets::surface::SurfaceHandle;
+use crayon::video::assets::texture::TextureHandle;
 
 use spatial::prelude::Transform;
 
+use super::fog::Fog;
+use super::pbr::PbrEnvironment;
+
 /// A `Camera` is a device through which the player views the world.
 #[derive(Debug, Clone, Copy)]
 pub struct Camera {
     frustum: Frustum<f32>,
     surface: Option<SurfaceHandle>,
+    culling_mask: u32,
+    viewport: Option<Aabb2<f32>>,
+    order: i32,
+    projection_matrix: Option<Matrix4<f32>>,
+    oblique_plane: Option<Plane<f32>>,
+    exposure: f32,
+    color_controls: ColorControls,
 
     #[doc(hidden)]
     pub(crate) transform: Transform,
+    #[doc(hidden)]
+    pub(crate) viewport_surface: Option<SurfaceHandle>,
+    #[doc(hidden)]
+    pub(crate) environment: Option<PbrEnvironment>,
+    pub(crate) fog: Option<Fog>,
 }
 
 impl Default for Camera {
@@ -34,7 +50,17 @@ impl Camera {
         Camera {
             frustum: Frustum::new(projection),
             surface: None,
+            culling_mask: !0,
+            viewport: None,
+            order: 0,
+            projection_matrix: None,
+            oblique_plane: None,
+            exposure: 1.0,
+            color_controls: ColorControls::default(),
             transform: Transform::default(),
+            viewport_surface: None,
+            environment: None,
+            fog: None,
         }
     }
 
@@ -74,9 +100,65 @@ impl Camera {
         self.surface = surface.into();
     }
 
-    /// Gets the handle of surface.
+    /// Gets the handle of surface, which is the one created for the viewport of camera if
+    /// no surface is assigned.
     pub fn surface(&self) -> Option<SurfaceHandle> {
-        self.surface
+        self.surface.or(self.viewport_surface)
+    }
+
+    /// Sets the viewport rectangle in normalized coordinates of window, which ranges from
+    /// (0, 0) at the bottom-left corner to (1, 1) at the top-right corner, e.g. the halves
+    /// of window in split-screen games. The area inside viewport is cleared before drawing.
+    ///
+    /// The aspect of projection should be updated to match the viewport. The viewport is
+    /// ignored if a surface is assigned with `set_surface`, in which case the viewport of
+    /// surface is used.
+    #[inline]
+    pub fn set_viewport<T>(&mut self, viewport: T)
+    where
+        T: Into<Option<Aabb2<f32>>>,
+    {
+        self.viewport = viewport.into();
+    }
+
+    /// Gets the viewport rectangle in normalized coordinates of window.
+    #[inline]
+    pub fn viewport(&self) -> Option<Aabb2<f32>> {
+        self.viewport
+    }
+
+    /// Sets the order of camera, the cameras with lower orders are drawn first. Cameras
+    /// with the same order are drawn in the order they are added, e.g. the render textures
+    /// of a mirror should be drawn before the main camera that samples them.
+    #[inline]
+    pub fn set_order(&mut self, order: i32) {
+        self.order = order;
+    }
+
+    /// Gets the order of camera.
+    #[inline]
+    pub fn order(&self) -> i32 {
+        self.order
+    }
+
+    /// Sets the mask of layers that are visible to this camera, e.g. `TERRAIN | UNITS`
+    /// for a minimap camera. The entities are drawn if any of their layers are in the
+    /// mask. All the layers are visible by default.
+    #[inline]
+    pub fn set_culling_mask(&mut self, mask: u32) {
+        self.culling_mask = mask;
+    }
+
+    /// Gets the mask of layers that are visible to this camera.
+    #[inline]
+    pub fn culling_mask(&self) -> u32 {
+        self.culling_mask
+    }
+
+    /// Checks if the entities in `layers` are visible to this camera.
+    #[inline]
+    pub fn is_visible(&self, layers: u32) -> bool {
+        self.culling_mask & layers != 0
     }
 
     /// Sets the near/far clipping plane distances.
@@ -118,13 +200,62 @@ impl Camera {
         }
     }
 
+    /// Sets the aspect of width / height, which should match the aspect of viewport. The
+    /// height of orthographic window is kept.
+    pub fn set_aspect(&mut self, aspect: f32) {
+        let projection = match self.frustum.projection() {
+            Projection::Ortho {
+                height, near, far, ..
+            } => Projection::Ortho {
+                width: height * aspect,
+                height,
+                near,
+                far,
+            },
+            Projection::Perspective {
+                fovy, near, far, ..
+            } => Projection::Perspective {
+                fovy,
+                aspect,
+                near,
+                far,
+            },
+        };
+
+        self.set_projection(projection);
+    }
+
+    /// Gets the aspect of width / height.
+    #[inline]
+    pub fn aspect(&self) -> f32 {
+        match self.frustum.projection() {
+            Projection::Ortho { width, height, .. } => width / height,
+            Projection::Perspective { aspect, .. } => aspect,
+        }
+    }
+
+    /// Sets the height of orthographic window in world units, while the aspect and clip
+    /// planes are kept. A perspective camera is switched to orthographic projection.
+    pub fn set_ortho_size(&mut self, size: f32) {
+        let aspect = self.aspect();
+        let projection = Projection::Ortho {
+            width: size * aspect,
+            height: size,
+            near: self.near_clip_plane(),
+            far: self.far_clip_plane(),
+        };
+
+        self.set_projection(projection);
+    }
+
     /// Gets the projection type and its payload.
     #[inline]
     pub fn projection(&self) -> Projection<f32> {
         self.frustum.projection()
     }
 
-    /// Gets the underlying frustum.
+    /// Gets the underlying frustum, which is extracted from the custom projection matrix
+    /// if there is one.
     pub fn frustum(&self) -> Frustum<f32> {
         self.frustum
     }
@@ -132,6 +263,333 @@ impl Camera {
     /// Sets the projection type.
     #[inline]
     pub fn set_projection(&mut self, projection: Projection<f32>) {
-        self.frustum = Frustum::new(projection);
+        projection.validate();
+        self.frustum = match self.projection_matrix {
+            Some(m) if m.invert().is_some() => Frustum::with_matrix(projection, m),
+            _ => Frustum::new(projection),
+        };
+    }
+
+    /// Overrides the projection matrix with a custom one in left hand coordinates, e.g.
+    /// for off-axis projections. The `projection` is still used to fit the shadows and
+    /// clip planes. Sets it to `None` to go back to the matrix of `projection`, which is
+    /// also used if the custom one is not invertible.
+    pub fn set_projection_matrix<T>(&mut self, matrix: T)
+    where
+        T: Into<Option<Matrix4<f32>>>,
+    {
+        self.projection_matrix = matrix.into();
+        let projection = self.frustum.projection();
+        self.set_projection(projection);
+    }
+
+    /// Gets the custom projection matrix set with `set_projection_matrix`.
+    #[inline]
+    pub fn custom_projection_matrix(&self) -> Option<Matrix4<f32>> {
+        self.projection_matrix
+    }
+
+    /// Sets the clip plane in world space, which replaces the near plane of projection
+    /// matrix so that only the geometries on the positive side of plane are drawn, e.g.
+    /// the ones in front of a mirror when drawing planar reflections. The culling still
+    /// uses the original near plane.
+    #[inline]
+    pub fn set_oblique_plane<T>(&mut self, plane: T)
+    where
+        T: Into<Option<Plane<f32>>>,
+    {
+        self.oblique_plane = plane.into();
+    }
+
+    /// Gets the oblique clip plane in world space.
+    #[inline]
+    pub fn oblique_plane(&self) -> Option<Plane<f32>> {
+        self.oblique_plane
+    }
+
+    /// Sets the exposure, which scales the light from `Lit`s before shading. The ambient
+    /// and emissive colors of materials are not affected.
+    #[inline]
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure.max(0.0);
+    }
+
+    /// Sets the exposure with the exposure value at ISO 100, e.g. about `15` for sunny
+    /// days and `7` for indoor lighting. The maximum luminance before clipping is
+    /// `1.2 * 2^ev100`, which is mapped into `1.0`.
+    #[inline]
+    pub fn set_exposure_ev100(&mut self, ev100: f32) {
+        self.set_exposure(1.0 / (1.2 * 2f32.powf(ev100)));
+    }
+
+    /// Gets the exposure.
+    #[inline]
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// Sets the color controls that are applied in the final resolve of `PostProcessing`.
+    #[inline]
+    pub fn set_color_controls(&mut self, controls: ColorControls) {
+        self.color_controls = controls;
+    }
+
+    /// Gets the color controls.
+    #[inline]
+    pub fn color_controls(&self) -> ColorControls {
+        self.color_controls
+    }
+
+    /// Gets the projection matrix that is used for drawing, which is the custom one if
+    /// there is, with the near plane replaced by oblique clip plane.
+    ///
+    /// The transform of camera is the one updated in last `Scene::draw`. The oblique clip
+    /// plane is ignored if the view matrix is not invertible.
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        let matrix = self.frustum.to_matrix();
+        let inv = self.transform.view_matrix().invert();
+
+        match (self.oblique_plane, inv) {
+            (Some(plane), Some(inv)) => {
+                let plane = Vector4::new(plane.n.x, plane.n.y, plane.n.z, -plane.d);
+                Projection::oblique_matrix(matrix, inv.transpose() * plane)
+            }
+            _ => matrix,
+        }
+    }
+
+    /// Returns a ray going from camera through a screen point, whose position is relative
+    /// to the bottom-left corner of screen with `dimensions`, e.g. the mouse position and
+    /// the dimensions of window. The viewport of camera is taken into account.
+    ///
+    /// The transform of camera is the one updated in last `Scene::draw`.
+    ///
+    /// ```rust,ignore
+    /// let ray = camera.screen_point_to_ray(input::mouse_position(), window::dimensions());
+    /// let picked = scene.raycast(ray).first().map(|v| v.ent);
+    /// ```
+    pub fn screen_point_to_ray<T1, T2>(&self, position: T1, dimensions: T2) -> Ray<f32>
+    where
+        T1: Into<Vector2<f32>>,
+        T2: Into<Vector2<f32>>,
+    {
+        let (position, dimensions) = (position.into(), dimensions.into());
+        let mut x = position.x / dimensions.x.max(1.0);
+        let mut y = position.y / dimensions.y.max(1.0);
+
+        if let Some(rect) = self.viewport {
+            let dims = rect.dim();
+            x = (x - rect.min.x) / dims.x.max(::std::f32::EPSILON);
+            y = (y - rect.min.y) / dims.y.max(::std::f32::EPSILON);
+        }
+
+        let (x, y) = (x * 2.0 - 1.0, y * 2.0 - 1.0);
+
+        let inv = self
+            .projection_matrix
+            .and_then(|_| self.frustum.to_matrix().invert());
+        if let Some(inv) = inv {
+            let near = inv * Vector4::new(x, y, -1.0, 1.0);
+            let far = inv * Vector4::new(x, y, 1.0, 1.0);
+            let (near, far) = (near.truncate() / near.w, far.truncate() / far.w);
+
+            let origin = self.transform.transform_point(near);
+            let direction = self.transform.transform_direction(far - near);
+            return Ray::new(origin, direction);
+        }
+
+        match self.frustum.projection() {
+            Projection::Ortho { width, height, .. } => {
+                let origin = self.transform.position
+                    + self.transform.right() * (x * width * 0.5)
+                    + self.transform.up() * (y * height * 0.5);
+                Ray::new(origin, self.transform.forward())
+            }
+            Projection::Perspective { fovy, aspect, .. } => {
+                let tan = (fovy * 0.5).tan();
+                let direction = Vector3::new(x * tan * aspect, y * tan, 1.0);
+                let direction = self.transform.transform_direction(direction);
+                Ray::new(self.transform.position, direction)
+            }
+        }
+    }
+}
+
+/// The color controls of camera, which are applied to the final image when
+/// `PostProcessing` resolves into the window framebuffer, see `PostProcessing::submit`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorControls {
+    /// The exposure compensation in EV, which scales the colors by `2^ev` before
+    /// tonemapping.
+    pub exposure_compensation: f32,
+    /// The color temperature in kelvin of the light that is considered white. Lower
+    /// values make the image cooler, and higher ones make it warmer. Defaults to `6500`.
+    pub temperature: f32,
+    /// The shift between green and magenta of white balance in [-1, 1]. Positive values
+    /// make the image more magenta.
+    pub tint: f32,
+    /// The lookup table of color grading and its size, which is unwrapped into a strip
+    /// texture in the same layout of `ColorGrading`. It could be loaded as a regular
+    /// texture asset with `video::create_texture_from`.
+    pub lut: Option<(TextureHandle, u32)>,
+    /// The blending factor between the original and remapped colors of lookup table.
+    pub lut_contribution: f32,
+}
+
+impl Default for ColorControls {
+    fn default() -> Self {
+        ColorControls {
+            exposure_compensation: 0.0,
+            temperature: 6500.0,
+            tint: 0.0,
+            lut: None,
+            lut_contribution: 1.0,
+        }
+    }
+}
+
+impl ColorControls {
+    /// Checks if the controls leave the colors untouched.
+    pub fn is_neutral(&self) -> bool {
+        self.exposure_compensation == 0.0
+            && self.temperature == 6500.0
+            && self.tint == 0.0
+            && self.lut.is_none()
+    }
+
+    /// Gets the scale of colors, which is `2^exposure_compensation`.
+    #[inline]
+    pub fn exposure(&self) -> f32 {
+        2f32.powf(self.exposure_compensation)
+    }
+
+    /// Gets the scales of linear RGB channels that balance the white of `temperature`
+    /// and `tint` into neutral. The luminance of colors is preserved.
+    pub fn white_balance(&self) -> Vector3<f32> {
+        let white = kelvin_to_rgb(6500.0);
+        let light = kelvin_to_rgb(self.temperature);
+
+        let mut v = Vector3::new(
+            white.x / light.x.max(0.001),
+            white.y / light.y.max(0.001),
+            white.z / light.z.max(0.001),
+        );
+
+        v.y *= 1.0 - self.tint.max(-1.0).min(1.0) * 0.2;
+        v / v.dot(Vector3::new(0.2126, 0.7152, 0.0722))
+    }
+}
+
+// Approximates the linear color of black body radiation at `kelvin`, with the curves
+// fitted by Tanner Helland.
+fn kelvin_to_rgb(kelvin: f32) -> Vector3<f32> {
+    let t = kelvin.max(1000.0).min(40000.0) / 100.0;
+
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+
+    let g = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_17 * (t - 60.0).powf(-0.075_514_85)
+    };
+
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+
+    let linear = |v: f32| (v.max(0.0).min(255.0) / 255.0).powf(2.2);
+    Vector3::new(linear(r), linear(g), linear(b))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn projections() {
+        let mut camera = Camera::perspective(Deg(60.0), 2.0, 0.1, 100.0);
+        camera.set_ortho_size(10.0);
+        assert_eq!(
+            camera.projection(),
+            Projection::ortho(20.0, 10.0, 0.1, 100.0)
+        );
+
+        camera.set_exposure_ev100(0.0);
+        assert!((camera.exposure() - 1.0 / 1.2).abs() < 0.0001);
+        camera.set_exposure(-1.0);
+        assert_eq!(camera.exposure(), 0.0);
+
+        camera.set_aspect(0.5);
+        assert_eq!(
+            camera.projection(),
+            Projection::ortho(5.0, 10.0, 0.1, 100.0)
+        );
+        assert_eq!(camera.aspect(), 0.5);
+
+        // The rays of custom projection matrix are the same as the ones of projection.
+        let mut camera = Camera::default();
+        camera.transform.position = [1.0, 2.0, 3.0].into();
+        let ray = camera.screen_point_to_ray([20.0, 70.0], [100.0, 100.0]);
+
+        let matrix = camera.projection().to_matrix();
+        camera.set_projection_matrix(matrix);
+        let ray2 = camera.screen_point_to_ray([20.0, 70.0], [100.0, 100.0]);
+        assert_relative_eq!(ray.direction, ray2.direction, epsilon = 1e-4);
+
+        // The frustum is extracted from custom projection matrix.
+        let matrix = Projection::ortho_matrix(2.0, 2.0, 0.1, 100.0);
+        camera.set_projection_matrix(matrix);
+        assert_eq!(camera.frustum().to_matrix(), matrix);
+        assert_eq!(camera.projection_matrix(), matrix);
+
+        camera.set_projection_matrix(None);
+        assert_eq!(camera.projection_matrix(), camera.projection().to_matrix());
+
+        // The near plane is replaced by the oblique plane y = 2.
+        camera.set_oblique_plane(Plane::new(Vector3::new(0.0, -1.0, 0.0), -2.0));
+        let m = camera.projection_matrix() * camera.transform.view_matrix();
+        let v = m * Vector4::new(1.0, 2.0, 10.0, 1.0);
+        assert_relative_eq!(v.z / v.w, -1.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn color_controls() {
+        let mut controls = ColorControls::default();
+        assert!(controls.is_neutral());
+        assert_relative_eq!(controls.white_balance(), Vector3::new(1.0, 1.0, 1.0));
+
+        controls.exposure_compensation = 1.0;
+        assert_eq!(controls.exposure(), 2.0);
+
+        // The white of tungsten light is orange, so the image is balanced into blue.
+        controls.temperature = 3200.0;
+        let v = controls.white_balance();
+        assert!(v.z > v.x);
+        assert_relative_eq!(v.dot(Vector3::new(0.2126, 0.7152, 0.0722)), 1.0);
+
+        controls.temperature = 6500.0;
+        controls.tint = 1.0;
+        let v = controls.white_balance();
+        assert!(v.y < v.x && v.x == v.z);
+        assert!(!controls.is_neutral());
+    }
+
+    #[test]
+    fn degenerated() {
+        // The degenerated custom projection matrix falls back to the one of projection.
+        let mut camera = Camera::default();
+        camera.set_projection_matrix(Matrix4::from_scale(0.0));
+        assert_eq!(camera.projection_matrix(), camera.projection().to_matrix());
+
+        let ray = camera.screen_point_to_ray([50.0, 50.0], [100.0, 100.0]);
+        assert_relative_eq!(ray.direction, Vector3::new(0.0, 0.0, 1.0), epsilon = 1e-4);
     }
 }
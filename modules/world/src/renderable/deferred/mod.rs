@@ -0,0 +1,623 @@
+use crayon::prelude::*;
+use crayon::utils::hash::FastHashMap;
+use failure::Error;
+
+use utils::prelude::Component;
+use Entity;
+
+use super::fog::{Fog, FOG_FS};
+use super::lit::ATTENUATION_FS;
+use super::mesh_renderer::Lightmap;
+use super::queue::{DrawOrder, RenderQueue};
+use super::shadow::{self, spot, Shadow, MAX_PCF_RADIUS, MAX_SHADOW_CASCADES};
+use super::simple::SimpleMaterial;
+use super::{Camera, Lit, LitSource, MeshRenderer, SRGB_FS};
+
+/// The maximum shininess that could be encoded into the G-buffer.
+pub const MAX_SHININESS: f32 = 256.0;
+
+/// A deferred renderer that writes the surface attributes of meshes into G-buffers
+/// (albedo, normal, specular, lightmap and depth) with multiple render targets first, and then
+/// resolves the lighting with one full-screen pass per `Lit`.
+///
+/// Since the cost of lighting does not depend on the number of meshes any more, it
+/// scales much better than `SimpleRenderer` when there are dozens of lights in the
+/// scene. Notes that the ambient color of `SimpleMaterial` is ignored, only the global
+/// ambient takes effects. And the `Lightmap` of mesh replaces the global ambient like
+/// `SimpleRenderer`, which is clamped into [0, 1] in the G-buffer. Every spot light could
+/// have its own cookie, and the first spot light that casting shadows is rendered with
+/// shadow map. The shadows of other lights are not supported yet.
+///
+/// The colors of diffuse textures are decoded from sRGB space like `SimpleRenderer`, and
+/// the lights are accumulated in linear space. If the surface is not encoded by hardware,
+/// they are accumulated into an intermediate render texture first, and then encoded into
+/// sRGB space when resolving onto the surface.
+pub struct DeferredRenderer {
+    materials: Component<SimpleMaterial>,
+    shadow: Option<Shadow>,
+
+    // The G-buffers of surfaces that cameras draw into, which have the same dimensions as
+    // them.
+    gbuffers: FastHashMap<SurfaceHandle, GBuffer>,
+    surface: SurfaceHandle,
+
+    gbuffer_shader: ShaderHandle,
+    gbuffer_uniforms: UniformVariableLayout,
+    ambient_shader: ShaderHandle,
+    dir_lit_shader: ShaderHandle,
+    point_lit_shader: ShaderHandle,
+    spot_lit_shader: ShaderHandle,
+    resolve_shader: ShaderHandle,
+
+    drawcalls: DrawCommandBuffer<DrawOrder>,
+    lightings: DrawCommandBuffer<u32>,
+
+    global_ambient: Color<f32>,
+}
+
+impl Drop for DeferredRenderer {
+    fn drop(&mut self) {
+        video::delete_surface(self.surface);
+        video::delete_shader(self.gbuffer_shader);
+        video::delete_shader(self.ambient_shader);
+        video::delete_shader(self.dir_lit_shader);
+        video::delete_shader(self.point_lit_shader);
+        video::delete_shader(self.spot_lit_shader);
+        video::delete_shader(self.resolve_shader);
+    }
+}
+
+impl DeferredRenderer {
+    /// Creates a new `DeferredRenderer`.
+    pub fn new() -> Result<Self, Error> {
+        // Create shader state of geometry pass.
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Normal, 3)
+            .with_optional(Attribute::Tangent, 4)
+            .with_optional(Attribute::Color0, 3)
+            .with_optional(Attribute::Texcoord0, 2)
+            .with_optional(Attribute::Texcoord1, 2)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_ModelViewMatrix", UniformVariableType::Matrix4f)
+            .with("u_MVPMatrix", UniformVariableType::Matrix4f)
+            .with("u_ViewNormalMatrix", UniformVariableType::Matrix4f)
+            .with("u_Diffuse", UniformVariableType::Vector3f)
+            .with("u_DiffuseTexture", UniformVariableType::Texture)
+            .with("u_DiffuseDecodeSRGB", UniformVariableType::F32)
+            .with("u_Specular", UniformVariableType::Vector3f)
+            .with("u_SpecularTexture", UniformVariableType::Texture)
+            .with("u_Shininess", UniformVariableType::F32)
+            .with("u_NormalScale", UniformVariableType::F32)
+            .with("u_NormalTexture", UniformVariableType::Texture)
+            .with("u_LightmapTexture", UniformVariableType::Texture)
+            .with("u_LightmapScaleOffset", UniformVariableType::Vector4f)
+            .with("u_LightmapIntensity", UniformVariableType::F32)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "
+            #version 100
+            precision lowp float;
+            {0}
+            ",
+            include_str!("shaders/gbuffer.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            #extension GL_EXT_draw_buffers : require
+            precision lowp float;
+
+            #define MAX_SHININESS {0:.1}
+            {1}
+            {2}
+            ",
+            MAX_SHININESS,
+            SRGB_FS,
+            include_str!("shaders/gbuffer.fs")
+        );
+
+        let gbuffer_uniforms = params.uniforms.clone();
+        let gbuffer_shader = video::create_shader(params, vs, fs)?;
+
+        // Create shader states of lighting passes.
+        let uniforms = Fog::uniforms(UniformVariableLayout::build())
+            .with("u_Albedo", UniformVariableType::RenderTexture)
+            .with("u_Depth", UniformVariableType::RenderTexture)
+            .with("u_Lightmap", UniformVariableType::RenderTexture)
+            .with("u_InvProjectionMatrix", UniformVariableType::Matrix4f)
+            .with("u_GlobalAmbient", UniformVariableType::Vector3f);
+
+        let ambient_shader = Self::create_lighting_shader("AMBIENT", uniforms, None)?;
+
+        let uniforms = Self::lighting_uniforms()
+            .with("u_LitViewDir", UniformVariableType::Vector3f)
+            .with("u_LitColor", UniformVariableType::Vector3f);
+
+        let blend = Some((Equation::Add, BlendFactor::One, BlendFactor::One));
+        let dir_lit_shader = Self::create_lighting_shader("DIR_LIT", uniforms, blend)?;
+
+        let uniforms = Self::lighting_uniforms()
+            .with("u_LitViewPos", UniformVariableType::Vector3f)
+            .with("u_LitColor", UniformVariableType::Vector3f)
+            .with("u_LitAttenuation", UniformVariableType::Vector4f);
+
+        let point_lit_shader = Self::create_lighting_shader("POINT_LIT", uniforms, blend)?;
+
+        let uniforms = Shadow::spot_uniforms(Self::lighting_uniforms())
+            .with("u_LitViewPos", UniformVariableType::Vector3f)
+            .with("u_LitViewDir", UniformVariableType::Vector3f)
+            .with("u_LitColor", UniformVariableType::Vector3f)
+            .with("u_LitAttenuation", UniformVariableType::Vector4f)
+            .with("u_LitCone", UniformVariableType::Vector2f)
+            .with("u_LitCookie", UniformVariableType::Texture)
+            .with("u_LitCookieMatrix", UniformVariableType::Matrix4f);
+
+        let spot_lit_shader = Self::create_lighting_shader("SPOT_LIT", uniforms, blend)?;
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Lighting", UniformVariableType::RenderTexture)
+            .with("u_EncodeSRGB", UniformVariableType::F32);
+
+        let resolve_shader = Self::create_lighting_shader("RESOLVE", uniforms, None)?;
+
+        let params = SurfaceParams::default();
+        let surface = video::create_surface(params)?;
+
+        Ok(DeferredRenderer {
+            materials: Component::new(),
+            shadow: None,
+            gbuffers: FastHashMap::default(),
+            surface: surface,
+            gbuffer_shader: gbuffer_shader,
+            gbuffer_uniforms: gbuffer_uniforms,
+            ambient_shader: ambient_shader,
+            dir_lit_shader: dir_lit_shader,
+            point_lit_shader: point_lit_shader,
+            spot_lit_shader: spot_lit_shader,
+            resolve_shader: resolve_shader,
+            drawcalls: DrawCommandBuffer::new(),
+            lightings: DrawCommandBuffer::new(),
+            global_ambient: Color::gray(),
+        })
+    }
+
+    #[inline]
+    pub fn add(&mut self, ent: Entity, material: SimpleMaterial) -> Option<SimpleMaterial> {
+        self.materials.add(ent, material)
+    }
+
+    #[inline]
+    pub fn has(&self, ent: Entity) -> bool {
+        self.materials.has(ent)
+    }
+
+    #[inline]
+    pub fn material(&self, ent: Entity) -> Option<&SimpleMaterial> {
+        self.materials.get(ent)
+    }
+
+    #[inline]
+    pub fn material_mut(&mut self, ent: Entity) -> Option<&mut SimpleMaterial> {
+        self.materials.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove(&mut self, ent: Entity) {
+        self.materials.remove(ent)
+    }
+
+    #[inline]
+    pub fn set_global_ambient<T: Into<Color<f32>>>(&mut self, color: T) {
+        self.global_ambient = color.into();
+    }
+
+    /// Sets the shadow maps of lights, which could be created with `Shadow::build()`.
+    #[inline]
+    pub fn set_shadow(&mut self, shadow: Shadow) {
+        self.shadow = Some(shadow);
+    }
+
+    /// Gets the shadow maps of lights.
+    #[inline]
+    pub fn shadow(&self) -> Option<&Shadow> {
+        self.shadow.as_ref()
+    }
+
+    fn lighting_uniforms() -> UniformVariableLayoutBuilder {
+        Fog::uniforms(UniformVariableLayout::build())
+            .with("u_Albedo", UniformVariableType::RenderTexture)
+            .with("u_Normal", UniformVariableType::RenderTexture)
+            .with("u_Specular", UniformVariableType::RenderTexture)
+            .with("u_Depth", UniformVariableType::RenderTexture)
+            .with("u_Lightmap", UniformVariableType::RenderTexture)
+            .with("u_InvProjectionMatrix", UniformVariableType::Matrix4f)
+            .with("u_LitBaked", UniformVariableType::F32)
+    }
+
+    fn create_lighting_shader(
+        variant: &str,
+        uniforms: UniformVariableLayoutBuilder,
+        blend: Option<(Equation, BlendFactor, BlendFactor)>,
+    ) -> Result<ShaderHandle, Error> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Texcoord0, 2)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.state.color_blend = blend;
+        params.attributes = attributes;
+        params.uniforms = uniforms.finish();
+
+        let vs = format!(
+            "
+            #version 100
+            precision lowp float;
+            {0}
+            ",
+            include_str!("shaders/lighting.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision mediump float;
+
+            #define {0}
+            #define MAX_SHININESS {1:.1}
+            #define MAX_SHADOW_CASCADES {4}
+            #define MAX_PCF_RADIUS {5}
+            {2}
+            {6}
+            {7}
+            {8}
+            {3}
+            ",
+            variant,
+            MAX_SHININESS,
+            FOG_FS,
+            include_str!("shaders/lighting.fs"),
+            MAX_SHADOW_CASCADES,
+            MAX_PCF_RADIUS,
+            shadow::RECEIVER_FS,
+            ATTENUATION_FS,
+            SRGB_FS
+        );
+
+        Ok(video::create_shader(params, vs, fs)?)
+    }
+
+    /// Makes sure the G-buffer of `surface` matches the dimensions it draws into and
+    /// whether it's encoded by shaders, the ones of deleted surfaces are released.
+    fn prepare_gbuffer(&mut self, surface: SurfaceHandle) -> Result<(), Error> {
+        self.gbuffers
+            .retain(|&k, _| video::surface_state(k) != ResourceState::NotReady);
+
+        let dimensions = video::surface_dimensions(surface)
+            .ok_or_else(|| format_err!("{:?} is invalid.", surface))?;
+
+        // The surfaces with zero size have nothing to draw into.
+        let dimensions = Vector2::new(dimensions.x.max(1), dimensions.y.max(1));
+
+        let encoding = video::requires_srgb_encoding(surface);
+        let key = self
+            .gbuffers
+            .get(&surface)
+            .map(|v| (v.dimensions, v.lighting.is_some()));
+
+        if key != Some((dimensions, encoding)) {
+            self.gbuffers.remove(&surface);
+            self.gbuffers
+                .insert(surface, GBuffer::new(dimensions, encoding)?);
+        }
+
+        Ok(())
+    }
+}
+
+impl super::Renderer for DeferredRenderer {
+    type Mtl = SimpleMaterial;
+
+    fn add_mtl(&mut self, ent: Entity, mtl: Self::Mtl) {
+        self.add(ent, mtl);
+    }
+
+    fn mtl(&self, ent: Entity) -> Option<&Self::Mtl> {
+        self.material(ent)
+    }
+
+    fn mtl_mut(&mut self, ent: Entity) -> Option<&mut Self::Mtl> {
+        self.material_mut(ent)
+    }
+
+    fn remove_mtl(&mut self, ent: Entity) {
+        self.remove(ent);
+    }
+
+    fn submit_shadows(
+        &mut self,
+        camera: &Camera,
+        lits: &[Lit],
+        casters: &[&MeshRenderer],
+    ) -> Result<(), Error> {
+        if let Some(ref mut shadow) = self.shadow {
+            shadow.reset(camera);
+        }
+
+        let spot = lits.iter().find(|v| match v.source {
+            LitSource::Spot { .. } => v.enable && v.shadow_caster,
+            _ => false,
+        });
+
+        if let Some(lit) = spot {
+            if self.shadow.is_none() {
+                self.shadow = Some(Shadow::build().finish()?);
+            }
+
+            self.shadow.as_mut().unwrap().draw(camera, lit, casters)?;
+        }
+
+        Ok(())
+    }
+
+    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[&MeshRenderer]) {
+        use crayon::math::prelude::{InnerSpace, Matrix, MetricSpace, SquareMatrix};
+
+        let surface = camera.surface().unwrap_or(self.surface);
+        if let Err(err) = self.prepare_gbuffer(surface) {
+            warn!("Failed to create G-buffers of DeferredRenderer. {}", err);
+            return;
+        }
+
+        let gbuffer = &self.gbuffers[&surface];
+        let view_matrix = camera.transform.view_matrix();
+        let projection_matrix = camera.projection_matrix();
+        let inv_projection_matrix = projection_matrix.invert().unwrap_or(projection_matrix);
+        let inv_view_matrix = view_matrix.invert().unwrap_or(view_matrix);
+
+        // Geometry pass, which writes the attributes of meshes into G-buffers.
+        for mesh in meshes {
+            let model_matrix = mesh.transform.matrix();
+            let mv = view_matrix * model_matrix;
+            let mvp = projection_matrix * mv;
+            let vn = mv.invert().and_then(|v| Some(v.transpose())).unwrap_or(mv);
+
+            let mut dc = Draw::new(self.gbuffer_shader, mesh.mesh);
+            dc.set_uniform_variable("u_ModelViewMatrix", mv);
+            dc.set_uniform_variable("u_MVPMatrix", mvp);
+            dc.set_uniform_variable("u_ViewNormalMatrix", vn);
+
+            let mat = self.material(mesh.ent).cloned().unwrap_or_default();
+            let diffuse = mat.diffuse_texture.unwrap_or(crate::default().white);
+            let specular = mat.specular_texture.unwrap_or(crate::default().white);
+            let normal = mat.normal_texture.unwrap_or(crate::default().white);
+            let normal_scale = if mat.normal_texture.is_some() {
+                mat.normal_scale
+            } else {
+                0.0
+            };
+
+            dc.set_uniform_variable("u_Diffuse", mat.diffuse.rgb());
+            dc.set_uniform_variable("u_DiffuseTexture", diffuse);
+            dc.set_uniform_variable("u_DiffuseDecodeSRGB", super::srgb_decoding(diffuse));
+            dc.set_uniform_variable("u_Specular", mat.specular.rgb());
+            dc.set_uniform_variable("u_SpecularTexture", specular);
+            dc.set_uniform_variable("u_Shininess", mat.shininess);
+            dc.set_uniform_variable("u_NormalScale", normal_scale);
+            dc.set_uniform_variable("u_NormalTexture", normal);
+
+            let lightmap = mesh.lightmap.unwrap_or_else(|| {
+                let mut v = Lightmap::from(crate::default().white);
+                v.intensity = 0.0;
+                v
+            });
+
+            dc.set_uniform_variable("u_LightmapTexture", lightmap.texture);
+            dc.set_uniform_variable("u_LightmapScaleOffset", lightmap.scale_offset);
+            dc.set_uniform_variable("u_LightmapIntensity", lightmap.intensity);
+            mesh.properties.apply(&self.gbuffer_uniforms, &mut dc);
+
+            let order = DrawOrder::new(
+                RenderQueue::Opaque,
+                self.gbuffer_shader,
+                mesh.transform.position.distance(camera.transform.position),
+            );
+
+            self.drawcalls.draw(order, dc);
+        }
+
+        self.drawcalls.submit(gbuffer.surface).unwrap();
+
+        // Lighting passes, which accumulate the contributions of lits additively.
+        let quad = crate::default().quad;
+
+        let mut dc = Draw::new(self.ambient_shader, quad);
+        dc.set_uniform_variable("u_Albedo", gbuffer.albedo);
+        dc.set_uniform_variable("u_Depth", gbuffer.depth);
+        dc.set_uniform_variable("u_Lightmap", gbuffer.lightmap);
+        dc.set_uniform_variable("u_InvProjectionMatrix", inv_projection_matrix);
+        dc.set_uniform_variable("u_GlobalAmbient", self.global_ambient.rgb());
+        Fog::bind(camera.fog.as_ref(), &mut dc);
+        self.lightings.draw(0, dc);
+
+        for (i, lit) in lits.iter().enumerate() {
+            if !lit.enable {
+                continue;
+            }
+
+            let color = lit.radiance(camera.exposure());
+
+            let mut dc = match lit.source {
+                LitSource::Dir => {
+                    let dir = view_matrix * lit.transform.forward().extend(0.0);
+                    let mut dc = Draw::new(self.dir_lit_shader, quad);
+                    dc.set_uniform_variable("u_LitViewDir", dir.truncate().normalize());
+                    dc
+                }
+                LitSource::Point { .. } => {
+                    let mut pos = view_matrix * lit.transform.position.extend(1.0);
+                    pos /= pos.w;
+                    let attenuation = lit.attenuation_params();
+
+                    let mut dc = Draw::new(self.point_lit_shader, quad);
+                    dc.set_uniform_variable("u_LitViewPos", pos.truncate());
+                    dc.set_uniform_variable("u_LitAttenuation", attenuation);
+                    dc
+                }
+                LitSource::Spot {
+                    radius,
+                    angle,
+                    inner_angle,
+                    ..
+                } => {
+                    let mut pos = view_matrix * lit.transform.position.extend(1.0);
+                    pos /= pos.w;
+                    let dir = view_matrix * lit.transform.forward().extend(0.0);
+                    let attenuation = lit.attenuation_params();
+
+                    let (cookie, cookie_matrix) = match lit.cookie {
+                        Some(texture) => {
+                            let vp = spot::frustum(&lit.transform, angle, radius);
+                            (texture, spot::bias() * vp * inv_view_matrix)
+                        }
+                        None => (crate::default().white, Matrix4::identity()),
+                    };
+
+                    let mut dc = Draw::new(self.spot_lit_shader, quad);
+                    dc.set_uniform_variable("u_LitViewPos", pos.truncate());
+                    dc.set_uniform_variable("u_LitViewDir", dir.truncate().normalize());
+                    dc.set_uniform_variable("u_LitAttenuation", attenuation);
+                    dc.set_uniform_variable("u_LitCone", spot::cone(angle, inner_angle));
+                    dc.set_uniform_variable("u_LitCookie", cookie);
+                    dc.set_uniform_variable("u_LitCookieMatrix", cookie_matrix);
+
+                    let shadow = self.shadow.as_ref();
+                    let casting = shadow.map(|s| s.is_casting(camera, lit)).unwrap_or(false);
+                    Shadow::bind_spot(shadow, camera, casting, view_matrix, &mut dc);
+                    dc
+                }
+            };
+
+            dc.set_uniform_variable("u_Albedo", gbuffer.albedo);
+            dc.set_uniform_variable("u_Normal", gbuffer.normal);
+            dc.set_uniform_variable("u_Specular", gbuffer.specular);
+            dc.set_uniform_variable("u_Depth", gbuffer.depth);
+            dc.set_uniform_variable("u_Lightmap", gbuffer.lightmap);
+            dc.set_uniform_variable("u_InvProjectionMatrix", inv_projection_matrix);
+            dc.set_uniform_variable("u_LitColor", color);
+            dc.set_uniform_variable("u_LitBaked", if lit.baked { 1.0 } else { 0.0 });
+            Fog::bind(camera.fog.as_ref(), &mut dc);
+            self.lightings.draw(i as u32 + 1, dc);
+        }
+
+        match gbuffer.lighting {
+            Some((lighting, target)) => {
+                self.lightings.submit(target).unwrap();
+
+                let mut dc = Draw::new(self.resolve_shader, quad);
+                dc.set_uniform_variable("u_Lighting", lighting);
+                dc.set_uniform_variable("u_EncodeSRGB", 1.0);
+
+                let mut cmds = CommandBuffer::new();
+                cmds.draw(dc);
+                cmds.submit(surface).unwrap();
+            }
+            None => self.lightings.submit(surface).unwrap(),
+        }
+    }
+}
+
+/// The render targets that holds the intermediate attributes of geometry pass.
+struct GBuffer {
+    dimensions: Vector2<u32>,
+    albedo: RenderTextureHandle,
+    normal: RenderTextureHandle,
+    specular: RenderTextureHandle,
+    lightmap: RenderTextureHandle,
+    depth: RenderTextureHandle,
+    surface: SurfaceHandle,
+    // The render texture and its surface that lights are accumulated into, if they are
+    // encoded into sRGB space by shaders.
+    lighting: Option<(RenderTextureHandle, SurfaceHandle)>,
+}
+
+impl GBuffer {
+    fn new(dimensions: Vector2<u32>, encoding: bool) -> Result<Self, Error> {
+        let mut params = RenderTextureParams::default();
+        params.format = RenderTextureFormat::RGBA8;
+        params.filter = TextureFilter::Nearest;
+        params.dimensions = dimensions;
+
+        let albedo = video::create_render_texture(params)?;
+        let normal = video::create_render_texture(params)?;
+        let specular = video::create_render_texture(params)?;
+        let lightmap = video::create_render_texture(params)?;
+
+        params.format = RenderTextureFormat::Depth24;
+        let depth = video::create_render_texture(params)?;
+
+        let mut params = SurfaceParams::default();
+        params.set_attachments(&[albedo, normal, specular, lightmap], depth)?;
+        params.set_clear(Color::transparent(), 1.0, None);
+        let surface = video::create_surface(params)?;
+
+        let lighting = if encoding {
+            let mut params = RenderTextureParams::default();
+            params.filter = TextureFilter::Nearest;
+            params.dimensions = dimensions;
+
+            // The linear colors need more precision than RGBA8 in dark areas.
+            let hdr = video::is_render_texture_format_supported(RenderTextureFormat::RGBA16F);
+            params.format = if hdr {
+                RenderTextureFormat::RGBA16F
+            } else {
+                RenderTextureFormat::RGBA8
+            };
+
+            let texture = video::create_render_texture(params)?;
+            let mut params = SurfaceParams::default();
+            params.set_attachments(&[texture], None)?;
+            Some((texture, video::create_surface(params)?))
+        } else {
+            None
+        };
+
+        Ok(GBuffer {
+            dimensions: dimensions,
+            albedo: albedo,
+            normal: normal,
+            specular: specular,
+            lightmap: lightmap,
+            depth: depth,
+            surface: surface,
+            lighting: lighting,
+        })
+    }
+}
+
+impl Drop for GBuffer {
+    fn drop(&mut self) {
+        video::delete_surface(self.surface);
+        video::delete_render_texture(self.albedo);
+        video::delete_render_texture(self.normal);
+        video::delete_render_texture(self.specular);
+        video::delete_render_texture(self.lightmap);
+        video::delete_render_texture(self.depth);
+
+        if let Some((texture, surface)) = self.lighting {
+            video::delete_surface(surface);
+            video::delete_render_texture(texture);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+varying vec3 v_EyeNormal;
+varying vec4 v_EyeTangent;
+varying vec4 v_Color;
+varying vec2 v_Texcoord;
+varying vec2 v_Texcoord1;
+
+uniform vec3 u_Diffuse;
+uniform sampler2D u_DiffuseTexture;
+uniform float u_DiffuseDecodeSRGB;
+
+uniform vec3 u_Specular;
+uniform sampler2D u_SpecularTexture;
+
+uniform float u_Shininess;
+
+uniform float u_NormalScale;
+uniform sampler2D u_NormalTexture;
+
+uniform sampler2D u_LightmapTexture;
+uniform vec4 u_LightmapScaleOffset;
+uniform float u_LightmapIntensity;
+
+vec3 CalculateNormal()
+{
+    vec3 N = normalize(v_EyeNormal);
+
+    // Meshes without tangents have zero vectors here.
+    if (u_NormalScale > 0.0 && dot(v_EyeTangent.xyz, v_EyeTangent.xyz) > 0.0)
+    {
+        vec3 T = normalize(v_EyeTangent.xyz - N * dot(N, v_EyeTangent.xyz));
+        vec3 B = cross(N, T) * v_EyeTangent.w;
+        vec3 n = texture2D(u_NormalTexture, v_Texcoord).xyz * 2.0 - 1.0;
+        n.xy *= u_NormalScale;
+        N = normalize(mat3(T, B, N) * n);
+    }
+
+    return N;
+}
+
+void main()
+{
+    vec3 normal = CalculateNormal();
+    vec3 diffuse = DecodeSRGB(texture2D(u_DiffuseTexture, v_Texcoord).rgb, u_DiffuseDecodeSRGB) * u_Diffuse * v_Color.rgb;
+    vec3 specular = texture2D(u_SpecularTexture, v_Texcoord).rgb * u_Specular;
+
+    // albedo
+    gl_FragData[0] = vec4(diffuse, 1.0);
+    // view space normal and encoded shininess
+    gl_FragData[1] = vec4(normal * 0.5 + 0.5, clamp(u_Shininess / MAX_SHININESS, 0.0, 1.0));
+    // specular
+    gl_FragData[2] = vec4(specular, 1.0);
+
+    // lightmap, whose alpha indicates whether the mesh is lightmapped
+    if (u_LightmapIntensity > 0.0)
+    {
+        vec2 uv = v_Texcoord1 * u_LightmapScaleOffset.xy + u_LightmapScaleOffset.zw;
+        gl_FragData[3] = vec4(texture2D(u_LightmapTexture, uv).rgb * u_LightmapIntensity, 1.0);
+    }
+    else
+    {
+        gl_FragData[3] = vec4(0.0);
+    }
+}
@@ -0,0 +1,119 @@
+varying vec2 v_Texcoord;
+
+uniform sampler2D u_Albedo;
+uniform sampler2D u_Depth;
+uniform sampler2D u_Lightmap;
+uniform mat4 u_InvProjectionMatrix;
+
+// Reconstructs the view space position from depth buffer.
+vec3 CalculateFragPos(float depth)
+{
+    vec4 ndc = vec4(v_Texcoord * 2.0 - 1.0, depth * 2.0 - 1.0, 1.0);
+    vec4 eyePos = u_InvProjectionMatrix * ndc;
+    return eyePos.xyz / eyePos.w;
+}
+
+#if defined(RESOLVE)
+
+uniform sampler2D u_Lighting;
+
+void main()
+{
+    gl_FragColor = vec4(EncodeSRGB(texture2D(u_Lighting, v_Texcoord).rgb), 1.0);
+}
+
+#elif defined(AMBIENT)
+
+uniform vec3 u_GlobalAmbient;
+
+void main()
+{
+    // The lightmap replaces the global ambient of lightmapped meshes.
+    vec4 lightmap = texture2D(u_Lightmap, v_Texcoord);
+    vec3 diffuse = texture2D(u_Albedo, v_Texcoord).rgb;
+    vec3 result = mix(u_GlobalAmbient, lightmap.rgb, lightmap.a) * diffuse;
+
+    // The fog color is added once in ambient pass, and the background is not fogged.
+    float depth = texture2D(u_Depth, v_Texcoord).r;
+    if (depth < 1.0)
+    {
+        result = ApplyFog(result, length(CalculateFragPos(depth)));
+    }
+
+    gl_FragColor = vec4(result, 1.0);
+}
+
+#else
+
+uniform sampler2D u_Normal;
+uniform sampler2D u_Specular;
+
+uniform vec3 u_LitColor;
+// 1.0 if the lighting of this light is baked into lightmaps.
+uniform float u_LitBaked;
+
+#if defined(DIR_LIT)
+uniform vec3 u_LitViewDir;
+#else
+uniform vec3 u_LitViewPos;
+uniform vec4 u_LitAttenuation;
+#endif
+
+#if defined(SPOT_LIT)
+uniform vec3 u_LitViewDir;
+// x: the cosine of outer cone, y: the cosine of inner cone.
+uniform vec2 u_LitCone;
+uniform sampler2D u_LitCookie;
+uniform mat4 u_LitCookieMatrix;
+#endif
+
+vec3 Calculate(vec3 normal, vec3 viewDir, vec3 lightDir, vec3 reflectDir, vec3 d, vec3 s, float shininess)
+{
+    vec3 diffuse = max(dot(normal, -lightDir), 0.0) * d;
+    vec3 specular = pow(max(dot(viewDir, reflectDir), 0.0), shininess) * s;
+    return diffuse + specular;
+}
+
+void main()
+{
+    float depth = texture2D(u_Depth, v_Texcoord).r;
+    vec3 fragPos = CalculateFragPos(depth);
+
+    vec4 encoded = texture2D(u_Normal, v_Texcoord);
+    vec3 normal = normalize(encoded.xyz * 2.0 - 1.0);
+    float shininess = encoded.w * MAX_SHININESS;
+    vec3 viewDir = normalize(fragPos);
+
+    vec3 diffuse = texture2D(u_Albedo, v_Texcoord).rgb;
+    vec3 specular = texture2D(u_Specular, v_Texcoord).rgb;
+
+#if defined(DIR_LIT)
+    vec3 reflectDir = reflect(-u_LitViewDir, normal);
+    vec3 result = Calculate(normal, viewDir, u_LitViewDir, reflectDir, diffuse, specular, shininess) * u_LitColor;
+#else
+    vec3 lightDir = normalize(fragPos - u_LitViewPos);
+    vec3 reflectDir = reflect(-lightDir, normal);
+    float distance = length(u_LitViewPos - fragPos);
+    float attenuation = CalculateAttenuation(u_LitAttenuation, distance);
+
+    vec3 power = Calculate(normal, viewDir, lightDir, reflectDir, diffuse, specular, shininess) * u_LitColor;
+
+#if defined(SPOT_LIT)
+    float theta = dot(lightDir, u_LitViewDir);
+    power *= clamp((theta - u_LitCone.x) / max(u_LitCone.y - u_LitCone.x, 0.0001), 0.0, 1.0);
+
+    vec4 cookie = u_LitCookieMatrix * vec4(fragPos, 1.0);
+    power *= cookie.w > 0.0 ? texture2D(u_LitCookie, cookie.xy / cookie.w).rgb : vec3(0.0);
+    power *= CalculateSpotShadow(fragPos, normal, lightDir);
+#endif
+
+    vec3 result = max(power * attenuation, vec3(0.0, 0.0, 0.0));
+#endif
+
+    // The baked lights only light the meshes without lightmaps.
+    result *= 1.0 - u_LitBaked * texture2D(u_Lightmap, v_Texcoord).a;
+
+    gl_FragColor = vec4(result * FogFactor(length(fragPos)), 1.0);
+}
+
+#endif
@@ -0,0 +1,142 @@
+use crayon::prelude::*;
+
+/// The shader chunk of `ApplyFog(color, distance)`, which blends the linear color of
+/// fragment at `distance` from eye with the fog color. The uniform variable `u_Fog` is set
+/// by the built-in renderers, and by `MaterialRenderer` if it's declared by shader.
+pub const FOG_FS: &str = include_str!("shaders/fog.fs");
+
+/// The ways that the visibility of fragments falls off over the distance from eye.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FogMode {
+    /// Falls off linearly from `start` to `end`.
+    Linear,
+    /// Falls off with `exp(-density * distance)`.
+    Exp,
+    /// Falls off with `exp(-(density * distance)^2)`.
+    Exp2,
+}
+
+/// The distance fog of scene, which is set with `Renderable::set_fog`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub mode: FogMode,
+    /// The linear color of fog.
+    pub color: Color<f32>,
+    /// The density of exponential fogs.
+    pub density: f32,
+    /// The distance where the linear fog starts.
+    pub start: f32,
+    /// The distance where the linear fog fully covers fragments.
+    pub end: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Fog {
+            mode: FogMode::Exp2,
+            color: Color::gray(),
+            density: 0.01,
+            start: 0.0,
+            end: 300.0,
+        }
+    }
+}
+
+impl Fog {
+    /// Creates a linear fog between `start` and `end`.
+    pub fn linear<T: Into<Color<f32>>>(color: T, start: f32, end: f32) -> Self {
+        Fog {
+            mode: FogMode::Linear,
+            color: color.into(),
+            start: start,
+            end: end,
+            ..Default::default()
+        }
+    }
+
+    /// Creates an exponential fog with `density`.
+    pub fn exp<T: Into<Color<f32>>>(color: T, density: f32) -> Self {
+        Fog {
+            mode: FogMode::Exp,
+            color: color.into(),
+            density: density,
+            ..Default::default()
+        }
+    }
+
+    /// Creates an exponential squared fog with `density`.
+    pub fn exp2<T: Into<Color<f32>>>(color: T, density: f32) -> Self {
+        Fog {
+            mode: FogMode::Exp2,
+            color: color.into(),
+            density: density,
+            ..Default::default()
+        }
+    }
+
+    /// Gets the visibility of the fragment at `distance` from eye, which is `1.0` if it's
+    /// not fogged at all. It's the same as `FogFactor` in `FOG_FS`.
+    pub fn factor(&self, distance: f32) -> f32 {
+        let f = match self.mode {
+            FogMode::Linear => (self.end - distance) / (self.end - self.start).max(0.0001),
+            FogMode::Exp => (-self.density * distance).exp(),
+            FogMode::Exp2 => (-(self.density * distance).powi(2)).exp(),
+        };
+
+        f.max(0.0).min(1.0)
+    }
+
+    /// Gets the value of `u_Fog`, whose columns are the color, the mode and density, and
+    /// the start and end of linear fog. The mode is 0 for none, 1 for linear, 2 for
+    /// exponential and 3 for exponential squared.
+    pub fn uniform(fog: Option<&Fog>) -> [[f32; 3]; 3] {
+        match fog {
+            Some(v) => {
+                let mode = match v.mode {
+                    FogMode::Linear => 1.0,
+                    FogMode::Exp => 2.0,
+                    FogMode::Exp2 => 3.0,
+                };
+
+                [v.color.rgb(), [mode, v.density, 0.0], [v.start, v.end, 0.0]]
+            }
+            None => [[0.0; 3]; 3],
+        }
+    }
+
+    /// Appends the uniform variable of `FOG_FS`.
+    pub(crate) fn uniforms(uniforms: UniformVariableLayoutBuilder) -> UniformVariableLayoutBuilder {
+        uniforms.with("u_Fog", UniformVariableType::Matrix3f)
+    }
+
+    /// Sets the uniform variable of `FOG_FS`, the fragments are not fogged if `fog` is
+    /// none.
+    pub(crate) fn bind(fog: Option<&Fog>, dc: &mut Draw) {
+        dc.set_uniform_variable("u_Fog", Self::uniform(fog));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn factor() {
+        let fog = Fog::linear(Color::white(), 10.0, 20.0);
+        assert_eq!(fog.factor(5.0), 1.0);
+        assert_eq!(fog.factor(15.0), 0.5);
+        assert_eq!(fog.factor(25.0), 0.0);
+        assert_eq!(
+            Fog::uniform(Some(&fog)),
+            [[1.0, 1.0, 1.0], [1.0, 0.01, 0.0], [10.0, 20.0, 0.0]]
+        );
+
+        let fog = Fog::exp(Color::white(), 0.1);
+        assert!((fog.factor(10.0) - (-1f32).exp()).abs() < 1e-6);
+
+        let fog = Fog::exp2(Color::white(), 0.1);
+        assert!((fog.factor(20.0) - (-4f32).exp()).abs() < 1e-6);
+        assert!(fog.factor(0.0) == 1.0 && fog.factor(1000.0) < 1e-6);
+        assert_eq!(Fog::uniform(None)[1][0], 0.0);
+    }
+}
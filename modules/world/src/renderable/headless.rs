@@ -34,5 +34,5 @@ impl Renderer for HeadlessRenderer {
         self.materials.remove(ent);
     }
 
-    fn submit(&mut self, _: &Camera, _: &[Lit], _: &[MeshRenderer]) {}
+    fn submit(&mut self, _: &Camera, _: &[Lit], _: &[&MeshRenderer]) {}
 }
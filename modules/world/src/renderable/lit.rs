@@ -1,11 +1,18 @@
-use crayon::math::prelude::Color;
+use crayon::math::prelude::{Color, Vector3, Vector4};
+use crayon::video::assets::texture::TextureHandle;
 
 use spatial::prelude::Transform;
 
+use super::shadow::ShadowCascades;
+
+/// The shader chunk of `CalculateAttenuation`, which evaluates the falloff of point and
+/// spot lights at distance with the parameters of `Lit::attenuation_params`.
+pub(crate) const ATTENUATION_FS: &str = include_str!("shaders/attenuation.fs");
+
 /// In order to calculate the shading of a 3D object, we needs to knowns the intensity,
 /// direction and color of the light that falls on it. These properties are provided by
 /// Lit components in the scene.
-#[derive(Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub struct Lit {
     /// Is this light enable.
     pub enable: bool,
@@ -13,17 +20,34 @@ pub struct Lit {
     pub shadow_caster: bool,
     /// Color of the light.
     pub color: Color<f32>,
-    /// Brightness of the light source, in lumens.
+    /// Brightness of the light source, in `units`.
     pub intensity: f32,
+    /// The units of `intensity`.
+    #[serde(default)]
+    pub units: LitUnits,
     /// Lit source
     pub source: LitSource,
+    /// The falloff of point and spot lights over distance.
+    #[serde(default)]
+    pub attenuation: LitAttenuation,
+    /// The shadow cascades, which only takes effects on directional lights.
+    pub cascades: ShadowCascades,
+    /// Indicates whether the lighting of this light is baked into lightmaps already, so
+    /// it only lights the meshes without lightmaps.
+    #[serde(default)]
+    pub baked: bool,
+    /// The texture projected by spot lights, which tints and masks the light in its
+    /// cone. It's not saved into scene snapshots.
+    #[serde(skip)]
+    pub cookie: Option<TextureHandle>,
 
     #[doc(hidden)]
+    #[serde(skip)]
     pub(crate) transform: Transform,
 }
 
 /// Enumeration for all light sources.
-#[derive(Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub enum LitSource {
     /// A direcitonal light.
     Dir,
@@ -34,6 +58,59 @@ pub enum LitSource {
         /// Smoothness of the light-to-dark transition from the center to the radius.
         smoothness: f32,
     },
+    /// A spot light, which lights the cone along its forward direction.
+    Spot {
+        /// Maximum raidus of the spot light's affected data.
+        radius: f32,
+        /// Smoothness of the light-to-dark transition from the center to the radius.
+        smoothness: f32,
+        /// The angle of the cone in degrees.
+        angle: f32,
+        /// The angle of the inner cone in degrees, the light fades out from the inner
+        /// cone to the outer one.
+        inner_angle: f32,
+    },
+}
+
+/// The units of the intensity of lights.
+///
+/// With physical units and `LitAttenuation::InverseSquare`, the same lights produce the
+/// same illuminance in different scenes, and the `exposure` of `Camera` maps them into
+/// the displayable range.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LitUnits {
+    /// The intensity scales the color of light directly.
+    Scalar,
+    /// The luminous power in lumens, which spreads over the whole sphere around point
+    /// lights, and over the cone of spot lights. It's the same as `Lux` for directional
+    /// lights.
+    Lumen,
+    /// The illuminance in lux. For point and spot lights, it's the illuminance at one
+    /// unit of distance, which is the luminous intensity in candelas.
+    Lux,
+}
+
+impl Default for LitUnits {
+    fn default() -> Self {
+        LitUnits::Scalar
+    }
+}
+
+/// The falloffs of point and spot lights over distance.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LitAttenuation {
+    /// The falloff decreases linearly, and quadratically with `smoothness`, and reaches
+    /// zero at `radius`.
+    Linear,
+    /// The physically based inverse-square falloff, which is windowed to reach zero at
+    /// `radius` smoothly. The `smoothness` is ignored.
+    InverseSquare,
+}
+
+impl Default for LitAttenuation {
+    fn default() -> Self {
+        LitAttenuation::Linear
+    }
 }
 
 impl Default for Lit {
@@ -43,8 +120,104 @@ impl Default for Lit {
             shadow_caster: false,
             color: Color::white(),
             intensity: 1.0,
+            units: LitUnits::Scalar,
             source: LitSource::Dir,
+            attenuation: LitAttenuation::Linear,
+            cascades: ShadowCascades::default(),
+            baked: false,
+            cookie: None,
             transform: Transform::default(),
         }
     }
 }
+
+impl Lit {
+    /// Gets the color of light scaled by its `intensity` and the `exposure` of camera,
+    /// which is used by shaders directly.
+    pub fn radiance(&self, exposure: f32) -> Vector3<f32> {
+        let scale = match (self.units, self.source) {
+            (LitUnits::Lumen, LitSource::Point { .. }) => 0.25 / ::std::f32::consts::PI,
+            (LitUnits::Lumen, LitSource::Spot { angle, .. }) => {
+                // The solid angle of cone.
+                let cos = (angle.max(0.0).min(360.0) * 0.5).to_radians().cos();
+                0.5 / (::std::f32::consts::PI * (1.0 - cos).max(0.0001))
+            }
+            _ => 1.0,
+        };
+
+        let intensity = self.intensity * scale * exposure;
+        Vector3::new(self.color.r, self.color.g, self.color.b) * intensity
+    }
+
+    /// Gets the parameters of attenuation in shaders. The `xyz` components are the
+    /// constant, linear and quadratic coefficients of `LitAttenuation::Linear`, and the
+    /// `w` component is the radius of `LitAttenuation::InverseSquare`.
+    pub(crate) fn attenuation_params(&self) -> Vector4<f32> {
+        let (radius, smoothness) = match self.source {
+            LitSource::Dir => return Vector4::new(1.0, 0.0, 0.0, 0.0),
+            LitSource::Point { radius, smoothness } => (radius, smoothness),
+            LitSource::Spot {
+                radius, smoothness, ..
+            } => (radius, smoothness),
+        };
+
+        match self.attenuation {
+            LitAttenuation::Linear => Vector4::new(
+                1.0,
+                -1.0 / (radius + smoothness * radius * radius),
+                -smoothness / (radius + smoothness * radius * radius),
+                0.0,
+            ),
+            LitAttenuation::InverseSquare => Vector4::new(0.0, 0.0, 0.0, radius.max(0.0001)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn units() {
+        let mut lit = Lit::default();
+        lit.color = Color::new(1.0, 0.5, 0.0, 1.0);
+        lit.intensity = 4.0 * ::std::f32::consts::PI;
+        assert_eq!(
+            lit.radiance(0.5),
+            Vector3::new(2.0 * ::std::f32::consts::PI, ::std::f32::consts::PI, 0.0)
+        );
+
+        // The luminous power spreads over the sphere around point lights.
+        lit.units = LitUnits::Lumen;
+        lit.source = LitSource::Point {
+            radius: 10.0,
+            smoothness: 0.0,
+        };
+
+        let v = lit.radiance(1.0);
+        assert!((v.x - 1.0).abs() < 0.0001 && (v.y - 0.5).abs() < 0.0001);
+
+        // The luminous power spreads over the cone of spot lights, whose solid angle is
+        // a quarter of sphere with 120 degrees.
+        lit.source = LitSource::Spot {
+            radius: 10.0,
+            smoothness: 0.0,
+            angle: 120.0,
+            inner_angle: 60.0,
+        };
+
+        let v = lit.radiance(1.0);
+        assert!((v.x - 4.0).abs() < 0.0001 && (v.y - 2.0).abs() < 0.0001);
+        lit.source = LitSource::Point {
+            radius: 10.0,
+            smoothness: 0.0,
+        };
+
+        lit.units = LitUnits::Lux;
+        assert_eq!(lit.radiance(1.0).x, lit.intensity);
+
+        assert_eq!(lit.attenuation_params(), Vector4::new(1.0, -0.1, 0.0, 0.0));
+        lit.attenuation = LitAttenuation::InverseSquare;
+        assert_eq!(lit.attenuation_params(), Vector4::new(0.0, 0.0, 0.0, 10.0));
+    }
+}
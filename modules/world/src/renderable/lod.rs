@@ -0,0 +1,192 @@
+use crayon::math::prelude::*;
+use crayon::video::prelude::*;
+
+use assets::prelude::MaterialHandle;
+use spatial::prelude::Transform;
+use Entity;
+
+use super::camera::Camera;
+
+/// A level of detail in `LodGroup`.
+#[derive(Debug, Clone, Copy)]
+pub struct LodLevel {
+    /// The mesh drawn at this level.
+    pub mesh: MeshHandle,
+    /// The material drawn at this level, the one of `MeshRenderer` is used if it's none.
+    pub material: Option<MaterialHandle>,
+    /// The minimum screen size that this level is drawn, which is the projected height of
+    /// bounding sphere relative to the height of screen.
+    pub screen_size: f32,
+
+    #[doc(hidden)]
+    pub(crate) bounds: Option<(MeshHandle, Aabb3<f32>)>,
+}
+
+impl LodLevel {
+    pub fn new<T>(mesh: MeshHandle, material: T, screen_size: f32) -> Self
+    where
+        T: Into<Option<MaterialHandle>>,
+    {
+        LodLevel {
+            mesh: mesh,
+            material: material.into(),
+            screen_size: screen_size,
+            bounds: None,
+        }
+    }
+}
+
+/// `LodGroup` replaces the mesh and material of `MeshRenderer` on the same entity with
+/// the ones of a level, which is picked per camera based on the projected size of its
+/// bounding sphere. The mesh is not drawn if the size is smaller than all the levels.
+///
+/// ```rust,ignore
+/// let mut lods = LodGroup::new();
+/// lods.add(LodLevel::new(high, None, 0.5));
+/// lods.add(LodLevel::new(medium, None, 0.2));
+/// lods.add(LodLevel::new(low, None, 0.05));
+/// scene.add_mesh(ent, high);
+/// scene.add_lod_group(ent, lods);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LodGroup {
+    /// The levels sorted from the most detailed one to the least, whose screen sizes
+    /// should be decreasing.
+    pub levels: Vec<LodLevel>,
+    /// The fraction of screen size that the size has to go beyond the range of current
+    /// level before switching to others, which avoids popping when the size hovers around
+    /// the threshold. Defaults to 0.1.
+    pub hysteresis: f32,
+
+    // The picked levels of cameras.
+    current: Vec<(Entity, usize)>,
+}
+
+impl Default for LodGroup {
+    fn default() -> Self {
+        LodGroup {
+            levels: Vec::new(),
+            hysteresis: 0.1,
+            current: Vec::new(),
+        }
+    }
+}
+
+impl LodGroup {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a level, which should be less detailed than the ones added before.
+    #[inline]
+    pub fn add(&mut self, level: LodLevel) {
+        self.levels.push(level);
+    }
+
+    /// Gets the level picked by camera in last frame.
+    pub fn level(&self, camera: Entity) -> Option<usize> {
+        self.current
+            .iter()
+            .find(|v| v.0 == camera)
+            .map(|v| v.1)
+            .filter(|&v| v < self.levels.len())
+    }
+
+    /// Forgets the levels picked by the cameras that do not exist anymore.
+    pub(crate) fn retain_cameras<F: Fn(Entity) -> bool>(&mut self, exists: F) {
+        self.current.retain(|v| exists(v.0));
+    }
+
+    /// Updates the bounding boxes of the meshes of levels.
+    pub(crate) fn update_bounds(&mut self) {
+        for v in &mut self.levels {
+            if v.bounds.map(|(handle, _)| handle != v.mesh).unwrap_or(true) {
+                v.bounds = ::crayon::video::mesh(v.mesh).map(|params| (v.mesh, params.aabb));
+            }
+        }
+    }
+
+    /// Picks the level drawn by camera, the bounding box of the most detailed level is
+    /// used to measure the screen size.
+    pub(crate) fn pick(
+        &mut self,
+        ent: Entity,
+        camera: &Camera,
+        transform: &Transform,
+    ) -> Option<usize> {
+        let size = match self.levels.first().and_then(|v| v.bounds) {
+            Some((_, aabb)) => screen_size(camera, transform, aabb),
+            None => 1.0,
+        };
+
+        let current = self.level(ent);
+        let level = self.select(size, current);
+
+        self.current.retain(|v| v.0 != ent);
+        if let Some(level) = level {
+            self.current.push((ent, level));
+        }
+
+        level
+    }
+
+    fn select(&self, size: f32, current: Option<usize>) -> Option<usize> {
+        if let Some(c) = current {
+            let upper = if c == 0 {
+                ::std::f32::MAX
+            } else {
+                self.levels[c - 1].screen_size * (1.0 + self.hysteresis)
+            };
+
+            let lower = self.levels[c].screen_size * (1.0 - self.hysteresis);
+            if size >= lower && size < upper {
+                return Some(c);
+            }
+        }
+
+        self.levels.iter().position(|v| size >= v.screen_size)
+    }
+}
+
+/// Gets the projected height of the bounding sphere of `aabb` relative to the height of
+/// screen.
+fn screen_size(camera: &Camera, transform: &Transform, aabb: Aabb3<f32>) -> f32 {
+    let center = transform.transform_point(aabb.center().to_vec());
+    let radius = aabb.dim().magnitude() * 0.5 * transform.scale;
+
+    match camera.projection() {
+        Projection::Ortho { height, .. } => radius * 2.0 / height,
+        Projection::Perspective { fovy, .. } => {
+            let distance = (center - camera.transform.position).magnitude();
+            if distance <= radius {
+                return ::std::f32::MAX;
+            }
+
+            radius / (distance * Rad::tan(fovy * 0.5))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn select() {
+        let mut lods = LodGroup::new();
+        lods.add(LodLevel::new(MeshHandle::default(), None, 0.5));
+        lods.add(LodLevel::new(MeshHandle::default(), None, 0.2));
+
+        assert_eq!(lods.select(0.8, None), Some(0));
+        assert_eq!(lods.select(0.3, None), Some(1));
+        assert_eq!(lods.select(0.1, None), None);
+
+        // The current level is kept around the thresholds.
+        assert_eq!(lods.select(0.48, Some(0)), Some(0));
+        assert_eq!(lods.select(0.52, Some(1)), Some(1));
+        assert_eq!(lods.select(0.19, Some(1)), Some(1));
+        assert_eq!(lods.select(0.44, Some(0)), Some(1));
+        assert_eq!(lods.select(0.56, Some(1)), Some(0));
+        assert_eq!(lods.select(0.17, Some(1)), None);
+    }
+}
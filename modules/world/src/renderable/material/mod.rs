@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use crayon::prelude::*;
+use crayon::utils::handle::HandleLike;
+use failure::Error;
+
+use assets::prelude::{MaterialHandle, MaterialParam};
+use utils::prelude::Component;
+use Entity;
+
+use super::fog::Fog;
+use super::queue::{DrawOrder, RenderQueue};
+use super::{Camera, Lit, MeshRenderer};
+
+/// A renderer that draws meshes with `Material` assets.
+///
+/// The shaders are registered by names, and the materials refer to them with the same
+/// names. The material of mesh is the one added into this renderer, or the `material`
+/// of `MeshRenderer` if there is none. Meshes are skipped until both their materials
+/// and shaders are ready.
+///
+/// Besides the parameters of material, the following uniform variables are set if they
+/// are declared by shader: `u_ModelMatrix`, `u_ViewMatrix`, `u_ProjectionMatrix`,
+/// `u_ModelViewMatrix`, `u_MVPMatrix` and `u_ViewNormalMatrix`. And the `Lightmap` of mesh
+/// is set as `u_LightmapTexture`, `u_LightmapScaleOffset` and `u_LightmapIntensity`. The
+/// `Fog` of scene is set as `u_Fog`, which could be applied with the shader chunk
+/// `FOG_FS`. The `MaterialPropertyBlock` of mesh overrides all of them.
+///
+/// The vertex colors are optional if the attributes of shader are populated by reflection,
+/// so shaders could read `Color0` from meshes without colors, which is white then.
+pub struct MaterialRenderer {
+    materials: Component<MaterialHandle>,
+    shaders: HashMap<String, MaterialShader>,
+
+    surface: SurfaceHandle,
+    drawcalls: DrawCommandBuffer<DrawOrder>,
+}
+
+struct MaterialShader {
+    params: ShaderParams,
+    vs: String,
+    fs: String,
+    // The shader objects of different render states, the first one uses the render
+    // state of `params`.
+    variants: Vec<(RenderState, ShaderHandle)>,
+}
+
+impl Drop for MaterialShader {
+    fn drop(&mut self) {
+        for &(_, v) in &self.variants {
+            video::delete_shader(v);
+        }
+    }
+}
+
+impl MaterialShader {
+    fn variant(&mut self, state: RenderState) -> Result<ShaderHandle, Error> {
+        if let Some(&(_, v)) = self.variants.iter().find(|v| v.0 == state) {
+            return Ok(v);
+        }
+
+        let mut params = self.params.clone();
+        params.state = state;
+
+        let shader = video::create_shader(params, self.vs.clone(), self.fs.clone())?;
+        self.variants.push((state, shader));
+        Ok(shader)
+    }
+}
+
+impl Drop for MaterialRenderer {
+    fn drop(&mut self) {
+        video::delete_surface(self.surface);
+    }
+}
+
+impl MaterialRenderer {
+    /// Creates a new `MaterialRenderer`.
+    pub fn new() -> Result<Self, Error> {
+        let params = SurfaceParams::default();
+        let surface = video::create_surface(params)?;
+
+        Ok(MaterialRenderer {
+            materials: Component::new(),
+            shaders: HashMap::new(),
+            surface: surface,
+            drawcalls: DrawCommandBuffer::new(),
+        })
+    }
+
+    /// Registers a shader with name, which replaces the one with the same name. The
+    /// shader objects with other render states are created when materials override
+    /// the render state.
+    pub fn register_shader<T: Into<String>>(
+        &mut self,
+        name: T,
+        params: ShaderParams,
+        vs: String,
+        fs: String,
+    ) -> Result<(), Error> {
+        let state = params.state;
+        let mut shader = MaterialShader {
+            params: params,
+            vs: vs,
+            fs: fs,
+            variants: Vec::new(),
+        };
+
+        shader.variant(state)?;
+        self.shaders.insert(name.into(), shader);
+        Ok(())
+    }
+
+    /// Removes the shader with name.
+    #[inline]
+    pub fn unregister_shader<T: AsRef<str>>(&mut self, name: T) {
+        self.shaders.remove(name.as_ref());
+    }
+
+    /// Checks if there is a shader registered with name.
+    #[inline]
+    pub fn has_shader<T: AsRef<str>>(&self, name: T) -> bool {
+        self.shaders.contains_key(name.as_ref())
+    }
+
+    #[inline]
+    pub fn add(&mut self, ent: Entity, material: MaterialHandle) -> Option<MaterialHandle> {
+        self.materials.add(ent, material)
+    }
+
+    #[inline]
+    pub fn has(&self, ent: Entity) -> bool {
+        self.materials.has(ent)
+    }
+
+    #[inline]
+    pub fn material(&self, ent: Entity) -> Option<&MaterialHandle> {
+        self.materials.get(ent)
+    }
+
+    #[inline]
+    pub fn material_mut(&mut self, ent: Entity) -> Option<&mut MaterialHandle> {
+        self.materials.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove(&mut self, ent: Entity) {
+        self.materials.remove(ent)
+    }
+}
+
+impl super::Renderer for MaterialRenderer {
+    type Mtl = MaterialHandle;
+
+    fn add_mtl(&mut self, ent: Entity, mtl: Self::Mtl) {
+        self.add(ent, mtl);
+    }
+
+    fn mtl(&self, ent: Entity) -> Option<&Self::Mtl> {
+        self.material(ent)
+    }
+
+    fn mtl_mut(&mut self, ent: Entity) -> Option<&mut Self::Mtl> {
+        self.material_mut(ent)
+    }
+
+    fn remove_mtl(&mut self, ent: Entity) {
+        self.remove(ent);
+    }
+
+    fn queue(&self, mesh: &MeshRenderer) -> RenderQueue {
+        self.materials
+            .get(mesh.ent)
+            .cloned()
+            .or(mesh.material)
+            .and_then(crate::material)
+            .map(|v| v.queue)
+            .unwrap_or_default()
+    }
+
+    fn submit(&mut self, camera: &Camera, _: &[Lit], meshes: &[&MeshRenderer]) {
+        use crayon::math::prelude::{Matrix, MetricSpace, SquareMatrix};
+
+        let view_matrix = camera.transform.view_matrix();
+        let projection_matrix = camera.projection_matrix();
+
+        for mesh in meshes {
+            let handle = match self.materials.get(mesh.ent).cloned().or(mesh.material) {
+                Some(handle) => handle,
+                None => continue,
+            };
+
+            let material = match crate::material(handle) {
+                Some(material) => material,
+                None => continue,
+            };
+
+            let (params, shader) = match self.shaders.get_mut(&material.shader) {
+                Some(v) => {
+                    let state = material.render_state.unwrap_or(v.params.state);
+                    match v.variant(state) {
+                        Ok(shader) => (&v.params, shader),
+                        Err(err) => {
+                            warn!("[MaterialRenderer] {}", err);
+                            continue;
+                        }
+                    }
+                }
+                None => continue,
+            };
+
+            let model_matrix = mesh.transform.matrix();
+            let mv = view_matrix * model_matrix;
+            let mvp = projection_matrix * mv;
+            let vn = mv.invert().and_then(|v| Some(v.transpose())).unwrap_or(mv);
+
+            let mut dc = Draw::new(shader, mesh.mesh);
+            let builtins = [
+                ("u_ModelMatrix", model_matrix),
+                ("u_ViewMatrix", view_matrix),
+                ("u_ProjectionMatrix", projection_matrix),
+                ("u_ModelViewMatrix", mv),
+                ("u_MVPMatrix", mvp),
+                ("u_ViewNormalMatrix", vn),
+            ];
+
+            for &(name, v) in &builtins {
+                if params.uniforms.variable_type(name) == Some(UniformVariableType::Matrix4f) {
+                    dc.set_uniform_variable(name, v);
+                }
+            }
+
+            if let Some(lightmap) = mesh.lightmap {
+                let builtins: [(&str, UniformVariable); 3] = [
+                    ("u_LightmapTexture", lightmap.texture.into()),
+                    ("u_LightmapScaleOffset", lightmap.scale_offset.into()),
+                    ("u_LightmapIntensity", lightmap.intensity.into()),
+                ];
+
+                for &(name, v) in &builtins {
+                    if params.uniforms.variable_type(name) == Some(v.variable_type()) {
+                        dc.set_uniform_variable(name, v);
+                    }
+                }
+            }
+
+            if params.uniforms.variable_type("u_Fog") == Some(UniformVariableType::Matrix3f) {
+                dc.set_uniform_variable("u_Fog", Fog::uniform(camera.fog.as_ref()));
+            }
+
+            // Parameters which are not declared by shader are ignored.
+            for (name, &v) in &material.params {
+                let variable: UniformVariable = match v {
+                    MaterialParam::F32(v) => v.into(),
+                    MaterialParam::Vector2f(v) => v.into(),
+                    MaterialParam::Vector3f(v) => v.into(),
+                    MaterialParam::Vector4f(v) => v.into(),
+                    MaterialParam::Matrix4f(v) => Matrix4::from(v).into(),
+                    MaterialParam::Texture(index) => match material.textures.get(index) {
+                        Some(&texture) => texture.into(),
+                        None => continue,
+                    },
+                };
+
+                let name = name.as_str();
+                if params.uniforms.variable_type(name) == Some(variable.variable_type()) {
+                    dc.set_uniform_variable(name, variable);
+                }
+            }
+
+            mesh.properties.apply(&params.uniforms, &mut dc);
+
+            let distance = mesh.transform.position.distance(camera.transform.position);
+            let order = DrawOrder::build(material.queue, distance)
+                .with_material(handle.index())
+                .with_shader(shader)
+                .finish()
+                .into();
+
+            self.drawcalls.draw(order, dc);
+        }
+
+        let surface = camera.surface().unwrap_or(self.surface);
+        self.drawcalls.submit(surface).unwrap();
+    }
+}
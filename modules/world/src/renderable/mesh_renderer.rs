@@ -1,23 +1,105 @@
+use crayon::math::prelude::{Aabb3, Frustum, Matrix4, PlaneRelation, Vector4};
 use crayon::video::prelude::*;
 
+use assets::prelude::MaterialHandle;
 use spatial::prelude::Transform;
+use tags::DEFAULT_LAYERS;
 use Entity;
 
-#[derive(Debug, Clone, Copy)]
+use super::property_block::MaterialPropertyBlock;
+
+#[derive(Debug, Clone)]
 pub struct MeshRenderer {
     /// The mesh handle used by the renderer.
     pub mesh: MeshHandle,
+    /// The material asset, which is used by `MaterialRenderer`.
+    pub material: Option<MaterialHandle>,
     /// Indicates whether this object cast shadows.
     pub shadow_caster: bool,
     /// Indicates whether this object receive shadows.
     pub shadow_receiver: bool,
     /// Is this renderer visible.
     pub visible: bool,
+    /// Indicates whether this object could be merged into static batches.
+    pub static_batching: bool,
+    /// The pre-baked lightmap, which is sampled with the second set of texture coordinates.
+    pub lightmap: Option<Lightmap>,
+    /// The uniform variables that override the ones of material. The meshes with
+    /// properties are never merged into static batches.
+    pub properties: MaterialPropertyBlock,
 
     #[doc(hidden)]
     pub(crate) transform: Transform,
     #[doc(hidden)]
+    pub(crate) layers: u32,
+    #[doc(hidden)]
     pub(crate) ent: Entity,
+    #[doc(hidden)]
+    pub(crate) skinning: Vec<Matrix4<f32>>,
+    #[doc(hidden)]
+    pub(crate) bounds: Option<(MeshHandle, Aabb3<f32>)>,
+    #[doc(hidden)]
+    pub(crate) batched: bool,
+}
+
+/// The region of mesh in pre-baked lightmap, which holds the static lighting that is
+/// mixed with realtime lights. The meshes with lightmaps are never merged into static
+/// batches, since their regions are different.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lightmap {
+    pub texture: TextureHandle,
+    /// The scale (xy) and offset (zw) that transform the second set of texture coordinates
+    /// of mesh into the region in texture.
+    pub scale_offset: Vector4<f32>,
+    pub intensity: f32,
+}
+
+impl From<TextureHandle> for Lightmap {
+    fn from(texture: TextureHandle) -> Self {
+        Lightmap {
+            texture: texture,
+            scale_offset: Vector4::new(1.0, 1.0, 0.0, 0.0),
+            intensity: 1.0,
+        }
+    }
+}
+
+impl MeshRenderer {
+    /// Gets the skinning matrices evaluated by the `Animator` on the same entity, which
+    /// is empty if this mesh is not animated.
+    #[inline]
+    pub fn skinning_matrices(&self) -> &[Matrix4<f32>] {
+        &self.skinning
+    }
+
+    /// Gets the bounding box of mesh in world space, if it's valid.
+    pub(crate) fn world_bounds(&self) -> Option<Aabb3<f32>> {
+        match self.bounds {
+            Some((_, aabb)) if self.is_bounded() => Some(aabb.transform(&self.transform.matrix())),
+            _ => None,
+        }
+    }
+
+    /// Checks if the mesh has a valid bounding box, which is kept in the bounding volume
+    /// hierarchy.
+    pub(crate) fn is_bounded(&self) -> bool {
+        match self.bounds {
+            Some((handle, aabb)) => handle == self.mesh && aabb.min != aabb.max,
+            None => false,
+        }
+    }
+
+    /// Checks if the bounding box of mesh is outside of the view frustum. Meshes without
+    /// valid bounding boxes are never culled.
+    pub(crate) fn is_culled(&self, view_matrix: &Matrix4<f32>, frustum: &Frustum<f32>) -> bool {
+        match self.bounds {
+            Some((_, aabb)) if aabb.min != aabb.max => {
+                let aabb = aabb.transform(&(view_matrix * self.transform.matrix()));
+                frustum.contains(&aabb) == PlaneRelation::Out
+            }
+            _ => false,
+        }
+    }
 }
 
 impl From<MeshHandle> for MeshRenderer {
@@ -33,11 +115,19 @@ impl Default for MeshRenderer {
     fn default() -> Self {
         MeshRenderer {
             mesh: MeshHandle::default(),
+            material: None,
             shadow_caster: false,
             shadow_receiver: false,
             visible: true,
+            static_batching: true,
+            lightmap: None,
+            properties: MaterialPropertyBlock::default(),
             transform: Transform::default(),
+            layers: DEFAULT_LAYERS,
             ent: Entity::default(),
+            skinning: Vec::new(),
+            bounds: None,
+            batched: false,
         }
     }
 }
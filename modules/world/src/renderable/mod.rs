@@ -1,25 +1,65 @@
 mod camera;
+mod deferred;
+mod fog;
 mod lit;
+mod lod;
+mod material;
 mod mesh_renderer;
+mod pbr;
+mod postprocessing;
+mod property_block;
+mod queue;
+mod shadow;
 mod simple;
+mod skinning;
+mod skybox;
+mod static_batch;
 
 pub mod headless;
 
 pub mod prelude {
-    pub use super::camera::Camera;
-    pub use super::lit::{Lit, LitSource};
-    pub use super::mesh_renderer::MeshRenderer;
-    pub use super::simple::{SimpleMaterial, SimpleRenderer};
-    pub use super::{Renderable, Renderer};
+    pub use super::camera::{Camera, ColorControls};
+    pub use super::deferred::DeferredRenderer;
+    pub use super::fog::{Fog, FogMode, FOG_FS};
+    pub use super::lit::{Lit, LitAttenuation, LitSource, LitUnits};
+    pub use super::lod::{LodGroup, LodLevel};
+    pub use super::material::MaterialRenderer;
+    pub use super::mesh_renderer::{Lightmap, MeshRenderer};
+    pub use super::pbr::{PbrEnvironment, PbrMaterial, PbrRenderer};
+    pub use super::postprocessing::{create_fullscreen_shader, PostEffect, PostProcessing};
+    pub use super::postprocessing::{Bloom, ColorGrading, Fxaa, Tonemapper, Tonemapping, Vignette};
+    pub use super::property_block::MaterialPropertyBlock;
+    pub use super::queue::RenderQueue;
+    pub use super::shadow::{CascadeSplits, ShadowCascades, MAX_SHADOW_CASCADES};
+    pub use super::shadow::{Shadow, ShadowBuilder, MAX_PCF_RADIUS};
+    pub use super::simple::{DebugView, SimpleMaterial, SimpleRenderer};
+    pub use super::skinning::{SkinningMode, MAX_BONES, SKINNING_VS};
+    pub use super::skybox::{decode_hdr, Skybox};
+    pub use super::{FrameInfo, Renderable, Renderer};
 }
 
-use spatial::prelude::SceneGraph;
-use utils::prelude::Component;
+use std::any::Any;
+
+use crayon::math::prelude::{Aabb2, Aabb3, EuclideanSpace, Vector3};
+use crayon::utils::hash::FastHashMap;
+use crayon::video;
+use crayon::video::assets::surface::{SurfaceHandle, SurfaceParams};
+use crayon::video::assets::texture::TextureHandle;
+use failure::Error;
+
+use spatial::prelude::{Bvh, SceneGraph};
+use tags::Tags;
+use utils::dispatcher::StoragePtrs;
+use utils::prelude::{Component, Filter};
 use Entity;
 
 use self::camera::Camera;
+use self::fog::Fog;
 use self::lit::{Lit, LitSource};
+use self::lod::LodGroup;
 use self::mesh_renderer::MeshRenderer;
+use self::queue::RenderQueue;
+use self::skybox::Skybox;
 
 pub trait Renderer {
     type Mtl;
@@ -29,13 +69,94 @@ pub trait Renderer {
     fn mtl_mut(&mut self, ent: Entity) -> Option<&mut Self::Mtl>;
     fn remove_mtl(&mut self, ent: Entity);
 
-    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[MeshRenderer]);
+    /// Gets the queue that mesh is drawn in, which is decided by its material.
+    fn queue(&self, _mesh: &MeshRenderer) -> RenderQueue {
+        RenderQueue::Opaque
+    }
+
+    /// Submits the visible meshes of camera. Meshes outside of the view frustum have
+    /// been culled already. The meshes in transparent queues are submitted in another
+    /// call after the opaque ones.
+    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[&MeshRenderer]);
+
+    /// Submits the shadow casters before the visible meshes of camera. The casters are not
+    /// culled by camera, since they might cast shadows into the view frustum from outside.
+    /// But only the ones in the ranges of point and spot lights are gathered if there is
+    /// no directional light casting shadows.
+    fn submit_shadows(
+        &mut self,
+        _camera: &Camera,
+        _lits: &[Lit],
+        _casters: &[&MeshRenderer],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// The shader chunk of `EncodeSRGB`, which encodes the linear colors into sRGB space if
+/// the uniform `u_EncodeSRGB` is positive. The built-in renderers shade in linear space,
+/// and encode the colors with it before writing into surface. And the colors of textures
+/// that are not decoded by hardware are decoded with `DecodeSRGB`.
+pub(crate) const SRGB_FS: &str = include_str!("shaders/srgb.fs");
+
+/// Gets the value of `u_EncodeSRGB` when drawing into `surface`.
+pub(crate) fn srgb_encoding(surface: SurfaceHandle) -> f32 {
+    if video::requires_srgb_encoding(surface) {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Gets the flag of `DecodeSRGB` when sampling the color `texture`.
+pub(crate) fn srgb_decoding(texture: TextureHandle) -> f32 {
+    if video::requires_srgb_decoding(texture) {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// The statistics of renderables during last frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// The number of mesh renderers submitted to renderer, accumulated by cameras.
+    pub submitted: u32,
+    /// The number of mesh renderers culled by view frustums, accumulated by cameras.
+    pub culled: u32,
 }
 
 pub struct Renderable {
     cameras: Component<Camera>,
     lits: Component<Lit>,
     meshes: Component<MeshRenderer>,
+    lods: Component<LodGroup>,
+    skyboxes: Component<Skybox>,
+    culling: bool,
+    fog: Option<Fog>,
+    info: FrameInfo,
+    batches: Vec<MeshRenderer>,
+    // The bounding volume hierarchies of dynamic and static mesh renderers in world space.
+    // The static meshes rarely move, so their hierarchy is seldom re-built by the moves of
+    // dynamic ones.
+    bvhs: [Bvh<Entity>; 2],
+    // The meshes kept out of hierarchies, and the buffer of meshes returned by culling.
+    unbounded: Vec<Entity>,
+    visibles: Vec<Entity>,
+    // The surfaces of cameras with viewports, and the indices of cameras sorted by their
+    // orders.
+    viewports: FastHashMap<Entity, (Aabb2<f32>, SurfaceHandle)>,
+    ordered: Vec<usize>,
+}
+
+impl Drop for Renderable {
+    fn drop(&mut self) {
+        self.clear_static_batches();
+
+        for (_, (_, surface)) in self.viewports.drain() {
+            video::delete_surface(surface);
+        }
+    }
 }
 
 impl Renderable {
@@ -44,9 +165,101 @@ impl Renderable {
             cameras: Component::new(),
             lits: Component::new(),
             meshes: Component::new(),
+            lods: Component::new(),
+            skyboxes: Component::new(),
+            culling: true,
+            fog: None,
+            info: FrameInfo::default(),
+            batches: Vec::new(),
+            bvhs: [Bvh::new(), Bvh::new()],
+            unbounded: Vec::new(),
+            visibles: Vec::new(),
+            viewports: FastHashMap::default(),
+            ordered: Vec::new(),
         }
     }
 
+    /// Enables or disables the frustum culling of meshes, its enabled by default.
+    #[inline]
+    pub fn set_culling(&mut self, enable: bool) {
+        self.culling = enable;
+    }
+
+    /// Sets the distance fog of scene, which is applied by the built-in renderers.
+    #[inline]
+    pub fn set_fog<T: Into<Option<Fog>>>(&mut self, fog: T) {
+        self.fog = fog.into();
+    }
+
+    /// Gets the distance fog of scene.
+    #[inline]
+    pub fn fog(&self) -> Option<Fog> {
+        self.fog
+    }
+
+    /// Gets the statistics during last frame.
+    #[inline]
+    pub fn frame_info(&self) -> FrameInfo {
+        self.info
+    }
+
+    /// Sets the tick that the following changes of components are stamped with.
+    pub(crate) fn set_tick(&mut self, tick: u32) {
+        self.cameras.set_tick(tick);
+        self.lits.set_tick(tick);
+        self.meshes.set_tick(tick);
+        self.lods.set_tick(tick);
+        self.skyboxes.set_tick(tick);
+    }
+
+    /// Gets the entities whose components pass the filter `F` since the tick, if the
+    /// type of components is stored in renderables.
+    pub(crate) fn filter<F: Filter>(&self, since: u32) -> Option<Vec<Entity>> {
+        let storages: [&dyn Any; 5] = [
+            &self.cameras,
+            &self.lits,
+            &self.meshes,
+            &self.lods,
+            &self.skyboxes,
+        ];
+        storages
+            .iter()
+            .filter_map(|v| v.downcast_ref::<Component<F::Component>>())
+            .map(|v| v.filter::<F>(since).collect())
+            .next()
+    }
+
+    /// Exposes the storages of components to the systems of `Dispatcher`.
+    pub(crate) fn expose(&mut self, ptrs: &mut StoragePtrs) {
+        ptrs.insert(&mut self.cameras);
+        ptrs.insert(&mut self.lits);
+        ptrs.insert(&mut self.meshes);
+        ptrs.insert(&mut self.lods);
+        ptrs.insert(&mut self.skyboxes);
+    }
+
+    /// Gets the mesh renderers.
+    #[inline]
+    pub(crate) fn meshes(&self) -> &Component<MeshRenderer> {
+        &self.meshes
+    }
+
+    /// Gets the bounding volume hierarchies of dynamic and static mesh renderers, which
+    /// are updated in last `update_bounds`.
+    #[inline]
+    pub(crate) fn bvhs(&self) -> &[Bvh<Entity>] {
+        &self.bvhs
+    }
+
+    /// Gets the cameras sorted by their orders, whose transforms are updated in last
+    /// `draw`.
+    #[inline]
+    pub(crate) fn cameras<'a>(&'a self) -> impl Iterator<Item = &'a Camera> + 'a {
+        self.ordered
+            .iter()
+            .filter_map(move |&i| self.cameras.data.get(i))
+    }
+
     #[inline]
     pub fn add_camera(&mut self, ent: Entity, camera: Camera) {
         self.cameras.add(ent, camera);
@@ -65,6 +278,10 @@ impl Renderable {
     #[inline]
     pub fn remove_camera(&mut self, ent: Entity) {
         self.cameras.remove(ent);
+
+        if let Some((_, surface)) = self.viewports.remove(&ent) {
+            video::delete_surface(surface);
+        }
     }
 
     #[inline]
@@ -106,31 +323,348 @@ impl Renderable {
     pub fn remove_mesh(&mut self, ent: Entity) {
         self.meshes.remove(ent);
     }
+
+    /// Adds the level of details to the entity of mesh renderer.
+    #[inline]
+    pub fn add_lod_group(&mut self, ent: Entity, lods: LodGroup) {
+        self.lods.add(ent, lods);
+    }
+
+    #[inline]
+    pub fn lod_group(&self, ent: Entity) -> Option<&LodGroup> {
+        self.lods.get(ent)
+    }
+
+    #[inline]
+    pub fn lod_group_mut(&mut self, ent: Entity) -> Option<&mut LodGroup> {
+        self.lods.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_lod_group(&mut self, ent: Entity) {
+        self.lods.remove(ent);
+    }
+
+    /// Adds the skybox to the entity of camera, which is drawn as the background of it.
+    #[inline]
+    pub fn add_skybox(&mut self, ent: Entity, skybox: Skybox) {
+        self.skyboxes.add(ent, skybox);
+    }
+
+    #[inline]
+    pub fn skybox(&self, ent: Entity) -> Option<&Skybox> {
+        self.skyboxes.get(ent)
+    }
+
+    #[inline]
+    pub fn skybox_mut(&mut self, ent: Entity) -> Option<&mut Skybox> {
+        self.skyboxes.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_skybox(&mut self, ent: Entity) {
+        self.skyboxes.remove(ent);
+    }
 }
 
 impl Renderable {
-    pub fn draw<R: Renderer>(&mut self, renderer: &mut R, sg: &SceneGraph) {
-        for (i, v) in self.cameras.data.iter_mut().enumerate() {
-            if let Some(transform) = sg.transform(self.cameras.entities[i]) {
+    /// Updates the transforms and bounding boxes of lits and meshes in world space.
+    ///
+    /// The static meshes, which could be merged into static batches, and the dynamic ones
+    /// are kept in separate bounding volume hierarchies. Each one is re-built if its meshes
+    /// have been added or removed, or more than half of them have moved since last build.
+    /// Otherwise, only the moved ones are refitted.
+    pub fn update_bounds(&mut self, sg: &SceneGraph, tags: &Tags) {
+        for (i, v) in self.lits.data.iter_mut().enumerate() {
+            if let Some(transform) = sg.transform(self.lits.entities[i]) {
                 v.transform = transform;
             }
         }
 
-        for (i, v) in self.lits.data.iter_mut().enumerate() {
-            if let Some(transform) = sg.transform(self.lits.entities[i]) {
+        let mut rebuild = [false; 2];
+        let mut counts = [0; 2];
+        self.unbounded.clear();
+
+        for (i, v) in self.meshes.data.iter_mut().enumerate() {
+            let ent = self.meshes.entities[i];
+            let mut moved = false;
+
+            if let Some(transform) = sg.transform(ent) {
+                moved = transform != v.transform;
                 v.transform = transform;
             }
+
+            v.ent = ent;
+
+            v.layers = tags.layers(ent);
+
+            if v.bounds.map(|(handle, _)| handle != v.mesh).unwrap_or(true) {
+                v.bounds = crayon::video::mesh(v.mesh).map(|params| (v.mesh, params.aabb));
+                moved = true;
+            }
+
+            // Meshes without valid bounding boxes are kept out of hierarchies. The ones that
+            // are not in hierarchy yet fail to refit, and trigger a re-build.
+            match hierarchy(v, &self.lods) {
+                Some(index) => {
+                    counts[index] += 1;
+                    if moved && !rebuild[index] {
+                        if let Some(aabb) = v.world_bounds() {
+                            rebuild[index] = !self.bvhs[index].refit(ent, aabb);
+                        }
+                    }
+                }
+                None => {
+                    if !v.is_bounded() {
+                        self.unbounded.push(ent);
+                    }
+                }
+            }
         }
 
-        for (i, v) in self.meshes.data.iter_mut().enumerate() {
-            if let Some(transform) = sg.transform(self.meshes.entities[i]) {
+        for v in &mut self.lods.data {
+            v.update_bounds();
+        }
+
+        for (index, bvh) in self.bvhs.iter_mut().enumerate() {
+            if rebuild[index] || counts[index] != bvh.len() || bvh.refits() > bvh.len() / 2 {
+                let lods = &self.lods;
+                let bounds = self
+                    .meshes
+                    .data
+                    .iter()
+                    .filter(|v| hierarchy(v, lods) == Some(index))
+                    .filter_map(|v| v.world_bounds().map(|aabb| (v.ent, aabb)));
+
+                bvh.build(bounds);
+            }
+        }
+    }
+
+    pub fn draw<R: Renderer>(&mut self, renderer: &mut R, sg: &SceneGraph, tags: &Tags) {
+        for (i, v) in self.cameras.data.iter_mut().enumerate() {
+            let ent = self.cameras.entities[i];
+            if let Some(transform) = sg.transform(ent) {
                 v.transform = transform;
-                v.ent = self.meshes.entities[i];
+            }
+
+            v.environment = self.skyboxes.get(ent).and_then(|v| v.environment);
+            v.fog = self.fog;
+            v.viewport_surface = match v.viewport() {
+                Some(rect) => viewport_surface(&mut self.viewports, ent, rect),
+                None => {
+                    if let Some((_, surface)) = self.viewports.remove(&ent) {
+                        video::delete_surface(surface);
+                    }
+
+                    None
+                }
+            };
+        }
+
+        // The cameras with the same order are drawn in the order they are added. The
+        // indices are re-sorted only if cameras have been added, removed or re-ordered.
+        {
+            let cameras = &self.cameras.data;
+            let key = |i: usize| (cameras[i].order(), i);
+
+            if self.ordered.len() != cameras.len() {
+                self.ordered.clear();
+                self.ordered.extend(0..cameras.len());
+                self.ordered.sort_by_key(|&i| key(i));
+            } else if self.ordered.windows(2).any(|v| key(v[0]) > key(v[1])) {
+                self.ordered.sort_by_key(|&i| key(i));
+            }
+        }
+
+        self.update_bounds(sg, tags);
+        self.info = FrameInfo::default();
+
+        let cameras = &self.cameras;
+        for group in &mut self.lods.data {
+            group.retain_cameras(|ent| cameras.has(ent));
+        }
+
+        let ranges = self.shadow_ranges();
+
+        for &i in &self.ordered {
+            let v = &self.cameras.data[i];
+            let view_matrix = v.transform.view_matrix();
+            let frustum = v.frustum();
+
+            // The meshes with level of details are swapped with the levels picked by camera
+            // in place, and the originals are restored after drawing.
+            let mut lods = Vec::new();
+            for (j, group) in self.lods.data.iter_mut().enumerate() {
+                if let Some(&index) = self.meshes.remap.get(&self.lods.entities[j]) {
+                    let mesh = &mut self.meshes.data[index];
+                    let ent = self.cameras.entities[i];
+                    if let Some(level) = group.pick(ent, v, &mesh.transform) {
+                        let level = group.levels[level];
+                        let material = level.material.or(mesh.material);
+                        let original = (mesh.mesh, mesh.material, mesh.bounds);
+                        mesh.mesh = level.mesh;
+                        mesh.material = material;
+                        mesh.bounds = level.bounds;
+                        lods.push((index, original));
+                    }
+                }
+            }
+
+            let all = &self.meshes;
+            let lod_meshes: Vec<_> = lods.iter().map(|v| &all.data[v.0]).collect();
+            let groups = &self.lods;
+            let drawable = |m: &&MeshRenderer| !m.batched && !groups.has(m.ent);
+
+            // The meshes in hierarchies are culled with them, and the others are tested one
+            // by one.
+            let mut candidates = Vec::new();
+            if self.culling {
+                self.visibles.clear();
+                for bvh in &self.bvhs {
+                    bvh.cull(&view_matrix, &frustum, &mut self.visibles);
+                    self.info.culled += bvh.len() as u32;
+                }
+
+                self.info.culled -= self.visibles.len() as u32;
+
+                let ents = self.visibles.iter().chain(&self.unbounded);
+                candidates.extend(ents.filter_map(|&ent| all.get(ent)).filter(&drawable));
+            } else {
+                candidates.extend(all.data.iter().filter(&drawable));
+            }
+
+            for mesh in self.batches.iter().chain(lod_meshes.iter().cloned()) {
+                if self.culling && mesh.is_culled(&view_matrix, &frustum) {
+                    self.info.culled += 1;
+                } else {
+                    candidates.push(mesh);
+                }
+            }
+
+            // The meshes in the layers culled by camera do not cast shadows either.
+            let mut casters = Vec::new();
+            match ranges {
+                Some(ref ents) => {
+                    let ents = ents.iter().chain(&self.unbounded);
+                    casters.extend(ents.filter_map(|&ent| all.get(ent)).filter(&drawable));
+                }
+                None => casters.extend(all.data.iter().filter(&drawable)),
+            }
+
+            casters.extend(self.batches.iter().chain(lod_meshes.iter().cloned()));
+            casters.retain(|m| m.visible && m.shadow_caster && v.is_visible(m.layers));
+
+            let mut meshes = Vec::with_capacity(candidates.len());
+            let mut transparents = Vec::new();
+            for &mesh in &candidates {
+                if !mesh.visible || !v.is_visible(mesh.layers) {
+                    continue;
+                }
+
+                if renderer.queue(mesh).is_transparent() {
+                    transparents.push(mesh);
+                } else {
+                    meshes.push(mesh);
+                }
+            }
+
+            self.info.submitted += (meshes.len() + transparents.len()) as u32;
+            if let Err(err) = renderer.submit_shadows(&v, &self.lits.data, &casters) {
+                warn!("Failed to draw shadows. {}", err);
+            }
+
+            renderer.submit(&v, &self.lits.data, &meshes);
+
+            if let Some(skybox) = self.skyboxes.get(self.cameras.entities[i]) {
+                if let Err(err) = skybox.submit(v) {
+                    warn!("Failed to draw skybox. {}", err);
+                }
+            }
+
+            // The transparent meshes are submitted after skybox, since they do not write
+            // depth and would be covered by it.
+            if !transparents.is_empty() {
+                renderer.submit(&v, &self.lits.data, &transparents);
+            }
+
+            for (index, (mesh, material, bounds)) in lods {
+                let v = &mut self.meshes.data[index];
+                v.mesh = mesh;
+                v.material = material;
+                v.bounds = bounds;
+            }
+        }
+    }
+}
+
+impl Renderable {
+    // Gathers the meshes in the ranges of point and spot lights casting shadows, or
+    // `None` if any directional light casts shadows, which might be casted by all the
+    // meshes.
+    fn shadow_ranges(&self) -> Option<Vec<Entity>> {
+        let mut ents = Vec::new();
+        for v in self.lits.data.iter().filter(|v| v.enable && v.shadow_caster) {
+            let radius = match v.source {
+                LitSource::Dir => return None,
+                LitSource::Point { radius, .. } | LitSource::Spot { radius, .. } => radius,
+            };
+
+            let extents = Vector3::new(radius, radius, radius);
+            let aabb = Aabb3::new(
+                EuclideanSpace::from_vec(v.transform.position - extents),
+                EuclideanSpace::from_vec(v.transform.position + extents),
+            );
+
+            for bvh in &self.bvhs {
+                bvh.intersect(&aabb, &mut ents);
             }
         }
 
-        for v in &self.cameras.data {
-            renderer.submit(&v, &self.lits.data, &self.meshes.data);
+        ents.sort();
+        ents.dedup();
+        Some(ents)
+    }
+}
+
+// Gets the index of hierarchy that the mesh is kept in. The batched meshes and the ones
+// with level of details are drawn with their batches and levels, which are tested one by
+// one instead.
+fn hierarchy(v: &MeshRenderer, lods: &Component<LodGroup>) -> Option<usize> {
+    if v.batched || lods.has(v.ent) || !v.is_bounded() {
+        None
+    } else {
+        Some(v.static_batching as usize)
+    }
+}
+
+/// Gets the surface drawing into the viewport of camera, which is re-created when the
+/// viewport changes.
+fn viewport_surface(
+    viewports: &mut FastHashMap<Entity, (Aabb2<f32>, SurfaceHandle)>,
+    ent: Entity,
+    rect: Aabb2<f32>,
+) -> Option<SurfaceHandle> {
+    if let Some(&(v, surface)) = viewports.get(&ent) {
+        if v == rect {
+            return Some(surface);
+        }
+
+        video::delete_surface(surface);
+        viewports.remove(&ent);
+    }
+
+    let mut params = SurfaceParams::default();
+    params.set_viewport(rect);
+
+    match video::create_surface(params) {
+        Ok(surface) => {
+            viewports.insert(ent, (rect, surface));
+            Some(surface)
+        }
+        Err(err) => {
+            warn!("Failed to create the surface of viewport. {}", err);
+            None
         }
     }
 }
@@ -0,0 +1,78 @@
+use crayon::math::prelude::Color;
+use crayon::video::assets::texture::{TextureCubeHandle, TextureHandle};
+
+use super::super::queue::RenderQueue;
+
+/// The metallic-roughness material of `PbrRenderer`, which follows the definitions of
+/// glTF 2.0. The factors are multiplied with the values sampled from textures.
+#[derive(Debug, Copy, Clone)]
+pub struct PbrMaterial {
+    /// The base color in linear space, the alpha channel is ignored for now.
+    pub albedo: Color<f32>,
+    /// The base color texture in sRGB space.
+    pub albedo_texture: Option<TextureHandle>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// The metalness in blue channel, and roughness in green channel.
+    pub metallic_roughness_texture: Option<TextureHandle>,
+    /// The tangent space normal texture, which requires the tangents of mesh.
+    pub normal_texture: Option<TextureHandle>,
+    pub normal_scale: f32,
+    /// The ambient occlusion in red channel.
+    pub occlusion_texture: Option<TextureHandle>,
+    pub occlusion_strength: f32,
+    /// The set of texture coordinates that occlusion texture uses, `1` samples with
+    /// `Attribute::Texcoord1` which is usually unwrapped without overlapping.
+    pub occlusion_texcoord: usize,
+    /// The emissive color in linear space.
+    pub emissive: Color<f32>,
+    /// The emissive texture in sRGB space.
+    pub emissive_texture: Option<TextureHandle>,
+    /// The queue that meshes are drawn in, the alpha blending is not supported yet.
+    pub queue: RenderQueue,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        PbrMaterial {
+            albedo: Color::white(),
+            albedo_texture: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            occlusion_texcoord: 0,
+            emissive: Color::black(),
+            emissive_texture: None,
+            queue: RenderQueue::Opaque,
+        }
+    }
+}
+
+/// The image-based lighting environment of `PbrRenderer`.
+///
+/// The irradiance map stores the diffuse convolution of environment, and the mipmaps
+/// of prefiltered map store the specular convolutions with increasing roughness. The
+/// global ambient of renderer is used instead if the map is not set.
+#[derive(Debug, Copy, Clone)]
+pub struct PbrEnvironment {
+    pub irradiance: Option<TextureCubeHandle>,
+    pub prefiltered: Option<TextureCubeHandle>,
+    /// The number of mipmaps of prefiltered map.
+    pub prefiltered_mips: u32,
+    pub intensity: f32,
+}
+
+impl Default for PbrEnvironment {
+    fn default() -> Self {
+        PbrEnvironment {
+            irradiance: None,
+            prefiltered: None,
+            prefiltered_mips: 1,
+            intensity: 1.0,
+        }
+    }
+}
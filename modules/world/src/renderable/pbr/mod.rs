@@ -0,0 +1,587 @@
+mod material;
+pub use self::material::{PbrEnvironment, PbrMaterial};
+
+use crayon::prelude::*;
+use crayon::video::assets::texture::{TextureCubeData, TextureCubeHandle, TextureCubeParams};
+use failure::Error;
+
+use utils::prelude::Component;
+use Entity;
+
+use super::fog::{Fog, FOG_FS};
+use super::lit::ATTENUATION_FS;
+use super::mesh_renderer::Lightmap;
+use super::queue::{DrawOrder, RenderQueue};
+use super::shadow::{self, Shadow, MAX_PCF_RADIUS, MAX_SHADOW_CASCADES};
+use super::skinning::{Skinning, SkinningMode, MAX_BONES, SKINNING_VS};
+use super::{Camera, Lit, LitSource, MeshRenderer};
+
+pub const MAX_DIR_LITS: usize = 1;
+pub const MAX_POINT_LITS: usize = 4;
+pub const MAX_SPOT_LITS: usize = 2;
+
+/// A physically based renderer with the metallic-roughness material model of glTF 2.0,
+/// and image-based lighting from the optional `PbrEnvironment`.
+///
+/// Meshes are shaded in linear space. Textures of albedo and emission are decoded from
+/// sRGB space, by hardware if they are in sRGB formats or by shaders otherwise. And the
+/// final color is encoded into sRGB space if the surface is not encoded by hardware.
+/// Shadows are rendered in the same way as `SimpleRenderer`.
+///
+/// The albedo is multiplied by the vertex colors in linear space if meshes have `Color0`.
+/// And the `Lightmap` of mesh replaces the diffuse irradiance of environment, which is
+/// sampled with `Texcoord1`. Animated meshes are skinned on GPU like `SimpleRenderer`.
+pub struct PbrRenderer {
+    materials: Component<PbrMaterial>,
+    environment: PbrEnvironment,
+    shadow: Option<Shadow>,
+
+    surface: SurfaceHandle,
+    shader: ShaderHandle,
+    uniforms: UniformVariableLayout,
+    black: TextureCubeHandle,
+    drawcalls: DrawCommandBuffer<DrawOrder>,
+    skinning: Skinning,
+
+    global_ambient: Color<f32>,
+    dir_lits: Vec<(String, String)>,
+    point_lits: Vec<(String, String, String)>,
+    spot_lits: Vec<(String, String, String, String, String)>,
+}
+
+impl Drop for PbrRenderer {
+    fn drop(&mut self) {
+        video::delete_surface(self.surface);
+        video::delete_shader(self.shader);
+        video::delete_texture_cube(self.black);
+    }
+}
+
+impl PbrRenderer {
+    /// Creates a new `PbrRenderer`.
+    pub fn new() -> Result<Self, Error> {
+        let mut uniforms = UniformVariableLayout::build()
+            .with("u_ModelViewMatrix", UniformVariableType::Matrix4f)
+            .with("u_MVPMatrix", UniformVariableType::Matrix4f)
+            .with("u_ViewNormalMatrix", UniformVariableType::Matrix4f)
+            .with("u_ViewToWorldMatrix", UniformVariableType::Matrix4f)
+            .with("u_GlobalAmbient", UniformVariableType::Vector3f)
+            .with("u_EncodeSRGB", UniformVariableType::F32)
+            .with("u_LightmapTexture", UniformVariableType::Texture)
+            .with("u_LightmapScaleOffset", UniformVariableType::Vector4f)
+            .with("u_LightmapIntensity", UniformVariableType::F32)
+            .with("u_Albedo", UniformVariableType::Vector3f)
+            .with("u_AlbedoTexture", UniformVariableType::Texture)
+            .with("u_MetallicRoughness", UniformVariableType::Vector2f)
+            .with("u_MetallicRoughnessTexture", UniformVariableType::Texture)
+            .with("u_NormalScale", UniformVariableType::F32)
+            .with("u_NormalTexture", UniformVariableType::Texture)
+            .with("u_Occlusion", UniformVariableType::Vector2f)
+            .with("u_OcclusionTexture", UniformVariableType::Texture)
+            .with("u_Emissive", UniformVariableType::Vector3f)
+            .with("u_EmissiveTexture", UniformVariableType::Texture)
+            .with("u_DecodeSRGB", UniformVariableType::Vector2f)
+            .with("u_EnvironmentParams", UniformVariableType::Vector4f)
+            .with("u_IrradianceMap", UniformVariableType::TextureCube)
+            .with("u_PrefilteredMap", UniformVariableType::TextureCube);
+
+        uniforms = Fog::uniforms(Shadow::uniforms(uniforms))
+            .with("u_SpotLitCookie", UniformVariableType::Texture)
+            .with("u_SpotLitCookieMatrix", UniformVariableType::Matrix4f);
+
+        let mut dir_lits = Vec::new();
+        let mut point_lits = Vec::new();
+        let mut spot_lits = Vec::new();
+
+        for i in 0..MAX_DIR_LITS {
+            let name = (
+                format!("u_DirLitViewDir[{0}]", i),
+                format!("u_DirLitColor[{0}]", i),
+            );
+
+            uniforms = uniforms
+                .with(name.0.as_str(), UniformVariableType::Vector3f)
+                .with(name.1.as_str(), UniformVariableType::Vector3f);
+
+            dir_lits.push(name);
+        }
+
+        for i in 0..MAX_POINT_LITS {
+            let name = (
+                format!("u_PointLitViewPos[{0}]", i),
+                format!("u_PointLitColor[{0}]", i),
+                format!("u_PointLitAttenuation[{0}]", i),
+            );
+
+            uniforms = uniforms
+                .with(name.0.as_str(), UniformVariableType::Vector3f)
+                .with(name.1.as_str(), UniformVariableType::Vector3f)
+                .with(name.2.as_str(), UniformVariableType::Vector4f);
+
+            point_lits.push(name);
+        }
+
+        for i in 0..MAX_SPOT_LITS {
+            let name = (
+                format!("u_SpotLitViewPos[{0}]", i),
+                format!("u_SpotLitViewDir[{0}]", i),
+                format!("u_SpotLitColor[{0}]", i),
+                format!("u_SpotLitAttenuation[{0}]", i),
+                format!("u_SpotLitCone[{0}]", i),
+            );
+
+            uniforms = uniforms
+                .with(name.0.as_str(), UniformVariableType::Vector3f)
+                .with(name.1.as_str(), UniformVariableType::Vector3f)
+                .with(name.2.as_str(), UniformVariableType::Vector3f)
+                .with(name.3.as_str(), UniformVariableType::Vector4f)
+                .with(name.4.as_str(), UniformVariableType::Vector2f);
+
+            spot_lits.push(name);
+        }
+
+        let uniforms = uniforms.finish();
+        let shader = Self::create_shader(uniforms.clone(), None)?;
+
+        let params = SurfaceParams::default();
+        let surface = video::create_surface(params)?;
+
+        // The samplers of environment maps are always bound to a cube map.
+        let mut params = TextureCubeParams::default();
+        params.dimensions = (1, 1).into();
+        let face = || vec![vec![0; 4].into_boxed_slice()];
+        let data = TextureCubeData {
+            faces: [face(), face(), face(), face(), face(), face()],
+        };
+
+        let black = video::create_texture_cube(params, data)?;
+
+        Ok(PbrRenderer {
+            materials: Component::new(),
+            environment: PbrEnvironment::default(),
+            shadow: None,
+            surface: surface,
+            shader: shader,
+            uniforms: uniforms,
+            black: black,
+            drawcalls: DrawCommandBuffer::new(),
+            skinning: Skinning::new(MAX_BONES),
+            dir_lits: dir_lits,
+            point_lits: point_lits,
+            spot_lits: spot_lits,
+            global_ambient: Color::gray(),
+        })
+    }
+
+    // Creates the shader, or the skinned variant of it if `skinning` is specified.
+    fn create_shader(
+        uniforms: UniformVariableLayout,
+        skinning: Option<&Skinning>,
+    ) -> Result<ShaderHandle, Error> {
+        let mut attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Normal, 3)
+            .with_optional(Attribute::Tangent, 4)
+            .with_optional(Attribute::Color0, 3)
+            .with_optional(Attribute::Texcoord0, 2)
+            .with_optional(Attribute::Texcoord1, 2);
+
+        let mut params = ShaderParams::default();
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+        params.uniforms = uniforms;
+
+        if let Some(skinning) = skinning {
+            attributes = Skinning::attributes(attributes);
+            params.uniforms = skinning.uniforms(&params.uniforms);
+        }
+
+        params.attributes = attributes.finish();
+
+        let vs = format!(
+            "
+            #version 100
+            precision highp float;
+            {1}
+            {2}
+            {0}
+            ",
+            include_str!("shaders/pbr.vs"),
+            skinning.map(|v| v.defines()).unwrap_or_default(),
+            SKINNING_VS
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            #ifdef GL_FRAGMENT_PRECISION_HIGH
+            precision highp float;
+            #else
+            precision mediump float;
+            #endif
+
+            #define MAX_DIR_LITS {0}
+            #define MAX_POINT_LITS {1}
+            #define MAX_SPOT_LITS {6}
+            #define MAX_SHADOW_CASCADES {2}
+            #define MAX_PCF_RADIUS {3}
+            {4}
+            {7}
+            {8}
+            {9}
+            {5}
+            ",
+            MAX_DIR_LITS,
+            MAX_POINT_LITS,
+            MAX_SHADOW_CASCADES,
+            MAX_PCF_RADIUS,
+            shadow::RECEIVER_FS,
+            include_str!("shaders/pbr.fs"),
+            MAX_SPOT_LITS,
+            super::SRGB_FS,
+            FOG_FS,
+            ATTENUATION_FS
+        );
+
+        Ok(video::create_shader(params, vs, fs)?)
+    }
+
+    #[inline]
+    pub fn add(&mut self, ent: Entity, material: PbrMaterial) -> Option<PbrMaterial> {
+        self.materials.add(ent, material)
+    }
+
+    #[inline]
+    pub fn has(&self, ent: Entity) -> bool {
+        self.materials.has(ent)
+    }
+
+    #[inline]
+    pub fn material(&self, ent: Entity) -> Option<&PbrMaterial> {
+        self.materials.get(ent)
+    }
+
+    #[inline]
+    pub fn material_mut(&mut self, ent: Entity) -> Option<&mut PbrMaterial> {
+        self.materials.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove(&mut self, ent: Entity) {
+        self.materials.remove(ent)
+    }
+
+    /// Sets the constant ambient light, which is used if there is no environment map.
+    #[inline]
+    pub fn set_global_ambient<T: Into<Color<f32>>>(&mut self, color: T) {
+        self.global_ambient = color.into();
+    }
+
+    /// Sets the environment of image-based lighting, which overrides the environment of
+    /// the skybox of camera.
+    #[inline]
+    pub fn set_environment(&mut self, environment: PbrEnvironment) {
+        self.environment = environment;
+    }
+
+    /// Gets the environment of image-based lighting.
+    #[inline]
+    pub fn environment(&self) -> PbrEnvironment {
+        self.environment
+    }
+
+    /// Sets the shadow maps of lights, which could be created with `Shadow::build()`.
+    #[inline]
+    pub fn set_shadow(&mut self, shadow: Shadow) {
+        self.shadow = Some(shadow);
+    }
+
+    /// Gets the shadow maps of lights.
+    #[inline]
+    pub fn shadow(&self) -> Option<&Shadow> {
+        self.shadow.as_ref()
+    }
+
+    /// Sets the maximum number of bones that skinned meshes could have, which is
+    /// `MAX_BONES` by default.
+    pub fn set_max_bones(&mut self, max_bones: usize) {
+        self.skinning = Skinning::new(max_bones);
+    }
+
+    /// Gets the maximum number of bones that skinned meshes could have.
+    #[inline]
+    pub fn max_bones(&self) -> usize {
+        self.skinning.max_bones()
+    }
+
+    /// Gets the way that bone matrices are uploaded, which is `None` if the GPU skinning
+    /// is not supported by this device.
+    #[inline]
+    pub fn skinning_mode(&self) -> Option<SkinningMode> {
+        self.skinning.mode()
+    }
+}
+
+impl super::Renderer for PbrRenderer {
+    type Mtl = PbrMaterial;
+
+    fn add_mtl(&mut self, ent: Entity, mtl: Self::Mtl) {
+        self.add(ent, mtl);
+    }
+
+    fn mtl(&self, ent: Entity) -> Option<&Self::Mtl> {
+        self.material(ent)
+    }
+
+    fn mtl_mut(&mut self, ent: Entity) -> Option<&mut Self::Mtl> {
+        self.material_mut(ent)
+    }
+
+    fn remove_mtl(&mut self, ent: Entity) {
+        self.remove(ent);
+    }
+
+    fn queue(&self, mesh: &MeshRenderer) -> RenderQueue {
+        self.material(mesh.ent).map(|v| v.queue).unwrap_or_default()
+    }
+
+    fn submit_shadows(
+        &mut self,
+        camera: &Camera,
+        lits: &[Lit],
+        casters: &[&MeshRenderer],
+    ) -> Result<(), Error> {
+        if let Some(ref mut shadow) = self.shadow {
+            shadow.reset(camera);
+        }
+
+        let dir = lits.iter().find(|v| match v.source {
+            LitSource::Dir => v.enable && v.shadow_caster,
+            _ => false,
+        });
+
+        let point = lits.iter().find(|v| match v.source {
+            LitSource::Point { .. } => v.enable && v.shadow_caster,
+            _ => false,
+        });
+
+        let spot = lits.iter().find(|v| match v.source {
+            LitSource::Spot { .. } => v.enable && v.shadow_caster,
+            _ => false,
+        });
+
+        for lit in dir.iter().chain(point.iter()).chain(spot.iter()) {
+            if self.shadow.is_none() {
+                self.shadow = Some(Shadow::build().finish()?);
+            }
+
+            self.shadow.as_mut().unwrap().draw(camera, lit, casters)?;
+        }
+
+        Ok(())
+    }
+
+    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[&MeshRenderer]) {
+        use crayon::math::prelude::{InnerSpace, Matrix, MetricSpace, SquareMatrix};
+
+        let view_matrix = camera.transform.view_matrix();
+        let surface = camera.surface().unwrap_or(self.surface);
+        let encoding = super::srgb_encoding(surface);
+        let inv_view_matrix = view_matrix.invert().unwrap_or(view_matrix);
+        let projection_matrix = camera.projection_matrix();
+        let mut lits = Vec::from(lits);
+
+        // The environment of skybox is used if there is no environment maps.
+        let mut env = self.environment;
+        if env.irradiance.is_none() && env.prefiltered.is_none() {
+            env = camera.environment.unwrap_or(env);
+        }
+
+        let env_params = [
+            if env.irradiance.is_some() { 1.0 } else { 0.0 },
+            if env.prefiltered.is_some() { 1.0 } else { 0.0 },
+            env.prefiltered_mips.max(1) as f32 - 1.0,
+            env.intensity,
+        ];
+
+        for mesh in meshes {
+            let model_matrix = mesh.transform.matrix();
+            let mv = view_matrix * model_matrix;
+            let mvp = projection_matrix * mv;
+            let vn = mv.invert().and_then(|v| Some(v.transpose())).unwrap_or(mv);
+
+            let uniforms = &self.uniforms;
+            let shader = if self.skinning.is_skinned(mesh) {
+                self.skinning
+                    .shader(|v| Self::create_shader(uniforms.clone(), Some(v)))
+            } else {
+                None
+            };
+
+            let mut dc = Draw::new(shader.unwrap_or(self.shader), mesh.mesh);
+            dc.set_uniform_variable("u_ModelViewMatrix", mv);
+            dc.set_uniform_variable("u_MVPMatrix", mvp);
+            dc.set_uniform_variable("u_ViewNormalMatrix", vn);
+            dc.set_uniform_variable("u_ViewToWorldMatrix", inv_view_matrix);
+            dc.set_uniform_variable("u_GlobalAmbient", self.global_ambient.rgb());
+            dc.set_uniform_variable("u_EncodeSRGB", encoding);
+
+            // The baked lights are skipped for the lightmapped meshes.
+            let lightmap = mesh.lightmap.unwrap_or_else(|| {
+                let mut v = Lightmap::from(crate::default().white);
+                v.intensity = 0.0;
+                v
+            });
+
+            dc.set_uniform_variable("u_LightmapTexture", lightmap.texture);
+            dc.set_uniform_variable("u_LightmapScaleOffset", lightmap.scale_offset);
+            dc.set_uniform_variable("u_LightmapIntensity", lightmap.intensity);
+
+            let white = crate::default().white;
+            let mat = self.material(mesh.ent).cloned().unwrap_or_default();
+            let normal_scale = if mat.normal_texture.is_some() {
+                mat.normal_scale
+            } else {
+                0.0
+            };
+
+            let albedo = mat.albedo_texture.unwrap_or(white);
+            let emissive = mat.emissive_texture.unwrap_or(white);
+            let decoding = [super::srgb_decoding(albedo), super::srgb_decoding(emissive)];
+
+            dc.set_uniform_variable("u_Albedo", mat.albedo.rgb());
+            dc.set_uniform_variable("u_AlbedoTexture", albedo);
+            dc.set_uniform_variable("u_MetallicRoughness", [mat.metallic, mat.roughness]);
+            dc.set_uniform_variable(
+                "u_MetallicRoughnessTexture",
+                mat.metallic_roughness_texture.unwrap_or(white),
+            );
+            dc.set_uniform_variable("u_NormalScale", normal_scale);
+            dc.set_uniform_variable("u_NormalTexture", mat.normal_texture.unwrap_or(white));
+            let occlusion = [mat.occlusion_strength, mat.occlusion_texcoord.min(1) as f32];
+            dc.set_uniform_variable("u_Occlusion", occlusion);
+            dc.set_uniform_variable("u_OcclusionTexture", mat.occlusion_texture.unwrap_or(white));
+            dc.set_uniform_variable("u_Emissive", mat.emissive.rgb());
+            dc.set_uniform_variable("u_EmissiveTexture", emissive);
+            dc.set_uniform_variable("u_DecodeSRGB", decoding);
+
+            dc.set_uniform_variable("u_EnvironmentParams", env_params);
+            dc.set_uniform_variable("u_IrradianceMap", env.irradiance.unwrap_or(self.black));
+            dc.set_uniform_variable("u_PrefilteredMap", env.prefiltered.unwrap_or(self.black));
+
+            // The lights that casting shadows are always placed in the first slots.
+            let shadow = self.shadow.as_ref();
+            lits.sort_by_key(|v| {
+                let casting = shadow.map(|s| s.is_casting(camera, v)).unwrap_or(false);
+                let distance = mesh.transform.position.distance2(v.transform.position) as u32;
+                (!casting, distance)
+            });
+
+            let mut casting = (false, false, false);
+            let mut cookie = (crate::default().white, Matrix4::identity());
+            let (mut dir_index, mut point_index, mut spot_index) = (0, 0, 0);
+            for lit in &lits {
+                if lit.baked && mesh.lightmap.is_some() {
+                    continue;
+                }
+
+                match lit.source {
+                    LitSource::Dir => {
+                        if dir_index < self.dir_lits.len() {
+                            if dir_index == 0 {
+                                casting.0 =
+                                    shadow.map(|s| s.is_casting(camera, lit)).unwrap_or(false);
+                            }
+
+                            let names = &self.dir_lits[dir_index];
+                            let dir = view_matrix * lit.transform.forward().extend(0.0);
+                            let color = lit.radiance(camera.exposure());
+                            dc.set_uniform_variable(&names.0, dir.truncate().normalize());
+                            dc.set_uniform_variable(&names.1, color);
+                            dir_index += 1;
+                        }
+                    }
+                    LitSource::Point { .. } => {
+                        if point_index < self.point_lits.len() {
+                            if point_index == 0 {
+                                casting.1 =
+                                    shadow.map(|s| s.is_casting(camera, lit)).unwrap_or(false);
+                            }
+
+                            let names = &self.point_lits[point_index];
+                            let mut pos = view_matrix * lit.transform.position.extend(1.0);
+                            pos /= pos.w;
+                            let attenuation = lit.attenuation_params();
+                            let color = lit.radiance(camera.exposure());
+                            dc.set_uniform_variable(&names.0, pos.truncate());
+                            dc.set_uniform_variable(&names.1, color);
+                            dc.set_uniform_variable(&names.2, attenuation);
+                            point_index += 1;
+                        }
+                    }
+                    LitSource::Spot {
+                        radius,
+                        angle,
+                        inner_angle,
+                        ..
+                    } => {
+                        if spot_index < self.spot_lits.len() {
+                            if spot_index == 0 {
+                                casting.2 =
+                                    shadow.map(|s| s.is_casting(camera, lit)).unwrap_or(false);
+                                if let Some(texture) = lit.cookie {
+                                    let vp = shadow::spot::frustum(&lit.transform, angle, radius);
+                                    let inv_view_matrix =
+                                        view_matrix.invert().unwrap_or(view_matrix);
+                                    cookie = (texture, shadow::spot::bias() * vp * inv_view_matrix);
+                                }
+                            }
+
+                            let names = &self.spot_lits[spot_index];
+                            let mut pos = view_matrix * lit.transform.position.extend(1.0);
+                            pos /= pos.w;
+                            let dir = view_matrix * lit.transform.forward().extend(0.0);
+                            let attenuation = lit.attenuation_params();
+                            let color = lit.radiance(camera.exposure());
+                            dc.set_uniform_variable(&names.0, pos.truncate());
+                            dc.set_uniform_variable(&names.1, dir.truncate().normalize());
+                            dc.set_uniform_variable(&names.2, color);
+                            dc.set_uniform_variable(&names.3, attenuation);
+                            dc.set_uniform_variable(
+                                &names.4,
+                                shadow::spot::cone(angle, inner_angle),
+                            );
+                            spot_index += 1;
+                        }
+                    }
+                }
+            }
+
+            if !mesh.shadow_receiver {
+                casting = (false, false, false);
+            }
+
+            dc.set_uniform_variable("u_SpotLitCookie", cookie.0);
+            dc.set_uniform_variable("u_SpotLitCookieMatrix", cookie.1);
+
+            Shadow::bind(shadow, camera, casting, view_matrix, &mut dc);
+            Fog::bind(camera.fog.as_ref(), &mut dc);
+            mesh.properties.apply(&self.uniforms, &mut dc);
+
+            if shader.is_some() {
+                self.skinning.bind(mesh, &mut self.drawcalls, &mut dc);
+            }
+
+            let order = DrawOrder::new(
+                mat.queue,
+                dc.shader,
+                mesh.transform.position.distance(camera.transform.position),
+            );
+
+            self.drawcalls.draw(order, dc);
+        }
+
+        self.drawcalls.submit(surface).unwrap();
+        self.skinning.collect();
+    }
+}
@@ -0,0 +1,233 @@
+varying vec3 v_EyeFragPos;
+varying vec3 v_EyeNormal;
+varying vec4 v_EyeTangent;
+varying vec4 v_Color;
+varying vec2 v_Texcoord;
+varying vec2 v_Texcoord1;
+
+uniform mat4 u_ViewToWorldMatrix;
+
+uniform vec3 u_DirLitViewDir[MAX_DIR_LITS];
+uniform vec3 u_DirLitColor[MAX_DIR_LITS];
+
+uniform vec3 u_PointLitViewPos[MAX_POINT_LITS];
+uniform vec3 u_PointLitColor[MAX_POINT_LITS];
+uniform vec4 u_PointLitAttenuation[MAX_POINT_LITS];
+
+uniform vec3 u_SpotLitViewPos[MAX_SPOT_LITS];
+uniform vec3 u_SpotLitViewDir[MAX_SPOT_LITS];
+uniform vec3 u_SpotLitColor[MAX_SPOT_LITS];
+uniform vec4 u_SpotLitAttenuation[MAX_SPOT_LITS];
+// x: the cosine of outer cone, y: the cosine of inner cone.
+uniform vec2 u_SpotLitCone[MAX_SPOT_LITS];
+
+// The cookie of the first spot light, which is projected from view space.
+uniform sampler2D u_SpotLitCookie;
+uniform mat4 u_SpotLitCookieMatrix;
+
+uniform vec3 u_GlobalAmbient;
+
+// The lightmap replaces the diffuse irradiance if its intensity is positive.
+uniform sampler2D u_LightmapTexture;
+uniform vec4 u_LightmapScaleOffset;
+uniform float u_LightmapIntensity;
+
+uniform vec3 u_Albedo;
+uniform sampler2D u_AlbedoTexture;
+
+uniform vec2 u_MetallicRoughness;
+uniform sampler2D u_MetallicRoughnessTexture;
+
+uniform float u_NormalScale;
+uniform sampler2D u_NormalTexture;
+
+// x: strength, y: 0 samples with the first texcoords, 1 samples with the second texcoords.
+uniform vec2 u_Occlusion;
+uniform sampler2D u_OcclusionTexture;
+
+uniform vec3 u_Emissive;
+uniform sampler2D u_EmissiveTexture;
+
+// The flags of `DecodeSRGB`, x: albedo texture, y: emissive texture.
+uniform vec2 u_DecodeSRGB;
+
+// x: has irradiance map, y: has prefiltered map, z: max mipmap level, w: intensity
+uniform vec4 u_EnvironmentParams;
+uniform samplerCube u_IrradianceMap;
+uniform samplerCube u_PrefilteredMap;
+
+const float PI = 3.14159265359;
+
+float DistributionGGX(float NdotH, float roughness)
+{
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float GeometrySmith(float NdotV, float NdotL, float roughness)
+{
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+    return (NdotV / (NdotV * (1.0 - k) + k)) * (NdotL / (NdotL * (1.0 - k) + k));
+}
+
+vec3 FresnelSchlick(float cosTheta, vec3 F0)
+{
+    return F0 + (1.0 - F0) * pow(1.0 - cosTheta, 5.0);
+}
+
+vec3 FresnelSchlickRoughness(float cosTheta, vec3 F0, float roughness)
+{
+    return F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(1.0 - cosTheta, 5.0);
+}
+
+// The analytical approximation of pre-integrated BRDF, which saves the look-up table.
+vec2 EnvBRDFApprox(float NdotV, float roughness)
+{
+    const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+    const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+    vec4 r = roughness * c0 + c1;
+    float a004 = min(r.x * r.x, exp2(-9.28 * NdotV)) * r.x + r.y;
+    return vec2(-1.04, 1.04) * a004 + r.zw;
+}
+
+// Cook-Torrance BRDF with the incoming light from `L`.
+vec3 Calculate(vec3 N, vec3 V, vec3 L, vec3 albedo, float metallic, float roughness, vec3 F0)
+{
+    vec3 H = normalize(V + L);
+    float NdotL = max(dot(N, L), 0.0);
+    float NdotV = max(dot(N, V), 0.0001);
+    float NdotH = max(dot(N, H), 0.0);
+
+    float D = DistributionGGX(NdotH, roughness);
+    float G = GeometrySmith(NdotV, NdotL, roughness);
+    vec3 F = FresnelSchlick(max(dot(H, V), 0.0), F0);
+
+    vec3 specular = D * G * F / (4.0 * NdotV * NdotL + 0.0001);
+    vec3 kD = (vec3(1.0) - F) * (1.0 - metallic);
+    return (kD * albedo / PI + specular) * NdotL;
+}
+
+vec3 CalculateCookie(vec3 eyePos)
+{
+    vec4 pos = u_SpotLitCookieMatrix * vec4(eyePos, 1.0);
+    if (pos.w <= 0.0)
+    {
+        return vec3(0.0, 0.0, 0.0);
+    }
+
+    return texture2D(u_SpotLitCookie, pos.xy / pos.w).rgb;
+}
+
+vec3 CalculateNormal()
+{
+    vec3 N = normalize(v_EyeNormal);
+
+    // Meshes without tangents have zero vectors here.
+    if (u_NormalScale > 0.0 && dot(v_EyeTangent.xyz, v_EyeTangent.xyz) > 0.0)
+    {
+        vec3 T = normalize(v_EyeTangent.xyz - N * dot(N, v_EyeTangent.xyz));
+        vec3 B = cross(N, T) * v_EyeTangent.w;
+        vec3 n = texture2D(u_NormalTexture, v_Texcoord).xyz * 2.0 - 1.0;
+        n.xy *= u_NormalScale;
+        N = normalize(mat3(T, B, N) * n);
+    }
+
+    return N;
+}
+
+void main()
+{
+    vec3 N = CalculateNormal();
+    vec3 V = normalize(-v_EyeFragPos);
+
+    vec3 albedo = u_Albedo * v_Color.rgb * DecodeSRGB(texture2D(u_AlbedoTexture, v_Texcoord).rgb, u_DecodeSRGB.x);
+    vec4 mr = texture2D(u_MetallicRoughnessTexture, v_Texcoord);
+    float metallic = clamp(u_MetallicRoughness.x * mr.b, 0.0, 1.0);
+    float roughness = clamp(u_MetallicRoughness.y * mr.g, 0.04, 1.0);
+    vec2 aoTexcoord = mix(v_Texcoord, v_Texcoord1, u_Occlusion.y);
+    float ao = 1.0 + u_Occlusion.x * (texture2D(u_OcclusionTexture, aoTexcoord).r - 1.0);
+
+    vec3 F0 = mix(vec3(0.04), albedo, metallic);
+    vec3 result = vec3(0.0);
+
+    // directional light
+    for(int i = 0; i < MAX_DIR_LITS; i++)
+    {
+        // only the first directional light casts shadow.
+        float shadow = i == 0 ? CalculateShadow(v_EyeFragPos, N, u_DirLitViewDir[i]) : 1.0;
+
+        vec3 L = -u_DirLitViewDir[i];
+        result += Calculate(N, V, L, albedo, metallic, roughness, F0) * u_DirLitColor[i] * shadow;
+    }
+
+    // point lights
+    for(int i = 0; i < MAX_POINT_LITS; i++)
+    {
+        vec3 L = normalize(u_PointLitViewPos[i] - v_EyeFragPos);
+        float distance = length(u_PointLitViewPos[i] - v_EyeFragPos);
+        float attenuation = CalculateAttenuation(u_PointLitAttenuation[i], distance);
+
+        // only the first point light casts shadow.
+        float shadow = i == 0 ? CalculatePointShadow(v_EyeFragPos) : 1.0;
+
+        vec3 power = Calculate(N, V, L, albedo, metallic, roughness, F0) * u_PointLitColor[i];
+        result += max(power * attenuation * shadow, vec3(0.0, 0.0, 0.0));
+    }
+
+    // spot lights
+    for(int i = 0; i < MAX_SPOT_LITS; i++)
+    {
+        vec3 L = normalize(u_SpotLitViewPos[i] - v_EyeFragPos);
+        float distance = length(u_SpotLitViewPos[i] - v_EyeFragPos);
+        float attenuation = CalculateAttenuation(u_SpotLitAttenuation[i], distance);
+
+        float theta = dot(-L, u_SpotLitViewDir[i]);
+        float cone = clamp((theta - u_SpotLitCone[i].x) / max(u_SpotLitCone[i].y - u_SpotLitCone[i].x, 0.0001), 0.0, 1.0);
+
+        // only the first spot light has cookie and casts shadow.
+        vec3 shadow = i == 0 ? CalculateCookie(v_EyeFragPos) * CalculateSpotShadow(v_EyeFragPos, N, -L) : vec3(1.0);
+
+        vec3 power = Calculate(N, V, L, albedo, metallic, roughness, F0) * u_SpotLitColor[i];
+        result += max(power * attenuation * cone * shadow, vec3(0.0, 0.0, 0.0));
+    }
+
+    // image-based lighting
+    float NdotV = max(dot(N, V), 0.0001);
+    vec3 F = FresnelSchlickRoughness(NdotV, F0, roughness);
+    vec3 kD = (vec3(1.0) - F) * (1.0 - metallic);
+
+    vec3 worldN = normalize(vec3(u_ViewToWorldMatrix * vec4(N, 0.0)));
+    vec3 worldR = normalize(vec3(u_ViewToWorldMatrix * vec4(reflect(-V, N), 0.0)));
+
+    vec3 irradiance = u_GlobalAmbient;
+    if (u_EnvironmentParams.x > 0.0)
+    {
+        irradiance = textureCube(u_IrradianceMap, worldN).rgb * u_EnvironmentParams.w;
+    }
+
+    if (u_LightmapIntensity > 0.0)
+    {
+        vec2 uv = v_Texcoord1 * u_LightmapScaleOffset.xy + u_LightmapScaleOffset.zw;
+        irradiance = texture2D(u_LightmapTexture, uv).rgb * u_LightmapIntensity;
+    }
+
+    // the mipmap level is selected with bias, since explicit lod is not available here.
+    vec3 prefiltered = u_GlobalAmbient;
+    if (u_EnvironmentParams.y > 0.0)
+    {
+        float lod = roughness * u_EnvironmentParams.z;
+        prefiltered = textureCube(u_PrefilteredMap, worldR, lod).rgb * u_EnvironmentParams.w;
+    }
+
+    vec2 brdf = EnvBRDFApprox(NdotV, roughness);
+    vec3 ambient = kD * irradiance * albedo + prefiltered * (F * brdf.x + brdf.y);
+    result += ambient * ao;
+
+    result += u_Emissive * DecodeSRGB(texture2D(u_EmissiveTexture, v_Texcoord).rgb, u_DecodeSRGB.y);
+
+    result = ApplyFog(result, length(v_EyeFragPos));
+    gl_FragColor = vec4(EncodeSRGB(result), 1.0);
+}
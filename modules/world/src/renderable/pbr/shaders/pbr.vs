@@ -0,0 +1,31 @@
+attribute vec3 Position;
+attribute vec3 Normal;
+attribute vec4 Tangent;
+attribute vec4 Color0;
+attribute vec2 Texcoord0;
+attribute vec2 Texcoord1;
+
+uniform mat4 u_ModelViewMatrix;
+uniform mat4 u_MVPMatrix;
+uniform mat4 u_ViewNormalMatrix;
+
+varying vec3 v_EyeFragPos;
+varying vec3 v_EyeNormal;
+varying vec4 v_EyeTangent;
+varying vec4 v_Color;
+varying vec2 v_Texcoord;
+varying vec2 v_Texcoord1;
+
+void main() {
+    mat4 skin = SkinMatrix();
+    vec4 position = skin * vec4(Position, 1.0);
+    gl_Position = u_MVPMatrix * position;
+
+    vec4 eyePos = u_ModelViewMatrix * position;
+    v_EyeFragPos = eyePos.xyz / eyePos.w;
+    v_EyeNormal = vec3(u_ViewNormalMatrix * skin * vec4(Normal, 0.0));
+    v_EyeTangent = vec4(vec3(u_ModelViewMatrix * skin * vec4(Tangent.xyz, 0.0)), Tangent.w);
+    v_Color = Color0;
+    v_Texcoord = Texcoord0;
+    v_Texcoord1 = Texcoord1;
+}
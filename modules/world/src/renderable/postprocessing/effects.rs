@@ -0,0 +1,398 @@
+use crayon::prelude::*;
+use failure::Error;
+
+use super::super::{srgb_encoding, SRGB_FS};
+use super::{create_fullscreen_shader, PostEffect, LUT_FS, TONEMAP_FS};
+
+fn draw(surface: SurfaceHandle, dc: Draw) -> Result<(), Error> {
+    let mut cmds = CommandBuffer::new();
+    cmds.draw(dc);
+    cmds.submit(surface)?;
+    Ok(())
+}
+
+/// Adds the blurred bright parts of image, which makes the lights glow.
+pub struct Bloom {
+    /// The brightness above which parts of image glow.
+    pub threshold: f32,
+    /// The strength of glow.
+    pub intensity: f32,
+
+    threshold_shader: ShaderHandle,
+    blur_shader: ShaderHandle,
+    combine_shader: ShaderHandle,
+    targets: Option<BloomTargets>,
+}
+
+impl Drop for Bloom {
+    fn drop(&mut self) {
+        video::delete_shader(self.threshold_shader);
+        video::delete_shader(self.blur_shader);
+        video::delete_shader(self.combine_shader);
+    }
+}
+
+impl Bloom {
+    pub fn new() -> Result<Self, Error> {
+        let fs = include_str!("shaders/bloom.fs");
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_Threshold", UniformVariableType::F32)
+            .finish();
+
+        let threshold_shader = create_fullscreen_shader(uniforms, &["THRESHOLD"], fs)?;
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_Direction", UniformVariableType::Vector2f)
+            .finish();
+
+        let blur_shader = create_fullscreen_shader(uniforms, &["BLUR"], fs)?;
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_Bloom", UniformVariableType::RenderTexture)
+            .with("u_Intensity", UniformVariableType::F32)
+            .finish();
+
+        let combine_shader = create_fullscreen_shader(uniforms, &[], fs)?;
+
+        Ok(Bloom {
+            threshold: 0.8,
+            intensity: 1.0,
+            threshold_shader: threshold_shader,
+            blur_shader: blur_shader,
+            combine_shader: combine_shader,
+            targets: None,
+        })
+    }
+}
+
+impl PostEffect for Bloom {
+    fn apply(
+        &mut self,
+        source: RenderTextureHandle,
+        destination: SurfaceHandle,
+        dimensions: Vector2<u32>,
+    ) -> Result<(), Error> {
+        // The bright parts are blurred in half resolution, with the same format of source.
+        let half = Vector2::new((dimensions.x / 2).max(1), (dimensions.y / 2).max(1));
+        let format = video::render_texture(source)
+            .map(|v| v.format)
+            .unwrap_or(RenderTextureFormat::RGBA8);
+
+        if self.targets.as_ref().map(|v| (v.dimensions, v.format)) != Some((half, format)) {
+            self.targets = None;
+            self.targets = Some(BloomTargets::new(half, format)?);
+        }
+
+        let targets = self.targets.as_ref().unwrap();
+        let quad = crate::default().quad;
+
+        let mut dc = Draw::new(self.threshold_shader, quad);
+        dc.set_uniform_variable("u_Source", source);
+        dc.set_uniform_variable("u_Threshold", self.threshold);
+        draw(targets.surfaces[0], dc)?;
+
+        let directions = [
+            Vector2::new(1.0 / half.x as f32, 0.0),
+            Vector2::new(0.0, 1.0 / half.y as f32),
+        ];
+
+        for (i, &v) in directions.iter().enumerate() {
+            let mut dc = Draw::new(self.blur_shader, quad);
+            dc.set_uniform_variable("u_Source", targets.colors[i]);
+            dc.set_uniform_variable("u_Direction", v);
+            draw(targets.surfaces[(i + 1) % 2], dc)?;
+        }
+
+        let mut dc = Draw::new(self.combine_shader, quad);
+        dc.set_uniform_variable("u_Source", source);
+        dc.set_uniform_variable("u_Bloom", targets.colors[0]);
+        dc.set_uniform_variable("u_Intensity", self.intensity);
+        draw(destination, dc)
+    }
+}
+
+struct BloomTargets {
+    dimensions: Vector2<u32>,
+    format: RenderTextureFormat,
+    colors: [RenderTextureHandle; 2],
+    surfaces: [SurfaceHandle; 2],
+}
+
+impl BloomTargets {
+    fn new(dimensions: Vector2<u32>, format: RenderTextureFormat) -> Result<Self, Error> {
+        let mut params = RenderTextureParams::default();
+        params.format = format;
+        params.dimensions = dimensions;
+
+        let colors = [
+            video::create_render_texture(params)?,
+            video::create_render_texture(params)?,
+        ];
+
+        let mut surfaces = [SurfaceHandle::default(); 2];
+        for (i, v) in surfaces.iter_mut().enumerate() {
+            let mut params = SurfaceParams::default();
+            params.set_attachments(&[colors[i]], None)?;
+            params.set_clear(None, None, None);
+            *v = video::create_surface(params)?;
+        }
+
+        Ok(BloomTargets {
+            dimensions: dimensions,
+            format: format,
+            colors: colors,
+            surfaces: surfaces,
+        })
+    }
+}
+
+impl Drop for BloomTargets {
+    fn drop(&mut self) {
+        for &v in &self.surfaces {
+            video::delete_surface(v);
+        }
+
+        for &v in &self.colors {
+            video::delete_render_texture(v);
+        }
+    }
+}
+
+/// The operators that map the colors of high dynamic range into displayable range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Tonemapper {
+    Reinhard,
+    Aces,
+}
+
+/// Maps the colors into displayable range, which should be applied after the effects
+/// that work in high dynamic range, e.g. `Bloom`. The colors are encoded into sRGB space
+/// too if the destination is not encoded by hardware.
+pub struct Tonemapping {
+    /// The scale of colors before mapping.
+    pub exposure: f32,
+
+    tonemapper: Tonemapper,
+    shader: ShaderHandle,
+}
+
+impl Drop for Tonemapping {
+    fn drop(&mut self) {
+        video::delete_shader(self.shader);
+    }
+}
+
+impl Tonemapping {
+    pub fn new(tonemapper: Tonemapper) -> Result<Self, Error> {
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_Exposure", UniformVariableType::F32)
+            .with("u_EncodeSRGB", UniformVariableType::F32)
+            .finish();
+
+        let defines: &[&str] = match tonemapper {
+            Tonemapper::Reinhard => &[],
+            Tonemapper::Aces => &["ACES"],
+        };
+
+        let fs = format!(
+            "{}{}{}",
+            SRGB_FS,
+            TONEMAP_FS,
+            include_str!("shaders/tonemapping.fs")
+        );
+        let shader = create_fullscreen_shader(uniforms, defines, &fs)?;
+
+        Ok(Tonemapping {
+            exposure: 1.0,
+            tonemapper: tonemapper,
+            shader: shader,
+        })
+    }
+
+    #[inline]
+    pub fn tonemapper(&self) -> Tonemapper {
+        self.tonemapper
+    }
+}
+
+impl PostEffect for Tonemapping {
+    fn apply(
+        &mut self,
+        source: RenderTextureHandle,
+        destination: SurfaceHandle,
+        _: Vector2<u32>,
+    ) -> Result<(), Error> {
+        let mut dc = Draw::new(self.shader, crate::default().quad);
+        dc.set_uniform_variable("u_Source", source);
+        dc.set_uniform_variable("u_Exposure", self.exposure);
+        dc.set_uniform_variable("u_EncodeSRGB", srgb_encoding(destination));
+        draw(destination, dc)
+    }
+}
+
+/// Fast approximate anti-aliasing, which smooths the edges by the contrast of luma.
+pub struct Fxaa {
+    shader: ShaderHandle,
+}
+
+impl Drop for Fxaa {
+    fn drop(&mut self) {
+        video::delete_shader(self.shader);
+    }
+}
+
+impl Fxaa {
+    pub fn new() -> Result<Self, Error> {
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_InvResolution", UniformVariableType::Vector2f)
+            .finish();
+
+        let fs = include_str!("shaders/fxaa.fs");
+        let shader = create_fullscreen_shader(uniforms, &[], fs)?;
+        Ok(Fxaa { shader: shader })
+    }
+}
+
+impl PostEffect for Fxaa {
+    fn apply(
+        &mut self,
+        source: RenderTextureHandle,
+        destination: SurfaceHandle,
+        dimensions: Vector2<u32>,
+    ) -> Result<(), Error> {
+        let inv = Vector2::new(1.0 / dimensions.x as f32, 1.0 / dimensions.y as f32);
+
+        let mut dc = Draw::new(self.shader, crate::default().quad);
+        dc.set_uniform_variable("u_Source", source);
+        dc.set_uniform_variable("u_InvResolution", inv);
+        draw(destination, dc)
+    }
+}
+
+/// Darkens the borders of image.
+pub struct Vignette {
+    /// The color of borders.
+    pub color: Color<f32>,
+    /// The amount of darkening, the borders are fully darkened at `2.0`.
+    pub intensity: f32,
+    /// The smoothness of the transition from center to borders.
+    pub smoothness: f32,
+
+    shader: ShaderHandle,
+}
+
+impl Drop for Vignette {
+    fn drop(&mut self) {
+        video::delete_shader(self.shader);
+    }
+}
+
+impl Vignette {
+    pub fn new() -> Result<Self, Error> {
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_Color", UniformVariableType::Vector3f)
+            .with("u_Intensity", UniformVariableType::F32)
+            .with("u_Smoothness", UniformVariableType::F32)
+            .finish();
+
+        let fs = include_str!("shaders/vignette.fs");
+        let shader = create_fullscreen_shader(uniforms, &[], fs)?;
+
+        Ok(Vignette {
+            color: Color::black(),
+            intensity: 1.2,
+            smoothness: 1.0,
+            shader: shader,
+        })
+    }
+}
+
+impl PostEffect for Vignette {
+    fn apply(
+        &mut self,
+        source: RenderTextureHandle,
+        destination: SurfaceHandle,
+        _: Vector2<u32>,
+    ) -> Result<(), Error> {
+        let mut dc = Draw::new(self.shader, crate::default().quad);
+        dc.set_uniform_variable("u_Source", source);
+        dc.set_uniform_variable("u_Color", self.color.rgb());
+        dc.set_uniform_variable("u_Intensity", self.intensity);
+        dc.set_uniform_variable("u_Smoothness", self.smoothness);
+        draw(destination, dc)
+    }
+}
+
+/// Remaps the colors with a lookup table.
+///
+/// The 3D lookup table of `size`^3 colors is unwrapped into a 2D texture of `size * size`
+/// x `size`, which consists of `size` slices from left to right with increasing blue. In
+/// each slice, red increases from left to right and green increases from the first row
+/// to the last one.
+pub struct ColorGrading {
+    /// The blending factor between the original and remapped colors.
+    pub contribution: f32,
+
+    lut: TextureHandle,
+    size: u32,
+    shader: ShaderHandle,
+}
+
+impl Drop for ColorGrading {
+    fn drop(&mut self) {
+        video::delete_shader(self.shader);
+    }
+}
+
+impl ColorGrading {
+    /// Creates a new `ColorGrading` with the lookup table of `size`^3 colors, which should
+    /// be created with linear filter and clamp wrap.
+    pub fn new(lut: TextureHandle, size: u32) -> Result<Self, Error> {
+        let uniforms = UniformVariableLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_Lut", UniformVariableType::Texture)
+            .with("u_LutSize", UniformVariableType::F32)
+            .with("u_Contribution", UniformVariableType::F32)
+            .finish();
+
+        let fs = format!("{}{}", LUT_FS, include_str!("shaders/color_grading.fs"));
+        let shader = create_fullscreen_shader(uniforms, &[], &fs)?;
+
+        Ok(ColorGrading {
+            contribution: 1.0,
+            lut: lut,
+            size: size,
+            shader: shader,
+        })
+    }
+
+    /// Sets the lookup table of `size`^3 colors.
+    #[inline]
+    pub fn set_lut(&mut self, lut: TextureHandle, size: u32) {
+        self.lut = lut;
+        self.size = size;
+    }
+}
+
+impl PostEffect for ColorGrading {
+    fn apply(
+        &mut self,
+        source: RenderTextureHandle,
+        destination: SurfaceHandle,
+        _: Vector2<u32>,
+    ) -> Result<(), Error> {
+        let mut dc = Draw::new(self.shader, crate::default().quad);
+        dc.set_uniform_variable("u_Source", source);
+        dc.set_uniform_variable("u_Lut", self.lut);
+        dc.set_uniform_variable("u_LutSize", self.size as f32);
+        dc.set_uniform_variable("u_Contribution", self.contribution);
+        draw(destination, dc)
+    }
+}
@@ -0,0 +1,453 @@
+mod effects;
+
+pub use self::effects::{Bloom, ColorGrading, Fxaa, Tonemapper, Tonemapping, Vignette};
+
+use std::any::Any;
+
+use crayon::prelude::*;
+use crayon::utils::hash::FastHashMap;
+use failure::Error;
+
+use super::camera::Camera;
+use super::{srgb_encoding, SRGB_FS};
+
+// The shader chunk of `tonemap`, which maps colors with `Tonemapper::Reinhard`, or
+// `Tonemapper::Aces` if `ACES` is defined.
+const TONEMAP_FS: &str = include_str!("shaders/tonemap.fs");
+
+// The shader chunk of `lookup`, which remaps colors with the strip lookup table in
+// uniform `u_Lut` of `u_LutSize`^3 colors.
+const LUT_FS: &str = include_str!("shaders/lut.fs");
+
+/// An effect of `PostProcessing`, which draws the `source` into `destination` usually
+/// with full-screen quads. Custom effects could be inserted into the stack by
+/// implementing this trait.
+pub trait PostEffect {
+    /// Draws `source` into `destination`, both of them have the same `dimensions` in
+    /// pixels.
+    fn apply(
+        &mut self,
+        source: RenderTextureHandle,
+        destination: SurfaceHandle,
+        dimensions: Vector2<u32>,
+    ) -> Result<(), Error>;
+}
+
+// The effects are stored as `Any` too, so they could be downcasted in `effect_mut`.
+trait AnyPostEffect: PostEffect {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: PostEffect + 'static> AnyPostEffect for T {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A stack of post-processing effects.
+///
+/// The cameras render into the internal render texture by setting their surfaces with
+/// the one returned from `prepare`, and then the effects are applied in sequence by
+/// `submit`, the last one draws into the window framebuffer.
+///
+/// With `set_hdr`, the internal render textures are floating-point so the lighting is not
+/// clamped before post-processing, and the colors are mapped into displayable range with
+/// the tonemapper when resolving into the window framebuffer.
+///
+/// The effects work in linear space, the colors are encoded into sRGB space at last if
+/// the window framebuffer is not encoded by hardware.
+///
+/// The `ColorControls` of the camera passed to `submit`, e.g. exposure compensation, white
+/// balance and the lookup table of color grading, are applied in the final resolve after
+/// tonemapping.
+///
+/// ```rust,ignore
+/// let mut stack = PostProcessing::new()?;
+/// stack.push("bloom", Bloom::new()?).push("fxaa", Fxaa::new()?);
+///
+/// // Once per frame.
+/// let surface = stack.prepare()?;
+/// scene.camera_mut(camera).unwrap().set_surface(surface);
+/// scene.draw();
+/// stack.submit(scene.camera(camera).unwrap())?;
+/// ```
+pub struct PostProcessing {
+    effects: Vec<PostEffectEntry>,
+    targets: Option<Targets>,
+    tonemapper: Option<Tonemapper>,
+    exposure: f32,
+    // The shaders of final resolve with different tonemappers, with or without lookup
+    // table.
+    resolves: FastHashMap<(Option<Tonemapper>, bool), ShaderHandle>,
+    output: SurfaceHandle,
+}
+
+struct PostEffectEntry {
+    name: String,
+    enable: bool,
+    effect: Box<dyn AnyPostEffect>,
+}
+
+impl Drop for PostProcessing {
+    fn drop(&mut self) {
+        for (_, v) in self.resolves.drain() {
+            video::delete_shader(v);
+        }
+
+        video::delete_surface(self.output);
+    }
+}
+
+impl PostProcessing {
+    /// Creates a new and empty `PostProcessing`.
+    pub fn new() -> Result<Self, Error> {
+        let mut resolves = FastHashMap::default();
+        resolves.insert((None, false), Self::create_resolve_shader(None, false)?);
+
+        let mut params = SurfaceParams::default();
+        params.set_clear(None, None, None);
+        let output = video::create_surface(params)?;
+
+        Ok(PostProcessing {
+            effects: Vec::new(),
+            targets: None,
+            tonemapper: None,
+            exposure: 1.0,
+            resolves: resolves,
+            output: output,
+        })
+    }
+
+    /// Pushes an effect at the end of stack.
+    pub fn push<T1, T2>(&mut self, name: T1, effect: T2) -> &mut Self
+    where
+        T1: Into<String>,
+        T2: PostEffect + 'static,
+    {
+        let len = self.effects.len();
+        self.insert(len, name, effect)
+    }
+
+    /// Inserts an effect at position `index` of stack.
+    pub fn insert<T1, T2>(&mut self, index: usize, name: T1, effect: T2) -> &mut Self
+    where
+        T1: Into<String>,
+        T2: PostEffect + 'static,
+    {
+        let entry = PostEffectEntry {
+            name: name.into(),
+            enable: true,
+            effect: Box::new(effect),
+        };
+
+        self.effects.insert(index, entry);
+        self
+    }
+
+    /// Removes the effect with `name`.
+    pub fn remove<T: AsRef<str>>(&mut self, name: T) {
+        self.effects.retain(|v| v.name != name.as_ref());
+    }
+
+    /// Checks if there is a effect with `name`.
+    #[inline]
+    pub fn has<T: AsRef<str>>(&self, name: T) -> bool {
+        self.effects.iter().any(|v| v.name == name.as_ref())
+    }
+
+    /// Gets the mutable reference of the effect with `name` and type `T`.
+    pub fn effect_mut<T1, T2>(&mut self, name: T1) -> Option<&mut T2>
+    where
+        T1: AsRef<str>,
+        T2: PostEffect + 'static,
+    {
+        self.effects
+            .iter_mut()
+            .filter(|v| v.name == name.as_ref())
+            .filter_map(|v| v.effect.as_any_mut().downcast_mut::<T2>())
+            .next()
+    }
+
+    /// Enables or disables the effect with `name`, effects are enabled by default.
+    pub fn set_enable<T: AsRef<str>>(&mut self, name: T, enable: bool) {
+        for v in &mut self.effects {
+            if v.name == name.as_ref() {
+                v.enable = enable;
+            }
+        }
+    }
+
+    /// Returns the names of effects in the order they are applied.
+    pub fn effects(&self) -> Vec<&str> {
+        self.effects.iter().map(|v| v.name.as_str()).collect()
+    }
+
+    /// Enables rendering in high dynamic range, the colors are mapped by `tonemapper`
+    /// after all the effects. `SRGBA8` or `RGBA8` is used if the floating-point render
+    /// textures are not supported by device, but the tonemapper still takes effects.
+    pub fn set_hdr(&mut self, tonemapper: Option<Tonemapper>) -> Result<(), Error> {
+        self.resolve_shader(tonemapper, false)?;
+        self.tonemapper = tonemapper;
+        Ok(())
+    }
+
+    /// Gets the tonemapper if rendering in high dynamic range.
+    #[inline]
+    pub fn hdr(&self) -> Option<Tonemapper> {
+        self.tonemapper
+    }
+
+    /// Sets the scale of colors before tonemapping, which only takes effects in high
+    /// dynamic range.
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure;
+    }
+
+    /// Makes sure the internal render textures match the size of window, and returns the
+    /// surface that the cameras should render into.
+    pub fn prepare(&mut self) -> Result<SurfaceHandle, Error> {
+        let dimensions = window::dimensions();
+        let dpr = window::device_pixel_ratio();
+        let dimensions = Vector2::new(
+            (dimensions.x as f32 * dpr) as u32,
+            (dimensions.y as f32 * dpr) as u32,
+        );
+
+        // The linear colors are kept in sRGB render textures if possible, which have more
+        // precision in dark colors.
+        let format = if self.tonemapper.is_some()
+            && video::is_render_texture_format_supported(RenderTextureFormat::RGBA16F)
+        {
+            RenderTextureFormat::RGBA16F
+        } else if video::is_render_texture_format_supported(RenderTextureFormat::SRGBA8) {
+            RenderTextureFormat::SRGBA8
+        } else {
+            RenderTextureFormat::RGBA8
+        };
+
+        if self.targets.as_ref().map(|v| (v.dimensions, v.format)) != Some((dimensions, format)) {
+            self.targets = None;
+            self.targets = Some(Targets::new(dimensions, format)?);
+        }
+
+        Ok(self.targets.as_ref().unwrap().scene)
+    }
+
+    /// Applies the enabled effects in sequence, the result of last one is drawn into the
+    /// window framebuffer. The final resolve, which applies the tonemapping of high
+    /// dynamic range and the color controls of `camera`, is skipped if there is nothing
+    /// to do and the colors are encoded into sRGB space by hardware.
+    pub fn submit(&mut self, camera: &Camera) -> Result<(), Error> {
+        if self.targets.is_none() {
+            return Ok(());
+        }
+
+        let controls = camera.color_controls();
+        let lut = controls.lut;
+        let shader = self.resolve_shader(self.tonemapper, lut.is_some())?;
+        let targets = self.targets.as_ref().unwrap();
+
+        let encoding = srgb_encoding(self.output);
+        let resolve = self.tonemapper.is_some() || encoding > 0.0 || !controls.is_neutral();
+
+        let mut effects: Vec<&mut dyn AnyPostEffect> = self
+            .effects
+            .iter_mut()
+            .filter(|v| v.enable)
+            .map(|v| v.effect.as_mut())
+            .collect();
+
+        let len = effects.len();
+        for (i, v) in effects.iter_mut().enumerate() {
+            let source = targets.colors[i % 2];
+            let destination = if i == len - 1 && !resolve {
+                self.output
+            } else {
+                targets.surfaces[(i + 1) % 2]
+            };
+
+            v.apply(source, destination, targets.dimensions)?;
+        }
+
+        if len == 0 || resolve {
+            let exposure = if self.tonemapper.is_some() {
+                self.exposure * controls.exposure()
+            } else {
+                controls.exposure()
+            };
+
+            let mut dc = Draw::new(shader, crate::default().quad);
+            dc.set_uniform_variable("u_Source", targets.colors[len % 2]);
+            dc.set_uniform_variable("u_Exposure", exposure);
+            dc.set_uniform_variable("u_WhiteBalance", controls.white_balance());
+            dc.set_uniform_variable("u_EncodeSRGB", encoding);
+
+            if let Some((texture, size)) = lut {
+                dc.set_uniform_variable("u_Lut", texture);
+                dc.set_uniform_variable("u_LutSize", size as f32);
+                dc.set_uniform_variable("u_LutContribution", controls.lut_contribution);
+            }
+
+            let mut cmds = CommandBuffer::new();
+            cmds.draw(dc);
+            cmds.submit(self.output)?;
+        }
+
+        Ok(())
+    }
+
+    fn resolve_shader(
+        &mut self,
+        tonemapper: Option<Tonemapper>,
+        lut: bool,
+    ) -> Result<ShaderHandle, Error> {
+        if let Some(&v) = self.resolves.get(&(tonemapper, lut)) {
+            return Ok(v);
+        }
+
+        let shader = Self::create_resolve_shader(tonemapper, lut)?;
+        self.resolves.insert((tonemapper, lut), shader);
+        Ok(shader)
+    }
+
+    fn create_resolve_shader(
+        tonemapper: Option<Tonemapper>,
+        lut: bool,
+    ) -> Result<ShaderHandle, Error> {
+        let mut uniforms = UniformVariableLayout::build()
+            .with("u_Source", UniformVariableType::RenderTexture)
+            .with("u_Exposure", UniformVariableType::F32)
+            .with("u_WhiteBalance", UniformVariableType::Vector3f)
+            .with("u_EncodeSRGB", UniformVariableType::F32);
+
+        let mut defines = Vec::new();
+        let mut fs = SRGB_FS.to_owned();
+
+        if let Some(v) = tonemapper {
+            defines.push("TONEMAP");
+            if v == Tonemapper::Aces {
+                defines.push("ACES");
+            }
+
+            fs.push_str(TONEMAP_FS);
+        }
+
+        if lut {
+            uniforms = uniforms
+                .with("u_Lut", UniformVariableType::Texture)
+                .with("u_LutSize", UniformVariableType::F32)
+                .with("u_LutContribution", UniformVariableType::F32);
+
+            defines.push("LUT");
+            fs.push_str(LUT_FS);
+        }
+
+        fs.push_str(include_str!("shaders/resolve.fs"));
+        create_fullscreen_shader(uniforms.finish(), &defines, &fs)
+    }
+}
+
+/// Creates a shader that draws the full-screen quad `WorldDefaultResources::quad`, with
+/// the varying `v_Texcoord` in fragment shader. The `defines` are prepended to the
+/// sources.
+pub fn create_fullscreen_shader(
+    uniforms: UniformVariableLayout,
+    defines: &[&str],
+    fs: &str,
+) -> Result<ShaderHandle, Error> {
+    let attributes = AttributeLayout::build()
+        .with(Attribute::Position, 3)
+        .with(Attribute::Texcoord0, 2)
+        .finish();
+
+    let mut params = ShaderParams::default();
+    params.attributes = attributes;
+    params.uniforms = uniforms;
+
+    let vs = format!(
+        "
+        #version 100
+        precision lowp float;
+        {0}
+        ",
+        include_str!("shaders/fullscreen.vs")
+    );
+
+    let defines: String = defines.iter().map(|v| format!("#define {}\n", v)).collect();
+
+    let fs = format!(
+        "
+        #version 100
+        precision mediump float;
+        {0}
+        {1}
+        ",
+        defines, fs
+    );
+
+    Ok(video::create_shader(params, vs, fs)?)
+}
+
+/// The render textures that are drawn by effects alternately.
+struct Targets {
+    dimensions: Vector2<u32>,
+    format: RenderTextureFormat,
+    colors: [RenderTextureHandle; 2],
+    depth: RenderTextureHandle,
+    // The surface that cameras render into, with depth attachment.
+    scene: SurfaceHandle,
+    surfaces: [SurfaceHandle; 2],
+}
+
+impl Targets {
+    fn new(dimensions: Vector2<u32>, format: RenderTextureFormat) -> Result<Self, Error> {
+        let mut params = RenderTextureParams::default();
+        params.format = format;
+        params.dimensions = dimensions;
+
+        let colors = [
+            video::create_render_texture(params)?,
+            video::create_render_texture(params)?,
+        ];
+
+        params.format = RenderTextureFormat::Depth24;
+        params.sampler = false;
+        let depth = video::create_render_texture(params)?;
+
+        let mut params = SurfaceParams::default();
+        params.set_attachments(&[colors[0]], depth)?;
+        let scene = video::create_surface(params)?;
+
+        let mut surfaces = [scene; 2];
+        for (i, v) in surfaces.iter_mut().enumerate() {
+            let mut params = SurfaceParams::default();
+            params.set_attachments(&[colors[i]], None)?;
+            params.set_clear(None, None, None);
+            *v = video::create_surface(params)?;
+        }
+
+        Ok(Targets {
+            dimensions: dimensions,
+            format: format,
+            colors: colors,
+            depth: depth,
+            scene: scene,
+            surfaces: surfaces,
+        })
+    }
+}
+
+impl Drop for Targets {
+    fn drop(&mut self) {
+        video::delete_surface(self.scene);
+        for &v in &self.surfaces {
+            video::delete_surface(v);
+        }
+
+        for &v in &self.colors {
+            video::delete_render_texture(v);
+        }
+
+        video::delete_render_texture(self.depth);
+    }
+}
@@ -0,0 +1,42 @@
+varying vec2 v_Texcoord;
+
+uniform sampler2D u_Source;
+
+#if defined(THRESHOLD)
+
+uniform float u_Threshold;
+
+void main() {
+    vec3 color = texture2D(u_Source, v_Texcoord).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution = max(brightness - u_Threshold, 0.0) / max(brightness, 0.0001);
+    gl_FragColor = vec4(color * contribution, 1.0);
+}
+
+#elif defined(BLUR)
+
+// The offset of texels in the direction of blur.
+uniform vec2 u_Direction;
+
+void main() {
+    // 9-tap gaussian blur with linear sampling.
+    vec3 color = texture2D(u_Source, v_Texcoord).rgb * 0.2270270270;
+    color += texture2D(u_Source, v_Texcoord + u_Direction * 1.3846153846).rgb * 0.3162162162;
+    color += texture2D(u_Source, v_Texcoord - u_Direction * 1.3846153846).rgb * 0.3162162162;
+    color += texture2D(u_Source, v_Texcoord + u_Direction * 3.2307692308).rgb * 0.0702702703;
+    color += texture2D(u_Source, v_Texcoord - u_Direction * 3.2307692308).rgb * 0.0702702703;
+    gl_FragColor = vec4(color, 1.0);
+}
+
+#else
+
+uniform sampler2D u_Bloom;
+uniform float u_Intensity;
+
+void main() {
+    vec4 color = texture2D(u_Source, v_Texcoord);
+    vec3 bloom = texture2D(u_Bloom, v_Texcoord).rgb;
+    gl_FragColor = vec4(color.rgb + bloom * u_Intensity, color.a);
+}
+
+#endif
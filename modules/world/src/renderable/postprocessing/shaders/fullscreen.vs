@@ -0,0 +1,10 @@
+attribute vec3 Position;
+attribute vec2 Texcoord0;
+
+varying vec2 v_Texcoord;
+
+void main() {
+    // The default quad spans [-0.5, 0.5], scales it to cover the whole screen.
+    gl_Position = vec4(Position.xy * 2.0, 0.0, 1.0);
+    v_Texcoord = Texcoord0;
+}
@@ -0,0 +1,21 @@
+uniform sampler2D u_Lut;
+uniform float u_LutSize;
+
+// Looks up the 3D LUT which is unwrapped into a strip of `size` slices horizontally,
+// each slice is a `size` x `size` square of red and green with the same blue.
+vec3 lookup(vec3 color) {
+    float size = u_LutSize;
+    color = clamp(color, 0.0, 1.0);
+
+    float blue = color.b * (size - 1.0);
+    float slice0 = floor(blue);
+    float slice1 = min(slice0 + 1.0, size - 1.0);
+
+    vec2 uv = vec2(
+        (color.r * (size - 1.0) + 0.5) / (size * size),
+        (color.g * (size - 1.0) + 0.5) / size);
+
+    vec3 c0 = texture2D(u_Lut, uv + vec2(slice0 / size, 0.0)).rgb;
+    vec3 c1 = texture2D(u_Lut, uv + vec2(slice1 / size, 0.0)).rgb;
+    return mix(c0, c1, blue - slice0);
+}
@@ -0,0 +1,24 @@
+varying vec2 v_Texcoord;
+
+uniform sampler2D u_Source;
+uniform float u_Exposure;
+uniform vec3 u_WhiteBalance;
+
+#if defined(LUT)
+uniform float u_LutContribution;
+#endif
+
+void main() {
+    vec4 color = texture2D(u_Source, v_Texcoord);
+    vec3 rgb = color.rgb * u_WhiteBalance * u_Exposure;
+
+#if defined(TONEMAP)
+    rgb = tonemap(rgb);
+#endif
+
+#if defined(LUT)
+    rgb = mix(rgb, lookup(rgb), u_LutContribution);
+#endif
+
+    gl_FragColor = vec4(EncodeSRGB(rgb), color.a);
+}
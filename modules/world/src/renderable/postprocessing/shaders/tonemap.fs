@@ -0,0 +1,10 @@
+vec3 tonemap(vec3 color) {
+#if defined(ACES)
+    // The fitted curve of ACES filmic tonemapping by Krzysztof Narkowicz.
+    color *= 0.6;
+    vec3 v = (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14);
+    return clamp(v, 0.0, 1.0);
+#else
+    return color / (color + vec3(1.0));
+#endif
+}
@@ -0,0 +1,9 @@
+varying vec2 v_Texcoord;
+
+uniform sampler2D u_Source;
+uniform float u_Exposure;
+
+void main() {
+    vec4 color = texture2D(u_Source, v_Texcoord);
+    gl_FragColor = vec4(EncodeSRGB(tonemap(color.rgb * u_Exposure)), color.a);
+}
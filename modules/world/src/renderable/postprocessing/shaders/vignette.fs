@@ -0,0 +1,13 @@
+varying vec2 v_Texcoord;
+
+uniform sampler2D u_Source;
+uniform vec3 u_Color;
+uniform float u_Intensity;
+uniform float u_Smoothness;
+
+void main() {
+    vec4 color = texture2D(u_Source, v_Texcoord);
+    vec2 d = (v_Texcoord - 0.5) * u_Intensity;
+    float factor = pow(clamp(1.0 - dot(d, d), 0.0, 1.0), u_Smoothness);
+    gl_FragColor = vec4(mix(u_Color, color.rgb, factor), color.a);
+}
@@ -0,0 +1,106 @@
+use crayon::prelude::*;
+use crayon::utils::hash_value::HashValue;
+
+/// A set of uniform variables that override the ones of material per `MeshRenderer`,
+/// e.g. tints the mesh red when it's hit, without duplicating the whole material.
+///
+/// The variables are applied when building the draw calls of mesh, after the parameters
+/// of material. Variables that are not declared by the shader of renderer, or whose types
+/// do not match the declarations, are ignored.
+///
+/// ```rust,ignore
+/// let mut mr = scene.renderable.mesh_mut(ent).unwrap();
+/// mr.properties.set("u_Diffuse", [1.0, 0.0, 0.0]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MaterialPropertyBlock {
+    variables: Vec<(HashValue<str>, UniformVariable)>,
+}
+
+impl MaterialPropertyBlock {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the value of uniform variable `name`.
+    pub fn set<T1, T2>(&mut self, name: T1, variable: T2) -> &mut Self
+    where
+        T1: Into<HashValue<str>>,
+        T2: Into<UniformVariable>,
+    {
+        let (name, variable) = (name.into(), variable.into());
+        match self.variables.iter_mut().find(|v| v.0 == name) {
+            Some(v) => v.1 = variable,
+            None => self.variables.push((name, variable)),
+        }
+
+        self
+    }
+
+    /// Gets the value of uniform variable `name`.
+    pub fn get<T: Into<HashValue<str>>>(&self, name: T) -> Option<UniformVariable> {
+        let name = name.into();
+        self.variables.iter().find(|v| v.0 == name).map(|v| v.1)
+    }
+
+    /// Removes the uniform variable `name`, so the value of material is used again.
+    pub fn remove<T: Into<HashValue<str>>>(&mut self, name: T) -> Option<UniformVariable> {
+        let name = name.into();
+        let index = self.variables.iter().position(|v| v.0 == name)?;
+        Some(self.variables.remove(index).1)
+    }
+
+    /// Removes all the uniform variables.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.variables.clear();
+    }
+
+    /// Gets the number of uniform variables.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    /// Checks if there is no uniform variable.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    /// Sets the uniform variables that are declared in `layout` into draw call.
+    pub(crate) fn apply(&self, layout: &UniformVariableLayout, dc: &mut Draw) {
+        for &(name, v) in &self.variables {
+            if layout.variable_type(name) == Some(v.variable_type()) {
+                dc.set_uniform_variable(name, v);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn properties() {
+        let mut block = MaterialPropertyBlock::new();
+        block
+            .set("u_Diffuse", [1.0, 0.0, 0.0])
+            .set("u_Emission", 2.0);
+        block.set("u_Emission", 4.0);
+        assert_eq!(block.len(), 2);
+
+        match block.get("u_Emission") {
+            Some(UniformVariable::F32(v)) => assert_eq!(v, 4.0),
+            v => panic!("unexpected variable {:?}.", v),
+        }
+
+        assert!(block.remove("u_Emission").is_some());
+        assert!(block.remove("u_Emission").is_none());
+        assert!(block.get("u_Emission").is_none());
+
+        block.clear();
+        assert!(block.is_empty());
+    }
+}
@@ -0,0 +1,90 @@
+//! The queues of materials, which decide the order of drawing meshes.
+
+use crayon::video::assets::shader::ShaderHandle;
+use crayon::video::sort_key::{DepthOrder, SortKey, SortKeyBuilder};
+
+/// The queue that meshes of material are drawn in. Queues are drawn from `Opaque` to
+/// `Overlay`, the meshes in `Opaque` and `AlphaTest` are sorted from front to back to
+/// reduce overdraw, and the meshes in `Transparent` and `Overlay` are sorted from back
+/// to front to be blended correctly.
+///
+/// The queue only decides the order of drawing, the blending is decided by the render
+/// state of shader.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderQueue {
+    Opaque,
+    /// Meshes which discard some fragments, e.g. foliage.
+    AlphaTest,
+    /// Meshes which are blended with the background, they are drawn after skyboxes.
+    Transparent,
+    /// Meshes which are drawn on top of everything else, e.g. the lens flares.
+    Overlay,
+}
+
+impl Default for RenderQueue {
+    fn default() -> Self {
+        RenderQueue::Opaque
+    }
+}
+
+impl RenderQueue {
+    /// Checks if meshes in this queue are sorted from back to front.
+    #[inline]
+    pub fn is_transparent(self) -> bool {
+        self >= RenderQueue::Transparent
+    }
+}
+
+/// The key of draw calls in `DrawCommandBuffer`, which sorts by the queue first, then
+/// the distance to camera and the shader.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DrawOrder(SortKey);
+
+impl DrawOrder {
+    pub fn new(queue: RenderQueue, shader: ShaderHandle, distance: f32) -> Self {
+        DrawOrder::build(queue, distance)
+            .with_shader(shader)
+            .finish()
+            .into()
+    }
+
+    /// Starts a `SortKey` with the queue and the distance to camera, which is encoded from
+    /// front to back or back to front depends on the queue.
+    pub fn build(queue: RenderQueue, distance: f32) -> SortKeyBuilder {
+        let order = if queue.is_transparent() {
+            DepthOrder::BackToFront
+        } else {
+            DepthOrder::FrontToBack
+        };
+
+        SortKey::build()
+            .with_translucency(queue as u8)
+            .with_depth(distance, order)
+    }
+}
+
+impl From<SortKey> for DrawOrder {
+    fn from(key: SortKey) -> Self {
+        DrawOrder(key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crayon::utils::handle::HandleLike;
+
+    #[test]
+    fn order() {
+        let shader = ShaderHandle::new(1, 1);
+        let opaque = |v| DrawOrder::new(RenderQueue::Opaque, shader, v);
+        let transparent = |v| DrawOrder::new(RenderQueue::Transparent, shader, v);
+
+        assert!(opaque(0.5) < opaque(1.0));
+        assert!(opaque(100.0) < DrawOrder::new(RenderQueue::AlphaTest, shader, 0.0));
+        assert!(opaque(100.0) < transparent(100.0));
+        assert!(transparent(1.0) < transparent(0.5));
+        assert!(transparent(0.0) < DrawOrder::new(RenderQueue::Overlay, shader, 100.0));
+        assert!(opaque(1.0) < DrawOrder::new(RenderQueue::Opaque, ShaderHandle::new(2, 1), 1.0));
+    }
+}
@@ -0,0 +1,13 @@
+// The attenuation of point and spot lights. The `xyz` of parameters are the coefficients
+// of linear falloff, and the `w` is the radius of windowed inverse-square falloff.
+float CalculateAttenuation(vec4 params, float distance)
+{
+    if (params.w > 0.0)
+    {
+        float f = distance / params.w;
+        float window = clamp(1.0 - f * f * f * f, 0.0, 1.0);
+        return window * window / max(distance * distance, 0.0001);
+    }
+
+    return params.x + params.y * distance + params.z * (distance * distance);
+}
@@ -0,0 +1,33 @@
+// The columns are the color, the mode and density, and the start and end of linear fog.
+// The mode is 0 for none, 1 for linear, 2 for exponential and 3 for exponential squared.
+uniform mat3 u_Fog;
+
+// Gets the visibility of the fragment at `distance` from eye, which is 1 if it's not
+// fogged at all.
+float FogFactor(float distance)
+{
+    float f = 1.0;
+    float mode = u_Fog[1].x;
+    float density = u_Fog[1].y;
+
+    if (mode > 2.5)
+    {
+        float d = density * distance;
+        f = exp(-d * d);
+    }
+    else if (mode > 1.5)
+    {
+        f = exp(-density * distance);
+    }
+    else if (mode > 0.5)
+    {
+        f = (u_Fog[2].y - distance) / max(u_Fog[2].y - u_Fog[2].x, 0.0001);
+    }
+
+    return clamp(f, 0.0, 1.0);
+}
+
+vec3 ApplyFog(vec3 color, float distance)
+{
+    return mix(u_Fog[0], color, FogFactor(distance));
+}
@@ -0,0 +1,44 @@
+#ifdef SKINNING
+
+attribute highp vec4 Indices;
+attribute highp vec4 Weight;
+
+#ifdef SKINNING_TEXTURE
+
+// The columns of bone matrices are packed into the texels of a single row.
+uniform highp sampler2D u_BoneTexture;
+
+highp mat4 BoneMatrix(highp float index) {
+    highp float texel = 1.0 / float(MAX_BONES * 4);
+    highp float x = (index * 4.0 + 0.5) * texel;
+    return mat4(
+        texture2D(u_BoneTexture, vec2(x, 0.5)),
+        texture2D(u_BoneTexture, vec2(x + texel, 0.5)),
+        texture2D(u_BoneTexture, vec2(x + texel * 2.0, 0.5)),
+        texture2D(u_BoneTexture, vec2(x + texel * 3.0, 0.5)));
+}
+
+#else
+
+uniform highp mat4 u_Bones[MAX_BONES];
+
+highp mat4 BoneMatrix(highp float index) {
+    return u_Bones[int(index + 0.5)];
+}
+
+#endif
+
+highp mat4 SkinMatrix() {
+    return BoneMatrix(Indices.x) * Weight.x
+        + BoneMatrix(Indices.y) * Weight.y
+        + BoneMatrix(Indices.z) * Weight.z
+        + BoneMatrix(Indices.w) * Weight.w;
+}
+
+#else
+
+highp mat4 SkinMatrix() {
+    return mat4(1.0);
+}
+
+#endif
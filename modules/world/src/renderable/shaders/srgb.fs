@@ -0,0 +1,15 @@
+// Encodes the linear colors into sRGB space if positive, which is set when the surface
+// is not encoded by hardware.
+uniform float u_EncodeSRGB;
+
+vec3 EncodeSRGB(vec3 color)
+{
+    return u_EncodeSRGB > 0.0 ? pow(max(color, vec3(0.0)), vec3(1.0 / 2.2)) : color;
+}
+
+// Decodes the colors sampled from textures into linear space if `decode` is positive,
+// which is set unless they are decoded by hardware.
+vec3 DecodeSRGB(vec3 color, float decode)
+{
+    return decode > 0.0 ? pow(color, vec3(2.2)) : color;
+}
@@ -0,0 +1,152 @@
+use crayon::math::prelude::*;
+
+use renderable::prelude::{Camera, MeshRenderer};
+
+/// The maximum number of cascades of directional light.
+pub const MAX_SHADOW_CASCADES: usize = 4;
+
+/// The schemes to split the view frustum of camera into cascades.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum CascadeSplits {
+    /// Blends between the uniform and logarithmic split schemes with a factor in `[0, 1]`,
+    /// `0.0` is uniform and `1.0` is logarithmic.
+    Practical(f32),
+    /// The far distances of cascades, which are normalized by the shadow distance.
+    Manual([f32; MAX_SHADOW_CASCADES]),
+}
+
+/// The cascades of directional light, each of them is rendered into a separated shadow
+/// map which covers a slice of the view frustum of camera.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ShadowCascades {
+    /// The number of cascades, which is clamped into `[1, MAX_SHADOW_CASCADES]`.
+    pub count: usize,
+    /// The scheme to split the view frustum.
+    pub splits: CascadeSplits,
+    /// The maximum distance from camera that shadows are visible, which is clamped by
+    /// the far clip plane of camera.
+    pub distance: f32,
+}
+
+impl Default for ShadowCascades {
+    fn default() -> Self {
+        ShadowCascades {
+            count: MAX_SHADOW_CASCADES,
+            splits: CascadeSplits::Practical(0.75),
+            distance: 50.0,
+        }
+    }
+}
+
+impl ShadowCascades {
+    /// Gets the far distances of cascades in the view space of camera, whose clip planes
+    /// are `near` and `far`.
+    pub fn split_distances(&self, near: f32, far: f32) -> Vec<f32> {
+        let count = self.count.max(1).min(MAX_SHADOW_CASCADES);
+        let near = near.max(::std::f32::EPSILON);
+        let far = far.min(self.distance).max(near);
+
+        let mut distances = Vec::with_capacity(count);
+        for i in 0..count {
+            let v = match self.splits {
+                CascadeSplits::Practical(lambda) => {
+                    let lambda = lambda.max(0.0).min(1.0);
+                    let p = (i + 1) as f32 / count as f32;
+                    let log = near * (far / near).powf(p);
+                    let uniform = near + (far - near) * p;
+                    lambda * log + (1.0 - lambda) * uniform
+                }
+                CascadeSplits::Manual(splits) => splits[i] * far,
+            };
+
+            let min = distances.last().cloned().unwrap_or(near);
+            distances.push(v.max(min).min(far));
+        }
+
+        distances
+    }
+}
+
+/// Gets the minimum depth of shadow casters in light space, which is used to extend the
+/// projection of cascades towards the light.
+pub(crate) fn casters_depth(rotation: Quaternion<f32>, casters: &[&MeshRenderer]) -> f32 {
+    let inv_rotation = rotation.invert();
+    let mut depth = ::std::f32::MAX;
+
+    for v in casters {
+        let (min, max) = match v.bounds {
+            Some((_, aabb)) => (aabb.min, aabb.max),
+            None => continue,
+        };
+
+        for i in 0..8 {
+            let x = if i & 1 == 0 { min.x } else { max.x };
+            let y = if i & 2 == 0 { min.y } else { max.y };
+            let z = if i & 4 == 0 { min.z } else { max.z };
+
+            let p = v.transform.transform_point([x, y, z]);
+            depth = depth.min((inv_rotation * p).z);
+        }
+    }
+
+    depth
+}
+
+/// Computes the view-projection matrix of cascade, which covers the slice `[near, far]`
+/// of the view frustum of camera.
+///
+/// The cascade is fitted to the bounding sphere of the slice, and snapped to texels of
+/// shadow map, so the shadow edges would not shimmer when the camera moves or rotates.
+pub(crate) fn fit(
+    camera: &Camera,
+    rotation: Quaternion<f32>,
+    (near, far): (f32, f32),
+    casters_depth: f32,
+    resolution: u32,
+) -> Matrix4<f32> {
+    let (sx, sy) = match camera.projection() {
+        Projection::Ortho { width, height, .. } => (width * 0.5, height * 0.5),
+        Projection::Perspective { fovy, aspect, .. } => {
+            let t = Rad::tan(fovy * 0.5);
+            (t * aspect, t)
+        }
+    };
+
+    let mut corners = [Vector3::new(0.0, 0.0, 0.0); 8];
+    for (i, &d) in [near, far].iter().enumerate() {
+        let (hw, hh) = match camera.projection() {
+            Projection::Ortho { .. } => (sx, sy),
+            Projection::Perspective { .. } => (sx * d, sy * d),
+        };
+
+        let points = [[-hw, -hh], [hw, -hh], [hw, hh], [-hw, hh]];
+        for (j, p) in points.iter().enumerate() {
+            let v = Vector3::new(p[0], p[1], d);
+            corners[i * 4 + j] = camera.transform.rotation * v + camera.transform.position;
+        }
+    }
+
+    let center = corners.iter().fold(Vector3::zero(), |acc, &v| acc + v) / 8.0;
+    let radius = corners
+        .iter()
+        .map(|&v| (v - center).magnitude())
+        .fold(0.0f32, f32::max);
+
+    // Quantizes the radius, so the size of cascade keeps unchanged when camera rotates.
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    // Snaps the center to texels in light space.
+    let inv_rotation = rotation.invert();
+    let texel = 2.0 * radius / resolution.max(1) as f32;
+    let mut center = inv_rotation * center;
+    center.x = (center.x / texel).floor() * texel;
+    center.y = (center.y / texel).floor() * texel;
+
+    let z = (center.z - radius).min(casters_depth);
+    let depth = (center.z + radius - z).max(::std::f32::EPSILON);
+
+    let eye = Vector3::new(center.x, center.y, z);
+    let view = Matrix4::from_translation(-eye) * Matrix4::from(inv_rotation);
+    let projection = Projection::ortho_matrix(2.0 * radius, 2.0 * radius, 0.0, depth);
+    projection * view
+}
@@ -0,0 +1,30 @@
+use crayon::math::prelude::*;
+
+/// The axes `(right, up, forward)` of cube faces in the order of `+X`, `-X`, `+Y`, `-Y`,
+/// `+Z` and `-Z`. The faces are packed into a `3x2` texture atlas from left to right and
+/// bottom to top, and the shader of receivers must follow the same layout.
+const CUBE_FACES: [[[f32; 3]; 3]; 6] = [
+    [[0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]],
+    [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]],
+    [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]],
+    [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]],
+    [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    [[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]],
+];
+
+/// Computes the view-projection matrix of cube face at `position`.
+pub(crate) fn face(position: Vector3<f32>, index: usize, near: f32, far: f32) -> Matrix4<f32> {
+    let [r, u, f] = CUBE_FACES[index];
+
+    // The rows of rotation are the axes of face.
+    let rotation = Matrix4::from_cols(
+        Vector4::new(r[0], u[0], f[0], 0.0),
+        Vector4::new(r[1], u[1], f[1], 0.0),
+        Vector4::new(r[2], u[2], f[2], 0.0),
+        Vector4::new(0.0, 0.0, 0.0, 1.0),
+    );
+
+    let view = rotation * Matrix4::from_translation(-position);
+    let projection = Projection::perspective_matrix(Deg(90.0).into(), 1.0, near, far);
+    projection * view
+}
@@ -0,0 +1,725 @@
+//! Cascaded shadow maps of directional lights, cube shadow maps of point lights and
+//! perspective shadow maps of spot lights.
+
+mod cascade;
+mod cube;
+pub(crate) mod spot;
+pub use self::cascade::{CascadeSplits, ShadowCascades, MAX_SHADOW_CASCADES};
+
+use crayon::prelude::*;
+use crayon::utils::hash::FastHashMap;
+use failure::Error;
+
+use super::{Camera, Lit, LitSource, MeshRenderer};
+
+/// The maximum radius of percentage-closer filtering kernel in texels.
+pub const MAX_PCF_RADIUS: u32 = 2;
+
+/// The shader chunk that samples shadows in receivers, which requires the definitions
+/// of `MAX_SHADOW_CASCADES` and `MAX_PCF_RADIUS`.
+pub(crate) const RECEIVER_FS: &str = include_str!("shaders/receiver.fs");
+
+const SHADOW_MATRICES: [&str; MAX_SHADOW_CASCADES] = [
+    "u_ShadowMatrix[0]",
+    "u_ShadowMatrix[1]",
+    "u_ShadowMatrix[2]",
+    "u_ShadowMatrix[3]",
+];
+
+/// The builder of `Shadow`, which exposes the resolution, bias and filtering parameters
+/// of shadow maps.
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowBuilder {
+    resolution: u32,
+    point_resolution: u32,
+    spot_resolution: u32,
+    bias: f32,
+    pcf_radius: u32,
+    pcf_spread: f32,
+}
+
+impl Default for ShadowBuilder {
+    fn default() -> Self {
+        ShadowBuilder {
+            resolution: 1024,
+            point_resolution: 512,
+            spot_resolution: 1024,
+            bias: 0.005,
+            pcf_radius: 1,
+            pcf_spread: 1.0,
+        }
+    }
+}
+
+impl ShadowBuilder {
+    /// Sets the resolution of each cascade in pixels.
+    #[inline]
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    /// Sets the resolution of each cube face of point light in pixels.
+    #[inline]
+    pub fn with_point_resolution(mut self, resolution: u32) -> Self {
+        self.point_resolution = resolution.max(1);
+        self
+    }
+
+    /// Sets the resolution of the shadow map of spot light in pixels.
+    #[inline]
+    pub fn with_spot_resolution(mut self, resolution: u32) -> Self {
+        self.spot_resolution = resolution.max(1);
+        self
+    }
+
+    /// Sets the depth bias, which is scaled by the slope of receivers to prevent shadow
+    /// acne.
+    #[inline]
+    pub fn with_bias(mut self, bias: f32) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    /// Sets the percentage-closer filtering parameters. `radius` is the number of texels
+    /// sampled on each side, which is clamped to `MAX_PCF_RADIUS` and `0` produces hard
+    /// shadows. `spread` scales the distance between samples in texels.
+    #[inline]
+    pub fn with_pcf(mut self, radius: u32, spread: f32) -> Self {
+        self.pcf_radius = radius.min(MAX_PCF_RADIUS);
+        self.pcf_spread = spread;
+        self
+    }
+
+    /// Creates the shadow maps.
+    pub fn finish(self) -> Result<Shadow, Error> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_MVPMatrix", UniformVariableType::Matrix4f)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+        params.state.color_write = (false, false, false, false);
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+
+        let vs = format!(
+            "#version 100\nprecision lowp float;\n{}",
+            include_str!("shaders/shadow.vs")
+        );
+
+        let fs = format!(
+            "#version 100\nprecision lowp float;\n{}",
+            include_str!("shaders/shadow.fs")
+        );
+
+        let caster_shader = video::create_shader(params.clone(), vs.clone(), fs.clone())?;
+
+        // The shadow maps of cameras rendering into the same surface are only cleared
+        // once per frame. So cascades are cleared by drawing quads at the far plane.
+        params.state.depth_test = Comparison::Always;
+        let clear_shader = match video::create_shader(params, vs, fs) {
+            Ok(v) => v,
+            Err(err) => {
+                video::delete_shader(caster_shader);
+                return Err(err.into());
+            }
+        };
+
+        Ok(Shadow {
+            params: self,
+            caster_shader: caster_shader,
+            clear_shader: clear_shader,
+            cmds: CommandBuffer::new(),
+            maps: FastHashMap::default(),
+        })
+    }
+}
+
+/// The shadow maps of lights rendered for the cameras of one target surface.
+struct ShadowMaps {
+    texture: RenderTextureHandle,
+    surface: SurfaceHandle,
+    point_texture: RenderTextureHandle,
+    point_surface: SurfaceHandle,
+    spot_texture: RenderTextureHandle,
+    spot_surface: SurfaceHandle,
+    cascades: Vec<(Matrix4<f32>, f32)>,
+    dir: Option<Quaternion<f32>>,
+    point: Option<(Vector3<f32>, f32, f32)>,
+    spot: Option<(Vector3<f32>, Quaternion<f32>, Matrix4<f32>)>,
+}
+
+impl ShadowMaps {
+    fn new(params: &ShadowBuilder) -> Result<Self, Error> {
+        let resolution = params.resolution * 2;
+        let (texture, surface) = depth_target(Vector2::new(resolution, resolution))?;
+
+        // The six faces of cube are packed into a 3x2 atlas, since cube render textures
+        // are not available on all platforms.
+        let resolution = params.point_resolution;
+        let point = depth_target(Vector2::new(resolution * 3, resolution * 2));
+
+        let resolution = params.spot_resolution;
+        let spot = depth_target(Vector2::new(resolution, resolution));
+
+        match (point, spot) {
+            (Ok(point), Ok(spot)) => Ok(ShadowMaps {
+                texture: texture,
+                surface: surface,
+                point_texture: point.0,
+                point_surface: point.1,
+                spot_texture: spot.0,
+                spot_surface: spot.1,
+                cascades: Vec::new(),
+                dir: None,
+                point: None,
+                spot: None,
+            }),
+            (point, spot) => {
+                let target = (texture, surface);
+                for &(texture, surface) in point.iter().chain(spot.iter()).chain(Some(&target)) {
+                    video::delete_surface(surface);
+                    video::delete_render_texture(texture);
+                }
+
+                Err(point.and(spot).err().unwrap())
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.cascades.clear();
+        self.dir = None;
+        self.point = None;
+        self.spot = None;
+    }
+}
+
+impl Drop for ShadowMaps {
+    fn drop(&mut self) {
+        video::delete_surface(self.surface);
+        video::delete_render_texture(self.texture);
+        video::delete_surface(self.point_surface);
+        video::delete_render_texture(self.point_texture);
+        video::delete_surface(self.spot_surface);
+        video::delete_render_texture(self.spot_texture);
+    }
+}
+
+fn depth_target(dimensions: Vector2<u32>) -> Result<(RenderTextureHandle, SurfaceHandle), Error> {
+    let mut params = RenderTextureParams::default();
+    params.format = RenderTextureFormat::Depth24;
+    params.filter = TextureFilter::Nearest;
+    params.dimensions = dimensions;
+    let texture = video::create_render_texture(params)?;
+
+    let mut params = SurfaceParams::default();
+    params.set_clear(None, 1.0, None);
+    let surface = params
+        .set_attachments(&[], texture)
+        .map_err(Error::from)
+        .and_then(|_| Ok(video::create_surface(params)?));
+
+    match surface {
+        Ok(surface) => Ok((texture, surface)),
+        Err(err) => {
+            video::delete_render_texture(texture);
+            Err(err)
+        }
+    }
+}
+
+/// The shadow maps of one directional light, one point light and one spot light.
+///
+/// The cascades of directional light are packed into one depth texture atlas, and
+/// refitted to the view frustum of each camera. The point light renders its six cube
+/// faces into another atlas, which is sampled as a cube map by receivers. And the spot
+/// light renders its cone with a perspective projection.
+///
+/// The shadow maps are created for each target surface of cameras lazily, so cameras
+/// rendering into different surfaces do not overwrite the shadows of each other.
+pub struct Shadow {
+    params: ShadowBuilder,
+    caster_shader: ShaderHandle,
+    clear_shader: ShaderHandle,
+    cmds: CommandBuffer,
+    maps: FastHashMap<Option<SurfaceHandle>, ShadowMaps>,
+}
+
+impl Drop for Shadow {
+    fn drop(&mut self) {
+        video::delete_shader(self.caster_shader);
+        video::delete_shader(self.clear_shader);
+    }
+}
+
+impl Shadow {
+    /// Creates a builder of shadow maps.
+    #[inline]
+    pub fn build() -> ShadowBuilder {
+        ShadowBuilder::default()
+    }
+
+    /// Gets the resolution of each cascade in pixels.
+    #[inline]
+    pub fn resolution(&self) -> u32 {
+        self.params.resolution
+    }
+
+    /// Gets the depth texture atlas of cascades rendered for `camera`.
+    #[inline]
+    pub fn texture(&self, camera: &Camera) -> Option<RenderTextureHandle> {
+        self.maps.get(&camera.surface()).map(|v| v.texture)
+    }
+
+    /// Gets the depth texture atlas of cube faces rendered for `camera`.
+    #[inline]
+    pub fn point_texture(&self, camera: &Camera) -> Option<RenderTextureHandle> {
+        self.maps.get(&camera.surface()).map(|v| v.point_texture)
+    }
+
+    /// Gets the depth texture of spot light rendered for `camera`.
+    #[inline]
+    pub fn spot_texture(&self, camera: &Camera) -> Option<RenderTextureHandle> {
+        self.maps.get(&camera.surface()).map(|v| v.spot_texture)
+    }
+
+    /// Checks if the shadow maps of `lit` have been rendered for `camera` since last
+    /// `reset`.
+    pub fn is_casting(&self, camera: &Camera, lit: &Lit) -> bool {
+        let maps = match self.maps.get(&camera.surface()) {
+            Some(v) => v,
+            None => return false,
+        };
+
+        let (position, rotation) = (lit.transform.position, lit.transform.rotation);
+        match lit.source {
+            LitSource::Dir => maps.dir.map_or(false, |v| is_same_rotation(v, rotation)),
+            LitSource::Point { .. } => maps
+                .point
+                .map_or(false, |v| is_same_position(v.0, position)),
+            LitSource::Spot { .. } => maps.spot.map_or(false, |v| {
+                is_same_position(v.0, position) && is_same_rotation(v.1, rotation)
+            }),
+        }
+    }
+
+    /// Discards the shadow maps rendered for `camera` before. The shadow maps of the
+    /// surfaces that have been deleted are released too.
+    pub fn reset(&mut self, camera: &Camera) {
+        self.maps.retain(|k, _| match *k {
+            Some(v) => video::surface_state(v) != ResourceState::NotReady,
+            None => true,
+        });
+
+        if let Some(maps) = self.maps.get_mut(&camera.surface()) {
+            maps.reset();
+        }
+    }
+
+    /// Declares the uniforms of shadow receivers.
+    pub(crate) fn uniforms(builder: UniformVariableLayoutBuilder) -> UniformVariableLayoutBuilder {
+        let mut builder = builder
+            .with("u_ShadowSplits", UniformVariableType::Vector4f)
+            .with("u_ShadowParams", UniformVariableType::Vector4f)
+            .with("u_ShadowMap", UniformVariableType::RenderTexture)
+            .with("u_PointShadowMatrix", UniformVariableType::Matrix4f)
+            .with("u_PointShadowParams", UniformVariableType::Vector4f)
+            .with("u_PointShadowMap", UniformVariableType::RenderTexture)
+            .with("u_SpotShadowMatrix", UniformVariableType::Matrix4f)
+            .with("u_SpotShadowParams", UniformVariableType::Vector4f)
+            .with("u_SpotShadowMap", UniformVariableType::RenderTexture);
+
+        for name in &SHADOW_MATRICES {
+            builder = builder.with(*name, UniformVariableType::Matrix4f);
+        }
+
+        builder
+    }
+
+    /// Declares the uniforms of the receivers that only sample the shadow map of spot
+    /// light with `CalculateSpotShadow`.
+    pub(crate) fn spot_uniforms(
+        builder: UniformVariableLayoutBuilder,
+    ) -> UniformVariableLayoutBuilder {
+        builder
+            .with("u_ShadowParams", UniformVariableType::Vector4f)
+            .with("u_SpotShadowMatrix", UniformVariableType::Matrix4f)
+            .with("u_SpotShadowParams", UniformVariableType::Vector4f)
+            .with("u_SpotShadowMap", UniformVariableType::RenderTexture)
+    }
+
+    /// Renders the shadow casters into the shadow maps of `lit`. The cascades of
+    /// directional light cover the view frustum of camera, the cube faces of point
+    /// light cover its radius, and the shadow map of spot light covers its cone.
+    ///
+    /// The casters outside of the projection of each shadow map are culled.
+    pub fn draw(
+        &mut self,
+        camera: &Camera,
+        lit: &Lit,
+        casters: &[&MeshRenderer],
+    ) -> Result<(), Error> {
+        let key = camera.surface();
+        if !self.maps.contains_key(&key) {
+            let maps = ShadowMaps::new(&self.params)?;
+            self.maps.insert(key, maps);
+        }
+
+        match lit.source {
+            LitSource::Dir => self.draw_cascades(camera, lit, casters),
+            LitSource::Point { radius, .. } => self.draw_cube(camera, lit, radius, casters),
+            LitSource::Spot { radius, angle, .. } => {
+                self.draw_spot(camera, lit, radius, angle, casters)
+            }
+        }
+    }
+
+    fn draw_cascades(
+        &mut self,
+        camera: &Camera,
+        lit: &Lit,
+        casters: &[&MeshRenderer],
+    ) -> Result<(), Error> {
+        let rotation = lit.transform.rotation;
+        let resolution = self.params.resolution;
+        let distances = lit
+            .cascades
+            .split_distances(camera.near_clip_plane(), camera.far_clip_plane());
+
+        let depth = cascade::casters_depth(rotation, casters);
+        let mut cascades = Vec::with_capacity(distances.len());
+        for (i, &far) in distances.iter().enumerate() {
+            let near = if i == 0 {
+                camera.near_clip_plane()
+            } else {
+                distances[i - 1]
+            };
+
+            let vp = cascade::fit(camera, rotation, (near, far), depth, resolution);
+            cascades.push((vp, far));
+
+            let (x, y) = ((i % 2) as u32, (i / 2) as u32);
+            self.cmds.update_viewport(SurfaceViewport {
+                position: Vector2::new((x * resolution) as i32, (y * resolution) as i32),
+                size: Vector2::new(resolution, resolution),
+            });
+
+            self.draw_casters(vp, casters);
+        }
+
+        let maps = self.maps.get_mut(&camera.surface()).unwrap();
+        maps.cascades = cascades;
+        maps.dir = Some(rotation);
+        self.cmds.submit(maps.surface)?;
+        Ok(())
+    }
+
+    fn draw_cube(
+        &mut self,
+        camera: &Camera,
+        lit: &Lit,
+        radius: f32,
+        casters: &[&MeshRenderer],
+    ) -> Result<(), Error> {
+        let position = lit.transform.position;
+        let resolution = self.params.point_resolution;
+        let (near, far) = ((radius * 0.01).max(0.01), radius.max(0.02));
+
+        // Skips the casters which are out of the range of point light.
+        let casters: Vec<_> = casters
+            .iter()
+            .filter(|v| match v.bounds {
+                Some((_, aabb)) => {
+                    let aabb = aabb.transform(&v.transform.matrix());
+                    let x = position.x.max(aabb.min.x).min(aabb.max.x);
+                    let y = position.y.max(aabb.min.y).min(aabb.max.y);
+                    let z = position.z.max(aabb.min.z).min(aabb.max.z);
+                    (Vector3::new(x, y, z) - position).magnitude2() <= far * far
+                }
+                None => true,
+            })
+            .cloned()
+            .collect();
+
+        for i in 0..6 {
+            let (x, y) = ((i % 3) as u32, (i / 3) as u32);
+            self.cmds.update_viewport(SurfaceViewport {
+                position: Vector2::new((x * resolution) as i32, (y * resolution) as i32),
+                size: Vector2::new(resolution, resolution),
+            });
+
+            self.draw_casters(cube::face(position, i, near, far), &casters);
+        }
+
+        let maps = self.maps.get_mut(&camera.surface()).unwrap();
+        maps.point = Some((position, near, far));
+        self.cmds.submit(maps.point_surface)?;
+        Ok(())
+    }
+
+    fn draw_spot(
+        &mut self,
+        camera: &Camera,
+        lit: &Lit,
+        radius: f32,
+        angle: f32,
+        casters: &[&MeshRenderer],
+    ) -> Result<(), Error> {
+        let resolution = self.params.spot_resolution;
+        let vp = spot::frustum(&lit.transform, angle, radius);
+
+        self.cmds.update_viewport(SurfaceViewport {
+            position: Vector2::new(0, 0),
+            size: Vector2::new(resolution, resolution),
+        });
+
+        self.draw_casters(vp, casters);
+
+        let maps = self.maps.get_mut(&camera.surface()).unwrap();
+        maps.spot = Some((lit.transform.position, lit.transform.rotation, vp));
+        self.cmds.submit(maps.spot_surface)?;
+        Ok(())
+    }
+
+    fn draw_casters(&mut self, vp: Matrix4<f32>, casters: &[&MeshRenderer]) {
+        let clear = Matrix4::from_translation(Vector3::new(0.0, 0.0, 1.0))
+            * Matrix4::from_nonuniform_scale(2.0, 2.0, 0.0);
+
+        let mut dc = Draw::new(self.clear_shader, crate::default().quad);
+        dc.set_uniform_variable("u_MVPMatrix", clear);
+        self.cmds.draw(dc);
+
+        for v in casters {
+            let mvp = vp * v.transform.matrix();
+            if let Some((_, aabb)) = v.bounds {
+                if is_outside(mvp, aabb) {
+                    continue;
+                }
+            }
+
+            let mut dc = Draw::new(self.caster_shader, v.mesh);
+            dc.set_uniform_variable("u_MVPMatrix", mvp);
+            self.cmds.draw(dc);
+        }
+    }
+
+    /// Binds the shadow maps to the uniforms of receiver. The shadows of the first
+    /// directional light, the first point light and the first spot light are enabled
+    /// by `dir`, `point` and `spot`.
+    pub(crate) fn bind(
+        shadow: Option<&Shadow>,
+        camera: &Camera,
+        (dir, point, spot): (bool, bool, bool),
+        view_matrix: Matrix4<f32>,
+        dc: &mut Draw,
+    ) {
+        use crayon::math::prelude::SquareMatrix;
+
+        let mut splits = [0.0; MAX_SHADOW_CASCADES];
+        let mut matrices = [Matrix4::identity(); MAX_SHADOW_CASCADES];
+        let mut params = [0.0; 4];
+        let mut point_matrix = Matrix4::identity();
+        let mut point_params = [0.0; 4];
+        let mut spot_matrix = Matrix4::identity();
+        let mut spot_params = [0.0; 4];
+
+        let maps = shadow.and_then(|v| v.maps.get(&camera.surface()).map(|m| (v, m)));
+        if let Some((shadow, maps)) = maps {
+            let inv_view_matrix = view_matrix.invert().unwrap_or(view_matrix);
+            let resolution = shadow.params.resolution as f32;
+
+            if dir {
+                for (i, &(vp, far)) in maps.cascades.iter().enumerate() {
+                    // Transforms from NDC of cascade into its tile in texture atlas.
+                    let (x, y) = ((i % 2) as f32, (i / 2) as f32);
+                    let tile = Matrix4::from_translation(Vector3::new(
+                        x * 0.5 + 0.25,
+                        y * 0.5 + 0.25,
+                        0.5,
+                    )) * Matrix4::from_nonuniform_scale(0.25, 0.25, 0.5);
+
+                    matrices[i] = tile * vp * inv_view_matrix;
+                    splits[i] = far;
+                }
+            }
+
+            if let (true, Some((position, near, far))) = (point, maps.point) {
+                // Transforms from view space into the world axes centered at light.
+                point_matrix = Matrix4::from_translation(-position) * inv_view_matrix;
+                point_params[0] = near;
+                point_params[1] = far;
+                point_params[2] = 0.5 / shadow.params.point_resolution as f32;
+            }
+
+            if let (true, Some((_, _, vp))) = (spot, maps.spot) {
+                let (matrix, params) = shadow.spot_params(vp, inv_view_matrix);
+                spot_matrix = matrix;
+                spot_params = params;
+            }
+
+            params[0] = shadow.params.bias;
+            params[1] = shadow.params.pcf_spread / (2.0 * resolution);
+            params[2] = shadow.params.pcf_radius as f32;
+            params[3] = 0.5 / (2.0 * resolution);
+            point_params[3] = shadow.params.bias;
+
+            dc.set_uniform_variable("u_ShadowMap", maps.texture);
+            dc.set_uniform_variable("u_PointShadowMap", maps.point_texture);
+            dc.set_uniform_variable("u_SpotShadowMap", maps.spot_texture);
+        } else {
+            dc.set_uniform_variable("u_ShadowMap", crate::default().white);
+            dc.set_uniform_variable("u_PointShadowMap", crate::default().white);
+            dc.set_uniform_variable("u_SpotShadowMap", crate::default().white);
+        }
+
+        dc.set_uniform_variable("u_ShadowSplits", splits);
+        dc.set_uniform_variable("u_ShadowParams", params);
+        dc.set_uniform_variable("u_PointShadowMatrix", point_matrix);
+        dc.set_uniform_variable("u_PointShadowParams", point_params);
+        dc.set_uniform_variable("u_SpotShadowMatrix", spot_matrix);
+        dc.set_uniform_variable("u_SpotShadowParams", spot_params);
+
+        for (name, &v) in SHADOW_MATRICES.iter().zip(matrices.iter()) {
+            dc.set_uniform_variable(*name, v);
+        }
+    }
+
+    /// Binds the shadow map of spot light to the uniforms declared by `spot_uniforms`,
+    /// which is enabled by `spot`.
+    pub(crate) fn bind_spot(
+        shadow: Option<&Shadow>,
+        camera: &Camera,
+        spot: bool,
+        view_matrix: Matrix4<f32>,
+        dc: &mut Draw,
+    ) {
+        use crayon::math::prelude::SquareMatrix;
+
+        let mut params = [0.0; 4];
+        let mut spot_matrix = Matrix4::identity();
+        let mut spot_params = [0.0; 4];
+        let mut texture = None;
+
+        let maps = shadow.and_then(|v| v.maps.get(&camera.surface()).map(|m| (v, m)));
+        if let Some((shadow, maps)) = maps {
+            if let (true, Some((_, _, vp))) = (spot, maps.spot) {
+                let inv_view_matrix = view_matrix.invert().unwrap_or(view_matrix);
+                let (matrix, v) = shadow.spot_params(vp, inv_view_matrix);
+                spot_matrix = matrix;
+                spot_params = v;
+            }
+
+            params[2] = shadow.params.pcf_radius as f32;
+            texture = Some(maps.spot_texture);
+        }
+
+        match texture {
+            Some(v) => dc.set_uniform_variable("u_SpotShadowMap", v),
+            None => dc.set_uniform_variable("u_SpotShadowMap", crate::default().white),
+        }
+
+        dc.set_uniform_variable("u_ShadowParams", params);
+        dc.set_uniform_variable("u_SpotShadowMatrix", spot_matrix);
+        dc.set_uniform_variable("u_SpotShadowParams", spot_params);
+    }
+
+    // Gets the matrix from view space into the shadow map of spot light, and the
+    // parameters of receivers.
+    fn spot_params(
+        &self,
+        vp: Matrix4<f32>,
+        inv_view_matrix: Matrix4<f32>,
+    ) -> (Matrix4<f32>, [f32; 4]) {
+        let matrix = spot::bias() * vp * inv_view_matrix;
+        let spread = self.params.pcf_spread / self.params.spot_resolution as f32;
+        (matrix, [1.0, self.params.bias, spread, 0.0])
+    }
+}
+
+// The transforms of lights are compared with tolerances, since they might be recomputed
+// from the scene graph with rounding errors between the shadow and lit passes.
+fn is_same_position(lhs: Vector3<f32>, rhs: Vector3<f32>) -> bool {
+    let scale = lhs.magnitude().max(rhs.magnitude()).max(1.0);
+    (lhs - rhs).magnitude() <= scale * 1e-4
+}
+
+// The quaternions `q` and `-q` represent the same rotation.
+fn is_same_rotation(lhs: Quaternion<f32>, rhs: Quaternion<f32>) -> bool {
+    let dot = lhs.s * rhs.s + lhs.v.dot(rhs.v);
+    let len = (lhs.magnitude() * rhs.magnitude()).max(::std::f32::EPSILON);
+    (dot / len).abs() >= 1.0 - 1e-6
+}
+
+/// Checks if the bounding box is outside of the clip space of `mvp`, which is true if
+/// all of its corners are outside of the same clip plane.
+fn is_outside(mvp: Matrix4<f32>, aabb: Aabb3<f32>) -> bool {
+    let mut outside = [true; 6];
+    for i in 0..8 {
+        let x = if i & 1 == 0 { aabb.min.x } else { aabb.max.x };
+        let y = if i & 2 == 0 { aabb.min.y } else { aabb.max.y };
+        let z = if i & 4 == 0 { aabb.min.z } else { aabb.max.z };
+
+        let v = mvp * Vector4::new(x, y, z, 1.0);
+        let planes = [
+            v.w + v.x,
+            v.w - v.x,
+            v.w + v.y,
+            v.w - v.y,
+            v.w + v.z,
+            v.w - v.z,
+        ];
+        for (o, &d) in outside.iter_mut().zip(planes.iter()) {
+            *o = *o && d < 0.0;
+        }
+    }
+
+    outside.iter().any(|&v| v)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn casting() {
+        // The tolerance of positions is relative to their magnitudes.
+        let position = Vector3::new(100.0, -20.0, 5.0);
+        let (near, far) = (Vector3::new(0.001, 0.0, 0.0), Vector3::new(0.1, 0.0, 0.0));
+        assert!(is_same_position(position, position + near));
+        assert!(!is_same_position(position, position + far));
+        assert!(is_same_position(Vector3::zero(), near * 0.01));
+        assert!(!is_same_position(Vector3::zero(), near));
+
+        let rotation = Quaternion::from(Euler::new(Deg(30.0), Deg(45.0), Deg(0.0)));
+        assert!(is_same_rotation(rotation, rotation));
+        assert!(is_same_rotation(rotation, -rotation));
+        assert!(is_same_rotation(rotation, rotation * 1.00001));
+
+        let other = Quaternion::from(Euler::new(Deg(30.0), Deg(46.0), Deg(0.0)));
+        assert!(!is_same_rotation(rotation, other));
+    }
+
+    #[test]
+    fn culling() {
+        let vp = Projection::ortho_matrix(2.0, 2.0, 0.0, 10.0);
+        let aabb = Aabb3::new([-0.5, -0.5, 1.0].into(), [0.5, 0.5, 2.0].into());
+        assert!(!is_outside(vp, aabb));
+
+        // The boxes crossing the clip planes are kept.
+        let mvp = vp * Matrix4::from_translation(Vector3::new(1.2, 0.0, 0.0));
+        assert!(!is_outside(mvp, aabb));
+
+        let mvp = vp * Matrix4::from_translation(Vector3::new(2.0, 0.0, 0.0));
+        assert!(is_outside(mvp, aabb));
+
+        let mvp = vp * Matrix4::from_translation(Vector3::new(0.0, 0.0, 20.0));
+        assert!(is_outside(mvp, aabb));
+    }
+}
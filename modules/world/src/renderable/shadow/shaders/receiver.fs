@@ -0,0 +1,159 @@
+// The shadows of receivers, which are shared by the renderers that support shadows.
+
+uniform mat4 u_ShadowMatrix[MAX_SHADOW_CASCADES];
+uniform vec4 u_ShadowSplits;
+uniform vec4 u_ShadowParams;
+uniform sampler2D u_ShadowMap;
+
+uniform mat4 u_PointShadowMatrix;
+uniform vec4 u_PointShadowParams;
+uniform sampler2D u_PointShadowMap;
+
+uniform mat4 u_SpotShadowMatrix;
+uniform vec4 u_SpotShadowParams;
+uniform sampler2D u_SpotShadowMap;
+
+// Samples the cascade at `tile` of shadow map atlas with percentage-closer filtering.
+float SampleShadow(vec3 eyePos, mat4 matrix, vec2 tile, float bias)
+{
+    vec4 pos = matrix * vec4(eyePos, 1.0);
+    vec3 coord = pos.xyz / pos.w;
+    if (coord.z >= 1.0)
+    {
+        return 1.0;
+    }
+
+    // Keeps the samples inside the tile of cascade.
+    vec2 lower = tile + vec2(u_ShadowParams.w);
+    vec2 upper = tile + vec2(0.5 - u_ShadowParams.w);
+
+    float lit = 0.0;
+    float count = 0.0;
+    for(int x = -MAX_PCF_RADIUS; x <= MAX_PCF_RADIUS; x++)
+    {
+        for(int y = -MAX_PCF_RADIUS; y <= MAX_PCF_RADIUS; y++)
+        {
+            vec2 offset = vec2(float(x), float(y));
+            if (abs(offset.x) <= u_ShadowParams.z && abs(offset.y) <= u_ShadowParams.z)
+            {
+                vec2 uv = clamp(coord.xy + offset * u_ShadowParams.y, lower, upper);
+                float closest = texture2D(u_ShadowMap, uv).r;
+                lit += coord.z - bias > closest ? 0.0 : 1.0;
+                count += 1.0;
+            }
+        }
+    }
+
+    return lit / max(count, 1.0);
+}
+
+float CalculateShadow(vec3 eyePos, vec3 normal, vec3 lightDir)
+{
+    // slope-scale depth bias
+    float bias = max(u_ShadowParams.x * (1.0 - dot(normal, -lightDir)), u_ShadowParams.x * 0.1);
+    float depth = eyePos.z;
+
+    if (depth < u_ShadowSplits.x)
+    {
+        return SampleShadow(eyePos, u_ShadowMatrix[0], vec2(0.0, 0.0), bias);
+    }
+
+    if (depth < u_ShadowSplits.y)
+    {
+        return SampleShadow(eyePos, u_ShadowMatrix[1], vec2(0.5, 0.0), bias);
+    }
+
+    if (depth < u_ShadowSplits.z)
+    {
+        return SampleShadow(eyePos, u_ShadowMatrix[2], vec2(0.0, 0.5), bias);
+    }
+
+    if (depth < u_ShadowSplits.w)
+    {
+        return SampleShadow(eyePos, u_ShadowMatrix[3], vec2(0.5, 0.5), bias);
+    }
+
+    return 1.0;
+}
+
+float CalculatePointShadow(vec3 eyePos)
+{
+    if (u_PointShadowParams.y <= 0.0)
+    {
+        return 1.0;
+    }
+
+    // direction from the light to fragment in world space.
+    vec3 d = (u_PointShadowMatrix * vec4(eyePos, 1.0)).xyz;
+    vec3 a = abs(d);
+
+    // picks the cube face with the major axis, faces are packed in a 3x2 atlas.
+    float z;
+    vec2 uv;
+    vec2 tile;
+
+    if (a.x >= a.y && a.x >= a.z)
+    {
+        z = a.x;
+        uv = d.x > 0.0 ? vec2(-d.z, d.y) : vec2(d.z, d.y);
+        tile = d.x > 0.0 ? vec2(0.0, 0.0) : vec2(1.0, 0.0);
+    }
+    else if (a.y >= a.z)
+    {
+        z = a.y;
+        uv = d.y > 0.0 ? vec2(d.x, -d.z) : vec2(d.x, d.z);
+        tile = d.y > 0.0 ? vec2(2.0, 0.0) : vec2(0.0, 1.0);
+    }
+    else
+    {
+        z = a.z;
+        uv = d.z > 0.0 ? vec2(d.x, d.y) : vec2(-d.x, d.y);
+        tile = d.z > 0.0 ? vec2(1.0, 1.0) : vec2(2.0, 1.0);
+    }
+
+    float n = u_PointShadowParams.x;
+    float f = u_PointShadowParams.y;
+
+    uv = clamp(uv / z * 0.5 + 0.5, vec2(u_PointShadowParams.z), vec2(1.0 - u_PointShadowParams.z));
+    float depth = ((f + n) / (f - n) - 2.0 * f * n / ((f - n) * z)) * 0.5 + 0.5;
+    float closest = texture2D(u_PointShadowMap, (tile + uv) / vec2(3.0, 2.0)).r;
+
+    return depth - u_PointShadowParams.w > closest ? 0.0 : 1.0;
+}
+
+float CalculateSpotShadow(vec3 eyePos, vec3 normal, vec3 lightDir)
+{
+    if (u_SpotShadowParams.x <= 0.0)
+    {
+        return 1.0;
+    }
+
+    vec4 pos = u_SpotShadowMatrix * vec4(eyePos, 1.0);
+    vec3 coord = pos.xyz / pos.w;
+    if (pos.w <= 0.0 || coord.z >= 1.0)
+    {
+        return 1.0;
+    }
+
+    // slope-scale depth bias
+    float bias = max(u_SpotShadowParams.y * (1.0 - dot(normal, -lightDir)), u_SpotShadowParams.y * 0.1);
+
+    float lit = 0.0;
+    float count = 0.0;
+    for(int x = -MAX_PCF_RADIUS; x <= MAX_PCF_RADIUS; x++)
+    {
+        for(int y = -MAX_PCF_RADIUS; y <= MAX_PCF_RADIUS; y++)
+        {
+            vec2 offset = vec2(float(x), float(y));
+            if (abs(offset.x) <= u_ShadowParams.z && abs(offset.y) <= u_ShadowParams.z)
+            {
+                vec2 uv = clamp(coord.xy + offset * u_SpotShadowParams.z, vec2(0.0), vec2(1.0));
+                float closest = texture2D(u_SpotShadowMap, uv).r;
+                lit += coord.z - bias > closest ? 0.0 : 1.0;
+                count += 1.0;
+            }
+        }
+    }
+
+    return lit / max(count, 1.0);
+}
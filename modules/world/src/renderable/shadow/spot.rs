@@ -0,0 +1,57 @@
+use crayon::math::prelude::*;
+
+use spatial::prelude::Transform;
+
+/// Computes the view-projection matrix of spot light, whose frustum covers the cone of
+/// `angle` in degrees and `radius`. It's shared by the shadow maps and cookies.
+pub(crate) fn frustum(transform: &Transform, angle: f32, radius: f32) -> Matrix4<f32> {
+    let (near, far) = ((radius * 0.01).max(0.01), radius.max(0.02));
+    let fovy = Deg(angle.max(1.0).min(179.0));
+    let projection = Projection::perspective_matrix(fovy.into(), 1.0, near, far);
+    projection * transform.view_matrix()
+}
+
+/// Gets the cosines of the half angles of outer and inner cones, which are compared
+/// with the angles between the spot direction and fragments in shaders.
+pub(crate) fn cone(angle: f32, inner_angle: f32) -> [f32; 2] {
+    let outer = (angle * 0.5).to_radians().cos();
+    let inner = (inner_angle.min(angle) * 0.5).to_radians().cos();
+    [outer, inner.max(outer + 0.0001)]
+}
+
+/// Transforms from the NDC of spot light into texture coordinates and depth in `[0, 1]`.
+pub(crate) fn bias() -> Matrix4<f32> {
+    Matrix4::from_translation(Vector3::new(0.5, 0.5, 0.5)) * Matrix4::from_scale(0.5)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frustum() {
+        let mut transform = Transform::default();
+        transform.position = Vector3::new(1.0, 2.0, 3.0);
+
+        let vp = bias() * super::frustum(&transform, 90.0, 10.0);
+        let project = |v: Vector3<f32>| {
+            let v = vp * (transform.position + v).extend(1.0);
+            v.truncate() / v.w
+        };
+
+        // The axis of cone is projected into the center, and the edges of cone are
+        // projected into the borders.
+        let center = project(Vector3::new(0.0, 0.0, 5.0));
+        assert!((center.x - 0.5).abs() < 0.001);
+        assert!((center.y - 0.5).abs() < 0.001);
+
+        let edge = project(Vector3::new(5.0, 0.0, 5.0));
+        assert!((edge.x - 1.0).abs() < 0.001);
+        assert!(project(Vector3::new(0.0, 0.0, 9.0)).z < 1.0);
+
+        let [outer, inner] = cone(90.0, 60.0);
+        assert!((outer - 45f32.to_radians().cos()).abs() < 0.001);
+        assert!((inner - 30f32.to_radians().cos()).abs() < 0.001);
+        assert!(cone(60.0, 90.0)[1] > cone(60.0, 90.0)[0]);
+    }
+}
@@ -0,0 +1,79 @@
+use crayon::prelude::*;
+use failure::Error;
+
+/// The debug visualizations of `SimpleRenderer`, which replace the lighting of meshes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugView {
+    /// Draws the lit meshes as usual.
+    Lit,
+    /// Draws the view space normals of meshes, which are mapped from [-1, 1] to [0, 1].
+    Normals,
+    /// Draws the linear depth of meshes, which is normalized by the far clip plane of
+    /// camera.
+    Depth,
+    /// Draws a heatmap of overdraw. Meshes are blended additively without depth test,
+    /// so the pixels that drawn many times turn into bright colors.
+    Overdraw,
+}
+
+impl Default for DebugView {
+    fn default() -> Self {
+        DebugView::Lit
+    }
+}
+
+/// Creates the shader that draws meshes with the debug visualization `view`.
+pub(crate) fn create_shader(view: DebugView) -> Result<ShaderHandle, Error> {
+    let attributes = AttributeLayout::build()
+        .with(Attribute::Position, 3)
+        .with_optional(Attribute::Normal, 3)
+        .finish();
+
+    let uniforms = UniformVariableLayout::build()
+        .with("u_ModelViewMatrix", UniformVariableType::Matrix4f)
+        .with("u_MVPMatrix", UniformVariableType::Matrix4f)
+        .with("u_ViewNormalMatrix", UniformVariableType::Matrix4f)
+        .with("u_FarClipPlane", UniformVariableType::F32)
+        .finish();
+
+    let mut params = ShaderParams::default();
+    params.attributes = attributes;
+    params.uniforms = uniforms;
+
+    let variant = match view {
+        DebugView::Lit => bail!("The lit view has no debug shader."),
+        DebugView::Normals => "DEBUG_NORMALS",
+        DebugView::Depth => "DEBUG_DEPTH",
+        DebugView::Overdraw => "DEBUG_OVERDRAW",
+    };
+
+    if view == DebugView::Overdraw {
+        params.state.color_blend = Some((Equation::Add, BlendFactor::One, BlendFactor::One));
+    } else {
+        params.state.depth_write = true;
+        params.state.depth_test = Comparison::Less;
+    }
+
+    let vs = format!(
+        "
+        #version 100
+        precision lowp float;
+        {0}
+        ",
+        include_str!("shaders/debug.vs")
+    );
+
+    let fs = format!(
+        "
+        #version 100
+        precision mediump float;
+
+        #define {0}
+        {1}
+        ",
+        variant,
+        include_str!("shaders/debug.fs")
+    );
+
+    Ok(video::create_shader(params, vs, fs)?)
+}
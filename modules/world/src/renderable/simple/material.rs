@@ -1,6 +1,8 @@
 use crayon::math::prelude::Color;
 use crayon::video::assets::texture::TextureHandle;
 
+use super::super::queue::RenderQueue;
+
 #[derive(Debug, Copy, Clone)]
 pub struct SimpleMaterial {
     pub ambient: Color<f32>,
@@ -9,6 +11,10 @@ pub struct SimpleMaterial {
     pub specular: Color<f32>,
     pub specular_texture: Option<TextureHandle>,
     pub shininess: f32,
+    /// The tangent space normal texture, which requires the tangents of mesh.
+    pub normal_texture: Option<TextureHandle>,
+    pub normal_scale: f32,
+    pub queue: RenderQueue,
 }
 
 impl Default for SimpleMaterial {
@@ -20,6 +26,9 @@ impl Default for SimpleMaterial {
             specular: Color::black(),
             specular_texture: None,
             shininess: 0.0,
+            normal_texture: None,
+            normal_scale: 1.0,
+            queue: RenderQueue::Opaque,
         }
     }
 }
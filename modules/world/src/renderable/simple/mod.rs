@@ -1,4 +1,6 @@
+mod debug;
 mod material;
+pub use self::debug::DebugView;
 pub use self::material::SimpleMaterial;
 
 use crayon::prelude::*;
@@ -7,54 +9,95 @@ use failure::Error;
 use utils::prelude::Component;
 use Entity;
 
+use super::fog::{Fog, FOG_FS};
+use super::lit::ATTENUATION_FS;
+use super::mesh_renderer::Lightmap;
+use super::queue::{DrawOrder, RenderQueue};
+use super::shadow::{self, Shadow, MAX_PCF_RADIUS, MAX_SHADOW_CASCADES};
+use super::skinning::{Skinning, SkinningMode, MAX_BONES, SKINNING_VS};
 use super::{Camera, Lit, LitSource, MeshRenderer};
 
 pub const MAX_DIR_LITS: usize = 1;
 pub const MAX_POINT_LITS: usize = 4;
+pub const MAX_SPOT_LITS: usize = 2;
 
 /// A simple renderer that draws some color into mesh objects.
+///
+/// The first directional light and the first point light that casting shadows are
+/// rendered with shadow maps. The default `Shadow` is created lazily, use `set_shadow`
+/// to customize it.
+///
+/// The diffuse colors are multiplied by the vertex colors if meshes have `Color0`. And the
+/// `Lightmap` of mesh replaces the global ambient, which is sampled with `Texcoord1`.
+///
+/// The lighting is calculated in linear space, and the result is encoded into sRGB space
+/// if the surface is not encoded by hardware. The colors of diffuse textures are assumed
+/// in sRGB space, they are decoded by hardware if the textures are in sRGB formats, or by
+/// shaders otherwise.
+///
+/// The lighting could be replaced by the debug visualizations of normals, depth or
+/// overdraw with `set_debug_view`.
+///
+/// Meshes with the skinning matrices of `Animator` are skinned on GPU if they have the
+/// `Indices` and `Weight` attributes, see `SkinningMode` for how the bone matrices are
+/// uploaded. Notes that the shadows are still cast in bind pose.
 pub struct SimpleRenderer {
     materials: Component<SimpleMaterial>,
+    shadow: Option<Shadow>,
 
     surface: SurfaceHandle,
     shader: ShaderHandle,
+    uniforms: UniformVariableLayout,
     drawcalls: DrawCommandBuffer<DrawOrder>,
+    skinning: Skinning,
+    debug_view: DebugView,
+    debug_shader: Option<(DebugView, ShaderHandle)>,
 
     global_ambient: Color<f32>,
     dir_lits: Vec<(String, String)>,
     point_lits: Vec<(String, String, String)>,
+    spot_lits: Vec<(String, String, String, String, String)>,
 }
 
 impl Drop for SimpleRenderer {
     fn drop(&mut self) {
         video::delete_surface(self.surface);
         video::delete_shader(self.shader);
+
+        if let Some((_, shader)) = self.debug_shader {
+            video::delete_shader(shader);
+        }
     }
 }
 
 impl SimpleRenderer {
     /// Creates a new `SimpleRenderer`.
     pub fn new() -> Result<Self, Error> {
-        // Create shader state.
-        let attributes = AttributeLayout::build()
-            .with(Attribute::Position, 3)
-            .with(Attribute::Normal, 3)
-            .with_optional(Attribute::Texcoord0, 2)
-            .finish();
-
         let mut uniforms = UniformVariableLayout::build()
             .with("u_ModelViewMatrix", UniformVariableType::Matrix4f)
             .with("u_MVPMatrix", UniformVariableType::Matrix4f)
             .with("u_ViewNormalMatrix", UniformVariableType::Matrix4f)
             .with("u_GlobalAmbient", UniformVariableType::Vector3f)
+            .with("u_EncodeSRGB", UniformVariableType::F32)
+            .with("u_LightmapTexture", UniformVariableType::Texture)
+            .with("u_LightmapScaleOffset", UniformVariableType::Vector4f)
+            .with("u_LightmapIntensity", UniformVariableType::F32)
             .with("u_Diffuse", UniformVariableType::Vector3f)
             .with("u_DiffuseTexture", UniformVariableType::Texture)
+            .with("u_DiffuseDecodeSRGB", UniformVariableType::F32)
             .with("u_Specular", UniformVariableType::Vector3f)
             .with("u_SpecularTexture", UniformVariableType::Texture)
-            .with("u_Shininess", UniformVariableType::F32);
+            .with("u_Shininess", UniformVariableType::F32)
+            .with("u_NormalScale", UniformVariableType::F32)
+            .with("u_NormalTexture", UniformVariableType::Texture);
+
+        uniforms = Fog::uniforms(Shadow::uniforms(uniforms))
+            .with("u_SpotLitCookie", UniformVariableType::Texture)
+            .with("u_SpotLitCookieMatrix", UniformVariableType::Matrix4f);
 
         let mut dir_lits = Vec::new();
         let mut point_lits = Vec::new();
+        let mut spot_lits = Vec::new();
 
         for i in 0..MAX_DIR_LITS {
             let name = (
@@ -79,16 +122,77 @@ impl SimpleRenderer {
             uniforms = uniforms
                 .with(name.0.as_str(), UniformVariableType::Vector3f)
                 .with(name.1.as_str(), UniformVariableType::Vector3f)
-                .with(name.2.as_str(), UniformVariableType::Vector3f);
+                .with(name.2.as_str(), UniformVariableType::Vector4f);
 
             point_lits.push(name);
         }
 
+        for i in 0..MAX_SPOT_LITS {
+            let name = (
+                format!("u_SpotLitViewPos[{0}]", i),
+                format!("u_SpotLitViewDir[{0}]", i),
+                format!("u_SpotLitColor[{0}]", i),
+                format!("u_SpotLitAttenuation[{0}]", i),
+                format!("u_SpotLitCone[{0}]", i),
+            );
+
+            uniforms = uniforms
+                .with(name.0.as_str(), UniformVariableType::Vector3f)
+                .with(name.1.as_str(), UniformVariableType::Vector3f)
+                .with(name.2.as_str(), UniformVariableType::Vector3f)
+                .with(name.3.as_str(), UniformVariableType::Vector4f)
+                .with(name.4.as_str(), UniformVariableType::Vector2f);
+
+            spot_lits.push(name);
+        }
+
+        let uniforms = uniforms.finish();
+        let shader = Self::create_shader(uniforms.clone(), None)?;
+
+        let params = SurfaceParams::default();
+        let surface = video::create_surface(params)?;
+
+        Ok(SimpleRenderer {
+            materials: Component::new(),
+            shadow: None,
+            surface: surface,
+            shader: shader,
+            uniforms: uniforms,
+            drawcalls: DrawCommandBuffer::new(),
+            skinning: Skinning::new(MAX_BONES),
+            debug_view: DebugView::default(),
+            debug_shader: None,
+            dir_lits: dir_lits,
+            point_lits: point_lits,
+            spot_lits: spot_lits,
+            global_ambient: Color::gray(),
+        })
+    }
+
+    // Creates the shader, or the skinned variant of it if `skinning` is specified.
+    fn create_shader(
+        uniforms: UniformVariableLayout,
+        skinning: Option<&Skinning>,
+    ) -> Result<ShaderHandle, Error> {
+        let mut attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with(Attribute::Normal, 3)
+            .with_optional(Attribute::Tangent, 4)
+            .with_optional(Attribute::Color0, 3)
+            .with_optional(Attribute::Texcoord0, 2)
+            .with_optional(Attribute::Texcoord1, 2);
+
         let mut params = ShaderParams::default();
         params.state.depth_write = true;
         params.state.depth_test = Comparison::Less;
-        params.attributes = attributes;
-        params.uniforms = uniforms.finish();
+        params.uniforms = uniforms;
+
+        if let Some(skinning) = skinning {
+            attributes = Skinning::attributes(attributes);
+            params.uniforms = skinning.uniforms(&params.uniforms);
+        }
+
+        params.attributes = attributes.finish();
 
         let vs = format!(
             "
@@ -97,41 +201,54 @@ impl SimpleRenderer {
 
             #define MAX_DIR_LITS {0}
             #define MAX_POINT_LITS {1}
-            {2}
+            #define MAX_SHADOW_CASCADES {2}
+            #define MAX_PCF_RADIUS {3}
+            {5}
+            {6}
+            {4}
             ",
             MAX_DIR_LITS,
             MAX_POINT_LITS,
-            include_str!("shaders/simple.vs")
+            MAX_SHADOW_CASCADES,
+            MAX_PCF_RADIUS,
+            include_str!("shaders/simple.vs"),
+            skinning.map(|v| v.defines()).unwrap_or_default(),
+            SKINNING_VS
         );
 
         let fs = format!(
             "
             #version 100
-            precision lowp float;
+            #ifdef GL_FRAGMENT_PRECISION_HIGH
+            precision highp float;
+            #else
+            precision mediump float;
+            #endif
 
             #define MAX_DIR_LITS {0}
             #define MAX_POINT_LITS {1}
-            {2}
+            #define MAX_SPOT_LITS {6}
+            #define MAX_SHADOW_CASCADES {2}
+            #define MAX_PCF_RADIUS {3}
+            {4}
+            {7}
+            {8}
+            {9}
+            {5}
             ",
             MAX_DIR_LITS,
             MAX_POINT_LITS,
-            include_str!("shaders/simple.fs")
+            MAX_SHADOW_CASCADES,
+            MAX_PCF_RADIUS,
+            shadow::RECEIVER_FS,
+            include_str!("shaders/simple.fs"),
+            MAX_SPOT_LITS,
+            super::SRGB_FS,
+            FOG_FS,
+            ATTENUATION_FS
         );
 
-        let shader = video::create_shader(params, vs, fs)?;
-
-        let params = SurfaceParams::default();
-        let surface = video::create_surface(params)?;
-
-        Ok(SimpleRenderer {
-            materials: Component::new(),
-            surface: surface,
-            shader: shader,
-            drawcalls: DrawCommandBuffer::new(),
-            dir_lits: dir_lits,
-            point_lits: point_lits,
-            global_ambient: Color::gray(),
-        })
+        Ok(video::create_shader(params, vs, fs)?)
     }
 
     #[inline]
@@ -163,6 +280,104 @@ impl SimpleRenderer {
     pub fn set_global_ambient<T: Into<Color<f32>>>(&mut self, color: T) {
         self.global_ambient = color.into();
     }
+
+    /// Sets the shadow maps of lights, which could be created with `Shadow::build()`.
+    #[inline]
+    pub fn set_shadow(&mut self, shadow: Shadow) {
+        self.shadow = Some(shadow);
+    }
+
+    /// Gets the shadow maps of lights.
+    #[inline]
+    pub fn shadow(&self) -> Option<&Shadow> {
+        self.shadow.as_ref()
+    }
+
+    /// Sets the maximum number of bones that skinned meshes could have, which is
+    /// `MAX_BONES` by default. The way that bone matrices are uploaded is selected
+    /// again, and the skinned shader is re-created lazily.
+    pub fn set_max_bones(&mut self, max_bones: usize) {
+        self.skinning = Skinning::new(max_bones);
+    }
+
+    /// Gets the maximum number of bones that skinned meshes could have.
+    #[inline]
+    pub fn max_bones(&self) -> usize {
+        self.skinning.max_bones()
+    }
+
+    /// Gets the way that bone matrices are uploaded, which is `None` if the GPU skinning
+    /// is not supported by this device.
+    #[inline]
+    pub fn skinning_mode(&self) -> Option<SkinningMode> {
+        self.skinning.mode()
+    }
+
+    /// Sets the debug visualization that replaces the lighting of meshes, the shader of
+    /// it is created lazily.
+    #[inline]
+    pub fn set_debug_view(&mut self, view: DebugView) {
+        self.debug_view = view;
+    }
+
+    /// Gets the debug visualization of meshes.
+    #[inline]
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_view
+    }
+
+    fn submit_debug(&mut self, camera: &Camera, meshes: &[&MeshRenderer]) {
+        use crayon::math::prelude::{Matrix, MetricSpace, SquareMatrix};
+
+        let shader = match self.debug_shader {
+            Some((view, shader)) if view == self.debug_view => shader,
+            _ => {
+                if let Some((_, shader)) = self.debug_shader.take() {
+                    video::delete_shader(shader);
+                }
+
+                match debug::create_shader(self.debug_view) {
+                    Ok(shader) => {
+                        self.debug_shader = Some((self.debug_view, shader));
+                        shader
+                    }
+                    Err(err) => {
+                        let view = self.debug_view;
+                        warn!("Failed to create the shader of {:?}. {}", view, err);
+                        return;
+                    }
+                }
+            }
+        };
+
+        let view_matrix = camera.transform.view_matrix();
+        let surface = camera.surface().unwrap_or(self.surface);
+        let projection_matrix = camera.projection_matrix();
+
+        for mesh in meshes {
+            let mv = view_matrix * mesh.transform.matrix();
+            let mvp = projection_matrix * mv;
+            let vn = mv.invert().and_then(|v| Some(v.transpose())).unwrap_or(mv);
+
+            let mut dc = Draw::new(shader, mesh.mesh);
+            dc.set_uniform_variable("u_ModelViewMatrix", mv);
+            dc.set_uniform_variable("u_MVPMatrix", mvp);
+            dc.set_uniform_variable("u_ViewNormalMatrix", vn);
+            dc.set_uniform_variable("u_FarClipPlane", camera.far_clip_plane());
+
+            let queue = self.material(mesh.ent).map(|v| v.queue).unwrap_or_default();
+            let distance = mesh.transform.position.distance(camera.transform.position);
+            let order = DrawOrder::new(queue, shader, distance);
+            self.drawcalls.draw(order, dc);
+        }
+
+        if let Err(err) = self.drawcalls.submit(surface) {
+            warn!(
+                "Failed to draw the debug view {:?}. {}",
+                self.debug_view, err
+            );
+        }
+    }
 }
 
 impl super::Renderer for SimpleRenderer {
@@ -184,11 +399,62 @@ impl super::Renderer for SimpleRenderer {
         self.remove(ent);
     }
 
-    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[MeshRenderer]) {
+    fn queue(&self, mesh: &MeshRenderer) -> RenderQueue {
+        self.material(mesh.ent).map(|v| v.queue).unwrap_or_default()
+    }
+
+    fn submit_shadows(
+        &mut self,
+        camera: &Camera,
+        lits: &[Lit],
+        casters: &[&MeshRenderer],
+    ) -> Result<(), Error> {
+        if let Some(ref mut shadow) = self.shadow {
+            shadow.reset(camera);
+        }
+
+        if self.debug_view != DebugView::Lit {
+            return Ok(());
+        }
+
+        let dir = lits.iter().find(|v| match v.source {
+            LitSource::Dir => v.enable && v.shadow_caster,
+            _ => false,
+        });
+
+        let point = lits.iter().find(|v| match v.source {
+            LitSource::Point { .. } => v.enable && v.shadow_caster,
+            _ => false,
+        });
+
+        let spot = lits.iter().find(|v| match v.source {
+            LitSource::Spot { .. } => v.enable && v.shadow_caster,
+            _ => false,
+        });
+
+        for lit in dir.iter().chain(point.iter()).chain(spot.iter()) {
+            if self.shadow.is_none() {
+                self.shadow = Some(Shadow::build().finish()?);
+            }
+
+            self.shadow.as_mut().unwrap().draw(camera, lit, casters)?;
+        }
+
+        Ok(())
+    }
+
+    fn submit(&mut self, camera: &Camera, lits: &[Lit], meshes: &[&MeshRenderer]) {
         use crayon::math::prelude::{InnerSpace, Matrix, MetricSpace, SquareMatrix};
 
+        if self.debug_view != DebugView::Lit {
+            self.submit_debug(camera, meshes);
+            return;
+        }
+
         let view_matrix = camera.transform.view_matrix();
-        let projection_matrix = camera.frustum().to_matrix();
+        let surface = camera.surface().unwrap_or(self.surface);
+        let encoding = super::srgb_encoding(surface);
+        let projection_matrix = camera.projection_matrix();
         let mut lits = Vec::from(lits);
 
         for mesh in meshes {
@@ -197,7 +463,15 @@ impl super::Renderer for SimpleRenderer {
             let mvp = projection_matrix * mv;
             let vn = mv.invert().and_then(|v| Some(v.transpose())).unwrap_or(mv);
 
-            let mut dc = Draw::new(self.shader, mesh.mesh);
+            let uniforms = &self.uniforms;
+            let shader = if self.skinning.is_skinned(mesh) {
+                self.skinning
+                    .shader(|v| Self::create_shader(uniforms.clone(), Some(v)))
+            } else {
+                None
+            };
+
+            let mut dc = Draw::new(shader.unwrap_or(self.shader), mesh.mesh);
             dc.set_uniform_variable("u_ModelViewMatrix", mv);
             dc.set_uniform_variable("u_MVPMatrix", mvp);
             dc.set_uniform_variable("u_ViewNormalMatrix", vn);
@@ -205,6 +479,12 @@ impl super::Renderer for SimpleRenderer {
             let mat = self.material(mesh.ent).cloned().unwrap_or_default();
             let diffuse = mat.diffuse_texture.unwrap_or(crate::default().white);
             let specular = mat.specular_texture.unwrap_or(crate::default().white);
+            let normal = mat.normal_texture.unwrap_or(crate::default().white);
+            let normal_scale = if mat.normal_texture.is_some() {
+                mat.normal_scale
+            } else {
+                0.0
+            };
 
             let mut ambient = mat.ambient.rgb();
             ambient[0] *= self.global_ambient.r;
@@ -212,74 +492,140 @@ impl super::Renderer for SimpleRenderer {
             ambient[2] *= self.global_ambient.b;
 
             dc.set_uniform_variable("u_GlobalAmbient", ambient);
+            dc.set_uniform_variable("u_EncodeSRGB", encoding);
+
+            // The baked lights are skipped for the lightmapped meshes.
+            let lightmap = mesh.lightmap.unwrap_or_else(|| {
+                let mut v = Lightmap::from(crate::default().white);
+                v.intensity = 0.0;
+                v
+            });
+
+            dc.set_uniform_variable("u_LightmapTexture", lightmap.texture);
+            dc.set_uniform_variable("u_LightmapScaleOffset", lightmap.scale_offset);
+            dc.set_uniform_variable("u_LightmapIntensity", lightmap.intensity);
             dc.set_uniform_variable("u_Diffuse", mat.diffuse.rgb());
             dc.set_uniform_variable("u_DiffuseTexture", diffuse);
+            dc.set_uniform_variable("u_DiffuseDecodeSRGB", super::srgb_decoding(diffuse));
             dc.set_uniform_variable("u_Specular", mat.specular.rgb());
             dc.set_uniform_variable("u_SpecularTexture", specular);
             dc.set_uniform_variable("u_Shininess", mat.shininess);
-
-            lits.sort_by_key(|v| mesh.transform.position.distance2(v.transform.position) as u32);
-
-            let (mut dir_index, mut point_index) = (0, 0);
+            dc.set_uniform_variable("u_NormalScale", normal_scale);
+            dc.set_uniform_variable("u_NormalTexture", normal);
+
+            // The lights that casting shadows are always placed in the first slots.
+            let shadow = self.shadow.as_ref();
+            lits.sort_by_key(|v| {
+                let casting = shadow.map(|s| s.is_casting(camera, v)).unwrap_or(false);
+                let distance = mesh.transform.position.distance2(v.transform.position) as u32;
+                (!casting, distance)
+            });
+
+            let mut casting = (false, false, false);
+            let mut cookie = (crate::default().white, Matrix4::identity());
+            let (mut dir_index, mut point_index, mut spot_index) = (0, 0, 0);
             for lit in &lits {
+                if lit.baked && mesh.lightmap.is_some() {
+                    continue;
+                }
+
                 match lit.source {
                     LitSource::Dir => {
                         if dir_index < self.dir_lits.len() {
+                            if dir_index == 0 {
+                                casting.0 =
+                                    shadow.map(|s| s.is_casting(camera, lit)).unwrap_or(false);
+                            }
+
                             let names = &self.dir_lits[dir_index];
                             let mut dir = view_matrix * lit.transform.forward().extend(0.0);
-                            let mut color = lit.color.rgb();
-                            color[0] *= lit.intensity;
-                            color[1] *= lit.intensity;
-                            color[2] *= lit.intensity;
+                            let color = lit.radiance(camera.exposure());
                             dc.set_uniform_variable(&names.0, dir.truncate().normalize());
                             dc.set_uniform_variable(&names.1, color);
                             dir_index += 1;
                         }
                     }
-                    LitSource::Point { radius, smoothness } => {
+                    LitSource::Point { .. } => {
                         if point_index < self.point_lits.len() {
+                            if point_index == 0 {
+                                casting.1 =
+                                    shadow.map(|s| s.is_casting(camera, lit)).unwrap_or(false);
+                            }
+
                             let names = &self.point_lits[point_index];
                             let mut pos = view_matrix * lit.transform.position.extend(1.0);
                             pos /= pos.w;
-                            let attenuation = Vector3::new(
-                                1.0,
-                                -1.0 / (radius + smoothness * radius * radius),
-                                -smoothness / (radius + smoothness * radius * radius),
-                            );
-                            let mut color = lit.color.rgb();
-                            color[0] *= lit.intensity;
-                            color[1] *= lit.intensity;
-                            color[2] *= lit.intensity;
+                            let attenuation = lit.attenuation_params();
+                            let color = lit.radiance(camera.exposure());
                             dc.set_uniform_variable(&names.0, pos.truncate());
                             dc.set_uniform_variable(&names.1, color);
                             dc.set_uniform_variable(&names.2, attenuation);
                             point_index += 1;
                         }
                     }
+                    LitSource::Spot {
+                        radius,
+                        angle,
+                        inner_angle,
+                        ..
+                    } => {
+                        if spot_index < self.spot_lits.len() {
+                            if spot_index == 0 {
+                                casting.2 =
+                                    shadow.map(|s| s.is_casting(camera, lit)).unwrap_or(false);
+                                if let Some(texture) = lit.cookie {
+                                    let vp = shadow::spot::frustum(&lit.transform, angle, radius);
+                                    let inv_view_matrix =
+                                        view_matrix.invert().unwrap_or(view_matrix);
+                                    cookie = (texture, shadow::spot::bias() * vp * inv_view_matrix);
+                                }
+                            }
+
+                            let names = &self.spot_lits[spot_index];
+                            let mut pos = view_matrix * lit.transform.position.extend(1.0);
+                            pos /= pos.w;
+                            let dir = view_matrix * lit.transform.forward().extend(0.0);
+                            let attenuation = lit.attenuation_params();
+                            let color = lit.radiance(camera.exposure());
+                            dc.set_uniform_variable(&names.0, pos.truncate());
+                            dc.set_uniform_variable(&names.1, dir.truncate().normalize());
+                            dc.set_uniform_variable(&names.2, color);
+                            dc.set_uniform_variable(&names.3, attenuation);
+                            dc.set_uniform_variable(
+                                &names.4,
+                                shadow::spot::cone(angle, inner_angle),
+                            );
+                            spot_index += 1;
+                        }
+                    }
                 }
             }
 
+            if !mesh.shadow_receiver {
+                casting = (false, false, false);
+            }
+
+            dc.set_uniform_variable("u_SpotLitCookie", cookie.0);
+            dc.set_uniform_variable("u_SpotLitCookieMatrix", cookie.1);
+
+            Shadow::bind(shadow, camera, casting, view_matrix, &mut dc);
+            Fog::bind(camera.fog.as_ref(), &mut dc);
+            mesh.properties.apply(&self.uniforms, &mut dc);
+
+            if shader.is_some() {
+                self.skinning.bind(mesh, &mut self.drawcalls, &mut dc);
+            }
+
             let order = DrawOrder::new(
-                self.shader,
-                false,
-                mesh.transform.position.distance2(camera.transform.position) as u32,
+                mat.queue,
+                dc.shader,
+                mesh.transform.position.distance(camera.transform.position),
             );
 
             self.drawcalls.draw(order, dc);
         }
 
-        let surface = camera.surface().unwrap_or(self.surface);
         self.drawcalls.submit(surface).unwrap();
-    }
-}
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-struct DrawOrder(u64);
-
-impl DrawOrder {
-    fn new(shader: ShaderHandle, translucent: bool, zorder: u32) -> Self {
-        let prefix = if translucent { (!zorder) } else { zorder };
-        let suffix = shader.index();
-        DrawOrder((u64::from(prefix) << 32) | u64::from(suffix))
+        self.skinning.collect();
     }
 }
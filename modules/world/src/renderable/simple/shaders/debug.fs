@@ -0,0 +1,17 @@
+varying vec3 v_EyeFragPos;
+varying vec3 v_EyeNormal;
+
+uniform float u_FarClipPlane;
+
+void main() {
+#if defined(DEBUG_NORMALS)
+    gl_FragColor = vec4(normalize(v_EyeNormal) * 0.5 + 0.5, 1.0);
+#elif defined(DEBUG_DEPTH)
+    float depth = clamp(-v_EyeFragPos.z / u_FarClipPlane, 0.0, 1.0);
+    gl_FragColor = vec4(depth, depth, depth, 1.0);
+#else
+    // Every layer adds a small warm increment, so the pixels that drawn many times
+    // saturate into bright colors.
+    gl_FragColor = vec4(0.1, 0.04, 0.02, 1.0);
+#endif
+}
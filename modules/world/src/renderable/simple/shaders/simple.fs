@@ -1,24 +1,47 @@
 varying vec3 v_EyeFragPos;
 varying vec3 v_EyeNormal;
+varying vec4 v_EyeTangent;
+varying vec4 v_Color;
 varying vec2 v_Texcoord;
+varying vec2 v_Texcoord1;
 
 uniform vec3 u_DirLitViewDir[MAX_DIR_LITS];
 uniform vec3 u_DirLitColor[MAX_DIR_LITS];
 
 uniform vec3 u_PointLitViewPos[MAX_POINT_LITS];
 uniform vec3 u_PointLitColor[MAX_POINT_LITS];
-uniform vec3 u_PointLitAttenuation[MAX_POINT_LITS];
+uniform vec4 u_PointLitAttenuation[MAX_POINT_LITS];
+
+uniform vec3 u_SpotLitViewPos[MAX_SPOT_LITS];
+uniform vec3 u_SpotLitViewDir[MAX_SPOT_LITS];
+uniform vec3 u_SpotLitColor[MAX_SPOT_LITS];
+uniform vec4 u_SpotLitAttenuation[MAX_SPOT_LITS];
+// x: the cosine of outer cone, y: the cosine of inner cone.
+uniform vec2 u_SpotLitCone[MAX_SPOT_LITS];
+
+// The cookie of the first spot light, which is projected from view space.
+uniform sampler2D u_SpotLitCookie;
+uniform mat4 u_SpotLitCookieMatrix;
 
 uniform vec3 u_GlobalAmbient;
 
+// The lightmap replaces the global ambient if its intensity is positive.
+uniform sampler2D u_LightmapTexture;
+uniform vec4 u_LightmapScaleOffset;
+uniform float u_LightmapIntensity;
+
 uniform vec3 u_Diffuse;
 uniform sampler2D u_DiffuseTexture;
+uniform float u_DiffuseDecodeSRGB;
 
 uniform vec3 u_Specular;
 uniform sampler2D u_SpecularTexture;
 
 uniform float u_Shininess;
 
+uniform float u_NormalScale;
+uniform sampler2D u_NormalTexture;
+
 vec3 Calculate(vec3 normal, vec3 viewDir, vec3 lightDir, vec3 reflectDir, vec3 d, vec3 s)
 {
     vec3 diffuse = max(dot(normal, -lightDir), 0.0) * u_Diffuse * d;
@@ -26,27 +49,59 @@ vec3 Calculate(vec3 normal, vec3 viewDir, vec3 lightDir, vec3 reflectDir, vec3 d
     return diffuse + specular;
 }
 
+vec3 CalculateCookie(vec3 eyePos)
+{
+    vec4 pos = u_SpotLitCookieMatrix * vec4(eyePos, 1.0);
+    if (pos.w <= 0.0)
+    {
+        return vec3(0.0, 0.0, 0.0);
+    }
+
+    return texture2D(u_SpotLitCookie, pos.xy / pos.w).rgb;
+}
+
+vec3 CalculateNormal()
+{
+    vec3 N = normalize(v_EyeNormal);
+
+    // Meshes without tangents have zero vectors here.
+    if (u_NormalScale > 0.0 && dot(v_EyeTangent.xyz, v_EyeTangent.xyz) > 0.0)
+    {
+        vec3 T = normalize(v_EyeTangent.xyz - N * dot(N, v_EyeTangent.xyz));
+        vec3 B = cross(N, T) * v_EyeTangent.w;
+        vec3 n = texture2D(u_NormalTexture, v_Texcoord).xyz * 2.0 - 1.0;
+        n.xy *= u_NormalScale;
+        N = normalize(mat3(T, B, N) * n);
+    }
+
+    return N;
+}
+
 void main()
 {
-    vec3 normal = normalize(v_EyeNormal);
+    vec3 normal = CalculateNormal();
     vec3 viewDir = normalize(v_EyeFragPos);
 
-    vec3 diffuse = texture2D(u_DiffuseTexture, v_Texcoord).rgb;
+    vec3 diffuse = DecodeSRGB(texture2D(u_DiffuseTexture, v_Texcoord).rgb, u_DiffuseDecodeSRGB) * v_Color.rgb;
     vec3 specular = texture2D(u_SpecularTexture, v_Texcoord).rgb;
 
-    //
-    vec3 result = u_GlobalAmbient * diffuse;
+    vec3 ambient = u_GlobalAmbient;
+    if (u_LightmapIntensity > 0.0)
+    {
+        vec2 uv = v_Texcoord1 * u_LightmapScaleOffset.xy + u_LightmapScaleOffset.zw;
+        ambient = texture2D(u_LightmapTexture, uv).rgb * u_LightmapIntensity;
+    }
+
+    vec3 result = ambient * diffuse;
 
     // directional light
     for(int i = 0; i < MAX_DIR_LITS; i++)
     {
-        // slope-scale depth bias
-        // float bias = max(0.005 * (1.0 - dot(normal, u_DirLitViewDir[i])), 0.0005);
-        // float shadow = CalculateShadow(scn_DirLitShadowTexture[i], v_DirLitShadowPos[i], bias);
-        // result += Calculate(normal, viewDir, u_DirLitViewDir[i], reflectDir, shadow) * u_DirLitColor[i];
+        // only the first directional light casts shadow.
+        float shadow = i == 0 ? CalculateShadow(v_EyeFragPos, normal, u_DirLitViewDir[i]) : 1.0;
 
         vec3 reflectDir = reflect(-u_DirLitViewDir[i], normal);
-        result += Calculate(normal, viewDir, u_DirLitViewDir[i], reflectDir, diffuse, specular) * u_DirLitColor[i];
+        result += Calculate(normal, viewDir, u_DirLitViewDir[i], reflectDir, diffuse, specular) * u_DirLitColor[i] * shadow;
     }
 
     // point lights
@@ -55,14 +110,33 @@ void main()
         vec3 lightDir2 = normalize(v_EyeFragPos - u_PointLitViewPos[i]);
         vec3 reflectDir2 = reflect(-lightDir2, normal);
         float distance = length(u_PointLitViewPos[i] - v_EyeFragPos);
-        float attenuation =
-            u_PointLitAttenuation[i].x +
-            u_PointLitAttenuation[i].y * distance +
-            u_PointLitAttenuation[i].z * (distance * distance);
+        float attenuation = CalculateAttenuation(u_PointLitAttenuation[i], distance);
+
+        // only the first point light casts shadow.
+        float shadow = i == 0 ? CalculatePointShadow(v_EyeFragPos) : 1.0;
 
         vec3 power = Calculate(normal, viewDir, lightDir2, reflectDir2, diffuse, specular) * u_PointLitColor[i];
-        result += max(power * attenuation, vec3(0.0, 0.0, 0.0));
+        result += max(power * attenuation * shadow, vec3(0.0, 0.0, 0.0));
+    }
+
+    // spot lights
+    for(int i = 0; i < MAX_SPOT_LITS; i++)
+    {
+        vec3 lightDir3 = normalize(v_EyeFragPos - u_SpotLitViewPos[i]);
+        vec3 reflectDir3 = reflect(-lightDir3, normal);
+        float distance = length(u_SpotLitViewPos[i] - v_EyeFragPos);
+        float attenuation = CalculateAttenuation(u_SpotLitAttenuation[i], distance);
+
+        float theta = dot(lightDir3, u_SpotLitViewDir[i]);
+        float cone = clamp((theta - u_SpotLitCone[i].x) / max(u_SpotLitCone[i].y - u_SpotLitCone[i].x, 0.0001), 0.0, 1.0);
+
+        // only the first spot light has cookie and casts shadow.
+        vec3 shadow = i == 0 ? CalculateCookie(v_EyeFragPos) * CalculateSpotShadow(v_EyeFragPos, normal, lightDir3) : vec3(1.0);
+
+        vec3 power = Calculate(normal, viewDir, lightDir3, reflectDir3, diffuse, specular) * u_SpotLitColor[i];
+        result += max(power * attenuation * cone * shadow, vec3(0.0, 0.0, 0.0));
     }
 
-    gl_FragColor = vec4(result, 1.0);
+    result = ApplyFog(result, length(v_EyeFragPos));
+    gl_FragColor = vec4(EncodeSRGB(result), 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,295 @@
+use crayon::prelude::*;
+use crayon::utils::hash::FastHashMap;
+use failure::Error;
+
+use Entity;
+
+use super::mesh_renderer::MeshRenderer;
+use super::queue::DrawOrder;
+
+/// The default maximum number of bones that skinned meshes could have.
+pub const MAX_BONES: usize = 64;
+
+/// The shader chunk of `SkinMatrix()`, which blends the bone matrices with the `Indices`
+/// and `Weight` of vertex if `SKINNING` is defined, or returns the identity matrix.
+pub const SKINNING_VS: &str = include_str!("shaders/skinning.vs");
+
+/// The vectors of vertex uniforms that are reserved for the transforms of meshes, when
+/// checking if the bone matrices fit into the rest.
+const RESERVED_VERTEX_UNIFORM_VECTORS: u32 = 16;
+
+/// The ways that the bone matrices of skinned meshes are uploaded to vertex shaders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkinningMode {
+    /// The matrices are bound to an uniform array `u_Bones`, which is the fastest way
+    /// but limited by the uniform vectors of vertex shaders, e.g. 128 on some GLES2
+    /// devices.
+    Uniforms,
+    /// The matrices are packed into a float texture `u_BoneTexture` per mesh, which
+    /// needs the vertex texture fetch and `RGBA32F` textures.
+    Texture,
+}
+
+impl SkinningMode {
+    /// Selects the mode that `max_bones` bone matrices could be uploaded with on current
+    /// device. Returns `None` if the device supports neither of them.
+    pub fn select(max_bones: usize) -> Option<SkinningMode> {
+        let capabilities = video::capabilities();
+        let float_texture = video::is_texture_format_supported(TextureFormat::RGBA32F);
+        Self::select_with(&capabilities, float_texture, max_bones)
+    }
+
+    fn select_with(
+        capabilities: &Capabilities,
+        float_texture: bool,
+        max_bones: usize,
+    ) -> Option<SkinningMode> {
+        let vectors = max_bones as u32 * 4 + RESERVED_VERTEX_UNIFORM_VECTORS;
+        if vectors <= capabilities.max_vertex_uniform_vectors {
+            Some(SkinningMode::Uniforms)
+        } else if capabilities.max_vertex_texture_units > 0 && float_texture {
+            Some(SkinningMode::Texture)
+        } else {
+            None
+        }
+    }
+}
+
+/// The GPU skinning of renderers, which uploads the bone matrices of meshes with the
+/// `SkinningMode` selected automatically, and keeps the skinned variant of shader.
+pub(crate) struct Skinning {
+    mode: Option<SkinningMode>,
+    max_bones: usize,
+    shader: Option<Option<ShaderHandle>>,
+    textures: FastHashMap<Entity, (TextureHandle, bool)>,
+    bytes: Vec<u8>,
+}
+
+impl Drop for Skinning {
+    fn drop(&mut self) {
+        if let Some(Some(shader)) = self.shader {
+            video::delete_shader(shader);
+        }
+
+        for (_, (texture, _)) in self.textures.drain() {
+            video::delete_texture(texture);
+        }
+    }
+}
+
+impl Skinning {
+    pub fn new(max_bones: usize) -> Self {
+        let mode = SkinningMode::select(max_bones);
+        if mode.is_none() {
+            warn!(
+                "GPU skinning with {} bones is not supported by this device.",
+                max_bones
+            );
+        }
+
+        Skinning {
+            mode: mode,
+            max_bones: max_bones,
+            shader: None,
+            textures: FastHashMap::default(),
+            bytes: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn mode(&self) -> Option<SkinningMode> {
+        self.mode
+    }
+
+    #[inline]
+    pub fn max_bones(&self) -> usize {
+        self.max_bones
+    }
+
+    /// Checks if the mesh should be drawn with skinning, which has skinning matrices and
+    /// the `Indices` and `Weight` attributes. Otherwise it's drawn in bind pose.
+    pub fn is_skinned(&self, mesh: &MeshRenderer) -> bool {
+        if self.mode.is_none() || mesh.skinning.is_empty() {
+            return false;
+        }
+
+        video::mesh(mesh.mesh)
+            .map(|v| {
+                v.layout.offset(Attribute::Indices).is_some()
+                    && v.layout.offset(Attribute::Weight).is_some()
+            })
+            .unwrap_or(false)
+    }
+
+    /// Gets the skinned variant of shader, which is created with `func` lazily.
+    pub fn shader<F>(&mut self, func: F) -> Option<ShaderHandle>
+    where
+        F: FnOnce(&Skinning) -> Result<ShaderHandle, Error>,
+    {
+        if self.shader.is_none() {
+            let shader = match func(self) {
+                Ok(shader) => Some(shader),
+                Err(err) => {
+                    warn!("Failed to create the skinned shader. {}", err);
+                    None
+                }
+            };
+
+            self.shader = Some(shader);
+        }
+
+        self.shader.unwrap()
+    }
+
+    /// Gets the definitions that enable `SKINNING_VS`.
+    pub fn defines(&self) -> String {
+        match self.mode {
+            Some(SkinningMode::Uniforms) => {
+                format!("#define SKINNING\n#define MAX_BONES {}\n", self.max_bones)
+            }
+            Some(SkinningMode::Texture) => format!(
+                "#define SKINNING\n#define SKINNING_TEXTURE\n#define MAX_BONES {}\n",
+                self.max_bones
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// Appends the attributes of `SKINNING_VS`.
+    pub fn attributes(builder: AttributeLayoutBuilder) -> AttributeLayoutBuilder {
+        builder
+            .with(Attribute::Indices, 4)
+            .with(Attribute::Weight, 4)
+    }
+
+    /// Gets the layout of `uniforms` with the ones of `SKINNING_VS`.
+    pub fn uniforms(&self, uniforms: &UniformVariableLayout) -> UniformVariableLayout {
+        let mut builder = UniformVariableLayout::build();
+        for &(ref name, tp) in uniforms.iter() {
+            builder = builder.with(name.as_str(), tp);
+        }
+
+        match self.mode {
+            Some(SkinningMode::Uniforms) => {
+                builder = builder.with("u_Bones", UniformVariableType::Matrix4fArray)
+            }
+            Some(SkinningMode::Texture) => {
+                builder = builder.with("u_BoneTexture", UniformVariableType::Texture)
+            }
+            None => {}
+        }
+
+        builder.finish()
+    }
+
+    /// Uploads the skinning matrices of mesh, and binds them to the draw call. The
+    /// bones exceed `max_bones` are ignored.
+    pub fn bind(
+        &mut self,
+        mesh: &MeshRenderer,
+        drawcalls: &mut DrawCommandBuffer<DrawOrder>,
+        dc: &mut Draw,
+    ) {
+        let len = mesh.skinning.len().min(self.max_bones);
+        let matrices = &mesh.skinning[..len];
+
+        match self.mode {
+            Some(SkinningMode::Uniforms) => {
+                dc.set_uniform_variable("u_Bones", drawcalls.matrix_array(matrices));
+            }
+            Some(SkinningMode::Texture) => {
+                let texture = match self.textures.get(&mesh.ent) {
+                    Some(&(texture, _)) => texture,
+                    None => {
+                        let mut params = TextureParams::default();
+                        params.hint = TextureHint::Stream;
+                        params.filter = TextureFilter::Nearest;
+                        params.format = TextureFormat::RGBA32F;
+                        params.dimensions = Vector2::new(self.max_bones as u32 * 4, 1);
+
+                        match video::create_texture(params, None) {
+                            Ok(texture) => texture,
+                            Err(err) => {
+                                warn!("Failed to create the bone texture. {}", err);
+                                return;
+                            }
+                        }
+                    }
+                };
+
+                pack(matrices, &mut self.bytes);
+                let area = Aabb2::new([0, 0].into(), [len as u32 * 4, 1].into());
+                if video::update_texture(texture, area, &self.bytes).is_ok() {
+                    dc.set_uniform_variable("u_BoneTexture", texture);
+                }
+
+                self.textures.insert(mesh.ent, (texture, true));
+            }
+            None => {}
+        }
+    }
+
+    /// Deletes the bone textures of meshes that are not drawn since last call.
+    pub fn collect(&mut self) {
+        self.textures.retain(|_, v| {
+            if !v.1 {
+                video::delete_texture(v.0);
+            }
+
+            let used = v.1;
+            v.1 = false;
+            used
+        });
+    }
+}
+
+// Packs the columns of matrices into the texels of `RGBA32F` texture.
+fn pack(matrices: &[Matrix4<f32>], bytes: &mut Vec<u8>) {
+    bytes.clear();
+    for m in matrices {
+        let columns: &[[f32; 4]; 4] = m.as_ref();
+        for v in columns.iter().flat_map(|c| c.iter()) {
+            bytes.extend_from_slice(&v.to_bits().to_ne_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn select() {
+        let mut capabilities = Capabilities::default();
+        capabilities.max_vertex_uniform_vectors = 128;
+        capabilities.max_vertex_texture_units = 4;
+
+        let mode = SkinningMode::select_with(&capabilities, true, 28);
+        assert_eq!(mode, Some(SkinningMode::Uniforms));
+        let mode = SkinningMode::select_with(&capabilities, true, MAX_BONES);
+        assert_eq!(mode, Some(SkinningMode::Texture));
+        let mode = SkinningMode::select_with(&capabilities, false, MAX_BONES);
+        assert_eq!(mode, None);
+
+        capabilities.max_vertex_uniform_vectors = 1024;
+        let mode = SkinningMode::select_with(&capabilities, false, MAX_BONES);
+        assert_eq!(mode, Some(SkinningMode::Uniforms));
+    }
+
+    #[test]
+    fn packing() {
+        let matrices = [Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0))];
+        let mut bytes = Vec::new();
+        pack(&matrices, &mut bytes);
+        assert_eq!(bytes.len(), 64);
+
+        let texels: Vec<_> = bytes
+            .chunks(4)
+            .map(|v| f32::from_bits(u32::from_ne_bytes([v[0], v[1], v[2], v[3]])))
+            .collect();
+
+        // The translation is stored in the last column.
+        assert_eq!((texels[0], texels[5], texels[10]), (1.0, 1.0, 1.0));
+        assert_eq!(&texels[12..], &[1.0, 2.0, 3.0, 1.0]);
+    }
+}
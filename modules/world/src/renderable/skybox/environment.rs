@@ -0,0 +1,402 @@
+//! Conversions of environment maps on CPU, from equirectangular images into the faces
+//! of cube maps and their diffuse convolutions.
+
+use std::f32::consts::PI;
+
+use crayon::math::prelude::*;
+use failure::Error;
+
+/// The pixels of a square face of cube map in linear RGB.
+pub type Face = Vec<[f32; 3]>;
+
+/// Decodes the image in Radiance HDR (.hdr) format, returns the dimensions and pixels
+/// from the top row to the bottom one.
+pub fn decode_hdr(bytes: &[u8]) -> Result<(Vector2<u32>, Vec<[f32; 3]>), Error> {
+    let mut cursor = 0;
+
+    if !read_line(bytes, &mut cursor)?.starts_with("#?") {
+        bail!("[HDR] MAGIC number not match.");
+    }
+
+    loop {
+        let line = read_line(bytes, &mut cursor)?;
+        if line.is_empty() {
+            break;
+        }
+
+        if line.starts_with("FORMAT=") && line.trim() != "FORMAT=32-bit_rle_rgbe" {
+            bail!(
+                "[HDR] Unsupported pixel format {}.",
+                &line["FORMAT=".len()..]
+            );
+        }
+    }
+
+    let resolution = read_line(bytes, &mut cursor)?;
+    let (w, h) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => (w.parse::<usize>()?, h.parse::<usize>()?),
+        _ => bail!("[HDR] Unsupported orientation {}.", resolution),
+    };
+
+    // A run of 2 bytes decodes at most 127 values of a channel, so the scanlines could not
+    // hold more than 16 pixels per byte.
+    let len = w
+        .checked_mul(h)
+        .filter(|&v| v > 0 && v <= (bytes.len() - cursor).saturating_mul(16))
+        .ok_or_else(|| format_err!("[HDR] Invalid dimensions {}x{}.", w, h))?;
+
+    let eof = || format_err!("[HDR] Unexpected end of scanlines.");
+    let mut pixels = Vec::with_capacity(len);
+    let mut scanline = vec![[0u8; 4]; w];
+
+    for _ in 0..h {
+        let rest = &bytes[cursor..];
+        let rle = w >= 8
+            && w < 0x8000
+            && rest.len() >= 4
+            && rest[0] == 2
+            && rest[1] == 2
+            && ((rest[2] as usize) << 8 | rest[3] as usize) == w;
+
+        if rle {
+            // The channels of scanline are run-length encoded separately.
+            cursor += 4;
+            for c in 0..4 {
+                let mut x = 0;
+                while x < w {
+                    let count = *bytes.get(cursor).ok_or_else(eof)? as usize;
+                    cursor += 1;
+
+                    if count > 128 {
+                        let count = count - 128;
+                        let v = *bytes.get(cursor).ok_or_else(eof)?;
+                        cursor += 1;
+
+                        if x + count > w {
+                            bail!("[HDR] Run of scanline is out of bounds.");
+                        }
+
+                        for p in &mut scanline[x..x + count] {
+                            p[c] = v;
+                        }
+
+                        x += count;
+                    } else {
+                        if count == 0 || x + count > w {
+                            bail!("[HDR] Run of scanline is out of bounds.");
+                        }
+
+                        let src = bytes.get(cursor..cursor + count).ok_or_else(eof)?;
+                        for (p, &v) in scanline[x..x + count].iter_mut().zip(src) {
+                            p[c] = v;
+                        }
+
+                        cursor += count;
+                        x += count;
+                    }
+                }
+            }
+        } else {
+            let src = rest.get(..w * 4).ok_or_else(eof)?;
+            for (p, v) in scanline.iter_mut().zip(src.chunks(4)) {
+                p.copy_from_slice(v);
+            }
+
+            cursor += w * 4;
+        }
+
+        pixels.extend(scanline.iter().map(|&v| rgbe(v)));
+    }
+
+    Ok((Vector2::new(w as u32, h as u32), pixels))
+}
+
+fn read_line<'a>(bytes: &'a [u8], cursor: &mut usize) -> Result<&'a str, Error> {
+    let start = *cursor;
+    let len = bytes[start..]
+        .iter()
+        .position(|&v| v == b'\n')
+        .ok_or_else(|| format_err!("[HDR] Unexpected end of header."))?;
+
+    *cursor = start + len + 1;
+    Ok(::std::str::from_utf8(&bytes[start..start + len])?)
+}
+
+fn rgbe(v: [u8; 4]) -> [f32; 3] {
+    if v[3] == 0 {
+        return [0.0; 3];
+    }
+
+    let f = 2.0f32.powi(v[3] as i32 - 136);
+    [v[0] as f32 * f, v[1] as f32 * f, v[2] as f32 * f]
+}
+
+/// Gets the direction of texel at (`s`, `t`) in [-1, 1] of cube map face, the faces are
+/// in order of `+X`, `-X`, `+Y`, `-Y`, `+Z` and `-Z`.
+fn direction(face: usize, s: f32, t: f32) -> Vector3<f32> {
+    let v = match face {
+        0 => Vector3::new(1.0, -t, -s),
+        1 => Vector3::new(-1.0, -t, s),
+        2 => Vector3::new(s, 1.0, t),
+        3 => Vector3::new(s, -1.0, -t),
+        4 => Vector3::new(s, -t, 1.0),
+        _ => Vector3::new(-s, -t, -1.0),
+    };
+
+    v.normalize()
+}
+
+/// Iterates the texels of face with their directions and solid angles.
+fn texels(size: u32) -> impl Iterator<Item = (usize, Vector3<f32>, f32)> {
+    let inv = 2.0 / size as f32;
+    (0..6).flat_map(move |face| {
+        (0..size * size).map(move |i| {
+            let s = ((i % size) as f32 + 0.5) * inv - 1.0;
+            let t = ((i / size) as f32 + 0.5) * inv - 1.0;
+            let solid_angle = inv * inv / (1.0 + s * s + t * t).powf(1.5);
+            (face, direction(face, s, t), solid_angle)
+        })
+    })
+}
+
+/// Samples the equirectangular image into the six faces of cube map with `size`.
+pub fn equirectangular_to_cube(
+    dimensions: Vector2<u32>,
+    pixels: &[[f32; 3]],
+    size: u32,
+) -> Result<[Face; 6], Error> {
+    let (w, h) = (dimensions.x as usize, dimensions.y as usize);
+    if w == 0 || h == 0 || pixels.len() != w * h {
+        bail!("The pixels of equirectangular image do not match its dimensions.");
+    }
+
+    let fetch = |x: isize, y: isize| {
+        let x = x.rem_euclid(w as isize) as usize;
+        let y = y.max(0).min(h as isize - 1) as usize;
+        pixels[y * w + x]
+    };
+
+    let mut faces: [Face; 6] = Default::default();
+    for (face, dir, _) in texels(size) {
+        let u = 0.5 + dir.x.atan2(-dir.z) / (2.0 * PI);
+        let v = dir.y.max(-1.0).min(1.0).acos() / PI;
+
+        // Bilinear filtering, which wraps around horizontally.
+        let x = u * w as f32 - 0.5;
+        let y = v * h as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as isize);
+
+        let mut texel = [0.0; 3];
+        let samples = [
+            (fetch(x0, y0), (1.0 - fx) * (1.0 - fy)),
+            (fetch(x0 + 1, y0), fx * (1.0 - fy)),
+            (fetch(x0, y0 + 1), (1.0 - fx) * fy),
+            (fetch(x0 + 1, y0 + 1), fx * fy),
+        ];
+
+        for &(p, weight) in &samples {
+            for c in 0..3 {
+                texel[c] += p[c] * weight;
+            }
+        }
+
+        faces[face].push(texel);
+    }
+
+    Ok(faces)
+}
+
+/// Downsamples the faces with a 2x2 box filter.
+pub fn downsample(faces: &[Face; 6], size: u32) -> [Face; 6] {
+    let size = size as usize;
+    let half = (size / 2).max(1);
+
+    let mut result: [Face; 6] = Default::default();
+    for (src, dst) in faces.iter().zip(result.iter_mut()) {
+        for i in 0..half * half {
+            let (x, y) = ((i % half) * 2, (i / half) * 2);
+            let (x1, y1) = ((x + 1).min(size - 1), (y + 1).min(size - 1));
+
+            let mut texel = [0.0; 3];
+            for &(x, y) in &[(x, y), (x1, y), (x, y1), (x1, y1)] {
+                for c in 0..3 {
+                    texel[c] += src[y * size + x][c] * 0.25;
+                }
+            }
+
+            dst.push(texel);
+        }
+    }
+
+    result
+}
+
+/// Calculates the diffuse convolution of faces into irradiance map with `out` size. The
+/// environment is projected onto 9 spherical harmonics coefficients, which is accurate
+/// enough for the low frequency irradiance.
+pub fn irradiance(faces: &[Face; 6], size: u32, out: u32) -> [Face; 6] {
+    let mut coeffs = [[0.0f32; 3]; 9];
+    let mut indices = [0usize; 6];
+    let mut total = 0.0;
+
+    for (face, dir, solid_angle) in texels(size) {
+        let texel = faces[face][indices[face]];
+        indices[face] += 1;
+        total += solid_angle;
+
+        for (coeff, basis) in coeffs.iter_mut().zip(sh9(dir).iter()) {
+            for c in 0..3 {
+                coeff[c] += texel[c] * basis * solid_angle;
+            }
+        }
+    }
+
+    // The convolution with clamped cosine lobe, divided by PI to be used as the
+    // radiance of lambertian surfaces.
+    let bands = [
+        1.0,
+        2.0 / 3.0,
+        2.0 / 3.0,
+        2.0 / 3.0,
+        0.25,
+        0.25,
+        0.25,
+        0.25,
+        0.25,
+    ];
+    // The solid angles of texels are approximated, normalizes their sum to the sphere.
+    let norm = 4.0 * PI / total;
+
+    let mut result: [Face; 6] = Default::default();
+    for (face, dir, _) in texels(out) {
+        let mut texel = [0.0; 3];
+        for ((coeff, basis), band) in coeffs.iter().zip(sh9(dir).iter()).zip(&bands) {
+            for c in 0..3 {
+                texel[c] += coeff[c] * basis * band * norm;
+            }
+        }
+
+        result[face].push([texel[0].max(0.0), texel[1].max(0.0), texel[2].max(0.0)]);
+    }
+
+    result
+}
+
+fn sh9(v: Vector3<f32>) -> [f32; 9] {
+    [
+        0.282_095,
+        0.488_603 * v.y,
+        0.488_603 * v.z,
+        0.488_603 * v.x,
+        1.092_548 * v.x * v.y,
+        1.092_548 * v.y * v.z,
+        0.315_392 * (3.0 * v.z * v.z - 1.0),
+        1.092_548 * v.x * v.z,
+        0.546_274 * (v.x * v.x - v.y * v.y),
+    ]
+}
+
+/// Encodes the pixels into `TextureFormat::RGBA16F`.
+pub fn encode(pixels: &[[f32; 3]]) -> Box<[u8]> {
+    let mut bytes = Vec::with_capacity(pixels.len() * 8);
+    for p in pixels {
+        for &v in p.iter().chain(Some(&1.0)) {
+            let v = half(v);
+            bytes.push(v as u8);
+            bytes.push((v >> 8) as u8);
+        }
+    }
+
+    bytes.into_boxed_slice()
+}
+
+/// Converts into half-precision floating-point number, the values out of range are
+/// clamped and the mantissa is truncated.
+fn half(v: f32) -> u16 {
+    let bits = v.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+
+    if v.is_nan() {
+        return sign | 0x7e00;
+    }
+
+    let exp = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exp >= 0x1f {
+        sign | 0x7bff
+    } else if exp <= 0 {
+        if exp < -10 {
+            sign
+        } else {
+            sign | ((mantissa | 0x80_0000) >> (14 - exp)) as u16
+        }
+    } else {
+        sign | ((exp as u16) << 10) | (mantissa >> 13) as u16
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn half_float() {
+        assert_eq!(half(0.0), 0);
+        assert_eq!(half(1.0), 0x3c00);
+        assert_eq!(half(-2.0), 0xc000);
+        assert_eq!(half(65504.0), 0x7bff);
+        assert_eq!(half(1_000_000.0), 0x7bff);
+        assert_eq!(half(0.000_060_976), 0x03ff);
+    }
+
+    #[test]
+    fn hdr() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 8\n".to_vec();
+        // Flat scanline.
+        for _ in 0..8 {
+            bytes.extend_from_slice(&[128, 64, 0, 129]);
+        }
+        // Run-length encoded scanline.
+        bytes.extend_from_slice(&[2, 2, 0, 8]);
+        bytes.extend_from_slice(&[136, 128]);
+        bytes.extend_from_slice(&[8, 1, 2, 3, 4, 5, 6, 7, 8]);
+        bytes.extend_from_slice(&[136, 0]);
+        bytes.extend_from_slice(&[136, 128]);
+
+        let (dimensions, pixels) = decode_hdr(&bytes).unwrap();
+        assert_eq!(dimensions, Vector2::new(8, 2));
+        assert_eq!(pixels[0], [1.0, 0.5, 0.0]);
+        assert_eq!(pixels[8], [0.5, 1.0 / 256.0, 0.0]);
+        assert_eq!(pixels[15], [0.5, 8.0 / 256.0, 0.0]);
+
+        assert!(decode_hdr(b"#?RADIANCE\n\n+Y 2 +X 8\n").is_err());
+        assert!(decode_hdr(b"#?RADIANCE\n\n-Y 0 +X 8\n").is_err());
+        assert!(decode_hdr(b"#?RADIANCE\n\n-Y 65536 +X 65536\n\x02\x02").is_err());
+        assert!(decode_hdr(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn uniform_irradiance() {
+        let dimensions = Vector2::new(16, 8);
+        let pixels = vec![[1.0, 0.5, 0.25]; 16 * 8];
+
+        let faces = equirectangular_to_cube(dimensions, &pixels, 8).unwrap();
+        assert!(faces.iter().all(|v| v.len() == 64));
+        assert!(faces[2].iter().all(|v| *v == [1.0, 0.5, 0.25]));
+
+        let faces = downsample(&faces, 8);
+        assert!(faces.iter().all(|v| v.len() == 16));
+
+        // The irradiance of uniform environment equals its radiance.
+        for face in &irradiance(&faces, 4, 2) {
+            for v in face {
+                assert!((v[0] - 1.0).abs() < 0.01);
+                assert!((v[1] - 0.5).abs() < 0.01);
+                assert!((v[2] - 0.25).abs() < 0.01);
+            }
+        }
+    }
+}
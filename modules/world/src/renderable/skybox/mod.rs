@@ -0,0 +1,233 @@
+mod environment;
+
+pub use self::environment::decode_hdr;
+
+use crayon::prelude::*;
+use crayon::video::assets::texture::{TextureCubeData, TextureCubeHandle, TextureCubeParams};
+use failure::Error;
+
+use self::environment::Face;
+use super::pbr::PbrEnvironment;
+use super::Camera;
+
+/// The size of irradiance map that generated from equirectangular images.
+pub const IRRADIANCE_SIZE: u32 = 32;
+
+/// The background of camera, which is drawn after the opaque meshes at the far plane, so
+/// only the pixels not covered by meshes are filled.
+///
+/// A skybox is added to the entity of camera with `Renderable::add_skybox`. Its optional
+/// `environment` is used by `PbrRenderer` for image-based lighting, if the renderer does
+/// not have its own environment maps.
+///
+/// ```rust,ignore
+/// let (dimensions, pixels) = decode_hdr(&bytes)?;
+/// let skybox = Skybox::from_equirectangular(dimensions, &pixels, 512)?;
+/// scene.renderables.add_skybox(camera, skybox);
+/// ```
+pub struct Skybox {
+    /// The cube map of background.
+    pub texture: TextureCubeHandle,
+    /// The scale of colors.
+    pub intensity: f32,
+    /// The environment of image-based lighting of the meshes under this skybox.
+    pub environment: Option<PbrEnvironment>,
+
+    // The cube map is in linear space, which is never decoded from sRGB space.
+    linear: bool,
+    textures: Vec<TextureCubeHandle>,
+    shader: ShaderHandle,
+    surface: SurfaceHandle,
+}
+
+impl Drop for Skybox {
+    fn drop(&mut self) {
+        video::delete_shader(self.shader);
+        video::delete_surface(self.surface);
+
+        for &v in &self.textures {
+            video::delete_texture_cube(v);
+        }
+    }
+}
+
+impl Skybox {
+    /// Creates a new `Skybox` with the cube map in sRGB space, the texture is not owned
+    /// by the skybox. The colors are decoded by hardware if the cube map is in
+    /// `TextureFormat::SRGBA8`, or by shaders otherwise.
+    pub fn new(texture: TextureCubeHandle) -> Result<Self, Error> {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .finish();
+
+        let uniforms = UniformVariableLayout::build()
+            .with("u_ViewProjMatrix", UniformVariableType::Matrix4f)
+            .with("u_Skybox", UniformVariableType::TextureCube)
+            .with("u_SkyboxIntensity", UniformVariableType::F32)
+            .with("u_SkyboxDecodeSRGB", UniformVariableType::F32)
+            .with("u_EncodeSRGB", UniformVariableType::F32)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.attributes = attributes;
+        params.uniforms = uniforms;
+        params.state.depth_write = false;
+        params.state.depth_test = Comparison::LessOrEqual;
+
+        let vs = format!(
+            "
+            #version 100
+            precision highp float;
+            {0}
+            ",
+            include_str!("shaders/skybox.vs")
+        );
+
+        let fs = format!(
+            "
+            #version 100
+            precision mediump float;
+            {0}
+            {1}
+            ",
+            super::SRGB_FS,
+            include_str!("shaders/skybox.fs")
+        );
+
+        let shader = video::create_shader(params, vs, fs)?;
+
+        // The camera surface has been cleared by renderer already.
+        let mut params = SurfaceParams::default();
+        params.set_clear(None, None, None);
+        let surface = video::create_surface(params)?;
+
+        Ok(Skybox {
+            texture: texture,
+            intensity: 1.0,
+            environment: None,
+            linear: false,
+            textures: Vec::new(),
+            shader: shader,
+            surface: surface,
+        })
+    }
+
+    /// Creates a new `Skybox` from the equirectangular image in linear space, which is
+    /// converted into cube map with faces of `size`, e.g. the HDR images decoded with
+    /// `decode_hdr`.
+    ///
+    /// The environment of image-based lighting is generated too. The mipmaps of cube map
+    /// are used as the prefiltered map, which are box-filtered instead of the exact
+    /// specular convolutions.
+    pub fn from_equirectangular(
+        dimensions: Vector2<u32>,
+        pixels: &[[f32; 3]],
+        size: u32,
+    ) -> Result<Self, Error> {
+        if !size.is_power_of_two() {
+            bail!("The size of skybox faces must be power of two.");
+        }
+
+        let mut params = TextureCubeParams::default();
+        params.format = TextureFormat::RGBA16F;
+        params.dimensions = Vector2::new(size, size);
+
+        let mut faces = environment::equirectangular_to_cube(dimensions, pixels, size)?;
+        let mut data = TextureCubeData {
+            faces: Default::default(),
+        };
+
+        // The irradiance is calculated from a smaller mipmap, which makes no difference
+        // but is much faster.
+        let mut irradiance = None;
+        let mut mips = 0;
+        let mut dims = size;
+
+        loop {
+            for (dst, src) in data.faces.iter_mut().zip(&faces) {
+                dst.push(environment::encode(src));
+            }
+
+            if irradiance.is_none() && dims <= IRRADIANCE_SIZE * 2 {
+                irradiance = Some(environment::irradiance(&faces, dims, IRRADIANCE_SIZE));
+            }
+
+            mips += 1;
+            if dims == 1 {
+                break;
+            }
+
+            faces = environment::downsample(&faces, dims);
+            dims /= 2;
+        }
+
+        let cube = video::create_texture_cube(params, data)?;
+        let irradiance = create(irradiance.unwrap(), IRRADIANCE_SIZE).map_err(|err| {
+            video::delete_texture_cube(cube);
+            err
+        })?;
+
+        let mut skybox = Skybox::new(cube).map_err(|err| {
+            video::delete_texture_cube(cube);
+            video::delete_texture_cube(irradiance);
+            err
+        })?;
+
+        skybox.linear = true;
+        skybox.textures = vec![cube, irradiance];
+        skybox.environment = Some(PbrEnvironment {
+            irradiance: Some(irradiance),
+            prefiltered: Some(cube),
+            prefiltered_mips: mips,
+            intensity: 1.0,
+        });
+
+        Ok(skybox)
+    }
+
+    /// Creates a new `Skybox` from the image in Radiance HDR (.hdr) format with
+    /// equirectangular projection.
+    pub fn from_hdr(bytes: &[u8], size: u32) -> Result<Self, Error> {
+        let (dimensions, pixels) = decode_hdr(bytes)?;
+        Self::from_equirectangular(dimensions, &pixels, size)
+    }
+
+    /// Draws the skybox into the surface of camera.
+    pub(crate) fn submit(&self, camera: &Camera) -> Result<(), Error> {
+        // Only the rotation of camera is taken into account.
+        let mut view_matrix = camera.transform.view_matrix();
+        view_matrix.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let projection_matrix = camera.projection_matrix();
+
+        let surface = camera.surface().unwrap_or(self.surface);
+        let decoding = !self.linear && video::requires_srgb_decoding_cube(self.texture);
+
+        let mut dc = Draw::new(self.shader, crate::default().cube);
+        dc.set_uniform_variable("u_ViewProjMatrix", projection_matrix * view_matrix);
+        dc.set_uniform_variable("u_Skybox", self.texture);
+        dc.set_uniform_variable("u_SkyboxIntensity", self.intensity);
+        dc.set_uniform_variable("u_SkyboxDecodeSRGB", if decoding { 1.0 } else { 0.0 });
+        dc.set_uniform_variable("u_EncodeSRGB", super::srgb_encoding(surface));
+
+        let mut cmds = CommandBuffer::new();
+        cmds.draw(dc);
+        cmds.submit(surface)?;
+        Ok(())
+    }
+}
+
+fn create(faces: [Face; 6], size: u32) -> Result<TextureCubeHandle, Error> {
+    let mut params = TextureCubeParams::default();
+    params.format = TextureFormat::RGBA16F;
+    params.dimensions = Vector2::new(size, size);
+
+    let mut data = TextureCubeData {
+        faces: Default::default(),
+    };
+
+    for (dst, src) in data.faces.iter_mut().zip(&faces) {
+        dst.push(environment::encode(src));
+    }
+
+    Ok(video::create_texture_cube(params, data)?)
+}
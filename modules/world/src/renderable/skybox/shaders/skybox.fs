@@ -0,0 +1,10 @@
+uniform samplerCube u_Skybox;
+uniform float u_SkyboxIntensity;
+uniform float u_SkyboxDecodeSRGB;
+
+varying vec3 v_Direction;
+
+void main() {
+    vec3 color = DecodeSRGB(textureCube(u_Skybox, v_Direction).rgb, u_SkyboxDecodeSRGB) * u_SkyboxIntensity;
+    gl_FragColor = vec4(EncodeSRGB(color), 1.0);
+}
@@ -0,0 +1,13 @@
+attribute vec3 Position;
+
+uniform mat4 u_ViewProjMatrix;
+
+varying vec3 v_Direction;
+
+void main() {
+    v_Direction = Position;
+
+    // Places the cube at the far plane, so it only covers the background.
+    vec4 position = u_ViewProjMatrix * vec4(Position, 1.0);
+    gl_Position = position.xyww;
+}
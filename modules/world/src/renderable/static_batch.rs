@@ -0,0 +1,301 @@
+//! The static batches that merge the meshes of non-moving entities sharing a material
+//! into one mesh, so they could be drawn with one draw call.
+
+use crayon::errors::Result;
+use crayon::math::prelude::*;
+use crayon::video;
+use crayon::video::assets::prelude::*;
+
+use spatial::prelude::{SceneGraph, Transform};
+use tags::Tags;
+use Entity;
+
+use super::Renderable;
+
+impl Renderable {
+    /// Merges the meshes of entities that share the same material into static batches,
+    /// which replace the meshes when drawing. The vertices are transformed into world
+    /// space with the current transforms of entities, so the batches should be re-built
+    /// once the merged entities are moved or changed.
+    ///
+    /// Only the triangle meshes whose data are readable, see `video::set_mesh_readable`,
+    /// and have material assets but no lightmaps or `MaterialPropertyBlock`s are merged. The
+    /// entities could opt out by clearing `MeshRenderer::static_batching`, or being rejected
+    /// by `filter`. Returns the number of batches.
+    pub fn build_static_batches<F>(
+        &mut self,
+        sg: &SceneGraph,
+        tags: &Tags,
+        filter: F,
+    ) -> Result<usize>
+    where
+        F: Fn(Entity) -> bool,
+    {
+        self.clear_static_batches();
+
+        for (i, v) in self.meshes.data.iter_mut().enumerate() {
+            v.layers = tags.layers(self.meshes.entities[i]);
+        }
+
+        // The sources are grouped by the states of mesh renderers and vertex layouts.
+        let mut groups: Vec<(usize, MeshParams, Vec<(usize, Transform, MeshData)>)> = Vec::new();
+        for (i, mr) in self.meshes.data.iter().enumerate() {
+            let ent = self.meshes.entities[i];
+            if !mr.static_batching || !mr.visible || mr.material.is_none() || mr.lightmap.is_some()
+            {
+                continue;
+            }
+
+            if !mr.properties.is_empty() {
+                continue;
+            }
+
+            if self.lods.has(ent) || !filter(ent) {
+                continue;
+            }
+
+            let params = match video::mesh(mr.mesh) {
+                Some(params) => params,
+                None => continue,
+            };
+
+            let data = match video::mesh_data(mr.mesh) {
+                Some(data) => data,
+                None => continue,
+            };
+
+            if params.primitive != MeshPrimitive::Triangles || position(&params.layout).is_none() {
+                continue;
+            }
+
+            let transform = sg.transform(ent).unwrap_or_default();
+            let group = groups.iter().position(|(j, v, _)| {
+                let rhs = &self.meshes.data[*j];
+                v.layout == params.layout
+                    && rhs.material == mr.material
+                    && rhs.shadow_caster == mr.shadow_caster
+                    && rhs.shadow_receiver == mr.shadow_receiver
+                    && rhs.layers == mr.layers
+            });
+
+            match group {
+                Some(group) => groups[group].2.push((i, transform, data)),
+                None => groups.push((i, params.clone(), vec![(i, transform, data)])),
+            }
+        }
+
+        for (i, params, sources) in groups {
+            // There is nothing to save with a single mesh.
+            if sources.len() < 2 {
+                continue;
+            }
+
+            let (params, data) = {
+                let iter = sources.iter().map(|v| (v.1, &params, &v.2));
+                merge(iter.collect())?
+            };
+
+            let aabb = params.aabb;
+            let mesh = video::create_mesh(params, data)?;
+
+            let mut batch = self.meshes.data[i].clone();
+            batch.mesh = mesh;
+            batch.transform = Transform::default();
+            batch.ent = Entity::default();
+            batch.bounds = Some((mesh, aabb));
+            self.batches.push(batch);
+
+            for &(j, _, _) in &sources {
+                self.meshes.data[j].batched = true;
+            }
+        }
+
+        Ok(self.batches.len())
+    }
+
+    /// Removes all the static batches, and draws the meshes that have been merged.
+    pub fn clear_static_batches(&mut self) {
+        for v in self.batches.drain(..) {
+            video::delete_mesh(v.mesh);
+        }
+
+        for v in &mut self.meshes.data {
+            v.batched = false;
+        }
+    }
+}
+
+fn position(layout: &VertexLayout) -> Option<usize> {
+    attribute(layout, Attribute::Position)
+}
+
+// Gets the offset of attribute with 3 floats at least.
+fn attribute(layout: &VertexLayout, name: Attribute) -> Option<usize> {
+    let element = layout.element(name)?;
+    if element.format != VertexFormat::Float || element.size < 3 {
+        return None;
+    }
+
+    layout.offset(name).map(|v| v as usize)
+}
+
+fn read(bytes: &[u8], offset: usize) -> Vector3<f32> {
+    let mut v = [0.0; 3];
+    for (i, c) in v.iter_mut().enumerate() {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(&bytes[offset + i * 4..offset + i * 4 + 4]);
+        *c = f32::from_bits(u32::from_ne_bytes(buf));
+    }
+
+    v.into()
+}
+
+fn write(bytes: &mut [u8], offset: usize, v: Vector3<f32>) {
+    for i in 0..3 {
+        let buf = v[i].to_bits().to_ne_bytes();
+        bytes[offset + i * 4..offset + i * 4 + 4].copy_from_slice(&buf);
+    }
+}
+
+/// Merges the meshes with the same vertex layout into one, whose vertices are transformed
+/// by the transforms of sources.
+fn merge(sources: Vec<(Transform, &MeshParams, &MeshData)>) -> Result<(MeshParams, MeshData)> {
+    let layout = match sources.first() {
+        Some(v) => v.1.layout,
+        None => bail!("There are no meshes to merge."),
+    };
+
+    let stride = layout.stride() as usize;
+    let num_verts: usize = sources.iter().map(|v| v.1.num_verts).sum();
+    let num_idxes: usize = sources.iter().map(|v| v.1.num_idxes).sum();
+
+    let mut verts = Vec::with_capacity(num_verts * stride);
+    let mut idxes = Vec::with_capacity(num_idxes);
+    let mut aabb: Option<Aabb3<f32>> = None;
+
+    let position = position(&layout).ok_or_else(|| format_err!("No positions."))?;
+    let normal = attribute(&layout, Attribute::Normal);
+    let tangent = attribute(&layout, Attribute::Tangent);
+
+    for (transform, params, data) in sources {
+        let len = params.num_verts * stride;
+        if data.vptr.len() < len || data.iptr.len() < params.index_buffer_len() {
+            bail!("The data of mesh does not match its parameters.");
+        }
+
+        let base = verts.len() / stride;
+        verts.extend_from_slice(&data.vptr[..len]);
+
+        for v in verts[base * stride..].chunks_mut(stride) {
+            let p = transform.transform_point(read(v, position));
+            write(v, position, p);
+
+            for &offset in normal.iter().chain(tangent.iter()) {
+                let n = transform.transform_direction(read(v, offset));
+                write(v, offset, n);
+            }
+        }
+
+        let iptr = &data.iptr[..params.index_buffer_len()];
+        match params.index_format {
+            IndexFormat::U16 => idxes.extend(
+                iptr.chunks(2)
+                    .map(|v| u32::from(u16::from_ne_bytes([v[0], v[1]])) + base as u32),
+            ),
+            IndexFormat::U32 => idxes.extend(
+                iptr.chunks(4)
+                    .map(|v| u32::from_ne_bytes([v[0], v[1], v[2], v[3]]) + base as u32),
+            ),
+        }
+
+        let bounds = params.aabb.transform(&transform.matrix());
+        aabb = Some(match aabb {
+            Some(v) => v.grow(bounds.min).grow(bounds.max),
+            None => bounds,
+        });
+    }
+
+    let mut params = MeshParams::default();
+    params.layout = layout;
+    params.num_verts = num_verts;
+    params.num_idxes = num_idxes;
+    params.aabb = aabb.unwrap_or_else(Aabb3::zero);
+
+    let iptr = if num_verts <= ::std::u16::MAX as usize {
+        let idxes: Vec<u16> = idxes.iter().map(|&v| v as u16).collect();
+        IndexFormat::encode(&idxes).to_vec()
+    } else {
+        params.index_format = IndexFormat::U32;
+        IndexFormat::encode(&idxes).to_vec()
+    };
+
+    let data = MeshData {
+        vptr: verts.into_boxed_slice(),
+        iptr: iptr.into_boxed_slice(),
+    };
+
+    Ok((params, data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge() {
+        let layout = VertexLayout::build()
+            .with(Attribute::Position, VertexFormat::Float, 3, false)
+            .with(Attribute::Normal, VertexFormat::Float, 3, false)
+            .finish();
+
+        let verts: [[f32; 6]; 3] = [
+            [0.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            [0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+        ];
+
+        let mut params = MeshParams::default();
+        params.layout = layout;
+        params.num_verts = 3;
+        params.num_idxes = 3;
+        params.aabb = Aabb3::new([0.0, 0.0, 0.0].into(), [1.0, 1.0, 0.0].into());
+
+        let data = MeshData {
+            vptr: IndexFormat::encode(&verts).to_vec().into_boxed_slice(),
+            iptr: IndexFormat::encode(&[0u16, 1, 2])
+                .to_vec()
+                .into_boxed_slice(),
+        };
+
+        let mut transform = Transform::default();
+        transform.position = [10.0, 0.0, 0.0].into();
+        transform.rotation = Quaternion::from(Euler::new(Deg(0.0), Deg(180.0), Deg(0.0)));
+
+        let sources = vec![
+            (Transform::default(), &params, &data),
+            (transform, &params, &data),
+        ];
+
+        let (params, data) = super::merge(sources).unwrap();
+        assert_eq!(params.num_verts, 6);
+        assert_eq!(params.num_idxes, 6);
+        assert_eq!(params.index_format, IndexFormat::U16);
+        assert_relative_eq!(
+            params.aabb.min.to_vec(),
+            Vector3::new(0.0, 0.0, 0.0),
+            epsilon = 1e-4
+        );
+        assert_relative_eq!(
+            params.aabb.max.to_vec(),
+            Vector3::new(10.0, 1.0, 0.0),
+            epsilon = 1e-4
+        );
+
+        assert_eq!(&data.iptr[..], IndexFormat::encode(&[0u16, 1, 2, 3, 4, 5]));
+
+        let stride = layout.stride() as usize;
+        let v = &data.vptr[stride * 4..stride * 5];
+        assert_relative_eq!(read(v, 0), Vector3::new(9.0, 0.0, 0.0), epsilon = 1e-4);
+        assert_relative_eq!(read(v, 12), Vector3::new(0.0, 0.0, -1.0), epsilon = 1e-4);
+    }
+}
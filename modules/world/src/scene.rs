@@ -1,13 +1,30 @@
 //! Scenes contain the environments and menus of your game.
 
-use crayon::errors::Result;
-use crayon::math::prelude::{Quaternion, Vector3};
-use crayon::utils::prelude::HandlePool;
+use std::any::{Any, TypeId};
+use std::iter;
+use std::time::Duration;
 
-use assets::prelude::PrefabHandle;
-use renderable::prelude::{Camera, Lit, MeshRenderer, Renderable, Renderer};
+use crayon::errors::Result;
+use crayon::math::prelude::{Quaternion, Ray, Vector3};
+use crayon::utils::prelude::{FastHashMap, HandleLike, HandlePool};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use animation::prelude::{Animations, Animator};
+use assets::prelude::{LightmapAtlas, PrefabHandle};
+use raycast::{MeshCollider, RayHit};
+use renderable::prelude::{Camera, FrameInfo, Lit, LodGroup, MeshRenderer, Renderable, Renderer};
+use renderable::prelude::{Lightmap, Skybox};
+use serialization::{CameraData, EntityData, LodGroupData, MeshRendererData, SceneData};
+use serialization::{SceneResources, SCENE_DATA_VERSION};
 use spatial::prelude::{SceneGraph, Transform};
+use sprite::prelude::{Sprite, Sprites};
 use tags::Tags;
+use text::prelude::{TextRenderer, Texts};
+use utils::dispatcher::StoragePtrs;
+use utils::prelude::{
+    Component, ComponentRegistry, ComponentStorage, Dispatcher, Filter, StorageKind,
+};
 use Entity;
 
 /// Scenes contain the environments and menus of your game. Think of each unique
@@ -16,21 +33,38 @@ use Entity;
 pub struct Scene<R: Renderer> {
     entities: HandlePool<Entity>,
     tags: Tags,
+    colliders: Component<MeshCollider>,
+    components: ComponentRegistry,
+    resources: SceneResources,
+    tick: u32,
 
     pub nodes: SceneGraph,
     pub renderables: Renderable,
+    pub animations: Animations,
+    pub sprites: Sprites,
+    pub texts: Texts,
     pub renderer: R,
 }
 
 impl<R: Renderer> Scene<R> {
     pub fn new(renderer: R) -> Self {
-        Scene {
+        let mut scene = Scene {
             entities: HandlePool::new(),
             tags: Tags::new(),
+            colliders: Component::new(),
+            components: ComponentRegistry::new(),
+            resources: SceneResources::default(),
+            tick: 0,
             nodes: SceneGraph::new(),
             renderables: Renderable::new(),
+            animations: Animations::new(),
+            sprites: Sprites::new(),
+            texts: Texts::new(),
             renderer: renderer,
-        }
+        };
+
+        scene.increase_tick();
+        scene
     }
 
     /// Get the length of entitis in this Scene.
@@ -39,6 +73,12 @@ impl<R: Renderer> Scene<R> {
         self.entities.len()
     }
 
+    /// Gets an iterator over all the entities in this Scene.
+    #[inline]
+    pub fn entities<'a>(&'a self) -> impl Iterator<Item = Entity> + 'a {
+        self.entities.iter()
+    }
+
     /// Checks if specified `Entity` was created by this scene, and has not been
     /// deleted yet.
     #[inline]
@@ -67,6 +107,21 @@ impl<R: Renderer> Scene<R> {
         self.tags.add(ent, name.as_ref());
     }
 
+    /// Sets the layers of this Entity as a bit mask, e.g. `1 << 3`. The entities are only
+    /// drawn by cameras whose culling masks contain any of their layers.
+    ///
+    /// The layers are not inherited by the children.
+    #[inline]
+    pub fn set_layers(&mut self, ent: Entity, layers: u32) {
+        self.tags.set_layers(ent, layers);
+    }
+
+    /// Gets the layers of this Entity, which is `DEFAULT_LAYERS` if not set.
+    #[inline]
+    pub fn layers(&self, ent: Entity) -> u32 {
+        self.tags.layers(ent)
+    }
+
     /// Removes a Entity and all of its descendants from this world.
     pub fn delete(&mut self, ent: Entity) -> Option<Vec<Entity>> {
         if let Some(deletions) = self.nodes.remove(ent) {
@@ -76,6 +131,13 @@ impl<R: Renderer> Scene<R> {
                 self.renderables.remove_mesh(v);
                 self.renderables.remove_lit(v);
                 self.renderables.remove_camera(v);
+                self.renderables.remove_skybox(v);
+                self.renderables.remove_lod_group(v);
+                self.animations.remove_animator(v);
+                self.sprites.remove_sprite(v);
+                self.texts.remove_text(v);
+                self.colliders.remove(v);
+                self.components.remove_all(v);
             }
 
             Some(deletions)
@@ -215,10 +277,235 @@ impl<R: Renderer> Scene<R> {
         }
     }
 
+    /// Saves the entities and their components into `SceneData`.
+    ///
+    /// The sprites, texts, animators, skyboxes, surfaces of cameras and materials of
+    /// renderer are not saved.
+    /// The user-defined components are saved if they are registered with
+    /// `register_component`.
+    pub fn save_scene(&self) -> Result<SceneData> {
+        let mut roots: Vec<_> = self.nodes.roots.iter().cloned().collect();
+        roots.sort_by_key(|v| v.index());
+
+        let mut remap = FastHashMap::default();
+        let mut data = SceneData::default();
+
+        for root in roots {
+            for ent in iter::once(root).chain(self.nodes.descendants(root)) {
+                let mesh_renderer = match self.renderables.mesh(ent) {
+                    Some(v) => Some(MeshRendererData::new(v)?),
+                    None => None,
+                };
+
+                let lod_group = match self.renderables.lod_group(ent) {
+                    Some(v) => Some(LodGroupData::new(v)?),
+                    None => None,
+                };
+
+                remap.insert(ent, data.entities.len());
+                data.entities.push(EntityData {
+                    name: self.name(ent).map(|v| v.to_owned()),
+                    parent: self.nodes.parent(ent).map(|v| remap[&v]),
+                    local_transform: self.nodes.local_transform(ent).unwrap(),
+                    layers: self.layers(ent),
+                    mesh_renderer: mesh_renderer,
+                    lod_group: lod_group,
+                    camera: self.renderables.camera(ent).map(CameraData::new),
+                    lit: self.renderables.lit(ent).cloned(),
+                    components: self.components.save(ent)?,
+                });
+            }
+        }
+
+        data.version = SCENE_DATA_VERSION;
+        data.fog = self.renderables.fog();
+        Ok(data)
+    }
+
+    /// Loads the entities in `SceneData` into this scene, and returns them in the same
+    /// order. Nothing is loaded if it fails.
+    ///
+    /// The meshes and materials referenced by uuids are created once, and kept alive
+    /// until this scene is dropped. The fog of this scene is replaced if there is one in
+    /// `data`.
+    pub fn load_scene(&mut self, data: &SceneData) -> Result<Vec<Entity>> {
+        data.validate()?;
+
+        let mut ents = Vec::with_capacity(data.entities.len());
+        for v in &data.entities {
+            let e = self.entities.create().into();
+            self.nodes.add(e);
+            if let Some(ref name) = v.name {
+                self.tags.add(e, name.as_str());
+            }
+
+            self.nodes.set_local_transform(e, v.local_transform);
+            self.tags.set_layers(e, v.layers);
+            ents.push(e);
+        }
+
+        let mut resources = SceneResources::default();
+        if let Err(err) = self.load_components(data, &ents, &mut resources) {
+            for &v in &ents {
+                self.delete(v);
+            }
+
+            return Err(err);
+        }
+
+        if let Some(fog) = data.fog {
+            self.renderables.set_fog(fog);
+        }
+
+        self.resources.merge(resources);
+        Ok(ents)
+    }
+
+    fn load_components(
+        &mut self,
+        data: &SceneData,
+        ents: &[Entity],
+        resources: &mut SceneResources,
+    ) -> Result<()> {
+        // Children are attached before their existing siblings, so they are attached in
+        // reversed order to keep it.
+        for (i, v) in data.entities.iter().enumerate().rev() {
+            if let Some(parent) = v.parent {
+                self.nodes.set_parent(ents[i], ents[parent], false)?;
+            }
+        }
+
+        for (&e, v) in ents.iter().zip(&data.entities) {
+            if let Some(ref mr) = v.mesh_renderer {
+                self.renderables
+                    .add_mesh(e, mr.to_mesh_renderer(resources)?);
+            }
+
+            if let Some(ref lods) = v.lod_group {
+                self.renderables
+                    .add_lod_group(e, lods.to_lod_group(resources)?);
+            }
+
+            if let Some(ref camera) = v.camera {
+                self.renderables.add_camera(e, camera.to_camera());
+            }
+
+            if let Some(lit) = v.lit {
+                self.renderables.add_lit(e, lit);
+            }
+
+            for &(ref name, ref json) in &v.components {
+                self.components.load(e, name, json)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the current tick, which the changes of transforms and components are stamped
+    /// with.
+    #[inline]
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Increases the tick and returns the previous one. The changes made afterwards are
+    /// stamped with the new tick, so they pass the filters since the returned tick.
+    ///
+    /// ```rust,ignore
+    /// // Visits the meshes which are added or changed since last time.
+    /// let since = std::mem::replace(&mut last, scene.increase_tick());
+    /// for ent in scene.query::<Changed<MeshRenderer>>(since) { ... }
+    /// ```
+    pub fn increase_tick(&mut self) -> u32 {
+        let tick = self.tick;
+        self.tick += 1;
+
+        self.nodes.set_tick(self.tick);
+        self.renderables.set_tick(self.tick);
+        self.colliders.set_tick(self.tick);
+        self.components.set_tick(self.tick);
+        tick
+    }
+
+    /// Gets the entities whose components pass the filter since the tick, e.g.
+    /// `Changed<Transform>` or `Added<MeshRenderer>`.
+    ///
+    /// Transforms, cameras, lits, mesh renderers, skyboxes, colliders and user-defined
+    /// components are tracked. The descendants are not marked as changed when the
+    /// transforms of ancestors change.
+    pub fn query<F: Filter>(&self, since: u32) -> Vec<Entity> {
+        if TypeId::of::<F::Component>() == TypeId::of::<Transform>() {
+            return self.nodes.filter_with(F::matches, since);
+        }
+
+        if let Some(ents) = self.renderables.filter::<F>(since) {
+            return ents;
+        }
+
+        if let Some(colliders) =
+            (&self.colliders as &dyn Any).downcast_ref::<Component<F::Component>>()
+        {
+            return colliders.filter::<F>(since).collect();
+        }
+
+        self.components.filter::<F>(since)
+    }
+
+    /// Advances the animations of current scene.
+    #[inline]
+    pub fn advance(&mut self, dt: Duration) {
+        let dt = dt.as_secs() as f32 + dt.subsec_nanos() as f32 * 1e-9;
+        self.animations.advance(dt, &mut self.renderables);
+    }
+
+    /// Propagates the changes of transforms into the world space in one pass, and updates
+    /// the bounding volume hierarchy of meshes.
+    #[inline]
+    pub fn update_transforms(&mut self) {
+        self.nodes.update_transforms();
+        self.renderables.update_bounds(&self.nodes, &self.tags);
+    }
+
     /// Draw current scene.
     #[inline]
     pub fn draw(&mut self) {
-        self.renderables.draw(&mut self.renderer, &self.nodes);
+        self.nodes.update_transforms();
+        self.renderables
+            .draw(&mut self.renderer, &self.nodes, &self.tags);
+
+        let cameras = self.renderables.cameras();
+        if let Err(err) = self.sprites.draw(cameras, &self.nodes, &self.tags) {
+            warn!("Failed to draw sprites. {}", err);
+        }
+
+        let cameras = self.renderables.cameras();
+        if let Err(err) = self.texts.draw(cameras, &self.nodes, &self.tags) {
+            warn!("Failed to draw texts. {}", err);
+        }
+    }
+
+    /// Casts a ray against the visible meshes of current scene, returns the hits sorted
+    /// from near to far.
+    ///
+    /// The ray is tested against the triangles of `MeshCollider` if the entity has one, or
+    /// the bounding box of mesh otherwise. The meshes that have not moved since last `draw`
+    /// or `update_transforms` are found with the bounding volume hierarchy.
+    #[inline]
+    pub fn raycast(&self, ray: Ray<f32>) -> Vec<RayHit> {
+        ::raycast::raycast(
+            ray,
+            &self.nodes,
+            self.renderables.meshes(),
+            &self.colliders,
+            self.renderables.bvhs(),
+        )
+    }
+
+    /// Gets the statistics of renderables during last frame.
+    #[inline]
+    pub fn frame_info(&self) -> FrameInfo {
+        self.renderables.frame_info()
     }
 }
 
@@ -245,6 +532,28 @@ impl<R: Renderer> Scene<R> {
         self.renderables.remove_camera(ent);
     }
 
+    /// Add skybox component to this Entity, which should have a camera.
+    #[inline]
+    pub fn add_skybox(&mut self, ent: Entity, skybox: Skybox) {
+        self.renderables.add_skybox(ent, skybox);
+    }
+
+    #[inline]
+    pub fn skybox(&self, ent: Entity) -> Option<&Skybox> {
+        self.renderables.skybox(ent)
+    }
+
+    #[inline]
+    pub fn skybox_mut(&mut self, ent: Entity) -> Option<&mut Skybox> {
+        self.renderables.skybox_mut(ent)
+    }
+
+    /// Remove skybox component from this Entity.
+    #[inline]
+    pub fn remove_skybox(&mut self, ent: Entity) {
+        self.renderables.remove_skybox(ent);
+    }
+
     /// Add light component to this Entity.
     #[inline]
     pub fn add_lit(&mut self, ent: Entity, lit: Lit) {
@@ -289,6 +598,242 @@ impl<R: Renderer> Scene<R> {
         self.renderables.remove_mesh(ent);
     }
 
+    /// Applies the lightmaps of atlas to the mesh components of entities, which are found
+    /// by the paths of entries, see `find`. Returns the number of meshes that lightmapped.
+    ///
+    /// The lightmaps are saved into `SceneData` with the uuids of textures, so the textures
+    /// of atlas should be created from files to save scenes.
+    pub fn set_lightmaps(&mut self, atlas: &LightmapAtlas) -> usize {
+        let mut num = 0;
+        for (path, v) in &atlas.entries {
+            let texture = match atlas.textures.get(v.texture) {
+                Some(&texture) => texture,
+                None => continue,
+            };
+
+            let ent = match self.find(path) {
+                Some(ent) => ent,
+                None => {
+                    warn!("[Scene] Could not find {} to apply lightmap.", path);
+                    continue;
+                }
+            };
+
+            if let Some(mr) = self.renderables.mesh_mut(ent) {
+                let mut lightmap = Lightmap::from(texture);
+                lightmap.scale_offset = v.scale_offset.into();
+                mr.lightmap = Some(lightmap);
+                num += 1;
+            }
+        }
+
+        num
+    }
+
+    /// Merges the meshes of entities that share the same material into static batches,
+    /// which are drawn instead of the merged meshes with less draw calls. The batches
+    /// should be re-built after the merged entities are moved or changed. Returns the
+    /// number of batches.
+    ///
+    /// Only the meshes created while `video::set_mesh_readable` is enabled are merged,
+    /// and the animated ones are skipped. Clears `MeshRenderer::static_batching` to opt
+    /// entities out.
+    pub fn build_static_batches(&mut self) -> Result<usize> {
+        let animations = &self.animations;
+        self.renderables
+            .build_static_batches(&self.nodes, &self.tags, |ent| {
+                animations.animator(ent).is_none()
+            })
+    }
+
+    /// Removes the static batches, and draws the merged meshes individually again.
+    #[inline]
+    pub fn clear_static_batches(&mut self) {
+        self.renderables.clear_static_batches();
+    }
+
+    /// Add level of details to the mesh component of this Entity, which replaces its
+    /// mesh and material with the level picked per camera.
+    #[inline]
+    pub fn add_lod_group(&mut self, ent: Entity, lods: LodGroup) {
+        self.renderables.add_lod_group(ent, lods);
+    }
+
+    #[inline]
+    pub fn lod_group(&self, ent: Entity) -> Option<&LodGroup> {
+        self.renderables.lod_group(ent)
+    }
+
+    #[inline]
+    pub fn lod_group_mut(&mut self, ent: Entity) -> Option<&mut LodGroup> {
+        self.renderables.lod_group_mut(ent)
+    }
+
+    /// Remove level of details from this Entity.
+    #[inline]
+    pub fn remove_lod_group(&mut self, ent: Entity) {
+        self.renderables.remove_lod_group(ent);
+    }
+
+    /// Add mesh collider component to this Entity, which is used by `raycast`.
+    #[inline]
+    pub fn add_collider(&mut self, ent: Entity, collider: MeshCollider) {
+        self.colliders.add(ent, collider);
+    }
+
+    #[inline]
+    pub fn collider(&self, ent: Entity) -> Option<&MeshCollider> {
+        self.colliders.get(ent)
+    }
+
+    /// Remove mesh collider component from this Entity.
+    #[inline]
+    pub fn remove_collider(&mut self, ent: Entity) {
+        self.colliders.remove(ent);
+    }
+
+    /// Registers the type of user-defined component with a unique name, so it could be
+    /// saved with `save_scene`.
+    #[inline]
+    pub fn register_component<T>(&mut self, name: &str) -> Result<()>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        self.components.register::<T>(name)
+    }
+
+    /// Serializes the registered user-defined components of entity into pairs of name
+    /// and JSON.
+    #[inline]
+    pub fn serialize_components(&self, ent: Entity) -> Result<Vec<(String, String)>> {
+        self.components.save(ent)
+    }
+
+    /// Deserializes the user-defined component with registered name from JSON, and adds
+    /// it to entity. The existing one is replaced.
+    #[inline]
+    pub fn deserialize_component(&mut self, ent: Entity, name: &str, json: &str) -> Result<()> {
+        self.components.load(ent, name, json)
+    }
+
+    /// Sets the kind of storage of user-defined components with type `T`, it should be
+    /// called before any components of that type are added.
+    #[inline]
+    pub fn set_component_storage<T: Send + 'static>(&mut self, kind: StorageKind) -> Result<()> {
+        self.components.set_storage::<T>(kind)
+    }
+
+    /// Gets the storage of user-defined components with type `T`, which could be used
+    /// to iterate all the components.
+    #[inline]
+    pub fn components<T: 'static>(&self) -> Option<&dyn ComponentStorage<T>> {
+        self.components.storage::<T>()
+    }
+
+    /// Add user-defined component to this Entity.
+    #[inline]
+    pub fn add_component<T: Send + 'static>(&mut self, ent: Entity, component: T) {
+        self.components.add(ent, component);
+    }
+
+    #[inline]
+    pub fn component<T: 'static>(&self, ent: Entity) -> Option<&T> {
+        self.components.get(ent)
+    }
+
+    #[inline]
+    pub fn component_mut<T: 'static>(&mut self, ent: Entity) -> Option<&mut T> {
+        self.components.get_mut(ent)
+    }
+
+    /// Remove user-defined component from this Entity.
+    #[inline]
+    pub fn remove_component<T: 'static>(&mut self, ent: Entity) {
+        self.components.remove::<T>(ent);
+    }
+
+    /// Runs the systems of `dispatcher` over the user-defined components, and the
+    /// built-in ones except transforms, which are kept in the scene graph.
+    ///
+    /// The systems should not add or remove the built-in components, which skips the
+    /// cleanups of scene, e.g. the surfaces of camera viewports.
+    pub fn dispatch(&mut self, dispatcher: &mut Dispatcher) {
+        let mut builtins = StoragePtrs::default();
+        builtins.insert(&mut self.colliders);
+        self.renderables.expose(&mut builtins);
+        self.animations.expose(&mut builtins);
+        self.sprites.expose(&mut builtins);
+        self.texts.expose(&mut builtins);
+
+        dispatcher.run_with(&mut self.components, builtins);
+    }
+
+    /// Add animator component to this Entity.
+    #[inline]
+    pub fn add_animator(&mut self, ent: Entity, animator: Animator) {
+        self.animations.add_animator(ent, animator);
+    }
+
+    #[inline]
+    pub fn animator(&self, ent: Entity) -> Option<&Animator> {
+        self.animations.animator(ent)
+    }
+
+    #[inline]
+    pub fn animator_mut(&mut self, ent: Entity) -> Option<&mut Animator> {
+        self.animations.animator_mut(ent)
+    }
+
+    /// Remove animator component from this Entity.
+    #[inline]
+    pub fn remove_animator(&mut self, ent: Entity) {
+        self.animations.remove_animator(ent);
+    }
+
+    /// Add sprite component to this Entity.
+    #[inline]
+    pub fn add_sprite<T: Into<Sprite>>(&mut self, ent: Entity, sprite: T) {
+        self.sprites.add_sprite(ent, sprite);
+    }
+
+    #[inline]
+    pub fn sprite(&self, ent: Entity) -> Option<&Sprite> {
+        self.sprites.sprite(ent)
+    }
+
+    #[inline]
+    pub fn sprite_mut(&mut self, ent: Entity) -> Option<&mut Sprite> {
+        self.sprites.sprite_mut(ent)
+    }
+
+    /// Remove sprite component from this Entity.
+    #[inline]
+    pub fn remove_sprite(&mut self, ent: Entity) {
+        self.sprites.remove_sprite(ent);
+    }
+
+    /// Add text component to this Entity.
+    #[inline]
+    pub fn add_text(&mut self, ent: Entity, text: TextRenderer) {
+        self.texts.add_text(ent, text);
+    }
+
+    #[inline]
+    pub fn text(&self, ent: Entity) -> Option<&TextRenderer> {
+        self.texts.text(ent)
+    }
+
+    #[inline]
+    pub fn text_mut(&mut self, ent: Entity) -> Option<&mut TextRenderer> {
+        self.texts.text_mut(ent)
+    }
+
+    /// Remove text component from this Entity.
+    #[inline]
+    pub fn remove_text(&mut self, ent: Entity) {
+        self.texts.remove_text(ent);
+    }
+
     /// Add material component to this Entity.
     #[inline]
     pub fn add_mtl(&mut self, ent: Entity, mtl: R::Mtl) {
@@ -374,6 +919,12 @@ impl<R: Renderer> Scene<R> {
         self.nodes.transform(ent)
     }
 
+    /// Sets the transform in world space.
+    #[inline]
+    pub fn set_transform(&mut self, ent: Entity, transform: Transform) {
+        self.nodes.set_transform(ent, transform);
+    }
+
     /// Gets the transform in local space.
     #[inline]
     pub fn local_transform(&self, ent: Entity) -> Option<Transform> {
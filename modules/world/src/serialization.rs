@@ -0,0 +1,540 @@
+//! The serialized snapshots of scenes, which could be saved as JSON or binary files and
+//! loaded back with `Scene::load_scene`.
+
+use std::io::Cursor;
+
+use crayon::bincode;
+use crayon::errors::Result;
+use crayon::math::prelude::{Aabb2, Matrix4, Plane, Projection};
+use crayon::utils::hash::FastHashMap;
+use crayon::uuid::Uuid;
+use crayon::video;
+use crayon::video::assets::mesh::MeshHandle;
+use crayon::video::assets::texture::TextureHandle;
+use serde_json;
+
+use assets::prelude::MaterialHandle;
+use renderable::prelude::{Camera, Fog, Lightmap, Lit, LodGroup, LodLevel, MeshRenderer};
+use spatial::prelude::Transform;
+use tags::DEFAULT_LAYERS;
+
+pub const MAGIC: [u8; 8] = [
+    'S' as u8, 'C' as u8, 'E' as u8, 'N' as u8, ' ' as u8, 0, 0, 1,
+];
+
+/// The version of `SceneData` format, the data saved by later versions are rejected.
+///
+/// The JSON saved by earlier versions is loaded with the defaults of newer fields, while
+/// the binary data must be saved by the current version.
+///
+/// * 2: Added the layers of entities.
+/// * 3: Added the level of details of mesh renderers.
+/// * 4: Added the static batching flags of mesh renderers.
+/// * 5: Added the lightmaps of mesh renderers.
+/// * 6: Added the distance fog of scene.
+pub const SCENE_DATA_VERSION: u32 = 6;
+
+/// The snapshot of entities and their components in a scene.
+///
+/// The entities are stored in tree order, so parents always come before their children.
+/// Resources are referenced by their uuids, and the ones created at runtime without files
+/// could not be saved except the default resources of world.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SceneData {
+    pub version: u32,
+    pub entities: Vec<EntityData>,
+    /// The optional distance fog of scene.
+    #[serde(default)]
+    pub fog: Option<Fog>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EntityData {
+    /// The name of this entity, which is `None` if it has no name.
+    pub name: Option<String>,
+    /// The index of parent entity.
+    pub parent: Option<usize>,
+    /// The transformation in local space.
+    pub local_transform: Transform,
+    /// The bit mask of layers.
+    #[serde(default = "default_layers")]
+    pub layers: u32,
+    /// The optional mesh renderer.
+    pub mesh_renderer: Option<MeshRendererData>,
+    /// The optional level of details of mesh renderer.
+    #[serde(default)]
+    pub lod_group: Option<LodGroupData>,
+    /// The optional camera.
+    pub camera: Option<CameraData>,
+    /// The optional light.
+    pub lit: Option<Lit>,
+    /// The registered user-defined components, as pairs of name and JSON.
+    pub components: Vec<(String, String)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshReference {
+    /// The mesh created from file.
+    Uuid(Uuid),
+    /// The default cube of world.
+    Cube,
+    /// The default sphere of world.
+    Sphere,
+    /// The default quad of world.
+    Quad,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct MeshRendererData {
+    /// The mesh used by the renderer.
+    pub mesh: MeshReference,
+    /// The uuid of optional material asset.
+    pub material: Option<Uuid>,
+    /// Indicates whether this object cast shadows.
+    pub shadow_caster: bool,
+    /// Indicates whether this object receive shadows.
+    pub shadow_receiver: bool,
+    /// Is this renderer visible.
+    pub visible: bool,
+    /// Indicates whether this object could be merged into static batches.
+    #[serde(default = "default_static_batching")]
+    pub static_batching: bool,
+    /// The optional pre-baked lightmap.
+    #[serde(default)]
+    pub lightmap: Option<LightmapData>,
+}
+
+/// The pre-baked lightmap, whose texture is referenced by uuid.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct LightmapData {
+    pub texture: Uuid,
+    pub scale_offset: [f32; 4],
+    pub intensity: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LodGroupData {
+    /// The levels, as the meshes, the uuids of optional materials and screen sizes.
+    pub levels: Vec<(MeshReference, Option<Uuid>, f32)>,
+    pub hysteresis: f32,
+}
+
+/// The settings of camera. The surface is created at runtime and could not be saved.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct CameraData {
+    pub projection: Projection<f32>,
+    pub projection_matrix: Option<Matrix4<f32>>,
+    pub oblique_plane: Option<Plane<f32>>,
+    pub viewport: Option<Aabb2<f32>>,
+    pub order: i32,
+    pub culling_mask: u32,
+    pub exposure: f32,
+}
+
+/// The resources created for the uuids referenced by loaded scenes, each of them is
+/// created once and deleted when this is dropped.
+#[derive(Default)]
+pub struct SceneResources {
+    meshes: FastHashMap<Uuid, MeshHandle>,
+    materials: FastHashMap<Uuid, MaterialHandle>,
+    textures: FastHashMap<Uuid, TextureHandle>,
+}
+
+impl Default for SceneData {
+    fn default() -> Self {
+        SceneData {
+            version: SCENE_DATA_VERSION,
+            entities: Vec::new(),
+            fog: None,
+        }
+    }
+}
+
+impl SceneData {
+    pub fn validate(&self) -> Result<()> {
+        if self.version > SCENE_DATA_VERSION {
+            bail!(
+                "The version {} of scene data is not supported.",
+                self.version
+            );
+        }
+
+        for (i, v) in self.entities.iter().enumerate() {
+            if v.parent.map(|parent| parent >= i).unwrap_or(false) {
+                bail!("The parent of entity {} does not come before it.", i);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serializes into JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Deserializes from JSON.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let data: SceneData = serde_json::from_str(json)?;
+        data.validate()?;
+        Ok(data)
+    }
+
+    /// Serializes into binary format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, self)?;
+        Ok(bytes)
+    }
+
+    /// Deserializes from binary format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 8 || bytes[0..8] != MAGIC[..] {
+            bail!("[SceneData] MAGIC number not match.");
+        }
+
+        // The binary data is not self-describing, so the fields added later could not be
+        // defaulted.
+        let version: u32 = bincode::deserialize(&bytes[8..])?;
+        if version != SCENE_DATA_VERSION {
+            bail!(
+                "The version {} of binary scene data is not supported, it should be {}.",
+                version,
+                SCENE_DATA_VERSION
+            );
+        }
+
+        let mut file = Cursor::new(&bytes[8..]);
+        let data: SceneData = bincode::deserialize_from(&mut file)?;
+        data.validate()?;
+        Ok(data)
+    }
+}
+
+fn default_layers() -> u32 {
+    DEFAULT_LAYERS
+}
+
+fn default_static_batching() -> bool {
+    true
+}
+
+impl CameraData {
+    pub fn new(camera: &Camera) -> Self {
+        CameraData {
+            projection: camera.projection(),
+            projection_matrix: camera.custom_projection_matrix(),
+            oblique_plane: camera.oblique_plane(),
+            viewport: camera.viewport(),
+            order: camera.order(),
+            culling_mask: camera.culling_mask(),
+            exposure: camera.exposure(),
+        }
+    }
+
+    pub fn to_camera(&self) -> Camera {
+        let mut camera = Camera::new(self.projection);
+        camera.set_projection_matrix(self.projection_matrix);
+        camera.set_oblique_plane(self.oblique_plane);
+        camera.set_viewport(self.viewport);
+        camera.set_order(self.order);
+        camera.set_culling_mask(self.culling_mask);
+        camera.set_exposure(self.exposure);
+        camera
+    }
+}
+
+impl SceneResources {
+    /// Gets the mesh of uuid, which is created if it has not been referenced yet.
+    pub fn mesh(&mut self, uuid: Uuid) -> Result<MeshHandle> {
+        if let Some(&handle) = self.meshes.get(&uuid) {
+            return Ok(handle);
+        }
+
+        let handle = video::create_mesh_from_uuid(uuid)?;
+        self.meshes.insert(uuid, handle);
+        Ok(handle)
+    }
+
+    /// Gets the material of uuid, which is created if it has not been referenced yet.
+    pub fn material(&mut self, uuid: Uuid) -> Result<MaterialHandle> {
+        if let Some(&handle) = self.materials.get(&uuid) {
+            return Ok(handle);
+        }
+
+        let handle = ::create_material_from_uuid(uuid)?;
+        self.materials.insert(uuid, handle);
+        Ok(handle)
+    }
+
+    /// Gets the texture of uuid, which is created if it has not been referenced yet.
+    pub fn texture(&mut self, uuid: Uuid) -> Result<TextureHandle> {
+        if let Some(&handle) = self.textures.get(&uuid) {
+            return Ok(handle);
+        }
+
+        let handle = video::create_texture_from_uuid(uuid)?;
+        self.textures.insert(uuid, handle);
+        Ok(handle)
+    }
+
+    /// Moves the resources of `other` into this, the duplicated ones are deleted.
+    pub fn merge(&mut self, mut other: SceneResources) {
+        for (uuid, handle) in other.meshes.drain() {
+            if self.meshes.contains_key(&uuid) {
+                video::delete_mesh(handle);
+            } else {
+                self.meshes.insert(uuid, handle);
+            }
+        }
+
+        for (uuid, handle) in other.materials.drain() {
+            if self.materials.contains_key(&uuid) {
+                ::delete_material(handle);
+            } else {
+                self.materials.insert(uuid, handle);
+            }
+        }
+
+        for (uuid, handle) in other.textures.drain() {
+            if self.textures.contains_key(&uuid) {
+                video::delete_texture(handle);
+            } else {
+                self.textures.insert(uuid, handle);
+            }
+        }
+    }
+}
+
+impl Drop for SceneResources {
+    fn drop(&mut self) {
+        for (_, handle) in self.meshes.drain() {
+            video::delete_mesh(handle);
+        }
+
+        for (_, handle) in self.materials.drain() {
+            ::delete_material(handle);
+        }
+
+        for (_, handle) in self.textures.drain() {
+            video::delete_texture(handle);
+        }
+    }
+}
+
+impl MeshReference {
+    pub fn new(mesh: MeshHandle) -> Result<Self> {
+        let default = ::default();
+        if mesh == default.cube {
+            Ok(MeshReference::Cube)
+        } else if mesh == default.sphere {
+            Ok(MeshReference::Sphere)
+        } else if mesh == default.quad {
+            Ok(MeshReference::Quad)
+        } else {
+            let uuid = video::mesh_uuid(mesh)
+                .ok_or_else(|| format_err!("{:?} is not created from file.", mesh))?;
+            Ok(MeshReference::Uuid(uuid))
+        }
+    }
+
+    /// Gets the mesh it references, which is owned by `resources` if it's created from file.
+    pub fn to_mesh(&self, resources: &mut SceneResources) -> Result<MeshHandle> {
+        let default = ::default();
+        match *self {
+            MeshReference::Uuid(uuid) => resources.mesh(uuid),
+            MeshReference::Cube => Ok(default.cube),
+            MeshReference::Sphere => Ok(default.sphere),
+            MeshReference::Quad => Ok(default.quad),
+        }
+    }
+}
+
+fn material_uuid(material: Option<MaterialHandle>) -> Result<Option<Uuid>> {
+    match material {
+        Some(v) => {
+            let uuid = ::material_uuid(v)
+                .ok_or_else(|| format_err!("{:?} is not created from file.", v))?;
+            Ok(Some(uuid))
+        }
+        None => Ok(None),
+    }
+}
+
+fn material(uuid: Option<Uuid>, resources: &mut SceneResources) -> Result<Option<MaterialHandle>> {
+    match uuid {
+        Some(uuid) => Ok(Some(resources.material(uuid)?)),
+        None => Ok(None),
+    }
+}
+
+impl MeshRendererData {
+    pub fn new(mr: &MeshRenderer) -> Result<Self> {
+        Ok(MeshRendererData {
+            mesh: MeshReference::new(mr.mesh)?,
+            material: material_uuid(mr.material)?,
+            shadow_caster: mr.shadow_caster,
+            shadow_receiver: mr.shadow_receiver,
+            visible: mr.visible,
+            static_batching: mr.static_batching,
+            lightmap: match mr.lightmap {
+                Some(ref v) => Some(LightmapData::new(v)?),
+                None => None,
+            },
+        })
+    }
+
+    /// Creates the `MeshRenderer` with the resources it references, which are owned by
+    /// `resources`.
+    pub fn to_mesh_renderer(&self, resources: &mut SceneResources) -> Result<MeshRenderer> {
+        let mut mr = MeshRenderer::default();
+        mr.mesh = self.mesh.to_mesh(resources)?;
+        mr.material = material(self.material, resources)?;
+        mr.shadow_caster = self.shadow_caster;
+        mr.shadow_receiver = self.shadow_receiver;
+        mr.visible = self.visible;
+        mr.static_batching = self.static_batching;
+        mr.lightmap = match self.lightmap {
+            Some(ref v) => Some(v.to_lightmap(resources)?),
+            None => None,
+        };
+
+        Ok(mr)
+    }
+}
+
+impl LightmapData {
+    pub fn new(lightmap: &Lightmap) -> Result<Self> {
+        let uuid = video::texture_uuid(lightmap.texture)
+            .ok_or_else(|| format_err!("{:?} is not created from file.", lightmap.texture))?;
+
+        Ok(LightmapData {
+            texture: uuid,
+            scale_offset: lightmap.scale_offset.into(),
+            intensity: lightmap.intensity,
+        })
+    }
+
+    /// Creates the `Lightmap` with the texture it references, which is owned by
+    /// `resources`.
+    pub fn to_lightmap(&self, resources: &mut SceneResources) -> Result<Lightmap> {
+        Ok(Lightmap {
+            texture: resources.texture(self.texture)?,
+            scale_offset: self.scale_offset.into(),
+            intensity: self.intensity,
+        })
+    }
+}
+
+impl LodGroupData {
+    pub fn new(lods: &LodGroup) -> Result<Self> {
+        let mut levels = Vec::with_capacity(lods.levels.len());
+        for v in &lods.levels {
+            let mesh = MeshReference::new(v.mesh)?;
+            levels.push((mesh, material_uuid(v.material)?, v.screen_size));
+        }
+
+        Ok(LodGroupData {
+            levels: levels,
+            hysteresis: lods.hysteresis,
+        })
+    }
+
+    /// Creates the `LodGroup` with the resources it references, which are owned by
+    /// `resources`.
+    pub fn to_lod_group(&self, resources: &mut SceneResources) -> Result<LodGroup> {
+        let mut lods = LodGroup::new();
+        for &(ref mesh, uuid, screen_size) in &self.levels {
+            let mesh = mesh.to_mesh(resources)?;
+            lods.add(LodLevel::new(mesh, material(uuid, resources)?, screen_size));
+        }
+
+        lods.hysteresis = self.hysteresis;
+        Ok(lods)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let mut data = SceneData::default();
+        for i in 0..2 {
+            data.entities.push(EntityData {
+                name: Some(format!("{}", i)),
+                parent: if i > 0 { Some(i - 1) } else { None },
+                local_transform: Transform::default(),
+                layers: 1 << i,
+                mesh_renderer: None,
+                lod_group: None,
+                camera: None,
+                lit: Some(Lit::default()),
+                components: vec![("health".into(), "100".into())],
+            });
+        }
+
+        let v = SceneData::from_json(&data.to_json().unwrap()).unwrap();
+        assert_eq!(v.entities.len(), 2);
+        assert_eq!(v.entities[1].parent, Some(0));
+        assert_eq!(v.entities[1].components[0].1, "100");
+
+        let v = SceneData::from_bytes(&data.to_bytes().unwrap()).unwrap();
+        assert_eq!(v.entities[1].name, Some("1".to_owned()));
+        assert!(SceneData::from_bytes(&[0; 4]).is_err());
+
+        data.entities[0].parent = Some(1);
+        assert!(SceneData::from_json(&data.to_json().unwrap()).is_err());
+
+        data.entities[0].parent = None;
+        data.version = SCENE_DATA_VERSION + 1;
+        assert!(SceneData::from_json(&data.to_json().unwrap()).is_err());
+        assert!(SceneData::from_bytes(&data.to_bytes().unwrap()).is_err());
+    }
+
+    #[test]
+    fn versions() {
+        let mut data = SceneData::default();
+        data.entities.push(EntityData {
+            name: None,
+            parent: None,
+            local_transform: Transform::default(),
+            layers: 1 << 2,
+            mesh_renderer: Some(MeshRendererData {
+                mesh: MeshReference::Cube,
+                material: None,
+                shadow_caster: true,
+                shadow_receiver: true,
+                visible: true,
+                static_batching: false,
+                lightmap: None,
+            }),
+            lod_group: None,
+            camera: None,
+            lit: None,
+            components: Vec::new(),
+        });
+
+        // Removes the fields added after version 1.
+        let mut json: serde_json::Value = serde_json::from_str(&data.to_json().unwrap()).unwrap();
+        json["version"] = 1.into();
+        json["entities"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("layers");
+        json["entities"][0]["mesh_renderer"]
+            .as_object_mut()
+            .unwrap()
+            .remove("static_batching");
+        json["entities"][0]["mesh_renderer"]
+            .as_object_mut()
+            .unwrap()
+            .remove("lightmap");
+
+        let v = SceneData::from_json(&json.to_string()).unwrap();
+        assert_eq!(v.entities[0].layers, DEFAULT_LAYERS);
+        assert!(v.entities[0].mesh_renderer.unwrap().static_batching);
+        assert!(v.entities[0].mesh_renderer.unwrap().lightmap.is_none());
+    }
+}
@@ -0,0 +1,367 @@
+use std::hash::Hash;
+
+use crayon::math::prelude::{Aabb3, Frustum, Matrix4, PlaneRelation, Ray};
+use crayon::utils::hash::FastHashMap;
+
+/// A bounding volume hierarchy over the axis aligned bounding boxes of items, which is
+/// used to find the items in view frustums, along rays or inside boxes without testing
+/// all of them.
+///
+/// The hierarchy is built top-down by splitting the items at the median of their centers
+/// along the longest axis. Moving items are refitted in place, which keeps the hierarchy
+/// correct but makes it looser, so it should be re-built once lots of items have moved.
+#[derive(Debug, Clone)]
+pub struct Bvh<T: Copy + Eq + Hash> {
+    nodes: Vec<BvhNode<T>>,
+    leaves: FastHashMap<T, usize>,
+    refits: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BvhNode<T> {
+    aabb: Aabb3<f32>,
+    parent: Option<usize>,
+    content: BvhContent<T>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BvhContent<T> {
+    Leaf(T),
+    Branch(usize, usize),
+}
+
+impl<T: Copy + Eq + Hash> Default for Bvh<T> {
+    fn default() -> Self {
+        Bvh {
+            nodes: Vec::new(),
+            leaves: FastHashMap::default(),
+            refits: 0,
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> Bvh<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Re-builds the hierarchy with items and their bounding boxes. The later one wins
+    /// if there are duplicated items.
+    pub fn build<I>(&mut self, items: I)
+    where
+        I: IntoIterator<Item = (T, Aabb3<f32>)>,
+    {
+        let mut remap = FastHashMap::default();
+        let mut items: Vec<_> = items.into_iter().collect();
+        for (i, v) in items.iter().enumerate() {
+            remap.insert(v.0, i);
+        }
+
+        let mut i = 0;
+        items.retain(|v| {
+            i += 1;
+            remap[&v.0] == i - 1
+        });
+
+        self.nodes.clear();
+        self.leaves.clear();
+        self.refits = 0;
+
+        if !items.is_empty() {
+            self.nodes.reserve(items.len() * 2 - 1);
+            self.build_node(&mut items, None);
+        }
+    }
+
+    /// Removes all the items.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.leaves.clear();
+        self.refits = 0;
+    }
+
+    /// Gets the number of items.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Checks if there is no item in hierarchy.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Checks if the item is in hierarchy.
+    #[inline]
+    pub fn contains(&self, item: T) -> bool {
+        self.leaves.contains_key(&item)
+    }
+
+    /// Gets the bounding box of item.
+    #[inline]
+    pub fn aabb(&self, item: T) -> Option<Aabb3<f32>> {
+        self.leaves.get(&item).map(|&v| self.nodes[v].aabb)
+    }
+
+    /// Gets the bounding box of all the items.
+    #[inline]
+    pub fn bounds(&self) -> Option<Aabb3<f32>> {
+        self.nodes.first().map(|v| v.aabb)
+    }
+
+    /// Gets the number of items that have been refitted since last build.
+    #[inline]
+    pub fn refits(&self) -> usize {
+        self.refits
+    }
+
+    /// Updates the bounding box of item, and refits its ancestors. Returns false if the
+    /// item is not in hierarchy.
+    pub fn refit(&mut self, item: T, aabb: Aabb3<f32>) -> bool {
+        let mut index = match self.leaves.get(&item) {
+            Some(&v) => v,
+            None => return false,
+        };
+
+        if self.nodes[index].aabb == aabb {
+            return true;
+        }
+
+        self.refits += 1;
+        self.nodes[index].aabb = aabb;
+
+        while let Some(parent) = self.nodes[index].parent {
+            let aabb = match self.nodes[parent].content {
+                BvhContent::Branch(lhs, rhs) => union(&self.nodes[lhs].aabb, &self.nodes[rhs].aabb),
+                BvhContent::Leaf(_) => unreachable!(),
+            };
+
+            if self.nodes[parent].aabb == aabb {
+                break;
+            }
+
+            self.nodes[parent].aabb = aabb;
+            index = parent;
+        }
+
+        true
+    }
+
+    /// Gets the items whose bounding boxes are not outside of the view frustum. The
+    /// bounding boxes are transformed into view space with `view_matrix`.
+    pub fn cull(&self, view_matrix: &Matrix4<f32>, frustum: &Frustum<f32>, output: &mut Vec<T>) {
+        self.visit(output, |aabb| {
+            match frustum.contains(&aabb.transform(view_matrix)) {
+                PlaneRelation::In => Some(true),
+                PlaneRelation::Cross => Some(false),
+                PlaneRelation::Out => None,
+            }
+        });
+    }
+
+    /// Gets the items whose bounding boxes are hit by the ray.
+    pub fn raycast(&self, ray: &Ray<f32>, output: &mut Vec<T>) {
+        self.visit(output, |aabb| ray.intersect_aabb(aabb).map(|_| false));
+    }
+
+    /// Gets the items whose bounding boxes overlap with `aabb`.
+    pub fn intersect(&self, aabb: &Aabb3<f32>, output: &mut Vec<T>) {
+        self.visit(output, |v| {
+            if overlaps(v, aabb) {
+                Some(contains(aabb, v))
+            } else {
+                None
+            }
+        });
+    }
+
+    // Visits the hierarchy from top to bottom. The subtree is skipped if `func` returns
+    // `None`, and all the items of it are accepted without further tests if it returns
+    // `Some(true)`.
+    fn visit<F>(&self, output: &mut Vec<T>, func: F)
+    where
+        F: Fn(&Aabb3<f32>) -> Option<bool>,
+    {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = vec![(0, false)];
+        while let Some((index, accepted)) = stack.pop() {
+            let node = &self.nodes[index];
+            let accepted = accepted
+                || match func(&node.aabb) {
+                    Some(v) => v,
+                    None => continue,
+                };
+
+            match node.content {
+                BvhContent::Leaf(v) => output.push(v),
+                BvhContent::Branch(lhs, rhs) => {
+                    stack.push((rhs, accepted));
+                    stack.push((lhs, accepted));
+                }
+            }
+        }
+    }
+
+    fn build_node(&mut self, items: &mut [(T, Aabb3<f32>)], parent: Option<usize>) -> usize {
+        let index = self.nodes.len();
+
+        if items.len() == 1 {
+            self.nodes.push(BvhNode {
+                aabb: items[0].1,
+                parent: parent,
+                content: BvhContent::Leaf(items[0].0),
+            });
+
+            self.leaves.insert(items[0].0, index);
+            return index;
+        }
+
+        let aabb = items[1..]
+            .iter()
+            .fold(items[0].1, |acc, v| union(&acc, &v.1));
+
+        self.nodes.push(BvhNode {
+            aabb: aabb,
+            parent: parent,
+            content: BvhContent::Branch(0, 0),
+        });
+
+        // Splits at the median of centers along the longest axis.
+        let centers = items[1..].iter().fold(
+            Aabb3::new(items[0].1.center(), items[0].1.center()),
+            |acc, v| acc.grow(v.1.center()),
+        );
+
+        let dim = centers.dim();
+        let axis = if dim.x >= dim.y && dim.x >= dim.z {
+            0
+        } else if dim.y >= dim.z {
+            1
+        } else {
+            2
+        };
+
+        items.sort_by(|lhs, rhs| {
+            let (lhs, rhs) = (lhs.1.center()[axis], rhs.1.center()[axis]);
+            lhs.partial_cmp(&rhs).unwrap_or(::std::cmp::Ordering::Equal)
+        });
+
+        let (lhs, rhs) = items.split_at_mut(items.len() / 2);
+        let lhs = self.build_node(lhs, Some(index));
+        let rhs = self.build_node(rhs, Some(index));
+        self.nodes[index].content = BvhContent::Branch(lhs, rhs);
+        index
+    }
+}
+
+#[inline]
+fn union(lhs: &Aabb3<f32>, rhs: &Aabb3<f32>) -> Aabb3<f32> {
+    lhs.grow(rhs.min).grow(rhs.max)
+}
+
+#[inline]
+fn overlaps(lhs: &Aabb3<f32>, rhs: &Aabb3<f32>) -> bool {
+    (0..3).all(|i| lhs.min[i] <= rhs.max[i] && lhs.max[i] >= rhs.min[i])
+}
+
+#[inline]
+fn contains(outer: &Aabb3<f32>, inner: &Aabb3<f32>) -> bool {
+    (0..3).all(|i| outer.min[i] <= inner.min[i] && outer.max[i] >= inner.max[i])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crayon::math::prelude::{Projection, Vector3};
+
+    fn cube(x: f32, y: f32, z: f32) -> Aabb3<f32> {
+        Aabb3::new(
+            [x - 0.5, y - 0.5, z - 0.5].into(),
+            [x + 0.5, y + 0.5, z + 0.5].into(),
+        )
+    }
+
+    fn sorted(mut v: Vec<usize>) -> Vec<usize> {
+        v.sort();
+        v
+    }
+
+    #[test]
+    fn queries() {
+        let mut bvh = Bvh::new();
+        bvh.build((0..100).map(|i| (i, cube(i as f32 * 2.0, 0.0, 0.0))));
+        assert_eq!(bvh.len(), 100);
+        assert!(bvh.contains(99));
+        assert_eq!(
+            bvh.bounds(),
+            Some(Aabb3::new(
+                [-0.5, -0.5, -0.5].into(),
+                [198.5, 0.5, 0.5].into(),
+            ))
+        );
+
+        let mut output = Vec::new();
+        bvh.intersect(&cube(10.0, 0.0, 0.0), &mut output);
+        assert_eq!(sorted(output), vec![5]);
+
+        let mut output = Vec::new();
+        let ray = Ray::new(Vector3::new(4.0, 10.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        bvh.raycast(&ray, &mut output);
+        assert_eq!(output, vec![2]);
+
+        let mut output = Vec::new();
+        let ray = Ray::new(Vector3::new(-10.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        bvh.raycast(&ray, &mut output);
+        assert_eq!(sorted(output), (0..100).collect::<Vec<_>>());
+
+        // The camera looks down the positive z-axis, and the cubes are moved in front of it.
+        let frustum = Frustum::new(Projection::Ortho {
+            width: 10.0,
+            height: 10.0,
+            near: 0.1,
+            far: 100.0,
+        });
+
+        let mut output = Vec::new();
+        bvh.cull(
+            &Matrix4::from_translation(Vector3::new(0.0, 0.0, 10.0)),
+            &frustum,
+            &mut output,
+        );
+        assert_eq!(sorted(output), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn refit() {
+        let mut bvh = Bvh::new();
+        bvh.build((0..16).map(|i| (i, cube(i as f32, 0.0, 0.0))));
+        assert!(!bvh.refit(16, cube(0.0, 0.0, 0.0)));
+        assert!(bvh.refit(3, cube(3.0, 0.0, 0.0)));
+        assert_eq!(bvh.refits(), 0);
+
+        assert!(bvh.refit(3, cube(100.0, 0.0, 0.0)));
+        assert_eq!(bvh.refits(), 1);
+        assert_eq!(bvh.aabb(3), Some(cube(100.0, 0.0, 0.0)));
+        assert_eq!(bvh.bounds().unwrap().max.x, 100.5);
+
+        let mut output = Vec::new();
+        bvh.intersect(&cube(100.0, 0.0, 0.0), &mut output);
+        assert_eq!(output, vec![3]);
+
+        let mut output = Vec::new();
+        bvh.intersect(&cube(3.0, 0.0, 0.0), &mut output);
+        assert_eq!(sorted(output), vec![2, 4]);
+
+        bvh.build(vec![(0, cube(0.0, 0.0, 0.0)), (0, cube(1.0, 0.0, 0.0))]);
+        assert_eq!(bvh.len(), 1);
+        assert_eq!(bvh.aabb(0), Some(cube(1.0, 0.0, 0.0)));
+
+        bvh.clear();
+        assert!(bvh.is_empty());
+        assert_eq!(bvh.bounds(), None);
+    }
+}
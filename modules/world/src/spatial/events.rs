@@ -0,0 +1,155 @@
+use crayon::utils::prelude::{FastHashMap, HandlePool};
+
+use Entity;
+
+impl_handle!(SceneGraphReader);
+
+/// The changes of `SceneGraph`, which are read with `SceneGraph::read_events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneGraphEvent {
+    /// A node has been added.
+    Created(Entity),
+    /// A node has been removed.
+    Destroyed(Entity),
+    /// The parent of node has been changed, the transform in world space of it and its
+    /// descendants might be changed too.
+    ParentChanged(Entity),
+    /// The transform in local space of node has been changed, the transforms in world
+    /// space of its descendants are changed too.
+    ///
+    /// It is sent once until it has been read by any reader, no matter how many times the
+    /// transform has been changed.
+    TransformChanged(Entity),
+}
+
+/// The channel of events, which keeps the events until all the readers have read them.
+pub struct SceneGraphEvents {
+    events: Vec<SceneGraphEvent>,
+    // The index of the first event in `events` since the channel was created.
+    offset: usize,
+    readers: HandlePool<SceneGraphReader>,
+    cursors: FastHashMap<SceneGraphReader, usize>,
+    // The index of last `TransformChanged` event of nodes.
+    transforms: FastHashMap<Entity, usize>,
+}
+
+impl SceneGraphEvents {
+    pub fn new() -> Self {
+        SceneGraphEvents {
+            events: Vec::new(),
+            offset: 0,
+            readers: HandlePool::new(),
+            cursors: FastHashMap::default(),
+            transforms: FastHashMap::default(),
+        }
+    }
+
+    /// Creates a reader, which reads the events sent after it is created.
+    pub fn create_reader(&mut self) -> SceneGraphReader {
+        let reader = self.readers.create();
+        self.cursors.insert(reader, self.offset + self.events.len());
+        reader
+    }
+
+    /// Deletes a reader, the events are not kept for it any more.
+    pub fn delete_reader(&mut self, reader: SceneGraphReader) {
+        if self.readers.free(reader) {
+            self.cursors.remove(&reader);
+            self.trim();
+        }
+    }
+
+    /// Reads the events sent since last read. The events that have been read by all the
+    /// readers are removed in the next read.
+    pub fn read(&mut self, reader: SceneGraphReader) -> &[SceneGraphEvent] {
+        self.trim();
+
+        let end = self.offset + self.events.len();
+        let start = match self.cursors.get_mut(&reader) {
+            Some(cursor) => ::std::mem::replace(cursor, end),
+            None => return &[],
+        };
+
+        &self.events[start - self.offset..]
+    }
+
+    pub fn send(&mut self, event: SceneGraphEvent) {
+        if self.cursors.is_empty() {
+            return;
+        }
+
+        if let SceneGraphEvent::TransformChanged(ent) = event {
+            // Skips it if the last one has not been read by any reader yet.
+            if let Some(&last) = self.transforms.get(&ent) {
+                if last >= self.offset && self.cursors.values().all(|&v| v <= last) {
+                    return;
+                }
+            }
+
+            self.transforms.insert(ent, self.offset + self.events.len());
+        }
+
+        if let SceneGraphEvent::Destroyed(ent) = event {
+            self.transforms.remove(&ent);
+        }
+
+        self.events.push(event);
+    }
+
+    // Removes the events that have been read by all the readers.
+    fn trim(&mut self) {
+        let end = self.offset + self.events.len();
+        let min = self.cursors.values().cloned().min().unwrap_or(end);
+        if min > self.offset {
+            self.events.drain(..min - self.offset);
+            self.offset = min;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crayon::utils::handle::HandleLike;
+
+    #[test]
+    fn events() {
+        let (e1, e2) = (Entity::new(1, 1), Entity::new(2, 1));
+        let mut events = SceneGraphEvents::new();
+        events.send(SceneGraphEvent::Created(e1));
+
+        let r1 = events.create_reader();
+        assert!(events.read(r1).is_empty());
+
+        events.send(SceneGraphEvent::Created(e2));
+        events.send(SceneGraphEvent::TransformChanged(e2));
+        events.send(SceneGraphEvent::TransformChanged(e2));
+
+        let r2 = events.create_reader();
+        events.send(SceneGraphEvent::ParentChanged(e2));
+
+        assert_eq!(
+            events.read(r1),
+            &[
+                SceneGraphEvent::Created(e2),
+                SceneGraphEvent::TransformChanged(e2),
+                SceneGraphEvent::ParentChanged(e2),
+            ]
+        );
+
+        // The transform is changed again after r1 has read the last event.
+        events.send(SceneGraphEvent::TransformChanged(e2));
+        events.send(SceneGraphEvent::TransformChanged(e2));
+        events.send(SceneGraphEvent::Destroyed(e1));
+
+        assert_eq!(events.read(r1).len(), 2);
+        assert_eq!(events.read(r2).len(), 3);
+        assert!(events.read(r2).is_empty());
+        assert!(events.events.is_empty());
+
+        events.delete_reader(r1);
+        events.delete_reader(r2);
+        events.send(SceneGraphEvent::Destroyed(e2));
+        assert!(events.events.is_empty());
+    }
+}
@@ -4,19 +4,30 @@ use crayon::prelude::*;
 use crayon::utils::hash::{FastHashMap, FastHashSet};
 use failure::Error;
 
+use super::events::{SceneGraphEvent, SceneGraphEvents, SceneGraphReader};
 use super::node::Node;
 use super::transform::Transform;
 
+use utils::prelude::{ChangeTicks, Filter};
 use Entity;
 
 /// A simple scene graph that used to tore and manipulate the postiion, rotation and scale
 /// of the object. We do also keeps a tree relationships betweens object in scene graph, so
 /// you can access properties of transformation in both local and world space.
+///
+/// The transforms in world space are cached. Once a node is changed, it and all of its
+/// descendants are marked as dirty, and their world transforms are recomputed from their
+/// ancestors until `update_transforms` propagates the changes in one pass.
 pub struct SceneGraph {
     remap: FastHashMap<Entity, usize>,
     entities: Vec<Entity>,
     nodes: Vec<Node>,
     local_transforms: Vec<Transform>,
+    world_transforms: Vec<Transform>,
+    dirty: Vec<bool>,
+    ticks: Vec<ChangeTicks>,
+    tick: u32,
+    events: SceneGraphEvents,
 
     pub(crate) roots: FastHashSet<Entity>,
 }
@@ -28,6 +39,11 @@ impl SceneGraph {
             entities: Vec::new(),
             nodes: Vec::new(),
             local_transforms: Vec::new(),
+            world_transforms: Vec::new(),
+            dirty: Vec::new(),
+            ticks: Vec::new(),
+            tick: 0,
+            events: SceneGraphEvents::new(),
             roots: FastHashSet::default(),
         }
     }
@@ -43,21 +59,33 @@ impl SceneGraph {
         self.entities.push(ent);
         self.nodes.push(Node::default());
         self.local_transforms.push(Transform::default());
+        self.world_transforms.push(Transform::default());
+        self.dirty.push(false);
+        self.ticks.push(ChangeTicks {
+            added: self.tick,
+            changed: self.tick,
+        });
         self.roots.insert(ent);
+        self.events.send(SceneGraphEvent::Created(ent));
     }
 
     /// Removes a node and all of its descendants from SceneGraph.
     pub(crate) fn remove(&mut self, ent: Entity) -> Option<Vec<Entity>> {
         if self.remap.contains_key(&ent) {
-            self.remove_from_parent(ent, false).unwrap();
+            self.unlink(ent, false).unwrap();
             self.roots.remove(&ent);
 
             let removes: Vec<_> = iter::once(ent).chain(self.descendants(ent)).collect();
             for w in removes.iter() {
+                self.events.send(SceneGraphEvent::Destroyed(*w));
+
                 let index = self.remap.remove(w).unwrap();
                 self.entities.swap_remove(index);
                 self.nodes.swap_remove(index);
                 self.local_transforms.swap_remove(index);
+                self.world_transforms.swap_remove(index);
+                self.dirty.swap_remove(index);
+                self.ticks.swap_remove(index);
 
                 if self.entities.len() != index {
                     *self.remap.get_mut(&self.entities[index]).unwrap() = index;
@@ -70,6 +98,105 @@ impl SceneGraph {
         }
     }
 
+    /// Creates a reader of the events of this scene graph, which reads the events sent
+    /// after it is created.
+    ///
+    /// The events are kept until they have been read by all the readers, so the reader
+    /// should be deleted once it is not used any more.
+    #[inline]
+    pub fn create_reader(&mut self) -> SceneGraphReader {
+        self.events.create_reader()
+    }
+
+    /// Deletes a reader of events.
+    #[inline]
+    pub fn delete_reader(&mut self, reader: SceneGraphReader) {
+        self.events.delete_reader(reader);
+    }
+
+    /// Reads the events sent since last read, e.g. to react to the changes incrementally
+    /// instead of scanning all the nodes every frame.
+    ///
+    /// ```rust,ignore
+    /// let reader = scene.nodes.create_reader();
+    /// // ...
+    /// for v in scene.nodes.read_events(reader) {
+    ///     if let SceneGraphEvent::TransformChanged(ent) = *v {
+    ///         // Syncs the transform of entity and its descendants.
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn read_events(&mut self, reader: SceneGraphReader) -> &[SceneGraphEvent] {
+        self.events.read(reader)
+    }
+
+    /// Sets the tick that the following changes of transforms are stamped with.
+    #[inline]
+    pub(crate) fn set_tick(&mut self, tick: u32) {
+        self.tick = tick;
+    }
+
+    /// Gets the ticks when the node is added and its transform is changed last time.
+    ///
+    /// The transform is changed if its local transform or parent is changed, the
+    /// descendants are not marked as changed when the transforms of ancestors change.
+    #[inline]
+    pub fn change_ticks(&self, ent: Entity) -> Option<ChangeTicks> {
+        self.remap.get(&ent).map(|&index| self.ticks[index])
+    }
+
+    /// Gets the nodes whose transforms pass the filter `F` since the tick.
+    #[inline]
+    pub fn filter<F>(&self, since: u32) -> Vec<Entity>
+    where
+        F: Filter<Component = Transform>,
+    {
+        self.filter_with(F::matches, since)
+    }
+
+    pub(crate) fn filter_with(
+        &self,
+        matches: fn(ChangeTicks, u32) -> bool,
+        since: u32,
+    ) -> Vec<Entity> {
+        self.entities
+            .iter()
+            .zip(&self.ticks)
+            .filter(|(_, &ticks)| matches(ticks, since))
+            .map(|(&ent, _)| ent)
+            .collect()
+    }
+
+    fn transform_changed(&mut self, ent: Entity) {
+        self.stamp(ent);
+        self.invalidate(ent);
+        self.events.send(SceneGraphEvent::TransformChanged(ent));
+    }
+
+    // Marks the node and its descendants as dirty, the descendants of dirty node are
+    // always dirty.
+    fn invalidate(&mut self, ent: Entity) {
+        if let Some(&index) = self.remap.get(&ent) {
+            if self.dirty[index] {
+                return;
+            }
+
+            self.dirty[index] = true;
+            let descendants: Vec<_> = self.descendants(ent).collect();
+            for v in descendants {
+                let index = unsafe { self.index_unchecked(v) };
+                self.dirty[index] = true;
+            }
+        }
+    }
+
+    fn stamp(&mut self, ent: Entity) {
+        if let Some(&index) = self.remap.get(&ent) {
+            self.ticks[index].changed = self.tick;
+        }
+    }
+
     #[inline]
     fn index(&self, ent: Entity) -> Result<usize, Error> {
         self.remap
@@ -127,7 +254,7 @@ impl SceneGraph {
                 self.local_transforms[child_index].position
             };
 
-            self.remove_from_parent(child, false)?;
+            self.unlink(child, false)?;
 
             if let Some(parent) = parent.into() {
                 if parent != child {
@@ -147,10 +274,14 @@ impl SceneGraph {
                 self.roots.remove(&child);
             }
 
+            self.invalidate(child);
+
             if keep_world_pose {
                 self.set_position(child, position);
             }
 
+            self.stamp(child);
+            self.events.send(SceneGraphEvent::ParentChanged(child));
             Ok(())
         }
     }
@@ -161,6 +292,18 @@ impl SceneGraph {
         child: Entity,
         keep_world_pose: bool,
     ) -> Result<(), Error> {
+        self.unlink(child, keep_world_pose)?;
+        self.stamp(child);
+        self.events.send(SceneGraphEvent::ParentChanged(child));
+
+        if keep_world_pose {
+            self.transform_changed(child);
+        }
+
+        Ok(())
+    }
+
+    fn unlink(&mut self, child: Entity, keep_world_pose: bool) -> Result<(), Error> {
         unsafe {
             let child_index = self.index(child)?;
             let position = if keep_world_pose {
@@ -195,6 +338,7 @@ impl SceneGraph {
 
             self.local_transforms[child_index].position = position;
             self.roots.insert(child);
+            self.invalidate(child);
             Ok(())
         }
     }
@@ -337,18 +481,92 @@ impl<'a> Iterator for Descendants<'a> {
 }
 
 impl SceneGraph {
-    /// Gets the transform in world space.
+    /// Gets the transform in world space. It's recomputed from the ancestors if the node
+    /// has been changed since last `update_transforms`.
     #[inline]
     pub fn transform(&self, ent: Entity) -> Option<Transform> {
         self.remap.get(&ent).map(|&index| unsafe {
-            self.ancestors(ent)
-                .map(|v| self.index_unchecked(v))
-                .fold(self.local_transforms[index], |acc, rhs| {
-                    self.local_transforms[rhs] * acc
-                })
+            if !self.dirty[index] {
+                return self.world_transforms[index];
+            }
+
+            let mut transform = self.local_transforms[index];
+            for v in self.ancestors(ent) {
+                let index = self.index_unchecked(v);
+                if !self.dirty[index] {
+                    return self.world_transforms[index] * transform;
+                }
+
+                transform = self.local_transforms[index] * transform;
+            }
+
+            transform
         })
     }
 
+    /// Sets the transform in world space, the local transform is solved with the
+    /// transform of parent.
+    pub fn set_transform(&mut self, ent: Entity, transform: Transform) {
+        if let Some(&index) = self.remap.get(&ent) {
+            let local = match self.parent(ent).and_then(|v| self.transform(v)) {
+                Some(parent) => parent.inverse().map(|v| v * transform),
+                None => Some(transform),
+            };
+
+            if let Some(local) = local {
+                self.local_transforms[index] = local;
+                self.transform_changed(ent);
+            }
+        }
+    }
+
+    /// Checks if the transform of node has been changed since last `update_transforms`.
+    #[inline]
+    pub(crate) fn is_dirty(&self, ent: Entity) -> bool {
+        self.remap
+            .get(&ent)
+            .map(|&v| self.dirty[v])
+            .unwrap_or(false)
+    }
+
+    /// Propagates the changes of transforms into the cached world transforms, the dirty
+    /// subtrees are updated in one traversal from their roots. It's called by `Scene`
+    /// before drawing.
+    pub fn update_transforms(&mut self) {
+        let mut subtree = Vec::new();
+        for i in 0..self.entities.len() {
+            if !self.dirty[i] {
+                continue;
+            }
+
+            // The node is updated with its dirty ancestor.
+            let parent = self.nodes[i]
+                .parent
+                .map(|v| unsafe { self.index_unchecked(v) });
+            if parent.map(|v| self.dirty[v]).unwrap_or(false) {
+                continue;
+            }
+
+            subtree.clear();
+            subtree.extend(iter::once(self.entities[i]).chain(self.descendants(self.entities[i])));
+
+            for &v in &subtree {
+                unsafe {
+                    let index = self.index_unchecked(v);
+                    self.world_transforms[index] = match self.nodes[index].parent {
+                        Some(parent) => {
+                            let parent = self.index_unchecked(parent);
+                            self.world_transforms[parent] * self.local_transforms[index]
+                        }
+                        None => self.local_transforms[index],
+                    };
+
+                    self.dirty[index] = false;
+                }
+            }
+        }
+    }
+
     /// Gets the transform in local space.
     #[inline]
     pub fn local_transform(&self, ent: Entity) -> Option<Transform> {
@@ -362,6 +580,7 @@ impl SceneGraph {
     pub fn set_local_transform(&mut self, ent: Entity, transform: Transform) {
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index] = transform;
+            self.transform_changed(ent);
         }
     }
 }
@@ -375,6 +594,7 @@ impl SceneGraph {
     {
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].position += translation.into();
+            self.transform_changed(ent);
         }
     }
 
@@ -392,11 +612,12 @@ impl SceneGraph {
         if let Some(&index) = self.remap.get(&ent) {
             let t = self
                 .parent(ent)
-                .map(|v| self.transform(v).unwrap())
-                .unwrap_or(Transform::default());
+                .and_then(|v| self.transform(v))
+                .unwrap_or_default();
 
             if let Some(inverse) = t.inverse() {
                 self.local_transforms[index].position = inverse.transform_point(position);
+                self.transform_changed(ent);
             }
         }
     }
@@ -417,6 +638,7 @@ impl SceneGraph {
     {
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].position = position.into();
+            self.transform_changed(ent);
         }
     }
 }
@@ -431,6 +653,7 @@ impl SceneGraph {
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].rotation =
                 rotation.into() * self.local_transforms[index].rotation;
+            self.transform_changed(ent);
         }
     }
 
@@ -440,29 +663,16 @@ impl SceneGraph {
         T1: Into<Vector3<f32>>,
         T2: Into<Vector3<f32>>,
     {
-        if let Some(eye) = self.position(ent) {
-            let center = center.into();
-            let up = up.into();
-
-            let dir = (center - eye).normalize();
-            let side = up.cross(dir).normalize();
-            let up = dir.cross(side).normalize();
-            let rotation: Quaternion<f32> = Matrix3::from_cols(side, up, dir).into();
-
-            self.set_rotation(ent, rotation);
+        if let Some(mut transform) = self.transform(ent) {
+            transform.look_at(center, up);
+            self.set_rotation(ent, transform.rotation);
         }
     }
 
     /// Get rotation of the transform in world space.
     #[inline]
     pub fn rotation(&self, ent: Entity) -> Option<Quaternion<f32>> {
-        self.remap.get(&ent).map(|&index| unsafe {
-            self.ancestors(ent)
-                .map(|v| self.index_unchecked(v))
-                .fold(self.local_transforms[index].rotation, |acc, rhs| {
-                    self.local_transforms[rhs].rotation * acc
-                })
-        })
+        self.transform(ent).map(|transform| transform.rotation)
     }
 
     /// Sets rotation of the transform in world space.
@@ -470,18 +680,15 @@ impl SceneGraph {
     where
         T: Into<Quaternion<f32>>,
     {
-        unsafe {
-            if let Some(&index) = self.remap.get(&ent) {
-                let ancestor_rotation = self
-                    .ancestors(ent)
-                    .map(|v| self.index_unchecked(v))
-                    .fold(Quaternion::one(), |acc, rhs| {
-                        self.local_transforms[rhs].rotation * acc
-                    });
-
-                self.local_transforms[index].rotation =
-                    rotation.into() * ancestor_rotation.invert();
-            }
+        if let Some(&index) = self.remap.get(&ent) {
+            let ancestor_rotation = self
+                .parent(ent)
+                .and_then(|v| self.transform(v))
+                .map(|v| v.rotation)
+                .unwrap_or_else(Quaternion::one);
+
+            self.local_transforms[index].rotation = ancestor_rotation.invert() * rotation.into();
+            self.transform_changed(ent);
         }
     }
 
@@ -501,6 +708,7 @@ impl SceneGraph {
     {
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].rotation = rotation.into();
+            self.transform_changed(ent);
         }
     }
 }
@@ -509,30 +717,25 @@ impl SceneGraph {
     /// Get scale of the transform in world space.
     #[inline]
     pub fn scale(&self, ent: Entity) -> Option<f32> {
-        self.remap.get(&ent).map(|&index| unsafe {
-            self.ancestors(ent)
-                .map(|v| self.index_unchecked(v))
-                .fold(self.local_transforms[index].scale, |acc, rhs| {
-                    self.local_transforms[rhs].scale * acc
-                })
-        })
+        self.transform(ent).map(|transform| transform.scale)
     }
 
     /// Sets scale of the transform in world space.
     pub fn set_scale(&mut self, ent: Entity, scale: f32) {
-        unsafe {
-            if let Some(&index) = self.remap.get(&ent) {
-                let ancestor_scale = self
-                    .ancestors(ent)
-                    .map(|v| self.index_unchecked(v))
-                    .fold(1.0, |acc, rhs| self.local_transforms[rhs].scale * acc);
-
-                if ancestor_scale.abs() > ::std::f32::EPSILON {
-                    self.local_transforms[index].scale = scale / ancestor_scale;
-                } else {
-                    self.local_transforms[index].scale = scale;
-                }
+        if let Some(&index) = self.remap.get(&ent) {
+            let ancestor_scale = self
+                .parent(ent)
+                .and_then(|v| self.transform(v))
+                .map(|v| v.scale)
+                .unwrap_or(1.0);
+
+            if ancestor_scale.abs() > ::std::f32::EPSILON {
+                self.local_transforms[index].scale = scale / ancestor_scale;
+            } else {
+                self.local_transforms[index].scale = scale;
             }
+
+            self.transform_changed(ent);
         }
     }
 
@@ -549,6 +752,7 @@ impl SceneGraph {
     pub fn set_local_scale(&mut self, ent: Entity, scale: f32) {
         if let Some(&index) = self.remap.get(&ent) {
             self.local_transforms[index].scale = scale;
+            self.transform_changed(ent);
         }
     }
 }
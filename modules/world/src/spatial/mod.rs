@@ -1,8 +1,12 @@
+pub mod bvh;
+pub mod events;
 pub mod graph;
 pub mod node;
 pub mod transform;
 
 pub mod prelude {
+    pub use super::bvh::Bvh;
+    pub use super::events::{SceneGraphEvent, SceneGraphReader};
     pub use super::graph::SceneGraph;
     pub use super::node::Node;
     pub use super::transform::Transform;
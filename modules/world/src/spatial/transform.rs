@@ -2,7 +2,7 @@ use crayon::math::prelude::*;
 
 /// `Transform` is used to store and manipulate the postiion, rotation and scale
 /// of the object. We use a left handed, y-up world coordinate system.
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub struct Transform {
     pub scale: f32,
     pub position: Vector3<f32>,
@@ -103,6 +103,36 @@ impl Transform {
         self.transform_direction(Vector3::new(1.0, 0.0, 0.0))
     }
 
+    /// Rotates the transform so the forward vector points at `target`. It keeps the rotation
+    /// if the target is at the position of transform, and an alternative up vector is used if
+    /// `up` is parallel with the forward direction.
+    pub fn look_at<T1, T2>(&mut self, target: T1, up: T2)
+    where
+        T1: Into<Vector3<f32>>,
+        T2: Into<Vector3<f32>>,
+    {
+        let dir = target.into() - self.position;
+        if dir.magnitude2() <= ::std::f32::EPSILON {
+            return;
+        }
+
+        let dir = dir.normalize();
+        let mut side = up.into().cross(dir);
+        if side.magnitude2() <= ::std::f32::EPSILON {
+            let up = if dir.y.abs() < 0.99 {
+                Vector3::unit_y()
+            } else {
+                Vector3::unit_z()
+            };
+
+            side = up.cross(dir);
+        }
+
+        let side = side.normalize();
+        let up = dir.cross(side);
+        self.rotation = Matrix3::from_cols(side, up, dir).into();
+    }
+
     // Returns the view matrix from world space to view space.
     #[inline]
     pub fn view_matrix(&self) -> Matrix4<f32> {
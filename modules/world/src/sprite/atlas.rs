@@ -0,0 +1,262 @@
+use crayon::errors::*;
+use crayon::math::prelude::{Aabb2, Vector2};
+use crayon::res::utils::prelude::ResourceState;
+use crayon::sched::prelude::LatchProbe;
+use crayon::utils::hash::FastHashMap;
+use crayon::uuid::Uuid;
+use crayon::video::assets::texture::TextureHandle;
+
+use super::slice::NineSlice;
+use super::sprite::Sprite;
+
+impl_handle!(TextureAtlasHandle);
+
+/// The region of a frame in texture atlas. The frames could be trimmed by the packing
+/// tools, which removes the transparent pixels around them, and rotated 90 degrees
+/// clockwise to fit the texture better.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    /// The rectangle of frame in texture, in pixels. Its width and height are swapped
+    /// if the frame is rotated.
+    pub rect: Aabb2<u32>,
+    /// Is the frame rotated 90 degrees clockwise in texture.
+    pub rotated: bool,
+    /// The position of the trimmed frame in its source image, in pixels.
+    pub offset: Vector2<u32>,
+    /// The dimensions of the source image before trimming, in pixels.
+    pub source: Vector2<u32>,
+}
+
+impl From<Aabb2<u32>> for AtlasRegion {
+    fn from(rect: Aabb2<u32>) -> Self {
+        AtlasRegion {
+            rect: rect,
+            rotated: false,
+            offset: Vector2::new(0, 0),
+            source: rect.dim(),
+        }
+    }
+}
+
+impl AtlasRegion {
+    /// Gets the dimensions of the trimmed frame before rotation.
+    #[inline]
+    pub fn dimensions(&self) -> Vector2<u32> {
+        let dim = self.rect.dim();
+        if self.rotated {
+            Vector2::new(dim.y, dim.x)
+        } else {
+            dim
+        }
+    }
+}
+
+/// A `TextureAtlas` packs multiple frames of sprites into one texture, so they could
+/// be drawn in a single batch.
+///
+/// The frames are described with rectangles in pixels, which takes the top-left corner
+/// of image as origin.
+///
+/// Texture atlases could be packed offline and loaded as assets, or packed at runtime
+/// with `AtlasPacker`. The texture is owned by the atlas if it's created as an asset.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TextureAtlas {
+    #[serde(skip)]
+    texture: TextureHandle,
+    universe_texture: Option<Uuid>,
+    dimensions: Vector2<u32>,
+    pixels_per_unit: f32,
+    frames: Vec<AtlasRegion>,
+    names: FastHashMap<String, usize>,
+}
+
+impl TextureAtlas {
+    /// Creates a new and empty atlas of texture with `dimensions` in pixels.
+    pub fn new<T>(texture: TextureHandle, dimensions: T) -> Self
+    where
+        T: Into<Vector2<u32>>,
+    {
+        TextureAtlas {
+            texture: texture,
+            universe_texture: None,
+            dimensions: dimensions.into(),
+            pixels_per_unit: 100.0,
+            frames: Vec::new(),
+            names: FastHashMap::default(),
+        }
+    }
+
+    /// Creates a new and empty atlas of texture resource with `uuid`, which is created
+    /// when the atlas is loaded as an asset.
+    pub fn from_uuid<T>(uuid: Uuid, dimensions: T) -> Self
+    where
+        T: Into<Vector2<u32>>,
+    {
+        let mut atlas = TextureAtlas::new(TextureHandle::default(), dimensions);
+        atlas.universe_texture = Some(uuid);
+        atlas
+    }
+
+    /// Creates a new atlas by slicing the texture into cells of same size, the frames
+    /// are indexed from left to right and top to bottom.
+    pub fn grid<T1, T2>(texture: TextureHandle, dimensions: T1, cell: T2) -> Self
+    where
+        T1: Into<Vector2<u32>>,
+        T2: Into<Vector2<u32>>,
+    {
+        let mut atlas = TextureAtlas::new(texture, dimensions);
+        let cell = cell.into();
+
+        if cell.x > 0 && cell.y > 0 {
+            for y in 0..(atlas.dimensions.y / cell.y) {
+                for x in 0..(atlas.dimensions.x / cell.x) {
+                    let min = [x * cell.x, y * cell.y];
+                    let max = [min[0] + cell.x, min[1] + cell.y];
+                    let rect = Aabb2::new(min.into(), max.into());
+                    atlas.frames.push(rect.into());
+                }
+            }
+        }
+
+        atlas
+    }
+
+    /// Gets the texture of this atlas.
+    #[inline]
+    pub fn texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    /// Gets the UUID of the texture resource.
+    #[inline]
+    pub fn universe_texture(&self) -> Option<Uuid> {
+        self.universe_texture
+    }
+
+    #[inline]
+    pub(crate) fn set_texture(&mut self, texture: TextureHandle) {
+        self.texture = texture;
+    }
+
+    /// Gets the dimensions of texture in pixels.
+    #[inline]
+    pub fn dimensions(&self) -> Vector2<u32> {
+        self.dimensions
+    }
+
+    /// Sets how many pixels in the texture correspond to one unit in world space, which
+    /// determines the size of sprites. Its 100 by default.
+    #[inline]
+    pub fn set_pixels_per_unit(&mut self, pixels_per_unit: f32) {
+        self.pixels_per_unit = pixels_per_unit;
+    }
+
+    /// Adds a named frame and returns its index.
+    pub fn add<T: Into<String>>(&mut self, name: T, rect: Aabb2<u32>) -> usize {
+        self.add_region(name, rect.into())
+    }
+
+    /// Adds a named frame that might be trimmed or rotated, and returns its index.
+    pub fn add_region<T: Into<String>>(&mut self, name: T, region: AtlasRegion) -> usize {
+        let index = self.frames.len();
+        self.frames.push(region);
+        self.names.insert(name.into(), index);
+        index
+    }
+
+    /// Finds the index of frame by name.
+    #[inline]
+    pub fn find<T: AsRef<str>>(&self, name: T) -> Option<usize> {
+        self.names.get(name.as_ref()).cloned()
+    }
+
+    /// Gets the number of frames in this atlas.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Gets the rectangle of frame in pixels.
+    #[inline]
+    pub fn frame(&self, index: usize) -> Option<Aabb2<u32>> {
+        self.frames.get(index).map(|v| v.rect)
+    }
+
+    /// Gets the region of frame, including the informations of trimming and rotation.
+    #[inline]
+    pub fn region(&self, index: usize) -> Option<&AtlasRegion> {
+        self.frames.get(index)
+    }
+
+    /// Gets the normalized texture coordinates of frame.
+    pub fn texcoord(&self, index: usize) -> Option<Aabb2<f32>> {
+        let rect = self.frames.get(index)?.rect;
+
+        let (w, h) = (self.dimensions.x as f32, self.dimensions.y as f32);
+        let min = [rect.min.x as f32 / w, rect.min.y as f32 / h];
+        let max = [rect.max.x as f32 / w, rect.max.y as f32 / h];
+        Some(Aabb2::new(min.into(), max.into()))
+    }
+
+    /// Creates a sprite that displays the frame at `index`. The pivot of sprite is placed
+    /// at the center of the source image if the frame is trimmed.
+    pub fn sprite(&self, index: usize) -> Option<Sprite> {
+        let region = self.frames.get(index)?;
+        let texcoord = self.texcoord(index)?;
+
+        let size = region.dimensions();
+        let size = Vector2::new(size.x as f32, size.y as f32);
+        let source = Vector2::new(region.source.x as f32, region.source.y as f32);
+
+        // The pivot is measured from the bottom-left corner of the trimmed frame.
+        let x = source.x * 0.5 - region.offset.x as f32;
+        let y = source.y * 0.5 - (source.y - region.offset.y as f32 - size.y);
+        let pivot = Vector2::new(
+            if size.x > 0.0 { x / size.x } else { 0.5 },
+            if size.y > 0.0 { y / size.y } else { 0.5 },
+        );
+
+        Some(Sprite {
+            texture: Some(self.texture),
+            texcoord: texcoord,
+            rotated: region.rotated,
+            size: size / self.pixels_per_unit,
+            pivot: pivot,
+            ..Default::default()
+        })
+    }
+
+    /// Creates a nine-slice sprite that displays the frame at `index`, the `borders`
+    /// are measured in pixels in the order of left, bottom, right and top. The rotated
+    /// frames are not supported.
+    pub fn sliced_sprite(&self, index: usize, borders: [u32; 4]) -> Option<Sprite> {
+        let mut sprite = self.sprite(index)?;
+        let dimensions = self.frames[index].dimensions();
+        let scale = 1.0 / self.pixels_per_unit;
+        sprite.slice = Some(NineSlice::from_pixels(dimensions, borders, scale));
+        Some(sprite)
+    }
+
+    /// Checks if the frames are inside the texture.
+    pub fn validate(&self) -> Result<()> {
+        for (k, &v) in &self.names {
+            if v >= self.frames.len() {
+                bail!("The frame {} is out of bounds.", k);
+            }
+        }
+
+        for (i, v) in self.frames.iter().enumerate() {
+            if v.rect.max.x > self.dimensions.x || v.rect.max.y > self.dimensions.y {
+                bail!("The rectangle of frame {} is out of texture.", i);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl LatchProbe for TextureAtlasHandle {
+    fn is_set(&self) -> bool {
+        ResourceState::NotReady != crate::texture_atlas_state(*self)
+    }
+}
@@ -0,0 +1,242 @@
+use crayon::errors::*;
+use crayon::math::prelude::{Aabb2, Matrix4};
+use crayon::video;
+use crayon::video::assets::mesh::*;
+use crayon::video::prelude::*;
+
+use renderable::prelude::Camera;
+
+use super::sprite::Sprite;
+
+/// The maximum number of sprites in one batch, which keeps the indices in `u16`.
+pub const MAX_BATCH_SPRITES: usize = 16384;
+
+impl_vertex!{
+    SpriteVertex {
+        position => [Position; Float; 3; false],
+        texcoord => [Texcoord0; Float; 2; false],
+        color => [Color0; Float; 4; false],
+    }
+}
+
+/// A textured quad which has been transformed already.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Quad {
+    pub texture: Option<TextureHandle>,
+    /// The corners in counter-clockwise order starting from the bottom-left one.
+    pub corners: [[f32; 3]; 4],
+    pub texcoord: Aabb2<f32>,
+    /// Is the region in texture rotated 90 degrees clockwise.
+    pub rotated: bool,
+    pub color: [f32; 4],
+}
+
+/// A 2D renderer that batches sprites into a dynamic mesh. Sprites that share the
+/// same texture are drawn with one draw call.
+pub struct SpriteBatch {
+    shader: ShaderHandle,
+    surface: SurfaceHandle,
+    mesh: Option<(MeshHandle, usize)>,
+    quads: Vec<Quad>,
+    verts: Vec<SpriteVertex>,
+    cmds: CommandBuffer,
+}
+
+impl Drop for SpriteBatch {
+    fn drop(&mut self) {
+        if let Some((mesh, _)) = self.mesh {
+            video::delete_mesh(mesh);
+        }
+
+        video::delete_surface(self.surface);
+        video::delete_shader(self.shader);
+    }
+}
+
+impl SpriteBatch {
+    /// Creates a new `SpriteBatch`.
+    pub fn new() -> Result<Self> {
+        let uniforms = UniformVariableLayout::build()
+            .with("u_ViewProjMatrix", UniformVariableType::Matrix4f)
+            .with("u_Texture", UniformVariableType::Texture)
+            .finish();
+
+        let mut params = ShaderParams::default();
+        params.attributes = SpriteVertex::attributes();
+        params.uniforms = uniforms;
+        params.state.color_blend = Some((
+            Equation::Add,
+            BlendFactor::Value(BlendValue::SourceAlpha),
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+        ));
+
+        let vs = format!(
+            "#version 100\nprecision lowp float;\n{}",
+            include_str!("shaders/sprite.vs")
+        );
+
+        let fs = format!(
+            "#version 100\nprecision lowp float;\n{}",
+            include_str!("shaders/sprite.fs")
+        );
+
+        let shader = video::create_shader(params, vs, fs)?;
+
+        let mut params = SurfaceParams::default();
+        params.set_clear(None, None, None);
+        let surface = video::create_surface(params)?;
+
+        Ok(SpriteBatch {
+            shader: shader,
+            surface: surface,
+            mesh: None,
+            quads: Vec::new(),
+            verts: Vec::new(),
+            cmds: CommandBuffer::new(),
+        })
+    }
+
+    /// Draws sprites with camera. The sprites are sorted by their orders and textures,
+    /// and then batched into as few draw calls as possible.
+    pub fn draw(&mut self, camera: &Camera, sprites: &mut [&Sprite]) -> Result<()> {
+        sprites.sort_by_key(|v| (v.order, v.texture));
+
+        let mut quads = ::std::mem::replace(&mut self.quads, Vec::new());
+        quads.clear();
+        for v in sprites.iter() {
+            v.quads(&mut quads);
+        }
+
+        let view_matrix = camera.transform.view_matrix();
+        let projection_matrix = camera.projection_matrix();
+        let surface = camera.surface().unwrap_or(self.surface);
+        let result = self.draw_quads(projection_matrix * view_matrix, surface, &quads);
+
+        self.quads = quads;
+        result
+    }
+
+    /// Draws quads in order, the adjacent quads with the same texture are batched into
+    /// one draw call.
+    pub(crate) fn draw_quads<T>(
+        &mut self,
+        vp: Matrix4<f32>,
+        surface: T,
+        quads: &[Quad],
+    ) -> Result<()>
+    where
+        T: Into<Option<SurfaceHandle>>,
+    {
+        self.draw_clipped_quads(vp, surface, quads, &[])
+    }
+
+    /// Draws quads like `draw_quads`, and applies the scissor of `clips[i]` to the quads
+    /// starting from the index `clips[i].0`. The clips should be sorted by indices, and
+    /// the scissor test is disabled after drawing.
+    pub(crate) fn draw_clipped_quads<T>(
+        &mut self,
+        vp: Matrix4<f32>,
+        surface: T,
+        quads: &[Quad],
+        clips: &[(usize, SurfaceScissor)],
+    ) -> Result<()>
+    where
+        T: Into<Option<SurfaceHandle>>,
+    {
+        let white = crate::default().white;
+        let mut clip = 0;
+
+        for (i, chunk) in quads.chunks(MAX_BATCH_SPRITES).enumerate() {
+            let base = i * MAX_BATCH_SPRITES;
+            let mesh = self.reserve(chunk.len())?;
+
+            self.verts.clear();
+            for v in chunk {
+                let (min, max) = (v.texcoord.min, v.texcoord.max);
+
+                // The texture coordinates take the top-left corner of image as origin,
+                // the rotated regions take the top-left corner of texture as the
+                // bottom-left corner of quad.
+                let uvs = [[min.x, max.y], [max.x, max.y], [max.x, min.y], [min.x, min.y]];
+                let shift = if v.rotated { 3 } else { 0 };
+                for i in 0..4 {
+                    let uv = uvs[(i + shift) % 4];
+                    self.verts.push(SpriteVertex::new(v.corners[i], uv, v.color));
+                }
+            }
+
+            self.cmds
+                .update_vertex_buffer(mesh, 0, SpriteVertex::encode(&self.verts));
+
+            let mut from = 0;
+            while from < chunk.len() {
+                while clip < clips.len() && clips[clip].0 <= base + from {
+                    self.cmds.update_scissor(clips[clip].1);
+                    clip += 1;
+                }
+
+                // Batches are broken at the quads where the next scissor starts.
+                let end = match clips.get(clip) {
+                    Some(v) => (v.0 - base).min(chunk.len()),
+                    None => chunk.len(),
+                };
+
+                let texture = chunk[from].texture;
+                let mut to = from + 1;
+                while to < end && chunk[to].texture == texture {
+                    to += 1;
+                }
+
+                let mut dc = Draw::new(self.shader, mesh);
+                dc.mesh_index = MeshIndex::Ptr(from * 6, (to - from) * 6);
+                dc.set_uniform_variable("u_ViewProjMatrix", vp);
+                dc.set_uniform_variable("u_Texture", texture.unwrap_or(white));
+                self.cmds.draw(dc);
+
+                from = to;
+            }
+        }
+
+        if clip > 0 {
+            self.cmds.update_scissor(SurfaceScissor::Disable);
+        }
+
+        self.cmds.submit(surface.into().unwrap_or(self.surface))?;
+        Ok(())
+    }
+
+    /// Makes sure that the dynamic mesh could hold `len` sprites.
+    fn reserve(&mut self, len: usize) -> Result<MeshHandle> {
+        if let Some((mesh, capacity)) = self.mesh {
+            if capacity >= len {
+                return Ok(mesh);
+            }
+
+            video::delete_mesh(mesh);
+            self.mesh = None;
+        }
+
+        let capacity = len.next_power_of_two().max(64).min(MAX_BATCH_SPRITES);
+
+        let mut idxes = Vec::with_capacity(capacity * 6);
+        for i in 0..capacity {
+            let v = (i * 4) as u16;
+            idxes.extend_from_slice(&[v, v + 1, v + 2, v, v + 2, v + 3]);
+        }
+
+        let mut params = MeshParams::default();
+        params.hint = MeshHint::Stream;
+        params.layout = SpriteVertex::layout();
+        params.num_verts = capacity * 4;
+        params.num_idxes = capacity * 6;
+
+        let data = MeshData {
+            vptr: vec![0; params.vertex_buffer_len()].into_boxed_slice(),
+            iptr: IndexFormat::encode(&idxes).into(),
+        };
+
+        let mesh = video::create_mesh(params, Some(data))?;
+        self.mesh = Some((mesh, capacity));
+        Ok(mesh)
+    }
+}
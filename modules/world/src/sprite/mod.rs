@@ -0,0 +1,112 @@
+//! 2D sprites, which are batched by textures into dynamic meshes.
+//!
+//! The `Sprite` component draws a textured quad in the local XY plane of entity. Frames
+//! of a `TextureAtlas` share the same texture, so they could be drawn in one batch.
+//!
+//! Sprites with `NineSlice` are split into nine quads, whose corners keep their sizes
+//! when the sprites are scaled, which is useful for the panels and buttons of UI.
+
+mod atlas;
+mod packer;
+pub(crate) mod batch;
+mod slice;
+mod sprite;
+
+pub mod prelude {
+    pub use super::atlas::{AtlasRegion, TextureAtlas, TextureAtlasHandle};
+    pub use super::packer::{AtlasPacker, RectPacker};
+    pub use super::batch::SpriteBatch;
+    pub use super::slice::NineSlice;
+    pub use super::sprite::Sprite;
+    pub use super::Sprites;
+}
+
+use crayon::errors::*;
+
+use renderable::prelude::Camera;
+use spatial::prelude::SceneGraph;
+use tags::Tags;
+use utils::dispatcher::StoragePtrs;
+use utils::prelude::Component;
+use Entity;
+
+use self::batch::SpriteBatch;
+use self::sprite::Sprite;
+
+pub struct Sprites {
+    sprites: Component<Sprite>,
+    batch: Option<SpriteBatch>,
+}
+
+impl Sprites {
+    pub fn new() -> Self {
+        Sprites {
+            sprites: Component::new(),
+            batch: None,
+        }
+    }
+
+    /// Exposes the storage of sprites to the systems of `Dispatcher`.
+    pub(crate) fn expose(&mut self, ptrs: &mut StoragePtrs) {
+        ptrs.insert(&mut self.sprites);
+    }
+
+    #[inline]
+    pub fn add_sprite<T: Into<Sprite>>(&mut self, ent: Entity, sprite: T) {
+        self.sprites.add(ent, sprite.into());
+    }
+
+    #[inline]
+    pub fn sprite(&self, ent: Entity) -> Option<&Sprite> {
+        self.sprites.get(ent)
+    }
+
+    #[inline]
+    pub fn sprite_mut(&mut self, ent: Entity) -> Option<&mut Sprite> {
+        self.sprites.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_sprite(&mut self, ent: Entity) {
+        self.sprites.remove(ent);
+    }
+}
+
+impl Sprites {
+    /// Draws the visible sprites with cameras. The `SpriteBatch` is created lazily, so
+    /// scenes without sprites would never allocate video resources for it.
+    pub fn draw<'a, T>(&mut self, cameras: T, sg: &SceneGraph, tags: &Tags) -> Result<()>
+    where
+        T: IntoIterator<Item = &'a Camera>,
+    {
+        for (i, v) in self.sprites.data.iter_mut().enumerate() {
+            if let Some(transform) = sg.transform(self.sprites.entities[i]) {
+                v.transform = transform;
+            }
+
+            v.layers = tags.layers(self.sprites.entities[i]);
+        }
+
+        let sprites: Vec<_> = self.sprites.data.iter().filter(|v| v.visible).collect();
+        if sprites.is_empty() {
+            return Ok(());
+        }
+
+        if self.batch.is_none() {
+            self.batch = Some(SpriteBatch::new()?);
+        }
+
+        let batch = self.batch.as_mut().unwrap();
+        for v in cameras {
+            let mut visibles: Vec<_> = sprites
+                .iter()
+                .filter(|s| v.is_visible(s.layers))
+                .cloned()
+                .collect();
+
+            batch.draw(v, &mut visibles)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use crayon::errors::*;
+use crayon::math::prelude::{Aabb2, Vector2};
+use crayon::video;
+use crayon::video::assets::texture::*;
+
+use super::atlas::TextureAtlas;
+
+/// A shelf packer that places rectangles into a fixed area, row by row. A rectangle is
+/// placed on the shelf whose height fits it best, or a new shelf is opened below.
+#[derive(Debug, Clone)]
+pub struct RectPacker {
+    dimensions: Vector2<u32>,
+    padding: u32,
+    shelves: Vec<Shelf>,
+    bottom: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    x: u32,
+}
+
+impl RectPacker {
+    /// Creates a new packer of area with `dimensions`, the rectangles are separated by
+    /// `padding` pixels to avoid bleeding when sampled with linear filter.
+    pub fn new<T>(dimensions: T, padding: u32) -> Self
+    where
+        T: Into<Vector2<u32>>,
+    {
+        RectPacker {
+            dimensions: dimensions.into(),
+            padding: padding,
+            shelves: Vec::new(),
+            bottom: 0,
+        }
+    }
+
+    /// Places a rectangle with `dimensions`, returns none if there is no more space.
+    pub fn insert<T>(&mut self, dimensions: T) -> Option<Aabb2<u32>>
+    where
+        T: Into<Vector2<u32>>,
+    {
+        let dimensions = dimensions.into();
+        let (w, h) = match (
+            dimensions.x.checked_add(self.padding),
+            dimensions.y.checked_add(self.padding),
+        ) {
+            (Some(w), Some(h)) => (w, h),
+            _ => return None,
+        };
+
+        let width = self.dimensions.x.saturating_add(self.padding);
+        let height = self.dimensions.y.saturating_add(self.padding);
+        if w > width {
+            return None;
+        }
+
+        let best = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.height >= h && v.x.checked_add(w).map_or(false, |x| x <= width))
+            .min_by_key(|(_, v)| v.height - h)
+            .map(|(i, _)| i);
+
+        let index = match best {
+            Some(i) => i,
+            None => {
+                if self.bottom.checked_add(h).map_or(true, |y| y > height) {
+                    return None;
+                }
+
+                self.shelves.push(Shelf {
+                    y: self.bottom,
+                    height: h,
+                    x: 0,
+                });
+
+                self.bottom += h;
+                self.shelves.len() - 1
+            }
+        };
+
+        let shelf = &mut self.shelves[index];
+        let min = [shelf.x, shelf.y];
+        let max = [min[0] + dimensions.x, min[1] + dimensions.y];
+        shelf.x += w;
+        Some(Aabb2::new(min.into(), max.into()))
+    }
+
+    /// Removes all the rectangles.
+    pub fn clear(&mut self) {
+        self.shelves.clear();
+        self.bottom = 0;
+    }
+}
+
+/// Packs images into a texture atlas at runtime, e.g. the glyphs of dynamic texts and
+/// the avatars downloaded from network, so they could be batched with each other.
+///
+/// The texture is owned by packer, and will be deleted with it.
+pub struct AtlasPacker {
+    packer: RectPacker,
+    atlas: TextureAtlas,
+}
+
+impl Drop for AtlasPacker {
+    fn drop(&mut self) {
+        video::delete_texture(self.atlas.texture());
+    }
+}
+
+impl AtlasPacker {
+    /// Creates a new packer with an empty RGBA8 texture of `dimensions` in pixels.
+    pub fn new<T>(dimensions: T) -> Result<Self>
+    where
+        T: Into<Vector2<u32>>,
+    {
+        let mut params = TextureParams::default();
+        params.hint = TextureHint::Dynamic;
+        params.dimensions = dimensions.into();
+
+        let texture = video::create_texture(params, None)?;
+        Ok(AtlasPacker {
+            packer: RectPacker::new(params.dimensions, 1),
+            atlas: TextureAtlas::new(texture, params.dimensions),
+        })
+    }
+
+    /// Packs the RGBA8 pixels of an image with `dimensions` into texture, and adds it as
+    /// a named frame of atlas. Returns the index of frame.
+    pub fn pack<T1, T2>(&mut self, name: T1, dimensions: T2, bytes: &[u8]) -> Result<usize>
+    where
+        T1: Into<String>,
+        T2: Into<Vector2<u32>>,
+    {
+        let dimensions = dimensions.into();
+        let len = u64::from(dimensions.x) * u64::from(dimensions.y) * 4;
+        if bytes.len() as u64 != len {
+            bail!("The length of pixels does not match the dimensions of image.");
+        }
+
+        let name = name.into();
+        let rect = match self.packer.insert(dimensions) {
+            Some(rect) => rect,
+            None => bail!("The atlas is full, which can't hold {}.", name),
+        };
+
+        video::update_texture(self.atlas.texture(), rect, bytes)?;
+        Ok(self.atlas.add(name, rect))
+    }
+
+    /// Gets the texture atlas.
+    #[inline]
+    pub fn atlas(&self) -> &TextureAtlas {
+        &self.atlas
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insert() {
+        let mut packer = RectPacker::new((64, 32), 1);
+
+        let a = packer.insert((31, 15)).unwrap();
+        let b = packer.insert((32, 10)).unwrap();
+        assert_eq!(a, Aabb2::new([0, 0].into(), [31, 15].into()));
+        assert_eq!(b, Aabb2::new([32, 0].into(), [64, 10].into()));
+
+        // The shelf is full, so a new one is opened below.
+        let c = packer.insert((8, 8)).unwrap();
+        assert_eq!(c, Aabb2::new([0, 16].into(), [8, 24].into()));
+
+        // Fits the new shelf better than opening another one.
+        let d = packer.insert((8, 4)).unwrap();
+        assert_eq!(d, Aabb2::new([9, 16].into(), [17, 20].into()));
+
+        assert!(packer.insert((65, 1)).is_none());
+        assert!(packer.insert((8, 16)).is_none());
+        assert!(packer.insert((::std::u32::MAX, 1)).is_none());
+        assert!(packer.insert((1, ::std::u32::MAX)).is_none());
+
+        packer.clear();
+        assert!(packer.insert((64, 32)).is_some());
+    }
+}
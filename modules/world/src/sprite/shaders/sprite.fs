@@ -0,0 +1,8 @@
+varying vec2 v_Texcoord;
+varying vec4 v_Color;
+
+uniform sampler2D u_Texture;
+
+void main() {
+    gl_FragColor = texture2D(u_Texture, v_Texcoord) * v_Color;
+}
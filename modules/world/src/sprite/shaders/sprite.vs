@@ -0,0 +1,14 @@
+attribute vec3 Position;
+attribute vec2 Texcoord0;
+attribute vec4 Color0;
+
+uniform mat4 u_ViewProjMatrix;
+
+varying vec2 v_Texcoord;
+varying vec4 v_Color;
+
+void main() {
+    gl_Position = u_ViewProjMatrix * vec4(Position, 1.0);
+    v_Texcoord = Texcoord0;
+    v_Color = Color0;
+}
@@ -0,0 +1,154 @@
+use crayon::math::prelude::{Aabb2, Vector2};
+
+/// The borders of a nine-slice image, which are kept in their sizes when the image is
+/// scaled, so the corners of panels and buttons would not be stretched. Only the edges
+/// and the center are stretched.
+///
+/// The borders are in the order of left, bottom, right and top.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NineSlice {
+    /// The borders in texture, which are the fractions of the texture region.
+    pub insets: [f32; 4],
+    /// The sizes of borders when drawn, which are measured in the units of the image,
+    /// e.g. the local space of sprites and the points of UI. The borders are shrunk
+    /// proportionally if the image is smaller than them.
+    pub borders: [f32; 4],
+}
+
+impl NineSlice {
+    /// Creates a `NineSlice` from the borders in pixels of a texture region with
+    /// `dimensions` in pixels, and the borders are drawn with `scale` units per pixel.
+    pub fn from_pixels<T>(dimensions: T, borders: [u32; 4], scale: f32) -> Self
+    where
+        T: Into<Vector2<u32>>,
+    {
+        let dimensions = dimensions.into();
+        let (w, h) = (dimensions.x.max(1) as f32, dimensions.y.max(1) as f32);
+
+        NineSlice {
+            insets: [
+                borders[0] as f32 / w,
+                borders[1] as f32 / h,
+                borders[2] as f32 / w,
+                borders[3] as f32 / h,
+            ],
+            borders: [
+                borders[0] as f32 * scale,
+                borders[1] as f32 * scale,
+                borders[2] as f32 * scale,
+                borders[3] as f32 * scale,
+            ],
+        }
+    }
+
+    /// Splits the rectangle and its texture coordinates into nine cells, the empty
+    /// cells are skipped. The texture coordinates take the top-left corner of image as
+    /// origin, and the positions take the bottom-left corner if `y_up` is true, or the
+    /// top-left one if not.
+    pub(crate) fn cells(
+        &self,
+        min: Vector2<f32>,
+        max: Vector2<f32>,
+        texcoord: Aabb2<f32>,
+        y_up: bool,
+    ) -> Vec<(Vector2<f32>, Vector2<f32>, Aabb2<f32>)> {
+        let (i, b) = (self.insets, self.borders);
+        let (tmin, tmax) = (texcoord.min, texcoord.max);
+
+        let (xs, us) = axis([min.x, max.x], [b[0], b[2]], [tmin.x, tmax.x], [i[0], i[2]]);
+        let (ys, vs) = if y_up {
+            axis([min.y, max.y], [b[1], b[3]], [tmax.y, tmin.y], [i[1], i[3]])
+        } else {
+            axis([min.y, max.y], [b[3], b[1]], [tmin.y, tmax.y], [i[3], i[1]])
+        };
+
+        let mut cells = Vec::with_capacity(9);
+        for y in 0..3 {
+            for x in 0..3 {
+                if xs[x + 1] - xs[x] <= 0.0 || ys[y + 1] - ys[y] <= 0.0 {
+                    continue;
+                }
+
+                let uv = Aabb2::new([us[x], vs[y]].into(), [us[x + 1], vs[y + 1]].into());
+                let min = Vector2::new(xs[x], ys[y]);
+                let max = Vector2::new(xs[x + 1], ys[y + 1]);
+                cells.push((min, max, uv));
+            }
+        }
+
+        cells
+    }
+}
+
+/// Splits one axis into three segments, returns the positions and texture coordinates
+/// of the four edges.
+fn axis(p: [f32; 2], borders: [f32; 2], t: [f32; 2], insets: [f32; 2]) -> ([f32; 4], [f32; 4]) {
+    let len = (p[1] - p[0]).max(0.0);
+    let sum = borders[0].max(0.0) + borders[1].max(0.0);
+    let scale = if sum > len && sum > 0.0 {
+        len / sum
+    } else {
+        1.0
+    };
+
+    let positions = [
+        p[0],
+        p[0] + borders[0].max(0.0) * scale,
+        p[1] - borders[1].max(0.0) * scale,
+        p[1],
+    ];
+
+    let d = t[1] - t[0];
+    let texcoords = [t[0], t[0] + d * insets[0], t[1] - d * insets[1], t[1]];
+
+    (positions, texcoords)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cells() {
+        let slice = NineSlice::from_pixels((32, 16), [8, 4, 8, 4], 0.5);
+        assert_eq!(slice.insets, [0.25, 0.25, 0.25, 0.25]);
+        assert_eq!(slice.borders, [4.0, 2.0, 4.0, 2.0]);
+
+        let texcoord = Aabb2::new([0.0, 0.5].into(), [1.0, 1.0].into());
+        let cells = slice.cells(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(20.0, 10.0),
+            texcoord,
+            true,
+        );
+
+        // The bottom-left corner keeps its size, and takes the bottom-left of texture.
+        assert_eq!(cells.len(), 9);
+        assert_eq!(cells[0].0, Vector2::new(0.0, 0.0));
+        assert_eq!(cells[0].1, Vector2::new(4.0, 2.0));
+        assert_eq!(
+            cells[0].2,
+            Aabb2::new([0.0, 0.875].into(), [0.25, 1.0].into())
+        );
+
+        // The center is stretched.
+        assert_eq!(cells[4].0, Vector2::new(4.0, 2.0));
+        assert_eq!(cells[4].1, Vector2::new(16.0, 8.0));
+
+        // The borders are shrunk if the image is smaller than them, and the empty
+        // center column is skipped.
+        let cells = slice.cells(
+            Vector2::new(0.0, 0.0),
+            Vector2::new(4.0, 10.0),
+            texcoord,
+            false,
+        );
+
+        assert_eq!(cells.len(), 6);
+        assert_eq!(cells[0].1, Vector2::new(2.0, 2.0));
+        assert_eq!(
+            cells[0].2,
+            Aabb2::new([0.0, 0.5].into(), [0.25, 0.625].into())
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use crayon::math::prelude::{Aabb2, Color, Vector2};
+use crayon::video::assets::texture::TextureHandle;
+
+use spatial::prelude::Transform;
+use tags::DEFAULT_LAYERS;
+
+use super::batch::Quad;
+use super::slice::NineSlice;
+
+/// A textured quad in the local XY plane of entity, which would be batched with other
+/// sprites that share the same texture.
+#[derive(Debug, Clone, Copy)]
+pub struct Sprite {
+    /// The texture used by this sprite. The default white texture will be used if none.
+    pub texture: Option<TextureHandle>,
+    /// The normalized texture coordinates of this sprite in texture.
+    pub texcoord: Aabb2<f32>,
+    /// Is the region in texture rotated 90 degrees clockwise, e.g. the rotated frames
+    /// of texture atlases.
+    pub rotated: bool,
+    /// The size of this sprite in local space.
+    pub size: Vector2<f32>,
+    /// The normalized pivot of this sprite, `(0.5, 0.5)` is the center of quad.
+    pub pivot: Vector2<f32>,
+    /// Draws this sprite as a nine-slice image, whose borders are measured in local
+    /// space.
+    pub slice: Option<NineSlice>,
+    /// The color that multiplied with texture.
+    pub color: Color<f32>,
+    /// The sprites with greater order will be drawn on top of others.
+    pub order: i32,
+    /// Is this sprite visible.
+    pub visible: bool,
+
+    #[doc(hidden)]
+    pub(crate) transform: Transform,
+    #[doc(hidden)]
+    pub(crate) layers: u32,
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Sprite {
+            texture: None,
+            texcoord: Aabb2::new([0.0, 0.0].into(), [1.0, 1.0].into()),
+            rotated: false,
+            size: Vector2::new(1.0, 1.0),
+            pivot: Vector2::new(0.5, 0.5),
+            slice: None,
+            color: Color::white(),
+            order: 0,
+            visible: true,
+            transform: Transform::default(),
+            layers: DEFAULT_LAYERS,
+        }
+    }
+}
+
+impl From<TextureHandle> for Sprite {
+    fn from(texture: TextureHandle) -> Self {
+        Sprite {
+            texture: Some(texture),
+            ..Default::default()
+        }
+    }
+}
+
+impl Sprite {
+    /// Gets the corners of this sprite in world space, in counter-clockwise order
+    /// starting from the bottom-left one.
+    pub fn corners(&self) -> [[f32; 3]; 4] {
+        let min = Vector2::new(-self.pivot.x * self.size.x, -self.pivot.y * self.size.y);
+        let max = min + self.size;
+
+        let points = [
+            [min.x, min.y, 0.0],
+            [max.x, min.y, 0.0],
+            [max.x, max.y, 0.0],
+            [min.x, max.y, 0.0],
+        ];
+
+        let mut corners = [[0.0; 3]; 4];
+        for (i, v) in points.iter().enumerate() {
+            corners[i] = self.transform.transform_point(*v).into();
+        }
+
+        corners
+    }
+
+    /// Appends the quads of this sprite, which are nine quads at most if it's sliced.
+    pub(crate) fn quads(&self, quads: &mut Vec<Quad>) {
+        let slice = match self.slice {
+            Some(ref slice) => slice,
+            None => {
+                quads.push(Quad {
+                    texture: self.texture,
+                    corners: self.corners(),
+                    texcoord: self.texcoord,
+                    rotated: self.rotated,
+                    color: self.color.rgba(),
+                });
+
+                return;
+            }
+        };
+
+        let min = Vector2::new(-self.pivot.x * self.size.x, -self.pivot.y * self.size.y);
+        let max = min + self.size;
+
+        for (min, max, texcoord) in slice.cells(min, max, self.texcoord, true) {
+            let points = [
+                [min.x, min.y, 0.0],
+                [max.x, min.y, 0.0],
+                [max.x, max.y, 0.0],
+                [min.x, max.y, 0.0],
+            ];
+
+            let mut corners = [[0.0; 3]; 4];
+            for (i, v) in points.iter().enumerate() {
+                corners[i] = self.transform.transform_point(*v).into();
+            }
+
+            quads.push(Quad {
+                texture: self.texture,
+                corners: corners,
+                texcoord: texcoord,
+                rotated: self.rotated,
+                color: self.color.rgba(),
+            });
+        }
+    }
+}
@@ -1,12 +1,19 @@
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 use crayon::application::prelude::*;
+use crayon::input::prelude::Key;
+use crayon::logging::LogSinkHandle;
 use crayon::res::utils::prelude::*;
+use crayon::uuid::Uuid;
 use crayon::video::assets::prelude::*;
 use failure::Error;
 
 use assets::prelude::*;
 use assets::{mesh_builder, texture_builder};
+use console::Console;
+use hud::PerformanceHud;
+use sprite::prelude::{TextureAtlas, TextureAtlasHandle};
+use WorldParams;
 
 #[derive(Debug, Clone, Copy)]
 pub struct WorldDefaultResources {
@@ -18,30 +25,68 @@ pub struct WorldDefaultResources {
 
 pub struct WorldSystem {
     prefabs: Arc<RwLock<ResourcePool<PrefabHandle, PrefabLoader>>>,
+    skeletons: Arc<RwLock<ResourcePool<SkeletonHandle, SkeletonLoader>>>,
+    clips: Arc<RwLock<ResourcePool<AnimationClipHandle, AnimationClipLoader>>>,
+    controllers: Arc<RwLock<ResourcePool<AnimationControllerHandle, AnimationControllerLoader>>>,
+    materials: Arc<RwLock<ResourcePool<MaterialHandle, MaterialLoader>>>,
+    lightmaps: Arc<RwLock<ResourcePool<LightmapAtlasHandle, LightmapAtlasLoader>>>,
+    atlases: Arc<RwLock<ResourcePool<TextureAtlasHandle, TextureAtlasLoader>>>,
+    hud: Arc<Mutex<PerformanceHud>>,
+    console: Arc<Mutex<Console>>,
     lis: LifecycleListenerHandle,
+    logs: Option<LogSinkHandle>,
 
     pub default: WorldDefaultResources,
 }
 
 struct WorldState {
     prefabs: Arc<RwLock<ResourcePool<PrefabHandle, PrefabLoader>>>,
+    skeletons: Arc<RwLock<ResourcePool<SkeletonHandle, SkeletonLoader>>>,
+    clips: Arc<RwLock<ResourcePool<AnimationClipHandle, AnimationClipLoader>>>,
+    controllers: Arc<RwLock<ResourcePool<AnimationControllerHandle, AnimationControllerLoader>>>,
+    materials: Arc<RwLock<ResourcePool<MaterialHandle, MaterialLoader>>>,
+    lightmaps: Arc<RwLock<ResourcePool<LightmapAtlasHandle, LightmapAtlasLoader>>>,
+    atlases: Arc<RwLock<ResourcePool<TextureAtlasHandle, TextureAtlasLoader>>>,
+    hud: Arc<Mutex<PerformanceHud>>,
+    console: Arc<Mutex<Console>>,
 }
 
 impl LifecycleListener for WorldState {
     fn on_pre_update(&mut self) -> Result<(), Error> {
         self.prefabs.write().unwrap().advance()?;
+        self.skeletons.write().unwrap().advance()?;
+        self.clips.write().unwrap().advance()?;
+        self.controllers.write().unwrap().advance()?;
+        self.materials.write().unwrap().advance()?;
+        self.lightmaps.write().unwrap().advance()?;
+        self.atlases.write().unwrap().advance()?;
         Ok(())
     }
+
+    fn on_post_update(&mut self) -> Result<(), Error> {
+        // The submitted lines are executed without locking console, so the commands
+        // could visit it as well.
+        let submitted = self.console.lock().unwrap().advance()?;
+        for v in submitted {
+            let _ = ::console::execute(&v);
+        }
+
+        self.hud.lock().unwrap().advance()
+    }
 }
 
 impl Drop for WorldSystem {
     fn drop(&mut self) {
         crayon::application::detach(self.lis);
+
+        if let Some(handle) = self.logs {
+            crayon::logging::detach(handle);
+        }
     }
 }
 
 impl WorldSystem {
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(params: WorldParams) -> Result<Self, Error> {
         let default = WorldDefaultResources {
             white: texture_builder::white()?,
             sphere: mesh_builder::sphere(2)?,
@@ -50,10 +95,48 @@ impl WorldSystem {
         };
 
         let prefabs = Arc::new(RwLock::new(ResourcePool::new(PrefabLoader::new())));
+        let skeletons = Arc::new(RwLock::new(ResourcePool::new(SkeletonLoader::new())));
+        let clips = Arc::new(RwLock::new(ResourcePool::new(AnimationClipLoader::new())));
+        let controllers = Arc::new(RwLock::new(ResourcePool::new(
+            AnimationControllerLoader::new(),
+        )));
+        let materials = Arc::new(RwLock::new(ResourcePool::new(MaterialLoader::new())));
+        let lightmaps = Arc::new(RwLock::new(ResourcePool::new(LightmapAtlasLoader::new())));
+        let atlases = Arc::new(RwLock::new(ResourcePool::new(TextureAtlasLoader::new())));
+        let hud = Arc::new(Mutex::new(PerformanceHud::new(params.hud)));
+        let console = Arc::new(Mutex::new(Console::new()));
 
-        let shared = WorldSystem {
+        // Prints the records of logging in console.
+        let logs = if crayon::logging::valid() {
+            Some(crayon::logging::attach(console.lock().unwrap().sink()))
+        } else {
+            None
+        };
+
+        let state = WorldState {
             prefabs: prefabs.clone(),
-            lis: crayon::application::attach(WorldState { prefabs }),
+            skeletons: skeletons.clone(),
+            clips: clips.clone(),
+            controllers: controllers.clone(),
+            materials: materials.clone(),
+            lightmaps: lightmaps.clone(),
+            atlases: atlases.clone(),
+            hud: hud.clone(),
+            console: console.clone(),
+        };
+
+        let shared = WorldSystem {
+            prefabs,
+            skeletons,
+            clips,
+            controllers,
+            materials,
+            lightmaps,
+            atlases,
+            hud,
+            console,
+            lis: crayon::application::attach(state),
+            logs: logs,
             default: default,
         };
 
@@ -93,4 +176,296 @@ impl WorldSystem {
     pub fn delete_prefab(&self, handle: PrefabHandle) {
         self.prefabs.write().unwrap().delete(handle);
     }
+
+    /// Create a skeleton object from file asynchronously.
+    #[inline]
+    pub fn create_skeleton_from<T: AsRef<str>>(&self, url: T) -> Result<SkeletonHandle, Error> {
+        let handle = self.skeletons.write().unwrap().create_from(url)?;
+        Ok(handle)
+    }
+
+    /// Creates a skeleton object.
+    #[inline]
+    pub fn create_skeleton(&self, skeleton: Skeleton) -> Result<SkeletonHandle, Error> {
+        skeleton.validate()?;
+        let handle = self.skeletons.write().unwrap().create(skeleton)?;
+        Ok(handle)
+    }
+
+    /// Return the skeleton obejct if exists.
+    #[inline]
+    pub fn skeleton(&self, handle: SkeletonHandle) -> Option<Arc<Skeleton>> {
+        self.skeletons.read().unwrap().resource(handle).cloned()
+    }
+
+    /// Query the resource state of specified skeleton.
+    #[inline]
+    pub fn skeleton_state(&self, handle: SkeletonHandle) -> ResourceState {
+        self.skeletons.read().unwrap().state(handle)
+    }
+
+    /// Delete a skeleton object from this world.
+    #[inline]
+    pub fn delete_skeleton(&self, handle: SkeletonHandle) {
+        self.skeletons.write().unwrap().delete(handle);
+    }
+
+    /// Create a animation clip object from file asynchronously.
+    #[inline]
+    pub fn create_animation_clip_from<T: AsRef<str>>(
+        &self,
+        url: T,
+    ) -> Result<AnimationClipHandle, Error> {
+        let handle = self.clips.write().unwrap().create_from(url)?;
+        Ok(handle)
+    }
+
+    /// Create a animation clip object from file asynchronously.
+    #[inline]
+    pub fn create_animation_clip_from_uuid(
+        &self,
+        uuid: Uuid,
+    ) -> Result<AnimationClipHandle, Error> {
+        let handle = self.clips.write().unwrap().create_from_uuid(uuid)?;
+        Ok(handle)
+    }
+
+    /// Creates a animation clip object.
+    #[inline]
+    pub fn create_animation_clip(&self, clip: AnimationClip) -> Result<AnimationClipHandle, Error> {
+        clip.validate()?;
+        let handle = self.clips.write().unwrap().create(clip)?;
+        Ok(handle)
+    }
+
+    /// Return the animation clip obejct if exists.
+    #[inline]
+    pub fn animation_clip(&self, handle: AnimationClipHandle) -> Option<Arc<AnimationClip>> {
+        self.clips.read().unwrap().resource(handle).cloned()
+    }
+
+    /// Query the resource state of specified animation clip.
+    #[inline]
+    pub fn animation_clip_state(&self, handle: AnimationClipHandle) -> ResourceState {
+        self.clips.read().unwrap().state(handle)
+    }
+
+    /// Delete a animation clip object from this world.
+    #[inline]
+    pub fn delete_animation_clip(&self, handle: AnimationClipHandle) {
+        self.clips.write().unwrap().delete(handle);
+    }
+
+    /// Create a animation controller object from file asynchronously.
+    #[inline]
+    pub fn create_animation_controller_from<T: AsRef<str>>(
+        &self,
+        url: T,
+    ) -> Result<AnimationControllerHandle, Error> {
+        let handle = self.controllers.write().unwrap().create_from(url)?;
+        Ok(handle)
+    }
+
+    /// Creates a animation controller object. The clips are owned by controller, which
+    /// are deleted if it fails.
+    pub fn create_animation_controller(
+        &self,
+        controller: AnimationController,
+    ) -> Result<AnimationControllerHandle, Error> {
+        let clips = controller.clips.clone();
+        let result = match controller.validate() {
+            Ok(_) => self.controllers.write().unwrap().create(controller),
+            Err(err) => Err(err),
+        };
+
+        if result.is_err() {
+            for v in clips {
+                self.delete_animation_clip(v);
+            }
+        }
+
+        Ok(result?)
+    }
+
+    /// Return the animation controller obejct if exists.
+    #[inline]
+    pub fn animation_controller(
+        &self,
+        handle: AnimationControllerHandle,
+    ) -> Option<Arc<AnimationController>> {
+        self.controllers.read().unwrap().resource(handle).cloned()
+    }
+
+    /// Query the resource state of specified animation controller.
+    #[inline]
+    pub fn animation_controller_state(&self, handle: AnimationControllerHandle) -> ResourceState {
+        self.controllers.read().unwrap().state(handle)
+    }
+
+    /// Delete a animation controller object from this world.
+    #[inline]
+    pub fn delete_animation_controller(&self, handle: AnimationControllerHandle) {
+        self.controllers.write().unwrap().delete(handle);
+    }
+
+    /// Create a material object from file asynchronously.
+    #[inline]
+    pub fn create_material_from<T: AsRef<str>>(&self, url: T) -> Result<MaterialHandle, Error> {
+        let handle = self.materials.write().unwrap().create_from(url)?;
+        Ok(handle)
+    }
+
+    /// Create a material object from file asynchronously.
+    #[inline]
+    pub fn create_material_from_uuid(&self, uuid: Uuid) -> Result<MaterialHandle, Error> {
+        let handle = self.materials.write().unwrap().create_from_uuid(uuid)?;
+        Ok(handle)
+    }
+
+    /// Gets the uuid of material, if it is created from file.
+    #[inline]
+    pub fn material_uuid(&self, handle: MaterialHandle) -> Option<Uuid> {
+        self.materials.read().unwrap().uuid(handle)
+    }
+
+    /// Creates a material object.
+    #[inline]
+    pub fn create_material(&self, material: Material) -> Result<MaterialHandle, Error> {
+        material.validate()?;
+        let handle = self.materials.write().unwrap().create(material)?;
+        Ok(handle)
+    }
+
+    /// Return the material obejct if exists.
+    #[inline]
+    pub fn material(&self, handle: MaterialHandle) -> Option<Arc<Material>> {
+        self.materials.read().unwrap().resource(handle).cloned()
+    }
+
+    /// Query the resource state of specified material.
+    #[inline]
+    pub fn material_state(&self, handle: MaterialHandle) -> ResourceState {
+        self.materials.read().unwrap().state(handle)
+    }
+
+    /// Delete a material object from this world.
+    #[inline]
+    pub fn delete_material(&self, handle: MaterialHandle) {
+        self.materials.write().unwrap().delete(handle);
+    }
+
+    /// Create a lightmap atlas object from file asynchronously.
+    #[inline]
+    pub fn create_lightmap_atlas_from<T: AsRef<str>>(
+        &self,
+        url: T,
+    ) -> Result<LightmapAtlasHandle, Error> {
+        let handle = self.lightmaps.write().unwrap().create_from(url)?;
+        Ok(handle)
+    }
+
+    /// Create a lightmap atlas object from file asynchronously.
+    #[inline]
+    pub fn create_lightmap_atlas_from_uuid(
+        &self,
+        uuid: Uuid,
+    ) -> Result<LightmapAtlasHandle, Error> {
+        let handle = self.lightmaps.write().unwrap().create_from_uuid(uuid)?;
+        Ok(handle)
+    }
+
+    /// Creates a lightmap atlas object.
+    #[inline]
+    pub fn create_lightmap_atlas(
+        &self,
+        atlas: LightmapAtlas,
+    ) -> Result<LightmapAtlasHandle, Error> {
+        atlas.validate()?;
+        let handle = self.lightmaps.write().unwrap().create(atlas)?;
+        Ok(handle)
+    }
+
+    /// Return the lightmap atlas obejct if exists.
+    #[inline]
+    pub fn lightmap_atlas(&self, handle: LightmapAtlasHandle) -> Option<Arc<LightmapAtlas>> {
+        self.lightmaps.read().unwrap().resource(handle).cloned()
+    }
+
+    /// Query the resource state of specified lightmap atlas.
+    #[inline]
+    pub fn lightmap_atlas_state(&self, handle: LightmapAtlasHandle) -> ResourceState {
+        self.lightmaps.read().unwrap().state(handle)
+    }
+
+    /// Delete a lightmap atlas object from this world.
+    #[inline]
+    pub fn delete_lightmap_atlas(&self, handle: LightmapAtlasHandle) {
+        self.lightmaps.write().unwrap().delete(handle);
+    }
+
+    /// Create a texture atlas object from file asynchronously.
+    #[inline]
+    pub fn create_texture_atlas_from<T: AsRef<str>>(
+        &self,
+        url: T,
+    ) -> Result<TextureAtlasHandle, Error> {
+        let handle = self.atlases.write().unwrap().create_from(url)?;
+        Ok(handle)
+    }
+
+    /// Create a texture atlas object from file asynchronously.
+    #[inline]
+    pub fn create_texture_atlas_from_uuid(&self, uuid: Uuid) -> Result<TextureAtlasHandle, Error> {
+        let handle = self.atlases.write().unwrap().create_from_uuid(uuid)?;
+        Ok(handle)
+    }
+
+    /// Creates a texture atlas object.
+    #[inline]
+    pub fn create_texture_atlas(&self, atlas: TextureAtlas) -> Result<TextureAtlasHandle, Error> {
+        atlas.validate()?;
+        let handle = self.atlases.write().unwrap().create(atlas)?;
+        Ok(handle)
+    }
+
+    /// Return the texture atlas obejct if exists.
+    #[inline]
+    pub fn texture_atlas(&self, handle: TextureAtlasHandle) -> Option<Arc<TextureAtlas>> {
+        self.atlases.read().unwrap().resource(handle).cloned()
+    }
+
+    /// Query the resource state of specified texture atlas.
+    #[inline]
+    pub fn texture_atlas_state(&self, handle: TextureAtlasHandle) -> ResourceState {
+        self.atlases.read().unwrap().state(handle)
+    }
+
+    /// Delete a texture atlas object from this world.
+    #[inline]
+    pub fn delete_texture_atlas(&self, handle: TextureAtlasHandle) {
+        self.atlases.write().unwrap().delete(handle);
+    }
+
+    #[inline]
+    pub(crate) fn console(&self) -> &Arc<Mutex<Console>> {
+        &self.console
+    }
+
+    /// Sets whether the performance HUD is shown.
+    #[inline]
+    pub fn set_hud_visible(&self, visible: bool) {
+        self.hud.lock().unwrap().set_visible(visible);
+    }
+
+    /// Checks if the performance HUD is shown.
+    #[inline]
+    pub fn hud_visible(&self) -> bool {
+        self.hud.lock().unwrap().visible()
+    }
+
+    /// Sets the key that toggles the performance HUD.
+    #[inline]
+    pub fn set_hud_hotkey(&self, hotkey: Option<Key>) {
+        self.hud.lock().unwrap().set_hotkey(hotkey);
+    }
 }
@@ -3,14 +3,19 @@ use inlinable_string::InlinableString;
 use utils::prelude::Component;
 use Entity;
 
+/// The layers of entities which have not been set explicitly, aka. the first layer.
+pub const DEFAULT_LAYERS: u32 = 1;
+
 pub struct Tags {
     names: Component<InlinableString>,
+    layers: Component<u32>,
 }
 
 impl Tags {
     pub fn new() -> Self {
         Tags {
             names: Component::new(),
+            layers: Component::new(),
         }
     }
 
@@ -22,10 +27,25 @@ impl Tags {
     #[inline]
     pub fn remove(&mut self, ent: Entity) {
         self.names.remove(ent);
+        self.layers.remove(ent);
     }
 
     #[inline]
     pub fn name(&self, ent: Entity) -> Option<&str> {
         self.names.get(ent).map(|v| v.as_ref())
     }
+
+    #[inline]
+    pub fn set_layers(&mut self, ent: Entity, layers: u32) {
+        if layers == DEFAULT_LAYERS {
+            self.layers.remove(ent);
+        } else {
+            self.layers.add(ent, layers);
+        }
+    }
+
+    #[inline]
+    pub fn layers(&self, ent: Entity) -> u32 {
+        self.layers.get(ent).cloned().unwrap_or(DEFAULT_LAYERS)
+    }
 }
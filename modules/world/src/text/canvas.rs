@@ -0,0 +1,152 @@
+//! A canvas that places rectangles and texts in points on top of the window, which is
+//! used by the debugging overlays like the performance HUD and the console, and the GUI.
+
+use crayon::errors::*;
+use crayon::math::prelude::{Aabb2, Color, Matrix4, SquareMatrix, Vector2};
+use crayon::video::assets::surface::SurfaceScissor;
+use crayon::window;
+
+use sprite::batch::{Quad, SpriteBatch};
+use ui::prelude::UiImage;
+
+use super::font::BitmapFont;
+
+pub(crate) struct Canvas {
+    dimensions: Vector2<f32>,
+    device_pixel_ratio: f32,
+    scale: Vector2<f32>,
+    quads: Vec<Quad>,
+    clips: Vec<(usize, SurfaceScissor)>,
+}
+
+impl Canvas {
+    pub fn new() -> Self {
+        Canvas {
+            dimensions: Vector2::new(0.0, 0.0),
+            device_pixel_ratio: 1.0,
+            scale: Vector2::new(0.0, 0.0),
+            quads: Vec::new(),
+            clips: Vec::new(),
+        }
+    }
+
+    /// Clears the quads, and updates the scales from points to normalized device
+    /// coordinates with the dimensions of window.
+    pub fn begin(&mut self) {
+        let dimensions = window::dimensions();
+        let dimensions = Vector2::new(dimensions.x as f32, dimensions.y as f32);
+        self.begin_with(dimensions, window::device_pixel_ratio());
+    }
+
+    /// Clears the quads with the dimensions of window in points, and the device pixel
+    /// ratio that converts points into pixels.
+    pub fn begin_with(&mut self, dimensions: Vector2<f32>, device_pixel_ratio: f32) {
+        self.dimensions = dimensions;
+        self.device_pixel_ratio = device_pixel_ratio;
+        self.scale = Vector2::new(2.0 / dimensions.x.max(1.0), 2.0 / dimensions.y.max(1.0));
+
+        self.quads.clear();
+        self.clips.clear();
+    }
+
+    /// Clips the quads added after this into the rectangle with the scissor test, the
+    /// clipping is disabled with `None`.
+    pub fn clip(&mut self, rect: Option<([f32; 2], [f32; 2])>) {
+        let scissor = match rect {
+            Some((min, max)) => {
+                let dpr = self.device_pixel_ratio;
+                let x = (min[0] * dpr).floor();
+                let y = ((self.dimensions.y - max[1]) * dpr).floor();
+                let w = ((max[0] - min[0]).max(0.0) * dpr).ceil();
+                let h = ((max[1] - min[1]).max(0.0) * dpr).ceil();
+
+                SurfaceScissor::Enable {
+                    position: Vector2::new(x as i32, y as i32),
+                    size: Vector2::new(w as u32, h as u32),
+                }
+            }
+            None => SurfaceScissor::Disable,
+        };
+
+        self.clips.push((self.quads.len(), scissor));
+    }
+
+    /// Adds a rectangle, which takes the top-left corner of window as origin.
+    pub fn rect(&mut self, min: [f32; 2], max: [f32; 2], color: Color<f32>) {
+        let quad = self.quad(min, max, color);
+        self.quads.push(quad);
+    }
+
+    /// Adds the glyphs of text, whose top-left corner is placed at `position`. The glyphs
+    /// are scaled from the pixels of font to `line_height` in points.
+    pub fn text(
+        &mut self,
+        font: &BitmapFont,
+        position: [f32; 2],
+        line_height: f32,
+        text: &str,
+        color: Color<f32>,
+    ) {
+        let scale = line_height / font.line_height().max(::std::f32::EPSILON);
+        let (x, y) = (position[0], position[1]);
+
+        for glyph in font.layout(text) {
+            let (min, max) = (glyph.rect.min, glyph.rect.max);
+            let mut quad = self.quad(
+                [x + min.x * scale, y - max.y * scale],
+                [x + max.x * scale, y - min.y * scale],
+                color,
+            );
+
+            quad.texture = Some(font.texture());
+            quad.texcoord = glyph.texcoord;
+            self.quads.push(quad);
+        }
+    }
+
+    /// Adds an image that is stretched to fill the rectangle. It's drawn as a nine-slice
+    /// image if it has slice, whose borders are measured in points.
+    pub fn image(&mut self, image: &UiImage, min: [f32; 2], max: [f32; 2]) {
+        let slice = match image.slice {
+            Some(ref slice) => slice,
+            None => {
+                let mut quad = self.quad(min, max, image.color);
+                quad.texture = Some(image.texture);
+                quad.texcoord = image.texcoord;
+                quad.rotated = image.rotated;
+                self.quads.push(quad);
+                return;
+            }
+        };
+
+        let (min, max) = (Vector2::from(min), Vector2::from(max));
+        for (min, max, texcoord) in slice.cells(min, max, image.texcoord, false) {
+            let mut quad = self.quad(min.into(), max.into(), image.color);
+            quad.texture = Some(image.texture);
+            quad.texcoord = texcoord;
+            self.quads.push(quad);
+        }
+    }
+
+    /// Draws the quads with their orders.
+    pub fn draw(&self, batch: &mut SpriteBatch) -> Result<()> {
+        if self.quads.is_empty() {
+            return Ok(());
+        }
+
+        batch.draw_clipped_quads(Matrix4::identity(), None, &self.quads, &self.clips)
+    }
+
+    fn quad(&self, min: [f32; 2], max: [f32; 2], color: Color<f32>) -> Quad {
+        let (x0, x1) = (min[0] * self.scale.x - 1.0, max[0] * self.scale.x - 1.0);
+        let (y0, y1) = (1.0 - max[1] * self.scale.y, 1.0 - min[1] * self.scale.y);
+
+        Quad {
+            texture: None,
+            corners: [[x0, y0, 0.0], [x1, y0, 0.0], [x1, y1, 0.0], [x0, y1, 0.0]],
+            texcoord: Aabb2::new([0.0, 0.0].into(), [1.0, 1.0].into()),
+            rotated: false,
+            color: color.rgba(),
+        }
+    }
+}
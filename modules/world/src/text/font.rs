@@ -0,0 +1,187 @@
+use crayon::math::prelude::{Aabb2, Vector2};
+use crayon::utils::hash::FastHashMap;
+use crayon::video::assets::texture::TextureHandle;
+
+/// The metrics of a glyph in `BitmapFont`, all of them are measured in pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct Glyph {
+    /// The rectangle of this glyph in texture, which takes the top-left corner of image
+    /// as origin.
+    pub frame: Aabb2<u32>,
+    /// The offset from the pen position to the top-left corner of glyph, the y axis
+    /// points down.
+    pub offset: Vector2<f32>,
+    /// The horizontal distance that the pen moves after drawing this glyph.
+    pub advance: f32,
+}
+
+/// A `BitmapFont` stores pre-rasterized glyphs in one texture, which could be consumed
+/// by `TextRenderer`s directly.
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+    texture: TextureHandle,
+    dimensions: Vector2<u32>,
+    line_height: f32,
+    glyphs: FastHashMap<char, Glyph>,
+}
+
+impl BitmapFont {
+    /// Creates a new and empty font of texture with `dimensions` in pixels.
+    pub fn new<T>(texture: TextureHandle, dimensions: T, line_height: f32) -> Self
+    where
+        T: Into<Vector2<u32>>,
+    {
+        BitmapFont {
+            texture: texture,
+            dimensions: dimensions.into(),
+            line_height: line_height,
+            glyphs: FastHashMap::default(),
+        }
+    }
+
+    /// Creates a new monospaced font by slicing the texture into cells of same size. The
+    /// cells are assigned to the continuous characters starting from `first`, from left
+    /// to right and top to bottom.
+    pub fn grid<T1, T2>(texture: TextureHandle, dimensions: T1, cell: T2, first: char) -> Self
+    where
+        T1: Into<Vector2<u32>>,
+        T2: Into<Vector2<u32>>,
+    {
+        let cell = cell.into();
+        let mut font = BitmapFont::new(texture, dimensions, cell.y as f32);
+
+        if cell.x > 0 && cell.y > 0 {
+            let mut code = first as u32;
+            for y in 0..(font.dimensions.y / cell.y) {
+                for x in 0..(font.dimensions.x / cell.x) {
+                    if let Some(ch) = ::std::char::from_u32(code) {
+                        let min = [x * cell.x, y * cell.y];
+                        let max = [min[0] + cell.x, min[1] + cell.y];
+                        let glyph = Glyph {
+                            frame: Aabb2::new(min.into(), max.into()),
+                            offset: Vector2::new(0.0, 0.0),
+                            advance: cell.x as f32,
+                        };
+
+                        font.add(ch, glyph);
+                    }
+
+                    code += 1;
+                }
+            }
+        }
+
+        font
+    }
+
+    /// Gets the texture of this font.
+    #[inline]
+    pub fn texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    /// Gets the distance between two baselines in pixels.
+    #[inline]
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Adds or replaces the glyph of character.
+    #[inline]
+    pub fn add(&mut self, ch: char, glyph: Glyph) {
+        self.glyphs.insert(ch, glyph);
+    }
+
+    /// Gets the glyph of character.
+    #[inline]
+    pub fn glyph(&self, ch: char) -> Option<&Glyph> {
+        self.glyphs.get(&ch)
+    }
+
+    /// Measures the size of text block in pixels.
+    pub fn measure(&self, text: &str) -> Vector2<f32> {
+        let mut width = 0.0f32;
+        let mut lines = 0;
+
+        for line in text.split('\n') {
+            let advance: f32 = line
+                .chars()
+                .filter_map(|v| self.glyphs.get(&v))
+                .map(|v| v.advance)
+                .sum();
+
+            width = width.max(advance);
+            lines += 1;
+        }
+
+        Vector2::new(width, lines as f32 * self.line_height)
+    }
+
+    /// Lays out the text, and returns an iterator over the glyph quads. The characters
+    /// without glyph are skipped.
+    pub fn layout<'a>(&'a self, text: &'a str) -> GlyphIter<'a> {
+        GlyphIter {
+            font: self,
+            chars: text.chars(),
+            pen: Vector2::new(0.0, 0.0),
+        }
+    }
+}
+
+/// The quad of a glyph, which is produced by `BitmapFont::layout`.
+#[derive(Debug, Clone, Copy)]
+pub struct GlyphQuad {
+    /// The rectangle of glyph in pixels. The text block starts from origin, and grows
+    /// towards the positive x-axis and the negative y-axis.
+    pub rect: Aabb2<f32>,
+    /// The normalized texture coordinates of glyph.
+    pub texcoord: Aabb2<f32>,
+}
+
+/// An iterator over the glyph quads of text.
+pub struct GlyphIter<'a> {
+    font: &'a BitmapFont,
+    chars: ::std::str::Chars<'a>,
+    pen: Vector2<f32>,
+}
+
+impl<'a> Iterator for GlyphIter<'a> {
+    type Item = GlyphQuad;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(ch) = self.chars.next() {
+            if ch == '\n' {
+                self.pen.x = 0.0;
+                self.pen.y -= self.font.line_height;
+                continue;
+            }
+
+            if let Some(glyph) = self.font.glyphs.get(&ch) {
+                let size = glyph.frame.dim();
+                let min = Vector2::new(
+                    self.pen.x + glyph.offset.x,
+                    self.pen.y - glyph.offset.y - size.y as f32,
+                );
+                let max = Vector2::new(min.x + size.x as f32, min.y + size.y as f32);
+
+                let dims = self.font.dimensions;
+                let tmin = [
+                    glyph.frame.min.x as f32 / dims.x as f32,
+                    glyph.frame.min.y as f32 / dims.y as f32,
+                ];
+                let tmax = [
+                    glyph.frame.max.x as f32 / dims.x as f32,
+                    glyph.frame.max.y as f32 / dims.y as f32,
+                ];
+
+                self.pen.x += glyph.advance;
+                return Some(GlyphQuad {
+                    rect: Aabb2::new([min.x, min.y].into(), [max.x, max.y].into()),
+                    texcoord: Aabb2::new(tmin.into(), tmax.into()),
+                });
+            }
+        }
+
+        None
+    }
+}
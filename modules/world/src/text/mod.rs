@@ -0,0 +1,193 @@
+//! Texts that are laid out with bitmap fonts, and batched into dynamic meshes.
+//!
+//! The `TextRenderer` component either draws text in the local XY plane of entity, or
+//! draws it at the projected position of entity with fixed size in points. The glyph
+//! quads are sorted back-to-front before blending, and rendered with the `SpriteBatch`.
+
+pub(crate) mod builtin;
+pub(crate) mod canvas;
+mod font;
+mod text;
+
+pub mod prelude {
+    pub use super::font::{BitmapFont, Glyph, GlyphIter, GlyphQuad};
+    pub use super::text::{TextRenderer, TextSpace};
+    pub use super::Texts;
+}
+
+use std::cmp::Ordering;
+
+use crayon::errors::*;
+use crayon::math::prelude::{Matrix4, SquareMatrix, Vector2};
+use crayon::window;
+
+use renderable::prelude::Camera;
+use spatial::prelude::SceneGraph;
+use sprite::batch::{Quad, SpriteBatch};
+use tags::Tags;
+use utils::dispatcher::StoragePtrs;
+use utils::prelude::Component;
+use Entity;
+
+use self::text::{TextRenderer, TextSpace};
+
+pub struct Texts {
+    texts: Component<TextRenderer>,
+    batch: Option<SpriteBatch>,
+    quads: Vec<Quad>,
+}
+
+impl Texts {
+    pub fn new() -> Self {
+        Texts {
+            texts: Component::new(),
+            batch: None,
+            quads: Vec::new(),
+        }
+    }
+
+    /// Exposes the storage of texts to the systems of `Dispatcher`.
+    pub(crate) fn expose(&mut self, ptrs: &mut StoragePtrs) {
+        ptrs.insert(&mut self.texts);
+    }
+
+    #[inline]
+    pub fn add_text(&mut self, ent: Entity, text: TextRenderer) {
+        self.texts.add(ent, text);
+    }
+
+    #[inline]
+    pub fn text(&self, ent: Entity) -> Option<&TextRenderer> {
+        self.texts.get(ent)
+    }
+
+    #[inline]
+    pub fn text_mut(&mut self, ent: Entity) -> Option<&mut TextRenderer> {
+        self.texts.get_mut(ent)
+    }
+
+    #[inline]
+    pub fn remove_text(&mut self, ent: Entity) {
+        self.texts.remove(ent);
+    }
+}
+
+impl Texts {
+    /// Draws the visible texts with cameras. The world-space texts are drawn before
+    /// screen-space ones, so the latter would always be on top.
+    pub fn draw<'a, T>(&mut self, cameras: T, sg: &SceneGraph, tags: &Tags) -> Result<()>
+    where
+        T: IntoIterator<Item = &'a Camera>,
+    {
+        for (i, v) in self.texts.data.iter_mut().enumerate() {
+            if let Some(transform) = sg.transform(self.texts.entities[i]) {
+                v.transform = transform;
+            }
+
+            v.layers = tags.layers(self.texts.entities[i]);
+        }
+
+        if !self.texts.data.iter().any(|v| v.visible) {
+            return Ok(());
+        }
+
+        if self.batch.is_none() {
+            self.batch = Some(SpriteBatch::new()?);
+        }
+
+        // The scales from points to normalized device coordinates.
+        let dimensions = window::dimensions();
+        let ndc = Vector2::new(
+            2.0 / dimensions.x.max(1) as f32,
+            2.0 / dimensions.y.max(1) as f32,
+        );
+        let batch = self.batch.as_mut().unwrap();
+
+        for camera in cameras {
+            let view_matrix = camera.transform.view_matrix();
+            let vp = camera.projection_matrix() * view_matrix;
+
+            // Sorts texts by orders, and then back-to-front for proper alpha blending.
+            let mut texts: Vec<_> = self
+                .texts
+                .data
+                .iter()
+                .filter(|v| v.visible && camera.is_visible(v.layers))
+                .map(|v| ((view_matrix * v.transform.position.extend(1.0)).z, v))
+                .collect();
+
+            texts.sort_by(|lhs, rhs| {
+                (lhs.1.order)
+                    .cmp(&rhs.1.order)
+                    .then(rhs.0.partial_cmp(&lhs.0).unwrap_or(Ordering::Equal))
+            });
+
+            for &space in &[TextSpace::World, TextSpace::Screen] {
+                self.quads.clear();
+
+                for &(_, v) in texts.iter().filter(|v| v.1.space == space) {
+                    let (offset, scale) = v.placement();
+                    let color = v.color.rgba();
+                    let texture = Some(v.font.texture());
+
+                    // The screen-space texts are placed in normalized device coordinates.
+                    let center = if space == TextSpace::Screen {
+                        let clip = vp * v.transform.position.extend(1.0);
+                        if clip.w <= 0.0 {
+                            continue;
+                        }
+
+                        Vector2::new(clip.x / clip.w, clip.y / clip.w)
+                    } else {
+                        Vector2::new(0.0, 0.0)
+                    };
+
+                    for glyph in v.font.layout(&v.text) {
+                        let (min, max) = (glyph.rect.min, glyph.rect.max);
+                        let points = [
+                            [min.x, min.y],
+                            [max.x, min.y],
+                            [max.x, max.y],
+                            [min.x, max.y],
+                        ];
+
+                        let mut corners = [[0.0; 3]; 4];
+                        for (i, p) in points.iter().enumerate() {
+                            let x = (p[0] + offset.x) * scale;
+                            let y = (p[1] + offset.y) * scale;
+
+                            corners[i] = match space {
+                                TextSpace::World => v.transform.transform_point([x, y, 0.0]).into(),
+                                TextSpace::Screen => {
+                                    [center.x + x * ndc.x, center.y + y * ndc.y, 0.0]
+                                }
+                            };
+                        }
+
+                        self.quads.push(Quad {
+                            texture: texture,
+                            corners: corners,
+                            texcoord: glyph.texcoord,
+                            rotated: false,
+                            color: color,
+                        });
+                    }
+                }
+
+                if self.quads.is_empty() {
+                    continue;
+                }
+
+                let m = if space == TextSpace::World {
+                    vp
+                } else {
+                    Matrix4::identity()
+                };
+
+                batch.draw_quads(m, camera.surface(), &self.quads)?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use crayon::math::prelude::{Color, Vector2};
+
+use spatial::prelude::Transform;
+use tags::DEFAULT_LAYERS;
+
+use super::font::BitmapFont;
+
+/// The space that `TextRenderer` is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextSpace {
+    /// The text is placed in the local XY plane of entity, and the size is measured
+    /// in world units.
+    World,
+    /// The text is placed at the projected position of entity and always faces the
+    /// screen, the size is measured in points.
+    Screen,
+}
+
+/// A block of text which is drawn with the glyphs of `BitmapFont`.
+#[derive(Debug, Clone)]
+pub struct TextRenderer {
+    /// The text, lines are separated by `\n`.
+    pub text: String,
+    /// The font used to lay out and draw glyphs.
+    pub font: Arc<BitmapFont>,
+    /// The height of a line, in world units or points depending on `space`.
+    pub size: f32,
+    /// The normalized anchor of the text block, `(0.0, 0.0)` is the bottom-left corner.
+    pub anchor: Vector2<f32>,
+    /// The color that multiplied with glyphs.
+    pub color: Color<f32>,
+    /// The space that this text is drawn in.
+    pub space: TextSpace,
+    /// The texts with greater order will be drawn on top of others.
+    pub order: i32,
+    /// Is this text visible.
+    pub visible: bool,
+
+    #[doc(hidden)]
+    pub(crate) transform: Transform,
+    #[doc(hidden)]
+    pub(crate) layers: u32,
+}
+
+impl TextRenderer {
+    /// Creates a new world-space `TextRenderer`.
+    pub fn new<T: Into<String>>(font: Arc<BitmapFont>, text: T) -> Self {
+        TextRenderer {
+            text: text.into(),
+            font: font,
+            size: 1.0,
+            anchor: Vector2::new(0.5, 0.5),
+            color: Color::white(),
+            space: TextSpace::World,
+            order: 0,
+            visible: true,
+            transform: Transform::default(),
+            layers: DEFAULT_LAYERS,
+        }
+    }
+
+    /// Gets the offset from the anchor to the top-left corner of text block, and the
+    /// scale from font pixels to the units of `space`.
+    pub(crate) fn placement(&self) -> (Vector2<f32>, f32) {
+        let size = self.font.measure(&self.text);
+        let scale = self.size / self.font.line_height().max(::std::f32::EPSILON);
+        let offset = Vector2::new(-self.anchor.x * size.x, (1.0 - self.anchor.y) * size.y);
+        (offset, scale)
+    }
+}
@@ -0,0 +1,154 @@
+use crayon::math::prelude::{Aabb2, Vector2};
+
+use super::widget::{UiAlign, UiDirection, UiFlex, Widget};
+
+#[inline]
+pub(crate) fn rect(min: Vector2<f32>, size: Vector2<f32>) -> Aabb2<f32> {
+    let size = Vector2::new(size.x.max(0.0), size.y.max(0.0));
+    Aabb2::new(
+        [min.x, min.y].into(),
+        [min.x + size.x, min.y + size.y].into(),
+    )
+}
+
+#[inline]
+pub(crate) fn intersect(lhs: Aabb2<f32>, rhs: Aabb2<f32>) -> Aabb2<f32> {
+    let min = Vector2::new(lhs.min.x.max(rhs.min.x), lhs.min.y.max(rhs.min.y));
+    let max = Vector2::new(lhs.max.x.min(rhs.max.x), lhs.max.y.min(rhs.max.y));
+    rect(min, max - min)
+}
+
+#[inline]
+pub(crate) fn contains(rect: Aabb2<f32>, point: Vector2<f32>) -> bool {
+    point.x >= rect.min.x && point.x < rect.max.x && point.y >= rect.min.y && point.y < rect.max.y
+}
+
+/// Places the widget in the rectangle of parent with its anchors and pivot.
+pub(crate) fn anchored(parent: Aabb2<f32>, widget: &Widget) -> Aabb2<f32> {
+    let origin = Vector2::new(parent.min.x, parent.min.y);
+    let extent = Vector2::new(parent.max.x - parent.min.x, parent.max.y - parent.min.y);
+
+    let (amin, amax, pivot) = (widget.anchor_min, widget.anchor_max, widget.pivot);
+    let size = Vector2::new(
+        (amax.x - amin.x) * extent.x + widget.size[0].resolve(extent.x),
+        (amax.y - amin.y) * extent.y + widget.size[1].resolve(extent.y),
+    );
+
+    let reference = Vector2::new(
+        origin.x + (amin.x + (amax.x - amin.x) * pivot.x) * extent.x,
+        origin.y + (amin.y + (amax.y - amin.y) * pivot.y) * extent.y,
+    );
+
+    let min = reference + widget.position - Vector2::new(pivot.x * size.x, pivot.y * size.y);
+    rect(min, size)
+}
+
+/// Places the widgets one after another in the rectangle of flex container. The
+/// remaining space along the main axis is distributed by the `grow` of widgets.
+pub(crate) fn flex(parent: Aabb2<f32>, flex: &UiFlex, widgets: &[&Widget]) -> Vec<Aabb2<f32>> {
+    let row = flex.direction == UiDirection::Row;
+    let pick = |v: Vector2<f32>| if row { (v.x, v.y) } else { (v.y, v.x) };
+    let unpick = |main: f32, cross: f32| {
+        if row {
+            Vector2::new(main, cross)
+        } else {
+            Vector2::new(cross, main)
+        }
+    };
+
+    let padding = flex.padding;
+    let (origin_main, origin_cross) = pick(Vector2::new(parent.min.x, parent.min.y));
+    let (extent_main, extent_cross) = pick(Vector2::new(
+        parent.max.x - parent.min.x - padding * 2.0,
+        parent.max.y - parent.min.y - padding * 2.0,
+    ));
+
+    let (imain, icross) = if row { (0, 1) } else { (1, 0) };
+    let basis: Vec<_> = widgets
+        .iter()
+        .map(|v| v.size[imain].resolve(extent_main).max(0.0))
+        .collect();
+
+    let spacing = flex.spacing * (widgets.len().max(1) - 1) as f32;
+    let free = extent_main - basis.iter().sum::<f32>() - spacing;
+    let grow: f32 = widgets.iter().map(|v| v.grow.max(0.0)).sum();
+
+    let mut cursor = origin_main + padding;
+    let mut rects = Vec::with_capacity(widgets.len());
+    for (widget, basis) in widgets.iter().zip(basis) {
+        let mut main = basis;
+        if free > 0.0 && grow > 0.0 {
+            main += free * widget.grow.max(0.0) / grow;
+        }
+
+        let available = extent_cross.max(0.0);
+        let cross = match flex.align {
+            UiAlign::Stretch => available,
+            _ => widget.size[icross].resolve(extent_cross),
+        };
+
+        let offset = match flex.align {
+            UiAlign::Start | UiAlign::Stretch => 0.0,
+            UiAlign::Center => (available - cross) * 0.5,
+            UiAlign::End => available - cross,
+        };
+
+        let min = unpick(cursor, origin_cross + padding + offset);
+        rects.push(rect(min, unpick(main, cross)));
+        cursor += main + flex.spacing;
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::widget::UiLength;
+    use super::*;
+
+    #[test]
+    fn anchored() {
+        let parent = rect(Vector2::new(10.0, 20.0), Vector2::new(200.0, 100.0));
+
+        let mut widget = Widget::fixed(40.0, 20.0).anchored([1.0, 0.0]);
+        widget.position = Vector2::new(-5.0, 5.0);
+        let v = super::anchored(parent, &widget);
+        assert_eq!(v, rect(Vector2::new(165.0, 25.0), Vector2::new(40.0, 20.0)));
+
+        // Stretches with parent, and insets by 10 points.
+        let mut widget = Widget::stretch();
+        widget.position = Vector2::new(10.0, 10.0);
+        widget.size = [UiLength::Points(-20.0), UiLength::Percent(-50.0)];
+        let v = super::anchored(parent, &widget);
+        assert_eq!(v, rect(Vector2::new(20.0, 30.0), Vector2::new(180.0, 50.0)));
+    }
+
+    #[test]
+    fn flex() {
+        let parent = rect(Vector2::new(0.0, 0.0), Vector2::new(100.0, 40.0));
+        let flex = UiFlex {
+            direction: UiDirection::Row,
+            padding: 5.0,
+            spacing: 10.0,
+            align: UiAlign::Center,
+        };
+
+        let mut fill = Widget::fixed(10.0, 10.0);
+        fill.grow = 1.0;
+        let fixed = Widget::fixed(20.0, 30.0);
+
+        let rects = super::flex(parent, &flex, &[&fixed, &fill, &fixed]);
+        assert_eq!(
+            rects[0],
+            rect(Vector2::new(5.0, 5.0), Vector2::new(20.0, 30.0))
+        );
+        assert_eq!(
+            rects[1],
+            rect(Vector2::new(35.0, 15.0), Vector2::new(30.0, 10.0))
+        );
+        assert_eq!(
+            rects[2],
+            rect(Vector2::new(75.0, 5.0), Vector2::new(20.0, 30.0))
+        );
+    }
+}
@@ -0,0 +1,598 @@
+//! A retained-mode UI, which keeps a tree of widgets across frames for the menus and
+//! HUDs of games.
+//!
+//! Widgets are created as the children of other widgets, and placed in the rectangles
+//! of their parents with anchors and pivots, or one after another in flex containers.
+//! Lengths could be measured in points or the percentages of parents:
+//!
+//! ```rust,ignore
+//! let mut ui = Ui::new();
+//!
+//! let mut menu = Widget::fixed(200.0, 0.0).anchored([0.5, 0.5]);
+//! menu.size[1] = UiLength::Percent(50.0);
+//! menu.layout = UiLayout::Flex(UiFlex { spacing: 4.0, ..UiFlex::default() });
+//! let menu = ui.create(ui.root(), menu)?;
+//!
+//! let mut button = Widget::fixed(0.0, 24.0);
+//! button.interactive = true;
+//! button.text = "Start".into();
+//! let start = ui.create(menu, button)?;
+//!
+//! // Every frame.
+//! ui.update();
+//! for v in ui.events() {
+//!     if *v == UiEvent::Click(start) {
+//!         ...
+//!     }
+//! }
+//!
+//! ui.draw()?;
+//! ```
+//!
+//! Only the widgets that are modified with `Ui::widget_mut`, and the ones whose
+//! rectangles are changed by them, are laid out again in `Ui::update`.
+//!
+//! The positions and lengths are measured in points, so the UI keeps its physical size
+//! on displays with different device pixel ratios. It could be scaled further with
+//! `Ui::set_scale`, e.g. to fit the window with a reference resolution.
+//!
+//! The events of mouse are routed to the top-most interactive widget under cursor,
+//! and the pressed widget takes the focus.
+
+mod layout;
+mod widget;
+
+pub mod prelude {
+    pub use super::widget::{UiAlign, UiDirection, UiFlex, UiImage, UiLayout, UiLength, Widget};
+    pub use super::{Ui, UiEvent, WidgetHandle};
+}
+
+use crayon::errors::*;
+use crayon::math::prelude::{Aabb2, Vector2};
+use crayon::utils::prelude::ObjectPool;
+use crayon::video;
+
+use gui::prelude::GuiInput;
+use sprite::batch::SpriteBatch;
+use text::canvas::Canvas;
+use text::prelude::BitmapFont;
+
+use self::widget::{UiLayout, Widget};
+
+impl_handle!(WidgetHandle);
+
+/// The events of widgets, which are generated in `Ui::update`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiEvent {
+    /// The cursor enters the widget.
+    Enter(WidgetHandle),
+    /// The cursor leaves the widget.
+    Leave(WidgetHandle),
+    /// The mouse button is pressed on the widget.
+    Press(WidgetHandle),
+    /// The mouse button is released after it's pressed on the widget.
+    Release(WidgetHandle),
+    /// The mouse button is pressed and released on the same widget.
+    Click(WidgetHandle),
+    /// The widget takes the focus.
+    Focus(WidgetHandle),
+    /// The widget loses the focus.
+    Blur(WidgetHandle),
+}
+
+struct Node {
+    widget: Widget,
+    parent: Option<WidgetHandle>,
+    children: Vec<WidgetHandle>,
+    rect: Aabb2<f32>,
+    clip: Option<Aabb2<f32>>,
+    dirty: bool,
+}
+
+impl Node {
+    fn new(widget: Widget, parent: Option<WidgetHandle>) -> Self {
+        Node {
+            widget: widget,
+            parent: parent,
+            children: Vec::new(),
+            rect: Aabb2::zero(),
+            clip: None,
+            dirty: true,
+        }
+    }
+}
+
+/// The retained-mode UI, see the module documentation for details.
+pub struct Ui {
+    nodes: ObjectPool<WidgetHandle, Node>,
+    root: WidgetHandle,
+    scale: f32,
+    input: GuiInput,
+    order: Vec<WidgetHandle>,
+    events: Vec<UiEvent>,
+    hovered: Option<WidgetHandle>,
+    pressed: Option<WidgetHandle>,
+    focused: Option<WidgetHandle>,
+    layouts: usize,
+
+    font: Option<(BitmapFont, bool)>,
+    batch: Option<SpriteBatch>,
+    canvas: Canvas,
+}
+
+impl Drop for Ui {
+    fn drop(&mut self) {
+        if let Some((font, true)) = self.font.take() {
+            video::delete_texture(font.texture());
+        }
+    }
+}
+
+impl Ui {
+    /// Creates a new `Ui` with a root widget that fills the window.
+    pub fn new() -> Self {
+        let mut nodes = ObjectPool::new();
+        let root = nodes.create(Node::new(Widget::stretch(), None));
+
+        Ui {
+            nodes: nodes,
+            root: root,
+            scale: 1.0,
+            input: GuiInput::default(),
+            order: Vec::new(),
+            events: Vec::new(),
+            hovered: None,
+            pressed: None,
+            focused: None,
+            layouts: 0,
+            font: None,
+            batch: None,
+            canvas: Canvas::new(),
+        }
+    }
+
+    /// Gets the root widget, which fills the window.
+    #[inline]
+    pub fn root(&self) -> WidgetHandle {
+        self.root
+    }
+
+    /// Sets the scale of UI, the widgets are laid out in the window whose dimensions are
+    /// divided by `scale`, and then magnified when drawing.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(::std::f32::EPSILON);
+        let root = self.root;
+        self.mark_dirty(root);
+    }
+
+    /// Gets the scale of UI.
+    #[inline]
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets the font of texts, which replaces the built-in font. The texture of font is
+    /// still owned by the caller.
+    pub fn set_font(&mut self, font: BitmapFont) {
+        if let Some((font, true)) = self.font.take() {
+            video::delete_texture(font.texture());
+        }
+
+        self.font = Some((font, false));
+    }
+
+    /// Creates a widget as the last child of `parent`.
+    pub fn create(&mut self, parent: WidgetHandle, widget: Widget) -> Result<WidgetHandle> {
+        if !self.nodes.contains(parent) {
+            bail!("The parent widget {:?} does not exist.", parent);
+        }
+
+        let handle = self.nodes.create(Node::new(widget, Some(parent)));
+        self.nodes.get_mut(parent).unwrap().children.push(handle);
+        self.mark_dirty(parent);
+        Ok(handle)
+    }
+
+    /// Deletes a widget and all its children. The root widget could not be deleted.
+    pub fn delete(&mut self, handle: WidgetHandle) -> Result<()> {
+        if handle == self.root {
+            bail!("The root widget could not be deleted.");
+        }
+
+        let parent = match self.nodes.get(handle) {
+            Some(node) => node.parent.unwrap(),
+            None => bail!("The widget {:?} does not exist.", handle),
+        };
+
+        self.nodes
+            .get_mut(parent)
+            .unwrap()
+            .children
+            .retain(|&v| v != handle);
+
+        let mut stack = vec![handle];
+        while let Some(v) = stack.pop() {
+            if let Some(node) = self.nodes.free(v) {
+                stack.extend(node.children);
+            }
+        }
+
+        let nodes = &self.nodes;
+        for v in &mut [&mut self.hovered, &mut self.pressed, &mut self.focused] {
+            if v.map(|h| !nodes.contains(h)).unwrap_or(false) {
+                **v = None;
+            }
+        }
+
+        self.mark_dirty(parent);
+        Ok(())
+    }
+
+    /// Gets the description of widget.
+    #[inline]
+    pub fn widget(&self, handle: WidgetHandle) -> Option<&Widget> {
+        self.nodes.get(handle).map(|v| &v.widget)
+    }
+
+    /// Gets the mutable description of widget, which is laid out again in next update.
+    pub fn widget_mut(&mut self, handle: WidgetHandle) -> Option<&mut Widget> {
+        if !self.nodes.contains(handle) {
+            return None;
+        }
+
+        self.mark_dirty(handle);
+        self.nodes.get_mut(handle).map(|v| &mut v.widget)
+    }
+
+    /// Gets the parent of widget.
+    #[inline]
+    pub fn parent(&self, handle: WidgetHandle) -> Option<WidgetHandle> {
+        self.nodes.get(handle).and_then(|v| v.parent)
+    }
+
+    /// Gets the children of widget in drawing order.
+    #[inline]
+    pub fn children(&self, handle: WidgetHandle) -> &[WidgetHandle] {
+        self.nodes
+            .get(handle)
+            .map(|v| &v.children[..])
+            .unwrap_or(&[])
+    }
+
+    /// Gets the rectangle of widget in points, which is updated in `Ui::update`.
+    #[inline]
+    pub fn rect(&self, handle: WidgetHandle) -> Option<Aabb2<f32>> {
+        self.nodes.get(handle).map(|v| v.rect)
+    }
+
+    /// Gets the interactive widget under cursor.
+    #[inline]
+    pub fn hovered(&self) -> Option<WidgetHandle> {
+        self.hovered
+    }
+
+    /// Gets the focused widget.
+    #[inline]
+    pub fn focused(&self) -> Option<WidgetHandle> {
+        self.focused
+    }
+
+    /// Gets the events generated in last update.
+    #[inline]
+    pub fn events(&self) -> &[UiEvent] {
+        &self.events
+    }
+
+    /// Lays out the widgets and routes the events with the input of window.
+    pub fn update(&mut self) {
+        self.update_with(GuiInput::capture())
+    }
+
+    /// Lays out the widgets and routes the events with `input`.
+    pub fn update_with(&mut self, input: GuiInput) {
+        self.input = input;
+        self.events.clear();
+        self.layouts = 0;
+
+        let dimensions = self.input.dimensions / self.scale;
+        let root = self.root;
+        self.layout(root, layout::rect(Vector2::new(0.0, 0.0), dimensions), None);
+
+        self.order.clear();
+        let mut stack = vec![root];
+        while let Some(v) = stack.pop() {
+            let node = self.nodes.get(v).unwrap();
+            if node.widget.visible {
+                self.order.push(v);
+                stack.extend(node.children.iter().rev());
+            }
+        }
+
+        self.route();
+    }
+
+    /// Draws the visible widgets in the order of depth-first traversal.
+    pub fn draw(&mut self) -> Result<()> {
+        if self.font.is_none() {
+            self.font = Some((::text::builtin::font()?, true));
+        }
+
+        if self.batch.is_none() {
+            self.batch = Some(SpriteBatch::new()?);
+        }
+
+        let scale = self.scale;
+        let font = &self.font.as_ref().unwrap().0;
+        let canvas = &mut self.canvas;
+        canvas.begin_with(self.input.dimensions, self.input.device_pixel_ratio);
+
+        let mut clip = None;
+        for &v in &self.order {
+            let node = self.nodes.get(v).unwrap();
+            if node.clip != clip {
+                clip = node.clip;
+                canvas.clip(clip.map(|v| ((v.min * scale).into(), (v.max * scale).into())));
+            }
+
+            let (min, max) = (node.rect.min * scale, node.rect.max * scale);
+            let widget = &node.widget;
+            if widget.color.a > 0.0 {
+                canvas.rect(min.into(), max.into(), widget.color);
+            }
+
+            if let Some(mut image) = widget.image {
+                if let Some(ref mut slice) = image.slice {
+                    for border in &mut slice.borders {
+                        *border *= scale;
+                    }
+                }
+
+                canvas.image(&image, min.into(), max.into());
+            }
+
+            if !widget.text.is_empty() {
+                let height = widget.text_height * scale;
+                let size = font.measure(&widget.text) * height / font.line_height().max(1.0);
+                let x = min.x + ((max.x - min.x - size.x) * 0.5).floor();
+                let y = min.y + ((max.y - min.y - size.y) * 0.5).floor();
+                canvas.text(font, [x, y], height, &widget.text, widget.text_color);
+            }
+        }
+
+        canvas.draw(self.batch.as_mut().unwrap())
+    }
+
+    /// Marks the widget and all its ancestors to be laid out in next update.
+    fn mark_dirty(&mut self, handle: WidgetHandle) {
+        let mut iter = Some(handle);
+        while let Some(v) = iter {
+            let node = self.nodes.get_mut(v).unwrap();
+            node.dirty = true;
+            iter = node.parent;
+        }
+    }
+
+    fn layout(&mut self, handle: WidgetHandle, rect: Aabb2<f32>, clip: Option<Aabb2<f32>>) {
+        let (layout, children) = {
+            let node = self.nodes.get_mut(handle).unwrap();
+            if !node.dirty && node.rect == rect && node.clip == clip {
+                return;
+            }
+
+            node.rect = rect;
+            node.clip = clip;
+            node.dirty = false;
+            (node.widget.layout, node.children.clone())
+        };
+
+        self.layouts += 1;
+
+        let node = self.nodes.get(handle).unwrap();
+        let clip = if node.widget.clip {
+            Some(clip.map(|v| layout::intersect(v, rect)).unwrap_or(rect))
+        } else {
+            clip
+        };
+
+        let children: Vec<_> = children
+            .into_iter()
+            .filter(|&v| self.nodes.get(v).unwrap().widget.visible)
+            .collect();
+
+        let rects = {
+            let widgets: Vec<_> = children
+                .iter()
+                .map(|&v| &self.nodes.get(v).unwrap().widget)
+                .collect();
+
+            match layout {
+                UiLayout::Anchored => widgets.iter().map(|v| layout::anchored(rect, v)).collect(),
+                UiLayout::Flex(ref flex) => layout::flex(rect, flex, &widgets),
+            }
+        };
+
+        for (v, rect) in children.into_iter().zip(rects) {
+            self.layout(v, rect, clip);
+        }
+    }
+
+    fn route(&mut self) {
+        let mouse = self.input.mouse_position / self.scale;
+        let hit = self
+            .order
+            .iter()
+            .rev()
+            .find(|&&v| {
+                let node = self.nodes.get(v).unwrap();
+                node.widget.interactive
+                    && layout::contains(node.rect, mouse)
+                    && node
+                        .clip
+                        .map(|c| layout::contains(c, mouse))
+                        .unwrap_or(true)
+            })
+            .cloned();
+
+        if hit != self.hovered {
+            if let Some(v) = self.hovered {
+                self.events.push(UiEvent::Leave(v));
+            }
+
+            if let Some(v) = hit {
+                self.events.push(UiEvent::Enter(v));
+            }
+
+            self.hovered = hit;
+        }
+
+        if self.input.mouse_press {
+            if let Some(v) = hit {
+                self.events.push(UiEvent::Press(v));
+            }
+
+            if hit != self.focused {
+                if let Some(v) = self.focused {
+                    self.events.push(UiEvent::Blur(v));
+                }
+
+                if let Some(v) = hit {
+                    self.events.push(UiEvent::Focus(v));
+                }
+
+                self.focused = hit;
+            }
+
+            self.pressed = hit;
+        }
+
+        if self.input.mouse_release {
+            if let Some(v) = self.pressed.take() {
+                self.events.push(UiEvent::Release(v));
+                if hit == Some(v) {
+                    self.events.push(UiEvent::Click(v));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::widget::{UiFlex, UiLength};
+    use super::*;
+
+    fn input(x: f32, y: f32, press: bool, release: bool) -> GuiInput {
+        GuiInput {
+            dimensions: Vector2::new(320.0, 240.0),
+            mouse_position: Vector2::new(x, y),
+            mouse_down: press,
+            mouse_press: press,
+            mouse_release: release,
+            ..GuiInput::default()
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let mut ui = Ui::new();
+        let root = ui.root();
+
+        let mut panel = Widget::fixed(100.0, 0.0).anchored([1.0, 0.0]);
+        panel.size[1] = UiLength::Percent(50.0);
+        panel.layout = UiLayout::Flex(UiFlex {
+            padding: 5.0,
+            spacing: 10.0,
+            ..UiFlex::default()
+        });
+
+        let panel = ui.create(root, panel).unwrap();
+        let a = ui.create(panel, Widget::fixed(0.0, 20.0)).unwrap();
+        let b = ui.create(panel, Widget::fixed(0.0, 20.0)).unwrap();
+        let c = ui.create(b, Widget::stretch()).unwrap();
+
+        ui.update_with(input(0.0, 0.0, false, false));
+        assert_eq!(ui.layouts, 5);
+        assert_eq!(
+            ui.rect(panel),
+            Some(layout::rect([220.0, 0.0].into(), [100.0, 120.0].into()))
+        );
+        assert_eq!(
+            ui.rect(a),
+            Some(layout::rect([225.0, 5.0].into(), [90.0, 20.0].into()))
+        );
+        assert_eq!(
+            ui.rect(c),
+            Some(layout::rect([225.0, 35.0].into(), [90.0, 20.0].into()))
+        );
+
+        // Nothing is laid out again if nothing changes.
+        ui.update_with(input(0.0, 0.0, false, false));
+        assert_eq!(ui.layouts, 0);
+
+        // Only the ancestors of modified widget and the widgets that are moved.
+        ui.widget_mut(a).unwrap().size[1] = UiLength::Points(30.0);
+        ui.update_with(input(0.0, 0.0, false, false));
+        assert_eq!(ui.layouts, 5);
+        assert_eq!(
+            ui.rect(c),
+            Some(layout::rect([225.0, 45.0].into(), [90.0, 20.0].into()))
+        );
+
+        ui.widget_mut(c).unwrap().text = "Start".into();
+        ui.update_with(input(0.0, 0.0, false, false));
+        assert_eq!(ui.layouts, 4);
+
+        // The UI is laid out in scaled dimensions.
+        ui.set_scale(2.0);
+        ui.update_with(input(0.0, 0.0, false, false));
+        assert_eq!(
+            ui.rect(panel),
+            Some(layout::rect([60.0, 0.0].into(), [100.0, 60.0].into()))
+        );
+
+        ui.delete(b).unwrap();
+        assert!(ui.widget(c).is_none());
+        assert_eq!(ui.children(panel), &[a]);
+        assert!(ui.delete(root).is_err());
+    }
+
+    #[test]
+    fn events() {
+        let mut ui = Ui::new();
+        let root = ui.root();
+
+        let mut widget = Widget::fixed(100.0, 100.0);
+        widget.clip = true;
+        let panel = ui.create(root, widget).unwrap();
+
+        let mut widget = Widget::fixed(200.0, 50.0);
+        widget.interactive = true;
+        let button = ui.create(panel, widget).unwrap();
+
+        // The button is clipped by panel.
+        ui.update_with(input(150.0, 10.0, false, false));
+        assert!(ui.events().is_empty());
+
+        ui.update_with(input(50.0, 10.0, false, false));
+        assert_eq!(ui.events(), &[UiEvent::Enter(button)]);
+
+        ui.update_with(input(50.0, 10.0, true, false));
+        assert_eq!(
+            ui.events(),
+            &[UiEvent::Press(button), UiEvent::Focus(button)]
+        );
+
+        ui.update_with(input(50.0, 20.0, false, true));
+        assert_eq!(
+            ui.events(),
+            &[UiEvent::Release(button), UiEvent::Click(button)]
+        );
+
+        ui.update_with(input(50.0, 80.0, true, false));
+        assert_eq!(
+            ui.events(),
+            &[UiEvent::Leave(button), UiEvent::Blur(button)]
+        );
+        assert_eq!(ui.focused(), None);
+    }
+}
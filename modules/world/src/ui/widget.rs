@@ -0,0 +1,217 @@
+use crayon::math::prelude::{Aabb2, Color, Vector2};
+use crayon::video::assets::texture::TextureHandle;
+
+use sprite::prelude::{NineSlice, TextureAtlas};
+
+/// The length of widget along one axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UiLength {
+    /// The length in points.
+    Points(f32),
+    /// The percentage of the length of parent, e.g. `Percent(50.0)` takes the half.
+    Percent(f32),
+}
+
+impl Default for UiLength {
+    fn default() -> Self {
+        UiLength::Points(0.0)
+    }
+}
+
+impl UiLength {
+    /// Resolves the length in points with the length of parent.
+    #[inline]
+    pub fn resolve(self, parent: f32) -> f32 {
+        match self {
+            UiLength::Points(v) => v,
+            UiLength::Percent(v) => parent * v * 0.01,
+        }
+    }
+}
+
+/// The main axis of flex containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiDirection {
+    /// Places children from left to right.
+    Row,
+    /// Places children from top to bottom.
+    Column,
+}
+
+/// The alignment of children along the cross axis of flex containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiAlign {
+    Start,
+    Center,
+    End,
+    /// Stretches children to fill the container.
+    Stretch,
+}
+
+/// The parameters of flex containers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiFlex {
+    pub direction: UiDirection,
+    /// The space between the borders of container and children.
+    pub padding: f32,
+    /// The space between children along the main axis.
+    pub spacing: f32,
+    pub align: UiAlign,
+}
+
+impl Default for UiFlex {
+    fn default() -> Self {
+        UiFlex {
+            direction: UiDirection::Column,
+            padding: 0.0,
+            spacing: 0.0,
+            align: UiAlign::Stretch,
+        }
+    }
+}
+
+/// The way how a widget places its children.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UiLayout {
+    /// Children are placed with their own anchors, pivots and positions.
+    Anchored,
+    /// Children are placed one after another along the main axis, their anchors,
+    /// pivots and positions are ignored.
+    Flex(UiFlex),
+}
+
+impl Default for UiLayout {
+    fn default() -> Self {
+        UiLayout::Anchored
+    }
+}
+
+/// The image of widget, which is drawn on top of the background color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiImage {
+    pub texture: TextureHandle,
+    /// The texture coordinates, which take the top-left corner of image as origin.
+    pub texcoord: Aabb2<f32>,
+    /// Is the region in texture rotated 90 degrees clockwise.
+    pub rotated: bool,
+    /// Draws the image as a nine-slice image, whose borders are measured in points. The
+    /// rotated regions are not supported.
+    pub slice: Option<NineSlice>,
+    /// The color that multiplied with texture.
+    pub color: Color<f32>,
+}
+
+impl From<TextureHandle> for UiImage {
+    fn from(texture: TextureHandle) -> Self {
+        UiImage {
+            texture: texture,
+            texcoord: Aabb2::new([0.0, 0.0].into(), [1.0, 1.0].into()),
+            rotated: false,
+            slice: None,
+            color: Color::white(),
+        }
+    }
+}
+
+impl UiImage {
+    /// Creates an image that displays the frame of texture atlas at `index`, the frame
+    /// is stretched to fill the widget if it's trimmed.
+    pub fn from_atlas(atlas: &TextureAtlas, index: usize) -> Option<Self> {
+        let region = atlas.region(index)?;
+        let mut image = UiImage::from(atlas.texture());
+        image.texcoord = atlas.texcoord(index)?;
+        image.rotated = region.rotated;
+        Some(image)
+    }
+}
+
+/// The description of widget in the retained UI tree. All the positions and lengths are
+/// measured in points, and take the top-left corner as origin.
+///
+/// In anchored layout, the anchors are the fractions of the rectangle of parent. Along
+/// each axis, the length of widget is the distance between its anchors plus `size`, so
+/// the widget keeps its size if the anchors are the same, and stretches with parent
+/// if they are not. The `pivot` of widget, which is a fraction of its own rectangle,
+/// is placed at `position` relative to the same fraction between anchors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Widget {
+    /// Hides the widget and all its children.
+    pub visible: bool,
+    /// Makes the widget receive the events of mouse and focus.
+    pub interactive: bool,
+    /// Clips the children into the rectangle of widget.
+    pub clip: bool,
+
+    pub anchor_min: Vector2<f32>,
+    pub anchor_max: Vector2<f32>,
+    pub pivot: Vector2<f32>,
+    pub position: Vector2<f32>,
+    pub size: [UiLength; 2],
+    /// The ratio of the remaining space that is taken by this widget along the main
+    /// axis of flex container.
+    pub grow: f32,
+    /// The layout of children.
+    pub layout: UiLayout,
+
+    /// The background color, which is not drawn if it's transparent.
+    pub color: Color<f32>,
+    /// The image that is stretched to fill the widget.
+    pub image: Option<UiImage>,
+    /// The text that is placed at the center of widget.
+    pub text: String,
+    pub text_color: Color<f32>,
+    pub text_height: f32,
+}
+
+impl Default for Widget {
+    fn default() -> Self {
+        Widget {
+            visible: true,
+            interactive: false,
+            clip: false,
+            anchor_min: Vector2::new(0.0, 0.0),
+            anchor_max: Vector2::new(0.0, 0.0),
+            pivot: Vector2::new(0.0, 0.0),
+            position: Vector2::new(0.0, 0.0),
+            size: [UiLength::default(); 2],
+            grow: 0.0,
+            layout: UiLayout::default(),
+            color: Color::transparent(),
+            image: None,
+            text: String::new(),
+            text_color: Color::white(),
+            text_height: 12.0,
+        }
+    }
+}
+
+impl Widget {
+    /// Creates a widget with fixed size in points at the top-left corner of parent.
+    pub fn fixed(width: f32, height: f32) -> Self {
+        Widget {
+            size: [UiLength::Points(width), UiLength::Points(height)],
+            ..Widget::default()
+        }
+    }
+
+    /// Creates a widget that fills its parent.
+    pub fn stretch() -> Self {
+        Widget {
+            anchor_max: Vector2::new(1.0, 1.0),
+            ..Widget::default()
+        }
+    }
+
+    /// Sets both the anchors to `anchor`, and places the pivot at the same fraction,
+    /// e.g. `[1.0, 0.0]` keeps the widget at the top-right corner of parent.
+    pub fn anchored<T>(mut self, anchor: T) -> Self
+    where
+        T: Into<Vector2<f32>>,
+    {
+        let anchor = anchor.into();
+        self.anchor_min = anchor;
+        self.anchor_max = anchor;
+        self.pivot = anchor;
+        self
+    }
+}
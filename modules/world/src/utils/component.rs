@@ -1,10 +1,60 @@
+use std::marker::PhantomData;
+
 use crayon::utils::hash::FastHashMap;
 use Entity;
 
+/// The ticks when a component is added and changed last time.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeTicks {
+    pub added: u32,
+    pub changed: u32,
+}
+
+/// The filter of components by their `ChangeTicks`.
+pub trait Filter {
+    /// The type of components to be filtered.
+    type Component: 'static;
+
+    /// Checks if the component passes the filter, which is true if it has been added
+    /// or changed after the tick `since`.
+    fn matches(ticks: ChangeTicks, since: u32) -> bool;
+}
+
+/// Matches the components that have been added after a tick.
+pub struct Added<T>(PhantomData<T>);
+
+impl<T: 'static> Filter for Added<T> {
+    type Component = T;
+
+    #[inline]
+    fn matches(ticks: ChangeTicks, since: u32) -> bool {
+        ticks.added > since
+    }
+}
+
+/// Matches the components that have been added or changed after a tick.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: 'static> Filter for Changed<T> {
+    type Component = T;
+
+    #[inline]
+    fn matches(ticks: ChangeTicks, since: u32) -> bool {
+        ticks.changed > since
+    }
+}
+
+/// The storage of components with type `T`.
+///
+/// The changes are stamped with the tick set by `set_tick` when the components are added
+/// or borrowed with `get_mut`. The modifications through `data` directly are not tracked,
+/// `mark_changed` should be called in that case.
 pub struct Component<T> {
     pub remap: FastHashMap<Entity, usize>,
     pub entities: Vec<Entity>,
     pub data: Vec<T>,
+    pub ticks: Vec<ChangeTicks>,
+    tick: u32,
 }
 
 impl<T> Component<T> {
@@ -13,11 +63,19 @@ impl<T> Component<T> {
             remap: FastHashMap::default(),
             entities: Vec::new(),
             data: Vec::new(),
+            ticks: Vec::new(),
+            tick: 0,
         }
     }
 
     pub fn add(&mut self, ent: Entity, mut v: T) -> Option<T> {
+        let ticks = ChangeTicks {
+            added: self.tick,
+            changed: self.tick,
+        };
+
         if let Some(&index) = self.remap.get(&ent) {
+            self.ticks[index] = ticks;
             unsafe {
                 ::std::ptr::swap(&mut self.data[index], &mut v);
                 Some(v)
@@ -26,6 +84,7 @@ impl<T> Component<T> {
             self.remap.insert(ent, self.data.len());
             self.entities.push(ent);
             self.data.push(v);
+            self.ticks.push(ticks);
             None
         }
     }
@@ -39,6 +98,7 @@ impl<T> Component<T> {
         if let Some(index) = self.remap.remove(&ent) {
             self.entities.swap_remove(index);
             self.data.swap_remove(index);
+            self.ticks.swap_remove(index);
 
             if self.remap.len() != index {
                 *self.remap.get_mut(&self.entities[index]).unwrap() = index;
@@ -52,9 +112,50 @@ impl<T> Component<T> {
         self.remap.get(&ent).map(|&index| &data[index])
     }
 
+    /// Gets the mutable component of entity, which is marked as changed.
     #[inline]
     pub fn get_mut(&mut self, ent: Entity) -> Option<&mut T> {
-        let data = &mut self.data;
-        self.remap.get(&ent).map(move |&index| &mut data[index])
+        let (data, ticks, tick) = (&mut self.data, &mut self.ticks, self.tick);
+        self.remap.get(&ent).map(move |&index| {
+            ticks[index].changed = tick;
+            &mut data[index]
+        })
+    }
+
+    /// Sets the tick that the following changes are stamped with.
+    #[inline]
+    pub fn set_tick(&mut self, tick: u32) {
+        self.tick = tick;
+    }
+
+    /// Gets the tick that the changes are stamped with.
+    #[inline]
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Marks the component of entity as changed.
+    #[inline]
+    pub fn mark_changed(&mut self, ent: Entity) {
+        if let Some(&index) = self.remap.get(&ent) {
+            self.ticks[index].changed = self.tick;
+        }
+    }
+
+    #[inline]
+    pub fn change_ticks(&self, ent: Entity) -> Option<ChangeTicks> {
+        self.remap.get(&ent).map(|&index| self.ticks[index])
+    }
+
+    /// Gets the entities whose components pass the filter `F` since the tick.
+    pub fn filter<F>(&self, since: u32) -> impl Iterator<Item = Entity> + '_
+    where
+        F: Filter<Component = T>,
+    {
+        self.entities
+            .iter()
+            .zip(&self.ticks)
+            .filter(move |(_, &ticks)| F::matches(ticks, since))
+            .map(|(&ent, _)| ent)
     }
 }
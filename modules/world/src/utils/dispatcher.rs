@@ -0,0 +1,439 @@
+//! The dispatcher that runs systems over the storages of components, the systems which
+//! do not conflict with each other are run in parallel.
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+
+use crayon::utils::hash::FastHashMap;
+
+use super::component::Component;
+use super::registry::{AnyComponent, ComponentRegistry};
+use super::storage::{ComponentStorage, StorageKind};
+
+fn create<T: Send + Sync + 'static>() -> Box<dyn AnyComponent> {
+    Box::new(StorageKind::default().create::<T>())
+}
+
+/// The types of components that a system reads and writes.
+#[derive(Default, Clone)]
+pub struct SystemAccess {
+    pub(crate) reads: Vec<(TypeId, fn() -> Box<dyn AnyComponent>)>,
+    pub(crate) writes: Vec<(TypeId, fn() -> Box<dyn AnyComponent>)>,
+}
+
+impl SystemAccess {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Declares that components with type `T` are read by system.
+    pub fn read<T: Send + Sync + 'static>(mut self) -> Self {
+        self.reads.push((TypeId::of::<T>(), create::<T>));
+        self
+    }
+
+    /// Declares that components with type `T` are written by system.
+    pub fn write<T: Send + Sync + 'static>(mut self) -> Self {
+        self.writes.push((TypeId::of::<T>(), create::<T>));
+        self
+    }
+
+    /// Checks if the systems could not be run at the same time, which is the case if any
+    /// of them writes the components that the other one visits.
+    pub fn conflicts(&self, rhs: &SystemAccess) -> bool {
+        let visits = |access: &SystemAccess, tid: TypeId| {
+            access
+                .reads
+                .iter()
+                .chain(&access.writes)
+                .any(|v| v.0 == tid)
+        };
+
+        self.writes.iter().any(|v| visits(rhs, v.0)) || rhs.writes.iter().any(|v| visits(self, v.0))
+    }
+
+    fn can_read(&self, tid: TypeId) -> bool {
+        self.reads.iter().chain(&self.writes).any(|v| v.0 == tid)
+    }
+
+    fn can_write(&self, tid: TypeId) -> bool {
+        self.writes.iter().any(|v| v.0 == tid)
+    }
+}
+
+/// The logic that runs over the components, e.g. moving the entities with velocities.
+///
+/// ```rust,ignore
+/// struct Movement;
+///
+/// impl System for Movement {
+///     fn access(&self) -> SystemAccess {
+///         SystemAccess::new().read::<Velocity>().write::<Position>()
+///     }
+///
+///     fn run(&mut self, components: &ComponentView) {
+///         let velocities = components.read::<Velocity>();
+///         for (ent, v) in components.write::<Position>().iter_mut() {
+///             v.0 += velocities.get(ent).map(|v| v.0).unwrap_or(0.0);
+///         }
+///     }
+/// }
+/// ```
+pub trait System: Send {
+    /// The components that this system visits. It should stay the same once the system
+    /// has been added to `Dispatcher`.
+    fn access(&self) -> SystemAccess;
+
+    fn run(&mut self, components: &ComponentView);
+
+    /// The name of this system, which is used to identify it in `Dispatcher`. Defaults
+    /// to the name of type.
+    fn name(&self) -> &str {
+        ::std::any::type_name::<Self>()
+    }
+}
+
+/// The pointers of storages, which are either the boxed `ComponentStorage`s of
+/// `ComponentRegistry` or the `Component`s of built-in types.
+#[derive(Default)]
+pub(crate) struct StoragePtrs(pub(crate) FastHashMap<TypeId, *mut dyn Any>);
+
+impl StoragePtrs {
+    /// Exposes the storage of built-in components to systems.
+    pub(crate) fn insert<T: Send + 'static>(&mut self, storage: &mut Component<T>) {
+        let storage = storage as &mut dyn Any;
+        self.0.insert(TypeId::of::<T>(), storage as *mut dyn Any);
+    }
+}
+
+// The storages are only visited by the systems which have declared their types, and
+// those types are `Send` and `Sync`.
+unsafe impl Send for StoragePtrs {}
+unsafe impl Sync for StoragePtrs {}
+
+/// The storages of components which could be visited by a running system, according to
+/// its `SystemAccess`.
+pub struct ComponentView<'a> {
+    storages: &'a StoragePtrs,
+    access: &'a SystemAccess,
+    fetched: RefCell<Vec<(TypeId, bool)>>,
+}
+
+impl<'a> ComponentView<'a> {
+    /// Gets the storage of components with type `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` has not been declared by the system, or it has been fetched with
+    /// `write` already.
+    pub fn read<T: Send + 'static>(&self) -> &'a dyn ComponentStorage<T> {
+        let tid = TypeId::of::<T>();
+        assert!(
+            self.access.can_read(tid),
+            "Component has not been declared in the access of system."
+        );
+
+        self.fetch(tid, false);
+        unsafe {
+            let storage = &*self.storages.0[&tid];
+            match storage.downcast_ref::<Box<dyn ComponentStorage<T>>>() {
+                Some(v) => v.as_ref(),
+                None => storage.downcast_ref::<Component<T>>().unwrap(),
+            }
+        }
+    }
+
+    /// Gets the mutable storage of components with type `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `T` has not been declared as written by the system, or it has been
+    /// fetched already.
+    pub fn write<T: Send + 'static>(&self) -> &'a mut dyn ComponentStorage<T> {
+        let tid = TypeId::of::<T>();
+        assert!(
+            self.access.can_write(tid),
+            "Component has not been declared as written in the access of system."
+        );
+
+        self.fetch(tid, true);
+        unsafe {
+            let storage = &mut *self.storages.0[&tid];
+            if storage.is::<Box<dyn ComponentStorage<T>>>() {
+                let storage: &mut Box<dyn ComponentStorage<T>> = storage.downcast_mut().unwrap();
+                storage.as_mut()
+            } else {
+                storage.downcast_mut::<Component<T>>().unwrap()
+            }
+        }
+    }
+
+    fn fetch(&self, tid: TypeId, mutable: bool) {
+        let mut fetched = self.fetched.borrow_mut();
+        assert!(
+            !fetched.iter().any(|&(k, m)| k == tid && (m || mutable)),
+            "Component has been fetched mutably already."
+        );
+
+        fetched.push((tid, mutable));
+    }
+}
+
+/// Runs systems over the components of `ComponentRegistry`, and the built-in components
+/// of scene if it is run with `Scene::dispatch`.
+///
+/// Systems are grouped into stages in the order they are added, a system is placed after
+/// all the systems added before it which conflict with it, so the results are the same as
+/// running them one by one. The systems in the same stage are run in parallel with the
+/// scheduler, if it has been set up with worker threads.
+pub struct Dispatcher {
+    systems: Vec<SystemEntry>,
+    stages: usize,
+    barrier: usize,
+    parallel: bool,
+}
+
+struct SystemEntry {
+    stage: usize,
+    access: SystemAccess,
+    system: Box<dyn System>,
+    enabled: bool,
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Dispatcher {
+            systems: Vec::new(),
+            stages: 0,
+            barrier: 0,
+            parallel: true,
+        }
+    }
+}
+
+impl Dispatcher {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a system, which runs after the conflicting systems that have been added.
+    pub fn add<T: System + 'static>(&mut self, system: T) {
+        let access = system.access();
+        let stage = self
+            .systems
+            .iter()
+            .filter(|v| v.access.conflicts(&access))
+            .map(|v| v.stage + 1)
+            .fold(self.barrier, ::std::cmp::max);
+
+        self.stages = self.stages.max(stage + 1);
+        self.systems.push(SystemEntry {
+            stage: stage,
+            access: access,
+            system: Box::new(system),
+            enabled: true,
+        });
+    }
+
+    /// Makes the systems added afterwards run after all the systems that have been added.
+    pub fn add_barrier(&mut self) {
+        self.barrier = self.stages;
+    }
+
+    /// Sets whether the systems in the same stage are run in parallel. Otherwise, all the
+    /// systems are run one by one in the order they are added, which could be useful for
+    /// debugging. Defaults to true.
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    /// Gets the number of stages, which the systems are run in.
+    #[inline]
+    pub fn stages(&self) -> usize {
+        self.stages
+    }
+
+    /// Gets the names of systems and whether they are enabled, in the order they are added.
+    pub fn systems<'a>(&'a self) -> impl Iterator<Item = (&'a str, bool)> + 'a {
+        self.systems.iter().map(|v| (v.system.name(), v.enabled))
+    }
+
+    /// Enables or disables the systems with `name`, the disabled systems are skipped
+    /// until they are enabled again. Returns false if there is no such system.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for v in &mut self.systems {
+            if v.system.name() == name {
+                v.enabled = enabled;
+                found = true;
+            }
+        }
+
+        found
+    }
+
+    /// Runs all the systems over the components.
+    pub fn run(&mut self, components: &mut ComponentRegistry) {
+        self.run_with(components, StoragePtrs::default());
+    }
+
+    /// Runs all the systems over the components, including the built-in ones in `builtins`.
+    pub(crate) fn run_with(&mut self, components: &mut ComponentRegistry, builtins: StoragePtrs) {
+        let mut access = SystemAccess::new();
+        for v in &self.systems {
+            access.reads.extend_from_slice(&v.access.reads);
+            access.writes.extend_from_slice(&v.access.writes);
+        }
+
+        let storages = components.storages(&access, builtins);
+
+        if !self.parallel || !crayon::sched::valid() {
+            for v in self.systems.iter_mut().filter(|v| v.enabled) {
+                run(&storages, &v.access, v.system.as_mut());
+            }
+
+            return;
+        }
+
+        for stage in 0..self.stages {
+            let mut systems: Vec<_> = self
+                .systems
+                .iter_mut()
+                .filter(|v| v.stage == stage && v.enabled)
+                .map(|v| (&v.access, v.system.as_mut()))
+                .collect();
+
+            if systems.len() == 1 {
+                let (access, system) = systems.pop().unwrap();
+                run(&storages, access, system);
+                continue;
+            }
+
+            let storages = &storages;
+            crayon::sched::scope(|s| {
+                for (access, system) in systems {
+                    s.spawn(move |_| run(storages, access, system));
+                }
+            });
+        }
+    }
+}
+
+fn run(storages: &StoragePtrs, access: &SystemAccess, system: &mut dyn System) {
+    let view = ComponentView {
+        storages: storages,
+        access: access,
+        fetched: RefCell::new(Vec::new()),
+    };
+
+    system.run(&view);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crayon::utils::handle::HandleLike;
+    use Entity;
+
+    struct Position(f32);
+    struct Velocity(f32);
+
+    struct Movement;
+
+    impl System for Movement {
+        fn access(&self) -> SystemAccess {
+            SystemAccess::new().read::<Velocity>().write::<Position>()
+        }
+
+        fn run(&mut self, components: &ComponentView) {
+            let velocities = components.read::<Velocity>();
+            for (ent, v) in components.write::<Position>().iter_mut() {
+                if let Some(velocity) = velocities.get(ent) {
+                    v.0 += velocity.0;
+                }
+            }
+        }
+    }
+
+    struct Acceleration;
+
+    impl System for Acceleration {
+        fn access(&self) -> SystemAccess {
+            SystemAccess::new().write::<Velocity>()
+        }
+
+        fn run(&mut self, components: &ComponentView) {
+            for (_, v) in components.write::<Velocity>().iter_mut() {
+                v.0 *= 2.0;
+            }
+        }
+    }
+
+    struct Counter(usize);
+
+    impl System for Counter {
+        fn access(&self) -> SystemAccess {
+            SystemAccess::new().read::<Position>()
+        }
+
+        fn run(&mut self, components: &ComponentView) {
+            self.0 += components.read::<Position>().len();
+        }
+    }
+
+    #[test]
+    fn stages() {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add(Counter(0));
+        dispatcher.add(Acceleration);
+        assert_eq!(dispatcher.stages(), 1);
+
+        dispatcher.add(Movement);
+        assert_eq!(dispatcher.stages(), 2);
+
+        dispatcher.add(Counter(0));
+        assert_eq!(dispatcher.stages(), 3);
+
+        dispatcher.add_barrier();
+        dispatcher.add(Acceleration);
+        assert_eq!(dispatcher.stages(), 4);
+
+        let e1 = Entity::new(1, 1);
+        let mut registry = ComponentRegistry::new();
+        registry.add(e1, Position(1.0));
+        registry.add(e1, Velocity(1.0));
+
+        dispatcher.run(&mut registry);
+        assert_eq!(registry.get::<Position>(e1).unwrap().0, 3.0);
+        assert_eq!(registry.get::<Velocity>(e1).unwrap().0, 4.0);
+
+        let name = ::std::any::type_name::<Acceleration>();
+        assert!(dispatcher.set_enabled(name, false));
+        assert!(!dispatcher.set_enabled("Gravity", false));
+        assert_eq!(dispatcher.systems().filter(|v| !v.1).count(), 2);
+
+        dispatcher.run(&mut registry);
+        assert_eq!(registry.get::<Position>(e1).unwrap().0, 7.0);
+        assert_eq!(registry.get::<Velocity>(e1).unwrap().0, 4.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn undeclared() {
+        struct Invalid;
+
+        impl System for Invalid {
+            fn access(&self) -> SystemAccess {
+                SystemAccess::new().read::<Position>()
+            }
+
+            fn run(&mut self, components: &ComponentView) {
+                components.write::<Position>();
+            }
+        }
+
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.add(Invalid);
+        dispatcher.run(&mut ComponentRegistry::new());
+    }
+}
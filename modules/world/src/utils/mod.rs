@@ -1,5 +1,11 @@
 pub mod component;
+pub mod dispatcher;
+pub mod registry;
+pub mod storage;
 
 pub mod prelude {
-    pub use super::component::Component;
+    pub use super::component::{Added, ChangeTicks, Changed, Component, Filter};
+    pub use super::dispatcher::{ComponentView, Dispatcher, System, SystemAccess};
+    pub use super::registry::ComponentRegistry;
+    pub use super::storage::{ComponentStorage, HashMapComponent, StorageKind};
 }
@@ -0,0 +1,345 @@
+use std::any::{Any, TypeId};
+
+use crayon::errors::Result;
+use crayon::utils::hash::FastHashMap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+
+use super::component::Filter;
+use super::dispatcher::{StoragePtrs, SystemAccess};
+use super::storage::{ComponentStorage, StorageKind};
+use Entity;
+
+/// The storage of components with type erased.
+pub trait AnyComponent: Any + Send {
+    fn remove(&mut self, ent: Entity);
+    fn set_tick(&mut self, tick: u32);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyComponent for Box<dyn ComponentStorage<T>> {
+    #[inline]
+    fn remove(&mut self, ent: Entity) {
+        self.as_mut().remove(ent);
+    }
+
+    #[inline]
+    fn set_tick(&mut self, tick: u32) {
+        self.as_mut().set_tick(tick);
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+struct Serializer {
+    name: String,
+    tid: TypeId,
+    save: fn(&dyn AnyComponent, Entity) -> Result<Option<String>>,
+    load: fn(&mut dyn AnyComponent, Entity, &str) -> Result<()>,
+}
+
+/// The components of user-defined types, the registered ones are saved with scenes by
+/// their names.
+///
+/// The components are stored in vectors by default, which could be changed with
+/// `set_storage` before any components of that type are added.
+pub struct ComponentRegistry {
+    storages: FastHashMap<TypeId, Box<dyn AnyComponent>>,
+    serializers: Vec<Serializer>,
+    tick: u32,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        ComponentRegistry {
+            storages: FastHashMap::default(),
+            serializers: Vec::new(),
+            tick: 0,
+        }
+    }
+
+    /// Registers the component type with a unique name, which is used to identify it in
+    /// serialized scenes.
+    pub fn register<T>(&mut self, name: &str) -> Result<()>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+    {
+        let tid = TypeId::of::<T>();
+        if self
+            .serializers
+            .iter()
+            .any(|v| v.name == name || v.tid == tid)
+        {
+            bail!("Component {} has been registered already.", name);
+        }
+
+        self.storage_mut::<T>();
+        self.serializers.push(Serializer {
+            name: name.to_owned(),
+            tid: tid,
+            save: save::<T>,
+            load: load::<T>,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the kind of storage of components with type `T`.
+    pub fn set_storage<T: Send + 'static>(&mut self, kind: StorageKind) -> Result<()> {
+        if let Some(v) = self.storage::<T>() {
+            if v.kind() == kind {
+                return Ok(());
+            }
+
+            if v.len() > 0 {
+                bail!("The storage can not be changed after components have been added.");
+            }
+        }
+
+        let mut storage = kind.create::<T>();
+        storage.set_tick(self.tick);
+        self.storages.insert(TypeId::of::<T>(), Box::new(storage));
+        Ok(())
+    }
+
+    /// Gets the storage of components with type `T`.
+    #[inline]
+    pub fn storage<T: 'static>(&self) -> Option<&dyn ComponentStorage<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .and_then(|v| v.as_any().downcast_ref::<Box<dyn ComponentStorage<T>>>())
+            .map(|v| v.as_ref())
+    }
+
+    #[inline]
+    pub fn add<T: Send + 'static>(&mut self, ent: Entity, v: T) {
+        self.storage_mut::<T>().add(ent, v);
+    }
+
+    #[inline]
+    pub fn get<T: 'static>(&self, ent: Entity) -> Option<&T> {
+        self.storage::<T>().and_then(|v| v.get(ent))
+    }
+
+    #[inline]
+    pub fn get_mut<T: 'static>(&mut self, ent: Entity) -> Option<&mut T> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|v| {
+                v.as_any_mut()
+                    .downcast_mut::<Box<dyn ComponentStorage<T>>>()
+            })
+            .and_then(|v| v.get_mut(ent))
+    }
+
+    #[inline]
+    pub fn remove<T: 'static>(&mut self, ent: Entity) {
+        if let Some(v) = self.storages.get_mut(&TypeId::of::<T>()) {
+            v.remove(ent);
+        }
+    }
+
+    /// Gets the entities whose components pass the filter `F` since the tick.
+    pub fn filter<F: Filter>(&self, since: u32) -> Vec<Entity> {
+        self.storage::<F::Component>()
+            .map(|v| v.filter_with(F::matches, since))
+            .unwrap_or_default()
+    }
+
+    /// Sets the tick that the following changes of components are stamped with.
+    pub fn set_tick(&mut self, tick: u32) {
+        self.tick = tick;
+        for v in self.storages.values_mut() {
+            v.set_tick(tick);
+        }
+    }
+
+    /// Removes all the components of entity.
+    pub fn remove_all(&mut self, ent: Entity) {
+        for v in self.storages.values_mut() {
+            v.remove(ent);
+        }
+    }
+
+    /// Serializes the registered components of entity into pairs of name and JSON.
+    pub fn save(&self, ent: Entity) -> Result<Vec<(String, String)>> {
+        let mut components = Vec::new();
+        for v in &self.serializers {
+            if let Some(data) = (v.save)(self.storages[&v.tid].as_ref(), ent)? {
+                components.push((v.name.clone(), data));
+            }
+        }
+
+        Ok(components)
+    }
+
+    /// Deserializes the component with registered name, and adds it to entity.
+    pub fn load(&mut self, ent: Entity, name: &str, data: &str) -> Result<()> {
+        let v = self
+            .serializers
+            .iter()
+            .find(|v| v.name == name)
+            .ok_or_else(|| format_err!("Component {} has not been registered.", name))?;
+
+        (v.load)(self.storages.get_mut(&v.tid).unwrap().as_mut(), ent, data)
+    }
+
+    /// Creates the storages that the systems with `access` might visit, and returns the
+    /// pointers of all the storages along with the built-in ones.
+    pub(crate) fn storages(
+        &mut self,
+        access: &SystemAccess,
+        mut builtins: StoragePtrs,
+    ) -> StoragePtrs {
+        for &(tid, create) in access.reads.iter().chain(&access.writes) {
+            if builtins.0.contains_key(&tid) {
+                continue;
+            }
+
+            let tick = self.tick;
+            self.storages.entry(tid).or_insert_with(|| {
+                let mut storage = create();
+                storage.set_tick(tick);
+                storage
+            });
+        }
+
+        // The built-in storages take precedence over the user-defined ones of same types.
+        for (&k, v) in &mut self.storages {
+            builtins
+                .0
+                .entry(k)
+                .or_insert(v.as_any_mut() as *mut dyn Any);
+        }
+
+        builtins
+    }
+
+    fn storage_mut<T: Send + 'static>(&mut self) -> &mut dyn ComponentStorage<T> {
+        let tick = self.tick;
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                let mut storage = StorageKind::default().create::<T>();
+                storage.set_tick(tick);
+                Box::new(storage)
+            })
+            .as_any_mut()
+            .downcast_mut::<Box<dyn ComponentStorage<T>>>()
+            .unwrap()
+            .as_mut()
+    }
+}
+
+fn save<T: Serialize + 'static>(storage: &dyn AnyComponent, ent: Entity) -> Result<Option<String>> {
+    let storage = storage
+        .as_any()
+        .downcast_ref::<Box<dyn ComponentStorage<T>>>()
+        .unwrap();
+
+    match storage.get(ent) {
+        Some(v) => Ok(Some(serde_json::to_string(v)?)),
+        None => Ok(None),
+    }
+}
+
+fn load<T: DeserializeOwned + 'static>(
+    storage: &mut dyn AnyComponent,
+    ent: Entity,
+    data: &str,
+) -> Result<()> {
+    let v: T = serde_json::from_str(data)?;
+    let storage = storage
+        .as_any_mut()
+        .downcast_mut::<Box<dyn ComponentStorage<T>>>()
+        .unwrap();
+
+    storage.add(ent, v);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crayon::utils::handle::HandleLike;
+    use utils::component::{Added, Changed};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Health(u32);
+
+    #[test]
+    fn registry() {
+        let e1 = Entity::new(1, 1);
+
+        let mut registry = ComponentRegistry::new();
+        registry.add(e1, Health(100));
+        registry.add(e1, 1.0f32);
+        assert_eq!(registry.get::<Health>(e1), Some(&Health(100)));
+        assert_eq!(registry.get::<f32>(e1), Some(&1.0));
+
+        registry.get_mut::<Health>(e1).unwrap().0 = 50;
+        assert_eq!(registry.get::<Health>(e1), Some(&Health(50)));
+
+        registry.remove::<f32>(e1);
+        assert_eq!(registry.get::<f32>(e1), None);
+
+        registry.remove_all(e1);
+        assert_eq!(registry.get::<Health>(e1), None);
+    }
+
+    #[test]
+    fn serialization() {
+        let (e1, e2) = (Entity::new(1, 1), Entity::new(2, 1));
+
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Health>("health").unwrap();
+        assert!(registry.register::<Health>("hp").is_err());
+
+        registry.add(e1, Health(100));
+        registry.add(e1, 1.0f32);
+
+        let components = registry.save(e1).unwrap();
+        assert_eq!(components, vec![("health".to_owned(), "100".to_owned())]);
+
+        registry.load(e2, "health", "50").unwrap();
+        assert_eq!(registry.get::<Health>(e2), Some(&Health(50)));
+        assert!(registry.load(e2, "mana", "50").is_err());
+    }
+
+    #[test]
+    fn storages() {
+        let (e1, e2) = (Entity::new(1, 1), Entity::new(2, 1));
+
+        let mut registry = ComponentRegistry::new();
+        registry.set_storage::<Health>(StorageKind::HashMap).unwrap();
+        registry.register::<Health>("health").unwrap();
+        registry.set_tick(1);
+        registry.add(e1, Health(100));
+        assert!(registry.set_storage::<Health>(StorageKind::Vec).is_err());
+
+        let storage = registry.storage::<Health>().unwrap();
+        assert_eq!(storage.kind(), StorageKind::HashMap);
+        assert_eq!(storage.iter().collect::<Vec<_>>(), vec![(e1, &Health(100))]);
+
+        registry.load(e2, "health", "50").unwrap();
+        assert_eq!(registry.save(e2).unwrap()[0].1, "50");
+
+        registry.set_tick(2);
+        registry.get_mut::<Health>(e1).unwrap().0 = 75;
+        assert_eq!(registry.filter::<Changed<Health>>(1), vec![e1]);
+        assert_eq!(registry.filter::<Added<Health>>(1), vec![]);
+
+        registry.remove_all(e1);
+        assert_eq!(registry.storage::<Health>().unwrap().len(), 1);
+    }
+}
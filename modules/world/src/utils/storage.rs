@@ -0,0 +1,218 @@
+//! The storages of user-defined components.
+
+use crayon::utils::hash::FastHashMap;
+
+use super::component::{ChangeTicks, Component};
+use Entity;
+
+/// The kinds of storages of user-defined components.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StorageKind {
+    /// Stores the components in a continuous vector, which is the fastest to iterate.
+    /// It's suitable for the components that most of entities have.
+    Vec,
+    /// Stores the components in a hash map, which makes adding and removing cheaper. It's
+    /// suitable for the components that are rare or added and removed frequently.
+    HashMap,
+}
+
+impl Default for StorageKind {
+    fn default() -> Self {
+        StorageKind::Vec
+    }
+}
+
+impl StorageKind {
+    pub fn create<T: Send + 'static>(self) -> Box<dyn ComponentStorage<T>> {
+        match self {
+            StorageKind::Vec => Box::new(Component::<T>::new()),
+            StorageKind::HashMap => Box::new(HashMapComponent::<T>::new()),
+        }
+    }
+}
+
+/// The storage of components with type `T`.
+///
+/// The changes are stamped with the tick set by `set_tick`, when the components are
+/// added or borrowed mutably.
+pub trait ComponentStorage<T>: Send {
+    fn kind(&self) -> StorageKind;
+    fn len(&self) -> usize;
+    fn has(&self, ent: Entity) -> bool;
+    fn add(&mut self, ent: Entity, v: T) -> Option<T>;
+    fn remove(&mut self, ent: Entity);
+    fn get(&self, ent: Entity) -> Option<&T>;
+    fn get_mut(&mut self, ent: Entity) -> Option<&mut T>;
+
+    /// Iterates the entities and their components.
+    fn iter(&self) -> Box<dyn Iterator<Item = (Entity, &T)> + '_>;
+
+    /// Iterates the entities and their mutable components, all of them are marked as
+    /// changed.
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (Entity, &mut T)> + '_>;
+
+    fn set_tick(&mut self, tick: u32);
+    fn change_ticks(&self, ent: Entity) -> Option<ChangeTicks>;
+
+    /// Gets the entities whose change ticks match since the tick.
+    fn filter_with(&self, matches: fn(ChangeTicks, u32) -> bool, since: u32) -> Vec<Entity>;
+}
+
+impl<T: Send> ComponentStorage<T> for Component<T> {
+    #[inline]
+    fn kind(&self) -> StorageKind {
+        StorageKind::Vec
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    fn has(&self, ent: Entity) -> bool {
+        Component::has(self, ent)
+    }
+
+    #[inline]
+    fn add(&mut self, ent: Entity, v: T) -> Option<T> {
+        Component::add(self, ent, v)
+    }
+
+    #[inline]
+    fn remove(&mut self, ent: Entity) {
+        Component::remove(self, ent);
+    }
+
+    #[inline]
+    fn get(&self, ent: Entity) -> Option<&T> {
+        Component::get(self, ent)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, ent: Entity) -> Option<&mut T> {
+        Component::get_mut(self, ent)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Entity, &T)> + '_> {
+        Box::new(self.entities.iter().cloned().zip(&self.data))
+    }
+
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (Entity, &mut T)> + '_> {
+        let tick = self.tick();
+        for v in &mut self.ticks {
+            v.changed = tick;
+        }
+
+        Box::new(self.entities.iter().cloned().zip(&mut self.data))
+    }
+
+    #[inline]
+    fn set_tick(&mut self, tick: u32) {
+        Component::set_tick(self, tick);
+    }
+
+    #[inline]
+    fn change_ticks(&self, ent: Entity) -> Option<ChangeTicks> {
+        Component::change_ticks(self, ent)
+    }
+
+    fn filter_with(&self, matches: fn(ChangeTicks, u32) -> bool, since: u32) -> Vec<Entity> {
+        self.entities
+            .iter()
+            .zip(&self.ticks)
+            .filter(|(_, &ticks)| matches(ticks, since))
+            .map(|(&ent, _)| ent)
+            .collect()
+    }
+}
+
+/// The storage of components in a hash map.
+pub struct HashMapComponent<T> {
+    data: FastHashMap<Entity, (T, ChangeTicks)>,
+    tick: u32,
+}
+
+impl<T> HashMapComponent<T> {
+    pub fn new() -> Self {
+        HashMapComponent {
+            data: FastHashMap::default(),
+            tick: 0,
+        }
+    }
+}
+
+impl<T: Send> ComponentStorage<T> for HashMapComponent<T> {
+    #[inline]
+    fn kind(&self) -> StorageKind {
+        StorageKind::HashMap
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    fn has(&self, ent: Entity) -> bool {
+        self.data.contains_key(&ent)
+    }
+
+    fn add(&mut self, ent: Entity, v: T) -> Option<T> {
+        let ticks = ChangeTicks {
+            added: self.tick,
+            changed: self.tick,
+        };
+
+        self.data.insert(ent, (v, ticks)).map(|v| v.0)
+    }
+
+    #[inline]
+    fn remove(&mut self, ent: Entity) {
+        self.data.remove(&ent);
+    }
+
+    #[inline]
+    fn get(&self, ent: Entity) -> Option<&T> {
+        self.data.get(&ent).map(|v| &v.0)
+    }
+
+    #[inline]
+    fn get_mut(&mut self, ent: Entity) -> Option<&mut T> {
+        let tick = self.tick;
+        self.data.get_mut(&ent).map(|v| {
+            v.1.changed = tick;
+            &mut v.0
+        })
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (Entity, &T)> + '_> {
+        Box::new(self.data.iter().map(|(&k, v)| (k, &v.0)))
+    }
+
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (Entity, &mut T)> + '_> {
+        let tick = self.tick;
+        Box::new(self.data.iter_mut().map(move |(&k, v)| {
+            v.1.changed = tick;
+            (k, &mut v.0)
+        }))
+    }
+
+    #[inline]
+    fn set_tick(&mut self, tick: u32) {
+        self.tick = tick;
+    }
+
+    #[inline]
+    fn change_ticks(&self, ent: Entity) -> Option<ChangeTicks> {
+        self.data.get(&ent).map(|v| v.1)
+    }
+
+    fn filter_with(&self, matches: fn(ChangeTicks, u32) -> bool, since: u32) -> Vec<Entity> {
+        self.data
+            .iter()
+            .filter(|(_, v)| matches(v.1, since))
+            .map(|(&k, _)| k)
+            .collect()
+    }
+}
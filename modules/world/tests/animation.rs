@@ -0,0 +1,123 @@
+extern crate crayon;
+extern crate crayon_world;
+
+use crayon::math::prelude::*;
+use crayon_world::prelude::*;
+
+#[test]
+fn sample() {
+    let mut channel = AnimationChannel::default();
+    channel.bone = 1;
+    channel
+        .positions
+        .push(Keyframe::new(0.0, Vector3::new(0.0, 0.0, 0.0)));
+    channel
+        .positions
+        .push(Keyframe::new(1.0, Vector3::new(2.0, 0.0, 0.0)));
+    channel.scales.push(Keyframe::new(0.5, 2.0));
+
+    let clip = AnimationClip {
+        duration: 1.0,
+        channels: vec![channel],
+        events: Vec::new(),
+    };
+
+    assert!(clip.validate().is_ok());
+
+    let mut pose = vec![Transform::default(); 2];
+    clip.sample(0.5, &mut pose);
+    assert_eq!(pose[0].position, Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(pose[1].position, Vector3::new(1.0, 0.0, 0.0));
+    assert_eq!(pose[1].scale, 2.0);
+
+    clip.sample(2.0, &mut pose);
+    assert_eq!(pose[1].position, Vector3::new(2.0, 0.0, 0.0));
+
+    clip.sample(-1.0, &mut pose);
+    assert_eq!(pose[1].position, Vector3::new(0.0, 0.0, 0.0));
+}
+
+#[test]
+fn unsorted_keyframes() {
+    let mut channel = AnimationChannel::default();
+    channel.scales.push(Keyframe::new(1.0, 1.0));
+    channel.scales.push(Keyframe::new(0.0, 1.0));
+
+    let clip = AnimationClip {
+        duration: 1.0,
+        channels: vec![channel],
+        events: Vec::new(),
+    };
+
+    assert!(clip.validate().is_err());
+}
+
+#[test]
+fn skinning() {
+    let mut root = Bone::default();
+    root.name = "root".into();
+    root.local_transform.position = Vector3::new(0.0, 1.0, 0.0);
+    root.inverse_bind_matrix = Matrix4::from_translation(Vector3::new(0.0, -1.0, 0.0));
+
+    let mut child = Bone::default();
+    child.name = "child".into();
+    child.parent = Some(0);
+    child.local_transform.position = Vector3::new(0.0, 1.0, 0.0);
+    child.inverse_bind_matrix = Matrix4::from_translation(Vector3::new(0.0, -2.0, 0.0));
+
+    let skeleton = Skeleton {
+        bones: vec![root, child],
+    };
+
+    assert!(skeleton.validate().is_ok());
+    assert_eq!(skeleton.find("child"), Some(1));
+
+    // Skinning matrices of bind pose are identities.
+    let mut matrices = Vec::new();
+    let mut pose = skeleton.bind_pose();
+    skeleton.skinning(&pose, &mut matrices);
+    assert_eq!(matrices, vec![Matrix4::identity(); 2]);
+
+    // Moving the root bone moves its descendants too.
+    pose[0].position = Vector3::new(1.0, 1.0, 0.0);
+    skeleton.skinning(&pose, &mut matrices);
+    let v = matrices[1] * Vector4::new(0.0, 2.0, 0.0, 1.0);
+    assert_eq!(v, Vector4::new(1.0, 2.0, 0.0, 1.0));
+}
+
+#[test]
+fn invalid_hierarchy() {
+    let mut bone = Bone::default();
+    bone.parent = Some(0);
+
+    let skeleton = Skeleton { bones: vec![bone] };
+    assert!(skeleton.validate().is_err());
+}
+
+#[test]
+fn events() {
+    let mut clip = AnimationClip::default();
+    clip.duration = 1.0;
+    clip.events.push(AnimationEvent::new(0.0, "start"));
+    clip.events.push(AnimationEvent::new(0.5, "step"));
+    clip.events.push(AnimationEvent::new(1.0, "end"));
+    assert!(clip.validate().is_ok());
+
+    let crossed = |from, to| {
+        let mut names = Vec::new();
+        clip.crossed_events(from, to, |v| names.push(v.name.clone()));
+        names
+    };
+
+    assert_eq!(crossed(0.0, 0.5), vec!["start"]);
+    assert_eq!(crossed(0.5, 0.6), vec!["step"]);
+    assert!(crossed(0.6, 0.6).is_empty());
+    assert_eq!(crossed(0.6, 1.0), vec!["end"]);
+    assert!(crossed(1.0, 1.0).is_empty());
+
+    // Wraps around the end of looping clip.
+    assert_eq!(crossed(0.8, 0.1), vec!["start", "end"]);
+
+    clip.events.swap(0, 1);
+    assert!(clip.validate().is_err());
+}
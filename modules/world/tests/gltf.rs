@@ -0,0 +1,167 @@
+extern crate crayon;
+extern crate crayon_world;
+
+use crayon::math::prelude::*;
+use std::sync::Once;
+
+use crayon_world::assets::gltf_importer;
+use crayon_world::prelude::*;
+
+static SETUP: Once = Once::new();
+
+fn setup() {
+    SETUP.call_once(|| crayon::application::oneshot().unwrap());
+}
+
+const DOCUMENT: &str = r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [
+        { "name": "root", "children": [1], "mesh": 0, "skin": 0, "translation": [0, 0, 1] },
+        { "name": "joint", "translation": [0, 1, 0] }
+    ],
+    "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] }],
+    "materials": [{ "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 1], "roughnessFactor": 0.5 } }],
+    "skins": [{ "joints": [0, 1], "inverseBindMatrices": 2 }],
+    "animations": [{
+        "channels": [{ "sampler": 0, "target": { "node": 1, "path": "translation" } }],
+        "samplers": [{ "input": 3, "output": 4 }]
+    }],
+    "accessors": [
+        { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 1] },
+        { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" },
+        { "bufferView": 2, "componentType": 5126, "count": 2, "type": "MAT4" },
+        { "bufferView": 3, "componentType": 5126, "count": 2, "type": "SCALAR" },
+        { "bufferView": 4, "componentType": 5126, "count": 2, "type": "VEC3" }
+    ],
+    "bufferViews": [
+        { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+        { "buffer": 0, "byteOffset": 36, "byteLength": 6 },
+        { "buffer": 0, "byteOffset": 44, "byteLength": 128 },
+        { "buffer": 0, "byteOffset": 172, "byteLength": 8 },
+        { "buffer": 0, "byteOffset": 180, "byteLength": 24 }
+    ],
+    "buffers": [{
+        "byteLength": 204,
+        "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAIA/AAABAAIAAAAAAIA/AAAAAAAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAAAAAAIA/AACAPwAAAAAAAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAAAAAACAPwAAAAAAAABAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABA"
+    }]
+}"#;
+
+#[test]
+fn import() {
+    setup();
+
+    assert!(gltf_importer::is_gltf(DOCUMENT.as_bytes()));
+    let asset = gltf_importer::import(DOCUMENT.as_bytes()).unwrap();
+
+    let prefab = &asset.prefab;
+    assert_eq!(prefab.nodes.len(), 2);
+    assert_eq!(prefab.nodes[0].name, "root");
+    assert_eq!(prefab.nodes[0].first_child, Some(1));
+    assert_eq!(prefab.nodes[0].mesh_renderer, Some(0));
+    assert_eq!(prefab.nodes[1].name, "joint");
+    assert_eq!(prefab.nodes[1].next_sib, None);
+
+    // The z-axis is flipped into left handed coordinate system.
+    let position = prefab.nodes[0].local_transform.position;
+    assert_eq!(position, Vector3::new(0.0, 0.0, -1.0));
+
+    assert_eq!(prefab.meshes.len(), 1);
+    let params = crayon::video::mesh(prefab.meshes[0]).unwrap();
+    assert_eq!(params.num_verts, 3);
+    assert_eq!(params.num_idxes, 3);
+    assert_eq!(params.aabb.min.z, -1.0);
+    assert_eq!(params.aabb.max.z, 0.0);
+
+    assert_eq!(asset.materials.len(), 1);
+    assert_eq!(asset.materials[0].albedo, Color::red());
+    assert_eq!(asset.materials[0].metallic, 1.0);
+    assert_eq!(asset.materials[0].roughness, 0.5);
+    assert_eq!(asset.mesh_materials, vec![Some(0)]);
+
+    assert_eq!(asset.skeletons.len(), 1);
+    let skeleton = &asset.skeletons[0];
+    assert_eq!(skeleton.bones.len(), 2);
+    assert_eq!(skeleton.bones[1].parent, Some(0));
+    assert_eq!(skeleton.find("joint"), Some(1));
+
+    assert_eq!(asset.animations.len(), 1);
+    let clip = &asset.animations[0];
+    assert_eq!(clip.duration, 2.0);
+    assert_eq!(clip.channels.len(), 1);
+    assert_eq!(clip.channels[0].bone, 1);
+    assert_eq!(clip.channels[0].positions.len(), 2);
+    assert_eq!(
+        clip.channels[0].positions[1].value,
+        Vector3::new(0.0, 0.0, -2.0)
+    );
+}
+
+#[test]
+fn materials() {
+    setup();
+
+    let asset = gltf_importer::import(DOCUMENT.as_bytes()).unwrap();
+    let mut scene = Scene::new(PbrRenderer::new().unwrap());
+
+    let root = scene.create("root");
+    let child = scene.create("child");
+    scene.nodes.set_parent(child, root, false).unwrap();
+    scene.add_mesh(child, asset.prefab.meshes[0]);
+
+    // Only the entities with the meshes of asset are assigned.
+    let other = scene.create("other");
+    scene.add_mesh(other, asset.prefab.meshes[0]);
+    assert_eq!(asset.add_materials(&mut scene, root), 1);
+
+    let material = scene.renderer.material(child).unwrap();
+    assert_eq!(material.albedo, Color::red());
+    assert_eq!(material.roughness, 0.5);
+    assert!(!scene.renderer.has(root));
+    assert!(!scene.renderer.has(other));
+}
+
+#[test]
+fn external_buffers() {
+    let document = r#"{ "buffers": [{ "byteLength": 4, "uri": "mesh.bin" }] }"#;
+    assert!(gltf_importer::import(document.as_bytes()).is_err());
+    assert!(!gltf_importer::is_gltf(b"PREB"));
+}
+
+#[test]
+fn textures() {
+    setup();
+
+    // The images are not available, so the textures are skipped without failing.
+    let document = r#"{
+        "materials": [{
+            "pbrMetallicRoughness": { "baseColorTexture": { "index": 0 } },
+            "normalTexture": { "index": 1, "scale": 0.5 },
+            "occlusionTexture": { "index": 0, "texCoord": 1, "strength": 0.25 }
+        }],
+        "textures": [{ "source": 0 }, { "source": 1 }],
+        "images": [{ "uri": "albedo.png" }, { "uri": "data:image/png;base64,AAAA" }]
+    }"#;
+
+    let asset = gltf_importer::import_from(document.as_bytes(), "res:box.gltf").unwrap();
+    assert_eq!(asset.materials.len(), 1);
+    assert_eq!(asset.materials[0].albedo_texture, None);
+    assert_eq!(asset.materials[0].normal_texture, None);
+    assert_eq!(asset.materials[0].normal_scale, 0.5);
+    assert_eq!(asset.materials[0].occlusion_strength, 0.25);
+    assert_eq!(asset.materials[0].occlusion_texcoord, 1);
+}
+
+#[test]
+fn overflowed_accessors() {
+    let document = r#"{
+        "nodes": [{ "mesh": 0, "scale": [1, 2, 1] }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 } }] }],
+        "accessors": [{ "bufferView": 0, "componentType": 5126, "count": 4611686018427387904, "type": "VEC3" }],
+        "bufferViews": [{ "buffer": 0, "byteOffset": 0, "byteLength": 12 }],
+        "buffers": [{ "byteLength": 12, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAA" }]
+    }"#;
+
+    assert!(gltf_importer::import(document.as_bytes()).is_err());
+}
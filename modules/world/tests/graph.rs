@@ -89,6 +89,38 @@ fn remove() {
     assert!(scene.len() == 2);
 }
 
+#[test]
+fn events() {
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let e1 = scene.create("e1");
+
+    let reader = scene.nodes.create_reader();
+    let e2 = scene.create("e2");
+    scene.set_parent(e2, e1, false).unwrap();
+    scene.set_position(e2, [1.0, 0.0, 0.0]);
+    scene.set_local_scale(e2, 2.0);
+
+    assert_eq!(
+        scene.nodes.read_events(reader),
+        &[
+            SceneGraphEvent::Created(e2),
+            SceneGraphEvent::ParentChanged(e2),
+            SceneGraphEvent::TransformChanged(e2),
+        ]
+    );
+
+    scene.delete(e1);
+    assert_eq!(
+        scene.nodes.read_events(reader),
+        &[
+            SceneGraphEvent::Destroyed(e1),
+            SceneGraphEvent::Destroyed(e2),
+        ]
+    );
+
+    scene.nodes.delete_reader(reader);
+}
+
 #[test]
 fn transform() {
     let mut scene = Scene::new(HeadlessRenderer::new());
@@ -159,6 +191,67 @@ fn look_at() {
     scene.look_at(e1, [0.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
     let euler = Euler::new(Deg(0.0), Deg(225.0), Deg(0.0));
     assert_ulps_eq!(scene.rotation(e1).unwrap(), euler.into());
+
+    // Keeps the rotation if the target is at the same position.
+    scene.look_at(e1, [1.0, 0.0, 1.0], [0.0, 1.0, 0.0]);
+    assert_ulps_eq!(scene.rotation(e1).unwrap(), euler.into());
+
+    // Picks another up vector if it's parallel with the forward direction.
+    scene.set_position(e1, [0.0, 0.0, 0.0]);
+    scene.look_at(e1, [0.0, 5.0, 0.0], [0.0, 1.0, 0.0]);
+    let forward = scene.transform(e1).unwrap().forward();
+    assert_ulps_eq!(forward, [0.0, 1.0, 0.0].into(), epsilon = 1e-6);
+}
+
+#[test]
+fn world_transform() {
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let e1 = scene.create("e1");
+    let e2 = scene.create("e2");
+    let e3 = scene.create("e3");
+    scene.set_parent(e2, e1, false).unwrap();
+    scene.set_parent(e3, e2, false).unwrap();
+
+    scene.set_position(e1, [1.0, 0.0, 0.0]);
+    scene.set_scale(e1, 2.0);
+    scene.set_local_position(e2, [0.0, 1.0, 0.0]);
+    scene.update_transforms();
+    assert_ulps_eq!(scene.position(e3).unwrap(), [1.0, 2.0, 0.0].into());
+
+    // Changes are visible before propagation.
+    scene.set_position(e1, [0.0, 0.0, 0.0]);
+    assert_ulps_eq!(scene.position(e2).unwrap(), [0.0, 2.0, 0.0].into());
+    assert_ulps_eq!(scene.position(e3).unwrap(), [0.0, 2.0, 0.0].into());
+
+    scene.update_transforms();
+    assert_ulps_eq!(scene.position(e3).unwrap(), [0.0, 2.0, 0.0].into());
+    assert_ulps_eq!(scene.scale(e3).unwrap(), 2.0);
+
+    let rotation = Quaternion::from(Euler::new(Deg(0.0), Deg(90.0), Deg(0.0)));
+    let mut transform = Transform::default();
+    transform.position = [3.0, 3.0, 3.0].into();
+    transform.rotation = rotation;
+    transform.scale = 4.0;
+    scene.set_transform(e3, transform);
+
+    let local = scene.local_transform(e3).unwrap();
+    assert_ulps_eq!(local.position, [1.5, 0.5, 1.5].into());
+    assert_ulps_eq!(local.scale, 2.0);
+
+    scene.update_transforms();
+    let world = scene.transform(e3).unwrap();
+    assert_ulps_eq!(world.position, [3.0, 3.0, 3.0].into());
+    assert_ulps_eq!(world.rotation, rotation);
+    assert_ulps_eq!(world.scale, 4.0);
+
+    // Reparenting invalidates the cached transforms of subtree.
+    scene.remove_from_parent(e2, false).unwrap();
+    assert_ulps_eq!(scene.position(e3).unwrap(), [1.5, 1.5, 1.5].into());
+
+    scene.set_parent(e2, e1, true).unwrap();
+    scene.update_transforms();
+    assert_ulps_eq!(scene.position(e2).unwrap(), [0.0, 1.0, 0.0].into());
+    assert_ulps_eq!(scene.position(e3).unwrap(), [3.0, 2.0, 3.0].into());
 }
 
 #[test]
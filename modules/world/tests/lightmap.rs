@@ -0,0 +1,52 @@
+extern crate crayon;
+extern crate crayon_world;
+
+use crayon::bincode;
+use crayon::math::prelude::*;
+use crayon::utils::prelude::HandleLike;
+use crayon::video::assets::prelude::*;
+use crayon_world::prelude::*;
+use crayon_world::renderable::headless::HeadlessRenderer;
+
+#[test]
+fn atlas() {
+    let mut atlas = LightmapAtlas::default();
+    atlas.add("room/floor", TextureHandle::new(1, 1), [0.5, 0.5, 0.0, 0.0]);
+    atlas.add("room/wall", TextureHandle::new(1, 1), [0.5, 0.5, 0.5, 0.0]);
+    atlas.add("room/roof", TextureHandle::new(2, 1), [1.0, 1.0, 0.0, 0.0]);
+    assert!(atlas.validate().is_ok());
+    assert_eq!(atlas.textures.len(), 2);
+    assert_eq!(atlas.entries["room/wall"].texture, 0);
+    assert_eq!(atlas.entries["room/roof"].texture, 1);
+
+    let bytes = bincode::serialize(&atlas).unwrap();
+    let v: LightmapAtlas = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(v.entries, atlas.entries);
+    assert!(v.textures.is_empty());
+    assert!(v.validate().is_err());
+}
+
+#[test]
+fn apply() {
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let room = scene.create("room");
+    let floor = scene.create("floor");
+    let wall = scene.create("wall");
+    scene.set_parent(floor, room, false).unwrap();
+    scene.set_parent(wall, room, false).unwrap();
+    scene.add_mesh(floor, MeshHandle::new(1, 1));
+
+    let mut atlas = LightmapAtlas::default();
+    atlas.add("room/floor", TextureHandle::new(1, 1), [0.5, 0.5, 0.5, 0.0]);
+    atlas.add("room/wall", TextureHandle::new(1, 1), [0.5, 0.5, 0.0, 0.0]);
+    atlas.add("room/door", TextureHandle::new(1, 1), [0.5, 0.5, 0.0, 0.5]);
+
+    // Only the floor has mesh.
+    assert_eq!(scene.set_lightmaps(&atlas), 1);
+    assert_eq!(scene.mesh(wall).map(|v| v.lightmap), None);
+
+    let lightmap = scene.mesh(floor).unwrap().lightmap.unwrap();
+    assert_eq!(lightmap.texture, TextureHandle::new(1, 1));
+    assert_eq!(lightmap.scale_offset, Vector4::new(0.5, 0.5, 0.5, 0.0));
+    assert_eq!(lightmap.intensity, 1.0);
+}
@@ -0,0 +1,46 @@
+extern crate crayon;
+extern crate crayon_world;
+
+use crayon::bincode;
+use crayon::math::prelude::*;
+use crayon::utils::prelude::HandleLike;
+use crayon::video::assets::prelude::*;
+use crayon_world::prelude::*;
+
+#[test]
+fn params() {
+    let mut material = Material::new("unlit");
+    material.set("u_Color", Color::red());
+    material.set("u_Scale", 2.0);
+    material.set_texture("u_MainTex", TextureHandle::new(1, 1));
+    material.set_texture("u_DetailTex", TextureHandle::new(1, 1));
+    assert!(material.validate().is_ok());
+
+    assert_eq!(material.param("u_Scale"), Some(MaterialParam::F32(2.0)));
+    assert_eq!(
+        material.param("u_Color"),
+        Some(MaterialParam::Vector4f([1.0, 0.0, 0.0, 1.0]))
+    );
+    assert_eq!(material.textures.len(), 1);
+    assert_eq!(
+        material.texture("u_DetailTex"),
+        Some(TextureHandle::new(1, 1))
+    );
+    assert_eq!(material.texture("u_Scale"), None);
+
+    material.set("u_Invalid", MaterialParam::Texture(1));
+    assert!(material.validate().is_err());
+}
+
+#[test]
+fn serialization() {
+    let mut material = Material::new("unlit");
+    material.set("u_Offset", [1.0, 2.0]);
+    material.render_state = Some(RenderState::default());
+
+    let bytes = bincode::serialize(&material).unwrap();
+    let v: Material = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(v.shader, "unlit");
+    assert_eq!(v.params, material.params);
+    assert_eq!(v.render_state, Some(RenderState::default()));
+}
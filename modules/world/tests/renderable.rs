@@ -0,0 +1,334 @@
+extern crate crayon;
+extern crate crayon_world;
+
+use std::sync::Once;
+
+use crayon_world::prelude::*;
+use crayon_world::renderable::headless::HeadlessRenderer;
+
+static SETUP: Once = Once::new();
+
+// The engine could be set up only once in a process, so it's shared by the tests.
+fn setup() {
+    SETUP.call_once(|| crayon::application::oneshot().unwrap());
+}
+
+// Creates an empty mesh with the bounds of unit cube.
+fn create_mesh() -> crayon::video::prelude::MeshHandle {
+    use crayon::math::prelude::Aabb3;
+    use crayon::video::prelude::*;
+    setup();
+
+    let mut params = MeshParams::default();
+    params.hint = MeshHint::Dynamic;
+    params.num_verts = 3;
+    params.num_idxes = 3;
+    params.aabb = Aabb3::new([-0.5, -0.5, -0.5].into(), [0.5, 0.5, 0.5].into());
+    crayon::video::create_mesh(params, None).unwrap()
+}
+
+#[test]
+fn culling() {
+    use crayon::math::prelude::{Aabb2, InnerSpace, Ray, Vector3};
+    let mesh = create_mesh();
+
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let camera = scene.create("camera");
+    scene.add_camera(camera, Camera::default());
+
+    let front = scene.create("front");
+    scene.add_mesh(front, mesh);
+    scene.set_position(front, [0.0, 0.0, 10.0]);
+
+    let back = scene.create("back");
+    scene.add_mesh(back, mesh);
+    scene.set_position(back, [0.0, 0.0, -10.0]);
+
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 1);
+    assert_eq!(scene.frame_info().culled, 1);
+
+    scene.renderables.set_culling(false);
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 2);
+    assert_eq!(scene.frame_info().culled, 0);
+
+    // Split-screen cameras draw into the surfaces of their viewports.
+    let left = Aabb2::new([0.0, 0.0].into(), [0.5, 1.0].into());
+    scene.camera_mut(camera).unwrap().set_viewport(left);
+
+    let right = scene.create("right");
+    let mut v = Camera::default();
+    v.set_viewport(Aabb2::new([0.5, 0.0].into(), [1.0, 1.0].into()));
+    v.set_order(-1);
+    scene.add_camera(right, v);
+
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 4);
+    assert!(scene.camera(camera).unwrap().surface().is_some());
+    assert!(scene.camera(camera).unwrap().surface() != scene.camera(right).unwrap().surface());
+
+    let ray = scene
+        .camera(right)
+        .unwrap()
+        .screen_point_to_ray([75.0, 50.0], [100.0, 100.0]);
+    assert!(
+        (ray.direction - crayon::math::prelude::Vector3::new(0.0, 0.0, 1.0)).magnitude() < 1e-4
+    );
+
+    // The meshes are culled and raycasted with the bounding volume hierarchy.
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let camera = scene.create("camera");
+    scene.add_camera(camera, Camera::default());
+
+    let mut ents = Vec::new();
+    for i in 0..64 {
+        let ent = scene.create(format!("{}", i));
+        let x = (i % 4) as f32 * 2.0 - 3.0;
+        let y = (i / 4 % 4) as f32 * 2.0 - 3.0;
+        let z = if i < 32 { 20.0 } else { -20.0 } + (i / 16 % 2) as f32 * 2.0;
+        scene.add_mesh(ent, mesh);
+        scene.set_position(ent, [x, y, z]);
+        ents.push(ent);
+    }
+
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 32);
+    assert_eq!(scene.frame_info().culled, 32);
+
+    let ray = Ray::new(Vector3::new(-3.0, -3.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+    let hits = scene.raycast(ray);
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].ent, ents[0]);
+    assert_eq!(hits[1].ent, ents[16]);
+
+    // The meshes moved after drawing are tested without the hierarchy.
+    scene.set_position(ents[0], [100.0, -3.0, 20.0]);
+    let hits = scene.raycast(ray);
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].ent, ents[16]);
+
+    let ray = Ray::new(Vector3::new(100.0, -3.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+    let hits = scene.raycast(ray);
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].ent, ents[0]);
+
+    scene.update_transforms();
+    assert_eq!(scene.raycast(ray).len(), 1);
+
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 31);
+    assert_eq!(scene.frame_info().culled, 33);
+
+    scene.delete(ents[0]);
+    assert!(scene.raycast(ray).is_empty());
+}
+
+#[test]
+fn skinning() {
+    setup();
+
+    // The bone matrices fit into the uniforms of headless device.
+    let mut renderer = SimpleRenderer::new().unwrap();
+    assert_eq!(renderer.skinning_mode(), Some(SkinningMode::Uniforms));
+    renderer.set_max_bones(1024);
+    assert_eq!(renderer.skinning_mode(), Some(SkinningMode::Texture));
+    assert_eq!(PbrRenderer::new().unwrap().max_bones(), MAX_BONES);
+}
+
+#[test]
+fn static_batches() {
+    use crayon::math::prelude::Aabb3;
+    use crayon::utils::prelude::HandleLike;
+    use crayon::video::prelude::*;
+    setup();
+
+    // The meshes sharing a material are merged into static batches.
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let camera = scene.create("camera");
+    scene.add_camera(camera, Camera::default());
+
+    crayon::video::set_mesh_readable(true);
+
+    let mut params = MeshParams::default();
+    params.layout = VertexLayout::build()
+        .with(Attribute::Position, VertexFormat::Float, 3, false)
+        .finish();
+    params.num_verts = 3;
+    params.num_idxes = 3;
+    params.aabb = Aabb3::new([0.0, 0.0, 0.0].into(), [1.0, 1.0, 0.0].into());
+
+    let verts: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+    let data = MeshData {
+        vptr: IndexFormat::encode(&verts).to_vec().into_boxed_slice(),
+        iptr: IndexFormat::encode(&[0u16, 1, 2]).to_vec().into_boxed_slice(),
+    };
+    let triangle = crayon::video::create_mesh(params, data).unwrap();
+
+    let mut ents = Vec::new();
+    for i in 0..3 {
+        let mut mr = MeshRenderer::from(triangle);
+        mr.material = Some(MaterialHandle::new(1, 1));
+
+        let e = scene.create("static");
+        scene.add_mesh(e, mr);
+        scene.set_position(e, [i as f32, 0.0, 5.0]);
+        ents.push(e);
+    }
+
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 3);
+
+    assert_eq!(scene.build_static_batches().unwrap(), 1);
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 1);
+
+    scene.mesh_mut(ents[0]).unwrap().static_batching = false;
+    assert_eq!(scene.build_static_batches().unwrap(), 1);
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 2);
+
+    scene.clear_static_batches();
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 3);
+}
+
+#[test]
+fn lods() {
+    let (mesh, low) = (create_mesh(), create_mesh());
+
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let camera = scene.create("camera");
+    scene.add_camera(camera, Camera::default());
+
+    let mut lods = LodGroup::new();
+    lods.add(LodLevel::new(mesh, None, 0.5));
+    lods.add(LodLevel::new(low, None, 0.2));
+
+    let ent = scene.create("lods");
+    scene.add_mesh(ent, mesh);
+    scene.add_lod_group(ent, lods);
+
+    let levels = [
+        (2.0, Some(0)),
+        (5.0, Some(1)),
+        (3.1, Some(1)),
+        (2.5, Some(0)),
+    ];
+    for &(z, level) in &levels {
+        scene.set_position(ent, [0.0, 0.0, z]);
+        scene.draw();
+        assert_eq!(scene.lod_group(ent).unwrap().level(camera), level);
+        assert_eq!(scene.frame_info().submitted, 1);
+    }
+
+    // The levels are swapped in only while drawing.
+    scene.set_position(ent, [0.0, 0.0, 5.0]);
+    scene.draw();
+    assert_eq!(scene.lod_group(ent).unwrap().level(camera), Some(1));
+    assert_eq!(scene.mesh(ent).unwrap().mesh, mesh);
+
+    scene.set_position(ent, [0.0, 0.0, 20.0]);
+    scene.draw();
+    assert_eq!(scene.lod_group(ent).unwrap().level(camera), None);
+    assert_eq!(scene.frame_info().submitted, 0);
+
+    // The meshes with level of details are culled with their levels only.
+    scene.set_position(ent, [0.0, 0.0, -5.0]);
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 0);
+    assert_eq!(scene.frame_info().culled, 1);
+
+    // The levels picked by deleted cameras are forgotten.
+    scene.set_position(ent, [0.0, 0.0, 2.0]);
+    scene.draw();
+    scene.delete(camera);
+    scene.draw();
+    assert_eq!(scene.lod_group(ent).unwrap().level(camera), None);
+}
+
+#[test]
+fn layers() {
+    let mesh = create_mesh();
+
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let camera = scene.create("camera");
+    scene.add_camera(camera, Camera::default());
+
+    let (front, back) = (scene.create("front"), scene.create("back"));
+    for &(ent, x) in &[(front, -1.0), (back, 1.0)] {
+        scene.add_mesh(ent, mesh);
+        scene.set_position(ent, [x, 0.0, 10.0]);
+    }
+
+    scene.set_layers(back, 1 << 2);
+    assert_eq!(scene.layers(front), crayon_world::tags::DEFAULT_LAYERS);
+    assert_eq!(scene.layers(back), 1 << 2);
+
+    scene.camera_mut(camera).unwrap().set_culling_mask(1 << 2);
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 1);
+
+    scene.camera_mut(camera).unwrap().set_culling_mask(1 << 1);
+    scene.draw();
+    assert_eq!(scene.frame_info().submitted, 0);
+}
+
+#[test]
+fn effects() {
+    setup();
+
+    let mut stack = PostProcessing::new().unwrap();
+    stack
+        .push("fxaa", Fxaa::new().unwrap())
+        .push("vignette", Vignette::new().unwrap());
+
+    assert!(stack.effect_mut::<_, Fxaa>("fxaa").is_some());
+    assert!(stack.effect_mut::<_, Vignette>("fxaa").is_none());
+    assert!(stack.effect_mut::<_, Vignette>("none").is_none());
+
+    let vignette = stack.effect_mut::<_, Vignette>("vignette").unwrap();
+    vignette.intensity = 2.0;
+    assert_eq!(vignette.intensity, 2.0);
+}
+
+#[test]
+fn debug_views() {
+    use crayon::math::prelude::Aabb3;
+    use crayon::video::prelude::*;
+    setup();
+
+    // The meshes without normals are drawn with the debug views too.
+    let mut params = MeshParams::default();
+    params.hint = MeshHint::Dynamic;
+    params.layout = VertexLayout::build()
+        .with(Attribute::Position, VertexFormat::Float, 3, false)
+        .finish();
+    params.num_verts = 3;
+    params.num_idxes = 3;
+    params.aabb = Aabb3::new([-0.5, -0.5, -0.5].into(), [0.5, 0.5, 0.5].into());
+    let mesh = crayon::video::create_mesh(params, None).unwrap();
+
+    let mut scene = Scene::new(SimpleRenderer::new().unwrap());
+    let camera = scene.create("camera");
+    scene.add_camera(camera, Camera::default());
+
+    let ent = scene.create("mesh");
+    scene.add_mesh(ent, mesh);
+    scene.set_position(ent, [0.0, 0.0, 5.0]);
+
+    for &view in &[DebugView::Normals, DebugView::Depth, DebugView::Overdraw] {
+        scene.renderer.set_debug_view(view);
+        scene.draw();
+        assert_eq!(scene.renderer.debug_view(), view);
+        assert_eq!(scene.frame_info().submitted, 1);
+    }
+
+    // The wireframe overrides all the surfaces until it's disabled.
+    assert!(!crayon::video::wireframe());
+    crayon::video::set_wireframe(true);
+    assert!(crayon::video::wireframe());
+    scene.draw();
+    crayon::video::set_wireframe(false);
+    assert!(!crayon::video::wireframe());
+}
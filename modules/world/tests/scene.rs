@@ -1,6 +1,7 @@
 extern crate crayon;
 extern crate crayon_world;
 
+use crayon::math::prelude::{Color, Projection};
 use crayon_world::prelude::*;
 use crayon_world::renderable::headless::HeadlessRenderer;
 
@@ -88,3 +89,108 @@ fn instantiate() {
         scene.find("room.obj/floor/tallBox")
     );
 }
+
+#[test]
+fn changes() {
+    struct Health(u32);
+
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let e1 = scene.create("e1");
+    let e2 = scene.create("e2");
+    scene.add_camera(e1, Camera::default());
+    scene.add_component(e2, Health(100));
+
+    let since = scene.increase_tick();
+    assert_eq!(scene.query::<Added<Transform>>(0), vec![e1, e2]);
+    assert_eq!(scene.query::<Added<Camera>>(0), vec![e1]);
+    assert!(scene.query::<Changed<Transform>>(since).is_empty());
+
+    scene.set_position(e2, [1.0, 0.0, 0.0]);
+    scene.camera_mut(e1).unwrap().set_clip_plane(0.1, 100.0);
+    scene.component_mut::<Health>(e2).unwrap().0 = 50;
+
+    assert_eq!(scene.query::<Changed<Transform>>(since), vec![e2]);
+    assert_eq!(scene.query::<Changed<Camera>>(since), vec![e1]);
+    assert!(scene.query::<Added<Camera>>(since).is_empty());
+    assert_eq!(scene.query::<Changed<Health>>(since), vec![e2]);
+
+    let since = scene.increase_tick();
+    scene.set_parent(e2, e1, false).unwrap();
+    assert_eq!(scene.query::<Changed<Transform>>(since), vec![e2]);
+    assert!(scene.query::<Changed<Health>>(since).is_empty());
+    assert!(scene.query::<Changed<MeshRenderer>>(since).is_empty());
+}
+
+#[test]
+fn serialization() {
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let e1 = scene.create("room");
+    let e2 = scene.create("camera");
+    scene.set_parent(e2, e1, false).unwrap();
+    scene.set_layers(e2, 1 << 3);
+
+    let mut camera = Camera::ortho(4.0, 3.0, 0.1, 10.0);
+    camera.set_order(2);
+    camera.set_culling_mask(1 << 3);
+    camera.set_exposure(0.5);
+    scene.add_camera(e2, camera);
+    let fog = Fog::linear(Color::white(), 10.0, 20.0);
+    scene.renderables.set_fog(fog);
+
+    let data = scene.save_scene().unwrap();
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let ents = scene.load_scene(&data).unwrap();
+
+    assert_eq!(scene.find("room/camera"), Some(ents[1]));
+    assert_eq!(scene.layers(ents[1]), 1 << 3);
+
+    let camera = scene.camera(ents[1]).unwrap();
+    assert_eq!(
+        camera.projection(),
+        Projection::Ortho {
+            width: 4.0,
+            height: 3.0,
+            near: 0.1,
+            far: 10.0
+        }
+    );
+    assert_eq!(camera.order(), 2);
+    assert_eq!(camera.culling_mask(), 1 << 3);
+    assert_eq!(camera.exposure(), 0.5);
+    assert_eq!(scene.renderables.fog(), Some(fog));
+}
+
+#[test]
+fn dispatch() {
+    struct Brightness(f32);
+
+    struct Exposure;
+
+    impl System for Exposure {
+        fn access(&self) -> SystemAccess {
+            SystemAccess::new().read::<Brightness>().write::<Camera>()
+        }
+
+        fn run(&mut self, components: &ComponentView) {
+            let brightness = components.read::<Brightness>();
+            for (ent, v) in components.write::<Camera>().iter_mut() {
+                if let Some(b) = brightness.get(ent) {
+                    v.set_exposure(b.0);
+                }
+            }
+        }
+    }
+
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let e1 = scene.create("e1");
+    scene.add_camera(e1, Camera::default());
+    scene.add_component(e1, Brightness(0.5));
+
+    let since = scene.increase_tick();
+    let mut dispatcher = Dispatcher::new();
+    dispatcher.add(Exposure);
+    scene.dispatch(&mut dispatcher);
+
+    assert_eq!(scene.camera(e1).unwrap().exposure(), 0.5);
+    assert_eq!(scene.query::<Changed<Camera>>(since), vec![e1]);
+}
@@ -0,0 +1,36 @@
+extern crate crayon_world;
+
+use crayon_world::prelude::*;
+
+#[test]
+fn split_distances() {
+    let mut cascades = ShadowCascades::default();
+    cascades.count = 4;
+    cascades.distance = 100.0;
+
+    cascades.splits = CascadeSplits::Practical(0.0);
+    let distances = cascades.split_distances(1.0, 1000.0);
+    assert_eq!(distances, vec![25.75, 50.5, 75.25, 100.0]);
+
+    cascades.splits = CascadeSplits::Practical(1.0);
+    let distances = cascades.split_distances(1.0, 1000.0);
+    assert_eq!(distances.len(), 4);
+    assert!((distances[0] - 100.0f32.powf(0.25)).abs() < 1e-4);
+    assert!((distances[1] - 10.0).abs() < 1e-4);
+    assert!((distances[3] - 100.0).abs() < 1e-3);
+
+    // The distances are clamped by the far clip plane, and kept in ascending order.
+    cascades.count = 3;
+    cascades.splits = CascadeSplits::Manual([0.5, 0.2, 2.0, 1.0]);
+    let distances = cascades.split_distances(1.0, 80.0);
+    assert_eq!(distances, vec![40.0, 40.0, 80.0]);
+
+    cascades.count = 0;
+    assert_eq!(cascades.split_distances(1.0, 80.0).len(), 1);
+
+    cascades.count = MAX_SHADOW_CASCADES + 1;
+    assert_eq!(
+        cascades.split_distances(1.0, 80.0).len(),
+        MAX_SHADOW_CASCADES
+    );
+}
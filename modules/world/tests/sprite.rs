@@ -0,0 +1,96 @@
+extern crate crayon;
+extern crate crayon_world;
+
+use crayon::bincode;
+use crayon::math::prelude::Aabb2;
+use crayon::uuid::Uuid;
+use crayon::video::assets::texture::TextureHandle;
+use crayon_world::prelude::*;
+use crayon_world::renderable::headless::HeadlessRenderer;
+
+#[test]
+fn atlas() {
+    let texture = TextureHandle::default();
+
+    let mut atlas = TextureAtlas::grid(texture, [256, 128], [64, 64]);
+    assert_eq!(atlas.len(), 8);
+    assert_eq!(
+        atlas.frame(5),
+        Some(Aabb2::new([64, 64].into(), [128, 128].into()))
+    );
+
+    let index = atlas.add("hero", Aabb2::new([0, 0].into(), [128, 64].into()));
+    assert_eq!(atlas.find("hero"), Some(index));
+    assert_eq!(atlas.find("villain"), None);
+
+    atlas.set_pixels_per_unit(64.0);
+    let sprite = atlas.sprite(index).unwrap();
+    assert_eq!(sprite.texture, Some(texture));
+    assert_eq!(sprite.size, [2.0, 1.0].into());
+    assert_eq!(sprite.texcoord.min, [0.0, 0.0].into());
+    assert_eq!(sprite.texcoord.max, [0.5, 0.5].into());
+
+    assert!(atlas.sprite(atlas.len()).is_none());
+
+    let sprite = atlas.sliced_sprite(index, [16, 8, 16, 8]).unwrap();
+    let slice = sprite.slice.unwrap();
+    assert_eq!(slice.insets, [0.125, 0.125, 0.125, 0.125]);
+    assert_eq!(slice.borders, [0.25, 0.125, 0.25, 0.125]);
+    assert!(atlas.sliced_sprite(atlas.len(), [0; 4]).is_none());
+}
+
+#[test]
+fn regions() {
+    let uuid = Uuid::from_bytes([1; 16]);
+    let mut atlas = TextureAtlas::from_uuid(uuid, [128, 64]);
+
+    // A 32x16 frame which is trimmed from a 64x64 image, and rotated in texture.
+    let region = AtlasRegion {
+        rect: Aabb2::new([100, 0].into(), [116, 32].into()),
+        rotated: true,
+        offset: [8, 40].into(),
+        source: [64, 64].into(),
+    };
+
+    let index = atlas.add_region("hero", region);
+    assert!(atlas.validate().is_ok());
+    assert_eq!(atlas.region(index).unwrap().dimensions(), [32, 16].into());
+
+    // The pivot is kept at the center of source image.
+    let sprite = atlas.sprite(index).unwrap();
+    assert!(sprite.rotated);
+    assert_eq!(sprite.size, [0.32, 0.16].into());
+    assert_eq!(sprite.pivot, [0.75, 1.5].into());
+
+    let bytes = bincode::serialize(&atlas).unwrap();
+    let v: TextureAtlas = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(v.universe_texture(), Some(uuid));
+    assert_eq!(v.find("hero"), Some(index));
+    assert_eq!(v.region(index), Some(&region));
+
+    atlas.add("outside", Aabb2::new([120, 0].into(), [136, 16].into()));
+    assert!(atlas.validate().is_err());
+}
+
+#[test]
+fn sprites() {
+    let mut scene = Scene::new(HeadlessRenderer::new());
+
+    let e1 = scene.create("e1");
+    let e2 = scene.create("e2");
+    scene.set_parent(e2, e1, false).unwrap();
+
+    scene.add_sprite(e1, Sprite::default());
+    scene.add_sprite(e2, TextureHandle::default());
+    assert_eq!(
+        scene.sprite(e2).unwrap().texture,
+        Some(TextureHandle::default())
+    );
+
+    scene.sprite_mut(e1).unwrap().order = 1;
+    assert_eq!(scene.sprite(e1).unwrap().order, 1);
+
+    scene.delete(e1);
+    assert!(scene.sprite(e1).is_none());
+    assert!(scene.sprite(e2).is_none());
+}
@@ -0,0 +1,66 @@
+extern crate crayon;
+extern crate crayon_world;
+
+use std::sync::Arc;
+
+use crayon::math::prelude::{Aabb2, Vector2};
+use crayon::video::assets::texture::TextureHandle;
+use crayon_world::prelude::*;
+use crayon_world::renderable::headless::HeadlessRenderer;
+
+#[test]
+fn layout() {
+    let font = BitmapFont::grid(TextureHandle::default(), [128, 64], [8, 16], ' ');
+    assert_eq!(font.line_height(), 16.0);
+    assert!(font.glyph('!').is_some());
+    assert!(font.glyph('\u{4e2d}').is_none());
+
+    assert_eq!(font.measure("AB\nC"), Vector2::new(16.0, 32.0));
+    assert_eq!(font.measure("A\u{4e2d}"), Vector2::new(8.0, 16.0));
+
+    let quads: Vec<_> = font.layout("AB\nC").collect();
+    assert_eq!(quads.len(), 3);
+    assert_eq!(
+        quads[1].rect,
+        Aabb2::new([8.0, -16.0].into(), [16.0, 0.0].into())
+    );
+    assert_eq!(
+        quads[2].rect,
+        Aabb2::new([0.0, -32.0].into(), [8.0, -16.0].into())
+    );
+
+    // ' ' is the first glyph at the top-left corner.
+    let quad = font.layout(" ").next().unwrap();
+    assert_eq!(quad.texcoord.min, [0.0, 0.0].into());
+    assert_eq!(quad.texcoord.max, [0.0625, 0.25].into());
+}
+
+#[test]
+fn texts() {
+    let font = Arc::new(BitmapFont::grid(
+        TextureHandle::default(),
+        [128, 64],
+        [8, 16],
+        ' ',
+    ));
+
+    let mut scene = Scene::new(HeadlessRenderer::new());
+    let e1 = scene.create("e1");
+    let e2 = scene.create("e2");
+    scene.set_parent(e2, e1, false).unwrap();
+
+    scene.add_text(e1, TextRenderer::new(font.clone(), "Hello"));
+    let mut text = TextRenderer::new(font, "World");
+    text.space = TextSpace::Screen;
+    scene.add_text(e2, text);
+
+    scene.text_mut(e1).unwrap().order = 1;
+    assert_eq!(scene.text(e1).unwrap().order, 1);
+    assert_eq!(scene.text(e2).unwrap().space, TextSpace::Screen);
+
+    scene.remove_text(e1);
+    assert!(scene.text(e1).is_none());
+
+    scene.delete(e1);
+    assert!(scene.text(e2).is_none());
+}
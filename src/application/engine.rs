@@ -1,11 +1,12 @@
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use crate::math::prelude::Vector2;
 use crate::sched::prelude::LatchProbe;
 use crate::window::prelude::{Event, EventListener, EventListenerHandle, WindowEvent};
 
 use super::lifecycle::LifecycleListener;
-use super::Params;
+use super::{Headless, Params};
 
 type Result<T> = ::std::result::Result<T, ::failure::Error>;
 
@@ -20,18 +21,108 @@ pub struct EngineSystem {
 
 struct EngineState {
     alive: AtomicBool,
+    suspended: AtomicBool,
+    focused: AtomicBool,
+    // The window events that are dispatched to lifecycle listeners after `on_pre_update`,
+    // since the listeners are being visited when the events are polled.
+    events: Mutex<Vec<WindowEvent>>,
 }
 
 impl EventListener for Arc<EngineState> {
     fn on(&mut self, v: &Event) -> Result<()> {
-        if let Event::Window(WindowEvent::Closed) = *v {
-            self.alive.store(false, Ordering::Relaxed);
+        match *v {
+            Event::Window(WindowEvent::Closed) => {
+                self.alive.store(false, Ordering::Relaxed);
+            }
+            Event::Window(v @ WindowEvent::Suspended)
+            | Event::Window(v @ WindowEvent::Resumed)
+            | Event::Window(v @ WindowEvent::GainFocus)
+            | Event::Window(v @ WindowEvent::LostFocus)
+            | Event::Window(v @ WindowEvent::Resized(_, _))
+            | Event::Window(v @ WindowEvent::DpiChanged(_)) => {
+                self.events.lock().unwrap().push(v);
+            }
+            _ => {}
         }
 
         Ok(())
     }
 }
 
+impl EngineState {
+    fn new(alive: bool) -> Self {
+        EngineState {
+            alive: AtomicBool::new(alive),
+            suspended: AtomicBool::new(false),
+            focused: AtomicBool::new(true),
+            events: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn dispatch(&self) -> Result<()> {
+        for v in self.callbacks() {
+            super::foreach(|lis| v.notify(lis))?;
+        }
+
+        // The frame rate is limited with `Params::max_inactive_fps` in background.
+        let active = self.focused.load(Ordering::Relaxed) && !self.suspended();
+        super::inside::time_ctx().set_active(active);
+        Ok(())
+    }
+
+    // Turns the window events into the callbacks of lifecycle, the repeated suspensions and
+    // focus changes are ignored.
+    fn callbacks(&self) -> Vec<Callback> {
+        let events = std::mem::replace(&mut *self.events.lock().unwrap(), Vec::new());
+        events
+            .into_iter()
+            .filter_map(|v| match v {
+                WindowEvent::Suspended if !self.suspended.swap(true, Ordering::Relaxed) => {
+                    Some(Callback::Suspend)
+                }
+                WindowEvent::Resumed if self.suspended.swap(false, Ordering::Relaxed) => {
+                    Some(Callback::Resume)
+                }
+                WindowEvent::GainFocus if !self.focused.swap(true, Ordering::Relaxed) => {
+                    Some(Callback::FocusChanged(true))
+                }
+                WindowEvent::LostFocus if self.focused.swap(false, Ordering::Relaxed) => {
+                    Some(Callback::FocusChanged(false))
+                }
+                WindowEvent::Resized(w, h) => Some(Callback::Resize(Vector2::new(w, h))),
+                WindowEvent::DpiChanged(dpr) => Some(Callback::DpiChanged(dpr)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[inline]
+    fn suspended(&self) -> bool {
+        self.suspended.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Callback {
+    Suspend,
+    Resume,
+    FocusChanged(bool),
+    Resize(Vector2<u32>),
+    DpiChanged(f32),
+}
+
+impl Callback {
+    fn notify(self, lis: &mut dyn LifecycleListener) -> Result<()> {
+        match self {
+            Callback::Suspend => lis.on_suspend(),
+            Callback::Resume => lis.on_resume(),
+            Callback::FocusChanged(focused) => lis.on_focus_changed(focused),
+            Callback::Resize(dimensions) => lis.on_resize(dimensions),
+            Callback::DpiChanged(dpr) => lis.on_dpi_changed(dpr),
+        }
+    }
+}
+
 impl Drop for EngineSystem {
     fn drop(&mut self) {
         crate::window::detach(self.events);
@@ -42,6 +133,7 @@ impl Drop for EngineSystem {
             crate::video::inside::discard();
             crate::window::inside::discard();
             crate::sched::inside::discard();
+            crate::profiler::inside::discard();
         }
     }
 }
@@ -49,30 +141,45 @@ impl Drop for EngineSystem {
 impl EngineSystem {
     /// Setup engine with specified settings.
     pub unsafe fn new(params: Params) -> Result<Self> {
+        crate::profiler::inside::setup();
+
         #[cfg(not(target_arch = "wasm32"))]
         crate::sched::inside::setup(4, None, None);
         #[cfg(target_arch = "wasm32")]
         crate::sched::inside::setup(0, None, None);
 
-        crate::window::inside::setup(params.window)?;
-        crate::video::inside::setup()?;
+        match params.headless {
+            None => {
+                crate::window::inside::setup(params.window, params.video.vsync)?;
+                crate::video::inside::setup(params.video)?;
+            }
+            Some(Headless::Null) => {
+                crate::window::inside::headless();
+                crate::video::inside::headless();
+            }
+            Some(Headless::Offscreen) => {
+                crate::window::inside::offscreen(params.window)?;
+                crate::video::inside::offscreen()?;
+            }
+        }
+
         crate::input::inside::setup(params.input);
         crate::res::inside::setup(params.res)?;
 
-        let state = Arc::new(EngineState {
-            alive: AtomicBool::new(true),
-        });
+        let state = Arc::new(EngineState::new(true));
 
         let sys = EngineSystem {
             events: crate::window::attach(state.clone()),
             state,
-            headless: false,
+            headless: params.headless.is_some(),
         };
 
         Ok(sys)
     }
 
     pub unsafe fn new_headless(params: Params) -> Result<Self> {
+        crate::profiler::inside::setup();
+
         #[cfg(not(target_arch = "wasm32"))]
         crate::sched::inside::setup(4, None, None);
         #[cfg(target_arch = "wasm32")]
@@ -83,9 +190,7 @@ impl EngineSystem {
         crate::input::inside::setup(params.input);
         crate::res::inside::setup(params.res)?;
 
-        let state = Arc::new(EngineState {
-            alive: AtomicBool::new(false),
-        });
+        let state = Arc::new(EngineState::new(false));
 
         let sys = EngineSystem {
             events: crate::window::attach(state.clone()),
@@ -107,10 +212,26 @@ impl EngineSystem {
     }
 
     pub fn run_oneshot(&self) -> Result<()> {
-        super::foreach(|v| v.on_pre_update())?;
-        super::foreach(|v| v.on_update())?;
-        super::foreach(|v| v.on_render())?;
-        super::foreach_rev(|v| v.on_post_update())?;
+        Self::advance(&self.state)
+    }
+
+    fn advance(state: &EngineState) -> Result<()> {
+        {
+            crate::profile_scope!("frame");
+            super::foreach(|v| v.on_pre_update())?;
+            state.dispatch()?;
+
+            // Skips the updates and renders in background, where the window surface might
+            // have been destroyed.
+            if !state.suspended() {
+                super::foreach(|v| v.on_update())?;
+                super::foreach(|v| v.on_render())?;
+            }
+
+            super::foreach_rev(|v| v.on_post_update())?;
+        }
+
+        crate::profiler::inside::ctx().advance();
         Ok(())
     }
 
@@ -138,11 +259,7 @@ impl EngineSystem {
 
                 super::sys::run_forever(
                     move || {
-                        super::foreach(|v| v.on_pre_update())?;
-                        super::foreach(|v| v.on_update())?;
-                        super::foreach(|v| v.on_render())?;
-                        super::foreach_rev(|v| v.on_post_update())?;
-
+                        Self::advance(&state)?;
                         Ok(state.alive.load(Ordering::Relaxed))
                     },
                     move || {
@@ -160,3 +277,81 @@ impl EngineSystem {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        suspended: bool,
+        focused: Option<bool>,
+        dimensions: Option<Vector2<u32>>,
+    }
+
+    impl LifecycleListener for Recorder {
+        fn on_suspend(&mut self) -> Result<()> {
+            self.suspended = true;
+            Ok(())
+        }
+
+        fn on_resume(&mut self) -> Result<()> {
+            self.suspended = false;
+            Ok(())
+        }
+
+        fn on_focus_changed(&mut self, focused: bool) -> Result<()> {
+            self.focused = Some(focused);
+            Ok(())
+        }
+
+        fn on_resize(&mut self, dimensions: Vector2<u32>) -> Result<()> {
+            self.dimensions = Some(dimensions);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn callbacks() {
+        let mut state = Arc::new(EngineState::new(true));
+        let events = [
+            WindowEvent::Suspended,
+            WindowEvent::Suspended,
+            WindowEvent::GainFocus,
+            WindowEvent::LostFocus,
+            WindowEvent::Resized(2, 3),
+            WindowEvent::Closed,
+        ];
+
+        for &v in &events {
+            state.on(&Event::Window(v)).unwrap();
+        }
+
+        let callbacks = state.callbacks();
+        assert_eq!(
+            callbacks,
+            [
+                Callback::Suspend,
+                Callback::FocusChanged(false),
+                Callback::Resize(Vector2::new(2, 3)),
+            ]
+        );
+
+        assert!(state.suspended());
+        assert!(!state.alive.load(Ordering::Relaxed));
+
+        let mut recorder = Recorder::default();
+        for v in callbacks {
+            v.notify(&mut recorder).unwrap();
+        }
+
+        assert!(recorder.suspended);
+        assert_eq!(recorder.focused, Some(false));
+        assert_eq!(recorder.dimensions, Some(Vector2::new(2, 3)));
+
+        state.on(&Event::Window(WindowEvent::Resumed)).unwrap();
+        assert_eq!(state.callbacks(), [Callback::Resume]);
+        assert!(!state.suspended());
+        assert!(state.callbacks().is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use std::sync::{Arc, Mutex};
 
+use crate::math::prelude::Vector2;
 use crate::utils::object_pool::ObjectPool;
 
 impl_handle!(LifecycleListenerHandle);
@@ -24,6 +25,33 @@ pub trait LifecycleListener {
     fn on_exit(&mut self) -> Result<(), failure::Error> {
         Ok(())
     }
+
+    /// Called when the application is sent to background, e.g. switched out on mobile
+    /// devices. The `on_update` and `on_render` are not called until it's resumed.
+    fn on_suspend(&mut self) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    /// Called when the application is brought back to foreground.
+    fn on_resume(&mut self) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    /// Called when the window gained or lost the focus of user input.
+    fn on_focus_changed(&mut self, _focused: bool) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    /// Called when the size in *points* of window has changed.
+    fn on_resize(&mut self, _dimensions: Vector2<u32>) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    /// Called when the device pixel ratio of window has changed, e.g. it has been moved to
+    /// another monitor. The resources rasterized in *pixels* should be recreated.
+    fn on_dpi_changed(&mut self, _device_pixel_ratio: f32) -> Result<(), failure::Error> {
+        Ok(())
+    }
 }
 
 pub struct LifecycleSystem {
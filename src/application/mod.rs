@@ -24,6 +24,71 @@
 //! `Engine` mentioned above is the most fundamental module in crayon. It binds various
 //! essential systems in a central place, and responsible for running the main loop.
 //!
+//! # Headless
+//!
+//! The engine could run without window by setting `Params::headless`, e.g. in tests or the
+//! thumbnail renderers on servers. With `Headless::Null`, the same code paths of rendering
+//! run without GPU, while the invalid handles and parameters are reported as usual. With
+//! `Headless::Offscreen`, the surfaces are drawn with an offscreen OpenGL context and could
+//! be read back with `video::read_render_texture` or `video::capture_screenshot`. The
+//! engine keeps running until `application::discard`.
+//!
+//! ```rust,ignore
+//! let mut params = Params::default();
+//! params.headless = Some(Headless::Offscreen);
+//! params.window.size = (256, 256).into();
+//! application::setup(params, || Thumbnails::new())?;
+//! ```
+//!
+//! Besides, launching with the `headless` argument runs a single frame with the null
+//! backends, which is used to smoke-test the applications.
+//!
+//! # Lifecycle Events
+//!
+//! Besides the updates of every frame, the listeners are notified with `on_suspend` and
+//! `on_resume` when the application is sent to background and brought back, e.g. on
+//! mobile devices or when the page is hidden in browser. The `on_update` and `on_render`
+//! are skipped in background. And `on_focus_changed` and `on_resize` are called when the
+//! window gained or lost focus and has been resized.
+//!
+//! The OpenGL context could be lost in background on Android and WebGL. With
+//! `VideoParams::restore_lost_context`, the video objects are recreated with their latest
+//! data once it's restored, the contents of render textures should be redrawn on the
+//! `WindowEvent::ContextRestored` event.
+//!
+//! # Frame Pacing
+//!
+//! The frames are paced by the vertical blanks of monitor with `VideoParams::vsync`, or
+//! limited to `Params::max_fps` by waiting before each frame. The `SleepStrategy` trades
+//! the precision of waiting for the power consumption, a game with fixed frame rate could
+//! spin the last few milliseconds, while a tool that idles most time should just sleep.
+//!
+//! ```rust,ignore
+//! let mut params = Params::default();
+//! params.video.vsync = VSync::Off;
+//! params.max_fps = 60;
+//! params.sleep_strategy = SleepStrategy::Hybrid(Duration::from_millis(2));
+//! ```
+//!
+//! The intervals between frames and the vertical blanks missed by them are reported in
+//! `video::frame_info`.
+//!
+//! # Replay
+//!
+//! The input events, timesteps and random seed of every frame could be recorded into file
+//! with `Params::replay`, and played back later to rerun a session deterministically, e.g.
+//! to reproduce bugs or in regression tests. The games should derive their random numbers
+//! from `application::seed` for that.
+//!
+//! ```rust,ignore
+//! let mut params = Params::default();
+//! params.replay = Some(Replay::Playback("session.rpl".into()));
+//! application::setup(params, || Game::new())?;
+//! ```
+//!
+//! The window events, e.g. resizing and focusing, are always live. And the time measured by
+//! the wall clock instead of `application::frame_duration` is not replayed.
+//!
 
 pub mod ins;
 pub mod sys;
@@ -31,25 +96,34 @@ pub mod sys;
 mod engine;
 mod launcher;
 mod lifecycle;
+mod replay;
 mod time;
 
 pub mod prelude {
     pub use super::launcher::Launcher;
     pub use super::lifecycle::{LifecycleListener, LifecycleListenerHandle};
-    pub use super::Params;
+    pub use super::replay::Replay;
+    pub use super::{Headless, Params, SleepStrategy};
 }
 
+use std::time::Duration;
+
 use crate::errors::*;
 
 use self::lifecycle::{LifecycleListener, LifecycleListenerHandle};
 
 use self::engine::EngineSystem;
-use self::inside::{ctx, lifecycle_ctx, time_ctx, CTX, LIFECYCLE_CTX, TIME_CTX};
+use self::inside::{ctx, lifecycle_ctx, replay_ctx, time_ctx};
+use self::inside::{CTX, LIFECYCLE_CTX, REPLAY_CTX, TIME_CTX};
 use self::lifecycle::LifecycleSystem;
+use self::replay::{Replay, ReplaySystem};
 use self::time::TimeSystem;
 
 use crate::input::InputParams;
+use crate::logging::LogParams;
 use crate::res::ResourceParams;
+use crate::video::VideoParams;
+use crate::window::prelude::Event;
 use crate::window::WindowParams;
 
 /// A structure containing configuration data for the game engine, which are
@@ -64,6 +138,8 @@ pub struct Params {
     /// Set maximum frames per second. The engine will sleep if fps is higher
     /// than this for less resource(e.g. power) consumptions.
     pub max_fps: u32,
+    /// Set how to wait for the next frame if fps is higher than `max_fps`.
+    pub sleep_strategy: SleepStrategy,
     /// Set maximum frames per second when the application does not have input
     /// focus.
     pub max_inactive_fps: u32,
@@ -71,10 +147,54 @@ pub struct Params {
     pub time_smooth_step: u32,
     /// The setup parameters for window sub-system.
     pub window: WindowParams,
+    /// The setup parameters for video sub-system.
+    pub video: VideoParams,
     /// The setup parameters for input sub-system.
     pub input: InputParams,
     /// The setup params for resource sub-system.
     pub res: ResourceParams,
+    /// The setup params for logging sub-system.
+    pub log: LogParams,
+    /// Runs the engine without window if specified, e.g. in tests or on servers.
+    pub headless: Option<Headless>,
+    /// Records or plays back the inputs of every frame if specified.
+    pub replay: Option<Replay>,
+}
+
+/// The modes of running the engine without window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Headless {
+    /// Nothing is actually drawn with the null backends, which need neither GPU nor GL
+    /// libraries, e.g. on dedicated game servers. The video objects and commands are
+    /// still validated like the GL backend, and the readbacks of pixels are blank.
+    Null,
+    /// Renders with an offscreen OpenGL context, the window framebuffer is emulated
+    /// with a framebuffer object of `WindowParams::size`. So surfaces could be drawn
+    /// and read back as usual.
+    ///
+    /// # Platform-specific
+    ///
+    /// The context is created by glutin with OSMesa on Linux, so the library of OSMesa is
+    /// required but no display server is needed. It is not supported in web environment.
+    Offscreen,
+}
+
+/// The strategies of waiting for the next frame when the frame rate is limited.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SleepStrategy {
+    /// Sleeps until the next frame, which consumes the least power. But the frames could
+    /// be late by the timer resolution of OS, which is a few milliseconds on some platforms.
+    Sleep,
+    /// Spins until the next frame, which is the most precise but keeps a core busy.
+    Spin,
+    /// Sleeps until the specified duration before the next frame, and spins the rest.
+    Hybrid(Duration),
+}
+
+impl Default for SleepStrategy {
+    fn default() -> Self {
+        SleepStrategy::Hybrid(Duration::from_millis(2))
+    }
 }
 
 impl Default for Params {
@@ -82,11 +202,16 @@ impl Default for Params {
         Params {
             min_fps: 0,
             max_fps: 30,
+            sleep_strategy: SleepStrategy::default(),
             max_inactive_fps: 0,
             time_smooth_step: 0,
             window: WindowParams::default(),
+            video: VideoParams::default(),
             input: InputParams::default(),
             res: ResourceParams::default(),
+            log: LogParams::default(),
+            headless: None,
+            replay: None,
         }
     }
 }
@@ -96,10 +221,23 @@ impl Params {
     pub fn validate(&mut self) {
         #[cfg(target_arch = "wasm32")]
         {
-            if self.max_fps > 0 {
+            if self.max_fps > 0 || self.max_inactive_fps > 0 {
                 self.max_fps = 0;
+                self.max_inactive_fps = 0;
                 warn!("The max FPS could not be controlled in web environment.");
             }
+
+            use crate::video::VSync;
+
+            if self.video.vsync != VSync::On {
+                self.video.vsync = VSync::On;
+                warn!("The vsync could not be disabled in web environment.");
+            }
+
+            if self.replay.is_some() {
+                self.replay = None;
+                warn!("The inputs could not be replayed in web environment.");
+            }
         }
     }
 }
@@ -114,10 +252,15 @@ where
         debug_assert!(LIFECYCLE_CTX.is_null(), "duplicated setup of crayon.");
 
         sys::init();
+        crate::logging::inside::setup(params.log.clone());
         params.validate();
 
+        // The replay file is opened before any global, which would leak if it failed.
+        let replay = ReplaySystem::new(params.replay.as_ref())?;
+
         let dirs = params.res.dirs.clone();
         LIFECYCLE_CTX = Box::into_raw(Box::new(LifecycleSystem::new()));
+        REPLAY_CTX = Box::into_raw(Box::new(replay));
         TIME_CTX = Box::into_raw(Box::new(TimeSystem::new(&params)));
 
         if std::env::args().any(|v| v == "headless") {
@@ -139,7 +282,11 @@ pub fn oneshot() -> Result<()> {
         let params = Params::default();
 
         sys::init();
+        crate::logging::inside::setup(params.log.clone());
+        let replay = ReplaySystem::new(None)?;
+
         LIFECYCLE_CTX = Box::into_raw(Box::new(LifecycleSystem::new()));
+        REPLAY_CTX = Box::into_raw(Box::new(replay));
         TIME_CTX = Box::into_raw(Box::new(TimeSystem::new(&params)));
         CTX = Box::into_raw(Box::new(EngineSystem::new_headless(params)?));
 
@@ -160,8 +307,13 @@ pub(crate) unsafe fn late_discard() {
     drop(Box::from_raw(TIME_CTX as *mut TimeSystem));
     TIME_CTX = std::ptr::null();
 
+    drop(Box::from_raw(REPLAY_CTX as *mut ReplaySystem));
+    REPLAY_CTX = std::ptr::null();
+
     drop(Box::from_raw(LIFECYCLE_CTX as *mut LifecycleSystem));
     LIFECYCLE_CTX = std::ptr::null();
+
+    crate::logging::inside::discard();
 }
 
 /// Checks if the engine is enabled.
@@ -211,10 +363,23 @@ pub fn set_max_fps(mut fps: u32) {
     time_ctx().set_max_fps(fps);
 }
 
+/// Set how to wait for the next frame if fps is higher than the maximum.
+#[inline]
+pub fn set_sleep_strategy(strategy: SleepStrategy) {
+    time_ctx().set_sleep_strategy(strategy);
+}
+
 /// Set maximum frames per second when the application does not have input
 /// focus.
+#[allow(unused_assignments, unused_mut)]
 #[inline]
-pub fn set_max_inactive_fps(fps: u32) {
+pub fn set_max_inactive_fps(mut fps: u32) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        warn!("The max FPS could not be controlled in web environment.");
+        fps = 0;
+    }
+
     time_ctx().set_max_inactive_fps(fps);
 }
 
@@ -236,6 +401,23 @@ pub fn frame_duration() -> ::std::time::Duration {
     time_ctx().frame_duration()
 }
 
+/// Gets the seed of random numbers, which is recorded and played back with the inputs.
+#[inline]
+pub fn seed() -> u64 {
+    replay_ctx().seed()
+}
+
+/// Checks if the recorded inputs are being played back.
+#[inline]
+pub fn is_replaying() -> bool {
+    replay_ctx().is_playing()
+}
+
+#[inline]
+pub(crate) fn replay_events(events: &mut Vec<Event>) {
+    replay_ctx().process(events)
+}
+
 #[inline]
 fn foreach<T>(func: T) -> Result<()>
 where
@@ -255,9 +437,11 @@ where
 mod inside {
     use super::engine::EngineSystem;
     use super::lifecycle::LifecycleSystem;
+    use super::replay::ReplaySystem;
     use super::time::TimeSystem;
 
     pub static mut LIFECYCLE_CTX: *const LifecycleSystem = std::ptr::null();
+    pub static mut REPLAY_CTX: *const ReplaySystem = std::ptr::null();
     pub static mut TIME_CTX: *const TimeSystem = std::ptr::null();
     pub static mut CTX: *const EngineSystem = std::ptr::null();
 
@@ -293,4 +477,15 @@ mod inside {
             &*TIME_CTX
         }
     }
+
+    pub fn replay_ctx() -> &'static ReplaySystem {
+        unsafe {
+            debug_assert!(
+                !REPLAY_CTX.is_null(),
+                "replay system has not been initialized properly."
+            );
+
+            &*REPLAY_CTX
+        }
+    }
 }
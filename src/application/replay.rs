@@ -0,0 +1,289 @@
+//! Records the input events, timesteps and random seed of every frame into file, and plays
+//! them back for deterministic reruns.
+//!
+//! The file starts with `MAGIC` and the seed, followed by the frames which are serialized
+//! with bincode and prefixed with their lengths.
+
+use std::fs;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::errors::*;
+use crate::input::events::InputEvent;
+use crate::utils::time::Timestamp;
+use crate::window::prelude::Event;
+
+pub const MAGIC: [u8; 8] = [b'R', b'P', b'L', b'Y', b' ', 0, 0, 1];
+
+/// The modes of replaying the inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Replay {
+    /// Records the input events, timesteps and random seed of every frame into file.
+    Record(PathBuf),
+    /// Plays back the recording in file. The live input events are ignored until all the
+    /// frames have been played, and then the engine keeps running with live input.
+    Playback(PathBuf),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct ReplayFrame {
+    timestep: Duration,
+    events: Vec<InputEvent>,
+}
+
+enum ReplayState {
+    Live,
+    Record {
+        file: BufWriter<fs::File>,
+        frame: Option<ReplayFrame>,
+    },
+    Playback {
+        file: BufReader<fs::File>,
+        frame: ReplayFrame,
+        frames: u64,
+    },
+}
+
+pub struct ReplaySystem {
+    seed: u64,
+    state: Mutex<ReplayState>,
+}
+
+impl Drop for ReplaySystem {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            warn!("Failed to finish the recording of inputs. {}", err);
+        }
+    }
+}
+
+impl ReplaySystem {
+    pub fn new(params: Option<&Replay>) -> Result<Self> {
+        let (seed, state) = match params {
+            None => (Self::random_seed(), ReplayState::Live),
+            Some(Replay::Record(path)) => {
+                let seed = Self::random_seed();
+                let mut file = BufWriter::new(fs::File::create(path)?);
+                file.write_all(&MAGIC)?;
+                file.write_u64::<LittleEndian>(seed)?;
+
+                info!("Records the inputs into {:?}.", path);
+                (seed, ReplayState::Record { file, frame: None })
+            }
+            Some(Replay::Playback(path)) => {
+                let mut file = BufReader::new(fs::File::open(path)?);
+
+                let mut buf = [0; 8];
+                file.read_exact(&mut buf)?;
+                if buf != MAGIC {
+                    bail!("[Replay] MAGIC number not match.");
+                }
+
+                let seed = file.read_u64::<LittleEndian>()?;
+
+                info!("Plays back the inputs from {:?}.", path);
+                let frame = ReplayFrame::default();
+                (
+                    seed,
+                    ReplayState::Playback {
+                        file,
+                        frame,
+                        frames: 0,
+                    },
+                )
+            }
+        };
+
+        Ok(ReplaySystem {
+            seed,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Gets the seed of random numbers.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Checks if the recording is being played back.
+    pub fn is_playing(&self) -> bool {
+        match *self.state.lock().unwrap() {
+            ReplayState::Playback { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Starts a new frame with the measured timestep, and returns the timestep that should
+    /// be used in this frame.
+    pub fn advance(&self, timestep: Duration) -> Result<Duration> {
+        let mut state = self.state.lock().unwrap();
+
+        let finished = match *state {
+            ReplayState::Live => return Ok(timestep),
+            ReplayState::Record {
+                ref mut file,
+                ref mut frame,
+            } => {
+                if let Some(v) = frame.take() {
+                    Self::write(file, &v)?;
+                }
+
+                *frame = Some(ReplayFrame {
+                    timestep,
+                    events: Vec::new(),
+                });
+
+                return Ok(timestep);
+            }
+            ReplayState::Playback {
+                ref mut file,
+                ref mut frame,
+                ref mut frames,
+            } => match Self::read(file)? {
+                Some(v) => {
+                    *frame = v;
+                    *frames += 1;
+                    return Ok(frame.timestep);
+                }
+                None => *frames,
+            },
+        };
+
+        info!("The playback of inputs finished after {} frames.", finished);
+        *state = ReplayState::Live;
+        Ok(timestep)
+    }
+
+    /// Records the input events of current frame, or replaces them with the recorded ones.
+    /// The window events are always live.
+    pub fn process(&self, events: &mut Vec<Event>) {
+        match *self.state.lock().unwrap() {
+            ReplayState::Live => {}
+            ReplayState::Record { ref mut frame, .. } => {
+                if let Some(ref mut frame) = *frame {
+                    for v in events.iter() {
+                        if let Event::InputDevice(v) = *v {
+                            frame.events.push(v);
+                        }
+                    }
+                }
+            }
+            ReplayState::Playback { ref mut frame, .. } => {
+                events.retain(|v| match *v {
+                    Event::InputDevice(_) => false,
+                    _ => true,
+                });
+
+                events.extend(frame.events.drain(..).map(Event::InputDevice));
+            }
+        }
+    }
+
+    /// Writes the pending frame into file.
+    pub fn flush(&self) -> Result<()> {
+        if let ReplayState::Record {
+            ref mut file,
+            ref mut frame,
+        } = *self.state.lock().unwrap()
+        {
+            if let Some(v) = frame.take() {
+                Self::write(file, &v)?;
+            }
+
+            file.flush()?;
+        }
+
+        Ok(())
+    }
+
+    fn write(file: &mut dyn Write, frame: &ReplayFrame) -> Result<()> {
+        let bytes = bincode::serialize(frame)?;
+        file.write_u32::<LittleEndian>(bytes.len() as u32)?;
+        file.write_all(&bytes)?;
+        Ok(())
+    }
+
+    fn read(file: &mut dyn Read) -> Result<Option<ReplayFrame>> {
+        let len = match file.read_u32::<LittleEndian>() {
+            Ok(v) => v,
+            Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        // The length is untrusted, so the bytes are allocated as they are read.
+        let mut bytes = Vec::new();
+        file.take(u64::from(len)).read_to_end(&mut bytes)?;
+        if bytes.len() != len as usize {
+            bail!("[Replay] The frame of {} bytes is truncated.", len);
+        }
+
+        Ok(Some(bincode::deserialize(&bytes)?))
+    }
+
+    fn random_seed() -> u64 {
+        // The precise timestamp starts from the launch, so it's mixed with the wall clock.
+        let now = super::sys::precise_timestamp();
+        let wall = super::sys::timestamp() - Timestamp::from_millis(0);
+        (wall.as_secs() ^ now.as_secs()).wrapping_mul(1_000_000_007)
+            ^ u64::from(wall.subsec_nanos() ^ now.subsec_nanos())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::prelude::Key;
+    use crate::window::prelude::WindowEvent;
+
+    #[test]
+    fn replay() {
+        let name = format!("crayon-replay-test-{}.rpl", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let pressed = Event::InputDevice(InputEvent::KeyboardPressed { key: Key::A });
+        let moved = Event::InputDevice(InputEvent::MouseMoved {
+            position: (1.0, 2.0),
+        });
+
+        let seed = {
+            let replay = ReplaySystem::new(Some(&Replay::Record(path.clone()))).unwrap();
+            let ms = Duration::from_millis;
+            assert_eq!(replay.advance(ms(16)).unwrap(), ms(16));
+            replay.process(&mut vec![pressed, Event::Window(WindowEvent::GainFocus)]);
+            assert_eq!(replay.advance(ms(17)).unwrap(), ms(17));
+            replay.process(&mut vec![moved]);
+            replay.seed()
+        };
+
+        let replay = ReplaySystem::new(Some(&Replay::Playback(path.clone()))).unwrap();
+        assert_eq!(replay.seed(), seed);
+        assert!(replay.is_playing());
+
+        let mut events = vec![moved, Event::Window(WindowEvent::LostFocus)];
+        let timestep = replay.advance(Duration::from_millis(33)).unwrap();
+        replay.process(&mut events);
+        assert_eq!(timestep, Duration::from_millis(16));
+        assert_eq!(events.len(), 2);
+
+        match (events[0], events[1]) {
+            (
+                Event::Window(WindowEvent::LostFocus),
+                Event::InputDevice(InputEvent::KeyboardPressed { key: Key::A }),
+            ) => {}
+            _ => panic!("unexpected events {:?}.", events),
+        }
+
+        let timestep = replay.advance(Duration::from_millis(33)).unwrap();
+        assert_eq!(timestep, Duration::from_millis(17));
+
+        let timestep = replay.advance(Duration::from_millis(33)).unwrap();
+        assert_eq!(timestep, Duration::from_millis(33));
+        assert!(!replay.is_playing());
+
+        let _ = fs::remove_file(path);
+    }
+}
@@ -1,3 +1,6 @@
+use std::sync::Once;
+use std::time::Instant;
+
 use crate::utils::time::Timestamp;
 
 pub fn timestamp() -> Timestamp {
@@ -9,6 +12,18 @@ pub fn timestamp() -> Timestamp {
     Timestamp::from_millis(ms)
 }
 
+static EPOCH_INIT: Once = Once::new();
+static mut EPOCH: Option<Instant> = None;
+
+/// Gets the time since the first call with the precision of platform. It's monotonic, so
+/// the jumps of wall clock never make it go backwards.
+pub fn precise_timestamp() -> std::time::Duration {
+    unsafe {
+        EPOCH_INIT.call_once(|| EPOCH = Some(Instant::now()));
+        EPOCH.unwrap().elapsed()
+    }
+}
+
 pub(crate) fn init() {}
 
 pub(crate) fn run_forever<F, F2>(mut advance: F, mut finished: F2) -> Result<(), failure::Error>
@@ -16,10 +16,19 @@ pub fn timestamp() -> Timestamp {
     Timestamp::from_millis(ms as u64)
 }
 
+/// Gets the time since the time origin of page with the precision of platform.
+pub fn precise_timestamp() -> std::time::Duration {
+    let ms = web_sys::window()
+        .expect("should have a window in this context")
+        .performance()
+        .expect("performance should be available")
+        .now();
+
+    std::time::Duration::from_micros((ms * 1000.0) as u64)
+}
+
 pub(crate) fn init() {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-    log::set_boxed_logger(Box::new(WebBrowserLogger {})).unwrap();
-    log::set_max_level(log::LevelFilter::Info);
 }
 
 pub(crate) fn run_forever<F, F2>(mut advance: F, mut finished: F2) -> Result<(), failure::Error>
@@ -52,33 +61,3 @@ where
 
     Ok(())
 }
-
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console)]
-    fn log(s: &str);
-}
-
-struct WebBrowserLogger;
-
-impl ::log::Log for WebBrowserLogger {
-    fn enabled(&self, metadata: &::log::Metadata) -> bool {
-        metadata.level() <= ::log::Level::Info
-    }
-
-    fn log(&self, record: &::log::Record) {
-        if self.enabled(record.metadata()) {
-            let filename = record.file().unwrap_or("Unknown");
-
-            log(&format!(
-                "{}: {} ({}:{})",
-                record.level(),
-                record.args(),
-                filename,
-                record.line().unwrap_or(0)
-            ));
-        }
-    }
-
-    fn flush(&self) {}
-}
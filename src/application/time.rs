@@ -6,9 +6,8 @@ use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use crate::application::{LifecycleListener, LifecycleListenerHandle};
-use crate::utils::time::Timestamp;
 
-use super::Params;
+use super::{Params, SleepStrategy};
 
 pub struct TimeSystem {
     lis: LifecycleListenerHandle,
@@ -18,7 +17,9 @@ pub struct TimeSystem {
 struct TimeStateShared {
     min_fps: RwLock<u32>,
     max_fps: RwLock<u32>,
+    sleep_strategy: RwLock<SleepStrategy>,
     max_inactive_fps: RwLock<u32>,
+    active: RwLock<bool>,
     smoothing_step: RwLock<usize>,
     timestep: RwLock<Duration>,
 }
@@ -26,11 +27,15 @@ struct TimeStateShared {
 struct TimeState {
     min_fps: u32,
     max_fps: u32,
+    sleep_strategy: SleepStrategy,
     max_inactive_fps: u32,
+    active: bool,
     smoothing_step: usize,
     timestep: Duration,
     previous_timesteps: VecDeque<Duration>,
-    last_frame_timepoint: Timestamp,
+    last_frame_timepoint: Duration,
+    // The time when the current frame should start if fps is limited.
+    next_frame_timepoint: Duration,
     shared: Arc<TimeStateShared>,
 }
 
@@ -39,24 +44,55 @@ impl LifecycleListener for TimeState {
         // Synchonize with configurations.
         self.min_fps = *self.shared.min_fps.read().unwrap();
         self.max_fps = *self.shared.max_fps.read().unwrap();
+        self.sleep_strategy = *self.shared.sleep_strategy.read().unwrap();
         self.max_inactive_fps = *self.shared.max_inactive_fps.read().unwrap();
+        self.active = *self.shared.active.read().unwrap();
         self.smoothing_step = *self.shared.smoothing_step.read().unwrap();
 
         // Perform waiting loop if maximum fps set, cooperatively gives up
         // a timeslice to the OS scheduler.
-        if self.max_fps > 0 {
-            let td = Duration::from_millis(u64::from(1000 / self.max_fps));
-            while self.last_frame_timepoint.elapsed() <= td {
-                if (self.last_frame_timepoint.elapsed() + Duration::from_millis(2)) < td {
-                    std::thread::sleep(Duration::from_millis(1));
-                } else {
-                    std::thread::yield_now();
+        let mut now = crate::application::sys::precise_timestamp();
+        let max_fps = if !self.active && self.max_inactive_fps > 0 {
+            self.max_inactive_fps
+        } else {
+            self.max_fps
+        };
+
+        if max_fps > 0 {
+            let td = Duration::from_nanos(1_000_000_000 / u64::from(max_fps));
+
+            // The deadlines are accumulated to keep the average frame rate exact, unless
+            // the frames fall behind too much.
+            self.next_frame_timepoint += td;
+            if now > self.next_frame_timepoint + td {
+                self.next_frame_timepoint = now;
+            }
+
+            while now < self.next_frame_timepoint {
+                let remains = self.next_frame_timepoint - now;
+                match self.sleep_strategy {
+                    SleepStrategy::Sleep => std::thread::sleep(remains),
+                    SleepStrategy::Spin => std::thread::yield_now(),
+                    SleepStrategy::Hybrid(spin) => {
+                        if remains > spin {
+                            std::thread::sleep(remains - spin);
+                        } else {
+                            std::thread::yield_now();
+                        }
+                    }
                 }
+
+                now = crate::application::sys::precise_timestamp();
             }
+        } else {
+            self.next_frame_timepoint = now;
         }
 
-        let mut elapsed = self.last_frame_timepoint.elapsed();
-        self.last_frame_timepoint = Timestamp::now();
+        // The timestamps are monotonic, but the first frame could start before the setup.
+        let mut elapsed = now
+            .checked_sub(self.last_frame_timepoint)
+            .unwrap_or_default();
+        self.last_frame_timepoint = now;
 
         // If fps lower than minimum, simply clamp it.
         if self.min_fps > 0 {
@@ -84,6 +120,9 @@ impl LifecycleListener for TimeState {
             self.timestep = elapsed;
         }
 
+        // The recorded timesteps are used when playing back the inputs.
+        self.timestep = super::inside::replay_ctx().advance(self.timestep)?;
+
         *self.shared.timestep.write().unwrap() = self.timestep;
         Ok(())
     }
@@ -100,19 +139,25 @@ impl TimeSystem {
         let shared = Arc::new(TimeStateShared {
             min_fps: RwLock::new(setup.min_fps),
             max_fps: RwLock::new(setup.max_fps),
+            sleep_strategy: RwLock::new(setup.sleep_strategy),
             max_inactive_fps: RwLock::new(setup.max_inactive_fps),
+            active: RwLock::new(true),
             smoothing_step: RwLock::new(setup.time_smooth_step as usize),
             timestep: RwLock::new(Duration::new(0, 0)),
         });
 
+        let now = crate::application::sys::precise_timestamp();
         let state = TimeState {
             min_fps: setup.min_fps,
             max_fps: setup.max_fps,
+            sleep_strategy: setup.sleep_strategy,
             max_inactive_fps: setup.max_inactive_fps,
+            active: true,
             smoothing_step: setup.time_smooth_step as usize,
             previous_timesteps: VecDeque::new(),
             timestep: Duration::new(0, 0),
-            last_frame_timepoint: Timestamp::now(),
+            last_frame_timepoint: now,
+            next_frame_timepoint: now,
             shared: shared.clone(),
         };
 
@@ -137,6 +182,12 @@ impl TimeSystem {
         *self.shared.max_fps.write().unwrap() = fps;
     }
 
+    /// Set how to wait for the next frame if fps is higher than the maximum.
+    #[inline]
+    pub fn set_sleep_strategy(&self, strategy: SleepStrategy) {
+        *self.shared.sleep_strategy.write().unwrap() = strategy;
+    }
+
     /// Set maximum frames per second when the application does not have input
     /// focus.
     #[inline]
@@ -144,6 +195,12 @@ impl TimeSystem {
         *self.shared.max_inactive_fps.write().unwrap() = fps;
     }
 
+    /// Set whether the application is in foreground and has input focus.
+    #[inline]
+    pub fn set_active(&self, active: bool) {
+        *self.shared.active.write().unwrap() = active;
+    }
+
     /// Set how many frames to average for timestep smoothing.
     #[inline]
     pub fn set_time_smoothing_step(&self, step: u32) {
@@ -0,0 +1,129 @@
+//! Maps the named actions to keys, mouse buttons and gamepad buttons.
+
+use crate::utils::hash::FastHashMap;
+
+use super::gamepad::GamepadButton;
+use super::keyboard::Key;
+use super::mouse::MouseButton;
+
+/// The physical input that an action could be bound to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+    /// The button of any connected gamepad.
+    Gamepad(GamepadButton),
+}
+
+impl From<Key> for Binding {
+    fn from(key: Key) -> Self {
+        Binding::Key(key)
+    }
+}
+
+impl From<MouseButton> for Binding {
+    fn from(button: MouseButton) -> Self {
+        Binding::Mouse(button)
+    }
+}
+
+impl From<GamepadButton> for Binding {
+    fn from(button: GamepadButton) -> Self {
+        Binding::Gamepad(button)
+    }
+}
+
+/// The bindings of named actions, e.g. `jump` to the space key and the left mouse button.
+/// The game logic checks the actions instead of specific keys, so the bindings could be
+/// configured by players and saved with serde.
+///
+/// ```rust
+/// use crayon::prelude::*;
+/// use crayon::input::actions::ActionMap;
+///
+/// let mut actions = ActionMap::new();
+/// actions.bind("jump", Key::Space);
+/// actions.bind("jump", MouseButton::Left);
+/// assert_eq!(actions.bindings("jump").len(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActionMap {
+    actions: FastHashMap<String, Vec<Binding>>,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Binds the action to a key or button, an action could have multiple bindings.
+    pub fn bind<T1, T2>(&mut self, action: T1, binding: T2)
+    where
+        T1: Into<String>,
+        T2: Into<Binding>,
+    {
+        let binding = binding.into();
+        let bindings = self.actions.entry(action.into()).or_insert_with(Vec::new);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes a binding of the action.
+    pub fn unbind<T: Into<Binding>>(&mut self, action: &str, binding: T) {
+        let binding = binding.into();
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|&v| v != binding);
+        }
+    }
+
+    /// Removes the action and all of its bindings.
+    pub fn remove(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    /// Gets the bindings of the action.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map(|v| &v[..]).unwrap_or(&[])
+    }
+
+    /// Returns an iterator over the names of actions.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(|v| v.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bindings() {
+        let mut actions = ActionMap::new();
+        actions.bind("jump", Key::Space);
+        actions.bind("jump", Key::Space);
+        actions.bind("jump", MouseButton::Other(4));
+        actions.bind("fire", MouseButton::Left);
+
+        assert_eq!(
+            actions.bindings("jump"),
+            &[
+                Binding::Key(Key::Space),
+                Binding::Mouse(MouseButton::Other(4))
+            ]
+        );
+
+        let json = serde_json::to_string(&actions).unwrap();
+        assert_eq!(serde_json::from_str::<ActionMap>(&json).unwrap(), actions);
+
+        actions.unbind("jump", Key::Space);
+        assert_eq!(
+            actions.bindings("jump"),
+            &[Binding::Mouse(MouseButton::Other(4))]
+        );
+
+        actions.remove("fire");
+        assert!(actions.bindings("fire").is_empty());
+        assert_eq!(actions.actions().collect::<Vec<_>>(), vec!["jump"]);
+    }
+}
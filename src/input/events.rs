@@ -1,11 +1,12 @@
 use crate::math::prelude::Vector2;
 
+use super::gamepad::{GamepadAxis, GamepadButton};
 use super::keyboard::Key;
 use super::mouse::MouseButton;
 use super::touchpad::TouchState;
 
-/// Input device event, supports mouse and keyboard only.
-#[derive(Debug, Clone, Copy)]
+/// Input device event.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum InputEvent {
     /// The cursor has moved on the window.
     /// The parameter are the (x, y) coords in pixels relative to the bottom-left
@@ -34,9 +35,28 @@ pub enum InputEvent {
     /// There may be 0 or more Move events.
     ///
     /// Depending on platform implementation id may or may not be reused by system after End event.
+    ///
+    /// The position is in points relative to the bottom-left corner of the window, and the
+    /// pressure is in [0, 1], which is 1.0 if the device could not detect it.
     Touch {
         id: u8,
         state: TouchState,
         position: Vector2<f32>,
+        pressure: f32,
+    },
+
+    /// A gamepad has been plugged in.
+    GamepadConnected { id: u8 },
+    /// A gamepad has been unplugged, the id might be reused by the next connected one.
+    GamepadDisconnected { id: u8 },
+    /// Pressed event on gamepad has been received.
+    GamepadPressed { id: u8, button: GamepadButton },
+    /// Released event from gamepad has been received.
+    GamepadReleased { id: u8, button: GamepadButton },
+    /// The value of gamepad axis has changed, without dead zone applied.
+    GamepadAxisChanged {
+        id: u8,
+        axis: GamepadAxis,
+        value: f32,
     },
 }
@@ -0,0 +1,212 @@
+use crate::utils::hash::{FastHashMap, FastHashSet};
+
+/// The setup parameters of gamepad devices.
+#[derive(Debug, Clone, Copy)]
+pub struct GamepadParams {
+    /// The values of axes whose absolute values are less than `dead_zone` are reported as
+    /// zero, the others are rescaled into [-1, 1]. It filters the drifts of sticks around
+    /// the center.
+    pub dead_zone: f32,
+}
+
+impl Default for GamepadParams {
+    fn default() -> Self {
+        GamepadParams { dead_zone: 0.15 }
+    }
+}
+
+/// Describes a button of gamepad in the standard layout. The face buttons are named by
+/// their positions, e.g. `South` is the `A` button of Xbox controllers and the `Cross`
+/// button of PlayStation controllers.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    /// The button of pressing left stick.
+    LeftStick,
+    /// The button of pressing right stick.
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    /// The button in the center, e.g. the `Xbox` or `PS` button.
+    Mode,
+    Other(u8),
+}
+
+/// Describes an axis of gamepad in the standard layout. The values are in [-1, 1], and
+/// the positive directions are right and up.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    Other(u8),
+}
+
+#[derive(Default)]
+struct GamepadState {
+    downs: FastHashSet<GamepadButton>,
+    presses: FastHashSet<GamepadButton>,
+    releases: FastHashSet<GamepadButton>,
+    axes: FastHashMap<GamepadAxis, f32>,
+}
+
+pub struct Gamepads {
+    devices: FastHashMap<u8, GamepadState>,
+    params: GamepadParams,
+}
+
+impl Gamepads {
+    pub fn new(params: GamepadParams) -> Self {
+        Gamepads {
+            devices: FastHashMap::default(),
+            params,
+        }
+    }
+
+    #[inline]
+    pub fn reset(&mut self) {
+        for v in self.devices.values_mut() {
+            v.downs.clear();
+            v.presses.clear();
+            v.releases.clear();
+            v.axes.clear();
+        }
+    }
+
+    #[inline]
+    pub fn advance(&mut self) {
+        for v in self.devices.values_mut() {
+            v.presses.clear();
+            v.releases.clear();
+        }
+    }
+
+    #[inline]
+    pub fn on_connected(&mut self, id: u8) {
+        self.devices.insert(id, GamepadState::default());
+    }
+
+    #[inline]
+    pub fn on_disconnected(&mut self, id: u8) {
+        self.devices.remove(&id);
+    }
+
+    #[inline]
+    pub fn on_button_pressed(&mut self, id: u8, button: GamepadButton) {
+        let v = self.devices.entry(id).or_insert_with(GamepadState::default);
+        if !v.downs.contains(&button) {
+            v.downs.insert(button);
+            v.presses.insert(button);
+        }
+    }
+
+    #[inline]
+    pub fn on_button_released(&mut self, id: u8, button: GamepadButton) {
+        let v = self.devices.entry(id).or_insert_with(GamepadState::default);
+        if v.downs.remove(&button) {
+            v.releases.insert(button);
+        }
+    }
+
+    #[inline]
+    pub fn on_axis_changed(&mut self, id: u8, axis: GamepadAxis, value: f32) {
+        let v = self.devices.entry(id).or_insert_with(GamepadState::default);
+        v.axes.insert(axis, value);
+    }
+
+    /// Returns the ids of connected gamepads in ascending order.
+    pub fn gamepads(&self) -> Vec<u8> {
+        let mut ids: Vec<_> = self.devices.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    #[inline]
+    pub fn is_connected(&self, id: u8) -> bool {
+        self.devices.contains_key(&id)
+    }
+
+    #[inline]
+    pub fn is_button_down(&self, id: u8, button: GamepadButton) -> bool {
+        self.devices
+            .get(&id)
+            .map(|v| v.downs.contains(&button))
+            .unwrap_or(false)
+    }
+
+    #[inline]
+    pub fn is_button_press(&self, id: u8, button: GamepadButton) -> bool {
+        self.devices
+            .get(&id)
+            .map(|v| v.presses.contains(&button))
+            .unwrap_or(false)
+    }
+
+    #[inline]
+    pub fn is_button_release(&self, id: u8, button: GamepadButton) -> bool {
+        self.devices
+            .get(&id)
+            .map(|v| v.releases.contains(&button))
+            .unwrap_or(false)
+    }
+
+    /// Gets the value of axis with dead zone applied.
+    pub fn axis(&self, id: u8, axis: GamepadAxis) -> f32 {
+        let value = self
+            .devices
+            .get(&id)
+            .and_then(|v| v.axes.get(&axis).cloned())
+            .unwrap_or(0.0);
+
+        let dead_zone = self.params.dead_zone.max(0.0).min(0.99);
+        if value.abs() <= dead_zone {
+            0.0
+        } else {
+            let scaled = (value.abs() - dead_zone) / (1.0 - dead_zone);
+            scaled.min(1.0) * value.signum()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dead_zone() {
+        let mut gamepads = Gamepads::new(GamepadParams { dead_zone: 0.2 });
+        gamepads.on_connected(1);
+        assert_eq!(gamepads.gamepads(), vec![1]);
+
+        gamepads.on_axis_changed(1, GamepadAxis::LeftStickX, 0.1);
+        assert_eq!(gamepads.axis(1, GamepadAxis::LeftStickX), 0.0);
+
+        gamepads.on_axis_changed(1, GamepadAxis::LeftStickX, -0.6);
+        assert!((gamepads.axis(1, GamepadAxis::LeftStickX) + 0.5).abs() < 1e-5);
+
+        gamepads.on_axis_changed(1, GamepadAxis::LeftStickX, 1.0);
+        assert_eq!(gamepads.axis(1, GamepadAxis::LeftStickX), 1.0);
+
+        gamepads.on_button_pressed(1, GamepadButton::South);
+        assert!(gamepads.is_button_press(1, GamepadButton::South));
+        gamepads.advance();
+        assert!(!gamepads.is_button_press(1, GamepadButton::South));
+        assert!(gamepads.is_button_down(1, GamepadButton::South));
+
+        gamepads.on_disconnected(1);
+        assert!(!gamepads.is_connected(1));
+        assert_eq!(gamepads.axis(1, GamepadAxis::LeftStickX), 0.0);
+    }
+}
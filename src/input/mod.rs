@@ -44,6 +44,9 @@
 //! input::text();
 //! ```
 //!
+//! The text includes the characters composed with input methods (IME), which are received
+//! once they have been committed.
+//!
 //! # Mouse Inputs
 //!
 //! Similar to keyboard device, to find out whether the host platform provides mouse
@@ -109,11 +112,30 @@
 //!
 //! Notes we also have APIs with `_in_points` suffix to works in logical points.
 //!
+//! # Actions
+//!
+//! Instead of checking specific keys, the game logic could check the named actions. Each
+//! action is bound to keys or mouse buttons with `ActionMap`, which could be serialized into
+//! the configuration files of players.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! let mut actions = ActionMap::new();
+//! actions.bind("jump", Key::Space);
+//! actions.bind("jump", MouseButton::Left);
+//! input::set_actions(actions);
+//!
+//! // Checks if any binding of the action has been pressed during last frame.
+//! input::is_action_press("jump");
+//! ```
+//!
 //! # `TouchPad` Inputs
 //!
 //! The touch input functions provides access to basic touch- and multi-touch-input,
-//! and is currently only implemented on mobile platforms and not for notebook
-//! touchpads. You can get the touch informations by the finger index, which is
+//! and is currently only implemented on mobile platforms and touch screens in browsers,
+//! not for notebook touchpads. You can get the touch informations by the finger index, which is
 //! ordered by the first touch time.
 //!
 //! ```rust
@@ -155,28 +177,67 @@
 //!     GesturePan::End { start_position, position } => { },
 //!     GesturePan::None => { },
 //! }
+//!
+//! // Gets the pinching gesture.
+//! match input::finger_pinch() {
+//!     GesturePinch::Start { center } => { },
+//!     GesturePinch::Move { center, scale, movement } => { },
+//!     GesturePinch::End { center, scale } => { },
+//!     GesturePinch::None => { },
+//! }
 //! ```
 //!
 //! Notes we also have APIs with `_in_points` suffix to works in logical points.
 //!
+//! # Gamepad Inputs
+//!
+//! The gamepads are identified by ids, and the buttons and axes are named after the
+//! standard layout. The `InputEvent::GamepadConnected` and `GamepadDisconnected` events
+//! are sent once they have been plugged in or unplugged.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! for id in input::gamepads() {
+//!     // Checks if a button of gamepad has been pressed during last frame.
+//!     input::is_gamepad_button_press(id, GamepadButton::South);
+//!
+//!     // Gets the value of axis, the small values are filtered by `GamepadParams::dead_zone`.
+//!     input::gamepad_axis(id, GamepadAxis::LeftStickX);
+//! }
+//! ```
+//!
+//! The buttons of gamepads could be bound to actions too, with `Binding::Gamepad`.
+//!
+//! # Platform-specific
+//!
+//! The gamepads are supported in browsers with the Gamepad API, and on Linux with the
+//! joystick devices `/dev/input/js*`, whose layout is assumed to be the one of Xbox
+//! controllers. The rumble is only supported in browsers, and the gamepads are not
+//! supported on the other desktop platforms yet.
+//!
 //! # Others Inputs
 //!
 //! Somethings that nice to have, but not implemented right now:
 //!
 //! 1. Device sensor inputs;
-//! 2. Game pad inputs;
-//! 3. More touch gesture like `Pinching`.
+//! 2. More touch gesture like `Rotating`.
 
+pub mod actions;
 pub mod events;
+pub mod gamepad;
 pub mod keyboard;
 pub mod mouse;
 pub mod touchpad;
 
 pub mod prelude {
+    pub use super::actions::{ActionMap, Binding};
     pub use super::events::InputEvent;
+    pub use super::gamepad::{GamepadAxis, GamepadButton, GamepadParams};
     pub use super::keyboard::{Key, KeyboardParams};
     pub use super::mouse::{MouseButton, MouseParams};
-    pub use super::touchpad::{GesturePan, GestureTap, TouchPadParams};
+    pub use super::touchpad::{GesturePan, GesturePinch, GestureTap, TouchPadParams};
     pub use super::InputParams;
 }
 
@@ -185,12 +246,17 @@ mod system;
 /// Maximum touches that would be tracked at sametime.
 pub const MAX_TOUCHES: usize = 4;
 
+use std::time::Duration;
+
+use crate::errors::*;
 use crate::math::prelude::Vector2;
 
+use self::actions::ActionMap;
+use self::gamepad::{GamepadAxis, GamepadButton, GamepadParams};
 use self::inside::{ctx, CTX};
 use self::keyboard::{Key, KeyboardParams};
 use self::mouse::{MouseButton, MouseParams};
-use self::touchpad::{GesturePan, GestureTap, TouchPadParams};
+use self::touchpad::{GesturePan, GesturePinch, GestureTap, TouchPadParams};
 
 /// The setup parameters of all supported input devices.
 #[derive(Debug, Clone, Copy, Default)]
@@ -199,6 +265,7 @@ pub struct InputParams {
     pub keyboard: KeyboardParams,
     pub mouse: MouseParams,
     pub touchpad: TouchPadParams,
+    pub gamepad: GamepadParams,
 }
 
 /// Checks if the resource system is enabled.
@@ -303,6 +370,80 @@ pub fn mouse_scroll() -> Vector2<f32> {
     ctx().mouse_scroll()
 }
 
+/// Returns the ids of connected gamepads.
+#[inline]
+pub fn gamepads() -> Vec<u8> {
+    ctx().gamepads()
+}
+
+/// Checks if the gamepad is connected.
+#[inline]
+pub fn is_gamepad_connected(id: u8) -> bool {
+    ctx().is_gamepad_connected(id)
+}
+
+/// Checks if a button of gamepad is held down.
+#[inline]
+pub fn is_gamepad_button_down(id: u8, button: GamepadButton) -> bool {
+    ctx().is_gamepad_button_down(id, button)
+}
+
+/// Checks if a button of gamepad has been pressed during last frame.
+#[inline]
+pub fn is_gamepad_button_press(id: u8, button: GamepadButton) -> bool {
+    ctx().is_gamepad_button_press(id, button)
+}
+
+/// Checks if a button of gamepad has been released during last frame.
+#[inline]
+pub fn is_gamepad_button_release(id: u8, button: GamepadButton) -> bool {
+    ctx().is_gamepad_button_release(id, button)
+}
+
+/// Gets the value of gamepad axis in [-1, 1], with dead zone applied.
+#[inline]
+pub fn gamepad_axis(id: u8, axis: GamepadAxis) -> f32 {
+    ctx().gamepad_axis(id, axis)
+}
+
+/// Vibrates the gamepad with `strength` in [0, 1] for `duration`. Returns error if the
+/// gamepad does not have rumble motors, or the platform does not support it.
+#[inline]
+pub fn rumble_gamepad(id: u8, strength: f32, duration: Duration) -> Result<()> {
+    ctx().rumble_gamepad(id, strength, duration)
+}
+
+/// Sets the bindings of named actions.
+#[inline]
+pub fn set_actions(actions: ActionMap) {
+    ctx().set_actions(actions);
+}
+
+/// Gets the bindings of named actions.
+#[inline]
+pub fn actions() -> ActionMap {
+    ctx().actions()
+}
+
+/// Checks if any binding of the action is held down.
+#[inline]
+pub fn is_action_down(action: &str) -> bool {
+    ctx().is_action_down(action)
+}
+
+/// Checks if any binding of the action has been pressed during last frame.
+#[inline]
+pub fn is_action_press(action: &str) -> bool {
+    ctx().is_action_press(action)
+}
+
+/// Checks if a binding of the action has been released during last frame, and none of
+/// the others are held down.
+#[inline]
+pub fn is_action_release(action: &str) -> bool {
+    ctx().is_action_release(action)
+}
+
 /// Returns true if a touchpad is attached
 #[inline]
 pub fn has_touchpad_attached() -> bool {
@@ -321,6 +462,13 @@ pub fn finger_position(n: usize) -> Option<Vector2<f32>> {
     ctx().finger_position(n)
 }
 
+/// Gets the pressure of the `n`th touched finger in [0, 1]. It is always 1.0 if the device
+/// could not detect the pressure.
+#[inline]
+pub fn finger_pressure(n: usize) -> Option<f32> {
+    ctx().finger_pressure(n)
+}
+
 /// Gets the tap gesture.
 #[inline]
 pub fn finger_tap() -> GestureTap {
@@ -339,6 +487,12 @@ pub fn finger_pan() -> GesturePan {
     ctx().finger_pan()
 }
 
+/// Gets the pinching gesture of two fingers.
+#[inline]
+pub fn finger_pinch() -> GesturePinch {
+    ctx().finger_pinch()
+}
+
 pub(crate) mod inside {
     use super::system::InputSystem;
     use super::InputParams;
@@ -1,12 +1,15 @@
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use crate::application::prelude::{LifecycleListener, LifecycleListenerHandle};
 use crate::window::prelude::{Event, EventListener, EventListenerHandle};
 
+use super::actions::{ActionMap, Binding};
 use super::events::InputEvent;
+use super::gamepad::{GamepadAxis, GamepadButton, Gamepads};
 use super::keyboard::{Key, Keyboard};
 use super::mouse::{Mouse, MouseButton};
-use super::touchpad::{GesturePan, GestureTap, TouchPad, TouchState};
+use super::touchpad::{GesturePan, GesturePinch, GestureTap, TouchPad, TouchState};
 use super::InputParams;
 
 use crate::math::prelude::Vector2;
@@ -25,6 +28,8 @@ struct InputState {
     mouse: RwLock<Mouse>,
     keyboard: RwLock<Keyboard>,
     touchpad: RwLock<TouchPad>,
+    gamepads: RwLock<Gamepads>,
+    actions: RwLock<ActionMap>,
 }
 
 impl EventListener for Arc<InputState> {
@@ -37,6 +42,7 @@ impl EventListener for Arc<InputState> {
                             255,
                             TouchState::Move,
                             self.mouse.read().unwrap().position(),
+                            1.0,
                         );
                     }
 
@@ -50,6 +56,7 @@ impl EventListener for Arc<InputState> {
                             255,
                             TouchState::Start,
                             self.mouse.read().unwrap().position(),
+                            1.0,
                         );
                     }
 
@@ -64,6 +71,7 @@ impl EventListener for Arc<InputState> {
                             255,
                             TouchState::End,
                             self.mouse.read().unwrap().position(),
+                            1.0,
                         );
                     }
 
@@ -90,9 +98,37 @@ impl EventListener for Arc<InputState> {
                     id,
                     state,
                     position,
+                    pressure,
                 } => {
-                    self.touchpad.write().unwrap().on_touch(id, state, position);
+                    self.touchpad
+                        .write()
+                        .unwrap()
+                        .on_touch(id, state, position, pressure);
                 }
+
+                InputEvent::GamepadConnected { id } => {
+                    self.gamepads.write().unwrap().on_connected(id)
+                }
+
+                InputEvent::GamepadDisconnected { id } => {
+                    self.gamepads.write().unwrap().on_disconnected(id)
+                }
+
+                InputEvent::GamepadPressed { id, button } => {
+                    self.gamepads.write().unwrap().on_button_pressed(id, button)
+                }
+
+                InputEvent::GamepadReleased { id, button } => self
+                    .gamepads
+                    .write()
+                    .unwrap()
+                    .on_button_released(id, button),
+
+                InputEvent::GamepadAxisChanged { id, axis, value } => self
+                    .gamepads
+                    .write()
+                    .unwrap()
+                    .on_axis_changed(id, axis, value),
             }
         }
 
@@ -105,6 +141,7 @@ impl LifecycleListener for Arc<InputState> {
         self.mouse.write().unwrap().advance();
         self.keyboard.write().unwrap().advance();
         self.touchpad.write().unwrap().advance();
+        self.gamepads.write().unwrap().advance();
         Ok(())
     }
 }
@@ -126,6 +163,8 @@ impl InputSystem {
             mouse: RwLock::new(Mouse::new(setup.mouse)),
             keyboard: RwLock::new(Keyboard::new(setup.keyboard)),
             touchpad: RwLock::new(TouchPad::new(setup.touchpad)),
+            gamepads: RwLock::new(Gamepads::new(setup.gamepad)),
+            actions: RwLock::new(ActionMap::new()),
         });
 
         InputSystem {
@@ -140,6 +179,7 @@ impl InputSystem {
         self.state.mouse.write().unwrap().reset();
         self.state.keyboard.write().unwrap().reset();
         self.state.touchpad.write().unwrap().reset();
+        self.state.gamepads.write().unwrap().reset();
 
         *self.state.touch_emulation_button.write().unwrap() = None;
     }
@@ -241,6 +281,126 @@ impl InputSystem {
         self.state.mouse.read().unwrap().scroll()
     }
 
+    /// Returns the ids of connected gamepads.
+    #[inline]
+    pub fn gamepads(&self) -> Vec<u8> {
+        self.state.gamepads.read().unwrap().gamepads()
+    }
+
+    /// Checks if the gamepad is connected.
+    #[inline]
+    pub fn is_gamepad_connected(&self, id: u8) -> bool {
+        self.state.gamepads.read().unwrap().is_connected(id)
+    }
+
+    /// Checks if a button of gamepad is held down.
+    #[inline]
+    pub fn is_gamepad_button_down(&self, id: u8, button: GamepadButton) -> bool {
+        self.state
+            .gamepads
+            .read()
+            .unwrap()
+            .is_button_down(id, button)
+    }
+
+    /// Checks if a button of gamepad has been pressed during last frame.
+    #[inline]
+    pub fn is_gamepad_button_press(&self, id: u8, button: GamepadButton) -> bool {
+        self.state
+            .gamepads
+            .read()
+            .unwrap()
+            .is_button_press(id, button)
+    }
+
+    /// Checks if a button of gamepad has been released during last frame.
+    #[inline]
+    pub fn is_gamepad_button_release(&self, id: u8, button: GamepadButton) -> bool {
+        self.state
+            .gamepads
+            .read()
+            .unwrap()
+            .is_button_release(id, button)
+    }
+
+    /// Gets the value of gamepad axis, with dead zone applied.
+    #[inline]
+    pub fn gamepad_axis(&self, id: u8, axis: GamepadAxis) -> f32 {
+        self.state.gamepads.read().unwrap().axis(id, axis)
+    }
+
+    /// Vibrates the gamepad with `strength` for `duration`.
+    pub fn rumble_gamepad(
+        &self,
+        id: u8,
+        strength: f32,
+        duration: Duration,
+    ) -> Result<(), failure::Error> {
+        if !self.is_gamepad_connected(id) {
+            bail!("The gamepad {} is not connected.", id);
+        }
+
+        let strength = strength.max(0.0).min(1.0);
+        crate::window::inside::ctx().rumble_gamepad(id, strength, duration)
+    }
+
+    /// Sets the bindings of named actions.
+    #[inline]
+    pub fn set_actions(&self, actions: ActionMap) {
+        *self.state.actions.write().unwrap() = actions;
+    }
+
+    /// Gets the bindings of named actions.
+    #[inline]
+    pub fn actions(&self) -> ActionMap {
+        self.state.actions.read().unwrap().clone()
+    }
+
+    /// Checks if any binding of the action is held down.
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.any_binding(action, |v| match v {
+            Binding::Key(key) => self.is_key_down(key),
+            Binding::Mouse(button) => self.is_mouse_down(button),
+            Binding::Gamepad(button) => {
+                self.any_gamepad(|id| self.is_gamepad_button_down(id, button))
+            }
+        })
+    }
+
+    /// Checks if any binding of the action has been pressed during last frame.
+    pub fn is_action_press(&self, action: &str) -> bool {
+        self.any_binding(action, |v| match v {
+            Binding::Key(key) => self.is_key_press(key),
+            Binding::Mouse(button) => self.is_mouse_press(button),
+            Binding::Gamepad(button) => {
+                self.any_gamepad(|id| self.is_gamepad_button_press(id, button))
+            }
+        })
+    }
+
+    /// Checks if a binding of the action has been released during last frame, and none of
+    /// the others are held down.
+    pub fn is_action_release(&self, action: &str) -> bool {
+        let released = self.any_binding(action, |v| match v {
+            Binding::Key(key) => self.is_key_release(key),
+            Binding::Mouse(button) => self.is_mouse_release(button),
+            Binding::Gamepad(button) => {
+                self.any_gamepad(|id| self.is_gamepad_button_release(id, button))
+            }
+        });
+
+        released && !self.is_action_down(action)
+    }
+
+    fn any_gamepad<T: Fn(u8) -> bool>(&self, f: T) -> bool {
+        self.gamepads().into_iter().any(f)
+    }
+
+    fn any_binding<T: Fn(Binding) -> bool>(&self, action: &str, f: T) -> bool {
+        let actions = self.state.actions.read().unwrap();
+        actions.bindings(action).iter().any(|&v| f(v))
+    }
+
     /// Returns true if a touchpad is attached
     #[inline]
     pub fn has_touchpad_attached(&self) -> bool {
@@ -260,6 +420,12 @@ impl InputSystem {
         self.state.touchpad.read().unwrap().position(n)
     }
 
+    /// Gets the pressure of the `n`th touched finger.
+    #[inline]
+    pub fn finger_pressure(&self, n: usize) -> Option<f32> {
+        self.state.touchpad.read().unwrap().pressure(n)
+    }
+
     /// Gets the tap gesture.
     #[inline]
     pub fn finger_tap(&self) -> GestureTap {
@@ -277,4 +443,10 @@ impl InputSystem {
     pub fn finger_pan(&self) -> GesturePan {
         self.state.touchpad.read().unwrap().pan()
     }
+
+    /// Gets the pinching gesture.
+    #[inline]
+    pub fn finger_pinch(&self) -> GesturePinch {
+        self.state.touchpad.read().unwrap().pinch()
+    }
 }
@@ -21,6 +21,9 @@ pub struct TouchPadParams {
     pub touch_timeout: Duration,
     /// The minimum distance before a touch the touch pressing and releasing.
     pub max_touch_distance: f32,
+    /// The minimum change of the distance between two fingers before they are recognized
+    /// as pinching.
+    pub min_pinch_distance: f32,
 }
 
 impl Default for TouchPadParams {
@@ -33,12 +36,14 @@ impl Default for TouchPadParams {
 
             touch_timeout: Duration::from_millis(250),
             max_touch_distance: 20.0,
+
+            min_pinch_distance: 10.0,
         }
     }
 }
 
 /// Describes touch-screen input state.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum TouchState {
     Start,
     Move,
@@ -57,6 +62,9 @@ pub struct TouchPad {
 
     double_tap_detector: GestureTapDetector,
     double_tap: GestureTap,
+
+    pinch_detector: GesturePinchDetector,
+    pinch: GesturePinch,
 }
 
 impl TouchPad {
@@ -72,6 +80,9 @@ impl TouchPad {
 
             double_tap_detector: GestureTapDetector::new(2, params),
             double_tap: GestureTap::None,
+
+            pinch_detector: GesturePinchDetector::new(params),
+            pinch: GesturePinch::None,
         }
     }
 
@@ -79,6 +90,7 @@ impl TouchPad {
         self.pan = GesturePan::None;
         self.tap = GestureTap::None;
         self.double_tap = GestureTap::None;
+        self.pinch = GesturePinch::None;
     }
 
     pub fn reset(&mut self) {
@@ -89,13 +101,16 @@ impl TouchPad {
         self.tap = GestureTap::None;
         self.double_tap_detector.reset();
         self.double_tap = GestureTap::None;
+        self.pinch_detector.reset();
+        self.pinch = GesturePinch::None;
     }
 
-    pub fn on_touch(&mut self, id: u8, state: TouchState, position: Vector2<f32>) {
+    pub fn on_touch(&mut self, id: u8, state: TouchState, position: Vector2<f32>, pressure: f32) {
         let touch = TouchEvent {
             id,
             state,
             position,
+            pressure,
         };
 
         self.record.update_touch(touch);
@@ -103,6 +118,26 @@ impl TouchPad {
         self.pan = self.pan_detector.detect(&self.record);
         self.tap = self.tap_detector.detect(&self.record);
         self.double_tap = self.double_tap_detector.detect(&self.record);
+
+        // Both fingers might move in one frame, the movements are accumulated. And the start
+        // and end of pinching are kept until the next frame.
+        self.pinch = match (self.pinch, self.pinch_detector.detect(&self.record)) {
+            (v, GesturePinch::None) => v,
+            (v @ GesturePinch::Start { .. }, GesturePinch::Move { .. }) => v,
+            (
+                GesturePinch::Move { movement: m1, .. },
+                GesturePinch::Move {
+                    center,
+                    scale,
+                    movement,
+                },
+            ) => GesturePinch::Move {
+                center,
+                scale,
+                movement: m1 + movement,
+            },
+            (_, v) => v,
+        };
     }
 
     #[inline]
@@ -115,6 +150,11 @@ impl TouchPad {
         self.record.position(index)
     }
 
+    #[inline]
+    pub fn pressure(&self, index: usize) -> Option<f32> {
+        self.record.pressure(index)
+    }
+
     #[inline]
     pub fn pan(&self) -> GesturePan {
         self.pan
@@ -129,6 +169,11 @@ impl TouchPad {
     pub fn double_tap(&self) -> GestureTap {
         self.double_tap
     }
+
+    #[inline]
+    pub fn pinch(&self) -> GesturePinch {
+        self.pinch
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -396,11 +441,140 @@ impl GesturePanDetector {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum GesturePinch {
+    Start {
+        /// The center of two fingers.
+        center: Vector2<f32>,
+    },
+    Move {
+        /// The center of two fingers.
+        center: Vector2<f32>,
+        /// The ratio between the current and start distances of two fingers.
+        scale: f32,
+        /// The movement of center during last frame.
+        movement: Vector2<f32>,
+    },
+    End {
+        /// The center of two fingers.
+        center: Vector2<f32>,
+        /// The ratio between the last and start distances of two fingers.
+        scale: f32,
+    },
+    None,
+}
+
+impl GesturePinch {
+    pub fn scale(&self, device_pixel_ratio: f32) -> GesturePinch {
+        match *self {
+            GesturePinch::Start { center } => GesturePinch::Start {
+                center: center * device_pixel_ratio,
+            },
+
+            GesturePinch::Move {
+                center,
+                scale,
+                movement,
+            } => GesturePinch::Move {
+                center: center * device_pixel_ratio,
+                scale,
+                movement: movement * device_pixel_ratio,
+            },
+
+            GesturePinch::End { center, scale } => GesturePinch::End {
+                center: center * device_pixel_ratio,
+                scale,
+            },
+
+            GesturePinch::None => GesturePinch::None,
+        }
+    }
+}
+
+struct GesturePinchDetector {
+    center: Vector2<f32>,
+    start_distance: f32,
+    distance: f32,
+    pinch: bool,
+    record: TouchesRecord,
+
+    params: TouchPadParams,
+}
+
+impl GesturePinchDetector {
+    pub fn new(params: TouchPadParams) -> Self {
+        GesturePinchDetector {
+            params,
+            center: Vector2::new(0.0, 0.0),
+            start_distance: 0.0,
+            distance: 0.0,
+            pinch: false,
+            record: TouchesRecord::default(),
+        }
+    }
+
+    pub fn detect(&mut self, record: &TouchesRecord) -> GesturePinch {
+        // Checks for required number of touches, the pinching ends once a finger has been
+        // lifted.
+        if record.len != 2 || (self.record.len > 0 && !self.record.is_same(record)) {
+            let pinch = self.pinch;
+            self.reset();
+
+            if pinch {
+                return GesturePinch::End {
+                    center: self.center,
+                    scale: self.distance / self.start_distance.max(std::f32::EPSILON),
+                };
+            }
+
+            if record.len != 2 {
+                return GesturePinch::None;
+            }
+        }
+
+        let (p1, p2) = (record.touches[0].1.position, record.touches[1].1.position);
+        let center = (p1 + p2) * 0.5;
+        let distance = p1.distance(p2);
+
+        if self.record.len == 0 {
+            self.record = *record;
+            self.center = center;
+            self.start_distance = distance;
+            self.distance = distance;
+            return GesturePinch::None;
+        }
+
+        let movement = center - self.center;
+        self.center = center;
+        self.distance = distance;
+
+        if self.pinch {
+            GesturePinch::Move {
+                center,
+                scale: distance / self.start_distance.max(std::f32::EPSILON),
+                movement,
+            }
+        } else if (distance - self.start_distance).abs() >= self.params.min_pinch_distance {
+            // Checks if min-distance is reached before starting pinching.
+            self.pinch = true;
+            GesturePinch::Start { center }
+        } else {
+            GesturePinch::None
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.record.reset();
+        self.pinch = false;
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct TouchEvent {
     pub id: u8,
     pub state: TouchState,
     pub position: Vector2<f32>,
+    pub pressure: f32,
 }
 
 impl Default for TouchEvent {
@@ -409,6 +583,7 @@ impl Default for TouchEvent {
             id: 0,
             state: TouchState::End,
             position: Vector2::new(0.0, 0.0),
+            pressure: 0.0,
         }
     }
 }
@@ -456,6 +631,14 @@ impl TouchesRecord {
         }
     }
 
+    fn pressure(&self, index: usize) -> Option<f32> {
+        if self.len > index {
+            Some(self.touches[index].1.pressure)
+        } else {
+            None
+        }
+    }
+
     fn update_touch(&mut self, touch: TouchEvent) {
         let mut found = false;
         for i in 0..self.len {
@@ -484,3 +667,51 @@ impl TouchesRecord {
         lhs.1.state.cmp(&rhs.1.state).then(lhs.0.cmp(&rhs.0))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pinch() {
+        let mut touchpad = TouchPad::new(TouchPadParams::default());
+        touchpad.on_touch(1, TouchState::Start, Vector2::new(0.0, 0.0), 1.0);
+        touchpad.on_touch(2, TouchState::Start, Vector2::new(100.0, 0.0), 0.5);
+        assert_eq!(touchpad.pressure(1), Some(0.5));
+
+        touchpad.on_touch(2, TouchState::Move, Vector2::new(105.0, 0.0), 0.5);
+        match touchpad.pinch() {
+            GesturePinch::None => {}
+            v => panic!("{:?}", v),
+        }
+
+        touchpad.on_touch(2, TouchState::Move, Vector2::new(150.0, 0.0), 0.5);
+        match touchpad.pinch() {
+            GesturePinch::Start { center } => assert_eq!(center, Vector2::new(75.0, 0.0)),
+            v => panic!("{:?}", v),
+        }
+
+        touchpad.advance();
+        touchpad.on_touch(1, TouchState::Move, Vector2::new(-50.0, 0.0), 1.0);
+        touchpad.on_touch(2, TouchState::Move, Vector2::new(200.0, 0.0), 0.5);
+        match touchpad.pinch() {
+            GesturePinch::Move {
+                center,
+                scale,
+                movement,
+            } => {
+                assert_eq!(center, Vector2::new(75.0, 0.0));
+                assert!((scale - 2.5).abs() < 1e-5);
+                assert_eq!(movement, Vector2::new(0.0, 0.0));
+            }
+            v => panic!("{:?}", v),
+        }
+
+        touchpad.advance();
+        touchpad.on_touch(2, TouchState::End, Vector2::new(200.0, 0.0), 0.5);
+        match touchpad.pinch() {
+            GesturePinch::End { scale, .. } => assert!((scale - 2.5).abs() < 1e-5),
+            v => panic!("{:?}", v),
+        }
+    }
+}
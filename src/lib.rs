@@ -28,6 +28,8 @@
 #[cfg(not(target_arch = "wasm32"))]
 extern crate gl;
 #[cfg(not(target_arch = "wasm32"))]
+extern crate arboard;
+#[cfg(not(target_arch = "wasm32"))]
 extern crate glutin;
 
 #[cfg(target_arch = "wasm32")]
@@ -52,6 +54,7 @@ extern crate byteorder;
 extern crate serde_json;
 
 extern crate crossbeam_deque;
+extern crate flate2;
 extern crate inlinable_string;
 extern crate smallvec;
 
@@ -69,10 +72,15 @@ pub mod application;
 #[macro_use]
 pub mod video;
 pub mod input;
+pub mod logging;
 pub mod math;
 pub mod prelude;
+#[macro_use]
+pub mod profiler;
 pub mod res;
 pub mod sched;
+#[cfg(feature = "golden-test")]
+pub mod testing;
 pub mod window;
 
 #[macro_export]
@@ -0,0 +1,196 @@
+//! The logging facade, which dispatches the records of `info!`, `warn!` and so on to the
+//! attached sinks.
+//!
+//! # Verbosity
+//!
+//! The records are filtered by the level of the module they come from, which is the
+//! longest module path in `LogParams::modules` matching the target of record. The default
+//! level is used if there is no match.
+//!
+//! ```rust,ignore
+//! let mut params = Params::default();
+//! params.log.level = LevelFilter::Warn;
+//! params.log.modules.push(("crayon::res".into(), LevelFilter::Info));
+//! params.log.file = Some("game.log".into());
+//! ```
+//!
+//! # Sinks
+//!
+//! The records are written into the standard output (`console.log` on the web platform)
+//! and the file set in `LogParams`. Other sinks could be attached with `logging::attach`,
+//! e.g. the in-game console of `crayon_world`.
+//!
+//! Notes that the global logger of `log` could only be set once, so the records would be
+//! dispatched to the one set before `application::setup` instead if any.
+
+mod sinks;
+mod system;
+
+pub mod prelude {
+    pub use super::sinks::{FileSink, StdoutSink};
+    pub use super::{Level, LevelFilter, LogParams, LogRecord, LogSink, LogSinkHandle};
+}
+
+pub use log::{Level, LevelFilter};
+
+pub use self::sinks::{FileSink, StdoutSink};
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use self::inside::ctx;
+
+impl_handle!(LogSinkHandle);
+
+/// The setup parameters of logging.
+#[derive(Debug, Clone)]
+pub struct LogParams {
+    /// The default level of modules.
+    pub level: LevelFilter,
+    /// The levels of modules, e.g. `("crayon::video", LevelFilter::Warn)`.
+    pub modules: Vec<(String, LevelFilter)>,
+    /// Prefixes the records with the time since setup.
+    pub timestamps: bool,
+    /// Writes the records into the standard output, or `console.log` on the web platform.
+    pub stdout: bool,
+    /// Writes the records into the file.
+    pub file: Option<PathBuf>,
+}
+
+impl Default for LogParams {
+    fn default() -> Self {
+        LogParams {
+            level: LevelFilter::Info,
+            modules: Vec::new(),
+            timestamps: true,
+            stdout: true,
+            file: None,
+        }
+    }
+}
+
+/// A record which has passed the filter of levels.
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub level: Level,
+    /// The module path that the record comes from.
+    pub target: String,
+    pub message: String,
+    /// The time since setup, if `LogParams::timestamps` is enabled.
+    pub timestamp: Option<Duration>,
+}
+
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(v) = self.timestamp {
+            write!(f, "[{:>4}.{:03}] ", v.as_secs(), v.subsec_millis())?;
+        }
+
+        write!(f, "{:<5} {}: {}", self.level, self.target, self.message)
+    }
+}
+
+/// The destination of records.
+pub trait LogSink: Send + Sync {
+    fn write(&self, record: &LogRecord);
+
+    fn flush(&self) {}
+}
+
+/// Checks if the logging system is enabled.
+#[inline]
+pub fn valid() -> bool {
+    inside::valid()
+}
+
+/// Sets the default level of modules.
+#[inline]
+pub fn set_level(level: LevelFilter) {
+    ctx().set_level(level);
+}
+
+/// Sets the level of module and its sub-modules, which overrides the default level.
+#[inline]
+pub fn set_module_level<T: Into<String>>(module: T, level: LevelFilter) {
+    ctx().set_module_level(module.into(), level);
+}
+
+/// Gets the level of the module path.
+#[inline]
+pub fn level(target: &str) -> LevelFilter {
+    ctx().level(target)
+}
+
+/// Attaches a sink, which receives the records that pass the filter of levels.
+#[inline]
+pub fn attach<T: LogSink + 'static>(sink: T) -> LogSinkHandle {
+    ctx().attach(sink)
+}
+
+/// Detaches a sink.
+#[inline]
+pub fn detach(handle: LogSinkHandle) {
+    ctx().detach(handle);
+}
+
+/// Flushes the buffered records of all the sinks.
+#[inline]
+pub fn flush() {
+    ctx().flush();
+}
+
+pub(crate) mod inside {
+    use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+    use super::system::LoggingSystem;
+    use super::LogParams;
+
+    // The global logger of `log` could be used by any thread at any time, e.g. the workers
+    // that are still running while the engine is being discarded. So the logging system is
+    // never freed once created, and it's reset by the setups afterwards.
+    static CTX: AtomicPtr<LoggingSystem> = AtomicPtr::new(std::ptr::null_mut());
+    static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+    #[inline]
+    pub fn ctx() -> &'static LoggingSystem {
+        let ctx = CTX.load(Ordering::Acquire);
+        debug_assert!(
+            !ctx.is_null(),
+            "logging system has not been initialized properly."
+        );
+
+        unsafe { &*ctx }
+    }
+
+    #[inline]
+    pub fn valid() -> bool {
+        ACTIVE.load(Ordering::Acquire)
+    }
+
+    /// Setup the logging system.
+    pub unsafe fn setup(params: LogParams) {
+        debug_assert!(!valid(), "duplicated setup of logging system.");
+
+        if CTX.load(Ordering::Acquire).is_null() {
+            let ctx = Box::into_raw(Box::new(LoggingSystem::new(params)));
+            CTX.store(ctx, Ordering::Release);
+        } else {
+            ctx().reset(params);
+        }
+
+        ACTIVE.store(true, Ordering::Release);
+        super::system::install();
+    }
+
+    /// Discard the logging system, the sinks are dropped and the records are ignored
+    /// afterwards.
+    pub unsafe fn discard() {
+        if !ACTIVE.swap(false, Ordering::AcqRel) {
+            return;
+        }
+
+        ctx().flush();
+        ctx().clear();
+    }
+}
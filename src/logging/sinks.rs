@@ -0,0 +1,61 @@
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::errors::*;
+
+use super::{LogRecord, LogSink};
+
+/// Writes the records into the standard output, or `console.log` on the web platform.
+pub struct StdoutSink;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl LogSink for StdoutSink {
+    fn write(&self, record: &LogRecord) {
+        println!("{}", record);
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stdout().flush();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = console)]
+        pub fn log(s: &str);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl LogSink for StdoutSink {
+    fn write(&self, record: &LogRecord) {
+        web::log(&format!("{}", record));
+    }
+}
+
+/// Writes the records into a file line by line.
+pub struct FileSink(Mutex<LineWriter<File>>);
+
+impl FileSink {
+    /// Creates a new `FileSink`, the file is truncated if it exists already.
+    pub fn new<T: AsRef<Path>>(path: T) -> Result<Self> {
+        let file = File::create(path)?;
+        Ok(FileSink(Mutex::new(LineWriter::new(file))))
+    }
+}
+
+impl LogSink for FileSink {
+    fn write(&self, record: &LogRecord) {
+        let _ = writeln!(self.0.lock().unwrap(), "{}", record);
+    }
+
+    fn flush(&self) {
+        let _ = self.0.lock().unwrap().flush();
+    }
+}
@@ -0,0 +1,295 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::utils::object_pool::ObjectPool;
+
+use super::sinks::{FileSink, StdoutSink};
+use super::{Level, LevelFilter, LogParams, LogRecord, LogSink, LogSinkHandle};
+
+/// The global logger of `log`, which dispatches the records to the logging system.
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        super::valid() && super::ctx().enabled(metadata.level(), metadata.target())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if super::valid() {
+            super::ctx().log(record);
+        }
+    }
+
+    fn flush(&self) {
+        if super::valid() {
+            super::ctx().flush();
+        }
+    }
+}
+
+/// Sets the global logger of `log`, it's ignored if there is one already.
+pub fn install() {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(super::ctx().max_level());
+}
+
+struct LoggingState {
+    level: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+    sinks: ObjectPool<LogSinkHandle, Arc<dyn LogSink>>,
+    epoch: Duration,
+    timestamps: bool,
+}
+
+impl LoggingState {
+    fn new(params: LogParams) -> Self {
+        let mut sinks: ObjectPool<LogSinkHandle, Arc<dyn LogSink>> = ObjectPool::new();
+        if params.stdout {
+            sinks.create(Arc::new(StdoutSink));
+        }
+
+        if let Some(ref path) = params.file {
+            match FileSink::new(path) {
+                Ok(sink) => {
+                    sinks.create(Arc::new(sink));
+                }
+                Err(err) => StdoutSink.write(&LogRecord {
+                    level: Level::Warn,
+                    target: module_path!().to_owned(),
+                    message: format!("Failed to create log file {:?}. {}", path, err),
+                    timestamp: None,
+                }),
+            }
+        }
+
+        LoggingState {
+            level: params.level,
+            modules: params.modules,
+            sinks,
+            epoch: crate::application::sys::precise_timestamp(),
+            timestamps: params.timestamps,
+        }
+    }
+}
+
+pub struct LoggingSystem {
+    state: RwLock<LoggingState>,
+}
+
+impl LoggingSystem {
+    pub fn new(params: LogParams) -> Self {
+        LoggingSystem {
+            state: RwLock::new(LoggingState::new(params)),
+        }
+    }
+
+    /// Replaces the levels and sinks with `params`.
+    pub fn reset(&self, params: LogParams) {
+        let state = LoggingState::new(params);
+        // Makes sure that the lock has been freed before the drop of sinks.
+        let _state = std::mem::replace(&mut *self.state.write().unwrap(), state);
+    }
+
+    /// Drops all the sinks.
+    pub fn clear(&self) {
+        let _sinks = std::mem::replace(&mut self.state.write().unwrap().sinks, ObjectPool::new());
+    }
+
+    pub fn set_level(&self, level: LevelFilter) {
+        self.state.write().unwrap().level = level;
+        log::set_max_level(self.max_level());
+    }
+
+    pub fn set_module_level(&self, module: String, level: LevelFilter) {
+        {
+            let mut state = self.state.write().unwrap();
+            state.modules.retain(|v| v.0 != module);
+            state.modules.push((module, level));
+        }
+
+        log::set_max_level(self.max_level());
+    }
+
+    /// Gets the level of the longest module path that matches the target.
+    pub fn level(&self, target: &str) -> LevelFilter {
+        let state = self.state.read().unwrap();
+        level(&state, target)
+    }
+
+    pub fn max_level(&self) -> LevelFilter {
+        let state = self.state.read().unwrap();
+        state
+            .modules
+            .iter()
+            .map(|v| v.1)
+            .fold(state.level, std::cmp::max)
+    }
+
+    #[inline]
+    pub fn enabled(&self, level: Level, target: &str) -> bool {
+        level <= self.level(target)
+    }
+
+    pub fn attach<T: LogSink + 'static>(&self, sink: T) -> LogSinkHandle {
+        self.state.write().unwrap().sinks.create(Arc::new(sink))
+    }
+
+    pub fn detach(&self, handle: LogSinkHandle) {
+        // Makes sure that the lock has been freed before the drop of sink.
+        let _sink = self.state.write().unwrap().sinks.free(handle);
+    }
+
+    pub fn log(&self, record: &log::Record) {
+        // The sinks are cloned, so they could log without dead lock.
+        let (sinks, epoch): (Vec<_>, _) = {
+            let state = self.state.read().unwrap();
+            if record.level() > level(&state, record.target()) {
+                return;
+            }
+
+            let epoch = if state.timestamps {
+                Some(state.epoch)
+            } else {
+                None
+            };
+
+            (state.sinks.values().cloned().collect(), epoch)
+        };
+
+        let timestamp = epoch.map(|epoch| {
+            let now = crate::application::sys::precise_timestamp();
+            if now > epoch {
+                now - epoch
+            } else {
+                Duration::from_secs(0)
+            }
+        });
+
+        let record = LogRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: format!("{}", record.args()),
+            timestamp,
+        };
+
+        for v in sinks {
+            v.write(&record);
+        }
+    }
+
+    pub fn flush(&self) {
+        let sinks: Vec<_> = self.state.read().unwrap().sinks.values().cloned().collect();
+        for v in sinks {
+            v.flush();
+        }
+    }
+}
+
+fn level(state: &LoggingState, target: &str) -> LevelFilter {
+    state
+        .modules
+        .iter()
+        .filter(|(module, _)| {
+            target == module.as_str()
+                || (target.starts_with(module.as_str()) && target[module.len()..].starts_with("::"))
+        })
+        .max_by_key(|(module, _)| module.len())
+        .map(|v| v.1)
+        .unwrap_or(state.level)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Records(Arc<Mutex<Vec<String>>>);
+
+    impl LogSink for Records {
+        fn write(&self, record: &LogRecord) {
+            self.0.lock().unwrap().push(record.message.clone());
+        }
+    }
+
+    #[test]
+    fn levels() {
+        let mut params = LogParams::default();
+        params.level = LevelFilter::Warn;
+        params.stdout = false;
+        params.timestamps = false;
+        params
+            .modules
+            .push(("crayon::res".into(), LevelFilter::Info));
+
+        let sys = LoggingSystem::new(params);
+        sys.set_module_level("crayon::res::utils".into(), LevelFilter::Error);
+
+        assert_eq!(sys.level("crayon::video"), LevelFilter::Warn);
+        assert_eq!(sys.level("crayon::res"), LevelFilter::Info);
+        assert_eq!(sys.level("crayon::res::url"), LevelFilter::Info);
+        assert_eq!(sys.level("crayon::resx"), LevelFilter::Warn);
+        assert_eq!(sys.level("crayon::res::utils::pool"), LevelFilter::Error);
+        assert_eq!(sys.max_level(), LevelFilter::Info);
+
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let handle = sys.attach(Records(records.clone()));
+
+        let write = |level, target, msg| {
+            sys.log(
+                &log::Record::builder()
+                    .level(level)
+                    .target(target)
+                    .args(format_args!("{}", msg))
+                    .build(),
+            )
+        };
+
+        write(Level::Info, "crayon::video", "a");
+        write(Level::Info, "crayon::res::url", "b");
+        write(Level::Warn, "crayon::res::utils", "c");
+        write(Level::Error, "crayon::res::utils", "d");
+        sys.detach(handle);
+        write(Level::Error, "crayon::video", "e");
+
+        assert_eq!(*records.lock().unwrap(), vec!["b", "d"]);
+    }
+
+    #[test]
+    fn reset() {
+        let mut params = LogParams::default();
+        params.stdout = false;
+        params.level = LevelFilter::Info;
+
+        let sys = LoggingSystem::new(params.clone());
+        let records = Arc::new(Mutex::new(Vec::new()));
+        sys.attach(Records(records.clone()));
+        assert_eq!(Arc::strong_count(&records), 2);
+
+        // The sinks are dropped once the system is discarded.
+        sys.clear();
+        assert_eq!(Arc::strong_count(&records), 1);
+
+        sys.attach(Records(records.clone()));
+        params.level = LevelFilter::Error;
+        sys.reset(params);
+        assert_eq!(Arc::strong_count(&records), 1);
+        assert_eq!(sys.level("crayon"), LevelFilter::Error);
+    }
+
+    #[test]
+    fn format() {
+        let record = LogRecord {
+            level: Level::Warn,
+            target: "crayon::video".into(),
+            message: "hello".into(),
+            timestamp: Some(Duration::from_millis(1234)),
+        };
+
+        assert_eq!(
+            format!("{}", record),
+            "[   1.234] WARN  crayon::video: hello"
+        );
+    }
+}
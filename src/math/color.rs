@@ -2,7 +2,7 @@ use cgmath::BaseFloat;
 
 /// A RGBA `Color`. Each color component is a floating point value
 /// with a range from 0 to 1.
-#[derive(Debug, Default, Copy, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq)]
 pub struct Color<S> {
     pub r: S,
     pub g: S,
@@ -4,10 +4,10 @@ use crate::math::prelude::{Aabb3, Plane, PlaneBound, PlaneRelation};
 
 use cgmath::num_traits::cast;
 use cgmath::prelude::*;
-use cgmath::{BaseFloat, Matrix, Matrix4, Point3, Rad};
+use cgmath::{BaseFloat, Matrix, Matrix4, Point3, Rad, Vector4};
 
 /// Projections.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum Projection<S: BaseFloat> {
     /// Orthographic projection.
     Ortho {
@@ -186,12 +186,44 @@ impl<S: BaseFloat> Projection<S> {
         let c3 = [zero, zero, (two * f * n) / (n - f), zero];
         Matrix4::from_cols(c0.into(), c1.into(), c2.into(), c3.into())
     }
+
+    /// Replaces the near plane of projection `matrix` with the clip `plane` in view space,
+    /// whose positive side is visible, while keeping the far plane as close as possible
+    /// to the original one. It's used to clip the geometries behind mirrors in planar
+    /// reflections.
+    ///
+    /// See _Oblique View Frustum Depth Projection and Clipping_ by Eric Lengyel.
+    pub fn oblique_matrix(matrix: Matrix4<S>, plane: Vector4<S>) -> Matrix4<S> {
+        let one = S::one();
+        let two: S = cast(2.0).unwrap();
+
+        let inv = match matrix.invert() {
+            Some(inv) => inv,
+            None => return matrix,
+        };
+
+        // The corner of clip space opposite to the plane, which the far plane should
+        // pass through.
+        let clip = inv.transpose() * plane;
+        let corner = Vector4::new(clip.x.signum(), clip.y.signum(), one, one);
+        let q = inv * corner;
+
+        let c = plane * (two / plane.dot(q));
+        let mut m = matrix;
+        let row = c - m.row(3);
+        m.x.z = row.x;
+        m.y.z = row.y;
+        m.z.z = row.z;
+        m.w.z = row.w;
+        m
+    }
 }
 
 /// View frustum, used for frustum culling
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Frustum<S: BaseFloat> {
     projection: Projection<S>,
+    matrix: Matrix4<S>,
 
     /// Left plane
     pub left: Plane<S>,
@@ -211,10 +243,15 @@ impl<S: BaseFloat> Frustum<S> {
     /// Construct a frustum.
     pub fn new(projection: Projection<S>) -> Frustum<S> {
         projection.validate();
-        let mat = Projection::matrix(projection);
+        Self::with_matrix(projection, Projection::matrix(projection))
+    }
 
+    /// Construct a frustum with custom projection matrix, the planes are extracted from
+    /// `mat` instead of `projection`.
+    pub fn with_matrix(projection: Projection<S>, mat: Matrix4<S>) -> Frustum<S> {
         Frustum {
             projection,
+            matrix: mat,
 
             left: Plane::from_vector4_alt(mat.row(3) + mat.row(0))
                 .normalize()
@@ -256,19 +293,19 @@ impl<S: BaseFloat> Frustum<S> {
             self.near,
             self.far,
         ]
-            .iter()
-            .fold(PlaneRelation::In, |cur, p| {
-                use std::cmp::max;
-                let r = bound.relate(*p);
-                // If any of the planes are `Out`, the bound is outside.
-                // Otherwise, if any are `Cross`, the bound is crossing.
-                // Otherwise, the bound is fully inside.
-                max(cur, r)
-            })
+        .iter()
+        .fold(PlaneRelation::In, |cur, p| {
+            use std::cmp::max;
+            let r = bound.relate(*p);
+            // If any of the planes are `Out`, the bound is outside.
+            // Otherwise, if any are `Cross`, the bound is crossing.
+            // Otherwise, the bound is fully inside.
+            max(cur, r)
+        })
     }
 
     pub fn to_matrix(&self) -> Matrix4<S> {
-        self.projection.to_matrix()
+        self.matrix
     }
 }
 
@@ -392,6 +429,42 @@ impl<S: BaseFloat> Into<FrustumPoints<S>> for Frustum<S> {
 
 impl<S: BaseFloat> Into<Matrix4<S>> for Frustum<S> {
     fn into(self) -> Matrix4<S> {
-        self.projection.to_matrix()
+        self.matrix
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cgmath::Deg;
+
+    #[test]
+    fn oblique() {
+        let m = Projection::perspective_matrix(Deg(60.0).into(), 1.0, 0.1, 100.0);
+
+        // The plane z - y = 1 in view space, whose positive side is visible.
+        let plane = Vector4::new(0.0, -1.0, 1.0, -1.0);
+        let om = Projection::oblique_matrix(m, plane);
+
+        // The points on the plane are projected onto the near plane.
+        for p in &[[0.0, 0.0, 1.0], [0.5, 1.0, 2.0], [-2.0, 3.0, 4.0]] {
+            let v = om * Vector4::new(p[0], p[1], p[2], 1.0);
+            assert_relative_eq!(v.z / v.w, -1.0, epsilon = 1e-4);
+        }
+
+        // The points in front of plane are not clipped.
+        let v = om * Vector4::new(0.0, -1.0, 2.0, 1.0);
+        assert!(v.z / v.w > -1.0 && v.z / v.w < 1.0);
+
+        // The points behind plane are clipped.
+        let v = om * Vector4::new(0.0, 1.0, 1.5, 1.0);
+        assert!(v.z / v.w < -1.0);
+
+        // The x and y are the same as the original projection.
+        let v0 = m * Vector4::new(1.0, 2.0, 3.0, 1.0);
+        let v1 = om * Vector4::new(1.0, 2.0, 3.0, 1.0);
+        assert_relative_eq!(v0.x, v1.x, epsilon = 1e-4);
+        assert_relative_eq!(v0.y, v1.y, epsilon = 1e-4);
+        assert_relative_eq!(v0.w, v1.w, epsilon = 1e-4);
     }
 }
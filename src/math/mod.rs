@@ -4,12 +4,14 @@ pub mod aabb;
 pub mod color;
 pub mod frustum;
 pub mod plane;
+pub mod ray;
 
 pub mod prelude {
     pub use super::aabb::{Aabb2, Aabb3};
     pub use super::color::Color;
     pub use super::frustum::{Frustum, FrustumPoints, Projection};
     pub use super::plane::{Plane, PlaneBound, PlaneRelation};
+    pub use super::ray::Ray;
 
     pub use cgmath::prelude::{EuclideanSpace, InnerSpace, MetricSpace, VectorSpace};
     pub use cgmath::prelude::{One, Zero};
@@ -18,7 +18,7 @@ use cgmath::{BaseFloat, Point3, Vector3, Vector4};
 /// The `A*x + B*y + C*z - D = 0` form is preferred over the other common
 /// alternative, `A*x + B*y + C*z + D = 0`, because it tends to avoid
 /// superfluous negations (see _Real Time Collision Detection_, p. 55).
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq)]
 pub struct Plane<S> {
     /// Plane normal
     pub n: Vector3<S>,
@@ -0,0 +1,135 @@
+//! Rays, which are used to test intersections with bounding volumes and triangles, e.g.
+//! picking objects with mouse.
+
+use cgmath::prelude::*;
+use cgmath::{BaseFloat, Vector3};
+
+use crate::math::prelude::Aabb3;
+
+/// A ray that starts from `origin` and goes infinitely in `direction`.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Ray<S> {
+    /// The start point of ray.
+    pub origin: Vector3<S>,
+    /// The direction of ray.
+    pub direction: Vector3<S>,
+}
+
+impl<S: BaseFloat> Ray<S> {
+    /// Creates a new ray, the direction is normalized.
+    #[inline]
+    pub fn new(origin: Vector3<S>, direction: Vector3<S>) -> Self {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// Gets the point at `distance` along the ray, the distance is measured in the lengths
+    /// of `direction`.
+    #[inline]
+    pub fn at(&self, distance: S) -> Vector3<S> {
+        self.origin + self.direction * distance
+    }
+
+    /// Tests the intersection with axis-aligned bounding box, returns the distance to the
+    /// entry point, or zero if the origin is inside of box.
+    pub fn intersect_aabb(&self, aabb: &Aabb3<S>) -> Option<S> {
+        let mut near = S::zero();
+        let mut far = S::infinity();
+
+        for i in 0..3 {
+            let (o, d) = (self.origin[i], self.direction[i]);
+            let (min, max) = (aabb.min[i], aabb.max[i]);
+
+            if d.abs() <= S::epsilon() {
+                if o < min || o > max {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let (mut t0, mut t1) = ((min - o) / d, (max - o) / d);
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            near = near.max(t0);
+            far = far.min(t1);
+
+            if near > far {
+                return None;
+            }
+        }
+
+        Some(near)
+    }
+
+    /// Tests the intersection with both sides of triangle, returns the distance to the
+    /// intersection point.
+    pub fn intersect_triangle(&self, a: Vector3<S>, b: Vector3<S>, c: Vector3<S>) -> Option<S> {
+        // Möller–Trumbore intersection algorithm.
+        let e1 = b - a;
+        let e2 = c - a;
+
+        // The determinant is compared relatively to the lengths of edges, so the rays
+        // parallel to triangles are rejected regardless of the sizes of triangles.
+        let p = self.direction.cross(e2);
+        let det = e1.dot(p);
+        let scale = e1.magnitude2() * e2.magnitude2() * self.direction.magnitude2();
+        if det * det <= S::epsilon() * S::epsilon() * scale {
+            return None;
+        }
+
+        let inv = S::one() / det;
+        let s = self.origin - a;
+        let u = s.dot(p) * inv;
+        if u < S::zero() || u > S::one() {
+            return None;
+        }
+
+        let q = s.cross(e1);
+        let v = self.direction.dot(q) * inv;
+        if v < S::zero() || u + v > S::one() {
+            return None;
+        }
+
+        let t = e2.dot(q) * inv;
+        if t >= S::zero() {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn triangles() {
+        let ray = Ray::new(Vector3::new(0.1, 0.1, 1.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let (a, b, c) = (
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+
+        assert_eq!(ray.intersect_triangle(a, b, c), Some(1.0));
+        assert_eq!(ray.intersect_triangle(a, c, b), Some(1.0));
+
+        // The small triangles are hit as well as the large ones.
+        let ray = Ray::new(Vector3::new(1e-5, 1e-5, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = |ray: Ray<f64>, s: f64| ray.intersect_triangle(a * s, b * s, c * s);
+        assert!(hit(ray, 1e-4).is_some());
+        assert!(hit(ray, 1e4).is_some());
+
+        // The rays parallel to triangles and the degenerated triangles are missed.
+        let ray = Ray::new(Vector3::new(0.1, 0.1, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(hit(ray, 1e4), None);
+        assert_eq!(ray.intersect_triangle(a, b, b * 2.0), None);
+    }
+}
@@ -1,10 +1,14 @@
 pub use crate::application::prelude::*;
 pub use crate::input::prelude::*;
+pub use crate::logging::prelude::*;
 pub use crate::math::prelude::*;
+pub use crate::profiler::prelude::*;
 pub use crate::res::prelude::*;
 pub use crate::sched::prelude::*;
 pub use crate::video::prelude::*;
 pub use crate::window::prelude::*;
-pub use crate::{application, input, main, math, res, sched, video, window};
+pub use crate::{
+    application, input, logging, main, math, profile_scope, profiler, res, sched, video, window,
+};
 
 pub use crate::errors::{Error as CrError, Result as CrResult};
@@ -0,0 +1,268 @@
+//! A lightweight profiler which records the hierarchical CPU timings of scopes per thread
+//! per frame.
+//!
+//! # Scopes
+//!
+//! The scopes are measured with the `profile_scope!` macro, which records the time from
+//! where it's placed to the end of the enclosing block. The scopes could be nested, and
+//! be used on any threads, e.g. the jobs of `sched`.
+//!
+//! ```rust,ignore
+//! fn on_update(&mut self) -> Result<()> {
+//!     profile_scope!("update");
+//!
+//!     {
+//!         profile_scope!("physics");
+//!         self.physics.advance();
+//!     }
+//!
+//!     self.scene.advance();
+//!     Ok(())
+//! }
+//! ```
+//!
+//! # Frames
+//!
+//! The profiler is disabled by default, which makes the scopes cost almost nothing. Once
+//! it's enabled with `profiler::set_enabled`, the scopes that ended during a frame are
+//! collected into a `ProfileFrame` at the end of frame. The last frames are kept, and
+//! could be retrieved with `profiler::frames` to find out the spikes of main loop.
+//!
+//! ```rust,ignore
+//! profiler::set_enabled(true);
+//! // ...
+//! for frame in profiler::frames(10) {
+//!     if frame.duration > Duration::from_millis(33) {
+//!         std::fs::write("spike.json", profiler::chrome_trace(&[frame]))?;
+//!     }
+//! }
+//! ```
+//!
+//! The frames could be exported in the trace event format with `profiler::chrome_trace`,
+//! which could be loaded by `chrome://tracing`.
+
+mod system;
+
+pub mod prelude {
+    pub use super::{ProfileFrame, ProfileScope, ProfileThread};
+}
+
+use std::time::Duration;
+
+use self::inside::{ctx, CTX};
+use self::system::PendingScope;
+
+/// A scope measured with `profile_scope!`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileScope {
+    pub name: &'static str,
+    /// The number of scopes that enclose this one.
+    pub depth: u32,
+    /// The time that the scope began, since the profiler has been set up.
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// The scopes recorded on a thread, which are ordered by the time they ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileThread {
+    pub name: String,
+    pub scopes: Vec<ProfileScope>,
+}
+
+/// The scopes that ended during a frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileFrame {
+    pub index: u64,
+    /// The time that the frame began, since the profiler has been set up.
+    pub start: Duration,
+    pub duration: Duration,
+    pub threads: Vec<ProfileThread>,
+}
+
+/// Measures the time from where it's placed to the end of the enclosing block.
+#[macro_export]
+macro_rules! profile_scope {
+    ($name: expr) => {
+        let _profile_scope = $crate::profiler::ScopeGuard::new($name);
+    };
+}
+
+/// The guard created by `profile_scope!`, which ends the scope when it's dropped.
+pub struct ScopeGuard(Option<PendingScope>);
+
+impl ScopeGuard {
+    #[inline]
+    pub fn new(name: &'static str) -> Self {
+        if valid() {
+            ScopeGuard(ctx().begin(name))
+        } else {
+            ScopeGuard(None)
+        }
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        if let Some(scope) = self.0.take() {
+            if valid() {
+                ctx().end(scope);
+            }
+        }
+    }
+}
+
+/// Checks if the profiler system is enabled.
+#[inline]
+pub fn valid() -> bool {
+    unsafe { !CTX.is_null() }
+}
+
+/// Sets whether the scopes are recorded. Defaults to false.
+#[inline]
+pub fn set_enabled(enabled: bool) {
+    ctx().set_enabled(enabled);
+}
+
+/// Checks if the scopes are recorded.
+#[inline]
+pub fn enabled() -> bool {
+    ctx().enabled()
+}
+
+/// Sets the number of frames that are kept. Defaults to 60.
+#[inline]
+pub fn set_max_frames(max_frames: usize) {
+    ctx().set_max_frames(max_frames);
+}
+
+/// Gets the last `n` recorded frames, from the oldest one to the latest.
+#[inline]
+pub fn frames(n: usize) -> Vec<ProfileFrame> {
+    ctx().frames(n)
+}
+
+/// Exports the frames in the trace event format of Chrome.
+pub fn chrome_trace(frames: &[ProfileFrame]) -> String {
+    let mut threads: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for frame in frames {
+        events.push(serde_json::json!({
+            "name": format!("frame {}", frame.index),
+            "ph": "X",
+            "ts": micros(frame.start),
+            "dur": micros(frame.duration),
+            "pid": 0,
+            "tid": 0,
+        }));
+
+        for thread in &frame.threads {
+            let tid = match threads.iter().position(|&v| v == thread.name) {
+                Some(tid) => tid,
+                None => {
+                    threads.push(&thread.name);
+                    threads.len() - 1
+                }
+            };
+
+            for scope in &thread.scopes {
+                events.push(serde_json::json!({
+                    "name": scope.name,
+                    "ph": "X",
+                    "ts": micros(scope.start),
+                    "dur": micros(scope.duration),
+                    "pid": 0,
+                    "tid": tid + 1,
+                }));
+            }
+        }
+    }
+
+    let names = std::iter::once("frames").chain(threads.iter().cloned());
+    for (tid, name) in names.enumerate() {
+        events.push(serde_json::json!({
+            "name": "thread_name",
+            "ph": "M",
+            "pid": 0,
+            "tid": tid,
+            "args": { "name": name },
+        }));
+    }
+
+    serde_json::json!({ "traceEvents": events }).to_string()
+}
+
+fn micros(v: Duration) -> f64 {
+    v.as_secs() as f64 * 1_000_000.0 + f64::from(v.subsec_nanos()) / 1000.0
+}
+
+pub(crate) mod inside {
+    use super::system::ProfilerSystem;
+
+    pub static mut CTX: *const ProfilerSystem = std::ptr::null();
+
+    #[inline]
+    pub fn ctx() -> &'static ProfilerSystem {
+        unsafe {
+            debug_assert!(
+                !CTX.is_null(),
+                "profiler system has not been initialized properly."
+            );
+
+            &*CTX
+        }
+    }
+
+    /// Setup the profiler system.
+    pub unsafe fn setup() {
+        debug_assert!(CTX.is_null(), "duplicated setup of profiler system.");
+
+        let ctx = ProfilerSystem::new();
+        CTX = Box::into_raw(Box::new(ctx));
+    }
+
+    /// Discard the profiler system.
+    pub unsafe fn discard() {
+        if CTX.is_null() {
+            return;
+        }
+
+        drop(Box::from_raw(CTX as *mut ProfilerSystem));
+        CTX = std::ptr::null();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn trace() {
+        let scope = ProfileScope {
+            name: "update",
+            depth: 0,
+            start: Duration::from_micros(10),
+            duration: Duration::from_micros(5),
+        };
+
+        let frame = ProfileFrame {
+            index: 1,
+            start: Duration::from_micros(0),
+            duration: Duration::from_micros(20),
+            threads: vec![ProfileThread {
+                name: "main".into(),
+                scopes: vec![scope],
+            }],
+        };
+
+        let v: serde_json::Value = serde_json::from_str(&chrome_trace(&[frame])).unwrap();
+        let events = v["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[1]["name"], "update");
+        assert_eq!(events[1]["ts"], 10.0);
+        assert_eq!(events[1]["dur"], 5.0);
+        assert_eq!(events[1]["tid"], 1);
+        assert_eq!(events[3]["args"]["name"], "main");
+    }
+}
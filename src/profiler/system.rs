@@ -0,0 +1,242 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use super::{ProfileFrame, ProfileScope, ProfileThread};
+
+// The identifiers of profiler systems, which tell the thread local buffers registered
+// into the discarded systems apart.
+static SYSTEMS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static LOCAL: RefCell<Option<(usize, Weak<Mutex<ThreadBuffer>>)>> = RefCell::new(None);
+}
+
+struct ThreadBuffer {
+    name: String,
+    depth: u32,
+    scopes: Vec<ProfileScope>,
+}
+
+struct FrameState {
+    index: u64,
+    start: Duration,
+    max_frames: usize,
+    frames: VecDeque<ProfileFrame>,
+}
+
+/// A scope which has been began but not ended yet.
+pub struct PendingScope {
+    buffer: Arc<Mutex<ThreadBuffer>>,
+    name: &'static str,
+    depth: u32,
+    start: Duration,
+}
+
+pub struct ProfilerSystem {
+    id: usize,
+    epoch: Duration,
+    enabled: AtomicBool,
+    buffers: Mutex<Vec<Arc<Mutex<ThreadBuffer>>>>,
+    state: Mutex<FrameState>,
+}
+
+impl ProfilerSystem {
+    pub fn new() -> Self {
+        let epoch = crate::application::sys::precise_timestamp();
+
+        ProfilerSystem {
+            id: SYSTEMS.fetch_add(1, Ordering::Relaxed),
+            epoch,
+            enabled: AtomicBool::new(false),
+            buffers: Mutex::new(Vec::new()),
+            state: Mutex::new(FrameState {
+                index: 0,
+                start: Duration::from_secs(0),
+                max_frames: 60,
+                frames: VecDeque::new(),
+            }),
+        }
+    }
+
+    #[inline]
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_frames(&self, max_frames: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.max_frames = max_frames;
+
+        while state.frames.len() > max_frames {
+            state.frames.pop_front();
+        }
+    }
+
+    /// Gets the last `n` frames, from the oldest one to the latest.
+    pub fn frames(&self, n: usize) -> Vec<ProfileFrame> {
+        let state = self.state.lock().unwrap();
+        let skip = state.frames.len().saturating_sub(n);
+        state.frames.iter().skip(skip).cloned().collect()
+    }
+
+    /// Begins a scope on current thread.
+    pub fn begin(&self, name: &'static str) -> Option<PendingScope> {
+        if !self.enabled() {
+            return None;
+        }
+
+        let buffer = self.buffer();
+        let depth = {
+            let mut v = buffer.lock().unwrap();
+            v.depth += 1;
+            v.depth - 1
+        };
+
+        Some(PendingScope {
+            buffer,
+            name,
+            depth,
+            start: self.now(),
+        })
+    }
+
+    /// Ends a scope and records its duration.
+    pub fn end(&self, scope: PendingScope) {
+        let end = self.now();
+        let mut v = scope.buffer.lock().unwrap();
+        v.depth = v.depth.saturating_sub(1);
+        v.scopes.push(ProfileScope {
+            name: scope.name,
+            depth: scope.depth,
+            start: scope.start,
+            duration: end - scope.start,
+        });
+    }
+
+    /// Collects the scopes that have been ended since last frame into a new frame.
+    pub fn advance(&self) {
+        let now = self.now();
+        let mut threads = Vec::new();
+
+        {
+            let mut buffers = self.buffers.lock().unwrap();
+            for buffer in buffers.iter() {
+                let mut v = buffer.lock().unwrap();
+                if !v.scopes.is_empty() {
+                    threads.push(ProfileThread {
+                        name: v.name.clone(),
+                        scopes: std::mem::replace(&mut v.scopes, Vec::new()),
+                    });
+                }
+            }
+
+            // Removes the buffers of exited threads.
+            buffers.retain(|v| Arc::weak_count(v) > 0);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let frame = ProfileFrame {
+            index: state.index,
+            start: state.start,
+            duration: now - state.start,
+            threads,
+        };
+
+        state.index += 1;
+        state.start = now;
+
+        if self.enabled() && state.max_frames > 0 {
+            if state.frames.len() >= state.max_frames {
+                state.frames.pop_front();
+            }
+
+            state.frames.push_back(frame);
+        }
+    }
+
+    fn now(&self) -> Duration {
+        let now = crate::application::sys::precise_timestamp();
+        if now > self.epoch {
+            now - self.epoch
+        } else {
+            Duration::from_secs(0)
+        }
+    }
+
+    fn buffer(&self) -> Arc<Mutex<ThreadBuffer>> {
+        LOCAL.with(|local| {
+            let mut local = local.borrow_mut();
+            if let Some((id, ref weak)) = *local {
+                if id == self.id {
+                    if let Some(buffer) = weak.upgrade() {
+                        return buffer;
+                    }
+                }
+            }
+
+            let mut buffers = self.buffers.lock().unwrap();
+            let name = match std::thread::current().name() {
+                Some(name) => name.to_owned(),
+                None => format!("thread-{}", buffers.len()),
+            };
+
+            let buffer = Arc::new(Mutex::new(ThreadBuffer {
+                name,
+                depth: 0,
+                scopes: Vec::new(),
+            }));
+
+            buffers.push(buffer.clone());
+            *local = Some((self.id, Arc::downgrade(&buffer)));
+            buffer
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scopes() {
+        let sys = ProfilerSystem::new();
+        assert!(sys.begin("disabled").is_none());
+
+        sys.set_enabled(true);
+        sys.set_max_frames(2);
+
+        let outer = sys.begin("outer").unwrap();
+        let inner = sys.begin("inner").unwrap();
+        sys.end(inner);
+        sys.end(outer);
+        sys.advance();
+
+        let frames = sys.frames(8);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].index, 0);
+        assert_eq!(frames[0].threads.len(), 1);
+
+        let scopes = &frames[0].threads[0].scopes;
+        assert_eq!(scopes.len(), 2);
+        assert_eq!((scopes[0].name, scopes[0].depth), ("inner", 1));
+        assert_eq!((scopes[1].name, scopes[1].depth), ("outer", 0));
+        assert!(scopes[1].duration >= scopes[0].duration);
+
+        sys.advance();
+        sys.advance();
+
+        let frames = sys.frames(8);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].index, 1);
+        assert!(frames[0].threads.is_empty());
+        assert_eq!(sys.frames(1)[0].index, 2);
+    }
+}
@@ -1,6 +1,6 @@
 //! Manifest for all the AssetBundles in the build.
 
-use std::io::Read;
+use std::io::{Read, Write};
 
 use bincode;
 use inlinable_string::{InlinableString, StringExt};
@@ -25,6 +25,10 @@ pub struct ManifestItem {
 pub struct Manifest {
     pub items: Vec<ManifestItem>,
     pub buf: DataBuffer,
+    /// The content hashes of items, which are stored after the items optionally, so the
+    /// manifests without them are still readable.
+    #[serde(skip)]
+    pub hashes: Vec<u64>,
 }
 
 impl Manifest {
@@ -32,6 +36,25 @@ impl Manifest {
         Default::default()
     }
 
+    /// Adds an item, and returns its index that could be referenced as dependency.
+    pub fn add<T>(&mut self, filename: T, uuid: Uuid, dependencies: &[usize], hash: u64) -> usize
+    where
+        T: AsRef<str>,
+    {
+        let dependencies = self.buf.extend_from_slice(dependencies);
+        let filename = self.buf.extend_from_str(filename.as_ref());
+
+        self.hashes.resize(self.items.len(), 0);
+        self.hashes.push(hash);
+        self.items.push(ManifestItem {
+            filename,
+            dependencies,
+            uuid,
+        });
+
+        self.items.len() - 1
+    }
+
     pub fn load_from(mut file: &mut dyn Read) -> Result<Manifest> {
         let mut buf = [0; 16];
         file.read_exact(&mut buf[0..8])?;
@@ -41,7 +64,22 @@ impl Manifest {
             bail!("[ManifestLoader] MAGIC number not match.");
         }
 
-        Ok(bincode::deserialize_from(&mut file)?)
+        let mut manifest: Manifest = bincode::deserialize_from(&mut file)?;
+
+        let mut hashes = Vec::new();
+        file.read_to_end(&mut hashes)?;
+        if !hashes.is_empty() {
+            manifest.hashes = bincode::deserialize(&hashes)?;
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn save_to(&self, mut file: &mut dyn Write) -> Result<()> {
+        file.write_all(&MAGIC[..])?;
+        bincode::serialize_into(&mut file, self)?;
+        bincode::serialize_into(&mut file, &self.hashes)?;
+        Ok(())
     }
 }
 
@@ -63,8 +101,15 @@ impl ManfiestResolver {
         }
     }
 
-    pub fn add<T: Into<InlinableString>>(&mut self, prefix: T, file: &mut dyn Read) -> Result<()> {
+    /// Adds the manifest whose resources are located with `prefix`. Returns the resources
+    /// that were added by former manifests with different content hashes, which should be
+    /// reloaded.
+    pub fn add<T>(&mut self, prefix: T, file: &mut dyn Read) -> Result<Vec<Uuid>>
+    where
+        T: Into<InlinableString>,
+    {
         let manifest = Manifest::load_from(file)?;
+        let mut modified = Vec::new();
 
         let mut prefix = prefix.into();
         if !prefix.ends_with('/') {
@@ -76,13 +121,20 @@ impl ManfiestResolver {
             let filename = manifest.buf.as_str(v.filename);
             let fullname = format!("{}{}", prefix, filename);
 
+            // The hashes are zero if the manifests do not record them.
+            let hash = manifest.hashes.get(sub_index).cloned().unwrap_or(0);
+            match self.hash(v.uuid) {
+                Some(prev) if prev != 0 && hash != 0 && prev != hash => modified.push(v.uuid),
+                _ => {}
+            }
+
             self.uuids.insert(v.uuid, (index, sub_index));
             self.filenames.insert(fullname.into(), v.uuid);
         }
 
         self.manifests.push(manifest);
         self.manifest_prefixs.push(prefix);
-        Ok(())
+        Ok(modified)
     }
 
     /// Checks if the uuid exists in this registry.
@@ -107,6 +159,16 @@ impl ManfiestResolver {
             .map(|prefix| format!("{}/{:X}", prefix, uuid.to_simple()))
     }
 
+    /// Returns the content hash of resource, which is zero if the manifest does not record
+    /// it.
+    #[inline]
+    pub fn hash(&self, uuid: Uuid) -> Option<u64> {
+        self.uuids.get(&uuid).map(|&(index, sub_index)| {
+            let hashes = &self.manifests[index].hashes;
+            hashes.get(sub_index).cloned().unwrap_or(0)
+        })
+    }
+
     /// Return the iterator over all the dependencies of specified resource if exists.
     #[inline]
     pub fn dependencies(&self, uuid: Uuid) -> Option<Dependencies> {
@@ -115,7 +177,7 @@ impl ManfiestResolver {
                 let dependencies = manifest.items[sub_index].dependencies;
                 Dependencies {
                     index: 0,
-                    dependencies: manifest.buf.as_slice(dependencies),
+                    dependencies: manifest.buf.as_bytes(dependencies),
                     items: manifest.items.as_ref(),
                 }
             })
@@ -126,7 +188,9 @@ impl ManfiestResolver {
 /// An iterator visiting all the dependencies of specified resource.
 pub struct Dependencies<'a> {
     index: usize,
-    dependencies: &'a [usize],
+    // The indices of dependencies, which are read from bytes since the buffer might not be
+    // aligned.
+    dependencies: &'a [u8],
     items: &'a [ManifestItem],
 }
 
@@ -134,11 +198,15 @@ impl<'a> Iterator for Dependencies<'a> {
     type Item = Uuid;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.index < self.dependencies.len() {
-            self.index += 1;
-            Some(self.items[self.index - 1].uuid)
-        } else {
-            None
-        }
+        const SIZE: usize = ::std::mem::size_of::<usize>();
+
+        let bytes = self
+            .dependencies
+            .get(self.index * SIZE..(self.index + 1) * SIZE)?;
+        self.index += 1;
+
+        let mut buf = [0; SIZE];
+        buf.copy_from_slice(bytes);
+        self.items.get(usize::from_ne_bytes(buf)).map(|v| v.uuid)
     }
 }
@@ -15,16 +15,16 @@
 //! # Resource
 //!
 //! A _resource_ is a abstraction of some `piece of data` that are fully prepared for using at runtime.
-//! We are providing a command line tool [crayon-cli](https://github.com/shawnscode/crayon-tools) that
-//! automatically compiles assets into resources for runtime.
+//! We are providing a command line tool `crayon-assets` (under `tools/assets`) that automatically
+//! compiles assets into resources for runtime.
 //!
 //! ## UUID
 //!
 //! An asset can produces multiple resources eventually. For example, `FBX` file can have multiple
 //! models, and it can also contains a spatial description of objects. For every resource that an
 //! asset might produces, a universal-uniqued id (UUID) is assigned to it. UUIDs are stored in .meta
-//! files. These .meta files are generated when _crayon-cli_ first imports an asset, and are stored
-//! in the same directory as the asset.
+//! files. These .meta files are generated when _crayon-assets_ first imports an asset, and are
+//! stored in the same directory as the asset.
 //!
 //! # Virtual Filesystem (VFS)
 //!
@@ -35,11 +35,56 @@
 //! data asynchronously. A trival `Directory` is provided to supports local host filesystem.
 //! And it should be easy to add features like compression and encrpytion.
 //!
+//! The `Package` is an indexed archive of files, which could be compressed individually. It is
+//! mounted as a schema just like `Directory`, so games could be shipped without loose files.
+//!
+//! On web, files are fetched over network with `Http` instead. Both `Dir` and `Http` could be
+//! mounted at a base location, which resolves the paths of urls relatively. So the same game
+//! code works on native and web by mounting them at the same schema respectively:
+//!
+//! ```rust,ignore
+//! #[cfg(not(target_arch = "wasm32"))]
+//! params.res.schemas.add("bundle", Dir::mount("resources"));
+//! #[cfg(target_arch = "wasm32")]
+//! params.res.schemas.add("bundle", Http::mount("https://example.com/resources"));
+//!
+//! params.res.shortcuts.add("res:", "bundle:///")?;
+//! params.res.dirs.push("res:".into());
+//! ```
+//!
+//! Since `Request` implements `LatchProbe`, the request of a manifest or resource could be
+//! used to launch the application once it is completed.
+//!
 //! ## Manifest
 //!
 //! Every VFS should have a `Manifest` file which could be used to locate resources in actual path
 //! from general UUID or readable identifier. The `Manifest` file is generated after the build
-//! process of `crayon-cli`.
+//! process of `crayon-assets`, which records the content hashes of resources as well.
+//!
+//! ## Priority & Cancellation
+//!
+//! At most `ResourceParams::max_concurrent_loads` files are loading at the same time, and the
+//! other requests wait in a queue ordered by their `LoadPriority`. The `RequestHandle` returned
+//! by `res::load_with_priority` could change the priority of a pending request, or cancel it
+//! so its callback is never invoked. This makes it possible to deprioritize or abort the
+//! loading of far-away content when streaming open worlds.
+//!
+//! ## Dependencies
+//!
+//! The `Manifest` records the dependencies of resources too, e.g. the textures referenced by
+//! a material. They are created and deleted by the `ResourceLoader` of the resource which
+//! refers them, and the references to them are counted by `res::references`. And
+//! `res::ready` returns a single `Request` which completes once the resource and all of
+//! its dependencies are ready, or fails once any of them is deleted.
+//!
+//! # Hot-Reload
+//!
+//! With the `hot-reload` feature enabled, the files loaded from local host filesystem are
+//! watched. Once a file is modified on disk, the resources created from it with `ResourcePool`
+//! are reloaded, and swapped behind their existing handles.
+//!
+//! The resources are rewritten by `crayon-assets` only if the contents of their assets have
+//! changed, so rebuilding the assets while the game is running reloads the modified ones only.
 //!
 
 pub mod manifest;
@@ -48,19 +93,24 @@ pub mod shortcut;
 pub mod url;
 pub mod utils;
 pub mod vfs;
+#[cfg(feature = "hot-reload")]
+pub mod watcher;
 
 pub mod prelude {
+    pub use super::request::{LoadPriority, RequestHandle};
     pub use super::utils::prelude::ResourceState;
     pub use super::ResourceParams;
 }
 
 mod system;
+mod tracker;
 
 use uuid::Uuid;
 
 use self::inside::{ctx, CTX};
-use self::request::{Request, Response};
+use self::request::{LoadPriority, Request, RequestHandle, Response};
 use self::shortcut::ShortcutResolver;
+use self::utils::prelude::ResourceState;
 use self::vfs::SchemaResolver;
 
 #[derive(Debug, Clone)]
@@ -68,6 +118,9 @@ pub struct ResourceParams {
     pub shortcuts: ShortcutResolver,
     pub schemas: SchemaResolver,
     pub dirs: Vec<String>,
+    /// The maximum number of files that are loading concurrently, the other requests
+    /// wait in the queue by their `LoadPriority`.
+    pub max_concurrent_loads: usize,
 }
 
 impl Default for ResourceParams {
@@ -76,6 +129,7 @@ impl Default for ResourceParams {
             shortcuts: ShortcutResolver::new(),
             schemas: SchemaResolver::new(),
             dirs: Vec::new(),
+            max_concurrent_loads: 8,
         };
 
         #[cfg(not(target_arch = "wasm32"))]
@@ -111,18 +165,62 @@ pub fn exists(uuid: Uuid) -> bool {
     ctx().exists(uuid)
 }
 
-/// Loads file asynchronously with response callback.
+/// Returns all the dependencies of resource recursively.
+#[inline]
+pub fn dependencies(uuid: Uuid) -> Vec<Uuid> {
+    ctx().dependencies(uuid)
+}
+
+/// Gets the state of resource that is created from file with `ResourcePool`.
+#[inline]
+pub fn state(uuid: Uuid) -> ResourceState {
+    ctx().state(uuid)
+}
+
+/// Gets the number of alive resources that depend on this resource, directly or
+/// indirectly. The dependencies are retained once a resource is created from file with
+/// `ResourcePool`, and released once it's deleted.
 #[inline]
-pub fn load_with_callback<T>(uuid: Uuid, func: T) -> Result<(), failure::Error>
+pub fn references(uuid: Uuid) -> u32 {
+    ctx().references(uuid)
+}
+
+/// Returns a `Request` which completes once the resource and all of its dependencies
+/// are ready, or fails if any of them failed to load or have been deleted.
+#[inline]
+pub fn ready(uuid: Uuid) -> Result<Request, failure::Error> {
+    ctx().ready(uuid)
+}
+
+/// Loads file asynchronously with response callback. The returned `RequestHandle` could be
+/// used to cancel the request, the callback will never be invoked once it's cancelled.
+#[inline]
+pub fn load_with_callback<T>(uuid: Uuid, func: T) -> Result<RequestHandle, failure::Error>
 where
     T: FnOnce(Response) + Send + 'static,
 {
     ctx().load_with_callback(uuid, func)
 }
 
+/// Loads file asynchronously with priority and response callback.
+#[inline]
+pub fn load_with_priority<T>(
+    uuid: Uuid,
+    priority: LoadPriority,
+    func: T,
+) -> Result<RequestHandle, failure::Error>
+where
+    T: FnOnce(Response) + Send + 'static,
+{
+    ctx().load_with_priority(uuid, priority, func)
+}
+
 /// Loads file asynchronously with response callback.
 #[inline]
-pub fn load_from_with_callback<T1, T2>(filename: T1, func: T2) -> Result<(), failure::Error>
+pub fn load_from_with_callback<T1, T2>(
+    filename: T1,
+    func: T2,
+) -> Result<RequestHandle, failure::Error>
 where
     T1: AsRef<str>,
     T2: FnOnce(Response) + Send + 'static,
@@ -136,6 +234,14 @@ pub fn load(uuid: Uuid) -> Result<Request, failure::Error> {
     ctx().load(uuid)
 }
 
+/// Returns the resources whose files have been modified on disk since `cursor`, and
+/// advances the cursor.
+#[cfg(feature = "hot-reload")]
+#[inline]
+pub fn modified(cursor: &mut usize) -> Vec<Uuid> {
+    ctx().modified(cursor)
+}
+
 /// Loads file asynchronously. This method will returns a `Request` object immediatedly,
 /// its user's responsibility to store the object and frequently check it for completion.
 pub fn load_from<T: AsRef<str>>(filename: T) -> Result<Request, failure::Error> {
@@ -146,10 +252,12 @@ pub(crate) mod inside {
     use std::sync::Arc;
 
     use failure::ResultExt;
+    use uuid::Uuid;
 
     use crate::sched::prelude::{CountLatch, Latch};
 
     use super::system::ResourceSystem;
+    use super::utils::prelude::ResourceState;
     use super::ResourceParams;
 
     pub static mut CTX: *const ResourceSystem = std::ptr::null();
@@ -199,6 +307,13 @@ pub(crate) mod inside {
         Ok(latch)
     }
 
+    /// Updates the state of resource created from file, `None` if it has been deleted.
+    pub fn set_state(uuid: Uuid, state: Option<ResourceState>) {
+        if super::valid() {
+            ctx().set_state(uuid, state);
+        }
+    }
+
     /// Discard the resource system.
     pub unsafe fn discard() {
         if CTX.is_null() {
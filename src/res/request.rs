@@ -1,9 +1,17 @@
 //! A asynchronous loading request.
 
-use std::sync::{Arc, Mutex};
+use std::cmp::Reverse;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
 
 use crate::sched::prelude::{LatchProbe, LockLatch};
 
+use super::url::Url;
+use super::vfs::VFS;
+
 pub type Response = Result<Box<[u8]>, failure::Error>;
 
 /// A asynchronous loading request. You sould checks the completion status with
@@ -65,6 +73,38 @@ impl Request {
     }
 }
 
+/// The request could be used as the latch of `Engine::run`, which launches the application
+/// once the request is completed, no matter if it is loaded from disk or over network.
+impl LatchProbe for Request {
+    fn is_set(&self) -> bool {
+        match *self {
+            Request::NotReady(ref state) => state.is_set(),
+            Request::Ok(_) => true,
+        }
+    }
+}
+
+/// The request could be awaited in async functions, which resolves to the response.
+impl Future for Request {
+    type Output = Response;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Response> {
+        if let Request::NotReady(ref state) = *self {
+            if !state.register(cx.waker()) {
+                return Poll::Pending;
+            }
+        }
+
+        Request::poll(&mut self);
+
+        let taken = Request::err(format_err!("The response has been taken."));
+        match std::mem::replace(&mut *self, taken) {
+            Request::Ok(rsp) => Poll::Ready(rsp),
+            Request::NotReady(_) => unreachable!(),
+        }
+    }
+}
+
 impl Into<Option<Response>> for Request {
     fn into(self) -> Option<Response> {
         match self {
@@ -74,7 +114,185 @@ impl Into<Option<Response>> for Request {
     }
 }
 
-type FrameTasks = Mutex<Vec<(Request, Box<dyn FnMut(Response) + Send>)>>;
+/// The priority of loading request. The pending requests with higher priority are
+/// dispatched first, and the ones with the same priority are dispatched in FIFO order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LoadPriority {
+    /// Dispatched only if there is nothing else to load, e.g. the far-away content.
+    Background,
+    Normal,
+    High,
+    /// Dispatched at once, regardless of the limit of concurrent loads.
+    Blocking,
+}
+
+impl Default for LoadPriority {
+    fn default() -> Self {
+        LoadPriority::Normal
+    }
+}
+
+impl LoadPriority {
+    fn from_usize(v: usize) -> Self {
+        match v {
+            0 => LoadPriority::Background,
+            1 => LoadPriority::Normal,
+            2 => LoadPriority::High,
+            _ => LoadPriority::Blocking,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RequestToken {
+    priority: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+/// The handle of loading request, which could be used to change its priority or cancel
+/// it from any thread.
+#[derive(Debug, Clone)]
+pub struct RequestHandle(Arc<RequestToken>);
+
+impl RequestHandle {
+    pub fn new(priority: LoadPriority) -> Self {
+        RequestHandle(Arc::new(RequestToken {
+            priority: AtomicUsize::new(priority as usize),
+            cancelled: AtomicBool::new(false),
+        }))
+    }
+
+    #[inline]
+    pub fn priority(&self) -> LoadPriority {
+        LoadPriority::from_usize(self.0.priority.load(Ordering::Acquire))
+    }
+
+    /// Changes the priority, which takes effect if the request has not been dispatched.
+    #[inline]
+    pub fn set_priority(&self, priority: LoadPriority) {
+        self.0.priority.store(priority as usize, Ordering::Release);
+    }
+
+    /// Cancels the request. The pending IO is dropped without being dispatched, and the
+    /// callback will never be invoked. The IO in flight is completed, but its response
+    /// is discarded.
+    #[inline]
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Release);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Acquire)
+    }
+}
+
+struct PendingLoad {
+    vfs: Arc<dyn VFS>,
+    url: Url,
+    // The IO is dropped if nobody is waiting for its response.
+    state: Weak<LockLatch<Response>>,
+    handle: RequestHandle,
+    seq: usize,
+}
+
+/// The queue of IO requests to `VFS`, which dispatches at most `max` of them concurrently
+/// in the order of priorities.
+pub struct LoadQueue {
+    pending: Mutex<(usize, Vec<PendingLoad>)>,
+    inflight: Mutex<Vec<Weak<LockLatch<Response>>>>,
+    max: usize,
+}
+
+impl LoadQueue {
+    pub fn new(max: usize) -> Self {
+        LoadQueue {
+            pending: Mutex::new((0, Vec::new())),
+            inflight: Mutex::new(Vec::new()),
+            max: max.max(1),
+        }
+    }
+
+    /// Adds a request of file at `url`, it will be dispatched at once if possible.
+    pub fn add(
+        &self,
+        vfs: Arc<dyn VFS>,
+        url: Url,
+        priority: LoadPriority,
+    ) -> (Request, RequestHandle) {
+        let state = Request::latch();
+        let handle = RequestHandle::new(priority);
+
+        {
+            let mut pending = self.pending.lock().unwrap();
+            pending.0 += 1;
+
+            let load = PendingLoad {
+                vfs,
+                url,
+                state: Arc::downgrade(&state),
+                handle: handle.clone(),
+                seq: pending.0,
+            };
+
+            pending.1.push(load);
+        }
+
+        self.dispatch();
+        (Request::new(state), handle)
+    }
+
+    /// Returns the number of requests that are waiting to be dispatched.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.lock().unwrap().1.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Dispatches the pending requests with the highest priorities.
+    pub fn dispatch(&self) {
+        let mut inflight = self.inflight.lock().unwrap();
+        inflight.retain(|v| v.upgrade().map(|v| !v.is_set()).unwrap_or(false));
+
+        let mut pending = self.pending.lock().unwrap();
+        pending
+            .1
+            .retain(|v| !v.handle.is_cancelled() && v.state.upgrade().is_some());
+
+        loop {
+            let next = pending
+                .1
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, v)| (v.handle.priority(), Reverse(v.seq)))
+                .map(|(i, v)| (i, v.handle.priority()));
+
+            let i = match next {
+                Some((_, priority))
+                    if inflight.len() >= self.max && priority != LoadPriority::Blocking =>
+                {
+                    break
+                }
+                Some((i, _)) => i,
+                None => break,
+            };
+
+            let load = pending.1.swap_remove(i);
+            if let Some(state) = load.state.upgrade() {
+                inflight.push(Arc::downgrade(&state));
+
+                let (vfs, url) = (load.vfs, load.url);
+                crate::sched::spawn(move || vfs.request(&url, state));
+            }
+        }
+    }
+}
+
+type FrameTasks = Mutex<Vec<(Request, RequestHandle, Box<dyn FnMut(Response) + Send>)>>;
 
 #[derive(Default)]
 pub struct RequestQueue {
@@ -93,7 +311,10 @@ impl RequestQueue {
         }
     }
 
-    pub fn add<T: FnOnce(Response) + Send + 'static>(&self, request: Request, func: T) {
+    pub fn add<T>(&self, request: Request, handle: RequestHandle, func: T)
+    where
+        T: FnOnce(Response) + Send + 'static,
+    {
         let mut v = Some(func);
         let wrapper = move |rsp| {
             let mut w = None;
@@ -107,7 +328,7 @@ impl RequestQueue {
         self.last_frame_tasks
             .lock()
             .unwrap()
-            .push((request, Box::new(wrapper)));
+            .push((request, handle, Box::new(wrapper)));
     }
 
     pub fn advance(&self) {
@@ -121,17 +342,24 @@ impl RequestQueue {
             tasks.extend(last_frame_tasks.drain(..));
         }
 
+        // Dropping the requests of cancelled tasks drops their pending IO too.
+        tasks.retain(|v| !v.1.is_cancelled());
+
         // FIXME: Use drain_filter instead of retain and `for` iteration.
-        for (i, &mut (ref mut request, _)) in tasks.iter_mut().enumerate().rev() {
+        for (i, &mut (ref mut request, _, _)) in tasks.iter_mut().enumerate().rev() {
             if request.poll() {
                 idxes.push(i)
             }
         }
 
         for i in idxes.drain(..) {
-            let (request, mut func) = tasks.remove(i);
+            let (request, handle, mut func) = tasks.remove(i);
             let v: Option<Response> = request.into();
-            crate::sched::spawn(move || func(v.unwrap()));
+            crate::sched::spawn(move || {
+                if !handle.is_cancelled() {
+                    func(v.unwrap());
+                }
+            });
         }
     }
 }
@@ -6,10 +6,14 @@ use uuid::Uuid;
 use crate::application::prelude::{LifecycleListener, LifecycleListenerHandle};
 
 use super::manifest::ManfiestResolver;
-use super::request::{Request, RequestQueue, Response};
+use super::request::{LoadPriority, LoadQueue, Request, RequestHandle, RequestQueue, Response};
 use super::shortcut::ShortcutResolver;
+use super::tracker::Tracker;
 use super::url::Url;
+use super::utils::prelude::ResourceState;
 use super::vfs::SchemaResolver;
+#[cfg(feature = "hot-reload")]
+use super::watcher::Watcher;
 use super::ResourceParams;
 
 pub struct ResourceSystem {
@@ -17,16 +21,28 @@ pub struct ResourceSystem {
     schemas: SchemaResolver,
     manifest: RwLock<ManfiestResolver>,
     requests: Arc<RequestQueue>,
+    loads: Arc<LoadQueue>,
+    tracker: Arc<Tracker>,
     lifecycle: LifecycleListenerHandle,
+    #[cfg(feature = "hot-reload")]
+    watcher: Arc<Watcher>,
 }
 
 struct Lifecycle {
     requests: Arc<RequestQueue>,
+    loads: Arc<LoadQueue>,
+    tracker: Arc<Tracker>,
+    #[cfg(feature = "hot-reload")]
+    watcher: Arc<Watcher>,
 }
 
 impl LifecycleListener for Lifecycle {
     fn on_post_update(&mut self) -> Result<(), failure::Error> {
         self.requests.advance();
+        self.loads.dispatch();
+        self.tracker.advance();
+        #[cfg(feature = "hot-reload")]
+        self.watcher.advance();
         Ok(())
     }
 }
@@ -42,12 +58,29 @@ impl ResourceSystem {
         debug_assert!(crate::application::valid(), "");
 
         let requests = Arc::new(RequestQueue::new());
+        let loads = Arc::new(LoadQueue::new(params.max_concurrent_loads));
+        let tracker = Arc::new(Tracker::new());
+        #[cfg(feature = "hot-reload")]
+        let watcher = Arc::new(Watcher::new());
+
+        let lifecycle = Lifecycle {
+            requests: requests.clone(),
+            loads: loads.clone(),
+            tracker: tracker.clone(),
+            #[cfg(feature = "hot-reload")]
+            watcher: watcher.clone(),
+        };
+
         let sys = ResourceSystem {
             shortcut: params.shortcuts,
             schemas: params.schemas,
             manifest: RwLock::new(ManfiestResolver::new()),
-            requests: requests.clone(),
-            lifecycle: crate::application::attach(Lifecycle { requests }),
+            requests,
+            loads,
+            tracker,
+            lifecycle: crate::application::attach(lifecycle),
+            #[cfg(feature = "hot-reload")]
+            watcher,
         };
 
         Ok(sys)
@@ -64,7 +97,17 @@ impl ResourceSystem {
             .shortcut
             .resolve(prefix)
             .ok_or_else(|| format_err!("Could not resolve manifest filename: {}.", prefix))?;
-        self.manifest.write().unwrap().add(url, file)
+        let modified = self.manifest.write().unwrap().add(url, file)?;
+
+        // The resources re-compiled with different contents are reloaded.
+        #[cfg(feature = "hot-reload")]
+        for uuid in modified {
+            self.watcher.notify(uuid);
+        }
+
+        #[cfg(not(feature = "hot-reload"))]
+        let _ = modified;
+        Ok(())
     }
 
     /// Resolve shortcuts in the provided string recursively and return None if not exists.
@@ -88,15 +131,103 @@ impl ResourceSystem {
         self.manifest.read().unwrap().contains(uuid)
     }
 
+    /// Returns all the dependencies of resource recursively.
+    pub fn dependencies(&self, uuid: Uuid) -> Vec<Uuid> {
+        let manifest = self.manifest.read().unwrap();
+
+        let mut dependencies = Vec::new();
+        let mut stack = vec![uuid];
+        while let Some(v) = stack.pop() {
+            for w in manifest.dependencies(v).into_iter().flatten() {
+                if w != uuid && !dependencies.contains(&w) {
+                    dependencies.push(w);
+                    stack.push(w);
+                }
+            }
+        }
+
+        dependencies
+    }
+
+    /// Gets the state of resource that is created from file with `ResourcePool`.
+    #[inline]
+    pub fn state(&self, uuid: Uuid) -> ResourceState {
+        self.tracker.state(uuid)
+    }
+
+    /// Gets the number of alive resources that depend on this resource, directly or
+    /// indirectly.
+    #[inline]
+    pub fn references(&self, uuid: Uuid) -> u32 {
+        self.tracker.references(uuid)
+    }
+
+    pub fn set_state(&self, uuid: Uuid, state: Option<ResourceState>) {
+        match state {
+            Some(state) => {
+                if self.tracker.set(uuid, state) {
+                    self.tracker.retain(&self.dependencies(uuid));
+                }
+            }
+            None => {
+                if !self.tracker.remove(uuid) {
+                    return;
+                }
+
+                let references = self.tracker.references(uuid);
+                if references > 0 {
+                    warn!(
+                        "Resource {} is deleted while {} resources still depend on it.",
+                        uuid, references
+                    );
+                }
+
+                self.tracker.release(&self.dependencies(uuid));
+            }
+        }
+    }
+
+    /// Returns a `Request` which completes once the resource and all of its dependencies
+    /// are ready, or fails if any of them failed to load or have been deleted.
+    pub fn ready(&self, uuid: Uuid) -> Result<Request, failure::Error> {
+        if !self.exists(uuid) {
+            bail!("Could not found resource {} in this registry.", uuid);
+        }
+
+        let mut uuids = self.dependencies(uuid);
+        uuids.push(uuid);
+
+        let state = Request::latch();
+        self.tracker.add(uuids, state.clone());
+        Ok(Request::new(state))
+    }
+
     /// Loads file asynchronously with response callback.
     #[inline]
-    pub fn load_with_callback<T>(&self, uuid: Uuid, func: T) -> Result<(), failure::Error>
+    pub fn load_with_callback<T>(
+        &self,
+        uuid: Uuid,
+        func: T,
+    ) -> Result<RequestHandle, failure::Error>
     where
         T: FnOnce(Response) + Send + 'static,
     {
-        let req = self.load(uuid)?;
-        self.requests.add(req, func);
-        Ok(())
+        self.load_with_priority(uuid, LoadPriority::Normal, func)
+    }
+
+    /// Loads file asynchronously with priority and response callback.
+    pub fn load_with_priority<T>(
+        &self,
+        uuid: Uuid,
+        priority: LoadPriority,
+        func: T,
+    ) -> Result<RequestHandle, failure::Error>
+    where
+        T: FnOnce(Response) + Send + 'static,
+    {
+        let (req, handle) = self.request(uuid, priority)?;
+        self.requests.add(req, handle.clone(), func);
+        Ok(handle)
     }
 
     #[inline]
@@ -104,7 +235,7 @@ impl ResourceSystem {
         &self,
         filename: T1,
         func: T2,
-    ) -> Result<(), failure::Error>
+    ) -> Result<RequestHandle, failure::Error>
     where
         T1: AsRef<str>,
         T2: FnOnce(Response) + Send + 'static,
@@ -115,15 +246,12 @@ impl ResourceSystem {
             .resolve(&filename)
             .ok_or_else(|| format_err!("Could not resolve filename: {}.", filename))?;
         let url = Url::new(url)?;
-
-        let state = Request::latch();
-        let req = Request::new(state.clone());
-        self.requests.add(req, func);
-
         let vfs = self.schemas.locate(url.schema())?;
-        crate::sched::spawn(move || vfs.request(&url, state));
 
-        Ok(())
+        // Manifests are required to locate any other resources.
+        let (req, handle) = self.loads.add(vfs, url, LoadPriority::High);
+        self.requests.add(req, handle.clone(), func);
+        Ok(handle)
     }
 
     /// Loads file asynchronously with response callback.
@@ -132,20 +260,29 @@ impl ResourceSystem {
         &self,
         filename: T1,
         func: T2,
-    ) -> Result<(), failure::Error>
+    ) -> Result<RequestHandle, failure::Error>
     where
         T1: AsRef<str>,
         T2: FnOnce(Response) + Send + 'static,
     {
-        let filename = filename.as_ref();
-        let req = self.load_from(filename)?;
-        self.requests.add(req, func);
-        Ok(())
+        let uuid = self.locate(filename.as_ref())?;
+        self.load_with_callback(uuid, func)
     }
 
     /// Loads file asynchronously. This method will returns a `Request` object immediatedly,
     /// its user's responsibility to store the object and frequently check it for completion.
+    ///
+    /// The pending IO is dropped if the `Request` is dropped before being dispatched.
+    #[inline]
     pub fn load(&self, uuid: Uuid) -> Result<Request, failure::Error> {
+        self.request(uuid, LoadPriority::Normal).map(|v| v.0)
+    }
+
+    fn request(
+        &self,
+        uuid: Uuid,
+        priority: LoadPriority,
+    ) -> Result<(Request, RequestHandle), failure::Error> {
         let url =
             self.manifest.read().unwrap().resolve(uuid).ok_or_else(|| {
                 format_err!("Could not found resource {} in this registry.", uuid)
@@ -154,18 +291,33 @@ impl ResourceSystem {
         let url = Url::new(url)?;
         let vfs = self.schemas.locate(url.schema())?;
 
-        let state = Request::latch();
-        let req = Request::new(state.clone());
+        // Only the files on local host filesystem could be watched.
+        #[cfg(feature = "hot-reload")]
+        {
+            if url.schema() == "file" {
+                self.watcher.watch(uuid, url.path());
+            }
+        }
 
-        crate::sched::spawn(move || vfs.request(&url, state));
-        Ok(req)
+        Ok(self.loads.add(vfs, url, priority))
+    }
+
+    /// Returns the resources whose files have been modified on disk since `cursor`, and
+    /// advances the cursor.
+    #[cfg(feature = "hot-reload")]
+    #[inline]
+    pub fn modified(&self, cursor: &mut usize) -> Vec<Uuid> {
+        self.watcher.changes(cursor)
     }
 
     /// Loads file asynchronously. This method will returns a `Request` object immediatedly,
     /// its user's responsibility to store the object and frequently check it for completion.
     pub fn load_from<T: AsRef<str>>(&self, filename: T) -> Result<Request, failure::Error> {
-        let filename = filename.as_ref();
+        let uuid = self.locate(filename.as_ref())?;
+        self.load(uuid)
+    }
 
+    fn locate(&self, filename: &str) -> Result<Uuid, failure::Error> {
         let url = self
             .shortcut
             .resolve(filename)
@@ -179,6 +331,6 @@ impl ResourceSystem {
             )
         })?;
 
-        self.load(uuid)
+        Ok(uuid)
     }
 }
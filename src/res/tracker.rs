@@ -0,0 +1,176 @@
+//! Tracks the states of resources that are created from files, so the completion of
+//! resources and all of their dependencies could be checked at once. The references to
+//! dependencies are counted too, which are retained by the alive resources that depend
+//! on them.
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use uuid::Uuid;
+
+use crate::sched::prelude::LockLatch;
+use crate::utils::hash::FastHashMap;
+
+use super::request::Response;
+use super::utils::prelude::ResourceState;
+
+#[derive(Default)]
+pub struct Tracker {
+    states: RwLock<FastHashMap<Uuid, ResourceState>>,
+    requests: Mutex<Vec<(Vec<Uuid>, Arc<LockLatch<Response>>)>>,
+    references: Mutex<FastHashMap<Uuid, u32>>,
+}
+
+impl Tracker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the state of resource, and returns true if it's not tracked before.
+    #[inline]
+    pub fn set(&self, uuid: Uuid, state: ResourceState) -> bool {
+        self.states.write().unwrap().insert(uuid, state).is_none()
+    }
+
+    /// Removes the deleted resource, the pending latches which are waiting for it fail
+    /// at once. Returns true if it was tracked.
+    pub fn remove(&self, uuid: Uuid) -> bool {
+        if self.states.write().unwrap().remove(&uuid).is_none() {
+            return false;
+        }
+
+        self.requests
+            .lock()
+            .unwrap()
+            .retain(|&(ref uuids, ref latch)| {
+                if uuids.contains(&uuid) {
+                    latch.set(Err(format_err!("Resource {} has been deleted.", uuid)));
+                    return false;
+                }
+
+                true
+            });
+
+        true
+    }
+
+    /// Retains the dependencies of a resource which has been created.
+    pub fn retain(&self, dependencies: &[Uuid]) {
+        let mut references = self.references.lock().unwrap();
+        for &v in dependencies {
+            *references.entry(v).or_insert(0) += 1;
+        }
+    }
+
+    /// Releases the dependencies of a resource which has been deleted.
+    pub fn release(&self, dependencies: &[Uuid]) {
+        let mut references = self.references.lock().unwrap();
+        for v in dependencies {
+            let rc = references.get_mut(v).map(|rc| {
+                *rc -= 1;
+                *rc
+            });
+
+            if rc == Some(0) {
+                references.remove(v);
+            }
+        }
+    }
+
+    /// Gets the number of alive resources that depend on this resource.
+    #[inline]
+    pub fn references(&self, uuid: Uuid) -> u32 {
+        self.references
+            .lock()
+            .unwrap()
+            .get(&uuid)
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Gets the state of resource, which is `NotReady` if it has not been created yet.
+    #[inline]
+    pub fn state(&self, uuid: Uuid) -> ResourceState {
+        self.states
+            .read()
+            .unwrap()
+            .get(&uuid)
+            .cloned()
+            .unwrap_or(ResourceState::NotReady)
+    }
+
+    /// Adds a latch which will be set once all the resources are ready, or any of them
+    /// failed to load.
+    pub fn add(&self, uuids: Vec<Uuid>, latch: Arc<LockLatch<Response>>) {
+        self.requests.lock().unwrap().push((uuids, latch));
+    }
+
+    pub fn advance(&self) {
+        let states = self.states.read().unwrap();
+        let state = |v: &Uuid| states.get(v).cloned().unwrap_or(ResourceState::NotReady);
+
+        self.requests
+            .lock()
+            .unwrap()
+            .retain(|&(ref uuids, ref latch)| {
+                if let Some(v) = uuids.iter().find(|v| state(v) == ResourceState::Err) {
+                    latch.set(Err(format_err!("Failed to load resource {}.", v)));
+                    return false;
+                }
+
+                if uuids.iter().all(|v| state(v) == ResourceState::Ok) {
+                    latch.set(Ok(Box::new([])));
+                    return false;
+                }
+
+                true
+            });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::request::Request;
+    use super::*;
+
+    #[test]
+    fn deleted() {
+        let tracker = Tracker::new();
+        let (a, b) = (Uuid::from_bytes([1; 16]), Uuid::from_bytes([2; 16]));
+
+        assert!(tracker.set(a, ResourceState::NotReady));
+        assert!(!tracker.set(a, ResourceState::Ok));
+
+        let latch = Request::latch();
+        tracker.add(vec![a, b], latch.clone());
+        let mut request = Request::new(latch);
+
+        tracker.advance();
+        assert!(!request.poll());
+
+        // The latch fails once any of the resources is deleted.
+        assert!(tracker.remove(a));
+        assert!(!tracker.remove(a));
+        assert!(request.poll());
+        assert!(request.response().unwrap().is_err());
+    }
+
+    #[test]
+    fn references() {
+        let tracker = Tracker::new();
+        let (a, b) = (Uuid::from_bytes([1; 16]), Uuid::from_bytes([2; 16]));
+
+        tracker.retain(&[a, b]);
+        tracker.retain(&[a]);
+        assert_eq!(tracker.references(a), 2);
+        assert_eq!(tracker.references(b), 1);
+
+        tracker.release(&[a, b]);
+        assert_eq!(tracker.references(a), 1);
+        assert_eq!(tracker.references(b), 0);
+        assert!(!tracker.references.lock().unwrap().contains_key(&b));
+
+        tracker.release(&[a]);
+        tracker.release(&[a]);
+        assert_eq!(tracker.references(a), 0);
+    }
+}
@@ -2,6 +2,6 @@ pub mod pool;
 pub mod state;
 
 pub mod prelude {
-    pub use super::pool::{ResourceLoader, ResourcePool};
+    pub use super::pool::{EvictionCallback, ResourceLoader, ResourcePool};
     pub use super::state::ResourceState;
 }
@@ -24,13 +24,29 @@
 //! the resource by 1. And when you are done with the resource, its the user's responsibility to
 //! drop the ownership of the resource. And when the last ownership to a given resource is dropped,
 //! the corresponding resource is also destroyed.
+//!
+//! ## Memory Budget
+//!
+//! By default, the resource is destroyed once its last ownership is dropped. If a memory budget
+//! is set with `set_budget`, the unreferenced resources created from files are kept in cache,
+//! so creating them again is free. Once the size of resources exceeds the budget, the least
+//! recently used unreferenced resources are evicted. The sizes are reported by
+//! `ResourceLoader::size`, and the eviction could be vetoed by the callback.
+//!
+//! ## Hot-Reload
+//!
+//! With the `hot-reload` feature enabled, the resources created from files are reloaded
+//! once their files are modified on disk. The reloaded resource replaces the old one with
+//! `ResourceLoader::reload`, so the handle keeps valid.
 
 use failure::Error;
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
 use crate::utils::prelude::{FastHashMap, HandleLike, ObjectPool};
 
+use super::super::request::RequestHandle;
 use super::state::ResourceState;
 
 pub trait ResourceLoader: Send + Sync {
@@ -41,8 +57,40 @@ pub trait ResourceLoader: Send + Sync {
     fn load(&self, _: Self::Handle, _: &[u8]) -> Result<Self::Intermediate, Error>;
     fn create(&self, _: Self::Handle, _: Self::Intermediate) -> Result<Self::Resource, Error>;
     fn delete(&self, _: Self::Handle, _: Self::Resource);
+
+    /// Returns the size in bytes of the resource that would be created from intermediate,
+    /// in either video or main memory.
+    fn size(&self, _: &Self::Intermediate) -> usize {
+        0
+    }
+
+    /// Checks if the resource could be created from intermediate. It's called before
+    /// reloading, so the old resource is kept if the reloaded intermediate is invalid.
+    fn validate(&self, _: &Self::Intermediate) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Replaces the resource with the reloaded intermediate, which has been validated
+    /// already. The default implementation deletes the old resource before creating the
+    /// new one with the same handle.
+    fn reload(
+        &self,
+        handle: Self::Handle,
+        resource: Self::Resource,
+        item: Self::Intermediate,
+    ) -> Result<Self::Resource, Error>
+    where
+        Self::Handle: Copy,
+    {
+        self.delete(handle, resource);
+        self.create(handle, item)
+    }
 }
 
+/// The callback which is invoked with the uuid and size of resource before evicting it, and
+/// returns false to keep the resource in cache.
+pub type EvictionCallback = Arc<dyn Fn(Uuid, usize) -> bool + Send + Sync>;
+
 // The `ResourcePool` is a standardized resources manager that defines a set of interface for creation,
 // destruction, sharing and lifetime management. It is used in all the built-in crayon modules.
 pub struct ResourcePool<H, Loader>
@@ -51,9 +99,16 @@ where
     Loader: ResourceLoader<Handle = H> + Clone + 'static,
 {
     items: ObjectPool<H, Item<Loader::Resource>>,
-    requests: FastHashMap<H, Arc<Mutex<ResourceAsyncState<Loader::Intermediate>>>>,
+    requests: FastHashMap<H, AsyncRequest<Loader::Intermediate>>,
     registry: FastHashMap<Uuid, H>,
     loader: Loader,
+    budget: Option<usize>,
+    memory: usize,
+    // The unreferenced resources in cache, from the least recently used.
+    unreferenced: VecDeque<H>,
+    on_evict: Option<EvictionCallback>,
+    #[cfg(feature = "hot-reload")]
+    modified: usize,
 }
 
 impl<H, Loader> ResourcePool<H, Loader>
@@ -68,15 +123,25 @@ where
             registry: FastHashMap::default(),
             requests: FastHashMap::default(),
             loader,
+            budget: None,
+            memory: 0,
+            unreferenced: VecDeque::new(),
+            on_evict: None,
+            #[cfg(feature = "hot-reload")]
+            modified: 0,
         }
     }
 
     pub fn advance(&mut self) -> Result<(), Error> {
+        #[cfg(feature = "hot-reload")]
+        self.reload();
+
         let items = &mut self.items;
         let loader = &self.loader;
+        let memory = &mut self.memory;
 
         self.requests.retain(|&handle, req| {
-            let mut req = req.lock().unwrap();
+            let mut req = req.0.lock().unwrap();
             if let ResourceAsyncState::NotReady = *req {
                 return true;
             }
@@ -88,18 +153,45 @@ where
                 ResourceAsyncState::Err(err) => {
                     warn!("{:?}", err);
                     if let Some(item) = items.get_mut(handle) {
-                        item.error = Some(err);
+                        // A failed reload keeps the old resource working.
+                        if item.resource.is_none() {
+                            item.error = Some(err);
+                            Self::track(item);
+                        }
                     }
                 }
                 ResourceAsyncState::Ok(intermediate) => {
                     if let Some(item) = items.get_mut(handle) {
-                        match loader.create(handle, intermediate) {
-                            Ok(resource) => item.resource = Some(resource),
+                        if item.resource.is_some() {
+                            if let Err(err) = loader.validate(&intermediate) {
+                                warn!("Failed to reload {:?}. {:?}", handle, err);
+                                return false;
+                            }
+                        }
+
+                        let size = loader.size(&intermediate);
+                        let rsp = match item.resource.take() {
+                            Some(resource) => loader.reload(handle, resource, intermediate),
+                            None => loader.create(handle, intermediate),
+                        };
+
+                        *memory -= item.size;
+                        item.size = 0;
+
+                        match rsp {
+                            Ok(resource) => {
+                                item.resource = Some(resource);
+                                item.error = None;
+                                item.size = size;
+                                *memory += size;
+                            }
                             Err(err) => {
                                 warn!("{:?}", err);
                                 item.error = Some(err);
                             }
                         }
+
+                        Self::track(item);
                     }
                 }
                 _ => unreachable!(),
@@ -108,6 +200,7 @@ where
             false
         });
 
+        self.evict();
         Ok(())
     }
 
@@ -117,9 +210,14 @@ where
     #[inline]
     pub fn create(&mut self, params: Loader::Intermediate) -> Result<H, Error> {
         let handle = self.alloc(None);
+        let size = self.loader.size(&params);
         match self.loader.create(handle, params) {
             Ok(value) => {
-                self.items.get_mut(handle).unwrap().resource = Some(value);
+                let item = self.items.get_mut(handle).unwrap();
+                item.resource = Some(value);
+                item.size = size;
+                self.memory += size;
+                self.evict();
                 Ok(handle)
             }
             Err(error) => {
@@ -142,17 +240,54 @@ where
     #[inline]
     pub fn create_from_uuid(&mut self, uuid: Uuid) -> Result<H, Error> {
         if let Some(&handle) = self.registry.get(&uuid) {
-            self.items.get_mut(handle).unwrap().rc += 1;
+            let item = self.items.get_mut(handle).unwrap();
+            item.rc += 1;
+
+            // Revives the resource in cache.
+            if item.rc == 1 {
+                self.unreferenced.retain(|&v| v != handle);
+            }
+
             return Ok(handle);
         }
 
         let handle = self.alloc(Some(uuid));
+        if let Err(err) = self.request(handle, uuid) {
+            self.delete(handle);
+            return Err(err);
+        }
+
+        Ok(handle)
+    }
+
+    /// Reloads the resources whose files have been modified on disk. The old resources
+    /// are kept until the new ones are ready.
+    #[cfg(feature = "hot-reload")]
+    fn reload(&mut self) {
+        if !crate::res::valid() {
+            return;
+        }
+
+        for uuid in crate::res::modified(&mut self.modified) {
+            if let Some(&handle) = self.registry.get(&uuid) {
+                if self.requests.contains_key(&handle) {
+                    continue;
+                }
 
+                info!("[ResourcePool] reload {:?} ({}).", handle, uuid);
+                if let Err(err) = self.request(handle, uuid) {
+                    warn!("{:?}", err);
+                }
+            }
+        }
+    }
+
+    fn request(&mut self, handle: H, uuid: Uuid) -> Result<(), Error> {
         let rx = Arc::new(Mutex::new(ResourceAsyncState::NotReady));
         let tx = rx.clone();
         let loader = self.loader.clone();
 
-        let result = crate::res::load_with_callback(uuid, move |rsp| match rsp {
+        let request = crate::res::load_with_callback(uuid, move |rsp| match rsp {
             Ok(bytes) => {
                 let itermediate = loader.load(handle, &bytes);
 
@@ -171,39 +306,104 @@ where
             }
         });
 
-        match result {
-            Ok(_) => {
-                self.requests.insert(handle, rx);
-                Ok(handle)
-            }
-            Err(err) => {
-                self.delete(handle);
-                Err(err)
-            }
-        }
+        self.requests.insert(handle, (rx, request?));
+        Ok(())
     }
 
-    /// Deletes a resource from loadery.
+    /// Deletes a resource from loadery. The resource created from file is kept in cache if
+    /// there is a memory budget.
     pub fn delete(&mut self, handle: H) {
-        let disposed = self
-            .items
-            .get_mut(handle)
-            .map(|e| {
+        let cached = match self.items.get_mut(handle) {
+            Some(ref mut e) if e.rc > 0 => {
                 e.rc -= 1;
-                e.rc == 0
-            })
-            .unwrap_or(false);
+                if e.rc > 0 {
+                    return;
+                }
+
+                self.budget.is_some() && e.uuid.is_some() && e.resource.is_some()
+            }
+            _ => return,
+        };
+
+        if cached {
+            self.unreferenced.push_back(handle);
+            self.evict();
+        } else {
+            self.free(handle);
+        }
+    }
 
-        if disposed {
-            let e = self.items.free(handle).unwrap();
+    /// Sets the memory budget in bytes, `None` destroys the resources once they are
+    /// unreferenced.
+    pub fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
 
-            if let Some(uuid) = e.uuid {
-                self.registry.remove(&uuid);
+        if budget.is_none() {
+            while let Some(handle) = self.unreferenced.pop_front() {
+                self.free(handle);
             }
+        }
+
+        self.evict();
+    }
+
+    #[inline]
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
 
-            if let Some(resource) = e.resource {
-                self.loader.delete(handle, resource);
+    /// Sets the callback that could veto the eviction of resources.
+    #[inline]
+    pub fn set_eviction_callback(&mut self, callback: Option<EvictionCallback>) {
+        self.on_evict = callback;
+    }
+
+    /// Returns the size in bytes of all the resources alive, including the ones in cache.
+    #[inline]
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    // Evicts the least recently used resources until the memory is within budget.
+    fn evict(&mut self) {
+        let budget = match self.budget {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        let mut i = 0;
+        while self.memory > budget && i < self.unreferenced.len() {
+            let handle = self.unreferenced[i];
+            let item = self.items.get(handle).unwrap();
+
+            if let (Some(uuid), Some(ref callback)) = (item.uuid, self.on_evict.as_ref()) {
+                if !callback(uuid, item.size) {
+                    i += 1;
+                    continue;
+                }
             }
+
+            self.unreferenced.remove(i);
+            self.free(handle);
+        }
+    }
+
+    fn free(&mut self, handle: H) {
+        let e = self.items.free(handle).unwrap();
+        self.memory -= e.size;
+
+        // There is no need to load the resource nobody refers to.
+        if let Some((_, request)) = self.requests.remove(&handle) {
+            request.cancel();
+        }
+
+        if let Some(uuid) = e.uuid {
+            self.registry.remove(&uuid);
+            crate::res::inside::set_state(uuid, None);
+        }
+
+        if let Some(resource) = e.resource {
+            self.loader.delete(handle, resource);
         }
     }
 
@@ -230,6 +430,12 @@ where
         self.items.contains(handle)
     }
 
+    /// Gets the uuid of resource, if it is created from file.
+    #[inline]
+    pub fn uuid(&self, handle: H) -> Option<Uuid> {
+        self.items.get(handle).and_then(|e| e.uuid)
+    }
+
     /// Return immutable reference to internal value with name `Handle`.
     #[inline]
     pub fn resource(&self, handle: H) -> Option<&Loader::Resource> {
@@ -249,16 +455,31 @@ where
             uuid,
             resource: None,
             error: None,
+            size: 0,
         };
 
         let handle = self.items.create(entry);
 
         if let Some(uuid) = uuid {
             self.registry.insert(uuid, handle);
+            crate::res::inside::set_state(uuid, Some(ResourceState::NotReady));
         }
 
         handle
     }
+
+    // Reports the state of resource created from file to the resource system.
+    fn track(item: &Item<Loader::Resource>) {
+        if let Some(uuid) = item.uuid {
+            let state = if item.resource.is_some() {
+                ResourceState::Ok
+            } else {
+                ResourceState::Err
+            };
+
+            crate::res::inside::set_state(uuid, Some(state));
+        }
+    }
 }
 
 struct Item<T> {
@@ -266,10 +487,134 @@ struct Item<T> {
     uuid: Option<Uuid>,
     resource: Option<T>,
     error: Option<Error>,
+    size: usize,
 }
 
+type AsyncRequest<T> = (Arc<Mutex<ResourceAsyncState<T>>>, RequestHandle);
+
 enum ResourceAsyncState<T> {
     Ok(T),
     Err(Error),
     NotReady,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::res::request::LoadPriority;
+    use crate::utils::handle::Handle;
+
+    #[derive(Clone)]
+    struct Loader;
+
+    impl ResourceLoader for Loader {
+        type Handle = Handle;
+        type Intermediate = usize;
+        type Resource = usize;
+
+        fn load(&self, _: Handle, _: &[u8]) -> Result<usize, Error> {
+            unreachable!();
+        }
+
+        fn create(&self, _: Handle, size: usize) -> Result<usize, Error> {
+            Ok(size)
+        }
+
+        fn delete(&self, _: Handle, _: usize) {}
+
+        fn size(&self, size: &usize) -> usize {
+            *size
+        }
+
+        fn validate(&self, size: &usize) -> Result<(), Error> {
+            if *size == 0 {
+                return Err(format_err!("Empty resource."));
+            }
+
+            Ok(())
+        }
+    }
+
+    // Simulates the resource that has been loaded from file.
+    fn loaded(pool: &mut ResourcePool<Handle, Loader>, i: u8, size: usize) -> Handle {
+        let handle = pool.alloc(Some(Uuid::from_bytes([i; 16])));
+        pool.items.get_mut(handle).unwrap().resource = Some(size);
+        pool.items.get_mut(handle).unwrap().size = size;
+        pool.memory += size;
+        handle
+    }
+
+    #[test]
+    fn budget() {
+        let mut pool = ResourcePool::new(Loader);
+        let runtime = pool.create(4).unwrap();
+        assert_eq!(pool.memory(), 4);
+
+        pool.delete(runtime);
+        assert!(!pool.contains(runtime));
+        assert_eq!(pool.memory(), 0);
+
+        pool.set_budget(Some(12));
+        let a = loaded(&mut pool, 1, 4);
+        let b = loaded(&mut pool, 2, 4);
+        let c = loaded(&mut pool, 3, 4);
+
+        // Unreferenced resources are kept in cache within budget.
+        pool.delete(a);
+        pool.delete(b);
+        assert!(pool.contains(a));
+        assert_eq!(pool.memory(), 12);
+
+        pool.set_budget(Some(10));
+        assert!(!pool.contains(a));
+        assert!(pool.contains(b));
+        assert_eq!(pool.memory(), 8);
+
+        // Revives `b` from cache, so `c` is the least recently used one.
+        assert_eq!(pool.create_from_uuid(Uuid::from_bytes([2; 16])).unwrap(), b);
+        pool.delete(c);
+        pool.delete(b);
+
+        pool.set_eviction_callback(Some(Arc::new(|uuid, _| uuid != Uuid::from_bytes([3; 16]))));
+        pool.set_budget(Some(0));
+        assert!(pool.contains(c));
+        assert!(!pool.contains(b));
+
+        pool.set_budget(None);
+        assert!(!pool.contains(c));
+        assert_eq!(pool.memory(), 0);
+    }
+
+    // Simulates the response of reloading request.
+    fn reloaded(pool: &mut ResourcePool<Handle, Loader>, h: Handle, rsp: Result<usize, Error>) {
+        let state = match rsp {
+            Ok(v) => ResourceAsyncState::Ok(v),
+            Err(err) => ResourceAsyncState::Err(err),
+        };
+
+        let request = RequestHandle::new(LoadPriority::default());
+        let state = Arc::new(Mutex::new(state));
+        pool.requests.insert(h, (state, request));
+        pool.advance().unwrap();
+    }
+
+    #[test]
+    fn reload() {
+        let mut pool = ResourcePool::new(Loader);
+        let handle = loaded(&mut pool, 1, 4);
+
+        reloaded(&mut pool, handle, Ok(8));
+        assert_eq!(pool.resource(handle), Some(&8));
+        assert_eq!(pool.memory(), 8);
+
+        // The old resource is kept if the reloading fails.
+        reloaded(&mut pool, handle, Err(format_err!("Missing file.")));
+        assert_eq!(pool.state(handle), ResourceState::Ok);
+        assert_eq!(pool.resource(handle), Some(&8));
+
+        reloaded(&mut pool, handle, Ok(0));
+        assert_eq!(pool.state(handle), ResourceState::Ok);
+        assert_eq!(pool.resource(handle), Some(&8));
+        assert_eq!(pool.memory(), 8);
+    }
+}
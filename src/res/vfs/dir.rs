@@ -1,22 +1,42 @@
 use std::fs;
 use std::io::Read;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::sched::prelude::LockLatch;
 
 use super::super::request::Response;
 use super::super::url::Url;
-use super::VFS;
+use super::{normalize, VFS};
 
-#[derive(Debug, Default, Clone, Copy)]
-pub struct Dir {}
+/// The local host filesystem.
+///
+/// By default, the paths of urls are used as locations directly. If it is mounted at a
+/// directory, the paths are resolved relatively to that directory, e.g. `res:///a.bin`
+/// locates at `{dir}/a.bin`.
+#[derive(Debug, Default, Clone)]
+pub struct Dir {
+    base: Option<PathBuf>,
+}
 
 impl Dir {
     pub fn new() -> Self {
-        Dir {}
+        Dir { base: None }
+    }
+
+    /// Mounts the directory at `base`.
+    pub fn mount<T: Into<PathBuf>>(base: T) -> Self {
+        Dir {
+            base: Some(base.into()),
+        }
     }
 
-    fn load_from(self, location: &str) -> Response {
+    fn load_from(&self, url: &Url) -> Response {
+        let location = match self.base {
+            Some(ref base) => base.join(normalize(url.path())),
+            None => PathBuf::from(url.path()),
+        };
+
         let mut file = fs::File::open(location)?;
         let mut buf = Vec::new();
         file.read_to_end(&mut buf)?;
@@ -26,7 +46,7 @@ impl Dir {
 
 impl VFS for Dir {
     fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
-        let response = self.load_from(url.path());
+        let response = self.load_from(url);
         state.set(response);
     }
 }
@@ -11,24 +11,59 @@ use crate::sched::prelude::LockLatch;
 
 use super::super::request::Response;
 use super::super::url::Url;
-use super::VFS;
+use super::{normalize, VFS};
 
-#[derive(Debug, Clone, Copy)]
-pub struct Http {}
+/// The files fetched over network with `XMLHttpRequest`.
+///
+/// By default, urls are requested as they are. If it is mounted at a base url, the paths
+/// of urls are requested relatively to that base url, e.g. `res:///a.bin` is fetched from
+/// `{base}/a.bin`. So the same urls could be used on both native and web, by mounting
+/// `Dir` and `Http` at the same schema respectively.
+#[derive(Debug, Default, Clone)]
+pub struct Http {
+    base: Option<String>,
+}
 
 impl Http {
     pub fn new() -> Self {
-        Http {}
+        Http { base: None }
+    }
+
+    /// Mounts at the `base` url, e.g. `https://example.com/assets`.
+    pub fn mount<T: Into<String>>(base: T) -> Self {
+        let mut base = base.into();
+        while base.ends_with('/') {
+            base.pop();
+        }
+
+        Http { base: Some(base) }
+    }
+
+    fn location(&self, url: &Url) -> String {
+        match self.base {
+            Some(ref base) => format!("{}/{}", base, normalize(url.path())),
+            None => url.to_string(),
+        }
     }
 }
 
 impl VFS for Http {
     fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        let location = self.location(url);
         let xhr = Rc::new(RefCell::new(Xhr::new(state)));
-        let clone = xhr.clone();
 
+        let clone = xhr.clone();
+        let name = location.clone();
         xhr.borrow_mut().on_load = Some(Closure::wrap(Box::new(move || {
             let xhr = clone.borrow();
+
+            let status = xhr.inner.status().unwrap_or(0);
+            if status < 200 || status >= 300 {
+                let err = format_err!("Failed to fetch {} with HTTP status {}.", name, status);
+                xhr.state.set(Err(err));
+                return;
+            }
+
             let rsp = xhr.inner.response().unwrap();
             let array = Uint8Array::new(&rsp);
 
@@ -39,6 +74,13 @@ impl VFS for Http {
             xhr.state.set(Ok(bytes.into_boxed_slice()));
         })));
 
+        let clone = xhr.clone();
+        let name = location.clone();
+        xhr.borrow_mut().on_error = Some(Closure::wrap(Box::new(move || {
+            let err = format_err!("Failed to fetch {}.", name);
+            clone.borrow().state.set(Err(err));
+        })));
+
         {
             let xhr = xhr.borrow();
 
@@ -48,10 +90,16 @@ impl VFS for Http {
                     .unwrap();
             }
 
+            if let Some(closure) = xhr.on_error.as_ref() {
+                (xhr.inner.as_ref() as &web_sys::EventTarget)
+                    .add_event_listener_with_callback("error", closure.as_ref().unchecked_ref())
+                    .unwrap();
+            }
+
             let ty = XmlHttpRequestResponseType::Arraybuffer;
             xhr.inner.set_response_type(ty);
 
-            xhr.inner.open_with_async("Get", url, true).unwrap();
+            xhr.inner.open_with_async("GET", &location, true).unwrap();
             xhr.inner.send().unwrap();
         }
     }
@@ -60,6 +108,7 @@ impl VFS for Http {
 struct Xhr {
     inner: XmlHttpRequest,
     on_load: Option<Closure<FnMut()>>,
+    on_error: Option<Closure<FnMut()>>,
     state: Arc<LockLatch<Response>>,
 }
 
@@ -69,6 +118,7 @@ impl Xhr {
             inner: XmlHttpRequest::new().unwrap(),
             state: state,
             on_load: None,
+            on_error: None,
         }
     }
 }
@@ -2,6 +2,7 @@
 pub mod dir;
 #[cfg(target_arch = "wasm32")]
 pub mod http;
+pub mod package;
 
 use std::sync::Arc;
 
@@ -15,6 +16,24 @@ pub trait VFS: std::fmt::Debug + Send + Sync + 'static {
     fn request(&self, url: &Url, state: Arc<LockLatch<Response>>);
 }
 
+/// Removes the leading and duplicated slashes of path, and resolves the `.` and `..`
+/// components, which makes it relative to the location that a `VFS` is mounted at. The
+/// parent components never go above that location.
+pub(crate) fn normalize(path: &str) -> String {
+    let mut components = Vec::new();
+    for v in path.split('/') {
+        match v {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            _ => components.push(v),
+        }
+    }
+
+    components.join("/")
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct SchemaResolver {
     schemas: FastHashMap<String, Arc<VFS>>,
@@ -42,3 +61,17 @@ impl SchemaResolver {
         Ok(vfs.clone())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalize_path() {
+        assert_eq!(normalize("/a//b/"), "a/b");
+        assert_eq!(normalize("./a/./b"), "a/b");
+        assert_eq!(normalize("a/../b/c/.."), "b");
+        assert_eq!(normalize("../../a"), "a");
+        assert_eq!(normalize("a/b/../../.."), "");
+    }
+}
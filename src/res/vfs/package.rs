@@ -0,0 +1,240 @@
+//! An indexed archive of files, which could be mounted alongside `Dir` to ship resources
+//! as a single file.
+//!
+//! The archive starts with `MAGIC`, followed by the length of index and the index itself
+//! serialized with bincode, and then the data of files. Every file could be compressed
+//! with DEFLATE individually.
+//!
+//! ```rust,ignore
+//! let mut builder = PackageBuilder::new();
+//! builder.add("MANIFEST", manifest, Compression::None);
+//! builder.add("0A1B2C...", texture, Compression::Deflate);
+//! builder.write(&mut fs::File::create("resources.pack")?)?;
+//!
+//! // Resolves `res:` into the files of archive, e.g. `res:MANIFEST`.
+//! params.res.schemas.add("pack", Package::open("resources.pack")?);
+//! params.res.shortcuts.add("res:", "pack:///")?;
+//! params.res.dirs.push("res:".into());
+//! ```
+
+use std::io::{Cursor, Read, Write};
+use std::sync::Arc;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+use crate::errors::*;
+use crate::sched::prelude::LockLatch;
+use crate::utils::hash::FastHashMap;
+
+use super::super::request::Response;
+use super::super::url::Url;
+use super::{normalize, VFS};
+
+pub const MAGIC: [u8; 8] = [b'P', b'A', b'C', b'K', b' ', 0, 0, 1];
+
+/// The compression method of file in archive.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Deflate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct PackageEntry {
+    offset: u64,
+    len: u64,
+    compression: Compression,
+}
+
+#[derive(Debug)]
+enum PackageSource {
+    #[cfg(not(target_arch = "wasm32"))]
+    File(std::path::PathBuf),
+    Memory(Box<[u8]>),
+}
+
+/// A read-only archive of files.
+///
+/// The index is loaded into memory when opening. Requests of files on disk open their
+/// own file handles, so they are served concurrently without locking the archive.
+#[derive(Debug, Clone)]
+pub struct Package {
+    source: Arc<PackageSource>,
+    entries: Arc<FastHashMap<String, PackageEntry>>,
+}
+
+impl Package {
+    /// Opens the archive on local host filesystem.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open<T: AsRef<std::path::Path>>(path: T) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = std::fs::File::open(path)?;
+        let size = file.metadata()?.len();
+        let entries = Self::parse(&mut file, size)?;
+
+        Ok(Package {
+            source: Arc::new(PackageSource::File(path.to_owned())),
+            entries: Arc::new(entries),
+        })
+    }
+
+    /// Creates the archive from bytes in memory.
+    pub fn from_bytes<T: Into<Box<[u8]>>>(bytes: T) -> Result<Self> {
+        let bytes = bytes.into();
+        let entries = Self::parse(&mut Cursor::new(&bytes), bytes.len() as u64)?;
+
+        Ok(Package {
+            source: Arc::new(PackageSource::Memory(bytes)),
+            entries: Arc::new(entries),
+        })
+    }
+
+    /// Checks if there is a file with `name` in this archive.
+    #[inline]
+    pub fn contains<T: AsRef<str>>(&self, name: T) -> bool {
+        self.entries.contains_key(&normalize(name.as_ref()))
+    }
+
+    /// Reads the file with `name`, which is decompressed if required.
+    pub fn read<T: AsRef<str>>(&self, name: T) -> Result<Box<[u8]>> {
+        let name = normalize(name.as_ref());
+        let entry = self
+            .entries
+            .get(&name)
+            .ok_or_else(|| format_err!("Could not found {} in package.", name))?;
+
+        // The bounds of entries have been checked when parsing, but the file might be
+        // modified since then. So the buffer grows with the data actually read.
+        let buf = match *self.source {
+            #[cfg(not(target_arch = "wasm32"))]
+            PackageSource::File(ref path) => {
+                use std::io::{Seek, SeekFrom};
+
+                let mut file = std::fs::File::open(path)?;
+                file.seek(SeekFrom::Start(entry.offset))?;
+
+                let mut buf = Vec::new();
+                file.take(entry.len).read_to_end(&mut buf)?;
+                if buf.len() as u64 != entry.len {
+                    bail!("The data of {} is out of bounds.", name);
+                }
+
+                buf
+            }
+            PackageSource::Memory(ref bytes) => {
+                let from = entry.offset as usize;
+                let to = from + entry.len as usize;
+                bytes[from..to].to_vec()
+            }
+        };
+
+        match entry.compression {
+            Compression::None => Ok(buf.into_boxed_slice()),
+            Compression::Deflate => {
+                let mut bytes = Vec::new();
+                DeflateDecoder::new(&buf[..]).read_to_end(&mut bytes)?;
+                Ok(bytes.into_boxed_slice())
+            }
+        }
+    }
+
+    // Parses the index of archive, whose lengths are checked against the `size` of
+    // archive in bytes.
+    fn parse(file: &mut dyn Read, size: u64) -> Result<FastHashMap<String, PackageEntry>> {
+        let mut buf = [0; 8];
+        file.read_exact(&mut buf)?;
+
+        if buf != MAGIC {
+            bail!("[Package] MAGIC number not match.");
+        }
+
+        let len = file.read_u64::<LittleEndian>()?;
+        let data = (MAGIC.len() as u64 + 8)
+            .checked_add(len)
+            .filter(|&v| v <= size)
+            .ok_or_else(|| format_err!("[Package] The index is out of bounds."))?;
+
+        let mut index = Vec::new();
+        file.take(len).read_to_end(&mut index)?;
+
+        let entries: Vec<(String, PackageEntry)> = bincode::deserialize(&index)?;
+        let mut v = FastHashMap::default();
+        for (name, mut entry) in entries {
+            let offset = entry.offset.checked_add(data);
+            match offset.and_then(|v| v.checked_add(entry.len)) {
+                Some(end) if end <= size => entry.offset = offset.unwrap(),
+                _ => bail!("[Package] The data of {} is out of bounds.", name),
+            }
+
+            v.insert(name, entry);
+        }
+
+        Ok(v)
+    }
+}
+
+impl VFS for Package {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        state.set(self.read(url.path()));
+    }
+}
+
+/// The builder of archive.
+#[derive(Default)]
+pub struct PackageBuilder {
+    files: Vec<(String, Compression, Vec<u8>)>,
+}
+
+impl PackageBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a file into archive, which replaces the one with the same name.
+    pub fn add<T1, T2>(&mut self, name: T1, bytes: T2, compression: Compression)
+    where
+        T1: AsRef<str>,
+        T2: Into<Vec<u8>>,
+    {
+        let name = normalize(name.as_ref());
+        self.files.retain(|v| v.0 != name);
+        self.files.push((name, compression, bytes.into()));
+    }
+
+    /// Writes the archive.
+    pub fn write(&self, file: &mut dyn Write) -> Result<()> {
+        let mut entries = Vec::with_capacity(self.files.len());
+        let mut data = Vec::new();
+
+        for &(ref name, compression, ref bytes) in &self.files {
+            let offset = data.len() as u64;
+            match compression {
+                Compression::None => data.extend_from_slice(bytes),
+                Compression::Deflate => {
+                    let mut encoder = DeflateEncoder::new(data, flate2::Compression::default());
+                    encoder.write_all(bytes)?;
+                    data = encoder.finish()?;
+                }
+            }
+
+            let len = data.len() as u64 - offset;
+            entries.push((
+                name.clone(),
+                PackageEntry {
+                    offset,
+                    len,
+                    compression,
+                },
+            ));
+        }
+
+        let index = bincode::serialize(&entries)?;
+        file.write_all(&MAGIC)?;
+        file.write_u64::<LittleEndian>(index.len() as u64)?;
+        file.write_all(&index)?;
+        file.write_all(&data)?;
+        Ok(())
+    }
+}
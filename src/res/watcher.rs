@@ -0,0 +1,127 @@
+//! A polling file watcher, which records the resources whose files are modified on disk
+//! for hot-reloading.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+
+use uuid::Uuid;
+
+use crate::utils::hash::FastHashMap;
+
+/// The number of frames between two scans of the watched files.
+pub const SCAN_INTERVAL: u32 = 30;
+
+/// The maximum number of changes kept for the pools which have not polled them yet.
+pub const MAX_CHANGES: usize = 1024;
+
+#[derive(Default)]
+pub struct Watcher {
+    files: Mutex<FastHashMap<Uuid, (String, Option<SystemTime>)>>,
+    // The number of changes that have been discarded, and the recent changes.
+    changes: RwLock<(usize, VecDeque<Uuid>)>,
+    frames: Mutex<u32>,
+}
+
+impl Watcher {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Watches the file at `path` that the resource is loaded from.
+    pub fn watch<T: Into<String>>(&self, uuid: Uuid, path: T) {
+        let mut files = self.files.lock().unwrap();
+        if !files.contains_key(&uuid) {
+            let path = path.into();
+            let modified = Self::modified(&path);
+            files.insert(uuid, (path, modified));
+        }
+    }
+
+    /// Scans the watched files every `SCAN_INTERVAL` frames, and records the resources
+    /// whose files have been modified since last scan.
+    pub fn advance(&self) {
+        {
+            let mut frames = self.frames.lock().unwrap();
+            *frames += 1;
+            if *frames < SCAN_INTERVAL {
+                return;
+            }
+
+            *frames = 0;
+        }
+
+        let mut files = self.files.lock().unwrap();
+        for (&uuid, &mut (ref path, ref mut modified)) in files.iter_mut() {
+            let v = Self::modified(path);
+            if v.is_some() && v != *modified {
+                info!("[Watcher] {} ({}) is modified.", path, uuid);
+                *modified = v;
+                self.push(uuid);
+            }
+        }
+    }
+
+    /// Returns the resources that have been modified since `cursor`, and advances the
+    /// cursor to the latest change. Only the recent `MAX_CHANGES` changes are returned if
+    /// the cursor falls behind.
+    pub fn changes(&self, cursor: &mut usize) -> Vec<Uuid> {
+        let changes = self.changes.read().unwrap();
+        let (discarded, ref recent) = *changes;
+        let v = recent
+            .iter()
+            .skip(cursor.saturating_sub(discarded))
+            .cloned()
+            .collect();
+        *cursor = discarded + recent.len();
+        v
+    }
+
+    /// Records that the resource has been modified, e.g. it's re-compiled with different
+    /// contents.
+    #[inline]
+    pub fn notify(&self, uuid: Uuid) {
+        self.push(uuid);
+    }
+
+    fn push(&self, uuid: Uuid) {
+        let mut changes = self.changes.write().unwrap();
+        if changes.1.len() >= MAX_CHANGES {
+            changes.1.pop_front();
+            changes.0 += 1;
+        }
+
+        changes.1.push_back(uuid);
+    }
+
+    fn modified(path: &str) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|v| v.modified()).ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let watcher = Watcher::new();
+        let uuid = |i: usize| Uuid::from_bytes([i as u8; 16]);
+
+        for i in 0..MAX_CHANGES + 2 {
+            watcher.push(uuid(i));
+        }
+
+        // The cursors which fall behind get the recent changes only.
+        let mut cursor = 0;
+        let v = watcher.changes(&mut cursor);
+        assert_eq!(v.len(), MAX_CHANGES);
+        assert_eq!(v[0], uuid(2));
+        assert_eq!(cursor, MAX_CHANGES + 2);
+        assert!(watcher.changes(&mut cursor).is_empty());
+
+        let mut cursor = MAX_CHANGES + 1;
+        assert_eq!(watcher.changes(&mut cursor), vec![uuid(MAX_CHANGES + 1)]);
+    }
+}
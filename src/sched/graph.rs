@@ -0,0 +1,172 @@
+use std::any::Any;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::errors::*;
+
+use super::latch::LockLatch;
+use super::task::Task;
+use super::unwind;
+
+/// The identifier of a job in `JobGraph`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct JobId(usize);
+
+/// A graph of jobs and the dependencies between them. A job is executed once all of its
+/// dependencies have finished, and the independent jobs run in parallel.
+///
+/// ```rust,ignore
+/// let mut graph = JobGraph::new();
+/// let animation = graph.add(|| update_animations());
+/// let physics = graph.add(|| step_physics());
+/// let culling = graph.add(|| cull_objects());
+/// graph.depend(culling, animation);
+/// graph.depend(culling, physics);
+///
+/// graph.spawn()?.wait();
+/// ```
+#[derive(Default)]
+pub struct JobGraph {
+    jobs: Vec<Box<dyn FnOnce() + Send>>,
+    dependencies: Vec<(JobId, JobId)>,
+}
+
+impl JobGraph {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a job into the graph.
+    pub fn add<F>(&mut self, func: F) -> JobId
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.jobs.push(Box::new(func));
+        JobId(self.jobs.len() - 1)
+    }
+
+    /// Makes `job` wait until `dependency` has finished.
+    pub fn depend(&mut self, job: JobId, dependency: JobId) {
+        self.dependencies.push((job, dependency));
+    }
+
+    /// Gets the number of jobs in the graph.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Checks if the graph has no job.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Spawns the jobs in the global `Scheduler`, and returns the task that finishes once
+    /// all the jobs have finished. It fails if the dependencies are cyclic.
+    ///
+    /// If any job panicked, the jobs that have not started yet are skipped, and the panic
+    /// is propagated to the one who waits for the task.
+    pub fn spawn(self) -> Result<Task<()>> {
+        let len = self.jobs.len();
+        let mut dependents = vec![Vec::new(); len];
+        let mut pendings = vec![0; len];
+
+        for &(job, dependency) in &self.dependencies {
+            if job.0 >= len || dependency.0 >= len {
+                bail!(
+                    "Undefined job in the dependency {:?} -> {:?}.",
+                    job,
+                    dependency
+                );
+            }
+
+            dependents[dependency.0].push(job.0);
+            pendings[job.0] += 1;
+        }
+
+        // Checks the cycles with the topological sort.
+        let mut counters = pendings.clone();
+        let mut stack: Vec<_> = (0..len).filter(|&v| counters[v] == 0).collect();
+        let mut sorted = 0;
+        while let Some(v) = stack.pop() {
+            sorted += 1;
+            for &w in &dependents[v] {
+                counters[w] -= 1;
+                if counters[w] == 0 {
+                    stack.push(w);
+                }
+            }
+        }
+
+        if sorted != len {
+            bail!("The dependencies of jobs are cyclic.");
+        }
+
+        let latch = Arc::new(LockLatch::new());
+        let task = Task::new(latch.clone());
+
+        if len == 0 {
+            latch.set(Ok(()));
+            return Ok(task);
+        }
+
+        let graph = Arc::new(GraphState {
+            jobs: self.jobs.into_iter().map(|v| Mutex::new(Some(v))).collect(),
+            pendings: pendings.iter().map(|&v| AtomicUsize::new(v)).collect(),
+            dependents,
+            remains: AtomicUsize::new(len),
+            panic: Mutex::new(None),
+            latch,
+        });
+
+        for (i, &v) in pendings.iter().enumerate() {
+            if v == 0 {
+                GraphState::spawn(&graph, i);
+            }
+        }
+
+        Ok(task)
+    }
+}
+
+struct GraphState {
+    jobs: Vec<Mutex<Option<Box<dyn FnOnce() + Send>>>>,
+    pendings: Vec<AtomicUsize>,
+    dependents: Vec<Vec<usize>>,
+    remains: AtomicUsize,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+    latch: Arc<LockLatch<std::thread::Result<()>>>,
+}
+
+impl GraphState {
+    fn spawn(graph: &Arc<GraphState>, index: usize) {
+        let graph = graph.clone();
+        super::inside::ctx().spawn(move || graph.execute(index));
+    }
+
+    fn execute(self: Arc<Self>, index: usize) {
+        let func = self.jobs[index].lock().unwrap().take().unwrap();
+        if self.panic.lock().unwrap().is_none() {
+            if let Err(err) = unwind::halt_unwinding(func) {
+                let mut panic = self.panic.lock().unwrap();
+                if panic.is_none() {
+                    *panic = Some(err);
+                }
+            }
+        }
+
+        for &v in &self.dependents[index] {
+            if self.pendings[v].fetch_sub(1, Ordering::SeqCst) == 1 {
+                GraphState::spawn(&self, v);
+            }
+        }
+
+        if self.remains.fetch_sub(1, Ordering::SeqCst) == 1 {
+            match self.panic.lock().unwrap().take() {
+                Some(err) => self.latch.set(Err(err)),
+                None => self.latch.set(Ok(())),
+            }
+        }
+    }
+}
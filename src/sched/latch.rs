@@ -1,5 +1,6 @@
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Condvar, Mutex};
+use std::task::Waker;
 
 /// We define various kinds of latches, which are all a primitive signaling
 /// mechanism. A latch starts as false. Eventually someone calls `set()` and
@@ -73,6 +74,8 @@ impl LatchProbe for SpinLatch {
 pub struct LockLatch<T> {
     m: Mutex<Option<T>>,
     v: Condvar,
+    // The task that is waiting for the latch asynchronously.
+    waker: Mutex<Option<Waker>>,
 }
 
 impl<T> Default for LockLatch<T> {
@@ -80,10 +83,19 @@ impl<T> Default for LockLatch<T> {
         LockLatch {
             m: Mutex::new(None),
             v: Condvar::new(),
+            waker: Mutex::new(None),
         }
     }
 }
 
+impl<T> std::fmt::Debug for LockLatch<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("LockLatch")
+            .field("set", &self.is_set())
+            .finish()
+    }
+}
+
 impl<T> LockLatch<T> {
     #[inline]
     pub fn new() -> LockLatch<T> {
@@ -95,6 +107,22 @@ impl<T> LockLatch<T> {
         let mut guard = self.m.lock().unwrap();
         *guard = Some(v);
         self.v.notify_all();
+
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    /// Checks if the latch is set, or registers `waker` to be woken once it's set. Only
+    /// the waker of latest call is kept.
+    pub fn register(&self, waker: &Waker) -> bool {
+        let guard = self.m.lock().unwrap();
+        if guard.is_some() {
+            return true;
+        }
+
+        *self.waker.lock().unwrap() = Some(waker.clone());
+        false
     }
 
     #[inline]
@@ -109,9 +137,7 @@ impl<T> LockLatch<T> {
 impl Latch for LockLatch<()> {
     #[inline]
     fn set(&self) {
-        let mut guard = self.m.lock().unwrap();
-        *guard = Some(());
-        self.v.notify_all();
+        LockLatch::set(self, ());
     }
 }
 
@@ -1,18 +1,59 @@
+//! A work-stealing job system, which runs the jobs on a pool of worker threads.
+//!
+//! The jobs are spawned with `sched::spawn`, or `sched::spawn_task` which returns a `Task`
+//! that could be waited for the result:
+//!
+//! ```rust,ignore
+//! let task = sched::spawn_task(|| build_navmesh(&terrain));
+//! // ...
+//! let navmesh = task.wait();
+//! ```
+//!
+//! The borrowed data could be processed in parallel with the fork-join `sched::scope`, or
+//! simply with `sched::parallel_for` over a slice:
+//!
+//! ```rust,ignore
+//! sched::parallel_for(&mut particles, |_, v| v.advance(dt));
+//! ```
+//!
+//! The `JobGraph` runs jobs once all of their dependencies have finished.
+//!
+//! # Async
+//!
+//! `Task` and the `Request` of resources are `Future`s, so they could be awaited inside
+//! the async runtime of games:
+//!
+//! ```rust,ignore
+//! async fn load_level(uuid: Uuid) -> Result<Level> {
+//!     let bytes = res::load(uuid)?.await?;
+//!     let level = sched::spawn_task(move || Level::parse(&bytes)).await?;
+//!     Ok(level)
+//! }
+//! ```
+//!
+//! The jobs are executed in place in web environment, where threads are not available.
+
 pub mod latch;
 pub mod scope;
 mod system;
 
+mod graph;
 mod job;
 mod scheduler;
+mod task;
 mod unwind;
 
 pub mod prelude {
+    pub use super::graph::{JobGraph, JobId};
     pub use super::latch::{CountLatch, Latch, LatchProbe, LockLatch, SpinLatch};
+    pub use super::scope::Scope;
     pub use super::system::PanicHandler;
+    pub use super::task::Task;
 }
 
 use self::inside::{ctx, CTX};
 use self::scope::Scope;
+use self::task::Task;
 
 /// Checks if the sched system is enabled.
 #[inline]
@@ -38,6 +79,32 @@ where
     ctx().spawn(func);
 }
 
+/// Spawn an asynchronous job in the global `Scheduler`, and returns the task that could be
+/// waited or awaited for its result.
+pub fn spawn_task<F, T>(func: F) -> Task<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    ctx().spawn_task(func)
+}
+
+/// Invokes `func` with the index and the mutable reference of every item in `items` in
+/// parallel, and blocks until all of them are finished.
+pub fn parallel_for<T, F>(items: &mut [T], func: F)
+where
+    T: Send,
+    F: Fn(usize, &mut T) + Sync,
+{
+    ctx().parallel_for(items, func)
+}
+
+/// Gets the number of worker threads, which is zero if the jobs are executed in place.
+#[inline]
+pub fn num_threads() -> usize {
+    ctx().num_threads()
+}
+
 /// Create a "fork-join" scope `s` and invokes the closure with a
 /// reference to `s`. This closure can then spawn asynchronous tasks
 /// into `s`. Those tasks may run asynchronously with respect to the
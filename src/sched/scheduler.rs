@@ -146,6 +146,12 @@ impl Scheduler {
         }
     }
 
+    /// Gets the number of worker threads.
+    #[inline]
+    pub fn num_threads(&self) -> usize {
+        self.threads.len()
+    }
+
     /// Handles panic.
     pub fn handle_panic(&self, err: Box<::std::any::Any + Send>) {
         match self.panic_handler {
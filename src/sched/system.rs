@@ -1,8 +1,10 @@
 use std::sync::Arc;
 
 use super::job::HeapJob;
+use super::latch::LockLatch;
 use super::scheduler::Scheduler;
 use super::scope::Scope;
+use super::task::Task;
 use super::unwind;
 
 pub struct SchedulerSystem {
@@ -81,6 +83,57 @@ impl SchedulerSystem {
         }
     }
 
+    /// Spawn an asynchronous job in the global `Scheduler`, and returns the task that
+    /// could be waited for its result.
+    pub fn spawn_task<F, T>(&self, func: F) -> Task<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let latch = Arc::new(LockLatch::new());
+        let task = Task::new(latch.clone());
+        self.spawn(move || latch.set(unwind::halt_unwinding(func)));
+        task
+    }
+
+    /// Invokes `func` with the index and the mutable reference of every item in `items`
+    /// in parallel, and blocks until all of them are finished.
+    pub fn parallel_for<T, F>(&self, items: &mut [T], func: F)
+    where
+        T: Send,
+        F: Fn(usize, &mut T) + Sync,
+    {
+        // Splits items into a few batches for each thread, so the busy threads could be
+        // balanced by stealing the batches of others.
+        let batch = match self.scheduler {
+            Some(ref scheduler) => items.len() / (scheduler.num_threads() * 4),
+            None => items.len(),
+        };
+
+        let batch = batch.max(1);
+        let func = &func;
+
+        self.scope(|s| {
+            for (i, chunk) in items.chunks_mut(batch).enumerate() {
+                s.spawn(move |_| {
+                    for (j, v) in chunk.iter_mut().enumerate() {
+                        func(i * batch + j, v);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Gets the number of worker threads, which is zero if the jobs are executed in place,
+    /// e.g. in web environment.
+    #[inline]
+    pub fn num_threads(&self) -> usize {
+        self.scheduler
+            .as_ref()
+            .map(|v| v.num_threads())
+            .unwrap_or(0)
+    }
+
     /// Create a "fork-join" scope `s` and invokes the closure with a
     /// reference to `s`. This closure can then spawn asynchronous tasks
     /// into `s`. Those tasks may run asynchronously with respect to the
@@ -0,0 +1,62 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+
+use super::latch::{LatchProbe, LatchWaitProbe, LockLatch};
+use super::scheduler::WorkerThread;
+use super::unwind;
+
+/// The handle of an asynchronous job that returns a value. The result could be waited in
+/// place with `wait`, or awaited in async functions since the task is a `Future`.
+///
+/// If the job panicked, the panic is propagated to the one who waits for it.
+pub struct Task<T> {
+    latch: Arc<LockLatch<thread::Result<T>>>,
+}
+
+impl<T> Task<T> {
+    pub(crate) fn new(latch: Arc<LockLatch<thread::Result<T>>>) -> Self {
+        Task { latch }
+    }
+
+    /// Checks if the job has finished.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.latch.is_set()
+    }
+
+    /// Blocks current thread until the job has finished, and returns its result. The
+    /// other jobs are executed in the meantime if it's called inside a job.
+    pub fn wait(self) -> T {
+        unsafe {
+            let worker = WorkerThread::current();
+            if worker.is_null() {
+                self.latch.wait();
+            } else {
+                (*worker).wait_until(&self.latch);
+            }
+        }
+
+        match self.latch.take() {
+            Ok(v) => v,
+            Err(err) => unwind::resume_unwinding(err),
+        }
+    }
+}
+
+impl<T> Future for Task<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        if !self.latch.register(cx.waker()) {
+            return Poll::Pending;
+        }
+
+        match self.latch.take() {
+            Ok(v) => Poll::Ready(v),
+            Err(err) => unwind::resume_unwinding(err),
+        }
+    }
+}
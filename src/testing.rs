@@ -0,0 +1,283 @@
+//! Utilities to test the rendering results against reference images.
+//!
+//! # Golden Images
+//!
+//! `assert_golden` renders a `LifecycleListener` for a few frames with the offscreen OpenGL
+//! context, reads back the window framebuffer, and compares it against the reference PNG
+//! image with the tolerance of `GoldenParams`. So the render codes could be covered by the
+//! regression tests.
+//!
+//! ```rust,ignore
+//! #[test]
+//! fn sprites() {
+//!     let mut params = GoldenParams::default();
+//!     params.engine.window.size = (128, 128).into();
+//!     testing::assert_golden("tests/golden/sprites.png", params, || Sprites::new()).unwrap();
+//! }
+//! ```
+//!
+//! If the reference image does not exist or the environment variable `CRAYON_UPDATE_GOLDEN`
+//! is set, the rendered image is written as the new reference. Otherwise, a mismatched image
+//! is written next to the reference with `.actual.png` extension for diagnosis.
+//!
+//! Notes that the engine could only be setup once at the same time, so the golden tests
+//! in the same process are executed one by one.
+//!
+//! # Platform-specific
+//!
+//! The context is created by glutin with OSMesa on Linux, so the library of OSMesa is
+//! required but no display server is needed. It is not supported in web environment.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::application::prelude::{Headless, LifecycleListener, Params};
+use crate::errors::*;
+use crate::math::prelude::Vector2;
+use crate::res::request::Request;
+use crate::video::capture::{decode_png, encode_png};
+
+/// The environment variable that updates the reference images with the rendered ones.
+pub const UPDATE_GOLDEN: &str = "CRAYON_UPDATE_GOLDEN";
+
+// The engine could be setup only once at the same time.
+static GUARD: Mutex<()> = Mutex::new(());
+
+/// The setup parameters of golden tests.
+#[derive(Debug, Clone)]
+pub struct GoldenParams {
+    /// The number of frames rendered before reading back, which gives the resources some
+    /// time to be loaded.
+    pub frames: u32,
+    /// The maximum difference of each channel between the pixels that are considered equal.
+    pub tolerance: u8,
+    /// The maximum number of pixels that are allowed to be different.
+    pub max_mismatched_pixels: usize,
+    /// The setup parameters of engine, it always runs with `Headless::Offscreen`. And
+    /// the size of window is the dimensions of rendered image.
+    pub engine: Params,
+}
+
+impl Default for GoldenParams {
+    fn default() -> Self {
+        let mut engine = Params::default();
+        engine.max_fps = 0;
+        engine.window.size = (256, 256).into();
+
+        GoldenParams {
+            frames: 3,
+            tolerance: 2,
+            max_mismatched_pixels: 0,
+            engine,
+        }
+    }
+}
+
+/// Renders the `LifecycleListener` created by `closure` for `GoldenParams::frames` frames,
+/// and returns the dimensions in pixels and RGBA8 pixels of the last frame, starting from
+/// the bottom row.
+pub fn render<T, T2>(params: &GoldenParams, closure: T) -> Result<(Vector2<u32>, Box<[u8]>)>
+where
+    T: FnOnce() -> Result<T2> + 'static,
+    T2: LifecycleListener + Send + 'static,
+{
+    let _guard = GUARD.lock().unwrap_or_else(|err| err.into_inner());
+
+    let mut engine = params.engine.clone();
+    engine.headless = Some(Headless::Offscreen);
+
+    let output = Arc::new(Mutex::new(None));
+    let frames = params.frames.max(1);
+
+    let capture = output.clone();
+    crate::application::setup(engine, move || {
+        Ok(GoldenListener {
+            inner: closure()?,
+            frames,
+            dimensions: Vector2::new(0, 0),
+            readback: None,
+            output: capture,
+        })
+    })?;
+
+    let rsp = output.lock().unwrap().take();
+    match rsp {
+        Some(Ok(v)) => Ok(v),
+        Some(Err(err)) => bail!("Failed to read back the framebuffer. {}", err),
+        None => bail!("The engine exited before the frames were rendered."),
+    }
+}
+
+/// Renders the `LifecycleListener` created by `closure`, and compares the last frame
+/// against the reference image at `path`.
+pub fn assert_golden<P, T, T2>(path: P, params: GoldenParams, closure: T) -> Result<()>
+where
+    P: AsRef<Path>,
+    T: FnOnce() -> Result<T2> + 'static,
+    T2: LifecycleListener + Send + 'static,
+{
+    let path = path.as_ref();
+    let (dimensions, pixels) = render(&params, closure)?;
+
+    if std::env::var_os(UPDATE_GOLDEN).is_some() || !path.exists() {
+        std::fs::write(path, encode_png(dimensions, &pixels)?)?;
+        info!("The reference image {:?} has been updated.", path);
+        return Ok(());
+    }
+
+    let (expected_dimensions, expected) = decode_png(&std::fs::read(path)?)?;
+    let rsp = if expected_dimensions != dimensions {
+        Err(format_err!(
+            "The dimensions {:?} does not match the reference {:?}.",
+            dimensions,
+            expected_dimensions
+        ))
+    } else {
+        compare(&expected, &pixels, &params)
+    };
+
+    if let Err(err) = rsp {
+        let actual = path.with_extension("actual.png");
+        std::fs::write(&actual, encode_png(dimensions, &pixels)?)?;
+        bail!("{:?} mismatched, see {:?}. {}", path, actual, err);
+    }
+
+    Ok(())
+}
+
+/// Compares the RGBA8 pixels with the tolerance of `GoldenParams`.
+pub fn compare(expected: &[u8], actual: &[u8], params: &GoldenParams) -> Result<()> {
+    if expected.len() != actual.len() {
+        bail!(
+            "The size of pixels {} does not match the reference {}.",
+            actual.len(),
+            expected.len()
+        );
+    }
+
+    let mut mismatched = 0;
+    let mut max_difference = 0;
+    for (lhs, rhs) in expected.chunks(4).zip(actual.chunks(4)) {
+        let difference = lhs
+            .iter()
+            .zip(rhs)
+            .map(|(&a, &b)| (i16::from(a) - i16::from(b)).abs() as u8)
+            .max()
+            .unwrap_or(0);
+
+        if difference > params.tolerance {
+            mismatched += 1;
+            max_difference = max_difference.max(difference);
+        }
+    }
+
+    if mismatched > params.max_mismatched_pixels {
+        bail!(
+            "{} pixels are different, and the maximum difference of channels is {}.",
+            mismatched,
+            max_difference
+        );
+    }
+
+    Ok(())
+}
+
+struct GoldenListener<T: LifecycleListener> {
+    inner: T,
+    frames: u32,
+    // The dimensions in pixels of the framebuffer being read back.
+    dimensions: Vector2<u32>,
+    readback: Option<Request>,
+    output: Arc<Mutex<Option<Result<(Vector2<u32>, Box<[u8]>)>>>>,
+}
+
+impl<T: LifecycleListener> LifecycleListener for GoldenListener<T> {
+    fn on_pre_update(&mut self) -> Result<()> {
+        self.inner.on_pre_update()
+    }
+
+    fn on_update(&mut self) -> Result<()> {
+        self.inner.on_update()
+    }
+
+    fn on_render(&mut self) -> Result<()> {
+        self.inner.on_render()
+    }
+
+    fn on_post_update(&mut self) -> Result<()> {
+        self.inner.on_post_update()?;
+
+        if let Some(ref mut readback) = self.readback {
+            if readback.poll() {
+                let rsp = match readback.response() {
+                    Some(Ok(pixels)) => Ok((self.dimensions, pixels.clone())),
+                    Some(Err(err)) => Err(format_err!("{}", err)),
+                    None => unreachable!(),
+                };
+
+                *self.output.lock().unwrap() = Some(rsp);
+                crate::application::discard();
+            }
+
+            return Ok(());
+        }
+
+        // The readback is scheduled after the draws of the last frame.
+        self.frames -= 1;
+        if self.frames == 0 {
+            let dimensions = crate::window::dimensions();
+            let dpr = crate::window::device_pixel_ratio();
+            self.dimensions = Vector2::new(
+                (dimensions.x as f32 * dpr) as u32,
+                (dimensions.y as f32 * dpr) as u32,
+            );
+
+            self.readback = Some(crate::video::read_framebuffer());
+        }
+
+        Ok(())
+    }
+
+    fn on_exit(&mut self) -> Result<()> {
+        self.inner.on_exit()
+    }
+
+    fn on_suspend(&mut self) -> Result<()> {
+        self.inner.on_suspend()
+    }
+
+    fn on_resume(&mut self) -> Result<()> {
+        self.inner.on_resume()
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) -> Result<()> {
+        self.inner.on_focus_changed(focused)
+    }
+
+    fn on_resize(&mut self, dimensions: Vector2<u32>) -> Result<()> {
+        self.inner.on_resize(dimensions)
+    }
+
+    fn on_dpi_changed(&mut self, device_pixel_ratio: f32) -> Result<()> {
+        self.inner.on_dpi_changed(device_pixel_ratio)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tolerance() {
+        let mut params = GoldenParams::default();
+        let expected = [0, 0, 0, 255, 100, 100, 100, 255];
+
+        assert!(compare(&expected, &[2, 0, 0, 255, 100, 98, 100, 255], &params).is_ok());
+        assert!(compare(&expected, &[3, 0, 0, 255, 100, 100, 100, 255], &params).is_err());
+        assert!(compare(&expected, &[0, 0, 0, 255], &params).is_err());
+
+        params.max_mismatched_pixels = 1;
+        assert!(compare(&expected, &[9, 0, 0, 255, 100, 100, 100, 255], &params).is_ok());
+        assert!(compare(&expected, &[9, 0, 0, 255, 0, 100, 100, 255], &params).is_err());
+    }
+}
@@ -17,6 +17,17 @@ impl DataBuffer {
         DataBuffer(Vec::with_capacity(capacity))
     }
 
+    /// Returns the number of bytes in the buffer.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn clear(&mut self) {
         unsafe {
             self.0.set_len(0);
@@ -75,6 +86,7 @@ impl DataBuffer {
     {
         let slice = self.as_bytes(ptr);
         assert_eq!(slice.len(), mem::size_of::<T>());
+        assert_eq!(slice.as_ptr() as usize % mem::align_of::<T>(), 0);
         unsafe { &*(slice.as_ptr() as *const _) }
     }
 
@@ -87,6 +99,7 @@ impl DataBuffer {
         let slice = self.as_bytes(ptr);
         let len = slice.len() / mem::size_of::<T>();
         assert_eq!(slice.len(), mem::size_of::<T>().wrapping_mul(len));
+        assert_eq!(slice.as_ptr() as usize % mem::align_of::<T>(), 0);
         unsafe { slice::from_raw_parts(slice.as_ptr() as *const T, len) }
     }
 
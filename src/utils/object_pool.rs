@@ -5,7 +5,7 @@ use super::handle_pool::HandlePool;
 /// attached instance `T` will be created/ freed.
 pub struct ObjectPool<H: HandleLike, T: Sized> {
     handles: HandlePool<H>,
-    entries: Vec<T>,
+    entries: Vec<Option<T>>,
 }
 
 impl<H: HandleLike, T: Sized> Default for ObjectPool<H, T> {
@@ -32,14 +32,13 @@ impl<H: HandleLike, T: Sized> ObjectPool<H, T> {
     }
 
     /// Creates a `T` and named it with `Handle`.
-    pub fn create(&mut self, mut value: T) -> H {
+    pub fn create(&mut self, value: T) -> H {
         let handle = self.handles.create();
 
         if handle.index() >= self.entries.len() as u32 {
-            self.entries.push(value);
+            self.entries.push(Some(value));
         } else {
-            ::std::mem::swap(&mut value, &mut self.entries[handle.index() as usize]);
-            ::std::mem::forget(value);
+            self.entries[handle.index() as usize] = Some(value);
         }
 
         handle
@@ -49,7 +48,7 @@ impl<H: HandleLike, T: Sized> ObjectPool<H, T> {
     #[inline]
     pub fn get_mut(&mut self, handle: H) -> Option<&mut T> {
         if self.handles.contains(handle) {
-            unsafe { Some(self.entries.get_unchecked_mut(handle.index() as usize)) }
+            self.entries[handle.index() as usize].as_mut()
         } else {
             None
         }
@@ -59,7 +58,7 @@ impl<H: HandleLike, T: Sized> ObjectPool<H, T> {
     #[inline]
     pub fn get(&self, handle: H) -> Option<&T> {
         if self.handles.contains(handle) {
-            unsafe { Some(self.entries.get_unchecked(handle.index() as usize)) }
+            self.entries[handle.index() as usize].as_ref()
         } else {
             None
         }
@@ -76,11 +75,7 @@ impl<H: HandleLike, T: Sized> ObjectPool<H, T> {
     #[inline]
     pub fn free(&mut self, handle: H) -> Option<T> {
         if self.handles.free(handle) {
-            unsafe {
-                let mut v = ::std::mem::uninitialized();
-                ::std::mem::swap(&mut v, &mut self.entries[handle.index() as usize]);
-                Some(v)
-            }
+            self.entries[handle.index() as usize].take()
         } else {
             None
         }
@@ -94,13 +89,12 @@ impl<H: HandleLike, T: Sized> ObjectPool<H, T> {
         P: FnMut(H, &mut T) -> bool,
     {
         let entries = &mut self.entries;
-        self.handles.retain(|handle| unsafe {
-            let mut v = entries.get_unchecked_mut(handle.index() as usize);
-            if predicate(handle, v) {
+        self.handles.retain(|handle| {
+            let v = &mut entries[handle.index() as usize];
+            if predicate(handle, v.as_mut().unwrap()) {
                 true
             } else {
-                #[allow(clippy::invalid_ref)]
-                std::mem::swap(&mut v, &mut std::mem::uninitialized());
+                *v = None;
                 false
             }
         });
@@ -123,7 +117,7 @@ impl<H: HandleLike, T: Sized> ObjectPool<H, T> {
     pub fn iter<'a>(&'a self) -> impl DoubleEndedIterator<Item = (H, &T)> + 'a {
         self.handles
             .iter()
-            .map(move |v| unsafe { (v, self.entries.get_unchecked(v.index() as usize)) })
+            .map(move |v| (v, self.entries[v.index() as usize].as_ref().unwrap()))
     }
 
     /// an iterator visiting all key-value pairs in order. the iterator element type is (h, &mut t).
@@ -131,7 +125,7 @@ impl<H: HandleLike, T: Sized> ObjectPool<H, T> {
     pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (H, &mut T)> {
         let entries = &mut self.entries;
         self.handles.iter().map(move |v| unsafe {
-            let w = entries.get_unchecked_mut(v.index() as usize);
+            let w = entries[v.index() as usize].as_mut().unwrap();
             (v, &mut *(w as *mut T))
         })
     }
@@ -147,7 +141,7 @@ impl<H: HandleLike, T: Sized> ObjectPool<H, T> {
     pub fn values<'a>(&'a self) -> impl DoubleEndedIterator<Item = &T> + 'a {
         self.handles
             .iter()
-            .map(move |v| unsafe { self.entries.get_unchecked(v.index() as usize) })
+            .map(move |v| self.entries[v.index() as usize].as_ref().unwrap())
     }
 
     /// An iterator visiting all entries in order. The iterator element type is &mut T.
@@ -155,20 +149,8 @@ impl<H: HandleLike, T: Sized> ObjectPool<H, T> {
     pub fn values_mut<'a>(&'a mut self) -> impl DoubleEndedIterator<Item = &mut T> + 'a {
         let entries = &mut self.entries;
         self.handles.iter().map(move |v| unsafe {
-            let w = entries.get_unchecked_mut(v.index() as usize);
+            let w = entries[v.index() as usize].as_mut().unwrap();
             &mut *(w as *mut T)
         })
     }
 }
-
-impl<H: HandleLike, T: Sized> Drop for ObjectPool<H, T> {
-    fn drop(&mut self) {
-        unsafe {
-            for v in &self.handles {
-                ::std::ptr::drop_in_place(&mut self.entries[v.index() as usize]);
-            }
-
-            self.entries.set_len(0);
-        }
-    }
-}
@@ -0,0 +1,565 @@
+//! The parser of KTX2 containers, which are loaded by the texture loaders along with the
+//! built-in binary formats. A KTX2 file could hold the mipmap chain of 2D textures, cube
+//! maps, 3D textures and texture arrays in one portable container.
+//!
+//! The levels could be supercompressed with Zstandard or ZLIB, which are decompressed
+//! when loading. The images in Basis Universal formats (ETC1S and UASTC) are transcoded
+//! into the best format supported by the device with a `BasisTranscoder`, which should be
+//! registered with `video::set_basis_transcoder` before loading them.
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use flate2::read::ZlibDecoder;
+use std::io::{Cursor, Read};
+use std::sync::{Arc, RwLock};
+
+use crate::errors::*;
+use crate::math::prelude::{Vector2, Vector3};
+
+use super::texture::*;
+
+pub const MAGIC: [u8; 12] = [
+    0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+];
+
+/// The supercompressed formats of Basis Universal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BasisFormat {
+    /// The ETC1S images, which are compressed with BasisLZ.
+    ETC1S,
+    /// The UASTC images, which are optionally compressed with Zstandard.
+    UASTC,
+}
+
+/// An image in Basis Universal format, which is passed to the `BasisTranscoder`.
+#[derive(Debug, Copy, Clone)]
+pub struct BasisImage<'a> {
+    pub format: BasisFormat,
+    /// The dimensions of the mipmap level.
+    pub dimensions: Vector2<u32>,
+    /// The mipmap level of image.
+    pub level: usize,
+    /// The index of image in the level, which enumerates the layers, faces and slices.
+    pub index: usize,
+    /// Whether the image has alpha channel.
+    pub alpha: bool,
+    /// The bytes of image. Notes that the ETC1S images are passed with the bytes of the
+    /// whole level, which are sliced with the image descriptors in `global`.
+    pub bytes: &'a [u8],
+    /// The supercompression global data, which holds the codebooks and image descriptors
+    /// of ETC1S images.
+    pub global: &'a [u8],
+}
+
+/// The transcoder of Basis Universal images, which is usually a binding of the reference
+/// transcoder. The images are transcoded into `RGBA8` or `SRGBA8` if none of the compressed
+/// formats is accepted by the transcoder and supported by device.
+pub trait BasisTranscoder: Send + Sync + 'static {
+    /// Checks if the images of `source` format could be transcoded into `target`.
+    fn accept(&self, source: BasisFormat, target: TextureFormat) -> bool;
+
+    /// Transcodes the image into `target` format.
+    fn transcode(&self, image: &BasisImage, target: TextureFormat) -> Result<Box<[u8]>>;
+}
+
+pub type BasisTranscoders = Arc<RwLock<Option<Arc<dyn BasisTranscoder>>>>;
+
+// The compressed targets of Basis Universal images from the most preferred one.
+const BASIS_TARGETS: [TextureFormat; 4] = [
+    TextureFormat::Astc4x4RGBA8BPP,
+    TextureFormat::S3tcDxt1RGB4BPP,
+    TextureFormat::Etc2RGB4BPP,
+    TextureFormat::PvrtcRGB4BPP,
+];
+
+const BASIS_ALPHA_TARGETS: [TextureFormat; 4] = [
+    TextureFormat::Astc4x4RGBA8BPP,
+    TextureFormat::S3tcDxt5RGBA8BPP,
+    TextureFormat::Etc2RGBA8BPP,
+    TextureFormat::PvrtcRGBA4BPP,
+];
+
+const SUPERCOMPRESSION_NONE: u32 = 0;
+const SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+const SUPERCOMPRESSION_ZLIB: u32 = 3;
+
+const DF_MODEL_ETC1S: u8 = 163;
+const DF_MODEL_UASTC: u8 = 166;
+const DF_TRANSFER_SRGB: u8 = 2;
+
+/// Checks if the bytes are a KTX2 container.
+#[inline]
+pub fn accept(bytes: &[u8]) -> bool {
+    bytes.len() >= MAGIC.len() && bytes[0..MAGIC.len()] == MAGIC[..]
+}
+
+/// The decoder of KTX2 containers, which chooses the transcoded formats with the texture
+/// formats supported by device.
+pub struct Ktx2Decoder {
+    formats: Vec<TextureFormat>,
+    transcoder: BasisTranscoders,
+}
+
+impl Ktx2Decoder {
+    pub fn new(formats: Vec<TextureFormat>, transcoder: BasisTranscoders) -> Self {
+        Ktx2Decoder {
+            formats,
+            transcoder,
+        }
+    }
+
+    /// Parses the container, and decodes its levels into `Ktx2`.
+    pub fn decode(&self, bytes: &[u8]) -> Result<Ktx2> {
+        if !accept(bytes) {
+            bail!("[Ktx2Decoder] MAGIC number not match.");
+        }
+
+        let mut file = Cursor::new(&bytes[MAGIC.len()..]);
+        let vk_format = file.read_u32::<LittleEndian>()?;
+        let _type_size = file.read_u32::<LittleEndian>()?;
+        let width = file.read_u32::<LittleEndian>()?;
+        let height = file.read_u32::<LittleEndian>()?.max(1);
+        let depth = file.read_u32::<LittleEndian>()?.max(1);
+        let layers = file.read_u32::<LittleEndian>()?.max(1);
+        let faces = file.read_u32::<LittleEndian>()?;
+        let levels = file.read_u32::<LittleEndian>()?.max(1) as usize;
+        let supercompression = file.read_u32::<LittleEndian>()?;
+
+        let dfd = (
+            file.read_u32::<LittleEndian>()? as usize,
+            file.read_u32::<LittleEndian>()? as usize,
+        );
+
+        let _kvd = (
+            file.read_u32::<LittleEndian>()?,
+            file.read_u32::<LittleEndian>()?,
+        );
+
+        let sgd = (
+            file.read_u64::<LittleEndian>()? as usize,
+            file.read_u64::<LittleEndian>()? as usize,
+        );
+
+        if width == 0 || (faces != 1 && faces != 6) || (faces == 6 && depth > 1) {
+            bail!(
+                "[Ktx2Decoder] Invalid dimensions {}x{}x{} with {} faces.",
+                width,
+                height,
+                depth,
+                faces
+            );
+        }
+
+        // The counts in header are untrusted, so the level index must fit in the file before
+        // anything is allocated with them.
+        if levels > file.get_ref().len().saturating_sub(68) / 24 {
+            bail!(
+                "[Ktx2Decoder] The index of {} levels is out of bounds.",
+                levels
+            );
+        }
+
+        let dimensions = Vector3::new(width, height, depth);
+        let (model, alpha, srgb) = descriptor(slice(bytes, dfd.0, dfd.1)?)?;
+        let global = slice(bytes, sgd.0, sgd.1)?;
+
+        let mut ktx2 = Ktx2 {
+            format: TextureFormat::RGBA8,
+            dimensions,
+            layers,
+            faces,
+            levels: Vec::with_capacity(levels),
+        };
+
+        let basis = match (vk_format, supercompression, model) {
+            (0, SUPERCOMPRESSION_BASIS_LZ, _) | (0, _, DF_MODEL_ETC1S) => Some(BasisFormat::ETC1S),
+            (0, _, DF_MODEL_UASTC) => Some(BasisFormat::UASTC),
+            (0, _, _) => bail!("[Ktx2Decoder] Undefined format of texels."),
+            _ => None,
+        };
+
+        let transcoder = self.transcoder.read().unwrap().clone();
+        let target = match basis {
+            Some(source) => {
+                let transcoder = transcoder
+                    .as_ref()
+                    .ok_or_else(|| format_err!("[Ktx2Decoder] {:?} images could not be transcoded without `BasisTranscoder`.", source))?;
+
+                self.basis_target(&**transcoder, source, alpha, srgb, dimensions)
+            }
+            None => vk_texture_format(vk_format).ok_or_else(|| {
+                format_err!("[Ktx2Decoder] Unsupported Vulkan format {}.", vk_format)
+            })?,
+        };
+
+        if !self.formats.contains(&target) {
+            bail!(
+                "[Ktx2Decoder] The texture format {:?} is not supported.",
+                target
+            );
+        }
+
+        ktx2.format = target;
+
+        // The level index follows the header.
+        file.set_position(68);
+        for level in 0..levels {
+            let offset = file.read_u64::<LittleEndian>()? as usize;
+            let len = file.read_u64::<LittleEndian>()? as usize;
+            let uncompressed = file.read_u64::<LittleEndian>()? as usize;
+            let src = slice(bytes, offset, len)?;
+
+            let buf = match supercompression {
+                SUPERCOMPRESSION_NONE | SUPERCOMPRESSION_BASIS_LZ => src.to_vec(),
+                SUPERCOMPRESSION_ZSTD => {
+                    let mut src = src;
+                    let decoder = ruzstd::StreamingDecoder::new(&mut src)
+                        .map_err(|err| format_err!("[Ktx2Decoder] {}", err))?;
+                    let mut buf = Vec::with_capacity(uncompressed.min(bytes.len()));
+                    decoder.take(uncompressed as u64).read_to_end(&mut buf)?;
+                    buf
+                }
+                SUPERCOMPRESSION_ZLIB => {
+                    let mut buf = Vec::with_capacity(uncompressed.min(bytes.len()));
+                    ZlibDecoder::new(src)
+                        .take(uncompressed as u64)
+                        .read_to_end(&mut buf)?;
+                    buf
+                }
+                v => bail!("[Ktx2Decoder] Unsupported supercompression scheme {}.", v),
+            };
+
+            let level_dimensions = Vector2::new((width >> level).max(1), (height >> level).max(1));
+            let images = layers
+                .checked_mul(faces)
+                .and_then(|v| v.checked_mul((depth >> level).max(1)))
+                .filter(|&v| v > 0)
+                .ok_or_else(|| format_err!("[Ktx2Decoder] Too many images in level {}.", level))?
+                as usize;
+
+            let buf = match (basis, transcoder.as_ref()) {
+                (Some(format), Some(transcoder)) => {
+                    let mut dst = Vec::new();
+                    for i in 0..images {
+                        let bytes = match format {
+                            BasisFormat::ETC1S => &buf[..],
+                            BasisFormat::UASTC => {
+                                let len = buf.len() / images;
+                                &buf[i * len..(i + 1) * len]
+                            }
+                        };
+
+                        let image = BasisImage {
+                            format,
+                            dimensions: level_dimensions,
+                            level,
+                            index: i,
+                            alpha,
+                            bytes,
+                            global,
+                        };
+
+                        dst.extend_from_slice(&transcoder.transcode(&image, target)?);
+                    }
+
+                    dst
+                }
+                _ => buf,
+            };
+
+            if buf.len() < (target.size(level_dimensions) as usize).saturating_mul(images) {
+                bail!("[Ktx2Decoder] The level {} is truncated.", level);
+            }
+
+            ktx2.levels.push(buf.into_boxed_slice());
+        }
+
+        Ok(ktx2)
+    }
+
+    fn basis_target(
+        &self,
+        transcoder: &dyn BasisTranscoder,
+        source: BasisFormat,
+        alpha: bool,
+        srgb: bool,
+        dimensions: Vector3<u32>,
+    ) -> TextureFormat {
+        let targets = if alpha {
+            &BASIS_ALPHA_TARGETS
+        } else {
+            &BASIS_TARGETS
+        };
+
+        // PVRTC only supports square textures whose sizes are power of two.
+        let pot = dimensions.x == dimensions.y && dimensions.x.is_power_of_two();
+
+        for &v in targets.iter() {
+            let pvrtc = v == TextureFormat::PvrtcRGB4BPP || v == TextureFormat::PvrtcRGBA4BPP;
+            if self.formats.contains(&v) && (pot || !pvrtc) && transcoder.accept(source, v) {
+                return v;
+            }
+        }
+
+        if srgb && self.formats.contains(&TextureFormat::SRGBA8) {
+            TextureFormat::SRGBA8
+        } else {
+            TextureFormat::RGBA8
+        }
+    }
+}
+
+/// The decoded KTX2 container.
+#[derive(Debug, Clone)]
+pub struct Ktx2 {
+    pub format: TextureFormat,
+    /// The width, height and depth of the base level.
+    pub dimensions: Vector3<u32>,
+    /// The number of array layers, which is 1 if it's not a texture array.
+    pub layers: u32,
+    /// The number of faces, which is 6 for cube maps and 1 otherwise.
+    pub faces: u32,
+    /// The bytes of mipmap levels from largest size to smallest size. Each level stores
+    /// its layers, faces and slices in order.
+    pub levels: Vec<Box<[u8]>>,
+}
+
+impl Ktx2 {
+    /// Converts into the params and data of 2D texture.
+    pub fn into_texture(self) -> Result<(TextureParams, TextureData)> {
+        if self.faces != 1 || self.layers != 1 || self.dimensions.z != 1 {
+            bail!("[Ktx2] Trying to load cube map, array or 3D texture as 2D texture.");
+        }
+
+        let mut params = TextureParams::default();
+        params.format = self.format;
+        params.dimensions = Vector2::new(self.dimensions.x, self.dimensions.y);
+
+        Ok((params, TextureData { bytes: self.levels }))
+    }
+
+    /// Converts into the params and data of cube texture.
+    pub fn into_texture_cube(self) -> Result<(TextureCubeParams, TextureCubeData)> {
+        if self.faces != 6 || self.layers != 1 {
+            bail!("[Ktx2] Trying to load 2D texture, array or 3D texture as cube map.");
+        }
+
+        let mut params = TextureCubeParams::default();
+        params.format = self.format;
+        params.dimensions = Vector2::new(self.dimensions.x, self.dimensions.y);
+
+        let mut faces: [Vec<Box<[u8]>>; 6] = Default::default();
+        for level in self.levels {
+            let len = level.len() / 6;
+            for (i, face) in faces.iter_mut().enumerate() {
+                face.push(level[i * len..(i + 1) * len].to_vec().into_boxed_slice());
+            }
+        }
+
+        Ok((params, TextureCubeData { faces }))
+    }
+
+    /// Converts into the params and data of 3D texture. The layers of texture array are
+    /// loaded as the slices of 3D texture, which keeps the base level only since the 3D
+    /// mipmaps are shrunk in depth also.
+    pub fn into_texture_3d(mut self) -> Result<(Texture3DParams, Texture3DData)> {
+        if self.faces != 1 {
+            bail!("[Ktx2] Trying to load cube map as 3D texture.");
+        }
+
+        if self.layers > 1 && self.dimensions.z > 1 {
+            bail!("[Ktx2] Arrays of 3D textures are not supported.");
+        }
+
+        let mut params = Texture3DParams::default();
+        params.format = self.format;
+        params.dimensions = self.dimensions;
+
+        if self.layers > 1 {
+            params.dimensions.z = self.layers;
+            if self.levels.len() > 1 {
+                warn!("[Ktx2] The mipmaps of texture array are dropped.");
+                self.levels.truncate(1);
+            }
+        }
+
+        Ok((params, Texture3DData { bytes: self.levels }))
+    }
+}
+
+fn slice(bytes: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    match offset.checked_add(len) {
+        Some(end) if end <= bytes.len() => Ok(&bytes[offset..end]),
+        _ => bail!(
+            "[Ktx2Decoder] The data at {}..+{} is out of bounds.",
+            offset,
+            len
+        ),
+    }
+}
+
+// Reads the color model, whether the texels have alpha and whether they are encoded in
+// sRGB space from the basic data format descriptor.
+fn descriptor(dfd: &[u8]) -> Result<(u8, bool, bool)> {
+    if dfd.len() < 32 {
+        return Ok((0, false, false));
+    }
+
+    let block = &dfd[4..];
+    let size = (u32::from(block[6]) | u32::from(block[7]) << 8) as usize;
+    let samples = size.saturating_sub(24) / 16;
+    let model = block[8];
+    let srgb = block[10] == DF_TRANSFER_SRGB;
+
+    let alpha = match model {
+        // The ETC1S images store alpha in the second slice.
+        DF_MODEL_ETC1S => samples > 1,
+        // The channel of UASTC sample is one of RGB, RGBA, RRR or RRRG.
+        DF_MODEL_UASTC => {
+            let channel = block[27] & 0x0F;
+            channel == 3 || channel == 5
+        }
+        _ => false,
+    };
+
+    Ok((model, alpha, srgb))
+}
+
+// Maps the Vulkan formats of KTX2 to texture formats.
+fn vk_texture_format(vk_format: u32) -> Option<TextureFormat> {
+    let format = match vk_format {
+        2 => TextureFormat::RGBA4,
+        4 => TextureFormat::RGB565,
+        6 => TextureFormat::RGBA5551,
+        9 => TextureFormat::R8,
+        16 => TextureFormat::RG8,
+        23 => TextureFormat::RGB8,
+        29 => TextureFormat::SRGB8,
+        37 => TextureFormat::RGBA8,
+        43 => TextureFormat::SRGBA8,
+        64 => TextureFormat::RGBA1010102,
+        76 => TextureFormat::R16F,
+        83 => TextureFormat::RG16F,
+        90 => TextureFormat::RGB16F,
+        97 => TextureFormat::RGBA16F,
+        100 => TextureFormat::R32F,
+        103 => TextureFormat::RG32F,
+        106 => TextureFormat::RGB32F,
+        109 => TextureFormat::RGBA32F,
+        131 => TextureFormat::S3tcDxt1RGB4BPP,
+        137 => TextureFormat::S3tcDxt5RGBA8BPP,
+        147 => TextureFormat::Etc2RGB4BPP,
+        151 => TextureFormat::Etc2RGBA8BPP,
+        157 => TextureFormat::Astc4x4RGBA8BPP,
+        171 => TextureFormat::Astc8x8RGBA2BPP,
+        1_000_054_000 => TextureFormat::PvrtcRGBA2BPP,
+        1_000_054_001 => TextureFormat::PvrtcRGBA4BPP,
+        _ => return None,
+    };
+
+    Some(format)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use byteorder::WriteBytesExt;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn container(vk_format: u32, faces: u32, scheme: u32, levels: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        let header = [vk_format, 1, 4, 4, 0, 0, faces, levels.len() as u32, scheme];
+        for &v in &header {
+            bytes.write_u32::<LittleEndian>(v).unwrap();
+        }
+
+        // Leaves the descriptors, key/values and global data empty.
+        bytes.extend_from_slice(&[0; 32]);
+
+        let mut offset = bytes.len() + levels.len() * 24;
+        let mut payloads = Vec::new();
+        for v in levels {
+            let payload = if scheme == SUPERCOMPRESSION_ZLIB {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(v).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                v.clone()
+            };
+
+            bytes.write_u64::<LittleEndian>(offset as u64).unwrap();
+            bytes
+                .write_u64::<LittleEndian>(payload.len() as u64)
+                .unwrap();
+            bytes.write_u64::<LittleEndian>(v.len() as u64).unwrap();
+            offset += payload.len();
+            payloads.push(payload);
+        }
+
+        for v in payloads {
+            bytes.extend_from_slice(&v);
+        }
+
+        bytes
+    }
+
+    fn decoder() -> Ktx2Decoder {
+        let formats = vec![TextureFormat::RGBA8];
+        Ktx2Decoder::new(formats, Arc::new(RwLock::new(None)))
+    }
+
+    #[test]
+    fn texture() {
+        let levels = vec![vec![1; 64], vec![2; 16], vec![3; 4]];
+        let bytes = container(37, 1, SUPERCOMPRESSION_NONE, &levels);
+        assert!(accept(&bytes));
+
+        let (params, data) = decoder().decode(&bytes).unwrap().into_texture().unwrap();
+        assert_eq!(params.format, TextureFormat::RGBA8);
+        assert_eq!(params.dimensions, Vector2::new(4, 4));
+        assert_eq!(data.bytes.len(), 3);
+        assert_eq!(&data.bytes[2][..], &[3; 4][..]);
+
+        let bytes = container(37, 1, SUPERCOMPRESSION_ZLIB, &levels);
+        let (_, data) = decoder().decode(&bytes).unwrap().into_texture().unwrap();
+        assert_eq!(&data.bytes[0][..], &[1; 64][..]);
+
+        // The levels are truncated.
+        let bytes = container(37, 1, SUPERCOMPRESSION_NONE, &[vec![1; 32]]);
+        assert!(decoder().decode(&bytes).is_err());
+
+        // The counts in header are out of bounds.
+        let mut bytes = container(37, 1, SUPERCOMPRESSION_NONE, &levels);
+        bytes[40..44].copy_from_slice(&[0xff; 4]);
+        assert!(decoder().decode(&bytes).is_err());
+
+        let mut bytes = container(37, 6, SUPERCOMPRESSION_NONE, &levels);
+        bytes[32..36].copy_from_slice(&[0xff; 4]);
+        assert!(decoder().decode(&bytes).is_err());
+
+        // The format is not supported by device.
+        let bytes = container(131, 1, SUPERCOMPRESSION_NONE, &[vec![1; 8]]);
+        assert!(decoder().decode(&bytes).is_err());
+
+        // The Basis Universal images could not be transcoded without transcoder.
+        let bytes = container(0, 1, SUPERCOMPRESSION_BASIS_LZ, &[vec![1; 8]]);
+        assert!(decoder().decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn texture_cube() {
+        let levels: Vec<_> = (0..6).map(|i| vec![i as u8; 64]).collect();
+        let bytes = container(37, 6, SUPERCOMPRESSION_NONE, &[levels.concat()]);
+
+        let ktx2 = decoder().decode(&bytes).unwrap();
+        assert!(ktx2.clone().into_texture().is_err());
+
+        let (params, data) = ktx2.into_texture_cube().unwrap();
+        assert_eq!(params.dimensions, Vector2::new(4, 4));
+        for (i, v) in data.faces.iter().enumerate() {
+            assert_eq!(&v[0][..], &levels[i][..]);
+        }
+    }
+}
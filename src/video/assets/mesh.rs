@@ -85,6 +85,48 @@ impl MeshParams {
     }
 }
 
+impl_handle!(InstanceBufferHandle);
+
+/// The setup parameters of instance buffer object. An instance buffer stores the
+/// per-instance attributes, which advance once per instance instead of once per
+/// vertex when drawing with `Draw::set_instance_data`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct InstanceBufferParams {
+    /// Usage hints.
+    pub hint: MeshHint,
+    /// How a single instance structure looks like.
+    pub layout: VertexLayout,
+    /// The number of instances in this buffer.
+    pub num: usize,
+}
+
+impl Default for InstanceBufferParams {
+    fn default() -> Self {
+        InstanceBufferParams {
+            hint: MeshHint::Stream,
+            layout: VertexLayout::default(),
+            num: 0,
+        }
+    }
+}
+
+impl InstanceBufferParams {
+    pub fn validate(&self, data: Option<&[u8]>) -> Result<()> {
+        if let Some(v) = data {
+            if v.len() > self.buffer_len() {
+                return Err(Error::OutOfBounds);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn buffer_len(&self) -> usize {
+        self.num * self.layout.stride() as usize
+    }
+}
+
 /// Mesh index.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum MeshIndex {
@@ -102,6 +144,10 @@ pub enum MeshHint {
     Immutable,
     /// The resource is initialized without data, but will be be updated by the
     /// CPU in each frame.
+    ///
+    /// The data store is orphaned whenever an update starts at offset zero, so the
+    /// CPU could rewrite it without waiting for the draws that still read the old
+    /// contents. Bytes that are not rewritten after orphaning become undefined.
     Stream,
     /// The resource is initialized without data and will be written by the CPU
     /// before use, updates will be infrequent.
@@ -179,6 +225,17 @@ pub enum VertexFormat {
     Float,
 }
 
+impl VertexFormat {
+    /// Returns the size in bytes of a component.
+    pub fn size(self) -> u8 {
+        match self {
+            VertexFormat::Byte | VertexFormat::UByte => 1,
+            VertexFormat::Short | VertexFormat::UShort => 2,
+            VertexFormat::Float => 4,
+        }
+    }
+}
+
 /// The details of a vertex attribute.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub struct VertexAttribute {
@@ -239,6 +296,12 @@ impl VertexLayout {
         self.len == 0
     }
 
+    /// Returns the elements in the layout.
+    #[inline]
+    pub fn elements(&self) -> &[VertexAttribute] {
+        &self.elements[..self.len as usize]
+    }
+
     /// Relative element offset from the layout.
     pub fn offset(&self, name: Attribute) -> Option<u8> {
         for i in 0..self.elements.len() {
@@ -310,7 +373,7 @@ impl VertexLayoutBuilder {
         self.0.stride = 0;
         for i in 0..self.0.len {
             let i = i as usize;
-            let len = self.0.elements[i].size * size_of_vertex(self.0.elements[i].format);
+            let len = self.0.elements[i].size * self.0.elements[i].format.size();
             self.0.offset[i] = self.0.stride;
             self.0.stride += len;
         }
@@ -318,14 +381,6 @@ impl VertexLayoutBuilder {
     }
 }
 
-fn size_of_vertex(format: VertexFormat) -> u8 {
-    match format {
-        VertexFormat::Byte | VertexFormat::UByte => 1,
-        VertexFormat::Short | VertexFormat::UShort => 2,
-        VertexFormat::Float => 4,
-    }
-}
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -490,6 +545,9 @@ pub mod macros {
     #[doc(hidden)]
     #[macro_export]
     macro_rules! impl_vertex_field {
+        (VertexFormat::Byte,1) => {
+            [i8; 1]
+        };
         (VertexFormat::Byte,2) => {
             [i8; 2]
         };
@@ -499,6 +557,9 @@ pub mod macros {
         (VertexFormat::Byte,4) => {
             [i8; 4]
         };
+        (VertexFormat::UByte,1) => {
+            [u8; 1]
+        };
         (VertexFormat::UByte,2) => {
             [u8; 2]
         };
@@ -508,6 +569,9 @@ pub mod macros {
         (VertexFormat::UByte,4) => {
             [u8; 4]
         };
+        (VertexFormat::Short,1) => {
+            [i16; 1]
+        };
         (VertexFormat::Short,2) => {
             [i16; 2]
         };
@@ -517,6 +581,9 @@ pub mod macros {
         (VertexFormat::Short,4) => {
             [i16; 4]
         };
+        (VertexFormat::UShort,1) => {
+            [u16; 1]
+        };
         (VertexFormat::UShort,2) => {
             [u16; 2]
         };
@@ -526,6 +593,9 @@ pub mod macros {
         (VertexFormat::UShort,4) => {
             [u16; 4]
         };
+        (VertexFormat::Float,1) => {
+            [f32; 1]
+        };
         (VertexFormat::Float,2) => {
             [f32; 2]
         };
@@ -556,6 +626,16 @@ pub mod macros {
             }
         }
 
+        impl_vertex! {
+            Vertex3 {
+                position => [Position; Float; 3; false],
+                color => [Color0; UByte; 4; true],
+                texcoord => [Texcoord0; Float; 2; false],
+                texcoord2 => [Texcoord1; UShort; 2; true],
+                weight => [Weight; Float; 1; false],
+            }
+        }
+
         fn as_bytes<T>(values: &[T]) -> &[u8]
         where
             T: Copy,
@@ -599,6 +679,19 @@ pub mod macros {
             let _v = Vertex2::new([1.0, 1.0], [0, 0, 0, 0], [0, 0]);
             let _b = Vertex2::encode(&[]);
             assert_eq!(layout.stride() as usize, ::std::mem::size_of::<Vertex2>());
+
+            let layout = Vertex3::layout();
+            assert_eq!(layout.stride() as usize, ::std::mem::size_of::<Vertex3>());
+            assert_eq!(layout.offset(Attribute::Color0), Some(12));
+            assert_eq!(layout.offset(Attribute::Texcoord1), Some(24));
+            assert_eq!(layout.offset(Attribute::Weight), Some(28));
+
+            let element = layout.element(Attribute::Texcoord1).unwrap();
+            assert_eq!(element.format, VertexFormat::UShort);
+            assert!(element.normalized);
+
+            let attributes = Vertex3::attributes();
+            assert!(attributes.is_match(&layout));
         }
     }
 }
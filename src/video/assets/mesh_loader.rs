@@ -1,24 +1,55 @@
 use bincode;
 use std::io::Cursor;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, RwLock};
 
 use crate::errors::*;
 use crate::res::utils::prelude::ResourceLoader;
 use crate::utils::double_buf::DoubleBuf;
+use crate::utils::hash::FastHashMap;
 
 use super::super::backends::frame::{Command, Frame};
 use super::mesh::*;
 
 pub const MAGIC: [u8; 8] = [b'V', b'M', b'S', b'H', b' ', 0, 0, 1];
 
+/// The copies of mesh data kept in memory, which could be read back by CPU.
+#[derive(Default)]
+pub struct MeshCopies {
+    /// Whether the data of meshes created afterwards are kept.
+    pub readable: bool,
+    pub data: FastHashMap<MeshHandle, MeshData>,
+}
+
+/// The parser of mesh files in other formats than the built-in binary one, e.g. the
+/// `ObjParser`. The files are passed to the first parser that accepts them.
+pub trait MeshParser: Send + Sync + 'static {
+    /// Checks if the bytes are in the format of this parser.
+    fn accept(&self, bytes: &[u8]) -> bool;
+
+    /// Parses the bytes into mesh.
+    fn parse(&self, bytes: &[u8]) -> Result<(MeshParams, MeshData)>;
+}
+
+pub type MeshParsers = Arc<RwLock<Vec<Arc<dyn MeshParser>>>>;
+
 #[derive(Clone)]
 pub struct MeshLoader {
     frames: Arc<DoubleBuf<Frame>>,
+    copies: Arc<Mutex<MeshCopies>>,
+    parsers: MeshParsers,
 }
 
 impl MeshLoader {
-    pub(crate) fn new(frames: Arc<DoubleBuf<Frame>>) -> Self {
-        MeshLoader { frames }
+    pub(crate) fn new(
+        frames: Arc<DoubleBuf<Frame>>,
+        copies: Arc<Mutex<MeshCopies>>,
+        parsers: MeshParsers,
+    ) -> Self {
+        MeshLoader {
+            frames,
+            copies,
+            parsers,
+        }
     }
 }
 
@@ -28,13 +59,17 @@ impl ResourceLoader for MeshLoader {
     type Resource = MeshParams;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if bytes[0..8] != MAGIC[..] {
-            bail!("[MeshLoader] MAGIC number not match.");
-        }
-
-        let mut file = Cursor::new(&bytes[8..]);
-        let params: MeshParams = bincode::deserialize_from(&mut file)?;
-        let data = bincode::deserialize_from(&mut file)?;
+        let (params, data) = if bytes.len() >= 8 && bytes[0..8] == MAGIC[..] {
+            let mut file = Cursor::new(&bytes[8..]);
+            let params: MeshParams = bincode::deserialize_from(&mut file)?;
+            (params, bincode::deserialize_from(&mut file)?)
+        } else {
+            let parsers = self.parsers.read().unwrap();
+            match parsers.iter().find(|v| v.accept(bytes)) {
+                Some(parser) => parser.parse(bytes)?,
+                None => bail!("[MeshLoader] MAGIC number not match."),
+            }
+        };
 
         info!(
             "[MeshLoader] load {:?}. (Verts: {}, Indxes: {})",
@@ -44,16 +79,34 @@ impl ResourceLoader for MeshLoader {
         Ok((params, Some(data)))
     }
 
+    fn validate(&self, item: &Self::Intermediate) -> Result<()> {
+        item.0.validate(item.1.as_ref())?;
+        Ok(())
+    }
+
     fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
         info!("[MeshLoader] create {:?}.", handle);
         item.0.validate(item.1.as_ref())?;
+
+        if let Some(ref data) = item.1 {
+            let mut copies = self.copies.lock().unwrap();
+            if copies.readable {
+                copies.data.insert(handle, data.clone());
+            }
+        }
+
         let cmd = Command::CreateMesh(Box::new((handle, item.0.clone(), item.1)));
         self.frames.write().cmds.push(cmd);
         Ok(item.0)
     }
 
+    fn size(&self, item: &Self::Intermediate) -> usize {
+        item.0.vertex_buffer_len() + item.0.index_buffer_len()
+    }
+
     fn delete(&self, handle: Self::Handle, _: Self::Resource) {
         info!("[MeshLoader] delete {:?}.", handle);
+        self.copies.lock().unwrap().data.remove(&handle);
         let cmd = Command::DeleteMesh(handle);
         self.frames.write().cmds.push(cmd);
     }
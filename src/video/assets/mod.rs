@@ -1,7 +1,18 @@
+pub mod ktx2;
+pub mod obj;
+pub mod object;
+pub mod query;
+pub mod sampler;
 pub mod shader;
+pub mod shader_preprocessor;
+pub mod shader_variants;
 pub mod surface;
+pub mod tangents;
 pub mod texture;
+pub mod texture_3d_loader;
+pub mod texture_cube_loader;
 pub mod texture_loader;
+pub mod uniform_buffer;
 #[macro_use]
 pub mod mesh;
 pub mod mesh_loader;
@@ -11,17 +22,35 @@ pub mod prelude {
 
     pub use super::shader::{
         Attribute, AttributeLayout, AttributeLayoutBuilder, BlendFactor, BlendValue, Comparison,
-        CullFace, Equation, FrontFaceOrder, RenderState, ShaderHandle, ShaderParams,
-        UniformVariable, UniformVariableLayout, UniformVariableLayoutBuilder, UniformVariableType,
+        CullFace, Equation, FrontFaceOrder, RenderState, ShaderHandle, ShaderParams, StencilFace,
+        StencilOp, StencilState, UniformVariable, UniformVariableLayout,
+        UniformVariableLayoutBuilder, UniformVariableType,
     };
 
+    pub use super::shader_preprocessor::{ShaderDefines, ShaderPreprocessor};
+    pub use super::shader_variants::ShaderVariants;
+
     pub use super::texture::{
-        RenderTextureFormat, RenderTextureHandle, RenderTextureParams, TextureData, TextureFilter,
-        TextureFormat, TextureHandle, TextureHint, TextureParams, TextureWrap,
+        RenderTextureFormat, RenderTextureHandle, RenderTextureParams, Texture3DData,
+        Texture3DHandle, Texture3DParams, TextureCubeData, TextureCubeHandle, TextureCubeParams,
+        TextureData, TextureFilter, TextureFormat, TextureHandle, TextureHint, TextureParams,
+        TextureStreamingParams, TextureWrap,
     };
 
+    pub use super::uniform_buffer::{UniformBufferHandle, UniformBufferParams};
+
+    pub use super::sampler::{SamplerHandle, SamplerParams};
+
+    pub use super::query::{QueryHandle, QueryKind, QueryParams};
+
+    pub use super::object::VideoObject;
+
     pub use super::mesh::{
-        IndexFormat, MeshData, MeshHandle, MeshHint, MeshIndex, MeshParams, MeshPrimitive,
-        VertexFormat, VertexLayout,
+        IndexFormat, InstanceBufferHandle, InstanceBufferParams, MeshData, MeshHandle, MeshHint,
+        MeshIndex, MeshParams, MeshPrimitive, VertexFormat, VertexLayout,
     };
+
+    pub use super::ktx2::{BasisFormat, BasisImage, BasisTranscoder};
+    pub use super::mesh_loader::MeshParser;
+    pub use super::obj::ObjParser;
 }
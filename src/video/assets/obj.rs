@@ -0,0 +1,601 @@
+//! Parser of Wavefront OBJ meshes and their MTL material libraries.
+//!
+//! The positions, normals and texture coordinates of faces are supported, polygons are
+//! triangulated as fans. The faces are split into sub-meshes by their `usemtl` statements,
+//! in the order that the materials first appear, which could be fetched by `materials`, or
+//! resolved with the MTL libraries referenced by `mtllib` with `resolve_materials`.
+//!
+//! Since OBJ uses a right handed coordinate system, the z-axis of positions and normals
+//! will be flipped to fit our left handed, y-up world. And the v-axis of texture coordinates
+//! is flipped too, so the origin of textures is at the top-left corner.
+//!
+//! Smooth normals are generated if the file does not provide any, and the tangents are
+//! generated for normal mapping if the file has texture coordinates.
+//!
+//! The vertex colors that follow positions, e.g. `v 0.0 1.0 0.0 1.0 0.0 0.0`, are supported
+//! as an extension, which are imported as `Attribute::Color0`.
+
+use std::collections::HashMap;
+use std::str;
+
+use crate::errors::*;
+use cgmath::Point3;
+
+use crate::math::prelude::{Aabb3, Color, InnerSpace, Vector3};
+
+use super::mesh::*;
+use super::mesh_loader::MeshParser;
+use super::shader::Attribute;
+use super::tangents;
+
+/// The parser of OBJ files, which is registered into the video system by default.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ObjParser;
+
+impl MeshParser for ObjParser {
+    fn accept(&self, bytes: &[u8]) -> bool {
+        let text = match str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(_) => return false,
+        };
+
+        const KEYWORDS: [&str; 8] = ["v", "vn", "vt", "f", "o", "g", "mtllib", "usemtl"];
+        text.lines()
+            .map(|v| v.trim())
+            .find(|v| !v.is_empty() && !v.starts_with('#'))
+            .and_then(|v| v.split_whitespace().next())
+            .map(|v| KEYWORDS.contains(&v))
+            .unwrap_or(false)
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<(MeshParams, MeshData)> {
+        let text = str::from_utf8(bytes)?;
+        parse(text)
+    }
+}
+
+/// The material described in MTL libraries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    /// The ambient color, `Ka`.
+    pub ambient: Color<f32>,
+    /// The diffuse color, `Kd`, whose alpha is the dissolve factor `d`.
+    pub diffuse: Color<f32>,
+    /// The specular color, `Ks`.
+    pub specular: Color<f32>,
+    /// The specular exponent, `Ns`.
+    pub shininess: f32,
+    /// The path of diffuse texture map, `map_Kd`.
+    pub diffuse_texture: Option<String>,
+    /// The path of specular texture map, `map_Ks`.
+    pub specular_texture: Option<String>,
+}
+
+impl ObjMaterial {
+    fn new(name: &str) -> Self {
+        ObjMaterial {
+            name: name.to_owned(),
+            ambient: Color::black(),
+            diffuse: Color::white(),
+            specular: Color::black(),
+            shininess: 0.0,
+            diffuse_texture: None,
+            specular_texture: None,
+        }
+    }
+}
+
+/// Parses the OBJ file into mesh.
+pub fn parse(text: &str) -> Result<(MeshParams, MeshData)> {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut texcoords = Vec::new();
+    let mut colors = Vec::new();
+
+    // The faces of sub-meshes, which are triangulated already.
+    let mut groups: Vec<(Option<&str>, Vec<[usize; 3]>)> = vec![(None, Vec::new())];
+    let mut current = 0;
+
+    for (i, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(v) => v,
+            None => continue,
+        };
+
+        match keyword {
+            "v" => {
+                positions.push(vector(&mut words, i)?);
+
+                // The optional weight `w` comes alone, while the colors come in threes.
+                let rest: Vec<_> = words.collect();
+                if rest.len() >= 3 {
+                    let r = float(Some(rest[0]), i)?;
+                    let g = float(Some(rest[1]), i)?;
+                    let b = float(Some(rest[2]), i)?;
+                    colors.resize(positions.len() - 1, [1.0; 3]);
+                    colors.push([r, g, b]);
+                }
+            }
+            "vn" => normals.push(vector(&mut words, i)?),
+            "vt" => {
+                let u = float(words.next(), i)?;
+                let v = words.next().map(|v| float(Some(v), i)).unwrap_or(Ok(0.0))?;
+                texcoords.push([u, 1.0 - v]);
+            }
+            "f" => {
+                let mut corners = Vec::new();
+                for v in words {
+                    corners.push(corner(
+                        v,
+                        (positions.len(), texcoords.len(), normals.len()),
+                        i,
+                    )?);
+                }
+
+                if corners.len() < 3 {
+                    bail!(
+                        "[ObjParser] face at line {} has less than 3 vertices.",
+                        i + 1
+                    );
+                }
+
+                for j in 1..corners.len() - 1 {
+                    // Reverses the winding order since we have flipped the z-axis.
+                    let faces = &mut groups[current].1;
+                    faces.push(corners[0]);
+                    faces.push(corners[j + 1]);
+                    faces.push(corners[j]);
+                }
+            }
+            "usemtl" => {
+                let name = words.next();
+                current = match groups.iter().position(|v| v.0 == name) {
+                    Some(index) => index,
+                    None => {
+                        groups.push((name, Vec::new()));
+                        groups.len() - 1
+                    }
+                };
+            }
+            _ => {}
+        }
+    }
+
+    groups.retain(|v| !v.1.is_empty());
+    if groups.is_empty() {
+        bail!("[ObjParser] there is no face in mesh.");
+    }
+
+    let has_normals = !normals.is_empty();
+    let has_texcoords = !texcoords.is_empty();
+    let has_colors = !colors.is_empty();
+    colors.resize(positions.len(), [1.0; 3]);
+
+    // Deduplicates the corners which refers to the same position, normal and texcoord.
+    let mut corners = HashMap::new();
+    let mut verts: Vec<[usize; 3]> = Vec::new();
+    let mut idxes = Vec::new();
+    let mut sub_mesh_offsets = Vec::new();
+
+    for (_, faces) in &groups {
+        sub_mesh_offsets.push(idxes.len());
+        for &v in faces {
+            let index = *corners.entry(v).or_insert_with(|| {
+                verts.push(v);
+                verts.len() - 1
+            });
+
+            idxes.push(index as u32);
+        }
+    }
+
+    let smooth_normals = if has_normals {
+        Vec::new()
+    } else {
+        generate_normals(&positions, &verts, &idxes)
+    };
+
+    let mut layout = VertexLayout::build()
+        .with(Attribute::Position, VertexFormat::Float, 3, false)
+        .with(Attribute::Normal, VertexFormat::Float, 3, false);
+
+    if has_texcoords {
+        layout = layout.with(Attribute::Texcoord0, VertexFormat::Float, 2, false);
+    }
+
+    if has_colors {
+        layout = layout.with(Attribute::Color0, VertexFormat::Float, 3, false);
+    }
+
+    let mut aabb: Option<Aabb3<f32>> = None;
+    let mut vptr = Vec::with_capacity(verts.len() * 8);
+    for (i, &[p, t, n]) in verts.iter().enumerate() {
+        let position = positions[p];
+        let normal = if has_normals {
+            normals.get(n.wrapping_sub(1)).cloned().unwrap_or([0.0; 3])
+        } else {
+            smooth_normals[i]
+        };
+
+        let point = Point3::new(position[0], position[1], position[2]);
+        aabb = Some(
+            aabb.map(|v| v.grow(point))
+                .unwrap_or_else(|| Aabb3::new(point, point)),
+        );
+
+        vptr.extend_from_slice(&position);
+        vptr.extend_from_slice(&normal);
+        if has_texcoords {
+            let texcoord = texcoords.get(t.wrapping_sub(1)).cloned();
+            vptr.extend_from_slice(&texcoord.unwrap_or([0.0; 2]));
+        }
+
+        if has_colors {
+            vptr.extend_from_slice(&colors[p]);
+        }
+    }
+
+    let mut params = MeshParams::default();
+    params.layout = layout.finish();
+    params.num_verts = verts.len();
+    params.num_idxes = idxes.len();
+    params.sub_mesh_offsets = sub_mesh_offsets.into_iter().collect();
+    params.aabb = aabb.unwrap_or_else(Aabb3::zero);
+
+    let iptr = if verts.len() <= 0xffff {
+        params.index_format = IndexFormat::U16;
+        let idxes: Vec<u16> = idxes.into_iter().map(|v| v as u16).collect();
+        IndexFormat::encode(&idxes).into()
+    } else {
+        params.index_format = IndexFormat::U32;
+        IndexFormat::encode(&idxes).into()
+    };
+
+    // The vertices are written in native byte order, like the indices.
+    let mut bytes = Vec::with_capacity(vptr.len() * 4);
+    for v in vptr {
+        bytes.extend_from_slice(&v.to_bits().to_ne_bytes());
+    }
+
+    let data = MeshData {
+        vptr: bytes.into_boxed_slice(),
+        iptr,
+    };
+
+    if has_texcoords {
+        return tangents::generate(&params, &data);
+    }
+
+    Ok((params, data))
+}
+
+/// Gets the names of materials used by the OBJ file, in the same order of sub-meshes that
+/// are produced by `parse`. The sub-mesh of faces without any material is named as `None`.
+pub fn materials(text: &str) -> Vec<Option<String>> {
+    let mut groups: Vec<(Option<&str>, bool)> = vec![(None, false)];
+    let mut current = 0;
+
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("f") => groups[current].1 = true,
+            Some("usemtl") => {
+                let name = words.next();
+                current = match groups.iter().position(|v| v.0 == name) {
+                    Some(index) => index,
+                    None => {
+                        groups.push((name, false));
+                        groups.len() - 1
+                    }
+                };
+            }
+            _ => {}
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|v| v.1)
+        .map(|v| v.0.map(|v| v.to_owned()))
+        .collect()
+}
+
+/// Gets the paths of MTL libraries referenced by the `mtllib` statements of OBJ file, which
+/// are relative to the OBJ file.
+pub fn material_libraries(text: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        if words.next() == Some("mtllib") {
+            for path in words {
+                if !paths.iter().any(|v| v == path) {
+                    paths.push(path.to_owned());
+                }
+            }
+        }
+    }
+
+    paths
+}
+
+/// Resolves the materials of sub-meshes that are produced by `parse`, with the MTL
+/// libraries referenced by the OBJ file. The libraries are read by `read` with their
+/// paths relative to the OBJ file.
+///
+/// The material of sub-mesh is `None` if its faces have no `usemtl`, or the material is
+/// not found in the libraries.
+pub fn resolve_materials<F>(text: &str, mut read: F) -> Result<Vec<Option<ObjMaterial>>>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut libraries = Vec::new();
+    for path in material_libraries(text) {
+        libraries.extend(parse_mtl(&read(&path)?)?);
+    }
+
+    let materials = materials(text)
+        .into_iter()
+        .map(|name| {
+            let name = name?;
+            // The later definitions override the former ones.
+            libraries.iter().rev().find(|v| v.name == name).cloned()
+        })
+        .collect();
+
+    Ok(materials)
+}
+
+/// Parses the materials in MTL library.
+pub fn parse_mtl(text: &str) -> Result<Vec<ObjMaterial>> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(v) => v,
+            None => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = words.next().unwrap_or_default();
+            materials.push(ObjMaterial::new(name));
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(v) => v,
+            None if keyword.starts_with('#') => continue,
+            None => bail!(
+                "[ObjParser] {} at line {} is out of any material.",
+                keyword,
+                i + 1
+            ),
+        };
+
+        match keyword {
+            "Ka" => material.ambient = color(&mut words, 1.0, i)?,
+            "Kd" => material.diffuse = color(&mut words, material.diffuse.a, i)?,
+            "Ks" => material.specular = color(&mut words, 1.0, i)?,
+            "Ns" => material.shininess = float(words.next(), i)?,
+            "d" => material.diffuse.a = float(words.next(), i)?,
+            "Tr" => material.diffuse.a = 1.0 - float(words.next(), i)?,
+            // The options of texture maps are ignored, the path is the last word.
+            "map_Kd" => material.diffuse_texture = words.last().map(|v| v.to_owned()),
+            "map_Ks" => material.specular_texture = words.last().map(|v| v.to_owned()),
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+fn generate_normals(positions: &[[f32; 3]], verts: &[[usize; 3]], idxes: &[u32]) -> Vec<[f32; 3]> {
+    // The normals are accumulated by positions, so the faces which share the same position
+    // are smoothed even if they have different texcoords.
+    let mut normals = vec![Vector3::new(0.0, 0.0, 0.0); positions.len()];
+    let point = |i: u32| {
+        let v = positions[verts[i as usize][0]];
+        Vector3::new(v[0], v[1], v[2])
+    };
+
+    for v in idxes.chunks(3) {
+        let (a, b, c) = (point(v[0]), point(v[1]), point(v[2]));
+        // The winding order is reversed along with the flipped z-axis.
+        let normal = (b - a).cross(c - a);
+        for &i in v {
+            normals[verts[i as usize][0]] += normal;
+        }
+    }
+
+    verts
+        .iter()
+        .map(|v| {
+            let n = normals[v[0]];
+            if n.magnitude2() > 0.0 {
+                n.normalize().into()
+            } else {
+                [0.0, 1.0, 0.0]
+            }
+        })
+        .collect()
+}
+
+// Parses the corner of face in forms of `v`, `v/vt`, `v//vn` or `v/vt/vn`. The indices of
+// texcoords and normals are kept 1-based, so zero means missing.
+fn corner(word: &str, lens: (usize, usize, usize), line: usize) -> Result<[usize; 3]> {
+    let mut indices = word.split('/');
+    let p = index(indices.next(), lens.0, line)?;
+    let t = match indices.next() {
+        Some("") | None => 0,
+        v => index(v, lens.1, line)? + 1,
+    };
+    let n = match indices.next() {
+        Some("") | None => 0,
+        v => index(v, lens.2, line)? + 1,
+    };
+
+    Ok([p, t, n])
+}
+
+// Resolves the 1-based or negative relative index into 0-based one.
+fn index(word: Option<&str>, len: usize, line: usize) -> Result<usize> {
+    let v: i64 = match word.map(|v| v.parse()) {
+        Some(Ok(v)) => v,
+        _ => bail!("[ObjParser] invalid index at line {}.", line + 1),
+    };
+
+    let index = if v < 0 { len as i64 + v } else { v - 1 };
+    if index < 0 || index >= len as i64 {
+        bail!(
+            "[ObjParser] index {} at line {} is out of bounds.",
+            v,
+            line + 1
+        );
+    }
+
+    Ok(index as usize)
+}
+
+fn vector<'a, T>(words: &mut T, line: usize) -> Result<[f32; 3]>
+where
+    T: Iterator<Item = &'a str>,
+{
+    let x = float(words.next(), line)?;
+    let y = float(words.next(), line)?;
+    let z = float(words.next(), line)?;
+    Ok([x, y, -z])
+}
+
+fn color<'a, T>(words: &mut T, alpha: f32, line: usize) -> Result<Color<f32>>
+where
+    T: Iterator<Item = &'a str>,
+{
+    let r = float(words.next(), line)?;
+    let g = float(words.next(), line)?;
+    let b = float(words.next(), line)?;
+    Ok(Color::new(r, g, b, alpha))
+}
+
+fn float(word: Option<&str>, line: usize) -> Result<f32> {
+    match word.map(|v| v.parse()) {
+        Some(Ok(v)) => Ok(v),
+        _ => bail!("[ObjParser] invalid number at line {}.", line + 1),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CUBE: &str = "
+# a quad and a triangle with different materials.
+mtllib quad.mtl
+v -1.0 -1.0 0.0
+v 1.0 -1.0 0.0
+v 1.0 1.0 0.0
+v -1.0 1.0 0.0
+vt 0.0 0.0
+vt 1.0 1.0
+usemtl red
+f 1/1 2/1 3/2 4/2
+usemtl blue
+f -4 -3 -2
+";
+
+    fn floats(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks(4)
+            .map(|v| f32::from_bits(u32::from_ne_bytes([v[0], v[1], v[2], v[3]])))
+            .collect()
+    }
+
+    #[test]
+    fn obj() {
+        assert!(ObjParser.accept(CUBE.as_bytes()));
+        assert!(!ObjParser.accept(b"VMSH \x00\x00\x01"));
+
+        let (params, data) = parse(CUBE).unwrap();
+        assert!(params.validate(Some(&data)).is_ok());
+        assert_eq!(params.num_idxes, 9);
+        assert_eq!(&params.sub_mesh_offsets[..], &[0, 6]);
+        assert_eq!(params.index_format, IndexFormat::U16);
+        assert_eq!(params.layout.stride(), 48);
+        assert_eq!(params.layout.offset(Attribute::Tangent), Some(32));
+        assert_eq!(params.num_verts, 7);
+        assert_eq!(data.vptr.len(), params.vertex_buffer_len());
+
+        let materials = materials(CUBE);
+        assert_eq!(materials, vec![Some("red".into()), Some("blue".into())]);
+
+        // The normals are generated, and pointing to -z after flipping.
+        let verts = floats(&data.vptr);
+        assert_eq!(verts.len(), params.num_verts * 12);
+        assert_eq!(&verts[0..8], &[-1.0, -1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 1.0]);
+
+        // The texcoords of quad are degenerated, so the tangent is picked arbitrarily.
+        assert_eq!(&verts[8..12], &[1.0, 0.0, 0.0, 1.0]);
+
+        // The vertex colors.
+        let (params, data) = parse("v 0 0 0 1 0 0\nv 1 0 0\nv 0 1 0 0 0 1\nf 1 2 3").unwrap();
+        assert_eq!(params.layout.offset(Attribute::Color0), Some(24));
+        let verts = floats(&data.vptr);
+        let mut colors: Vec<&[f32]> = verts.chunks(9).map(|v| &v[6..9]).collect();
+        colors.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            colors,
+            vec![&[0.0, 0.0, 1.0], &[1.0, 0.0, 0.0], &[1.0, 1.0, 1.0]]
+        );
+
+        // The weights of vertices are ignored.
+        let (params, _) = parse("v 0 0 0 1\nv 1 0 0 1\nv 0 1 0 1\nf 1 2 3").unwrap();
+        assert_eq!(params.layout.offset(Attribute::Color0), None);
+
+        assert!(parse("v 0 0 0\nf 1 2 3").is_err());
+        assert!(parse("v 0 0 0").is_err());
+    }
+
+    #[test]
+    fn mtl() {
+        let materials = parse_mtl(
+            "
+newmtl red
+Kd 1.0 0.0 0.0
+d 0.5
+Ns 32
+map_Kd -bm 1.0 textures/red.png
+newmtl blue
+Kd 0.0 0.0 1.0
+",
+        )
+        .unwrap();
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].diffuse, Color::new(1.0, 0.0, 0.0, 0.5));
+        assert_eq!(materials[0].shininess, 32.0);
+        assert_eq!(
+            materials[0].diffuse_texture,
+            Some("textures/red.png".into())
+        );
+        assert_eq!(materials[1].name, "blue");
+        assert!(parse_mtl("Kd 1.0 1.0 1.0").is_err());
+    }
+
+    #[test]
+    fn mtllib() {
+        assert_eq!(material_libraries(CUBE), vec!["quad.mtl".to_owned()]);
+
+        let materials = resolve_materials(CUBE, |path| {
+            assert_eq!(path, "quad.mtl");
+            Ok("newmtl red\nKd 1.0 0.0 0.0\n".to_owned())
+        })
+        .unwrap();
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].as_ref().unwrap().diffuse, Color::red());
+        assert_eq!(materials[1], None);
+
+        assert!(resolve_materials(CUBE, |_| bail!("not found.")).is_err());
+    }
+}
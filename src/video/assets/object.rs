@@ -0,0 +1,70 @@
+//! The handles of all kinds of video objects.
+
+use std::fmt::Debug;
+
+use crate::video::assets::mesh::{InstanceBufferHandle, MeshHandle};
+use crate::video::assets::query::QueryHandle;
+use crate::video::assets::sampler::SamplerHandle;
+use crate::video::assets::shader::ShaderHandle;
+use crate::video::assets::surface::SurfaceHandle;
+use crate::video::assets::texture::{
+    RenderTextureHandle, Texture3DHandle, TextureCubeHandle, TextureHandle,
+};
+use crate::video::assets::uniform_buffer::UniformBufferHandle;
+
+/// The handle of any kind of video objects, e.g. to name them with `video::set_debug_name`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VideoObject {
+    Surface(SurfaceHandle),
+    Shader(ShaderHandle),
+    UniformBuffer(UniformBufferHandle),
+    Query(QueryHandle),
+    Mesh(MeshHandle),
+    InstanceBuffer(InstanceBufferHandle),
+    Texture(TextureHandle),
+    TextureCube(TextureCubeHandle),
+    Texture3D(Texture3DHandle),
+    RenderTexture(RenderTextureHandle),
+    Sampler(SamplerHandle),
+}
+
+impl VideoObject {
+    /// Gets the wrapped handle.
+    pub(crate) fn handle(&self) -> &dyn Debug {
+        match *self {
+            VideoObject::Surface(ref v) => v,
+            VideoObject::Shader(ref v) => v,
+            VideoObject::UniformBuffer(ref v) => v,
+            VideoObject::Query(ref v) => v,
+            VideoObject::Mesh(ref v) => v,
+            VideoObject::InstanceBuffer(ref v) => v,
+            VideoObject::Texture(ref v) => v,
+            VideoObject::TextureCube(ref v) => v,
+            VideoObject::Texture3D(ref v) => v,
+            VideoObject::RenderTexture(ref v) => v,
+            VideoObject::Sampler(ref v) => v,
+        }
+    }
+}
+
+macro_rules! impl_video_object {
+    ($handle:ident, $variant:ident) => {
+        impl From<$handle> for VideoObject {
+            fn from(handle: $handle) -> Self {
+                VideoObject::$variant(handle)
+            }
+        }
+    };
+}
+
+impl_video_object!(SurfaceHandle, Surface);
+impl_video_object!(ShaderHandle, Shader);
+impl_video_object!(UniformBufferHandle, UniformBuffer);
+impl_video_object!(QueryHandle, Query);
+impl_video_object!(MeshHandle, Mesh);
+impl_video_object!(InstanceBufferHandle, InstanceBuffer);
+impl_video_object!(TextureHandle, Texture);
+impl_video_object!(TextureCubeHandle, TextureCube);
+impl_video_object!(Texture3DHandle, Texture3D);
+impl_video_object!(RenderTextureHandle, RenderTexture);
+impl_video_object!(SamplerHandle, Sampler);
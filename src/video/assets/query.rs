@@ -0,0 +1,29 @@
+//! Occlusion queries, which count the samples that pass the depth test between a pair
+//! of `begin_query` and `end_query` commands. The results are fetched asynchronously,
+//! usually a few frames after the draws were submitted.
+
+impl_handle!(QueryHandle);
+
+/// The kinds of occlusion query.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+pub enum QueryKind {
+    /// Counts the number of samples that pass the depth test. This fallbacks to
+    /// `AnySamplesPassed` on OpenGL ES and WebGL.
+    SamplesPassed,
+    /// Checks if any sample passed the depth test, the result would be 0 or 1.
+    AnySamplesPassed,
+}
+
+/// The setup parameters of query object.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct QueryParams {
+    pub kind: QueryKind,
+}
+
+impl Default for QueryParams {
+    fn default() -> Self {
+        QueryParams {
+            kind: QueryKind::AnySamplesPassed,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Sampler objects, which describe how textures are filtered and wrapped when they are
+//! sampled in shaders. The same texture could be sampled with different samplers in
+//! different draws, e.g. a nearest filtered preview and a trilinear filtered terrain.
+//!
+//! Textures are sampled with the wrap and filter modes of their own params by default,
+//! the sampler overrides them when it's bound along with texture:
+//!
+//! ```rust,ignore
+//! dc.set_uniform_variable("u_Texture", (texture, sampler));
+//! ```
+
+use crate::video::assets::texture::{TextureFilter, TextureWrap};
+use crate::video::errors::{Error, Result};
+
+impl_handle!(SamplerHandle);
+
+/// The setup parameters of sampler object.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
+pub struct SamplerParams {
+    /// The wrap modes of texture coordinates s, t and r.
+    pub wrap: [TextureWrap; 3],
+    /// The filter used when the texture is minified.
+    pub min_filter: TextureFilter,
+    /// The filter used when the texture is magnified.
+    pub mag_filter: TextureFilter,
+    /// The filter between mipmap levels, or `None` to sample the base level only. The
+    /// textures must have all their mipmaps if this is set.
+    pub mip_filter: Option<TextureFilter>,
+    /// The maximum degree of anisotropic filtering, 1 disables it. The value is clamped
+    /// to `Capabilities::max_anisotropy`.
+    pub max_anisotropy: u32,
+    /// The bias that is added to the level of detail before selecting mipmap. It's not
+    /// supported on OpenGL ES and WebGL, and ignored there.
+    pub lod_bias: f32,
+    /// Clamps the level of detail into `[min, max]`, e.g. `[0.0, 0.0]` samples the base
+    /// level only, and `[2.0, 1000.0]` skips the two largest mipmaps. It's not supported
+    /// on OpenGL ES 2.0.
+    pub lod_range: [f32; 2],
+}
+
+impl Default for SamplerParams {
+    fn default() -> Self {
+        SamplerParams {
+            wrap: [TextureWrap::Repeat; 3],
+            min_filter: TextureFilter::Linear,
+            mag_filter: TextureFilter::Linear,
+            mip_filter: None,
+            max_anisotropy: 1,
+            lod_bias: 0.0,
+            lod_range: [-1000.0, 1000.0],
+        }
+    }
+}
+
+impl SamplerParams {
+    /// Creates a sampler with the same `wrap` mode on all axes, and the same `filter`
+    /// for minification, magnification and mipmaps.
+    pub fn new(wrap: TextureWrap, filter: TextureFilter, mipmap: bool) -> Self {
+        SamplerParams {
+            wrap: [wrap; 3],
+            min_filter: filter,
+            mag_filter: filter,
+            mip_filter: if mipmap { Some(filter) } else { None },
+            ..Default::default()
+        }
+    }
+
+    /// Creates a trilinear filtered sampler with anisotropic filtering of `level`.
+    pub fn anisotropic(wrap: TextureWrap, level: u32) -> Self {
+        let mut params = SamplerParams::new(wrap, TextureFilter::Linear, true);
+        params.max_anisotropy = level;
+        params
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.max_anisotropy == 0 {
+            let err = "The max anisotropy of sampler should be at least 1.";
+            return Err(Error::ValidationFailed(err.into()));
+        }
+
+        if !self.lod_bias.is_finite() {
+            let err = "The lod bias of sampler should be finite.";
+            return Err(Error::ValidationFailed(err.into()));
+        }
+
+        if !(self.lod_range[0] <= self.lod_range[1]) {
+            let err = "The lod range of sampler should not be empty.";
+            return Err(Error::ValidationFailed(err.into()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn params() {
+        let params = SamplerParams::anisotropic(TextureWrap::Clamp, 8);
+        assert_eq!(params.wrap, [TextureWrap::Clamp; 3]);
+        assert_eq!(params.mip_filter, Some(TextureFilter::Linear));
+        assert!(params.validate().is_ok());
+
+        let params = SamplerParams::new(TextureWrap::Repeat, TextureFilter::Nearest, false);
+        assert_eq!(params.mip_filter, None);
+        assert_eq!(params.max_anisotropy, 1);
+
+        let mut params = SamplerParams::default();
+        params.max_anisotropy = 0;
+        assert!(params.validate().is_err());
+
+        let mut params = SamplerParams::default();
+        params.lod_range = [2.0, 1.0];
+        assert!(params.validate().is_err());
+    }
+}
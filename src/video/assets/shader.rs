@@ -6,14 +6,25 @@ use std::str::FromStr;
 use crate::math::prelude::{Matrix2, Matrix3, Matrix4, Vector2, Vector3, Vector4};
 use crate::utils::prelude::{FastHashMap, HashValue};
 use crate::video::assets::mesh::VertexLayout;
-use crate::video::assets::texture::{RenderTextureHandle, TextureHandle};
+use crate::video::assets::sampler::SamplerHandle;
+use crate::video::assets::texture::{
+    RenderTextureHandle, Texture3DHandle, TextureCubeHandle, TextureHandle,
+};
+use crate::video::assets::uniform_buffer::UniformBufferHandle;
+use crate::video::command::MatrixArray;
 use crate::video::errors::{Error, Result};
-use crate::video::{MAX_UNIFORM_VARIABLES, MAX_VERTEX_ATTRIBUTES};
+use crate::video::{MAX_FRAMEBUFFER_ATTACHMENTS, MAX_UNIFORM_VARIABLES, MAX_VERTEX_ATTRIBUTES};
 
 impl_handle!(ShaderHandle);
 
 /// A `ShaderParams` encapusulate all the informations we need to configurate
 /// OpenGL before real drawing, like shaders, render states, etc.
+///
+/// The declared `attributes` and `uniforms` are validated against the active variables
+/// of the linked program, and the mismatches are reported when creating the shader.
+/// The layout that is left empty would be populated automatically with reflection,
+/// where `sampler2D`s are treated as `Texture`s and the colors are optional attributes.
+/// Notes that the populated layouts are only known by the video backend.
 #[derive(Debug, Clone, Default)]
 pub struct ShaderParams {
     pub attributes: AttributeLayout,
@@ -62,6 +73,21 @@ pub enum Attribute {
     Texcoord1 = 9,
     Texcoord2 = 10,
     Texcoord3 = 11,
+    Instance0 = 12,
+    Instance1 = 13,
+    Instance2 = 14,
+    Instance3 = 15,
+}
+
+impl Attribute {
+    /// Gets the value of attribute when it is declared as optional, but is missing in the
+    /// vertex buffer. The colors default to white, and others default to `(0, 0, 0, 1)`.
+    pub fn default_value(self) -> [f32; 4] {
+        match self {
+            Attribute::Color0 | Attribute::Color1 => [1.0, 1.0, 1.0, 1.0],
+            _ => [0.0, 0.0, 0.0, 1.0],
+        }
+    }
 }
 
 impl Into<&'static str> for Attribute {
@@ -79,6 +105,10 @@ impl Into<&'static str> for Attribute {
             Attribute::Texcoord1 => "Texcoord1",
             Attribute::Texcoord2 => "Texcoord2",
             Attribute::Texcoord3 => "Texcoord3",
+            Attribute::Instance0 => "Instance0",
+            Attribute::Instance1 => "Instance1",
+            Attribute::Instance2 => "Instance2",
+            Attribute::Instance3 => "Instance3",
         }
     }
 }
@@ -100,6 +130,10 @@ impl FromStr for Attribute {
             "Texcoord1" => Ok(Attribute::Texcoord1),
             "Texcoord2" => Ok(Attribute::Texcoord2),
             "Texcoord3" => Ok(Attribute::Texcoord3),
+            "Instance0" => Ok(Attribute::Instance0),
+            "Instance1" => Ok(Attribute::Instance1),
+            "Instance2" => Ok(Attribute::Instance2),
+            "Instance3" => Ok(Attribute::Instance3),
             _ => Err(Error::AttributeUndefined(s.into())),
         }
     }
@@ -110,6 +144,7 @@ impl FromStr for Attribute {
 pub struct AttributeLayout {
     len: u8,
     elements: [(Attribute, u8, bool); MAX_VERTEX_ATTRIBUTES],
+    divisors: [u32; MAX_VERTEX_ATTRIBUTES],
 }
 
 impl Default for AttributeLayout {
@@ -117,6 +152,7 @@ impl Default for AttributeLayout {
         AttributeLayout {
             len: 0,
             elements: [(Attribute::Position, 0, false); MAX_VERTEX_ATTRIBUTES],
+            divisors: [0; MAX_VERTEX_ATTRIBUTES],
         }
     }
 }
@@ -126,6 +162,11 @@ impl AttributeLayout {
         AttributeLayoutBuilder::new()
     }
 
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn iter(&self) -> AttributeLayoutIter {
         AttributeLayoutIter {
             pos: 0,
@@ -133,8 +174,25 @@ impl AttributeLayout {
         }
     }
 
+    /// Returns the number of instances that will pass between updates of the attribute,
+    /// zero means the attribute advances per vertex.
+    pub fn divisor(&self, attribute: Attribute) -> u32 {
+        for i in 0..self.len as usize {
+            if self.elements[i].0 == attribute {
+                return self.divisors[i];
+            }
+        }
+
+        0
+    }
+
     pub fn is_match(&self, layout: &VertexLayout) -> bool {
         for (name, size, required) in self.iter() {
+            // Per-instance attributes are sourced from instance buffers.
+            if self.divisor(name) > 0 {
+                continue;
+            }
+
             if required {
                 if let Some(element) = layout.element(name) {
                     if element.size == size {
@@ -179,27 +237,39 @@ impl AttributeLayoutBuilder {
 
     #[inline]
     pub fn with(self, attribute: Attribute, size: u8) -> Self {
-        self.append(attribute, size, true)
+        self.append(attribute, size, true, 0)
     }
 
+    /// Declares an attribute that could be missing in the vertex buffer, whose value
+    /// is `Attribute::default_value` then.
     #[inline]
     pub fn with_optional(self, attribute: Attribute, size: u8) -> Self {
-        self.append(attribute, size, false)
+        self.append(attribute, size, false, 0)
+    }
+
+    /// Declares a per-instance attribute, which advances once every `divisor` instances
+    /// and is sourced from the instance buffer of draw call.
+    #[inline]
+    pub fn with_instanced(self, attribute: Attribute, size: u8, divisor: u32) -> Self {
+        assert!(divisor > 0);
+        self.append(attribute, size, true, divisor)
     }
 
-    fn append(mut self, attribute: Attribute, size: u8, required: bool) -> Self {
+    fn append(mut self, attribute: Attribute, size: u8, required: bool, divisor: u32) -> Self {
         assert!(size > 0 && size <= 4);
 
         for i in 0..self.0.len {
             let i = i as usize;
             if self.0.elements[i].0 == attribute {
                 self.0.elements[i] = (attribute, size, required);
+                self.0.divisors[i] = divisor;
                 return self;
             }
         }
 
         assert!((self.0.len as usize) < MAX_VERTEX_ATTRIBUTES);
         self.0.elements[self.0.len as usize] = (attribute, size, required);
+        self.0.divisors[self.0.len as usize] = divisor;
         self.0.len += 1;
         self
     }
@@ -211,7 +281,7 @@ impl AttributeLayoutBuilder {
 }
 
 /// Specify whether front- or back-facing polygons can be culled.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CullFace {
     Nothing,
     Front,
@@ -219,14 +289,14 @@ pub enum CullFace {
 }
 
 /// Define front- and back-facing polygons.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum FrontFaceOrder {
     Clockwise,
     CounterClockwise,
 }
 
 /// A pixel-wise comparison function.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Comparison {
     Never,
     Less,
@@ -240,7 +310,7 @@ pub enum Comparison {
 
 /// Specifies how incoming RGBA values (source) and the RGBA in framebuffer (destination)
 /// are combined.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Equation {
     /// Adds source and destination. Source and destination are multiplied
     /// by blending parameters before addition.
@@ -251,10 +321,14 @@ pub enum Equation {
     /// Subtracts source from destination. Source and destination are
     /// multiplied by blending parameters before subtraction.
     ReverseSubtract,
+    /// The minimum of source and destination, the blending parameters are ignored.
+    Min,
+    /// The maximum of source and destination, the blending parameters are ignored.
+    Max,
 }
 
 /// Blend values.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum BlendValue {
     SourceColor,
     SourceAlpha,
@@ -263,7 +337,7 @@ pub enum BlendValue {
 }
 
 /// Blend factors.
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum BlendFactor {
     Zero,
     One,
@@ -271,16 +345,111 @@ pub enum BlendFactor {
     OneMinusValue(BlendValue),
 }
 
+/// The actions on the stored stencil value.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    /// Sets the value to the reference value of `StencilState`.
+    Replace,
+    /// Increments the value, which is clamped to the maximum value.
+    Increment,
+    /// Increments the value, which wraps to zero when exceeding the maximum value.
+    IncrementWrap,
+    /// Decrements the value, which is clamped to zero.
+    Decrement,
+    /// Decrements the value, which wraps to the maximum value when going below zero.
+    DecrementWrap,
+    /// Inverts the bits of value.
+    Invert,
+}
+
+/// The stencil test and actions of front- or back-facing polygons.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct StencilFace {
+    /// The comparison between the reference value and the stored value.
+    pub comparison: Comparison,
+    /// The action when the stencil test fails.
+    pub fail: StencilOp,
+    /// The action when the stencil test passes, but the depth test fails.
+    pub depth_fail: StencilOp,
+    /// The action when both the stencil test and the depth test pass.
+    pub pass: StencilOp,
+}
+
+impl Default for StencilFace {
+    fn default() -> Self {
+        StencilFace {
+            comparison: Comparison::Always,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+/// The stencil test of shader, which requires a surface with `Depth24Stencil8` attachment
+/// or the default framebuffer with stencil buffer.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct StencilState {
+    pub front: StencilFace,
+    pub back: StencilFace,
+    /// The reference value of comparisons and `StencilOp::Replace`.
+    pub reference: u8,
+    /// The mask that is ANDed with both the reference value and the stored value before
+    /// comparisons.
+    pub read_mask: u8,
+    /// The mask of bits that could be written into stencil buffer.
+    pub write_mask: u8,
+}
+
+impl Default for StencilState {
+    fn default() -> Self {
+        StencilState {
+            front: StencilFace::default(),
+            back: StencilFace::default(),
+            reference: 0,
+            read_mask: 0xff,
+            write_mask: 0xff,
+        }
+    }
+}
+
+impl StencilState {
+    /// Creates a stencil state with the same test and actions of both faces.
+    pub fn new(face: StencilFace, reference: u8) -> Self {
+        StencilState {
+            front: face,
+            back: face,
+            reference,
+            ..Default::default()
+        }
+    }
+}
+
 /// A struct that encapsulate all the necessary render states.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
 pub struct RenderState {
     pub cull_face: CullFace,
     pub front_face_order: FrontFaceOrder,
     pub depth_test: Comparison,
     pub depth_write: bool,
     pub depth_write_offset: Option<(f32, f32)>,
+    /// The blending of color channels, which is applied to alpha channel too unless
+    /// `alpha_blend` is set. The blending is disabled if none.
     pub color_blend: Option<(Equation, BlendFactor, BlendFactor)>,
+    /// The separate blending of alpha channel, e.g. the premultiplied alpha that
+    /// accumulates coverage with `(Add, One, OneMinusValue(SourceAlpha))`.
+    #[serde(default)]
+    pub alpha_blend: Option<(Equation, BlendFactor, BlendFactor)>,
+    /// Enables the blending of color attachments of surface individually. Attachments
+    /// could only be disabled on devices with GL 3.0 or GLES 3.2.
+    #[serde(default = "blend_all_attachments")]
+    pub blend_attachments: [bool; MAX_FRAMEBUFFER_ATTACHMENTS],
     pub color_write: (bool, bool, bool, bool),
+    /// The stencil test is disabled if none.
+    #[serde(default)]
+    pub stencil: Option<StencilState>,
 }
 
 impl Default for RenderState {
@@ -292,16 +461,26 @@ impl Default for RenderState {
             depth_write: false,             // no depth write,
             depth_write_offset: None,
             color_blend: None,
+            alpha_blend: None,
+            blend_attachments: blend_all_attachments(),
             color_write: (true, true, true, true),
+            stencil: None,
         }
     }
 }
 
+fn blend_all_attachments() -> [bool; MAX_FRAMEBUFFER_ATTACHMENTS] {
+    [true; MAX_FRAMEBUFFER_ATTACHMENTS]
+}
+
 /// Uniform variable type.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum UniformVariableType {
     Texture,
+    TextureCube,
+    Texture3D,
     RenderTexture,
+    UniformBuffer,
     I32,
     F32,
     Vector2f,
@@ -310,14 +489,27 @@ pub enum UniformVariableType {
     Matrix2f,
     Matrix3f,
     Matrix4f,
+    /// An array of `mat4`, which is declared with its name without subscript.
+    Matrix4fArray,
 }
 
 /// Uniform variable for video program object. Each matrix based `UniformVariable`
 /// is assumed to be supplied in row major order with a optional transpose.
+///
+/// The textures could be sampled with an optional sampler object, which overrides
+/// the wrap and filter modes of textures.
+///
+/// The matrices of `Matrix4fArray` are stored in the command buffer that they are
+/// allocated from with `DrawCommandBuffer::matrix_array`, so it could only be used
+/// by the draws of the same buffer before it's submitted. The draws with the arrays
+/// kept from other buffers or previous frames fail to submit.
 #[derive(Debug, Copy, Clone)]
 pub enum UniformVariable {
-    Texture(TextureHandle),
-    RenderTexture(RenderTextureHandle),
+    Texture(TextureHandle, Option<SamplerHandle>),
+    TextureCube(TextureCubeHandle, Option<SamplerHandle>),
+    Texture3D(Texture3DHandle, Option<SamplerHandle>),
+    RenderTexture(RenderTextureHandle, Option<SamplerHandle>),
+    UniformBuffer(UniformBufferHandle),
     I32(i32),
     F32(f32),
     Vector2f([f32; 2]),
@@ -326,13 +518,17 @@ pub enum UniformVariable {
     Matrix2f([[f32; 2]; 2], bool),
     Matrix3f([[f32; 3]; 3], bool),
     Matrix4f([[f32; 4]; 4], bool),
+    Matrix4fArray(MatrixArray),
 }
 
 impl UniformVariable {
     pub fn variable_type(&self) -> UniformVariableType {
         match *self {
-            UniformVariable::RenderTexture(_) => UniformVariableType::RenderTexture,
-            UniformVariable::Texture(_) => UniformVariableType::Texture,
+            UniformVariable::RenderTexture(_, _) => UniformVariableType::RenderTexture,
+            UniformVariable::Texture(_, _) => UniformVariableType::Texture,
+            UniformVariable::TextureCube(_, _) => UniformVariableType::TextureCube,
+            UniformVariable::Texture3D(_, _) => UniformVariableType::Texture3D,
+            UniformVariable::UniformBuffer(_) => UniformVariableType::UniformBuffer,
             UniformVariable::I32(_) => UniformVariableType::I32,
             UniformVariable::F32(_) => UniformVariableType::F32,
             UniformVariable::Vector2f(_) => UniformVariableType::Vector2f,
@@ -341,19 +537,62 @@ impl UniformVariable {
             UniformVariable::Matrix2f(_, _) => UniformVariableType::Matrix2f,
             UniformVariable::Matrix3f(_, _) => UniformVariableType::Matrix3f,
             UniformVariable::Matrix4f(_, _) => UniformVariableType::Matrix4f,
+            UniformVariable::Matrix4fArray(_) => UniformVariableType::Matrix4fArray,
         }
     }
 }
 
 impl Into<UniformVariable> for TextureHandle {
     fn into(self) -> UniformVariable {
-        UniformVariable::Texture(self)
+        UniformVariable::Texture(self, None)
+    }
+}
+
+impl Into<UniformVariable> for (TextureHandle, SamplerHandle) {
+    fn into(self) -> UniformVariable {
+        UniformVariable::Texture(self.0, Some(self.1))
+    }
+}
+
+impl Into<UniformVariable> for TextureCubeHandle {
+    fn into(self) -> UniformVariable {
+        UniformVariable::TextureCube(self, None)
+    }
+}
+
+impl Into<UniformVariable> for (TextureCubeHandle, SamplerHandle) {
+    fn into(self) -> UniformVariable {
+        UniformVariable::TextureCube(self.0, Some(self.1))
+    }
+}
+
+impl Into<UniformVariable> for Texture3DHandle {
+    fn into(self) -> UniformVariable {
+        UniformVariable::Texture3D(self, None)
+    }
+}
+
+impl Into<UniformVariable> for (Texture3DHandle, SamplerHandle) {
+    fn into(self) -> UniformVariable {
+        UniformVariable::Texture3D(self.0, Some(self.1))
     }
 }
 
 impl Into<UniformVariable> for RenderTextureHandle {
     fn into(self) -> UniformVariable {
-        UniformVariable::RenderTexture(self)
+        UniformVariable::RenderTexture(self, None)
+    }
+}
+
+impl Into<UniformVariable> for (RenderTextureHandle, SamplerHandle) {
+    fn into(self) -> UniformVariable {
+        UniformVariable::RenderTexture(self.0, Some(self.1))
+    }
+}
+
+impl Into<UniformVariable> for UniformBufferHandle {
+    fn into(self) -> UniformVariable {
+        UniformVariable::UniformBuffer(self)
     }
 }
 
@@ -503,3 +742,79 @@ impl UniformVariableLayoutBuilder {
         self.0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::video::assets::mesh::VertexFormat;
+
+    #[test]
+    fn instanced_attributes() {
+        let attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with_instanced(Attribute::Instance0, 4, 1)
+            .finish();
+
+        assert_eq!(attributes.divisor(Attribute::Position), 0);
+        assert_eq!(attributes.divisor(Attribute::Instance0), 1);
+        assert_eq!(attributes.divisor(Attribute::Normal), 0);
+
+        let layout = VertexLayout::build()
+            .with(Attribute::Position, VertexFormat::Float, 3, false)
+            .finish();
+
+        assert!(attributes.is_match(&layout));
+    }
+
+    /// Deserializes the render state which is serialized without `field`, like the ones
+    /// saved before the field is introduced.
+    fn without(state: RenderState, field: &str) -> RenderState {
+        let mut value = serde_json::to_value(state).unwrap();
+        value.as_object_mut().unwrap().remove(field).unwrap();
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn stencil() {
+        let mut face = StencilFace::default();
+        face.comparison = Comparison::Equal;
+        face.pass = StencilOp::Replace;
+
+        let stencil = StencilState::new(face, 1);
+        assert_eq!(stencil.front, face);
+        assert_eq!(stencil.back, face);
+        assert_eq!(stencil.reference, 1);
+        assert_eq!(stencil.read_mask, 0xff);
+        assert_eq!(stencil.write_mask, 0xff);
+
+        let mut state = RenderState::default();
+        assert_eq!(state.stencil, None);
+
+        state.stencil = Some(stencil);
+        assert_eq!(without(state, "stencil").stencil, None);
+    }
+
+    #[test]
+    fn blend() {
+        let mut state = RenderState::default();
+        assert_eq!(state.alpha_blend, None);
+        assert!(state.blend_attachments.iter().all(|&v| v));
+
+        let premultiplied = (
+            Equation::Add,
+            BlendFactor::One,
+            BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+        );
+
+        state.color_blend = Some((Equation::Max, BlendFactor::One, BlendFactor::One));
+        state.alpha_blend = Some(premultiplied);
+        state.blend_attachments[1] = false;
+
+        assert_eq!(without(state, "alpha_blend").alpha_blend, None);
+        assert_eq!(without(state, "alpha_blend").blend_attachments[1], false);
+
+        let state = without(state, "blend_attachments");
+        assert_eq!(state.alpha_blend, Some(premultiplied));
+        assert!(state.blend_attachments.iter().all(|&v| v));
+    }
+}
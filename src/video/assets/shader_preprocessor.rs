@@ -0,0 +1,254 @@
+//! The preprocessing stage of shader sources, which resolves `#include` directives and
+//! prepends `#define`s before the sources are passed to `video::create_shader`.
+//!
+//! Included files are inserted at most once into the result, just like being guarded
+//! with `#pragma once`, so circular includes are harmless.
+//!
+//! With the `hot-reload` feature enabled, the files loaded with `load_include` are loaded
+//! again by `poll` once they are modified on disk.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::errors::*;
+use crate::res::request::Request;
+
+/// A sorted set of `#define`s, which identifies a permutation of shader sources.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShaderDefines(BTreeMap<String, String>);
+
+impl ShaderDefines {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Defines a macro with value.
+    #[inline]
+    pub fn with<T1: Into<String>, T2: ToString>(mut self, name: T1, value: T2) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    /// Defines a macro without value, which could be tested with `#ifdef`.
+    #[inline]
+    pub fn with_flag<T: Into<String>>(self, name: T) -> Self {
+        self.with(name, "")
+    }
+
+    #[inline]
+    pub fn insert<T1: Into<String>, T2: ToString>(&mut self, name: T1, value: T2) {
+        self.0.insert(name.into(), value.to_string());
+    }
+
+    #[inline]
+    pub fn remove<T: AsRef<str>>(&mut self, name: T) {
+        self.0.remove(name.as_ref());
+    }
+
+    #[inline]
+    pub fn contains<T: AsRef<str>>(&self, name: T) -> bool {
+        self.0.contains_key(name.as_ref())
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// The preprocessor of shader sources.
+///
+/// Files which could be included are either added directly with `add_include`, or
+/// loaded through the resource system with `load_include`. The names of included files
+/// are used as urls when loading nested includes.
+#[derive(Default)]
+pub struct ShaderPreprocessor {
+    includes: HashMap<String, String>,
+    requests: Vec<(String, Request)>,
+    revision: usize,
+    #[cfg(feature = "hot-reload")]
+    modified: usize,
+}
+
+impl ShaderPreprocessor {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds the source of file that could be included with `name`.
+    pub fn add_include<T1: Into<String>, T2: Into<String>>(&mut self, name: T1, source: T2) {
+        self.includes.insert(name.into(), source.into());
+        self.revision += 1;
+    }
+
+    /// Returns the revision of included files, which is increased whenever a file is
+    /// added or replaced.
+    #[inline]
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    /// Checks if the file with `name` is ready to be included.
+    #[inline]
+    pub fn has_include<T: AsRef<str>>(&self, name: T) -> bool {
+        self.includes.contains_key(name.as_ref())
+    }
+
+    /// Loads the file at `url` asynchronously, which could be included with the same url
+    /// once `poll` returns true.
+    pub fn load_include<T: AsRef<str>>(&mut self, url: T) -> Result<()> {
+        let url = url.as_ref();
+        if self.includes.contains_key(url) || self.requests.iter().any(|v| v.0 == url) {
+            return Ok(());
+        }
+
+        let request = crate::res::load_from(url)?;
+        self.requests.push((url.to_owned(), request));
+        Ok(())
+    }
+
+    /// Polls the pending loads of included files, and returns true if all of them are
+    /// ready. The files included by loaded files are loaded too.
+    pub fn poll(&mut self) -> Result<bool> {
+        #[cfg(feature = "hot-reload")]
+        self.reload()?;
+
+        let mut i = 0;
+        while i < self.requests.len() {
+            if !self.requests[i].1.poll() {
+                i += 1;
+                continue;
+            }
+
+            let (url, request) = self.requests.swap_remove(i);
+            let bytes = match request.response() {
+                Some(Ok(bytes)) => bytes.clone(),
+                Some(Err(err)) => bail!("Failed to load include file {}. {}", url, err),
+                None => unreachable!(),
+            };
+
+            let source = String::from_utf8(bytes.into_vec())?;
+            for line in source.lines() {
+                if let Some(name) = include(line) {
+                    self.load_include(name)?;
+                }
+            }
+
+            self.includes.insert(url, source);
+            self.revision += 1;
+        }
+
+        Ok(self.requests.is_empty())
+    }
+
+    #[cfg(feature = "hot-reload")]
+    fn reload(&mut self) -> Result<()> {
+        if !crate::res::valid() {
+            return Ok(());
+        }
+
+        for uuid in crate::res::modified(&mut self.modified) {
+            let url = self
+                .includes
+                .keys()
+                .find(|&v| crate::res::find(v) == Some(uuid))
+                .cloned();
+
+            if let Some(url) = url {
+                if !self.requests.iter().any(|v| v.0 == url) {
+                    let request = crate::res::load_from(&url)?;
+                    self.requests.push((url, request));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the `#include` directives in `source`, and prepends the `defines` after
+    /// the `#version` directive if there is one.
+    pub fn process(&self, source: &str, defines: &ShaderDefines) -> Result<String> {
+        let mut result = String::with_capacity(source.len());
+        let mut body = source;
+
+        // `#version` must be the first directive in GLSL.
+        if let Some(line) = source.lines().find(|v| !v.trim().is_empty()) {
+            if line.trim_start().starts_with("#version") {
+                let end = line.as_ptr() as usize - source.as_ptr() as usize + line.len();
+                result.push_str(&source[..end]);
+                result.push('\n');
+                body = source[end..].splitn(2, '\n').nth(1).unwrap_or("");
+            }
+        }
+
+        for (k, v) in defines.iter() {
+            result.push_str(&format!("#define {} {}\n", k, v));
+        }
+
+        let mut included = HashSet::new();
+        self.expand(body, &mut included, &mut result)?;
+        Ok(result)
+    }
+
+    fn expand<'a>(
+        &'a self,
+        source: &'a str,
+        included: &mut HashSet<&'a str>,
+        result: &mut String,
+    ) -> Result<()> {
+        for line in source.lines() {
+            match include(line) {
+                Some(name) => {
+                    let (name, source) = self
+                        .includes
+                        .get_key_value(name)
+                        .ok_or_else(|| format_err!("Include file {} is not found.", name))?;
+
+                    if included.insert(name.as_str()) {
+                        self.expand(source, included, result)?;
+                    }
+                }
+                None => {
+                    result.push_str(line);
+                    result.push('\n');
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the name of included file from `#include "name"` or `#include <name>`.
+fn include(line: &str) -> Option<&str> {
+    let line = line.trim();
+    if !line.starts_with('#') {
+        return None;
+    }
+
+    let line = line[1..].trim_start();
+    if !line.starts_with("include") {
+        return None;
+    }
+
+    let name = line["include".len()..].trim();
+    if name.len() >= 2
+        && ((name.starts_with('"') && name.ends_with('"'))
+            || (name.starts_with('<') && name.ends_with('>')))
+    {
+        Some(&name[1..name.len() - 1])
+    } else {
+        None
+    }
+}
@@ -0,0 +1,103 @@
+//! The cache of shader permutations.
+
+use std::collections::HashMap;
+
+use crate::errors::*;
+
+use super::shader::{ShaderHandle, ShaderParams};
+use super::shader_preprocessor::{ShaderDefines, ShaderPreprocessor};
+
+/// A cache of shader objects, which are created from the same sources with different
+/// `ShaderDefines`. So a single uber-shader could produce lit, unlit or skinned
+/// permutations on demand.
+///
+/// Since the attributes and uniforms compiled out by `#ifdef`s must not be declared,
+/// the `ShaderParams` of each permutation are produced by a closure.
+///
+/// Once the included files of preprocessor are changed, the created permutations are
+/// updated in place, so their handles keep valid.
+pub struct ShaderVariants {
+    vs: String,
+    fs: String,
+    params: Box<dyn Fn(&ShaderDefines) -> ShaderParams + Send + Sync>,
+    variants: HashMap<ShaderDefines, ShaderHandle>,
+    revision: usize,
+}
+
+impl ShaderVariants {
+    pub fn new<T>(vs: String, fs: String, params: T) -> Self
+    where
+        T: Fn(&ShaderDefines) -> ShaderParams + Send + Sync + 'static,
+    {
+        ShaderVariants {
+            vs,
+            fs,
+            params: Box::new(params),
+            variants: HashMap::new(),
+            revision: 0,
+        }
+    }
+
+    /// Gets the shader object of permutation, which will be created if not exists.
+    pub fn variant(
+        &mut self,
+        preprocessor: &ShaderPreprocessor,
+        defines: &ShaderDefines,
+    ) -> Result<ShaderHandle> {
+        if self.revision != preprocessor.revision() {
+            self.reload(preprocessor)?;
+        }
+
+        if let Some(&v) = self.variants.get(defines) {
+            return Ok(v);
+        }
+
+        let vs = preprocessor.process(&self.vs, defines)?;
+        let fs = preprocessor.process(&self.fs, defines)?;
+        let shader = crate::video::create_shader((self.params)(defines), vs, fs)?;
+        self.variants.insert(defines.clone(), shader);
+        Ok(shader)
+    }
+
+    /// Updates the sources of all the created permutations with preprocessor.
+    pub fn reload(&mut self, preprocessor: &ShaderPreprocessor) -> Result<()> {
+        for (defines, &v) in &self.variants {
+            let vs = preprocessor.process(&self.vs, defines)?;
+            let fs = preprocessor.process(&self.fs, defines)?;
+            crate::video::update_shader(v, vs, fs)?;
+        }
+
+        self.revision = preprocessor.revision();
+        Ok(())
+    }
+
+    /// Gets the shader object of permutation if it has been created.
+    #[inline]
+    pub fn get(&self, defines: &ShaderDefines) -> Option<ShaderHandle> {
+        self.variants.get(defines).cloned()
+    }
+
+    /// Returns the number of created permutations.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// Deletes all the created permutations.
+    pub fn clear(&mut self) {
+        for (_, v) in self.variants.drain() {
+            crate::video::delete_shader(v);
+        }
+    }
+}
+
+impl Drop for ShaderVariants {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
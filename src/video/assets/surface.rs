@@ -1,11 +1,12 @@
 //! Named bucket of draw calls with the wrapping of rendering operations to a render
 //! target, clearing, MSAA resolving and so on.
 
-use crate::math::prelude::{Color, Vector2};
+use crate::math::prelude::{Aabb2, Color, Vector2};
 
 use crate::video::assets::texture::RenderTextureHandle;
 use crate::video::errors::{Error, Result};
 use crate::video::MAX_FRAMEBUFFER_ATTACHMENTS;
+use crate::window::WindowHandle;
 
 /// The setup data of `Surface` which wraps common rendering operations to a render-target.
 /// Likes clearing, MSAA resolves, etc.. The `RenderTarget` is the window framebuffer as
@@ -18,6 +19,10 @@ pub struct SurfaceParams {
     pub(crate) clear_color: Option<Color<f32>>,
     pub(crate) clear_depth: Option<f32>,
     pub(crate) clear_stencil: Option<i32>,
+    pub(crate) window: Option<WindowHandle>,
+    pub(crate) viewport: Option<Aabb2<f32>>,
+    pub(crate) srgb: bool,
+    pub(crate) wireframe: bool,
 }
 
 impl Default for SurfaceParams {
@@ -28,6 +33,10 @@ impl Default for SurfaceParams {
             clear_color: Some(Color::black()),
             clear_depth: Some(1.0),
             clear_stencil: None,
+            window: None,
+            viewport: None,
+            srgb: false,
+            wireframe: false,
         }
     }
 }
@@ -52,6 +61,13 @@ impl SurfaceParams {
             return Err(Error::SurfaceInvalid("Too many color attachments.".into()));
         }
 
+        let depth_stencil = depth_stencil.into();
+        if self.window.is_some() && (!colors.is_empty() || depth_stencil.is_some()) {
+            return Err(Error::SurfaceInvalid(
+                "Surface targeting a window can't have attachments.".into(),
+            ));
+        }
+
         for (i, v) in self.colors.iter_mut().enumerate() {
             if i < colors.len() {
                 *v = Some(colors[i]);
@@ -60,10 +76,85 @@ impl SurfaceParams {
             }
         }
 
-        self.depth_stencil = depth_stencil.into();
+        self.depth_stencil = depth_stencil;
         Ok(())
     }
 
+    /// Sets the secondary window that this surface draws into, instead of the main window.
+    /// The window is created with `window::create_window`.
+    pub fn set_window<T>(&mut self, window: T) -> Result<()>
+    where
+        T: Into<Option<WindowHandle>>,
+    {
+        let window = window.into();
+        if window.is_some() && (self.colors[0].is_some() || self.depth_stencil.is_some()) {
+            return Err(Error::SurfaceInvalid(
+                "Surface with attachments can't target a window.".into(),
+            ));
+        }
+
+        self.window = window;
+        Ok(())
+    }
+
+    /// Sets the viewport rectangle of this surface in normalized coordinates, which ranges
+    /// from (0, 0) at the lower-left corner to (1, 1) at the upper-right corner of render
+    /// target. The draws and clears are restricted to the rectangle, e.g. the halves of
+    /// window in split-screen games.
+    #[inline]
+    pub fn set_viewport<T>(&mut self, viewport: T)
+    where
+        T: Into<Option<Aabb2<f32>>>,
+    {
+        self.viewport = viewport.into();
+    }
+
+    /// Gets the viewport and scissor box in pixels, with the `dimensions` of render target.
+    pub(crate) fn viewport(&self, dimensions: Vector2<u32>) -> (SurfaceViewport, SurfaceScissor) {
+        match self.viewport {
+            Some(rect) => {
+                let x = (rect.min.x * dimensions.x as f32).round() as i32;
+                let y = (rect.min.y * dimensions.y as f32).round() as i32;
+                let w = (rect.max.x * dimensions.x as f32).round() as i32 - x;
+                let h = (rect.max.y * dimensions.y as f32).round() as i32 - y;
+
+                let position = Vector2::new(x, y);
+                let size = Vector2::new(w.max(0) as u32, h.max(0) as u32);
+                let scissor = SurfaceScissor::Enable { position, size };
+                (SurfaceViewport { position, size }, scissor)
+            }
+            None => {
+                let position = Vector2::new(0, 0);
+                let size = dimensions;
+                (SurfaceViewport { position, size }, SurfaceScissor::Disable)
+            }
+        }
+    }
+
+    /// Encodes the linear colors into sRGB space by hardware when drawing into the window
+    /// framebuffer, which takes effect only if `video::capabilities().srgb_backbuffer` is
+    /// true. It's disabled by default, since the colors are usually written in sRGB space
+    /// by shaders. The sRGB color attachments are always encoded.
+    #[inline]
+    pub fn set_srgb(&mut self, srgb: bool) {
+        self.srgb = srgb;
+    }
+
+    /// Rasterizes the polygons drawn into this surface as lines, which is useful to
+    /// inspect the triangles of meshes. It takes effect only if
+    /// `video::capabilities().wireframe` is true, see `video::set_wireframe` to override
+    /// all the surfaces.
+    #[inline]
+    pub fn set_wireframe(&mut self, wireframe: bool) {
+        self.wireframe = wireframe;
+    }
+
+    /// Checks if this surface draws into a window framebuffer.
+    #[inline]
+    pub(crate) fn is_window_framebuffer(&self) -> bool {
+        self.colors[0].is_none() && self.depth_stencil.is_none()
+    }
+
     /// Sets the clear flags for this surface.A
     #[inline]
     pub fn set_clear<C, D, S>(&mut self, color: C, depth: D, stentil: S)
@@ -0,0 +1,271 @@
+//! Generation of the tangent frames of meshes, which are required by normal mapping.
+//!
+//! Similar to MikkTSpace, the tangents of triangles are calculated from the derivatives of
+//! their texture coordinates, accumulated into the shared vertices weighted by the areas
+//! of triangles in texture space, and then orthogonalized against the vertex normals with Gram-Schmidt.
+//!
+//! The tangents are stored as `Attribute::Tangent` with 4 components, the `w` component
+//! keeps the handedness of tangent frame, so the bitangent could be reconstructed in shaders
+//! with `cross(normal, tangent.xyz) * tangent.w`. Since the origin of textures is at the
+//! top-left corner, the bitangents point to the decreasing v-axis, which is the up direction
+//! of normal maps.
+
+use crate::errors::*;
+use crate::math::prelude::{InnerSpace, Vector2, Vector3};
+
+use super::mesh::*;
+use super::shader::Attribute;
+
+/// Checks if the tangents of mesh could be generated, which requires triangles with float
+/// positions, normals and texture coordinates.
+pub fn supported(params: &MeshParams) -> bool {
+    let float = |attribute, size| {
+        params
+            .layout
+            .element(attribute)
+            .map(|v| v.format == VertexFormat::Float && v.size >= size)
+            .unwrap_or(false)
+    };
+
+    params.primitive == MeshPrimitive::Triangles
+        && float(Attribute::Position, 3)
+        && float(Attribute::Normal, 3)
+        && float(Attribute::Texcoord0, 2)
+}
+
+/// Generates the tangents of mesh, and returns the mesh with `Attribute::Tangent` appended
+/// into its vertex layout. The existing tangents will be replaced.
+pub fn generate(params: &MeshParams, data: &MeshData) -> Result<(MeshParams, MeshData)> {
+    if !supported(params) {
+        bail!("Tangents requires triangles with float positions, normals and texcoords.");
+    }
+
+    if data.vptr.len() < params.vertex_buffer_len() || data.iptr.len() < params.index_buffer_len() {
+        bail!("The data of mesh does not match its parameters.");
+    }
+
+    let layout = params.layout;
+    let stride = layout.stride() as usize;
+    let vertex = |i: usize| &data.vptr[i * stride..(i + 1) * stride];
+    let offset = |attribute| layout.offset(attribute).unwrap() as usize;
+    let (position, normal, texcoord) = (
+        offset(Attribute::Position),
+        offset(Attribute::Normal),
+        offset(Attribute::Texcoord0),
+    );
+
+    let iptr = &data.iptr[..params.index_buffer_len()];
+    let idxes: Vec<usize> = match params.index_format {
+        IndexFormat::U16 => iptr
+            .chunks(2)
+            .map(|v| u16::from_ne_bytes([v[0], v[1]]) as usize)
+            .collect(),
+        IndexFormat::U32 => iptr
+            .chunks(4)
+            .map(|v| u32::from_ne_bytes([v[0], v[1], v[2], v[3]]) as usize)
+            .collect(),
+    };
+
+    if idxes.iter().any(|&v| v >= params.num_verts) {
+        bail!("The indices of mesh are out of bounds.");
+    }
+
+    let mut tangents = vec![Vector3::new(0.0, 0.0, 0.0); params.num_verts];
+    let mut bitangents = vec![Vector3::new(0.0, 0.0, 0.0); params.num_verts];
+
+    for v in idxes.chunks(3).filter(|v| v.len() == 3) {
+        let p = |i: usize| read3(vertex(v[i]), position);
+        let t = |i: usize| {
+            let uv = read2(vertex(v[i]), texcoord);
+            Vector2::new(uv.x, -uv.y)
+        };
+
+        let (e1, e2) = (p(1) - p(0), p(2) - p(0));
+        let (d1, d2) = (t(1) - t(0), t(2) - t(0));
+
+        // The determinant is compared relatively to the lengths of edges, so the triangles
+        // in small UV islands are not taken as degenerated.
+        let det = d1.x * d2.y - d2.x * d1.y;
+        if det.abs() <= ::std::f32::EPSILON * d1.magnitude() * d2.magnitude() {
+            continue;
+        }
+
+        // Multiplies by the sign instead of dividing by the determinant, so the magnitudes
+        // are weighted by the areas of triangles in texture space.
+        let sign = det.signum();
+        let tangent = (e1 * d2.y - e2 * d1.y) * sign;
+        let bitangent = (e2 * d1.x - e1 * d2.x) * sign;
+
+        for &i in v {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+
+    let new_layout = append(&layout);
+    let new_stride = new_layout.stride() as usize;
+    let tangent_offset = new_layout.offset(Attribute::Tangent).unwrap() as usize;
+
+    let mut vptr = vec![0; params.num_verts * new_stride];
+    for i in 0..params.num_verts {
+        let src = vertex(i);
+        let dst = &mut vptr[i * new_stride..(i + 1) * new_stride];
+
+        for element in layout.elements() {
+            if element.name == Attribute::Tangent {
+                continue;
+            }
+
+            let len = element.size as usize * element.format.size() as usize;
+            let from = layout.offset(element.name).unwrap() as usize;
+            let to = new_layout.offset(element.name).unwrap() as usize;
+            dst[to..to + len].copy_from_slice(&src[from..from + len]);
+        }
+
+        let n = read3(src, normal);
+        let n = if n.magnitude2() > 0.0 {
+            n.normalize()
+        } else {
+            Vector3::unit_y()
+        };
+
+        let mut t = tangents[i] - n * n.dot(tangents[i]);
+        if t.magnitude2() <= ::std::f32::EPSILON * tangents[i].magnitude2() {
+            // Picks any direction that perpendicular to normal for degenerated cases.
+            let axis = if n.x.abs() < 0.9 {
+                Vector3::unit_x()
+            } else {
+                Vector3::unit_y()
+            };
+
+            t = axis - n * n.dot(axis);
+        }
+
+        let t = t.normalize();
+        let w = if n.cross(t).dot(bitangents[i]) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        for (k, &v) in [t.x, t.y, t.z, w].iter().enumerate() {
+            let bytes = v.to_bits().to_ne_bytes();
+            dst[tangent_offset + k * 4..tangent_offset + k * 4 + 4].copy_from_slice(&bytes);
+        }
+    }
+
+    let mut params = params.clone();
+    params.layout = new_layout;
+
+    let data = MeshData {
+        vptr: vptr.into_boxed_slice(),
+        iptr: data.iptr.clone(),
+    };
+
+    Ok((params, data))
+}
+
+fn append(layout: &VertexLayout) -> VertexLayout {
+    let mut builder = VertexLayout::build();
+    for v in layout.elements() {
+        if v.name != Attribute::Tangent {
+            builder = builder.with(v.name, v.format, v.size, v.normalized);
+        }
+    }
+
+    builder
+        .with(Attribute::Tangent, VertexFormat::Float, 4, false)
+        .finish()
+}
+
+fn read(bytes: &[u8], offset: usize) -> f32 {
+    let mut buf = [0; 4];
+    buf.copy_from_slice(&bytes[offset..offset + 4]);
+    f32::from_bits(u32::from_ne_bytes(buf))
+}
+
+fn read2(bytes: &[u8], offset: usize) -> Vector2<f32> {
+    Vector2::new(read(bytes, offset), read(bytes, offset + 4))
+}
+
+fn read3(bytes: &[u8], offset: usize) -> Vector3<f32> {
+    Vector3::new(
+        read(bytes, offset),
+        read(bytes, offset + 4),
+        read(bytes, offset + 8),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generate() {
+        let layout = VertexLayout::build()
+            .with(Attribute::Position, VertexFormat::Float, 3, false)
+            .with(Attribute::Normal, VertexFormat::Float, 3, false)
+            .with(Attribute::Texcoord0, VertexFormat::Float, 2, false)
+            .finish();
+
+        // A quad facing -z, whose u-axis is +x and v-axis is -y.
+        let quad: [[f32; 8]; 4] = [
+            [0.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0, 0.0, 0.0, -1.0, 1.0, 1.0],
+            [1.0, 1.0, 0.0, 0.0, 0.0, -1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0],
+        ];
+
+        let mut params = MeshParams::default();
+        params.layout = layout;
+        params.num_verts = 4;
+        params.num_idxes = 6;
+
+        let data = MeshData {
+            vptr: IndexFormat::encode(&quad).to_vec().into_boxed_slice(),
+            iptr: IndexFormat::encode(&[0u16, 2, 1, 0, 3, 2])
+                .to_vec()
+                .into_boxed_slice(),
+        };
+
+        assert!(super::supported(&params));
+        let quad_params = params.clone();
+        let (mut params, data) = super::generate(&params, &data).unwrap();
+        assert_eq!(params.layout.stride(), 48);
+        assert_eq!(params.layout.offset(Attribute::Tangent), Some(32));
+        assert_eq!(data.vptr.len(), params.vertex_buffer_len());
+
+        let verts: Vec<_> = (0..4 * 12).map(|i| read(&data.vptr, i * 4)).collect();
+        for v in verts.chunks(12) {
+            assert_eq!(&v[8..12], &[1.0, 0.0, 0.0, -1.0]);
+        }
+
+        // The bitangent points to the up of texture.
+        let (n, t) = (Vector3::new(0.0, 0.0, -1.0), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(n.cross(t) * verts[11], Vector3::new(0.0, 1.0, 0.0));
+
+        // The triangles in small UV islands keep their tangents.
+        let mut small = quad;
+        for v in &mut small {
+            v[6] *= 1e-4;
+            v[7] *= 1e-4;
+        }
+
+        let small = MeshData {
+            vptr: IndexFormat::encode(&small).to_vec().into_boxed_slice(),
+            iptr: data.iptr.clone(),
+        };
+
+        let (_, small) = super::generate(&quad_params, &small).unwrap();
+        for i in 0..4 {
+            let tangent: Vec<_> = (8..12)
+                .map(|k| read(&small.vptr, (i * 12 + k) * 4))
+                .collect();
+            assert_eq!(tangent, vec![1.0, 0.0, 0.0, -1.0]);
+        }
+
+        params.primitive = MeshPrimitive::Lines;
+        assert!(!super::supported(&params));
+        assert!(super::generate(&params, &data).is_err());
+    }
+}
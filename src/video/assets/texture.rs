@@ -1,6 +1,7 @@
-//! Immutable or dynamic 2D texture. A texture is a container of one or more images. It
-//! can be the source of a texture access from a Shader.
-use crate::math::prelude::Vector2;
+//! Immutable or dynamic 2D and 3D texture, and immutable cube map texture. A texture
+//! is a container of one or more images. It can be the source of a texture access from
+//! a Shader.
+use crate::math::prelude::{Vector2, Vector3};
 use crate::video::errors::{Error, Result};
 
 impl_handle!(TextureHandle);
@@ -33,6 +34,22 @@ impl Default for TextureParams {
 }
 
 impl TextureParams {
+    /// Gets the number of levels of a full mipmap chain, from the base level down to 1x1.
+    #[inline]
+    pub fn mip_levels(&self) -> usize {
+        let max = self.dimensions.x.max(self.dimensions.y).max(1);
+        (32 - max.leading_zeros()) as usize
+    }
+
+    /// Gets the dimensions of mipmap `level`.
+    pub fn mip_dimensions(&self, level: usize) -> Vector2<u32> {
+        let shift = level.min(31) as u32;
+        Vector2::new(
+            (self.dimensions.x >> shift).max(1),
+            (self.dimensions.y >> shift).max(1),
+        )
+    }
+
     pub fn validate(&self, data: Option<&TextureData>) -> Result<()> {
         if let Some(buf) = data {
             let len = self.format.size(self.dimensions);
@@ -47,23 +64,182 @@ impl TextureParams {
 
 /// Continuous texture data of different mipmap levels.
 ///
-/// Notes that mipmaps are stored in order from largest size to smallest size
+/// Notes that mipmaps are stored in order from largest size to smallest size. The leading
+/// levels could be left empty, which are not sampled until they are uploaded with
+/// `video::update_texture_mip`.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TextureData {
     pub bytes: Vec<Box<[u8]>>,
 }
 
+/// The parameters of streaming the mipmaps of immutable textures.
+///
+/// Only the mipmaps whose dimensions are not larger than `resident` are uploaded when
+/// creating texture, so the texture is usable at once. The larger ones are streamed in
+/// the following frames, from smaller to larger sizes.
+#[derive(Debug, Copy, Clone)]
+pub struct TextureStreamingParams {
+    /// The maximum width or height of mipmaps that are uploaded when creating texture.
+    pub resident: u32,
+    /// The maximum size in bytes of mipmaps that are streamed per frame, at least one
+    /// level is streamed every frame.
+    pub budget: usize,
+}
+
+impl Default for TextureStreamingParams {
+    fn default() -> Self {
+        TextureStreamingParams {
+            resident: 256,
+            budget: 4 * 1024 * 1024,
+        }
+    }
+}
+
+impl_handle!(TextureCubeHandle);
+
+/// The parameters of a cube map texture object. A cube map consists of six square
+/// 2D faces with the same format and dimensions.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct TextureCubeParams {
+    /// Sets the wrap parameter for texture.
+    pub wrap: TextureWrap,
+    /// Specify how the texture is used whenever the pixel being sampled.
+    pub filter: TextureFilter,
+    /// Sets the format of data.
+    pub format: TextureFormat,
+    /// Sets the dimensions of each face.
+    pub dimensions: Vector2<u32>,
+}
+
+impl Default for TextureCubeParams {
+    fn default() -> Self {
+        TextureCubeParams {
+            format: TextureFormat::RGBA8,
+            wrap: TextureWrap::Clamp,
+            filter: TextureFilter::Linear,
+            dimensions: Vector2::new(0, 0),
+        }
+    }
+}
+
+impl TextureCubeParams {
+    pub fn validate(&self, data: Option<&TextureCubeData>) -> Result<()> {
+        if self.dimensions.x != self.dimensions.y {
+            return Err(Error::TextureInvalid(
+                "The faces of cube texture must be square.".into(),
+            ));
+        }
+
+        if let Some(buf) = data {
+            let len = self.format.size(self.dimensions) as usize;
+            for face in &buf.faces {
+                if !face.is_empty() && face[0].len() > len {
+                    return Err(Error::OutOfBounds);
+                }
+            }
+
+            let levels = buf.faces[0].len();
+            if buf.faces.iter().any(|v| v.len() != levels) {
+                return Err(Error::TextureInvalid(
+                    "The faces of cube texture must have the same mipmap levels.".into(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Continuous texture data of the six faces of a cube map.
+///
+/// Notes that faces are stored in order of `+X`, `-X`, `+Y`, `-Y`, `+Z` and `-Z`, and
+/// mipmaps of each face are stored in order from largest size to smallest size.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TextureCubeData {
+    pub faces: [Vec<Box<[u8]>>; 6],
+}
+
+impl_handle!(Texture3DHandle);
+
+/// The parameters of a 3D texture object. A 3D texture is a stack of 2D images with
+/// the same format and dimensions, which is useful for volume data and look-up tables.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct Texture3DParams {
+    /// Hint abouts the intended update strategy of the data.
+    pub hint: TextureHint,
+    /// Sets the wrap parameter for texture.
+    pub wrap: TextureWrap,
+    /// Specify how the texture is used whenever the pixel being sampled.
+    pub filter: TextureFilter,
+    /// Sets the format of data.
+    pub format: TextureFormat,
+    /// Sets the width, height and depth of texture.
+    pub dimensions: Vector3<u32>,
+}
+
+impl Default for Texture3DParams {
+    fn default() -> Self {
+        Texture3DParams {
+            format: TextureFormat::RGBA8,
+            wrap: TextureWrap::Clamp,
+            filter: TextureFilter::Linear,
+            hint: TextureHint::Immutable,
+            dimensions: Vector3::new(0, 0, 0),
+        }
+    }
+}
+
+impl Texture3DParams {
+    pub fn validate(&self, data: Option<&Texture3DData>) -> Result<()> {
+        if self.format.compressed() {
+            return Err(Error::TextureInvalid(
+                "Compressed formats are not supported by 3D texture.".into(),
+            ));
+        }
+
+        if let Some(buf) = data {
+            let len = self.size(self.dimensions);
+            if !buf.bytes.is_empty() && buf.bytes[0].len() > len as usize {
+                return Err(Error::OutOfBounds);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the size in bytes of a volume with `dimensions`.
+    #[inline]
+    pub fn size(&self, dimensions: Vector3<u32>) -> u32 {
+        self.format.size(Vector2::new(dimensions.x, dimensions.y)) * dimensions.z
+    }
+}
+
+/// Continuous texture data of different mipmap levels.
+///
+/// Notes that each level stores its slices from front to back, and mipmaps are stored in
+/// order from largest size to smallest size.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Texture3DData {
+    pub bytes: Vec<Box<[u8]>>,
+}
+
 /// A `RenderTexture` object is basicly texture object with special format. It can
 /// be used as a render target. If the `sampler` field is true, it can also be ther
 /// source of a texture access from a __shader__.
 ///
-#[derive(Debug, Copy, Clone)]
+/// If `samples` is greater than 1, a multisampled render target will be created. The
+/// samples are resolved into the regular texture automatically when the rendering into
+/// surface finishes, so it could be sampled by later passes as usual.
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct RenderTextureParams {
     pub format: RenderTextureFormat,
     pub wrap: TextureWrap,
     pub filter: TextureFilter,
     pub dimensions: Vector2<u32>,
     pub sampler: bool,
+    /// The number of samples per pixel, which would be clamped to the maximum that
+    /// supported by device.
+    pub samples: u8,
 }
 
 impl Default for RenderTextureParams {
@@ -74,6 +250,7 @@ impl Default for RenderTextureParams {
             filter: TextureFilter::Linear,
             dimensions: Vector2::new(0, 0),
             sampler: true,
+            samples: 1,
         }
     }
 }
@@ -128,12 +305,22 @@ pub enum TextureWrap {
 /// Each element of `Depth` is a single depth value. The `Graphics` converts it to
 /// floating point, multiplies by the signed scale factor, adds the signed bias, and
 /// clamps to the range [0,1].
+///
+/// The floating-point formats `RGBA16F` and `RG11B10F` hold colors of high dynamic range,
+/// which are not clamped to [0,1] when rendering. They might be unsupported by devices,
+/// check it with `video::is_render_texture_format_supported` before using.
+///
+/// The linear colors are encoded into sRGB space when writing into `SRGBA8`, and decoded
+/// when sampling it, so the dark colors keep more precision than `RGBA8`.
 #[repr(u8)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum RenderTextureFormat {
     RGB8,
     RGBA4,
     RGBA8,
+    SRGBA8,
+    RGBA16F,
+    RG11B10F,
     Depth16,
     Depth24,
     Depth32,
@@ -145,6 +332,19 @@ impl RenderTextureFormat {
         self == RenderTextureFormat::RGB8
             || self == RenderTextureFormat::RGBA4
             || self == RenderTextureFormat::RGBA8
+            || self == RenderTextureFormat::SRGBA8
+            || self.is_float()
+    }
+
+    /// Checks if this is a color format in sRGB space.
+    #[inline]
+    pub fn is_srgb(self) -> bool {
+        self == RenderTextureFormat::SRGBA8
+    }
+
+    /// Checks if this is a floating-point color format.
+    pub fn is_float(self) -> bool {
+        self == RenderTextureFormat::RGBA16F || self == RenderTextureFormat::RG11B10F
     }
 
     /// Returns the size in bytes of texture with `dimensions`.
@@ -154,13 +354,22 @@ impl RenderTextureFormat {
             RenderTextureFormat::RGBA4 | RenderTextureFormat::Depth16 => 2 * square,
             RenderTextureFormat::RGB8 | RenderTextureFormat::Depth24 => 3 * square,
             RenderTextureFormat::RGBA8
+            | RenderTextureFormat::SRGBA8
+            | RenderTextureFormat::RG11B10F
             | RenderTextureFormat::Depth32
             | RenderTextureFormat::Depth24Stencil8 => 4 * square,
+            RenderTextureFormat::RGBA16F => 8 * square,
         }
     }
 }
 
 /// List of all the possible formats of input data when uploading to texture.
+///
+/// The colors of `SRGB8` and `SRGBA8` are in sRGB space, which are decoded into linear
+/// space when sampled. They should be used for the color textures like albedo maps, while
+/// the data like normal maps should be kept in linear formats. If the sRGB textures are
+/// unsupported by devices, they are uploaded in linear formats and should be decoded by
+/// shaders, check it with `video::requires_srgb_decoding`.
 #[repr(u8)]
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TextureFormat {
@@ -193,6 +402,12 @@ pub enum TextureFormat {
 
     S3tcDxt1RGB4BPP,
     S3tcDxt5RGBA8BPP,
+
+    Astc4x4RGBA8BPP,
+    Astc8x8RGBA2BPP,
+
+    SRGB8,
+    SRGBA8,
 }
 
 impl TextureFormat {
@@ -208,8 +423,10 @@ impl TextureFormat {
             | TextureFormat::PvrtcRGB4BPP
             | TextureFormat::PvrtcRGB2BPP
             | TextureFormat::Etc2RGB4BPP
-            | TextureFormat::S3tcDxt1RGB4BPP => 3,
+            | TextureFormat::S3tcDxt1RGB4BPP
+            | TextureFormat::SRGB8 => 3,
             TextureFormat::RGBA8
+            | TextureFormat::SRGBA8
             | TextureFormat::RGBA4
             | TextureFormat::RGBA5551
             | TextureFormat::RGBA1010102
@@ -218,7 +435,9 @@ impl TextureFormat {
             | TextureFormat::PvrtcRGBA4BPP
             | TextureFormat::PvrtcRGBA2BPP
             | TextureFormat::Etc2RGBA8BPP
-            | TextureFormat::S3tcDxt5RGBA8BPP => 4,
+            | TextureFormat::S3tcDxt5RGBA8BPP
+            | TextureFormat::Astc4x4RGBA8BPP
+            | TextureFormat::Astc8x8RGBA2BPP => 4,
         }
     }
 
@@ -231,14 +450,22 @@ impl TextureFormat {
             TextureFormat::Etc2RGB4BPP | TextureFormat::S3tcDxt1RGB4BPP => square / 2,
             TextureFormat::S3tcDxt5RGBA8BPP => square,
             TextureFormat::Etc2RGBA8BPP => square,
+            // ASTC always encodes a block of texels into 128 bits.
+            TextureFormat::Astc4x4RGBA8BPP => {
+                ((dimensions.x + 3) / 4) * ((dimensions.y + 3) / 4) * 16
+            }
+            TextureFormat::Astc8x8RGBA2BPP => {
+                ((dimensions.x + 7) / 8) * ((dimensions.y + 7) / 8) * 16
+            }
             TextureFormat::R8 => square,
             TextureFormat::RG8
             | TextureFormat::RGB565
             | TextureFormat::RGBA4
             | TextureFormat::RGBA5551
             | TextureFormat::R16F => 2 * square,
-            TextureFormat::RGB8 => 3 * square,
+            TextureFormat::RGB8 | TextureFormat::SRGB8 => 3 * square,
             TextureFormat::RGBA8
+            | TextureFormat::SRGBA8
             | TextureFormat::RGBA1010102
             | TextureFormat::RG16F
             | TextureFormat::R32F => 4 * square,
@@ -249,6 +476,12 @@ impl TextureFormat {
         }
     }
 
+    /// Checks if this is a color format in sRGB space.
+    #[inline]
+    pub fn is_srgb(self) -> bool {
+        self == TextureFormat::SRGB8 || self == TextureFormat::SRGBA8
+    }
+
     pub fn compressed(self) -> bool {
         match self {
             TextureFormat::Etc2RGB4BPP
@@ -258,8 +491,48 @@ impl TextureFormat {
             | TextureFormat::PvrtcRGBA2BPP
             | TextureFormat::PvrtcRGBA4BPP
             | TextureFormat::S3tcDxt1RGB4BPP
-            | TextureFormat::S3tcDxt5RGBA8BPP => true,
+            | TextureFormat::S3tcDxt5RGBA8BPP
+            | TextureFormat::Astc4x4RGBA8BPP
+            | TextureFormat::Astc8x8RGBA2BPP => true,
             _ => false,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn astc() {
+        let format = TextureFormat::Astc4x4RGBA8BPP;
+        assert!(format.compressed());
+        assert_eq!(format.components(), 4);
+        assert_eq!(format.size(Vector2::new(4, 4)), 16);
+        assert_eq!(format.size(Vector2::new(5, 5)), 64);
+        assert_eq!(format.size(Vector2::new(1, 1)), 16);
+
+        let format = TextureFormat::Astc8x8RGBA2BPP;
+        assert!(format.compressed());
+        assert_eq!(format.size(Vector2::new(8, 8)), 16);
+        assert_eq!(format.size(Vector2::new(9, 16)), 64);
+    }
+
+    #[test]
+    fn texture_3d() {
+        let mut params = Texture3DParams::default();
+        params.dimensions = Vector3::new(4, 4, 2);
+        assert_eq!(params.size(params.dimensions), 4 * 4 * 4 * 2);
+
+        let data = Texture3DData {
+            bytes: vec![vec![0; 128].into_boxed_slice()],
+        };
+        assert!(params.validate(Some(&data)).is_ok());
+
+        params.dimensions.z = 1;
+        assert!(params.validate(Some(&data)).is_err());
+
+        params.format = TextureFormat::Etc2RGB4BPP;
+        assert!(params.validate(None).is_err());
+    }
+}
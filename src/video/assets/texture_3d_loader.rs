@@ -0,0 +1,82 @@
+use bincode;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crate::errors::*;
+use crate::res::utils::prelude::ResourceLoader;
+use crate::utils::double_buf::DoubleBuf;
+
+use super::super::backends::frame::{Command, Frame};
+use super::ktx2::{self, Ktx2Decoder};
+use super::texture::*;
+
+pub const MAGIC: [u8; 8] = [b'V', b'T', b'3', b'D', b' ', 0, 0, 1];
+
+#[derive(Clone)]
+pub struct Texture3DLoader {
+    frames: Arc<DoubleBuf<Frame>>,
+    ktx2: Arc<Ktx2Decoder>,
+}
+
+impl Texture3DLoader {
+    pub(crate) fn new(frames: Arc<DoubleBuf<Frame>>, ktx2: Arc<Ktx2Decoder>) -> Self {
+        Texture3DLoader { frames, ktx2 }
+    }
+}
+
+impl ResourceLoader for Texture3DLoader {
+    type Handle = Texture3DHandle;
+    type Intermediate = (Texture3DParams, Option<Texture3DData>);
+    type Resource = Texture3DParams;
+
+    fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
+        let (params, data) = if ktx2::accept(bytes) {
+            self.ktx2.decode(bytes)?.into_texture_3d()?
+        } else {
+            if bytes[0..8] != MAGIC[..] {
+                bail!("[Texture3DLoader] MAGIC number not match.");
+            }
+
+            let mut file = Cursor::new(&bytes[8..]);
+            let params: Texture3DParams = bincode::deserialize_from(&mut file)?;
+            (params, bincode::deserialize_from(&mut file)?)
+        };
+
+        info!(
+            "[Texture3DLoader] load {:?} ({}x{}x{} - {:?}).",
+            handle, params.dimensions.x, params.dimensions.y, params.dimensions.z, params.format
+        );
+
+        Ok((params, Some(data)))
+    }
+
+    fn validate(&self, item: &Self::Intermediate) -> Result<()> {
+        item.0.validate(item.1.as_ref())?;
+        Ok(())
+    }
+
+    fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
+        info!("[Texture3DLoader] create {:?}.", handle);
+
+        item.0.validate(item.1.as_ref())?;
+
+        let cmd = Command::CreateTexture3D(Box::new((handle, item.0, item.1)));
+        self.frames.write().cmds.push(cmd);
+
+        Ok(item.0)
+    }
+
+    fn size(&self, item: &Self::Intermediate) -> usize {
+        match item.1 {
+            Some(ref data) => data.bytes.iter().map(|v| v.len()).sum(),
+            None => item.0.size(item.0.dimensions) as usize,
+        }
+    }
+
+    fn delete(&self, handle: Self::Handle, _: Self::Resource) {
+        info!("[Texture3DLoader] delete {:?}.", handle);
+
+        let cmd = Command::DeleteTexture3D(handle);
+        self.frames.write().cmds.push(cmd);
+    }
+}
@@ -0,0 +1,82 @@
+use bincode;
+use std::io::Cursor;
+use std::sync::Arc;
+
+use crate::errors::*;
+use crate::res::utils::prelude::ResourceLoader;
+use crate::utils::double_buf::DoubleBuf;
+
+use super::super::backends::frame::{Command, Frame};
+use super::ktx2::{self, Ktx2Decoder};
+use super::texture::*;
+
+pub const MAGIC: [u8; 8] = [b'V', b'C', b'U', b'B', b' ', 0, 0, 1];
+
+#[derive(Clone)]
+pub struct TextureCubeLoader {
+    frames: Arc<DoubleBuf<Frame>>,
+    ktx2: Arc<Ktx2Decoder>,
+}
+
+impl TextureCubeLoader {
+    pub(crate) fn new(frames: Arc<DoubleBuf<Frame>>, ktx2: Arc<Ktx2Decoder>) -> Self {
+        TextureCubeLoader { frames, ktx2 }
+    }
+}
+
+impl ResourceLoader for TextureCubeLoader {
+    type Handle = TextureCubeHandle;
+    type Intermediate = (TextureCubeParams, Option<TextureCubeData>);
+    type Resource = TextureCubeParams;
+
+    fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
+        let (params, data) = if ktx2::accept(bytes) {
+            self.ktx2.decode(bytes)?.into_texture_cube()?
+        } else {
+            if bytes[0..8] != MAGIC[..] {
+                bail!("[TextureCubeLoader] MAGIC number not match.");
+            }
+
+            let mut file = Cursor::new(&bytes[8..]);
+            let params: TextureCubeParams = bincode::deserialize_from(&mut file)?;
+            (params, bincode::deserialize_from(&mut file)?)
+        };
+
+        info!(
+            "[TextureCubeLoader] load {:?} ({}x{} - {:?}).",
+            handle, params.dimensions.x, params.dimensions.y, params.format
+        );
+
+        Ok((params, Some(data)))
+    }
+
+    fn validate(&self, item: &Self::Intermediate) -> Result<()> {
+        item.0.validate(item.1.as_ref())?;
+        Ok(())
+    }
+
+    fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
+        info!("[TextureCubeLoader] create {:?}.", handle);
+
+        item.0.validate(item.1.as_ref())?;
+
+        let cmd = Command::CreateTextureCube(Box::new((handle, item.0, item.1)));
+        self.frames.write().cmds.push(cmd);
+
+        Ok(item.0)
+    }
+
+    fn size(&self, item: &Self::Intermediate) -> usize {
+        match item.1 {
+            Some(ref data) => data.faces.iter().flatten().map(|v| v.len()).sum(),
+            None => 6 * item.0.format.size(item.0.dimensions) as usize,
+        }
+    }
+
+    fn delete(&self, handle: Self::Handle, _: Self::Resource) {
+        info!("[TextureCubeLoader] delete {:?}.", handle);
+
+        let cmd = Command::DeleteTextureCube(handle);
+        self.frames.write().cmds.push(cmd);
+    }
+}
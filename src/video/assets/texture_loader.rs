@@ -1,12 +1,14 @@
 use bincode;
 use std::io::Cursor;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use crate::errors::*;
 use crate::res::utils::prelude::ResourceLoader;
 use crate::utils::double_buf::DoubleBuf;
+use crate::utils::hash::FastHashMap;
 
 use super::super::backends::frame::{Command, Frame};
+use super::ktx2::{self, Ktx2Decoder};
 use super::texture::*;
 
 pub const MAGIC: [u8; 8] = [b'V', b'T', b'E', b'X', b' ', 0, 0, 1];
@@ -14,11 +16,21 @@ pub const MAGIC: [u8; 8] = [b'V', b'T', b'E', b'X', b' ', 0, 0, 1];
 #[derive(Clone)]
 pub struct TextureLoader {
     frames: Arc<DoubleBuf<Frame>>,
+    streams: Arc<Mutex<TextureStreams>>,
+    ktx2: Arc<Ktx2Decoder>,
 }
 
 impl TextureLoader {
-    pub(crate) fn new(frames: Arc<DoubleBuf<Frame>>) -> Self {
-        TextureLoader { frames }
+    pub(crate) fn new(
+        frames: Arc<DoubleBuf<Frame>>,
+        streams: Arc<Mutex<TextureStreams>>,
+        ktx2: Arc<Ktx2Decoder>,
+    ) -> Self {
+        TextureLoader {
+            frames,
+            streams,
+            ktx2,
+        }
     }
 }
 
@@ -28,13 +40,17 @@ impl ResourceLoader for TextureLoader {
     type Resource = TextureParams;
 
     fn load(&self, handle: Self::Handle, bytes: &[u8]) -> Result<Self::Intermediate> {
-        if bytes[0..8] != MAGIC[..] {
-            bail!("[TextureLoader] MAGIC number not match.");
-        }
-
-        let mut file = Cursor::new(&bytes[8..]);
-        let params: TextureParams = bincode::deserialize_from(&mut file)?;
-        let data = bincode::deserialize_from(&mut file)?;
+        let (params, data) = if ktx2::accept(bytes) {
+            self.ktx2.decode(bytes)?.into_texture()?
+        } else {
+            if bytes[0..8] != MAGIC[..] {
+                bail!("[TextureLoader] MAGIC number not match.");
+            }
+
+            let mut file = Cursor::new(&bytes[8..]);
+            let params: TextureParams = bincode::deserialize_from(&mut file)?;
+            (params, bincode::deserialize_from(&mut file)?)
+        };
 
         info!(
             "[TextureLoader] load {:?} ({}x{} - {:?}).",
@@ -44,21 +60,193 @@ impl ResourceLoader for TextureLoader {
         Ok((params, Some(data)))
     }
 
+    fn validate(&self, item: &Self::Intermediate) -> Result<()> {
+        item.0.validate(item.1.as_ref())?;
+        Ok(())
+    }
+
     fn create(&self, handle: Self::Handle, item: Self::Intermediate) -> Result<Self::Resource> {
         info!("[TextureLoader] create {:?}.", handle);
 
-        item.0.validate(item.1.as_ref())?;
+        let (params, mut data) = item;
+        params.validate(data.as_ref())?;
+
+        if let Some(ref mut data) = data {
+            self.streams.lock().unwrap().split(handle, &params, data);
+        }
 
-        let cmd = Command::CreateTexture(Box::new((handle, item.0, item.1)));
+        let cmd = Command::CreateTexture(Box::new((handle, params, data)));
         self.frames.write().cmds.push(cmd);
 
-        Ok(item.0)
+        Ok(params)
+    }
+
+    fn size(&self, item: &Self::Intermediate) -> usize {
+        match item.1 {
+            Some(ref data) => data.bytes.iter().map(|v| v.len()).sum(),
+            None => item.0.format.size(item.0.dimensions) as usize,
+        }
     }
 
     fn delete(&self, handle: Self::Handle, _: Self::Resource) {
         info!("[TextureLoader] delete {:?}.", handle);
 
+        self.streams.lock().unwrap().remove(handle);
+
         let cmd = Command::DeleteTexture(handle);
         self.frames.write().cmds.push(cmd);
     }
 }
+
+/// The mipmaps of textures which are waiting to be streamed.
+pub struct TextureStreams {
+    params: Option<TextureStreamingParams>,
+    // The leading levels of each texture, in order from largest size to smallest size.
+    pending: FastHashMap<TextureHandle, Vec<Box<[u8]>>>,
+}
+
+impl TextureStreams {
+    pub fn new() -> Self {
+        TextureStreams {
+            params: Some(TextureStreamingParams::default()),
+            pending: FastHashMap::default(),
+        }
+    }
+
+    #[inline]
+    pub fn params(&self) -> Option<TextureStreamingParams> {
+        self.params
+    }
+
+    /// Sets the parameters of streaming, which takes effect on the textures created later.
+    #[inline]
+    pub fn set_params(&mut self, params: Option<TextureStreamingParams>) {
+        self.params = params;
+    }
+
+    /// Checks if there are mipmaps of the texture waiting to be streamed.
+    #[inline]
+    pub fn contains(&self, handle: TextureHandle) -> bool {
+        self.pending.contains_key(&handle)
+    }
+
+    /// Takes the levels larger than `resident` out of `data`, which are left empty.
+    pub fn split(&mut self, handle: TextureHandle, params: &TextureParams, data: &mut TextureData) {
+        let resident = match self.params {
+            Some(v) if params.hint == TextureHint::Immutable => v.resident,
+            _ => return,
+        };
+
+        // The smallest level is always uploaded, so the texture is usable at once.
+        let len = data.bytes.len();
+        let levels = (0..len.saturating_sub(1))
+            .take_while(|&i| {
+                let size = params.dimensions.x.max(params.dimensions.y);
+                (size >> i).max(1) > resident
+            })
+            .count();
+
+        if levels > 0 && data.bytes[..levels].iter().all(|v| !v.is_empty()) {
+            let mut bytes = Vec::with_capacity(levels);
+            for v in &mut data.bytes[..levels] {
+                bytes.push(std::mem::replace(v, Vec::new().into_boxed_slice()));
+            }
+
+            self.pending.insert(handle, bytes);
+        }
+    }
+
+    #[inline]
+    pub fn remove(&mut self, handle: TextureHandle) {
+        self.pending.remove(&handle);
+    }
+
+    /// Streams the next levels of textures within the budget of a frame.
+    pub fn advance(&mut self, frames: &DoubleBuf<Frame>) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        let budget = self.params.map(|v| v.budget).unwrap_or(0);
+        let mut streamed = 0;
+        let mut frame = frames.write();
+
+        self.pending.retain(|&handle, levels| {
+            while streamed == 0 || streamed < budget {
+                let bytes = match levels.pop() {
+                    Some(bytes) => bytes,
+                    None => break,
+                };
+
+                streamed += bytes.len().max(1);
+                let cmd = Command::UpdateTextureMip(Box::new((handle, levels.len(), bytes)));
+                frame.cmds.push(cmd);
+            }
+
+            !levels.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::math::prelude::Vector2;
+    use crate::utils::prelude::HandleLike;
+
+    #[test]
+    fn stream() {
+        let mut params = TextureParams::default();
+        params.format = TextureFormat::R8;
+        params.dimensions = Vector2::new(1024, 512);
+
+        let bytes = (0..11)
+            .map(|i| {
+                vec![
+                    i as u8;
+                    params
+                        .format
+                        .size(params.dimensions.map(|v| (v >> i).max(1)))
+                        as usize
+                ]
+            })
+            .map(|v| v.into_boxed_slice())
+            .collect();
+
+        let mut data = TextureData { bytes };
+        let mut streams = TextureStreams::new();
+        streams.set_params(Some(TextureStreamingParams {
+            resident: 256,
+            budget: 1,
+        }));
+
+        let handle = TextureHandle::new(1, 1);
+        streams.split(handle, &params, &mut data);
+        assert!(streams.contains(handle));
+        assert!(data.bytes[..2].iter().all(|v| v.is_empty()));
+        assert!(data.bytes[2..].iter().all(|v| !v.is_empty()));
+
+        let frames = DoubleBuf::new(Frame::with_capacity(0), Frame::with_capacity(0));
+        let mut levels = Vec::new();
+        while streams.contains(handle) {
+            streams.advance(&frames);
+            for v in frames.write().cmds.drain(..) {
+                if let Command::UpdateTextureMip(v) = v {
+                    assert_eq!(v.2[0], v.1 as u8);
+                    levels.push(v.1);
+                }
+            }
+        }
+
+        assert_eq!(levels, [1, 0]);
+
+        // Dynamic textures are updated by users, so they are never streamed.
+        let mut data = TextureData {
+            bytes: vec![vec![0; 1024 * 512].into_boxed_slice(); 2],
+        };
+
+        params.hint = TextureHint::Dynamic;
+        streams.split(handle, &params, &mut data);
+        assert!(!streams.contains(handle));
+    }
+}
@@ -0,0 +1,37 @@
+//! Shared blocks of uniform variables, which could be uploaded once and bound to
+//! multiple shaders. The data of uniform buffer should follow the `std140` layout rules.
+
+use crate::video::assets::mesh::MeshHint;
+use crate::video::errors::{Error, Result};
+
+impl_handle!(UniformBufferHandle);
+
+/// The setup parameters of uniform buffer object.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct UniformBufferParams {
+    /// Usage hints.
+    pub hint: MeshHint,
+    /// The size in bytes of this buffer.
+    pub size: usize,
+}
+
+impl Default for UniformBufferParams {
+    fn default() -> Self {
+        UniformBufferParams {
+            hint: MeshHint::Dynamic,
+            size: 0,
+        }
+    }
+}
+
+impl UniformBufferParams {
+    pub fn validate(&self, data: Option<&[u8]>) -> Result<()> {
+        if let Some(v) = data {
+            if v.len() > self.size {
+                return Err(Error::OutOfBounds);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,43 +1,101 @@
+use std::sync::Arc;
+
 use crate::errors::*;
-use crate::math::prelude::{Aabb2, Vector2};
-use crate::utils::prelude::{DataBuffer, DataBufferPtr, HashValue};
+use crate::math::prelude::{Aabb2, Aabb3, Vector2};
+use crate::res::request::Response;
+use crate::sched::prelude::LockLatch;
+use crate::utils::prelude::HashValue;
 
 use super::super::assets::prelude::*;
+use super::super::command::{ArenaPtr, FrameArena};
 use super::Visitor;
 
-type VarsPtr = DataBufferPtr<[(HashValue<str>, UniformVariable)]>;
-type BytesPtr = DataBufferPtr<[u8]>;
+type VarsPtr = ArenaPtr<[(HashValue<str>, UniformVariable)]>;
+type BytesPtr = ArenaPtr<[u8]>;
+type StrPtr = ArenaPtr<str>;
 
 #[derive(Debug, Clone)]
 pub enum Command {
     Bind(SurfaceHandle),
-    Draw(ShaderHandle, MeshHandle, MeshIndex, VarsPtr),
+    Draw(
+        ShaderHandle,
+        MeshHandle,
+        MeshIndex,
+        Option<(InstanceBufferHandle, u32)>,
+        VarsPtr,
+    ),
     UpdateScissor(SurfaceScissor),
     UpdateViewport(SurfaceViewport),
+    BeginQuery(QueryHandle),
+    EndQuery(QueryHandle),
+    PushDebugGroup(StrPtr),
+    PopDebugGroup,
+    SetDebugName(VideoObject, StrPtr),
 
     CreateSurface(Box<(SurfaceHandle, SurfaceParams)>),
     DeleteSurface(SurfaceHandle),
 
     CreateShader(Box<(ShaderHandle, ShaderParams, String, String)>),
+    UpdateShader(Box<(ShaderHandle, ShaderParams, String, String)>),
     DeleteShader(ShaderHandle),
 
+    CreateUniformBuffer(Box<(UniformBufferHandle, UniformBufferParams, Option<Box<[u8]>>)>),
+    UpdateUniformBuffer(UniformBufferHandle, usize, BytesPtr),
+    DeleteUniformBuffer(UniformBufferHandle),
+
+    CreateQuery(QueryHandle, QueryParams),
+    DeleteQuery(QueryHandle),
+
+    CreateSampler(SamplerHandle, SamplerParams),
+    DeleteSampler(SamplerHandle),
+
     CreateTexture(Box<(TextureHandle, TextureParams, Option<TextureData>)>),
-    UpdateTexture(TextureHandle, Aabb2<u32>, BytesPtr),
+    UpdateTexture(TextureHandle, usize, Aabb2<u32>, BytesPtr),
+    UpdateTextureMip(Box<(TextureHandle, usize, Box<[u8]>)>),
+    GenerateMipmaps(TextureHandle),
     DeleteTexture(TextureHandle),
+    SetTexturePlaceholder(Option<TextureHandle>),
+    SetTexturePending(TextureHandle, bool),
+    SetWireframe(bool),
+
+    CreateTextureCube(
+        Box<(
+            TextureCubeHandle,
+            TextureCubeParams,
+            Option<TextureCubeData>,
+        )>,
+    ),
+    DeleteTextureCube(TextureCubeHandle),
+
+    CreateTexture3D(Box<(Texture3DHandle, Texture3DParams, Option<Texture3DData>)>),
+    UpdateTexture3D(Texture3DHandle, Aabb3<u32>, BytesPtr),
+    DeleteTexture3D(Texture3DHandle),
 
     CreateRenderTexture(Box<(RenderTextureHandle, RenderTextureParams)>),
     DeleteRenderTexture(RenderTextureHandle),
+    ReadRenderTexture(Box<(RenderTextureHandle, Aabb2<u32>, Arc<LockLatch<Response>>)>),
+    ReadFramebuffer(Box<(Aabb2<u32>, Arc<LockLatch<Response>>)>),
 
     CreateMesh(Box<(MeshHandle, MeshParams, Option<MeshData>)>),
     UpdateVertexBuffer(MeshHandle, usize, BytesPtr),
     UpdateIndexBuffer(MeshHandle, usize, BytesPtr),
     DeleteMesh(MeshHandle),
+
+    CreateInstanceBuffer(
+        Box<(
+            InstanceBufferHandle,
+            InstanceBufferParams,
+            Option<Box<[u8]>>,
+        )>,
+    ),
+    UpdateInstanceBuffer(InstanceBufferHandle, usize, BytesPtr),
+    DeleteInstanceBuffer(InstanceBufferHandle),
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Frame {
     pub cmds: Vec<Command>,
-    pub bufs: DataBuffer,
+    pub bufs: FrameArena,
 }
 
 unsafe impl Send for Frame {}
@@ -48,7 +106,7 @@ impl Frame {
     pub fn with_capacity(capacity: usize) -> Self {
         Frame {
             cmds: Vec::with_capacity(16),
-            bufs: DataBuffer::with_capacity(capacity),
+            bufs: FrameArena::with_capacity(capacity),
         }
     }
 
@@ -58,6 +116,22 @@ impl Frame {
         self.bufs.clear();
     }
 
+    /// Drops the commands without dispatching, e.g. while the context is lost. The
+    /// pending readbacks are completed with errors.
+    pub fn discard(&mut self) {
+        for v in self.cmds.drain(..) {
+            match v {
+                Command::ReadRenderTexture(v) => {
+                    v.2.set(Err(format_err!("The video context has been lost.")));
+                }
+                Command::ReadFramebuffer(v) => {
+                    v.1.set(Err(format_err!("The video context has been lost.")));
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Dispatch frame tasks and draw calls to the backend context.
     pub fn dispatch(
         &mut self,
@@ -74,10 +148,11 @@ impl Frame {
                         visitor.bind(surface, dimensions)?;
                     }
 
-                    Command::Draw(shader, mesh, mesh_index, ptr) => {
+                    Command::Draw(shader, mesh, mesh_index, instances, ptr) => {
                         let vars = self.bufs.as_slice(ptr);
                         dc += 1;
-                        tris += visitor.draw(shader, mesh, mesh_index, vars)?;
+                        tris +=
+                            visitor.draw(shader, mesh, mesh_index, instances, vars, &self.bufs)?;
                     }
 
                     Command::UpdateScissor(scissor) => {
@@ -88,6 +163,26 @@ impl Frame {
                         visitor.update_surface_viewport(view)?;
                     }
 
+                    Command::BeginQuery(handle) => {
+                        visitor.begin_query(handle)?;
+                    }
+
+                    Command::EndQuery(handle) => {
+                        visitor.end_query(handle)?;
+                    }
+
+                    Command::PushDebugGroup(ptr) => {
+                        visitor.push_debug_group(self.bufs.as_str(ptr))?;
+                    }
+
+                    Command::PopDebugGroup => {
+                        visitor.pop_debug_group()?;
+                    }
+
+                    Command::SetDebugName(object, ptr) => {
+                        visitor.set_debug_name(object, self.bufs.as_str(ptr))?;
+                    }
+
                     Command::CreateSurface(v) => {
                         visitor.create_surface(v.0, v.1)?;
                     }
@@ -100,23 +195,101 @@ impl Frame {
                         visitor.create_shader(v.0, v.1, &v.2, &v.3)?;
                     }
 
+                    Command::UpdateShader(v) => {
+                        visitor.update_shader(v.0, v.1, &v.2, &v.3)?;
+                    }
+
                     Command::DeleteShader(handle) => {
                         visitor.delete_shader(handle)?;
                     }
 
+                    Command::CreateUniformBuffer(v) => {
+                        visitor.create_uniform_buffer(
+                            v.0,
+                            v.1,
+                            v.2.as_ref().map(|v| v.as_ref()),
+                        )?;
+                    }
+
+                    Command::UpdateUniformBuffer(handle, offset, ptr) => {
+                        let data = self.bufs.as_slice(ptr);
+                        visitor.update_uniform_buffer(handle, offset, data)?;
+                    }
+
+                    Command::DeleteUniformBuffer(handle) => {
+                        visitor.delete_uniform_buffer(handle)?;
+                    }
+
+                    Command::CreateQuery(handle, params) => {
+                        visitor.create_query(handle, params)?;
+                    }
+
+                    Command::DeleteQuery(handle) => {
+                        visitor.delete_query(handle)?;
+                    }
+
+                    Command::CreateSampler(handle, params) => {
+                        visitor.create_sampler(handle, params)?;
+                    }
+
+                    Command::DeleteSampler(handle) => {
+                        visitor.delete_sampler(handle)?;
+                    }
+
                     Command::CreateTexture(v) => {
                         visitor.create_texture(v.0, v.1, v.2)?;
                     }
 
-                    Command::UpdateTexture(handle, area, ptr) => {
+                    Command::UpdateTexture(handle, level, area, ptr) => {
                         let data = self.bufs.as_slice(ptr);
-                        visitor.update_texture(handle, area, data)?;
+                        visitor.update_texture(handle, level, area, data)?;
+                    }
+
+                    Command::UpdateTextureMip(v) => {
+                        visitor.update_texture_mip(v.0, v.1, &v.2)?;
+                    }
+
+                    Command::GenerateMipmaps(handle) => {
+                        visitor.generate_mipmaps(handle)?;
                     }
 
                     Command::DeleteTexture(handle) => {
                         visitor.delete_texture(handle)?;
                     }
 
+                    Command::SetTexturePlaceholder(handle) => {
+                        visitor.set_texture_placeholder(handle)?;
+                    }
+
+                    Command::SetTexturePending(handle, pending) => {
+                        visitor.set_texture_pending(handle, pending)?;
+                    }
+
+                    Command::SetWireframe(enable) => {
+                        visitor.set_wireframe(enable)?;
+                    }
+
+                    Command::CreateTextureCube(v) => {
+                        visitor.create_texture_cube(v.0, v.1, v.2)?;
+                    }
+
+                    Command::DeleteTextureCube(handle) => {
+                        visitor.delete_texture_cube(handle)?;
+                    }
+
+                    Command::CreateTexture3D(v) => {
+                        visitor.create_texture_3d(v.0, v.1, v.2)?;
+                    }
+
+                    Command::UpdateTexture3D(handle, area, ptr) => {
+                        let data = self.bufs.as_slice(ptr);
+                        visitor.update_texture_3d(handle, area, data)?;
+                    }
+
+                    Command::DeleteTexture3D(handle) => {
+                        visitor.delete_texture_3d(handle)?;
+                    }
+
                     Command::CreateRenderTexture(v) => {
                         visitor.create_render_texture(v.0, v.1)?;
                     }
@@ -125,6 +298,24 @@ impl Frame {
                         visitor.delete_render_texture(handle)?;
                     }
 
+                    // The readbacks are completed with errors if failed, otherwise the
+                    // requests would be pending forever.
+                    Command::ReadRenderTexture(v) => {
+                        let latch = v.2.clone();
+                        if let Err(err) = visitor.read_render_texture(v.0, v.1, v.2) {
+                            latch.set(Err(format_err!("{}", err)));
+                            return Err(err);
+                        }
+                    }
+
+                    Command::ReadFramebuffer(v) => {
+                        let latch = v.1.clone();
+                        if let Err(err) = visitor.read_framebuffer(v.0, v.1) {
+                            latch.set(Err(format_err!("{}", err)));
+                            return Err(err);
+                        }
+                    }
+
                     Command::CreateMesh(v) => {
                         visitor.create_mesh(v.0, v.1, v.2)?;
                     }
@@ -142,6 +333,23 @@ impl Frame {
                     Command::DeleteMesh(handle) => {
                         visitor.delete_mesh(handle)?;
                     }
+
+                    Command::CreateInstanceBuffer(v) => {
+                        visitor.create_instance_buffer(
+                            v.0,
+                            v.1,
+                            v.2.as_ref().map(|v| v.as_ref()),
+                        )?;
+                    }
+
+                    Command::UpdateInstanceBuffer(handle, offset, ptr) => {
+                        let data = self.bufs.as_slice(ptr);
+                        visitor.update_instance_buffer(handle, offset, data)?;
+                    }
+
+                    Command::DeleteInstanceBuffer(handle) => {
+                        visitor.delete_instance_buffer(handle)?;
+                    }
                 }
             }
 
@@ -5,6 +5,7 @@ use std::ffi;
 use std::mem;
 
 use crate::errors::*;
+use crate::video::assets::texture::RenderTextureFormat;
 
 /// Describes the OpenGL context profile.
 #[derive(Debug, Copy, Clone)]
@@ -95,7 +96,7 @@ impl Version {
 macro_rules! extensions {
     ($($string:expr => $field:ident,)+) => {
 /// Contains data about the list of extensions.
-        #[derive(Debug, Clone, Copy)]
+        #[derive(Debug, Clone, Copy, Default)]
         pub struct Extensions {
             $(
                 pub $field: bool,
@@ -164,6 +165,7 @@ extensions! {
     "GL_APPLE_vertex_array_object" => gl_apple_vertex_array_object,
     "GL_EXT_framebuffer_object" => gl_ext_framebuffer_object,
     "GL_EXT_framebuffer_blit" => gl_ext_framebuffer_blit,
+    "GL_EXT_framebuffer_multisample" => gl_ext_framebuffer_multisample,
     "GL_NV_fbo_color_attachments" => gl_nv_fbo_color_attachments,
     "GL_OES_vertex_array_object" => gl_oes_vertex_array_object,
     "GL_IMG_texture_compression_pvrtc" => gl_img_texture_compression_pvrtc,
@@ -171,13 +173,40 @@ extensions! {
     "GL_ARB_ES3_compatibility" => gl_arb_es3_compatibility,
     "GL_OES_compressed_ETC2_RGB8_texture" => gl_oes_compressed_etc2_rgb8_texture,
     "GL_OES_compressed_ETC2_RGBA8_texture" => gl_oes_compressed_etc2_rgba8_texture,
+    "GL_KHR_texture_compression_astc_ldr" => gl_khr_texture_compression_astc_ldr,
+    "GL_ARB_instanced_arrays" => gl_arb_instanced_arrays,
+    "GL_ARB_occlusion_query" => gl_arb_occlusion_query,
+    "GL_ARB_occlusion_query2" => gl_arb_occlusion_query2,
+    "GL_ARB_timer_query" => gl_arb_timer_query,
+    "GL_ARB_texture_float" => gl_arb_texture_float,
+    "GL_EXT_packed_float" => gl_ext_packed_float,
+    "GL_EXT_color_buffer_float" => gl_ext_color_buffer_float,
+    "GL_EXT_color_buffer_half_float" => gl_ext_color_buffer_half_float,
+    "GL_OES_texture_half_float" => gl_oes_texture_half_float,
+    "GL_EXT_texture_sRGB" => gl_ext_texture_srgb,
+    "GL_EXT_sRGB" => gl_ext_srgb,
+    "GL_ARB_framebuffer_sRGB" => gl_arb_framebuffer_srgb,
+    "GL_EXT_framebuffer_sRGB" => gl_ext_framebuffer_srgb,
+    "GL_KHR_debug" => gl_khr_debug,
+    "GL_ARB_sampler_objects" => gl_arb_sampler_objects,
+    "GL_ARB_texture_filter_anisotropic" => gl_arb_texture_filter_anisotropic,
+    "GL_EXT_texture_filter_anisotropic" => gl_ext_texture_filter_anisotropic,
 }
 
+/// The enums of `GL_EXT_texture_filter_anisotropic`, which are not included in the
+/// core profile bindings.
+pub const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+pub const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+/// The pixel type of `GL_OES_texture_half_float`, which differs from `GL_HALF_FLOAT`.
+pub const HALF_FLOAT_OES: GLenum = 0x8D61;
+
 #[derive(Debug, Copy, Clone)]
 pub enum TextureCompression {
     ETC2,
     PVRTC,
     S3TC,
+    ASTC,
 }
 
 /// Represents the capabilities of the context.
@@ -220,11 +249,26 @@ pub struct Capabilities {
     /// `glActiveTexture` must be between `GL_TEXTURE0` and `GL_TEXTURE0` + this value - 1.
     pub max_combined_texture_image_units: u8,
 
+    /// Maximum number of textures that can be accessed from vertex shaders.
+    pub max_vertex_texture_image_units: u32,
+
+    /// Maximum number of four-element vectors of uniforms in vertex shaders.
+    pub max_vertex_uniform_vectors: u32,
+
     /// Number of available buffer bind points for `GL_UNIFORM_BUFFER`.
     pub max_indexed_uniform_buffer: u32,
 
     /// Maximum number of color attachment bind points.
     pub max_color_attachments: u32,
+
+    /// Maximum number of samples of multisampled renderbuffers.
+    pub max_samples: u32,
+
+    /// The default framebuffer encodes the linear colors into sRGB space.
+    pub srgb_backbuffer: bool,
+
+    /// Maximum degree of anisotropic filtering, 1 if not supported.
+    pub max_anisotropy: u32,
 }
 
 impl Capabilities {
@@ -254,8 +298,13 @@ impl Capabilities {
             forward_compatible,
             max_viewport_dims: Capabilities::parse_viewport_dims(),
             max_combined_texture_image_units: Capabilities::parse_texture_image_units(),
+            max_vertex_texture_image_units: Capabilities::parse_vertex_texture_image_units(),
+            max_vertex_uniform_vectors: Capabilities::parse_vertex_uniform_vectors(version),
             max_indexed_uniform_buffer: Capabilities::parse_uniform_buffers(version, &extensions),
             max_color_attachments: Capabilities::parse_color_attachments(version, &extensions),
+            max_samples: Capabilities::parse_samples(version, &extensions),
+            srgb_backbuffer: Capabilities::parse_srgb_backbuffer(version),
+            max_anisotropy: Capabilities::parse_anisotropy(version, &extensions),
         })
     }
 
@@ -269,6 +318,180 @@ impl Capabilities {
             }
             TextureCompression::PVRTC => self.extensions.gl_img_texture_compression_pvrtc,
             TextureCompression::S3TC => self.extensions.gl_ext_texture_compression_s3tc,
+            TextureCompression::ASTC => {
+                self.version >= Version::ES(3, 2)
+                    || self.extensions.gl_khr_texture_compression_astc_ldr
+            }
+        }
+    }
+
+    /// Checks if the context supports drawing multiple instances with per-instance
+    /// vertex attributes.
+    pub fn has_instancing(&self) -> bool {
+        self.version >= Version::GL(3, 3)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_instanced_arrays
+    }
+
+    /// Checks if the context supports the textures in sRGB color space.
+    pub fn has_srgb_texture(&self) -> bool {
+        self.version >= Version::GL(2, 1)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_ext_texture_srgb
+            || self.extensions.gl_ext_srgb
+    }
+
+    /// Checks if the context supports rendering into sRGB color attachments.
+    pub fn has_srgb_framebuffer(&self) -> bool {
+        self.version >= Version::GL(3, 0)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_framebuffer_srgb
+            || self.extensions.gl_ext_framebuffer_srgb
+            || self.extensions.gl_ext_srgb
+    }
+
+    /// Checks if the sRGB encoding could be toggled with `GL_FRAMEBUFFER_SRGB`, it's
+    /// always enabled for sRGB color attachments on OpenGL ES contexts.
+    pub fn has_srgb_write_control(&self) -> bool {
+        self.version >= Version::GL(3, 0)
+            || self.extensions.gl_arb_framebuffer_srgb
+            || self.extensions.gl_ext_framebuffer_srgb
+    }
+
+    /// Checks if the polygons could be rasterized as lines with `glPolygonMode`, which is
+    /// not available in OpenGL ES.
+    pub fn has_polygon_mode(&self) -> bool {
+        match self.version {
+            Version::GL(_, _) => gl::PolygonMode::is_loaded(),
+            Version::ES(_, _) => false,
+        }
+    }
+
+    /// Checks if the context supports sampler objects.
+    pub fn has_sampler_objects(&self) -> bool {
+        let supported = self.version >= Version::GL(3, 3)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_sampler_objects;
+
+        supported && gl::GenSamplers::is_loaded() && gl::BindSampler::is_loaded()
+    }
+
+    /// Checks if the context supports the bias of level of detail, which is not a
+    /// sampler state on OpenGL ES.
+    #[inline]
+    pub fn has_lod_bias(&self) -> bool {
+        self.version >= Version::GL(1, 4)
+    }
+
+    /// Checks if the level of detail could be clamped with `TEXTURE_MIN_LOD` and
+    /// `TEXTURE_MAX_LOD`.
+    #[inline]
+    pub fn has_lod_range(&self) -> bool {
+        self.version >= Version::GL(1, 2) || self.version >= Version::ES(3, 0)
+    }
+
+    /// Checks if the context could generate the mipmaps of textures.
+    #[inline]
+    pub fn has_generate_mipmap(&self) -> bool {
+        gl::GenerateMipmap::is_loaded()
+    }
+
+    /// Checks if the context supports 3D textures.
+    #[inline]
+    pub fn has_texture_3d(&self) -> bool {
+        self.version >= Version::GL(1, 2) || self.version >= Version::ES(3, 0)
+    }
+
+    /// Checks if the base and max mipmap levels of textures could be specified, which
+    /// are not available on OpenGL ES 2.0.
+    #[inline]
+    pub fn has_texture_base_level(&self) -> bool {
+        self.version >= Version::GL(1, 2) || self.version >= Version::ES(3, 0)
+    }
+
+    /// Checks if the context supports uniform buffer objects.
+    pub fn has_uniform_buffer(&self) -> bool {
+        self.version >= Version::GL(3, 1)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_uniform_buffer_object
+    }
+
+    /// Checks if the context supports occlusion queries.
+    pub fn has_occlusion_query(&self) -> bool {
+        self.version >= Version::GL(1, 5)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_occlusion_query
+    }
+
+    /// Checks if the context supports boolean occlusion queries with `ANY_SAMPLES_PASSED`.
+    pub fn has_any_samples_passed(&self) -> bool {
+        self.version >= Version::GL(3, 3)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_occlusion_query2
+    }
+
+    /// Checks if the objects and groups of commands could be labeled for graphics debuggers.
+    pub fn has_debug_labels(&self) -> bool {
+        let supported = self.version >= Version::GL(4, 3)
+            || self.version >= Version::ES(3, 2)
+            || self.extensions.gl_khr_debug;
+
+        supported && gl::ObjectLabel::is_loaded() && gl::PushDebugGroup::is_loaded()
+    }
+
+    /// Checks if the context supports timer queries with `TIME_ELAPSED`. The timer queries
+    /// of OpenGL ES are not supported, since they could be disjoint.
+    pub fn has_timer_query(&self) -> bool {
+        self.version >= Version::GL(3, 3) || self.extensions.gl_arb_timer_query
+    }
+
+    /// Checks if the pixels could be read back asynchronously with pixel buffer objects
+    /// and fences.
+    #[inline]
+    pub fn has_pixel_buffer(&self) -> bool {
+        self.version >= Version::GL(3, 2) || self.version >= Version::ES(3, 0)
+    }
+
+    /// Checks if the framebuffers could be copied with `glBlitFramebuffer`.
+    pub fn has_blit_framebuffer(&self) -> bool {
+        let supported = self.version >= Version::GL(3, 0)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_framebuffer_object
+            || self.extensions.gl_ext_framebuffer_blit;
+
+        supported && gl::BlitFramebuffer::is_loaded()
+    }
+
+    /// Checks if the blending of color attachments could be enabled individually.
+    #[inline]
+    pub fn has_indexed_blend(&self) -> bool {
+        self.version >= Version::GL(3, 0) || self.version >= Version::ES(3, 2)
+    }
+
+    /// Checks if the context supports the textures with half-float pixels.
+    pub fn has_half_float_texture(&self) -> bool {
+        self.version >= Version::GL(3, 0)
+            || self.version >= Version::ES(3, 0)
+            || self.extensions.gl_arb_texture_float
+            || self.extensions.gl_oes_texture_half_float
+    }
+
+    /// Checks if the context supports rendering into textures with `format`.
+    pub fn has_render_texture_format(&self, format: RenderTextureFormat) -> bool {
+        let core = self.version >= Version::GL(3, 0) || self.version >= Version::ES(3, 2);
+        match format {
+            RenderTextureFormat::RGBA16F => {
+                core || (self.has_half_float_texture()
+                    && (self.extensions.gl_arb_texture_float
+                        || self.extensions.gl_ext_color_buffer_float
+                        || self.extensions.gl_ext_color_buffer_half_float))
+            }
+            RenderTextureFormat::RG11B10F => {
+                core || self.extensions.gl_ext_packed_float
+                    || self.extensions.gl_ext_color_buffer_float
+            }
+            RenderTextureFormat::SRGBA8 => self.has_srgb_framebuffer(),
+            _ => true,
         }
     }
 
@@ -283,6 +506,25 @@ impl Capabilities {
             .map_err(|_| format_err!("[GL] String of {} is unformaled.", id))
     }
 
+    unsafe fn parse_srgb_backbuffer(version: Version) -> bool {
+        let attachment = match version {
+            Version::GL(major, _) if major >= 3 => gl::BACK_LEFT,
+            Version::ES(major, _) if major >= 3 => gl::BACK,
+            _ => return false,
+        };
+
+        let mut encoding = 0;
+        gl::GetFramebufferAttachmentParameteriv(
+            gl::FRAMEBUFFER,
+            attachment,
+            gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING,
+            &mut encoding,
+        );
+
+        // The query fails if the default framebuffer has no back buffer.
+        gl::GetError() == gl::NO_ERROR && encoding as GLenum == gl::SRGB
+    }
+
     #[inline]
     unsafe fn parse_viewport_dims() -> (u32, u32) {
         let mut val: [gl::types::GLint; 2] = [0, 0];
@@ -315,9 +557,32 @@ impl Capabilities {
         val as u8
     }
 
+    #[inline]
+    unsafe fn parse_vertex_texture_image_units() -> u32 {
+        let mut val = 0;
+        gl::GetIntegerv(gl::MAX_VERTEX_TEXTURE_IMAGE_UNITS, &mut val);
+        val as u32
+    }
+
+    #[inline]
+    unsafe fn parse_vertex_uniform_vectors(version: Version) -> u32 {
+        // The limit is counted in components before OpenGL 4.1.
+        let mut val = 0;
+        if version >= Version::GL(4, 1) || version >= Version::ES(2, 0) {
+            gl::GetIntegerv(gl::MAX_VERTEX_UNIFORM_VECTORS, &mut val);
+            val as u32
+        } else {
+            gl::GetIntegerv(gl::MAX_VERTEX_UNIFORM_COMPONENTS, &mut val);
+            val as u32 / 4
+        }
+    }
+
     #[inline]
     unsafe fn parse_uniform_buffers(version: Version, exts: &Extensions) -> u32 {
-        if version >= Version::GL(3, 1) || exts.gl_arb_uniform_buffer_object {
+        if version >= Version::GL(3, 1)
+            || version >= Version::ES(3, 0)
+            || exts.gl_arb_uniform_buffer_object
+        {
             let mut val = mem::uninitialized();
             gl::GetIntegerv(gl::MAX_UNIFORM_BUFFER_BINDINGS, &mut val);
             val as u32
@@ -343,4 +608,98 @@ impl Capabilities {
             0
         }
     }
+
+    #[inline]
+    unsafe fn parse_anisotropy(version: Version, exts: &Extensions) -> u32 {
+        if version >= Version::GL(4, 6)
+            || exts.gl_arb_texture_filter_anisotropic
+            || exts.gl_ext_texture_filter_anisotropic
+        {
+            let mut val = 1.0;
+            gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut val);
+            cmp::max(val as u32, 1)
+        } else {
+            1
+        }
+    }
+
+    #[inline]
+    unsafe fn parse_samples(version: Version, exts: &Extensions) -> u32 {
+        if version >= Version::GL(3, 0)
+            || version >= Version::ES(3, 0)
+            || exts.gl_arb_framebuffer_object
+            || exts.gl_ext_framebuffer_multisample
+        {
+            let mut val = 1;
+            gl::GetIntegerv(gl::MAX_SAMPLES, &mut val);
+            val as u32
+        } else {
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn capabilities(version: Version, extensions: Extensions) -> Capabilities {
+        Capabilities {
+            version,
+            vendor: String::new(),
+            extensions,
+            renderer: String::new(),
+            profile: None,
+            debug: false,
+            forward_compatible: false,
+            max_viewport_dims: (1024, 1024),
+            max_combined_texture_image_units: 8,
+            max_vertex_texture_image_units: 0,
+            max_vertex_uniform_vectors: 128,
+            max_indexed_uniform_buffer: 0,
+            max_color_attachments: 1,
+            max_samples: 1,
+            srgb_backbuffer: false,
+            max_anisotropy: 1,
+        }
+    }
+
+    #[test]
+    fn fallbacks() {
+        let caps = capabilities(Version::ES(2, 0), Extensions::default());
+        assert!(!caps.has_instancing());
+        assert!(!caps.has_uniform_buffer());
+        assert!(!caps.has_srgb_texture());
+        assert!(!caps.has_srgb_framebuffer());
+        assert!(!caps.has_texture_base_level());
+        assert!(!caps.has_half_float_texture());
+        assert!(!caps.has_render_texture_format(RenderTextureFormat::RGBA16F));
+        assert!(!caps.has_polygon_mode());
+
+        let mut extensions = Extensions::default();
+        extensions.gl_ext_srgb = true;
+        extensions.gl_ext_color_buffer_half_float = true;
+        let caps = capabilities(Version::ES(2, 0), extensions);
+        assert!(caps.has_srgb_texture());
+        assert!(caps.has_srgb_framebuffer());
+        assert!(!caps.has_srgb_write_control());
+        assert!(!caps.has_render_texture_format(RenderTextureFormat::RGBA16F));
+
+        extensions.gl_oes_texture_half_float = true;
+        let caps = capabilities(Version::ES(2, 0), extensions);
+        assert!(caps.has_render_texture_format(RenderTextureFormat::RGBA16F));
+    }
+
+    #[test]
+    fn natives() {
+        for &version in &[Version::ES(3, 0), Version::GL(3, 3)] {
+            let caps = capabilities(version, Extensions::default());
+            assert!(caps.has_instancing());
+            assert!(caps.has_uniform_buffer());
+            assert!(caps.has_srgb_texture());
+            assert!(caps.has_srgb_framebuffer());
+            assert!(caps.has_texture_base_level());
+            assert!(caps.has_half_float_texture());
+        }
+    }
 }
@@ -1,8 +1,9 @@
+use byteorder::{ByteOrder, NativeEndian};
 use gl;
 use gl::types::*;
 
 use super::super::super::assets::prelude::*;
-use super::capabilities::{Capabilities, TextureCompression, Version};
+use super::capabilities::{Capabilities, TextureCompression, Version, HALF_FLOAT_OES};
 
 impl From<MeshHint> for GLenum {
     fn from(hint: MeshHint) -> Self {
@@ -29,12 +30,29 @@ impl From<Comparison> for GLenum {
     }
 }
 
+impl From<StencilOp> for GLenum {
+    fn from(op: StencilOp) -> Self {
+        match op {
+            StencilOp::Keep => gl::KEEP,
+            StencilOp::Zero => gl::ZERO,
+            StencilOp::Replace => gl::REPLACE,
+            StencilOp::Increment => gl::INCR,
+            StencilOp::IncrementWrap => gl::INCR_WRAP,
+            StencilOp::Decrement => gl::DECR,
+            StencilOp::DecrementWrap => gl::DECR_WRAP,
+            StencilOp::Invert => gl::INVERT,
+        }
+    }
+}
+
 impl From<Equation> for GLenum {
     fn from(eq: Equation) -> Self {
         match eq {
             Equation::Add => gl::FUNC_ADD,
             Equation::Subtract => gl::FUNC_SUBTRACT,
             Equation::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            Equation::Min => gl::MIN,
+            Equation::Max => gl::MAX,
         }
     }
 }
@@ -68,6 +86,46 @@ impl From<VertexFormat> for GLenum {
     }
 }
 
+/// Decodes a component of vertex attribute in native endian, the integers are mapped
+/// into [0, 1] or [-1, 1] if `normalized`.
+pub fn decode_vertex_component(format: VertexFormat, normalized: bool, bytes: &[u8]) -> f32 {
+    match format {
+        VertexFormat::Byte => {
+            let v = f32::from(bytes[0] as i8);
+            if normalized {
+                (v / 127.0).max(-1.0)
+            } else {
+                v
+            }
+        }
+        VertexFormat::UByte => {
+            let v = f32::from(bytes[0]);
+            if normalized {
+                v / 255.0
+            } else {
+                v
+            }
+        }
+        VertexFormat::Short => {
+            let v = f32::from(NativeEndian::read_i16(bytes));
+            if normalized {
+                (v / 32767.0).max(-1.0)
+            } else {
+                v
+            }
+        }
+        VertexFormat::UShort => {
+            let v = f32::from(NativeEndian::read_u16(bytes));
+            if normalized {
+                v / 65535.0
+            } else {
+                v
+            }
+        }
+        VertexFormat::Float => NativeEndian::read_f32(bytes),
+    }
+}
+
 impl From<MeshPrimitive> for GLenum {
     fn from(primitive: MeshPrimitive) -> Self {
         match primitive {
@@ -95,6 +153,14 @@ pub fn texture_format(format: TextureFormat, caps: &Capabilities) -> (GLenum, GL
         Version::ES(major, _) => major >= 3,
     };
 
+    // The sRGB textures are uploaded in linear formats if not supported, and decoded by
+    // shaders instead, see `video::requires_srgb_decoding`.
+    let format = match format {
+        TextureFormat::SRGB8 if !caps.has_srgb_texture() => TextureFormat::RGB8,
+        TextureFormat::SRGBA8 if !caps.has_srgb_texture() => TextureFormat::RGBA8,
+        v => v,
+    };
+
     // FIXME
     // gl::COMPRESSED_RGB_S3TC_DXT1_EXT = 0x83F0
     // gl::COMPRESSED_RGBA_S3TC_DXT5_EXT = 0x83F3
@@ -104,6 +170,8 @@ pub fn texture_format(format: TextureFormat, caps: &Capabilities) -> (GLenum, GL
     // gl::COMPRESSED_RGBA_PVRTC_4BPPV1_IMG = 0x8C02
     // gl::COMPRESSED_RGB8_ETC2 = 0x9274
     // gl::COMPRESSED_RGBA8_ETC2_EAC = 0x9278
+    // gl::COMPRESSED_RGBA_ASTC_4x4_KHR = 0x93B0
+    // gl::COMPRESSED_RGBA_ASTC_8x8_KHR = 0x93B7
 
     if sized {
         match format {
@@ -131,6 +199,10 @@ pub fn texture_format(format: TextureFormat, caps: &Capabilities) -> (GLenum, GL
             TextureFormat::PvrtcRGB4BPP => (0x8C00, gl::RGB, gl::UNSIGNED_BYTE),
             TextureFormat::PvrtcRGBA2BPP => (0x8C03, gl::RGB, gl::UNSIGNED_BYTE),
             TextureFormat::PvrtcRGBA4BPP => (0x8C02, gl::RGB, gl::UNSIGNED_BYTE),
+            TextureFormat::Astc4x4RGBA8BPP => (0x93B0, gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::Astc8x8RGBA2BPP => (0x93B7, gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::SRGB8 => (gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE),
+            TextureFormat::SRGBA8 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
         }
     } else {
         match format {
@@ -142,10 +214,10 @@ pub fn texture_format(format: TextureFormat, caps: &Capabilities) -> (GLenum, GL
             TextureFormat::RGBA4 => (gl::RGBA, gl::RGBA, gl::UNSIGNED_SHORT_4_4_4_4),
             TextureFormat::RGBA5551 => (gl::RGBA, gl::RGBA, gl::UNSIGNED_SHORT_5_5_5_1),
             TextureFormat::RGBA1010102 => (gl::RGBA, gl::RGBA, gl::UNSIGNED_INT_2_10_10_10_REV),
-            TextureFormat::R16F => (gl::RED, gl::RED, gl::HALF_FLOAT),
-            TextureFormat::RG16F => (gl::RG, gl::RG, gl::HALF_FLOAT),
-            TextureFormat::RGB16F => (gl::RGB, gl::RGB, gl::HALF_FLOAT),
-            TextureFormat::RGBA16F => (gl::RGBA, gl::RGBA, gl::HALF_FLOAT),
+            TextureFormat::R16F => (gl::RED, gl::RED, HALF_FLOAT_OES),
+            TextureFormat::RG16F => (gl::RG, gl::RG, HALF_FLOAT_OES),
+            TextureFormat::RGB16F => (gl::RGB, gl::RGB, HALF_FLOAT_OES),
+            TextureFormat::RGBA16F => (gl::RGBA, gl::RGBA, HALF_FLOAT_OES),
             TextureFormat::R32F => (gl::RED, gl::RED, gl::FLOAT),
             TextureFormat::RG32F => (gl::RG, gl::RG, gl::FLOAT),
             TextureFormat::RGB32F => (gl::RGB, gl::RGB, gl::FLOAT),
@@ -158,6 +230,11 @@ pub fn texture_format(format: TextureFormat, caps: &Capabilities) -> (GLenum, GL
             TextureFormat::PvrtcRGB4BPP => (0x8C00, gl::RGB, gl::UNSIGNED_BYTE),
             TextureFormat::PvrtcRGBA2BPP => (0x8C03, gl::RGB, gl::UNSIGNED_BYTE),
             TextureFormat::PvrtcRGBA4BPP => (0x8C02, gl::RGB, gl::UNSIGNED_BYTE),
+            TextureFormat::Astc4x4RGBA8BPP => (0x93B0, gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::Astc8x8RGBA2BPP => (0x93B7, gl::RGBA, gl::UNSIGNED_BYTE),
+            // The unsized formats of EXT_sRGB requires the same format and internal format.
+            TextureFormat::SRGB8 => (gl::SRGB, gl::SRGB, gl::UNSIGNED_BYTE),
+            TextureFormat::SRGBA8 => (gl::SRGB_ALPHA, gl::SRGB_ALPHA, gl::UNSIGNED_BYTE),
         }
     }
 }
@@ -177,6 +254,13 @@ impl TextureFormat {
             TextureFormat::S3tcDxt1RGB4BPP | TextureFormat::S3tcDxt5RGBA8BPP => {
                 capabilities.has_compression(TextureCompression::S3TC)
             }
+            TextureFormat::Astc4x4RGBA8BPP | TextureFormat::Astc8x8RGBA2BPP => {
+                capabilities.has_compression(TextureCompression::ASTC)
+            }
+            TextureFormat::R16F
+            | TextureFormat::RG16F
+            | TextureFormat::RGB16F
+            | TextureFormat::RGBA16F => capabilities.has_half_float_texture(),
             _ => true,
         }
     }
@@ -193,12 +277,37 @@ impl From<TextureWrap> for GLenum {
     }
 }
 
+/// Gets the minification filter that samples between mipmaps with `mip`.
+pub fn min_filter(min: TextureFilter, mip: Option<TextureFilter>) -> GLenum {
+    match (min, mip) {
+        (TextureFilter::Nearest, None) => gl::NEAREST,
+        (TextureFilter::Linear, None) => gl::LINEAR,
+        (TextureFilter::Nearest, Some(TextureFilter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+        (TextureFilter::Linear, Some(TextureFilter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+        (TextureFilter::Nearest, Some(TextureFilter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+        (TextureFilter::Linear, Some(TextureFilter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
+impl From<TextureFilter> for GLenum {
+    fn from(filter: TextureFilter) -> Self {
+        min_filter(filter, None)
+    }
+}
+
 impl From<RenderTextureFormat> for (GLenum, GLenum, GLenum) {
     fn from(format: RenderTextureFormat) -> Self {
         match format {
             RenderTextureFormat::RGB8 => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE),
             RenderTextureFormat::RGBA4 => (gl::RGBA4, gl::RGBA, gl::UNSIGNED_SHORT_4_4_4_4),
             RenderTextureFormat::RGBA8 => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+            RenderTextureFormat::SRGBA8 => (gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+            RenderTextureFormat::RGBA16F => (gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+            RenderTextureFormat::RG11B10F => (
+                gl::R11F_G11F_B10F,
+                gl::RGB,
+                gl::UNSIGNED_INT_10F_11F_11F_REV,
+            ),
             RenderTextureFormat::Depth16 => (gl::DEPTH_COMPONENT16, gl::DEPTH_COMPONENT, gl::FLOAT),
             RenderTextureFormat::Depth24 => (gl::DEPTH_COMPONENT24, gl::DEPTH_COMPONENT, gl::FLOAT),
             RenderTextureFormat::Depth32 => (gl::DEPTH_COMPONENT32, gl::DEPTH_COMPONENT, gl::FLOAT),
@@ -208,3 +317,45 @@ impl From<RenderTextureFormat> for (GLenum, GLenum, GLenum) {
         }
     }
 }
+
+/// Gets the formats of the texture storage of render textures, the half-float pixels are
+/// specified with unsized formats of `GL_OES_texture_half_float` on OpenGL ES 2.0.
+pub fn render_texture_format(
+    format: RenderTextureFormat,
+    caps: &Capabilities,
+) -> (GLenum, GLenum, GLenum) {
+    match (format, caps.version) {
+        (RenderTextureFormat::RGBA16F, Version::ES(2, _)) => (gl::RGBA, gl::RGBA, HALF_FLOAT_OES),
+        _ => format.into(),
+    }
+}
+
+/// Maps the type of active uniform into `UniformVariableType`.
+pub fn uniform_variable_type(tp: GLenum) -> Option<UniformVariableType> {
+    match tp {
+        gl::SAMPLER_2D => Some(UniformVariableType::Texture),
+        gl::SAMPLER_CUBE => Some(UniformVariableType::TextureCube),
+        gl::SAMPLER_3D => Some(UniformVariableType::Texture3D),
+        gl::INT | gl::BOOL => Some(UniformVariableType::I32),
+        gl::FLOAT => Some(UniformVariableType::F32),
+        gl::FLOAT_VEC2 => Some(UniformVariableType::Vector2f),
+        gl::FLOAT_VEC3 => Some(UniformVariableType::Vector3f),
+        gl::FLOAT_VEC4 => Some(UniformVariableType::Vector4f),
+        gl::FLOAT_MAT2 => Some(UniformVariableType::Matrix2f),
+        gl::FLOAT_MAT3 => Some(UniformVariableType::Matrix3f),
+        gl::FLOAT_MAT4 => Some(UniformVariableType::Matrix4f),
+        _ => None,
+    }
+}
+
+/// Returns the number of components of active attribute, zero if the type could not
+/// be sourced from vertex buffers.
+pub fn attribute_components(tp: GLenum) -> u8 {
+    match tp {
+        gl::FLOAT | gl::INT | gl::UNSIGNED_INT => 1,
+        gl::FLOAT_VEC2 | gl::INT_VEC2 | gl::UNSIGNED_INT_VEC2 => 2,
+        gl::FLOAT_VEC3 | gl::INT_VEC3 | gl::UNSIGNED_INT_VEC3 => 3,
+        gl::FLOAT_VEC4 | gl::INT_VEC4 | gl::UNSIGNED_INT_VEC4 => 4,
+        _ => 0,
+    }
+}
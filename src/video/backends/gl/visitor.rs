@@ -1,18 +1,26 @@
 use std::cell::RefCell;
+use std::sync::Arc;
 
 use gl;
 use gl::types::*;
 use smallvec::SmallVec;
 
 use crate::errors::*;
-use crate::math::prelude::{Aabb2, Color, Vector2};
+use crate::math::prelude::{Aabb2, Aabb3, Color, Vector2};
+use crate::res::request::Response;
+use crate::sched::prelude::LockLatch;
 use crate::utils::hash::{FastHashMap, FastHashSet};
 use crate::utils::hash_value::HashValue;
+use crate::window::WindowHandle;
 
 use super::super::super::assets::prelude::*;
-use super::super::utils::DataVec;
+use super::super::super::capabilities::Capabilities as VideoCapabilities;
+use super::super::super::command::FrameArena;
+use super::super::super::MAX_FRAMEBUFFER_ATTACHMENTS;
+use super::super::reflection::ShaderReflection;
+use super::super::utils::{self, draw_buffers, samples, DataVec};
 use super::super::{UniformVar, Visitor};
-use super::capabilities::{Capabilities, Version};
+use super::capabilities::{Capabilities, Version, TEXTURE_MAX_ANISOTROPY};
 use super::types;
 
 #[derive(Debug, Clone)]
@@ -21,6 +29,15 @@ struct GLSurfaceData {
     id: Option<GLuint>,
     dimensions: Option<Vector2<u32>>,
     params: SurfaceParams,
+    resolve: Option<GLResolveData>,
+}
+
+/// The framebuffer that multisampled attachments would be resolved into.
+#[derive(Debug, Clone)]
+struct GLResolveData {
+    id: GLuint,
+    colors: SmallVec<[GLenum; 8]>,
+    mask: GLbitfield,
 }
 
 #[derive(Debug, Clone)]
@@ -30,6 +47,7 @@ struct GLShaderData {
     params: ShaderParams,
     uniforms: RefCell<FastHashMap<HashValue<str>, GLint>>,
     attributes: RefCell<FastHashMap<HashValue<str>, GLint>>,
+    blocks: FastHashMap<HashValue<str>, GLuint>,
 }
 
 impl GLShaderData {
@@ -78,25 +96,130 @@ struct GLMeshData {
     params: MeshParams,
 }
 
+#[derive(Debug, Clone)]
+struct GLInstanceBufferData {
+    handle: InstanceBufferHandle,
+    vbo: GLuint,
+    params: InstanceBufferParams,
+    // The copy of instances on CPU, which is used to emulate the instanced draws if the
+    // context does not support instancing.
+    shadow: Option<RefCell<Vec<u8>>>,
+}
+
+#[derive(Debug, Clone)]
+struct GLUniformBufferData {
+    ubo: GLuint,
+    params: UniformBufferParams,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct GLQueryData {
+    id: GLuint,
+    target: GLenum,
+    // Emulates `AnySamplesPassed` with `SAMPLES_PASSED` target.
+    clamp: bool,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct GLSamplerData {
+    // The sampler object, which is 0 if the states are applied to textures instead.
+    id: GLuint,
+    params: SamplerParams,
+}
+
 #[derive(Debug, Clone)]
 struct GLTextureData {
     handle: TextureHandle,
     id: GLuint,
     params: TextureParams,
     allocated: RefCell<bool>,
+    // The largest mipmap level that has been uploaded.
+    base_level: RefCell<usize>,
+    // The number of mipmap levels that have storages.
+    levels: RefCell<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct GLTextureCubeData {
+    id: GLuint,
+}
+
+#[derive(Debug, Clone)]
+struct GLTexture3DData {
+    id: GLuint,
+    params: Texture3DParams,
 }
 
 #[derive(Debug, Copy, Clone)]
 struct GLRenderTextureData {
     handle: RenderTextureHandle,
     id: GLuint,
+    // The multisampled renderbuffer of sampler, which is resolved into `id`.
+    msaa: Option<GLuint>,
     params: RenderTextureParams,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+impl GLRenderTextureData {
+    /// Gets the object that should be rendered into.
+    fn target(&self) -> (GLuint, RenderTextureParams) {
+        match self.msaa {
+            Some(id) => {
+                let mut params = self.params;
+                params.sampler = false;
+                (id, params)
+            }
+            None => (self.id, self.params),
+        }
+    }
+}
+
+/// The framebuffer object that is rendered into in place of the window framebuffer, if
+/// the context is offscreen or the window is a secondary one.
+#[derive(Debug, Copy, Clone)]
+struct GLOffscreenData {
+    id: GLuint,
+    color: GLuint,
+    depth_stencil: GLuint,
+    dimensions: Vector2<u32>,
+}
+
+/// The pixels which are being copied into pixel buffer object by GPU.
+struct GLReadback {
+    pbo: GLuint,
+    fence: GLsync,
+    len: usize,
+    latch: Arc<LockLatch<Response>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 enum Sampler {
     RenderTexture(RenderTextureHandle),
     Texture(TextureHandle),
+    TextureCube(TextureCubeHandle),
+    Texture3D(Texture3DHandle),
+}
+
+impl Sampler {
+    fn target(self) -> GLenum {
+        match self {
+            Sampler::TextureCube(_) => gl::TEXTURE_CUBE_MAP,
+            Sampler::Texture3D(_) => gl::TEXTURE_3D,
+            _ => gl::TEXTURE_2D,
+        }
+    }
+}
+
+type VAOKey = (ShaderHandle, MeshHandle, Option<InstanceBufferHandle>);
+
+// The maximum number of timer queries waiting for results, the surfaces are not timed
+// once it's exceeded, e.g. when GPU falls behind.
+const MAX_PENDING_TIMERS: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+struct GLTimer {
+    id: GLuint,
+    surface: SurfaceHandle,
+    frame: u64,
 }
 
 struct GLMutableState {
@@ -104,12 +227,27 @@ struct GLMutableState {
     scissor: SurfaceScissor,
     view: SurfaceViewport,
     cleared_surfaces: FastHashSet<SurfaceHandle>,
-    vaos: FastHashMap<(ShaderHandle, MeshHandle), GLuint>,
+    vaos: FastHashMap<VAOKey, GLuint>,
     binded_surface: Option<SurfaceHandle>,
     binded_shader: Option<ShaderHandle>,
-    binded_vao: Option<(ShaderHandle, MeshHandle)>,
+    binded_vao: Option<VAOKey>,
     binded_texture_index: usize,
     binded_textures: SmallVec<[Option<Sampler>; 8]>,
+    binded_samplers: SmallVec<[Option<SamplerHandle>; 8]>,
+    // The sampler states of textures themselves, and the sampler that overrides them if
+    // sampler objects are not supported.
+    texture_samplers: FastHashMap<Sampler, (SamplerParams, Option<SamplerHandle>)>,
+    pending_queries: Vec<QueryHandle>,
+    pending_readbacks: Vec<GLReadback>,
+    state_changes: u32,
+    frame: u64,
+    timer: Option<GLTimer>,
+    pending_timers: Vec<GLTimer>,
+    free_timers: Vec<GLuint>,
+    // The debug group of bound surface, and the number of groups pushed in it.
+    surface_debug_group: bool,
+    debug_groups: u32,
+    polygon_mode: GLenum,
 }
 
 pub struct GLVisitor {
@@ -118,8 +256,23 @@ pub struct GLVisitor {
     surfaces: DataVec<GLSurfaceData>,
     shaders: DataVec<GLShaderData>,
     meshes: DataVec<GLMeshData>,
+    instance_buffers: DataVec<GLInstanceBufferData>,
+    uniform_buffers: DataVec<GLUniformBufferData>,
+    queries: DataVec<GLQueryData>,
+    samplers: DataVec<GLSamplerData>,
     textures: DataVec<GLTextureData>,
+    texture_cubes: DataVec<GLTextureCubeData>,
+    texture_3ds: DataVec<GLTexture3DData>,
     render_textures: DataVec<GLRenderTextureData>,
+    // The texture that is bound in place of the pending textures, which are still loading
+    // or failed to load.
+    texture_placeholder: Option<TextureHandle>,
+    pending_textures: FastHashSet<TextureHandle>,
+    wireframe: bool,
+    offscreen: Option<GLOffscreenData>,
+    windows: FastHashMap<WindowHandle, GLOffscreenData>,
+    // The names of surfaces, which are used as the debug groups of their commands.
+    surface_names: FastHashMap<SurfaceHandle, String>,
 }
 
 impl GLVisitor {
@@ -142,6 +295,18 @@ impl GLVisitor {
             binded_vao: None,
             binded_texture_index: 0,
             binded_textures: SmallVec::new(),
+            binded_samplers: SmallVec::new(),
+            texture_samplers: FastHashMap::default(),
+            pending_queries: Vec::new(),
+            pending_readbacks: Vec::new(),
+            state_changes: 0,
+            frame: 0,
+            timer: None,
+            pending_timers: Vec::new(),
+            free_timers: Vec::new(),
+            surface_debug_group: false,
+            debug_groups: 0,
+            polygon_mode: gl::FILL,
         };
 
         let mut visitor = GLVisitor {
@@ -150,22 +315,233 @@ impl GLVisitor {
             surfaces: DataVec::new(),
             shaders: DataVec::new(),
             meshes: DataVec::new(),
+            instance_buffers: DataVec::new(),
+            uniform_buffers: DataVec::new(),
+            queries: DataVec::new(),
+            samplers: DataVec::new(),
             textures: DataVec::new(),
+            texture_cubes: DataVec::new(),
+            texture_3ds: DataVec::new(),
             render_textures: DataVec::new(),
+            texture_placeholder: None,
+            pending_textures: FastHashSet::default(),
+            wireframe: false,
+            offscreen: None,
+            windows: FastHashMap::default(),
+            surface_names: FastHashMap::default(),
         };
 
         Self::reset_render_state(&mut visitor.state)?;
         Ok(visitor)
     }
+
+    /// Creates a new `GLVisitor` with offscreen context, the window framebuffer is
+    /// emulated with a framebuffer object of `dimensions`.
+    pub unsafe fn new_offscreen(dimensions: Vector2<u32>) -> Result<Self> {
+        let mut visitor = Self::new()?;
+        visitor.offscreen = Some(visitor.create_offscreen_framebuffer(dimensions)?);
+        Ok(visitor)
+    }
+
+    unsafe fn create_offscreen_framebuffer(
+        &self,
+        dimensions: Vector2<u32>,
+    ) -> Result<GLOffscreenData> {
+        let mut params = RenderTextureParams::default();
+        params.dimensions = dimensions;
+        params.sampler = false;
+
+        params.format = RenderTextureFormat::RGBA8;
+        let color = Self::create_renderbuffer(params)?;
+        params.format = RenderTextureFormat::Depth24Stencil8;
+        let depth_stencil = Self::create_renderbuffer(params)?;
+
+        let mut id = 0;
+        gl::GenFramebuffers(1, &mut id);
+        assert!(id != 0);
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+        params.format = RenderTextureFormat::RGBA8;
+        self.update_framebuffer_render_texture(color, params, 0)?;
+        params.format = RenderTextureFormat::Depth24Stencil8;
+        self.update_framebuffer_render_texture(depth_stencil, params, 0)?;
+
+        if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+            bail!("[GL] The offscreen framebuffer is incomplete.");
+        }
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        check()?;
+
+        Ok(GLOffscreenData {
+            id,
+            color,
+            depth_stencil,
+            dimensions,
+        })
+    }
+
+    unsafe fn delete_offscreen_framebuffer(v: GLOffscreenData) {
+        gl::DeleteFramebuffers(1, &v.id);
+        gl::DeleteRenderbuffers(1, &v.color);
+        gl::DeleteRenderbuffers(1, &v.depth_stencil);
+    }
+
+    /// Gets the framebuffer that is rendered into in place of the secondary window, it is
+    /// recreated once the window has been resized.
+    unsafe fn window_framebuffer(&mut self, handle: WindowHandle) -> Result<GLOffscreenData> {
+        if !self.capabilities.has_blit_framebuffer() {
+            bail!("The GL Context does not support rendering into secondary windows.");
+        }
+
+        let dimensions = crate::window::inside::ctx()
+            .window_dimensions(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        // The framebuffer of minimized window has zero size.
+        let dimensions = Vector2::new(dimensions.x.max(1), dimensions.y.max(1));
+
+        if let Some(v) = self.windows.get(&handle).cloned() {
+            if v.dimensions == dimensions {
+                return Ok(v);
+            }
+
+            Self::delete_offscreen_framebuffer(v);
+            self.windows.remove(&handle);
+        }
+
+        let v = self.create_offscreen_framebuffer(dimensions)?;
+        self.windows.insert(handle, v);
+        Ok(v)
+    }
+
+    /// Copies the framebuffers into secondary windows, and swaps their buffers.
+    unsafe fn present_windows(&mut self) -> Result<()> {
+        let ctx = crate::window::inside::ctx();
+
+        let deleted: Vec<_> = self
+            .windows
+            .keys()
+            .filter(|&&v| ctx.window_dimensions(v).is_none())
+            .cloned()
+            .collect();
+
+        for v in deleted {
+            Self::delete_offscreen_framebuffer(self.windows.remove(&v).unwrap());
+        }
+
+        for (&handle, v) in &self.windows {
+            if ctx.is_window_closed(handle) {
+                continue;
+            }
+
+            ctx.make_window_current(handle)?;
+            let rsp = Self::blit_window(*v).and_then(|_| ctx.swap_window_buffers(handle));
+            ctx.make_current()?;
+            rsp?;
+        }
+
+        Ok(())
+    }
+
+    unsafe fn blit_window(v: GLOffscreenData) -> Result<()> {
+        // Framebuffer objects are not shared between contexts, so the shared color buffer is
+        // read with a temporary one.
+        let mut fbo = 0;
+        gl::GenFramebuffers(1, &mut fbo);
+        assert!(fbo != 0);
+
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+        gl::FramebufferRenderbuffer(
+            gl::READ_FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::RENDERBUFFER,
+            v.color,
+        );
+
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+
+        let (w, h) = (v.dimensions.x as GLint, v.dimensions.y as GLint);
+        gl::BlitFramebuffer(0, 0, w, h, 0, 0, w, h, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::DeleteFramebuffers(1, &fbo);
+        check()
+    }
+
+    /// Gets the framebuffer that is used as the window framebuffer.
+    #[inline]
+    fn framebuffer(&self) -> GLuint {
+        self.offscreen.map(|v| v.id).unwrap_or(0)
+    }
+}
+
+impl Drop for GLVisitor {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(v) = self.offscreen {
+                Self::delete_offscreen_framebuffer(v);
+            }
+
+            for (_, v) in self.windows.drain() {
+                Self::delete_offscreen_framebuffer(v);
+            }
+
+            let timers = self.state.timer.take().into_iter();
+            let timers = timers.chain(self.state.pending_timers.drain(..));
+            for v in timers.map(|v| v.id).chain(self.state.free_timers.drain(..)) {
+                gl::DeleteQueries(1, &v);
+            }
+        }
+    }
 }
 
 impl Visitor for GLVisitor {
     unsafe fn advance(&mut self) -> Result<()> {
         self.state.cleared_surfaces.clear();
         self.state.binded_surface = None;
+        self.state.frame += 1;
         Ok(())
     }
 
+    fn is_texture_format_supported(&self, format: TextureFormat) -> bool {
+        format.is_support(&self.capabilities)
+    }
+
+    fn is_render_texture_format_supported(&self, format: RenderTextureFormat) -> bool {
+        self.capabilities.has_render_texture_format(format)
+    }
+
+    fn capabilities(&self) -> VideoCapabilities {
+        let caps = &self.capabilities;
+        let api = match caps.version {
+            Version::GL(major, minor) => format!("OpenGL {}.{}", major, minor),
+            Version::ES(major, minor) => format!("OpenGL ES {}.{}", major, minor),
+        };
+
+        VideoCapabilities {
+            api,
+            renderer: caps.renderer.clone(),
+            instancing: caps.has_instancing(),
+            uniform_buffer: caps.has_uniform_buffer(),
+            occlusion_query: caps.has_occlusion_query(),
+            timer_query: caps.has_timer_query(),
+            srgb_texture: caps.has_srgb_texture(),
+            srgb_framebuffer: caps.has_srgb_framebuffer(),
+            srgb_backbuffer: caps.srgb_backbuffer,
+            texture_3d: caps.has_texture_3d(),
+            texture_base_level: caps.has_texture_base_level(),
+            wireframe: caps.has_polygon_mode(),
+            sampler_objects: caps.has_sampler_objects(),
+            max_anisotropy: caps.max_anisotropy,
+            max_color_attachments: caps.max_color_attachments,
+            max_samples: caps.max_samples,
+            max_texture_units: u32::from(caps.max_combined_texture_image_units),
+            max_vertex_texture_units: caps.max_vertex_texture_image_units,
+            max_vertex_uniform_vectors: caps.max_vertex_uniform_vectors,
+        }
+    }
+
     unsafe fn create_surface(
         &mut self,
         handle: SurfaceHandle,
@@ -176,6 +552,7 @@ impl Visitor for GLVisitor {
             params,
             id: None,
             dimensions: None,
+            resolve: None,
         };
 
         if params.colors[0].is_some() || params.depth_stencil.is_some() {
@@ -187,6 +564,8 @@ impl Visitor for GLVisitor {
             self.state.binded_surface = None;
 
             let mut dimensions = None;
+            let mut samples = None;
+            let mut resolves: SmallVec<[(usize, GLRenderTextureData); 8]> = SmallVec::new();
             for (i, attachment) in params.colors.iter().enumerate() {
                 if let Some(v) = *attachment {
                     let rt = self
@@ -201,6 +580,14 @@ impl Visitor for GLVisitor {
                         );
                     }
 
+                    if i as u32 >= self.capabilities.max_color_attachments {
+                        bail!(
+                            "Too many color attachments of SurfaceObject {:?}, the maximum is {}.",
+                            id,
+                            self.capabilities.max_color_attachments
+                        );
+                    }
+
                     if dimensions.is_some() && dimensions != Some(rt.params.dimensions) {
                         bail!(
                             "Incompitable(mismatch dimensons) attachments of SurfaceObject {:?}",
@@ -208,11 +595,50 @@ impl Visitor for GLVisitor {
                         );
                     }
 
+                    if samples.is_some() && samples != Some(rt.params.samples) {
+                        bail!(
+                            "Incompitable(mismatch samples) attachments of SurfaceObject {:?}",
+                            id
+                        );
+                    }
+
                     dimensions = Some(rt.params.dimensions);
-                    self.update_framebuffer_render_texture(rt.id, rt.params, i)?;
+                    samples = Some(rt.params.samples);
+
+                    let (target, target_params) = rt.target();
+                    self.update_framebuffer_render_texture(target, target_params, i)?;
+
+                    if rt.msaa.is_some() {
+                        resolves.push((i, *rt));
+                    }
                 }
             }
 
+            // The i-th output of fragment shaders writes into the i-th color attachment, and
+            // the slots without attachments are disabled.
+            let buffers: SmallVec<[GLenum; 8]> = draw_buffers(&params.colors)
+                .map(|v| v.map_or(gl::NONE, |i| gl::COLOR_ATTACHMENT0 + i as u32))
+                .collect();
+
+            // Enables writing into multiple render targets, or the ones that are not at
+            // the first slot.
+            if buffers.len() > 1 {
+                gl::DrawBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+                check()?;
+            }
+
+            // Depth-only framebuffers are incomplete on desktop GL, unless the color buffers
+            // are disabled explicitly.
+            if buffers.is_empty()
+                && params.depth_stencil.is_some()
+                && self.capabilities.version >= Version::GL(2, 0)
+            {
+                gl::DrawBuffers(1, &gl::NONE);
+                gl::ReadBuffer(gl::NONE);
+                check()?;
+            }
+
+            let mut depth_stencil_resolve = None;
             if let Some(v) = params.depth_stencil {
                 let rt = self
                     .render_textures
@@ -233,8 +659,21 @@ impl Visitor for GLVisitor {
                     );
                 }
 
+                if samples.is_some() && samples != Some(rt.params.samples) {
+                    bail!(
+                        "Incompitable(mismatch samples) attachments of SurfaceObject {:?}",
+                        id
+                    );
+                }
+
                 dimensions = Some(rt.params.dimensions);
-                self.update_framebuffer_render_texture(rt.id, rt.params, 0)?;
+
+                let (target, target_params) = rt.target();
+                self.update_framebuffer_render_texture(target, target_params, 0)?;
+
+                if rt.msaa.is_some() {
+                    depth_stencil_resolve = Some(*rt);
+                }
             }
 
             let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
@@ -267,6 +706,11 @@ impl Visitor for GLVisitor {
                 }
             }
 
+            if !resolves.is_empty() || depth_stencil_resolve.is_some() {
+                let resolve = self.create_resolve_framebuffer(&resolves, depth_stencil_resolve)?;
+                data.resolve = Some(resolve);
+            }
+
             data.id = Some(id);
             data.dimensions = dimensions;
         };
@@ -286,11 +730,18 @@ impl Visitor for GLVisitor {
             self.state.binded_surface = None;
         }
 
+        self.surface_names.remove(&handle);
+
         if let Some(id) = surface.id {
             gl::DeleteFramebuffers(1, &id);
             check()?;
         }
 
+        if let Some(resolve) = surface.resolve {
+            gl::DeleteFramebuffers(1, &resolve.id);
+            check()?;
+        }
+
         Ok(())
     }
 
@@ -301,42 +752,31 @@ impl Visitor for GLVisitor {
         vs: &str,
         fs: &str,
     ) -> Result<()> {
-        let vs = Self::compile(gl::VERTEX_SHADER, vs)?;
-        let fs = Self::compile(gl::FRAGMENT_SHADER, fs)?;
-        let id = Self::link(&[vs, fs])?;
-
-        gl::DetachShader(id, vs);
-        gl::DeleteShader(vs);
-        gl::DetachShader(id, fs);
-        gl::DeleteShader(fs);
-        check()?;
-
-        let shader = GLShaderData {
-            handle,
-            id,
-            params,
-            uniforms: RefCell::new(FastHashMap::default()),
-            attributes: RefCell::new(FastHashMap::default()),
-        };
+        let shader = self.build_shader(handle, params, vs, fs)?;
+        self.shaders.create(handle, shader);
+        Ok(())
+    }
 
-        for (name, _, _) in shader.params.attributes.iter() {
-            let name: &'static str = name.into();
-            let location = shader.attribute_location(name)?;
-            if location == -1 {
-                gl::DeleteProgram(id);
-                bail!("Attribute({:?}) is undefined in shader sources.", name);
-            }
+    unsafe fn update_shader(
+        &mut self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        vs: &str,
+        fs: &str,
+    ) -> Result<()> {
+        if self.shaders.get(handle).is_none() {
+            bail!("{:?} is invalid.", handle);
         }
 
-        for &(ref name, _) in shader.params.uniforms.iter() {
-            let location = shader.uniform_location(name)?;
-            if location == -1 {
-                gl::DeleteProgram(id);
-                bail!("Uniform({:?}) is undefined in shader sources.", name);
+        // Keeps the old program if the new sources are broken.
+        match self.build_shader(handle, params, vs, fs) {
+            Ok(shader) => {
+                self.delete_shader(handle)?;
+                self.shaders.create(handle, shader);
             }
+            Err(err) => warn!("Failed to update {:?}. {}", handle, err),
         }
 
-        self.shaders.create(handle, shader);
         Ok(())
     }
 
@@ -347,7 +787,7 @@ impl Visitor for GLVisitor {
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
         // Removes deprecated `VertexArrayObject`s.
-        self.state.vaos.retain(|&(h, _), vao| {
+        self.state.vaos.retain(|&(h, _, _), vao| {
             if h == shader.handle {
                 gl::DeleteVertexArrays(1, vao as *mut u32);
                 false
@@ -382,58 +822,53 @@ impl Visitor for GLVisitor {
         gl::GenTextures(1, &mut id);
         assert!(id != 0);
 
-        let (internal_format, format, pixel_type) =
-            types::texture_format(params.format, &self.capabilities);
+        let format = types::texture_format(params.format, &self.capabilities);
         let compressed = params.format.compressed();
         let mut allocated = false;
+        let mut base_level = 0;
+        let mut levels = 0;
 
         if let Some(mut data) = data {
             let len = data.bytes.len();
             if len > 0 {
                 Self::bind_texture(&mut self.state, Some(Sampler::Texture(handle)), 0, id)?;
-                Self::bind_texture_params(params.wrap, params.filter, len as u32)?;
+                Self::bind_texture_params(
+                    &mut self.state,
+                    Sampler::Texture(handle),
+                    gl::TEXTURE_2D,
+                    params.wrap,
+                    params.filter,
+                    len as u32,
+                    &self.capabilities,
+                )?;
+
+                // The empty levels are streamed later.
+                base_level = data.bytes.iter().position(|v| !v.is_empty()).unwrap_or(0);
+                if base_level > 0 {
+                    if !self.capabilities.has_texture_base_level() {
+                        gl::DeleteTextures(1, &id);
+                        bail!("The GL Context does not support streaming the mipmaps.");
+                    }
+
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, base_level as GLint);
+                }
 
                 let mut dims = (
                     params.dimensions.x as GLsizei,
                     params.dimensions.y as GLsizei,
                 );
 
-                if compressed {
-                    for (i, v) in data.bytes.drain(..).enumerate() {
-                        gl::CompressedTexImage2D(
-                            gl::TEXTURE_2D,
-                            i as GLint,
-                            internal_format,
-                            dims.0,
-                            dims.1,
-                            0,
-                            v.len() as GLint,
-                            &v[0] as *const u8 as *const ::std::os::raw::c_void,
-                        );
-
-                        dims.0 = (dims.0 / 2).max(1);
-                        dims.1 = (dims.1 / 2).max(1);
+                for (i, v) in data.bytes.drain(..).enumerate() {
+                    if !v.is_empty() {
+                        Self::tex_image_2d(i, dims, format, compressed, &v);
                     }
-                } else {
-                    for (i, v) in data.bytes.drain(..).enumerate() {
-                        gl::TexImage2D(
-                            gl::TEXTURE_2D,
-                            i as GLint,
-                            internal_format as GLint,
-                            dims.0,
-                            dims.1,
-                            0,
-                            format,
-                            pixel_type,
-                            &v[0] as *const u8 as *const ::std::os::raw::c_void,
-                        );
 
-                        dims.0 = (dims.0 / 2).max(1);
-                        dims.1 = (dims.1 / 2).max(1);
-                    }
+                    dims.0 = (dims.0 / 2).max(1);
+                    dims.1 = (dims.1 / 2).max(1);
                 }
 
                 allocated = true;
+                levels = len;
             }
         }
 
@@ -446,6 +881,8 @@ impl Visitor for GLVisitor {
                 id,
                 params,
                 allocated: RefCell::new(allocated),
+                base_level: RefCell::new(base_level),
+                levels: RefCell::new(levels),
             },
         );
 
@@ -455,6 +892,7 @@ impl Visitor for GLVisitor {
     unsafe fn update_texture(
         &mut self,
         handle: TextureHandle,
+        level: usize,
         area: Aabb2<u32>,
         data: &[u8],
     ) -> Result<()> {
@@ -471,9 +909,11 @@ impl Visitor for GLVisitor {
             bail!("Trying to update compressed texture.");
         }
 
-        if data.len() > texture.params.format.size(area.dim()) as usize
-            || area.min.x >= texture.params.dimensions.x
-            || area.min.y >= texture.params.dimensions.y
+        let dims = texture.params.mip_dimensions(level);
+        if level >= texture.params.mip_levels()
+            || data.len() > texture.params.format.size(area.dim()) as usize
+            || area.min.x >= dims.x
+            || area.min.y >= dims.y
         {
             bail!("Trying to update texture data out of bounds.");
         }
@@ -488,27 +928,46 @@ impl Visitor for GLVisitor {
             texture.id,
         )?;
 
-        if !*texture.allocated.borrow() {
-            Self::bind_texture_params(texture.params.wrap, texture.params.filter, 1)?;
-
-            gl::TexImage2D(
+        // Allocates the storages of levels that are updated for the first time.
+        let levels = *texture.levels.borrow();
+        if level >= levels {
+            Self::bind_texture_params(
+                &mut self.state,
+                Sampler::Texture(handle),
                 gl::TEXTURE_2D,
-                0,
-                internal_format as GLint,
-                texture.params.dimensions.x as GLsizei,
-                texture.params.dimensions.y as GLsizei,
-                0,
-                format,
-                pixel_type,
-                ::std::ptr::null(),
-            );
+                texture.params.wrap,
+                texture.params.filter,
+                level as u32 + 1,
+                &self.capabilities,
+            )?;
+
+            let base_level = *texture.base_level.borrow();
+            if base_level > 0 {
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, base_level as GLint);
+            }
+
+            for i in levels..=level {
+                let dims = texture.params.mip_dimensions(i);
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    i as GLint,
+                    internal_format as GLint,
+                    dims.x as GLsizei,
+                    dims.y as GLsizei,
+                    0,
+                    format,
+                    pixel_type,
+                    ::std::ptr::null(),
+                );
+            }
 
+            *texture.levels.borrow_mut() = level + 1;
             *texture.allocated.borrow_mut() = true;
         }
 
         gl::TexSubImage2D(
             gl::TEXTURE_2D,
-            0,
+            level as GLint,
             area.min.x as i32,
             area.min.y as i32,
             area.dim().x as i32,
@@ -521,245 +980,1238 @@ impl Visitor for GLVisitor {
         check()
     }
 
-    unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()> {
+    unsafe fn update_texture_mip(
+        &mut self,
+        handle: TextureHandle,
+        level: usize,
+        data: &[u8],
+    ) -> Result<()> {
         let texture = self
             .textures
-            .free(handle)
+            .get(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-        for v in self.state.binded_textures.iter_mut() {
-            if *v == Some(Sampler::Texture(handle)) {
-                *v = None;
-            }
+        let base_level = *texture.base_level.borrow();
+        if !*texture.allocated.borrow() || level + 1 < base_level {
+            bail!("Trying to update the mipmap of texture out of order.");
         }
 
-        gl::DeleteTextures(1, &texture.id);
-        check()
-    }
+        let dims = (
+            (texture.params.dimensions.x >> level).max(1),
+            (texture.params.dimensions.y >> level).max(1),
+        );
 
-    unsafe fn create_render_texture(
-        &mut self,
-        handle: RenderTextureHandle,
-        params: RenderTextureParams,
-    ) -> Result<()> {
-        let id = if params.sampler {
-            let mut id = 0;
-            gl::GenTextures(1, &mut id);
-            assert!(id != 0);
+        if data.is_empty() || data.len() > texture.params.format.size(dims.into()) as usize {
+            bail!("Trying to update texture data out of bounds.");
+        }
 
-            Self::bind_texture(&mut self.state, Some(Sampler::RenderTexture(handle)), 0, id)?;
-            Self::bind_texture_params(params.wrap, params.filter, 1)?;
+        Self::bind_texture(
+            &mut self.state,
+            Some(Sampler::Texture(handle)),
+            0,
+            texture.id,
+        )?;
 
-            let (internal_format, format, pixel_type) = params.format.into();
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
+        let format = types::texture_format(texture.params.format, &self.capabilities);
+        let compressed = texture.params.format.compressed();
+        let dims = (dims.0 as GLsizei, dims.1 as GLsizei);
+        Self::tex_image_2d(level, dims, format, compressed, data);
+
+        if level < base_level {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, level as GLint);
+            *texture.base_level.borrow_mut() = level;
+        }
+
+        check()
+    }
+
+    unsafe fn generate_mipmaps(&mut self, handle: TextureHandle) -> Result<()> {
+        let texture = self
+            .textures
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if !self.capabilities.has_generate_mipmap() {
+            bail!("The GL Context does not support generating mipmaps.");
+        }
+
+        if texture.params.format.compressed() {
+            bail!("Can not generate mipmaps of compressed texture.");
+        }
+
+        if !*texture.allocated.borrow() || *texture.base_level.borrow() > 0 {
+            bail!("Trying to generate mipmaps of texture without its base level.");
+        }
+
+        Self::bind_texture(
+            &mut self.state,
+            Some(Sampler::Texture(handle)),
+            0,
+            texture.id,
+        )?;
+
+        let levels = texture.params.mip_levels();
+        Self::bind_texture_params(
+            &mut self.state,
+            Sampler::Texture(handle),
+            gl::TEXTURE_2D,
+            texture.params.wrap,
+            texture.params.filter,
+            levels as u32,
+            &self.capabilities,
+        )?;
+
+        gl::GenerateMipmap(gl::TEXTURE_2D);
+        *texture.levels.borrow_mut() = levels;
+        check()
+    }
+
+    unsafe fn set_wireframe(&mut self, enable: bool) -> Result<()> {
+        self.wireframe = enable;
+
+        if let Some(handle) = self.state.binded_surface {
+            let wireframe = self.surfaces.get(handle).map(|v| v.params.wireframe);
+            self.set_polygon_mode(wireframe.unwrap_or(false))?;
+        }
+
+        Ok(())
+    }
+
+    unsafe fn set_texture_placeholder(&mut self, handle: Option<TextureHandle>) -> Result<()> {
+        self.texture_placeholder = handle;
+        Ok(())
+    }
+
+    unsafe fn set_texture_pending(&mut self, handle: TextureHandle, pending: bool) -> Result<()> {
+        if pending {
+            self.pending_textures.insert(handle);
+        } else {
+            self.pending_textures.remove(&handle);
+        }
+
+        Ok(())
+    }
+
+    unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()> {
+        self.pending_textures.remove(&handle);
+        let texture = self
+            .textures
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        for v in self.state.binded_textures.iter_mut() {
+            if *v == Some(Sampler::Texture(handle)) {
+                *v = None;
+            }
+        }
+
+        self.state
+            .texture_samplers
+            .remove(&Sampler::Texture(handle));
+
+        gl::DeleteTextures(1, &texture.id);
+        check()
+    }
+
+    unsafe fn create_texture_cube(
+        &mut self,
+        handle: TextureCubeHandle,
+        params: TextureCubeParams,
+        data: Option<TextureCubeData>,
+    ) -> Result<()> {
+        if !params.format.is_support(&self.capabilities) {
+            bail!(
+                "The GL Context does not support the texture format {:?}.",
+                params.format
+            );
+        }
+
+        let mut id = 0;
+        gl::GenTextures(1, &mut id);
+        assert!(id != 0);
+
+        let (internal_format, format, pixel_type) =
+            types::texture_format(params.format, &self.capabilities);
+        let compressed = params.format.compressed();
+
+        Self::bind_texture(&mut self.state, Some(Sampler::TextureCube(handle)), 0, id)?;
+
+        let levels = data.as_ref().map(|v| v.faces[0].len()).unwrap_or(0);
+        Self::bind_texture_params(
+            &mut self.state,
+            Sampler::TextureCube(handle),
+            gl::TEXTURE_CUBE_MAP,
+            params.wrap,
+            params.filter,
+            levels.max(1) as u32,
+            &self.capabilities,
+        )?;
+
+        if let Some(data) = data {
+            for (face, mips) in data.faces.iter().enumerate() {
+                let target = gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLenum;
+                let mut dims = (
+                    params.dimensions.x as GLsizei,
+                    params.dimensions.y as GLsizei,
+                );
+
+                for (i, v) in mips.iter().enumerate() {
+                    if compressed {
+                        gl::CompressedTexImage2D(
+                            target,
+                            i as GLint,
+                            internal_format,
+                            dims.0,
+                            dims.1,
+                            0,
+                            v.len() as GLint,
+                            &v[0] as *const u8 as *const ::std::os::raw::c_void,
+                        );
+                    } else {
+                        gl::TexImage2D(
+                            target,
+                            i as GLint,
+                            internal_format as GLint,
+                            dims.0,
+                            dims.1,
+                            0,
+                            format,
+                            pixel_type,
+                            &v[0] as *const u8 as *const ::std::os::raw::c_void,
+                        );
+                    }
+
+                    dims.0 = (dims.0 / 2).max(1);
+                    dims.1 = (dims.1 / 2).max(1);
+                }
+            }
+        }
+
+        check()?;
+
+        self.texture_cubes.create(handle, GLTextureCubeData { id });
+
+        Ok(())
+    }
+
+    unsafe fn delete_texture_cube(&mut self, handle: TextureCubeHandle) -> Result<()> {
+        let texture = self
+            .texture_cubes
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        for v in self.state.binded_textures.iter_mut() {
+            if *v == Some(Sampler::TextureCube(handle)) {
+                *v = None;
+            }
+        }
+
+        self.state
+            .texture_samplers
+            .remove(&Sampler::TextureCube(handle));
+
+        gl::DeleteTextures(1, &texture.id);
+        check()
+    }
+
+    unsafe fn create_texture_3d(
+        &mut self,
+        handle: Texture3DHandle,
+        params: Texture3DParams,
+        data: Option<Texture3DData>,
+    ) -> Result<()> {
+        if !self.capabilities.has_texture_3d() {
+            bail!("The GL Context does not support 3D texture.");
+        }
+
+        if !params.format.is_support(&self.capabilities) || params.format.compressed() {
+            bail!(
+                "The GL Context does not support the 3D texture format {:?}.",
+                params.format
+            );
+        }
+
+        let mut id = 0;
+        gl::GenTextures(1, &mut id);
+        assert!(id != 0);
+
+        let (internal_format, format, pixel_type) =
+            types::texture_format(params.format, &self.capabilities);
+
+        Self::bind_texture(&mut self.state, Some(Sampler::Texture3D(handle)), 0, id)?;
+
+        let levels = data.as_ref().map(|v| v.bytes.len()).unwrap_or(0);
+        Self::bind_texture_params(
+            &mut self.state,
+            Sampler::Texture3D(handle),
+            gl::TEXTURE_3D,
+            params.wrap,
+            params.filter,
+            levels.max(1) as u32,
+            &self.capabilities,
+        )?;
+
+        let mut dims = (
+            params.dimensions.x as GLsizei,
+            params.dimensions.y as GLsizei,
+            params.dimensions.z as GLsizei,
+        );
+
+        if levels > 0 {
+            for (i, v) in data.unwrap().bytes.iter().enumerate() {
+                gl::TexImage3D(
+                    gl::TEXTURE_3D,
+                    i as GLint,
+                    internal_format as GLint,
+                    dims.0,
+                    dims.1,
+                    dims.2,
+                    0,
+                    format,
+                    pixel_type,
+                    &v[0] as *const u8 as *const ::std::os::raw::c_void,
+                );
+
+                dims.0 = (dims.0 / 2).max(1);
+                dims.1 = (dims.1 / 2).max(1);
+                dims.2 = (dims.2 / 2).max(1);
+            }
+        } else {
+            // Allocates the storage, so it could be filled later with `update_texture_3d`.
+            gl::TexImage3D(
+                gl::TEXTURE_3D,
                 0,
                 internal_format as GLint,
-                params.dimensions.x as GLsizei,
-                params.dimensions.y as GLsizei,
+                dims.0,
+                dims.1,
+                dims.2,
                 0,
                 format,
                 pixel_type,
                 ::std::ptr::null(),
             );
-
-            id
-        } else {
-            let mut id = 0;
-            gl::GenRenderbuffers(1, &mut id);
-            assert!(id != 0);
-            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
-
-            let (internal_format, _, _) = params.format.into();
-            gl::RenderbufferStorage(
-                gl::RENDERBUFFER,
-                internal_format,
-                params.dimensions.x as GLint,
-                params.dimensions.y as GLint,
-            );
-            id
-        };
+        }
 
         check()?;
 
-        self.render_textures
-            .create(handle, GLRenderTextureData { handle, id, params });
+        self.texture_3ds
+            .create(handle, GLTexture3DData { id, params });
 
         Ok(())
     }
 
-    unsafe fn delete_render_texture(&mut self, handle: RenderTextureHandle) -> Result<()> {
-        let rt = self
-            .render_textures
+    unsafe fn update_texture_3d(
+        &mut self,
+        handle: Texture3DHandle,
+        area: Aabb3<u32>,
+        data: &[u8],
+    ) -> Result<()> {
+        let texture = self
+            .texture_3ds
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if texture.params.hint == TextureHint::Immutable {
+            bail!("Trying to update immutable texture.");
+        }
+
+        if data.len() > texture.params.size(area.dim()) as usize
+            || area.min.x >= texture.params.dimensions.x
+            || area.min.y >= texture.params.dimensions.y
+            || area.min.z >= texture.params.dimensions.z
+        {
+            bail!("Trying to update texture data out of bounds.");
+        }
+
+        let (_, format, pixel_type) =
+            types::texture_format(texture.params.format, &self.capabilities);
+
+        Self::bind_texture(
+            &mut self.state,
+            Some(Sampler::Texture3D(handle)),
+            0,
+            texture.id,
+        )?;
+
+        gl::TexSubImage3D(
+            gl::TEXTURE_3D,
+            0,
+            area.min.x as i32,
+            area.min.y as i32,
+            area.min.z as i32,
+            area.dim().x as i32,
+            area.dim().y as i32,
+            area.dim().z as i32,
+            format,
+            pixel_type,
+            &data[0] as *const u8 as *const ::std::os::raw::c_void,
+        );
+
+        check()
+    }
+
+    unsafe fn delete_texture_3d(&mut self, handle: Texture3DHandle) -> Result<()> {
+        let texture = self
+            .texture_3ds
             .free(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-        if rt.params.sampler {
-            for v in self.state.binded_textures.iter_mut() {
-                if *v == Some(Sampler::RenderTexture(handle)) {
-                    *v = None;
+        for v in self.state.binded_textures.iter_mut() {
+            if *v == Some(Sampler::Texture3D(handle)) {
+                *v = None;
+            }
+        }
+
+        self.state
+            .texture_samplers
+            .remove(&Sampler::Texture3D(handle));
+
+        gl::DeleteTextures(1, &texture.id);
+        check()
+    }
+
+    unsafe fn create_render_texture(
+        &mut self,
+        handle: RenderTextureHandle,
+        mut params: RenderTextureParams,
+    ) -> Result<()> {
+        params.samples = samples(params.samples, self.capabilities.max_samples);
+
+        let id = if params.sampler {
+            let mut id = 0;
+            gl::GenTextures(1, &mut id);
+            assert!(id != 0);
+
+            Self::bind_texture(&mut self.state, Some(Sampler::RenderTexture(handle)), 0, id)?;
+            Self::bind_texture_params(
+                &mut self.state,
+                Sampler::RenderTexture(handle),
+                gl::TEXTURE_2D,
+                params.wrap,
+                params.filter,
+                1,
+                &self.capabilities,
+            )?;
+
+            let (internal_format, format, pixel_type) =
+                types::render_texture_format(params.format, &self.capabilities);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal_format as GLint,
+                params.dimensions.x as GLsizei,
+                params.dimensions.y as GLsizei,
+                0,
+                format,
+                pixel_type,
+                ::std::ptr::null(),
+            );
+
+            check()?;
+            id
+        } else {
+            Self::create_renderbuffer(params)?
+        };
+
+        // Samplers could not be multisampled, so we render into a multisampled renderbuffer
+        // and resolve it into the texture later.
+        let msaa = if params.sampler && params.samples > 1 {
+            Some(Self::create_renderbuffer(params)?)
+        } else {
+            None
+        };
+
+        self.render_textures.create(
+            handle,
+            GLRenderTextureData {
+                handle,
+                id,
+                msaa,
+                params,
+            },
+        );
+
+        Ok(())
+    }
+
+    unsafe fn delete_render_texture(&mut self, handle: RenderTextureHandle) -> Result<()> {
+        let rt = self
+            .render_textures
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if rt.params.sampler {
+            for v in self.state.binded_textures.iter_mut() {
+                if *v == Some(Sampler::RenderTexture(handle)) {
+                    *v = None;
+                }
+            }
+            self.state
+                .texture_samplers
+                .remove(&Sampler::RenderTexture(handle));
+
+            gl::DeleteTextures(1, &rt.id);
+        } else {
+            gl::DeleteRenderbuffers(1, &rt.id);
+        }
+
+        if let Some(id) = rt.msaa {
+            gl::DeleteRenderbuffers(1, &id);
+        }
+
+        check()
+    }
+
+    unsafe fn create_mesh(
+        &mut self,
+        handle: MeshHandle,
+        params: MeshParams,
+        data: Option<MeshData>,
+    ) -> Result<()> {
+        let vbo = self.create_buffer(
+            gl::ARRAY_BUFFER,
+            params.hint,
+            params.vertex_buffer_len(),
+            data.as_ref().map(|v| v.vptr.as_ref()),
+        )?;
+
+        let ibo = self.create_buffer(
+            gl::ELEMENT_ARRAY_BUFFER,
+            params.hint,
+            params.index_buffer_len(),
+            data.as_ref().map(|v| v.iptr.as_ref()),
+        )?;
+
+        self.meshes.create(
+            handle,
+            GLMeshData {
+                handle,
+                vbo,
+                ibo,
+                params,
+            },
+        );
+
+        Ok(())
+    }
+
+    unsafe fn update_vertex_buffer(
+        &mut self,
+        handle: MeshHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let (vbo, hint, len) = {
+            let mesh = self
+                .meshes
+                .get(handle)
+                .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+            if mesh.params.hint == MeshHint::Immutable {
+                bail!("Trying to update immutable buffer");
+            }
+
+            (mesh.vbo, mesh.params.hint, mesh.params.vertex_buffer_len())
+        };
+
+        Self::update_buffer(gl::ARRAY_BUFFER, vbo, hint, len, offset, data)?;
+        Ok(())
+    }
+
+    unsafe fn update_index_buffer(
+        &mut self,
+        handle: MeshHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let (ibo, hint, len) = {
+            let mesh = self
+                .meshes
+                .get(handle)
+                .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+            if mesh.params.hint == MeshHint::Immutable {
+                bail!("Trying to update immutable buffer");
+            }
+
+            (mesh.ibo, mesh.params.hint, mesh.params.index_buffer_len())
+        };
+
+        Self::update_buffer(gl::ELEMENT_ARRAY_BUFFER, ibo, hint, len, offset, data)?;
+        Ok(())
+    }
+
+    unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()> {
+        let mesh = self
+            .meshes
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        // Removes deprecated `VertexArrayObject`s.
+        self.state.vaos.retain(|&(_, h, _), vao| {
+            if h == mesh.handle {
+                gl::DeleteVertexArrays(1, vao as *mut u32);
+                false
+            } else {
+                true
+            }
+        });
+
+        gl::DeleteBuffers(1, &mesh.vbo);
+        gl::DeleteBuffers(1, &mesh.ibo);
+        check()
+    }
+
+    unsafe fn create_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        params: InstanceBufferParams,
+        data: Option<&[u8]>,
+    ) -> Result<()> {
+        let vbo = self.create_buffer(gl::ARRAY_BUFFER, params.hint, params.buffer_len(), data)?;
+
+        let shadow = if self.capabilities.has_instancing() {
+            None
+        } else {
+            let mut bytes = vec![0; params.buffer_len()];
+            if let Some(data) = data {
+                bytes[..data.len()].copy_from_slice(data);
+            }
+
+            Some(RefCell::new(bytes))
+        };
+
+        self.instance_buffers.create(
+            handle,
+            GLInstanceBufferData {
+                handle,
+                vbo,
+                params,
+                shadow,
+            },
+        );
+
+        Ok(())
+    }
+
+    unsafe fn update_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let (vbo, hint, len) = {
+            let buffer = self
+                .instance_buffers
+                .get(handle)
+                .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+            if buffer.params.hint == MeshHint::Immutable {
+                bail!("Trying to update immutable buffer");
+            }
+
+            (buffer.vbo, buffer.params.hint, buffer.params.buffer_len())
+        };
+
+        Self::update_buffer(gl::ARRAY_BUFFER, vbo, hint, len, offset, data)?;
+
+        if let Some(ref shadow) = self.instance_buffers.get(handle).unwrap().shadow {
+            shadow.borrow_mut()[offset..offset + data.len()].copy_from_slice(data);
+        }
+
+        Ok(())
+    }
+
+    unsafe fn delete_instance_buffer(&mut self, handle: InstanceBufferHandle) -> Result<()> {
+        let buffer = self
+            .instance_buffers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        // Removes deprecated `VertexArrayObject`s.
+        self.state.vaos.retain(|&(_, _, h), vao| {
+            if h == Some(buffer.handle) {
+                gl::DeleteVertexArrays(1, vao as *mut u32);
+                false
+            } else {
+                true
+            }
+        });
+
+        gl::DeleteBuffers(1, &buffer.vbo);
+        check()
+    }
+
+    unsafe fn create_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        params: UniformBufferParams,
+        data: Option<&[u8]>,
+    ) -> Result<()> {
+        if !self.capabilities.has_uniform_buffer() {
+            bail!("Uniform buffer object is not supported by this context.");
+        }
+
+        let ubo = self.create_buffer(gl::UNIFORM_BUFFER, params.hint, params.size, data)?;
+        self.uniform_buffers
+            .create(handle, GLUniformBufferData { ubo, params });
+
+        Ok(())
+    }
+
+    unsafe fn update_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let (ubo, hint, len) = {
+            let buffer = self
+                .uniform_buffers
+                .get(handle)
+                .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+            if buffer.params.hint == MeshHint::Immutable {
+                bail!("Trying to update immutable buffer");
+            }
+
+            (buffer.ubo, buffer.params.hint, buffer.params.size)
+        };
+
+        Self::update_buffer(gl::UNIFORM_BUFFER, ubo, hint, len, offset, data)?;
+        Ok(())
+    }
+
+    unsafe fn delete_uniform_buffer(&mut self, handle: UniformBufferHandle) -> Result<()> {
+        let buffer = self
+            .uniform_buffers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        gl::DeleteBuffers(1, &buffer.ubo);
+        check()
+    }
+
+    unsafe fn create_query(&mut self, handle: QueryHandle, params: QueryParams) -> Result<()> {
+        if !self.capabilities.has_occlusion_query() {
+            bail!("Occlusion query is not supported by this context.");
+        }
+
+        let es = self.capabilities.version >= Version::ES(3, 0);
+        let (target, clamp) = match params.kind {
+            QueryKind::SamplesPassed if es => (gl::ANY_SAMPLES_PASSED, false),
+            QueryKind::SamplesPassed => (gl::SAMPLES_PASSED, false),
+            QueryKind::AnySamplesPassed if self.capabilities.has_any_samples_passed() => {
+                (gl::ANY_SAMPLES_PASSED, false)
+            }
+            QueryKind::AnySamplesPassed => (gl::SAMPLES_PASSED, true),
+        };
+
+        let mut id = 0;
+        gl::GenQueries(1, &mut id);
+        check()?;
+
+        self.queries
+            .create(handle, GLQueryData { id, target, clamp });
+        Ok(())
+    }
+
+    unsafe fn delete_query(&mut self, handle: QueryHandle) -> Result<()> {
+        let query = self
+            .queries
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        self.state.pending_queries.retain(|&v| v != handle);
+        gl::DeleteQueries(1, &query.id);
+        check()
+    }
+
+    unsafe fn create_sampler(
+        &mut self,
+        handle: SamplerHandle,
+        params: SamplerParams,
+    ) -> Result<()> {
+        let mut id = 0;
+        if self.capabilities.has_sampler_objects() {
+            gl::GenSamplers(1, &mut id);
+            Self::bind_sampler_params(
+                &self.capabilities,
+                &params,
+                |k, v| gl::SamplerParameteri(id, k, v),
+                |k, v| gl::SamplerParameterf(id, k, v),
+                true,
+            );
+            check()?;
+        }
+
+        self.samplers.create(handle, GLSamplerData { id, params });
+        Ok(())
+    }
+
+    unsafe fn delete_sampler(&mut self, handle: SamplerHandle) -> Result<()> {
+        let sampler = self
+            .samplers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        // The sampler objects are unbound from texture units when deleted.
+        for v in self.state.binded_samplers.iter_mut() {
+            if *v == Some(handle) {
+                *v = None;
+            }
+        }
+
+        if sampler.id != 0 {
+            gl::DeleteSamplers(1, &sampler.id);
+        }
+
+        check()
+    }
+
+    unsafe fn bind(&mut self, handle: SurfaceHandle, dimensions: Vector2<u32>) -> Result<()> {
+        if self.state.binded_surface == Some(handle) {
+            return Ok(());
+        }
+
+        if let Some(v) = self.state.binded_surface {
+            if let Some(surface) = self.surfaces.get(v) {
+                Self::resolve(&mut self.state, surface)?;
+            }
+        }
+
+        Self::end_debug_groups(&mut self.state)?;
+
+        let window = self
+            .surfaces
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?
+            .params
+            .window;
+
+        let window = match window {
+            Some(v) => Some(self.window_framebuffer(v)?),
+            None => None,
+        };
+
+        let surface = self.surfaces.get(handle).unwrap();
+
+        // Bind frame buffer.
+        let (id, dimensions) = match window {
+            Some(v) => (v.id, v.dimensions),
+            None => (
+                surface.id.unwrap_or_else(|| self.framebuffer()),
+                surface.dimensions.unwrap_or(dimensions),
+            ),
+        };
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+        self.state.state_changes += 1;
+
+        // The sRGB color attachments are always encoded like OpenGL ES, while the window
+        // framebuffer is encoded only if the surface asks for it.
+        if self.capabilities.has_srgb_write_control() {
+            if surface.id.is_some() || surface.params.srgb {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            } else {
+                gl::Disable(gl::FRAMEBUFFER_SRGB);
+            }
+        }
+
+        if let Some(name) = self.surface_names.get(&handle) {
+            if self.capabilities.has_debug_labels() {
+                Self::begin_debug_group(name)?;
+                self.state.surface_debug_group = true;
+            }
+        }
+
+        if self.capabilities.has_timer_query() {
+            Self::begin_timer(&mut self.state, handle)?;
+        }
+
+        let wireframe = surface.params.wireframe;
+        self.set_polygon_mode(wireframe)?;
+        let surface = self.surfaces.get(handle).unwrap();
+
+        // Reset the viewport and scissor box, the clears are restricted to the viewport
+        // rectangle of surface too.
+        let (vp, scissor) = surface.params.viewport(dimensions);
+        Self::set_viewport(&mut self.state, vp)?;
+        Self::set_scissor(&mut self.state, scissor)?;
+
+        if !self.state.cleared_surfaces.contains(&handle) {
+            // Sets depth write enable to make sure that we can clear depth buffer properly.
+            if surface.params.clear_depth.is_some() {
+                self.state.binded_shader = None;
+                Self::set_depth_test(&mut self.state, true, Comparison::Always)?;
+            }
+
+            // The stencil buffer is cleared with the write mask of stencil.
+            if surface.params.clear_stencil.is_some() {
+                self.state.binded_shader = None;
+                Self::set_stencil(&mut self.state, None)?;
+            }
+
+            // Clears frame buffer.
+            Self::clear(
+                surface.params.clear_color,
+                surface.params.clear_depth,
+                surface.params.clear_stencil,
+            )?;
+
+            self.state.cleared_surfaces.insert(handle);
+        }
+
+        self.state.binded_surface = Some(handle);
+        Ok(())
+    }
+
+    unsafe fn update_surface_scissor(&mut self, scissor: SurfaceScissor) -> Result<()> {
+        Self::set_scissor(&mut self.state, scissor)
+    }
+
+    unsafe fn update_surface_viewport(&mut self, vp: SurfaceViewport) -> Result<()> {
+        Self::set_viewport(&mut self.state, vp)
+    }
+
+    unsafe fn begin_query(&mut self, handle: QueryHandle) -> Result<()> {
+        let query = self
+            .queries
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        // The result of an active query could not be polled.
+        self.state.pending_queries.retain(|&v| v != handle);
+        gl::BeginQuery(query.target, query.id);
+        check()
+    }
+
+    unsafe fn end_query(&mut self, handle: QueryHandle) -> Result<()> {
+        let query = self
+            .queries
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        gl::EndQuery(query.target);
+        check()?;
+
+        self.state.pending_queries.push(handle);
+        Ok(())
+    }
+
+    unsafe fn set_debug_name(&mut self, object: VideoObject, name: &str) -> Result<()> {
+        if let VideoObject::Surface(handle) = object {
+            self.surface_names.insert(handle, name.to_owned());
+        }
+
+        if !self.capabilities.has_debug_labels() {
+            return Ok(());
+        }
+
+        let label = |identifier: GLenum, id: GLuint| {
+            let len = name.len() as GLsizei;
+            gl::ObjectLabel(identifier, id, len, name.as_ptr() as *const GLchar);
+        };
+
+        match object {
+            VideoObject::Surface(handle) => {
+                let surface = self
+                    .surfaces
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                if let Some(id) = surface.id {
+                    label(gl::FRAMEBUFFER, id);
+                }
+            }
+
+            VideoObject::Shader(handle) => {
+                let shader = self
+                    .shaders
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                label(gl::PROGRAM, shader.id);
+            }
+
+            VideoObject::UniformBuffer(handle) => {
+                let buffer = self
+                    .uniform_buffers
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                label(gl::BUFFER, buffer.ubo);
+            }
+
+            // The query objects are not created until they are begun.
+            VideoObject::Query(_) => {}
+
+            VideoObject::Mesh(handle) => {
+                let mesh = self
+                    .meshes
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                label(gl::BUFFER, mesh.vbo);
+                label(gl::BUFFER, mesh.ibo);
+            }
+
+            VideoObject::InstanceBuffer(handle) => {
+                let buffer = self
+                    .instance_buffers
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                label(gl::BUFFER, buffer.vbo);
+            }
+
+            VideoObject::Texture(handle) => {
+                let texture = self
+                    .textures
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                label(gl::TEXTURE, texture.id);
+            }
+
+            VideoObject::TextureCube(handle) => {
+                let texture = self
+                    .texture_cubes
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                label(gl::TEXTURE, texture.id);
+            }
+
+            VideoObject::Texture3D(handle) => {
+                let texture = self
+                    .texture_3ds
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                label(gl::TEXTURE, texture.id);
+            }
+
+            VideoObject::RenderTexture(handle) => {
+                let rt = self
+                    .render_textures
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                if rt.params.sampler {
+                    label(gl::TEXTURE, rt.id);
+                } else {
+                    label(gl::RENDERBUFFER, rt.id);
+                }
+
+                if let Some(id) = rt.msaa {
+                    label(gl::RENDERBUFFER, id);
                 }
             }
 
-            gl::DeleteTextures(1, &rt.id);
-        } else {
-            gl::DeleteRenderbuffers(1, &rt.id);
+            VideoObject::Sampler(handle) => {
+                let sampler = self
+                    .samplers
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                if sampler.id != 0 {
+                    label(gl::SAMPLER, sampler.id);
+                }
+            }
         }
 
         check()
     }
 
-    unsafe fn create_mesh(
-        &mut self,
-        handle: MeshHandle,
-        params: MeshParams,
-        data: Option<MeshData>,
-    ) -> Result<()> {
-        let vbo = self.create_buffer(
-            gl::ARRAY_BUFFER,
-            params.hint,
-            params.vertex_buffer_len(),
-            data.as_ref().map(|v| v.vptr.as_ref()),
-        )?;
+    unsafe fn push_debug_group(&mut self, name: &str) -> Result<()> {
+        if self.capabilities.has_debug_labels() {
+            Self::begin_debug_group(name)?;
+            self.state.debug_groups += 1;
+        }
 
-        let ibo = self.create_buffer(
-            gl::ELEMENT_ARRAY_BUFFER,
-            params.hint,
-            params.index_buffer_len(),
-            data.as_ref().map(|v| v.iptr.as_ref()),
-        )?;
+        Ok(())
+    }
 
-        self.meshes.create(
-            handle,
-            GLMeshData {
-                handle,
-                vbo,
-                ibo,
-                params,
-            },
-        );
+    unsafe fn pop_debug_group(&mut self) -> Result<()> {
+        if self.state.debug_groups > 0 {
+            gl::PopDebugGroup();
+            self.state.debug_groups -= 1;
+            check()?;
+        }
 
         Ok(())
     }
 
-    unsafe fn update_vertex_buffer(
-        &mut self,
-        handle: MeshHandle,
-        offset: usize,
-        data: &[u8],
-    ) -> Result<()> {
-        let vbo = {
-            let mesh = self
-                .meshes
-                .get(handle)
-                .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+    unsafe fn query_results(&mut self, results: &mut Vec<(QueryHandle, u64)>) -> Result<()> {
+        let mut i = 0;
+        while i < self.state.pending_queries.len() {
+            let handle = self.state.pending_queries[i];
+            if let Some(query) = self.queries.get(handle) {
+                let mut available = 0;
+                gl::GetQueryObjectuiv(query.id, gl::QUERY_RESULT_AVAILABLE, &mut available);
+                check()?;
 
-            if mesh.params.hint == MeshHint::Immutable {
-                bail!("Trying to update immutable buffer");
+                if available == 0 {
+                    i += 1;
+                    continue;
+                }
+
+                let mut v = 0;
+                gl::GetQueryObjectuiv(query.id, gl::QUERY_RESULT, &mut v);
+                check()?;
+
+                let v = if query.clamp { v.min(1) } else { v };
+                results.push((handle, u64::from(v)));
             }
 
-            mesh.vbo
-        };
+            self.state.pending_queries.swap_remove(i);
+        }
 
-        Self::update_buffer(gl::ARRAY_BUFFER, vbo, offset, data)?;
         Ok(())
     }
 
-    unsafe fn update_index_buffer(
-        &mut self,
-        handle: MeshHandle,
-        offset: usize,
-        data: &[u8],
-    ) -> Result<()> {
-        let ibo = {
-            let mesh = self
-                .meshes
-                .get(handle)
-                .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+    unsafe fn timer_results(&mut self, results: &mut Vec<(SurfaceHandle, u64)>) -> Result<()> {
+        Self::end_timer(&mut self.state)?;
 
-            if mesh.params.hint == MeshHint::Immutable {
-                bail!("Trying to update immutable buffer");
+        // The timers are finished in the order they are issued.
+        let mut finished = 0;
+        for v in &self.state.pending_timers {
+            let mut available = 0;
+            gl::GetQueryObjectuiv(v.id, gl::QUERY_RESULT_AVAILABLE, &mut available);
+            if available == 0 {
+                break;
             }
 
-            mesh.ibo
-        };
+            finished += 1;
+        }
 
-        Self::update_buffer(gl::ELEMENT_ARRAY_BUFFER, ibo, offset, data)?;
-        Ok(())
-    }
+        check()?;
 
-    unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()> {
-        let mesh = self
-            .meshes
-            .free(handle)
-            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+        let frame = match self.state.pending_timers[..finished].last() {
+            Some(v) => v.frame,
+            None => return Ok(()),
+        };
 
-        // Removes deprecated `VertexArrayObject`s.
-        self.state.vaos.retain(|&(_, h), vao| {
-            if h == mesh.handle {
-                gl::DeleteVertexArrays(1, vao as *mut u32);
-                false
-            } else {
-                true
+        for v in self.state.pending_timers.drain(..finished) {
+            // Only the latest finished frame is reported.
+            if v.frame == frame {
+                let mut ns = 0;
+                gl::GetQueryObjectui64v(v.id, gl::QUERY_RESULT, &mut ns);
+                results.push((v.surface, ns));
             }
-        });
 
-        gl::DeleteBuffers(1, &mesh.vbo);
-        gl::DeleteBuffers(1, &mesh.ibo);
+            self.state.free_timers.push(v.id);
+        }
+
         check()
     }
 
-    unsafe fn bind(&mut self, handle: SurfaceHandle, dimensions: Vector2<u32>) -> Result<()> {
-        if self.state.binded_surface == Some(handle) {
-            return Ok(());
-        }
+    fn take_state_changes(&mut self) -> u32 {
+        std::mem::replace(&mut self.state.state_changes, 0)
+    }
 
-        let surface = self
-            .surfaces
+    unsafe fn read_render_texture(
+        &mut self,
+        handle: RenderTextureHandle,
+        area: Aabb2<u32>,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()> {
+        let rt = *self
+            .render_textures
             .get(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-        // Bind frame buffer.
-        let id = surface.id.unwrap_or(0);
-        let dimensions = surface.dimensions.unwrap_or(dimensions);
-        gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+        if rt.msaa.is_none() && rt.params.samples > 1 {
+            bail!("Can NOT read back multisampled render buffer {:?}.", handle);
+        }
 
-        // Reset the viewport and scissor box.
-        let vp = SurfaceViewport {
-            position: Vector2::new(0, 0),
-            size: dimensions,
-        };
+        // The multisampled attachments of current surface should be resolved first.
+        if let Some(v) = self.state.binded_surface {
+            if let Some(surface) = self.surfaces.get(v) {
+                Self::resolve(&mut self.state, surface)?;
+            }
+        }
 
-        Self::set_viewport(&mut self.state, vp)?;
-        Self::set_scissor(&mut self.state, SurfaceScissor::Disable)?;
+        let mut fbo = 0;
+        gl::GenFramebuffers(1, &mut fbo);
+        assert!(fbo != 0);
 
-        if !self.state.cleared_surfaces.contains(&handle) {
-            // Sets depth write enable to make sure that we can clear depth buffer properly.
-            if surface.params.clear_depth.is_some() {
-                self.state.binded_shader = None;
-                Self::set_depth_test(&mut self.state, true, Comparison::Always)?;
-            }
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        self.state.binded_surface = None;
 
-            // Clears frame buffer.
-            Self::clear(
-                surface.params.clear_color,
-                surface.params.clear_depth,
-                surface.params.clear_stencil,
-            )?;
+        // The floating-point formats are read as 32-bit floats.
+        let rsp = self
+            .update_framebuffer_render_texture(rt.id, rt.params, 0)
+            .and_then(|_| {
+                if rt.params.format.is_float() {
+                    self.read_pixels(area, gl::FLOAT, 16, latch)
+                } else {
+                    self.read_pixels(area, gl::UNSIGNED_BYTE, 4, latch)
+                }
+            });
 
-            self.state.cleared_surfaces.insert(handle);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::DeleteFramebuffers(1, &fbo);
+        rsp
+    }
+
+    unsafe fn read_framebuffer(
+        &mut self,
+        area: Aabb2<u32>,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()> {
+        if let Some(v) = self.state.binded_surface {
+            if let Some(surface) = self.surfaces.get(v) {
+                Self::resolve(&mut self.state, surface)?;
+            }
         }
 
-        self.state.binded_surface = Some(handle);
-        Ok(())
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer());
+        self.state.binded_surface = None;
+        self.read_pixels(area, gl::UNSIGNED_BYTE, 4, latch)
     }
 
-    unsafe fn update_surface_scissor(&mut self, scissor: SurfaceScissor) -> Result<()> {
-        Self::set_scissor(&mut self.state, scissor)
-    }
+    unsafe fn readback_results(&mut self) -> Result<()> {
+        let mut i = 0;
+        while i < self.state.pending_readbacks.len() {
+            let status = gl::ClientWaitSync(self.state.pending_readbacks[i].fence, 0, 0);
+            if status == gl::TIMEOUT_EXPIRED {
+                i += 1;
+                continue;
+            }
 
-    unsafe fn update_surface_viewport(&mut self, vp: SurfaceViewport) -> Result<()> {
-        Self::set_viewport(&mut self.state, vp)
+            let v = self.state.pending_readbacks.swap_remove(i);
+            gl::DeleteSync(v.fence);
+
+            let rsp = if status == gl::WAIT_FAILED {
+                Err(format_err!("[GL] Failed to wait for the readback."))
+            } else {
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, v.pbo);
+                let ptr =
+                    gl::MapBufferRange(gl::PIXEL_PACK_BUFFER, 0, v.len as isize, gl::MAP_READ_BIT);
+
+                let rsp = if ptr.is_null() {
+                    Err(format_err!(
+                        "[GL] Failed to map the pixel buffer of readback."
+                    ))
+                } else {
+                    let bytes = ::std::slice::from_raw_parts(ptr as *const u8, v.len);
+                    let bytes = bytes.to_vec().into_boxed_slice();
+                    gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+                    Ok(bytes)
+                };
+
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+                rsp
+            };
+
+            gl::DeleteBuffers(1, &v.pbo);
+            v.latch.set(rsp);
+            check()?;
+        }
+
+        Ok(())
     }
 
     unsafe fn draw(
@@ -767,7 +2219,9 @@ impl Visitor for GLVisitor {
         shader: ShaderHandle,
         mesh: MeshHandle,
         mesh_index: MeshIndex,
+        instances: Option<(InstanceBufferHandle, u32)>,
         uniforms: &[UniformVar],
+        arena: &FrameArena,
     ) -> Result<u32> {
         // Bind program and associated uniforms and textures.
         let shader = self
@@ -790,58 +2244,94 @@ impl Visitor for GLVisitor {
                     );
                 }
 
+                if let UniformVariable::UniformBuffer(handle) = variable {
+                    let binding = *shader
+                        .blocks
+                        .get(&field)
+                        .ok_or_else(|| format_err!("Undefined uniform block {:?}.", field))?;
+                    let ubo = self.uniform_buffers.get(handle).map(|v| v.ubo).unwrap_or(0);
+                    gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, ubo);
+                    check()?;
+                    continue;
+                }
+
                 let location = shader.hash_uniform_location(field).unwrap();
-                match variable {
-                    UniformVariable::Texture(handle) => {
-                        let v = UniformVariable::I32(index as i32);
-                        Self::bind_uniform_variable(location, &v)?;
-
-                        if let Some(texture) = self.textures.get(handle) {
-                            Self::bind_texture(
-                                &mut self.state,
-                                Some(Sampler::Texture(handle)),
-                                index,
-                                texture.id,
-                            )?;
-                        } else {
-                            Self::bind_texture(&mut self.state, None, index, 0)?;
+                let (texture, id, sampler) = match variable {
+                    UniformVariable::Texture(handle, sampler) => {
+                        let pending = self.pending_textures.contains(&handle)
+                            && self.textures.get(handle).is_none();
+                        let handle = match self.texture_placeholder {
+                            Some(v) if pending => v,
+                            _ => handle,
+                        };
+
+                        match self.textures.get(handle) {
+                            Some(v) => (Some(Sampler::Texture(handle)), v.id, sampler),
+                            None => (None, 0, None),
                         }
-
-                        index += 1;
                     }
-                    UniformVariable::RenderTexture(handle) => {
-                        let v = UniformVariable::I32(index as i32);
-                        Self::bind_uniform_variable(location, &v)?;
-
-                        if let Some(texture) = self.render_textures.get(handle) {
-                            if !texture.params.sampler {
+                    UniformVariable::TextureCube(handle, sampler) => {
+                        match self.texture_cubes.get(handle) {
+                            Some(v) => (Some(Sampler::TextureCube(handle)), v.id, sampler),
+                            None => (None, 0, None),
+                        }
+                    }
+                    UniformVariable::Texture3D(handle, sampler) => {
+                        match self.texture_3ds.get(handle) {
+                            Some(v) => (Some(Sampler::Texture3D(handle)), v.id, sampler),
+                            None => (None, 0, None),
+                        }
+                    }
+                    UniformVariable::RenderTexture(handle, sampler) => {
+                        match self.render_textures.get(handle) {
+                            Some(v) if !v.params.sampler => {
                                 bail!("The render buffer does not have a sampler.");
                             }
-
-                            Self::bind_texture(
-                                &mut self.state,
-                                Some(Sampler::RenderTexture(handle)),
-                                index,
-                                texture.id,
-                            )?;
-                        } else {
-                            Self::bind_texture(&mut self.state, None, index, 0)?;
+                            Some(v) => (Some(Sampler::RenderTexture(handle)), v.id, sampler),
+                            None => (None, 0, None),
                         }
-
-                        index += 1;
+                    }
+                    UniformVariable::Matrix4fArray(array) => {
+                        let matrices = arena.as_slice(array.ptr);
+                        let len = matrices.len() as GLsizei;
+                        let ptr = matrices.as_ptr() as *const GLfloat;
+                        gl::UniformMatrix4fv(location, len, gl::FALSE, ptr);
+                        check()?;
+                        continue;
                     }
                     _ => {
                         Self::bind_uniform_variable(location, &variable)?;
+                        continue;
                     }
-                }
+                };
+
+                let v = UniformVariable::I32(index as i32);
+                Self::bind_uniform_variable(location, &v)?;
+                Self::bind_texture(&mut self.state, texture, index, id)?;
+
+                let samplers = (&self.capabilities, &self.samplers);
+                Self::bind_sampler(&mut self.state, samplers, index, texture, sampler)?;
+                index += 1;
             } else {
                 bail!("Undefined uniform field {:?}.", field);
             }
         }
 
+        let instances = match instances {
+            Some((handle, num)) => {
+                let buffer = self
+                    .instance_buffers
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                Some((buffer, num))
+            }
+            None => None,
+        };
+
         if let Some(mesh) = self.meshes.get(mesh) {
             // Bind vertex buffer and vertex array object.
-            Self::bind_mesh(&mut self.state, &shader, &mesh)?;
+            Self::bind_mesh(&mut self.state, &shader, &mesh, instances.map(|v| v.0))?;
 
             let (from, len) = match mesh_index {
                 MeshIndex::Ptr(from, len) => {
@@ -870,31 +2360,123 @@ impl Visitor for GLVisitor {
                 MeshIndex::All => (0, mesh.params.num_idxes),
             };
 
-            gl::DrawElements(
-                mesh.params.primitive.into(),
-                len as i32,
-                mesh.params.index_format.into(),
-                from as *const u32 as *const ::std::os::raw::c_void,
-            );
+            if let Some((buffer, num)) = instances {
+                if let Some(ref shadow) = buffer.shadow {
+                    // Emulates the instanced draw by drawing instances one by one, with the
+                    // per-instance attributes as constant vertex attributes.
+                    let shadow = shadow.borrow();
+                    for i in 0..num {
+                        Self::bind_instance(&shader, buffer, &shadow, i)?;
+                        gl::DrawElements(
+                            mesh.params.primitive.into(),
+                            len as i32,
+                            mesh.params.index_format.into(),
+                            from as *const u32 as *const ::std::os::raw::c_void,
+                        );
+                    }
 
-            check()?;
-            Ok(mesh.params.primitive.assemble(len as u32))
+                    check()?;
+                    return Ok(mesh.params.primitive.assemble(len as u32) * num);
+                }
+
+                gl::DrawElementsInstanced(
+                    mesh.params.primitive.into(),
+                    len as i32,
+                    mesh.params.index_format.into(),
+                    from as *const u32 as *const ::std::os::raw::c_void,
+                    num as GLsizei,
+                );
+
+                check()?;
+                Ok(mesh.params.primitive.assemble(len as u32) * num)
+            } else {
+                gl::DrawElements(
+                    mesh.params.primitive.into(),
+                    len as i32,
+                    mesh.params.index_format.into(),
+                    from as *const u32 as *const ::std::os::raw::c_void,
+                );
+
+                check()?;
+                Ok(mesh.params.primitive.assemble(len as u32))
+            }
         } else {
             Ok(0)
         }
     }
 
     unsafe fn flush(&mut self) -> Result<()> {
+        if let Some(v) = self.state.binded_surface {
+            if let Some(surface) = self.surfaces.get(v) {
+                Self::resolve(&mut self.state, surface)?;
+            }
+        }
+
+        Self::end_debug_groups(&mut self.state)?;
+
         if self.state.cleared_surfaces.is_empty() {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer());
             Self::clear(Color::black(), None, None)?;
         }
 
         gl::Finish();
-        check()
+        check()?;
+
+        // The drawing of main context must be finished before the shared objects are used
+        // by the contexts of secondary windows.
+        if !self.windows.is_empty() {
+            self.present_windows()?;
+        }
+
+        Ok(())
     }
 }
 
 impl GLVisitor {
+    unsafe fn begin_debug_group(name: &str) -> Result<()> {
+        let len = name.len() as GLsizei;
+        let source = gl::DEBUG_SOURCE_APPLICATION;
+        gl::PushDebugGroup(source, 0, len, name.as_ptr() as *const GLchar);
+        check()
+    }
+
+    /// Ends the debug groups that are still open, and the one of bound surface.
+    unsafe fn end_debug_groups(state: &mut GLMutableState) -> Result<()> {
+        if state.surface_debug_group {
+            state.debug_groups += 1;
+            state.surface_debug_group = false;
+        }
+
+        while state.debug_groups > 0 {
+            gl::PopDebugGroup();
+            state.debug_groups -= 1;
+        }
+
+        check()
+    }
+
+    // Rasterizes the polygons as lines if the surface or the global override asks for it.
+    unsafe fn set_polygon_mode(&mut self, wireframe: bool) -> Result<()> {
+        if !self.capabilities.has_polygon_mode() {
+            return Ok(());
+        }
+
+        let mode = if wireframe || self.wireframe {
+            gl::LINE
+        } else {
+            gl::FILL
+        };
+
+        if self.state.polygon_mode != mode {
+            gl::PolygonMode(gl::FRONT_AND_BACK, mode);
+            self.state.polygon_mode = mode;
+            self.state.state_changes += 1;
+            check()?;
+        }
+
+        Ok(())
+    }
+
     unsafe fn bind_shader(state: &mut GLMutableState, shader: &GLShaderData) -> Result<()> {
         if state.binded_shader == Some(shader.handle) {
             return Ok(());
@@ -908,17 +2490,23 @@ impl GLVisitor {
         Self::set_front_face_order(state, rs.front_face_order)?;
         Self::set_depth_test(state, rs.depth_write, rs.depth_test)?;
         Self::set_depth_write_offset(state, rs.depth_write_offset)?;
-        Self::set_color_blend(state, rs.color_blend)?;
+        Self::set_color_blend(state, rs.color_blend, rs.alpha_blend, rs.blend_attachments)?;
         Self::set_color_write(state, rs.color_write)?;
+        Self::set_stencil(state, rs.stencil)?;
 
         state.binded_shader = Some(shader.handle);
+        state.state_changes += 1;
         Ok(())
     }
 
     unsafe fn bind_uniform_variable(location: GLint, variable: &UniformVariable) -> Result<()> {
         match *variable {
-            UniformVariable::Texture(_) => unreachable!(),
-            UniformVariable::RenderTexture(_) => unreachable!(),
+            UniformVariable::Texture(_, _) => unreachable!(),
+            UniformVariable::TextureCube(_, _) => unreachable!(),
+            UniformVariable::Texture3D(_, _) => unreachable!(),
+            UniformVariable::RenderTexture(_, _) => unreachable!(),
+            UniformVariable::UniformBuffer(_) => unreachable!(),
+            UniformVariable::Matrix4fArray(_) => unreachable!(),
             UniformVariable::I32(v) => gl::Uniform1i(location, v),
             UniformVariable::F32(v) => gl::Uniform1f(location, v),
             UniformVariable::Vector2f(v) => gl::Uniform2f(location, v[0], v[1]),
@@ -956,9 +2544,92 @@ impl GLVisitor {
             state.binded_textures.resize(index + 1, None);
         }
 
-        if state.binded_textures[index] != sampler {
-            state.binded_textures[index] = sampler;
-            gl::BindTexture(gl::TEXTURE_2D, id);
+        if state.binded_textures[index] != sampler {
+            state.binded_textures[index] = sampler;
+            let target = sampler.map(|v| v.target()).unwrap_or(gl::TEXTURE_2D);
+            gl::BindTexture(target, id);
+            state.state_changes += 1;
+        }
+
+        check()
+    }
+
+    /// Binds the sampler object to the texture unit `index`. The states of sampler are
+    /// applied to the texture bound at `index` if sampler objects are not supported.
+    unsafe fn bind_sampler(
+        state: &mut GLMutableState,
+        (caps, samplers): (&Capabilities, &DataVec<GLSamplerData>),
+        index: usize,
+        texture: Option<Sampler>,
+        sampler: Option<SamplerHandle>,
+    ) -> Result<()> {
+        let sampler = sampler.and_then(|v| samplers.get(v).map(|data| (v, *data)));
+
+        if caps.has_sampler_objects() {
+            if state.binded_samplers.len() <= index {
+                state.binded_samplers.resize(index + 1, None);
+            }
+
+            let handle = sampler.map(|v| v.0);
+            if state.binded_samplers[index] != handle {
+                state.binded_samplers[index] = handle;
+                gl::BindSampler(index as GLuint, sampler.map(|v| v.1.id).unwrap_or(0));
+                state.state_changes += 1;
+            }
+        } else if let Some(texture) = texture {
+            // The states of texture itself are restored once it's sampled without sampler.
+            let handle = sampler.map(|v| v.0);
+            if let Some(v) = state.texture_samplers.get_mut(&texture) {
+                if v.1 != handle {
+                    v.1 = handle;
+                    let params = sampler.map(|v| v.1.params).unwrap_or(v.0);
+                    let target = texture.target();
+                    Self::bind_sampler_params(
+                        caps,
+                        &params,
+                        |k, v| gl::TexParameteri(target, k, v),
+                        |k, v| gl::TexParameterf(target, k, v),
+                        target != gl::TEXTURE_2D,
+                    );
+                    state.state_changes += 1;
+                }
+            }
+        }
+
+        check()
+    }
+
+    /// Ends the timer of last surface, and begins a new one for `surface`.
+    unsafe fn begin_timer(state: &mut GLMutableState, surface: SurfaceHandle) -> Result<()> {
+        Self::end_timer(state)?;
+
+        if state.pending_timers.len() >= MAX_PENDING_TIMERS {
+            return Ok(());
+        }
+
+        let id = match state.free_timers.pop() {
+            Some(id) => id,
+            None => {
+                let mut id = 0;
+                gl::GenQueries(1, &mut id);
+                id
+            }
+        };
+
+        gl::BeginQuery(gl::TIME_ELAPSED, id);
+        state.timer = Some(GLTimer {
+            id,
+            surface,
+            frame: state.frame,
+        });
+
+        check()
+    }
+
+    unsafe fn end_timer(state: &mut GLMutableState) -> Result<()> {
+        if let Some(timer) = state.timer.take() {
+            gl::EndQuery(gl::TIME_ELAPSED);
+            state.pending_timers.push(timer);
         }
 
         check()
@@ -968,10 +2639,14 @@ impl GLVisitor {
         state: &mut GLMutableState,
         shader: &GLShaderData,
         mesh: &GLMeshData,
+        instances: Option<&GLInstanceBufferData>,
     ) -> Result<()> {
         assert!(state.binded_shader == Some(shader.handle));
 
-        let k = (shader.handle, mesh.handle);
+        // The per-instance attributes are set before each draw if instancing is emulated.
+        let emulated = instances.map(|v| v.shadow.is_some()).unwrap_or(false);
+
+        let k = (shader.handle, mesh.handle, instances.map(|v| v.handle));
         if state.binded_vao != Some(k) {
             if let Some(vao) = state.vaos.get(&k).cloned() {
                 gl::BindVertexArray(vao);
@@ -980,10 +2655,20 @@ impl GLVisitor {
                 let mut vao = 0;
                 gl::GenVertexArrays(1, &mut vao);
                 gl::BindVertexArray(vao);
-                gl::BindBuffer(gl::ARRAY_BUFFER, mesh.vbo);
 
                 for (name, size, required) in shader.params.attributes.iter() {
-                    if let Some(element) = mesh.params.layout.element(name) {
+                    let divisor = shader.params.attributes.divisor(name);
+                    let (vbo, layout) = if divisor > 0 {
+                        let buffer = instances.ok_or_else(|| {
+                            format_err!("Attribute {:?} requires an instance buffer.", name)
+                        })?;
+
+                        (buffer.vbo, &buffer.params.layout)
+                    } else {
+                        (mesh.vbo, &mesh.params.layout)
+                    };
+
+                    if let Some(element) = layout.element(name) {
                         if element.size < size {
                             bail!(
                                 "Vertex buffer has incompatible attribute `{:?}` [{:?} - {:?}].",
@@ -993,10 +2678,15 @@ impl GLVisitor {
                             );
                         }
 
-                        let offset = mesh.params.layout.offset(name).unwrap();
-                        let stride = mesh.params.layout.stride();
+                        if divisor > 0 && emulated {
+                            continue;
+                        }
+
+                        let offset = layout.offset(name).unwrap();
+                        let stride = layout.stride();
 
                         let location = shader.attribute_location(name.into())?;
+                        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
                         gl::EnableVertexAttribArray(location as GLuint);
                         gl::VertexAttribPointer(
                             location as GLuint,
@@ -1006,6 +2696,10 @@ impl GLVisitor {
                             GLsizei::from(stride),
                             offset as *const u8 as *const ::std::os::raw::c_void,
                         );
+
+                        if divisor > 0 {
+                            gl::VertexAttribDivisor(location as GLuint, divisor);
+                        }
                     } else if required {
                         bail!(
                             "Can't find attribute {:?} description in vertex buffer.",
@@ -1018,12 +2712,71 @@ impl GLVisitor {
                 state.vaos.insert(k, vao);
             }
 
+            // The missing optional attributes are read from the generic values, which are
+            // not a part of the states of vertex array objects.
+            for (name, _, required) in shader.params.attributes.iter() {
+                if required || mesh.params.layout.element(name).is_some() {
+                    continue;
+                }
+
+                let location = shader.attribute_location(name.into())?;
+                if location >= 0 {
+                    gl::VertexAttrib4fv(location as GLuint, name.default_value().as_ptr());
+                }
+            }
+
+            check()?;
             state.binded_vao = Some(k);
+            state.state_changes += 1;
         }
 
         gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo);
         Ok(())
     }
+
+    /// Sets the per-instance attributes of the `index`-th instance as constant vertex
+    /// attributes.
+    unsafe fn bind_instance(
+        shader: &GLShaderData,
+        buffer: &GLInstanceBufferData,
+        bytes: &[u8],
+        index: u32,
+    ) -> Result<()> {
+        let layout = &buffer.params.layout;
+        let stride = layout.stride() as usize;
+
+        for (name, _, _) in shader.params.attributes.iter() {
+            let divisor = shader.params.attributes.divisor(name);
+            if divisor == 0 {
+                continue;
+            }
+
+            if let Some(element) = layout.element(name) {
+                let offset =
+                    (index / divisor) as usize * stride + layout.offset(name).unwrap() as usize;
+                let size = element.format.size() as usize;
+
+                if offset + size * element.size as usize > bytes.len() {
+                    bail!("Trying to draw instances out of bounds.");
+                }
+
+                let mut values = [0.0, 0.0, 0.0, 1.0];
+                for (i, v) in values.iter_mut().enumerate().take(element.size as usize) {
+                    let from = offset + i * size;
+                    *v = types::decode_vertex_component(
+                        element.format,
+                        element.normalized,
+                        &bytes[from..from + size],
+                    );
+                }
+
+                let location = shader.attribute_location(name.into())?;
+                gl::VertexAttrib4fv(location as GLuint, values.as_ptr());
+            }
+        }
+
+        check()
+    }
 }
 
 impl GLVisitor {
@@ -1048,6 +2801,10 @@ impl GLVisitor {
         gl::ColorMask(1, 1, 1, 1);
         state.render_state.color_write = (true, true, true, true);
 
+        gl::Disable(gl::STENCIL_TEST);
+        gl::StencilMask(!0);
+        state.render_state.stencil = None;
+
         gl::Disable(gl::SCISSOR_TEST);
         state.scissor = SurfaceScissor::Disable;
 
@@ -1167,22 +2924,36 @@ impl GLVisitor {
     unsafe fn set_color_blend(
         state: &mut GLMutableState,
         blend: Option<(Equation, BlendFactor, BlendFactor)>,
+        alpha: Option<(Equation, BlendFactor, BlendFactor)>,
+        attachments: [bool; MAX_FRAMEBUFFER_ATTACHMENTS],
     ) -> Result<()> {
         let rs = &mut state.render_state;
 
-        if rs.color_blend != blend {
+        if rs.color_blend != blend || rs.alpha_blend != alpha || rs.blend_attachments != attachments
+        {
             if let Some((equation, src, dst)) = blend {
-                if rs.color_blend == None {
+                if attachments.iter().all(|&v| v) {
                     gl::Enable(gl::BLEND);
+                } else {
+                    for (i, &v) in attachments.iter().enumerate() {
+                        if v {
+                            gl::Enablei(gl::BLEND, i as GLuint);
+                        } else {
+                            gl::Disablei(gl::BLEND, i as GLuint);
+                        }
+                    }
                 }
 
-                gl::BlendFunc(src.into(), dst.into());
-                gl::BlendEquation(equation.into());
+                let (alpha_equation, alpha_src, alpha_dst) = alpha.unwrap_or((equation, src, dst));
+                gl::BlendFuncSeparate(src.into(), dst.into(), alpha_src.into(), alpha_dst.into());
+                gl::BlendEquationSeparate(equation.into(), alpha_equation.into());
             } else if rs.color_blend != None {
                 gl::Disable(gl::BLEND);
             }
 
             rs.color_blend = blend;
+            rs.alpha_blend = alpha;
+            rs.blend_attachments = attachments;
             check()?;
         }
 
@@ -1205,6 +2976,41 @@ impl GLVisitor {
         Ok(())
     }
 
+    /// Enable or disable the stencil test, the write mask is reset to all bits when the
+    /// stencil test is disabled.
+    unsafe fn set_stencil(state: &mut GLMutableState, stencil: Option<StencilState>) -> Result<()> {
+        let rs = &mut state.render_state;
+
+        if rs.stencil != stencil {
+            if let Some(v) = stencil {
+                if rs.stencil.is_none() {
+                    gl::Enable(gl::STENCIL_TEST);
+                }
+
+                for &(face, s) in &[(gl::FRONT, v.front), (gl::BACK, v.back)] {
+                    gl::StencilFuncSeparate(
+                        face,
+                        s.comparison.into(),
+                        GLint::from(v.reference),
+                        GLuint::from(v.read_mask),
+                    );
+
+                    gl::StencilOpSeparate(face, s.fail.into(), s.depth_fail.into(), s.pass.into());
+                }
+
+                gl::StencilMask(GLuint::from(v.write_mask));
+            } else {
+                gl::Disable(gl::STENCIL_TEST);
+                gl::StencilMask(!0);
+            }
+
+            rs.stencil = stencil;
+            check()?;
+        }
+
+        Ok(())
+    }
+
     /// Set the scissor box relative to the top-lef corner of th window, in pixels.
     unsafe fn set_scissor(state: &mut GLMutableState, scissor: SurfaceScissor) -> Result<()> {
         match scissor {
@@ -1279,6 +3085,160 @@ impl GLVisitor {
 }
 
 impl GLVisitor {
+    unsafe fn create_renderbuffer(params: RenderTextureParams) -> Result<GLuint> {
+        let mut id = 0;
+        gl::GenRenderbuffers(1, &mut id);
+        assert!(id != 0);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+
+        let (internal_format, _, _) = params.format.into();
+        if params.samples > 1 {
+            gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                GLsizei::from(params.samples),
+                internal_format,
+                params.dimensions.x as GLsizei,
+                params.dimensions.y as GLsizei,
+            );
+        } else {
+            gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                internal_format,
+                params.dimensions.x as GLsizei,
+                params.dimensions.y as GLsizei,
+            );
+        }
+
+        check()?;
+        Ok(id)
+    }
+
+    unsafe fn create_resolve_framebuffer(
+        &self,
+        colors: &[(usize, GLRenderTextureData)],
+        depth_stencil: Option<GLRenderTextureData>,
+    ) -> Result<GLResolveData> {
+        let mut id = 0;
+        gl::GenFramebuffers(1, &mut id);
+        assert!(id != 0);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+
+        let mut resolve = GLResolveData {
+            id,
+            colors: SmallVec::new(),
+            mask: 0,
+        };
+
+        for &(i, rt) in colors {
+            self.update_framebuffer_render_texture(rt.id, rt.params, i)?;
+            resolve.colors.push(gl::COLOR_ATTACHMENT0 + i as u32);
+        }
+
+        if let Some(rt) = depth_stencil {
+            self.update_framebuffer_render_texture(rt.id, rt.params, 0)?;
+            resolve.mask = if rt.params.format == RenderTextureFormat::Depth24Stencil8 {
+                gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT
+            } else {
+                gl::DEPTH_BUFFER_BIT
+            };
+        }
+
+        if gl::CheckFramebufferStatus(gl::FRAMEBUFFER) != gl::FRAMEBUFFER_COMPLETE {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteFramebuffers(1, &id);
+            bail!("[GL] The resolve framebuffer of multisampled surface is incomplete.");
+        }
+
+        Ok(resolve)
+    }
+
+    /// Copies the pixels in `area` of the bound framebuffer into a pixel buffer object.
+    unsafe fn read_pixels(
+        &mut self,
+        area: Aabb2<u32>,
+        ty: GLenum,
+        size: usize,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()> {
+        let dimensions = area.dim();
+        let len = (dimensions.x * dimensions.y) as usize * size;
+        let (x, y) = (area.min.x as GLint, area.min.y as GLint);
+        let (w, h) = (dimensions.x as GLsizei, dimensions.y as GLsizei);
+
+        if self.capabilities.has_pixel_buffer() {
+            let mut pbo = 0;
+            gl::GenBuffers(1, &mut pbo);
+            assert!(pbo != 0);
+
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pbo);
+            gl::BufferData(
+                gl::PIXEL_PACK_BUFFER,
+                len as isize,
+                ::std::ptr::null(),
+                gl::STREAM_READ,
+            );
+
+            gl::ReadPixels(x, y, w, h, gl::RGBA, ty, ::std::ptr::null_mut());
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+
+            // The latch is only taken over if the pixels are read successfully.
+            if let Err(err) = check() {
+                gl::DeleteBuffers(1, &pbo);
+                return Err(err);
+            }
+
+            let fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            self.state.pending_readbacks.push(GLReadback {
+                pbo,
+                fence,
+                len,
+                latch,
+            });
+        } else {
+            // Blocks until the pixels are copied if pixel buffer objects are not supported.
+            let mut bytes = vec![0u8; len];
+            gl::ReadPixels(x, y, w, h, gl::RGBA, ty, bytes.as_mut_ptr() as *mut _);
+            check()?;
+            latch.set(Ok(bytes.into_boxed_slice()));
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the multisampled attachments of surface into its samplers.
+    unsafe fn resolve(state: &mut GLMutableState, surface: &GLSurfaceData) -> Result<()> {
+        let (id, resolve, dimensions) = match (surface.id, &surface.resolve, surface.dimensions) {
+            (Some(id), Some(resolve), Some(dimensions)) => (id, resolve, dimensions),
+            _ => return Ok(()),
+        };
+
+        // Blitting is affected by the scissor test.
+        Self::set_scissor(state, SurfaceScissor::Disable)?;
+
+        let (w, h) = (dimensions.x as GLint, dimensions.y as GLint);
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, id);
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, resolve.id);
+
+        for &v in &resolve.colors {
+            // The i-th draw buffer must be either `COLOR_ATTACHMENTi` or `NONE`.
+            let index = (v - gl::COLOR_ATTACHMENT0) as usize;
+            let mut buffers: SmallVec<[GLenum; 8]> = SmallVec::from_elem(gl::NONE, index + 1);
+            buffers[index] = v;
+
+            gl::ReadBuffer(v);
+            gl::DrawBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+            gl::BlitFramebuffer(0, 0, w, h, 0, 0, w, h, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+        }
+
+        if resolve.mask != 0 {
+            gl::BlitFramebuffer(0, 0, w, h, 0, 0, w, h, resolve.mask, gl::NEAREST);
+        }
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        state.binded_surface = None;
+        check()
+    }
+
     unsafe fn update_framebuffer_render_texture(
         &self,
         id: GLuint,
@@ -1286,7 +3246,12 @@ impl GLVisitor {
         index: usize,
     ) -> Result<()> {
         match params.format {
-            RenderTextureFormat::RGB8 | RenderTextureFormat::RGBA4 | RenderTextureFormat::RGBA8 => {
+            RenderTextureFormat::RGB8
+            | RenderTextureFormat::RGBA4
+            | RenderTextureFormat::RGBA8
+            | RenderTextureFormat::SRGBA8
+            | RenderTextureFormat::RGBA16F
+            | RenderTextureFormat::RG11B10F => {
                 let location = gl::COLOR_ATTACHMENT0 + index as u32;
 
                 if params.sampler {
@@ -1368,6 +3333,153 @@ impl GLVisitor {
         }
     }
 
+    unsafe fn build_shader(
+        &self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        vs: &str,
+        fs: &str,
+    ) -> Result<GLShaderData> {
+        if params.state.blend_attachments.iter().any(|&v| !v)
+            && !self.capabilities.has_indexed_blend()
+        {
+            bail!("The blending of color attachments could not be disabled individually.");
+        }
+
+        let vs = Self::compile(gl::VERTEX_SHADER, vs)?;
+        let fs = Self::compile(gl::FRAGMENT_SHADER, fs)?;
+        let id = Self::link(&[vs, fs])?;
+
+        gl::DetachShader(id, vs);
+        gl::DeleteShader(vs);
+        gl::DetachShader(id, fs);
+        gl::DeleteShader(fs);
+        check()?;
+
+        // Populates or validates the declared layouts with the active variables.
+        let mut params = params;
+        if let Err(err) = Self::reflect(id).and_then(|v| v.apply(&mut params)) {
+            gl::DeleteProgram(id);
+            return Err(err);
+        }
+
+        let mut shader = GLShaderData {
+            handle,
+            id,
+            params,
+            uniforms: RefCell::new(FastHashMap::default()),
+            attributes: RefCell::new(FastHashMap::default()),
+            blocks: FastHashMap::default(),
+        };
+
+        for (name, _, _) in shader.params.attributes.iter() {
+            let name: &'static str = name.into();
+            let location = shader.attribute_location(name)?;
+            if location == -1 {
+                gl::DeleteProgram(id);
+                bail!("Attribute({:?}) is undefined in shader sources.", name);
+            }
+        }
+
+        for &(ref name, tp) in shader.params.uniforms.iter() {
+            if tp == UniformVariableType::UniformBuffer {
+                if !self.capabilities.has_uniform_buffer() {
+                    gl::DeleteProgram(id);
+                    bail!("Uniform buffer object is not supported by this context.");
+                }
+
+                let binding = shader.blocks.len() as GLuint;
+                if binding >= self.capabilities.max_indexed_uniform_buffer {
+                    gl::DeleteProgram(id);
+                    bail!(
+                        "Uniform block index out of bounds (max {}).",
+                        self.capabilities.max_indexed_uniform_buffer
+                    );
+                }
+
+                let c_name = ::std::ffi::CString::new(name.as_bytes()).unwrap();
+                let index = gl::GetUniformBlockIndex(id, c_name.as_ptr());
+                check()?;
+
+                if index == gl::INVALID_INDEX {
+                    gl::DeleteProgram(id);
+                    bail!("Uniform block({:?}) is undefined in shader sources.", name);
+                }
+
+                gl::UniformBlockBinding(id, index, binding);
+                check()?;
+
+                shader.blocks.insert(name.as_str().into(), binding);
+                continue;
+            }
+
+            let location = shader.uniform_location(name)?;
+            if location == -1 {
+                gl::DeleteProgram(id);
+                bail!("Uniform({:?}) is undefined in shader sources.", name);
+            }
+        }
+
+        Ok(shader)
+    }
+
+    /// Queries the active attributes and uniforms of linked program. The members of
+    /// uniform blocks are skipped since they have no locations.
+    unsafe fn reflect(id: GLuint) -> Result<ShaderReflection> {
+        let mut reflection = ShaderReflection::new();
+
+        let mut count = 0;
+        let mut len = 0;
+        gl::GetProgramiv(id, gl::ACTIVE_ATTRIBUTES, &mut count);
+        gl::GetProgramiv(id, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut len);
+
+        let mut buf = vec![0u8; len.max(1) as usize];
+        for i in 0..count as GLuint {
+            let (mut len, mut size, mut tp) = (0, 0, 0);
+            gl::GetActiveAttrib(
+                id,
+                i,
+                buf.len() as GLsizei,
+                &mut len,
+                &mut size,
+                &mut tp,
+                buf.as_mut_ptr() as *mut GLchar,
+            );
+
+            let name = ::std::str::from_utf8(&buf[..len as usize])?;
+            reflection.add_attribute(name, types::attribute_components(tp));
+        }
+
+        gl::GetProgramiv(id, gl::ACTIVE_UNIFORMS, &mut count);
+        gl::GetProgramiv(id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut len);
+
+        let mut buf = vec![0u8; len.max(1) as usize];
+        for i in 0..count as GLuint {
+            let (mut len, mut size, mut tp) = (0, 0, 0);
+            gl::GetActiveUniform(
+                id,
+                i,
+                buf.len() as GLsizei,
+                &mut len,
+                &mut size,
+                &mut tp,
+                buf.as_mut_ptr() as *mut GLchar,
+            );
+
+            let name = ::std::str::from_utf8(&buf[..len as usize])?;
+            let c_name = ::std::ffi::CString::new(name).unwrap();
+            if gl::GetUniformLocation(id, c_name.as_ptr()) == -1 {
+                continue;
+            }
+
+            let tp = types::uniform_variable_type(tp);
+            reflection.add_uniform(name, tp, size as usize);
+        }
+
+        check()?;
+        Ok(reflection)
+    }
+
     unsafe fn link<'a, T>(shaders: T) -> Result<GLuint>
     where
         T: IntoIterator<Item = &'a GLuint>,
@@ -1424,8 +3536,28 @@ impl GLVisitor {
         Ok(id)
     }
 
-    unsafe fn update_buffer(tp: GLuint, id: GLuint, offset: usize, data: &[u8]) -> Result<()> {
+    unsafe fn update_buffer(
+        tp: GLuint,
+        id: GLuint,
+        hint: MeshHint,
+        len: usize,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let orphan = utils::update_buffer(hint, len, offset, data.len())?;
         gl::BindBuffer(tp, id);
+
+        // Orphans the data store of streaming buffer when it's rewritten from the beginning,
+        // so the driver could allocate a fresh block instead of waiting for the pending draws
+        // which still read from the old one.
+        if orphan {
+            gl::BufferData(tp, len as isize, ::std::ptr::null(), hint.into());
+        }
+
+        if data.is_empty() {
+            return check();
+        }
+
         gl::BufferSubData(
             tp,
             offset as isize,
@@ -1435,14 +3567,96 @@ impl GLVisitor {
         check()
     }
 
+    unsafe fn tex_image_2d(
+        level: usize,
+        dims: (GLsizei, GLsizei),
+        (internal_format, format, pixel_type): (GLenum, GLenum, GLenum),
+        compressed: bool,
+        data: &[u8],
+    ) {
+        if compressed {
+            gl::CompressedTexImage2D(
+                gl::TEXTURE_2D,
+                level as GLint,
+                internal_format,
+                dims.0,
+                dims.1,
+                0,
+                data.len() as GLint,
+                &data[0] as *const u8 as *const ::std::os::raw::c_void,
+            );
+        } else {
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                level as GLint,
+                internal_format as GLint,
+                dims.0,
+                dims.1,
+                0,
+                format,
+                pixel_type,
+                &data[0] as *const u8 as *const ::std::os::raw::c_void,
+            );
+        }
+    }
+
+    unsafe fn bind_sampler_params<F1, F2>(
+        caps: &Capabilities,
+        params: &SamplerParams,
+        parameteri: F1,
+        parameterf: F2,
+        wrap_r: bool,
+    ) where
+        F1: Fn(GLenum, GLint),
+        F2: Fn(GLenum, GLfloat),
+    {
+        parameteri(gl::TEXTURE_WRAP_S, GLenum::from(params.wrap[0]) as GLint);
+        parameteri(gl::TEXTURE_WRAP_T, GLenum::from(params.wrap[1]) as GLint);
+        if wrap_r {
+            parameteri(gl::TEXTURE_WRAP_R, GLenum::from(params.wrap[2]) as GLint);
+        }
+
+        let min_filter = types::min_filter(params.min_filter, params.mip_filter);
+        parameteri(gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+        parameteri(
+            gl::TEXTURE_MAG_FILTER,
+            GLenum::from(params.mag_filter) as GLint,
+        );
+
+        if caps.max_anisotropy > 1 {
+            let anisotropy = params.max_anisotropy.min(caps.max_anisotropy).max(1);
+            parameterf(TEXTURE_MAX_ANISOTROPY, anisotropy as GLfloat);
+        }
+
+        if caps.has_lod_bias() {
+            parameterf(gl::TEXTURE_LOD_BIAS, params.lod_bias);
+        }
+
+        if caps.has_lod_range() {
+            parameterf(gl::TEXTURE_MIN_LOD, params.lod_range[0]);
+            parameterf(gl::TEXTURE_MAX_LOD, params.lod_range[1]);
+        }
+    }
+
     unsafe fn bind_texture_params(
+        state: &mut GLMutableState,
+        texture: Sampler,
+        target: GLenum,
         wrap: TextureWrap,
         filter: TextureFilter,
         levels: u32,
+        capabilities: &Capabilities,
     ) -> Result<()> {
+        let params = SamplerParams::new(wrap, filter, levels > 1);
+        state.texture_samplers.insert(texture, (params, None));
+
         let wrap: GLenum = wrap.into();
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap as GLint);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap as GLint);
+        gl::TexParameteri(target, gl::TEXTURE_WRAP_S, wrap as GLint);
+        gl::TexParameteri(target, gl::TEXTURE_WRAP_T, wrap as GLint);
+
+        if target == gl::TEXTURE_CUBE_MAP || target == gl::TEXTURE_3D {
+            gl::TexParameteri(target, gl::TEXTURE_WRAP_R, wrap as GLint);
+        }
 
         match filter {
             TextureFilter::Nearest => {
@@ -1452,8 +3666,8 @@ impl GLVisitor {
                     gl::NEAREST
                 };
 
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+                gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+                gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
             }
             TextureFilter::Linear => {
                 let min_filter = if levels > 1 {
@@ -1462,14 +3676,15 @@ impl GLVisitor {
                     gl::LINEAR
                 };
 
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
-                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+                gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+                gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
             }
         }
 
-        if levels > 1 {
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, 0);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, (levels - 1) as GLint);
+        // The levels are clamped by default without `TEXTURE_BASE_LEVEL` on OpenGL ES 2.0.
+        if levels > 1 && capabilities.has_texture_base_level() {
+            gl::TexParameteri(target, gl::TEXTURE_BASE_LEVEL, 0);
+            gl::TexParameteri(target, gl::TEXTURE_MAX_LEVEL, (levels - 1) as GLint);
         }
 
         Ok(())
@@ -1,102 +1,511 @@
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::sync::Arc;
+
 use super::super::assets::prelude::*;
+use super::super::capabilities::Capabilities;
+use super::super::command::FrameArena;
+use super::super::{MAX_FRAMEBUFFER_ATTACHMENTS, MAX_UNIFORM_TEXTURE_SLOTS};
+use super::utils::{self, samples, DataVec};
 use super::{UniformVar, Visitor};
 
 use crate::errors::*;
-use crate::math::prelude::{Aabb2, Vector2};
+use crate::math::prelude::{Aabb2, Aabb3, Vector2};
+use crate::res::request::Response;
+use crate::sched::prelude::LockLatch;
+use crate::utils::handle::Handle;
+
+const MAX_SAMPLES: u32 = 16;
 
-pub struct HeadlessVisitor {}
+/// A no-op backend which never touches GPU. It keeps track of the lifetimes and the
+/// parameters of video objects, and validates commands just like the GL backend does,
+/// so logic-only servers and tests could run the same code paths of rendering.
+///
+/// The readbacks of pixels are completed with zeros.
+pub struct HeadlessVisitor {
+    surfaces: DataVec<SurfaceParams>,
+    shaders: DataVec<ShaderParams>,
+    uniform_buffers: DataVec<UniformBufferParams>,
+    queries: DataVec<QueryParams>,
+    samplers: DataVec<SamplerParams>,
+    textures: DataVec<TextureParams>,
+    texture_cubes: DataVec<TextureCubeParams>,
+    texture_3ds: DataVec<Texture3DParams>,
+    render_textures: DataVec<RenderTextureParams>,
+    meshes: DataVec<MeshParams>,
+    instance_buffers: DataVec<InstanceBufferParams>,
+}
 
 impl HeadlessVisitor {
     pub fn new() -> Self {
-        HeadlessVisitor {}
+        HeadlessVisitor {
+            surfaces: DataVec::new(),
+            shaders: DataVec::new(),
+            uniform_buffers: DataVec::new(),
+            queries: DataVec::new(),
+            samplers: DataVec::new(),
+            textures: DataVec::new(),
+            texture_cubes: DataVec::new(),
+            texture_3ds: DataVec::new(),
+            render_textures: DataVec::new(),
+            meshes: DataVec::new(),
+            instance_buffers: DataVec::new(),
+        }
     }
 }
 
+fn create<T, H>(vec: &mut DataVec<T>, handle: H, value: T) -> Result<()>
+where
+    T: Sized + Clone,
+    H: Borrow<Handle> + Debug + Copy,
+{
+    if vec.get(handle).is_some() {
+        bail!("{:?} has been created already.", handle);
+    }
+
+    vec.create(handle, value);
+    Ok(())
+}
+
+fn get<T, H>(vec: &DataVec<T>, handle: H) -> Result<&T>
+where
+    T: Sized + Clone,
+    H: Borrow<Handle> + Debug + Copy,
+{
+    vec.get(handle)
+        .ok_or_else(|| format_err!("{:?} is invalid.", handle))
+}
+
+fn delete<T, H>(vec: &mut DataVec<T>, handle: H) -> Result<()>
+where
+    T: Sized + Clone,
+    H: Borrow<Handle> + Debug + Copy,
+{
+    get(vec, handle)?;
+    vec.free(handle);
+    Ok(())
+}
+
+fn update_buffer(hint: MeshHint, len: usize, offset: usize, data: &[u8]) -> Result<()> {
+    utils::update_buffer(hint, len, offset, data.len())?;
+    Ok(())
+}
+
+/// Completes the readback with blank pixels of `size` bytes.
+fn read_pixels(area: Aabb2<u32>, size: usize, latch: &LockLatch<Response>) {
+    let dims = area.dim();
+    let pixels = vec![0; dims.x as usize * dims.y as usize * size];
+    latch.set(Ok(pixels.into_boxed_slice()));
+}
+
 impl Visitor for HeadlessVisitor {
-    unsafe fn create_surface(&mut self, _: SurfaceHandle, _: SurfaceParams) -> Result<()> {
-        Ok(())
+    unsafe fn create_surface(
+        &mut self,
+        handle: SurfaceHandle,
+        params: SurfaceParams,
+    ) -> Result<()> {
+        let mut dimensions = None;
+        for attachment in &params.colors {
+            if let Some(v) = *attachment {
+                let rt = self
+                    .render_textures
+                    .get(v)
+                    .ok_or_else(|| format_err!("RenderTexture handle {:?} is invalid.", v))?;
+
+                if !rt.format.is_color() {
+                    bail!(
+                        "Incompitable(mismatch format) attachments of SurfaceObject {:?}",
+                        handle
+                    );
+                }
+
+                if dimensions.is_some() && dimensions != Some((rt.dimensions, rt.samples)) {
+                    bail!(
+                        "Incompitable(mismatch dimensions or samples) attachments of SurfaceObject {:?}",
+                        handle
+                    );
+                }
+
+                dimensions = Some((rt.dimensions, rt.samples));
+            }
+        }
+
+        if let Some(v) = params.depth_stencil {
+            let rt = self
+                .render_textures
+                .get(v)
+                .ok_or_else(|| format_err!("RenderTexture handle {:?} is invalid.", v))?;
+
+            if rt.format.is_color() {
+                bail!(
+                    "Incompitable(mismatch format) attachments of SurfaceObject {:?}",
+                    handle
+                );
+            }
+
+            if dimensions.is_some() && dimensions != Some((rt.dimensions, rt.samples)) {
+                bail!(
+                    "Incompitable(mismatch dimensions or samples) attachments of SurfaceObject {:?}",
+                    handle
+                );
+            }
+        }
+
+        create(&mut self.surfaces, handle, params)
     }
 
-    unsafe fn delete_surface(&mut self, _: SurfaceHandle) -> Result<()> {
-        Ok(())
+    unsafe fn delete_surface(&mut self, handle: SurfaceHandle) -> Result<()> {
+        delete(&mut self.surfaces, handle)
     }
 
     unsafe fn create_shader(
         &mut self,
-        _: ShaderHandle,
-        _: ShaderParams,
+        handle: ShaderHandle,
+        params: ShaderParams,
         _: &str,
         _: &str,
     ) -> Result<()> {
-        Ok(())
+        create(&mut self.shaders, handle, params)
     }
 
-    unsafe fn delete_shader(&mut self, _: ShaderHandle) -> Result<()> {
+    unsafe fn update_shader(
+        &mut self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        _: &str,
+        _: &str,
+    ) -> Result<()> {
+        get(&self.shaders, handle)?;
+        self.shaders.create(handle, params);
         Ok(())
     }
 
+    unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()> {
+        delete(&mut self.shaders, handle)
+    }
+
+    unsafe fn create_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        params: UniformBufferParams,
+        _: Option<&[u8]>,
+    ) -> Result<()> {
+        create(&mut self.uniform_buffers, handle, params)
+    }
+
+    unsafe fn update_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let buffer = get(&self.uniform_buffers, handle)?;
+        update_buffer(buffer.hint, buffer.size, offset, data)
+    }
+
+    unsafe fn delete_uniform_buffer(&mut self, handle: UniformBufferHandle) -> Result<()> {
+        delete(&mut self.uniform_buffers, handle)
+    }
+
+    unsafe fn create_query(&mut self, handle: QueryHandle, params: QueryParams) -> Result<()> {
+        create(&mut self.queries, handle, params)
+    }
+
+    unsafe fn delete_query(&mut self, handle: QueryHandle) -> Result<()> {
+        delete(&mut self.queries, handle)
+    }
+
+    unsafe fn create_sampler(
+        &mut self,
+        handle: SamplerHandle,
+        params: SamplerParams,
+    ) -> Result<()> {
+        create(&mut self.samplers, handle, params)
+    }
+
+    unsafe fn delete_sampler(&mut self, handle: SamplerHandle) -> Result<()> {
+        delete(&mut self.samplers, handle)
+    }
+
     unsafe fn create_texture(
         &mut self,
-        _: TextureHandle,
-        _: TextureParams,
+        handle: TextureHandle,
+        params: TextureParams,
         _: Option<TextureData>,
     ) -> Result<()> {
+        create(&mut self.textures, handle, params)
+    }
+
+    unsafe fn update_texture(
+        &mut self,
+        handle: TextureHandle,
+        level: usize,
+        area: Aabb2<u32>,
+        data: &[u8],
+    ) -> Result<()> {
+        let texture = get(&self.textures, handle)?;
+
+        if texture.hint == TextureHint::Immutable {
+            bail!("Trying to update immutable texture.");
+        }
+
+        if texture.format.compressed() {
+            bail!("Trying to update compressed texture.");
+        }
+
+        let dims = texture.mip_dimensions(level);
+        if level >= texture.mip_levels()
+            || data.len() > texture.format.size(area.dim()) as usize
+            || area.min.x >= dims.x
+            || area.min.y >= dims.y
+        {
+            bail!("Trying to update texture data out of bounds.");
+        }
+
         Ok(())
     }
 
-    unsafe fn update_texture(&mut self, _: TextureHandle, _: Aabb2<u32>, _: &[u8]) -> Result<()> {
+    unsafe fn update_texture_mip(
+        &mut self,
+        handle: TextureHandle,
+        level: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let texture = get(&self.textures, handle)?;
+        let dims = texture.mip_dimensions(level);
+
+        if data.is_empty() || data.len() > texture.format.size(dims) as usize {
+            bail!("Trying to update texture data out of bounds.");
+        }
+
         Ok(())
     }
 
-    unsafe fn delete_texture(&mut self, _: TextureHandle) -> Result<()> {
+    unsafe fn generate_mipmaps(&mut self, handle: TextureHandle) -> Result<()> {
+        let texture = get(&self.textures, handle)?;
+
+        if texture.format.compressed() {
+            bail!("Can not generate mipmaps of compressed texture.");
+        }
+
         Ok(())
     }
 
-    unsafe fn create_render_texture(
+    unsafe fn set_texture_placeholder(&mut self, _: Option<TextureHandle>) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn set_texture_pending(&mut self, _: TextureHandle, _: bool) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn set_wireframe(&mut self, _: bool) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()> {
+        delete(&mut self.textures, handle)
+    }
+
+    unsafe fn create_texture_cube(
         &mut self,
-        _: RenderTextureHandle,
-        _: RenderTextureParams,
+        handle: TextureCubeHandle,
+        params: TextureCubeParams,
+        _: Option<TextureCubeData>,
     ) -> Result<()> {
-        Ok(())
+        create(&mut self.texture_cubes, handle, params)
+    }
+
+    unsafe fn delete_texture_cube(&mut self, handle: TextureCubeHandle) -> Result<()> {
+        delete(&mut self.texture_cubes, handle)
     }
 
-    unsafe fn delete_render_texture(&mut self, _: RenderTextureHandle) -> Result<()> {
+    unsafe fn create_texture_3d(
+        &mut self,
+        handle: Texture3DHandle,
+        params: Texture3DParams,
+        _: Option<Texture3DData>,
+    ) -> Result<()> {
+        create(&mut self.texture_3ds, handle, params)
+    }
+
+    unsafe fn update_texture_3d(
+        &mut self,
+        handle: Texture3DHandle,
+        area: Aabb3<u32>,
+        data: &[u8],
+    ) -> Result<()> {
+        let texture = get(&self.texture_3ds, handle)?;
+
+        if texture.hint == TextureHint::Immutable {
+            bail!("Trying to update immutable texture.");
+        }
+
+        if data.len() > texture.size(area.dim()) as usize
+            || area.min.x >= texture.dimensions.x
+            || area.min.y >= texture.dimensions.y
+            || area.min.z >= texture.dimensions.z
+        {
+            bail!("Trying to update texture data out of bounds.");
+        }
+
         Ok(())
     }
 
+    unsafe fn delete_texture_3d(&mut self, handle: Texture3DHandle) -> Result<()> {
+        delete(&mut self.texture_3ds, handle)
+    }
+
+    unsafe fn create_render_texture(
+        &mut self,
+        handle: RenderTextureHandle,
+        mut params: RenderTextureParams,
+    ) -> Result<()> {
+        params.samples = samples(params.samples, MAX_SAMPLES);
+        create(&mut self.render_textures, handle, params)
+    }
+
+    unsafe fn delete_render_texture(&mut self, handle: RenderTextureHandle) -> Result<()> {
+        delete(&mut self.render_textures, handle)
+    }
+
     unsafe fn create_mesh(
         &mut self,
-        _: MeshHandle,
-        _: MeshParams,
+        handle: MeshHandle,
+        params: MeshParams,
         _: Option<MeshData>,
     ) -> Result<()> {
-        Ok(())
+        create(&mut self.meshes, handle, params)
     }
 
-    unsafe fn update_vertex_buffer(&mut self, _: MeshHandle, _: usize, _: &[u8]) -> Result<()> {
-        Ok(())
+    unsafe fn update_vertex_buffer(
+        &mut self,
+        handle: MeshHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let mesh = get(&self.meshes, handle)?;
+        update_buffer(mesh.hint, mesh.vertex_buffer_len(), offset, data)
     }
 
-    unsafe fn update_index_buffer(&mut self, _: MeshHandle, _: usize, _: &[u8]) -> Result<()> {
-        Ok(())
+    unsafe fn update_index_buffer(
+        &mut self,
+        handle: MeshHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let mesh = get(&self.meshes, handle)?;
+        update_buffer(mesh.hint, mesh.index_buffer_len(), offset, data)
     }
 
-    unsafe fn delete_mesh(&mut self, _: MeshHandle) -> Result<()> {
-        Ok(())
+    unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()> {
+        delete(&mut self.meshes, handle)
+    }
+
+    unsafe fn create_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        params: InstanceBufferParams,
+        _: Option<&[u8]>,
+    ) -> Result<()> {
+        create(&mut self.instance_buffers, handle, params)
     }
 
-    unsafe fn bind(&mut self, _: SurfaceHandle, _: Vector2<u32>) -> Result<()> {
+    unsafe fn update_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let buffer = get(&self.instance_buffers, handle)?;
+        update_buffer(buffer.hint, buffer.buffer_len(), offset, data)
+    }
+
+    unsafe fn delete_instance_buffer(&mut self, handle: InstanceBufferHandle) -> Result<()> {
+        delete(&mut self.instance_buffers, handle)
+    }
+
+    unsafe fn bind(&mut self, handle: SurfaceHandle, _: Vector2<u32>) -> Result<()> {
+        get(&self.surfaces, handle)?;
         Ok(())
     }
 
     unsafe fn draw(
         &mut self,
-        _: ShaderHandle,
-        _: MeshHandle,
-        _: MeshIndex,
-        _: &[UniformVar],
+        shader: ShaderHandle,
+        mesh: MeshHandle,
+        mesh_index: MeshIndex,
+        instances: Option<(InstanceBufferHandle, u32)>,
+        uniforms: &[UniformVar],
+        _: &FrameArena,
     ) -> Result<u32> {
-        Ok(0)
+        let shader = get(&self.shaders, shader)?;
+
+        // The layout left empty is populated with reflection by the GL backend, which is
+        // not available here. So any uniforms are accepted.
+        let uniforms = if shader.uniforms.is_empty() {
+            &[]
+        } else {
+            uniforms
+        };
+
+        for &(field, variable) in uniforms {
+            if let Some(tp) = shader.uniforms.variable_type(field) {
+                if tp != variable.variable_type() {
+                    let name = shader.uniforms.variable_name(field).unwrap();
+                    bail!(
+                        "The uniform {} needs a {:?} instead of {:?}.",
+                        name,
+                        tp,
+                        variable.variable_type(),
+                    );
+                }
+            } else {
+                bail!("Undefined uniform field {:?}.", field);
+            }
+        }
+
+        let num = match instances {
+            Some((handle, num)) => {
+                get(&self.instance_buffers, handle)?;
+                num
+            }
+            None => 1,
+        };
+
+        // Likes the GL backend, draws of deleted meshes are skipped silently.
+        let mesh = match self.meshes.get(mesh) {
+            Some(v) => v,
+            None => return Ok(0),
+        };
+
+        let len = match mesh_index {
+            MeshIndex::Ptr(from, len) => {
+                if (from + len) > mesh.num_idxes {
+                    bail!("MeshIndex is out of bounds");
+                }
+
+                len
+            }
+            MeshIndex::SubMesh(index) => {
+                let num = mesh.sub_mesh_offsets.len();
+                let from = mesh
+                    .sub_mesh_offsets
+                    .get(index)
+                    .ok_or_else(|| format_err!("MeshIndex is out of bounds"))?;
+
+                let to = if index == (num - 1) {
+                    mesh.num_idxes
+                } else {
+                    mesh.sub_mesh_offsets[index + 1]
+                };
+
+                to - from
+            }
+            MeshIndex::All => mesh.num_idxes,
+        };
+
+        Ok(mesh.primitive.assemble(len as u32) * num)
     }
 
     unsafe fn update_surface_scissor(&mut self, _: SurfaceScissor) -> Result<()> {
@@ -107,6 +516,79 @@ impl Visitor for HeadlessVisitor {
         Ok(())
     }
 
+    unsafe fn begin_query(&mut self, handle: QueryHandle) -> Result<()> {
+        get(&self.queries, handle)?;
+        Ok(())
+    }
+
+    unsafe fn end_query(&mut self, handle: QueryHandle) -> Result<()> {
+        get(&self.queries, handle)?;
+        Ok(())
+    }
+
+    unsafe fn set_debug_name(&mut self, object: VideoObject, _: &str) -> Result<()> {
+        match object {
+            VideoObject::Surface(v) => get(&self.surfaces, v).map(|_| ()),
+            VideoObject::Shader(v) => get(&self.shaders, v).map(|_| ()),
+            VideoObject::UniformBuffer(v) => get(&self.uniform_buffers, v).map(|_| ()),
+            VideoObject::Query(v) => get(&self.queries, v).map(|_| ()),
+            VideoObject::Mesh(v) => get(&self.meshes, v).map(|_| ()),
+            VideoObject::InstanceBuffer(v) => get(&self.instance_buffers, v).map(|_| ()),
+            VideoObject::Texture(v) => get(&self.textures, v).map(|_| ()),
+            VideoObject::TextureCube(v) => get(&self.texture_cubes, v).map(|_| ()),
+            VideoObject::Texture3D(v) => get(&self.texture_3ds, v).map(|_| ()),
+            VideoObject::RenderTexture(v) => get(&self.render_textures, v).map(|_| ()),
+            VideoObject::Sampler(v) => get(&self.samplers, v).map(|_| ()),
+        }
+    }
+
+    unsafe fn push_debug_group(&mut self, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn pop_debug_group(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn query_results(&mut self, _: &mut Vec<(QueryHandle, u64)>) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn timer_results(&mut self, _: &mut Vec<(SurfaceHandle, u64)>) -> Result<()> {
+        Ok(())
+    }
+
+    fn take_state_changes(&mut self) -> u32 {
+        0
+    }
+
+    unsafe fn read_render_texture(
+        &mut self,
+        handle: RenderTextureHandle,
+        area: Aabb2<u32>,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()> {
+        let rt = get(&self.render_textures, handle)?;
+
+        // The floating-point formats are read as 32-bit floats.
+        let size = if rt.format.is_float() { 16 } else { 4 };
+        read_pixels(area, size, &latch);
+        Ok(())
+    }
+
+    unsafe fn read_framebuffer(
+        &mut self,
+        area: Aabb2<u32>,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()> {
+        read_pixels(area, 4, &latch);
+        Ok(())
+    }
+
+    unsafe fn readback_results(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     unsafe fn flush(&mut self) -> Result<()> {
         Ok(())
     }
@@ -114,4 +596,174 @@ impl Visitor for HeadlessVisitor {
     unsafe fn advance(&mut self) -> Result<()> {
         Ok(())
     }
+
+    fn is_texture_format_supported(&self, _: TextureFormat) -> bool {
+        true
+    }
+
+    fn is_render_texture_format_supported(&self, _: RenderTextureFormat) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            api: "Null".into(),
+            renderer: "Null".into(),
+            instancing: true,
+            uniform_buffer: true,
+            occlusion_query: true,
+            timer_query: false,
+            srgb_texture: true,
+            srgb_framebuffer: true,
+            srgb_backbuffer: false,
+            texture_3d: true,
+            texture_base_level: true,
+            wireframe: true,
+            sampler_objects: true,
+            max_anisotropy: 16,
+            max_color_attachments: MAX_FRAMEBUFFER_ATTACHMENTS as u32,
+            max_samples: MAX_SAMPLES,
+            max_texture_units: MAX_UNIFORM_TEXTURE_SLOTS as u32,
+            max_vertex_texture_units: MAX_UNIFORM_TEXTURE_SLOTS as u32,
+            max_vertex_uniform_vectors: 1024,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::handle::HandleLike;
+
+    #[test]
+    fn lifetimes() {
+        let mut visitor = HeadlessVisitor::new();
+        let shader = ShaderHandle::new(1, 1);
+        let mesh = MeshHandle::new(1, 1);
+        let arena = FrameArena::default();
+
+        let mut params = MeshParams::default();
+        params.hint = MeshHint::Dynamic;
+        params.num_verts = 3;
+        params.num_idxes = 6;
+
+        unsafe {
+            visitor
+                .create_shader(shader, ShaderParams::default(), "", "")
+                .unwrap();
+            visitor.create_mesh(mesh, params, None).unwrap();
+            assert!(visitor
+                .create_mesh(mesh, MeshParams::default(), None)
+                .is_err());
+
+            assert!(visitor.update_index_buffer(mesh, 0, &[0; 12]).is_ok());
+            assert!(visitor.update_index_buffer(mesh, 2, &[0; 12]).is_err());
+
+            let tris = visitor.draw(shader, mesh, MeshIndex::All, None, &[], &arena);
+            assert_eq!(tris.unwrap(), 2);
+
+            // The uniforms of reflected layouts are unknown.
+            let uniforms = [("u_Color".into(), UniformVariable::F32(1.0))];
+            let tris = visitor.draw(shader, mesh, MeshIndex::All, None, &uniforms, &arena);
+            assert_eq!(tris.unwrap(), 2);
+
+            let instances = Some((InstanceBufferHandle::new(1, 1), 4));
+            assert!(visitor
+                .draw(shader, mesh, MeshIndex::All, instances, &[], &arena)
+                .is_err());
+            assert!(visitor
+                .draw(shader, mesh, MeshIndex::Ptr(3, 6), None, &[], &arena)
+                .is_err());
+
+            visitor.delete_mesh(mesh).unwrap();
+            assert!(visitor.delete_mesh(mesh).is_err());
+            assert!(visitor.update_vertex_buffer(mesh, 0, &[0; 4]).is_err());
+            assert!(visitor.delete_surface(SurfaceHandle::new(1, 1)).is_err());
+        }
+    }
+
+    #[test]
+    fn multisampled_surfaces() {
+        let mut visitor = HeadlessVisitor::new();
+        let color = RenderTextureHandle::new(1, 1);
+        let depth = RenderTextureHandle::new(2, 1);
+
+        let mut params = RenderTextureParams::default();
+        params.dimensions = Vector2::new(16, 16);
+        params.samples = 4;
+
+        unsafe {
+            visitor.create_render_texture(color, params).unwrap();
+            params.format = RenderTextureFormat::Depth24;
+            params.samples = 64;
+            visitor.create_render_texture(depth, params).unwrap();
+            assert_eq!(visitor.render_textures.get(depth).unwrap().samples, 16);
+
+            let mut surface = SurfaceParams::default();
+            surface.set_attachments(&[color], Some(depth)).unwrap();
+            assert!(visitor
+                .create_surface(SurfaceHandle::new(1, 1), surface)
+                .is_err());
+
+            params.samples = 4;
+            visitor.delete_render_texture(depth).unwrap();
+            visitor.create_render_texture(depth, params).unwrap();
+            assert!(visitor
+                .create_surface(SurfaceHandle::new(1, 1), surface)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn debug_names() {
+        let mut visitor = HeadlessVisitor::new();
+        let query = QueryHandle::new(1, 1);
+        let mesh = MeshHandle::new(1, 1);
+
+        unsafe {
+            visitor.create_query(query, QueryParams::default()).unwrap();
+            assert!(visitor.set_debug_name(query.into(), "occluder").is_ok());
+            assert!(visitor.set_debug_name(mesh.into(), "terrain").is_err());
+
+            assert!(visitor.push_debug_group("shadows").is_ok());
+            assert!(visitor.pop_debug_group().is_ok());
+        }
+    }
+
+    #[test]
+    fn queries() {
+        let mut visitor = HeadlessVisitor::new();
+        let query = QueryHandle::new(1, 1);
+
+        unsafe {
+            assert!(visitor.begin_query(query).is_err());
+
+            visitor.create_query(query, QueryParams::default()).unwrap();
+            assert!(visitor.create_query(query, QueryParams::default()).is_err());
+            assert!(visitor.begin_query(query).is_ok());
+            assert!(visitor.end_query(query).is_ok());
+
+            visitor.delete_query(query).unwrap();
+            assert!(visitor.end_query(query).is_err());
+            assert!(visitor.delete_query(query).is_err());
+        }
+    }
+
+    #[test]
+    fn failed_readbacks() {
+        use super::super::frame::{Command, Frame};
+        use crate::sched::prelude::LatchProbe;
+
+        let mut visitor = HeadlessVisitor::new();
+        let latch = Arc::new(LockLatch::new());
+        let area = Aabb2::new([0, 0].into(), [4, 4].into());
+
+        let mut frame = Frame::with_capacity(0);
+        let cmd = (RenderTextureHandle::new(1, 1), area, latch.clone());
+        frame.cmds.push(Command::ReadRenderTexture(Box::new(cmd)));
+
+        assert!(frame.dispatch(&mut visitor, Vector2::new(4, 4)).is_err());
+        assert!(latch.is_set());
+        assert!(latch.take().is_err());
+    }
 }
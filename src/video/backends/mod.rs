@@ -3,12 +3,20 @@
 
 pub mod frame;
 pub mod headless;
+mod reflection;
+pub mod retained;
 mod utils;
 
+use std::sync::Arc;
+
 use super::assets::prelude::*;
+use super::capabilities::Capabilities;
+use super::command::FrameArena;
 
 use crate::errors::*;
-use crate::math::prelude::{Aabb2, Vector2};
+use crate::math::prelude::{Aabb2, Aabb3, Vector2};
+use crate::res::request::Response;
+use crate::sched::prelude::LockLatch;
 use crate::utils::hash_value::HashValue;
 
 pub type UniformVar = (HashValue<str>, UniformVariable);
@@ -27,8 +35,41 @@ pub trait Visitor {
         fs: &str,
     ) -> Result<()>;
 
+    unsafe fn update_shader(
+        &mut self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        vs: &str,
+        fs: &str,
+    ) -> Result<()>;
+
     unsafe fn delete_shader(&mut self, handle: ShaderHandle) -> Result<()>;
 
+    unsafe fn create_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        params: UniformBufferParams,
+        bytes: Option<&[u8]>,
+    ) -> Result<()>;
+
+    unsafe fn update_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        o: usize,
+        bytes: &[u8],
+    ) -> Result<()>;
+
+    unsafe fn delete_uniform_buffer(&mut self, handle: UniformBufferHandle) -> Result<()>;
+
+    unsafe fn create_query(&mut self, handle: QueryHandle, params: QueryParams) -> Result<()>;
+
+    unsafe fn delete_query(&mut self, handle: QueryHandle) -> Result<()>;
+
+    unsafe fn create_sampler(&mut self, handle: SamplerHandle, params: SamplerParams)
+        -> Result<()>;
+
+    unsafe fn delete_sampler(&mut self, handle: SamplerHandle) -> Result<()>;
+
     unsafe fn create_texture(
         &mut self,
         handle: TextureHandle,
@@ -36,15 +77,63 @@ pub trait Visitor {
         bytes: Option<TextureData>,
     ) -> Result<()>;
 
+    /// Updates the `area` of mipmap `level` of texture, the storages of levels that are
+    /// updated for the first time are allocated.
     unsafe fn update_texture(
         &mut self,
         handle: TextureHandle,
+        level: usize,
         area: Aabb2<u32>,
         bytes: &[u8],
     ) -> Result<()>;
 
+    /// Uploads the mipmap `level` of texture, the levels that are left empty when creating
+    /// texture must be uploaded from smaller to larger sizes.
+    unsafe fn update_texture_mip(
+        &mut self,
+        handle: TextureHandle,
+        level: usize,
+        bytes: &[u8],
+    ) -> Result<()>;
+
+    /// Generates the full mipmap chain of texture from its base level.
+    unsafe fn generate_mipmaps(&mut self, handle: TextureHandle) -> Result<()>;
+
+    unsafe fn set_texture_placeholder(&mut self, handle: Option<TextureHandle>) -> Result<()>;
+
+    /// Marks the texture as pending, which is bound as the placeholder until created.
+    unsafe fn set_texture_pending(&mut self, handle: TextureHandle, pending: bool) -> Result<()>;
+
+    /// Rasterizes the polygons of all the surfaces as lines.
+    unsafe fn set_wireframe(&mut self, enable: bool) -> Result<()>;
+
     unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()>;
 
+    unsafe fn create_texture_cube(
+        &mut self,
+        handle: TextureCubeHandle,
+        params: TextureCubeParams,
+        bytes: Option<TextureCubeData>,
+    ) -> Result<()>;
+
+    unsafe fn delete_texture_cube(&mut self, handle: TextureCubeHandle) -> Result<()>;
+
+    unsafe fn create_texture_3d(
+        &mut self,
+        handle: Texture3DHandle,
+        params: Texture3DParams,
+        bytes: Option<Texture3DData>,
+    ) -> Result<()>;
+
+    unsafe fn update_texture_3d(
+        &mut self,
+        handle: Texture3DHandle,
+        area: Aabb3<u32>,
+        bytes: &[u8],
+    ) -> Result<()>;
+
+    unsafe fn delete_texture_3d(&mut self, handle: Texture3DHandle) -> Result<()>;
+
     unsafe fn create_render_texture(
         &mut self,
         handle: RenderTextureHandle,
@@ -53,6 +142,15 @@ pub trait Visitor {
 
     unsafe fn delete_render_texture(&mut self, handle: RenderTextureHandle) -> Result<()>;
 
+    /// Copies the pixels in `area` of render texture without blocking, the `latch` is set
+    /// once the copy has been finished by GPU.
+    unsafe fn read_render_texture(
+        &mut self,
+        handle: RenderTextureHandle,
+        area: Aabb2<u32>,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()>;
+
     unsafe fn create_mesh(
         &mut self,
         handle: MeshHandle,
@@ -76,6 +174,22 @@ pub trait Visitor {
 
     unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()>;
 
+    unsafe fn create_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        params: InstanceBufferParams,
+        bytes: Option<&[u8]>,
+    ) -> Result<()>;
+
+    unsafe fn update_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        o: usize,
+        bytes: &[u8],
+    ) -> Result<()>;
+
+    unsafe fn delete_instance_buffer(&mut self, handle: InstanceBufferHandle) -> Result<()>;
+
     unsafe fn bind(&mut self, surface: SurfaceHandle, dimensions: Vector2<u32>) -> Result<()>;
 
     unsafe fn draw(
@@ -83,19 +197,67 @@ pub trait Visitor {
         shader: ShaderHandle,
         mesh: MeshHandle,
         mesh_index: MeshIndex,
+        instances: Option<(InstanceBufferHandle, u32)>,
         vars: &[UniformVar],
+        arena: &FrameArena,
     ) -> Result<u32>;
 
     unsafe fn update_surface_scissor(&mut self, scissor: SurfaceScissor) -> Result<()>;
 
     unsafe fn update_surface_viewport(&mut self, vp: SurfaceViewport) -> Result<()>;
 
+    unsafe fn begin_query(&mut self, handle: QueryHandle) -> Result<()>;
+
+    unsafe fn end_query(&mut self, handle: QueryHandle) -> Result<()>;
+
+    /// Labels the object, which is shown by graphics debuggers.
+    unsafe fn set_debug_name(&mut self, object: VideoObject, name: &str) -> Result<()>;
+
+    /// Begins a named group of commands, which is shown by graphics debuggers. The groups
+    /// that are still open are ended when another surface is bound.
+    unsafe fn push_debug_group(&mut self, name: &str) -> Result<()>;
+
+    unsafe fn pop_debug_group(&mut self) -> Result<()>;
+
+    /// Collects the results of finished queries without blocking. Queries whose results
+    /// are not available yet will be polled again in later frames.
+    unsafe fn query_results(&mut self, results: &mut Vec<(QueryHandle, u64)>) -> Result<()>;
+
+    /// Ends the timer of the surface being drawn, and collects the GPU time in nanoseconds
+    /// spent on the surfaces of the latest finished frame without blocking. Nothing is
+    /// collected if no frame has been finished since last call.
+    unsafe fn timer_results(&mut self, results: &mut Vec<(SurfaceHandle, u64)>) -> Result<()>;
+
+    /// Gets the number of GPU state changes since last call, e.g. binding shaders, meshes,
+    /// textures and framebuffers.
+    fn take_state_changes(&mut self) -> u32;
+
+    /// Copies the pixels in `area` of the window framebuffer without blocking, the `latch`
+    /// is set once the copy has been finished by GPU.
+    unsafe fn read_framebuffer(
+        &mut self,
+        area: Aabb2<u32>,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()>;
+
+    /// Sets the latches of finished readbacks without blocking.
+    unsafe fn readback_results(&mut self) -> Result<()>;
+
     /// Blocks until all execution is complete. Such effects include all changes to render state, all
     /// changes to connection state, and all changes to the frame buffer contents.
     unsafe fn flush(&mut self) -> Result<()>;
 
     /// Advance one frame, it will be called every frames.
     unsafe fn advance(&mut self) -> Result<()>;
+
+    /// Checks if the textures with `format` could be created.
+    fn is_texture_format_supported(&self, format: TextureFormat) -> bool;
+
+    /// Checks if the render textures with `format` could be created.
+    fn is_render_texture_format_supported(&self, format: RenderTextureFormat) -> bool;
+
+    /// Gets the features and limits of device.
+    fn capabilities(&self) -> Capabilities;
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -107,6 +269,12 @@ pub fn new() -> Result<Box<Visitor>> {
     Ok(Box::new(visitor))
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub fn new_offscreen(dimensions: Vector2<u32>) -> Result<Box<dyn Visitor>> {
+    let visitor = unsafe { self::gl::visitor::GLVisitor::new_offscreen(dimensions)? };
+    Ok(Box::new(visitor))
+}
+
 #[cfg(target_arch = "wasm32")]
 pub mod webgl;
 
@@ -116,6 +284,11 @@ pub fn new() -> Result<Box<Visitor>> {
     Ok(Box::new(visitor))
 }
 
+#[cfg(target_arch = "wasm32")]
+pub fn new_offscreen(_: Vector2<u32>) -> Result<Box<dyn Visitor>> {
+    bail!("Offscreen rendering is not supported in web environment.");
+}
+
 pub fn new_headless() -> Box<Visitor> {
     Box::new(self::headless::HeadlessVisitor::new())
 }
@@ -0,0 +1,234 @@
+//! The reflection of active attributes and uniforms of linked programs, which is used
+//! to populate or validate the layouts declared in `ShaderParams`.
+
+use std::str::FromStr;
+
+use crate::errors::*;
+
+use super::super::assets::prelude::*;
+use super::super::MAX_UNIFORM_VARIABLES;
+
+/// The active variables of a linked program.
+#[derive(Debug, Default)]
+pub struct ShaderReflection {
+    attributes: Vec<(String, u8)>,
+    uniforms: Vec<(String, Option<UniformVariableType>)>,
+}
+
+impl ShaderReflection {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds an active attribute with the number of its components, zero if the type
+    /// could not be sourced from vertex buffers.
+    pub fn add_attribute(&mut self, name: &str, components: u8) {
+        if !name.starts_with("gl_") {
+            self.attributes.push((name.to_owned(), components));
+        }
+    }
+
+    /// Adds an active uniform, arrays with `size` elements are expanded into
+    /// `name[0]`, `name[1]`, etc. The type is `None` if it could not be represented
+    /// by `UniformVariableType`.
+    pub fn add_uniform(&mut self, name: &str, tp: Option<UniformVariableType>, size: usize) {
+        if name.starts_with("gl_") {
+            return;
+        }
+
+        if name.ends_with("[0]") {
+            let name = &name[..name.len() - 3];
+            for i in 0..size.max(1) {
+                self.uniforms.push((format!("{}[{}]", name, i), tp));
+            }
+        } else {
+            self.uniforms.push((name.to_owned(), tp));
+        }
+    }
+
+    /// Populates the layouts of `params` which are left empty with the active variables,
+    /// and validates the declared ones. All the mismatches are listed in the error.
+    ///
+    /// Active variables that are not declared are only warned about, since they are
+    /// harmless as long as the sources do not rely on their values.
+    pub fn apply(&self, params: &mut ShaderParams) -> Result<()> {
+        let mut errors = Vec::new();
+
+        if params.attributes.is_empty() {
+            let mut builder = AttributeLayout::build();
+            for &(ref name, components) in &self.attributes {
+                match Attribute::from_str(name) {
+                    Ok(_) if components == 0 || components > 4 => errors.push(format!(
+                        "Attribute({:?}) has a type that could not be sourced from vertex buffers.",
+                        name
+                    )),
+                    // The colors are optional since they default to white.
+                    Ok(v @ Attribute::Color0) | Ok(v @ Attribute::Color1) => {
+                        builder = builder.with_optional(v, components)
+                    }
+                    Ok(v) => builder = builder.with(v, components),
+                    Err(_) => errors.push(format!(
+                        "Attribute({:?}) is not a pre-defined attribute.",
+                        name
+                    )),
+                }
+            }
+
+            params.attributes = builder.finish();
+        } else {
+            for (attribute, size, _) in params.attributes.iter() {
+                let name: &'static str = attribute.into();
+                match self.attributes.iter().find(|v| v.0 == name) {
+                    Some(&(_, components)) if components < size => errors.push(format!(
+                        "Attribute({:?}) is declared with {} components, but has {} in shader sources.",
+                        name, size, components
+                    )),
+                    Some(_) => {}
+                    None => errors.push(format!(
+                        "Attribute({:?}) is undefined in shader sources.",
+                        name
+                    )),
+                }
+            }
+
+            for &(ref name, _) in &self.attributes {
+                let declared = Attribute::from_str(name)
+                    .map(|v| params.attributes.iter().any(|(a, _, _)| a == v))
+                    .unwrap_or(false);
+
+                if !declared {
+                    warn!("Attribute({:?}) is active but not declared.", name);
+                }
+            }
+        }
+
+        if params.uniforms.is_empty() {
+            let mut builder = UniformVariableLayout::build();
+            for &(ref name, tp) in &self.uniforms {
+                match tp {
+                    Some(tp) => builder = builder.with(name.as_str(), tp),
+                    None => errors.push(format!(
+                        "Uniform({:?}) has a type that is not supported.",
+                        name
+                    )),
+                }
+            }
+
+            params.uniforms = builder.finish();
+            if params.uniforms.len() > MAX_UNIFORM_VARIABLES {
+                errors.push(format!(
+                    "Too many uniform variables (>= {:?}).",
+                    MAX_UNIFORM_VARIABLES
+                ));
+            }
+        } else {
+            for &(ref name, tp) in params.uniforms.iter() {
+                // Uniform blocks are validated with their indices.
+                if tp == UniformVariableType::UniformBuffer {
+                    continue;
+                }
+
+                match self.uniform(name) {
+                    Some(Some(v)) if Self::is_compatible(tp, v) => {}
+                    Some(Some(v)) => errors.push(format!(
+                        "Uniform({:?}) is declared as {:?}, but is {:?} in shader sources.",
+                        name, tp, v
+                    )),
+                    Some(None) => errors.push(format!(
+                        "Uniform({:?}) is declared as {:?}, but has a type that is not supported.",
+                        name, tp
+                    )),
+                    None => errors.push(format!(
+                        "Uniform({:?}) is undefined in shader sources.",
+                        name
+                    )),
+                }
+            }
+
+            for &(ref name, _) in &self.uniforms {
+                let array = name.find('[').map(|i| &name[..i]).unwrap_or(name);
+                let declared = params.uniforms.variable_type(name.as_str()).is_some()
+                    || (name.ends_with("[0]") && params.uniforms.variable_type(array).is_some())
+                    || params.uniforms.variable_type(array)
+                        == Some(UniformVariableType::Matrix4fArray);
+
+                if !declared {
+                    warn!("Uniform({:?}) is active but not declared.", name);
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            bail!(
+                "Shader layouts do not match the sources:\n{}",
+                errors.join("\n")
+            );
+        }
+
+        Ok(())
+    }
+
+    fn uniform(&self, name: &str) -> Option<Option<UniformVariableType>> {
+        // The first element of array could be referred without subscript.
+        let element = format!("{}[0]", name);
+        self.uniforms
+            .iter()
+            .find(|v| v.0 == name || v.0 == element)
+            .map(|v| v.1)
+    }
+
+    fn is_compatible(declared: UniformVariableType, active: UniformVariableType) -> bool {
+        // Both textures and render textures are sampled with `sampler2D`.
+        declared == active
+            || (declared == UniformVariableType::RenderTexture
+                && active == UniformVariableType::Texture)
+            || (declared == UniformVariableType::Matrix4fArray
+                && active == UniformVariableType::Matrix4f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn populate_and_validate() {
+        let mut reflection = ShaderReflection::new();
+        reflection.add_attribute("Position", 3);
+        reflection.add_attribute("gl_VertexID", 1);
+        reflection.add_attribute("Color0", 4);
+        reflection.add_uniform("u_MVPMatrix", Some(UniformVariableType::Matrix4f), 1);
+        reflection.add_uniform("u_Lits[0]", Some(UniformVariableType::Vector3f), 2);
+        reflection.add_uniform("u_Bones[0]", Some(UniformVariableType::Matrix4f), 4);
+
+        let mut params = ShaderParams::default();
+        reflection.apply(&mut params).unwrap();
+        assert!(params.attributes.iter().eq(vec![
+            (Attribute::Position, 3, true),
+            (Attribute::Color0, 4, false)
+        ]));
+        assert_eq!(params.uniforms.len(), 7);
+        assert_eq!(
+            params.uniforms.variable_type("u_Lits[1]"),
+            Some(UniformVariableType::Vector3f)
+        );
+
+        let mut params = ShaderParams::default();
+        params.attributes = AttributeLayout::build()
+            .with(Attribute::Position, 4)
+            .with(Attribute::Normal, 3)
+            .finish();
+        params.uniforms = UniformVariableLayout::build()
+            .with("u_MVPMatrix", UniformVariableType::Matrix3f)
+            .with("u_Lits", UniformVariableType::Vector3f)
+            .with("u_Bones", UniformVariableType::Matrix4fArray)
+            .finish();
+
+        let err = reflection.apply(&mut params).unwrap_err().to_string();
+        assert!(err.contains("Attribute(\"Position\") is declared with 4 components"));
+        assert!(err.contains("Attribute(\"Normal\") is undefined"));
+        assert!(err.contains("Uniform(\"u_MVPMatrix\") is declared as Matrix3f"));
+        assert!(!err.contains("u_Lits"));
+        assert!(!err.contains("u_Bones"));
+    }
+}
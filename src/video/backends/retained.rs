@@ -0,0 +1,479 @@
+//! The creations of video objects which are kept in memory, so the objects could be
+//! recreated once the lost context is restored.
+//!
+//! The later updates of buffers and textures are written into the retained data, so the
+//! objects are restored with their latest contents.
+
+use crate::math::prelude::{Vector2, Vector3};
+use crate::utils::hash::{FastHashMap, FastHashSet};
+
+use super::super::assets::prelude::*;
+use super::super::command::FrameArena;
+use super::frame::{Command, Frame};
+use super::Visitor;
+
+use crate::errors::*;
+
+#[derive(Default)]
+pub struct RetainedObjects {
+    // The creation commands of alive objects, which are replayed in the order they were
+    // submitted.
+    creations: FastHashMap<VideoObject, Retained>,
+    placeholder: Option<TextureHandle>,
+    pending: FastHashSet<TextureHandle>,
+    wireframe: bool,
+    counter: u64,
+}
+
+struct Retained {
+    counter: u64,
+    creation: Command,
+    // Whether the mipmaps of texture have been generated.
+    mipmaps: bool,
+}
+
+impl RetainedObjects {
+    pub fn new() -> Self {
+        RetainedObjects::default()
+    }
+
+    /// Gets the number of objects retained.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.creations.len()
+    }
+
+    /// Keeps a copy of the object created or updated by `cmd`, or forgets the one deleted
+    /// by it.
+    pub fn retain(&mut self, cmd: &Command, bufs: &FrameArena) {
+        if let Command::SetTexturePlaceholder(handle) = *cmd {
+            self.placeholder = handle;
+            return;
+        }
+
+        if let Command::SetTexturePending(handle, pending) = *cmd {
+            if pending {
+                self.pending.insert(handle);
+            } else {
+                self.pending.remove(&handle);
+            }
+
+            return;
+        }
+
+        if let Command::SetWireframe(enable) = *cmd {
+            self.wireframe = enable;
+            return;
+        }
+
+        if let Some(object) = Self::deleted(cmd) {
+            if let VideoObject::Texture(handle) = object {
+                self.pending.remove(&handle);
+            }
+
+            self.creations.remove(&object);
+            return;
+        }
+
+        if let Some((object, creation)) = Self::duplicate(cmd) {
+            // The updated shaders are recreated in the order they were created at first.
+            let counter = match self.creations.remove(&object) {
+                Some(v) => v.counter,
+                None => {
+                    self.counter += 1;
+                    self.counter
+                }
+            };
+
+            let retained = Retained {
+                counter,
+                creation,
+                mipmaps: false,
+            };
+
+            self.creations.insert(object, retained);
+            return;
+        }
+
+        self.update(cmd, bufs);
+    }
+
+    // Writes the updates into the retained data of objects, which is allocated with zeros
+    // if the object was created without data.
+    fn update(&mut self, cmd: &Command, bufs: &FrameArena) {
+        match *cmd {
+            Command::UpdateUniformBuffer(handle, offset, ptr) => {
+                let object = VideoObject::UniformBuffer(handle);
+                if let Some(Command::CreateUniformBuffer(ref mut v)) = self.creation(object) {
+                    let len = v.1.size;
+                    let buf = v.2.get_or_insert_with(|| zeroed(len));
+                    patch(buf, offset, bufs.as_slice(ptr));
+                }
+            }
+            Command::UpdateTexture(handle, level, area, ptr) => {
+                let object = VideoObject::Texture(handle);
+                if let Some(Command::CreateTexture(ref mut v)) = self.creation(object) {
+                    let params = v.1;
+                    if params.format.compressed() {
+                        return;
+                    }
+
+                    let dimensions = Vector2::new(
+                        (params.dimensions.x >> level).max(1),
+                        (params.dimensions.y >> level).max(1),
+                    );
+
+                    let data = v.2.get_or_insert_with(|| TextureData { bytes: Vec::new() });
+                    let buf = level_mut(&mut data.bytes, level, |i| {
+                        let dimensions = Vector2::new(
+                            (params.dimensions.x >> i).max(1),
+                            (params.dimensions.y >> i).max(1),
+                        );
+
+                        params.format.size(dimensions) as usize
+                    });
+
+                    patch_area(
+                        buf,
+                        params.format.size(Vector2::new(1, 1)) as usize,
+                        Vector3::new(dimensions.x, dimensions.y, 1),
+                        Vector3::new(area.min.x, area.min.y, 0),
+                        Vector3::new(area.dim().x, area.dim().y, 1),
+                        bufs.as_slice(ptr),
+                    );
+                }
+            }
+            Command::UpdateTextureMip(ref v) => {
+                let object = VideoObject::Texture(v.0);
+                if let Some(Command::CreateTexture(ref mut texture)) = self.creation(object) {
+                    if let Some(ref mut data) = texture.2 {
+                        *level_mut(&mut data.bytes, v.1, |_| 0) = v.2.clone();
+                    }
+                }
+            }
+            Command::GenerateMipmaps(handle) => {
+                if let Some(v) = self.creations.get_mut(&VideoObject::Texture(handle)) {
+                    v.mipmaps = true;
+                }
+            }
+            Command::UpdateTexture3D(handle, area, ptr) => {
+                let object = VideoObject::Texture3D(handle);
+                if let Some(Command::CreateTexture3D(ref mut v)) = self.creation(object) {
+                    let params = v.1;
+                    if params.format.compressed() {
+                        return;
+                    }
+
+                    let data =
+                        v.2.get_or_insert_with(|| Texture3DData { bytes: Vec::new() });
+                    let len = params.size(params.dimensions) as usize;
+                    let buf = level_mut(&mut data.bytes, 0, |_| len);
+                    patch_area(
+                        buf,
+                        params.format.size(Vector2::new(1, 1)) as usize,
+                        params.dimensions,
+                        Vector3::new(area.min.x, area.min.y, area.min.z),
+                        area.dim(),
+                        bufs.as_slice(ptr),
+                    );
+                }
+            }
+            Command::UpdateVertexBuffer(handle, offset, ptr) => {
+                if let Some(data) = self.mesh(handle) {
+                    patch(&mut data.vptr, offset, bufs.as_slice(ptr));
+                }
+            }
+            Command::UpdateIndexBuffer(handle, offset, ptr) => {
+                if let Some(data) = self.mesh(handle) {
+                    patch(&mut data.iptr, offset, bufs.as_slice(ptr));
+                }
+            }
+            Command::UpdateInstanceBuffer(handle, offset, ptr) => {
+                let object = VideoObject::InstanceBuffer(handle);
+                if let Some(Command::CreateInstanceBuffer(ref mut v)) = self.creation(object) {
+                    let len = v.1.buffer_len();
+                    let buf = v.2.get_or_insert_with(|| zeroed(len));
+                    patch(buf, offset, bufs.as_slice(ptr));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn creation(&mut self, object: VideoObject) -> Option<&mut Command> {
+        self.creations.get_mut(&object).map(|v| &mut v.creation)
+    }
+
+    fn mesh(&mut self, handle: MeshHandle) -> Option<&mut MeshData> {
+        match self.creation(VideoObject::Mesh(handle)) {
+            Some(Command::CreateMesh(ref mut v)) => {
+                let (vlen, ilen) = (v.1.vertex_buffer_len(), v.1.index_buffer_len());
+                Some(v.2.get_or_insert_with(|| MeshData {
+                    vptr: zeroed(vlen),
+                    iptr: zeroed(ilen),
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Recreates all the retained objects with `visitor`.
+    pub unsafe fn restore(&self, visitor: &mut dyn Visitor) -> Result<()> {
+        let mut creations: Vec<_> = self.creations.iter().collect();
+        creations.sort_by_key(|v| v.1.counter);
+
+        let mut frame = Frame::with_capacity(0);
+        for (object, v) in creations {
+            frame.cmds.extend(Self::duplicate(&v.creation).map(|v| v.1));
+
+            if let (&VideoObject::Texture(handle), true) = (object, v.mipmaps) {
+                frame.cmds.push(Command::GenerateMipmaps(handle));
+            }
+        }
+
+        if self.placeholder.is_some() {
+            frame
+                .cmds
+                .push(Command::SetTexturePlaceholder(self.placeholder));
+        }
+
+        for &v in &self.pending {
+            frame.cmds.push(Command::SetTexturePending(v, true));
+        }
+
+        if self.wireframe {
+            frame.cmds.push(Command::SetWireframe(true));
+        }
+
+        frame.dispatch(visitor, Vector2::new(0, 0))?;
+        Ok(())
+    }
+
+    // Duplicates the creation command of object, the updates of shaders are turned into
+    // creations.
+    fn duplicate(cmd: &Command) -> Option<(VideoObject, Command)> {
+        let v = match *cmd {
+            Command::CreateSurface(ref v) => {
+                (VideoObject::Surface(v.0), Command::CreateSurface(v.clone()))
+            }
+            Command::CreateShader(ref v) | Command::UpdateShader(ref v) => {
+                (VideoObject::Shader(v.0), Command::CreateShader(v.clone()))
+            }
+            Command::CreateUniformBuffer(ref v) => (
+                VideoObject::UniformBuffer(v.0),
+                Command::CreateUniformBuffer(v.clone()),
+            ),
+            Command::CreateQuery(handle, params) => (
+                VideoObject::Query(handle),
+                Command::CreateQuery(handle, params),
+            ),
+            Command::CreateSampler(handle, params) => (
+                VideoObject::Sampler(handle),
+                Command::CreateSampler(handle, params),
+            ),
+            Command::CreateTexture(ref v) => {
+                (VideoObject::Texture(v.0), Command::CreateTexture(v.clone()))
+            }
+            Command::CreateTextureCube(ref v) => (
+                VideoObject::TextureCube(v.0),
+                Command::CreateTextureCube(v.clone()),
+            ),
+            Command::CreateTexture3D(ref v) => (
+                VideoObject::Texture3D(v.0),
+                Command::CreateTexture3D(v.clone()),
+            ),
+            Command::CreateRenderTexture(ref v) => (
+                VideoObject::RenderTexture(v.0),
+                Command::CreateRenderTexture(v.clone()),
+            ),
+            Command::CreateMesh(ref v) => (VideoObject::Mesh(v.0), Command::CreateMesh(v.clone())),
+            Command::CreateInstanceBuffer(ref v) => (
+                VideoObject::InstanceBuffer(v.0),
+                Command::CreateInstanceBuffer(v.clone()),
+            ),
+            _ => return None,
+        };
+
+        Some(v)
+    }
+
+    fn deleted(cmd: &Command) -> Option<VideoObject> {
+        match *cmd {
+            Command::DeleteSurface(v) => Some(VideoObject::Surface(v)),
+            Command::DeleteShader(v) => Some(VideoObject::Shader(v)),
+            Command::DeleteUniformBuffer(v) => Some(VideoObject::UniformBuffer(v)),
+            Command::DeleteQuery(v) => Some(VideoObject::Query(v)),
+            Command::DeleteSampler(v) => Some(VideoObject::Sampler(v)),
+            Command::DeleteTexture(v) => Some(VideoObject::Texture(v)),
+            Command::DeleteTextureCube(v) => Some(VideoObject::TextureCube(v)),
+            Command::DeleteTexture3D(v) => Some(VideoObject::Texture3D(v)),
+            Command::DeleteRenderTexture(v) => Some(VideoObject::RenderTexture(v)),
+            Command::DeleteMesh(v) => Some(VideoObject::Mesh(v)),
+            Command::DeleteInstanceBuffer(v) => Some(VideoObject::InstanceBuffer(v)),
+            _ => None,
+        }
+    }
+}
+
+fn zeroed(len: usize) -> Box<[u8]> {
+    vec![0; len].into_boxed_slice()
+}
+
+// Gets the data of mipmap `level`, which is allocated with zeros up to the size of level.
+fn level_mut<T>(bytes: &mut Vec<Box<[u8]>>, level: usize, size: T) -> &mut Box<[u8]>
+where
+    T: Fn(usize) -> usize,
+{
+    while bytes.len() <= level {
+        let len = size(bytes.len());
+        bytes.push(zeroed(len));
+    }
+
+    let len = size(level);
+    if bytes[level].len() < len {
+        let mut buf = bytes[level].to_vec();
+        buf.resize(len, 0);
+        bytes[level] = buf.into_boxed_slice();
+    }
+
+    &mut bytes[level]
+}
+
+// Copies `src` into `dst` at `offset`, the bytes out of bounds are ignored since they
+// are rejected by backends too.
+fn patch(dst: &mut [u8], offset: usize, src: &[u8]) {
+    let end = offset.saturating_add(src.len());
+    if let Some(dst) = dst.get_mut(offset..end) {
+        dst.copy_from_slice(src);
+    }
+}
+
+// Copies the rows of `src` into the area at `min` with `dim` of the image `dimensions`,
+// whose rows and slices are tightly packed. The areas out of bounds are ignored.
+fn patch_area(
+    dst: &mut [u8],
+    bpp: usize,
+    dimensions: Vector3<u32>,
+    min: Vector3<u32>,
+    dim: Vector3<u32>,
+    src: &[u8],
+) {
+    let row = dim.x as usize * bpp;
+    let bounds = min + dim;
+    if row == 0 || dim.y == 0 || bounds.x > dimensions.x || bounds.y > dimensions.y {
+        return;
+    }
+
+    for (i, v) in src.chunks(row).enumerate() {
+        let y = min.y as usize + i % dim.y as usize;
+        let z = min.z as usize + i / dim.y as usize;
+        let offset = (z * dimensions.y as usize + y) * dimensions.x as usize + min.x as usize;
+        patch(dst, offset * bpp, v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::headless::HeadlessVisitor;
+    use super::*;
+    use crate::math::prelude::Aabb2;
+    use crate::utils::prelude::HandleLike;
+
+    #[test]
+    fn retain() {
+        let mut retained = RetainedObjects::new();
+        let bufs = FrameArena::with_capacity(0);
+
+        let sampler = SamplerHandle::new(1, 1);
+        retained.retain(
+            &Command::CreateSampler(sampler, SamplerParams::default()),
+            &bufs,
+        );
+
+        let texture = TextureHandle::new(1, 1);
+        let mut params = TextureParams::default();
+        params.dimensions = (1, 1).into();
+        let data = TextureData {
+            bytes: vec![vec![255, 0, 255, 255].into_boxed_slice()],
+        };
+
+        retained.retain(
+            &Command::CreateTexture(Box::new((texture, params, Some(data)))),
+            &bufs,
+        );
+        retained.retain(&Command::SetTexturePlaceholder(Some(texture)), &bufs);
+
+        let pending = TextureHandle::new(2, 1);
+        retained.retain(&Command::SetTexturePending(pending, true), &bufs);
+        assert!(retained.pending.contains(&pending));
+        retained.retain(&Command::DeleteTexture(pending), &bufs);
+        assert!(retained.pending.is_empty());
+        retained.retain(&Command::SetTexturePending(pending, true), &bufs);
+
+        let query = QueryHandle::new(1, 1);
+        retained.retain(&Command::CreateQuery(query, QueryParams::default()), &bufs);
+        retained.retain(&Command::DeleteQuery(query), &bufs);
+        retained.retain(&Command::SetWireframe(true), &bufs);
+        assert_eq!(retained.len(), 2);
+        assert!(retained.wireframe);
+
+        let mut visitor = HeadlessVisitor::new();
+        unsafe {
+            retained.restore(&mut visitor).unwrap();
+            assert!(retained.restore(&mut visitor).is_err());
+        }
+    }
+
+    #[test]
+    fn update() {
+        let mut retained = RetainedObjects::new();
+        let mut bufs = FrameArena::with_capacity(0);
+
+        let texture = TextureHandle::new(1, 1);
+        let mut params = TextureParams::default();
+        params.hint = TextureHint::Stream;
+        params.dimensions = (2, 2).into();
+        retained.retain(
+            &Command::CreateTexture(Box::new((texture, params, None))),
+            &bufs,
+        );
+
+        let ptr = bufs.extend_from_slice(&[1; 8]);
+        let area = Aabb2::new([0, 1].into(), [2, 2].into());
+        retained.retain(&Command::UpdateTexture(texture, 0, area, ptr), &bufs);
+        retained.retain(&Command::GenerateMipmaps(texture), &bufs);
+
+        let v = &retained.creations[&VideoObject::Texture(texture)];
+        assert!(v.mipmaps);
+        match v.creation {
+            Command::CreateTexture(ref v) => {
+                let bytes = &v.2.as_ref().unwrap().bytes;
+                assert_eq!(
+                    &bytes[0][..],
+                    &[0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1]
+                );
+            }
+            _ => unreachable!(),
+        }
+
+        let ptr = bufs.extend_from_slice(&[1; 4]);
+        let buffer = UniformBufferHandle::new(1, 1);
+        let mut params = UniformBufferParams::default();
+        params.size = 8;
+        retained.retain(
+            &Command::CreateUniformBuffer(Box::new((buffer, params, None))),
+            &bufs,
+        );
+
+        retained.retain(&Command::UpdateUniformBuffer(buffer, 2, ptr), &bufs);
+        // The updates out of bounds are ignored.
+        retained.retain(&Command::UpdateUniformBuffer(buffer, 6, ptr), &bufs);
+
+        match retained.creations[&VideoObject::UniformBuffer(buffer)].creation {
+            Command::CreateUniformBuffer(ref v) => {
+                assert_eq!(&v.2.as_ref().unwrap()[..], &[0, 0, 1, 1, 1, 1, 0, 0]);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use std::borrow::Borrow;
 
+use crate::errors::*;
 use crate::utils::handle::Handle;
 
+use super::super::assets::mesh::MeshHint;
+
 #[derive(Debug)]
 pub struct DataVec<T>
 where
@@ -66,3 +69,78 @@ where
         }
     }
 }
+
+/// Maps the color attachments to the indices of draw buffers, so the i-th output of
+/// fragment shaders always writes into the i-th attachment. The slots without attachments
+/// are `None`, and the ones after the last attachment are omitted.
+pub fn draw_buffers<T>(colors: &[Option<T>]) -> impl Iterator<Item = Option<usize>> + '_ {
+    let len = colors
+        .iter()
+        .rposition(|v| v.is_some())
+        .map_or(0, |i| i + 1);
+    colors[..len]
+        .iter()
+        .enumerate()
+        .map(|(i, v)| v.as_ref().map(|_| i))
+}
+
+/// Checks the update of `size` bytes at `offset` into a buffer of `len` bytes. Returns
+/// true if the data store should be orphaned before writing, which happens when a
+/// streaming buffer is rewritten from the beginning.
+pub fn update_buffer(hint: MeshHint, len: usize, offset: usize, size: usize) -> Result<bool> {
+    if hint == MeshHint::Immutable {
+        bail!("Trying to update immutable buffer");
+    }
+
+    if offset.checked_add(size).map_or(true, |v| v > len) {
+        bail!("Trying to update buffer out of bounds.");
+    }
+
+    Ok(hint == MeshHint::Stream && offset == 0)
+}
+
+/// Clamps the requested number of samples per pixel into `1..=max`.
+pub fn samples(requested: u8, max: u32) -> u8 {
+    u32::from(requested).min(max).max(1) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn orphaned_buffers() {
+        assert!(update_buffer(MeshHint::Immutable, 16, 0, 16).is_err());
+        assert!(update_buffer(MeshHint::Dynamic, 16, 8, 16).is_err());
+        assert!(update_buffer(MeshHint::Stream, 16, ::std::usize::MAX, 1).is_err());
+
+        assert_eq!(update_buffer(MeshHint::Dynamic, 16, 0, 16).unwrap(), false);
+        assert_eq!(update_buffer(MeshHint::Stream, 16, 0, 8).unwrap(), true);
+        assert_eq!(update_buffer(MeshHint::Stream, 16, 0, 0).unwrap(), true);
+        assert_eq!(update_buffer(MeshHint::Stream, 16, 8, 8).unwrap(), false);
+    }
+
+    #[test]
+    fn clamped_samples() {
+        assert_eq!(samples(0, 4), 1);
+        assert_eq!(samples(1, 4), 1);
+        assert_eq!(samples(4, 4), 4);
+        assert_eq!(samples(8, 4), 4);
+        assert_eq!(samples(8, 0), 1);
+    }
+
+    #[test]
+    fn draw_buffers_of_sparse_attachments() {
+        let buffers: Vec<_> = draw_buffers::<u32>(&[None, None, None]).collect();
+        assert!(buffers.is_empty());
+
+        let buffers: Vec<_> = draw_buffers(&[Some(1), None, None]).collect();
+        assert_eq!(buffers, [Some(0)]);
+
+        let buffers: Vec<_> = draw_buffers(&[Some(1), None, Some(2), None]).collect();
+        assert_eq!(buffers, [Some(0), None, Some(2)]);
+
+        let buffers: Vec<_> = draw_buffers(&[None, Some(1)]).collect();
+        assert_eq!(buffers, [None, Some(1)]);
+    }
+}
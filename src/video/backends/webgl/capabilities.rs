@@ -1,6 +1,8 @@
 use web_sys::WebGl2RenderingContext as WebGL;
 
-use crate::video::assets::texture::TextureFormat;
+use crate::video::assets::texture::{RenderTextureFormat, TextureFormat};
+
+use super::types::MAX_TEXTURE_MAX_ANISOTROPY;
 
 /// Represents the capabilities of the context.
 ///
@@ -9,12 +11,85 @@ use crate::video::assets::texture::TextureFormat;
 pub struct Capabilities {
     /// The list of OpenGL extensions support by this implementation.
     pub extensions: Extensions,
+
+    /// The name of the renderer.
+    pub renderer: String,
+
+    /// The version of WebGL, e.g. `WebGL 2.0`.
+    pub version: String,
+
+    /// Number of available buffer bind points for `UNIFORM_BUFFER`.
+    pub max_uniform_buffer_bindings: u32,
+
+    /// Maximum width, height and depth of 3D textures.
+    pub max_3d_texture_size: u32,
+
+    /// Maximum number of samples of multisampled renderbuffers.
+    pub max_samples: u32,
+
+    /// Maximum number of color attachment bind points.
+    pub max_color_attachments: u32,
+
+    /// Maximum number of textures that can be bound to a program.
+    pub max_texture_units: u32,
+
+    /// Maximum number of textures that can be accessed from vertex shaders.
+    pub max_vertex_texture_units: u32,
+
+    /// Maximum number of four-element vectors of uniforms in vertex shaders.
+    pub max_vertex_uniform_vectors: u32,
+
+    /// Maximum degree of anisotropic filtering, 1 if not supported.
+    pub max_anisotropy: u32,
+
+    /// Supports rendering into the `SRGB8_ALPHA8` render textures.
+    pub srgb_framebuffer: bool,
 }
 
 impl Capabilities {
     pub unsafe fn new(ctx: &WebGL) -> Result<Capabilities, failure::Error> {
+        let parse = |id, default| {
+            ctx.get_parameter(id)
+                .ok()
+                .and_then(|v| v.as_f64())
+                .unwrap_or(default) as u32
+        };
+
+        let parse_str = |id| {
+            ctx.get_parameter(id)
+                .ok()
+                .and_then(|v| v.as_string())
+                .unwrap_or_default()
+        };
+
+        let extensions = Extensions::parse(ctx)?;
+        let max_anisotropy = if extensions.ext_texture_filter_anisotropic {
+            parse(MAX_TEXTURE_MAX_ANISOTROPY, 1.0).max(1)
+        } else {
+            1
+        };
+
+        // The formats that are not renderable are rejected with `INVALID_ENUM`, which is
+        // cleared here.
+        let srgb_framebuffer = ctx
+            .get_internalformat_parameter(WebGL::RENDERBUFFER, WebGL::SRGB8_ALPHA8, WebGL::SAMPLES)
+            .map(|v| !v.is_null() && !v.is_undefined())
+            .unwrap_or(false);
+        ctx.get_error();
+
         Ok(Capabilities {
-            extensions: Extensions::parse(ctx)?,
+            extensions: extensions,
+            renderer: parse_str(WebGL::RENDERER),
+            version: parse_str(WebGL::VERSION),
+            max_uniform_buffer_bindings: parse(WebGL::MAX_UNIFORM_BUFFER_BINDINGS, 0.0),
+            max_3d_texture_size: parse(WebGL::MAX_3D_TEXTURE_SIZE, 0.0),
+            max_samples: parse(WebGL::MAX_SAMPLES, 1.0),
+            max_color_attachments: parse(WebGL::MAX_COLOR_ATTACHMENTS, 4.0),
+            max_texture_units: parse(WebGL::MAX_COMBINED_TEXTURE_IMAGE_UNITS, 8.0),
+            max_vertex_texture_units: parse(WebGL::MAX_VERTEX_TEXTURE_IMAGE_UNITS, 0.0),
+            max_vertex_uniform_vectors: parse(WebGL::MAX_VERTEX_UNIFORM_VECTORS, 128.0),
+            max_anisotropy: max_anisotropy,
+            srgb_framebuffer: srgb_framebuffer,
         })
     }
 
@@ -30,6 +105,22 @@ impl Capabilities {
             TextureFormat::S3tcDxt1RGB4BPP | TextureFormat::S3tcDxt5RGBA8BPP => {
                 self.extensions.webgl_compressed_texture_s3tc
             }
+            TextureFormat::Astc4x4RGBA8BPP | TextureFormat::Astc8x8RGBA2BPP => {
+                self.extensions.webgl_compressed_texture_astc
+            }
+            _ => true,
+        }
+    }
+
+    /// Checks if the context supports rendering into textures with `format`.
+    pub fn support_render_texture_format(&self, format: RenderTextureFormat) -> bool {
+        match format {
+            RenderTextureFormat::RGBA16F => {
+                self.extensions.ext_color_buffer_float
+                    || self.extensions.ext_color_buffer_half_float
+            }
+            RenderTextureFormat::RG11B10F => self.extensions.ext_color_buffer_float,
+            RenderTextureFormat::SRGBA8 => self.srgb_framebuffer,
             _ => true,
         }
     }
@@ -72,4 +163,8 @@ extensions! {
     "WEBGL_compressed_texture_s3tc" => webgl_compressed_texture_s3tc,
     "WEBGL_compressed_texture_pvrtc" => webgl_compressed_texture_pvrtc,
     "WEBGL_compressed_texture_etc" => webgl_compressed_texture_etc,
+    "WEBGL_compressed_texture_astc" => webgl_compressed_texture_astc,
+    "EXT_color_buffer_float" => ext_color_buffer_float,
+    "EXT_color_buffer_half_float" => ext_color_buffer_half_float,
+    "EXT_texture_filter_anisotropic" => ext_texture_filter_anisotropic,
 }
@@ -60,12 +60,29 @@ impl From<Comparison> for u32 {
     }
 }
 
+impl From<StencilOp> for u32 {
+    fn from(op: StencilOp) -> Self {
+        match op {
+            StencilOp::Keep => WebGL::KEEP,
+            StencilOp::Zero => WebGL::ZERO,
+            StencilOp::Replace => WebGL::REPLACE,
+            StencilOp::Increment => WebGL::INCR,
+            StencilOp::IncrementWrap => WebGL::INCR_WRAP,
+            StencilOp::Decrement => WebGL::DECR,
+            StencilOp::DecrementWrap => WebGL::DECR_WRAP,
+            StencilOp::Invert => WebGL::INVERT,
+        }
+    }
+}
+
 impl From<Equation> for u32 {
     fn from(eq: Equation) -> Self {
         match eq {
             Equation::Add => WebGL::FUNC_ADD,
             Equation::Subtract => WebGL::FUNC_SUBTRACT,
             Equation::ReverseSubtract => WebGL::FUNC_REVERSE_SUBTRACT,
+            Equation::Min => WebGL::MIN,
+            Equation::Max => WebGL::MAX,
         }
     }
 }
@@ -99,6 +116,22 @@ impl From<TextureWrap> for u32 {
     }
 }
 
+/// The enum of `EXT_texture_filter_anisotropic`.
+pub const TEXTURE_MAX_ANISOTROPY: u32 = 0x84FE;
+pub const MAX_TEXTURE_MAX_ANISOTROPY: u32 = 0x84FF;
+
+/// Gets the minification filter that samples between mipmaps with `mip`.
+pub fn min_filter(min: TextureFilter, mip: Option<TextureFilter>) -> u32 {
+    match (min, mip) {
+        (TextureFilter::Nearest, None) => WebGL::NEAREST,
+        (TextureFilter::Linear, None) => WebGL::LINEAR,
+        (TextureFilter::Nearest, Some(TextureFilter::Nearest)) => WebGL::NEAREST_MIPMAP_NEAREST,
+        (TextureFilter::Linear, Some(TextureFilter::Nearest)) => WebGL::LINEAR_MIPMAP_NEAREST,
+        (TextureFilter::Nearest, Some(TextureFilter::Linear)) => WebGL::NEAREST_MIPMAP_LINEAR,
+        (TextureFilter::Linear, Some(TextureFilter::Linear)) => WebGL::LINEAR_MIPMAP_LINEAR,
+    }
+}
+
 impl From<TextureFormat> for (u32, u32, u32) {
     fn from(format: TextureFormat) -> Self {
         // FIXME
@@ -110,6 +143,8 @@ impl From<TextureFormat> for (u32, u32, u32) {
         // WebGL::COMPRESSED_RGBA_PVRTC_4BPPV1_IMG = 0x8C02
         // WebGL::COMPRESSED_RGB8_ETC2 = 0x9274
         // WebGL::COMPRESSED_RGBA8_ETC2_EAC = 0x9278
+        // WebGL::COMPRESSED_RGBA_ASTC_4x4_KHR = 0x93B0
+        // WebGL::COMPRESSED_RGBA_ASTC_8x8_KHR = 0x93B7
         match format {
             TextureFormat::R8 => (WebGL::RED, WebGL::RED, WebGL::UNSIGNED_BYTE),
             TextureFormat::RG8 => (WebGL::RG, WebGL::RG, WebGL::UNSIGNED_BYTE),
@@ -137,6 +172,10 @@ impl From<TextureFormat> for (u32, u32, u32) {
             TextureFormat::PvrtcRGB4BPP => (0x8C00, WebGL::RGB, WebGL::UNSIGNED_BYTE),
             TextureFormat::PvrtcRGBA2BPP => (0x8C03, WebGL::RGB, WebGL::UNSIGNED_BYTE),
             TextureFormat::PvrtcRGBA4BPP => (0x8C02, WebGL::RGB, WebGL::UNSIGNED_BYTE),
+            TextureFormat::Astc4x4RGBA8BPP => (0x93B0, WebGL::RGBA, WebGL::UNSIGNED_BYTE),
+            TextureFormat::Astc8x8RGBA2BPP => (0x93B7, WebGL::RGBA, WebGL::UNSIGNED_BYTE),
+            TextureFormat::SRGB8 => (WebGL::SRGB8, WebGL::RGB, WebGL::UNSIGNED_BYTE),
+            TextureFormat::SRGBA8 => (WebGL::SRGB8_ALPHA8, WebGL::RGBA, WebGL::UNSIGNED_BYTE),
         }
     }
 }
@@ -148,6 +187,14 @@ impl From<RenderTextureFormat> for (u32, u32, u32) {
             RenderTextureFormat::RGB8 => (WebGL::RGB, WebGL::RGB, WebGL::UNSIGNED_BYTE),
             RenderTextureFormat::RGBA4 => (WebGL::RGBA, WebGL::RGBA, WebGL::UNSIGNED_SHORT_4_4_4_4),
             RenderTextureFormat::RGBA8 => (WebGL::RGBA, WebGL::RGBA, WebGL::UNSIGNED_BYTE),
+            // Floating-point and sRGB formats must be sized.
+            RenderTextureFormat::SRGBA8 => (WebGL::SRGB8_ALPHA8, WebGL::RGBA, WebGL::UNSIGNED_BYTE),
+            RenderTextureFormat::RGBA16F => (WebGL::RGBA16F, WebGL::RGBA, WebGL::HALF_FLOAT),
+            RenderTextureFormat::RG11B10F => (
+                WebGL::R11F_G11F_B10F,
+                WebGL::RGB,
+                WebGL::UNSIGNED_INT_10F_11F_11F_REV,
+            ),
             RenderTextureFormat::Depth16 => {
                 (WebGL::DEPTH_COMPONENT, WebGL::DEPTH_COMPONENT, WebGL::FLOAT)
             }
@@ -165,3 +212,33 @@ impl From<RenderTextureFormat> for (u32, u32, u32) {
         }
     }
 }
+
+/// Maps the type of active uniform into `UniformVariableType`.
+pub fn uniform_variable_type(tp: u32) -> Option<UniformVariableType> {
+    match tp {
+        WebGL::SAMPLER_2D => Some(UniformVariableType::Texture),
+        WebGL::SAMPLER_CUBE => Some(UniformVariableType::TextureCube),
+        WebGL::SAMPLER_3D => Some(UniformVariableType::Texture3D),
+        WebGL::INT | WebGL::BOOL => Some(UniformVariableType::I32),
+        WebGL::FLOAT => Some(UniformVariableType::F32),
+        WebGL::FLOAT_VEC2 => Some(UniformVariableType::Vector2f),
+        WebGL::FLOAT_VEC3 => Some(UniformVariableType::Vector3f),
+        WebGL::FLOAT_VEC4 => Some(UniformVariableType::Vector4f),
+        WebGL::FLOAT_MAT2 => Some(UniformVariableType::Matrix2f),
+        WebGL::FLOAT_MAT3 => Some(UniformVariableType::Matrix3f),
+        WebGL::FLOAT_MAT4 => Some(UniformVariableType::Matrix4f),
+        _ => None,
+    }
+}
+
+/// Returns the number of components of active attribute, zero if the type could not
+/// be sourced from vertex buffers.
+pub fn attribute_components(tp: u32) -> u8 {
+    match tp {
+        WebGL::FLOAT | WebGL::INT | WebGL::UNSIGNED_INT => 1,
+        WebGL::FLOAT_VEC2 | WebGL::INT_VEC2 | WebGL::UNSIGNED_INT_VEC2 => 2,
+        WebGL::FLOAT_VEC3 | WebGL::INT_VEC3 | WebGL::UNSIGNED_INT_VEC3 => 3,
+        WebGL::FLOAT_VEC4 | WebGL::INT_VEC4 | WebGL::UNSIGNED_INT_VEC4 => 4,
+        _ => 0,
+    }
+}
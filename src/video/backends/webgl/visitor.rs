@@ -1,9 +1,11 @@
 use std::cell::RefCell;
+use std::sync::Arc;
 
 use smallvec::SmallVec;
 use web_sys::{
-    self, HtmlCanvasElement, WebGlBuffer, WebGlFramebuffer, WebGlProgram, WebGlRenderbuffer,
-    WebGlShader, WebGlTexture, WebGlUniformLocation, WebGlVertexArrayObject,
+    self, HtmlCanvasElement, WebGlBuffer, WebGlFramebuffer, WebGlProgram, WebGlQuery,
+    WebGlRenderbuffer, WebGlSampler, WebGlShader, WebGlSync, WebGlTexture, WebGlUniformLocation,
+    WebGlVertexArrayObject,
 };
 
 use wasm_bindgen::JsCast;
@@ -11,13 +13,19 @@ use web_sys::WebGl2RenderingContext as WebGL;
 
 use crate::errors::*;
 use crate::math::prelude::*;
+use crate::res::request::Response;
+use crate::sched::prelude::LockLatch;
 use crate::utils::hash::{FastHashMap, FastHashSet};
 use crate::utils::hash_value::HashValue;
 use crate::video::assets::prelude::*;
+use crate::video::capabilities::Capabilities as VideoCapabilities;
+use crate::video::command::FrameArena;
 
-use super::super::utils::DataVec;
+use super::super::reflection::ShaderReflection;
+use super::super::utils::{self, draw_buffers, samples, DataVec};
 use super::super::{UniformVar, Visitor};
 use super::capabilities::Capabilities;
+use super::types;
 
 #[derive(Debug, Clone)]
 struct GLSurfaceData {
@@ -25,6 +33,15 @@ struct GLSurfaceData {
     id: Option<WebGlFramebuffer>,
     dims: Option<Vector2<u32>>,
     params: SurfaceParams,
+    resolve: Option<GLResolveData>,
+}
+
+/// The framebuffer that multisampled attachments would be resolved into.
+#[derive(Debug, Clone)]
+struct GLResolveData {
+    id: WebGlFramebuffer,
+    colors: SmallVec<[u32; 8]>,
+    mask: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -34,6 +51,7 @@ pub struct GLShaderData {
     params: ShaderParams,
     uniforms: RefCell<FastHashMap<HashValue<str>, WebGlUniformLocation>>,
     attributes: RefCell<FastHashMap<HashValue<str>, i32>>,
+    blocks: FastHashMap<HashValue<str>, u32>,
 }
 
 impl GLShaderData {
@@ -80,12 +98,32 @@ impl GLShaderData {
     }
 }
 
+#[derive(Debug, Clone)]
+struct GLSamplerData {
+    id: WebGlSampler,
+}
+
 #[derive(Debug, Clone)]
 struct GLTextureData {
     handle: TextureHandle,
     id: WebGlTexture,
     params: TextureParams,
     allocated: RefCell<bool>,
+    // The largest mipmap level that has been uploaded.
+    base_level: RefCell<usize>,
+    // The number of mipmap levels that have storages.
+    levels: RefCell<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct GLTextureCubeData {
+    id: WebGlTexture,
+}
+
+#[derive(Debug, Clone)]
+struct GLTexture3DData {
+    id: WebGlTexture,
+    params: Texture3DParams,
 }
 
 #[derive(Debug, Clone)]
@@ -94,17 +132,49 @@ enum GLRenderTexture {
     T(WebGlTexture),
 }
 
+/// The pixels which are being copied into pixel buffer object by GPU.
+struct GLReadback {
+    pbo: WebGlBuffer,
+    fence: WebGlSync,
+    len: usize,
+    latch: Arc<LockLatch<Response>>,
+}
+
 #[derive(Debug, Clone)]
 struct GLRenderTextureData {
     handle: RenderTextureHandle,
     id: GLRenderTexture,
+    // The multisampled renderbuffer of sampler, which is resolved into `id`.
+    msaa: Option<WebGlRenderbuffer>,
     params: RenderTextureParams,
 }
 
+impl GLRenderTextureData {
+    /// Gets the object that should be rendered into.
+    fn target(&self) -> GLRenderTexture {
+        match self.msaa {
+            Some(ref v) => GLRenderTexture::R(v.clone()),
+            None => self.id.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Sampler {
     RenderTexture(RenderTextureHandle),
     Texture(TextureHandle),
+    TextureCube(TextureCubeHandle),
+    Texture3D(Texture3DHandle),
+}
+
+impl Sampler {
+    fn target(self) -> u32 {
+        match self {
+            Sampler::TextureCube(_) => WebGL::TEXTURE_CUBE_MAP,
+            Sampler::Texture3D(_) => WebGL::TEXTURE_3D,
+            _ => WebGL::TEXTURE_2D,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -115,17 +185,41 @@ struct GLMeshData {
     params: MeshParams,
 }
 
+#[derive(Debug, Clone)]
+struct GLInstanceBufferData {
+    handle: InstanceBufferHandle,
+    vbo: WebGlBuffer,
+    params: InstanceBufferParams,
+}
+
+#[derive(Debug, Clone)]
+struct GLUniformBufferData {
+    ubo: WebGlBuffer,
+    params: UniformBufferParams,
+}
+
+#[derive(Debug, Clone)]
+struct GLQueryData {
+    id: WebGlQuery,
+}
+
+type VAOKey = (ShaderHandle, MeshHandle, Option<InstanceBufferHandle>);
+
 struct WebGLState {
     render_state: RenderState,
     scissor: SurfaceScissor,
     view: SurfaceViewport,
     cleared_surfaces: FastHashSet<SurfaceHandle>,
-    vaos: FastHashMap<(ShaderHandle, MeshHandle), WebGlVertexArrayObject>,
+    vaos: FastHashMap<VAOKey, WebGlVertexArrayObject>,
     binded_surface: Option<SurfaceHandle>,
     binded_shader: Option<ShaderHandle>,
     binded_texture_index: usize,
     binded_textures: SmallVec<[Option<Sampler>; 8]>,
-    binded_vao: Option<(ShaderHandle, MeshHandle)>,
+    binded_samplers: SmallVec<[Option<SamplerHandle>; 8]>,
+    binded_vao: Option<VAOKey>,
+    pending_queries: Vec<QueryHandle>,
+    pending_readbacks: Vec<GLReadback>,
+    state_changes: u32,
 }
 
 pub struct WebGLVisitor {
@@ -136,8 +230,18 @@ pub struct WebGLVisitor {
     surfaces: DataVec<GLSurfaceData>,
     shaders: DataVec<GLShaderData>,
     meshes: DataVec<GLMeshData>,
+    instance_buffers: DataVec<GLInstanceBufferData>,
+    uniform_buffers: DataVec<GLUniformBufferData>,
+    queries: DataVec<GLQueryData>,
+    samplers: DataVec<GLSamplerData>,
     textures: DataVec<GLTextureData>,
+    texture_cubes: DataVec<GLTextureCubeData>,
+    texture_3ds: DataVec<GLTexture3DData>,
     render_textures: DataVec<GLRenderTextureData>,
+    // The texture that is bound in place of the pending textures, which are still loading
+    // or failed to load.
+    texture_placeholder: Option<TextureHandle>,
+    pending_textures: FastHashSet<TextureHandle>,
 }
 
 impl WebGLVisitor {
@@ -153,7 +257,7 @@ impl WebGLVisitor {
             .unwrap()
             .get_context("webgl2")
             .unwrap()
-            .unwrap()
+            .ok_or_else(|| format_err!("WebGL 2.0 is not supported by the browser."))?
             .dyn_into::<WebGL>()
             .unwrap();
 
@@ -169,8 +273,12 @@ impl WebGLVisitor {
             binded_shader: None,
             binded_texture_index: 0,
             binded_textures: SmallVec::new(),
+            binded_samplers: SmallVec::new(),
             vaos: FastHashMap::default(),
             binded_vao: None,
+            pending_queries: Vec::new(),
+            pending_readbacks: Vec::new(),
+            state_changes: 0,
         };
 
         Self::reset_render_state(&ctx, &mut state)?;
@@ -182,8 +290,16 @@ impl WebGLVisitor {
             surfaces: DataVec::new(),
             shaders: DataVec::new(),
             textures: DataVec::new(),
+            texture_cubes: DataVec::new(),
+            texture_3ds: DataVec::new(),
             render_textures: DataVec::new(),
+            texture_placeholder: None,
+            pending_textures: FastHashSet::default(),
             meshes: DataVec::new(),
+            instance_buffers: DataVec::new(),
+            uniform_buffers: DataVec::new(),
+            queries: DataVec::new(),
+            samplers: DataVec::new(),
         })
     }
 }
@@ -195,6 +311,43 @@ impl Visitor for WebGLVisitor {
         Ok(())
     }
 
+    fn is_texture_format_supported(&self, format: TextureFormat) -> bool {
+        self.capabilities.support_texture_format(format)
+    }
+
+    fn is_render_texture_format_supported(&self, format: RenderTextureFormat) -> bool {
+        self.capabilities.support_render_texture_format(format)
+    }
+
+    fn capabilities(&self) -> VideoCapabilities {
+        let caps = &self.capabilities;
+
+        // The instancing, occlusion queries and sampler objects are in the core of WebGL
+        // 2.0, which is required when creating the context. The timer queries and wireframe
+        // are not implemented.
+        VideoCapabilities {
+            api: caps.version.clone(),
+            renderer: caps.renderer.clone(),
+            instancing: true,
+            uniform_buffer: caps.max_uniform_buffer_bindings > 0,
+            occlusion_query: true,
+            timer_query: false,
+            srgb_texture: caps.support_texture_format(TextureFormat::SRGBA8),
+            srgb_framebuffer: caps.support_render_texture_format(RenderTextureFormat::SRGBA8),
+            srgb_backbuffer: false,
+            texture_3d: caps.max_3d_texture_size > 0,
+            texture_base_level: true,
+            wireframe: false,
+            sampler_objects: true,
+            max_anisotropy: caps.max_anisotropy,
+            max_color_attachments: caps.max_color_attachments,
+            max_samples: caps.max_samples,
+            max_texture_units: caps.max_texture_units,
+            max_vertex_texture_units: caps.max_vertex_texture_units,
+            max_vertex_uniform_vectors: caps.max_vertex_uniform_vectors,
+        }
+    }
+
     unsafe fn create_surface(
         &mut self,
         handle: SurfaceHandle,
@@ -205,6 +358,7 @@ impl Visitor for WebGLVisitor {
             id: None,
             dims: None,
             params: params,
+            resolve: None,
         };
 
         if params.colors[0].is_some() || params.depth_stencil.is_some() {
@@ -213,6 +367,8 @@ impl Visitor for WebGLVisitor {
             self.state.binded_surface = None;
 
             let mut dimensions = None;
+            let mut samples = None;
+            let mut resolves = Vec::new();
             for (i, attachment) in params.colors.iter().enumerate() {
                 if let Some(v) = *attachment {
                     let rt = self
@@ -228,11 +384,36 @@ impl Visitor for WebGLVisitor {
                         bail!("Incompitable(mismatch dimensons) attachments of SurfaceObject.");
                     }
 
+                    if samples.is_some() && samples != Some(rt.params.samples) {
+                        bail!("Incompitable(mismatch samples) attachments of SurfaceObject.");
+                    }
+
                     dimensions = Some(rt.params.dimensions);
-                    Self::bind_surface_render_texture(&self.ctx, &rt, i)?;
+                    samples = Some(rt.params.samples);
+                    Self::bind_surface_render_texture(&self.ctx, &rt.target(), rt.params, i)?;
+
+                    if rt.msaa.is_some() {
+                        resolves.push((i, rt.clone()));
+                    }
                 }
             }
 
+            // The i-th output of fragment shaders writes into the i-th color attachment, and
+            // the slots without attachments are disabled.
+            let buffers = js_sys::Array::new();
+            for v in draw_buffers(&params.colors) {
+                let v = v.map_or(WebGL::NONE, |i| WebGL::COLOR_ATTACHMENT0 + i as u32);
+                buffers.push(&v.into());
+            }
+
+            // Enables writing into multiple render targets, or the ones that are not at
+            // the first slot.
+            if buffers.length() > 1 {
+                self.ctx.draw_buffers(&buffers);
+                check(&self.ctx)?;
+            }
+
+            let mut depth_stencil_resolve = None;
             if let Some(v) = params.depth_stencil {
                 let rt = self
                     .render_textures
@@ -247,8 +428,16 @@ impl Visitor for WebGLVisitor {
                     bail!("Incompitable(mismatch dimensions) attachments of SurfaceObject.");
                 }
 
+                if samples.is_some() && samples != Some(rt.params.samples) {
+                    bail!("Incompitable(mismatch samples) attachments of SurfaceObject.");
+                }
+
                 dimensions = Some(rt.params.dimensions);
-                Self::bind_surface_render_texture(&self.ctx, &rt, 0)?;
+                Self::bind_surface_render_texture(&self.ctx, &rt.target(), rt.params, 0)?;
+
+                if rt.msaa.is_some() {
+                    depth_stencil_resolve = Some(rt.clone());
+                }
             }
 
             let status = self.ctx.check_framebuffer_status(WebGL::FRAMEBUFFER);
@@ -281,6 +470,12 @@ impl Visitor for WebGLVisitor {
                 }
             }
 
+            if !resolves.is_empty() || depth_stencil_resolve.is_some() {
+                let resolve =
+                    Self::create_resolve_framebuffer(&self.ctx, &resolves, depth_stencil_resolve)?;
+                data.resolve = Some(resolve);
+            }
+
             data.id = Some(id);
             data.dims = dimensions;
         };
@@ -304,6 +499,11 @@ impl Visitor for WebGLVisitor {
             check(&self.ctx)?;
         }
 
+        if let Some(v) = surface.resolve {
+            self.ctx.delete_framebuffer(Some(&v.id));
+            check(&self.ctx)?;
+        }
+
         Ok(())
     }
 
@@ -314,34 +514,31 @@ impl Visitor for WebGLVisitor {
         vs: &str,
         fs: &str,
     ) -> Result<()> {
-        let vs = Self::compile(&self.ctx, WebGL::VERTEX_SHADER, vs)?;
-        let fs = Self::compile(&self.ctx, WebGL::FRAGMENT_SHADER, fs)?;
-        let id = Self::link(&self.ctx, &[vs, fs])?;
-
-        let shader = GLShaderData {
-            handle: handle,
-            id: id,
-            params: params,
-            uniforms: RefCell::new(FastHashMap::default()),
-            attributes: RefCell::new(FastHashMap::default()),
-        };
+        let shader = self.build_shader(handle, params, vs, fs)?;
+        self.shaders.create(handle, shader);
+        Ok(())
+    }
 
-        for (name, _, _) in shader.params.attributes.iter() {
-            let name: &'static str = name.into();
-            if let Err(err) = shader.attribute_location(&self.ctx, name) {
-                self.ctx.delete_program(Some(&shader.id));
-                bail!(err);
-            }
+    unsafe fn update_shader(
+        &mut self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        vs: &str,
+        fs: &str,
+    ) -> Result<()> {
+        if self.shaders.get(handle).is_none() {
+            bail!("{:?} is invalid.", handle);
         }
 
-        for &(ref name, _) in shader.params.uniforms.iter() {
-            if let Err(err) = shader.uniform_location(&self.ctx, name) {
-                self.ctx.delete_program(Some(&shader.id));
-                bail!(err);
+        // Keeps the old program if the new sources are broken.
+        match self.build_shader(handle, params, vs, fs) {
+            Ok(shader) => {
+                self.delete_shader(handle)?;
+                self.shaders.create(handle, shader);
             }
+            Err(err) => warn!("Failed to update {:?}. {}", handle, err),
         }
 
-        self.shaders.create(handle, shader);
         Ok(())
     }
 
@@ -354,7 +551,7 @@ impl Visitor for WebGLVisitor {
         // Removes deprecated `VertexArrayObject`s.
         {
             let ctx = &self.ctx;
-            self.state.vaos.retain(|&(h, _), vao| {
+            self.state.vaos.retain(|&(h, _, _), vao| {
                 if h == shader.handle {
                     ctx.delete_vertex_array(Some(&vao));
                     false
@@ -387,6 +584,8 @@ impl Visitor for WebGLVisitor {
 
         let id = self.ctx.create_texture().unwrap();
         let mut allocated = false;
+        let mut base_level = 0;
+        let mut levels = 0;
 
         if let Some(mut data) = data {
             let len = data.bytes.len();
@@ -399,49 +598,39 @@ impl Visitor for WebGLVisitor {
                     Some(&id),
                 )?;
 
-                Self::bind_texture_params(&self.ctx, params.wrap, params.filter, len as u32)?;
+                Self::bind_texture_params(
+                    &self.ctx,
+                    WebGL::TEXTURE_2D,
+                    params.wrap,
+                    params.filter,
+                    len as u32,
+                )?;
 
-                let (internal_format, format, pixel_type) = params.format.into();
-                let mut dims = (params.dimensions.x as i32, params.dimensions.y as i32);
+                // The empty levels are streamed later.
+                base_level = data.bytes.iter().position(|v| !v.is_empty()).unwrap_or(0);
+                if base_level > 0 {
+                    self.ctx.tex_parameteri(
+                        WebGL::TEXTURE_2D,
+                        WebGL::TEXTURE_BASE_LEVEL,
+                        base_level as i32,
+                    );
+                }
 
-                if params.format.compressed() {
-                    for (i, v) in data.bytes.drain(..).enumerate() {
-                        let mv = ::std::slice::from_raw_parts_mut(v.as_ptr() as *mut u8, v.len());
-                        self.ctx.compressed_tex_image_2d_with_u8_array(
-                            WebGL::TEXTURE_2D,
-                            i as i32,
-                            internal_format,
-                            dims.0,
-                            dims.1,
-                            0,
-                            mv,
-                        );
+                let format = params.format.into();
+                let compressed = params.format.compressed();
+                let mut dims = (params.dimensions.x as i32, params.dimensions.y as i32);
 
-                        dims.0 = (dims.0 / 2).max(1);
-                        dims.1 = (dims.1 / 2).max(1);
+                for (i, v) in data.bytes.drain(..).enumerate() {
+                    if !v.is_empty() {
+                        Self::tex_image_2d(&self.ctx, i, dims, format, compressed, &v);
                     }
-                } else {
-                    for (i, v) in data.bytes.drain(..).enumerate() {
-                        let mv = ::std::slice::from_raw_parts_mut(v.as_ptr() as *mut u8, v.len());
-                        self.ctx
-                            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
-                                WebGL::TEXTURE_2D,
-                                i as i32,
-                                internal_format as i32,
-                                dims.0,
-                                dims.1,
-                                0,
-                                format,
-                                pixel_type,
-                                Some(mv),
-                            ).unwrap();
 
-                        dims.0 = (dims.0 / 2).max(1);
-                        dims.1 = (dims.1 / 2).max(1);
-                    }
+                    dims.0 = (dims.0 / 2).max(1);
+                    dims.1 = (dims.1 / 2).max(1);
                 }
 
                 allocated = true;
+                levels = len;
             }
         }
 
@@ -454,6 +643,8 @@ impl Visitor for WebGLVisitor {
                 id: id,
                 params: params,
                 allocated: RefCell::new(allocated),
+                base_level: RefCell::new(base_level),
+                levels: RefCell::new(levels),
             },
         );
 
@@ -463,6 +654,7 @@ impl Visitor for WebGLVisitor {
     unsafe fn update_texture(
         &mut self,
         handle: TextureHandle,
+        level: usize,
         area: Aabb2<u32>,
         data: &[u8],
     ) -> Result<()> {
@@ -479,9 +671,11 @@ impl Visitor for WebGLVisitor {
             bail!("Trying to update compressed texture.");
         }
 
-        if data.len() > texture.params.format.size(area.dim()) as usize
-            || area.min.x >= texture.params.dimensions.x
-            || area.min.y >= texture.params.dimensions.y
+        let dims = texture.params.mip_dimensions(level);
+        if level >= texture.params.mip_levels()
+            || data.len() > texture.params.format.size(area.dim()) as usize
+            || area.min.x >= dims.x
+            || area.min.y >= dims.y
         {
             bail!("Trying to update texture data out of bounds.");
         }
@@ -496,22 +690,44 @@ impl Visitor for WebGLVisitor {
             Some(&texture.id),
         )?;
 
-        if !*texture.allocated.borrow() {
-            Self::bind_texture_params(&self.ctx, texture.params.wrap, texture.params.filter, 1)?;
+        // Allocates the storages of levels that are updated for the first time.
+        let levels = *texture.levels.borrow();
+        if level >= levels {
+            Self::bind_texture_params(
+                &self.ctx,
+                WebGL::TEXTURE_2D,
+                texture.params.wrap,
+                texture.params.filter,
+                level as u32 + 1,
+            )?;
 
-            self.ctx
-                .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            let base_level = *texture.base_level.borrow();
+            if base_level > 0 {
+                self.ctx.tex_parameteri(
                     WebGL::TEXTURE_2D,
-                    0,
-                    internal_format as i32,
-                    texture.params.dimensions.x as i32,
-                    texture.params.dimensions.y as i32,
-                    0,
-                    format,
-                    pixel_type,
-                    None,
-                ).unwrap();
+                    WebGL::TEXTURE_BASE_LEVEL,
+                    base_level as i32,
+                );
+            }
 
+            for i in levels..=level {
+                let dims = texture.params.mip_dimensions(i);
+                self.ctx
+                    .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                        WebGL::TEXTURE_2D,
+                        i as i32,
+                        internal_format as i32,
+                        dims.x as i32,
+                        dims.y as i32,
+                        0,
+                        format,
+                        pixel_type,
+                        None,
+                    )
+                    .unwrap();
+            }
+
+            *texture.levels.borrow_mut() = level + 1;
             *texture.allocated.borrow_mut() = true;
         }
 
@@ -519,7 +735,7 @@ impl Visitor for WebGLVisitor {
         self.ctx
             .tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_u8_array(
                 WebGL::TEXTURE_2D,
-                0,
+                level as i32,
                 area.min.x as i32,
                 area.min.y as i32,
                 area.dim().x as i32,
@@ -527,113 +743,476 @@ impl Visitor for WebGLVisitor {
                 format,
                 pixel_type,
                 Some(mv),
-            ).unwrap();
+            )
+            .unwrap();
 
         check(&self.ctx)
     }
 
-    unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()> {
+    unsafe fn update_texture_mip(
+        &mut self,
+        handle: TextureHandle,
+        level: usize,
+        data: &[u8],
+    ) -> Result<()> {
         let texture = self
             .textures
-            .free(handle)
+            .get(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-        for v in self.state.binded_textures.iter_mut() {
-            if *v == Some(Sampler::Texture(handle)) {
-                *v = None;
-            }
+        let base_level = *texture.base_level.borrow();
+        if !*texture.allocated.borrow() || level + 1 < base_level {
+            bail!("Trying to update the mipmap of texture out of order.");
+        }
+
+        let dims = (
+            (texture.params.dimensions.x >> level).max(1),
+            (texture.params.dimensions.y >> level).max(1),
+        );
+
+        if data.is_empty() || data.len() > texture.params.format.size(dims.into()) as usize {
+            bail!("Trying to update texture data out of bounds.");
+        }
+
+        Self::bind_texture(
+            &self.ctx,
+            &mut self.state,
+            Some(Sampler::Texture(handle)),
+            0,
+            Some(&texture.id),
+        )?;
+
+        let format = texture.params.format.into();
+        let compressed = texture.params.format.compressed();
+        let dims = (dims.0 as i32, dims.1 as i32);
+        Self::tex_image_2d(&self.ctx, level, dims, format, compressed, data);
+
+        if level < base_level {
+            self.ctx
+                .tex_parameteri(WebGL::TEXTURE_2D, WebGL::TEXTURE_BASE_LEVEL, level as i32);
+            *texture.base_level.borrow_mut() = level;
         }
 
-        self.ctx.delete_texture(Some(&texture.id));
         check(&self.ctx)
     }
 
-    unsafe fn create_render_texture(
-        &mut self,
-        handle: RenderTextureHandle,
-        params: RenderTextureParams,
-    ) -> Result<()> {
-        let id = if params.sampler {
-            let id = self.ctx.create_texture().unwrap();
+    unsafe fn generate_mipmaps(&mut self, handle: TextureHandle) -> Result<()> {
+        let texture = self
+            .textures
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-            Self::bind_texture(
-                &self.ctx,
-                &mut self.state,
-                Some(Sampler::RenderTexture(handle)),
-                0,
-                Some(&id),
-            )?;
-            Self::bind_texture_params(&self.ctx, params.wrap, params.filter, 1)?;
+        if texture.params.format.compressed() {
+            bail!("Can not generate mipmaps of compressed texture.");
+        }
 
-            let (internal_format, format, pixel_type) = params.format.into();
-            self.ctx
-                .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
-                    WebGL::TEXTURE_2D,
-                    0,
-                    internal_format as i32,
-                    params.dimensions.x as i32,
-                    params.dimensions.y as i32,
-                    0,
-                    format,
-                    pixel_type,
-                    None,
-                ).unwrap();
+        if !*texture.allocated.borrow() || *texture.base_level.borrow() > 0 {
+            bail!("Trying to generate mipmaps of texture without its base level.");
+        }
 
-            GLRenderTexture::T(id)
-        } else {
-            let id = self.ctx.create_renderbuffer().unwrap();
-            self.ctx.bind_renderbuffer(WebGL::RENDERBUFFER, Some(&id));
+        Self::bind_texture(
+            &self.ctx,
+            &mut self.state,
+            Some(Sampler::Texture(handle)),
+            0,
+            Some(&texture.id),
+        )?;
 
-            let (internal_format, _, _) = params.format.into();
-            self.ctx.renderbuffer_storage(
-                WebGL::RENDERBUFFER,
-                internal_format,
-                params.dimensions.x as i32,
-                params.dimensions.y as i32,
-            );
+        let levels = texture.params.mip_levels();
+        Self::bind_texture_params(
+            &self.ctx,
+            WebGL::TEXTURE_2D,
+            texture.params.wrap,
+            texture.params.filter,
+            levels as u32,
+        )?;
 
-            GLRenderTexture::R(id)
-        };
+        self.ctx.generate_mipmap(WebGL::TEXTURE_2D);
+        *texture.levels.borrow_mut() = levels;
+        check(&self.ctx)
+    }
 
-        check(&self.ctx)?;
+    unsafe fn set_texture_placeholder(&mut self, handle: Option<TextureHandle>) -> Result<()> {
+        self.texture_placeholder = handle;
+        Ok(())
+    }
 
-        self.render_textures.create(
-            handle,
-            GLRenderTextureData {
-                handle: handle,
-                id: id,
-                params: params,
-            },
-        );
+    unsafe fn set_texture_pending(&mut self, handle: TextureHandle, pending: bool) -> Result<()> {
+        if pending {
+            self.pending_textures.insert(handle);
+        } else {
+            self.pending_textures.remove(&handle);
+        }
 
         Ok(())
     }
 
-    unsafe fn delete_render_texture(&mut self, handle: RenderTextureHandle) -> Result<()> {
-        let rt = self
-            .render_textures
+    // The polygon mode is not available in WebGL.
+    unsafe fn set_wireframe(&mut self, _: bool) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn delete_texture(&mut self, handle: TextureHandle) -> Result<()> {
+        self.pending_textures.remove(&handle);
+        let texture = self
+            .textures
             .free(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-        match rt.id {
-            GLRenderTexture::T(v) => {
-                for v in self.state.binded_textures.iter_mut() {
-                    if *v == Some(Sampler::RenderTexture(handle)) {
-                        *v = None;
-                    }
-                }
-
-                self.ctx.delete_texture(Some(&v));
-            }
-            GLRenderTexture::R(v) => {
-                self.ctx.delete_renderbuffer(Some(&v));
+        for v in self.state.binded_textures.iter_mut() {
+            if *v == Some(Sampler::Texture(handle)) {
+                *v = None;
             }
         }
 
+        self.ctx.delete_texture(Some(&texture.id));
         check(&self.ctx)
     }
 
-    unsafe fn create_mesh(
+    unsafe fn create_texture_cube(
+        &mut self,
+        handle: TextureCubeHandle,
+        params: TextureCubeParams,
+        data: Option<TextureCubeData>,
+    ) -> Result<()> {
+        if !self.capabilities.support_texture_format(params.format) {
+            bail!(
+                "The GL Context does not support the texture format {:?}.",
+                params.format
+            );
+        }
+
+        let id = self.ctx.create_texture().unwrap();
+
+        Self::bind_texture(
+            &self.ctx,
+            &mut self.state,
+            Some(Sampler::TextureCube(handle)),
+            0,
+            Some(&id),
+        )?;
+
+        let levels = data.as_ref().map(|v| v.faces[0].len()).unwrap_or(0);
+        Self::bind_texture_params(
+            &self.ctx,
+            WebGL::TEXTURE_CUBE_MAP,
+            params.wrap,
+            params.filter,
+            levels.max(1) as u32,
+        )?;
+
+        if let Some(data) = data {
+            let (internal_format, format, pixel_type) = params.format.into();
+
+            for (face, mips) in data.faces.iter().enumerate() {
+                let target = WebGL::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32;
+                let mut dims = (params.dimensions.x as i32, params.dimensions.y as i32);
+
+                for (i, v) in mips.iter().enumerate() {
+                    let mv = ::std::slice::from_raw_parts_mut(v.as_ptr() as *mut u8, v.len());
+
+                    if params.format.compressed() {
+                        self.ctx.compressed_tex_image_2d_with_u8_array(
+                            target,
+                            i as i32,
+                            internal_format,
+                            dims.0,
+                            dims.1,
+                            0,
+                            mv,
+                        );
+                    } else {
+                        self.ctx
+                            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                                target,
+                                i as i32,
+                                internal_format as i32,
+                                dims.0,
+                                dims.1,
+                                0,
+                                format,
+                                pixel_type,
+                                Some(mv),
+                            ).unwrap();
+                    }
+
+                    dims.0 = (dims.0 / 2).max(1);
+                    dims.1 = (dims.1 / 2).max(1);
+                }
+            }
+        }
+
+        check(&self.ctx)?;
+
+        self.texture_cubes
+            .create(handle, GLTextureCubeData { id: id });
+
+        Ok(())
+    }
+
+    unsafe fn delete_texture_cube(&mut self, handle: TextureCubeHandle) -> Result<()> {
+        let texture = self
+            .texture_cubes
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        for v in self.state.binded_textures.iter_mut() {
+            if *v == Some(Sampler::TextureCube(handle)) {
+                *v = None;
+            }
+        }
+
+        self.ctx.delete_texture(Some(&texture.id));
+        check(&self.ctx)
+    }
+
+    unsafe fn create_texture_3d(
+        &mut self,
+        handle: Texture3DHandle,
+        params: Texture3DParams,
+        data: Option<Texture3DData>,
+    ) -> Result<()> {
+        if !self.capabilities.support_texture_format(params.format) || params.format.compressed() {
+            bail!(
+                "The WebGL Context does not support the 3D texture format {:?}.",
+                params.format
+            );
+        }
+
+        let id = self.ctx.create_texture().unwrap();
+        let (internal_format, format, pixel_type) = params.format.into();
+
+        Self::bind_texture(
+            &self.ctx,
+            &mut self.state,
+            Some(Sampler::Texture3D(handle)),
+            0,
+            Some(&id),
+        )?;
+
+        let levels = data.as_ref().map(|v| v.bytes.len()).unwrap_or(0);
+        Self::bind_texture_params(
+            &self.ctx,
+            WebGL::TEXTURE_3D,
+            params.wrap,
+            params.filter,
+            levels.max(1) as u32,
+        )?;
+
+        let mut dims = (
+            params.dimensions.x as i32,
+            params.dimensions.y as i32,
+            params.dimensions.z as i32,
+        );
+
+        if levels > 0 {
+            for (i, v) in data.unwrap().bytes.iter().enumerate() {
+                let mv = ::std::slice::from_raw_parts_mut(v.as_ptr() as *mut u8, v.len());
+                self.ctx
+                    .tex_image_3d_with_opt_u8_array(
+                        WebGL::TEXTURE_3D,
+                        i as i32,
+                        internal_format as i32,
+                        dims.0,
+                        dims.1,
+                        dims.2,
+                        0,
+                        format,
+                        pixel_type,
+                        Some(mv),
+                    )
+                    .unwrap();
+
+                dims.0 = (dims.0 / 2).max(1);
+                dims.1 = (dims.1 / 2).max(1);
+                dims.2 = (dims.2 / 2).max(1);
+            }
+        } else {
+            // Allocates the storage, so it could be filled later with `update_texture_3d`.
+            self.ctx
+                .tex_image_3d_with_opt_u8_array(
+                    WebGL::TEXTURE_3D,
+                    0,
+                    internal_format as i32,
+                    dims.0,
+                    dims.1,
+                    dims.2,
+                    0,
+                    format,
+                    pixel_type,
+                    None,
+                )
+                .unwrap();
+        }
+
+        check(&self.ctx)?;
+
+        self.texture_3ds
+            .create(handle, GLTexture3DData { id, params });
+
+        Ok(())
+    }
+
+    unsafe fn update_texture_3d(
+        &mut self,
+        handle: Texture3DHandle,
+        area: Aabb3<u32>,
+        data: &[u8],
+    ) -> Result<()> {
+        let texture = self
+            .texture_3ds
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if texture.params.hint == TextureHint::Immutable {
+            bail!("Trying to update immutable texture.");
+        }
+
+        if data.len() > texture.params.size(area.dim()) as usize
+            || area.min.x >= texture.params.dimensions.x
+            || area.min.y >= texture.params.dimensions.y
+            || area.min.z >= texture.params.dimensions.z
+        {
+            bail!("Trying to update texture data out of bounds.");
+        }
+
+        let (_, format, pixel_type) = texture.params.format.into();
+
+        Self::bind_texture(
+            &self.ctx,
+            &mut self.state,
+            Some(Sampler::Texture3D(handle)),
+            0,
+            Some(&texture.id),
+        )?;
+
+        let mv = ::std::slice::from_raw_parts_mut(data.as_ptr() as *mut u8, data.len());
+        self.ctx
+            .tex_sub_image_3d_with_opt_u8_array(
+                WebGL::TEXTURE_3D,
+                0,
+                area.min.x as i32,
+                area.min.y as i32,
+                area.min.z as i32,
+                area.dim().x as i32,
+                area.dim().y as i32,
+                area.dim().z as i32,
+                format,
+                pixel_type,
+                Some(mv),
+            )
+            .unwrap();
+
+        check(&self.ctx)
+    }
+
+    unsafe fn delete_texture_3d(&mut self, handle: Texture3DHandle) -> Result<()> {
+        let texture = self
+            .texture_3ds
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        for v in self.state.binded_textures.iter_mut() {
+            if *v == Some(Sampler::Texture3D(handle)) {
+                *v = None;
+            }
+        }
+
+        self.ctx.delete_texture(Some(&texture.id));
+        check(&self.ctx)
+    }
+
+    unsafe fn create_render_texture(
+        &mut self,
+        handle: RenderTextureHandle,
+        mut params: RenderTextureParams,
+    ) -> Result<()> {
+        params.samples = samples(params.samples, self.capabilities.max_samples);
+
+        let id = if params.sampler {
+            let id = self.ctx.create_texture().unwrap();
+
+            Self::bind_texture(
+                &self.ctx,
+                &mut self.state,
+                Some(Sampler::RenderTexture(handle)),
+                0,
+                Some(&id),
+            )?;
+            Self::bind_texture_params(&self.ctx, WebGL::TEXTURE_2D, params.wrap, params.filter, 1)?;
+
+            let (internal_format, format, pixel_type) = params.format.into();
+            self.ctx
+                .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                    WebGL::TEXTURE_2D,
+                    0,
+                    internal_format as i32,
+                    params.dimensions.x as i32,
+                    params.dimensions.y as i32,
+                    0,
+                    format,
+                    pixel_type,
+                    None,
+                )
+                .unwrap();
+
+            GLRenderTexture::T(id)
+        } else {
+            GLRenderTexture::R(Self::create_renderbuffer(&self.ctx, params)?)
+        };
+
+        // Samplers could not be multisampled, so we render into a multisampled renderbuffer
+        // and resolve it into the texture later.
+        let msaa = if params.sampler && params.samples > 1 {
+            Some(Self::create_renderbuffer(&self.ctx, params)?)
+        } else {
+            None
+        };
+
+        check(&self.ctx)?;
+
+        self.render_textures.create(
+            handle,
+            GLRenderTextureData {
+                handle: handle,
+                id: id,
+                msaa: msaa,
+                params: params,
+            },
+        );
+
+        Ok(())
+    }
+
+    unsafe fn delete_render_texture(&mut self, handle: RenderTextureHandle) -> Result<()> {
+        let rt = self
+            .render_textures
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        match rt.id {
+            GLRenderTexture::T(v) => {
+                for v in self.state.binded_textures.iter_mut() {
+                    if *v == Some(Sampler::RenderTexture(handle)) {
+                        *v = None;
+                    }
+                }
+
+                self.ctx.delete_texture(Some(&v));
+            }
+            GLRenderTexture::R(v) => {
+                self.ctx.delete_renderbuffer(Some(&v));
+            }
+        }
+
+        if let Some(v) = rt.msaa {
+            self.ctx.delete_renderbuffer(Some(&v));
+        }
+
+        check(&self.ctx)
+    }
+
+    unsafe fn create_mesh(
         &mut self,
         handle: MeshHandle,
         params: MeshParams,
@@ -647,20 +1226,123 @@ impl Visitor for WebGLVisitor {
             data.as_ref().map(|v| v.vptr.as_ref()),
         )?;
 
-        let ibo = Self::create_buffer(
+        let ibo = Self::create_buffer(
+            &self.ctx,
+            WebGL::ELEMENT_ARRAY_BUFFER,
+            params.hint,
+            params.index_buffer_len(),
+            data.as_ref().map(|v| v.iptr.as_ref()),
+        )?;
+
+        self.meshes.create(
+            handle,
+            GLMeshData {
+                handle: handle,
+                vbo: vbo,
+                ibo: ibo,
+                params: params,
+            },
+        );
+
+        Ok(())
+    }
+
+    unsafe fn update_vertex_buffer(
+        &mut self,
+        handle: MeshHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let mesh = self
+            .meshes
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if mesh.params.hint == MeshHint::Immutable {
+            bail!("Trying to update immutable buffer");
+        }
+
+        let len = mesh.params.vertex_buffer_len();
+        Self::update_buffer(
+            &self.ctx,
+            WebGL::ARRAY_BUFFER,
+            &mesh.vbo,
+            mesh.params.hint,
+            len,
+            offset,
+            data,
+        )
+    }
+
+    unsafe fn update_index_buffer(
+        &mut self,
+        handle: MeshHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let mesh = self
+            .meshes
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if mesh.params.hint == MeshHint::Immutable {
+            bail!("Trying to update immutable buffer");
+        }
+
+        let len = mesh.params.index_buffer_len();
+        Self::update_buffer(
+            &self.ctx,
+            WebGL::ELEMENT_ARRAY_BUFFER,
+            &mesh.ibo,
+            mesh.params.hint,
+            len,
+            offset,
+            data,
+        )
+    }
+
+    unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()> {
+        let mesh = self
+            .meshes
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        // Removes deprecated `VertexArrayObject`s.
+        {
+            let ctx = &self.ctx;
+            self.state.vaos.retain(|&(_, h, _), vao| {
+                if h == mesh.handle {
+                    ctx.delete_vertex_array(Some(&vao));
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+
+        self.ctx.delete_buffer(Some(&mesh.vbo));
+        self.ctx.delete_buffer(Some(&mesh.ibo));
+        check(&self.ctx)
+    }
+
+    unsafe fn create_uniform_buffer(
+        &mut self,
+        handle: UniformBufferHandle,
+        params: UniformBufferParams,
+        data: Option<&[u8]>,
+    ) -> Result<()> {
+        let ubo = Self::create_buffer(
             &self.ctx,
-            WebGL::ELEMENT_ARRAY_BUFFER,
+            WebGL::UNIFORM_BUFFER,
             params.hint,
-            params.index_buffer_len(),
-            data.as_ref().map(|v| v.iptr.as_ref()),
+            params.size,
+            data,
         )?;
 
-        self.meshes.create(
+        self.uniform_buffers.create(
             handle,
-            GLMeshData {
-                handle: handle,
-                vbo: vbo,
-                ibo: ibo,
+            GLUniformBufferData {
+                ubo: ubo,
                 params: params,
             },
         );
@@ -668,59 +1350,187 @@ impl Visitor for WebGLVisitor {
         Ok(())
     }
 
-    unsafe fn update_vertex_buffer(
+    unsafe fn update_uniform_buffer(
         &mut self,
-        handle: MeshHandle,
+        handle: UniformBufferHandle,
         offset: usize,
         data: &[u8],
     ) -> Result<()> {
-        let mesh = self
-            .meshes
+        let buffer = self
+            .uniform_buffers
             .get(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-        if mesh.params.hint == MeshHint::Immutable {
+        if buffer.params.hint == MeshHint::Immutable {
             bail!("Trying to update immutable buffer");
         }
 
-        Self::update_buffer(&self.ctx, WebGL::ARRAY_BUFFER, &mesh.vbo, offset, data)
+        Self::update_buffer(
+            &self.ctx,
+            WebGL::UNIFORM_BUFFER,
+            &buffer.ubo,
+            buffer.params.hint,
+            buffer.params.size,
+            offset,
+            data,
+        )
+    }
+
+    unsafe fn delete_uniform_buffer(&mut self, handle: UniformBufferHandle) -> Result<()> {
+        let buffer = self
+            .uniform_buffers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        self.ctx.delete_buffer(Some(&buffer.ubo));
+        check(&self.ctx)
     }
 
-    unsafe fn update_index_buffer(
+    unsafe fn create_query(&mut self, handle: QueryHandle, _: QueryParams) -> Result<()> {
+        // WebGL2 only supports boolean occlusion queries.
+        let id = self
+            .ctx
+            .create_query()
+            .ok_or_else(|| format_err!("Failed to create query object."))?;
+
+        self.queries.create(handle, GLQueryData { id: id });
+        check(&self.ctx)
+    }
+
+    unsafe fn delete_query(&mut self, handle: QueryHandle) -> Result<()> {
+        let query = self
+            .queries
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        self.state.pending_queries.retain(|&v| v != handle);
+        self.ctx.delete_query(Some(&query.id));
+        check(&self.ctx)
+    }
+
+    unsafe fn create_sampler(
         &mut self,
-        handle: MeshHandle,
+        handle: SamplerHandle,
+        params: SamplerParams,
+    ) -> Result<()> {
+        let id = self
+            .ctx
+            .create_sampler()
+            .ok_or_else(|| format_err!("Failed to create sampler object."))?;
+
+        let wrap = |v: TextureWrap| u32::from(v) as i32;
+        self.ctx
+            .sampler_parameteri(&id, WebGL::TEXTURE_WRAP_S, wrap(params.wrap[0]));
+        self.ctx
+            .sampler_parameteri(&id, WebGL::TEXTURE_WRAP_T, wrap(params.wrap[1]));
+        self.ctx
+            .sampler_parameteri(&id, WebGL::TEXTURE_WRAP_R, wrap(params.wrap[2]));
+
+        let min_filter = types::min_filter(params.min_filter, params.mip_filter);
+        let mag_filter = types::min_filter(params.mag_filter, None);
+        self.ctx
+            .sampler_parameteri(&id, WebGL::TEXTURE_MIN_FILTER, min_filter as i32);
+        self.ctx
+            .sampler_parameteri(&id, WebGL::TEXTURE_MAG_FILTER, mag_filter as i32);
+
+        self.ctx
+            .sampler_parameterf(&id, WebGL::TEXTURE_MIN_LOD, params.lod_range[0]);
+        self.ctx
+            .sampler_parameterf(&id, WebGL::TEXTURE_MAX_LOD, params.lod_range[1]);
+
+        // The bias of level of detail is not a sampler state of WebGL.
+        let max_anisotropy = self.capabilities.max_anisotropy;
+        if max_anisotropy > 1 {
+            let v = params.max_anisotropy.min(max_anisotropy).max(1);
+            self.ctx
+                .sampler_parameterf(&id, types::TEXTURE_MAX_ANISOTROPY, v as f32);
+        }
+
+        self.samplers.create(handle, GLSamplerData { id: id });
+        check(&self.ctx)
+    }
+
+    unsafe fn delete_sampler(&mut self, handle: SamplerHandle) -> Result<()> {
+        let sampler = self
+            .samplers
+            .free(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        // The sampler objects are unbound from texture units when deleted.
+        for v in self.state.binded_samplers.iter_mut() {
+            if *v == Some(handle) {
+                *v = None;
+            }
+        }
+
+        self.ctx.delete_sampler(Some(&sampler.id));
+        check(&self.ctx)
+    }
+
+    unsafe fn create_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
+        params: InstanceBufferParams,
+        data: Option<&[u8]>,
+    ) -> Result<()> {
+        let vbo = Self::create_buffer(
+            &self.ctx,
+            WebGL::ARRAY_BUFFER,
+            params.hint,
+            params.buffer_len(),
+            data,
+        )?;
+
+        self.instance_buffers.create(
+            handle,
+            GLInstanceBufferData {
+                handle: handle,
+                vbo: vbo,
+                params: params,
+            },
+        );
+
+        Ok(())
+    }
+
+    unsafe fn update_instance_buffer(
+        &mut self,
+        handle: InstanceBufferHandle,
         offset: usize,
         data: &[u8],
     ) -> Result<()> {
-        let mesh = self
-            .meshes
+        let buffer = self
+            .instance_buffers
             .get(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
-        if mesh.params.hint == MeshHint::Immutable {
+        if buffer.params.hint == MeshHint::Immutable {
             bail!("Trying to update immutable buffer");
         }
 
+        let len = buffer.params.buffer_len();
         Self::update_buffer(
             &self.ctx,
-            WebGL::ELEMENT_ARRAY_BUFFER,
-            &mesh.ibo,
+            WebGL::ARRAY_BUFFER,
+            &buffer.vbo,
+            buffer.params.hint,
+            len,
             offset,
             data,
         )
     }
 
-    unsafe fn delete_mesh(&mut self, handle: MeshHandle) -> Result<()> {
-        let mesh = self
-            .meshes
+    unsafe fn delete_instance_buffer(&mut self, handle: InstanceBufferHandle) -> Result<()> {
+        let buffer = self
+            .instance_buffers
             .free(handle)
             .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
 
         // Removes deprecated `VertexArrayObject`s.
         {
             let ctx = &self.ctx;
-            self.state.vaos.retain(|&(_, h), vao| {
-                if h == mesh.handle {
+            self.state.vaos.retain(|&(_, _, h), vao| {
+                if h == Some(buffer.handle) {
                     ctx.delete_vertex_array(Some(&vao));
                     false
                 } else {
@@ -729,8 +1539,7 @@ impl Visitor for WebGLVisitor {
             });
         }
 
-        self.ctx.delete_buffer(Some(&mesh.vbo));
-        self.ctx.delete_buffer(Some(&mesh.ibo));
+        self.ctx.delete_buffer(Some(&buffer.vbo));
         check(&self.ctx)
     }
 
@@ -739,6 +1548,12 @@ impl Visitor for WebGLVisitor {
             return Ok(());
         }
 
+        if let Some(v) = self.state.binded_surface {
+            if let Some(surface) = self.surfaces.get(v) {
+                Self::resolve(&self.ctx, &mut self.state, surface)?;
+            }
+        }
+
         let surface = self
             .surfaces
             .get(handle)
@@ -748,15 +1563,13 @@ impl Visitor for WebGLVisitor {
         let id = surface.id.as_ref();
         let dimensions = surface.dims.unwrap_or(dimensions);
         self.ctx.bind_framebuffer(WebGL::FRAMEBUFFER, id);
+        self.state.state_changes += 1;
 
-        // Reset the viewport and scissor box.
-        let vp = SurfaceViewport {
-            position: Vector2::new(0, 0),
-            size: dimensions,
-        };
-
+        // Reset the viewport and scissor box, the clears are restricted to the viewport
+        // rectangle of surface too.
+        let (vp, scissor) = surface.params.viewport(dimensions);
         Self::set_viewport(&self.ctx, &mut self.state, vp)?;
-        Self::set_scissor(&self.ctx, &mut self.state, SurfaceScissor::Disable)?;
+        Self::set_scissor(&self.ctx, &mut self.state, scissor)?;
 
         if !self.state.cleared_surfaces.contains(&handle) {
             // Sets depth write enable to make sure that we can clear depth buffer properly.
@@ -765,6 +1578,12 @@ impl Visitor for WebGLVisitor {
                 Self::set_depth_test(&self.ctx, &mut self.state, true, Comparison::Always)?;
             }
 
+            // The stencil buffer is cleared with the write mask of stencil.
+            if surface.params.clear_stencil.is_some() {
+                self.state.binded_shader = None;
+                Self::set_stencil(&self.ctx, &mut self.state, None)?;
+            }
+
             // Clears frame buffer.
             Self::clear(
                 &self.ctx,
@@ -785,7 +1604,9 @@ impl Visitor for WebGLVisitor {
         shader: ShaderHandle,
         mesh: MeshHandle,
         mesh_index: MeshIndex,
+        instances: Option<(InstanceBufferHandle, u32)>,
         uniforms: &[UniformVar],
+        arena: &FrameArena,
     ) -> Result<u32> {
         // Bind program and associated uniforms and textures.
         let shader = self
@@ -808,114 +1629,345 @@ impl Visitor for WebGLVisitor {
                     );
                 }
 
+                if let UniformVariable::UniformBuffer(handle) = variable {
+                    let binding = *shader
+                        .blocks
+                        .get(&field)
+                        .ok_or_else(|| format_err!("Undefined uniform block {:?}.", field))?;
+                    let ubo = self.uniform_buffers.get(handle).map(|v| &v.ubo);
+                    self.ctx
+                        .bind_buffer_base(WebGL::UNIFORM_BUFFER, binding, ubo);
+                    check(&self.ctx)?;
+                    continue;
+                }
+
                 let location = shader.hash_uniform_location(field).unwrap();
-                match variable {
-                    UniformVariable::Texture(handle) => {
-                        let v = UniformVariable::I32(index as i32);
-                        Self::bind_uniform_variable(&self.ctx, &location, &v)?;
-
-                        if let Some(texture) = self.textures.get(handle) {
-                            Self::bind_texture(
-                                &self.ctx,
-                                &mut self.state,
-                                Some(Sampler::Texture(handle)),
-                                index,
-                                Some(&texture.id),
-                            )?;
-                        } else {
-                            Self::bind_texture(&self.ctx, &mut self.state, None, index, None)?;
+                let (texture, id, sampler) = match variable {
+                    UniformVariable::Texture(handle, sampler) => {
+                        let pending = self.pending_textures.contains(&handle)
+                            && self.textures.get(handle).is_none();
+                        let handle = match self.texture_placeholder {
+                            Some(v) if pending => v,
+                            _ => handle,
+                        };
+
+                        match self.textures.get(handle) {
+                            Some(v) => (Some(Sampler::Texture(handle)), Some(&v.id), sampler),
+                            None => (None, None, None),
+                        }
+                    }
+                    UniformVariable::TextureCube(handle, sampler) => {
+                        match self.texture_cubes.get(handle) {
+                            Some(v) => (Some(Sampler::TextureCube(handle)), Some(&v.id), sampler),
+                            None => (None, None, None),
                         }
-
-                        index += 1;
                     }
-                    UniformVariable::RenderTexture(handle) => {
-                        let v = UniformVariable::I32(index as i32);
-                        Self::bind_uniform_variable(&self.ctx, &location, &v)?;
-
-                        if let Some(texture) = self.render_textures.get(handle) {
-                            match texture.id {
-                                GLRenderTexture::T(ref w) => {
-                                    Self::bind_texture(
-                                        &self.ctx,
-                                        &mut self.state,
-                                        Some(Sampler::RenderTexture(handle)),
-                                        index,
-                                        Some(w),
-                                    )?;
-                                }
-                                _ => {
-                                    bail!("The render buffer does not have a sampler.");
-                                }
+                    UniformVariable::Texture3D(handle, sampler) => {
+                        match self.texture_3ds.get(handle) {
+                            Some(v) => (Some(Sampler::Texture3D(handle)), Some(&v.id), sampler),
+                            None => (None, None, None),
+                        }
+                    }
+                    UniformVariable::RenderTexture(handle, sampler) => {
+                        match self.render_textures.get(handle).map(|v| &v.id) {
+                            Some(GLRenderTexture::T(ref w)) => {
+                                (Some(Sampler::RenderTexture(handle)), Some(w), sampler)
                             }
-                        } else {
-                            Self::bind_texture(&self.ctx, &mut self.state, None, index, None)?;
+                            Some(_) => bail!("The render buffer does not have a sampler."),
+                            None => (None, None, None),
                         }
+                    }
+                    UniformVariable::Matrix4fArray(array) => {
+                        let mut mv: Vec<f32> = arena
+                            .as_slice(array.ptr)
+                            .iter()
+                            .flat_map(|m| m.iter().flat_map(|c| c.iter().cloned()))
+                            .collect();
 
-                        index += 1;
+                        self.ctx
+                            .uniform_matrix4fv_with_f32_array(Some(&location), false, &mut mv);
+                        check(&self.ctx)?;
+                        continue;
                     }
                     _ => {
                         Self::bind_uniform_variable(&self.ctx, &location, &variable)?;
+                        continue;
                     }
-                }
+                };
+
+                let v = UniformVariable::I32(index as i32);
+                Self::bind_uniform_variable(&self.ctx, &location, &v)?;
+                Self::bind_texture(&self.ctx, &mut self.state, texture, index, id)?;
+
+                let sampler = sampler.and_then(|v| self.samplers.get(v).map(|data| (v, data)));
+                Self::bind_sampler(&self.ctx, &mut self.state, index, sampler)?;
+                index += 1;
             } else {
                 bail!("Undefined uniform field {:?}.", field);
             }
         }
 
-        if let Some(mesh) = self.meshes.get(mesh) {
-            // Bind vertex buffer and vertex array object.
-            Self::bind_mesh(&self.ctx, &mut self.state, &shader, &mesh)?;
+        let instances = match instances {
+            Some((handle, num)) => {
+                let buffer = self
+                    .instance_buffers
+                    .get(handle)
+                    .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+                Some((buffer, num))
+            }
+            None => None,
+        };
+
+        if let Some(mesh) = self.meshes.get(mesh) {
+            // Bind vertex buffer and vertex array object.
+            Self::bind_mesh(
+                &self.ctx,
+                &mut self.state,
+                &shader,
+                &mesh,
+                instances.map(|v| v.0),
+            )?;
+
+            let (from, len) = match mesh_index {
+                MeshIndex::Ptr(from, len) => {
+                    if (from + len) > mesh.params.num_idxes {
+                        bail!("MeshIndex is out of bounds");
+                    }
+
+                    ((from * mesh.params.index_format.stride()), len)
+                }
+                MeshIndex::SubMesh(index) => {
+                    let num = mesh.params.sub_mesh_offsets.len();
+                    let from = mesh
+                        .params
+                        .sub_mesh_offsets
+                        .get(index)
+                        .ok_or_else(|| format_err!("MeshIndex is out of bounds"))?;
+
+                    let to = if index == (num - 1) {
+                        mesh.params.num_idxes
+                    } else {
+                        mesh.params.sub_mesh_offsets[index + 1]
+                    };
+
+                    ((from * mesh.params.index_format.stride()), (to - from))
+                }
+                MeshIndex::All => (0, mesh.params.num_idxes),
+            };
+
+            if let Some((_, num)) = instances {
+                self.ctx.draw_elements_instanced_with_i32(
+                    mesh.params.primitive.into(),
+                    len as i32,
+                    mesh.params.index_format.into(),
+                    from as i32,
+                    num as i32,
+                );
+
+                check(&self.ctx)?;
+                Ok(mesh.params.primitive.assemble(len as u32) * num)
+            } else {
+                self.ctx.draw_elements_with_i32(
+                    mesh.params.primitive.into(),
+                    len as i32,
+                    mesh.params.index_format.into(),
+                    from as i32,
+                );
+
+                check(&self.ctx)?;
+                Ok(mesh.params.primitive.assemble(len as u32))
+            }
+        } else {
+            Ok(0)
+        }
+    }
+
+    unsafe fn update_surface_scissor(&mut self, scissor: SurfaceScissor) -> Result<()> {
+        Self::set_scissor(&self.ctx, &mut self.state, scissor)
+    }
+
+    unsafe fn update_surface_viewport(&mut self, vp: SurfaceViewport) -> Result<()> {
+        Self::set_viewport(&self.ctx, &mut self.state, vp)
+    }
+
+    unsafe fn begin_query(&mut self, handle: QueryHandle) -> Result<()> {
+        let query = self
+            .queries
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        // The result of an active query could not be polled.
+        self.state.pending_queries.retain(|&v| v != handle);
+        self.ctx.begin_query(WebGL::ANY_SAMPLES_PASSED, &query.id);
+        check(&self.ctx)
+    }
+
+    unsafe fn end_query(&mut self, handle: QueryHandle) -> Result<()> {
+        if self.queries.get(handle).is_none() {
+            bail!("{:?} is invalid.", handle);
+        }
+
+        self.ctx.end_query(WebGL::ANY_SAMPLES_PASSED);
+        check(&self.ctx)?;
+
+        self.state.pending_queries.push(handle);
+        Ok(())
+    }
+
+    // WebGL has no debug labels, the frame captures of browsers name objects by themselves.
+    unsafe fn set_debug_name(&mut self, _: VideoObject, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn push_debug_group(&mut self, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn pop_debug_group(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    unsafe fn query_results(&mut self, results: &mut Vec<(QueryHandle, u64)>) -> Result<()> {
+        let mut i = 0;
+        while i < self.state.pending_queries.len() {
+            let handle = self.state.pending_queries[i];
+            if let Some(query) = self.queries.get(handle) {
+                let available = self
+                    .ctx
+                    .get_query_parameter(&query.id, WebGL::QUERY_RESULT_AVAILABLE)
+                    .as_bool()
+                    .unwrap_or(false);
+
+                if !available {
+                    i += 1;
+                    continue;
+                }
+
+                let v = self
+                    .ctx
+                    .get_query_parameter(&query.id, WebGL::QUERY_RESULT)
+                    .as_f64()
+                    .unwrap_or(0.0);
+
+                results.push((handle, v as u64));
+            }
+
+            self.state.pending_queries.swap_remove(i);
+        }
+
+        check(&self.ctx)
+    }
+
+    unsafe fn timer_results(&mut self, _: &mut Vec<(SurfaceHandle, u64)>) -> Result<()> {
+        Ok(())
+    }
+
+    fn take_state_changes(&mut self) -> u32 {
+        std::mem::replace(&mut self.state.state_changes, 0)
+    }
+
+    unsafe fn read_render_texture(
+        &mut self,
+        handle: RenderTextureHandle,
+        area: Aabb2<u32>,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()> {
+        let rt = self
+            .render_textures
+            .get(handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        if rt.msaa.is_none() && rt.params.samples > 1 {
+            bail!("Can NOT read back multisampled render buffer {:?}.", handle);
+        }
+
+        // The multisampled attachments of current surface should be resolved first.
+        if let Some(v) = self.state.binded_surface {
+            if let Some(surface) = self.surfaces.get(v) {
+                Self::resolve(&self.ctx, &mut self.state, surface)?;
+            }
+        }
+
+        let fbo = self.ctx.create_framebuffer().unwrap();
+        self.ctx.bind_framebuffer(WebGL::FRAMEBUFFER, Some(&fbo));
+        self.state.binded_surface = None;
+
+        // The floating-point formats are read as 32-bit floats.
+        let float = rt.params.format.is_float();
+        let rsp =
+            Self::bind_surface_render_texture(&self.ctx, &rt.id, rt.params, 0).and_then(|_| {
+                if float {
+                    self.read_pixels(area, WebGL::FLOAT, 16, latch)
+                } else {
+                    self.read_pixels(area, WebGL::UNSIGNED_BYTE, 4, latch)
+                }
+            });
+
+        self.ctx.bind_framebuffer(WebGL::FRAMEBUFFER, None);
+        self.ctx.delete_framebuffer(Some(&fbo));
+        rsp
+    }
+
+    unsafe fn read_framebuffer(
+        &mut self,
+        area: Aabb2<u32>,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()> {
+        if let Some(v) = self.state.binded_surface {
+            if let Some(surface) = self.surfaces.get(v) {
+                Self::resolve(&self.ctx, &mut self.state, surface)?;
+            }
+        }
+
+        self.ctx.bind_framebuffer(WebGL::FRAMEBUFFER, None);
+        self.state.binded_surface = None;
+        self.read_pixels(area, WebGL::UNSIGNED_BYTE, 4, latch)
+    }
 
-            let (from, len) = match mesh_index {
-                MeshIndex::Ptr(from, len) => {
-                    if (from + len) > mesh.params.num_idxes {
-                        bail!("MeshIndex is out of bounds");
-                    }
+    unsafe fn readback_results(&mut self) -> Result<()> {
+        let mut i = 0;
+        while i < self.state.pending_readbacks.len() {
+            let status =
+                self.ctx
+                    .client_wait_sync_with_u32(&self.state.pending_readbacks[i].fence, 0, 0);
 
-                    ((from * mesh.params.index_format.stride()), len)
-                }
-                MeshIndex::SubMesh(index) => {
-                    let num = mesh.params.sub_mesh_offsets.len();
-                    let from = mesh
-                        .params
-                        .sub_mesh_offsets
-                        .get(index)
-                        .ok_or_else(|| format_err!("MeshIndex is out of bounds"))?;
+            if status == WebGL::TIMEOUT_EXPIRED {
+                i += 1;
+                continue;
+            }
 
-                    let to = if index == (num - 1) {
-                        mesh.params.num_idxes
-                    } else {
-                        mesh.params.sub_mesh_offsets[index + 1]
-                    };
+            let v = self.state.pending_readbacks.swap_remove(i);
+            self.ctx.delete_sync(Some(&v.fence));
 
-                    ((from * mesh.params.index_format.stride()), (to - from))
-                }
-                MeshIndex::All => (0, mesh.params.num_idxes),
+            let rsp = if status == WebGL::WAIT_FAILED {
+                Err(format_err!("[WebGL] Failed to wait for the readback."))
+            } else {
+                let mut bytes = vec![0u8; v.len];
+                self.ctx.bind_buffer(WebGL::PIXEL_PACK_BUFFER, Some(&v.pbo));
+                self.ctx.get_buffer_sub_data_with_i32_and_u8_array(
+                    WebGL::PIXEL_PACK_BUFFER,
+                    0,
+                    &mut bytes,
+                );
+                self.ctx.bind_buffer(WebGL::PIXEL_PACK_BUFFER, None);
+                Ok(bytes.into_boxed_slice())
             };
 
-            self.ctx.draw_elements_with_i32(
-                mesh.params.primitive.into(),
-                len as i32,
-                mesh.params.index_format.into(),
-                from as i32,
-            );
-
-            check(&self.ctx)?;
-            Ok(mesh.params.primitive.assemble(len as u32))
-        } else {
-            Ok(0)
+            self.ctx.delete_buffer(Some(&v.pbo));
+            v.latch.set(rsp);
         }
-    }
-
-    unsafe fn update_surface_scissor(&mut self, scissor: SurfaceScissor) -> Result<()> {
-        Self::set_scissor(&self.ctx, &mut self.state, scissor)
-    }
 
-    unsafe fn update_surface_viewport(&mut self, vp: SurfaceViewport) -> Result<()> {
-        Self::set_viewport(&self.ctx, &mut self.state, vp)
+        check(&self.ctx)
     }
 
     unsafe fn flush(&mut self) -> Result<()> {
+        if let Some(v) = self.state.binded_surface {
+            if let Some(surface) = self.surfaces.get(v) {
+                Self::resolve(&self.ctx, &mut self.state, surface)?;
+            }
+        }
+
         self.ctx.finish();
         Ok(())
     }
@@ -924,20 +1976,24 @@ impl Visitor for WebGLVisitor {
 impl WebGLVisitor {
     unsafe fn bind_surface_render_texture(
         ctx: &WebGL,
-        rt: &GLRenderTextureData,
+        id: &GLRenderTexture,
+        params: RenderTextureParams,
         index: usize,
     ) -> Result<()> {
-        let location = match rt.params.format {
-            RenderTextureFormat::RGB8 | RenderTextureFormat::RGBA4 | RenderTextureFormat::RGBA8 => {
-                WebGL::COLOR_ATTACHMENT0 + index as u32
-            }
+        let location = match params.format {
+            RenderTextureFormat::RGB8
+            | RenderTextureFormat::RGBA4
+            | RenderTextureFormat::RGBA8
+            | RenderTextureFormat::SRGBA8
+            | RenderTextureFormat::RGBA16F
+            | RenderTextureFormat::RG11B10F => WebGL::COLOR_ATTACHMENT0 + index as u32,
             RenderTextureFormat::Depth16
             | RenderTextureFormat::Depth24
             | RenderTextureFormat::Depth32 => WebGL::DEPTH_ATTACHMENT,
             RenderTextureFormat::Depth24Stencil8 => WebGL::DEPTH_STENCIL_ATTACHMENT,
         };
 
-        match rt.id {
+        match *id {
             GLRenderTexture::T(ref v) => ctx.framebuffer_texture_2d(
                 WebGL::FRAMEBUFFER,
                 location,
@@ -955,6 +2011,171 @@ impl WebGLVisitor {
 
         check(&ctx)
     }
+
+    unsafe fn create_renderbuffer(
+        ctx: &WebGL,
+        params: RenderTextureParams,
+    ) -> Result<WebGlRenderbuffer> {
+        let id = ctx.create_renderbuffer().unwrap();
+        ctx.bind_renderbuffer(WebGL::RENDERBUFFER, Some(&id));
+
+        let (internal_format, _, _) = params.format.into();
+        if params.samples > 1 {
+            ctx.renderbuffer_storage_multisample(
+                WebGL::RENDERBUFFER,
+                params.samples as i32,
+                internal_format,
+                params.dimensions.x as i32,
+                params.dimensions.y as i32,
+            );
+        } else {
+            ctx.renderbuffer_storage(
+                WebGL::RENDERBUFFER,
+                internal_format,
+                params.dimensions.x as i32,
+                params.dimensions.y as i32,
+            );
+        }
+
+        check(&ctx)?;
+        Ok(id)
+    }
+
+    unsafe fn create_resolve_framebuffer(
+        ctx: &WebGL,
+        colors: &[(usize, GLRenderTextureData)],
+        depth_stencil: Option<GLRenderTextureData>,
+    ) -> Result<GLResolveData> {
+        let id = ctx.create_framebuffer().unwrap();
+        ctx.bind_framebuffer(WebGL::FRAMEBUFFER, Some(&id));
+
+        let mut resolve = GLResolveData {
+            id: id,
+            colors: SmallVec::new(),
+            mask: 0,
+        };
+
+        for &(i, ref rt) in colors {
+            Self::bind_surface_render_texture(ctx, &rt.id, rt.params, i)?;
+            resolve.colors.push(WebGL::COLOR_ATTACHMENT0 + i as u32);
+        }
+
+        if let Some(rt) = depth_stencil {
+            Self::bind_surface_render_texture(ctx, &rt.id, rt.params, 0)?;
+            resolve.mask = if rt.params.format == RenderTextureFormat::Depth24Stencil8 {
+                WebGL::DEPTH_BUFFER_BIT | WebGL::STENCIL_BUFFER_BIT
+            } else {
+                WebGL::DEPTH_BUFFER_BIT
+            };
+        }
+
+        if ctx.check_framebuffer_status(WebGL::FRAMEBUFFER) != WebGL::FRAMEBUFFER_COMPLETE {
+            ctx.bind_framebuffer(WebGL::FRAMEBUFFER, None);
+            ctx.delete_framebuffer(Some(&resolve.id));
+            bail!("[GL] The resolve framebuffer of multisampled surface is incomplete.");
+        }
+
+        Ok(resolve)
+    }
+
+    /// Copies the pixels in `area` of the bound framebuffer into a pixel buffer object.
+    unsafe fn read_pixels(
+        &mut self,
+        area: Aabb2<u32>,
+        ty: u32,
+        size: usize,
+        latch: Arc<LockLatch<Response>>,
+    ) -> Result<()> {
+        let dims = area.dim();
+        let len = (dims.x * dims.y) as usize * size;
+
+        let pbo = self.ctx.create_buffer().unwrap();
+        self.ctx.bind_buffer(WebGL::PIXEL_PACK_BUFFER, Some(&pbo));
+        self.ctx
+            .buffer_data_with_i32(WebGL::PIXEL_PACK_BUFFER, len as i32, WebGL::STREAM_READ);
+
+        let rsp = self.ctx.read_pixels_with_i32(
+            area.min.x as i32,
+            area.min.y as i32,
+            dims.x as i32,
+            dims.y as i32,
+            WebGL::RGBA,
+            ty,
+            0,
+        );
+
+        self.ctx.bind_buffer(WebGL::PIXEL_PACK_BUFFER, None);
+
+        // The latch is only taken over if the pixels are read successfully.
+        let fence = match (rsp, check(&self.ctx)) {
+            (Ok(_), Ok(_)) => self.ctx.fence_sync(WebGL::SYNC_GPU_COMMANDS_COMPLETE, 0),
+            _ => None,
+        };
+
+        let fence = match fence {
+            Some(fence) => fence,
+            None => {
+                self.ctx.delete_buffer(Some(&pbo));
+                bail!("[WebGL] Failed to read pixels.");
+            }
+        };
+
+        self.state.pending_readbacks.push(GLReadback {
+            pbo,
+            fence,
+            len,
+            latch,
+        });
+
+        Ok(())
+    }
+
+    /// Resolves the multisampled attachments of surface into its samplers.
+    unsafe fn resolve(ctx: &WebGL, state: &mut WebGLState, surface: &GLSurfaceData) -> Result<()> {
+        let (id, resolve, dims) = match (&surface.id, &surface.resolve, surface.dims) {
+            (Some(id), Some(resolve), Some(dims)) => (id, resolve, dims),
+            _ => return Ok(()),
+        };
+
+        // Blitting is affected by the scissor test.
+        Self::set_scissor(ctx, state, SurfaceScissor::Disable)?;
+
+        let (w, h) = (dims.x as i32, dims.y as i32);
+        ctx.bind_framebuffer(WebGL::READ_FRAMEBUFFER, Some(id));
+        ctx.bind_framebuffer(WebGL::DRAW_FRAMEBUFFER, Some(&resolve.id));
+
+        for &v in &resolve.colors {
+            // The i-th draw buffer must be either `COLOR_ATTACHMENTi` or `NONE`.
+            let buffers = js_sys::Array::new();
+            for _ in WebGL::COLOR_ATTACHMENT0..v {
+                buffers.push(&WebGL::NONE.into());
+            }
+            buffers.push(&v.into());
+
+            ctx.read_buffer(v);
+            ctx.draw_buffers(&buffers);
+            ctx.blit_framebuffer(
+                0,
+                0,
+                w,
+                h,
+                0,
+                0,
+                w,
+                h,
+                WebGL::COLOR_BUFFER_BIT,
+                WebGL::NEAREST,
+            );
+        }
+
+        if resolve.mask != 0 {
+            ctx.blit_framebuffer(0, 0, w, h, 0, 0, w, h, resolve.mask, WebGL::NEAREST);
+        }
+
+        ctx.bind_framebuffer(WebGL::FRAMEBUFFER, None);
+        state.binded_surface = None;
+        check(&ctx)
+    }
 }
 
 impl WebGLVisitor {
@@ -982,6 +2203,107 @@ impl WebGLVisitor {
         }
     }
 
+    unsafe fn build_shader(
+        &self,
+        handle: ShaderHandle,
+        params: ShaderParams,
+        vs: &str,
+        fs: &str,
+    ) -> Result<GLShaderData> {
+        if params.state.blend_attachments.iter().any(|&v| !v) {
+            bail!("The blending of color attachments could not be disabled individually.");
+        }
+
+        let vs = Self::compile(&self.ctx, WebGL::VERTEX_SHADER, vs)?;
+        let fs = Self::compile(&self.ctx, WebGL::FRAGMENT_SHADER, fs)?;
+        let id = Self::link(&self.ctx, &[vs, fs])?;
+
+        // Populates or validates the declared layouts with the active variables.
+        let mut params = params;
+        if let Err(err) = Self::reflect(&self.ctx, &id).apply(&mut params) {
+            self.ctx.delete_program(Some(&id));
+            return Err(err);
+        }
+
+        let mut shader = GLShaderData {
+            handle: handle,
+            id: id,
+            params: params,
+            uniforms: RefCell::new(FastHashMap::default()),
+            attributes: RefCell::new(FastHashMap::default()),
+            blocks: FastHashMap::default(),
+        };
+
+        for (name, _, _) in shader.params.attributes.iter() {
+            let name: &'static str = name.into();
+            if let Err(err) = shader.attribute_location(&self.ctx, name) {
+                self.ctx.delete_program(Some(&shader.id));
+                bail!(err);
+            }
+        }
+
+        for &(ref name, tp) in shader.params.uniforms.iter() {
+            if tp == UniformVariableType::UniformBuffer {
+                let index = self.ctx.get_uniform_block_index(&shader.id, name);
+                if index == WebGL::INVALID_INDEX {
+                    self.ctx.delete_program(Some(&shader.id));
+                    bail!("Uniform block({:?}) is undefined in shader sources.", name);
+                }
+
+                let binding = shader.blocks.len() as u32;
+                self.ctx.uniform_block_binding(&shader.id, index, binding);
+                check(&self.ctx)?;
+
+                shader.blocks.insert(name.as_str().into(), binding);
+                continue;
+            }
+
+            if let Err(err) = shader.uniform_location(&self.ctx, name) {
+                self.ctx.delete_program(Some(&shader.id));
+                bail!(err);
+            }
+        }
+
+        Ok(shader)
+    }
+
+    /// Queries the active attributes and uniforms of linked program. The members of
+    /// uniform blocks are skipped since they have no locations.
+    unsafe fn reflect(ctx: &WebGL, id: &WebGlProgram) -> ShaderReflection {
+        let mut reflection = ShaderReflection::new();
+
+        let count = ctx
+            .get_program_parameter(id, WebGL::ACTIVE_ATTRIBUTES)
+            .as_f64()
+            .unwrap_or(0.0) as u32;
+
+        for i in 0..count {
+            if let Some(info) = ctx.get_active_attrib(id, i) {
+                let components = types::attribute_components(info.type_());
+                reflection.add_attribute(&info.name(), components);
+            }
+        }
+
+        let count = ctx
+            .get_program_parameter(id, WebGL::ACTIVE_UNIFORMS)
+            .as_f64()
+            .unwrap_or(0.0) as u32;
+
+        for i in 0..count {
+            if let Some(info) = ctx.get_active_uniform(id, i) {
+                let name = info.name();
+                if ctx.get_uniform_location(id, &name).is_none() {
+                    continue;
+                }
+
+                let tp = types::uniform_variable_type(info.type_());
+                reflection.add_uniform(&name, tp, info.size() as usize);
+            }
+        }
+
+        reflection
+    }
+
     unsafe fn link<'a, T>(ctx: &WebGL, shaders: T) -> Result<WebGlProgram>
     where
         T: IntoIterator<Item = &'a WebGlShader>,
@@ -1028,10 +2350,12 @@ impl WebGLVisitor {
         Self::set_front_face_order(ctx, state, rs.front_face_order)?;
         Self::set_depth_test(ctx, state, rs.depth_write, rs.depth_test)?;
         Self::set_depth_write_offset(ctx, state, rs.depth_write_offset)?;
-        Self::set_color_blend(ctx, state, rs.color_blend)?;
+        Self::set_color_blend(ctx, state, rs.color_blend, rs.alpha_blend)?;
         Self::set_color_write(ctx, state, rs.color_write)?;
+        Self::set_stencil(ctx, state, rs.stencil)?;
 
         state.binded_shader = Some(shader.handle);
+        state.state_changes += 1;
         Ok(())
     }
 
@@ -1040,10 +2364,11 @@ impl WebGLVisitor {
         state: &mut WebGLState,
         shader: &GLShaderData,
         mesh: &GLMeshData,
+        instances: Option<&GLInstanceBufferData>,
     ) -> Result<()> {
         assert!(state.binded_shader == Some(shader.handle));
 
-        let k = (shader.handle, mesh.handle);
+        let k = (shader.handle, mesh.handle, instances.map(|v| v.handle));
         if state.binded_vao != Some(k) {
             if let Some(vao) = state.vaos.get(&k).cloned() {
                 ctx.bind_vertex_array(Some(&vao));
@@ -1051,10 +2376,20 @@ impl WebGLVisitor {
             } else {
                 let vao = ctx.create_vertex_array().unwrap();
                 ctx.bind_vertex_array(Some(&vao));
-                ctx.bind_buffer(WebGL::ARRAY_BUFFER, Some(&mesh.vbo));
 
                 for (name, size, required) in shader.params.attributes.iter() {
-                    if let Some(element) = mesh.params.layout.element(name) {
+                    let divisor = shader.params.attributes.divisor(name);
+                    let (vbo, layout) = if divisor > 0 {
+                        let buffer = instances.ok_or_else(|| {
+                            format_err!("Attribute {:?} requires an instance buffer.", name)
+                        })?;
+
+                        (&buffer.vbo, &buffer.params.layout)
+                    } else {
+                        (&mesh.vbo, &mesh.params.layout)
+                    };
+
+                    if let Some(element) = layout.element(name) {
                         if element.size < size {
                             bail!(
                                 "Vertex buffer has incompatible attribute `{:?}` [{:?} - {:?}].",
@@ -1064,10 +2399,11 @@ impl WebGLVisitor {
                             );
                         }
 
-                        let offset = mesh.params.layout.offset(name).unwrap();
-                        let stride = mesh.params.layout.stride();
+                        let offset = layout.offset(name).unwrap();
+                        let stride = layout.stride();
 
                         let location = shader.attribute_location(ctx, name.into())?;
+                        ctx.bind_buffer(WebGL::ARRAY_BUFFER, Some(vbo));
                         ctx.enable_vertex_attrib_array(location as u32);
                         ctx.vertex_attrib_pointer_with_i32(
                             location as u32,
@@ -1077,6 +2413,10 @@ impl WebGLVisitor {
                             stride as i32,
                             offset as i32,
                         );
+
+                        if divisor > 0 {
+                            ctx.vertex_attrib_divisor(location as u32, divisor);
+                        }
                     } else {
                         if required {
                             bail!(
@@ -1091,7 +2431,21 @@ impl WebGLVisitor {
                 state.vaos.insert(k, vao);
             }
 
+            // The missing optional attributes are read from the generic values, which are
+            // not a part of the states of vertex array objects.
+            for (name, _, required) in shader.params.attributes.iter() {
+                if required || mesh.params.layout.element(name).is_some() {
+                    continue;
+                }
+
+                if let Ok(location) = shader.attribute_location(ctx, name.into()) {
+                    let v = name.default_value();
+                    ctx.vertex_attrib4f(location as u32, v[0], v[1], v[2], v[3]);
+                }
+            }
+
             state.binded_vao = Some(k);
+            state.state_changes += 1;
         }
 
         ctx.bind_buffer(WebGL::ELEMENT_ARRAY_BUFFER, Some(&mesh.ibo));
@@ -1104,8 +2458,12 @@ impl WebGLVisitor {
         variable: &UniformVariable,
     ) -> Result<()> {
         match *variable {
-            UniformVariable::Texture(_) => unreachable!(),
-            UniformVariable::RenderTexture(_) => unreachable!(),
+            UniformVariable::Texture(_, _) => unreachable!(),
+            UniformVariable::TextureCube(_, _) => unreachable!(),
+            UniformVariable::Texture3D(_, _) => unreachable!(),
+            UniformVariable::RenderTexture(_, _) => unreachable!(),
+            UniformVariable::UniformBuffer(_) => unreachable!(),
+            UniformVariable::Matrix4fArray(_) => unreachable!(),
             UniformVariable::I32(v) => ctx.uniform1i(Some(&location), v),
             UniformVariable::F32(v) => ctx.uniform1f(Some(&location), v),
             UniformVariable::Vector2f(v) => ctx.uniform2f(Some(&location), v[0], v[1]),
@@ -1151,6 +2509,10 @@ impl WebGLVisitor {
         ctx.color_mask(true, true, true, true);
         rs.color_write = (true, true, true, true);
 
+        ctx.disable(WebGL::STENCIL_TEST);
+        ctx.stencil_mask(!0);
+        rs.stencil = None;
+
         ctx.disable(WebGL::SCISSOR_TEST);
         state.scissor = SurfaceScissor::Disable;
 
@@ -1270,22 +2632,25 @@ impl WebGLVisitor {
         ctx: &WebGL,
         state: &mut WebGLState,
         blend: Option<(Equation, BlendFactor, BlendFactor)>,
+        alpha: Option<(Equation, BlendFactor, BlendFactor)>,
     ) -> Result<()> {
         let state = &mut state.render_state;
 
-        if state.color_blend != blend {
+        if state.color_blend != blend || state.alpha_blend != alpha {
             if let Some((equation, src, dst)) = blend {
                 if state.color_blend == None {
                     ctx.enable(WebGL::BLEND);
                 }
 
-                ctx.blend_func(src.into(), dst.into());
-                ctx.blend_equation(equation.into());
+                let (alpha_equation, alpha_src, alpha_dst) = alpha.unwrap_or((equation, src, dst));
+                ctx.blend_func_separate(src.into(), dst.into(), alpha_src.into(), alpha_dst.into());
+                ctx.blend_equation_separate(equation.into(), alpha_equation.into());
             } else if state.color_blend != None {
                 ctx.disable(WebGL::BLEND);
             }
 
             state.color_blend = blend;
+            state.alpha_blend = alpha;
             check(&ctx)?;
         }
 
@@ -1309,6 +2674,50 @@ impl WebGLVisitor {
         Ok(())
     }
 
+    /// Enable or disable the stencil test, the write mask is reset to all bits when the
+    /// stencil test is disabled.
+    unsafe fn set_stencil(
+        ctx: &WebGL,
+        state: &mut WebGLState,
+        stencil: Option<StencilState>,
+    ) -> Result<()> {
+        let state = &mut state.render_state;
+
+        if state.stencil != stencil {
+            if let Some(v) = stencil {
+                if state.stencil.is_none() {
+                    ctx.enable(WebGL::STENCIL_TEST);
+                }
+
+                for &(face, s) in &[(WebGL::FRONT, v.front), (WebGL::BACK, v.back)] {
+                    ctx.stencil_func_separate(
+                        face,
+                        s.comparison.into(),
+                        i32::from(v.reference),
+                        u32::from(v.read_mask),
+                    );
+
+                    ctx.stencil_op_separate(
+                        face,
+                        s.fail.into(),
+                        s.depth_fail.into(),
+                        s.pass.into(),
+                    );
+                }
+
+                ctx.stencil_mask(u32::from(v.write_mask));
+            } else {
+                ctx.disable(WebGL::STENCIL_TEST);
+                ctx.stencil_mask(!0);
+            }
+
+            state.stencil = stencil;
+            check(&ctx)?;
+        }
+
+        Ok(())
+    }
+
     /// Set the scissor box relative to the top-lef corner of th window, in pixels.
     unsafe fn set_scissor(
         ctx: &WebGL,
@@ -1316,9 +2725,11 @@ impl WebGLVisitor {
         scissor: SurfaceScissor,
     ) -> Result<()> {
         match scissor {
-            SurfaceScissor::Disable => if state.scissor != SurfaceScissor::Disable {
-                ctx.disable(WebGL::SCISSOR_TEST);
-            },
+            SurfaceScissor::Disable => {
+                if state.scissor != SurfaceScissor::Disable {
+                    ctx.disable(WebGL::SCISSOR_TEST);
+                }
+            }
             SurfaceScissor::Enable { position, size } => {
                 if state.scissor == SurfaceScissor::Disable {
                     ctx.enable(WebGL::SCISSOR_TEST);
@@ -1408,14 +2819,72 @@ impl WebGLVisitor {
 
         if state.binded_textures[index] != sampler {
             state.binded_textures[index] = sampler;
-            ctx.bind_texture(WebGL::TEXTURE_2D, id);
+            let target = sampler.map(|v| v.target()).unwrap_or(WebGL::TEXTURE_2D);
+            ctx.bind_texture(target, id);
+            state.state_changes += 1;
+        }
+
+        check(ctx)
+    }
+
+    unsafe fn bind_sampler(
+        ctx: &WebGL,
+        state: &mut WebGLState,
+        index: usize,
+        sampler: Option<(SamplerHandle, &GLSamplerData)>,
+    ) -> Result<()> {
+        if state.binded_samplers.len() <= index {
+            state.binded_samplers.resize(index + 1, None);
+        }
+
+        let handle = sampler.map(|v| v.0);
+        if state.binded_samplers[index] != handle {
+            state.binded_samplers[index] = handle;
+            ctx.bind_sampler(index as u32, sampler.map(|v| &v.1.id));
+            state.state_changes += 1;
         }
 
         check(ctx)
     }
 
+    unsafe fn tex_image_2d(
+        ctx: &WebGL,
+        level: usize,
+        dims: (i32, i32),
+        (internal_format, format, pixel_type): (u32, u32, u32),
+        compressed: bool,
+        data: &[u8],
+    ) {
+        let mv = ::std::slice::from_raw_parts_mut(data.as_ptr() as *mut u8, data.len());
+        if compressed {
+            ctx.compressed_tex_image_2d_with_u8_array(
+                WebGL::TEXTURE_2D,
+                level as i32,
+                internal_format,
+                dims.0,
+                dims.1,
+                0,
+                mv,
+            );
+        } else {
+            ctx.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                WebGL::TEXTURE_2D,
+                level as i32,
+                internal_format as i32,
+                dims.0,
+                dims.1,
+                0,
+                format,
+                pixel_type,
+                Some(mv),
+            )
+            .unwrap();
+        }
+    }
+
     unsafe fn bind_texture_params(
         ctx: &WebGL,
+        target: u32,
         wrap: TextureWrap,
         filter: TextureFilter,
         levels: u32,
@@ -1423,8 +2892,12 @@ impl WebGLVisitor {
         let wrap: u32 = wrap.into();
         let wrap = wrap as i32;
 
-        ctx.tex_parameteri(WebGL::TEXTURE_2D, WebGL::TEXTURE_WRAP_S, wrap);
-        ctx.tex_parameteri(WebGL::TEXTURE_2D, WebGL::TEXTURE_WRAP_T, wrap);
+        ctx.tex_parameteri(target, WebGL::TEXTURE_WRAP_S, wrap);
+        ctx.tex_parameteri(target, WebGL::TEXTURE_WRAP_T, wrap);
+
+        if target == WebGL::TEXTURE_CUBE_MAP || target == WebGL::TEXTURE_3D {
+            ctx.tex_parameteri(target, WebGL::TEXTURE_WRAP_R, wrap);
+        }
 
         match filter {
             TextureFilter::Nearest => {
@@ -1434,13 +2907,9 @@ impl WebGLVisitor {
                     WebGL::NEAREST
                 } as i32;
 
-                ctx.tex_parameteri(WebGL::TEXTURE_2D, WebGL::TEXTURE_MIN_FILTER, min_filter);
+                ctx.tex_parameteri(target, WebGL::TEXTURE_MIN_FILTER, min_filter);
 
-                ctx.tex_parameteri(
-                    WebGL::TEXTURE_2D,
-                    WebGL::TEXTURE_MAG_FILTER,
-                    WebGL::NEAREST as i32,
-                );
+                ctx.tex_parameteri(target, WebGL::TEXTURE_MAG_FILTER, WebGL::NEAREST as i32);
             }
             TextureFilter::Linear => {
                 let min_filter = if levels > 1 {
@@ -1449,22 +2918,14 @@ impl WebGLVisitor {
                     WebGL::LINEAR
                 } as i32;
 
-                ctx.tex_parameteri(WebGL::TEXTURE_2D, WebGL::TEXTURE_MIN_FILTER, min_filter);
-                ctx.tex_parameteri(
-                    WebGL::TEXTURE_2D,
-                    WebGL::TEXTURE_MAG_FILTER,
-                    WebGL::LINEAR as i32,
-                );
+                ctx.tex_parameteri(target, WebGL::TEXTURE_MIN_FILTER, min_filter);
+                ctx.tex_parameteri(target, WebGL::TEXTURE_MAG_FILTER, WebGL::LINEAR as i32);
             }
         }
 
         if levels > 1 {
-            ctx.tex_parameteri(WebGL::TEXTURE_2D, WebGL::TEXTURE_BASE_LEVEL, 0);
-            ctx.tex_parameteri(
-                WebGL::TEXTURE_2D,
-                WebGL::TEXTURE_MAX_LEVEL,
-                (levels - 1) as i32,
-            );
+            ctx.tex_parameteri(target, WebGL::TEXTURE_BASE_LEVEL, 0);
+            ctx.tex_parameteri(target, WebGL::TEXTURE_MAX_LEVEL, (levels - 1) as i32);
         }
 
         check(&ctx)
@@ -1500,11 +2961,22 @@ impl WebGLVisitor {
         ctx: &WebGL,
         target: u32,
         id: &WebGlBuffer,
+        hint: MeshHint,
+        len: usize,
         offset: usize,
         data: &[u8],
     ) -> Result<()> {
-        let mv = ::std::slice::from_raw_parts_mut(data.as_ptr() as *mut u8, data.len());
+        let orphan = utils::update_buffer(hint, len, offset, data.len())?;
         ctx.bind_buffer(target, Some(&id));
+
+        // Orphans the data store of streaming buffer when it's rewritten from the beginning,
+        // so the browser could allocate a fresh block instead of waiting for the pending
+        // draws which still read from the old one.
+        if orphan {
+            ctx.buffer_data_with_i32(target, len as i32, hint.into());
+        }
+
+        let mv = ::std::slice::from_raw_parts_mut(data.as_ptr() as *mut u8, data.len());
         ctx.buffer_sub_data_with_i32_and_u8_array(target, offset as i32, mv);
         check(&ctx)
     }
@@ -0,0 +1,56 @@
+//! The features and limits of video device.
+
+/// Describes the features and limits of the video device, which could be used to choose
+/// the rendering paths at runtime.
+///
+/// The features are used natively if the device supports them, e.g. on OpenGL ES 3.0 or
+/// WebGL 2.0 contexts. Otherwise they are emulated if possible. For example, the draws
+/// with instance buffers are split into draws of each instance if `instancing` is false.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The name and version of graphics API, e.g. `OpenGL ES 3.0`.
+    pub api: String,
+    /// The name of renderer, which is typically specific to the GPU.
+    pub renderer: String,
+    /// Draws multiple instances with per-instance attributes natively.
+    pub instancing: bool,
+    /// Supports uniform buffer objects.
+    pub uniform_buffer: bool,
+    /// Supports occlusion queries.
+    pub occlusion_query: bool,
+    /// Supports timer queries, which measure the GPU time of surfaces.
+    pub timer_query: bool,
+    /// Supports the textures in sRGB color space.
+    pub srgb_texture: bool,
+    /// Supports rendering into the `RenderTextureFormat::SRGBA8` render textures.
+    pub srgb_framebuffer: bool,
+    /// The window framebuffer could encode linear colors into sRGB space, which is
+    /// requested with `WindowParams::srgb` and enabled with `SurfaceParams::set_srgb`.
+    pub srgb_backbuffer: bool,
+    /// Supports 3D textures.
+    pub texture_3d: bool,
+    /// Supports clamping the sampled mipmaps of textures with a base level, which is
+    /// required to stream the mipmaps. The streaming is disabled if it is false.
+    pub texture_base_level: bool,
+    /// Supports rasterizing the polygons as lines, see `SurfaceParams::set_wireframe`.
+    pub wireframe: bool,
+    /// Supports sampler objects natively. Otherwise the states of sampler are applied
+    /// to the sampled texture, and its own states are restored once it's sampled without
+    /// sampler.
+    pub sampler_objects: bool,
+    /// Maximum degree of anisotropic filtering, which is 1 if not supported.
+    pub max_anisotropy: u32,
+    /// Maximum number of color attachments of surfaces, the multiple render targets are
+    /// supported if it is greater than 1.
+    pub max_color_attachments: u32,
+    /// Maximum number of samples of multisampled render textures.
+    pub max_samples: u32,
+    /// Maximum number of textures that could be sampled in a draw.
+    pub max_texture_units: u32,
+    /// Maximum number of textures that could be sampled in vertex shaders, which is 0 if
+    /// the vertex texture fetch is not supported.
+    pub max_vertex_texture_units: u32,
+    /// Maximum number of four-component vectors of the uniform variables in vertex
+    /// shaders, each `mat4` takes four of them.
+    pub max_vertex_uniform_vectors: u32,
+}
@@ -0,0 +1,385 @@
+//! Screenshots and frame dumps of the window framebuffer.
+
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+
+use crate::math::prelude::{Aabb2, Vector2};
+use crate::res::request::{Request, Response};
+use crate::sched::prelude::{LatchProbe, LockLatch};
+
+use super::backends::frame::{Command, Frame};
+
+type Output = (PathBuf, Arc<LockLatch<Response>>);
+
+struct PendingCapture {
+    dimensions: Vector2<u32>,
+    pixels: Arc<LockLatch<Response>>,
+    outputs: Vec<Output>,
+}
+
+/// The captures of window framebuffer, which are read back at the end of frames and
+/// then encoded into PNG images on worker threads.
+#[derive(Default)]
+pub struct Captures {
+    screenshots: Vec<Output>,
+    readbacks: Vec<Arc<LockLatch<Response>>>,
+    // The directory and index of next frame to dump.
+    frame_dump: Option<(PathBuf, usize)>,
+    pending: Vec<PendingCapture>,
+}
+
+impl Captures {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a screenshot that will be captured at the end of current frame.
+    pub fn add_screenshot(&mut self, path: PathBuf) -> Request {
+        let latch = Request::latch();
+        self.screenshots.push((path, latch.clone()));
+        Request::new(latch)
+    }
+
+    /// Adds a readback of the raw pixels at the end of current frame.
+    pub fn add_readback(&mut self) -> Request {
+        let latch = Request::latch();
+        self.readbacks.push(latch.clone());
+        Request::new(latch)
+    }
+
+    pub fn set_frame_dump(&mut self, dir: Option<PathBuf>) {
+        self.frame_dump = dir.map(|v| (v, 0));
+    }
+
+    /// Schedules the readback of window framebuffer after all the draws of `frame`.
+    pub fn schedule(&mut self, frame: &mut Frame, dimensions: Vector2<u32>) {
+        let area = Aabb2::new([0, 0].into(), [dimensions.x, dimensions.y].into());
+        for latch in self.readbacks.drain(..) {
+            let cmd = Command::ReadFramebuffer(Box::new((area, latch)));
+            frame.cmds.push(cmd);
+        }
+
+        let mut outputs: Vec<Output> = self.screenshots.drain(..).collect();
+
+        if let Some((ref dir, ref mut index)) = self.frame_dump {
+            let path = dir.join(format!("{:06}.png", index));
+            outputs.push((path, Request::latch()));
+            *index += 1;
+        }
+
+        if outputs.is_empty() {
+            return;
+        }
+
+        let pixels = Request::latch();
+        let cmd = Command::ReadFramebuffer(Box::new((area, pixels.clone())));
+        frame.cmds.push(cmd);
+
+        self.pending.push(PendingCapture {
+            dimensions,
+            pixels,
+            outputs,
+        });
+    }
+
+    /// Encodes and writes the captures whose pixels have been read back.
+    pub fn advance(&mut self) {
+        let mut i = 0;
+        while i < self.pending.len() {
+            if !self.pending[i].pixels.is_set() {
+                i += 1;
+                continue;
+            }
+
+            let v = self.pending.swap_remove(i);
+            crate::sched::spawn(move || {
+                let rsp = v
+                    .pixels
+                    .take()
+                    .and_then(|pixels| encode_png(v.dimensions, &pixels));
+
+                for (path, latch) in v.outputs {
+                    let rsp = match rsp {
+                        Ok(ref bytes) => std::fs::write(&path, bytes)
+                            .map(|_| bytes.clone().into_boxed_slice())
+                            .map_err(|err| format_err!("Failed to write {:?}: {}", path, err)),
+                        Err(ref err) => Err(format_err!("Failed to capture {:?}: {}", path, err)),
+                    };
+
+                    if let Err(ref err) = rsp {
+                        warn!("{}", err);
+                    }
+
+                    latch.set(rsp);
+                }
+            });
+        }
+    }
+}
+
+/// Encodes the RGBA8 pixels of framebuffer into PNG image. The rows are flipped, since
+/// the framebuffer is read from bottom to top.
+pub fn encode_png(dimensions: Vector2<u32>, pixels: &[u8]) -> Result<Vec<u8>, failure::Error> {
+    let stride = dimensions.x as usize * 4;
+    if pixels.len() != stride * dimensions.y as usize {
+        bail!(
+            "The size of pixels does not match the dimensions {:?}.",
+            dimensions
+        );
+    }
+
+    let mut bytes = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+    let mut header = Vec::with_capacity(13);
+    header.write_u32::<BigEndian>(dimensions.x)?;
+    header.write_u32::<BigEndian>(dimensions.y)?;
+    // 8 bits per channel, RGBA, deflate, adaptive filters and no interlace.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(&mut bytes, b"IHDR", &header)?;
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+    if stride > 0 {
+        for row in pixels.chunks(stride).rev() {
+            // Rows are not filtered.
+            encoder.write_all(&[0])?;
+            encoder.write_all(row)?;
+        }
+    }
+
+    write_chunk(&mut bytes, b"IDAT", &encoder.finish()?)?;
+    write_chunk(&mut bytes, b"IEND", &[])?;
+    Ok(bytes)
+}
+
+/// Decodes the 8-bit RGB or RGBA PNG image into RGBA8 pixels, the rows are flipped into the
+/// order of framebuffer, from bottom to top.
+#[cfg_attr(not(feature = "golden-test"), allow(dead_code))]
+pub fn decode_png(bytes: &[u8]) -> Result<(Vector2<u32>, Vec<u8>), failure::Error> {
+    if bytes.len() < 8 || bytes[0..8] != [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A] {
+        bail!("[PNG] MAGIC number not match.");
+    }
+
+    let mut file = &bytes[8..];
+    let mut dimensions = Vector2::new(0, 0);
+    let mut channels = 0;
+    let mut data = Vec::new();
+
+    loop {
+        let len = file.read_u32::<BigEndian>()? as usize;
+        if file.len() < len + 8 {
+            bail!("[PNG] The chunk is out of bounds.");
+        }
+
+        let (ty, chunk) = (&file[0..4], &file[4..4 + len]);
+        match ty {
+            b"IHDR" => {
+                if len != 13 {
+                    bail!("[PNG] The IHDR chunk of {} bytes is invalid.", len);
+                }
+
+                let mut header = chunk;
+                dimensions.x = header.read_u32::<BigEndian>()?;
+                dimensions.y = header.read_u32::<BigEndian>()?;
+                channels = match (header.read_u8()?, header.read_u8()?) {
+                    (8, 2) => 3,
+                    (8, 6) => 4,
+                    (depth, color) => bail!(
+                        "[PNG] The bit depth {} of color type {} is not supported.",
+                        depth,
+                        color
+                    ),
+                };
+
+                if chunk[12] != 0 {
+                    bail!("[PNG] The interlaced images are not supported.");
+                }
+            }
+            b"IDAT" => data.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+
+        file = &file[len + 8..];
+    }
+
+    if channels == 0 {
+        bail!("[PNG] The IHDR chunk is missing.");
+    }
+
+    let mut filtered = Vec::new();
+    ZlibDecoder::new(&data[..]).read_to_end(&mut filtered)?;
+
+    let stride = dimensions.x as usize * channels;
+    if filtered.len() != (stride + 1) * dimensions.y as usize {
+        bail!(
+            "The size of pixels does not match the dimensions {:?}.",
+            dimensions
+        );
+    }
+
+    let mut rows = vec![0; stride * dimensions.y as usize];
+    for y in 0..dimensions.y as usize {
+        let filter = filtered[y * (stride + 1)];
+        let src = &filtered[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (prev, row) = rows.split_at_mut(y * stride);
+        let prev = if y > 0 {
+            &prev[prev.len() - stride..]
+        } else {
+            &[][..]
+        };
+        let row = &mut row[..stride];
+
+        for x in 0..stride {
+            let a = if x >= channels { row[x - channels] } else { 0 };
+            let b = if y > 0 { prev[x] } else { 0 };
+            let c = if x >= channels && y > 0 {
+                prev[x - channels]
+            } else {
+                0
+            };
+
+            row[x] = src[x].wrapping_add(match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => bail!("[PNG] Undefined filter type {}.", filter),
+            });
+        }
+    }
+
+    let mut pixels = Vec::with_capacity(dimensions.x as usize * dimensions.y as usize * 4);
+    if stride > 0 {
+        for row in rows.chunks(stride).rev() {
+            for v in row.chunks(channels) {
+                pixels.extend_from_slice(v);
+                if channels == 3 {
+                    pixels.push(255);
+                }
+            }
+        }
+    }
+
+    Ok((dimensions, pixels))
+}
+
+#[cfg_attr(not(feature = "golden-test"), allow(dead_code))]
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, ty: &[u8], data: &[u8]) -> Result<(), failure::Error> {
+    let mut crc = Crc::new();
+    crc.update(ty);
+    crc.update(data);
+
+    bytes.write_u32::<BigEndian>(data.len() as u32)?;
+    bytes.extend_from_slice(ty);
+    bytes.extend_from_slice(data);
+    bytes.write_u32::<BigEndian>(crc.sum())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn png() {
+        // The rows of 2x2 pixels from bottom to top.
+        let pixels = [
+            1, 2, 3, 4, 5, 6, 7, 8, //
+            9, 10, 11, 12, 13, 14, 15, 16,
+        ];
+
+        let bytes = encode_png(Vector2::new(2, 2), &pixels).unwrap();
+        assert_eq!(
+            &bytes[..8],
+            &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]
+        );
+        assert_eq!(&bytes[12..16], b"IHDR");
+        assert_eq!(&bytes[16..24], &[0, 0, 0, 2, 0, 0, 0, 2]);
+        assert_eq!(&bytes[bytes.len() - 8..bytes.len() - 4], b"IEND");
+
+        // The CRC of IEND chunk is constant.
+        assert_eq!(&bytes[bytes.len() - 4..], &[0xAE, 0x42, 0x60, 0x82]);
+
+        let len = (&bytes[33..37])
+            .iter()
+            .fold(0, |a, &b| (a << 8) | b as usize);
+        assert_eq!(&bytes[37..41], b"IDAT");
+
+        let mut rows = Vec::new();
+        ZlibDecoder::new(&bytes[41..41 + len])
+            .read_to_end(&mut rows)
+            .unwrap();
+
+        assert_eq!(
+            rows,
+            vec![0, 9, 10, 11, 12, 13, 14, 15, 16, 0, 1, 2, 3, 4, 5, 6, 7, 8]
+        );
+
+        assert!(encode_png(Vector2::new(3, 2), &pixels).is_err());
+
+        let (dimensions, decoded) = decode_png(&bytes).unwrap();
+        assert_eq!(dimensions, Vector2::new(2, 2));
+        assert_eq!(&decoded[..], &pixels[..]);
+        assert!(decode_png(&bytes[..bytes.len() - 12]).is_err());
+
+        // The IHDR chunk is truncated.
+        let mut truncated = bytes[..8].to_vec();
+        write_chunk(&mut truncated, b"IHDR", &bytes[16..28]).unwrap();
+        truncated.extend_from_slice(&bytes[33..]);
+        assert!(decode_png(&truncated).is_err());
+    }
+
+    #[test]
+    fn png_filters() {
+        let mut bytes = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        write_chunk(
+            &mut bytes,
+            b"IHDR",
+            &[0, 0, 0, 2, 0, 0, 0, 2, 8, 2, 0, 0, 0],
+        )
+        .unwrap();
+
+        // The rows [10, 20, 30, 40, 50, 60] and [15, 25, 35, 45, 55, 65] filtered with
+        // Sub and Paeth.
+        let rows = [
+            1, 10, 20, 30, 30, 30, 30, //
+            4, 5, 5, 5, 5, 5, 5,
+        ];
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(&rows).unwrap();
+        write_chunk(&mut bytes, b"IDAT", &encoder.finish().unwrap()).unwrap();
+        write_chunk(&mut bytes, b"IEND", &[]).unwrap();
+
+        let (dimensions, pixels) = decode_png(&bytes).unwrap();
+        assert_eq!(dimensions, Vector2::new(2, 2));
+        assert_eq!(
+            pixels,
+            vec![15, 25, 35, 255, 45, 55, 65, 255, 10, 20, 30, 255, 40, 50, 60, 255]
+        );
+    }
+}
@@ -1,5 +1,10 @@
-use crate::math::prelude::Aabb2;
-use crate::utils::prelude::{DataBuffer, HashValue};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{mem, ptr, slice, str};
+
+use crate::math::prelude::{Aabb2, Aabb3, Matrix4};
+use crate::utils::prelude::HashValue;
 
 use super::assets::prelude::*;
 use super::backends::frame::Command;
@@ -10,7 +15,7 @@ use super::MAX_UNIFORM_VARIABLES;
 #[derive(Default)]
 pub struct CommandBuffer {
     cmds: Vec<Command>,
-    bufs: DataBuffer,
+    bufs: FrameArena,
 }
 
 impl CommandBuffer {
@@ -19,7 +24,7 @@ impl CommandBuffer {
     pub fn new() -> Self {
         CommandBuffer {
             cmds: Vec::with_capacity(32),
-            bufs: DataBuffer::with_capacity(512),
+            bufs: FrameArena::with_capacity(512),
         }
     }
 
@@ -28,10 +33,17 @@ impl CommandBuffer {
     pub fn draw(&mut self, dc: Draw) {
         let len = dc.uniforms_len;
         let ptr = self.bufs.extend_from_slice(&dc.uniforms[0..len]);
-        let cmd = Command::Draw(dc.shader, dc.mesh, dc.mesh_index, ptr);
+        let cmd = Command::Draw(dc.shader, dc.mesh, dc.mesh_index, dc.instances, ptr);
         self.cmds.push(cmd);
     }
 
+    /// Copies the matrices into this buffer, and returns an uniform variable of `mat4`
+    /// array which could be bound to the draws of this buffer.
+    #[inline]
+    pub fn matrix_array(&mut self, matrices: &[Matrix4<f32>]) -> UniformVariable {
+        matrix_array(&mut self.bufs, matrices)
+    }
+
     /// Updates the scissor test of surface.
     ///
     /// The test is initially disabled. While the test is enabled, only pixels that lie within
@@ -47,12 +59,78 @@ impl CommandBuffer {
         self.cmds.push(Command::UpdateViewport(viewport));
     }
 
+    /// Begins an occlusion query, all the draws submitted before the matching `end_query`
+    /// would be counted into it.
+    #[inline]
+    pub fn begin_query(&mut self, query: QueryHandle) {
+        self.cmds.push(Command::BeginQuery(query));
+    }
+
+    /// Ends an occlusion query. Its result could be fetched with `video::query_result` in
+    /// later frames.
+    #[inline]
+    pub fn end_query(&mut self, query: QueryHandle) {
+        self.cmds.push(Command::EndQuery(query));
+    }
+
+    /// Begins a named group of commands, which is shown in the frame captures of graphics
+    /// debuggers. The groups could be nested, and are ended with `pop_debug_group`.
+    #[inline]
+    pub fn push_debug_group(&mut self, name: &str) {
+        let ptr = self.bufs.extend_from_str(name);
+        self.cmds.push(Command::PushDebugGroup(ptr));
+    }
+
+    /// Ends the last group of commands.
+    #[inline]
+    pub fn pop_debug_group(&mut self) {
+        self.cmds.push(Command::PopDebugGroup);
+    }
+
     /// Update a contiguous subregion of an existing two-dimensional texture object.
     #[inline]
     pub fn update_texture(&mut self, id: TextureHandle, area: Aabb2<u32>, bytes: &[u8]) {
+        self.update_texture_mip_area(id, 0, area, bytes);
+    }
+
+    /// Update a contiguous subregion of the mipmap `level` of an existing two-dimensional
+    /// texture object, the `area` is measured in the pixels of that level.
+    #[inline]
+    pub fn update_texture_mip_area(
+        &mut self,
+        id: TextureHandle,
+        level: usize,
+        area: Aabb2<u32>,
+        bytes: &[u8],
+    ) {
+        let bufs = &mut self.bufs;
+        let ptr = bufs.extend_from_slice(bytes);
+        self.cmds.push(Command::UpdateTexture(id, level, area, ptr));
+    }
+
+    /// Generates the full mipmap chain of texture from its base level, e.g. after the
+    /// texture is updated with the commands before.
+    #[inline]
+    pub fn generate_mipmaps(&mut self, id: TextureHandle) {
+        self.cmds.push(Command::GenerateMipmaps(id));
+    }
+
+    /// Update a subset of uniform buffer. Use `offset` specifies the offset into the
+    /// buffer object's data store where data replacement will begin, measured in bytes.
+    #[inline]
+    pub fn update_uniform_buffer(&mut self, id: UniformBufferHandle, offset: usize, bytes: &[u8]) {
+        let bufs = &mut self.bufs;
+        let ptr = bufs.extend_from_slice(bytes);
+        self.cmds
+            .push(Command::UpdateUniformBuffer(id, offset, ptr));
+    }
+
+    /// Update a contiguous subregion of an existing three-dimensional texture object.
+    #[inline]
+    pub fn update_texture_3d(&mut self, id: Texture3DHandle, area: Aabb3<u32>, bytes: &[u8]) {
         let bufs = &mut self.bufs;
         let ptr = bufs.extend_from_slice(bytes);
-        self.cmds.push(Command::UpdateTexture(id, area, ptr));
+        self.cmds.push(Command::UpdateTexture3D(id, area, ptr));
     }
 
     /// Update a subset of dynamic vertex buffer. Use `offset` specifies the offset
@@ -75,27 +153,68 @@ impl CommandBuffer {
         self.cmds.push(Command::UpdateIndexBuffer(id, offset, ptr));
     }
 
+    /// Update a subset of instance buffer. Use `offset` specifies the offset into the
+    /// buffer object's data store where data replacement will begin, measured in bytes.
+    #[inline]
+    pub fn update_instance_buffer(
+        &mut self,
+        id: InstanceBufferHandle,
+        offset: usize,
+        bytes: &[u8],
+    ) {
+        let bufs = &mut self.bufs;
+        let ptr = bufs.extend_from_slice(bytes);
+        self.cmds
+            .push(Command::UpdateInstanceBuffer(id, offset, ptr));
+    }
+
     /// Clears the batch, and submits all the commands into video device. Its guaranteed that
     /// all the commands in this batch will be executed one by one in order.
     ///
+    /// The whole batch is discarded if any of the commands failed the validation.
+    ///
     /// Notes that this method has no effect on the allocated capacity of the underlying storage.
     pub fn submit(&mut self, surface: SurfaceHandle) -> Result<()> {
+        if let Err(err) = validate(surface, self.cmds.iter(), &self.bufs) {
+            self.cmds.clear();
+            self.bufs.clear();
+            return Err(err);
+        }
+
         let doubele_frame = unsafe { super::inside::frames() };
         let mut frame = doubele_frame.write();
         frame.cmds.push(Command::Bind(surface));
 
         for v in self.cmds.drain(..) {
             match v {
-                Command::Draw(shader, mesh, mesh_index, ptr) => {
-                    let vars = self.bufs.as_slice(ptr);
-                    let ptr = frame.bufs.extend_from_slice(vars);
-                    let cmd = Command::Draw(shader, mesh, mesh_index, ptr);
+                Command::Draw(shader, mesh, mesh_index, instances, ptr) => {
+                    let ptr = copy_uniforms(&self.bufs, ptr, &mut frame.bufs);
+                    let cmd = Command::Draw(shader, mesh, mesh_index, instances, ptr);
                     frame.cmds.push(cmd);
                 }
 
-                Command::UpdateTexture(id, area, ptr) => {
+                Command::PushDebugGroup(ptr) => {
+                    let ptr = frame.bufs.extend_from_str(self.bufs.as_str(ptr));
+                    frame.cmds.push(Command::PushDebugGroup(ptr));
+                }
+
+                Command::UpdateTexture(id, level, area, ptr) => {
+                    let ptr = frame.bufs.extend_from_slice(self.bufs.as_slice(ptr));
+                    frame
+                        .cmds
+                        .push(Command::UpdateTexture(id, level, area, ptr));
+                }
+
+                Command::UpdateUniformBuffer(id, offset, ptr) => {
+                    let ptr = frame.bufs.extend_from_slice(self.bufs.as_slice(ptr));
+                    frame
+                        .cmds
+                        .push(Command::UpdateUniformBuffer(id, offset, ptr));
+                }
+
+                Command::UpdateTexture3D(id, area, ptr) => {
                     let ptr = frame.bufs.extend_from_slice(self.bufs.as_slice(ptr));
-                    frame.cmds.push(Command::UpdateTexture(id, area, ptr));
+                    frame.cmds.push(Command::UpdateTexture3D(id, area, ptr));
                 }
 
                 Command::UpdateVertexBuffer(id, offset, ptr) => {
@@ -109,6 +228,13 @@ impl CommandBuffer {
                     frame.cmds.push(Command::UpdateIndexBuffer(id, offset, ptr));
                 }
 
+                Command::UpdateInstanceBuffer(id, offset, ptr) => {
+                    let ptr = frame.bufs.extend_from_slice(self.bufs.as_slice(ptr));
+                    frame
+                        .cmds
+                        .push(Command::UpdateInstanceBuffer(id, offset, ptr));
+                }
+
                 other => frame.cmds.push(other),
             }
         }
@@ -119,16 +245,20 @@ impl CommandBuffer {
 }
 
 /// The draw call buffer of video system, which provides simple sort functionality for convenience.
+///
+/// The buffers are `Send`, so the draws of a large scene could be recorded from multiple
+/// threads in parallel, with one buffer per job, and merged into one submission with
+/// `DrawCommandBuffer::submit_all`.
 pub struct DrawCommandBuffer<T: Ord + Copy> {
     cmds: Vec<(T, Command)>,
-    bufs: DataBuffer,
+    bufs: FrameArena,
 }
 
 impl<T: Ord + Copy> Default for DrawCommandBuffer<T> {
     fn default() -> Self {
         DrawCommandBuffer {
             cmds: Vec::with_capacity(32),
-            bufs: DataBuffer::with_capacity(512),
+            bufs: FrameArena::with_capacity(512),
         }
     }
 }
@@ -144,32 +274,330 @@ impl<T: Ord + Copy> DrawCommandBuffer<T> {
     pub fn draw(&mut self, order: T, dc: Draw) {
         let len = dc.uniforms_len;
         let ptr = self.bufs.extend_from_slice(&dc.uniforms[0..len]);
-        let cmd = Command::Draw(dc.shader, dc.mesh, dc.mesh_index, ptr);
+        let cmd = Command::Draw(dc.shader, dc.mesh, dc.mesh_index, dc.instances, ptr);
         self.cmds.push((order, cmd));
     }
 
+    /// Copies the matrices into this buffer, and returns an uniform variable of `mat4`
+    /// array which could be bound to the draws of this buffer, e.g. the bone matrices
+    /// of skinned meshes.
+    #[inline]
+    pub fn matrix_array(&mut self, matrices: &[Matrix4<f32>]) -> UniformVariable {
+        matrix_array(&mut self.bufs, matrices)
+    }
+
     /// Clears the batch, and submits all the sorted commands into video device. Its guaranteed that
     /// all the commands in this batch will be executed one by one in order.
     ///
+    /// The whole batch is discarded if any of the draws failed the validation.
+    ///
     /// Notes that this method has no effect on the allocated capacity of the underlying storage.
     pub fn submit(&mut self, surface: SurfaceHandle) -> Result<()> {
-        let doubele_frame = unsafe { super::inside::frames() };
-        let mut frame = doubele_frame.write();
-        frame.cmds.push(Command::Bind(surface));
+        Self::submit_all(surface, slice::from_mut(self))
+    }
 
-        self.cmds.as_mut_slice().sort_by_key(|v| v.0);
-        for v in self.cmds.drain(..) {
-            if let (_, Command::Draw(shader, mesh, mesh_index, ptr)) = v {
-                let vars = self.bufs.as_slice(ptr);
-                let ptr = frame.bufs.extend_from_slice(vars);
-                let cmd = Command::Draw(shader, mesh, mesh_index, ptr);
-                frame.cmds.push(cmd);
+    /// Clears the batches, and submits the commands of all the buffers into video device,
+    /// which are sorted as a whole. The sort is stable, the commands with the same order are
+    /// executed in the order of buffers, and then the order they are recorded. So the result
+    /// is deterministic no matter which threads recorded the buffers.
+    ///
+    /// All the batches are discarded if any of the draws failed the validation.
+    pub fn submit_all(surface: SurfaceHandle, buffers: &mut [Self]) -> Result<()> {
+        let mut result = Ok(());
+        for v in buffers.iter() {
+            result = result.and_then(|_| validate(surface, v.cmds.iter().map(|v| &v.1), &v.bufs));
+        }
+
+        if result.is_ok() {
+            let mut cmds = Vec::with_capacity(buffers.iter().map(|v| v.cmds.len()).sum());
+            for (i, v) in buffers.iter().enumerate() {
+                cmds.extend(v.cmds.iter().map(|v| (v.0, i, &v.1)));
+            }
+
+            cmds.sort_by_key(|v| v.0);
+
+            let doubele_frame = unsafe { super::inside::frames() };
+            let mut frame = doubele_frame.write();
+            frame.cmds.push(Command::Bind(surface));
+
+            for (_, i, v) in cmds {
+                if let Command::Draw(shader, mesh, mesh_index, instances, ptr) = *v {
+                    let ptr = copy_uniforms(&buffers[i].bufs, ptr, &mut frame.bufs);
+                    let cmd = Command::Draw(shader, mesh, mesh_index, instances, ptr);
+                    frame.cmds.push(cmd);
+                }
             }
         }
 
-        self.bufs.clear();
-        Ok(())
+        for v in buffers.iter_mut() {
+            v.cmds.clear();
+            v.bufs.clear();
+        }
+
+        result
+    }
+}
+
+// Validates the commands that are going to be submitted into `surface`, if the validation
+// layer or the tracking of resources is enabled.
+fn validate<'a, T>(surface: SurfaceHandle, cmds: T, bufs: &FrameArena) -> Result<()>
+where
+    T: Iterator<Item = &'a Command> + Clone,
+{
+    // The arrays of matrices are always checked, the ones that are not allocated from
+    // `bufs` since last clear point to arbitrary data.
+    for v in cmds.clone() {
+        if let Command::Draw(_, _, _, _, ptr) = *v {
+            for &(_, v) in bufs.as_slice(ptr) {
+                if let UniformVariable::Matrix4fArray(array) = v {
+                    if array.generation != bufs.generation {
+                        return Err(Error::ValidationFailed(
+                            "the array of matrices is allocated from another buffer or frame."
+                                .into(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    let ctx = super::inside::ctx();
+    if ctx.validation() || ctx.tracking() {
+        ctx.validate(&Command::Bind(surface), bufs)?;
+        for v in cmds {
+            ctx.validate(v, bufs)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn matrix_array(bufs: &mut FrameArena, matrices: &[Matrix4<f32>]) -> UniformVariable {
+    let ptr = bufs.extend_from_slice(matrices);
+    UniformVariable::Matrix4fArray(MatrixArray {
+        ptr: ArenaPtr {
+            chunk: ptr.chunk,
+            offset: ptr.offset,
+            size: ptr.size,
+            _phantom: PhantomData,
+        },
+        generation: bufs.generation,
+    })
+}
+
+// Copies the uniform variables of draw from `src` into `dst`, along with the arrays of
+// matrices they refer to.
+fn copy_uniforms(
+    src: &FrameArena,
+    ptr: ArenaPtr<[(HashValue<str>, UniformVariable)]>,
+    dst: &mut FrameArena,
+) -> ArenaPtr<[(HashValue<str>, UniformVariable)]> {
+    let vars = src.as_slice(ptr);
+    if vars
+        .iter()
+        .all(|v| v.1.variable_type() != UniformVariableType::Matrix4fArray)
+    {
+        return dst.extend_from_slice(vars);
     }
+
+    let vars: Vec<_> = vars
+        .iter()
+        .map(|&(field, v)| match v {
+            UniformVariable::Matrix4fArray(array) => {
+                let array = MatrixArray {
+                    ptr: dst.extend_from_slice(src.as_slice(array.ptr)),
+                    generation: dst.generation,
+                };
+
+                (field, UniformVariable::Matrix4fArray(array))
+            }
+            _ => (field, v),
+        })
+        .collect();
+
+    dst.extend_from_slice(&vars)
+}
+
+/// The size of chunks of `FrameArena` in bytes.
+const ARENA_CHUNK_SIZE: usize = 64 * 1024;
+
+/// A bump allocator that backs the per-frame data of commands, e.g. the uniform variables
+/// of draws, the bytes of updates and the names of debug groups.
+///
+/// The data are stored in chunks which are never moved or freed once allocated. `clear`
+/// only rewinds the cursor, so the allocations are amortized over frames.
+#[derive(Clone)]
+pub struct FrameArena {
+    // The chunks are made of `u64`, so any data aligned to 8 bytes or less could be stored.
+    chunks: Vec<Box<[u64]>>,
+    chunk: usize,
+    cursor: usize,
+    // The unique number of arenas, which is changed by `clear` too.
+    generation: usize,
+}
+
+impl Default for FrameArena {
+    fn default() -> Self {
+        FrameArena {
+            chunks: Vec::new(),
+            chunk: 0,
+            cursor: 0,
+            generation: next_generation(),
+        }
+    }
+}
+
+impl FrameArena {
+    /// Creates a new arena with the first chunk of specified capacity.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut arena = FrameArena::default();
+        if capacity > 0 {
+            arena.chunks.push(chunk(capacity));
+        }
+
+        arena
+    }
+
+    /// Rewinds the arena, all the pointers allocated before are invalidated.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.chunk = 0;
+        self.cursor = 0;
+        self.generation = next_generation();
+    }
+
+    /// Gets the number of bytes allocated by the chunks.
+    pub fn memory(&self) -> usize {
+        self.chunks
+            .iter()
+            .map(|v| v.len() * mem::size_of::<u64>())
+            .sum()
+    }
+
+    /// Clones and appends all elements in a slice to the arena.
+    pub fn extend_from_slice<T: Copy>(&mut self, slice: &[T]) -> ArenaPtr<[T]> {
+        let size = mem::size_of::<T>() * slice.len();
+        let (chunk, offset) = self.alloc(size, mem::align_of::<T>());
+
+        unsafe {
+            let dst = (self.chunks[chunk].as_mut_ptr() as *mut u8).add(offset);
+            ptr::copy_nonoverlapping(slice.as_ptr() as *const u8, dst, size);
+        }
+
+        ArenaPtr {
+            chunk: chunk as u32,
+            offset: offset as u32,
+            size: size as u32,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Clones and appends all bytes in a string slice to the arena.
+    pub fn extend_from_str(&mut self, value: &str) -> ArenaPtr<str> {
+        let ptr = self.extend_from_slice(value.as_bytes());
+        ArenaPtr {
+            chunk: ptr.chunk,
+            offset: ptr.offset,
+            size: ptr.size,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns a object slice indicated by `ArenaPtr`.
+    #[inline]
+    pub fn as_slice<T: Copy>(&self, ptr: ArenaPtr<[T]>) -> &[T] {
+        let bytes = self.as_bytes(ptr);
+        let len = bytes.len().checked_div(mem::size_of::<T>()).unwrap_or(0);
+        unsafe { slice::from_raw_parts(bytes.as_ptr() as *const T, len) }
+    }
+
+    /// Returns string slice indicated by `ArenaPtr`.
+    #[inline]
+    pub fn as_str(&self, ptr: ArenaPtr<str>) -> &str {
+        str::from_utf8(self.as_bytes(ptr)).unwrap()
+    }
+
+    #[inline]
+    fn as_bytes<T: ?Sized>(&self, ptr: ArenaPtr<T>) -> &[u8] {
+        let chunk = &self.chunks[ptr.chunk as usize];
+        let len = chunk.len() * mem::size_of::<u64>();
+        let bytes = unsafe { slice::from_raw_parts(chunk.as_ptr() as *const u8, len) };
+        &bytes[ptr.offset as usize..(ptr.offset + ptr.size) as usize]
+    }
+
+    fn alloc(&mut self, size: usize, align: usize) -> (usize, usize) {
+        assert!(align <= mem::align_of::<u64>());
+
+        loop {
+            if let Some(chunk) = self.chunks.get(self.chunk) {
+                let offset = (self.cursor + align - 1) & !(align - 1);
+                if offset + size <= chunk.len() * mem::size_of::<u64>() {
+                    self.cursor = offset + size;
+                    return (self.chunk, offset);
+                }
+
+                // Skips to the next chunk that is kept from the previous frames.
+                if self.chunk + 1 < self.chunks.len() {
+                    self.chunk += 1;
+                    self.cursor = 0;
+                    continue;
+                }
+            }
+
+            self.chunks.push(chunk(size.max(ARENA_CHUNK_SIZE)));
+            self.chunk = self.chunks.len() - 1;
+            self.cursor = 0;
+        }
+    }
+}
+
+impl fmt::Debug for FrameArena {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FrameArena")
+            .field("chunks", &self.chunks.len())
+            .field("memory", &self.memory())
+            .finish()
+    }
+}
+
+fn next_generation() -> usize {
+    static GENERATION: AtomicUsize = AtomicUsize::new(0);
+    GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+fn chunk(size: usize) -> Box<[u64]> {
+    let len = (size + mem::size_of::<u64>() - 1) / mem::size_of::<u64>();
+    vec![0; len].into_boxed_slice()
+}
+
+/// A view into `FrameArena`, indicates where the object `T` stored.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ArenaPtr<T: ?Sized> {
+    chunk: u32,
+    offset: u32,
+    size: u32,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: ?Sized> Clone for ArenaPtr<T> {
+    fn clone(&self) -> Self {
+        ArenaPtr {
+            chunk: self.chunk,
+            offset: self.offset,
+            size: self.size,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Copy for ArenaPtr<T> {}
+
+/// The array of `mat4` that is allocated with `matrix_array` of command buffers.
+#[derive(Debug, Copy, Clone)]
+pub struct MatrixArray {
+    pub(crate) ptr: ArenaPtr<[[[f32; 4]; 4]]>,
+    // The generation of arena when the array is allocated.
+    pub(crate) generation: usize,
 }
 
 /// A draw call.
@@ -181,6 +609,7 @@ pub struct Draw {
     pub shader: ShaderHandle,
     pub mesh: MeshHandle,
     pub mesh_index: MeshIndex,
+    pub instances: Option<(InstanceBufferHandle, u32)>,
 }
 
 impl Draw {
@@ -193,9 +622,16 @@ impl Draw {
             uniforms: [nil; MAX_UNIFORM_VARIABLES],
             uniforms_len: 0,
             mesh_index: MeshIndex::All,
+            instances: None,
         }
     }
 
+    /// Draws `num` instances of the mesh at once, the per-instance attributes declared
+    /// with `AttributeLayoutBuilder::with_instanced` are sourced from `buffer`.
+    pub fn set_instance_data(&mut self, buffer: InstanceBufferHandle, num: u32) {
+        self.instances = Some((buffer, num));
+    }
+
     /// Binds the named field with `UniformVariable`.
     pub fn set_uniform_variable<F, V>(&mut self, field: F, variable: V)
     where
@@ -218,3 +654,121 @@ impl Draw {
         self.uniforms_len += 1;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::handle::HandleLike;
+
+    #[test]
+    fn send() {
+        fn is_send<T: Send>() {}
+        is_send::<CommandBuffer>();
+        is_send::<DrawCommandBuffer<u64>>();
+    }
+
+    #[test]
+    fn queries() {
+        let query = QueryHandle::new(1, 1);
+        let mut cmds = CommandBuffer::new();
+        cmds.begin_query(query);
+        cmds.end_query(query);
+
+        match cmds.cmds[..] {
+            [Command::BeginQuery(begin), Command::EndQuery(end)] => {
+                assert_eq!(begin, query);
+                assert_eq!(end, query);
+            }
+            _ => panic!("unexpected commands."),
+        }
+    }
+
+    #[test]
+    fn debug_groups() {
+        let mut cmds = CommandBuffer::new();
+        cmds.push_debug_group("shadows");
+        cmds.pop_debug_group();
+
+        match cmds.cmds[..] {
+            [Command::PushDebugGroup(ptr), Command::PopDebugGroup] => {
+                assert_eq!(cmds.bufs.as_str(ptr), "shadows");
+            }
+            _ => panic!("unexpected commands."),
+        }
+    }
+
+    #[test]
+    fn arena() {
+        let mut arena = FrameArena::with_capacity(16);
+
+        let bytes = arena.extend_from_slice(&[1u8, 2, 3]);
+        let ints = arena.extend_from_slice(&[4u32, 5]);
+        assert_eq!(ints.offset % 4, 0);
+        assert_eq!(arena.as_slice(bytes), &[1, 2, 3]);
+        assert_eq!(arena.as_slice(ints), &[4, 5]);
+
+        let large = [7u64; 1024 * 16];
+        let large = arena.extend_from_slice(&large[..]);
+        let text = arena.extend_from_str("debug group");
+        assert_eq!(arena.as_slice(large).len(), 1024 * 16);
+        assert_eq!(arena.as_str(text), "debug group");
+        assert_eq!(arena.as_slice(ints), &[4, 5]);
+
+        let memory = arena.memory();
+        for _ in 0..4 {
+            arena.clear();
+            arena.extend_from_slice(&[1u8, 2, 3]);
+            arena.extend_from_slice(&[7u64; 1024 * 16][..]);
+            arena.extend_from_str("debug group");
+        }
+
+        assert_eq!(arena.memory(), memory);
+    }
+
+    #[test]
+    fn matrix_array() {
+        use crate::math::prelude::SquareMatrix;
+
+        let mut src = FrameArena::with_capacity(16);
+        let mut dst = FrameArena::with_capacity(16);
+        dst.extend_from_slice(&[1u8, 2, 3]);
+
+        let matrices = [Matrix4::from_scale(2.0), Matrix4::identity()];
+        let vars = [
+            ("u_Bones".into(), super::matrix_array(&mut src, &matrices)),
+            ("u_Scale".into(), UniformVariable::F32(1.0)),
+        ];
+
+        let ptr = src.extend_from_slice(&vars);
+        let ptr = copy_uniforms(&src, ptr, &mut dst);
+        src.clear();
+        src.extend_from_slice(&[0u64; 64][..]);
+
+        let vars = dst.as_slice(ptr);
+        assert_eq!(vars.len(), 2);
+        match vars[0].1 {
+            UniformVariable::Matrix4fArray(array) => {
+                let expected: [[[f32; 4]; 4]; 2] = [matrices[0].into(), matrices[1].into()];
+                assert_eq!(dst.as_slice(array.ptr), &expected);
+                assert_eq!(array.generation, dst.generation);
+            }
+            v => panic!("unexpected variable {:?}.", v),
+        }
+    }
+
+    #[test]
+    fn stale_matrix_array() {
+        use crate::math::prelude::SquareMatrix;
+
+        let mut cmds = CommandBuffer::new();
+        let bones = cmds.matrix_array(&[Matrix4::identity()]);
+        cmds.bufs.clear();
+
+        let mut dc = Draw::new(ShaderHandle::new(1, 1), MeshHandle::new(1, 1));
+        dc.set_uniform_variable("u_Bones", bones);
+        cmds.draw(dc);
+
+        let surface = SurfaceHandle::new(1, 1);
+        assert!(validate(surface, cmds.cmds.iter(), &cmds.bufs).is_err());
+    }
+}
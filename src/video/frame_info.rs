@@ -0,0 +1,174 @@
+//! The statistics of video device during last frame.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::assets::surface::SurfaceHandle;
+
+/// The statistics of video device during last frame, which could be displayed in a
+/// performance HUD.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphicsFrameInfo {
+    /// The number of draw calls.
+    pub drawcall: u32,
+    /// The number of triangles drawn.
+    pub triangles: u32,
+    /// The number of GPU state changes, e.g. binding shaders, meshes, textures and
+    /// framebuffers.
+    pub state_changes: u32,
+    /// The GPU time spent on each surface, which is measured with timer queries and lags
+    /// a few frames behind. It's always empty if `Capabilities::timer_query` is false.
+    pub surfaces: Vec<(SurfaceHandle, Duration)>,
+    /// The size in bytes of all the meshes alive.
+    pub mesh_memory: usize,
+    /// The size in bytes of all the textures alive.
+    pub texture_memory: usize,
+    /// The duration between the presentations of last two frames.
+    pub frame_interval: Duration,
+    /// The estimated interval of vertical blanks, which is the shortest frame interval of
+    /// recent frames. It's zero if vsync is off.
+    pub vblank_interval: Duration,
+    /// The number of vertical blanks missed by last frame, e.g. a frame that takes 25ms
+    /// on a 60Hz monitor misses one. It's always zero if vsync is off.
+    pub missed_vblanks: u32,
+    /// The number of vertical blanks missed since the start of application.
+    pub total_missed_vblanks: u64,
+}
+
+impl GraphicsFrameInfo {
+    /// Gets the GPU time spent on all the surfaces.
+    pub fn gpu_time(&self) -> Duration {
+        self.surfaces
+            .iter()
+            .fold(Duration::from_secs(0), |acc, v| acc + v.1)
+    }
+
+    /// Gets the GPU time spent on `surface`.
+    pub fn surface_time(&self, surface: SurfaceHandle) -> Option<Duration> {
+        self.surfaces.iter().find(|v| v.0 == surface).map(|v| v.1)
+    }
+
+    /// Replaces the GPU time of surfaces with the results of timer queries in nanoseconds,
+    /// the results of a surface drawn several times are summed up. The last known times
+    /// are kept if there are no results.
+    pub(crate) fn collect_timers(&mut self, results: &mut Vec<(SurfaceHandle, u64)>) {
+        if results.is_empty() {
+            return;
+        }
+
+        self.surfaces.clear();
+        for (surface, ns) in results.drain(..) {
+            let duration = Duration::from_nanos(ns);
+            match self.surfaces.iter_mut().find(|v| v.0 == surface) {
+                Some(v) => v.1 += duration,
+                None => self.surfaces.push((surface, duration)),
+            }
+        }
+    }
+}
+
+// The number of recent frames that the interval of vertical blanks is estimated from.
+const VBLANK_SAMPLES: usize = 120;
+
+/// Counts the missed vertical blanks with the intervals between presented frames.
+pub(crate) struct VBlankTracker {
+    last: Option<Duration>,
+    intervals: VecDeque<Duration>,
+}
+
+impl VBlankTracker {
+    pub fn new() -> Self {
+        VBlankTracker {
+            last: None,
+            intervals: VecDeque::with_capacity(VBLANK_SAMPLES),
+        }
+    }
+
+    /// Advances with the timestamp of the presentation of a frame.
+    pub fn advance(&mut self, now: Duration, vsync: bool, info: &mut GraphicsFrameInfo) {
+        let interval = match self.last.replace(now) {
+            Some(last) if now > last => now - last,
+            _ => return,
+        };
+
+        info.frame_interval = interval;
+        if !vsync {
+            self.intervals.clear();
+            info.vblank_interval = Duration::from_secs(0);
+            info.missed_vblanks = 0;
+            return;
+        }
+
+        if self.intervals.len() >= VBLANK_SAMPLES {
+            self.intervals.pop_front();
+        }
+
+        self.intervals.push_back(interval);
+
+        let vblank = *self.intervals.iter().min().unwrap();
+        let vblank_ns = vblank.as_nanos();
+        let interval_ns = interval.as_nanos();
+
+        // Rounds to the nearest number of vertical blanks, since the timestamps jitter.
+        let vblanks = (interval_ns + vblank_ns / 2) / vblank_ns;
+        info.vblank_interval = vblank;
+        info.missed_vblanks = vblanks.saturating_sub(1) as u32;
+        info.total_missed_vblanks += u64::from(info.missed_vblanks);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::handle::HandleLike;
+
+    #[test]
+    fn timers() {
+        let (a, b) = (SurfaceHandle::new(1, 1), SurfaceHandle::new(2, 1));
+        let mut info = GraphicsFrameInfo::default();
+        assert_eq!(info.gpu_time(), Duration::from_secs(0));
+
+        let mut results = vec![(a, 1000), (b, 500), (a, 2000)];
+        info.collect_timers(&mut results);
+        assert!(results.is_empty());
+        assert_eq!(info.surface_time(a), Some(Duration::from_nanos(3000)));
+        assert_eq!(info.surface_time(b), Some(Duration::from_nanos(500)));
+        assert_eq!(info.gpu_time(), Duration::from_nanos(3500));
+
+        // The times lag behind, so they are kept until the next results are available.
+        info.collect_timers(&mut results);
+        assert_eq!(info.gpu_time(), Duration::from_nanos(3500));
+
+        results.push((b, 100));
+        info.collect_timers(&mut results);
+        assert_eq!(info.surface_time(a), None);
+        assert_eq!(info.gpu_time(), Duration::from_nanos(100));
+    }
+
+    #[test]
+    fn vblanks() {
+        let mut tracker = VBlankTracker::new();
+        let mut info = GraphicsFrameInfo::default();
+        let ms = |v: f64| Duration::from_micros((v * 1000.0) as u64);
+
+        tracker.advance(ms(0.0), true, &mut info);
+        tracker.advance(ms(16.6), true, &mut info);
+        assert_eq!(info.frame_interval, ms(16.6));
+        assert_eq!(info.vblank_interval, ms(16.6));
+        assert_eq!(info.missed_vblanks, 0);
+
+        tracker.advance(ms(50.0), true, &mut info);
+        assert_eq!(info.vblank_interval, ms(16.6));
+        assert_eq!(info.missed_vblanks, 1);
+
+        tracker.advance(ms(66.9), true, &mut info);
+        assert_eq!(info.missed_vblanks, 0);
+        assert_eq!(info.total_missed_vblanks, 1);
+
+        tracker.advance(ms(100.0), false, &mut info);
+        assert_eq!(info.frame_interval, ms(33.1));
+        assert_eq!(info.vblank_interval, Duration::from_secs(0));
+        assert_eq!(info.missed_vblanks, 0);
+        assert_eq!(info.total_missed_vblanks, 1);
+    }
+}
@@ -0,0 +1,625 @@
+//! The frame graph, which derives the order of surfaces from the render textures that
+//! passes read and write.
+//!
+//! Instead of ordering surfaces by hand, every pass declares the render textures it
+//! samples and the ones it renders into. The graph sorts the passes topologically, so
+//! a texture is written by all of its writers before being read, and validates the
+//! usages of attachments. Passes that render into nothing target the window framebuffer,
+//! and are executed after the others in the order they are added.
+//!
+//! The transient textures are created and owned by the graph. Transient textures whose
+//! lifetimes do not overlap share the same render texture object if they have the same
+//! `RenderTextureParams`, which saves video memory.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! let mut params = RenderTextureParams::default();
+//! params.dimensions = (256, 256).into();
+//!
+//! let mut graph = FrameGraph::new();
+//! let color = graph.create_texture("color", params);
+//!
+//! let pass = FramePass::new("scene").with_color(color);
+//! graph.add_pass(pass, |ctx| {
+//!     let mut dc = DrawCommandBuffer::<u32>::new();
+//!     // Draws into `color`...
+//!     dc.submit(ctx.surface())?;
+//!     Ok(())
+//! });
+//!
+//! let pass = FramePass::new("present").with_read(color);
+//! graph.add_pass(pass, move |ctx| {
+//!     // Samples `ctx.texture(color)`, and draws into window framebuffer...
+//!     Ok(())
+//! });
+//!
+//! graph.execute().unwrap();
+//! ```
+
+use crate::errors::*;
+use crate::math::prelude::Color;
+
+use super::assets::prelude::*;
+use super::MAX_FRAMEBUFFER_ATTACHMENTS;
+
+/// The render texture declared in `FrameGraph`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FrameGraphTexture(usize);
+
+#[derive(Debug, Copy, Clone)]
+enum TextureSource {
+    Transient(RenderTextureParams),
+    Imported(RenderTextureHandle),
+}
+
+/// The declaration of the render textures that a pass reads and writes.
+#[derive(Debug, Clone)]
+pub struct FramePass {
+    name: String,
+    reads: Vec<FrameGraphTexture>,
+    colors: Vec<FrameGraphTexture>,
+    depth_stencil: Option<FrameGraphTexture>,
+    clear_color: Option<Color<f32>>,
+    clear_depth: Option<f32>,
+    clear_stencil: Option<i32>,
+}
+
+impl FramePass {
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        let params = SurfaceParams::default();
+        FramePass {
+            name: name.into(),
+            reads: Vec::new(),
+            colors: Vec::new(),
+            depth_stencil: None,
+            clear_color: params.clear_color,
+            clear_depth: params.clear_depth,
+            clear_stencil: params.clear_stencil,
+        }
+    }
+
+    /// Declares a texture that would be sampled by this pass.
+    #[inline]
+    pub fn with_read(mut self, texture: FrameGraphTexture) -> Self {
+        self.reads.push(texture);
+        self
+    }
+
+    /// Declares a color attachment that this pass renders into.
+    #[inline]
+    pub fn with_color(mut self, texture: FrameGraphTexture) -> Self {
+        self.colors.push(texture);
+        self
+    }
+
+    /// Declares the depth/stencil attachment that this pass renders into.
+    #[inline]
+    pub fn with_depth_stencil(mut self, texture: FrameGraphTexture) -> Self {
+        self.depth_stencil = Some(texture);
+        self
+    }
+
+    /// Sets the clear flags of the surface of this pass.
+    #[inline]
+    pub fn with_clear<C, D, S>(mut self, color: C, depth: D, stencil: S) -> Self
+    where
+        C: Into<Option<Color<f32>>>,
+        D: Into<Option<f32>>,
+        S: Into<Option<i32>>,
+    {
+        self.clear_color = color.into();
+        self.clear_depth = depth.into();
+        self.clear_stencil = stencil.into();
+        self
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn writes<'a>(&'a self) -> impl Iterator<Item = FrameGraphTexture> + 'a {
+        self.colors.iter().chain(self.depth_stencil.iter()).cloned()
+    }
+
+    fn is_presenting(&self) -> bool {
+        self.colors.is_empty() && self.depth_stencil.is_none()
+    }
+}
+
+/// The objects that a pass is executed with.
+pub struct FramePassContext<'a> {
+    surface: SurfaceHandle,
+    textures: &'a [Option<RenderTextureHandle>],
+}
+
+impl<'a> FramePassContext<'a> {
+    /// Gets the surface that renders into the attachments of this pass.
+    #[inline]
+    pub fn surface(&self) -> SurfaceHandle {
+        self.surface
+    }
+
+    /// Gets the render texture object of a texture declared in graph.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texture is used by none of the passes.
+    #[inline]
+    pub fn texture(&self, texture: FrameGraphTexture) -> RenderTextureHandle {
+        self.textures[texture.0].expect("The texture is not used by any passes.")
+    }
+}
+
+type FramePassFunc = Box<dyn FnMut(&FramePassContext) -> Result<()> + Send>;
+
+/// A graph of passes, which are executed in the order derived from the render textures
+/// they read and write.
+#[derive(Default)]
+pub struct FrameGraph {
+    textures: Vec<(String, TextureSource)>,
+    passes: Vec<(FramePass, FramePassFunc)>,
+    compiled: Option<Compiled>,
+    // The render texture objects of transient textures, which are reused between
+    // compilations if possible.
+    allocated: Vec<(RenderTextureParams, RenderTextureHandle)>,
+}
+
+struct Compiled {
+    order: Vec<usize>,
+    surfaces: Vec<SurfaceHandle>,
+    textures: Vec<Option<RenderTextureHandle>>,
+}
+
+impl FrameGraph {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Declares a transient texture that is created and owned by this graph.
+    pub fn create_texture<T: Into<String>>(
+        &mut self,
+        name: T,
+        params: RenderTextureParams,
+    ) -> FrameGraphTexture {
+        self.release();
+        self.textures
+            .push((name.into(), TextureSource::Transient(params)));
+        FrameGraphTexture(self.textures.len() - 1)
+    }
+
+    /// Declares a render texture object that is created outside of this graph.
+    pub fn import_texture<T: Into<String>>(
+        &mut self,
+        name: T,
+        handle: RenderTextureHandle,
+    ) -> FrameGraphTexture {
+        self.release();
+        self.textures
+            .push((name.into(), TextureSource::Imported(handle)));
+        FrameGraphTexture(self.textures.len() - 1)
+    }
+
+    /// Changes the parameters of a transient texture, e.g. when the window is resized.
+    pub fn set_texture_params(&mut self, texture: FrameGraphTexture, params: RenderTextureParams) {
+        if let Some(v) = self.textures.get_mut(texture.0) {
+            if let TextureSource::Transient(_) = v.1 {
+                v.1 = TextureSource::Transient(params);
+                self.release();
+            }
+        }
+    }
+
+    /// Adds a pass, which is executed with `func` in every `execute`.
+    pub fn add_pass<T>(&mut self, pass: FramePass, func: T)
+    where
+        T: FnMut(&FramePassContext) -> Result<()> + Send + 'static,
+    {
+        self.release();
+        self.passes.push((pass, Box::new(func)));
+    }
+
+    /// Removes all the passes with `name`.
+    pub fn remove_pass<T: AsRef<str>>(&mut self, name: T) {
+        self.release();
+        self.passes.retain(|v| v.0.name != name.as_ref());
+    }
+
+    /// Returns the names of passes in the order they are executed, which is empty if the
+    /// graph has not been compiled.
+    pub fn order(&self) -> Vec<&str> {
+        match self.compiled {
+            Some(ref compiled) => compiled
+                .order
+                .iter()
+                .map(|&v| self.passes[v].0.name.as_str())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Validates the passes and derives their order, and then creates the surfaces and
+    /// render textures. It is called by `execute` automatically if the graph has been
+    /// changed.
+    pub fn compile(&mut self) -> Result<()> {
+        self.release();
+
+        let mut textures = Vec::with_capacity(self.textures.len());
+        for &(ref name, source) in &self.textures {
+            let params = match source {
+                TextureSource::Transient(params) => params,
+                TextureSource::Imported(handle) => crate::video::render_texture(handle)
+                    .ok_or_else(|| format_err!("Imported texture {} is invalid.", name))?,
+            };
+
+            textures.push((name.as_str(), params, source));
+        }
+
+        let passes: Vec<_> = self.passes.iter().map(|v| &v.0).collect();
+        let descs: Vec<_> = textures.iter().map(|v| (v.0, v.1)).collect();
+        let order = sort(&descs, &passes)?;
+
+        let transients: Vec<_> = textures
+            .iter()
+            .map(|v| match v.2 {
+                TextureSource::Transient(params) => Some(params),
+                TextureSource::Imported(_) => None,
+            })
+            .collect();
+
+        let (physicals, aliases) = alias(&transients, &passes, &order);
+
+        // Reuses the render texture objects created in previous compilations.
+        let mut allocated = std::mem::replace(&mut self.allocated, Vec::new());
+        for params in physicals {
+            let handle = match allocated.iter().position(|v| v.0 == params) {
+                Some(i) => allocated.swap_remove(i).1,
+                None => crate::video::create_render_texture(params)?,
+            };
+
+            self.allocated.push((params, handle));
+        }
+
+        for (_, v) in allocated {
+            crate::video::delete_render_texture(v);
+        }
+
+        let mut handles = vec![None; textures.len()];
+        for (i, v) in textures.iter().enumerate() {
+            handles[i] = match v.2 {
+                TextureSource::Transient(_) => aliases[i].map(|v| self.allocated[v].1),
+                TextureSource::Imported(handle) => Some(handle),
+            };
+        }
+
+        let mut surfaces = Vec::with_capacity(order.len());
+        for &v in &order {
+            let pass = &self.passes[v].0;
+            let colors: Vec<_> = pass.colors.iter().map(|v| handles[v.0].unwrap()).collect();
+            let depth_stencil = pass.depth_stencil.map(|v| handles[v.0].unwrap());
+
+            let mut params = SurfaceParams::default();
+            params.set_attachments(&colors, depth_stencil)?;
+            params.set_clear(pass.clear_color, pass.clear_depth, pass.clear_stencil);
+            surfaces.push(crate::video::create_surface(params)?);
+        }
+
+        self.compiled = Some(Compiled {
+            order,
+            surfaces,
+            textures: handles,
+        });
+
+        Ok(())
+    }
+
+    /// Executes all the passes in order, the graph is compiled if required.
+    pub fn execute(&mut self) -> Result<()> {
+        if self.compiled.is_none() {
+            self.compile()?;
+        }
+
+        let compiled = self.compiled.as_ref().unwrap();
+        for (i, &v) in compiled.order.iter().enumerate() {
+            let ctx = FramePassContext {
+                surface: compiled.surfaces[i],
+                textures: &compiled.textures,
+            };
+
+            (self.passes[v].1)(&ctx)?;
+        }
+
+        Ok(())
+    }
+
+    fn release(&mut self) {
+        if let Some(compiled) = self.compiled.take() {
+            for v in compiled.surfaces {
+                crate::video::delete_surface(v);
+            }
+        }
+    }
+}
+
+impl Drop for FrameGraph {
+    fn drop(&mut self) {
+        self.release();
+
+        for (_, v) in self.allocated.drain(..) {
+            crate::video::delete_render_texture(v);
+        }
+    }
+}
+
+/// Validates the usages of textures, and sorts the passes so that all the writes of a
+/// texture happen before its reads. Ties are broken by the order passes are added.
+fn sort(textures: &[(&str, RenderTextureParams)], passes: &[&FramePass]) -> Result<Vec<usize>> {
+    let mut errors = Vec::new();
+    let mut writers = vec![Vec::new(); textures.len()];
+
+    for (i, pass) in passes.iter().enumerate() {
+        let ids = pass.reads.iter().chain(pass.colors.iter());
+        if ids
+            .chain(pass.depth_stencil.iter())
+            .any(|v| v.0 >= textures.len())
+        {
+            bail!("Pass {} uses textures of other graphs.", pass.name);
+        }
+
+        if pass.colors.len() >= MAX_FRAMEBUFFER_ATTACHMENTS {
+            errors.push(format!(
+                "Pass {} has too many color attachments.",
+                pass.name
+            ));
+        }
+
+        for &v in &pass.colors {
+            if !textures[v.0].1.format.is_color() {
+                errors.push(format!(
+                    "Pass {} attaches {} as color, but its format is {:?}.",
+                    pass.name, textures[v.0].0, textures[v.0].1.format
+                ));
+            }
+        }
+
+        if let Some(v) = pass.depth_stencil {
+            if textures[v.0].1.format.is_color() {
+                errors.push(format!(
+                    "Pass {} attaches {} as depth/stencil, but its format is {:?}.",
+                    pass.name, textures[v.0].0, textures[v.0].1.format
+                ));
+            }
+        }
+
+        let mut dimensions = pass.writes().map(|v| textures[v.0].1.dimensions);
+        if let Some(first) = dimensions.next() {
+            if dimensions.any(|v| v != first) {
+                errors.push(format!(
+                    "Pass {} has attachments with different dimensions.",
+                    pass.name
+                ));
+            }
+        }
+
+        for v in pass.writes() {
+            if pass.reads.contains(&v) {
+                errors.push(format!(
+                    "Pass {} reads and writes {} at the same time.",
+                    pass.name, textures[v.0].0
+                ));
+            }
+
+            writers[v.0].push(i);
+        }
+
+        for &v in &pass.reads {
+            if !textures[v.0].1.sampler {
+                errors.push(format!(
+                    "Pass {} reads {}, which could not be sampled.",
+                    pass.name, textures[v.0].0
+                ));
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        bail!("Frame graph is invalid:\n{}", errors.join("\n"));
+    }
+
+    // The edges from passes to the ones that depend on them.
+    let mut edges: Vec<Vec<usize>> = vec![Vec::new(); passes.len()];
+    for w in &writers {
+        for v in w.windows(2) {
+            edges[v[0]].push(v[1]);
+        }
+    }
+
+    let mut presenting: Option<usize> = None;
+    for (i, pass) in passes.iter().enumerate() {
+        for v in &pass.reads {
+            if let Some(&last) = writers[v.0].last() {
+                edges[last].push(i);
+            }
+        }
+
+        if pass.is_presenting() {
+            if let Some(prev) = presenting {
+                edges[prev].push(i);
+            }
+
+            presenting = Some(i);
+        }
+    }
+
+    // The passes that render into textures are executed before the window framebuffer.
+    if let Some(first) = passes.iter().position(|v| v.is_presenting()) {
+        for (i, pass) in passes.iter().enumerate() {
+            if !pass.is_presenting() && !edges[i].contains(&first) {
+                edges[i].push(first);
+            }
+        }
+    }
+
+    let mut degrees = vec![0; passes.len()];
+    for v in edges.iter().flat_map(|v| v.iter()) {
+        degrees[*v] += 1;
+    }
+
+    let mut order = Vec::with_capacity(passes.len());
+    while order.len() < passes.len() {
+        let next = (0..passes.len()).find(|&v| degrees[v] == 0 && !order.contains(&v));
+        match next {
+            Some(v) => {
+                for &n in &edges[v] {
+                    degrees[n] -= 1;
+                }
+
+                order.push(v);
+            }
+            None => {
+                let cycle: Vec<_> = (0..passes.len())
+                    .filter(|v| !order.contains(v))
+                    .map(|v| passes[v].name.as_str())
+                    .collect();
+
+                bail!("Frame graph has cycles among passes {:?}.", cycle);
+            }
+        }
+    }
+
+    Ok(order)
+}
+
+/// Assigns the transient textures to render texture objects, the ones whose lifetimes
+/// do not overlap share the same object. Returns the parameters of objects and the index
+/// of object of every texture, `None` if the texture is imported or not used.
+fn alias(
+    transients: &[Option<RenderTextureParams>],
+    passes: &[&FramePass],
+    order: &[usize],
+) -> (Vec<RenderTextureParams>, Vec<Option<usize>>) {
+    // The first and last step that uses each texture.
+    let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; transients.len()];
+    for (step, &v) in order.iter().enumerate() {
+        let pass = passes[v];
+        for t in pass.reads.iter().cloned().chain(pass.writes()) {
+            let lifetime = lifetimes[t.0].get_or_insert((step, step));
+            lifetime.1 = step;
+        }
+    }
+
+    let mut textures: Vec<_> = (0..transients.len())
+        .filter(|&v| transients[v].is_some() && lifetimes[v].is_some())
+        .collect();
+    textures.sort_by_key(|&v| lifetimes[v].unwrap().0);
+
+    let mut physicals: Vec<(RenderTextureParams, usize)> = Vec::new();
+    let mut aliases = vec![None; transients.len()];
+    for v in textures {
+        let params = transients[v].unwrap();
+        let (first, last) = lifetimes[v].unwrap();
+
+        match physicals.iter().position(|p| p.0 == params && p.1 < first) {
+            Some(i) => {
+                physicals[i].1 = last;
+                aliases[v] = Some(i);
+            }
+            None => {
+                physicals.push((params, last));
+                aliases[v] = Some(physicals.len() - 1);
+            }
+        }
+    }
+
+    (physicals.into_iter().map(|v| v.0).collect(), aliases)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn texture(format: RenderTextureFormat) -> RenderTextureParams {
+        let mut params = RenderTextureParams::default();
+        params.format = format;
+        params.dimensions = (64, 64).into();
+        params
+    }
+
+    #[test]
+    fn sort_and_alias() {
+        let color = texture(RenderTextureFormat::RGBA8);
+        let depth = texture(RenderTextureFormat::Depth24);
+        let textures = [
+            ("shadow", depth),
+            ("scene", color),
+            ("bloom", color),
+            ("blur", color),
+        ];
+
+        let t = |v| FrameGraphTexture(v);
+        let passes = [
+            FramePass::new("present").with_read(t(1)).with_read(t(3)),
+            FramePass::new("blur").with_read(t(2)).with_color(t(3)),
+            FramePass::new("bloom").with_read(t(1)).with_color(t(2)),
+            FramePass::new("scene").with_read(t(0)).with_color(t(1)),
+            FramePass::new("shadow").with_depth_stencil(t(0)),
+        ];
+
+        let passes: Vec<_> = passes.iter().collect();
+        let order = sort(&textures, &passes).unwrap();
+        let names: Vec<_> = order.iter().map(|&v| passes[v].name()).collect();
+        assert_eq!(names, ["shadow", "scene", "bloom", "blur", "present"]);
+
+        // `bloom` is dead after `blur`, but `scene` is read by `present`.
+        let transients: Vec<_> = textures.iter().map(|v| Some(v.1)).collect();
+        let (physicals, aliases) = alias(&transients, &passes, &order);
+        assert_eq!(physicals.len(), 4);
+        assert_eq!(aliases, [Some(0), Some(1), Some(2), Some(3)]);
+
+        let passes = [
+            FramePass::new("a").with_color(t(1)),
+            FramePass::new("b").with_read(t(1)).with_color(t(2)),
+            FramePass::new("c").with_read(t(2)).with_color(t(3)),
+            FramePass::new("d").with_read(t(3)),
+        ];
+
+        let passes: Vec<_> = passes.iter().collect();
+        let order = sort(&textures, &passes).unwrap();
+        let (physicals, aliases) = alias(&transients, &passes, &order);
+        assert_eq!(physicals.len(), 2);
+        assert_eq!(aliases, [None, Some(0), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn validate() {
+        let color = texture(RenderTextureFormat::RGBA8);
+        let depth = texture(RenderTextureFormat::Depth24);
+        let textures = [("color", color), ("depth", depth)];
+
+        let t = |v| FrameGraphTexture(v);
+        let passes = [
+            FramePass::new("a")
+                .with_color(t(1))
+                .with_depth_stencil(t(0)),
+            FramePass::new("b").with_read(t(0)).with_color(t(0)),
+        ];
+
+        let passes: Vec<_> = passes.iter().collect();
+        let err = sort(&textures, &passes).unwrap_err().to_string();
+        assert!(err.contains("Pass a attaches depth as color"));
+        assert!(err.contains("Pass a attaches color as depth/stencil"));
+        assert!(err.contains("Pass b reads and writes color"));
+
+        let passes = [
+            FramePass::new("a").with_read(t(1)).with_color(t(0)),
+            FramePass::new("b").with_read(t(0)).with_depth_stencil(t(1)),
+        ];
+
+        let passes: Vec<_> = passes.iter().collect();
+        let err = sort(&textures, &passes).unwrap_err().to_string();
+        assert!(err.contains("cycles"));
+    }
+}
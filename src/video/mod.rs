@@ -53,7 +53,14 @@
 //!
 //! The idea here is to assign a integer key to a command which is used for sorting. Depending
 //! on where those bits are stored in the integer, you can apply different sorting criteria
-//! for the same array of commands, as long as you know how the keys were built.
+//! for the same array of commands, as long as you know how the keys were built. The
+//! `SortKey` packs the common criteria, e.g. layers, translucency and depth, into documented
+//! bit ranges, so the keys built by user code and built-in pipelines are consistent.
+//!
+//! The draws of a large scene could be recorded in parallel, with one `DrawCommandBuffer` per
+//! job of the scheduler, and submitted together with `DrawCommandBuffer::submit_all`. The
+//! commands of all the buffers are sorted with a stable sort, so the ones with the same key
+//! keep the order of buffers, no matter which threads recorded them.
 //!
 //! # Resource Objects
 //!
@@ -104,6 +111,10 @@
 //! video::delete_surface(surface);
 //! ```
 //!
+//! The surface renders into a secondary window created by `window::create_window` instead,
+//! if it is set with `SurfaceParams::set_window`. The window is presented at the end of
+//! frame.
+//!
 //! ### Shader Object
 //!
 //! Shader object is introduced to encapsulate all stateful things we need to configurate
@@ -142,6 +153,53 @@
 //! video::delete_shader(shader);
 //! ```
 //!
+//! ### Uniform Buffer Object
+//!
+//! Uniform buffer object stores a block of uniform variables with `std140` layout, which
+//! could be shared between shaders. Its useful to upload per-frame or per-view constants
+//! once, instead of setting them in every draw call.
+//!
+//! The block should be declared with `UniformVariableType::UniformBuffer` in shader's
+//! uniform layout, and bound with the handle of buffer like other uniform variables.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! let mut params = UniformBufferParams::default();
+//! params.size = 128;
+//!
+//! // Create a uniform buffer object, you can fill it later with `update_uniform_buffer`.
+//! let ubo = video::create_uniform_buffer(params, None).unwrap();
+//!
+//! // Deletes the uniform buffer object.
+//! video::delete_uniform_buffer(ubo);
+//! ```
+//!
+//! ### Occlusion Query
+//!
+//! Occlusion query counts the samples of draws that pass the depth test. Wraps the draws
+//! with `begin_query` and `end_query` in a `CommandBuffer`, and fetch the result in later
+//! frames, since the GPU usually runs a few frames behind.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! let query = video::create_query(QueryParams::default()).unwrap();
+//!
+//! let mut cmds = CommandBuffer::new();
+//! cmds.begin_query(query);
+//! // cmds.draw(...);
+//! cmds.end_query(query);
+//!
+//! // Returns `None` until the GPU finishes the query.
+//! let _visible = video::query_result(query).map(|v| v > 0);
+//!
+//! // Deletes the query object.
+//! video::delete_query(query);
+//! ```
+//!
 //! ### Texture Object
 //!
 //! A texture object is a container of one or more images. It can be the source of a texture
@@ -160,10 +218,97 @@
 //! video::delete_texture(texture);
 //! ```
 //!
+//! #### Streaming Mipmaps
+//!
+//! Uploading all the mipmaps of large textures at once would stall the frame. So only the
+//! mipmaps not larger than `TextureStreamingParams::resident` are uploaded when creating
+//! immutable textures, and the larger ones are streamed within a budget of bytes per frame
+//! afterwards. The texture is usable at once, and gets sharper as the mipmaps arrive. It
+//! could be configured or disabled with `video::set_texture_streaming`.
+//!
+//! #### Sampler Object
+//!
+//! Textures are sampled with the wrap and filter modes of their own params by default. A
+//! sampler object overrides them with per-axis wrap modes, separated min/mag/mip filters,
+//! anisotropic filtering and LOD bias, so one texture could be sampled differently in
+//! different passes.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! let texture = video::create_texture(TextureParams::default(), None).unwrap();
+//!
+//! let params = SamplerParams::anisotropic(TextureWrap::Repeat, 8);
+//! let sampler = video::create_sampler(params).unwrap();
+//!
+//! let mut dc = Draw::new(ShaderHandle::default(), MeshHandle::default());
+//! dc.set_uniform_variable("u_Texture", (texture, sampler));
+//!
+//! video::delete_sampler(sampler);
+//! ```
+//!
 //! #### Compressed Texture Format
 //!
-//! _TODO_: Cube texture.
-//! _TODO_: 3D texture.
+//! The compressed formats are device specific, which could be checked with
+//! `video::is_texture_format_supported`. Besides the built-in binary format, the texture
+//! loaders accept KTX2 containers, whose levels could be supercompressed with Zstandard or
+//! ZLIB. The KTX2 textures in Basis Universal formats are transcoded into the best format
+//! supported by device, with the transcoder registered by `video::set_basis_transcoder`.
+//!
+//! ```rust,ignore
+//! video::set_basis_transcoder(MyTranscoder::new());
+//! let texture = video::create_texture_from("res:textures/crate.ktx2").unwrap();
+//! ```
+//!
+//! #### Cube Texture
+//!
+//! A cube texture consists of six square 2D faces, which are sampled with a direction
+//! vector as `samplerCube` in shaders. It's commonly used for skyboxes and environment
+//! reflections.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! let mut params = TextureCubeParams::default();
+//! params.dimensions = (4, 4).into();
+//!
+//! // Faces are stored in order of +X, -X, +Y, -Y, +Z and -Z.
+//! let face = vec![vec![255u8; 4 * 4 * 4].into_boxed_slice()];
+//! let data = TextureCubeData {
+//!     faces: [face.clone(), face.clone(), face.clone(), face.clone(), face.clone(), face],
+//! };
+//!
+//! // Create a cube texture object with six faces.
+//! let texture = video::create_texture_cube(params, data).unwrap();
+//!
+//! // Deletes the cube texture object.
+//! video::delete_texture_cube(texture);
+//! ```
+//!
+//! #### 3D Texture
+//!
+//! A 3D texture is a stack of 2D images with the same format and dimensions, which are
+//! sampled with three-dimensional texture coordinates as `sampler3D` in shaders. It's
+//! commonly used for color grading look-up tables and volumetric data.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! let mut params = Texture3DParams::default();
+//! params.dimensions = (16, 16, 16).into();
+//!
+//! // Slices are stored from front to back.
+//! let bytes = vec![vec![255u8; 16 * 16 * 16 * 4].into_boxed_slice()];
+//!
+//! // Create a 3D texture object.
+//! let texture = video::create_texture_3d(params, Texture3DData { bytes }).unwrap();
+//!
+//! // Deletes the 3D texture object.
+//! video::delete_texture_3d(texture);
+//! ```
 //!
 //! ### Mesh Object
 //!
@@ -180,6 +325,144 @@
 //! video::delete_mesh(mesh);
 //! ```
 //!
+//! ### Instance Buffer Object
+//!
+//! An instance buffer stores attributes which advance once per instance, so a mesh can
+//! be drawn many times with one draw call. The shader declares these attributes with
+//! `AttributeLayoutBuilder::with_instanced`, and the draw call references the buffer with
+//! `Draw::set_instance_data`.
+//!
+//! If the context does not support instancing natively, e.g. OpenGL ES 2.0, the instances
+//! are drawn one by one with the same results.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! let mut params = InstanceBufferParams::default();
+//! params.layout = VertexLayout::build()
+//!     .with(Attribute::Instance0, VertexFormat::Float, 4, false)
+//!     .finish();
+//! params.num = 1024;
+//!
+//! // Create a instance buffer object, you can fill it later with `update_instance_buffer`.
+//! let instances = video::create_instance_buffer(params, None).unwrap();
+//!
+//! // Deletes the instance buffer object.
+//! video::delete_instance_buffer(instances);
+//! ```
+//!
+//! ### Readback
+//!
+//! The pixels of render textures could be read back with `read_render_texture`, e.g. for
+//! screenshots or color picking. The copy is scheduled in the command stream after the
+//! submitted draws, and never stalls the pipeline.
+//!
+//! ```rust
+//! use crayon::prelude::*;
+//! application::oneshot().unwrap();
+//!
+//! let mut params = RenderTextureParams::default();
+//! params.dimensions = (64, 64).into();
+//! let rt = video::create_render_texture(params).unwrap();
+//!
+//! let area = Aabb2::new([0, 0].into(), [64, 64].into());
+//! let mut request = video::read_render_texture(rt, area).unwrap();
+//!
+//! // Returns `true` once the GPU finishes the copy.
+//! if request.poll() {
+//!     let _pixels = request.response();
+//! }
+//!
+//! video::delete_render_texture(rt);
+//! ```
+//!
+//! The window could be captured into PNG images with `capture_screenshot`, or frame by
+//! frame with `start_frame_dump`. The images are encoded on worker threads.
+//!
+//! ```rust,ignore
+//! if input::is_key_press(Key::F12) {
+//!     video::capture_screenshot("screenshot.png");
+//! }
+//! ```
+//!
+//! # Capabilities
+//!
+//! The features and limits of device could be queried with `capabilities`, which are used
+//! natively on OpenGL ES 3.0 and WebGL 2.0 contexts, and emulated on older ones if
+//! possible. The rendering paths could be chosen at runtime with them.
+//!
+//! ```rust,ignore
+//! let caps = video::capabilities();
+//! info!("Running on {} ({}).", caps.api, caps.renderer);
+//!
+//! if caps.max_color_attachments >= 4 {
+//!     // Renders with the deferred pipeline.
+//! }
+//! ```
+//!
+//! # Validation
+//!
+//! A wrong uniform name or a mismatched attribute layout usually ends up with a black
+//! screen silently. The validation layer checks the commands when they are submitted,
+//! against the declared layouts of shaders, the layouts and sizes of buffers, and the
+//! formats of attachments. The invalid commands are rejected with errors that name the
+//! offending resource and field.
+//!
+//! It's enabled in debug builds, or with the `validation` feature, and could be toggled
+//! at runtime with `set_validation`. Notes that the uniforms and attributes are only
+//! checked if they are declared in `ShaderParams`.
+//!
+//! ```rust,ignore
+//! video::set_validation(true);
+//!
+//! let mut dc = Draw::new(shader, mesh);
+//! dc.set_uniform_variable("u_Colour", [1.0, 1.0, 1.0, 1.0]);
+//!
+//! let mut cmds = CommandBuffer::new();
+//! cmds.draw(dc);
+//!
+//! // Err(ValidationFailed("uniform ... is not declared by ShaderHandle(..), which declares [u_Color]."))
+//! let result = cmds.submit(surface);
+//! ```
+//!
+//! # Tracking
+//!
+//! Since all the resources are opaque handles, it's easy to leak them or to use the deleted
+//! ones. The lifetimes of resources created after `set_tracking(true)` are tracked, so
+//! the ones that are still alive are reported at shutdown, and the uses of deleted ones
+//! are rejected with errors instead of undefined rendering. The backtraces of where the
+//! resources are created and deleted are captured if `RUST_BACKTRACE` is set.
+//!
+//! ```rust,ignore
+//! video::set_tracking(true);
+//! level.load()?;
+//! level.unload();
+//!
+//! for v in video::alive_resources() {
+//!     warn!("Leaked {}", v);
+//! }
+//! ```
+//!
+//! # Debug Labels
+//!
+//! The objects are anonymous ids in the frame captures of graphics debuggers like RenderDoc.
+//! They could be named with `set_debug_name`, and the commands of named surfaces are
+//! grouped with the names of surfaces. The commands in a surface could be grouped further
+//! with `CommandBuffer::push_debug_group`. It takes effects on the contexts that support
+//! `KHR_debug`, and is ignored otherwise.
+//!
+//! ```rust,ignore
+//! video::set_debug_name(albedo, "player_albedo");
+//! video::set_debug_name(surface, "shadows");
+//!
+//! let mut cmds = CommandBuffer::new();
+//! cmds.push_debug_group("cascade 0");
+//! cmds.draw(dc);
+//! cmds.pop_debug_group();
+//! cmds.submit(surface)?;
+//! ```
+//!
 //! # Commands
 //!
 //! _TODO_: CommandBuffer
@@ -190,34 +473,151 @@ pub const MAX_VERTEX_ATTRIBUTES: usize = 12;
 /// Maximum number of attachments in framebuffer.
 pub const MAX_FRAMEBUFFER_ATTACHMENTS: usize = 8;
 /// Maximum number of uniform variables in shader.
-pub const MAX_UNIFORM_VARIABLES: usize = 32;
+pub const MAX_UNIFORM_VARIABLES: usize = 64;
 /// Maximum number of textures in shader.
 pub const MAX_UNIFORM_TEXTURE_SLOTS: usize = 8;
 
 #[macro_use]
 pub mod assets;
+pub mod capabilities;
 pub mod command;
 pub mod errors;
+pub mod frame_info;
+pub mod graph;
+pub mod sort_key;
 
+pub(crate) mod capture;
 mod system;
+mod tracker;
+mod validation;
 
 mod backends;
 
 pub mod prelude {
     pub use super::assets::prelude::*;
+    pub use super::capabilities::Capabilities;
     pub use super::command::{CommandBuffer, Draw, DrawCommandBuffer};
+    pub use super::frame_info::GraphicsFrameInfo;
+    pub use super::graph::{FrameGraph, FrameGraphTexture, FramePass, FramePassContext};
+    pub use super::sort_key::{DepthOrder, SortKey, SortKeyBuilder};
+    pub use super::{VSync, VideoParams};
 }
 
+use std::path::Path;
+
 use uuid::Uuid;
 
-use crate::math::prelude::Aabb2;
+use crate::math::prelude::{Aabb2, Aabb3, Vector2};
 use crate::prelude::CrResult;
-use crate::res::utils::prelude::ResourceState;
+use crate::res::request::Request;
+use crate::res::utils::prelude::{EvictionCallback, ResourceState};
 
 use self::assets::prelude::*;
+use self::capabilities::Capabilities;
 use self::errors::*;
+use self::frame_info::GraphicsFrameInfo;
 use self::inside::ctx;
 
+/// The setup parameters of video sub-system.
+#[derive(Debug, Clone, Copy)]
+pub struct VideoParams {
+    /// The vertical synchronization of main window.
+    pub vsync: VSync,
+    /// Keeps a copy of the data of video objects in memory, so they could be recreated
+    /// once the lost context is restored, see `WindowEvent::ContextLost`. It doubles the
+    /// memory of textures and meshes, so it defaults to true only in web environment,
+    /// where the browsers reclaim the contexts of pages in background.
+    pub restore_lost_context: bool,
+}
+
+impl Default for VideoParams {
+    fn default() -> Self {
+        VideoParams {
+            vsync: VSync::default(),
+            restore_lost_context: cfg!(target_arch = "wasm32"),
+        }
+    }
+}
+
+/// The modes of vertical synchronization, which decide whether the presentation of
+/// frames waits for the vertical blanks of monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VSync {
+    /// Frames are presented immediately, which may tear. The frame rate could be limited
+    /// with `Params::max_fps` instead.
+    Off,
+    /// Frames are presented at the vertical blanks, a frame that misses one waits for the
+    /// next, e.g. drops from 60 to 30 fps.
+    On,
+    /// Like `On`, but the late frames are presented immediately instead of waiting for the
+    /// next vertical blank. It's supported on X11 and Windows with the swap control tear
+    /// extensions, and falls back to `On` elsewhere.
+    Adaptive,
+}
+
+impl Default for VSync {
+    fn default() -> Self {
+        VSync::Off
+    }
+}
+
+/// Sets whether the submitted commands are validated. Defaults to true in debug builds
+/// or with the `validation` feature.
+#[inline]
+pub fn set_validation(enabled: bool) {
+    ctx().set_validation(enabled);
+}
+
+/// Checks if the submitted commands are validated.
+#[inline]
+pub fn validation() -> bool {
+    ctx().validation()
+}
+
+/// Rasterizes the polygons of all the surfaces as lines, which overrides the
+/// `SurfaceParams::set_wireframe` of surfaces. It takes effect only if
+/// `capabilities().wireframe` is true.
+#[inline]
+pub fn set_wireframe(enable: bool) {
+    ctx().set_wireframe(enable);
+}
+
+/// Checks if the polygons of all the surfaces are rasterized as lines.
+#[inline]
+pub fn wireframe() -> bool {
+    ctx().wireframe()
+}
+
+/// Sets whether the lifetimes of resources are tracked. Defaults to false.
+///
+/// The tracked resources that are still alive are reported at shutdown, and the uses of
+/// deleted ones are rejected with errors that tell where they were created and deleted.
+/// It also enables the validation of submitted commands.
+#[inline]
+pub fn set_tracking(enabled: bool) {
+    ctx().set_tracking(enabled);
+}
+
+/// Checks if the lifetimes of resources are tracked.
+#[inline]
+pub fn tracking() -> bool {
+    ctx().tracking()
+}
+
+/// Gets the descriptions of tracked resources that are still alive, e.g. to find out the
+/// leaks between levels.
+#[inline]
+pub fn alive_resources() -> Vec<String> {
+    ctx().alive_resources()
+}
+
+/// Sets the name of object, which is shown in the frame captures of graphics debuggers
+/// like RenderDoc. The commands of named surfaces are grouped with their names too.
+#[inline]
+pub fn set_debug_name<T: Into<VideoObject>>(object: T, name: &str) {
+    ctx().set_debug_name(object.into(), name);
+}
+
 /// Creates an surface with `SurfaceParams`.
 #[inline]
 pub fn create_surface(params: SurfaceParams) -> Result<SurfaceHandle> {
@@ -230,6 +630,22 @@ pub fn surface(handle: SurfaceHandle) -> Option<SurfaceParams> {
     ctx().surface(handle)
 }
 
+/// Gets the dimensions in pixels of the viewport that surface draws into, e.g. to create
+/// the intermediate render textures of the same size.
+#[inline]
+pub fn surface_dimensions(handle: SurfaceHandle) -> Option<Vector2<u32>> {
+    ctx().surface_dimensions(handle)
+}
+
+/// Checks if the linear colors should be encoded into sRGB space by shaders when drawing
+/// into the surface. It's true if the surface draws into a window framebuffer that is not
+/// encoded by hardware, see `SurfaceParams::set_srgb`. The colors in render textures are
+/// always kept in linear space.
+#[inline]
+pub fn requires_srgb_encoding(handle: SurfaceHandle) -> bool {
+    ctx().requires_srgb_encoding(handle)
+}
+
 /// Get the resource state of specified surface.
 #[inline]
 pub fn surface_state(handle: SurfaceHandle) -> ResourceState {
@@ -249,6 +665,13 @@ pub fn create_shader(params: ShaderParams, vs: String, fs: String) -> Result<Sha
     ctx().create_shader(params, vs, fs)
 }
 
+/// Replaces the sources of shader, the handle keeps valid. The old program is
+/// kept if the new sources failed to compile.
+#[inline]
+pub fn update_shader(handle: ShaderHandle, vs: String, fs: String) -> Result<()> {
+    ctx().update_shader(handle, vs, fs)
+}
+
 /// Gets the `ShaderParams` if available.
 #[inline]
 pub fn shader(handle: ShaderHandle) -> Option<ShaderParams> {
@@ -267,6 +690,100 @@ pub fn delete_shader(handle: ShaderHandle) {
     ctx().delete_shader(handle)
 }
 
+/// Creates a new uniform buffer object with optional initial data.
+#[inline]
+pub fn create_uniform_buffer(
+    params: UniformBufferParams,
+    data: Option<&[u8]>,
+) -> Result<UniformBufferHandle> {
+    ctx().create_uniform_buffer(params, data)
+}
+
+/// Gets the `UniformBufferParams` if available.
+#[inline]
+pub fn uniform_buffer(handle: UniformBufferHandle) -> Option<UniformBufferParams> {
+    ctx().uniform_buffer(handle)
+}
+
+/// Get the resource state of specified uniform buffer.
+#[inline]
+pub fn uniform_buffer_state(handle: UniformBufferHandle) -> ResourceState {
+    ctx().uniform_buffer_state(handle)
+}
+
+/// Update a subset of uniform buffer. Use `offset` specifies the offset into the
+/// buffer object's data store where data replacement will begin, measured in bytes.
+#[inline]
+pub fn update_uniform_buffer(
+    handle: UniformBufferHandle,
+    offset: usize,
+    data: &[u8],
+) -> Result<()> {
+    ctx().update_uniform_buffer(handle, offset, data)
+}
+
+/// Deletes uniform buffer object.
+#[inline]
+pub fn delete_uniform_buffer(handle: UniformBufferHandle) {
+    ctx().delete_uniform_buffer(handle)
+}
+
+/// Creates a new occlusion query object.
+#[inline]
+pub fn create_query(params: QueryParams) -> Result<QueryHandle> {
+    ctx().create_query(params)
+}
+
+/// Gets the `QueryParams` if available.
+#[inline]
+pub fn query(handle: QueryHandle) -> Option<QueryParams> {
+    ctx().query(handle)
+}
+
+/// Get the resource state of specified query.
+#[inline]
+pub fn query_state(handle: QueryHandle) -> ResourceState {
+    ctx().query_state(handle)
+}
+
+/// Gets the latest available result of query. This never blocks, and returns `None`
+/// if the GPU has not finished any `begin_query`/`end_query` pair of this query yet.
+#[inline]
+pub fn query_result(handle: QueryHandle) -> Option<u64> {
+    ctx().query_result(handle)
+}
+
+/// Deletes query object.
+#[inline]
+pub fn delete_query(handle: QueryHandle) {
+    ctx().delete_query(handle)
+}
+
+/// Creates a new sampler object, which overrides the wrap and filter modes of textures
+/// that are sampled with it.
+#[inline]
+pub fn create_sampler(params: SamplerParams) -> Result<SamplerHandle> {
+    ctx().create_sampler(params)
+}
+
+/// Gets the `SamplerParams` if available.
+#[inline]
+pub fn sampler(handle: SamplerHandle) -> Option<SamplerParams> {
+    ctx().sampler(handle)
+}
+
+/// Get the resource state of specified sampler.
+#[inline]
+pub fn sampler_state(handle: SamplerHandle) -> ResourceState {
+    ctx().sampler_state(handle)
+}
+
+/// Deletes sampler object.
+#[inline]
+pub fn delete_sampler(handle: SamplerHandle) {
+    ctx().delete_sampler(handle)
+}
+
 /// Create a new mesh object.
 #[inline]
 pub fn create_mesh<T>(params: MeshParams, data: T) -> CrResult<MeshHandle>
@@ -294,6 +811,41 @@ pub fn mesh(handle: MeshHandle) -> Option<MeshParams> {
     ctx().mesh(handle)
 }
 
+/// Gets the uuid of mesh, if it is created from file.
+#[inline]
+pub fn mesh_uuid(handle: MeshHandle) -> Option<Uuid> {
+    ctx().mesh_uuid(handle)
+}
+
+/// Registers the parser of mesh files in other formats than the built-in binary one, so
+/// they could be created with `create_mesh_from` directly. The OBJ files are supported by
+/// `ObjParser` by default.
+#[inline]
+pub fn add_mesh_parser<T: MeshParser>(parser: T) {
+    ctx().add_mesh_parser(parser);
+}
+
+/// Sets the transcoder of Basis Universal images, which are transcoded into the best
+/// format supported by device when the KTX2 textures are loaded.
+#[inline]
+pub fn set_basis_transcoder<T: BasisTranscoder>(transcoder: T) {
+    ctx().set_basis_transcoder(transcoder);
+}
+
+/// Sets whether the data of meshes created afterwards are kept in memory, so they
+/// could be read back with `mesh_data`, e.g. to build static batches. It's disabled by
+/// default.
+#[inline]
+pub fn set_mesh_readable(readable: bool) {
+    ctx().set_mesh_readable(readable);
+}
+
+/// Gets a copy of the data of mesh, if it's created while meshes are readable.
+#[inline]
+pub fn mesh_data(handle: MeshHandle) -> Option<MeshData> {
+    ctx().mesh_data(handle)
+}
+
 /// Get the resource state of specified mesh.
 #[inline]
 pub fn mesh_state(handle: MeshHandle) -> ResourceState {
@@ -303,6 +855,9 @@ pub fn mesh_state(handle: MeshHandle) -> ResourceState {
 /// Update a subset of dynamic vertex buffer. Use `offset` specifies the offset
 /// into the buffer object's data store where data replacement will begin, measured
 /// in bytes.
+///
+/// Meshes with `MeshHint::Stream` orphan their data store when the update starts at
+/// offset zero, see `MeshHint::Stream` for details.
 #[inline]
 pub fn update_vertex_buffer(handle: MeshHandle, offset: usize, data: &[u8]) -> CrResult<()> {
     ctx().update_vertex_buffer(handle, offset, data)
@@ -311,6 +866,9 @@ pub fn update_vertex_buffer(handle: MeshHandle, offset: usize, data: &[u8]) -> C
 /// Update a subset of dynamic index buffer. Use `offset` specifies the offset
 /// into the buffer object's data store where data replacement will begin, measured
 /// in bytes.
+///
+/// Meshes with `MeshHint::Stream` orphan their data store when the update starts at
+/// offset zero, see `MeshHint::Stream` for details.
 #[inline]
 pub fn update_index_buffer(handle: MeshHandle, offset: usize, data: &[u8]) -> CrResult<()> {
     ctx().update_index_buffer(handle, offset, data)
@@ -322,6 +880,44 @@ pub fn delete_mesh(handle: MeshHandle) {
     ctx().delete_mesh(handle);
 }
 
+/// Creates a new instance buffer object with optional initial data.
+#[inline]
+pub fn create_instance_buffer(
+    params: InstanceBufferParams,
+    data: Option<&[u8]>,
+) -> Result<InstanceBufferHandle> {
+    ctx().create_instance_buffer(params, data)
+}
+
+/// Gets the `InstanceBufferParams` if available.
+#[inline]
+pub fn instance_buffer(handle: InstanceBufferHandle) -> Option<InstanceBufferParams> {
+    ctx().instance_buffer(handle)
+}
+
+/// Get the resource state of specified instance buffer.
+#[inline]
+pub fn instance_buffer_state(handle: InstanceBufferHandle) -> ResourceState {
+    ctx().instance_buffer_state(handle)
+}
+
+/// Update a subset of instance buffer. Use `offset` specifies the offset into the
+/// buffer object's data store where data replacement will begin, measured in bytes.
+#[inline]
+pub fn update_instance_buffer(
+    handle: InstanceBufferHandle,
+    offset: usize,
+    data: &[u8],
+) -> Result<()> {
+    ctx().update_instance_buffer(handle, offset, data)
+}
+
+/// Deletes instance buffer object.
+#[inline]
+pub fn delete_instance_buffer(handle: InstanceBufferHandle) {
+    ctx().delete_instance_buffer(handle)
+}
+
 /// Create texture object. A texture is an image loaded in video memory,
 /// which can be sampled in shaders.
 #[inline]
@@ -350,24 +946,242 @@ pub fn texture_state(handle: TextureHandle) -> ResourceState {
     ctx().texture_state(handle)
 }
 
+/// Gets the uuid of texture, if it is created from file.
+#[inline]
+pub fn texture_uuid(handle: TextureHandle) -> Option<Uuid> {
+    ctx().texture_uuid(handle)
+}
+
+/// Checks if the colors sampled from texture should be decoded from sRGB space by
+/// shaders. It's false only if the texture is in sRGB formats that are decoded by
+/// hardware, the textures in linear formats are assumed to hold the sRGB colors.
+#[inline]
+pub fn requires_srgb_decoding(handle: TextureHandle) -> bool {
+    ctx().requires_srgb_decoding(handle)
+}
+
 /// Update a contiguous subregion of an existing two-dimensional texture object.
 #[inline]
 pub fn update_texture(handle: TextureHandle, area: Aabb2<u32>, data: &[u8]) -> CrResult<()> {
     ctx().update_texture(handle, area, data)
 }
 
+/// Uploads the mipmap `level` of an existing texture object, which replaces the old one.
+/// The levels that were left empty when creating texture must be uploaded from smaller
+/// to larger sizes.
+#[inline]
+pub fn update_texture_mip(handle: TextureHandle, level: usize, data: &[u8]) -> CrResult<()> {
+    ctx().update_texture_mip(handle, level, data)
+}
+
+/// Update a contiguous subregion of the mipmap `level` of an existing two-dimensional
+/// texture object, the `area` is measured in the pixels of that level.
+#[inline]
+pub fn update_texture_mip_area(
+    handle: TextureHandle,
+    level: usize,
+    area: Aabb2<u32>,
+    data: &[u8],
+) -> CrResult<()> {
+    ctx().update_texture_mip_area(handle, level, area, data)
+}
+
+/// Generates the full mipmap chain of texture from its base level, e.g. after the base
+/// level of a render target is updated.
+#[inline]
+pub fn generate_texture_mipmaps(handle: TextureHandle) -> CrResult<()> {
+    ctx().generate_texture_mipmaps(handle)
+}
+
+/// Sets the parameters of streaming the mipmaps of immutable textures, `None` uploads
+/// all the mipmaps when creating textures. It takes effect on the textures created later.
+#[inline]
+pub fn set_texture_streaming(params: Option<TextureStreamingParams>) {
+    ctx().set_texture_streaming(params);
+}
+
+/// Gets the parameters of streaming the mipmaps of immutable textures.
+#[inline]
+pub fn texture_streaming() -> Option<TextureStreamingParams> {
+    ctx().texture_streaming()
+}
+
+/// Checks if there are mipmaps of the texture waiting to be streamed.
+#[inline]
+pub fn is_texture_streaming(handle: TextureHandle) -> bool {
+    ctx().is_texture_streaming(handle)
+}
+
 /// Delete the texture object.
 #[inline]
 pub fn delete_texture(handle: TextureHandle) {
     ctx().delete_texture(handle);
 }
 
+/// Sets the texture that is bound in place of the textures which are still loading or
+/// failed to load, so textures created with `create_texture_from` are usable at once.
+/// A 1x1 magenta texture is used by default, and `None` leaves them unbound.
+/// The default texture is deleted once it's replaced. The handles that are invalid or
+/// deleted are never replaced.
+#[inline]
+pub fn set_texture_placeholder(handle: Option<TextureHandle>) {
+    ctx().set_texture_placeholder(handle);
+}
+
+/// Gets the texture that is bound in place of the textures which are not ready.
+#[inline]
+pub fn texture_placeholder() -> Option<TextureHandle> {
+    ctx().texture_placeholder()
+}
+
+/// Sets the memory budget in bytes of meshes. The unreferenced meshes created from
+/// files are kept in cache until the budget is exceeded.
+#[inline]
+pub fn set_mesh_budget(budget: Option<usize>) {
+    ctx().set_mesh_budget(budget);
+}
+
+/// Sets the memory budget in bytes of textures. The unreferenced textures created from
+/// files are kept in cache until the budget is exceeded.
+#[inline]
+pub fn set_texture_budget(budget: Option<usize>) {
+    ctx().set_texture_budget(budget);
+}
+
+/// Sets the callback that is invoked before evicting meshes or textures from cache,
+/// which could return false to veto the eviction.
+#[inline]
+pub fn set_eviction_callback(callback: Option<EvictionCallback>) {
+    ctx().set_eviction_callback(callback);
+}
+
+/// Returns the size in bytes of all the meshes alive.
+#[inline]
+pub fn mesh_memory() -> usize {
+    ctx().mesh_memory()
+}
+
+/// Returns the size in bytes of all the textures alive.
+#[inline]
+pub fn texture_memory() -> usize {
+    ctx().texture_memory()
+}
+
+/// Create cube texture object. A cube texture consists of six square 2D faces,
+/// which could be sampled with a direction vector in shaders.
+#[inline]
+pub fn create_texture_cube<T>(params: TextureCubeParams, data: T) -> CrResult<TextureCubeHandle>
+where
+    T: Into<Option<TextureCubeData>>,
+{
+    ctx().create_texture_cube(params, data)
+}
+
+/// Creates a cube texture object from file asynchronously.
+#[inline]
+pub fn create_texture_cube_from<T: AsRef<str>>(url: T) -> CrResult<TextureCubeHandle> {
+    ctx().create_texture_cube_from(url)
+}
+
+/// Creates a cube texture object from file asynchronously.
+#[inline]
+pub fn create_texture_cube_from_uuid(uuid: Uuid) -> CrResult<TextureCubeHandle> {
+    ctx().create_texture_cube_from_uuid(uuid)
+}
+
+/// Get the resource state of specified cube texture.
+#[inline]
+pub fn texture_cube_state(handle: TextureCubeHandle) -> ResourceState {
+    ctx().texture_cube_state(handle)
+}
+
+/// Checks if the colors sampled from cube texture should be decoded from sRGB space by
+/// shaders, see `requires_srgb_decoding`.
+#[inline]
+pub fn requires_srgb_decoding_cube(handle: TextureCubeHandle) -> bool {
+    ctx().requires_srgb_decoding_cube(handle)
+}
+
+/// Delete the cube texture object.
+#[inline]
+pub fn delete_texture_cube(handle: TextureCubeHandle) {
+    ctx().delete_texture_cube(handle);
+}
+
+/// Create 3D texture object. A 3D texture is a stack of 2D images, which could be
+/// sampled with three-dimensional texture coordinates in shaders.
+#[inline]
+pub fn create_texture_3d<T>(params: Texture3DParams, data: T) -> CrResult<Texture3DHandle>
+where
+    T: Into<Option<Texture3DData>>,
+{
+    ctx().create_texture_3d(params, data)
+}
+
+/// Creates a 3D texture object from file asynchronously.
+#[inline]
+pub fn create_texture_3d_from<T: AsRef<str>>(url: T) -> CrResult<Texture3DHandle> {
+    ctx().create_texture_3d_from(url)
+}
+
+/// Creates a 3D texture object from file asynchronously.
+#[inline]
+pub fn create_texture_3d_from_uuid(uuid: Uuid) -> CrResult<Texture3DHandle> {
+    ctx().create_texture_3d_from_uuid(uuid)
+}
+
+/// Get the resource state of specified 3D texture.
+#[inline]
+pub fn texture_3d_state(handle: Texture3DHandle) -> ResourceState {
+    ctx().texture_3d_state(handle)
+}
+
+/// Update a contiguous subregion of an existing three-dimensional texture object.
+#[inline]
+pub fn update_texture_3d(handle: Texture3DHandle, area: Aabb3<u32>, data: &[u8]) -> CrResult<()> {
+    ctx().update_texture_3d(handle, area, data)
+}
+
+/// Delete the 3D texture object.
+#[inline]
+pub fn delete_texture_3d(handle: Texture3DHandle) {
+    ctx().delete_texture_3d(handle);
+}
+
 /// Create render texture object, which could be attached with a framebuffer.
 #[inline]
 pub fn create_render_texture(params: RenderTextureParams) -> Result<RenderTextureHandle> {
     ctx().create_render_texture(params)
 }
 
+/// Checks if the textures with `format` could be created on this device, e.g. the
+/// compressed formats.
+#[inline]
+pub fn is_texture_format_supported(format: TextureFormat) -> bool {
+    ctx().is_texture_format_supported(format)
+}
+
+/// Checks if the render textures with `format` could be created on this device, e.g. the
+/// floating-point formats.
+#[inline]
+pub fn is_render_texture_format_supported(format: RenderTextureFormat) -> bool {
+    ctx().is_render_texture_format_supported(format)
+}
+
+/// Gets the statistics of video device during last frame, e.g. the number of draw calls
+/// and the GPU time of surfaces.
+#[inline]
+pub fn frame_info() -> GraphicsFrameInfo {
+    ctx().frame_info()
+}
+
+/// Gets the features and limits of video device, e.g. whether instancing or multiple
+/// render targets are supported natively.
+#[inline]
+pub fn capabilities() -> Capabilities {
+    ctx().capabilities()
+}
+
 /// Gets the `RenderTextureParams` if available.
 #[inline]
 pub fn render_texture(handle: RenderTextureHandle) -> Option<RenderTextureParams> {
@@ -380,6 +1194,45 @@ pub fn render_texture_state(handle: RenderTextureHandle) -> ResourceState {
     ctx().render_texture_state(handle)
 }
 
+/// Reads the pixels in `area` of render texture back without blocking. The readback is
+/// scheduled after the draws that have been submitted, and the returned `Request`
+/// completes a frame or two later once the GPU has finished the copy.
+///
+/// The pixels are tightly packed RGBA rows, starting from the row at `area.min.y`. Each
+/// channel is a `u8`, or a native-endian `f32` for the floating-point formats.
+#[inline]
+pub fn read_render_texture(handle: RenderTextureHandle, area: Aabb2<u32>) -> Result<Request> {
+    ctx().read_render_texture(handle, area)
+}
+
+/// Reads the pixels of window back at the end of current frame without blocking. The
+/// pixels are tightly packed RGBA8 rows in *pixels*, starting from the bottom row.
+#[inline]
+pub fn read_framebuffer() -> Request {
+    ctx().read_framebuffer()
+}
+
+/// Captures the window at the end of current frame, and saves it as PNG image at `path`.
+/// The image is encoded and written on a worker thread, the returned `Request` completes
+/// with the encoded bytes once the file has been written.
+#[inline]
+pub fn capture_screenshot<T: AsRef<Path>>(path: T) -> Request {
+    ctx().capture_screenshot(path)
+}
+
+/// Starts dumping every frame of the window into `dir` as a sequence of PNG images, which
+/// are named by the indices of frames, e.g. `000000.png`.
+#[inline]
+pub fn start_frame_dump<T: AsRef<Path>>(dir: T) {
+    ctx().start_frame_dump(dir)
+}
+
+/// Stops dumping frames, the frames which have been captured are still written.
+#[inline]
+pub fn stop_frame_dump() {
+    ctx().stop_frame_dump()
+}
+
 /// Delete the render texture object.
 #[inline]
 pub fn delete_render_texture(handle: RenderTextureHandle) {
@@ -394,6 +1247,7 @@ pub(crate) mod inside {
 
     use super::backends::frame::Frame;
     use super::system::VideoSystem;
+    use super::VideoParams;
 
     pub static mut CTX: *const VideoSystem = std::ptr::null();
 
@@ -410,11 +1264,32 @@ pub(crate) mod inside {
     }
 
     /// Setup the video system.
-    pub unsafe fn setup() -> Result<()> {
+    pub unsafe fn setup(params: VideoParams) -> Result<()> {
+        debug_assert!(CTX.is_null(), "duplicated setup of video system.");
+
+        let ctx = VideoSystem::new(params)?;
+        let capabilities = ctx.capabilities();
+        CTX = Box::into_raw(Box::new(ctx));
+
+        info!(
+            "Setup video device {} with {}.",
+            capabilities.renderer, capabilities.api
+        );
+        Ok(())
+    }
+
+    /// Setup the video system with offscreen context.
+    pub unsafe fn offscreen() -> Result<()> {
         debug_assert!(CTX.is_null(), "duplicated setup of video system.");
 
-        let ctx = VideoSystem::new()?;
+        let ctx = VideoSystem::offscreen()?;
+        let capabilities = ctx.capabilities();
         CTX = Box::into_raw(Box::new(ctx));
+
+        info!(
+            "Setup offscreen video device {} with {}.",
+            capabilities.renderer, capabilities.api
+        );
         Ok(())
     }
 
@@ -0,0 +1,197 @@
+//! The keys that decide the order of draw calls in `DrawCommandBuffer`.
+//!
+//! A `SortKey` packs the sorting criteria into the bits of an `u64`, from the most
+//! significant field to the least:
+//!
+//! | Bits      | Field        | Description                                              |
+//! |-----------|--------------|----------------------------------------------------------|
+//! | `56..64`  | layer        | User defined layers, e.g. the world before the HUD.     |
+//! | `52..56`  | translucency | The bucket of blending, e.g. opaque before transparent. |
+//! | `28..52`  | depth        | The quantized depth, see `DepthOrder`.                   |
+//! | `14..28`  | material     | The id of material, to reduce the changes of textures.   |
+//! | `0..14`   | shader       | The index of shader, to reduce the changes of programs.  |
+//!
+//! The ids of materials and shaders are truncated to their lowest 14 bits, they are only
+//! used to group the draws, so collisions are harmless.
+//!
+//! ```rust
+//! use crayon::video::prelude::*;
+//!
+//! let near = SortKey::build().with_depth(1.0, DepthOrder::BackToFront).finish();
+//! let far = SortKey::build().with_depth(100.0, DepthOrder::BackToFront).finish();
+//! assert!(far < near);
+//!
+//! let hud = SortKey::build().with_layer(1).finish();
+//! assert!(near < hud && far < hud);
+//! ```
+
+use super::assets::shader::ShaderHandle;
+use crate::utils::handle::HandleLike;
+
+const LAYER_SHIFT: u64 = 56;
+const TRANSLUCENCY_SHIFT: u64 = 52;
+const DEPTH_SHIFT: u64 = 28;
+const MATERIAL_SHIFT: u64 = 14;
+const SHADER_SHIFT: u64 = 0;
+
+const LAYER_MASK: u64 = 0xff;
+const TRANSLUCENCY_MASK: u64 = 0xf;
+const DEPTH_MASK: u64 = 0x00ff_ffff;
+const ID_MASK: u64 = 0x3fff;
+
+/// The order of draws with different depths.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DepthOrder {
+    /// Draws the nearest first, which reduces the overdraw of opaque meshes.
+    FrontToBack,
+    /// Draws the farthest first, which is required to blend transparent meshes correctly.
+    BackToFront,
+}
+
+impl DepthOrder {
+    /// Quantizes the non-negative depth into 24 bits. The bits of non-negative floats are
+    /// in the same order as themselves, so the precision is relative to the depth instead
+    /// of a fixed range.
+    pub fn encode(self, depth: f32) -> u32 {
+        let bits = (depth.max(0.0).to_bits() >> 7) as u64 & DEPTH_MASK;
+        match self {
+            DepthOrder::FrontToBack => bits as u32,
+            DepthOrder::BackToFront => (!bits & DEPTH_MASK) as u32,
+        }
+    }
+}
+
+/// The key of draw calls, see the module documents for its bit layout.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SortKey(u64);
+
+impl SortKey {
+    /// Creates a builder of `SortKey`, all the fields are zero by default.
+    #[inline]
+    pub fn build() -> SortKeyBuilder {
+        SortKeyBuilder(SortKey(0))
+    }
+
+    /// Creates a key from its raw bits.
+    #[inline]
+    pub fn from_bits(bits: u64) -> Self {
+        SortKey(bits)
+    }
+
+    #[inline]
+    pub fn bits(self) -> u64 {
+        self.0
+    }
+
+    #[inline]
+    pub fn layer(self) -> u8 {
+        self.field(LAYER_SHIFT, LAYER_MASK) as u8
+    }
+
+    #[inline]
+    pub fn translucency(self) -> u8 {
+        self.field(TRANSLUCENCY_SHIFT, TRANSLUCENCY_MASK) as u8
+    }
+
+    /// Gets the quantized depth, which is encoded by `DepthOrder`.
+    #[inline]
+    pub fn depth(self) -> u32 {
+        self.field(DEPTH_SHIFT, DEPTH_MASK) as u32
+    }
+
+    #[inline]
+    pub fn material(self) -> u32 {
+        self.field(MATERIAL_SHIFT, ID_MASK) as u32
+    }
+
+    #[inline]
+    pub fn shader(self) -> u32 {
+        self.field(SHADER_SHIFT, ID_MASK) as u32
+    }
+
+    #[inline]
+    fn field(self, shift: u64, mask: u64) -> u64 {
+        (self.0 >> shift) & mask
+    }
+}
+
+/// The builder of `SortKey`.
+#[derive(Debug, Copy, Clone)]
+pub struct SortKeyBuilder(SortKey);
+
+impl SortKeyBuilder {
+    /// Sets the layer, the draws of lower layers are drawn first.
+    #[inline]
+    pub fn with_layer(self, layer: u8) -> Self {
+        self.with_field(LAYER_SHIFT, LAYER_MASK, u64::from(layer))
+    }
+
+    /// Sets the bucket of translucency in `0..16`, the draws in lower buckets are drawn
+    /// first in the same layer.
+    #[inline]
+    pub fn with_translucency(self, bucket: u8) -> Self {
+        assert!(u64::from(bucket) <= TRANSLUCENCY_MASK);
+        self.with_field(TRANSLUCENCY_SHIFT, TRANSLUCENCY_MASK, u64::from(bucket))
+    }
+
+    /// Sets the depth, e.g. the distance to camera, which is encoded in `order`.
+    #[inline]
+    pub fn with_depth(self, depth: f32, order: DepthOrder) -> Self {
+        let bits = order.encode(depth);
+        self.with_field(DEPTH_SHIFT, DEPTH_MASK, u64::from(bits))
+    }
+
+    #[inline]
+    pub fn with_material(self, id: u32) -> Self {
+        self.with_field(MATERIAL_SHIFT, ID_MASK, u64::from(id))
+    }
+
+    #[inline]
+    pub fn with_shader(self, shader: ShaderHandle) -> Self {
+        self.with_field(SHADER_SHIFT, ID_MASK, u64::from(shader.index()))
+    }
+
+    #[inline]
+    pub fn finish(self) -> SortKey {
+        self.0
+    }
+
+    #[inline]
+    fn with_field(mut self, shift: u64, mask: u64, value: u64) -> Self {
+        (self.0).0 = ((self.0).0 & !(mask << shift)) | ((value & mask) << shift);
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn layout() {
+        let shader = ShaderHandle::new(0x4001, 1);
+        let key = SortKey::build()
+            .with_layer(3)
+            .with_translucency(2)
+            .with_depth(1.5, DepthOrder::FrontToBack)
+            .with_material(0x4005)
+            .with_shader(shader)
+            .finish();
+
+        assert_eq!(key.layer(), 3);
+        assert_eq!(key.translucency(), 2);
+        assert_eq!(key.depth(), DepthOrder::FrontToBack.encode(1.5));
+        assert_eq!(key.material(), 5);
+        assert_eq!(key.shader(), 1);
+        assert_eq!(SortKey::from_bits(key.bits()), key);
+
+        let depth = |v, order| SortKey::build().with_depth(v, order).finish();
+        assert!(depth(0.5, DepthOrder::FrontToBack) < depth(1.0, DepthOrder::FrontToBack));
+        assert!(depth(1.0, DepthOrder::BackToFront) < depth(0.5, DepthOrder::BackToFront));
+        assert_eq!(depth(-1.0, DepthOrder::FrontToBack), SortKey::default());
+
+        let bucket = |v| SortKey::build().with_translucency(v).finish();
+        assert!(depth(1000.0, DepthOrder::FrontToBack) < bucket(1));
+        assert!(bucket(15) < SortKey::build().with_layer(1).finish());
+    }
+}
@@ -1,22 +1,39 @@
-use std::sync::{Arc, RwLock};
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use uuid::Uuid;
 
 use crate::application::prelude::{LifecycleListener, LifecycleListenerHandle};
-use crate::math::prelude::{Aabb2, Vector2};
+use crate::math::prelude::{Aabb2, Aabb3, Vector2};
 use crate::prelude::CrResult;
-use crate::res::utils::prelude::{ResourcePool, ResourceState};
-use crate::utils::prelude::{DoubleBuf, ObjectPool};
+use crate::res::request::Request;
+use crate::res::utils::prelude::{EvictionCallback, ResourcePool, ResourceState};
+use crate::utils::prelude::{DoubleBuf, HashValue, ObjectPool};
+use crate::window::prelude::{Event, EventListener, EventListenerHandle, WindowEvent};
 
-use super::assets::mesh_loader::MeshLoader;
+use super::assets::ktx2::{BasisTranscoders, Ktx2Decoder};
+use super::assets::mesh_loader::{MeshCopies, MeshLoader, MeshParsers};
 use super::assets::prelude::*;
-use super::assets::texture_loader::TextureLoader;
+use super::assets::texture_3d_loader::Texture3DLoader;
+use super::assets::texture_cube_loader::TextureCubeLoader;
+use super::assets::texture_loader::{TextureLoader, TextureStreams};
 use super::backends::frame::*;
+use super::backends::retained::RetainedObjects;
 use super::backends::{self, Visitor};
+use super::capabilities::Capabilities;
+use super::capture::Captures;
+use super::command::FrameArena;
 use super::errors::*;
+use super::frame_info::{GraphicsFrameInfo, VBlankTracker};
+use super::tracker::HandleTracker;
+use super::validation;
+use super::{VSync, VideoParams};
 
 /// The centralized management of video sub-system.
 pub struct VideoSystem {
     lis: LifecycleListenerHandle,
+    events: EventListenerHandle,
     state: Arc<VideoState>,
 }
 
@@ -24,33 +41,234 @@ struct VideoState {
     frames: Arc<DoubleBuf<Frame>>,
     surfaces: RwLock<ObjectPool<SurfaceHandle, SurfaceParams>>,
     shaders: RwLock<ObjectPool<ShaderHandle, ShaderParams>>,
+    uniform_buffers: RwLock<ObjectPool<UniformBufferHandle, UniformBufferParams>>,
+    queries: RwLock<ObjectPool<QueryHandle, QueryData>>,
+    samplers: RwLock<ObjectPool<SamplerHandle, SamplerParams>>,
     meshes: RwLock<ResourcePool<MeshHandle, MeshLoader>>,
+    mesh_copies: Arc<Mutex<MeshCopies>>,
+    mesh_parsers: MeshParsers,
+    instance_buffers: RwLock<ObjectPool<InstanceBufferHandle, InstanceBufferParams>>,
     textures: RwLock<ResourcePool<TextureHandle, TextureLoader>>,
+    texture_placeholder: RwLock<Option<TextureHandle>>,
+    // The 1x1 magenta texture created by default, which is deleted once replaced.
+    default_texture_placeholder: RwLock<Option<TextureHandle>>,
+    texture_streams: Arc<Mutex<TextureStreams>>,
+    texture_formats: Vec<TextureFormat>,
+    basis_transcoder: BasisTranscoders,
+    texture_cubes: RwLock<ResourcePool<TextureCubeHandle, TextureCubeLoader>>,
+    texture_3ds: RwLock<ResourcePool<Texture3DHandle, Texture3DLoader>>,
     render_textures: RwLock<ObjectPool<RenderTextureHandle, RenderTextureParams>>,
+    render_texture_formats: Vec<RenderTextureFormat>,
+    capabilities: Capabilities,
+    captures: Mutex<Captures>,
+    frame_info: Mutex<GraphicsFrameInfo>,
+    validation: AtomicBool,
+    wireframe: AtomicBool,
+    tracker: HandleTracker,
 }
 
+#[derive(Debug, Copy, Clone)]
+struct QueryData {
+    params: QueryParams,
+    available: bool,
+    result: u64,
+}
+
+// All the formats of texture, which are filtered by the capabilities of backend.
+const TEXTURE_FORMATS: [TextureFormat; 28] = [
+    TextureFormat::R8,
+    TextureFormat::RG8,
+    TextureFormat::RGB8,
+    TextureFormat::RGBA8,
+    TextureFormat::RGB565,
+    TextureFormat::RGBA4,
+    TextureFormat::RGBA5551,
+    TextureFormat::RGBA1010102,
+    TextureFormat::R16F,
+    TextureFormat::RG16F,
+    TextureFormat::RGB16F,
+    TextureFormat::RGBA16F,
+    TextureFormat::R32F,
+    TextureFormat::RG32F,
+    TextureFormat::RGB32F,
+    TextureFormat::RGBA32F,
+    TextureFormat::PvrtcRGB4BPP,
+    TextureFormat::PvrtcRGB2BPP,
+    TextureFormat::PvrtcRGBA4BPP,
+    TextureFormat::PvrtcRGBA2BPP,
+    TextureFormat::Etc2RGB4BPP,
+    TextureFormat::Etc2RGBA8BPP,
+    TextureFormat::S3tcDxt1RGB4BPP,
+    TextureFormat::S3tcDxt5RGBA8BPP,
+    TextureFormat::Astc4x4RGBA8BPP,
+    TextureFormat::Astc8x8RGBA2BPP,
+    TextureFormat::SRGB8,
+    TextureFormat::SRGBA8,
+];
+
+// All the formats of render texture, which are filtered by the capabilities of backend.
+const RENDER_TEXTURE_FORMATS: [RenderTextureFormat; 10] = [
+    RenderTextureFormat::RGB8,
+    RenderTextureFormat::RGBA4,
+    RenderTextureFormat::RGBA8,
+    RenderTextureFormat::SRGBA8,
+    RenderTextureFormat::RGBA16F,
+    RenderTextureFormat::RG11B10F,
+    RenderTextureFormat::Depth16,
+    RenderTextureFormat::Depth24,
+    RenderTextureFormat::Depth32,
+    RenderTextureFormat::Depth24Stencil8,
+];
+
 impl VideoState {
-    fn new() -> Self {
+    fn new(visitor: &dyn Visitor) -> Self {
         let frames = Arc::new(DoubleBuf::new(
             Frame::with_capacity(64 * 1024),
             Frame::with_capacity(64 * 1024),
         ));
 
+        let mesh_copies = Arc::new(Mutex::new(MeshCopies::default()));
+        let parsers: Vec<Arc<dyn MeshParser>> = vec![Arc::new(ObjParser)];
+        let mesh_parsers = Arc::new(RwLock::new(parsers));
+        let mesh_loader =
+            MeshLoader::new(frames.clone(), mesh_copies.clone(), mesh_parsers.clone());
+
+        let texture_formats: Vec<_> = TEXTURE_FORMATS
+            .iter()
+            .cloned()
+            .filter(|&v| visitor.is_texture_format_supported(v))
+            .collect();
+
+        let basis_transcoder: BasisTranscoders = Arc::new(RwLock::new(None));
+        let ktx2 = Arc::new(Ktx2Decoder::new(
+            texture_formats.clone(),
+            basis_transcoder.clone(),
+        ));
+
+        // The streamed textures could not be sampled without clamping the base level.
+        let mut texture_streams = TextureStreams::new();
+        if !visitor.capabilities().texture_base_level {
+            texture_streams.set_params(None);
+        }
+
+        let texture_streams = Arc::new(Mutex::new(texture_streams));
+        let texture_loader =
+            TextureLoader::new(frames.clone(), texture_streams.clone(), ktx2.clone());
+        let texture_cube_loader = TextureCubeLoader::new(frames.clone(), ktx2.clone());
+        let texture_3d_loader = Texture3DLoader::new(frames.clone(), ktx2);
+
         VideoState {
             surfaces: RwLock::new(ObjectPool::new()),
             shaders: RwLock::new(ObjectPool::new()),
-            meshes: RwLock::new(ResourcePool::new(MeshLoader::new(frames.clone()))),
-            textures: RwLock::new(ResourcePool::new(TextureLoader::new(frames.clone()))),
+            uniform_buffers: RwLock::new(ObjectPool::new()),
+            queries: RwLock::new(ObjectPool::new()),
+            samplers: RwLock::new(ObjectPool::new()),
+            meshes: RwLock::new(ResourcePool::new(mesh_loader)),
+            mesh_copies,
+            mesh_parsers,
+            instance_buffers: RwLock::new(ObjectPool::new()),
+            textures: RwLock::new(ResourcePool::new(texture_loader)),
+            texture_placeholder: RwLock::new(None),
+            default_texture_placeholder: RwLock::new(None),
+            texture_streams,
+            texture_formats,
+            basis_transcoder,
+            texture_cubes: RwLock::new(ResourcePool::new(texture_cube_loader)),
+            texture_3ds: RwLock::new(ResourcePool::new(texture_3d_loader)),
             render_textures: RwLock::new(ObjectPool::new()),
+            render_texture_formats: RENDER_TEXTURE_FORMATS
+                .iter()
+                .cloned()
+                .filter(|&v| visitor.is_render_texture_format_supported(v))
+                .collect(),
+            capabilities: visitor.capabilities(),
+            captures: Mutex::new(Captures::new()),
+            frame_info: Mutex::new(GraphicsFrameInfo::default()),
+            validation: AtomicBool::new(cfg!(any(debug_assertions, feature = "validation"))),
+            wireframe: AtomicBool::new(false),
+            tracker: HandleTracker::new(),
             frames,
         }
     }
 }
 
+// The states of OpenGL context, which are changed by window events.
+#[derive(Default)]
+struct ContextState {
+    lost: AtomicBool,
+    restored: AtomicBool,
+}
+
+impl EventListener for Arc<ContextState> {
+    fn on(&mut self, v: &Event) -> CrResult<()> {
+        match *v {
+            Event::Window(WindowEvent::ContextLost) => {
+                self.lost.store(true, Ordering::Relaxed);
+            }
+            Event::Window(WindowEvent::ContextRestored) => {
+                self.restored.store(true, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
 struct Lifecycle {
     last_dimensions: Vector2<u32>,
     visitor: Box<dyn Visitor>,
     state: Arc<VideoState>,
+    query_results: Vec<(QueryHandle, u64)>,
+    timer_results: Vec<(SurfaceHandle, u64)>,
+    vsync: VSync,
+    vblanks: VBlankTracker,
+    context: Arc<ContextState>,
+    // The creations of alive objects, which are replayed once the lost context is restored.
+    retained: Option<RetainedObjects>,
+}
+
+impl Lifecycle {
+    fn new(visitor: Box<dyn Visitor>, state: Arc<VideoState>, params: VideoParams) -> Self {
+        let retained = if params.restore_lost_context {
+            Some(RetainedObjects::new())
+        } else {
+            None
+        };
+
+        Lifecycle {
+            state,
+            visitor,
+            last_dimensions: dimensions_pixels(),
+            query_results: Vec::new(),
+            timer_results: Vec::new(),
+            vsync: params.vsync,
+            vblanks: VBlankTracker::new(),
+            context: Arc::new(ContextState::default()),
+            retained,
+        }
+    }
+
+    // Recreates the backend with the restored context, and the objects inside it.
+    fn restore(&mut self) -> CrResult<()> {
+        self.context.lost.store(false, Ordering::Relaxed);
+        self.visitor = backends::new()?;
+
+        match self.retained {
+            Some(ref retained) => unsafe {
+                retained.restore(self.visitor.as_mut())?;
+                info!(
+                    "Restored {} video objects with the context.",
+                    retained.len()
+                );
+            },
+            None => {
+                warn!("The video objects have been lost with the context.");
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl LifecycleListener for Lifecycle {
@@ -60,6 +278,13 @@ impl LifecycleListener for Lifecycle {
         self.state.frames.write().clear();
         self.state.meshes.write().unwrap().advance()?;
         self.state.textures.write().unwrap().advance()?;
+        self.state
+            .texture_streams
+            .lock()
+            .unwrap()
+            .advance(&self.state.frames);
+        self.state.texture_cubes.write().unwrap().advance()?;
+        self.state.texture_3ds.write().unwrap().advance()?;
         Ok(())
     }
 
@@ -72,10 +297,77 @@ impl LifecycleListener for Lifecycle {
             crate::window::inside::resize(dimensions);
         }
 
+        // Captures the window after all the draws of this frame.
         self.state
-            .frames
-            .write_back_buf()
-            .dispatch(self.visitor.as_mut(), self.last_dimensions)?;
+            .captures
+            .lock()
+            .unwrap()
+            .schedule(&mut self.state.frames.write(), dimensions);
+
+        if self.context.restored.swap(false, Ordering::Relaxed) {
+            self.restore()?;
+        }
+
+        let mut frame = self.state.frames.write_back_buf();
+        if let Some(ref mut retained) = self.retained {
+            for v in &frame.cmds {
+                retained.retain(v, &frame.bufs);
+            }
+        }
+
+        // Nothing could be drawn until the lost context is restored.
+        if self.context.lost.load(Ordering::Relaxed) {
+            frame.discard();
+            self.state.captures.lock().unwrap().advance();
+            return Ok(());
+        }
+
+        let (drawcall, triangles) = frame.dispatch(self.visitor.as_mut(), self.last_dimensions)?;
+        drop(frame);
+
+        // Fetches the GPU time of surfaces in the latest frame finished by GPU.
+        unsafe {
+            self.visitor.timer_results(&mut self.timer_results)?;
+        }
+
+        {
+            let mut info = self.state.frame_info.lock().unwrap();
+            info.drawcall = drawcall;
+            info.triangles = triangles;
+            info.state_changes = self.visitor.take_state_changes();
+            info.mesh_memory = self.state.meshes.read().unwrap().memory();
+            info.texture_memory = self.state.textures.read().unwrap().memory();
+
+            // The frames are presented right after this, so the intervals between them are
+            // paced by the vertical blanks if vsync is enabled.
+            let now = crate::application::sys::precise_timestamp();
+            self.vblanks
+                .advance(now, self.vsync != VSync::Off, &mut info);
+
+            info.collect_timers(&mut self.timer_results);
+        }
+
+        // Fetches the results of occlusion queries which have been finished by GPU.
+        unsafe {
+            self.visitor.query_results(&mut self.query_results)?;
+        }
+
+        // Completes the requests of readbacks which have been finished by GPU.
+        unsafe {
+            self.visitor.readback_results()?;
+        }
+
+        self.state.captures.lock().unwrap().advance();
+
+        if !self.query_results.is_empty() {
+            let mut queries = self.state.queries.write().unwrap();
+            for (handle, result) in self.query_results.drain(..) {
+                if let Some(v) = queries.get_mut(handle) {
+                    v.available = true;
+                    v.result = result;
+                }
+            }
+        }
 
         Ok(())
     }
@@ -84,50 +376,345 @@ impl LifecycleListener for Lifecycle {
 impl Drop for VideoSystem {
     fn drop(&mut self) {
         crate::application::detach(self.lis);
+        crate::window::detach(self.events);
+
+        let alive = self.state.tracker.alive();
+        if !alive.is_empty() {
+            warn!(
+                "{} video resources are still alive at shutdown.",
+                alive.len()
+            );
+            for v in alive {
+                warn!("Leaked {}", v);
+            }
+        }
     }
 }
 
 impl VideoSystem {
     /// Create a new `VideoSystem`.
-    pub fn new() -> CrResult<Self> {
-        let state = Arc::new(VideoState::new());
+    pub fn new(params: VideoParams) -> CrResult<Self> {
         let visitor = backends::new()?;
+        let sys = VideoSystem::from(visitor, params);
+        sys.setup_texture_placeholder()?;
+        Ok(sys)
+    }
 
-        Ok(VideoSystem {
-            state: state.clone(),
-            lis: crate::application::attach(Lifecycle {
-                state,
-                visitor,
-                last_dimensions: dimensions_pixels(),
-            }),
-        })
+    /// Create a new `VideoSystem` with offscreen context, which renders into a framebuffer
+    /// object of the window dimensions instead.
+    pub fn offscreen() -> CrResult<Self> {
+        let visitor = backends::new_offscreen(dimensions_pixels())?;
+        let sys = VideoSystem::from(visitor, VideoSystem::detached_params());
+        sys.setup_texture_placeholder()?;
+        Ok(sys)
     }
 
     /// Create a headless `VideoSystem`.
     pub fn headless() -> Self {
-        let state = Arc::new(VideoState::new());
         let visitor = backends::new_headless();
+        let sys = VideoSystem::from(visitor, VideoSystem::detached_params());
+        sys.setup_texture_placeholder().unwrap();
+        sys
+    }
+
+    fn from(visitor: Box<dyn Visitor>, params: VideoParams) -> Self {
+        let state = Arc::new(VideoState::new(visitor.as_ref()));
+        let lifecycle = Lifecycle::new(visitor, state.clone(), params);
 
         VideoSystem {
-            state: state.clone(),
-            lis: crate::application::attach(Lifecycle {
-                state,
-                visitor,
-                last_dimensions: Vector2::new(0, 0),
-            }),
+            state,
+            events: crate::window::attach(lifecycle.context.clone()),
+            lis: crate::application::attach(lifecycle),
+        }
+    }
+
+    // The offscreen and headless contexts are neither presented nor lost.
+    fn detached_params() -> VideoParams {
+        VideoParams {
+            vsync: VSync::Off,
+            restore_lost_context: false,
         }
     }
 
+    // Creates the default 1x1 magenta placeholder of textures.
+    fn setup_texture_placeholder(&self) -> CrResult<()> {
+        let mut params = TextureParams::default();
+        params.dimensions = Vector2::new(1, 1);
+
+        let data = TextureData {
+            bytes: vec![vec![255, 0, 255, 255].into_boxed_slice()],
+        };
+
+        let texture = self.create_texture(params, data)?;
+        *self.state.default_texture_placeholder.write().unwrap() = Some(texture);
+        self.set_texture_placeholder(Some(texture));
+        Ok(())
+    }
+
     pub(crate) fn frames(&self) -> Arc<DoubleBuf<Frame>> {
         self.state.frames.clone()
     }
 }
 
+impl VideoSystem {
+    /// Sets whether the submitted commands are validated.
+    #[inline]
+    pub fn set_validation(&self, enabled: bool) {
+        self.state.validation.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Checks if the submitted commands are validated.
+    #[inline]
+    pub fn validation(&self) -> bool {
+        self.state.validation.load(Ordering::Relaxed)
+    }
+
+    /// Rasterizes the polygons of all the surfaces as lines.
+    pub fn set_wireframe(&self, enable: bool) {
+        if self.state.wireframe.swap(enable, Ordering::Relaxed) != enable {
+            let cmd = Command::SetWireframe(enable);
+            self.state.frames.write().cmds.push(cmd);
+        }
+    }
+
+    /// Checks if the polygons of all the surfaces are rasterized as lines.
+    #[inline]
+    pub fn wireframe(&self) -> bool {
+        self.state.wireframe.load(Ordering::Relaxed)
+    }
+
+    /// Sets whether the lifetimes of resources are tracked.
+    #[inline]
+    pub fn set_tracking(&self, enabled: bool) {
+        self.state.tracker.set_enabled(enabled);
+    }
+
+    /// Checks if the lifetimes of resources are tracked.
+    #[inline]
+    pub fn tracking(&self) -> bool {
+        self.state.tracker.enabled()
+    }
+
+    /// Sets the name of object, which is shown in the frame captures of graphics debuggers
+    /// and the descriptions of tracked resources.
+    pub fn set_debug_name(&self, object: VideoObject, name: &str) {
+        self.state.tracker.set_label(object.handle(), name);
+
+        let mut frame = self.state.frames.write();
+        let ptr = frame.bufs.extend_from_str(name);
+        frame.cmds.push(Command::SetDebugName(object, ptr));
+    }
+
+    /// Gets the descriptions of tracked resources that are still alive.
+    #[inline]
+    pub fn alive_resources(&self) -> Vec<String> {
+        self.state.tracker.alive()
+    }
+
+    /// Validates the command against the resources it refers to, the bytes and uniform
+    /// variables of command are stored in `bufs`.
+    pub(crate) fn validate(&self, cmd: &Command, bufs: &FrameArena) -> Result<()> {
+        match *cmd {
+            Command::Bind(surface) => {
+                let surfaces = self.state.surfaces.read().unwrap();
+                self.check_handle(surfaces.contains(surface), surface)?;
+            }
+
+            Command::Draw(shader, mesh, mesh_index, instances, ptr) => {
+                self.validate_draw(shader, mesh, mesh_index, instances, bufs.as_slice(ptr))?;
+            }
+
+            Command::BeginQuery(handle) | Command::EndQuery(handle) => {
+                let queries = self.state.queries.read().unwrap();
+                self.check_handle(queries.contains(handle), handle)?;
+            }
+
+            Command::UpdateTexture(handle, level, area, _) => {
+                let textures = self.state.textures.read().unwrap();
+                self.check_handle(textures.contains(handle), handle)?;
+
+                if let Some(params) = textures.resource(handle) {
+                    validation::mip_level(handle, level, params.mip_levels())?;
+                    validation::area(handle, area, params.mip_dimensions(level))?;
+                }
+            }
+
+            Command::GenerateMipmaps(handle) => {
+                let textures = self.state.textures.read().unwrap();
+                self.check_handle(textures.contains(handle), handle)?;
+            }
+
+            Command::UpdateTexture3D(handle, area, _) => {
+                let textures = self.state.texture_3ds.read().unwrap();
+                self.check_handle(textures.contains(handle), handle)?;
+
+                if let Some(params) = textures.resource(handle) {
+                    validation::volume(handle, area, params.dimensions)?;
+                }
+            }
+
+            Command::UpdateUniformBuffer(handle, offset, ptr) => {
+                let buffers = self.state.uniform_buffers.read().unwrap();
+                let params = buffers
+                    .get(handle)
+                    .ok_or_else(|| self.invalid_handle(handle))?;
+
+                validation::range(handle, offset, bufs.as_slice(ptr).len(), params.size)?;
+            }
+
+            Command::UpdateVertexBuffer(handle, offset, ptr) => {
+                let meshes = self.state.meshes.read().unwrap();
+                self.check_handle(meshes.contains(handle), handle)?;
+
+                if let Some(params) = meshes.resource(handle) {
+                    let len = bufs.as_slice(ptr).len();
+                    validation::range(handle, offset, len, params.vertex_buffer_len())?;
+                }
+            }
+
+            Command::UpdateIndexBuffer(handle, offset, ptr) => {
+                let meshes = self.state.meshes.read().unwrap();
+                self.check_handle(meshes.contains(handle), handle)?;
+
+                if let Some(params) = meshes.resource(handle) {
+                    let len = bufs.as_slice(ptr).len();
+                    validation::range(handle, offset, len, params.index_buffer_len())?;
+                }
+            }
+
+            Command::UpdateInstanceBuffer(handle, offset, ptr) => {
+                let buffers = self.state.instance_buffers.read().unwrap();
+                let params = buffers
+                    .get(handle)
+                    .ok_or_else(|| self.invalid_handle(handle))?;
+
+                validation::range(
+                    handle,
+                    offset,
+                    bufs.as_slice(ptr).len(),
+                    params.buffer_len(),
+                )?;
+            }
+
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn validate_draw(
+        &self,
+        shader: ShaderHandle,
+        mesh: MeshHandle,
+        mesh_index: MeshIndex,
+        instances: Option<(InstanceBufferHandle, u32)>,
+        vars: &[(HashValue<str>, UniformVariable)],
+    ) -> Result<()> {
+        let shaders = self.state.shaders.read().unwrap();
+        let params = shaders
+            .get(shader)
+            .ok_or_else(|| self.invalid_handle(shader))?;
+
+        validation::uniforms(shader, params, vars)?;
+        for &(_, variable) in vars {
+            self.validate_uniform_variable(variable)?;
+        }
+
+        let instances = match instances {
+            Some((handle, num)) => {
+                let buffers = self.state.instance_buffers.read().unwrap();
+                let v = buffers
+                    .get(handle)
+                    .ok_or_else(|| self.invalid_handle(handle))?;
+
+                validation::instances(handle, v, num)?;
+                Some((handle, v.layout))
+            }
+            None => None,
+        };
+
+        let meshes = self.state.meshes.read().unwrap();
+        self.check_handle(meshes.contains(mesh), mesh)?;
+
+        // The draws of meshes that are still loading are skipped by backend.
+        if let Some(v) = meshes.resource(mesh) {
+            let instances = instances.as_ref().map(|v| (v.0, &v.1));
+            validation::attributes(shader, params, (mesh, &v.layout), instances)?;
+            validation::mesh_index(mesh, v, mesh_index)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_uniform_variable(&self, variable: UniformVariable) -> Result<()> {
+        match variable {
+            UniformVariable::Texture(handle, sampler) => {
+                let textures = self.state.textures.read().unwrap();
+                self.check_handle(textures.contains(handle), handle)?;
+                self.validate_sampler(sampler)
+            }
+            UniformVariable::TextureCube(handle, sampler) => {
+                let textures = self.state.texture_cubes.read().unwrap();
+                self.check_handle(textures.contains(handle), handle)?;
+                self.validate_sampler(sampler)
+            }
+            UniformVariable::Texture3D(handle, sampler) => {
+                let textures = self.state.texture_3ds.read().unwrap();
+                self.check_handle(textures.contains(handle), handle)?;
+                self.validate_sampler(sampler)
+            }
+            UniformVariable::UniformBuffer(handle) => {
+                let buffers = self.state.uniform_buffers.read().unwrap();
+                self.check_handle(buffers.contains(handle), handle)
+            }
+            UniformVariable::RenderTexture(handle, sampler) => {
+                match self.state.render_textures.read().unwrap().get(handle) {
+                    Some(params) if !params.sampler => Err(Error::SampleRenderBuffer),
+                    Some(_) => self.validate_sampler(sampler),
+                    None => Err(self.invalid_handle(handle)),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_sampler(&self, sampler: Option<SamplerHandle>) -> Result<()> {
+        match sampler {
+            Some(handle) => {
+                let samplers = self.state.samplers.read().unwrap();
+                self.check_handle(samplers.contains(handle), handle)
+            }
+            None => Ok(()),
+        }
+    }
+
+    // Checks if the handle is contained in its pool, and has not been deleted. Notes that
+    // the resources created from files might be kept in cache after being deleted.
+    fn check_handle<H: Debug>(&self, contains: bool, handle: H) -> Result<()> {
+        if contains && !self.state.tracker.is_deleted(&handle) {
+            Ok(())
+        } else {
+            Err(self.invalid_handle(handle))
+        }
+    }
+
+    // Describes the invalid handle, with where it was created and deleted if it's tracked.
+    fn invalid_handle<H: Debug>(&self, handle: H) -> Error {
+        Error::HandleInvalid(self.state.tracker.describe(handle))
+    }
+}
+
 impl VideoSystem {
     /// Creates an surface with `SurfaceParams`.
     pub fn create_surface(&self, params: SurfaceParams) -> Result<SurfaceHandle> {
+        if self.validation() {
+            validation::attachments(&params, |v| self.render_texture(v))?;
+        }
+
         let handle = self.state.surfaces.write().unwrap().create(params);
 
+        self.state.tracker.create(handle);
+
         {
             let cmd = Command::CreateSurface(Box::new((handle, params)));
             self.state.frames.write().cmds.push(cmd);
@@ -141,6 +728,40 @@ impl VideoSystem {
         self.state.surfaces.read().unwrap().get(handle).cloned()
     }
 
+    /// Checks if the colors should be encoded into sRGB space by shaders when drawing
+    /// into the surface.
+    pub fn requires_srgb_encoding(&self, handle: SurfaceHandle) -> bool {
+        match self.surface(handle) {
+            // The framebuffers of secondary windows are never encoded by hardware.
+            Some(params) => {
+                params.is_window_framebuffer()
+                    && !(params.srgb
+                        && params.window.is_none()
+                        && self.state.capabilities.srgb_backbuffer)
+            }
+            None => false,
+        }
+    }
+
+    /// Gets the dimensions in pixels of the viewport that surface draws into.
+    pub fn surface_dimensions(&self, handle: SurfaceHandle) -> Option<Vector2<u32>> {
+        let params = self.surface(handle)?;
+        let attachment = params
+            .colors
+            .iter()
+            .chain(Some(&params.depth_stencil))
+            .filter_map(|&v| v)
+            .next();
+
+        let dimensions = match (attachment, params.window) {
+            (Some(rt), _) => self.render_texture(rt)?.dimensions,
+            (None, Some(window)) => crate::window::window_dimensions(window)?,
+            (None, None) => dimensions_pixels(),
+        };
+
+        Some(params.viewport(dimensions).0.size)
+    }
+
     /// Get the resource state of specified surface.
     #[inline]
     pub fn surface_state(&self, handle: SurfaceHandle) -> ResourceState {
@@ -153,6 +774,7 @@ impl VideoSystem {
 
     /// Deletes surface object.
     pub fn delete_surface(&self, handle: SurfaceHandle) {
+        self.state.tracker.delete(handle);
         if self.state.surfaces.write().unwrap().free(handle).is_some() {
             let cmd = Command::DeleteSurface(handle);
             self.state.frames.write().cmds.push(cmd);
@@ -173,6 +795,8 @@ impl VideoSystem {
 
         let handle = self.state.shaders.write().unwrap().create(params.clone());
 
+        self.state.tracker.create(handle);
+
         {
             let cmd = Command::CreateShader(Box::new((handle, params, vs, fs)));
             self.state.frames.write().cmds.push(cmd);
@@ -181,6 +805,25 @@ impl VideoSystem {
         Ok(handle)
     }
 
+    /// Replaces the sources of shader, the handle keeps valid. The old program is
+    /// kept if the new sources failed to compile.
+    pub fn update_shader(&self, handle: ShaderHandle, vs: String, fs: String) -> Result<()> {
+        let params = self
+            .state
+            .shaders
+            .read()
+            .unwrap()
+            .get(handle)
+            .cloned()
+            .ok_or_else(|| self.invalid_handle(handle))?;
+
+        params.validate(&vs, &fs)?;
+
+        let cmd = Command::UpdateShader(Box::new((handle, params, vs, fs)));
+        self.state.frames.write().cmds.push(cmd);
+        Ok(())
+    }
+
     /// Gets the `ShaderParams` if available.
     #[inline]
     pub fn shader(&self, handle: ShaderHandle) -> Option<ShaderParams> {
@@ -200,6 +843,7 @@ impl VideoSystem {
     /// Delete shader state object.
     #[inline]
     pub fn delete_shader(&self, handle: ShaderHandle) {
+        self.state.tracker.delete(handle);
         if self.state.shaders.write().unwrap().free(handle).is_some() {
             let cmd = Command::DeleteShader(handle);
             self.state.frames.write().cmds.push(cmd);
@@ -208,112 +852,463 @@ impl VideoSystem {
 }
 
 impl VideoSystem {
-    /// Create a new mesh object.
-    #[inline]
-    pub fn create_mesh<T>(&self, params: MeshParams, data: T) -> CrResult<MeshHandle>
-    where
-        T: Into<Option<MeshData>>,
-    {
-        let mut meshes = self.state.meshes.write().unwrap();
-        meshes.create((params, data.into()))
-    }
+    /// Creates a new uniform buffer object with optional initial data.
+    pub fn create_uniform_buffer(
+        &self,
+        params: UniformBufferParams,
+        data: Option<&[u8]>,
+    ) -> Result<UniformBufferHandle> {
+        params.validate(data)?;
 
-    /// Creates a mesh object from file asynchronously.
-    #[inline]
-    pub fn create_mesh_from<T: AsRef<str>>(&self, url: T) -> CrResult<MeshHandle> {
-        let mut meshes = self.state.meshes.write().unwrap();
-        meshes.create_from(url)
-    }
+        let handle = self.state.uniform_buffers.write().unwrap().create(params);
 
-    /// Creates a mesh object from file asynchronously.
-    #[inline]
-    pub fn create_mesh_from_uuid(&self, uuid: Uuid) -> CrResult<MeshHandle> {
-        let mut meshes = self.state.meshes.write().unwrap();
-        meshes.create_from_uuid(uuid)
-    }
+        self.state.tracker.create(handle);
 
-    /// Gets the `MeshParams` if available.
-    #[inline]
-    pub fn mesh(&self, handle: MeshHandle) -> Option<MeshParams> {
-        self.state.meshes.read().unwrap().resource(handle).cloned()
+        {
+            let data = data.map(|v| v.to_vec().into_boxed_slice());
+            let cmd = Command::CreateUniformBuffer(Box::new((handle, params, data)));
+            self.state.frames.write().cmds.push(cmd);
+        }
+
+        Ok(handle)
     }
 
-    /// Get the resource state of specified mesh.
+    /// Gets the `UniformBufferParams` if available.
     #[inline]
-    pub fn mesh_state(&self, handle: MeshHandle) -> ResourceState {
-        self.state.meshes.read().unwrap().state(handle)
+    pub fn uniform_buffer(&self, handle: UniformBufferHandle) -> Option<UniformBufferParams> {
+        self.state
+            .uniform_buffers
+            .read()
+            .unwrap()
+            .get(handle)
+            .cloned()
     }
 
-    /// Update a subset of dynamic vertex buffer. Use `offset` specifies the offset
-    /// into the buffer object's data store where data replacement will begin, measured
-    /// in bytes.
-    pub fn update_vertex_buffer(
-        &self,
-        handle: MeshHandle,
-        offset: usize,
-        data: &[u8],
-    ) -> CrResult<()> {
-        let meshes = self.state.meshes.read().unwrap();
-        if meshes.contains(handle) {
-            let mut frame = self.state.frames.write();
-            let ptr = frame.bufs.extend_from_slice(data);
-            let cmd = Command::UpdateVertexBuffer(handle, offset, ptr);
-            frame.cmds.push(cmd);
-            Ok(())
+    /// Get the resource state of specified uniform buffer.
+    #[inline]
+    pub fn uniform_buffer_state(&self, handle: UniformBufferHandle) -> ResourceState {
+        if self.state.uniform_buffers.read().unwrap().contains(handle) {
+            ResourceState::Ok
         } else {
-            bail!("{:?} is invalid.", handle);
+            ResourceState::NotReady
         }
     }
 
-    /// Update a subset of dynamic index buffer. Use `offset` specifies the offset
-    /// into the buffer object's data store where data replacement will begin, measured
-    /// in bytes.
-    pub fn update_index_buffer(
+    /// Update a subset of uniform buffer. Use `offset` specifies the offset into the
+    /// buffer object's data store where data replacement will begin, measured in bytes.
+    pub fn update_uniform_buffer(
         &self,
-        handle: MeshHandle,
+        handle: UniformBufferHandle,
         offset: usize,
         data: &[u8],
-    ) -> CrResult<()> {
-        let meshes = self.state.meshes.read().unwrap();
-        if meshes.contains(handle) {
+    ) -> Result<()> {
+        let buffers = self.state.uniform_buffers.read().unwrap();
+        if let Some(params) = buffers.get(handle) {
+            if params.hint == MeshHint::Immutable {
+                return Err(Error::UpdateImmutableBuffer);
+            }
+
+            if offset + data.len() > params.size {
+                return Err(Error::OutOfBounds);
+            }
+
             let mut frame = self.state.frames.write();
             let ptr = frame.bufs.extend_from_slice(data);
-            let cmd = Command::UpdateIndexBuffer(handle, offset, ptr);
+            let cmd = Command::UpdateUniformBuffer(handle, offset, ptr);
             frame.cmds.push(cmd);
             Ok(())
         } else {
-            bail!("{:?} is invalid.", handle);
+            Err(self.invalid_handle(handle))
         }
     }
 
-    /// Delete mesh object.
-    #[inline]
-    pub fn delete_mesh(&self, handle: MeshHandle) {
-        self.state.meshes.write().unwrap().delete(handle);
+    /// Deletes uniform buffer object.
+    pub fn delete_uniform_buffer(&self, handle: UniformBufferHandle) {
+        self.state.tracker.delete(handle);
+        if self
+            .state
+            .uniform_buffers
+            .write()
+            .unwrap()
+            .free(handle)
+            .is_some()
+        {
+            let cmd = Command::DeleteUniformBuffer(handle);
+            self.state.frames.write().cmds.push(cmd);
+        }
     }
 }
 
 impl VideoSystem {
-    /// Create texture object. A texture is an image loaded in video memory,
-    /// which can be sampled in shaders.
-    pub fn create_texture<T>(&self, params: TextureParams, data: T) -> CrResult<TextureHandle>
-    where
-        T: Into<Option<TextureData>>,
-    {
-        let mut textures = self.state.textures.write().unwrap();
-        textures.create((params, data.into()))
-    }
+    /// Creates a new occlusion query object.
+    pub fn create_query(&self, params: QueryParams) -> Result<QueryHandle> {
+        let data = QueryData {
+            params,
+            available: false,
+            result: 0,
+        };
 
-    /// Creates a texture object from file asynchronously.
-    pub fn create_texture_from<T: AsRef<str>>(&self, url: T) -> CrResult<TextureHandle> {
-        let mut textures = self.state.textures.write().unwrap();
-        textures.create_from(url)
-    }
+        let handle = self.state.queries.write().unwrap().create(data);
+
+        self.state.tracker.create(handle);
+
+        {
+            let cmd = Command::CreateQuery(handle, params);
+            self.state.frames.write().cmds.push(cmd);
+        }
+
+        Ok(handle)
+    }
+
+    /// Gets the `QueryParams` if available.
+    #[inline]
+    pub fn query(&self, handle: QueryHandle) -> Option<QueryParams> {
+        self.state
+            .queries
+            .read()
+            .unwrap()
+            .get(handle)
+            .map(|v| v.params)
+    }
+
+    /// Get the resource state of specified query.
+    #[inline]
+    pub fn query_state(&self, handle: QueryHandle) -> ResourceState {
+        if self.state.queries.read().unwrap().contains(handle) {
+            ResourceState::Ok
+        } else {
+            ResourceState::NotReady
+        }
+    }
+
+    /// Gets the latest available result of query. This never blocks, and returns `None`
+    /// if the GPU has not finished any `begin_query`/`end_query` pair of this query yet.
+    #[inline]
+    pub fn query_result(&self, handle: QueryHandle) -> Option<u64> {
+        self.state
+            .queries
+            .read()
+            .unwrap()
+            .get(handle)
+            .and_then(|v| if v.available { Some(v.result) } else { None })
+    }
+
+    /// Deletes query object.
+    pub fn delete_query(&self, handle: QueryHandle) {
+        self.state.tracker.delete(handle);
+        if self.state.queries.write().unwrap().free(handle).is_some() {
+            let cmd = Command::DeleteQuery(handle);
+            self.state.frames.write().cmds.push(cmd);
+        }
+    }
+}
+
+impl VideoSystem {
+    /// Creates a new sampler object.
+    pub fn create_sampler(&self, params: SamplerParams) -> Result<SamplerHandle> {
+        params.validate()?;
+
+        let handle = self.state.samplers.write().unwrap().create(params);
+
+        self.state.tracker.create(handle);
+
+        {
+            let cmd = Command::CreateSampler(handle, params);
+            self.state.frames.write().cmds.push(cmd);
+        }
+
+        Ok(handle)
+    }
+
+    /// Gets the `SamplerParams` if available.
+    #[inline]
+    pub fn sampler(&self, handle: SamplerHandle) -> Option<SamplerParams> {
+        self.state.samplers.read().unwrap().get(handle).cloned()
+    }
+
+    /// Get the resource state of specified sampler.
+    #[inline]
+    pub fn sampler_state(&self, handle: SamplerHandle) -> ResourceState {
+        if self.state.samplers.read().unwrap().contains(handle) {
+            ResourceState::Ok
+        } else {
+            ResourceState::NotReady
+        }
+    }
+
+    /// Deletes sampler object.
+    pub fn delete_sampler(&self, handle: SamplerHandle) {
+        self.state.tracker.delete(handle);
+        if self.state.samplers.write().unwrap().free(handle).is_some() {
+            let cmd = Command::DeleteSampler(handle);
+            self.state.frames.write().cmds.push(cmd);
+        }
+    }
+}
+
+impl VideoSystem {
+    /// Create a new mesh object.
+    #[inline]
+    pub fn create_mesh<T>(&self, params: MeshParams, data: T) -> CrResult<MeshHandle>
+    where
+        T: Into<Option<MeshData>>,
+    {
+        let mut meshes = self.state.meshes.write().unwrap();
+        let handle = meshes.create((params, data.into()))?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Creates a mesh object from file asynchronously.
+    #[inline]
+    pub fn create_mesh_from<T: AsRef<str>>(&self, url: T) -> CrResult<MeshHandle> {
+        let mut meshes = self.state.meshes.write().unwrap();
+        let handle = meshes.create_from(url)?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Creates a mesh object from file asynchronously.
+    #[inline]
+    pub fn create_mesh_from_uuid(&self, uuid: Uuid) -> CrResult<MeshHandle> {
+        let mut meshes = self.state.meshes.write().unwrap();
+        let handle = meshes.create_from_uuid(uuid)?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Gets the `MeshParams` if available.
+    #[inline]
+    pub fn mesh(&self, handle: MeshHandle) -> Option<MeshParams> {
+        self.state.meshes.read().unwrap().resource(handle).cloned()
+    }
+
+    /// Gets the uuid of mesh, if it is created from file.
+    #[inline]
+    pub fn mesh_uuid(&self, handle: MeshHandle) -> Option<Uuid> {
+        self.state.meshes.read().unwrap().uuid(handle)
+    }
+
+    /// Registers the parser of mesh files in other formats, which takes precedence over
+    /// the parsers registered before.
+    pub fn add_mesh_parser<T: MeshParser>(&self, parser: T) {
+        self.state
+            .mesh_parsers
+            .write()
+            .unwrap()
+            .insert(0, Arc::new(parser));
+    }
+
+    /// Sets the transcoder of Basis Universal images in KTX2 textures.
+    pub fn set_basis_transcoder<T: BasisTranscoder>(&self, transcoder: T) {
+        *self.state.basis_transcoder.write().unwrap() = Some(Arc::new(transcoder));
+    }
+
+    /// Sets whether the data of meshes created afterwards are kept in memory, so they
+    /// could be read back with `mesh_data`.
+    #[inline]
+    pub fn set_mesh_readable(&self, readable: bool) {
+        self.state.mesh_copies.lock().unwrap().readable = readable;
+    }
+
+    /// Gets a copy of the data of mesh, if it's created while meshes are readable.
+    #[inline]
+    pub fn mesh_data(&self, handle: MeshHandle) -> Option<MeshData> {
+        self.state
+            .mesh_copies
+            .lock()
+            .unwrap()
+            .data
+            .get(&handle)
+            .cloned()
+    }
+
+    /// Get the resource state of specified mesh.
+    #[inline]
+    pub fn mesh_state(&self, handle: MeshHandle) -> ResourceState {
+        self.state.meshes.read().unwrap().state(handle)
+    }
+
+    /// Update a subset of dynamic vertex buffer. Use `offset` specifies the offset
+    /// into the buffer object's data store where data replacement will begin, measured
+    /// in bytes.
+    pub fn update_vertex_buffer(
+        &self,
+        handle: MeshHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> CrResult<()> {
+        let meshes = self.state.meshes.read().unwrap();
+        if meshes.contains(handle) {
+            let mut frame = self.state.frames.write();
+            let ptr = frame.bufs.extend_from_slice(data);
+            let cmd = Command::UpdateVertexBuffer(handle, offset, ptr);
+            frame.cmds.push(cmd);
+            Ok(())
+        } else {
+            Err(self.invalid_handle(handle).into())
+        }
+    }
+
+    /// Update a subset of dynamic index buffer. Use `offset` specifies the offset
+    /// into the buffer object's data store where data replacement will begin, measured
+    /// in bytes.
+    pub fn update_index_buffer(
+        &self,
+        handle: MeshHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> CrResult<()> {
+        let meshes = self.state.meshes.read().unwrap();
+        if meshes.contains(handle) {
+            let mut frame = self.state.frames.write();
+            let ptr = frame.bufs.extend_from_slice(data);
+            let cmd = Command::UpdateIndexBuffer(handle, offset, ptr);
+            frame.cmds.push(cmd);
+            Ok(())
+        } else {
+            Err(self.invalid_handle(handle).into())
+        }
+    }
+
+    /// Delete mesh object.
+    #[inline]
+    pub fn delete_mesh(&self, handle: MeshHandle) {
+        self.state.tracker.delete(handle);
+        self.state.meshes.write().unwrap().delete(handle);
+    }
+}
+
+impl VideoSystem {
+    /// Creates a new instance buffer object with optional initial data.
+    pub fn create_instance_buffer(
+        &self,
+        params: InstanceBufferParams,
+        data: Option<&[u8]>,
+    ) -> Result<InstanceBufferHandle> {
+        params.validate(data)?;
+
+        let handle = self.state.instance_buffers.write().unwrap().create(params);
+
+        self.state.tracker.create(handle);
+
+        {
+            let data = data.map(|v| v.to_vec().into_boxed_slice());
+            let cmd = Command::CreateInstanceBuffer(Box::new((handle, params, data)));
+            self.state.frames.write().cmds.push(cmd);
+        }
+
+        Ok(handle)
+    }
+
+    /// Gets the `InstanceBufferParams` if available.
+    #[inline]
+    pub fn instance_buffer(&self, handle: InstanceBufferHandle) -> Option<InstanceBufferParams> {
+        self.state
+            .instance_buffers
+            .read()
+            .unwrap()
+            .get(handle)
+            .cloned()
+    }
+
+    /// Get the resource state of specified instance buffer.
+    #[inline]
+    pub fn instance_buffer_state(&self, handle: InstanceBufferHandle) -> ResourceState {
+        if self.state.instance_buffers.read().unwrap().contains(handle) {
+            ResourceState::Ok
+        } else {
+            ResourceState::NotReady
+        }
+    }
+
+    /// Update a subset of instance buffer. Use `offset` specifies the offset into the
+    /// buffer object's data store where data replacement will begin, measured in bytes.
+    pub fn update_instance_buffer(
+        &self,
+        handle: InstanceBufferHandle,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<()> {
+        let buffers = self.state.instance_buffers.read().unwrap();
+        if let Some(params) = buffers.get(handle) {
+            if params.hint == MeshHint::Immutable {
+                return Err(Error::UpdateImmutableBuffer);
+            }
+
+            if offset + data.len() > params.buffer_len() {
+                return Err(Error::OutOfBounds);
+            }
+
+            let mut frame = self.state.frames.write();
+            let ptr = frame.bufs.extend_from_slice(data);
+            let cmd = Command::UpdateInstanceBuffer(handle, offset, ptr);
+            frame.cmds.push(cmd);
+            Ok(())
+        } else {
+            Err(self.invalid_handle(handle))
+        }
+    }
+
+    /// Deletes instance buffer object.
+    pub fn delete_instance_buffer(&self, handle: InstanceBufferHandle) {
+        self.state.tracker.delete(handle);
+        if self
+            .state
+            .instance_buffers
+            .write()
+            .unwrap()
+            .free(handle)
+            .is_some()
+        {
+            let cmd = Command::DeleteInstanceBuffer(handle);
+            self.state.frames.write().cmds.push(cmd);
+        }
+    }
+}
+
+impl VideoSystem {
+    /// Create texture object. A texture is an image loaded in video memory,
+    /// which can be sampled in shaders.
+    pub fn create_texture<T>(&self, params: TextureParams, data: T) -> CrResult<TextureHandle>
+    where
+        T: Into<Option<TextureData>>,
+    {
+        let mut textures = self.state.textures.write().unwrap();
+        let handle = textures.create((params, data.into()))?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Creates a texture object from file asynchronously.
+    pub fn create_texture_from<T: AsRef<str>>(&self, url: T) -> CrResult<TextureHandle> {
+        let mut textures = self.state.textures.write().unwrap();
+        let handle = textures.create_from(url)?;
+        self.state.tracker.create(handle);
+        self.pend_texture(&textures, handle);
+        Ok(handle)
+    }
 
     /// Creates a texture object from file asynchronously.
     pub fn create_texture_from_uuid(&self, uuid: Uuid) -> CrResult<TextureHandle> {
         let mut textures = self.state.textures.write().unwrap();
-        textures.create_from_uuid(uuid)
+        let handle = textures.create_from_uuid(uuid)?;
+        self.state.tracker.create(handle);
+        self.pend_texture(&textures, handle);
+        Ok(handle)
+    }
+
+    // Binds the placeholder in place of the texture until it's created. The handles that
+    // are invalid or deleted are not replaced by the placeholder.
+    fn pend_texture(
+        &self,
+        textures: &ResourcePool<TextureHandle, TextureLoader>,
+        handle: TextureHandle,
+    ) {
+        if textures.state(handle) != ResourceState::Ok {
+            let cmd = Command::SetTexturePending(handle, true);
+            self.state.frames.write().cmds.push(cmd);
+        }
     }
 
     /// Get the resource state of specified texture.
@@ -322,28 +1317,321 @@ impl VideoSystem {
         self.state.textures.read().unwrap().state(handle)
     }
 
+    /// Gets the uuid of texture, if it is created from file.
+    #[inline]
+    pub fn texture_uuid(&self, handle: TextureHandle) -> Option<Uuid> {
+        self.state.textures.read().unwrap().uuid(handle)
+    }
+
+    /// Checks if the colors sampled from texture should be decoded from sRGB space by
+    /// shaders, which is false only if it is in sRGB formats decoded by hardware.
+    pub fn requires_srgb_decoding(&self, handle: TextureHandle) -> bool {
+        let textures = self.state.textures.read().unwrap();
+        match textures.resource(handle) {
+            Some(params) => !params.format.is_srgb() || !self.state.capabilities.srgb_texture,
+            None => true,
+        }
+    }
+
     /// Update a contiguous subregion of an existing two-dimensional texture object.
+    #[inline]
     pub fn update_texture(
         &self,
         handle: TextureHandle,
         area: Aabb2<u32>,
         data: &[u8],
+    ) -> CrResult<()> {
+        self.update_texture_mip_area(handle, 0, area, data)
+    }
+
+    /// Update a contiguous subregion of the mipmap `level` of an existing two-dimensional
+    /// texture object, the `area` is measured in the pixels of that level.
+    pub fn update_texture_mip_area(
+        &self,
+        handle: TextureHandle,
+        level: usize,
+        area: Aabb2<u32>,
+        data: &[u8],
     ) -> CrResult<()> {
         let textures = self.state.textures.read().unwrap();
         if textures.contains(handle) {
+            if let Some(params) = textures.resource(handle) {
+                validation::mip_level(handle, level, params.mip_levels())?;
+            }
+
             let mut frame = self.state.frames.write();
             let ptr = frame.bufs.extend_from_slice(data);
-            let cmd = Command::UpdateTexture(handle, area, ptr);
+            let cmd = Command::UpdateTexture(handle, level, area, ptr);
             frame.cmds.push(cmd);
             Ok(())
         } else {
-            bail!("{:?} is invalid.", handle);
+            Err(self.invalid_handle(handle).into())
+        }
+    }
+
+    /// Generates the full mipmap chain of texture from its base level. The mipmaps are
+    /// sampled with the filter of texture once they are generated.
+    pub fn generate_texture_mipmaps(&self, handle: TextureHandle) -> CrResult<()> {
+        let textures = self.state.textures.read().unwrap();
+        if !textures.contains(handle) {
+            return Err(self.invalid_handle(handle).into());
+        }
+
+        if self.state.texture_streams.lock().unwrap().contains(handle) {
+            bail!("The mipmaps of {:?} are being streamed.", handle);
+        }
+
+        let cmd = Command::GenerateMipmaps(handle);
+        self.state.frames.write().cmds.push(cmd);
+        Ok(())
+    }
+
+    /// Uploads the mipmap `level` of an existing texture object, which replaces the old one.
+    /// The levels that were left empty when creating texture must be uploaded from smaller
+    /// to larger sizes.
+    pub fn update_texture_mip(
+        &self,
+        handle: TextureHandle,
+        level: usize,
+        data: &[u8],
+    ) -> CrResult<()> {
+        let textures = self.state.textures.read().unwrap();
+        if !textures.contains(handle) {
+            return Err(self.invalid_handle(handle).into());
+        }
+
+        if self.state.texture_streams.lock().unwrap().contains(handle) {
+            bail!("The mipmaps of {:?} are being streamed.", handle);
         }
+
+        let cmd = Command::UpdateTextureMip(Box::new((handle, level, data.into())));
+        self.state.frames.write().cmds.push(cmd);
+        Ok(())
+    }
+
+    /// Sets the parameters of streaming the mipmaps of immutable textures, `None` uploads
+    /// all the mipmaps when creating textures. It takes effect on the textures created later.
+    ///
+    /// The streaming is always disabled if `Capabilities::texture_base_level` is false.
+    pub fn set_texture_streaming(&self, params: Option<TextureStreamingParams>) {
+        if params.is_some() && !self.state.capabilities.texture_base_level {
+            warn!("The video device does not support streaming the mipmaps of textures.");
+            return;
+        }
+
+        self.state
+            .texture_streams
+            .lock()
+            .unwrap()
+            .set_params(params);
+    }
+
+    /// Gets the parameters of streaming the mipmaps of immutable textures.
+    #[inline]
+    pub fn texture_streaming(&self) -> Option<TextureStreamingParams> {
+        self.state.texture_streams.lock().unwrap().params()
+    }
+
+    /// Checks if there are mipmaps of the texture waiting to be streamed.
+    #[inline]
+    pub fn is_texture_streaming(&self, handle: TextureHandle) -> bool {
+        self.state.texture_streams.lock().unwrap().contains(handle)
     }
 
     /// Delete the texture object.
     pub fn delete_texture(&self, handle: TextureHandle) {
-        self.state.textures.write().unwrap().delete(handle);
+        self.state.tracker.delete(handle);
+
+        let mut textures = self.state.textures.write().unwrap();
+        textures.delete(handle);
+
+        // The textures failed to load are freed without any deletion in backends.
+        if !textures.contains(handle) {
+            let cmd = Command::SetTexturePending(handle, false);
+            self.state.frames.write().cmds.push(cmd);
+        }
+    }
+
+    /// Sets the texture that is bound in place of the textures which are still loading or
+    /// failed to load, so textures created with `create_texture_from` are usable at once.
+    /// A 1x1 magenta texture is used by default, and `None` leaves them unbound.
+    /// The default texture is deleted once it's replaced. The handles that are invalid or
+    /// deleted are never replaced.
+    pub fn set_texture_placeholder(&self, handle: Option<TextureHandle>) {
+        *self.state.texture_placeholder.write().unwrap() = handle;
+
+        let cmd = Command::SetTexturePlaceholder(handle);
+        self.state.frames.write().cmds.push(cmd);
+
+        let mut default = self.state.default_texture_placeholder.write().unwrap();
+        if *default != handle {
+            if let Some(v) = default.take() {
+                self.delete_texture(v);
+            }
+        }
+    }
+
+    /// Gets the texture that is bound in place of the textures which are not ready.
+    #[inline]
+    pub fn texture_placeholder(&self) -> Option<TextureHandle> {
+        *self.state.texture_placeholder.read().unwrap()
+    }
+}
+
+impl VideoSystem {
+    /// Sets the memory budget in bytes of meshes. The unreferenced meshes created from
+    /// files are kept in cache until the budget is exceeded.
+    pub fn set_mesh_budget(&self, budget: Option<usize>) {
+        self.state.meshes.write().unwrap().set_budget(budget);
+    }
+
+    /// Sets the memory budget in bytes of textures. The unreferenced textures created from
+    /// files are kept in cache until the budget is exceeded.
+    pub fn set_texture_budget(&self, budget: Option<usize>) {
+        self.state.textures.write().unwrap().set_budget(budget);
+    }
+
+    /// Sets the callback that is invoked before evicting meshes or textures from cache,
+    /// which could return false to veto the eviction.
+    pub fn set_eviction_callback(&self, callback: Option<EvictionCallback>) {
+        let mut meshes = self.state.meshes.write().unwrap();
+        meshes.set_eviction_callback(callback.clone());
+
+        let mut textures = self.state.textures.write().unwrap();
+        textures.set_eviction_callback(callback);
+    }
+
+    /// Returns the size in bytes of all the meshes alive.
+    #[inline]
+    pub fn mesh_memory(&self) -> usize {
+        self.state.meshes.read().unwrap().memory()
+    }
+
+    /// Returns the size in bytes of all the textures alive.
+    #[inline]
+    pub fn texture_memory(&self) -> usize {
+        self.state.textures.read().unwrap().memory()
+    }
+}
+
+impl VideoSystem {
+    /// Create cube texture object. A cube texture consists of six square 2D faces,
+    /// which could be sampled with a direction vector in shaders.
+    pub fn create_texture_cube<T>(
+        &self,
+        params: TextureCubeParams,
+        data: T,
+    ) -> CrResult<TextureCubeHandle>
+    where
+        T: Into<Option<TextureCubeData>>,
+    {
+        let mut textures = self.state.texture_cubes.write().unwrap();
+        let handle = textures.create((params, data.into()))?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Creates a cube texture object from file asynchronously.
+    pub fn create_texture_cube_from<T: AsRef<str>>(&self, url: T) -> CrResult<TextureCubeHandle> {
+        let mut textures = self.state.texture_cubes.write().unwrap();
+        let handle = textures.create_from(url)?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Creates a cube texture object from file asynchronously.
+    pub fn create_texture_cube_from_uuid(&self, uuid: Uuid) -> CrResult<TextureCubeHandle> {
+        let mut textures = self.state.texture_cubes.write().unwrap();
+        let handle = textures.create_from_uuid(uuid)?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Get the resource state of specified cube texture.
+    #[inline]
+    pub fn texture_cube_state(&self, handle: TextureCubeHandle) -> ResourceState {
+        self.state.texture_cubes.read().unwrap().state(handle)
+    }
+
+    /// Checks if the colors sampled from cube texture should be decoded from sRGB space
+    /// by shaders, which is false only if it is in sRGB formats decoded by hardware.
+    pub fn requires_srgb_decoding_cube(&self, handle: TextureCubeHandle) -> bool {
+        let textures = self.state.texture_cubes.read().unwrap();
+        match textures.resource(handle) {
+            Some(params) => !params.format.is_srgb() || !self.state.capabilities.srgb_texture,
+            None => true,
+        }
+    }
+
+    /// Delete the cube texture object.
+    pub fn delete_texture_cube(&self, handle: TextureCubeHandle) {
+        self.state.tracker.delete(handle);
+        self.state.texture_cubes.write().unwrap().delete(handle);
+    }
+}
+
+impl VideoSystem {
+    /// Create 3D texture object. A 3D texture is a stack of 2D images, which could be
+    /// sampled with three-dimensional texture coordinates in shaders.
+    pub fn create_texture_3d<T>(
+        &self,
+        params: Texture3DParams,
+        data: T,
+    ) -> CrResult<Texture3DHandle>
+    where
+        T: Into<Option<Texture3DData>>,
+    {
+        let mut textures = self.state.texture_3ds.write().unwrap();
+        let handle = textures.create((params, data.into()))?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Creates a 3D texture object from file asynchronously.
+    pub fn create_texture_3d_from<T: AsRef<str>>(&self, url: T) -> CrResult<Texture3DHandle> {
+        let mut textures = self.state.texture_3ds.write().unwrap();
+        let handle = textures.create_from(url)?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Creates a 3D texture object from file asynchronously.
+    pub fn create_texture_3d_from_uuid(&self, uuid: Uuid) -> CrResult<Texture3DHandle> {
+        let mut textures = self.state.texture_3ds.write().unwrap();
+        let handle = textures.create_from_uuid(uuid)?;
+        self.state.tracker.create(handle);
+        Ok(handle)
+    }
+
+    /// Get the resource state of specified 3D texture.
+    #[inline]
+    pub fn texture_3d_state(&self, handle: Texture3DHandle) -> ResourceState {
+        self.state.texture_3ds.read().unwrap().state(handle)
+    }
+
+    /// Update a contiguous subregion of an existing three-dimensional texture object.
+    pub fn update_texture_3d(
+        &self,
+        handle: Texture3DHandle,
+        area: Aabb3<u32>,
+        data: &[u8],
+    ) -> CrResult<()> {
+        let textures = self.state.texture_3ds.read().unwrap();
+        if textures.contains(handle) {
+            let mut frame = self.state.frames.write();
+            let ptr = frame.bufs.extend_from_slice(data);
+            let cmd = Command::UpdateTexture3D(handle, area, ptr);
+            frame.cmds.push(cmd);
+            Ok(())
+        } else {
+            Err(self.invalid_handle(handle).into())
+        }
+    }
+
+    /// Delete the 3D texture object.
+    pub fn delete_texture_3d(&self, handle: Texture3DHandle) {
+        self.state.tracker.delete(handle);
+        self.state.texture_3ds.write().unwrap().delete(handle);
     }
 }
 
@@ -353,8 +1641,17 @@ impl VideoSystem {
         &self,
         params: RenderTextureParams,
     ) -> Result<RenderTextureHandle> {
+        if !self.is_render_texture_format_supported(params.format) {
+            return Err(Error::TextureInvalid(format!(
+                "{:?} is not supported by this device.",
+                params.format
+            )));
+        }
+
         let handle = self.state.render_textures.write().unwrap().create(params);
 
+        self.state.tracker.create(handle);
+
         {
             let cmd = Command::CreateRenderTexture(Box::new((handle, params)));
             self.state.frames.write().cmds.push(cmd);
@@ -363,6 +1660,30 @@ impl VideoSystem {
         Ok(handle)
     }
 
+    /// Checks if the textures with `format` could be created on this device.
+    #[inline]
+    pub fn is_texture_format_supported(&self, format: TextureFormat) -> bool {
+        self.state.texture_formats.contains(&format)
+    }
+
+    /// Checks if the render textures with `format` could be created on this device.
+    #[inline]
+    pub fn is_render_texture_format_supported(&self, format: RenderTextureFormat) -> bool {
+        self.state.render_texture_formats.contains(&format)
+    }
+
+    /// Gets the statistics of video device during last frame.
+    #[inline]
+    pub fn frame_info(&self) -> GraphicsFrameInfo {
+        self.state.frame_info.lock().unwrap().clone()
+    }
+
+    /// Gets the features and limits of video device.
+    #[inline]
+    pub fn capabilities(&self) -> Capabilities {
+        self.state.capabilities.clone()
+    }
+
     /// Gets the `RenderTextureParams` if available.
     pub fn render_texture(&self, handle: RenderTextureHandle) -> Option<RenderTextureParams> {
         self.state
@@ -383,8 +1704,70 @@ impl VideoSystem {
         }
     }
 
+    /// Reads the pixels in `area` of render texture back asynchronously.
+    pub fn read_render_texture(
+        &self,
+        handle: RenderTextureHandle,
+        area: Aabb2<u32>,
+    ) -> Result<Request> {
+        let params = self
+            .render_texture(handle)
+            .ok_or_else(|| self.invalid_handle(handle))?;
+
+        if !params.format.is_color() {
+            return Err(Error::TextureInvalid(format!(
+                "Can NOT read back {:?}.",
+                params.format
+            )));
+        }
+
+        if area.min.x >= area.max.x
+            || area.min.y >= area.max.y
+            || area.max.x > params.dimensions.x
+            || area.max.y > params.dimensions.y
+        {
+            return Err(Error::OutOfBounds);
+        }
+
+        let latch = Request::latch();
+        let cmd = Command::ReadRenderTexture(Box::new((handle, area, latch.clone())));
+        self.state.frames.write().cmds.push(cmd);
+        Ok(Request::new(latch))
+    }
+
+    /// Reads the pixels of window back at the end of current frame.
+    #[inline]
+    pub fn read_framebuffer(&self) -> Request {
+        self.state.captures.lock().unwrap().add_readback()
+    }
+
+    /// Captures the window at the end of current frame, and saves it as PNG image.
+    #[inline]
+    pub fn capture_screenshot<T: AsRef<Path>>(&self, path: T) -> Request {
+        let path = path.as_ref().to_owned();
+        self.state.captures.lock().unwrap().add_screenshot(path)
+    }
+
+    /// Starts dumping every frame of the window into `dir` as PNG images.
+    #[inline]
+    pub fn start_frame_dump<T: AsRef<Path>>(&self, dir: T) {
+        let dir = dir.as_ref().to_owned();
+        self.state
+            .captures
+            .lock()
+            .unwrap()
+            .set_frame_dump(Some(dir));
+    }
+
+    /// Stops dumping frames.
+    #[inline]
+    pub fn stop_frame_dump(&self) {
+        self.state.captures.lock().unwrap().set_frame_dump(None);
+    }
+
     /// Delete the render texture object.
     pub fn delete_render_texture(&self, handle: RenderTextureHandle) {
+        self.state.tracker.delete(handle);
         if self
             .state
             .render_textures
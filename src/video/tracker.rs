@@ -0,0 +1,220 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use failure::Backtrace;
+
+use crate::utils::prelude::FastHashMap;
+
+/// The maximum number of deleted resources that are remembered.
+const MAX_DELETED_RECORDS: usize = 1024;
+
+struct Record {
+    // The resources created from the same file share the handle, which are deleted once
+    // all the references are dropped.
+    rc: usize,
+    label: Option<String>,
+    created: Backtrace,
+    deleted: Option<Backtrace>,
+}
+
+#[derive(Default)]
+struct Records {
+    alive: FastHashMap<String, Record>,
+    deleted: VecDeque<(String, Record)>,
+}
+
+/// Tracks the lifetimes of handles, which remembers where the resources are created and
+/// deleted, so the leaks and uses after delete could be reported with backtraces.
+///
+/// Notes that the backtraces are captured only if `RUST_BACKTRACE` is set.
+#[derive(Default)]
+pub struct HandleTracker {
+    enabled: AtomicBool,
+    records: Mutex<Records>,
+}
+
+impl HandleTracker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline]
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+
+        if !enabled {
+            let mut records = self.records.lock().unwrap();
+            records.alive.clear();
+            records.deleted.clear();
+        }
+    }
+
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn create<T: Debug>(&self, handle: T) {
+        if !self.enabled() {
+            return;
+        }
+
+        let key = format!("{:?}", handle);
+        let mut records = self.records.lock().unwrap();
+
+        if let Some(record) = records.alive.get_mut(&key) {
+            record.rc += 1;
+            return;
+        }
+
+        records.deleted.retain(|v| v.0 != key);
+
+        let record = Record {
+            rc: 1,
+            label: None,
+            created: Backtrace::new(),
+            deleted: None,
+        };
+
+        records.alive.insert(key, record);
+    }
+
+    pub fn delete<T: Debug>(&self, handle: T) {
+        if !self.enabled() {
+            return;
+        }
+
+        let key = format!("{:?}", handle);
+        let mut records = self.records.lock().unwrap();
+
+        if let Some(record) = records.alive.get_mut(&key) {
+            record.rc -= 1;
+            if record.rc > 0 {
+                return;
+            }
+        }
+
+        if let Some(mut record) = records.alive.remove(&key) {
+            record.deleted = Some(Backtrace::new());
+
+            if records.deleted.len() >= MAX_DELETED_RECORDS {
+                records.deleted.pop_front();
+            }
+
+            records.deleted.push_back((key, record));
+        } else if let Some(v) = records.deleted.iter().find(|v| v.0 == key) {
+            warn!("{} is deleted twice.\n{}", key, describe(&v.1));
+        }
+    }
+
+    /// Sets the label of handle, which is shown in the descriptions.
+    pub fn set_label<T: Debug>(&self, handle: T, label: &str) {
+        if !self.enabled() {
+            return;
+        }
+
+        let key = format!("{:?}", handle);
+        if let Some(v) = self.records.lock().unwrap().alive.get_mut(&key) {
+            v.label = Some(label.to_owned());
+        }
+    }
+
+    /// Checks if the handle has been deleted after being tracked.
+    pub fn is_deleted<T: Debug>(&self, handle: T) -> bool {
+        if !self.enabled() {
+            return false;
+        }
+
+        let key = format!("{:?}", handle);
+        let records = self.records.lock().unwrap();
+        records.deleted.iter().any(|v| v.0 == key)
+    }
+
+    /// Describes the handle that is invalid, with the backtraces of where it was created
+    /// and deleted if it's tracked.
+    pub fn describe<T: Debug>(&self, handle: T) -> String {
+        let key = format!("{:?}", handle);
+        if self.enabled() {
+            let records = self.records.lock().unwrap();
+            if let Some(v) = records.deleted.iter().rev().find(|v| v.0 == key) {
+                return format!(
+                    "{} (used after delete)\n{}",
+                    name(&key, &v.1),
+                    describe(&v.1)
+                );
+            }
+        }
+
+        key
+    }
+
+    /// Gets the descriptions of resources that are still alive.
+    pub fn alive(&self) -> Vec<String> {
+        let records = self.records.lock().unwrap();
+        let mut alive: Vec<_> = records
+            .alive
+            .iter()
+            .map(|(k, v)| format!("{}\n{}", name(k, v), describe(v)))
+            .collect();
+
+        alive.sort();
+        alive
+    }
+}
+
+fn name(key: &str, record: &Record) -> String {
+    match record.label {
+        Some(ref label) => format!("{} \"{}\"", key, label),
+        None => key.to_owned(),
+    }
+}
+
+fn describe(record: &Record) -> String {
+    let mut s = format!("created at:\n{}", backtrace(&record.created));
+    if let Some(ref v) = record.deleted {
+        s += &format!("\ndeleted at:\n{}", backtrace(v));
+    }
+
+    s
+}
+
+fn backtrace(v: &Backtrace) -> String {
+    let s = format!("{}", v);
+    if s.is_empty() {
+        "    <set RUST_BACKTRACE=1 to capture backtraces>".to_owned()
+    } else {
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lifetimes() {
+        let tracker = HandleTracker::new();
+        tracker.create(1);
+        assert!(tracker.alive().is_empty());
+
+        tracker.set_enabled(true);
+        tracker.create(1);
+        tracker.create(2);
+        tracker.set_label(2, "player");
+        assert_eq!(tracker.alive().len(), 2);
+        assert_eq!(tracker.describe(1), "1");
+
+        tracker.create(1);
+        tracker.delete(1);
+        assert!(!tracker.is_deleted(1));
+
+        tracker.delete(1);
+        assert!(tracker.is_deleted(1));
+        assert_eq!(tracker.alive().len(), 1);
+        assert!(tracker.alive()[0].starts_with("2 \"player\"\n"));
+        assert!(tracker.describe(1).starts_with("1 (used after delete)"));
+        assert_eq!(tracker.describe(3), "3");
+    }
+}
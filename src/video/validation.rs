@@ -0,0 +1,284 @@
+//! The checks of validation layer, which compare the submitted commands with the declared
+//! layouts and sizes of resources.
+
+use crate::math::prelude::{Aabb2, Aabb3, Vector2, Vector3};
+use crate::utils::prelude::HashValue;
+
+use super::assets::prelude::*;
+use super::errors::*;
+
+/// Checks if the uniform variables are declared by the shader with the same types.
+pub fn uniforms(
+    handle: ShaderHandle,
+    params: &ShaderParams,
+    vars: &[(HashValue<str>, UniformVariable)],
+) -> Result<()> {
+    // The layout would be populated with reflection by backend if it's left empty.
+    if params.uniforms.is_empty() {
+        return Ok(());
+    }
+
+    for &(field, variable) in vars {
+        match params.uniforms.variable_type(field) {
+            Some(tt) if tt == variable.variable_type() => {}
+            Some(tt) => invalid(format!(
+                "uniform {} of {:?} is declared as {:?}, but {:?} is bound.",
+                params.uniforms.variable_name(field).unwrap_or_default(),
+                handle,
+                tt,
+                variable.variable_type()
+            ))?,
+            None => {
+                let mut names: Vec<_> = params.uniforms.iter().map(|v| v.0.as_str()).collect();
+                names.sort();
+
+                invalid(format!(
+                    "uniform {:?} is not declared by {:?}, which declares [{}].",
+                    field,
+                    handle,
+                    names.join(", ")
+                ))?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks if the attributes required by the shader are provided by the vertex layout of
+/// mesh, and the per-instance ones by the layout of instance buffer.
+pub fn attributes(
+    handle: ShaderHandle,
+    params: &ShaderParams,
+    mesh: (MeshHandle, &VertexLayout),
+    instances: Option<(InstanceBufferHandle, &VertexLayout)>,
+) -> Result<()> {
+    for (attribute, size, required) in params.attributes.iter() {
+        let (source, layout) = if params.attributes.divisor(attribute) > 0 {
+            match instances {
+                Some((buffer, layout)) => (format!("{:?}", buffer), layout),
+                None => invalid(format!(
+                    "per-instance attribute {:?} of {:?} requires the instance data of draw.",
+                    attribute, handle
+                ))?,
+            }
+        } else {
+            (format!("{:?}", mesh.0), mesh.1)
+        };
+
+        match layout.element(attribute) {
+            Some(element) if element.size != size => invalid(format!(
+                "attribute {:?} of {:?} has {} components, but {} has {}.",
+                attribute, handle, size, source, element.size
+            ))?,
+            None if required => invalid(format!(
+                "attribute {:?} of {:?} is not provided by {}.",
+                attribute, handle, source
+            ))?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks if the instances to draw are inside the instance buffer.
+pub fn instances(
+    handle: InstanceBufferHandle,
+    params: &InstanceBufferParams,
+    num: u32,
+) -> Result<()> {
+    if num as usize > params.num {
+        invalid(format!(
+            "{} instances are drawn, but {:?} only has {}.",
+            num, handle, params.num
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks if the indices to draw are inside the index buffer of mesh.
+pub fn mesh_index(handle: MeshHandle, params: &MeshParams, index: MeshIndex) -> Result<()> {
+    match index {
+        MeshIndex::SubMesh(i) if i >= params.sub_mesh_offsets.len() => invalid(format!(
+            "sub-mesh {} of {:?} is out of bounds (0..{}).",
+            i,
+            handle,
+            params.sub_mesh_offsets.len()
+        )),
+        MeshIndex::Ptr(from, len) if out_of_bounds(from, len, params.num_idxes) => {
+            invalid(format!(
+                "{} indices from {} of {:?} is out of bounds (0..{}).",
+                len, from, handle, params.num_idxes
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks if the bytes in `offset..offset + len` are inside the buffer.
+pub fn range<T: ::std::fmt::Debug>(
+    handle: T,
+    offset: usize,
+    len: usize,
+    size: usize,
+) -> Result<()> {
+    if out_of_bounds(offset, len, size) {
+        invalid(format!(
+            "{} bytes from {} of {:?} is out of bounds (0..{}).",
+            len, offset, handle, size
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks if the area is inside the texture.
+pub fn area<T: ::std::fmt::Debug>(
+    handle: T,
+    area: Aabb2<u32>,
+    dimensions: Vector2<u32>,
+) -> Result<()> {
+    if area.max.x > dimensions.x || area.max.y > dimensions.y {
+        invalid(format!(
+            "area {:?} of {:?} is out of bounds {:?}.",
+            area, handle, dimensions
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks if the mipmap `level` is inside the full chain of `levels`.
+pub fn mip_level<T: ::std::fmt::Debug>(handle: T, level: usize, levels: usize) -> Result<()> {
+    if level >= levels {
+        invalid(format!(
+            "mipmap level {} of {:?} is out of bounds {}.",
+            level, handle, levels
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks if the volume is inside the 3D texture.
+pub fn volume<T: ::std::fmt::Debug>(
+    handle: T,
+    area: Aabb3<u32>,
+    dimensions: Vector3<u32>,
+) -> Result<()> {
+    if area.max.x > dimensions.x || area.max.y > dimensions.y || area.max.z > dimensions.z {
+        invalid(format!(
+            "area {:?} of {:?} is out of bounds {:?}.",
+            area, handle, dimensions
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks if the attachments of surface have proper formats and the same dimensions.
+pub fn attachments<F>(params: &SurfaceParams, render_texture: F) -> Result<()>
+where
+    F: Fn(RenderTextureHandle) -> Option<RenderTextureParams>,
+{
+    let mut dimensions = None;
+
+    let colors = params.colors.iter().filter_map(|v| *v).map(|v| (v, true));
+    let depth_stencil = params.depth_stencil.map(|v| (v, false));
+
+    for (handle, color) in colors.chain(depth_stencil) {
+        let rt = match render_texture(handle) {
+            Some(rt) => rt,
+            None => return Err(Error::HandleInvalid(format!("{:?}", handle))),
+        };
+
+        if rt.format.is_color() != color {
+            invalid(format!(
+                "{:?} with format {:?} can NOT be attached as {} attachment.",
+                handle,
+                rt.format,
+                if color { "color" } else { "depth-stencil" }
+            ))?;
+        }
+
+        match dimensions {
+            Some(v) if v != rt.dimensions => invalid(format!(
+                "{:?} has dimensions {:?}, but the other attachments have {:?}.",
+                handle, rt.dimensions, v
+            ))?,
+            _ => dimensions = Some(rt.dimensions),
+        }
+    }
+
+    Ok(())
+}
+
+// Checks if `offset..offset + len` is outside `0..size`, without overflowing.
+#[inline]
+fn out_of_bounds(offset: usize, len: usize, size: usize) -> bool {
+    offset.checked_add(len).map_or(true, |end| end > size)
+}
+
+#[inline]
+fn invalid<T>(message: String) -> Result<T> {
+    Err(Error::ValidationFailed(message))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::handle::HandleLike;
+
+    #[test]
+    fn draw() {
+        let mut params = ShaderParams::default();
+        params.attributes = AttributeLayout::build()
+            .with(Attribute::Position, 3)
+            .with_optional(Attribute::Color0, 4)
+            .finish();
+        params.uniforms = UniformVariableLayout::build()
+            .with("u_Color", UniformVariableType::Vector4f)
+            .finish();
+
+        let shader = ShaderHandle::new(1, 1);
+        let vars = [("u_Color".into(), UniformVariable::Vector4f([1.0; 4]))];
+        assert!(uniforms(shader, &params, &vars).is_ok());
+
+        let vars = [("u_Color".into(), UniformVariable::F32(1.0))];
+        let err = uniforms(shader, &params, &vars).unwrap_err();
+        assert!(format!("{}", err).contains("u_Color"));
+
+        let vars = [("u_Colour".into(), UniformVariable::F32(1.0))];
+        assert!(uniforms(shader, &params, &vars).is_err());
+
+        let mesh = MeshHandle::new(1, 1);
+        let layout = VertexLayout::build()
+            .with(Attribute::Position, VertexFormat::Float, 3, false)
+            .finish();
+        assert!(attributes(shader, &params, (mesh, &layout), None).is_ok());
+
+        let layout = VertexLayout::build()
+            .with(Attribute::Position, VertexFormat::Float, 2, false)
+            .finish();
+        assert!(attributes(shader, &params, (mesh, &layout), None).is_err());
+
+        let layout = VertexLayout::build()
+            .with(Attribute::Normal, VertexFormat::Float, 3, false)
+            .finish();
+        let err = attributes(shader, &params, (mesh, &layout), None).unwrap_err();
+        assert!(format!("{}", err).contains("Position"));
+
+        let mut params = MeshParams::default();
+        params.num_idxes = 6;
+        params.sub_mesh_offsets.push(0);
+        assert!(mesh_index(mesh, &params, MeshIndex::Ptr(3, 3)).is_ok());
+        assert!(mesh_index(mesh, &params, MeshIndex::Ptr(3, 4)).is_err());
+        assert!(mesh_index(mesh, &params, MeshIndex::SubMesh(1)).is_err());
+        assert!(range(mesh, 4, 4, 8).is_ok());
+        assert!(range(mesh, 4, 5, 8).is_err());
+        assert!(range(mesh, 4, usize::max_value(), 8).is_err());
+        assert!(mesh_index(mesh, &params, MeshIndex::Ptr(1, usize::max_value())).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use arboard;
+
+use crate::errors::*;
+
+/// The system clipboard, which falls back to a clipboard local to the application if
+/// the system one is not available, e.g. without a display server.
+pub struct Clipboard {
+    system: Option<arboard::Clipboard>,
+    local: Option<String>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        let system = match arboard::Clipboard::new() {
+            Ok(v) => Some(v),
+            Err(err) => {
+                warn!("The system clipboard is not available: {}", err);
+                None
+            }
+        };
+
+        Clipboard {
+            system,
+            local: None,
+        }
+    }
+
+    pub fn contents(&mut self) -> Option<String> {
+        match self.system {
+            Some(ref mut v) => v.get_text().ok(),
+            None => self.local.clone(),
+        }
+    }
+
+    pub fn set_contents(&mut self, contents: String) -> Result<()> {
+        match self.system {
+            Some(ref mut v) => v
+                .set_text(contents)
+                .map_err(|err| format_err!("Failed to set the contents of clipboard: {}", err)),
+            None => {
+                self.local = Some(contents);
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! The gamepads on desktop, which are not reported by glutin.
+//!
+//! On Linux, they are read from the joystick devices `/dev/input/js*`, whose buttons and
+//! axes are mapped as the Xbox controllers with the `xpad` driver. The devices are
+//! scanned every second to detect the hotplugs. The gamepads are not supported on the
+//! other desktop platforms yet.
+
+#[cfg(target_os = "linux")]
+pub use self::linux::Gamepads;
+
+#[cfg(not(target_os = "linux"))]
+pub use self::unsupported::Gamepads;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::fs::{File, OpenOptions};
+    use std::io::{ErrorKind, Read};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::time::{Duration, Instant};
+
+    use crate::input::events::InputEvent;
+    use crate::input::prelude::{GamepadAxis, GamepadButton};
+    use crate::utils::hash::FastHashMap;
+    use crate::window::prelude::Event;
+
+    const MAX_GAMEPADS: u8 = 16;
+    const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+    // The flag of `open` and the types of `js_event` in `linux/joystick.h`.
+    const O_NONBLOCK: i32 = 0o4000;
+    const JS_EVENT_BUTTON: u8 = 0x01;
+    const JS_EVENT_AXIS: u8 = 0x02;
+    const JS_EVENT_INIT: u8 = 0x80;
+
+    pub struct Gamepads {
+        devices: FastHashMap<u8, Device>,
+        last_scan: Option<Instant>,
+    }
+
+    struct Device {
+        file: File,
+        // The pressed states of triggers, which are reported as axes.
+        triggers: [bool; 2],
+        // The directions of d-pad, which is reported as two axes.
+        dpad: [i16; 2],
+    }
+
+    impl Gamepads {
+        pub fn new() -> Self {
+            Gamepads {
+                devices: FastHashMap::default(),
+                last_scan: None,
+            }
+        }
+
+        /// Polls the joystick devices, and sends the events of changes since last frame.
+        pub fn poll(&mut self, events: &mut Vec<Event>) {
+            let now = Instant::now();
+            if self.last_scan.map_or(true, |v| now - v >= SCAN_INTERVAL) {
+                self.last_scan = Some(now);
+                self.scan(events);
+            }
+
+            let mut disconnected = Vec::new();
+            for (&id, device) in &mut self.devices {
+                // The `js_event` is made of `u32` time, `i16` value, `u8` type and `u8` number.
+                let mut buf = [0; 8];
+                loop {
+                    match device.file.read(&mut buf) {
+                        Ok(8) => device.on_event(id, buf, events),
+                        Ok(_) => break,
+                        Err(ref err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                        Err(_) => {
+                            disconnected.push(id);
+                            break;
+                        }
+                    }
+                }
+            }
+
+            for id in disconnected {
+                self.devices.remove(&id);
+                events.push(Event::InputDevice(InputEvent::GamepadDisconnected { id }));
+            }
+        }
+
+        fn scan(&mut self, events: &mut Vec<Event>) {
+            for id in 0..MAX_GAMEPADS {
+                if self.devices.contains_key(&id) {
+                    continue;
+                }
+
+                let file = OpenOptions::new()
+                    .read(true)
+                    .custom_flags(O_NONBLOCK)
+                    .open(format!("/dev/input/js{}", id));
+
+                if let Ok(file) = file {
+                    let device = Device {
+                        file,
+                        triggers: [false; 2],
+                        dpad: [0; 2],
+                    };
+
+                    self.devices.insert(id, device);
+                    events.push(Event::InputDevice(InputEvent::GamepadConnected { id }));
+                }
+            }
+        }
+    }
+
+    impl Device {
+        fn on_event(&mut self, id: u8, buf: [u8; 8], events: &mut Vec<Event>) {
+            let value = i16::from_ne_bytes([buf[4], buf[5]]);
+            let number = buf[7];
+
+            // The initial states are sent as the synthetic events once the device is opened.
+            match buf[6] & !JS_EVENT_INIT {
+                JS_EVENT_BUTTON => Self::on_button(id, from_button(number), value != 0, events),
+                JS_EVENT_AXIS => self.on_axis(id, number, value, events),
+                _ => {}
+            }
+        }
+
+        fn on_button(id: u8, button: GamepadButton, pressed: bool, events: &mut Vec<Event>) {
+            let evt = if pressed {
+                InputEvent::GamepadPressed { id, button }
+            } else {
+                InputEvent::GamepadReleased { id, button }
+            };
+
+            events.push(Event::InputDevice(evt));
+        }
+
+        fn on_axis(&mut self, id: u8, number: u8, value: i16, events: &mut Vec<Event>) {
+            // The y axes of sticks and d-pad point down.
+            let normalized = (f32::from(value) / 32767.0).max(-1.0);
+            let (axis, value) = match number {
+                0 => (GamepadAxis::LeftStickX, normalized),
+                1 => (GamepadAxis::LeftStickY, -normalized),
+                3 => (GamepadAxis::RightStickX, normalized),
+                4 => (GamepadAxis::RightStickY, -normalized),
+                2 | 5 => {
+                    let (index, button) = if number == 2 {
+                        (0, GamepadButton::LeftTrigger)
+                    } else {
+                        (1, GamepadButton::RightTrigger)
+                    };
+
+                    // The triggers rest at the minimum value.
+                    let pressed = value > 0;
+                    if self.triggers[index] != pressed {
+                        self.triggers[index] = pressed;
+                        Self::on_button(id, button, pressed, events);
+                    }
+
+                    return;
+                }
+                6 | 7 => {
+                    let (index, buttons) = if number == 6 {
+                        (0, [GamepadButton::DPadLeft, GamepadButton::DPadRight])
+                    } else {
+                        (1, [GamepadButton::DPadUp, GamepadButton::DPadDown])
+                    };
+
+                    let direction = value.signum();
+                    let last = self.dpad[index];
+                    if last != direction {
+                        self.dpad[index] = direction;
+
+                        if last != 0 {
+                            Self::on_button(id, buttons[(last > 0) as usize], false, events);
+                        }
+
+                        if direction != 0 {
+                            Self::on_button(id, buttons[(direction > 0) as usize], true, events);
+                        }
+                    }
+
+                    return;
+                }
+                i => (GamepadAxis::Other(i), normalized),
+            };
+
+            events.push(Event::InputDevice(InputEvent::GamepadAxisChanged {
+                id,
+                axis,
+                value,
+            }));
+        }
+    }
+
+    /// Converts the number of button of the `xpad` driver.
+    fn from_button(number: u8) -> GamepadButton {
+        match number {
+            0 => GamepadButton::South,
+            1 => GamepadButton::East,
+            2 => GamepadButton::West,
+            3 => GamepadButton::North,
+            4 => GamepadButton::LeftBumper,
+            5 => GamepadButton::RightBumper,
+            6 => GamepadButton::Select,
+            7 => GamepadButton::Start,
+            8 => GamepadButton::Mode,
+            9 => GamepadButton::LeftStick,
+            10 => GamepadButton::RightStick,
+            i => GamepadButton::Other(i),
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod unsupported {
+    use crate::window::prelude::Event;
+
+    pub struct Gamepads {}
+
+    impl Gamepads {
+        pub fn new() -> Self {
+            Gamepads {}
+        }
+
+        pub fn poll(&mut self, _: &mut Vec<Event>) {}
+    }
+}
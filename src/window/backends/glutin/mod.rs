@@ -1,3 +1,7 @@
+mod clipboard;
+mod gamepads;
+mod offscreen;
+mod swap_control;
 mod types;
 mod visitor;
 
@@ -5,8 +9,14 @@ use super::super::WindowParams;
 use super::Visitor;
 
 use crate::errors::*;
+use crate::video::VSync;
 
-pub fn new(params: WindowParams) -> Result<Box<Visitor>> {
-    let visitor = self::visitor::GlutinVisitor::from(params)?;
+pub fn new(params: WindowParams, vsync: VSync) -> Result<Box<Visitor>> {
+    let visitor = self::visitor::GlutinVisitor::from(params, vsync)?;
+    Ok(Box::new(visitor))
+}
+
+pub fn new_offscreen(params: WindowParams) -> Result<Box<dyn Visitor>> {
+    let visitor = self::offscreen::OffscreenVisitor::from(params)?;
     Ok(Box::new(visitor))
 }
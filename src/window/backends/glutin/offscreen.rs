@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use gl;
+use glutin;
+use glutin::GlContext;
+
+use crate::errors::*;
+use crate::math::prelude::Vector2;
+
+use super::super::super::events::Event;
+use super::super::super::{
+    CursorIcon, CursorMode, Monitor, WindowHandle, WindowIcon, WindowMode, WindowParams,
+};
+use super::super::Visitor;
+use super::clipboard::Clipboard;
+
+/// The context without window, which renders into a framebuffer object of fixed size
+/// instead.
+pub struct OffscreenVisitor {
+    context: glutin::Context,
+    // The events loop is required by the creation of context on some platforms.
+    _events_loop: glutin::EventsLoop,
+    dimensions: Vector2<u32>,
+    clipboard: Clipboard,
+}
+
+impl OffscreenVisitor {
+    pub fn from(params: WindowParams) -> Result<Self> {
+        let events_loop = glutin::EventsLoop::new();
+
+        // The context which is not shared with windows is created with OSMesa on Linux,
+        // whose software rasterizer might not support the core profile. So it falls back
+        // to the default profile of the latest version, and the features are checked with
+        // the capabilities of context.
+        let core = glutin::ContextBuilder::new()
+            .with_gl_profile(glutin::GlProfile::Core)
+            .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 3)));
+
+        let context = glutin::Context::new(&events_loop, core, false)
+            .or_else(|_| {
+                let builder = glutin::ContextBuilder::new().with_gl(glutin::GlRequest::Latest);
+                glutin::Context::new(&events_loop, builder, false)
+            })
+            .map_err(|err| format_err!("Failed to create offscreen context: {}", err))?;
+
+        unsafe {
+            context.make_current()?;
+            gl::load_with(|symbol| context.get_proc_address(symbol) as *const _);
+        }
+
+        Ok(OffscreenVisitor {
+            context,
+            _events_loop: events_loop,
+            dimensions: params.size,
+            clipboard: Clipboard::new(),
+        })
+    }
+}
+
+impl Visitor for OffscreenVisitor {
+    #[inline]
+    fn show(&self) {}
+
+    #[inline]
+    fn hide(&self) {}
+
+    #[inline]
+    fn position(&self) -> Vector2<i32> {
+        (0, 0).into()
+    }
+
+    #[inline]
+    fn dimensions(&self) -> Vector2<u32> {
+        self.dimensions
+    }
+
+    #[inline]
+    fn device_pixel_ratio(&self) -> f32 {
+        1.0
+    }
+
+    #[inline]
+    fn resize(&self, _: Vector2<u32>) {}
+
+    #[inline]
+    fn poll_events(&mut self, _: &mut Vec<Event>) {}
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        self.context.is_current()
+    }
+
+    #[inline]
+    fn make_current(&self) -> Result<()> {
+        unsafe {
+            self.context.make_current()?;
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn swap_buffers(&self) -> Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn set_size(&self, _: Vector2<u32>) {}
+
+    #[inline]
+    fn mode(&self) -> WindowMode {
+        WindowMode::Windowed
+    }
+
+    fn set_mode(&mut self, mode: WindowMode) -> Result<()> {
+        if mode != WindowMode::Windowed {
+            bail!("{:?} is not supported by offscreen context.", mode);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn monitors(&self) -> Vec<Monitor> {
+        Vec::new()
+    }
+
+    #[inline]
+    fn current_monitor(&self) -> Option<usize> {
+        None
+    }
+
+    fn rumble_gamepad(&self, _: u8, _: f32, _: Duration) -> Result<()> {
+        bail!("Gamepads are not supported by offscreen context.");
+    }
+
+    #[inline]
+    fn set_icon(&self, _: Option<WindowIcon>) -> Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn set_cursor_icon(&self, _: CursorIcon) {}
+
+    #[inline]
+    fn cursor_mode(&self) -> CursorMode {
+        CursorMode::Normal
+    }
+
+    fn set_cursor_mode(&mut self, mode: CursorMode) -> Result<()> {
+        if mode != CursorMode::Normal {
+            bail!("{:?} is not supported by offscreen context.", mode);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn clipboard(&mut self) -> Option<String> {
+        self.clipboard.contents()
+    }
+
+    #[inline]
+    fn set_clipboard(&mut self, contents: String) -> Result<()> {
+        self.clipboard.set_contents(contents)
+    }
+
+    fn create_window(&mut self, _: WindowHandle, _: WindowParams) -> Result<()> {
+        bail!("Secondary windows are not supported by offscreen context.");
+    }
+
+    fn delete_window(&mut self, _: WindowHandle) {}
+
+    #[inline]
+    fn window_dimensions(&self, _: WindowHandle) -> Option<Vector2<u32>> {
+        None
+    }
+
+    #[inline]
+    fn is_window_closed(&self, _: WindowHandle) -> bool {
+        true
+    }
+
+    fn make_window_current(&self, handle: WindowHandle) -> Result<()> {
+        bail!("{:?} is invalid.", handle);
+    }
+
+    fn swap_window_buffers(&self, handle: WindowHandle) -> Result<()> {
+        bail!("{:?} is invalid.", handle);
+    }
+}
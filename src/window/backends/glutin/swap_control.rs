@@ -0,0 +1,134 @@
+//! The adaptive vsync, which is not exposed by glutin.
+//!
+//! It's enabled by setting the swap interval of current context to -1 with the
+//! `GLX_EXT_swap_control_tear` extension on X11, or the `WGL_EXT_swap_control_tear`
+//! extension on Windows. The other platforms do not support it yet.
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd"
+))]
+pub use self::glx::enable_adaptive_vsync;
+
+#[cfg(target_os = "windows")]
+pub use self::wgl::enable_adaptive_vsync;
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "windows"
+)))]
+pub use self::unsupported::enable_adaptive_vsync;
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+// Checks whether the space-separated extensions contain `name`.
+#[allow(dead_code)]
+unsafe fn has_extension(extensions: *const c_char, name: &str) -> bool {
+    !extensions.is_null()
+        && CStr::from_ptr(extensions)
+            .to_string_lossy()
+            .split(' ')
+            .any(|v| v == name)
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd"
+))]
+mod glx {
+    use std::os::raw::{c_char, c_int, c_ulong, c_void};
+
+    use glutin;
+    use glutin::os::unix::{RawHandle, WindowExt};
+    use glutin::os::GlContextExt;
+    use glutin::GlContext;
+
+    type QueryExtensionsString = unsafe extern "C" fn(*mut c_void, c_int) -> *const c_char;
+    type SwapIntervalEXT = unsafe extern "C" fn(*mut c_void, c_ulong, c_int);
+
+    /// Enables the adaptive vsync of the current context of `window`, returns false if
+    /// it's not supported.
+    pub unsafe fn enable_adaptive_vsync(window: &glutin::GlWindow) -> bool {
+        // The contexts on Wayland are created with EGL, which does not support it.
+        if let RawHandle::Egl(_) = window.context().raw_handle() {
+            return false;
+        }
+
+        let (display, screen, drawable) = match (
+            window.get_xlib_display(),
+            window.get_xlib_screen_id(),
+            window.get_xlib_window(),
+        ) {
+            (Some(display), Some(screen), Some(drawable)) => (display, screen, drawable),
+            _ => return false,
+        };
+
+        let query = window.get_proc_address("glXQueryExtensionsString");
+        let swap = window.get_proc_address("glXSwapIntervalEXT");
+        if query.is_null() || swap.is_null() {
+            return false;
+        }
+
+        // A negative interval raises X errors without the extension.
+        let query: QueryExtensionsString = std::mem::transmute(query);
+        if !super::has_extension(query(display, screen), "GLX_EXT_swap_control_tear") {
+            return false;
+        }
+
+        let swap: SwapIntervalEXT = std::mem::transmute(swap);
+        swap(display, drawable, -1);
+        true
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod wgl {
+    use std::os::raw::{c_char, c_int};
+
+    use glutin;
+    use glutin::GlContext;
+
+    type GetExtensionsStringEXT = unsafe extern "system" fn() -> *const c_char;
+    type SwapIntervalEXT = unsafe extern "system" fn(c_int) -> c_int;
+
+    /// Enables the adaptive vsync of the current context of `window`, returns false if
+    /// it's not supported.
+    pub unsafe fn enable_adaptive_vsync(window: &glutin::GlWindow) -> bool {
+        let query = window.get_proc_address("wglGetExtensionsStringEXT");
+        let swap = window.get_proc_address("wglSwapIntervalEXT");
+        if query.is_null() || swap.is_null() {
+            return false;
+        }
+
+        let query: GetExtensionsStringEXT = std::mem::transmute(query);
+        if !super::has_extension(query(), "WGL_EXT_swap_control_tear") {
+            return false;
+        }
+
+        let swap: SwapIntervalEXT = std::mem::transmute(swap);
+        swap(-1) != 0
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "windows"
+)))]
+mod unsupported {
+    use glutin;
+
+    pub unsafe fn enable_adaptive_vsync(_: &glutin::GlWindow) -> bool {
+        false
+    }
+}
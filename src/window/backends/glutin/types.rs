@@ -1,6 +1,7 @@
 use glutin;
 
 use super::super::super::events::{Event, WindowEvent};
+use super::super::super::CursorIcon;
 
 use crate::input::events::InputEvent;
 use crate::input::keyboard::Key;
@@ -39,6 +40,10 @@ fn from_window_event(source: &glutin::WindowEvent, dimensions: Vector2<u32>) ->
             Event::Window(WindowEvent::Resized(width as u32, height as u32)),
         ),
 
+        glutin::WindowEvent::HiDpiFactorChanged(v) => {
+            Some(Event::Window(WindowEvent::DpiChanged(v as f32)))
+        }
+
         glutin::WindowEvent::CursorMoved { position, .. } => {
             Some(Event::InputDevice(InputEvent::MouseMoved {
                 position: (position.x as f32, dimensions.y as f32 - position.y as f32),
@@ -105,7 +110,12 @@ fn from_window_event(source: &glutin::WindowEvent, dimensions: Vector2<u32>) ->
         glutin::WindowEvent::Touch(touch) => Some(Event::InputDevice(InputEvent::Touch {
             id: touch.id as u8,
             state: from_touch_state(touch.phase),
-            position: (touch.location.x as f32, touch.location.y as f32).into(),
+            position: (
+                touch.location.x as f32,
+                dimensions.y as f32 - touch.location.y as f32,
+            )
+                .into(),
+            pressure: 1.0,
         })),
 
         _ => None,
@@ -132,6 +142,24 @@ impl From<glutin::MouseButton> for MouseButton {
     }
 }
 
+pub fn to_mouse_cursor(icon: CursorIcon) -> glutin::MouseCursor {
+    match icon {
+        CursorIcon::Default => glutin::MouseCursor::Default,
+        CursorIcon::Crosshair => glutin::MouseCursor::Crosshair,
+        CursorIcon::Hand => glutin::MouseCursor::Hand,
+        CursorIcon::Arrow => glutin::MouseCursor::Arrow,
+        CursorIcon::Move => glutin::MouseCursor::Move,
+        CursorIcon::Text => glutin::MouseCursor::Text,
+        CursorIcon::Wait => glutin::MouseCursor::Wait,
+        CursorIcon::Help => glutin::MouseCursor::Help,
+        CursorIcon::NotAllowed => glutin::MouseCursor::NotAllowed,
+        CursorIcon::Grab => glutin::MouseCursor::Grab,
+        CursorIcon::Grabbing => glutin::MouseCursor::Grabbing,
+        CursorIcon::EwResize => glutin::MouseCursor::EwResize,
+        CursorIcon::NsResize => glutin::MouseCursor::NsResize,
+    }
+}
+
 fn from_virtual_key_code(key: glutin::VirtualKeyCode) -> Option<Key> {
     match key {
         glutin::VirtualKeyCode::Key1 => Some(Key::Key1),
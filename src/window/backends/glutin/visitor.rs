@@ -1,56 +1,125 @@
+use std::cell::Cell;
+use std::time::Duration;
+
 use gl;
 use glutin;
 use glutin::GlContext;
 
 use crate::errors::*;
 use crate::math::prelude::Vector2;
+use crate::utils::hash::FastHashMap;
+use crate::video::VSync;
+
+use crate::input::events::InputEvent;
 
-use super::super::super::events::Event;
-use super::super::super::WindowParams;
+use super::super::super::events::{Event, WindowEvent};
+use super::super::super::{
+    CursorIcon, CursorMode, Monitor, WindowHandle, WindowIcon, WindowMode, WindowParams,
+};
 use super::super::Visitor;
+use super::clipboard::Clipboard;
+use super::gamepads::Gamepads;
+use super::swap_control;
 use super::types;
 
+// OpenGL 3.3 and OpenGL ES 3.0 support instancing, multiple render targets, uniform
+// buffers and sRGB natively. Notes that glutin creates OpenGL ES 2.0 contexts for the
+// `GlRequest::Latest`.
+const GL_REQUEST: glutin::GlRequest = glutin::GlRequest::GlThenGles {
+    opengl_version: (3, 3),
+    opengles_version: (3, 0),
+};
+
 pub struct GlutinVisitor {
     window: glutin::GlWindow,
+    // The request of main context, which is used by the secondary windows too.
+    gl: glutin::GlRequest,
+    // The parameters of main window, which is recreated once its context is lost.
+    params: WindowParams,
+    vsync: VSync,
+    // Whether the context has been lost, which is reported by `swap_buffers`.
+    lost: Cell<bool>,
+    restoring: bool,
     events_loop: glutin::EventsLoop,
+    windows: FastHashMap<WindowHandle, GlutinWindow>,
+    mode: WindowMode,
+    cursor_mode: CursorMode,
+    // The position of mouse, which is moved with the raw motion while it's locked.
+    cursor_position: Vector2<f32>,
+    clipboard: Clipboard,
+    gamepads: Gamepads,
 }
 
-impl GlutinVisitor {
-    pub fn from(params: WindowParams) -> Result<Self> {
-        let builder = glutin::WindowBuilder::new()
-            .with_title(params.title)
-            .with_dimensions(glutin::dpi::LogicalSize::new(
-                f64::from(params.size.x),
-                f64::from(params.size.y),
-            ))
-            .with_multitouch();
-
-        let context = glutin::ContextBuilder::new()
-            .with_multisampling(params.multisample as u16)
-            .with_gl_profile(glutin::GlProfile::Core)
-            .with_gl(glutin::GlRequest::Latest)
-            .with_vsync(params.vsync);
+struct GlutinWindow {
+    window: glutin::GlWindow,
+    closed: bool,
+}
 
+impl GlutinVisitor {
+    pub fn from(params: WindowParams, vsync: VSync) -> Result<Self> {
         let events_loop = glutin::EventsLoop::new();
-        let window = glutin::GlWindow::new(builder, context, &events_loop).unwrap();
+        let (window, gl) = main_window(&events_loop, &params, vsync)?;
+
         let mut visitor = GlutinVisitor {
             window,
+            gl,
+            mode: params.mode,
+            params,
+            vsync,
+            lost: Cell::new(false),
+            restoring: false,
             events_loop,
+            windows: FastHashMap::default(),
+            cursor_mode: CursorMode::Normal,
+            cursor_position: Vector2::new(0.0, 0.0),
+            clipboard: Clipboard::new(),
+            gamepads: Gamepads::new(),
         };
 
-        let size = visitor.dimensions();
-        let dpr = visitor.device_pixel_ratio();
-        let dims = Vector2::new((size.x as f32 * dpr) as u32, (size.y as f32 * dpr) as u32);
-
         visitor.events_loop.poll_events(|_| {});
-        visitor.resize(dims);
+        visitor.init()?;
+        Ok(visitor)
+    }
+
+    // Makes the context of main window current, and loads the OpenGL functions from it.
+    fn init(&mut self) -> Result<()> {
+        let size = self.dimensions();
+        let dpr = self.device_pixel_ratio();
+        let dims = Vector2::new((size.x as f32 * dpr) as u32, (size.y as f32 * dpr) as u32);
+        self.resize(dims);
 
         unsafe {
-            visitor.window.make_current()?;
-            gl::load_with(|symbol| visitor.window.get_proc_address(symbol) as *const _);
+            self.window.make_current()?;
+            gl::load_with(|symbol| self.window.get_proc_address(symbol) as *const _);
+
+            if self.vsync == VSync::Adaptive && !swap_control::enable_adaptive_vsync(&self.window) {
+                info!("Adaptive vsync is not supported, falls back to vsync on.");
+            }
         }
 
-        Ok(visitor)
+        Ok(())
+    }
+
+    // Recreates the main window with a new context, after the lost one is dropped.
+    fn restore(&mut self) -> Result<()> {
+        let mut params = self.params.clone();
+        params.mode = self.mode;
+
+        let (window, gl) = main_window(&self.events_loop, &params, self.vsync)?;
+        self.window = window;
+        self.gl = gl;
+        self.init()?;
+
+        let mode = self.cursor_mode;
+        self.set_cursor_mode(mode)?;
+
+        // The secondary windows share the objects of lost context, they are closed too.
+        for v in self.windows.values_mut() {
+            v.closed = true;
+        }
+
+        self.lost.set(false);
+        Ok(())
     }
 }
 
@@ -90,12 +159,77 @@ impl Visitor for GlutinVisitor {
 
     #[inline]
     fn poll_events(&mut self, events: &mut Vec<Event>) {
+        // The EGL contexts could be lost after power management events on Android.
+        if self.lost.get() {
+            if !self.restoring {
+                self.restoring = true;
+                events.push(Event::Window(WindowEvent::ContextLost));
+            }
+
+            match self.restore() {
+                Ok(_) => {
+                    self.restoring = false;
+                    events.push(Event::Window(WindowEvent::ContextRestored));
+                }
+                Err(err) => warn!("Failed to restore the lost context: {}", err),
+            }
+        }
+
         let dims = self.dimensions();
+        let id = self.window.id();
+        let windows = &mut self.windows;
+        let locked = self.cursor_mode == CursorMode::Locked;
+        let cursor = &mut self.cursor_position;
+
         self.events_loop.poll_events(|v| {
-            if let Some(e) = types::from_event(v, dims) {
-                events.push(e);
+            if let glutin::Event::DeviceEvent {
+                event: glutin::DeviceEvent::MouseMotion { delta },
+                ..
+            } = v
+            {
+                if locked {
+                    cursor.x += delta.0 as f32;
+                    cursor.y -= delta.1 as f32;
+
+                    let position = (cursor.x, cursor.y);
+                    events.push(Event::InputDevice(InputEvent::MouseMoved { position }));
+                }
+
+                return;
+            }
+
+            // Only the close requests of secondary windows are handled, which hide them.
+            if let glutin::Event::WindowEvent {
+                window_id,
+                ref event,
+            } = v
+            {
+                if window_id != id {
+                    if let glutin::WindowEvent::CloseRequested = *event {
+                        for w in windows.values_mut().filter(|w| w.window.id() == window_id) {
+                            w.window.hide();
+                            w.closed = true;
+                        }
+                    }
+
+                    return;
+                }
+            }
+
+            match types::from_event(v, dims) {
+                Some(Event::InputDevice(InputEvent::MouseMoved { position })) => {
+                    // The cursor stays still while it's locked.
+                    if !locked {
+                        *cursor = position.into();
+                        events.push(Event::InputDevice(InputEvent::MouseMoved { position }));
+                    }
+                }
+                Some(e) => events.push(e),
+                None => {}
             }
         });
+
+        self.gamepads.poll(events);
     }
 
     #[inline]
@@ -111,9 +245,264 @@ impl Visitor for GlutinVisitor {
         }
     }
 
-    #[inline]
     fn swap_buffers(&self) -> Result<()> {
-        self.window.swap_buffers()?;
+        if self.lost.get() {
+            return Ok(());
+        }
+
+        match self.window.swap_buffers() {
+            Err(glutin::ContextError::ContextLost) => {
+                self.lost.set(true);
+                Ok(())
+            }
+            v => Ok(v?),
+        }
+    }
+
+    #[inline]
+    fn set_size(&self, size: Vector2<u32>) {
+        let size = glutin::dpi::LogicalSize::new(f64::from(size.x), f64::from(size.y));
+        self.window.set_inner_size(size);
+    }
+
+    #[inline]
+    fn mode(&self) -> WindowMode {
+        self.mode
+    }
+
+    fn set_mode(&mut self, mode: WindowMode) -> Result<()> {
+        match mode {
+            WindowMode::Windowed | WindowMode::Borderless => {
+                self.window.set_fullscreen(None);
+                self.window.set_decorations(mode == WindowMode::Windowed);
+            }
+            WindowMode::Fullscreen(index) => {
+                let monitor = monitor(&self.events_loop, index)?;
+                self.window.set_fullscreen(Some(monitor));
+            }
+        }
+
+        self.mode = mode;
+        Ok(())
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        monitors(&self.events_loop)
+            .iter()
+            .map(from_monitor)
+            .collect()
+    }
+
+    fn current_monitor(&self) -> Option<usize> {
+        let current = from_monitor(&self.window.get_current_monitor());
+        self.monitors()
+            .iter()
+            .position(|v| v.name == current.name && v.position == current.position)
+    }
+
+    fn rumble_gamepad(&self, _: u8, _: f32, _: Duration) -> Result<()> {
+        bail!("The rumble of gamepads is not supported on desktop.");
+    }
+
+    fn set_icon(&self, icon: Option<WindowIcon>) -> Result<()> {
+        let icon = match icon {
+            Some(v) => {
+                let icon = glutin::Icon::from_rgba(v.rgba, v.dimensions.x, v.dimensions.y)
+                    .map_err(|err| format_err!("Failed to create icon: {:?}", err))?;
+                Some(icon)
+            }
+            None => None,
+        };
+
+        self.window.set_window_icon(icon);
+        Ok(())
+    }
+
+    #[inline]
+    fn set_cursor_icon(&self, icon: CursorIcon) {
+        self.window.set_cursor(types::to_mouse_cursor(icon));
+    }
+
+    #[inline]
+    fn cursor_mode(&self) -> CursorMode {
+        self.cursor_mode
+    }
+
+    fn set_cursor_mode(&mut self, mode: CursorMode) -> Result<()> {
+        self.window
+            .grab_cursor(mode == CursorMode::Locked)
+            .map_err(|err| format_err!("Failed to lock cursor: {}", err))?;
+
+        self.window.hide_cursor(mode != CursorMode::Normal);
+        self.cursor_mode = mode;
         Ok(())
     }
+
+    #[inline]
+    fn clipboard(&mut self) -> Option<String> {
+        self.clipboard.contents()
+    }
+
+    #[inline]
+    fn set_clipboard(&mut self, contents: String) -> Result<()> {
+        self.clipboard.set_contents(contents)
+    }
+
+    fn create_window(&mut self, handle: WindowHandle, params: WindowParams) -> Result<()> {
+        let builder = builder(&self.events_loop, &params)?;
+
+        // The objects like textures and buffers are shared with the main context, so it's
+        // created with the same API, version and profile. And the framebuffer is not
+        // multisampled, since it is blitted from the one in main context. And it's not
+        // vsynced, since the frames are already paced by the main window.
+        let context = context(self.gl).with_shared_lists(self.window.context());
+
+        let window = glutin::GlWindow::new(builder, context, &self.events_loop)
+            .map_err(|err| format_err!("Failed to create window: {}", err))?;
+
+        unsafe {
+            self.window.make_current()?;
+        }
+
+        let window = GlutinWindow {
+            window,
+            closed: false,
+        };
+
+        self.windows.insert(handle, window);
+        Ok(())
+    }
+
+    fn delete_window(&mut self, handle: WindowHandle) {
+        self.windows.remove(&handle);
+    }
+
+    fn window_dimensions(&self, handle: WindowHandle) -> Option<Vector2<u32>> {
+        let window = &self.windows.get(&handle)?.window;
+        let size = window
+            .get_inner_size()?
+            .to_physical(window.get_hidpi_factor());
+
+        Some(Vector2::new(size.width as u32, size.height as u32))
+    }
+
+    #[inline]
+    fn is_window_closed(&self, handle: WindowHandle) -> bool {
+        self.windows.get(&handle).map(|v| v.closed).unwrap_or(true)
+    }
+
+    fn make_window_current(&self, handle: WindowHandle) -> Result<()> {
+        let window = self
+            .windows
+            .get(&handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        unsafe {
+            window.window.make_current()?;
+            Ok(())
+        }
+    }
+
+    fn swap_window_buffers(&self, handle: WindowHandle) -> Result<()> {
+        let window = self
+            .windows
+            .get(&handle)
+            .ok_or_else(|| format_err!("{:?} is invalid.", handle))?;
+
+        window.window.swap_buffers()?;
+        Ok(())
+    }
+}
+
+fn main_window(
+    events_loop: &glutin::EventsLoop,
+    params: &WindowParams,
+    vsync: VSync,
+) -> Result<(glutin::GlWindow, glutin::GlRequest)> {
+    let builder = builder(events_loop, params)?.with_multitouch();
+
+    let main_context = |gl| {
+        context(gl)
+            .with_multisampling(params.multisample as u16)
+            .with_vsync(vsync != VSync::Off)
+            .with_srgb(params.srgb)
+    };
+
+    // Falls back to the latest version if the devices are older, and the features are
+    // emulated or disabled according to the capabilities of context.
+    match glutin::GlWindow::new(builder.clone(), main_context(GL_REQUEST), events_loop) {
+        Ok(window) => Ok((window, GL_REQUEST)),
+        Err(_) => {
+            let gl = glutin::GlRequest::Latest;
+            let window = glutin::GlWindow::new(builder, main_context(gl), events_loop)
+                .map_err(|err| format_err!("Failed to create window: {}", err))?;
+            Ok((window, gl))
+        }
+    }
+}
+
+// The attributes of context which are shared by the main and secondary windows.
+fn context<'a>(gl: glutin::GlRequest) -> glutin::ContextBuilder<'a> {
+    glutin::ContextBuilder::new()
+        .with_gl_profile(glutin::GlProfile::Core)
+        .with_gl(gl)
+}
+
+fn builder(
+    events_loop: &glutin::EventsLoop,
+    params: &WindowParams,
+) -> Result<glutin::WindowBuilder> {
+    let mut builder = glutin::WindowBuilder::new()
+        .with_title(params.title.clone())
+        .with_dimensions(glutin::dpi::LogicalSize::new(
+            f64::from(params.size.x),
+            f64::from(params.size.y),
+        ));
+
+    match params.mode {
+        WindowMode::Windowed => {}
+        WindowMode::Borderless => builder = builder.with_decorations(false),
+        WindowMode::Fullscreen(index) => {
+            builder = builder.with_fullscreen(Some(monitor(events_loop, index)?))
+        }
+    }
+
+    Ok(builder)
+}
+
+/// Gets the monitor at `index`, the primary monitor is always the first one.
+fn monitor(events_loop: &glutin::EventsLoop, index: usize) -> Result<glutin::MonitorId> {
+    let monitors = monitors(events_loop);
+    if index >= monitors.len() {
+        bail!("The monitor {} is invalid.", index);
+    }
+
+    Ok(monitors.into_iter().nth(index).unwrap())
+}
+
+fn monitors(events_loop: &glutin::EventsLoop) -> Vec<glutin::MonitorId> {
+    let primary = from_monitor(&events_loop.get_primary_monitor());
+    let mut monitors: Vec<_> = events_loop.get_available_monitors().collect();
+
+    if let Some(i) = monitors.iter().position(|v| {
+        let v = from_monitor(v);
+        v.name == primary.name && v.position == primary.position
+    }) {
+        let v = monitors.remove(i);
+        monitors.insert(0, v);
+    }
+
+    monitors
+}
+
+fn from_monitor(v: &glutin::MonitorId) -> Monitor {
+    let position = v.get_position();
+    let dimensions = v.get_dimensions();
+
+    Monitor {
+        name: v.get_name().unwrap_or_default(),
+        position: Vector2::new(position.x as i32, position.y as i32),
+        dimensions: Vector2::new(dimensions.width as u32, dimensions.height as u32),
+        device_pixel_ratio: v.get_hidpi_factor() as f32,
+    }
 }
@@ -1,10 +1,31 @@
+use std::time::Duration;
+
 use crate::errors::*;
 use crate::math::prelude::Vector2;
+use crate::utils::hash::FastHashMap;
 
 use super::super::events::Event;
+use super::super::{
+    CursorIcon, CursorMode, Monitor, WindowHandle, WindowIcon, WindowMode, WindowParams,
+};
 use super::Visitor;
 
-pub struct HeadlessVisitor {}
+pub struct HeadlessVisitor {
+    // The sizes of secondary windows, which are never shown.
+    windows: FastHashMap<WindowHandle, Vector2<u32>>,
+    cursor_mode: CursorMode,
+    clipboard: Option<String>,
+}
+
+impl HeadlessVisitor {
+    pub fn new() -> Self {
+        HeadlessVisitor {
+            windows: FastHashMap::default(),
+            cursor_mode: CursorMode::Normal,
+            clipboard: None,
+        }
+    }
+}
 
 impl Visitor for HeadlessVisitor {
     #[inline]
@@ -48,4 +69,92 @@ impl Visitor for HeadlessVisitor {
     fn swap_buffers(&self) -> Result<()> {
         Ok(())
     }
+
+    #[inline]
+    fn set_size(&self, _: Vector2<u32>) {}
+
+    #[inline]
+    fn mode(&self) -> WindowMode {
+        WindowMode::Windowed
+    }
+
+    fn set_mode(&mut self, mode: WindowMode) -> Result<()> {
+        if mode != WindowMode::Windowed {
+            bail!("{:?} is not supported by headless context.", mode);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn monitors(&self) -> Vec<Monitor> {
+        Vec::new()
+    }
+
+    #[inline]
+    fn current_monitor(&self) -> Option<usize> {
+        None
+    }
+
+    fn rumble_gamepad(&self, _: u8, _: f32, _: Duration) -> Result<()> {
+        bail!("Gamepads are not supported by headless context.");
+    }
+
+    #[inline]
+    fn set_icon(&self, _: Option<WindowIcon>) -> Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn set_cursor_icon(&self, _: CursorIcon) {}
+
+    #[inline]
+    fn cursor_mode(&self) -> CursorMode {
+        self.cursor_mode
+    }
+
+    fn set_cursor_mode(&mut self, mode: CursorMode) -> Result<()> {
+        self.cursor_mode = mode;
+        Ok(())
+    }
+
+    #[inline]
+    fn clipboard(&mut self) -> Option<String> {
+        self.clipboard.clone()
+    }
+
+    fn set_clipboard(&mut self, contents: String) -> Result<()> {
+        self.clipboard = Some(contents);
+        Ok(())
+    }
+
+    fn create_window(&mut self, handle: WindowHandle, params: WindowParams) -> Result<()> {
+        self.windows.insert(handle, params.size);
+        Ok(())
+    }
+
+    fn delete_window(&mut self, handle: WindowHandle) {
+        self.windows.remove(&handle);
+    }
+
+    #[inline]
+    fn window_dimensions(&self, handle: WindowHandle) -> Option<Vector2<u32>> {
+        self.windows.get(&handle).cloned()
+    }
+
+    // Likes the GL backend, the windows that are not created are regarded as closed.
+    #[inline]
+    fn is_window_closed(&self, handle: WindowHandle) -> bool {
+        !self.windows.contains_key(&handle)
+    }
+
+    #[inline]
+    fn make_window_current(&self, _: WindowHandle) -> Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn swap_window_buffers(&self, _: WindowHandle) -> Result<()> {
+        Ok(())
+    }
 }
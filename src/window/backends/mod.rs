@@ -1,9 +1,12 @@
 mod headless;
 
+use std::time::Duration;
+
 use crate::errors::*;
 use crate::math::prelude::Vector2;
 
 use super::events::Event;
+use super::{CursorIcon, CursorMode, Monitor, WindowHandle, WindowIcon, WindowMode, WindowParams};
 
 pub trait Visitor {
     fn show(&self);
@@ -16,18 +19,37 @@ pub trait Visitor {
     fn is_current(&self) -> bool;
     fn make_current(&self) -> Result<()>;
     fn swap_buffers(&self) -> Result<()>;
+    fn set_size(&self, size: Vector2<u32>);
+    fn mode(&self) -> WindowMode;
+    fn set_mode(&mut self, mode: WindowMode) -> Result<()>;
+    fn monitors(&self) -> Vec<Monitor>;
+    fn current_monitor(&self) -> Option<usize>;
+    fn rumble_gamepad(&self, id: u8, strength: f32, duration: Duration) -> Result<()>;
+    fn set_icon(&self, icon: Option<WindowIcon>) -> Result<()>;
+    fn set_cursor_icon(&self, icon: CursorIcon);
+    fn cursor_mode(&self) -> CursorMode;
+    fn set_cursor_mode(&mut self, mode: CursorMode) -> Result<()>;
+    fn clipboard(&mut self) -> Option<String>;
+    fn set_clipboard(&mut self, contents: String) -> Result<()>;
+
+    fn create_window(&mut self, handle: WindowHandle, params: WindowParams) -> Result<()>;
+    fn delete_window(&mut self, handle: WindowHandle);
+    fn window_dimensions(&self, handle: WindowHandle) -> Option<Vector2<u32>>;
+    fn is_window_closed(&self, handle: WindowHandle) -> bool;
+    fn make_window_current(&self, handle: WindowHandle) -> Result<()>;
+    fn swap_window_buffers(&self, handle: WindowHandle) -> Result<()>;
 }
 
 pub fn new_headless() -> Box<Visitor> {
-    Box::new(self::headless::HeadlessVisitor {})
+    Box::new(self::headless::HeadlessVisitor::new())
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 mod glutin;
 #[cfg(not(target_arch = "wasm32"))]
-pub use self::glutin::new;
+pub use self::glutin::{new, new_offscreen};
 
 #[cfg(target_arch = "wasm32")]
 mod web;
 #[cfg(target_arch = "wasm32")]
-pub use self::web::new;
+pub use self::web::{new, new_offscreen};
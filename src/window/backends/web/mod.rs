@@ -4,9 +4,16 @@ mod visitor;
 use super::Visitor;
 
 use crate::errors::*;
+use crate::video::VSync;
 use crate::window::WindowParams;
 
-pub fn new(params: WindowParams) -> Result<Box<Visitor>> {
+// The frames are always paced by `requestAnimationFrame`, which is synchronized with the
+// refresh of display.
+pub fn new(params: WindowParams, _: VSync) -> Result<Box<Visitor>> {
     let visitor = visitor::WebVisitor::new(params)?;
     Ok(Box::new(visitor))
 }
+
+pub fn new_offscreen(_: WindowParams) -> Result<Box<dyn Visitor>> {
+    bail!("Offscreen rendering is not supported in web environment.");
+}
@@ -1,4 +1,23 @@
-use crate::input::prelude::Key;
+use crate::input::prelude::{GamepadAxis, GamepadButton, Key};
+use crate::window::prelude::CursorIcon;
+
+/// Gets the name of cursor in CSS.
+pub fn cursor_name(icon: CursorIcon) -> &'static str {
+    match icon {
+        CursorIcon::Default | CursorIcon::Arrow => "default",
+        CursorIcon::Crosshair => "crosshair",
+        CursorIcon::Hand => "pointer",
+        CursorIcon::Move => "move",
+        CursorIcon::Text => "text",
+        CursorIcon::Wait => "wait",
+        CursorIcon::Help => "help",
+        CursorIcon::NotAllowed => "not-allowed",
+        CursorIcon::Grab => "grab",
+        CursorIcon::Grabbing => "grabbing",
+        CursorIcon::EwResize => "ew-resize",
+        CursorIcon::NsResize => "ns-resize",
+    }
+}
 
 pub fn from_virtual_key_code(key: &str) -> Option<Key> {
     match key {
@@ -129,3 +148,38 @@ pub fn from_virtual_key_code(key: &str) -> Option<Key> {
         _ => None,
     }
 }
+
+/// Converts the index of button in the standard gamepad layout of browsers.
+pub fn from_gamepad_button(index: usize) -> GamepadButton {
+    match index {
+        0 => GamepadButton::South,
+        1 => GamepadButton::East,
+        2 => GamepadButton::West,
+        3 => GamepadButton::North,
+        4 => GamepadButton::LeftBumper,
+        5 => GamepadButton::RightBumper,
+        6 => GamepadButton::LeftTrigger,
+        7 => GamepadButton::RightTrigger,
+        8 => GamepadButton::Select,
+        9 => GamepadButton::Start,
+        10 => GamepadButton::LeftStick,
+        11 => GamepadButton::RightStick,
+        12 => GamepadButton::DPadUp,
+        13 => GamepadButton::DPadDown,
+        14 => GamepadButton::DPadLeft,
+        15 => GamepadButton::DPadRight,
+        16 => GamepadButton::Mode,
+        i => GamepadButton::Other(i as u8),
+    }
+}
+
+/// Converts the index of axis in the standard gamepad layout of browsers.
+pub fn from_gamepad_axis(index: usize) -> GamepadAxis {
+    match index {
+        0 => GamepadAxis::LeftStickX,
+        1 => GamepadAxis::LeftStickY,
+        2 => GamepadAxis::RightStickX,
+        3 => GamepadAxis::RightStickY,
+        i => GamepadAxis::Other(i as u8),
+    }
+}
@@ -1,15 +1,22 @@
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{
-    self, Document, Element, HtmlCanvasElement, KeyboardEvent, MouseEvent, Node, UiEvent, Window,
+    self, CompositionEvent, Document, Element, Gamepad, HtmlCanvasElement, HtmlElement,
+    KeyboardEvent, MouseEvent, Node, TouchEvent, UiEvent, Window,
 };
 
 use crate::input::prelude::{InputEvent, MouseButton};
-use crate::window::prelude::{Event, WindowEvent, WindowParams};
+use crate::input::touchpad::TouchState;
+use crate::window::prelude::{
+    CursorIcon, CursorMode, Event, Monitor, WindowEvent, WindowHandle, WindowIcon, WindowMode,
+    WindowParams,
+};
 
 use crate::math::prelude::Vector2;
+use crate::utils::hash::{FastHashMap, FastHashSet};
 use crate::errors::*;
 
 use super::{types, Visitor};
@@ -19,15 +26,37 @@ pub struct WebVisitor {
     window: Window,
     document: Document,
     canvas: HtmlCanvasElement,
+    // The hidden text area that receives the compositions of IME, which are only started
+    // on editable elements.
+    ime: HtmlElement,
     events: Arc<Mutex<Vec<Event>>>,
     on_mouse_move: Closure<FnMut(MouseEvent)>,
     on_mouse_down: Closure<FnMut(MouseEvent)>,
     on_mouse_up: Closure<FnMut(MouseEvent)>,
     on_key_down: Closure<FnMut(KeyboardEvent)>,
     on_key_up: Closure<FnMut(KeyboardEvent)>,
+    on_composition_end: Closure<FnMut(CompositionEvent)>,
     on_resize: Closure<FnMut(UiEvent)>,
     on_focus: Closure<FnMut(UiEvent)>,
     on_lost_focus: Closure<FnMut(UiEvent)>,
+    on_visibility_change: Closure<FnMut(web_sys::Event)>,
+    on_context_lost: Closure<FnMut(web_sys::Event)>,
+    on_context_restored: Closure<FnMut(web_sys::Event)>,
+    on_touches: Vec<Closure<FnMut(TouchEvent)>>,
+    device_pixel_ratio: f32,
+    // The states of buttons and axes of gamepads in last frame.
+    gamepads: FastHashMap<u8, (Vec<bool>, Vec<f32>)>,
+    cursor: Arc<Mutex<WebCursor>>,
+    // The system clipboard could only be accessed asynchronously, so the texts are kept
+    // in a clipboard local to the application.
+    clipboard: Option<String>,
+}
+
+struct WebCursor {
+    icon: CursorIcon,
+    mode: CursorMode,
+    // The position of mouse, which is moved with the raw motion while it's locked.
+    position: (f32, f32),
 }
 
 impl WebVisitor {
@@ -63,11 +92,45 @@ impl WebVisitor {
             .append_child(canvas.as_ref())
             .unwrap();
 
+        let ime = document
+            .create_element("textarea")
+            .unwrap()
+            .dyn_into::<HtmlElement>()
+            .map_err(|_| ())
+            .unwrap();
+
+        for &(name, value) in &[
+            ("position", "absolute"),
+            ("left", "0"),
+            ("top", "0"),
+            ("width", "1px"),
+            ("height", "1px"),
+            ("opacity", "0"),
+            ("resize", "none"),
+            ("pointer-events", "none"),
+        ] {
+            ime.style().set_property(name, value).unwrap();
+        }
+
+        AsRef::<Node>::as_ref(&body)
+            .append_child(ime.as_ref())
+            .unwrap();
+
         let events = Arc::new(Mutex::new(Vec::new()));
+        let cursor = Arc::new(Mutex::new(WebCursor {
+            icon: CursorIcon::Default,
+            mode: CursorMode::Normal,
+            position: (0.0, 0.0),
+        }));
 
         let on_mouse_down = {
             let clone = events.clone();
+            let ime = ime.clone();
             Closure::wrap(Box::new(move |v: MouseEvent| {
+                // Keeps the focus on the hidden text area, so the IME could be activated.
+                v.prevent_default();
+                ime.focus().unwrap();
+
                 let button = match v.button() {
                     0 => MouseButton::Left,
                     1 => MouseButton::Middle,
@@ -107,16 +170,24 @@ impl WebVisitor {
             let clone = events.clone();
             let window = window.clone();
             let canvas = canvas.clone();
+            let cursor = cursor.clone();
             Closure::wrap(Box::new(move |v: MouseEvent| {
-                let dpr = window.device_pixel_ratio() as f32;
-                let height = canvas.height() as f32 / dpr;
-                let rect = canvas.get_bounding_client_rect();
-
-                let position = (
-                    v.client_x() as f32 - rect.x() as f32,
-                    height - v.client_y() as f32 + rect.y() as f32,
-                );
+                let mut cursor = cursor.lock().unwrap();
+                if cursor.mode == CursorMode::Locked {
+                    cursor.position.0 += v.movement_x() as f32;
+                    cursor.position.1 -= v.movement_y() as f32;
+                } else {
+                    let dpr = window.device_pixel_ratio() as f32;
+                    let height = canvas.height() as f32 / dpr;
+                    let rect = canvas.get_bounding_client_rect();
+
+                    cursor.position = (
+                        v.client_x() as f32 - rect.x() as f32,
+                        height - v.client_y() as f32 + rect.y() as f32,
+                    );
+                }
 
+                let position = cursor.position;
                 let evt = Event::InputDevice(InputEvent::MouseMoved { position });
                 clone.lock().unwrap().push(evt);
             }) as Box<FnMut(_)>)
@@ -129,10 +200,22 @@ impl WebVisitor {
         let on_key_down = {
             let clone = events.clone();
             Closure::wrap(Box::new(move |v: KeyboardEvent| {
+                let mut events = clone.lock().unwrap();
                 if let Some(key) = types::from_virtual_key_code(&v.key()) {
                     v.prevent_default();
                     let evt = Event::InputDevice(InputEvent::KeyboardPressed { key });
-                    clone.lock().unwrap().push(evt);
+                    events.push(evt);
+                }
+
+                // The printable keys are named with the characters they produce, while the
+                // characters of compositions are received once they are committed.
+                let key = v.key();
+                let mut chars = key.chars();
+                if let (Some(character), None) = (chars.next(), chars.next()) {
+                    if !v.ctrl_key() && !v.meta_key() && !v.is_composing() {
+                        let evt = Event::InputDevice(InputEvent::ReceivedCharacter { character });
+                        events.push(evt);
+                    }
                 }
             }) as Box<FnMut(_)>)
         };
@@ -156,6 +239,29 @@ impl WebVisitor {
             .add_event_listener_with_callback("keyup", on_key_up.as_ref().unchecked_ref())
             .unwrap();
 
+        let on_composition_end = {
+            let clone = events.clone();
+            let ime = ime.clone();
+            Closure::wrap(Box::new(move |v: CompositionEvent| {
+                if let Some(data) = v.data() {
+                    let mut events = clone.lock().unwrap();
+                    for character in data.chars() {
+                        let evt = Event::InputDevice(InputEvent::ReceivedCharacter { character });
+                        events.push(evt);
+                    }
+                }
+
+                js_sys::Reflect::set(&ime, &JsValue::from_str("value"), &JsValue::from_str(""))
+                    .unwrap();
+            }) as Box<FnMut(_)>)
+        };
+
+        ime.add_event_listener_with_callback(
+            "compositionend",
+            on_composition_end.as_ref().unchecked_ref(),
+        )
+        .unwrap();
+
         let on_focus = {
             let clone = events.clone();
             Closure::wrap(Box::new(move |_: UiEvent| {
@@ -171,7 +277,7 @@ impl WebVisitor {
         let on_lost_focus = {
             let clone = events.clone();
             Closure::wrap(Box::new(move |_: UiEvent| {
-                let evt = Event::Window(WindowEvent::GainFocus);
+                let evt = Event::Window(WindowEvent::LostFocus);
                 clone.lock().unwrap().push(evt);
             }) as Box<FnMut(_)>)
         };
@@ -180,6 +286,60 @@ impl WebVisitor {
             .add_event_listener_with_callback("blur", on_lost_focus.as_ref().unchecked_ref())
             .unwrap();
 
+        let on_visibility_change = {
+            let clone = events.clone();
+            let document = document.clone();
+            Closure::wrap(Box::new(move |_: web_sys::Event| {
+                let evt = if document.hidden() {
+                    Event::Window(WindowEvent::Suspended)
+                } else {
+                    Event::Window(WindowEvent::Resumed)
+                };
+
+                clone.lock().unwrap().push(evt);
+            }) as Box<FnMut(_)>)
+        };
+
+        document
+            .add_event_listener_with_callback(
+                "visibilitychange",
+                on_visibility_change.as_ref().unchecked_ref(),
+            )
+            .unwrap();
+
+        // The default behaviour of browser is prevented, otherwise the context would never
+        // be restored.
+        let on_context_lost = {
+            let clone = events.clone();
+            Closure::wrap(Box::new(move |v: web_sys::Event| {
+                v.prevent_default();
+                let evt = Event::Window(WindowEvent::ContextLost);
+                clone.lock().unwrap().push(evt);
+            }) as Box<FnMut(_)>)
+        };
+
+        canvas
+            .add_event_listener_with_callback(
+                "webglcontextlost",
+                on_context_lost.as_ref().unchecked_ref(),
+            )
+            .unwrap();
+
+        let on_context_restored = {
+            let clone = events.clone();
+            Closure::wrap(Box::new(move |_: web_sys::Event| {
+                let evt = Event::Window(WindowEvent::ContextRestored);
+                clone.lock().unwrap().push(evt);
+            }) as Box<FnMut(_)>)
+        };
+
+        canvas
+            .add_event_listener_with_callback(
+                "webglcontextrestored",
+                on_context_restored.as_ref().unchecked_ref(),
+            )
+            .unwrap();
+
         let on_resize = {
             let clone = events.clone();
             let canvas = canvas.clone();
@@ -193,19 +353,100 @@ impl WebVisitor {
             .add_event_listener_with_callback("resize", on_resize.as_ref().unchecked_ref())
             .unwrap();
 
+        // The identifiers of touches are arbitrary numbers in browsers, e.g. Safari. They are
+        // mapped to the smallest ids that are not used by the active touches.
+        let touch_ids = Arc::new(Mutex::new(FastHashMap::<i32, u8>::default()));
+
+        let mut on_touches = Vec::new();
+        for &(name, state) in &[
+            ("touchstart", TouchState::Start),
+            ("touchmove", TouchState::Move),
+            ("touchend", TouchState::End),
+            ("touchcancel", TouchState::Cancel),
+        ] {
+            let clone = events.clone();
+            let window = window.clone();
+            let canvas = canvas.clone();
+            let touch_ids = touch_ids.clone();
+            let on_touch = Closure::wrap(Box::new(move |v: TouchEvent| {
+                // Prevents the emulated mouse events and scrolling of page.
+                v.prevent_default();
+
+                let dpr = window.device_pixel_ratio() as f32;
+                let height = canvas.height() as f32 / dpr;
+                let rect = canvas.get_bounding_client_rect();
+
+                let mut events = clone.lock().unwrap();
+                let mut touch_ids = touch_ids.lock().unwrap();
+                let touches = v.changed_touches();
+                for i in 0..touches.length() {
+                    if let Some(touch) = touches.get(i) {
+                        let position = Vector2::new(
+                            touch.client_x() as f32 - rect.x() as f32,
+                            height - touch.client_y() as f32 + rect.y() as f32,
+                        );
+
+                        // The force is zero if the device could not detect it.
+                        let force = touch.force();
+                        let pressure = if force > 0.0 { force } else { 1.0 };
+
+                        let identifier = touch.identifier();
+                        let id = match touch_ids.get(&identifier).cloned() {
+                            Some(id) => id,
+                            None => {
+                                let id = (0..=u8::max_value())
+                                    .find(|v| touch_ids.values().all(|w| w != v))
+                                    .unwrap_or(u8::max_value());
+                                touch_ids.insert(identifier, id);
+                                id
+                            }
+                        };
+
+                        if state == TouchState::End || state == TouchState::Cancel {
+                            touch_ids.remove(&identifier);
+                        }
+
+                        events.push(Event::InputDevice(InputEvent::Touch {
+                            id,
+                            state,
+                            position,
+                            pressure,
+                        }));
+                    }
+                }
+            }) as Box<FnMut(_)>);
+
+            canvas
+                .add_event_listener_with_callback(name, on_touch.as_ref().unchecked_ref())
+                .unwrap();
+
+            on_touches.push(on_touch);
+        }
+
+        let device_pixel_ratio = window.device_pixel_ratio() as f32;
         let visitor = WebVisitor {
             window: window,
             document: document,
             canvas: canvas,
+            ime: ime,
             events: events,
             on_mouse_down: on_mouse_down,
             on_mouse_up: on_mouse_up,
             on_mouse_move: on_mouse_move,
             on_key_down: on_key_down,
             on_key_up: on_key_up,
+            on_composition_end: on_composition_end,
             on_focus: on_focus,
             on_lost_focus: on_lost_focus,
+            on_visibility_change: on_visibility_change,
+            on_context_lost: on_context_lost,
+            on_context_restored: on_context_restored,
             on_resize: on_resize,
+            on_touches: on_touches,
+            device_pixel_ratio: device_pixel_ratio,
+            gamepads: FastHashMap::default(),
+            cursor: cursor,
+            clipboard: None,
         };
 
         let dpr = visitor.device_pixel_ratio();
@@ -219,6 +460,102 @@ impl WebVisitor {
     }
 }
 
+impl WebVisitor {
+    fn apply_cursor_style(&self) {
+        let cursor = self.cursor.lock().unwrap();
+        let name = match cursor.mode {
+            CursorMode::Normal => types::cursor_name(cursor.icon),
+            CursorMode::Hidden | CursorMode::Locked => "none",
+        };
+
+        self.canvas.style().set_property("cursor", name).unwrap();
+    }
+
+    /// Polls the states of gamepads, and sends the events of changes since last frame.
+    fn poll_gamepads(&mut self, events: &mut Vec<Event>) {
+        let gamepads = match self.window.navigator().get_gamepads() {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+
+        let mut connected = FastHashSet::default();
+        for i in 0..gamepads.length() {
+            let gamepad = match gamepads.get(i).dyn_into::<Gamepad>() {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+
+            if !gamepad.connected() {
+                continue;
+            }
+
+            let id = gamepad.index() as u8;
+            connected.insert(id);
+
+            let last = self.gamepads.entry(id).or_insert_with(|| {
+                events.push(Event::InputDevice(InputEvent::GamepadConnected { id }));
+                (Vec::new(), Vec::new())
+            });
+
+            let buttons = gamepad.buttons();
+            last.0.resize(buttons.length() as usize, false);
+
+            for (i, v) in last.0.iter_mut().enumerate() {
+                let pressed = buttons
+                    .get(i as u32)
+                    .dyn_into::<web_sys::GamepadButton>()
+                    .map(|v| v.pressed())
+                    .unwrap_or(false);
+
+                if *v != pressed {
+                    *v = pressed;
+
+                    let button = types::from_gamepad_button(i);
+                    let evt = if pressed {
+                        InputEvent::GamepadPressed { id, button }
+                    } else {
+                        InputEvent::GamepadReleased { id, button }
+                    };
+
+                    events.push(Event::InputDevice(evt));
+                }
+            }
+
+            let axes = gamepad.axes();
+            last.1.resize(axes.length() as usize, 0.0);
+
+            for (i, v) in last.1.iter_mut().enumerate() {
+                let mut value = axes.get(i as u32).as_f64().unwrap_or(0.0) as f32;
+
+                // The y axes of sticks point down in browsers.
+                if i == 1 || i == 3 {
+                    value = -value;
+                }
+
+                if *v != value {
+                    *v = value;
+
+                    let axis = types::from_gamepad_axis(i);
+                    let evt = InputEvent::GamepadAxisChanged { id, axis, value };
+                    events.push(Event::InputDevice(evt));
+                }
+            }
+        }
+
+        let disconnected: Vec<_> = self
+            .gamepads
+            .keys()
+            .filter(|v| !connected.contains(v))
+            .cloned()
+            .collect();
+
+        for id in disconnected {
+            self.gamepads.remove(&id);
+            events.push(Event::InputDevice(InputEvent::GamepadDisconnected { id }));
+        }
+    }
+}
+
 impl Visitor for WebVisitor {
     #[inline]
     fn show(&self) {
@@ -265,12 +602,23 @@ impl Visitor for WebVisitor {
                     (dims.y as f32 / dpr) as u32
                 ),
             ).unwrap();
+
+        self.apply_cursor_style();
     }
 
     #[inline]
     fn poll_events(&mut self, v: &mut Vec<Event>) {
         let mut events = self.events.lock().unwrap();
         v.extend(events.drain(..));
+
+        // The ratio changes once the page is zoomed or moved to another monitor.
+        let dpr = self.device_pixel_ratio();
+        if dpr != self.device_pixel_ratio {
+            self.device_pixel_ratio = dpr;
+            v.push(Event::Window(WindowEvent::DpiChanged(dpr)));
+        }
+
+        self.poll_gamepads(v);
     }
 
     #[inline]
@@ -288,4 +636,131 @@ impl Visitor for WebVisitor {
     fn swap_buffers(&self) -> Result<()> {
         Ok(())
     }
+
+    #[inline]
+    fn set_size(&self, size: Vector2<u32>) {
+        let dpr = self.device_pixel_ratio();
+        self.resize(Vector2::new(
+            (size.x as f32 * dpr) as u32,
+            (size.y as f32 * dpr) as u32,
+        ));
+    }
+
+    #[inline]
+    fn mode(&self) -> WindowMode {
+        WindowMode::Windowed
+    }
+
+    fn set_mode(&mut self, mode: WindowMode) -> Result<()> {
+        if mode != WindowMode::Windowed {
+            bail!("{:?} is not supported in browser.", mode);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn monitors(&self) -> Vec<Monitor> {
+        Vec::new()
+    }
+
+    #[inline]
+    fn current_monitor(&self) -> Option<usize> {
+        None
+    }
+
+    fn rumble_gamepad(&self, id: u8, strength: f32, duration: Duration) -> Result<()> {
+        let gamepad = self
+            .window
+            .navigator()
+            .get_gamepads()
+            .map_err(|_| format_err!("Gamepads are not supported by this browser."))?
+            .get(u32::from(id));
+
+        // The vibration actuator is not standardized yet, so it is accessed dynamically.
+        let actuator = js_sys::Reflect::get(&gamepad, &JsValue::from_str("vibrationActuator"))
+            .unwrap_or(JsValue::UNDEFINED);
+
+        let play = js_sys::Reflect::get(&actuator, &JsValue::from_str("playEffect"))
+            .ok()
+            .and_then(|v| v.dyn_into::<js_sys::Function>().ok())
+            .ok_or_else(|| format_err!("The gamepad {} does not support rumble.", id))?;
+
+        let millis = duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_millis());
+        let params = js_sys::Object::new();
+        for &(k, v) in &[
+            ("duration", millis),
+            ("strongMagnitude", f64::from(strength)),
+            ("weakMagnitude", f64::from(strength)),
+        ] {
+            js_sys::Reflect::set(&params, &JsValue::from_str(k), &JsValue::from_f64(v))
+                .map_err(|_| format_err!("Failed to rumble the gamepad {}.", id))?;
+        }
+
+        play.call2(&actuator, &JsValue::from_str("dual-rumble"), &params)
+            .map_err(|_| format_err!("Failed to rumble the gamepad {}.", id))?;
+        Ok(())
+    }
+
+    #[inline]
+    fn set_icon(&self, _: Option<WindowIcon>) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor_icon(&self, icon: CursorIcon) {
+        self.cursor.lock().unwrap().icon = icon;
+        self.apply_cursor_style();
+    }
+
+    #[inline]
+    fn cursor_mode(&self) -> CursorMode {
+        self.cursor.lock().unwrap().mode
+    }
+
+    fn set_cursor_mode(&mut self, mode: CursorMode) -> Result<()> {
+        let last_mode = self.cursor_mode();
+        if mode == CursorMode::Locked {
+            self.canvas.request_pointer_lock();
+        } else if last_mode == CursorMode::Locked {
+            self.document.exit_pointer_lock();
+        }
+
+        self.cursor.lock().unwrap().mode = mode;
+        self.apply_cursor_style();
+        Ok(())
+    }
+
+    #[inline]
+    fn clipboard(&mut self) -> Option<String> {
+        self.clipboard.clone()
+    }
+
+    fn set_clipboard(&mut self, contents: String) -> Result<()> {
+        self.clipboard = Some(contents);
+        Ok(())
+    }
+
+    fn create_window(&mut self, _: WindowHandle, _: WindowParams) -> Result<()> {
+        bail!("Secondary windows are not supported in browser.");
+    }
+
+    fn delete_window(&mut self, _: WindowHandle) {}
+
+    #[inline]
+    fn window_dimensions(&self, _: WindowHandle) -> Option<Vector2<u32>> {
+        None
+    }
+
+    #[inline]
+    fn is_window_closed(&self, _: WindowHandle) -> bool {
+        true
+    }
+
+    fn make_window_current(&self, handle: WindowHandle) -> Result<()> {
+        bail!("{:?} is invalid.", handle);
+    }
+
+    fn swap_window_buffers(&self, handle: WindowHandle) -> Result<()> {
+        bail!("{:?} is invalid.", handle);
+    }
 }
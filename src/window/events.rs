@@ -19,6 +19,16 @@ pub enum WindowEvent {
     Resized(u32, u32),
     /// The position of window has changed.
     Moved(u32, u32),
+    /// The device pixel ratio of window has changed, e.g. it has been moved to another
+    /// monitor. The framebuffer is resized to match the new ratio at the end of frame.
+    DpiChanged(f32),
+    /// The OpenGL context has been lost, e.g. the browser reclaimed the WebGL context of a
+    /// page in background, or the EGL context was released by Android. Nothing is drawn
+    /// until it's restored.
+    ContextLost,
+    /// The lost OpenGL context has been restored. The video objects are recreated with
+    /// their latest data if `VideoParams::restore_lost_context` is set.
+    ContextRestored,
 }
 
 /// The enumerations of all events that come from various kinds of user input.
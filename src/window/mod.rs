@@ -4,7 +4,9 @@ pub mod events;
 pub mod prelude {
     pub use super::events::{Event, WindowEvent};
     pub use super::system::{EventListener, EventListenerHandle};
-    pub use super::WindowParams;
+    pub use super::{
+        CursorIcon, CursorMode, Monitor, WindowHandle, WindowIcon, WindowMode, WindowParams,
+    };
 }
 
 mod backends;
@@ -16,6 +18,8 @@ use self::system::{EventListener, EventListenerHandle};
 use crate::errors::*;
 use crate::math::prelude::Vector2;
 
+impl_handle!(WindowHandle);
+
 #[derive(Debug, Clone)]
 pub struct WindowParams {
     /// Sets the title of window.
@@ -25,8 +29,11 @@ pub struct WindowParams {
     /// Sets the multisampling level to request. A value of 0 indicates that
     /// multisampling must not be enabled.
     pub multisample: u16,
-    /// Specifies whether should we have vsync.
-    pub vsync: bool,
+    /// Requests a framebuffer that could encode linear colors into sRGB space, see
+    /// `SurfaceParams::set_srgb`. It only takes effect on the main window.
+    pub srgb: bool,
+    /// Sets the display mode of window.
+    pub mode: WindowMode,
 }
 
 impl Default for WindowParams {
@@ -35,8 +42,145 @@ impl Default for WindowParams {
             title: "Window".to_owned(),
             size: Vector2::new(640, 320),
             multisample: 2,
-            vsync: false,
+            srgb: false,
+            mode: WindowMode::Windowed,
+        }
+    }
+}
+
+/// The display mode of window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    /// The window with title bar and borders.
+    Windowed,
+    /// The window without title bar and borders.
+    Borderless,
+    /// The window that covers the whole monitor, which is the index into `window::monitors`.
+    ///
+    /// The resolution of monitor is kept, the framebuffer could be scaled down by rendering
+    /// into smaller render textures if the GPU is not fast enough.
+    ///
+    /// # Platform-specific
+    ///
+    /// The video modes of monitors could not be changed with glutin, so it's always the
+    /// borderless fullscreen instead of exclusive one.
+    Fullscreen(usize),
+}
+
+impl Default for WindowMode {
+    fn default() -> Self {
+        WindowMode::Windowed
+    }
+}
+
+/// The monitor that windows could be placed on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    /// The human-readable name of monitor.
+    pub name: String,
+    /// The position in *pixels* of the top-left hand corner of monitor on the desktop.
+    pub position: Vector2<i32>,
+    /// The current resolution in *pixels* of monitor.
+    pub dimensions: Vector2<u32>,
+    /// The ratio between physical pixels and points of monitor.
+    pub device_pixel_ratio: f32,
+}
+
+/// The pixels of window icon in RGBA8 format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowIcon {
+    pub rgba: Vec<u8>,
+    pub dimensions: Vector2<u32>,
+}
+
+impl WindowIcon {
+    /// Creates an icon from the pixels in RGBA8 format.
+    pub fn new(rgba: Vec<u8>, dimensions: Vector2<u32>) -> Result<Self> {
+        if rgba.len() != (dimensions.x * dimensions.y * 4) as usize {
+            bail!(
+                "The pixels of icon do not match its dimensions {:?}.",
+                dimensions
+            );
         }
+
+        Ok(WindowIcon { rgba, dimensions })
+    }
+
+    /// Decodes the base level of texture asset in `RGBA8` or `SRGBA8` format into icon.
+    pub fn from_texture(bytes: &[u8]) -> Result<Self> {
+        use crate::video::assets::texture::{TextureData, TextureFormat, TextureParams};
+        use crate::video::assets::texture_loader::MAGIC;
+
+        if bytes.len() < 8 || bytes[0..8] != MAGIC[..] {
+            bail!("[WindowIcon] MAGIC number not match.");
+        }
+
+        let mut file = ::std::io::Cursor::new(&bytes[8..]);
+        let params: TextureParams = bincode::deserialize_from(&mut file)?;
+        let data: TextureData = bincode::deserialize_from(&mut file)?;
+
+        if params.format != TextureFormat::RGBA8 && params.format != TextureFormat::SRGBA8 {
+            bail!(
+                "[WindowIcon] The format {:?} is not supported.",
+                params.format
+            );
+        }
+
+        match data.bytes.into_iter().next() {
+            Some(v) => WindowIcon::new(v.into_vec(), params.dimensions),
+            None => bail!("[WindowIcon] The texture has no data."),
+        }
+    }
+}
+
+/// The shapes of mouse cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorIcon {
+    /// The platform-dependent default cursor.
+    Default,
+    Crosshair,
+    /// A hand, which is often used to indicate links.
+    Hand,
+    Arrow,
+    /// Indicates something is to be moved.
+    Move,
+    /// Indicates text that may be selected or edited.
+    Text,
+    /// Indicates that the program is busy.
+    Wait,
+    Help,
+    /// Indicates that something cannot be done.
+    NotAllowed,
+    Grab,
+    Grabbing,
+    /// Indicates the edges that could be resized horizontally.
+    EwResize,
+    /// Indicates the edges that could be resized vertically.
+    NsResize,
+}
+
+impl Default for CursorIcon {
+    fn default() -> Self {
+        CursorIcon::Default
+    }
+}
+
+/// The modes of mouse cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorMode {
+    /// The cursor is visible and moves freely.
+    Normal,
+    /// The cursor is hidden when it's over the window.
+    Hidden,
+    /// The cursor is hidden and locked to the window, e.g. for the controls of first-person
+    /// cameras. The raw motion of mouse is reported by `input::mouse_movement`, and the
+    /// position of mouse is virtual while it's locked.
+    Locked,
+}
+
+impl Default for CursorMode {
+    fn default() -> Self {
+        CursorMode::Normal
     }
 }
 
@@ -111,10 +255,149 @@ pub fn device_pixel_ratio() -> f32 {
     ctx().device_pixel_ratio()
 }
 
+/// Sets the size in *points* of the client area of the window. It has no effect if the
+/// window is fullscreen.
+///
+/// # Platform-specific
+///
+/// Has no effect on mobile platform.
+#[inline]
+pub fn set_size(size: Vector2<u32>) {
+    ctx().set_size(size);
+}
+
+/// Returns the current display mode of window.
+#[inline]
+pub fn mode() -> WindowMode {
+    ctx().mode()
+}
+
+/// Sets the display mode of window, e.g. toggles the fullscreen on the monitor at `index`
+/// with `WindowMode::Fullscreen(index)`.
+///
+/// # Platform-specific
+///
+/// Only `WindowMode::Windowed` is supported with offscreen context and in web environment.
+#[inline]
+pub fn set_mode(mode: WindowMode) -> Result<()> {
+    ctx().set_mode(mode)
+}
+
+/// Returns the available monitors, the first one is the primary monitor.
+///
+/// The `WindowEvent::DpiChanged` event is sent once the window has been moved to monitor
+/// with different device pixel ratio.
+#[inline]
+pub fn monitors() -> Vec<Monitor> {
+    ctx().monitors()
+}
+
+/// Returns the index into `window::monitors` of the monitor that the window is currently
+/// on.
+#[inline]
+pub fn current_monitor() -> Option<usize> {
+    ctx().current_monitor()
+}
+
+/// Sets the icon of window, or resets it to the default one with `None`.
+///
+/// # Platform-specific
+///
+/// Has no effect on macOS and in web environment.
+#[inline]
+pub fn set_icon(icon: Option<WindowIcon>) -> Result<()> {
+    ctx().set_icon(icon)
+}
+
+/// Sets the icon of window from the texture asset at `url`, which should be imported in
+/// `RGBA8` or `SRGBA8` format. It's loaded asynchronously, and applied at the beginning
+/// of the next frame once loaded.
+#[inline]
+pub fn set_icon_from<T: AsRef<str>>(url: T) -> Result<()> {
+    ctx().set_icon_from(url)
+}
+
+/// Sets the shape of mouse cursor.
+#[inline]
+pub fn set_cursor_icon(icon: CursorIcon) {
+    ctx().set_cursor_icon(icon);
+}
+
+/// Returns the current mode of mouse cursor.
+#[inline]
+pub fn cursor_mode() -> CursorMode {
+    ctx().cursor_mode()
+}
+
+/// Shows, hides or locks the mouse cursor.
+///
+/// # Platform-specific
+///
+/// The cursor could only be locked during the handlers of user input in web environment,
+/// and it is unlocked by browser when user presses the escape key.
+#[inline]
+pub fn set_cursor_mode(mode: CursorMode) -> Result<()> {
+    ctx().set_cursor_mode(mode)
+}
+
+/// Returns the text in system clipboard.
+///
+/// # Platform-specific
+///
+/// The clipboard is local to the application with headless context and in web environment,
+/// since the system one could only be accessed asynchronously by web pages.
+#[inline]
+pub fn clipboard() -> Option<String> {
+    ctx().clipboard()
+}
+
+/// Puts the text into system clipboard.
+#[inline]
+pub fn set_clipboard<T: Into<String>>(contents: T) -> Result<()> {
+    ctx().set_clipboard(contents.into())
+}
+
+/// Creates a secondary window, e.g. the scene view of editors or the views on other
+/// monitors. It is drawn into by the surfaces targeting it with `SurfaceParams::set_window`.
+///
+/// The video objects are shared between windows. Only the close requests of secondary
+/// windows are handled, their input events are ignored.
+///
+/// # Platform-specific
+///
+/// Secondary windows are not supported with offscreen context and in web environment. The
+/// surfaces targeting them are drawn with framebuffer blitting, which is not available on
+/// OpenGL ES 2.0 contexts.
+#[inline]
+pub fn create_window(params: WindowParams) -> Result<WindowHandle> {
+    ctx().create_window(params)
+}
+
+/// Deletes the secondary window.
+#[inline]
+pub fn delete_window(handle: WindowHandle) {
+    ctx().delete_window(handle)
+}
+
+/// Returns the size in *pixels* of the framebuffer of secondary window.
+#[inline]
+pub fn window_dimensions(handle: WindowHandle) -> Option<Vector2<u32>> {
+    ctx().window_dimensions(handle)
+}
+
+/// Checks if the secondary window has been closed by user. The closed windows are hidden
+/// until they are deleted with `delete_window`.
+#[inline]
+pub fn is_window_closed(handle: WindowHandle) -> bool {
+    ctx().is_window_closed(handle)
+}
+
 pub(crate) mod inside {
     use crate::errors::*;
     use crate::math::prelude::Vector2;
 
+    use crate::video::VSync;
+
     use super::system::WindowSystem;
     use super::WindowParams;
 
@@ -133,10 +416,19 @@ pub(crate) mod inside {
     }
 
     /// Setup the window system.
-    pub unsafe fn setup(params: WindowParams) -> Result<()> {
+    pub unsafe fn setup(params: WindowParams, vsync: VSync) -> Result<()> {
+        debug_assert!(CTX.is_null(), "duplicated setup of window system.");
+
+        let ctx = WindowSystem::from(params, vsync)?;
+        CTX = Box::into_raw(Box::new(ctx));
+        Ok(())
+    }
+
+    /// Setup the window system with offscreen context.
+    pub unsafe fn offscreen(params: WindowParams) -> Result<()> {
         debug_assert!(CTX.is_null(), "duplicated setup of window system.");
 
-        let ctx = WindowSystem::from(params)?;
+        let ctx = WindowSystem::offscreen(params)?;
         CTX = Box::into_raw(Box::new(ctx));
         Ok(())
     }
@@ -164,3 +456,35 @@ pub(crate) mod inside {
         CTX = std::ptr::null();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::video::assets::texture::{TextureData, TextureFormat, TextureParams};
+    use crate::video::assets::texture_loader::MAGIC;
+
+    #[test]
+    fn icon() {
+        assert!(WindowIcon::new(vec![0; 15], Vector2::new(2, 2)).is_err());
+
+        let mut params = TextureParams::default();
+        params.dimensions = Vector2::new(2, 2);
+        let data = TextureData {
+            bytes: vec![vec![255; 16].into_boxed_slice()],
+        };
+
+        let mut bytes = MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, &params).unwrap();
+        bincode::serialize_into(&mut bytes, &data).unwrap();
+
+        let icon = WindowIcon::from_texture(&bytes).unwrap();
+        assert_eq!(icon.dimensions, Vector2::new(2, 2));
+        assert_eq!(icon.rgba, vec![255; 16]);
+
+        params.format = TextureFormat::RGB8;
+        let mut bytes = MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, &params).unwrap();
+        bincode::serialize_into(&mut bytes, &data).unwrap();
+        assert!(WindowIcon::from_texture(&bytes).is_err());
+    }
+}
@@ -1,13 +1,17 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 use crate::application::prelude::{LifecycleListener, LifecycleListenerHandle};
 use crate::errors::*;
 use crate::math::prelude::Vector2;
+use crate::utils::handle_pool::HandlePool;
 use crate::utils::object_pool::ObjectPool;
+use crate::video::VSync;
 
 use super::backends::{self, Visitor};
-use super::events::Event;
-use super::WindowParams;
+use super::events::{Event, WindowEvent};
+use super::{CursorIcon, CursorMode, Monitor, WindowHandle, WindowIcon, WindowMode, WindowParams};
 
 impl_handle!(EventListenerHandle);
 
@@ -26,6 +30,10 @@ struct WindowState {
     events: Mutex<Vec<Event>>,
     last_frame_listeners: Mutex<Vec<Arc<Mutex<dyn EventListener>>>>,
     listeners: Mutex<ObjectPool<EventListenerHandle, Arc<Mutex<dyn EventListener>>>>,
+    windows: Mutex<HandlePool<WindowHandle>>,
+    // The icon that is loaded asynchronously, which is applied at the next frame.
+    pending_icon: Arc<Mutex<Option<WindowIcon>>>,
+    suspended: AtomicBool,
 }
 
 impl LifecycleListener for Arc<WindowState> {
@@ -36,6 +44,23 @@ impl LifecycleListener for Arc<WindowState> {
 
         let mut visitor = self.visitor.write().unwrap();
         visitor.poll_events(&mut events);
+        crate::application::replay_events(&mut events);
+
+        for v in events.iter() {
+            match *v {
+                Event::Window(WindowEvent::Suspended) => {
+                    self.suspended.store(true, Ordering::Relaxed)
+                }
+                Event::Window(WindowEvent::Resumed) => {
+                    self.suspended.store(false, Ordering::Relaxed)
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(icon) = self.pending_icon.lock().unwrap().take() {
+            visitor.set_icon(Some(icon))?;
+        }
 
         let mut last_frame_listeners = self.last_frame_listeners.lock().unwrap();
 
@@ -61,7 +86,12 @@ impl LifecycleListener for Arc<WindowState> {
         // **Warning**: if you enabled vsync, this function will block until the next time the screen
         // is refreshed. However drivers can choose to override your vsync settings, which means that
         // you can't know in advance whether swap_buffers will block or not.
-        self.visitor.read().unwrap().swap_buffers()?;
+        //
+        // Nothing is presented in background, since the surface might have been destroyed.
+        if !self.suspended.load(Ordering::Relaxed) {
+            self.visitor.read().unwrap().swap_buffers()?;
+        }
+
         Ok(())
     }
 }
@@ -74,12 +104,15 @@ impl Drop for WindowSystem {
 
 impl WindowSystem {
     /// Creates a new `WindowSystem` and initalize OpenGL context.
-    pub fn from(params: WindowParams) -> Result<Self> {
+    pub fn from(params: WindowParams, vsync: VSync) -> Result<Self> {
         let state = Arc::new(WindowState {
             last_frame_listeners: Mutex::new(Vec::new()),
             listeners: Mutex::new(ObjectPool::new()),
             events: Mutex::new(Vec::new()),
-            visitor: RwLock::new(backends::new(params)?),
+            windows: Mutex::new(HandlePool::new()),
+            pending_icon: Arc::new(Mutex::new(None)),
+            suspended: AtomicBool::new(false),
+            visitor: RwLock::new(backends::new(params, vsync)?),
         });
 
         let window = WindowSystem {
@@ -90,12 +123,33 @@ impl WindowSystem {
         Ok(window)
     }
 
+    /// Creates a new `Window` with offscreen OpenGL context, which has no window.
+    pub fn offscreen(params: WindowParams) -> Result<Self> {
+        let state = Arc::new(WindowState {
+            last_frame_listeners: Mutex::new(Vec::new()),
+            listeners: Mutex::new(ObjectPool::new()),
+            events: Mutex::new(Vec::new()),
+            windows: Mutex::new(HandlePool::new()),
+            pending_icon: Arc::new(Mutex::new(None)),
+            suspended: AtomicBool::new(false),
+            visitor: RwLock::new(backends::new_offscreen(params)?),
+        });
+
+        Ok(WindowSystem {
+            state: state.clone(),
+            lis: crate::application::attach(state),
+        })
+    }
+
     /// Creates a new `Window` with headless context.
     pub fn headless() -> Self {
         let state = Arc::new(WindowState {
             last_frame_listeners: Mutex::new(Vec::new()),
             listeners: Mutex::new(ObjectPool::new()),
             events: Mutex::new(Vec::new()),
+            windows: Mutex::new(HandlePool::new()),
+            pending_icon: Arc::new(Mutex::new(None)),
+            suspended: AtomicBool::new(false),
             visitor: RwLock::new(backends::new_headless()),
         });
 
@@ -177,9 +231,150 @@ impl WindowSystem {
         self.state.visitor.read().unwrap().device_pixel_ratio()
     }
 
+    /// Sets the size in *points* of the client area of the window.
+    #[inline]
+    pub fn set_size(&self, size: Vector2<u32>) {
+        self.state.visitor.read().unwrap().set_size(size);
+    }
+
+    /// Returns the current display mode of window.
+    #[inline]
+    pub fn mode(&self) -> WindowMode {
+        self.state.visitor.read().unwrap().mode()
+    }
+
+    /// Sets the display mode of window.
+    #[inline]
+    pub fn set_mode(&self, mode: WindowMode) -> Result<()> {
+        self.state.visitor.write().unwrap().set_mode(mode)
+    }
+
+    /// Returns the available monitors, the first one is the primary monitor.
+    #[inline]
+    pub fn monitors(&self) -> Vec<Monitor> {
+        self.state.visitor.read().unwrap().monitors()
+    }
+
+    /// Vibrates the gamepad with `strength` for `duration`.
+    #[inline]
+    pub fn rumble_gamepad(&self, id: u8, strength: f32, duration: Duration) -> Result<()> {
+        self.state
+            .visitor
+            .read()
+            .unwrap()
+            .rumble_gamepad(id, strength, duration)
+    }
+
+    /// Returns the index of the monitor that the window is currently on.
+    #[inline]
+    pub fn current_monitor(&self) -> Option<usize> {
+        self.state.visitor.read().unwrap().current_monitor()
+    }
+
+    /// Sets the icon of window.
+    #[inline]
+    pub fn set_icon(&self, icon: Option<WindowIcon>) -> Result<()> {
+        self.state.visitor.read().unwrap().set_icon(icon)
+    }
+
+    /// Sets the icon of window from the texture asset at `url` asynchronously.
+    pub fn set_icon_from<T: AsRef<str>>(&self, url: T) -> Result<()> {
+        let pending = self.state.pending_icon.clone();
+        crate::res::load_from_with_callback(url, move |rsp| {
+            match rsp.and_then(|bytes| WindowIcon::from_texture(&bytes)) {
+                Ok(icon) => *pending.lock().unwrap() = Some(icon),
+                Err(err) => warn!("Failed to load the icon of window: {}", err),
+            }
+        })?;
+
+        Ok(())
+    }
+
+    /// Sets the shape of mouse cursor.
+    #[inline]
+    pub fn set_cursor_icon(&self, icon: CursorIcon) {
+        self.state.visitor.read().unwrap().set_cursor_icon(icon);
+    }
+
+    /// Returns the current mode of mouse cursor.
+    #[inline]
+    pub fn cursor_mode(&self) -> CursorMode {
+        self.state.visitor.read().unwrap().cursor_mode()
+    }
+
+    /// Shows, hides or locks the mouse cursor.
+    #[inline]
+    pub fn set_cursor_mode(&self, mode: CursorMode) -> Result<()> {
+        self.state.visitor.write().unwrap().set_cursor_mode(mode)
+    }
+
+    /// Returns the text in system clipboard.
+    #[inline]
+    pub fn clipboard(&self) -> Option<String> {
+        self.state.visitor.write().unwrap().clipboard()
+    }
+
+    /// Puts the text into system clipboard.
+    #[inline]
+    pub fn set_clipboard(&self, contents: String) -> Result<()> {
+        self.state.visitor.write().unwrap().set_clipboard(contents)
+    }
+
     /// Resize the GL context.
     #[inline]
     pub fn resize(&self, dimensions: Vector2<u32>) {
         self.state.visitor.read().unwrap().resize(dimensions);
     }
+
+    /// Creates a secondary window, which shares the video objects with the main window.
+    pub fn create_window(&self, params: WindowParams) -> Result<WindowHandle> {
+        let handle = self.state.windows.lock().unwrap().create();
+
+        let mut visitor = self.state.visitor.write().unwrap();
+        if let Err(err) = visitor.create_window(handle, params) {
+            self.state.windows.lock().unwrap().free(handle);
+            return Err(err);
+        }
+
+        Ok(handle)
+    }
+
+    /// Deletes the secondary window.
+    pub fn delete_window(&self, handle: WindowHandle) {
+        if self.state.windows.lock().unwrap().free(handle) {
+            self.state.visitor.write().unwrap().delete_window(handle);
+        }
+    }
+
+    /// Returns the size in *pixels* of the framebuffer of secondary window.
+    #[inline]
+    pub fn window_dimensions(&self, handle: WindowHandle) -> Option<Vector2<u32>> {
+        self.state.visitor.read().unwrap().window_dimensions(handle)
+    }
+
+    /// Checks if the secondary window has been closed by user.
+    #[inline]
+    pub fn is_window_closed(&self, handle: WindowHandle) -> bool {
+        self.state.visitor.read().unwrap().is_window_closed(handle)
+    }
+
+    /// Sets the context of secondary window as the active context in this thread.
+    #[inline]
+    pub fn make_window_current(&self, handle: WindowHandle) -> Result<()> {
+        self.state
+            .visitor
+            .read()
+            .unwrap()
+            .make_window_current(handle)
+    }
+
+    /// Swaps the buffers of secondary window.
+    #[inline]
+    pub fn swap_window_buffers(&self, handle: WindowHandle) -> Result<()> {
+        self.state
+            .visitor
+            .read()
+            .unwrap()
+            .swap_window_buffers(handle)
+    }
 }
@@ -0,0 +1,30 @@
+extern crate crayon;
+
+use crayon::res::request::Request;
+use crayon::res::url::Url;
+use crayon::res::vfs::dir::Dir;
+use crayon::res::vfs::VFS;
+use crayon::sched::prelude::LatchProbe;
+
+#[test]
+fn mount() {
+    let base = std::env::temp_dir().join("crayon_dir_mount");
+    std::fs::create_dir_all(base.join("textures")).unwrap();
+    std::fs::write(base.join("textures/crate"), b"crate").unwrap();
+
+    let dir = Dir::mount(&base);
+    let latch = Request::latch();
+    let mut request = Request::new(latch.clone());
+    assert!(!request.is_set());
+
+    dir.request(&Url::new("res:///textures//crate").unwrap(), latch);
+    assert!(request.is_set());
+    assert!(request.poll());
+    assert_eq!(&request.response().unwrap().as_ref().unwrap()[..], b"crate");
+
+    let latch = Request::latch();
+    dir.request(&Url::new("res:///textures/missing").unwrap(), latch.clone());
+    assert!(latch.take().is_err());
+
+    std::fs::remove_dir_all(&base).unwrap();
+}
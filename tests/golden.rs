@@ -0,0 +1,176 @@
+extern crate crayon;
+
+use crayon::errors::Result;
+use crayon::impl_vertex;
+use crayon::prelude::*;
+use crayon::testing::{self, GoldenParams};
+
+struct Clear {
+    surface: SurfaceHandle,
+    batch: CommandBuffer,
+}
+
+impl Clear {
+    fn new() -> Result<Self> {
+        let mut params = SurfaceParams::default();
+        params.set_clear(Color::red(), None, None);
+
+        Ok(Clear {
+            surface: video::create_surface(params)?,
+            batch: CommandBuffer::new(),
+        })
+    }
+}
+
+impl Drop for Clear {
+    fn drop(&mut self) {
+        video::delete_surface(self.surface);
+    }
+}
+
+impl LifecycleListener for Clear {
+    fn on_update(&mut self) -> Result<()> {
+        self.batch.submit(self.surface)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn clear() {
+    let mut params = GoldenParams::default();
+    params.engine.window.size = (64, 64).into();
+    testing::assert_golden("tests/golden/clear.png", params, Clear::new).unwrap();
+}
+
+impl_vertex! {
+    Vertex {
+        position => [Position; Float; 2; false],
+    }
+}
+
+// Draws a full-screen quad that samples the smallest mipmap of a generated chain.
+struct Mipmaps {
+    surface: SurfaceHandle,
+    shader: ShaderHandle,
+    mesh: MeshHandle,
+    texture: TextureHandle,
+    sampler: SamplerHandle,
+    batch: CommandBuffer,
+}
+
+impl Mipmaps {
+    fn new() -> Result<Self> {
+        let verts = [
+            Vertex::new([-1.0, -1.0]),
+            Vertex::new([1.0, -1.0]),
+            Vertex::new([1.0, 1.0]),
+            Vertex::new([-1.0, 1.0]),
+        ];
+        let idxes: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+        let mut params = MeshParams::default();
+        params.num_verts = 4;
+        params.num_idxes = 6;
+        params.layout = Vertex::layout();
+
+        let data = MeshData {
+            vptr: Vertex::encode(&verts[..]).into(),
+            iptr: IndexFormat::encode(&idxes).into(),
+        };
+
+        let mesh = video::create_mesh(params, Some(data))?;
+
+        let mut params = ShaderParams::default();
+        params.attributes = AttributeLayout::build()
+            .with(Attribute::Position, 2)
+            .finish();
+        params.uniforms = UniformVariableLayout::build()
+            .with("u_Texture", UniformVariableType::Texture)
+            .finish();
+
+        let vs = "
+            #version 100
+            precision lowp float;
+            attribute vec2 Position;
+            varying vec2 v_Texcoord;
+            void main() {
+                gl_Position = vec4(Position, 0.0, 1.0);
+                v_Texcoord = (Position + vec2(1.0, 1.0)) / 2.0;
+            }
+        ";
+
+        let fs = "
+            #version 100
+            precision lowp float;
+            varying vec2 v_Texcoord;
+            uniform sampler2D u_Texture;
+            void main() {
+                gl_FragColor = vec4(texture2D(u_Texture, v_Texcoord).rgb, 1.0);
+            }
+        ";
+
+        let shader = video::create_shader(params, vs.into(), fs.into())?;
+
+        // A 2x2 checkerboard, whose 1x1 mipmap is the average gray.
+        let mut params = TextureParams::default();
+        params.dimensions = (2, 2).into();
+        params.format = TextureFormat::RGBA8;
+
+        let mut pixels = vec![255; 16];
+        pixels[4..7].copy_from_slice(&[0, 0, 0]);
+        pixels[8..11].copy_from_slice(&[0, 0, 0]);
+
+        let data = TextureData {
+            bytes: vec![pixels.into_boxed_slice()],
+        };
+
+        let texture = video::create_texture(params, data)?;
+        video::generate_texture_mipmaps(texture)?;
+
+        let mut params = SamplerParams::new(TextureWrap::Clamp, TextureFilter::Nearest, true);
+        params.lod_range = [1.0, 1.0];
+        let sampler = video::create_sampler(params)?;
+
+        Ok(Mipmaps {
+            surface: video::create_surface(SurfaceParams::default())?,
+            shader,
+            mesh,
+            texture,
+            sampler,
+            batch: CommandBuffer::new(),
+        })
+    }
+}
+
+impl Drop for Mipmaps {
+    fn drop(&mut self) {
+        video::delete_surface(self.surface);
+        video::delete_shader(self.shader);
+        video::delete_mesh(self.mesh);
+        video::delete_texture(self.texture);
+        video::delete_sampler(self.sampler);
+    }
+}
+
+impl LifecycleListener for Mipmaps {
+    fn on_update(&mut self) -> Result<()> {
+        let mut dc = Draw::new(self.shader, self.mesh);
+        dc.set_uniform_variable("u_Texture", (self.texture, self.sampler));
+        self.batch.draw(dc);
+        self.batch.submit(self.surface)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn mipmaps() {
+    let mut params = GoldenParams::default();
+    params.engine.window.size = (16, 16).into();
+
+    let (_, pixels) = testing::render(&params, Mipmaps::new).unwrap();
+    for v in pixels.chunks(4) {
+        for &c in &v[0..3] {
+            assert!((i32::from(c) - 128).abs() <= 2, "{:?}", v);
+        }
+    }
+}
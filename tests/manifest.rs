@@ -0,0 +1,78 @@
+extern crate crayon;
+
+use std::io::Cursor;
+
+use crayon::bincode;
+use crayon::res::manifest::*;
+use crayon::uuid::Uuid;
+
+#[test]
+fn dependencies() {
+    let mut manifest = Manifest::new();
+    let uuids: Vec<_> = (0..3).map(|i| Uuid::from_bytes([i + 1; 16])).collect();
+    let dependencies: [&[usize]; 3] = [&[1, 2], &[2], &[]];
+
+    // Slices are extended before strings to keep them aligned.
+    let dependencies: Vec<_> = dependencies
+        .iter()
+        .map(|v| manifest.buf.extend_from_slice(v))
+        .collect();
+
+    for i in 0..3 {
+        let item = ManifestItem {
+            filename: manifest.buf.extend_from_str(format!("{}.bin", i)),
+            dependencies: dependencies[i],
+            uuid: uuids[i],
+        };
+
+        manifest.items.push(item);
+    }
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(bincode::serialize(&manifest).unwrap());
+
+    let mut resolver = ManfiestResolver::new();
+    resolver.add("res:", &mut Cursor::new(bytes)).unwrap();
+
+    let v: Vec<_> = resolver.dependencies(uuids[0]).unwrap().collect();
+    assert_eq!(v, vec![uuids[1], uuids[2]]);
+
+    let v: Vec<_> = resolver.dependencies(uuids[1]).unwrap().collect();
+    assert_eq!(v, vec![uuids[2]]);
+
+    assert_eq!(resolver.dependencies(uuids[2]).unwrap().count(), 0);
+    assert!(resolver.dependencies(Uuid::nil()).is_none());
+}
+
+#[test]
+fn hashes() {
+    let mut manifest = Manifest::new();
+    let uuids: Vec<_> = (0..2).map(|i| Uuid::from_bytes([i + 1; 16])).collect();
+    let texture = manifest.add("crate.bmp", uuids[0], &[], 0xdead_beef);
+    manifest.add("crate.mat", uuids[1], &[texture], 42);
+
+    let mut bytes = Vec::new();
+    manifest.save_to(&mut bytes).unwrap();
+
+    let mut resolver = ManfiestResolver::new();
+    resolver.add("res:", &mut Cursor::new(bytes)).unwrap();
+
+    assert_eq!(resolver.find("res:/crate.mat"), Some(uuids[1]));
+    assert_eq!(resolver.hash(uuids[0]), Some(0xdead_beef));
+    assert_eq!(resolver.hash(uuids[1]), Some(42));
+    assert_eq!(resolver.hash(Uuid::nil()), None);
+
+    let v: Vec<_> = resolver.dependencies(uuids[1]).unwrap().collect();
+    assert_eq!(v, vec![uuids[0]]);
+
+    // The resources whose hashes are changed by the re-attached manifests are reported.
+    let mut manifest = Manifest::new();
+    let texture = manifest.add("crate.bmp", uuids[0], &[], 0xdead_beef);
+    manifest.add("crate.mat", uuids[1], &[texture], 43);
+
+    let mut bytes = Vec::new();
+    manifest.save_to(&mut bytes).unwrap();
+    let modified = resolver.add("res:", &mut Cursor::new(bytes)).unwrap();
+    assert_eq!(modified, vec![uuids[1]]);
+    assert_eq!(resolver.hash(uuids[1]), Some(43));
+}
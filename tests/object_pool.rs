@@ -1,5 +1,8 @@
 extern crate crayon;
 
+use std::cell::Cell;
+use std::rc::Rc;
+
 use crayon::utils::prelude::*;
 
 #[test]
@@ -41,3 +44,40 @@ fn iterator() {
         assert_eq!(v, (i + 1) as i32);
     }
 }
+
+#[derive(Debug)]
+struct Counter(Rc<Cell<u32>>);
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn drops() {
+    let drops = Rc::new(Cell::new(0));
+    let mut set = ObjectPool::<Handle, Counter>::new();
+
+    let e1 = set.create(Counter(drops.clone()));
+    let e2 = set.create(Counter(drops.clone()));
+    let e3 = set.create(Counter(drops.clone()));
+
+    // Freed values are moved out, and dropped by the caller.
+    assert!(set.free(e1).is_some());
+    assert_eq!(drops.get(), 1);
+
+    // The slot of freed value is reused without dropping it again.
+    let e4 = set.create(Counter(drops.clone()));
+    assert_eq!(e4.index(), e1.index());
+    assert_eq!(drops.get(), 1);
+
+    set.retain(|handle, _| handle != e2);
+    assert_eq!(drops.get(), 2);
+    assert!(set.get(e2).is_none());
+    assert!(set.get(e3).is_some());
+
+    // The alive values are dropped with pool.
+    drop(set);
+    assert_eq!(drops.get(), 4);
+}
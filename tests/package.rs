@@ -0,0 +1,52 @@
+extern crate crayon;
+
+use crayon::res::vfs::package::*;
+
+#[test]
+fn read() {
+    let texture: Vec<u8> = (0..4096).map(|v| (v % 7) as u8).collect();
+
+    let mut builder = PackageBuilder::new();
+    builder.add("MANIFEST", &b"manifest"[..], Compression::None);
+    builder.add("/textures//crate", texture.clone(), Compression::Deflate);
+
+    let mut bytes = Vec::new();
+    builder.write(&mut bytes).unwrap();
+    assert!(bytes.len() < texture.len());
+
+    let package = Package::from_bytes(bytes.clone()).unwrap();
+    assert!(package.contains("/MANIFEST"));
+    assert!(package.contains("textures/crate"));
+    assert!(!package.contains("textures"));
+
+    assert_eq!(&package.read("MANIFEST").unwrap()[..], &b"manifest"[..]);
+    assert_eq!(&package.read("//textures/crate").unwrap()[..], &texture[..]);
+    assert!(package.read("textures").is_err());
+
+    let path = std::env::temp_dir().join("crayon_package_read.pack");
+    std::fs::write(&path, &bytes).unwrap();
+
+    let package = Package::open(&path).unwrap();
+    assert_eq!(&package.read("textures/crate").unwrap()[..], &texture[..]);
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(Package::from_bytes(&b"invalid package"[..]).is_err());
+}
+
+#[test]
+fn corrupted() {
+    let mut builder = PackageBuilder::new();
+    builder.add("MANIFEST", &b"manifest"[..], Compression::None);
+
+    let mut bytes = Vec::new();
+    builder.write(&mut bytes).unwrap();
+
+    // The lengths of index and files are checked against the size of archive.
+    let mut v = bytes.clone();
+    v[8..16].copy_from_slice(&u64::max_value().to_le_bytes());
+    assert!(Package::from_bytes(v).is_err());
+
+    let v = bytes[..bytes.len() - 1].to_vec();
+    assert!(Package::from_bytes(v).is_err());
+    assert!(Package::from_bytes(bytes).is_ok());
+}
@@ -0,0 +1,98 @@
+extern crate crayon;
+
+use std::sync::{Arc, Mutex};
+
+use crayon::res::request::*;
+use crayon::res::url::Url;
+use crayon::res::vfs::VFS;
+use crayon::sched::prelude::{LatchProbe, LockLatch};
+
+// Holds the requests in flight until they are released.
+#[derive(Default, Clone)]
+struct Recorder(Arc<Mutex<Vec<(String, Arc<LockLatch<Response>>)>>>);
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Recorder")
+    }
+}
+
+impl VFS for Recorder {
+    fn request(&self, url: &Url, state: Arc<LockLatch<Response>>) {
+        self.0.lock().unwrap().push((url.path().to_owned(), state));
+    }
+}
+
+impl Recorder {
+    fn release(&self) -> Vec<String> {
+        let requests = self.0.lock().unwrap();
+        for &(_, ref state) in requests.iter() {
+            if !state.is_set() {
+                state.set(Ok(Vec::new().into_boxed_slice()));
+            }
+        }
+
+        requests.iter().map(|v| v.0.clone()).collect()
+    }
+}
+
+#[test]
+fn priority_and_cancel() {
+    crayon::application::oneshot().unwrap();
+
+    let recorder = Recorder::default();
+    let vfs: Arc<VFS> = Arc::new(recorder.clone());
+    let queue = LoadQueue::new(1);
+
+    let add = |path: &str, priority| {
+        let url = Url::new(format!("mock://{}", path)).unwrap();
+        queue.add(vfs.clone(), url, priority)
+    };
+
+    // Dispatched at once, since there is nothing in flight.
+    let (_a, _) = add("/a", LoadPriority::Normal);
+    while recorder.0.lock().unwrap().is_empty() {
+        std::thread::yield_now();
+    }
+
+    let (_b, _) = add("/b", LoadPriority::Background);
+    let (_c, _) = add("/c", LoadPriority::High);
+    let (_d, _) = add("/d", LoadPriority::Normal);
+    let (_e, e) = add("/e", LoadPriority::Normal);
+    let (_f, f) = add("/f", LoadPriority::Background);
+    let (g, _) = add("/g", LoadPriority::Normal);
+    let (_h, _) = add("/h", LoadPriority::Blocking);
+
+    e.cancel();
+    f.set_priority(LoadPriority::High);
+    drop(g);
+
+    let mut order = Vec::new();
+    while order.len() < 6 {
+        order = recorder.release();
+        queue.dispatch();
+        std::thread::yield_now();
+    }
+
+    assert!(queue.is_empty());
+    assert_eq!(order, ["/a", "/h", "/c", "/f", "/d", "/b"]);
+
+    crayon::application::discard();
+}
+
+#[test]
+fn request_queue_cancel() {
+    let queue = RequestQueue::new();
+    let called = Arc::new(Mutex::new(false));
+
+    let handle = RequestHandle::new(LoadPriority::Normal);
+    let clone = called.clone();
+    queue.add(Request::ok(vec![0]), handle.clone(), move |_| {
+        *clone.lock().unwrap() = true;
+    });
+
+    handle.cancel();
+    assert!(handle.is_cancelled());
+    queue.advance();
+    assert!(!*called.lock().unwrap());
+}
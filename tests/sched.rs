@@ -0,0 +1,101 @@
+extern crate crayon;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crayon::res::request::Request;
+use crayon::sched;
+use crayon::sched::prelude::JobGraph;
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// A minimal executor that parks current thread until the future is woken.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(v) => return v,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+fn jobs() {
+    crayon::application::oneshot().unwrap();
+
+    // Tasks.
+    let task = sched::spawn_task(|| (0..100).sum::<u32>());
+    assert_eq!(task.wait(), 4950);
+
+    let task = sched::spawn_task(|| 42);
+    assert_eq!(block_on(task), 42);
+
+    let task = sched::spawn_task(|| -> u32 { panic!("expected") });
+    let rsp = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| task.wait()));
+    assert!(rsp.is_err());
+
+    // Parallel for.
+    let mut items: Vec<_> = (0..1000).collect();
+    sched::parallel_for(&mut items, |i, v| *v += i);
+    assert!(items.iter().enumerate().all(|(i, &v)| v == i * 2));
+
+    // Graph.
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let mut graph = JobGraph::new();
+    let jobs: Vec<_> = (0..4)
+        .map(|i| {
+            let order = order.clone();
+            graph.add(move || order.lock().unwrap().push(i))
+        })
+        .collect();
+
+    graph.depend(jobs[3], jobs[1]);
+    graph.depend(jobs[3], jobs[2]);
+    graph.depend(jobs[1], jobs[0]);
+    graph.depend(jobs[2], jobs[0]);
+    graph.spawn().unwrap().wait();
+
+    let order = order.lock().unwrap();
+    assert_eq!(order.len(), 4);
+    assert_eq!(order[0], 0);
+    assert_eq!(order[3], 3);
+
+    let counter = Arc::new(AtomicUsize::new(0));
+    let mut graph = JobGraph::new();
+    let a = graph.add(|| {});
+    let b = graph.add(|| {});
+    graph.depend(a, b);
+    graph.depend(b, a);
+    assert!(graph.spawn().is_err());
+
+    let mut graph = JobGraph::new();
+    for _ in 0..64 {
+        let counter = counter.clone();
+        graph.add(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+
+    block_on(graph.spawn().unwrap());
+    assert_eq!(counter.load(Ordering::SeqCst), 64);
+
+    // Requests.
+    let latch = Request::latch();
+    let request = Request::new(latch.clone());
+    sched::spawn(move || latch.set(Ok(vec![1, 2, 3].into_boxed_slice())));
+    let bytes = block_on(request).unwrap();
+    assert_eq!(&bytes[..], &[1, 2, 3]);
+}
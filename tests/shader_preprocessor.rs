@@ -0,0 +1,49 @@
+extern crate crayon;
+
+use crayon::video::assets::shader_preprocessor::*;
+
+#[test]
+fn defines() {
+    let mut preprocessor = ShaderPreprocessor::new();
+    preprocessor.add_include("common.glsl", "#include \"common.glsl\"\nfloat a;");
+
+    let defines = ShaderDefines::new()
+        .with("MAX_LITS", 4)
+        .with_flag("SKINNED");
+    let source =
+        "\n    #version 100\n#include \"common.glsl\"\n#include <common.glsl>\nvoid main() {}";
+    let result = preprocessor.process(source, &defines).unwrap();
+
+    let lines: Vec<_> = result.lines().map(|v| v.trim()).collect();
+    assert_eq!(
+        lines,
+        [
+            "",
+            "#version 100",
+            "#define MAX_LITS 4",
+            "#define SKINNED",
+            "float a;",
+            "void main() {}"
+        ]
+    );
+}
+
+#[test]
+fn permutations() {
+    let a = ShaderDefines::new().with_flag("LIT").with_flag("SKINNED");
+    let b = ShaderDefines::new().with_flag("SKINNED").with_flag("LIT");
+    assert_eq!(a, b);
+    assert!(a.contains("LIT"));
+
+    let mut c = a.clone();
+    c.remove("LIT");
+    assert_ne!(a, c);
+    assert_eq!(c.len(), 1);
+}
+
+#[test]
+fn missing_include() {
+    let preprocessor = ShaderPreprocessor::new();
+    let source = "#include \"missing.glsl\"";
+    assert!(preprocessor.process(source, &ShaderDefines::new()).is_err());
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "hot-reload")]
+
+extern crate crayon;
+
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use crayon::res::watcher::{Watcher, SCAN_INTERVAL};
+use crayon::uuid::Uuid;
+
+#[test]
+fn modified() {
+    let path = std::env::temp_dir().join("crayon_watcher_modified.txt");
+    fs::write(&path, "v1").unwrap();
+
+    let watcher = Watcher::new();
+    let uuid = Uuid::from_bytes([1; 16]);
+    watcher.watch(uuid, path.to_str().unwrap());
+
+    let mut cursor = 0;
+    for _ in 0..SCAN_INTERVAL {
+        watcher.advance();
+    }
+
+    assert!(watcher.changes(&mut cursor).is_empty());
+
+    fs::write(&path, "v2").unwrap();
+    let file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+
+    for _ in 0..SCAN_INTERVAL {
+        watcher.advance();
+    }
+
+    assert_eq!(watcher.changes(&mut cursor), vec![uuid]);
+    assert!(watcher.changes(&mut cursor).is_empty());
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crayon::res::manifest::{self, Manifest};
+use crayon::uuid::Uuid;
+use failure::Error;
+
+use super::importers::Importer;
+use super::meta::{self, Meta};
+use super::workspace::Workspace;
+
+/// The statistics of a build.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Summary {
+    pub converted: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} assets are compiled ({} converted, {} unchanged), {} stale resources are removed.",
+            self.converted + self.unchanged,
+            self.converted,
+            self.unchanged,
+            self.removed
+        )
+    }
+}
+
+struct Asset {
+    // The path relative to the source directory, which is separated by `/`.
+    name: String,
+    path: PathBuf,
+    importer: Importer,
+    meta: Meta,
+}
+
+pub struct Compiler {
+    workspace: Workspace,
+    force: bool,
+}
+
+impl Compiler {
+    pub fn new(workspace: Workspace) -> Self {
+        Compiler {
+            workspace,
+            force: false,
+        }
+    }
+
+    /// Converts all the assets even if their contents are unchanged.
+    pub fn set_force(&mut self, force: bool) {
+        self.force = force;
+    }
+
+    /// Compiles the assets, and writes the resources along with the manifest into the
+    /// destination directory.
+    pub fn run(&self) -> Result<Summary, Error> {
+        let mut files = Vec::new();
+        walk(&self.workspace.source, &mut files)?;
+        files.sort();
+
+        let mut assets = Vec::new();
+        for path in files {
+            if let Some(importer) = self.workspace.importer(&path) {
+                let name = name(&self.workspace.source, &path);
+                let meta = Meta::load_or_create(&path, &name)?;
+                assets.push(Asset {
+                    name,
+                    path,
+                    importer,
+                    meta,
+                });
+            }
+        }
+
+        let indices: HashMap<_, _> = assets
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.name.clone(), i))
+            .collect();
+
+        let resolve = |name: &str| indices.get(name).map(|&i| assets[i].meta.uuid);
+
+        fs::create_dir_all(&self.workspace.destination)?;
+
+        let mut summary = Summary::default();
+        let mut manifest = Manifest::new();
+        let mut metas = Vec::new();
+
+        for asset in &assets {
+            let bytes = fs::read(&asset.path)?;
+            let seed = meta::hash(format!("{:?}", asset.importer).as_bytes(), 0);
+            let mut hash = meta::hash(&bytes, seed);
+            let output = self.workspace.destination.join(filename(asset.meta.uuid));
+
+            let unchanged =
+                |hash| !self.force && asset.meta.hash() == Some(hash) && output.exists();
+
+            // The resources are written only if they are changed, so the unchanged ones
+            // would not be reloaded by the file watchers.
+            let dependencies = if unchanged(hash) && !asset.importer.has_dependencies() {
+                summary.unchanged += 1;
+                Vec::new()
+            } else {
+                let imported = asset
+                    .importer
+                    .import(&bytes, &resolve)
+                    .map_err(|err| format_err!("Failed to import {}. {}", asset.name, err))?;
+
+                // The resources refer to their dependencies by uuids, so they are changed
+                // once the dependencies are re-created with new uuids.
+                for v in &imported.dependencies {
+                    if let Some(uuid) = resolve(v) {
+                        hash = meta::hash(uuid.as_bytes(), hash);
+                    }
+                }
+
+                if unchanged(hash) {
+                    summary.unchanged += 1;
+                } else {
+                    fs::write(&output, &imported.bytes)?;
+                    summary.converted += 1;
+
+                    let mut meta = asset.meta.clone();
+                    meta.set_hash(hash);
+                    metas.push((&asset.path, meta));
+                }
+
+                imported.dependencies
+            };
+
+            let dependencies: Vec<_> = dependencies.iter().map(|v| indices[v]).collect();
+            manifest.add(&asset.name, asset.meta.uuid, &dependencies, hash);
+        }
+
+        for (path, meta) in metas {
+            meta.save(path)?;
+        }
+
+        summary.removed = self.remove_stale_resources(&assets)?;
+
+        let mut file = fs::File::create(self.workspace.destination.join(manifest::NAME))?;
+        manifest.save_to(&mut file)?;
+        Ok(summary)
+    }
+
+    // Removes the resources in destination that are not produced by any asset, which are
+    // recognized by their names of UUID.
+    fn remove_stale_resources(&self, assets: &[Asset]) -> Result<usize, Error> {
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.workspace.destination)? {
+            let path = entry?.path();
+            let uuid = match path.file_name().and_then(|v| v.to_str()) {
+                Some(v) if v.len() == 32 => Uuid::parse_str(v).ok(),
+                _ => None,
+            };
+
+            if let Some(uuid) = uuid {
+                if path.is_file() && !assets.iter().any(|v| v.meta.uuid == uuid) {
+                    fs::remove_file(&path)?;
+                    removed += 1;
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let entries = fs::read_dir(dir)
+        .map_err(|err| format_err!("Could not read directory {:?}. {}", dir, err))?;
+
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else if !path.to_string_lossy().ends_with(meta::EXTENSION) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn name(source: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(source).unwrap_or(path);
+    let components: Vec<_> = relative
+        .components()
+        .map(|v| v.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    components.join("/")
+}
+
+// The resources are named by the upper-case hex of their UUIDs, see `ManfiestResolver`.
+fn filename(uuid: Uuid) -> String {
+    format!("{:X}", uuid.to_simple())
+}
@@ -0,0 +1,403 @@
+//! The importers that convert source assets into the binary formats of engine.
+
+use std::collections::BTreeMap;
+
+use crayon::bincode;
+use crayon::math::prelude::{Aabb2, Vector2};
+use crayon::uuid::Uuid;
+use crayon::video::assets::obj;
+use crayon::video::assets::prelude::*;
+use crayon::video::assets::shader::RenderState;
+use crayon::video::assets::{mesh_loader, texture_loader};
+use crayon_world::assets::prelude::{Material, MaterialParam};
+use crayon_world::assets::{material_loader, texture_atlas_loader};
+use crayon_world::renderable::prelude::RenderQueue;
+use crayon_world::sprite::prelude::{AtlasRegion, TextureAtlas};
+use failure::Error;
+use image::{self, imageops, FilterType};
+
+/// The magic number of audio clips, which is the same as the one of `crayon-audio`.
+const AUDIO_MAGIC: [u8; 8] = [b'C', b'S', b'F', b'X', b' ', 0, 0, 1];
+
+/// The kinds of importers, which are named in `workspace.toml`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub enum Importer {
+    /// Decodes images into RGBA8 textures with full mipmap chains.
+    Texture,
+    /// Decodes images into sRGB textures with full mipmap chains, which are decoded into
+    /// linear space when sampled, e.g. the albedo maps.
+    SrgbTexture,
+    /// Decodes the glyph atlases of bitmap fonts into RGBA8 textures, which are sampled
+    /// with nearest filter and have no mipmaps.
+    Font,
+    /// Converts OBJ files into meshes.
+    Mesh,
+    /// Converts JSON descriptions into materials, see `MaterialSource`.
+    Material,
+    /// Converts the JSON data of texture atlases packed by tools like TexturePacker into
+    /// texture atlases, see `AtlasSource`.
+    Atlas,
+    /// Wraps Ogg Vorbis files into audio clips.
+    Audio,
+    /// Copies files as they are, e.g. glTF documents and KTX2 textures which are imported
+    /// at runtime.
+    Transmission,
+}
+
+/// The output of importer.
+pub struct Imported {
+    pub bytes: Vec<u8>,
+    /// The names of assets that are referenced by this one.
+    pub dependencies: Vec<String>,
+}
+
+/// The description of material in JSON, whose textures are referenced by the names of
+/// other assets, e.g.
+///
+/// ```json
+/// {
+///     "shader": "pbr",
+///     "params": { "u_Color": { "Vector4f": [1.0, 1.0, 1.0, 1.0] } },
+///     "textures": { "u_MainTex": "textures/crate.png" }
+/// }
+/// ```
+#[derive(Deserialize)]
+struct MaterialSource {
+    shader: String,
+    #[serde(default)]
+    params: BTreeMap<String, MaterialParam>,
+    #[serde(default)]
+    render_state: Option<RenderState>,
+    #[serde(default)]
+    queue: RenderQueue,
+    #[serde(default)]
+    textures: BTreeMap<String, String>,
+}
+
+/// The JSON data of texture atlas in the hash or array format of TexturePacker. The
+/// image is referenced by the name of other asset, e.g.
+///
+/// ```json
+/// {
+///     "frames": {
+///         "hero.png": {
+///             "frame": { "x": 0, "y": 0, "w": 32, "h": 16 },
+///             "rotated": true,
+///             "spriteSourceSize": { "x": 8, "y": 40, "w": 32, "h": 16 },
+///             "sourceSize": { "w": 64, "h": 64 }
+///         }
+///     },
+///     "meta": { "image": "sprites/atlas.png", "size": { "w": 128, "h": 64 } }
+/// }
+/// ```
+///
+/// The frames are named by their filenames without extensions, and the sizes of frames
+/// are the ones before rotation.
+#[derive(Deserialize)]
+struct AtlasSource {
+    frames: AtlasFrames,
+    meta: AtlasMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AtlasFrames {
+    Hash(BTreeMap<String, AtlasFrame>),
+    Array(Vec<AtlasFrame>),
+}
+
+#[derive(Deserialize)]
+struct AtlasFrame {
+    #[serde(default)]
+    filename: String,
+    frame: AtlasRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default, rename = "spriteSourceSize")]
+    sprite_source_size: Option<AtlasRect>,
+    #[serde(default, rename = "sourceSize")]
+    source_size: Option<AtlasSize>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+struct AtlasRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+struct AtlasSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct AtlasMeta {
+    image: String,
+    size: AtlasSize,
+}
+
+impl Importer {
+    /// Checks if the assets imported by this importer reference others, they are always
+    /// imported again to collect the dependencies.
+    pub fn has_dependencies(self) -> bool {
+        self == Importer::Material || self == Importer::Atlas
+    }
+
+    /// Imports the asset, `resolve` finds the UUID of other assets by their names, which
+    /// are relative to the source directory.
+    pub fn import(
+        self,
+        bytes: &[u8],
+        resolve: &dyn Fn(&str) -> Option<Uuid>,
+    ) -> Result<Imported, Error> {
+        let bytes = match self {
+            Importer::Texture => texture(bytes, TextureFormat::RGBA8, TextureFilter::Linear, true)?,
+            Importer::SrgbTexture => {
+                texture(bytes, TextureFormat::SRGBA8, TextureFilter::Linear, true)?
+            }
+            Importer::Font => texture(bytes, TextureFormat::RGBA8, TextureFilter::Nearest, false)?,
+            Importer::Mesh => mesh(bytes)?,
+            Importer::Material => return material(bytes, resolve),
+            Importer::Atlas => return atlas(bytes, resolve),
+            Importer::Audio => audio(bytes)?,
+            Importer::Transmission => bytes.to_vec(),
+        };
+
+        Ok(Imported {
+            bytes,
+            dependencies: Vec::new(),
+        })
+    }
+}
+
+fn texture(
+    bytes: &[u8],
+    format: TextureFormat,
+    filter: TextureFilter,
+    mipmaps: bool,
+) -> Result<Vec<u8>, Error> {
+    let mut image = image::load_from_memory(bytes)?.to_rgba();
+
+    let mut params = TextureParams::default();
+    params.format = format;
+    params.filter = filter;
+    params.dimensions = Vector2::new(image.width(), image.height());
+
+    let mut data = TextureData { bytes: Vec::new() };
+    loop {
+        let (width, height) = (image.width(), image.height());
+        data.bytes.push(image.clone().into_raw().into_boxed_slice());
+
+        if !mipmaps || (width == 1 && height == 1) {
+            break;
+        }
+
+        let (width, height) = ((width / 2).max(1), (height / 2).max(1));
+        image = imageops::resize(&image, width, height, FilterType::Triangle);
+    }
+
+    let mut out = texture_loader::MAGIC.to_vec();
+    bincode::serialize_into(&mut out, &params)?;
+    bincode::serialize_into(&mut out, &data)?;
+    Ok(out)
+}
+
+fn mesh(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let (params, data) = obj::parse(::std::str::from_utf8(bytes)?)?;
+
+    let mut out = mesh_loader::MAGIC.to_vec();
+    bincode::serialize_into(&mut out, &params)?;
+    bincode::serialize_into(&mut out, &data)?;
+    Ok(out)
+}
+
+fn material(bytes: &[u8], resolve: &dyn Fn(&str) -> Option<Uuid>) -> Result<Imported, Error> {
+    let source: MaterialSource = serde_json::from_slice(bytes)?;
+
+    let mut material = Material::new(source.shader);
+    material.params = source.params;
+    material.render_state = source.render_state;
+    material.queue = source.queue;
+
+    let mut dependencies: Vec<String> = Vec::new();
+    for (k, v) in source.textures {
+        let uuid = resolve(&v).ok_or_else(|| format_err!("Texture {} is not found.", v))?;
+        let index = match material.universe_textures.iter().position(|&w| w == uuid) {
+            Some(index) => index,
+            None => {
+                material.universe_textures.push(uuid);
+                dependencies.push(v);
+                material.universe_textures.len() - 1
+            }
+        };
+
+        material.params.insert(k, MaterialParam::Texture(index));
+    }
+
+    material.validate()?;
+
+    let mut out = material_loader::MAGIC.to_vec();
+    bincode::serialize_into(&mut out, &material)?;
+    Ok(Imported {
+        bytes: out,
+        dependencies,
+    })
+}
+
+fn atlas(bytes: &[u8], resolve: &dyn Fn(&str) -> Option<Uuid>) -> Result<Imported, Error> {
+    let source: AtlasSource = serde_json::from_slice(bytes)?;
+
+    let image = source.meta.image;
+    let uuid = resolve(&image).ok_or_else(|| format_err!("Texture {} is not found.", image))?;
+    let size = source.meta.size;
+    let mut atlas = TextureAtlas::from_uuid(uuid, (size.w, size.h));
+
+    let frames = match source.frames {
+        AtlasFrames::Hash(frames) => frames
+            .into_iter()
+            .map(|(k, mut v)| {
+                v.filename = k;
+                v
+            })
+            .collect(),
+        AtlasFrames::Array(frames) => frames,
+    };
+
+    for v in frames {
+        let (x, y, w, h) = (v.frame.x, v.frame.y, v.frame.w, v.frame.h);
+        let max = if v.rotated {
+            [x + h, y + w]
+        } else {
+            [x + w, y + h]
+        };
+        let trim = v
+            .sprite_source_size
+            .unwrap_or(AtlasRect { x: 0, y: 0, w, h });
+        let source = v.source_size.unwrap_or(AtlasSize { w, h });
+
+        let region = AtlasRegion {
+            rect: Aabb2::new([x, y].into(), max.into()),
+            rotated: v.rotated,
+            offset: Vector2::new(trim.x, trim.y),
+            source: Vector2::new(source.w, source.h),
+        };
+
+        let name = match v.filename.rfind('.') {
+            Some(i) => &v.filename[..i],
+            None => &v.filename[..],
+        };
+
+        atlas.add_region(name, region);
+    }
+
+    atlas.validate()?;
+
+    let mut out = texture_atlas_loader::MAGIC.to_vec();
+    bincode::serialize_into(&mut out, &atlas)?;
+    Ok(Imported {
+        bytes: out,
+        dependencies: vec![image],
+    })
+}
+
+fn audio(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    if bytes.len() < 4 || &bytes[0..4] != b"OggS" {
+        bail!("Only Ogg Vorbis files are supported.");
+    }
+
+    let mut out = AUDIO_MAGIC.to_vec();
+    out.extend_from_slice(bytes);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn material() {
+        let uuid = Uuid::from_bytes([1; 16]);
+        let resolve = |v: &str| if v == "crate.bmp" { Some(uuid) } else { None };
+
+        let json = br#"{
+            "shader": "pbr",
+            "params": { "u_Roughness": { "F32": 0.5 } },
+            "textures": { "u_Albedo": "crate.bmp", "u_Detail": "crate.bmp" }
+        }"#;
+
+        let imported = Importer::Material.import(json, &resolve).unwrap();
+        assert_eq!(imported.dependencies, vec!["crate.bmp".to_owned()]);
+
+        let material: Material = bincode::deserialize(&imported.bytes[8..]).unwrap();
+        assert_eq!(material.universe_textures, vec![uuid]);
+        assert_eq!(material.params["u_Detail"], MaterialParam::Texture(0));
+        assert_eq!(material.params["u_Roughness"], MaterialParam::F32(0.5));
+
+        let json = br#"{ "shader": "pbr", "textures": { "u_Albedo": "missing.bmp" } }"#;
+        assert!(Importer::Material.import(json, &resolve).is_err());
+    }
+
+    #[test]
+    fn atlas() {
+        let uuid = Uuid::from_bytes([1; 16]);
+        let resolve = |v: &str| if v == "atlas.png" { Some(uuid) } else { None };
+
+        let json = br#"{
+            "frames": [{
+                "filename": "hero.png",
+                "frame": { "x": 100, "y": 0, "w": 32, "h": 16 },
+                "rotated": true,
+                "trimmed": true,
+                "spriteSourceSize": { "x": 8, "y": 40, "w": 32, "h": 16 },
+                "sourceSize": { "w": 64, "h": 64 }
+            }],
+            "meta": { "image": "atlas.png", "size": { "w": 128, "h": 64 } }
+        }"#;
+
+        let imported = Importer::Atlas.import(json, &resolve).unwrap();
+        assert_eq!(imported.dependencies, vec!["atlas.png".to_owned()]);
+
+        let atlas: TextureAtlas = bincode::deserialize(&imported.bytes[8..]).unwrap();
+        assert_eq!(atlas.universe_texture(), Some(uuid));
+
+        let region = atlas.region(atlas.find("hero").unwrap()).unwrap();
+        assert_eq!(region.rect, Aabb2::new([100, 0].into(), [116, 32].into()));
+        assert_eq!(region.offset, Vector2::new(8, 40));
+        assert_eq!(region.source, Vector2::new(64, 64));
+
+        let json = br#"{
+            "frames": { "hero.png": { "frame": { "x": 120, "y": 0, "w": 32, "h": 16 } } },
+            "meta": { "image": "atlas.png", "size": { "w": 128, "h": 64 } }
+        }"#;
+
+        assert!(Importer::Atlas.import(json, &resolve).is_err());
+    }
+
+    #[test]
+    fn texture() {
+        // A 2x2 BMP with 24-bit pixels.
+        let mut bmp = vec![
+            b'B', b'M', 70, 0, 0, 0, 0, 0, 0, 0, 54, 0, 0, 0, 40, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0,
+            1, 0, 24, 0, 0, 0, 0, 0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        bmp.extend_from_slice(&[255; 16]);
+
+        let imported = Importer::Texture.import(&bmp, &|_| None).unwrap();
+        let mut file = &imported.bytes[8..];
+        let params: TextureParams = bincode::deserialize_from(&mut file).unwrap();
+        let data: TextureData = bincode::deserialize_from(&mut file).unwrap();
+
+        assert_eq!(params.dimensions, Vector2::new(2, 2));
+        assert_eq!(data.bytes.len(), 2);
+        assert_eq!(data.bytes[0].len(), 16);
+        assert_eq!(data.bytes[1].len(), 4);
+
+        let imported = Importer::SrgbTexture.import(&bmp, &|_| None).unwrap();
+        let params: TextureParams = bincode::deserialize(&imported.bytes[8..]).unwrap();
+        assert_eq!(params.format, TextureFormat::SRGBA8);
+        assert!(Importer::Audio.import(&bmp, &|_| None).is_err());
+    }
+}
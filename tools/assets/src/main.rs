@@ -0,0 +1,76 @@
+//! The offline asset compiler of crayon.
+//!
+//! It walks the source assets directory of a workspace, converts the textures, meshes,
+//! fonts, materials and texture atlases into the binary formats of engine, and emits a
+//! `MANIFEST` which is consumed by the resource system to find resources by their
+//! filenames.
+//!
+//! ```sh
+//! crayon-assets [--force] [path/to/workspace.toml]
+//! ```
+//!
+//! The workspace is described in `workspace.toml`, e.g.:
+//!
+//! ```toml
+//! [assets]
+//! source = 'assets'
+//! destination = 'resources'
+//!
+//! [[assets.importers]]
+//! type = 'Texture'
+//! extensions = ['.png', '.jpg', '.bmp', '.tga']
+//! ```
+//!
+//! The color textures like albedo maps could be imported with `SrgbTexture` instead, so
+//! their colors are decoded into linear space for shading.
+//!
+//! Every source file is given an UUID, which is persisted in the `.meta.toml` file next
+//! to it along with the content hash of the last build. Only the files whose contents have
+//! been changed are converted again, so the resources that are watched by hot-reloading are
+//! invalidated only if they are really modified.
+
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate serde;
+
+mod compiler;
+mod importers;
+mod meta;
+mod workspace;
+
+use std::path::PathBuf;
+use std::process;
+
+use self::compiler::Compiler;
+use self::workspace::Workspace;
+
+fn main() {
+    let mut force = false;
+    let mut path = PathBuf::from("workspace.toml");
+
+    for v in std::env::args().skip(1) {
+        match v.as_str() {
+            "-f" | "--force" => force = true,
+            "-h" | "--help" => {
+                println!("Usage: crayon-assets [--force] [path/to/workspace.toml]");
+                return;
+            }
+            _ => path = PathBuf::from(v),
+        }
+    }
+
+    let result = Workspace::load(&path).and_then(|workspace| {
+        let mut compiler = Compiler::new(workspace);
+        compiler.set_force(force);
+        compiler.run()
+    });
+
+    match result {
+        Ok(summary) => println!("{}", summary),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crayon::uuid::Uuid;
+use failure::Error;
+
+/// The extension of meta files, which are placed next to the source files.
+pub const EXTENSION: &str = ".meta.toml";
+
+/// The meta data of source file, which keeps its UUID stable across builds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Meta {
+    pub uuid: Uuid,
+    /// The content hash of the last build, in hex.
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+impl Meta {
+    /// Gets the path of the meta file of source.
+    pub fn path<T: AsRef<Path>>(source: T) -> PathBuf {
+        let mut path = source.as_ref().as_os_str().to_owned();
+        path.push(EXTENSION);
+        PathBuf::from(path)
+    }
+
+    /// Loads the meta of source, or creates a new one with an unique UUID if it does not
+    /// exist, which is generated from the name of source and current time.
+    pub fn load_or_create<T: AsRef<Path>>(source: T, name: &str) -> Result<Self, Error> {
+        let path = Self::path(source);
+        if !path.exists() {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+            let seeds = [now.as_secs(), u64::from(now.subsec_nanos())];
+
+            let mut bytes = [0; 16];
+            for (i, v) in bytes.iter_mut().enumerate() {
+                *v = (hash(name.as_bytes(), seeds[i / 8]) >> (i % 8 * 8)) as u8;
+            }
+
+            return Ok(Meta {
+                uuid: Uuid::from_bytes(bytes),
+                hash: None,
+            });
+        }
+
+        let text = fs::read_to_string(&path)?;
+        toml::from_str(&text).map_err(|err| format_err!("Invalid meta {:?}. {}", path, err))
+    }
+
+    pub fn save<T: AsRef<Path>>(&self, source: T) -> Result<(), Error> {
+        fs::write(Self::path(source), toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Gets the content hash of the last build.
+    pub fn hash(&self) -> Option<u64> {
+        self.hash
+            .as_ref()
+            .and_then(|v| u64::from_str_radix(v, 16).ok())
+    }
+
+    pub fn set_hash(&mut self, hash: u64) {
+        self.hash = Some(format!("{:016x}", hash));
+    }
+}
+
+/// The 64-bit FNV-1a hash, which is stable across platforms and builds.
+pub fn hash(bytes: &[u8], seed: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    for &v in bytes {
+        hash ^= u64::from(v);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+
+    hash
+}
@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+
+use super::importers::Importer;
+
+#[derive(Deserialize)]
+struct WorkspaceFile {
+    assets: AssetsSection,
+}
+
+#[derive(Deserialize)]
+struct AssetsSection {
+    source: String,
+    destination: String,
+    #[serde(default)]
+    importers: Vec<ImporterSection>,
+}
+
+#[derive(Deserialize)]
+struct ImporterSection {
+    #[serde(rename = "type")]
+    importer: Importer,
+    extensions: Vec<String>,
+}
+
+/// The workspace of assets, which is loaded from `workspace.toml`.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// The directory of source assets.
+    pub source: PathBuf,
+    /// The directory that the converted resources and manifest are written into.
+    pub destination: PathBuf,
+    /// The importers and the extensions of files they handle, e.g. `.png`.
+    pub importers: Vec<(Importer, Vec<String>)>,
+}
+
+impl Workspace {
+    /// Loads the workspace, the directories are relative to the workspace file.
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| format_err!("Could not read workspace {:?}. {}", path, err))?;
+        let file: WorkspaceFile = toml::from_str(&text)?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let importers = file
+            .assets
+            .importers
+            .into_iter()
+            .map(|v| {
+                let extensions = v.extensions.iter().map(|v| v.to_lowercase()).collect();
+                (v.importer, extensions)
+            })
+            .collect();
+
+        Ok(Workspace {
+            source: dir.join(file.assets.source),
+            destination: dir.join(file.assets.destination),
+            importers,
+        })
+    }
+
+    /// Finds the importer of file by its extension.
+    pub fn importer<T: AsRef<Path>>(&self, path: T) -> Option<Importer> {
+        let name = path.as_ref().file_name()?.to_str()?.to_lowercase();
+        self.importers
+            .iter()
+            .find(|v| v.1.iter().any(|e| name.ends_with(e.as_str())))
+            .map(|v| v.0)
+    }
+}